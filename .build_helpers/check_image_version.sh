@@ -0,0 +1,52 @@
+#!/usr/bin/env bash
+
+# This script checks if the last booted image was built from the current commit.
+# It reads the build info that the roottask logs during boot from the debugcon log
+# of the last QEMU run. Usage: "make run_nogui" followed by "make check_image".
+
+set -e
+
+ANSI_GREEN="\e[32m"
+ANSI_RED="\e[31m"
+ANSI_RESET="\e[0m"
+
+#########################################################################
+# nice "hack" which make the script work, even if not executed from "./"
+DIR=$(dirname "$(realpath "$0")")
+cd "$DIR" || exit
+#########################################################################
+
+DEBUGCON_LOG="../qemu_debugcon.txt"
+
+fn_main() {
+    if ! [ -f "$DEBUGCON_LOG" ]; then
+        echo -e "${ANSI_RED}no debugcon log found; boot the image first${ANSI_RESET}"
+        exit 1
+    fi
+
+    EXPECTED=$(git rev-parse --short=12 HEAD)
+    # matches the output of the "Display" impl of "libhrstd::build_info::BuildInfo"
+    BOOTED=$(grep -a -o "build info: roottask-bin [^ ]* (git=[^,]*" "$DEBUGCON_LOG" \
+        | tail -n 1 \
+        | sed 's/.*git=//')
+
+    if [ -z "$BOOTED" ]; then
+        echo -e "${ANSI_RED}the debugcon log contains no build info of the roottask${ANSI_RESET}"
+        exit 1
+    fi
+
+    if [ "$BOOTED" != "$EXPECTED" ]; then
+        echo -e "${ANSI_RED}booted image ($BOOTED) doesn't match the current commit ($EXPECTED)${ANSI_RESET}"
+        exit 1
+    fi
+
+    # the hello world app warns, if the userland tarball was built from another commit
+    if grep -a -q "build info mismatch" "$DEBUGCON_LOG"; then
+        echo -e "${ANSI_RED}the userland of the booted image is stale${ANSI_RESET}"
+        exit 1
+    fi
+
+    echo -e "${ANSI_GREEN}booted image matches the current commit ($EXPECTED)${ANSI_RESET}"
+}
+
+fn_main
@@ -31,6 +31,9 @@ fn_main() {
 }
 
 fn_run_qemu() {
+    # the roottask mounts it at /host
+    mkdir -p ../qemu_host_share
+
     QEMU_ARGS=(
         # Disable default devices
         # QEMU by default enables a ton of devices which slow down boot.
@@ -66,6 +69,7 @@ fn_run_qemu() {
 
         # QEMU passes this as Multiboot1 Modules to Hedron. Multiple modules are separated
         # by a comma. The text after the path is the "cmdline" string of the boot module.
+        # Boot arguments of the roottask follow its name, e.g. "roottask log_timestamps=off".
         "-initrd"
         "${ROOTTASK} roottask,${USERLAND} userland"
 
@@ -78,6 +82,31 @@ fn_run_qemu() {
         "-serial"
         "stdio"
 
+        # Network card for the network service of the roottask. The roottask drives
+        # the legacy interface of virtio-net. The user mode network forwards traffic
+        # to the host.
+        "-netdev"
+        "user,id=net0"
+        "-device"
+        "virtio-net-pci,netdev=net0,disable-legacy=off,disable-modern=on"
+
+        # Console of a virtio-serial device on a Unix socket. With the boot argument
+        # "virtio_console=on", the roottask uses it instead of the serial port, e.g.
+        # "socat - UNIX-CONNECT:qemu_virtio_console.sock".
+        "-chardev"
+        "socket,id=vcon0,path=../qemu_virtio_console.sock,server=on,wait=off"
+        "-device"
+        "virtio-serial-pci,disable-legacy=off,disable-modern=on"
+        "-device"
+        "virtconsole,chardev=vcon0"
+
+        # Directory of the host for the runtime environment, mounted at /host. The
+        # roottask is a 9P2000.L client via the legacy interface of virtio-9p.
+        "-fsdev"
+        "local,id=host0,path=../qemu_host_share,security_model=none"
+        "-device"
+        "virtio-9p-pci,fsdev=host0,mount_tag=host,disable-legacy=off,disable-modern=on"
+
         # Setup monitor
         "-monitor"
         "vc:1024x768"
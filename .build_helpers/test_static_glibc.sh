@@ -0,0 +1,91 @@
+#!/usr/bin/env bash
+
+# Integration test for statically linked glibc programs. Boots the runtime environment
+# in QEMU with a userland that only contains the glibc build of the C hello world
+# program and an autostart file that starts it. Succeeds, if the program prints its
+# greeting and exits with status 0. Usage: "make" followed by "make test_glibc".
+
+set -e
+
+ANSI_GREEN="\e[32m"
+ANSI_RED="\e[31m"
+ANSI_RESET="\e[0m"
+
+# seconds until the test fails
+TIMEOUT=60
+
+#########################################################################
+# nice "hack" which make the script work, even if not executed from "./"
+DIR=$(dirname "$(realpath "$0")")
+cd "$DIR" || exit
+#########################################################################
+
+BUILD_DIR="../build"
+HEDRON="$BUILD_DIR/hedron.elf32"
+ROOTTASK="$BUILD_DIR/roottask-bin"
+PROGRAM="linux_c_hello_world_glibc.elf"
+
+fn_main() {
+    ./_check_qemu_version.sh
+    for file in "$HEDRON" "$ROOTTASK" "$BUILD_DIR/$PROGRAM"; do
+        if ! [ -f "$file" ]; then
+            echo -e "${ANSI_RED}$file is missing; run make first${ANSI_RESET}"
+            exit 1
+        fi
+    done
+
+    WORK_DIR=$(mktemp -d)
+    trap 'rm -rf "$WORK_DIR"' EXIT
+    fn_build_userland
+    fn_run_qemu
+    fn_check_log
+}
+
+# The roottask mounts the tarball at "/bin" and starts the programs of "/bin/autostart".
+fn_build_userland() {
+    cp "$BUILD_DIR/$PROGRAM" "$WORK_DIR/"
+    echo "/bin/$PROGRAM" > "$WORK_DIR/autostart"
+    (cd "$WORK_DIR" && tar cf userland.tar "$PROGRAM" autostart)
+}
+
+# Runs QEMU until the program exits or the timeout expires.
+fn_run_qemu() {
+    LOG="$WORK_DIR/debugcon.txt"
+    touch "$LOG"
+    qemu-system-x86_64 \
+        -nodefaults \
+        -display none \
+        -machine q35,accel=kvm:tcg \
+        -m 2048M \
+        -cpu host \
+        -kernel "$HEDRON" \
+        -append "serial novga" \
+        -initrd "$ROOTTASK roottask,$WORK_DIR/userland.tar userland" \
+        -debugcon "file:$LOG" &
+    QEMU_PID=$!
+
+    for _ in $(seq "$TIMEOUT"); do
+        if grep -a -q "exited with status" "$LOG"; then
+            break
+        fi
+        sleep 1
+    done
+    kill "$QEMU_PID" 2>/dev/null || true
+    wait "$QEMU_PID" 2>/dev/null || true
+}
+
+fn_check_log() {
+    if ! grep -a -q "hello world from linux written in C" "$LOG"; then
+        echo -e "${ANSI_RED}the glibc program printed no greeting; log:${ANSI_RESET}"
+        tail -n 50 "$LOG"
+        exit 1
+    fi
+    if ! grep -a -q -E "pid=[0-9]+ exited with status 0" "$LOG"; then
+        echo -e "${ANSI_RED}the glibc program didn't exit with status 0; log:${ANSI_RESET}"
+        tail -n 50 "$LOG"
+        exit 1
+    fi
+    echo -e "${ANSI_GREEN}the static glibc program ran to completion${ANSI_RESET}"
+}
+
+fn_main
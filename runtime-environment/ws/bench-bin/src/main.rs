@@ -0,0 +1,315 @@
+//! Benchmarks of a native application that measure the costs of the communication with
+//! the roottask, i.e. cross-PD IPC:
+//! - native system call (`pt_ctrl` on a portal of the own PD; no IPC)
+//! - raw echo call and echo call (cross-PD IPC without and with my portal multiplexing)
+//! - open, write, read & close of a file via the file system service
+//! - allocations on the local heap and via the allocate service
+//!
+//! It prints a consolidated report with statistics per benchmark and stores it in
+//! [`BENCH_RESULTS_DIR`]. If the CPU has performance counters, it also prints the retired
+//! instructions, cache misses, and branch mispredictions per IPC call. If the hybrid benchmark ran before, the report also compares
+//! the costs of a cheap Linux system call of a hybrid foreign application, which the
+//! roottask handles via cross-PD IPC, with the native system call.
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::fs::File;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PdObject,
+    PortalIdentifier,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::Mtd;
+use libhrstd::rt::services::allocate::{
+    alloc_service,
+    dealloc_service,
+};
+use libhrstd::rt::services::echo::{
+    call_echo_service,
+    call_raw_echo_service,
+};
+use libhrstd::rt::services::fs::{
+    fs_service_list_dir,
+    FsListDirRequest,
+    FsOpenFlags,
+};
+use libhrstd::rt::services::perf_counter::{
+    perf_service_info,
+    perf_service_start,
+    perf_service_stop,
+};
+use libhrstd::rt::services::process::process_service_exit;
+use libhrstd::rt::services::stdout::stdout_service;
+use libhrstd::rt::user_logger::UserRustLogger;
+use libhrstd::time::{
+    tsc_freq_khz,
+    Instant,
+};
+use libhrstd::util::bench_report::{
+    BenchReport,
+    BENCH_RESULTS_DIR,
+};
+use libhrstd::util::{
+    ticks_to_ns,
+    BenchHelper,
+    BenchStats,
+};
+
+mod panic;
+
+const WARMUP_ITERATIONS: u64 = 1_000;
+const BENCH_ITERATIONS: u64 = 10_000;
+
+/// Free capability selectors for the local EC and the portal of the native system call
+/// benchmark.
+const BENCH_EC_SEL: u64 = 1000;
+const BENCH_PT_SEL: u64 = 1001;
+
+/// The file of the file system benchmark.
+const BENCH_FILE: &str = "/tmp/native_bench";
+
+/// Source of the reports of the hybrid benchmark under Hedron and the name of the
+/// Linux system call in them that serves as reference for the foreign system call costs.
+const HYBRID_SOURCE: &str = "hybrid_benchmark_hedron";
+const HYBRID_SYSCALL: &str = "set_tid_address syscall";
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+    log::info!("bench started");
+
+    let results = [
+        ("native pt_ctrl syscall", bench_native_syscall()),
+        ("raw echo call", bench(|_| call_raw_echo_service())),
+        ("echo call", bench(|_| call_echo_service())),
+        ("fs open write read close", bench_fs()),
+        ("heap alloc 1 byte", bench_heap_alloc(1)),
+        ("heap alloc 4096 byte", bench_heap_alloc(4096)),
+        ("alloc service 4096 byte", bench_alloc_service(4096)),
+    ];
+
+    let tsc_freq_khz = tsc_freq_khz();
+    let mut report = BenchReport::new(
+        "bench",
+        &libhrstd::build_info!().git_hash,
+        Instant::now().val(),
+    );
+    report.with_tsc_freq_khz(tsc_freq_khz);
+    results.iter().for_each(|(name, stats)| {
+        report.add_stats(name, *stats);
+    });
+
+    print_report(&report, tsc_freq_khz);
+    compare_with_foreign_syscall(results[0].1, tsc_freq_khz);
+    print_perf_counts("raw echo call", call_raw_echo_service);
+    print_perf_counts("echo call", call_echo_service);
+    persist_report(&report);
+    process_service_exit(0);
+}
+
+/// Benchmarks the function with the iteration counts of this program.
+fn bench(fnc: impl FnMut(u64)) -> BenchStats {
+    BenchHelper::<_, WARMUP_ITERATIONS, BENCH_ITERATIONS>::bench_direct_stats(fnc)
+}
+
+fn pt_entry(_id: PortalIdentifier) -> ! {
+    panic!("the portal of the native system call benchmark is never called")
+}
+
+/// A native system call that doesn't leave the PD. The portal only exists to have
+/// something to call `pt_ctrl` on.
+fn bench_native_syscall() -> BenchStats {
+    let self_pd = PdObject::self_in_user_cap_space(UserAppCapSpace::Pd.val());
+    let local_ec = LocalEcObject::create(BENCH_EC_SEL, &self_pd, 0xf00ba1, 0xdeadb000, 0);
+    let pt = PtObject::create(
+        BENCH_PT_SEL,
+        &local_ec,
+        Mtd::DEFAULT,
+        pt_entry,
+        PtCtx::ForeignSyscall,
+    );
+    bench(|i| unsafe {
+        pt.ctrl(i).expect("pt_ctrl must be executed");
+    })
+}
+
+/// Each iteration needs four calls of the file system service.
+fn bench_fs() -> BenchStats {
+    let data = [0xd_u8, 0xe, 0xa, 0xd, 0xb, 0xe, 0xe, 0xf];
+    bench(|_| {
+        let mut file = File::open(
+            BENCH_FILE,
+            FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+            0o644,
+        )
+        .expect("must open the bench file");
+        file.write_all(&data).unwrap();
+        file.lseek(0).unwrap();
+        let read_data = file.read_to_vec().unwrap();
+        assert_eq!(
+            &data,
+            read_data.as_slice(),
+            "written data must equal read data"
+        );
+        file.close().unwrap();
+    })
+}
+
+/// Allocates and frees on the heap of the process. Usually without IPC.
+fn bench_heap_alloc(size: usize) -> BenchStats {
+    bench(|_| {
+        let vec = Vec::<u8>::with_capacity(size);
+        unsafe {
+            let _x = core::ptr::read_volatile(vec.as_ptr());
+        }
+    })
+}
+
+/// Allocates and frees memory of the roottask allocator, i.e. two service calls.
+fn bench_alloc_service(size: usize) -> BenchStats {
+    let layout = Layout::from_size_align(size, 8).unwrap();
+    bench(|_| unsafe {
+        let ptr = alloc_service(layout);
+        assert!(!ptr.is_null(), "allocate service must return memory");
+        dealloc_service(ptr as u64, layout);
+    })
+}
+
+fn print_report(report: &BenchReport, tsc_freq_khz: u64) {
+    stdout_service(&format!(
+        "bench report {} ({} iterations, TSC: {} kHz) [ticks]",
+        report.run_id(),
+        BENCH_ITERATIONS,
+        tsc_freq_khz
+    ));
+    stdout_service(&format!(
+        "  {:<28} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "benchmark", "mean", "min", "median", "p99", "stddev", "mean [ns]"
+    ));
+    for res in report.results() {
+        let stats = res.stats.unwrap();
+        stdout_service(&format!(
+            "  {:<28} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            res.name,
+            stats.mean,
+            stats.min,
+            stats.median,
+            stats.p99,
+            stats.stddev,
+            ticks_to_ns(stats.mean, tsc_freq_khz)
+        ));
+    }
+}
+
+/// Compares the native system call with a Linux system call of the latest report of the
+/// hybrid benchmark, if there is one.
+fn compare_with_foreign_syscall(native: BenchStats, tsc_freq_khz: u64) {
+    let foreign = fs_service_list_dir(FsListDirRequest::new(String::from(BENCH_RESULTS_DIR)))
+        .into_iter()
+        .filter(|path| path.ends_with(".json"))
+        .filter_map(|path| {
+            let mut file = File::open(&path, FsOpenFlags::O_RDONLY, 0).ok()?;
+            let data = file.read_to_vec();
+            let _ = file.close();
+            let json = String::from_utf8(data.ok()?).ok()?;
+            BenchReport::from_json(&json).ok()
+        })
+        .filter(|report| report.source() == HYBRID_SOURCE)
+        .max_by_key(|report| report.timestamp())
+        .and_then(|report| report.get(HYBRID_SYSCALL).map(|ticks| (report, ticks)));
+
+    match foreign {
+        Some((report, ticks)) => stdout_service(&format!(
+            "foreign {} ({}): {} ticks ({} ns), {:.1}x the native pt_ctrl syscall",
+            HYBRID_SYSCALL,
+            report.run_id(),
+            ticks,
+            ticks_to_ns(ticks, tsc_freq_khz),
+            ticks as f64 / native.mean.max(1) as f64
+        )),
+        None => stdout_service(&format!(
+            "no report of {} in {}; run it for the foreign system call overhead",
+            HYBRID_SOURCE, BENCH_RESULTS_DIR
+        )),
+    }
+}
+
+/// Counts the hardware events during [`BENCH_ITERATIONS`] calls of the function and
+/// prints the average per call. The counts include the calls of the performance counter
+/// service itself, which are negligible at this number of iterations.
+fn print_perf_counts(name: &str, fnc: fn()) {
+    let info = perf_service_info();
+    let events = info
+        .events
+        .into_iter()
+        .take(info.num_counters as usize)
+        .collect::<Vec<_>>();
+    if events.is_empty() {
+        stdout_service("no performance counters; skipping the hardware events");
+        return;
+    }
+    if let Err(e) = perf_service_start(&events) {
+        stdout_service(&format!("can't start the performance counters: {:?}", e));
+        return;
+    }
+    for _ in 0..BENCH_ITERATIONS {
+        fnc();
+    }
+    let counts = perf_service_stop().unwrap();
+    let per_call = counts
+        .counts
+        .iter()
+        .map(|(event, count)| format!("{}={}", event.as_str(), count / BENCH_ITERATIONS))
+        .collect::<Vec<_>>();
+    stdout_service(&format!("{} per call: {}", name, per_call.join(", ")));
+}
+
+/// Writes the report as JSON and CSV into the file system, e.g. for the bench tool.
+fn persist_report(report: &BenchReport) {
+    for (path, data) in [
+        (report.path(), report.to_json()),
+        (report.csv_path(), report.to_csv()),
+    ] {
+        let mut file = File::open(&path, FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY, 0o644)
+            .expect("must create the bench report");
+        file.write_all(data.as_bytes()).unwrap();
+        file.close().unwrap();
+    }
+    log::info!(
+        "bench results written to {} and {}",
+        report.path(),
+        report.csv_path()
+    );
+}
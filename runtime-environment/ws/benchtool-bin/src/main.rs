@@ -0,0 +1,121 @@
+//! Lists all benchmark runs that are stored in the file system and compares each run
+//! with the previous run of the same program. Start it after the benchmarks are done.
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::fs::File;
+use libhrstd::rt::services::fs::{
+    fs_service_list_dir,
+    FsListDirRequest,
+    FsOpenFlags,
+};
+use libhrstd::rt::user_logger::UserRustLogger;
+use libhrstd::util::bench_report::{
+    BenchReport,
+    BENCH_RESULTS_DIR,
+};
+
+mod panic;
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+
+    let mut reports = load_reports();
+    // oldest first; makes it easy to find the previous run of each program
+    reports.sort_by(|a, b| {
+        a.source()
+            .cmp(b.source())
+            .then(a.timestamp().cmp(&b.timestamp()))
+    });
+
+    list_runs(&reports);
+    reports
+        .windows(2)
+        .filter(|pair| pair[0].source() == pair[1].source())
+        .for_each(|pair| diff_runs(&pair[0], &pair[1]));
+
+    log::info!("bench tool finished");
+
+    loop {}
+}
+
+/// Loads all reports from [`BENCH_RESULTS_DIR`]. Skips files that are not valid reports.
+fn load_reports() -> Vec<BenchReport> {
+    fs_service_list_dir(FsListDirRequest::new(String::from(BENCH_RESULTS_DIR)))
+        .into_iter()
+        .filter(|path| path.ends_with(".json"))
+        .filter_map(|path| {
+            let report = File::open(&path, FsOpenFlags::O_RDONLY, 0)
+                .and_then(|mut file| {
+                    let data = file.read_to_vec();
+                    let _ = file.close();
+                    data
+                })
+                .ok()
+                .and_then(|data| String::from_utf8(data).ok())
+                .and_then(|json| BenchReport::from_json(&json).ok());
+            if report.is_none() {
+                log::warn!("skipping {}: not a valid bench report", path);
+            }
+            report
+        })
+        .collect()
+}
+
+fn list_runs(reports: &[BenchReport]) {
+    log::info!("{} bench runs in {}", reports.len(), BENCH_RESULTS_DIR);
+    for report in reports {
+        log::info!(
+            "  {} (git={}, {} results)",
+            report.run_id(),
+            report.git_hash(),
+            report.results().len()
+        );
+    }
+}
+
+fn diff_runs(old: &BenchReport, new: &BenchReport) {
+    log::info!("diff {} -> {} [ticks]", old.run_id(), new.run_id());
+    for diff in old.diff(new) {
+        let fmt_value = |value: Option<u64>| value.map_or(String::from("-"), |v| format!("{}", v));
+        let change = diff
+            .change_percent()
+            .map_or(String::new(), |percent| format!(" ({:+.1}%)", percent));
+        log::info!(
+            "  {:<48} {:>12} -> {:>12}{}",
+            diff.name,
+            fmt_value(diff.old),
+            fmt_value(diff.new),
+            change
+        );
+    }
+}
@@ -72,8 +72,28 @@ function fn_build_rust_bin() {
 # some extra checks that I can not cover with the stuff above..
 function fn_build_extra_checks() {
     (
-        cd "libhrstd" || exit
-        cargo check --no-default-features --features foreign_rust_rt
+        # all runtime flavors of libhrstd, not only the ones of the binaries above
+        cargo xtask feature-matrix
+        cd "xtask" || exit
+        cargo fmt # automatically format everything
+    )
+    (
+        # host tool; not a Hedron binary
+        cd "logdecoder-host" || exit
+        cargo build --release
+        cargo fmt # automatically format everything
+    )
+    (
+        # host tool; not a Hedron binary
+        cd "serialxfer-host" || exit
+        cargo build --release
+        cargo fmt # automatically format everything
+    )
+    (
+        # host tool; not a Hedron binary
+        cd "symembed-host" || exit
+        cargo build --release
+        cargo fmt # automatically format everything
     )
 }
 
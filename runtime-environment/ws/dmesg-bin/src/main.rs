@@ -0,0 +1,170 @@
+//! Prints the log of the roottask and adjusts its filters, like `dmesg` on Linux. See
+//! [`libhrstd::rt::services::logging`].
+//!
+//! ```text
+//! native-dmesg-bin [-p PID] [-l LEVEL] [-c]      prints the log; -c clears it afterwards
+//! native-dmesg-bin -n LEVEL [-p PID | -m MODULE] sets the level of a filter
+//! native-dmesg-bin -s on|off                     enables or disables the console output
+//! ```
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::logging::{
+    log_service_clear,
+    log_service_read,
+    log_service_set_console,
+    log_service_set_level,
+    LogLevel,
+    LogScope,
+    LogServiceResponse,
+};
+use libhrstd::rt::services::process::process_service_exit;
+use libhrstd::rt::services::stderr::stderr_service;
+use libhrstd::rt::services::stdout::stdout_service;
+use libhrstd::rt::user_logger::UserRustLogger;
+
+mod panic;
+
+const USAGE: &str = "usage: native-dmesg-bin [-p PID] [-l LEVEL] [-c]\n       \
+                     native-dmesg-bin -n LEVEL [-p PID | -m MODULE]\n       \
+                     native-dmesg-bin -s on|off";
+
+/// What the tool does, according to its arguments.
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    /// Prints the records of the process, if given, with the level or a more severe one.
+    Print {
+        pid: Option<ProcessId>,
+        level: LogLevel,
+        clear: bool,
+    },
+    SetLevel {
+        scope: LogScope,
+        level: LogLevel,
+    },
+    SetConsole(bool),
+}
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+    let args = libhrstd::rt::env::args().skip(1).collect::<Vec<_>>();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            stderr_service(&format!("native-dmesg-bin: {}\n{}", e, USAGE));
+            process_service_exit(2);
+        }
+    };
+    let res = match command {
+        Command::Print { pid, level, clear } => print_log(pid, level, clear),
+        Command::SetLevel { scope, level } => log_service_set_level(scope, level),
+        Command::SetConsole(enabled) => log_service_set_console(enabled),
+    };
+    if let Err(e) = res {
+        stderr_service(&format!("native-dmesg-bin: {:?}", e));
+        process_service_exit(1);
+    }
+    process_service_exit(0);
+}
+
+/// Prints the matching records, one line each. With `clear`, removes all records up to
+/// the last printed one from the log, including those that don't match.
+fn print_log(pid: Option<ProcessId>, level: LogLevel, clear: bool) -> LogServiceResponse {
+    let (entries, next) = log_service_read(0, pid, level);
+    for entry in &entries {
+        stdout_service(&format!("{}", entry));
+    }
+    if clear {
+        log_service_clear(next)?;
+    }
+    Ok(())
+}
+
+fn parse_args(args: &[&str]) -> Result<Command, String> {
+    let mut pid = None;
+    let mut level = None;
+    let mut module = None;
+    let mut filter_level = None;
+    let mut clear = false;
+    let mut console = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match *arg {
+            "-p" => {
+                let value = value()?;
+                pid = Some(
+                    value
+                        .parse::<ProcessId>()
+                        .map_err(|_| format!("invalid PID: {}", value))?,
+                );
+            }
+            "-l" => level = Some(parse_level(value()?)?),
+            "-n" => filter_level = Some(parse_level(value()?)?),
+            "-m" => module = Some(String::from(*value()?)),
+            "-c" => clear = true,
+            "-s" => {
+                console = match *value()? {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    value => return Err(format!("expected on or off: {}", value)),
+                }
+            }
+            _ => return Err(format!("unknown argument: {}", arg)),
+        }
+    }
+
+    match (filter_level, console) {
+        (Some(_), Some(_)) => Err(String::from("-n and -s exclude each other")),
+        (None, Some(enabled)) => Ok(Command::SetConsole(enabled)),
+        (Some(level), None) => {
+            let scope = match (pid, module) {
+                (Some(_), Some(_)) => return Err(String::from("-p and -m exclude each other")),
+                (Some(pid), None) => LogScope::Process(pid),
+                (None, Some(module)) => LogScope::Module(module),
+                (None, None) => LogScope::Default,
+            };
+            Ok(Command::SetLevel { scope, level })
+        }
+        (None, None) if module.is_some() => Err(String::from("-m needs -n")),
+        (None, None) => Ok(Command::Print {
+            pid,
+            level: level.unwrap_or(LogLevel::Trace),
+            clear,
+        }),
+    }
+}
+
+fn parse_level(name: &str) -> Result<LogLevel, String> {
+    LogLevel::parse(name).ok_or_else(|| format!("unknown level: {}", name))
+}
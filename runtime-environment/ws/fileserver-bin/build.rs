@@ -1,3 +1,6 @@
+include!("../libhrstd/build_helpers/build_info_env.rs");
+
 fn main() {
     println!("cargo:rerun-if-changed=src/link.ld");
+    emit_build_info_env();
 }
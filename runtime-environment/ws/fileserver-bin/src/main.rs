@@ -1,3 +1,12 @@
+//! File server that runs as user app instead of inside the roottask. It keeps its own
+//! in-memory file system ([`libfileserver`]) and hosts it as service under
+//! [`FILE_SERVER_NAME`] in the name service. Clients find it with
+//! [`libhrstd::rt::services::name::name_service_lookup`] and call it with the functions of
+//! [`libhrstd::rt::services::fileserver`]. The roottask creates one portal per client; the
+//! file server separates the file descriptors of its clients by their PIDs.
+//!
+//! Clients must run on the same CPU as the file server.
+
 #![no_std]
 #![no_main]
 #![deny(
@@ -26,7 +35,82 @@
 #[macro_use]
 extern crate alloc;
 
+use libfileserver::FILESYSTEM;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fileserver::{
+    FileServer,
+    FileServerError,
+    FileServerRequest,
+    FILE_SERVER_MAX_IO_LEN,
+    FILE_SERVER_NAME,
+};
+use libhrstd::rt::services::fs::FD;
+use libhrstd::rt::services::name::{
+    name_service_host,
+    ServiceAccess,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::rt::services::timer::{
+    timer_service_create_periodic,
+    timer_service_wait,
+};
+use libhrstd::rt::user_logger::UserRustLogger;
+
 mod panic;
 
+/// The main EC has nothing to do after the start; it wakes up once per second.
+const IDLE_PERIOD_NS: u64 = 1_000_000_000;
+
 #[no_mangle]
-fn start() {}
+fn start() {
+    UserRustLogger::init();
+    name_service_host(FILE_SERVER_NAME, ServiceAccess::Everyone, handle_request)
+        .expect("can't host the file server");
+    log::info!("file server is ready");
+
+    // the service EC handles all calls
+    let timer = timer_service_create_periodic(IDLE_PERIOD_NS).unwrap();
+    loop {
+        timer_service_wait(timer);
+    }
+}
+
+/// Handles a call of `client` to the file server.
+fn handle_request(client: ProcessId, utcb: &mut Utcb) {
+    let request = utcb.load_data::<FileServerRequest>().unwrap();
+    let mut fs = FILESYSTEM.lock();
+    match request {
+        FileServerRequest::Open(request) => rpc_serve::<FileServer, _>(request, utcb, |r| {
+            fs.open_or_create_file(client, &r.path, r.flags, r.umode)
+                .map(|fd| FD::new(fd.val() as _))
+                .map_err(FileServerError::Fs)
+        }),
+        FileServerRequest::Read(request) => rpc_serve::<FileServer, _>(request, utcb, |r| {
+            if r.count > FILE_SERVER_MAX_IO_LEN {
+                return Err(FileServerError::TooLarge);
+            }
+            fs.read_file(client, fd(r.fd), r.count)
+                .map(|data| data.to_vec())
+                .map_err(FileServerError::Fs)
+        }),
+        FileServerRequest::Write(request) => rpc_serve::<FileServer, _>(request, utcb, |r| {
+            if r.data.len() > FILE_SERVER_MAX_IO_LEN {
+                return Err(FileServerError::TooLarge);
+            }
+            fs.write_file(client, fd(r.fd), &r.data)
+                .map_err(FileServerError::Fs)
+        }),
+        FileServerRequest::LSeek(request) => rpc_serve::<FileServer, _>(request, utcb, |r| {
+            fs.lseek_file(client, fd(r.fd), r.offset as usize)
+                .map_err(FileServerError::Fs)
+        }),
+        FileServerRequest::Close(request) => rpc_serve::<FileServer, _>(request, utcb, |r| {
+            fs.close_file(client, fd(r.fd)).map_err(FileServerError::Fs)
+        }),
+    }
+}
+
+fn fd(fd: FD) -> libfileserver::FileDescriptor {
+    (fd.raw() as u64).into()
+}
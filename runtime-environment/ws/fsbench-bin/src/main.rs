@@ -0,0 +1,329 @@
+//! Contention benchmark of the file system service. Launches N worker processes (copies of
+//! this program) that write to the file system at the same time, either each to its own
+//! file ("disjoint") or all to the same file ("shared"). It reports how the throughput
+//! scales with N and how much the latency of a call grows compared to the run without
+//! concurrency (N=1). If the roottask is built with the feature `lock_stats`, it also
+//! reports how long the roottask waited for its locks per operation, taken from
+//! [`LOCK_STATS_FILE`].
+//!
+//! Each worker writes its measurements into a file below [`RESULTS_DIR`]; the
+//! coordinator combines them into a bench report in the file system.
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::fs::File;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::env;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::process::{
+    process_service,
+    process_service_exit,
+    process_service_status,
+    ProcessServiceRequest,
+    ProcessStatus,
+};
+use libhrstd::rt::user_logger::UserRustLogger;
+use libhrstd::time::{
+    tsc_freq_khz,
+    Instant,
+};
+use libhrstd::util::bench_report::BenchReport;
+use libhrstd::util::percentile;
+
+mod panic;
+
+/// Path of this program; the workers are copies of it.
+const PROGRAM: &str = "/bin/native-fsbench-bin";
+
+/// Numbers of concurrent workers that get measured.
+const WORKER_COUNTS: [usize; 3] = [1, 2, 4];
+
+/// Operations (seek + write) per worker.
+const OPS_PER_WORKER: usize = 2000;
+
+/// Bytes per write.
+const WRITE_SIZE: usize = 64;
+
+/// Files that the workers write to and their results.
+const RESULTS_DIR: &str = "/tmp/fsbench";
+
+/// Contention statistics of the locks of the roottask.
+const LOCK_STATS_FILE: &str = "/proc/roottask/locks";
+
+/// Ticks between the launch of the first worker and the common start of all workers.
+/// Long enough for the roottask to start the workers. Roughly 100ms on a 2 GHz CPU.
+const START_DELAY_TICKS: u64 = 200_000_000;
+
+/// Whether the workers write to their own file or to the same file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Disjoint,
+    Shared,
+}
+
+impl Mode {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Disjoint => "disjoint",
+            Self::Shared => "shared",
+        }
+    }
+}
+
+/// Measurements of one worker.
+#[derive(Debug)]
+struct WorkerResult {
+    /// TSC values when the worker started and finished its operations.
+    begin: u64,
+    end: u64,
+    /// Duration of each operation in ticks, sorted.
+    latencies: Vec<u64>,
+}
+
+impl WorkerResult {
+    /// Encodes the result as one line of decimal numbers.
+    fn to_line(&self) -> String {
+        let mut line = format!("{} {}", self.begin, self.end);
+        for latency in &self.latencies {
+            line.push_str(&format!(" {}", latency));
+        }
+        line
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut values = line.split_whitespace().map(|val| val.parse::<u64>().ok());
+        let begin = values.next()??;
+        let end = values.next()??;
+        let latencies = values.collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            begin,
+            end,
+            latencies,
+        })
+    }
+}
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+    let args = env::args().collect::<Vec<_>>();
+    match args.as_slice() {
+        [_, "worker", file, result_file, start_at] => {
+            let start_at = start_at.parse().expect("start time must be a number");
+            worker(file, result_file, start_at);
+            process_service_exit(0);
+        }
+        _ => coordinator(),
+    }
+}
+
+/// Launches the workers for each mode and worker count and writes the bench report.
+fn coordinator() {
+    log::info!("fs bench started");
+    let mut report = BenchReport::new(
+        "fs_bench",
+        &libhrstd::build_info!().git_hash,
+        Instant::now().val(),
+    );
+    report.with_tsc_freq_khz(tsc_freq_khz());
+
+    let mut baseline_p50 = None;
+    for mode in [Mode::Disjoint, Mode::Shared] {
+        for workers in WORKER_COUNTS {
+            let lock_wait_before = lock_wait_ticks();
+            let results = match run(mode, workers) {
+                Some(results) => results,
+                None => {
+                    log::warn!("{} with {} workers failed", mode.name(), workers);
+                    continue;
+                }
+            };
+            let span = results.iter().map(|res| res.end).max().unwrap()
+                - results.iter().map(|res| res.begin).min().unwrap();
+            let mut latencies = results
+                .into_iter()
+                .flat_map(|res| res.latencies)
+                .collect::<Vec<_>>();
+            latencies.sort_unstable();
+            let ops = latencies.len() as u64;
+            let ticks_per_op = span / ops;
+            let p50 = percentile(&latencies, 50);
+            let p99 = percentile(&latencies, 99);
+            // the baseline is the first measurement without concurrency
+            let baseline_p50 = *baseline_p50.get_or_insert(p50);
+            let p50_increase = p50.saturating_sub(baseline_p50);
+            // includes the launch of the workers and the polling of the coordinator
+            let lock_wait_per_op = lock_wait_before
+                .zip(lock_wait_ticks())
+                .map(|(before, after)| after.saturating_sub(before) / ops);
+
+            log::info!(
+                "{:>8}, {} workers: {} ticks/op, latency [ticks] p50={} p99={}, p50 increase={}, lock wait/op={:?}",
+                mode.name(),
+                workers,
+                ticks_per_op,
+                p50,
+                p99,
+                p50_increase,
+                lock_wait_per_op
+            );
+            let prefix = format!("fs contention [{}, n={}]", mode.name(), workers);
+            report.add(&format!("{} ticks per op", prefix), ticks_per_op);
+            report.add(&format!("{} latency p50", prefix), p50);
+            report.add(&format!("{} latency p99", prefix), p99);
+            report.add(&format!("{} latency p50 increase", prefix), p50_increase);
+            if let Some(lock_wait_per_op) = lock_wait_per_op {
+                report.add(&format!("{} lock wait per op", prefix), lock_wait_per_op);
+            }
+        }
+    }
+
+    let mut file = File::open(
+        &report.path(),
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+        0o644,
+    )
+    .expect("must create the bench report");
+    file.write_all(report.to_json().as_bytes()).unwrap();
+    file.close().unwrap();
+    log::info!("bench results written to {}", report.path());
+    process_service_exit(0);
+}
+
+/// Returns the total wait of all locks of the roottask in ticks, or `None` if the roottask
+/// doesn't record lock statistics.
+fn lock_wait_ticks() -> Option<u64> {
+    let mut file = File::open(LOCK_STATS_FILE, FsOpenFlags::O_RDONLY, 0).ok()?;
+    let content = String::from_utf8(file.read_to_vec().ok()?).ok()?;
+    let _ = file.close();
+    if content.starts_with("disabled") {
+        return None;
+    }
+    // one lock per line: "lock <addr> (<site>): ..., wait [ticks] total=<ticks> avg=..."
+    let total = content
+        .lines()
+        .filter_map(|line| line.split_once("total="))
+        .filter_map(|(_, rest)| rest.split_whitespace().next()?.parse::<u64>().ok())
+        .sum();
+    Some(total)
+}
+
+/// Launches the workers, waits for them, and collects their results.
+fn run(mode: Mode, workers: usize) -> Option<Vec<WorkerResult>> {
+    let start_at = Instant::now().val() + START_DELAY_TICKS;
+    let mut pids = Vec::<(ProcessId, String)>::new();
+    for i in 0..workers {
+        let file = match mode {
+            Mode::Disjoint => format!("{}/data-{}-{}", RESULTS_DIR, workers, i),
+            Mode::Shared => format!("{}/data-{}", RESULTS_DIR, workers),
+        };
+        let result_file = format!("{}/result-{}-{}-{}", RESULTS_DIR, mode.name(), workers, i);
+        let request = ProcessServiceRequest::Launch {
+            path: String::from(PROGRAM),
+            argv: vec![
+                String::from(PROGRAM),
+                String::from("worker"),
+                file,
+                result_file.clone(),
+                format!("{}", start_at),
+            ],
+            envp: Vec::new(),
+            sched_params: None,
+            cpu: None,
+            preopened: Vec::new(),
+            aslr: false,
+            debug: false,
+            trace: false,
+        };
+        match process_service(request) {
+            Ok(pid) => pids.push((pid, result_file)),
+            Err(e) => {
+                log::warn!("can't launch worker: {:?}", e);
+                return None;
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    for (pid, result_file) in pids {
+        loop {
+            match process_service_status(pid) {
+                Ok(ProcessStatus::Running) => core::hint::spin_loop(),
+                Ok(ProcessStatus::Exited(0)) => break,
+                status => {
+                    log::warn!("worker {} failed: {:?}", pid, status);
+                    return None;
+                }
+            }
+        }
+        let mut file = File::open(&result_file, FsOpenFlags::O_RDONLY, 0).ok()?;
+        let content = String::from_utf8(file.read_to_vec().ok()?).ok()?;
+        let _ = file.close();
+        results.push(WorkerResult::parse(&content)?);
+    }
+    Some(results)
+}
+
+/// Waits for the common start and measures the operations on `file`.
+fn worker(file: &str, result_file: &str, start_at: u64) {
+    let mut file = File::open(file, FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR, 0o644)
+        .expect("must open the data file");
+    let data = [0xaa; WRITE_SIZE];
+    while Instant::now().val() < start_at {
+        core::hint::spin_loop();
+    }
+
+    let mut latencies = Vec::with_capacity(OPS_PER_WORKER);
+    let begin = Instant::now().val();
+    for _ in 0..OPS_PER_WORKER {
+        let op_begin = Instant::now().val();
+        file.lseek(0).unwrap();
+        file.write_all(&data).unwrap();
+        latencies.push(Instant::now().val() - op_begin);
+    }
+    let end = Instant::now().val();
+    file.close().unwrap();
+    latencies.sort_unstable();
+
+    let result = WorkerResult {
+        begin,
+        end,
+        latencies,
+    };
+    let mut file = File::open(
+        result_file,
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+        0o644,
+    )
+    .expect("must create the result file");
+    file.write_all(result.to_line().as_bytes()).unwrap();
+    file.close().unwrap();
+}
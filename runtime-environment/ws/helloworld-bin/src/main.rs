@@ -39,6 +39,17 @@ use libhrstd::kobjects::{
 };
 use libhrstd::libhedron::Mtd;
 use libhrstd::mem::UserPtrOrEmbedded;
+use libhrstd::rt::services::build_info::build_info_service;
+use libhrstd::rt::services::fs::{
+    fs_register_buffer,
+    fs_unregister_buffer,
+    FsBufferRef,
+};
+use libhrstd::rt::services::fs::{
+    fs_ring_setup,
+    fs_submit_batch,
+    FsRingOp,
+};
 use libhrstd::rt::services::fs::{
     fs_service_lseek,
     FsLseekRequest,
@@ -66,6 +77,9 @@ mod panic;
 #[no_mangle]
 fn start() {
     UserRustLogger::init();
+    check_build_info();
+    log::info!("args: {:?}", libhrstd::rt::env::args().collect::<Vec<_>>());
+    log::info!("vars: {:?}", libhrstd::rt::env::vars().collect::<Vec<_>>());
     let msg = "Hallo Welt Lorem Ipsum Dolor sit Damet.";
     stdout_service(msg);
     stderr_service(msg);
@@ -83,6 +97,10 @@ fn start() {
 
     fs_test_file_abstraction();
 
+    fs_test_ring();
+
+    fs_test_registered_buffer();
+
     hedron_bench_native_syscall();
 
     log::info!("Hedron-native Hello World finished!");
@@ -90,27 +108,46 @@ fn start() {
     loop {}
 }
 
+/// Warns if this binary and the roottask were not built from the same commit, which
+/// usually means that the userland tarball of the image is stale.
+fn check_build_info() {
+    let own = libhrstd::build_info!();
+    let response = build_info_service();
+    log::info!("build info: {}", own);
+    log::info!("platform: {:x?}", response.platform);
+    if !own.same_commit(&response.roottask) {
+        log::warn!(
+            "build info mismatch: roottask is {}, but this binary is {}",
+            response.roottask.git_hash,
+            own.git_hash
+        );
+    }
+}
+
 fn fs_test_direct_ipc_calls() {
     let fd = fs_service_open(FsOpenRequest::new(
         String::from("/foo/bar"),
         FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
         0o777,
-    ));
+    ))
+    .unwrap();
 
     fs_service_write(FsWriteRequest::new(
         fd,
         UserPtrOrEmbedded::new_slice(b"Hallo Welt!"),
         b"Hallo Welt!".len(),
-    ));
+    ))
+    .unwrap();
 
-    fs_service_lseek(FsLseekRequest::new(fd, "Hallo ".len() as u64));
+    fs_service_lseek(FsLseekRequest::new(fd, "Hallo ".len() as u64)).unwrap();
     let mut read_buf = Vec::with_capacity(100);
 
     let read_bytes = fs_service_read(FsReadRequest::new(
         fd,
         read_buf.as_mut_ptr() as usize,
         read_buf.capacity(),
-    ));
+    ))
+    .unwrap();
 
     unsafe {
         read_buf.set_len(read_bytes);
@@ -118,14 +155,15 @@ fn fs_test_direct_ipc_calls() {
     let read = String::from_utf8(read_buf).unwrap();
     assert_eq!(read, "Welt!");
 
-    fs_service_lseek(FsLseekRequest::new(fd, 0));
+    fs_service_lseek(FsLseekRequest::new(fd, 0)).unwrap();
     let mut read_buf = Vec::with_capacity(100);
 
     let read_bytes = fs_service_read(FsReadRequest::new(
         fd,
         read_buf.as_mut_ptr() as usize,
         read.capacity(),
-    ));
+    ))
+    .unwrap();
     unsafe {
         read_buf.set_len(read_bytes);
     };
@@ -134,16 +172,75 @@ fn fs_test_direct_ipc_calls() {
     assert_eq!(read, "Hallo Welt!")
 }
 
+fn fs_test_ring() {
+    let fd = fs_service_open(FsOpenRequest::new(
+        String::from("/foo/ring"),
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+        0o777,
+    ))
+    .unwrap();
+    let ring = fs_ring_setup();
+    let completions = fs_submit_batch(
+        &ring,
+        &[
+            FsRingOp::Write {
+                fd,
+                data: b"Hallo ",
+            },
+            FsRingOp::Write { fd, data: b"Ring!" },
+            FsRingOp::LSeek { fd, offset: 0 },
+            FsRingOp::Read { fd, count: 100 },
+        ],
+    );
+    let results = completions.iter().map(|c| c.result).collect::<Vec<_>>();
+    assert_eq!(results, [6, 5, 0, 11]);
+    assert_eq!(completions[3].data, b"Hallo Ring!");
+}
+
+fn fs_test_registered_buffer() {
+    let fd = fs_service_open(FsOpenRequest::new(
+        String::from("/foo/buffer"),
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+        0o777,
+    ))
+    .unwrap();
+    // bigger than the UTCB: the data only goes through the registered buffer
+    let mut buf = vec![0_u8; 3 * 4096];
+    let id = fs_register_buffer(&mut buf).unwrap();
+    let (data, copy) = buf.split_at_mut(6144);
+    data.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+
+    let bytes = fs_service_write(FsWriteRequest::new_registered(
+        fd,
+        FsBufferRef::new(id, 0),
+        data.len(),
+    ))
+    .unwrap();
+    assert_eq!(bytes, data.len(), "must write the whole buffer");
+    fs_service_lseek(FsLseekRequest::new(fd, 0)).unwrap();
+    let bytes = fs_service_read(FsReadRequest::new_registered(
+        fd,
+        FsBufferRef::new(id, data.len()),
+        copy.len(),
+    ))
+    .unwrap();
+    assert_eq!(bytes, data.len(), "must read the whole file");
+    assert_eq!(&copy[..bytes], data, "must read the written data");
+
+    fs_unregister_buffer(id).unwrap();
+}
+
 fn fs_test_file_abstraction() {
-    let mut file = File::open("foo.bar", FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR, 0o777);
+    let mut file =
+        File::open("foo.bar", FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR, 0o777).unwrap();
     let msg = b"na moin\n";
-    let bytes = file.write_all(msg);
+    let bytes = file.write_all(msg).unwrap();
     assert_eq!(bytes, msg.len(), "must write the expected number of bytes!");
     let msg = b"Wie gehts?\n";
-    let bytes = file.write_all(msg);
+    let bytes = file.write_all(msg).unwrap();
     assert_eq!(bytes, msg.len(), "must write the expected number of bytes!");
-    file.lseek(0);
-    let data = file.read_to_vec();
+    file.lseek(0).unwrap();
+    let data = file.read_to_vec().unwrap();
     let full_msg = "na moin\nWie gehts?\n";
     assert_eq!(
         data.len(),
@@ -161,7 +258,7 @@ fn hedron_bench_native_syscall() {
     let self_pd = PdObject::self_in_user_cap_space(UserAppCapSpace::Pd.val());
     // I never use the local ec; i.e. call a PT on it; I just need it to attach a PT to it for the
     // benchmark.
-    let local_ec = LocalEcObject::create(1000, &self_pd, 0xf00ba1, 0xdeadb000);
+    let local_ec = LocalEcObject::create(1000, &self_pd, 0xf00ba1, 0xdeadb000, 0);
     // some PT I never use; I just need it to be created
     let pt = PtObject::create(
         1001,
@@ -0,0 +1,109 @@
+//! Interface between the [`crate::Filesystem`] facade and the file systems that are
+//! mounted into it. See [`FsBackend`].
+
+use crate::inode::INode;
+use crate::FileStat;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsOpenFlags,
+};
+
+/// A file system that can be mounted into the [`crate::Filesystem`], for example the
+/// in-memory file system.
+///
+/// The facade takes care of the open file table, file offsets, and the mount point. Hence,
+/// a backend only sees paths relative to its mount point. They always start with a slash.
+/// Files are identified by an [`INode`] that is unique within the backend. Errors are
+/// reported as [`FsError`]; an unknown [`INode`] is [`FsError::NotFound`].
+pub trait FsBackend: Debug {
+    /// Looks up the file at `path`. If it doesn't exist and `flags` permit it, the file
+    /// gets created with the given `umode`. Returns the [`INode`] of the file.
+    fn open(
+        &mut self,
+        caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        umode: u16,
+    ) -> Result<INode, FsError>;
+
+    /// Looks up the file at `path` without opening it. Returns the [`INode`] of the file.
+    fn lookup(&self, path: &str) -> Result<INode, FsError>;
+
+    /// Returns the process that created the file, or `None` if the backend doesn't track
+    /// owners.
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError>;
+
+    /// Reads at most `count` bytes, starting at `offset`. Returns less bytes at the end of
+    /// the file. Devices may return less bytes at any time and generate the data on each
+    /// read, hence the mutable access.
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError>;
+
+    /// Writes the data at `offset`. Returns the number of written bytes.
+    fn write(&mut self, i_node: INode, offset: usize, data: &[u8]) -> Result<usize, FsError>;
+
+    /// Shrinks or grows the file to `len` bytes. New bytes are zero. Backends whose files
+    /// have a fixed size, such as devices, fail with [`FsError::InvalidArgument`], like
+    /// Linux.
+    fn truncate(&mut self, _i_node: INode, _len: usize) -> Result<(), FsError> {
+        Err(FsError::InvalidArgument)
+    }
+
+    /// Returns the metadata of a file.
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError>;
+
+    /// Removes a file from the namespace of the backend.
+    fn unlink(&mut self, path: &str) -> Result<(), FsError>;
+
+    /// Returns the paths of all files below `dir`, including those in subdirectories.
+    /// `dir` ends with a slash. The order is not specified.
+    fn readdir(&self, dir: &str) -> Vec<String>;
+
+    /// Replaces the content of the file at `path` or adds the file, even if the backend is
+    /// read-only otherwise. Lets developers replace programs of the boot image without a
+    /// reboot. Backends that don't support it fail.
+    fn replace(&mut self, _path: &str, _data: Vec<u8>) -> Result<(), FsError> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Moves the file at `from` to `to` and replaces a file at `to`. Renaming a directory
+    /// moves all files below it and only replaces an empty directory. Like POSIX, fails
+    /// with [`FsError::IsDir`], [`FsError::NotDir`], or [`FsError::NotEmpty`] otherwise,
+    /// without moving anything. Backends that don't support it fail.
+    fn rename(&mut self, _from: &str, _to: &str) -> Result<(), FsError> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Adds `new` as another path of the file at `existing`, i.e. a hard link. Fails if
+    /// `new` exists. Backends that don't support it fail.
+    fn link(&mut self, _existing: &str, _new: &str) -> Result<(), FsError> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Creates an empty directory at `path` with the given `umode`. Fails if a file or a
+    /// directory exists at `path`. Backends that don't support it fail.
+    fn mkdir(&mut self, _caller: ProcessId, _path: &str, _umode: u16) -> Result<(), FsError> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Removes the directory at `path`. Fails with [`FsError::NotEmpty`] if files are below
+    /// it. Backends that don't support it fail.
+    fn rmdir(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Creates a symbolic link at `path` that points to `target`. The backend stores the
+    /// target as it is; the facade resolves it. Backends that don't support it fail.
+    fn symlink(&mut self, _caller: ProcessId, _target: &str, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Unsupported)
+    }
+
+    /// Returns the target of the symbolic link at `path`. Fails with
+    /// [`FsError::InvalidArgument`] if the file is no symbolic link or the backend has none.
+    fn readlink(&self, _path: &str) -> Result<String, FsError> {
+        Err(FsError::InvalidArgument)
+    }
+}
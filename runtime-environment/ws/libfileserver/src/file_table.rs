@@ -1,8 +1,13 @@
 use crate::inode::INode;
+use crate::mount::MountId;
 use crate::FileDescriptor;
 use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use libhrstd::process::consts::ProcessId;
-use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsOpenFlags,
+};
 
 /// Holds information about all files that are currently open inside the system.
 #[derive(Debug)]
@@ -18,22 +23,23 @@ impl OpenFileTable {
     }
 
     /// Marks a file as opened and returns a [`FileDescriptor`] that identifies that entry.
+    /// `mount` is `None` for objects that are no files, such as sockets.
     pub(crate) fn open(
         &mut self,
         pid: ProcessId,
+        mount: Option<MountId>,
         inode: INode,
         flags: FsOpenFlags,
-    ) -> Result<FileDescriptor, ()> {
+    ) -> Result<FileDescriptor, FsError> {
         let fd = self.find_next_fd(pid);
         let key = (pid, fd);
-        let value = OpenFileHandle::new(flags, inode);
+        let value = OpenFileHandle::new(flags, mount, inode);
         self.data.insert(key, value);
         Ok(fd)
     }
 
     /// Checks if the given process has an opened file with the given file descriptor.
     /// If so, it returns the handle to the open file.
-    #[allow(unused)]
     pub(crate) fn lookup_handle(
         &self,
         pid: ProcessId,
@@ -58,10 +64,110 @@ impl OpenFileTable {
             .map(|(_id, val)| val)
     }
 
+    /// Moves an open file from one process to another process, where it gets the file
+    /// descriptor `to_fd`. Fails with [`FsError::Busy`] if `to_fd` is in use.
+    pub(crate) fn transfer(
+        &mut self,
+        from_pid: ProcessId,
+        from_fd: FileDescriptor,
+        to_pid: ProcessId,
+        to_fd: FileDescriptor,
+    ) -> Result<(), FsError> {
+        if self.check_fd_is_in_use(to_pid, to_fd) {
+            return Err(FsError::Busy);
+        }
+        let handle = self
+            .data
+            .remove(&(from_pid, from_fd))
+            .ok_or(FsError::BadFd)?;
+        self.data.insert((to_pid, to_fd), handle);
+        Ok(())
+    }
+
+    /// Gives `to_pid` a copy of the open file `from_fd` of `from_pid` at the file descriptor
+    /// `to_fd`. The copy has its own file offset, which starts at the offset of the
+    /// original. Fails with [`FsError::Busy`] if `to_fd` is in use and with
+    /// [`FsError::Unsupported`] for objects that are no files.
+    pub(crate) fn duplicate(
+        &mut self,
+        from_pid: ProcessId,
+        from_fd: FileDescriptor,
+        to_pid: ProcessId,
+        to_fd: FileDescriptor,
+    ) -> Result<(), FsError> {
+        let handle = self.data.get(&(from_pid, from_fd)).ok_or(FsError::BadFd)?;
+        if handle.mount().is_none() {
+            return Err(FsError::Unsupported);
+        }
+        if self.check_fd_is_in_use(to_pid, to_fd) {
+            return Err(FsError::Busy);
+        }
+        let handle = handle.clone();
+        self.data.insert((to_pid, to_fd), handle);
+        Ok(())
+    }
+
+    /// Returns the file descriptors of the open files of the process. Objects that are no
+    /// files, such as sockets, are not included.
+    pub(crate) fn files(&self, pid: ProcessId) -> Vec<FileDescriptor> {
+        self.data
+            .iter()
+            .filter(|((id_pid, _), handle)| *id_pid == pid && handle.mount().is_some())
+            .map(|((_, fd), _)| *fd)
+            .collect()
+    }
+
+    /// Returns all file descriptors of the process, including the ones of objects that are
+    /// no files.
+    pub(crate) fn fds(&self, pid: ProcessId) -> Vec<FileDescriptor> {
+        self.data
+            .keys()
+            .filter(|(id_pid, _)| *id_pid == pid)
+            .map(|(_, fd)| *fd)
+            .collect()
+    }
+
+    /// Exchanges the open files of two processes. Objects that are no files, such as
+    /// sockets, stay where they are. Fails with [`FsError::Busy`] without changing anything
+    /// if a file would get the file descriptor of such an object.
+    pub(crate) fn swap_files(&mut self, a: ProcessId, b: ProcessId) -> Result<(), FsError> {
+        let (files_a, files_b) = (self.files(a), self.files(b));
+        let collides = |files: &[FileDescriptor], other: ProcessId| {
+            files.iter().any(|fd| {
+                self.data
+                    .get(&(other, *fd))
+                    .map_or(false, |handle| handle.mount().is_none())
+            })
+        };
+        if collides(&files_a, b) || collides(&files_b, a) {
+            return Err(FsError::Busy);
+        }
+        let handles_a = files_a
+            .into_iter()
+            .map(|fd| (fd, self.data.remove(&(a, fd)).unwrap()))
+            .collect::<Vec<_>>();
+        let handles_b = files_b
+            .into_iter()
+            .map(|fd| (fd, self.data.remove(&(b, fd)).unwrap()))
+            .collect::<Vec<_>>();
+        self.data
+            .extend(handles_a.into_iter().map(|(fd, handle)| ((b, fd), handle)));
+        self.data
+            .extend(handles_b.into_iter().map(|(fd, handle)| ((a, fd), handle)));
+        Ok(())
+    }
+
     /// Closes a file.
-    pub(crate) fn close(&mut self, caller: ProcessId, fd: FileDescriptor) -> Result<(), ()> {
+    pub(crate) fn close(&mut self, caller: ProcessId, fd: FileDescriptor) -> Result<(), FsError> {
         let key = (caller, fd);
-        self.data.remove(&key).map(|_| ()).ok_or(())
+        self.data.remove(&key).map(|_| ()).ok_or(FsError::BadFd)
+    }
+
+    /// Checks if any process has an open file that belongs to the mount.
+    pub(crate) fn is_mount_in_use(&self, mount: MountId) -> bool {
+        self.data
+            .values()
+            .any(|handle| handle.mount() == Some(mount))
     }
 
     /// Checks if the passed [`FileDescriptor`]
@@ -74,7 +180,7 @@ impl OpenFileTable {
     }
 
     /// Returns the next available file descriptor for a process.
-    fn find_next_fd(&self, pid: ProcessId) -> FileDescriptor {
+    pub(crate) fn find_next_fd(&self, pid: ProcessId) -> FileDescriptor {
         // 0-2 reserved for stdin, stdout, stderr
         const MIN_FD: u64 = 3;
 
@@ -93,19 +199,22 @@ impl OpenFileTable {
 type OpenFileHandleId = (ProcessId, FileDescriptor);
 
 /// Describes an opened file.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct OpenFileHandle {
-    // used as ID
+    /// The backend that holds the file.
+    mount: Option<MountId>,
+    // used as ID; unique within the backend
     i_node: INode,
     pub(crate) file_offset: usize,
     flags: FsOpenFlags,
 }
 
 impl OpenFileHandle {
-    pub(crate) fn new(flags: FsOpenFlags, i_node: INode) -> Self {
+    pub(crate) fn new(flags: FsOpenFlags, mount: Option<MountId>, i_node: INode) -> Self {
         OpenFileHandle {
             file_offset: 0,
             flags,
+            mount,
             i_node,
         }
     }
@@ -116,6 +225,9 @@ impl OpenFileHandle {
     pub(crate) fn flags(&self) -> FsOpenFlags {
         self.flags
     }
+    pub(crate) fn mount(&self) -> Option<MountId> {
+        self.mount
+    }
     pub(crate) fn i_node(&self) -> INode {
         self.i_node
     }
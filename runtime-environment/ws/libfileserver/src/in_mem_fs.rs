@@ -1,35 +1,104 @@
+use crate::backend::FsBackend;
 use crate::inode::INode;
+use crate::{
+    now_ns,
+    FileStat,
+    ACCOUNTING,
+    INODE_ALLOCATOR,
+    MAX_FILE_SIZE,
+};
 use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::cmp::{
+    max,
+    min,
+};
 use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsOpenFlags,
+};
 
+/// The timestamps are nanoseconds since the Unix epoch, see [`crate::set_clock`].
 #[derive(Debug)]
 pub(crate) struct FileMetaData {
     umode: u16,
     owner: ProcessId,
+    /// Time of the last read.
+    atime_ns: u64,
+    /// Time of the last write.
+    mtime_ns: u64,
+    /// Time of the last change of the content or the metadata.
+    ctime_ns: u64,
 }
 
 impl FileMetaData {
     pub(crate) fn new(umode: u16, owner: ProcessId) -> Self {
-        FileMetaData { umode, owner }
+        let now = now_ns();
+        FileMetaData {
+            umode,
+            owner,
+            atime_ns: now,
+            mtime_ns: now,
+            ctime_ns: now,
+        }
     }
 
     pub(crate) fn umode(&self) -> u16 {
         self.umode
     }
-    #[allow(unused)]
     pub(crate) fn owner(&self) -> ProcessId {
         self.owner
     }
+    pub(crate) fn atime_ns(&self) -> u64 {
+        self.atime_ns
+    }
+    pub(crate) fn mtime_ns(&self) -> u64 {
+        self.mtime_ns
+    }
+    pub(crate) fn ctime_ns(&self) -> u64 {
+        self.ctime_ns
+    }
+
+    /// Updates the timestamps after a read.
+    fn accessed(&mut self) {
+        self.atime_ns = now_ns();
+    }
+
+    /// Updates the timestamps after a change of the metadata, e.g. the number of links.
+    fn changed(&mut self) {
+        self.ctime_ns = now_ns();
+    }
+
+    /// Updates the timestamps after a write.
+    fn modified(&mut self) {
+        let now = now_ns();
+        self.mtime_ns = now;
+        self.ctime_ns = now;
+    }
+}
+
+/// Kind of an [`InMemFile`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum FileKind {
+    Regular,
+    /// The data of the file is the target path.
+    Symlink,
+    /// An empty directory created by [`FsBackend::mkdir`]. Otherwise, directories only
+    /// exist implicitly as the prefix of the paths of files.
+    Dir,
 }
 
-/// An in-memory file.
+/// An in-memory file. The file itself doesn't know its paths, because hard links give it
+/// multiple paths, see [`InMemFilesystem`].
 #[derive(Debug)]
 pub(crate) struct InMemFile {
     // used as ID
     i_node: INode,
-    path: String,
+    kind: FileKind,
+    /// Number of paths that refer to the file.
+    links: u64,
     data: Vec<u8>,
     meta: FileMetaData,
 }
@@ -39,29 +108,57 @@ impl InMemFile {
     /// allocations for small file operations.
     pub(crate) const DEFAULT_CAPACITY: usize = 0x10000;
 
-    pub(crate) fn new(i_node: INode, path: String, meta: FileMetaData) -> Self {
+    pub(crate) fn new(i_node: INode, meta: FileMetaData) -> Self {
         Self {
             i_node,
-            path,
+            kind: FileKind::Regular,
+            links: 0,
             data: Vec::with_capacity(Self::DEFAULT_CAPACITY),
             meta,
         }
     }
+
+    /// Creates a symbolic link that points to `target`. Symbolic links have all
+    /// permissions, like on Linux.
+    pub(crate) fn new_symlink(i_node: INode, target: &str, owner: ProcessId) -> Self {
+        Self {
+            i_node,
+            kind: FileKind::Symlink,
+            links: 0,
+            data: Vec::from(target.as_bytes()),
+            meta: FileMetaData::new(0o777, owner),
+        }
+    }
+
+    /// Creates an empty directory.
+    pub(crate) fn new_dir(i_node: INode, umode: u16, owner: ProcessId) -> Self {
+        Self {
+            i_node,
+            kind: FileKind::Dir,
+            links: 0,
+            data: Vec::new(),
+            meta: FileMetaData::new(umode, owner),
+        }
+    }
+
     pub(crate) fn data(&self) -> &[u8] {
         self.data.as_slice()
     }
     pub(crate) fn data_mut(&mut self) -> &mut Vec<u8> {
         &mut self.data
     }
-    pub(crate) fn path(&self) -> &String {
-        &self.path
-    }
     pub(crate) fn meta(&self) -> &FileMetaData {
         &self.meta
     }
     pub(crate) fn i_node(&self) -> INode {
         self.i_node
     }
+    pub(crate) fn kind(&self) -> FileKind {
+        self.kind
+    }
+    pub(crate) fn links(&self) -> u64 {
+        self.links
+    }
     #[cfg(test)]
     pub(crate) fn inner_vec(&self) -> &Vec<u8> {
         &self.data
@@ -69,69 +166,346 @@ impl InMemFile {
 }
 
 /// The in-memory file system is implemented as a binary tree map
-/// from [`INode`] to [`InMemFile`].
+/// from [`INode`] to [`InMemFile`] and a second one from each path to the [`INode`] of its
+/// file. Hard links are multiple paths with the same [`INode`]. A file lives until its
+/// last path is gone. The data of the files counts towards the quota of their owners, see
+/// [`crate::set_quota`].
 #[derive(Debug)]
 pub(crate) struct InMemFilesystem {
     files: BTreeMap<INode, InMemFile>,
+    paths: BTreeMap<String, INode>,
 }
 
 impl InMemFilesystem {
     pub(crate) const fn new() -> Self {
         Self {
             files: BTreeMap::new(),
+            paths: BTreeMap::new(),
         }
     }
 
-    pub(crate) fn create_file(&mut self, i_node: INode, file: InMemFile) -> Result<(), ()> {
-        if self.files.contains_key(&i_node) {
-            Err(())
+    /// Adds a new file with its first path.
+    pub(crate) fn create_file(&mut self, path: &str, mut file: InMemFile) -> Result<(), FsError> {
+        if self.files.contains_key(&file.i_node()) || self.paths.contains_key(path) {
+            Err(FsError::Exists)
         } else {
-            self.files.insert(i_node, file);
+            // symbolic links have data from the beginning
+            ACCOUNTING
+                .lock()
+                .charge(file.meta().owner(), file.data().len())?;
+            file.links = 1;
+            self.paths.insert(String::from(path), file.i_node());
+            self.files.insert(file.i_node(), file);
             Ok(())
         }
     }
 
     pub(crate) fn get_file_by_inode(&self, i_node: INode) -> Option<&InMemFile> {
-        self.files
-            .iter()
-            .map(|(_, file)| file)
-            .find(|file| file.i_node() == i_node)
+        self.files.get(&i_node)
     }
 
     pub(crate) fn get_file_by_inode_mut(&mut self, i_node: INode) -> Option<&mut InMemFile> {
-        self.files
-            .iter_mut()
-            .map(|(_, file)| file)
-            .find(|file| file.i_node() == i_node)
-    }
-
-    fn get_entry_by_path(&self, filepath: &str) -> Option<(&INode, &InMemFile)> {
-        self.files.iter().find(|(_, file)| file.path() == filepath)
-    }
-
-    fn get_entry_by_path_mut(&mut self, filepath: &str) -> Option<(&INode, &mut InMemFile)> {
-        self.files
-            .iter_mut()
-            .find(|(_, file)| file.path() == filepath)
+        self.files.get_mut(&i_node)
     }
 
     pub(crate) fn get_file_by_path(&self, filepath: &str) -> Option<&InMemFile> {
-        self.get_entry_by_path(filepath).map(|(_, value)| value)
+        self.paths
+            .get(filepath)
+            .and_then(|i_node| self.files.get(i_node))
     }
 
     #[allow(unused)]
     pub(crate) fn get_file_by_path_mut(&mut self, filepath: &str) -> Option<&mut InMemFile> {
-        self.get_entry_by_path_mut(filepath).map(|(_, value)| value)
+        let i_node = *self.paths.get(filepath)?;
+        self.files.get_mut(&i_node)
+    }
+
+    /// Returns all paths that start with the given prefix.
+    pub(crate) fn paths_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.paths
+            .keys()
+            .filter(|path| path.starts_with(prefix))
+            .cloned()
+            .collect()
     }
 
+    /// Removes a path. The file is gone with its last path.
     pub(crate) fn delete_file_by_path(&mut self, filepath: &str) -> bool {
-        let key = self
-            .get_entry_by_path(filepath)
-            .map(|(key, _)| key)
-            // prevents borrow issue; copy is cheap here
-            .copied();
-
-        key.map(|key| self.files.remove(&key).is_some())
-            .unwrap_or(false)
+        let i_node = match self.paths.remove(filepath) {
+            Some(i_node) => i_node,
+            None => return false,
+        };
+        let file = self.files.get_mut(&i_node).unwrap();
+        file.links -= 1;
+        if file.links == 0 {
+            ACCOUNTING
+                .lock()
+                .release(file.meta().owner(), file.data().len());
+            self.files.remove(&i_node);
+        } else {
+            file.meta.changed();
+        }
+        true
+    }
+
+    /// Adds `new` as another path of the file at `existing`.
+    fn add_link(&mut self, existing: &str, new: &str) -> Result<(), FsError> {
+        if self.paths.contains_key(new) {
+            return Err(FsError::Exists);
+        }
+        let i_node = *self.paths.get(existing).ok_or(FsError::NotFound)?;
+        let file = self.files.get_mut(&i_node).unwrap();
+        file.links += 1;
+        file.meta.changed();
+        self.paths.insert(String::from(new), i_node);
+        Ok(())
+    }
+
+    /// Returns `None` if nothing exists at `path`. Otherwise, tells if `path` is a directory,
+    /// either an explicit one or the prefix of other paths, and if it is empty. Files count
+    /// as empty.
+    fn dir_state(&self, path: &str) -> Option<(bool, bool)> {
+        let dir = format!("{}/", path.trim_end_matches('/'));
+        let is_empty = self.paths_with_prefix(&dir).is_empty();
+        match self.get_file_by_path(path).map(InMemFile::kind) {
+            Some(FileKind::Dir) => Some((true, is_empty)),
+            Some(_) => Some((false, true)),
+            None if is_empty => None,
+            None => Some((true, false)),
+        }
+    }
+
+    /// Moves the existing path `from` to `to` and replaces the file at `to`. The caller
+    /// checked that the replacement is allowed, see [`FsBackend::rename`].
+    fn move_path(&mut self, from: &str, to: &str) {
+        let i_node = self.paths[from];
+        if self.paths.get(to) == Some(&i_node) {
+            // both are links of the same file: nothing to do, like on Linux
+            return;
+        }
+        self.delete_file_by_path(to);
+        self.paths.remove(from);
+        self.paths.insert(String::from(to), i_node);
+        self.files.get_mut(&i_node).unwrap().meta.changed();
+    }
+}
+
+impl FsBackend for InMemFilesystem {
+    fn open(
+        &mut self,
+        caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        umode: u16,
+    ) -> Result<INode, FsError> {
+        // the file either:
+        // - does not exist and may be created
+        // - or already exist
+        match self.get_file_by_path(path) {
+            Some(file) if file.kind() == FileKind::Dir && flags.can_write() => Err(FsError::IsDir),
+            Some(file) => Ok(file.i_node()),
+            None if flags.can_create() => {
+                let i_node = INODE_ALLOCATOR.lock().next(caller);
+                let new_file = InMemFile::new(i_node, FileMetaData::new(umode, caller));
+                self.create_file(path, new_file)?;
+                log::trace!("file creation successful: path={}, flags={:?}", path, flags);
+                Ok(i_node)
+            }
+            // file doesn't exist and can't get created
+            None => Err(FsError::NotFound),
+        }
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        self.get_file_by_path(path)
+            .map(|file| file.i_node())
+            .ok_or(FsError::NotFound)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        self.get_file_by_inode(i_node)
+            .map(|file| Some(file.meta().owner()))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        let file = self
+            .get_file_by_inode_mut(i_node)
+            .ok_or(FsError::NotFound)?;
+        if file.kind() == FileKind::Dir {
+            return Err(FsError::IsDir);
+        }
+        file.meta.accessed();
+        let data = file.data();
+        let from_index = min(offset, data.len());
+        let to_index = min(from_index + count, data.len());
+        Ok(&data[from_index..to_index])
+    }
+
+    fn write(&mut self, i_node: INode, offset: usize, new_data: &[u8]) -> Result<usize, FsError> {
+        if offset + new_data.len() > MAX_FILE_SIZE {
+            return Err(FsError::TooLarge);
+        }
+        let file = self
+            .get_file_by_inode_mut(i_node)
+            .ok_or(FsError::NotFound)?;
+        // the file ends with the new data
+        ACCOUNTING.lock().resize(
+            file.meta().owner(),
+            file.data().len(),
+            offset + new_data.len(),
+        )?;
+
+        // an offset beyond the end, e.g. after a truncate, leaves a hole of zeros, like on
+        // UNIX
+        if offset > file.data().len() {
+            file.data_mut().resize(offset, 0);
+        }
+
+        // This may truncate the vector but old data stay in memory unless overwritten.
+        // This is no data-leak because the length only grows by the new data
+        let old_len = file.data().len();
+        let offset = min(offset, old_len);
+        unsafe {
+            file.data_mut().set_len(offset);
+        }
+
+        // increase the capacity exactly, so the heap that the file occupies follows the
+        // accounted size instead of doubling
+        file.data_mut().reserve_exact(new_data.len());
+
+        file.data_mut().extend_from_slice(new_data);
+        let new_len = file.data().len();
+        if new_len < old_len {
+            // the quota only covers the size; keep the default capacity for rewrites
+            file.data_mut()
+                .shrink_to(max(new_len, InMemFile::DEFAULT_CAPACITY));
+        }
+        file.meta.modified();
+        Ok(new_data.len())
+    }
+
+    fn truncate(&mut self, i_node: INode, len: usize) -> Result<(), FsError> {
+        if len > MAX_FILE_SIZE {
+            return Err(FsError::TooLarge);
+        }
+        let file = self
+            .get_file_by_inode_mut(i_node)
+            .ok_or(FsError::NotFound)?;
+        let old_len = file.data().len();
+        ACCOUNTING
+            .lock()
+            .resize(file.meta().owner(), old_len, len)?;
+        // growing zeroes the old data behind the end
+        file.data_mut().resize(len, 0);
+        if len < old_len {
+            // the quota only covers the size, hence the memory must go back to the heap
+            file.data_mut().shrink_to(len);
+        }
+        file.meta.modified();
+        Ok(())
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        self.get_file_by_inode(i_node)
+            .map(FileStat::from)
+            .ok_or(FsError::NotFound)
+    }
+
+    fn unlink(&mut self, path: &str) -> Result<(), FsError> {
+        if self.get_file_by_path(path).map(InMemFile::kind) == Some(FileKind::Dir) {
+            return Err(FsError::IsDir);
+        }
+        if self.delete_file_by_path(path) {
+            Ok(())
+        } else {
+            Err(FsError::NotFound)
+        }
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        self.paths_with_prefix(dir)
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), FsError> {
+        // check everything before the first path moves, like POSIX: a file can only
+        // replace a file and a directory only an empty directory
+        let from_is_dir = match self.dir_state(from) {
+            None => return Err(FsError::NotFound),
+            Some((is_dir, _)) => is_dir,
+        };
+        let from_dir = format!("{}/", from.trim_end_matches('/'));
+        let to_dir = format!("{}/", to.trim_end_matches('/'));
+        if from_is_dir && to_dir.starts_with(&from_dir) {
+            // into itself
+            return Err(FsError::InvalidArgument);
+        }
+        match (from_is_dir, self.dir_state(to)) {
+            (false, Some((true, _))) => return Err(FsError::IsDir),
+            (true, Some((false, _))) => return Err(FsError::NotDir),
+            (true, Some((true, false))) => return Err(FsError::NotEmpty),
+            _ => {}
+        }
+        if !from_is_dir {
+            self.move_path(from, to);
+            return Ok(());
+        }
+
+        // renaming a directory moves all paths below it
+        for path in self.paths_with_prefix(&from_dir) {
+            let new_path = format!("{}{}", to_dir, &path[from_dir.len()..]);
+            self.move_path(&path, &new_path);
+        }
+        if self.paths.contains_key(from) {
+            self.move_path(from, to);
+        }
+        Ok(())
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<(), FsError> {
+        self.add_link(existing, new)
+    }
+
+    fn mkdir(&mut self, caller: ProcessId, path: &str, umode: u16) -> Result<(), FsError> {
+        let dir = format!("{}/", path.trim_end_matches('/'));
+        if self.paths.contains_key(path) || !self.paths_with_prefix(&dir).is_empty() {
+            return Err(FsError::Exists);
+        }
+        let i_node = INODE_ALLOCATOR.lock().next(caller);
+        self.create_file(path, InMemFile::new_dir(i_node, umode, caller))
+    }
+
+    fn rmdir(&mut self, path: &str) -> Result<(), FsError> {
+        let dir = format!("{}/", path.trim_end_matches('/'));
+        let is_empty = self.paths_with_prefix(&dir).is_empty();
+        match self.get_file_by_path(path).map(InMemFile::kind) {
+            Some(FileKind::Dir) if is_empty => {
+                self.delete_file_by_path(path);
+                Ok(())
+            }
+            Some(FileKind::Dir) => Err(FsError::NotEmpty),
+            Some(_) => Err(FsError::NotDir),
+            // an implicit directory vanishes with its last file
+            None if is_empty => Err(FsError::NotFound),
+            None => Err(FsError::NotEmpty),
+        }
+    }
+
+    fn symlink(&mut self, caller: ProcessId, target: &str, path: &str) -> Result<(), FsError> {
+        if self.paths.contains_key(path) {
+            return Err(FsError::Exists);
+        }
+        let i_node = INODE_ALLOCATOR.lock().next(caller);
+        self.create_file(path, InMemFile::new_symlink(i_node, target, caller))
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, FsError> {
+        match self.get_file_by_path(path) {
+            Some(file) if file.kind() == FileKind::Symlink => {
+                Ok(String::from_utf8_lossy(file.data()).into_owned())
+            }
+            Some(_) => Err(FsError::InvalidArgument),
+            None => Err(FsError::NotFound),
+        }
     }
 }
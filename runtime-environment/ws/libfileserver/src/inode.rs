@@ -1,3 +1,8 @@
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+
 /// I node.
 #[repr(transparent)]
 #[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Hash, Ord, Eq)]
@@ -21,3 +26,78 @@ where
         INode::new(val.into())
     }
 }
+
+/// Hands out unique inodes for files, sockets, and reserved file descriptors.
+///
+/// By default, inodes count up in the order of their creation over all processes. In the
+/// deterministic mode, each process counts its own inodes instead. Hence, the inodes of a
+/// process don't depend on what other processes create at the same time. The PID of the
+/// creator goes into the upper half of these inodes.
+#[derive(Debug)]
+pub(crate) struct INodeAllocator {
+    next: u64,
+    /// Inodes that each process created so far in the deterministic mode, indexed by PID.
+    per_process: Option<[u64; NUM_PROCESSES as usize]>,
+}
+
+impl INodeAllocator {
+    /// Marks inodes of the deterministic mode, so that they never collide with the ones
+    /// that were created before the mode was enabled.
+    const DETERMINISTIC_BIT: u64 = 1 << 63;
+
+    pub(crate) const fn new() -> Self {
+        Self {
+            next: 0,
+            per_process: None,
+        }
+    }
+
+    /// Enables or disables the deterministic mode. Inodes that exist already stay valid.
+    pub(crate) fn set_deterministic(&mut self, deterministic: bool) {
+        if deterministic != self.per_process.is_some() {
+            self.per_process = deterministic.then(|| [0; NUM_PROCESSES as usize]);
+        }
+    }
+
+    /// Returns a new inode for an object that `creator` creates.
+    pub(crate) fn next(&mut self, creator: ProcessId) -> INode {
+        match &mut self.per_process {
+            Some(per_process) => {
+                let count = &mut per_process[creator as usize];
+                *count += 1;
+                INode::new(Self::DETERMINISTIC_BIT | creator << 32 | *count)
+            }
+            None => {
+                self.next += 1;
+                INode::new(self.next - 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_inodes() {
+        let mut allocator = INodeAllocator::new();
+        let before = allocator.next(1);
+        assert_ne!(allocator.next(1), before);
+
+        // the inodes of a process don't depend on the ones of other processes
+        let mut a = INodeAllocator::new();
+        a.set_deterministic(true);
+        let mut b = INodeAllocator::new();
+        b.set_deterministic(true);
+        a.next(2);
+        let a_inodes = [a.next(1), a.next(1)];
+        let b_inodes = [b.next(1), b.next(1)];
+        assert_eq!(a_inodes, b_inodes);
+        assert_ne!(a_inodes[0], a_inodes[1]);
+        assert_ne!(a.next(2), a.next(3));
+
+        allocator.set_deterministic(true);
+        assert_ne!(allocator.next(0), before);
+    }
+}
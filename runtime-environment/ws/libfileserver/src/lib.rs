@@ -1,6 +1,9 @@
 //! File server lib. Currently this library only contains the internal interface of the
 //! file system server. The public interface (exported via Portals) must be build around
 //! these interfaces.
+//!
+//! All file operations report errors as [`FsError`], which the service portals forward to
+//! their clients. The local sockets have their own [`SocketError`].
 
 #![no_std]
 #![deny(
@@ -43,48 +46,209 @@ extern crate alloc;
 #[macro_use]
 extern crate libhrstd;
 
+mod backend;
 mod file_descriptor;
 mod file_table;
 mod in_mem_fs;
 mod inode;
+mod mount;
+mod quota;
+mod readiness;
+mod socket;
 mod stat;
 
 use crate::file_table::OpenFileTable;
-use crate::in_mem_fs::{
-    FileMetaData,
-    InMemFile,
-    InMemFilesystem,
+#[cfg(test)]
+use crate::in_mem_fs::InMemFile;
+use crate::in_mem_fs::InMemFilesystem;
+use crate::mount::{
+    MountId,
+    MountTable,
 };
+use crate::socket::SocketTable;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
+pub use backend::FsBackend;
 use core::cmp::min;
 pub use file_descriptor::FileDescriptor;
+pub use inode::INode;
+use inode::INodeAllocator;
 use libhrstd::process::consts::ProcessId;
-use libhrstd::rt::services::fs::FsOpenFlags;
+pub use libhrstd::rt::services::fs::FsError;
+use libhrstd::rt::services::fs::{
+    FsAccessMode,
+    FsOpenFlags,
+    SocketError,
+    SocketKind,
+};
 use libhrstd::sync::mutex::SimpleMutex;
-use libhrstd::util::global_counter::GlobalIncrementingCounter;
-pub use stat::FileStat;
+use quota::Accounting;
+pub use quota::{
+    FsQuota,
+    FsUsage,
+};
+pub use readiness::Readiness;
+pub use stat::{
+    FileStat,
+    S_IFDIR,
+    S_IFLNK,
+    S_IFMT,
+    S_IFREG,
+};
 
 /// Public facade to the file system. See [`Filesystem`].
 pub static FILESYSTEM: SimpleMutex<Filesystem> = SimpleMutex::new(Filesystem::new());
 
-/// Counter to give unique inodes (=identifiers) to files. Currently, this is auto incrementing
-/// for ever.
-static INODE_COUNTER: GlobalIncrementingCounter = GlobalIncrementingCounter::new();
+/// Umask of processes that didn't inherit one from their parent, like on Linux.
+pub const DEFAULT_UMASK: u16 = 0o022;
+
+/// Largest size that a file can get via [`Filesystem::truncate_file`]. Protects the heap of
+/// the roottask from a single call that asks for an absurd size.
+pub const MAX_FILE_SIZE: usize = 1 << 30;
+
+/// Limits of the file operations of a process, like `RLIMIT_NOFILE` and `RLIMIT_FSIZE` of
+/// Linux. See [`Filesystem::set_file_limits`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FileLimits {
+    /// Opening a file fails with [`FsError::TooManyOpenFiles`] if the process has no free
+    /// file descriptor below this number. Sockets and reserved file descriptors are not
+    /// limited, but they occupy file descriptors.
+    pub max_open_files: u64,
+    /// Regular files can't grow beyond this size via writes or truncation. Writes that
+    /// would cross it get shortened; writes at or beyond it fail with
+    /// [`FsError::TooLarge`].
+    pub max_file_size: u64,
+}
+
+impl FileLimits {
+    /// Limits of processes that didn't get others.
+    pub const UNLIMITED: Self = Self {
+        max_open_files: u64::MAX,
+        max_file_size: u64::MAX,
+    };
+}
+
+/// Gives unique inodes (=identifiers) to files, sockets, and reserved file descriptors. See
+/// [`set_deterministic_inodes`].
+static INODE_ALLOCATOR: SimpleMutex<INodeAllocator> = SimpleMutex::new(INodeAllocator::new());
+
+/// Enables or disables deterministic inodes. Then, the inodes of a process only depend on
+/// the files and sockets that the process itself created before and not on what other
+/// processes create at the same time. Used for reproducible traces.
+pub fn set_deterministic_inodes(deterministic: bool) {
+    INODE_ALLOCATOR.lock().set_deterministic(deterministic);
+}
+
+/// Counts the bytes of the in-memory file system, see [`set_quota`] and [`usage`].
+static ACCOUNTING: SimpleMutex<Accounting> = SimpleMutex::new(Accounting::new());
+
+/// Limits the bytes that the files of the in-memory file system may occupy in total and
+/// per owning process. Writes beyond a quota fail with [`FsError::NoSpace`]. Protects the
+/// heap of the roottask from a process that writes a giant file. Unlimited by default.
+pub fn set_quota(quota: FsQuota) {
+    ACCOUNTING.lock().set_quota(quota);
+}
+
+/// Returns the current usage of the in-memory file system and its quota. Doesn't need the
+/// lock of [`FILESYSTEM`], hence backends can report it.
+pub fn usage() -> FsUsage {
+    ACCOUNTING.lock().usage()
+}
+
+/// Maximum number of symbolic links that the resolution of a path follows, like
+/// `MAXSYMLINKS` of Linux. Stops loops of links.
+const MAX_SYMLINKS: usize = 40;
+
+/// Source of the timestamps of files. See [`set_clock`].
+static CLOCK: SimpleMutex<Option<fn() -> u64>> = SimpleMutex::new(None);
+
+/// Sets the clock that gives files their access, modification, and change times. It
+/// returns the nanoseconds since the Unix epoch. Without a clock, all timestamps are zero.
+pub fn set_clock(clock: fn() -> u64) {
+    CLOCK.lock().replace(clock);
+}
+
+/// Returns the current time of the clock of [`set_clock`] in nanoseconds.
+fn now_ns() -> u64 {
+    // don't hold the lock while the clock runs
+    let clock = *CLOCK.lock();
+    clock.map(|clock| clock()).unwrap_or(0)
+}
+
+/// Turns `path` into an absolute path: a relative path is relative to the directory `cwd`,
+/// e.g. the current working directory of a process. Absolute and empty paths stay as they
+/// are. `.` and `..` components are resolved when the file system resolves the path.
+pub fn absolute_path(cwd: &str, path: &str) -> String {
+    if path.is_empty() || path.starts_with('/') {
+        String::from(path)
+    } else {
+        format!("{}/{}", cwd.trim_end_matches('/'), path)
+    }
+}
 
-/// Facade over the virtual file system that contains the in-memory file system and possibly
-/// others in the future.
+/// Facade over the virtual file system. The in-memory file system is mounted at `/`.
+/// Further [`FsBackend`]s can be mounted at other paths, see [`Self::mount`].
 #[derive(Debug)]
 pub struct Filesystem {
     in_mem_fs: InMemFilesystem,
+    mount_table: MountTable,
     open_file_table: OpenFileTable,
+    socket_table: SocketTable,
+    /// Umask of each process that changed or inherited it.
+    umasks: BTreeMap<ProcessId, u16>,
+    /// Limits of each process that has some, see [`Self::set_file_limits`].
+    file_limits: BTreeMap<ProcessId, FileLimits>,
+    /// If set, no file can be created, written, or removed. See [`Self::set_read_only`].
+    read_only: bool,
 }
 
 impl Filesystem {
     const fn new() -> Self {
         Self {
             in_mem_fs: InMemFilesystem::new(),
+            mount_table: MountTable::new(),
             open_file_table: OpenFileTable::new(),
+            socket_table: SocketTable::new(),
+            umasks: BTreeMap::new(),
+            file_limits: BTreeMap::new(),
+            read_only: false,
+        }
+    }
+
+    /// Mounts a backend at the given absolute path. Afterwards, all paths below the mount
+    /// point refer to the backend. Mount points may be nested. The in-memory file system
+    /// at `/` can't be replaced.
+    pub fn mount(&mut self, mount_point: &str, backend: Box<dyn FsBackend>) -> Result<(), FsError> {
+        let id = self.mount_table.mount(mount_point, backend)?;
+        log::debug!("mounted backend {:?} at {}", id, mount_point);
+        Ok(())
+    }
+
+    /// Unmounts the backend at the given mount point and returns it. Fails if a process
+    /// still has an open file on it.
+    pub fn unmount(&mut self, mount_point: &str) -> Result<Box<dyn FsBackend>, FsError> {
+        let id = self
+            .mount_table
+            .lookup(mount_point)
+            .ok_or(FsError::NotFound)?;
+        if self.open_file_table.is_mount_in_use(id) {
+            return Err(FsError::Busy);
         }
+        self.mount_table.unmount(id).ok_or(FsError::NotFound)
+    }
+
+    /// Makes the whole file system read-only or writable again. Files can still be opened
+    /// for reading; opening files for writing, creating files, and removing files fails.
+    /// Used by the safe mode of the roottask.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Returns true if the file system is read-only, see [`Self::set_read_only`].
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
     }
 
     /// Public interface to the file system management data structures to open files.
@@ -92,44 +256,195 @@ impl Filesystem {
     /// This is not the public service API that gets exported via portals but the
     /// public service Portals will wrap around these functions.
     ///
-    /// The interface is close to UNIX. On success, a new [`FD`] gets returned.
+    /// The interface is close to UNIX. On success, a new [`FD`] gets returned. A new file
+    /// gets the `umode` without the bits of the umask of the caller, see [`Self::umask`].
     pub fn open_or_create_file(
         &mut self,
         caller: ProcessId,
         path: &str,
         flags: FsOpenFlags,
         umode: u16,
-    ) -> Result<FileDescriptor, ()> {
-        if flags.is_empty() {
-            return Err(());
-        };
+    ) -> Result<FileDescriptor, FsError> {
+        // empty flags are valid: O_RDONLY is zero
         if path.is_empty() {
-            return Err(());
-        }
-
-        // the file either:
-        // - does not exist and may be created
-        // - or already exist
-        let maybe_file = self.in_mem_fs.get_file_by_path(&path);
-
-        if maybe_file.is_none() & flags.can_create() {
-            // create new file
-            let i_node = INODE_COUNTER.next().into();
-            let new_file =
-                InMemFile::new(i_node, String::from(path), FileMetaData::new(umode, caller));
-            self.in_mem_fs.create_file(i_node, new_file)?;
-            let fd = self.open_file_table.open(caller, i_node, flags)?;
-            log::trace!("file creation successful: path={}, flags={:?}", path, flags);
-            Ok(fd)
-        } else if maybe_file.is_none() {
-            // file doesn't exist or can't get created
-            log::trace!("file open error: path={}, flags={:?}", path, flags);
-            Err(())
+            return Err(FsError::NotFound);
+        }
+        let path = self.resolve_symlinks(path, true)?;
+        if flags.contains(FsOpenFlags::O_CREAT | FsOpenFlags::O_EXCL)
+            && self.stat_resolved_path(caller, &path).is_ok()
+        {
+            return Err(FsError::Exists);
+        }
+
+        if self.read_only && flags.can_write() {
+            return Err(FsError::ReadOnly);
+        }
+        // O_CREAT is fine as long as the file exists
+        let flags = if self.read_only {
+            flags - FsOpenFlags::O_CREAT
+        } else {
+            flags
+        };
+
+        let next_fd = self.open_file_table.find_next_fd(caller);
+        if next_fd.val() >= self.file_limits(caller).max_open_files {
+            return Err(FsError::TooManyOpenFiles);
+        }
+
+        let umode = umode & !self.umask(caller);
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        let backend = self.backend_mut(mount)?;
+        match backend.open(caller, relative_path, flags, umode) {
+            Ok(i_node) => {
+                if flags.contains(FsOpenFlags::O_TRUNC) && flags.can_write() {
+                    // devices have no size and ignore it, like on Linux
+                    let _ = backend.truncate(i_node, 0);
+                }
+                self.open_file_table
+                    .open(caller, Some(mount), i_node, flags)
+            }
+            Err(FsError::NotFound) if self.read_only && flags.can_create() => {
+                Err(FsError::ReadOnly)
+            }
+            Err(err) => {
+                // file doesn't exist or can't get created
+                log::trace!(
+                    "file open error: path={}, flags={:?}, err={:?}",
+                    path,
+                    flags,
+                    err
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Opens a file on behalf of `caller` for a new process `child`, which gets it at the
+    /// file descriptor `fd`. Used by the process service to pre-open files. Files that
+    /// get created have the default permissions `0o666` without the umask of `caller`.
+    /// Fails if the file can't be opened or `fd` is in use ([`FsError::Busy`]).
+    pub fn preopen_file(
+        &mut self,
+        caller: ProcessId,
+        child: ProcessId,
+        fd: FileDescriptor,
+        path: &str,
+        flags: FsOpenFlags,
+    ) -> Result<(), FsError> {
+        if self.open_file_table.lookup_handle(child, fd).is_some() {
+            return Err(FsError::Busy);
+        }
+        let caller_fd = self.open_or_create_file(caller, path, flags, 0o666)?;
+        self.open_file_table
+            .transfer(caller, caller_fd, child, fd)
+            .map_err(|err| {
+                let _ = self.open_file_table.close(caller, caller_fd);
+                err
+            })
+    }
+
+    /// Gives the new process `child` a copy of the open file `parent_fd` of `parent` at the
+    /// file descriptor `child_fd`, like a file descriptor that survives `fork()` and
+    /// `exec()`. Unlike on UNIX, the copy has its own file offset, which starts at the
+    /// offset of the original. Used by the process service to spawn processes. Sockets and
+    /// reserved file descriptors can't be inherited ([`FsError::Unsupported`]). Fails with
+    /// [`FsError::Busy`] if `child_fd` is in use.
+    pub fn inherit_file(
+        &mut self,
+        parent: ProcessId,
+        parent_fd: FileDescriptor,
+        child: ProcessId,
+        child_fd: FileDescriptor,
+    ) -> Result<(), FsError> {
+        self.open_file_table
+            .duplicate(parent, parent_fd, child, child_fd)
+    }
+
+    /// Returns the file descriptors of the open files of the process, without sockets and
+    /// reserved file descriptors.
+    pub fn open_files(&self, caller: ProcessId) -> Vec<FileDescriptor> {
+        self.open_file_table.files(caller)
+    }
+
+    /// Exchanges the open files of two processes, e.g. to undo the changes that a child
+    /// made to the files of its parent before it started. Sockets and reserved file
+    /// descriptors stay with their process. Fails with [`FsError::Busy`] without changing
+    /// anything if a file would replace one of them.
+    pub fn swap_files(&mut self, a: ProcessId, b: ProcessId) -> Result<(), FsError> {
+        self.open_file_table.swap_files(a, b)
+    }
+
+    /// Closes all open files, sockets, and reserved file descriptors of a process that
+    /// exited and forgets its umask and its limits. The next process with the same PID
+    /// starts without them.
+    pub fn release_process(&mut self, pid: ProcessId) {
+        for fd in self.open_file_table.fds(pid) {
+            let _ = self.close_file(pid, fd);
+        }
+        self.umasks.remove(&pid);
+        self.file_limits.remove(&pid);
+    }
+
+    /// Returns the umask of a process. It clears permission bits of the `umode` of files
+    /// that the process creates.
+    pub fn umask(&self, caller: ProcessId) -> u16 {
+        self.umasks.get(&caller).copied().unwrap_or(DEFAULT_UMASK)
+    }
+
+    /// Sets the umask of a process and returns the previous one. Like on Linux, only the
+    /// permission bits (`0o777`) of the new umask are used.
+    pub fn set_umask(&mut self, caller: ProcessId, umask: u16) -> u16 {
+        let old = self.umask(caller);
+        self.umasks.insert(caller, umask & 0o777);
+        old
+    }
+
+    /// Gives a new process the umask of its parent. Called when the process starts.
+    pub fn inherit_umask(&mut self, parent: ProcessId, child: ProcessId) {
+        let umask = self.umask(parent);
+        self.umasks.insert(child, umask);
+    }
+
+    /// Returns the limits of the file operations of a process.
+    pub fn file_limits(&self, caller: ProcessId) -> FileLimits {
+        self.file_limits
+            .get(&caller)
+            .copied()
+            .unwrap_or(FileLimits::UNLIMITED)
+    }
+
+    /// Sets the limits of the file operations of a process. Files that are already open or
+    /// bigger stay as they are.
+    pub fn set_file_limits(&mut self, caller: ProcessId, limits: FileLimits) {
+        self.file_limits.insert(caller, limits);
+    }
+
+    /// Public interface to the file system management data structures to check the
+    /// permissions of a process for a file without opening it.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `access()`. There are no users and groups: the owner
+    /// class of the permission bits applies to the process that created the file and the
+    /// other class to all other processes. Files of backends without owners are only
+    /// checked against the other class. Missing permissions are [`FsError::Perm`].
+    pub fn access(&self, caller: ProcessId, path: &str, mode: FsAccessMode) -> Result<(), FsError> {
+        let path = self.resolve_symlinks(path, true)?;
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        let backend = self.backend(mount)?;
+        let i_node = backend.lookup(relative_path)?;
+        let umode = backend.stat(i_node)?.st_mode() as u16;
+        let owner = backend.owner(i_node)?;
+        let class_bits = if owner == Some(caller) {
+            umode >> 6
+        } else {
+            umode
+        };
+        if FsAccessMode::from_umode_class(class_bits).contains(mode) {
+            Ok(())
         } else {
-            let file = maybe_file.ok_or(())?;
-            // open existing file
-            let fd = self.open_file_table.open(caller, file.i_node(), flags)?;
-            Ok(fd)
+            Err(FsError::Perm)
         }
     }
 
@@ -144,26 +459,51 @@ impl Filesystem {
         caller: ProcessId,
         fd: FileDescriptor,
         count: usize,
-    ) -> Result<&[u8], ()> {
+    ) -> Result<&[u8], FsError> {
         let open_handle = self
             .open_file_table
             .lookup_handle_mut(caller, fd)
-            .ok_or(())?;
-
-        let file = self
-            .in_mem_fs
-            .get_file_by_inode(open_handle.i_node())
-            .ok_or(())?;
+            .ok_or(FsError::BadFd)?;
 
-        let from_index = open_handle.file_offset();
-        let to_index = min(from_index + count, file.data().len());
+        let backend = match open_handle.mount().ok_or(FsError::BadFd)? {
+            MountId::ROOT => &mut self.in_mem_fs as &mut dyn FsBackend,
+            mount => self
+                .mount_table
+                .backend_mut(mount)
+                .ok_or(FsError::NotFound)?,
+        };
+        let slice = backend.read(open_handle.i_node(), open_handle.file_offset(), count)?;
         // update file offset is important! So that next read continues where the
         // previous read stopped
-        open_handle.file_offset = to_index;
-        let slice = &file.data()[from_index..to_index];
+        open_handle.file_offset += slice.len();
         Ok(slice)
     }
 
+    /// Reads like [`Self::read_file`] but at `offset` and without changing the file offset,
+    /// like `pread()` on UNIX. Used to map files into memory and by `pread()` and
+    /// `preadv()` of the Linux personality.
+    pub fn pread_file(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        offset: usize,
+        count: usize,
+    ) -> Result<&[u8], FsError> {
+        let open_handle = self
+            .open_file_table
+            .lookup_handle(caller, fd)
+            .ok_or(FsError::BadFd)?;
+        let i_node = open_handle.i_node();
+        let backend = match open_handle.mount().ok_or(FsError::BadFd)? {
+            MountId::ROOT => &mut self.in_mem_fs as &mut dyn FsBackend,
+            mount => self
+                .mount_table
+                .backend_mut(mount)
+                .ok_or(FsError::NotFound)?,
+        };
+        backend.read(i_node, offset, count)
+    }
+
     /// Public interface to the file system management data structures to write to open files.
     ///
     /// This is not the public service API that gets exported via portals but the
@@ -175,45 +515,87 @@ impl Filesystem {
         caller: ProcessId,
         fd: FileDescriptor,
         new_data: &[u8],
-    ) -> Result<usize, ()> {
+    ) -> Result<usize, FsError> {
+        self.write_file_vectored(caller, fd, None, &[new_data])
+    }
+
+    /// Writes all buffers one after another in a single request, like `writev()` on UNIX,
+    /// hence, without concatenating them first. With an `offset`, the buffers get written
+    /// there and the file offset stays unchanged, like `pwritev()`; unlike Linux, also if
+    /// the file is open with `O_APPEND`. Like for [`Self::write_file`], the in-memory file
+    /// system ends the file with the written data. Stops at the first short write. Returns
+    /// the number of written bytes. Regular files don't grow beyond the
+    /// [`FileLimits::max_file_size`] of the caller.
+    pub fn write_file_vectored(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        offset: Option<usize>,
+        bufs: &[&[u8]],
+    ) -> Result<usize, FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let max_file_size = self.file_limits(caller).max_file_size;
         let open_handle = self
             .open_file_table
             .lookup_handle_mut(caller, fd)
-            .ok_or(())?;
+            .ok_or(FsError::BadFd)?;
 
-        let file = self
-            .in_mem_fs
-            .get_file_by_inode_mut(open_handle.i_node())
-            .ok_or(())?;
+        let backend = match open_handle.mount().ok_or(FsError::BadFd)? {
+            MountId::ROOT => &mut self.in_mem_fs as &mut dyn FsBackend,
+            mount => self
+                .mount_table
+                .backend_mut(mount)
+                .ok_or(FsError::NotFound)?,
+        };
+        let i_node = open_handle.i_node();
 
         // get offset; i.e.: the point where we start to append data
         // on UNIX, APPEND always appends; independent from the file offset
-        let write_begin_offset = if open_handle.flags().is_append() {
-            file.data().len()
+        let write_begin_offset = match offset {
+            Some(offset) => offset,
+            None if open_handle.flags().is_append() => backend.stat(i_node)?.st_size() as usize,
+            None => open_handle.file_offset(),
+        };
+        // devices have no size, like on Linux
+        let max_file_size = if max_file_size != u64::MAX && backend.stat(i_node)?.is_file() {
+            max_file_size
         } else {
-            open_handle.file_offset()
+            u64::MAX
         };
 
-        // This may truncate the vector but old data stay in memory unless overwritten.
-        // This is no data-leak because at this point the capacity can never shrink
-        unsafe {
-            file.data_mut().set_len(write_begin_offset);
+        let mut written_bytes = 0;
+        for buf in bufs {
+            let write_offset = (write_begin_offset + written_bytes) as u64;
+            let allowed = max_file_size.saturating_sub(write_offset);
+            if allowed == 0 && !buf.is_empty() {
+                if written_bytes > 0 {
+                    break;
+                }
+                return Err(FsError::TooLarge);
+            }
+            let res = backend.write(
+                i_node,
+                write_offset as usize,
+                &buf[..min(buf.len() as u64, allowed) as usize],
+            );
+            match res {
+                Ok(count) => {
+                    written_bytes += count;
+                    if count < buf.len() {
+                        break;
+                    }
+                }
+                // like UNIX, a later error only shortens the write
+                Err(_) if written_bytes > 0 => break,
+                Err(err) => return Err(err),
+            }
         }
-
-        // the final file offset, after the new data got written.
-        let new_length = write_begin_offset + new_data.len();
-        open_handle.file_offset = new_length;
-
-        // increase capacity if necessary
-        let vec_current_capacity = file.data_mut().capacity();
-        if new_data.len() > vec_current_capacity {
-            file.data_mut()
-                .reserve_exact(new_length - vec_current_capacity);
+        if offset.is_none() {
+            // the final file offset, after the new data got written.
+            open_handle.file_offset = write_begin_offset + written_bytes;
         }
-
-        file.data_mut().extend_from_slice(new_data);
-
-        let written_bytes = new_data.len();
         Ok(written_bytes)
     }
 
@@ -229,44 +611,165 @@ impl Filesystem {
         caller: ProcessId,
         fd: FileDescriptor,
         offset: usize,
-    ) -> Result<(), ()> {
+    ) -> Result<(), FsError> {
         let open_handle = self
             .open_file_table
             .lookup_handle_mut(caller, fd)
-            .ok_or(())?;
+            .ok_or(FsError::BadFd)?;
 
-        let file = self
-            .in_mem_fs
-            .get_file_by_inode(open_handle.i_node())
-            .ok_or(())?;
+        let backend = match open_handle.mount().ok_or(FsError::BadFd)? {
+            MountId::ROOT => &self.in_mem_fs as &dyn FsBackend,
+            mount => self.mount_table.backend(mount).ok_or(FsError::NotFound)?,
+        };
+        let file_size = backend.stat(open_handle.i_node())?.st_size() as usize;
 
-        if offset > file.data().len() {
-            log::warn!("offset >= file.data.len()");
+        if offset > file_size {
+            log::warn!("offset >= file size");
             // TODO not sure how UNIX handles this
         }
-        let offset = min(offset, file.data().len());
+        let offset = min(offset, file_size);
         open_handle.file_offset = offset;
         Ok(())
     }
 
+    /// Public interface to the file system management data structures to change the size
+    /// of a file.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `truncate()`: the file shrinks or grows to `len`
+    /// bytes, at most [`MAX_FILE_SIZE`] and the [`FileLimits::max_file_size`] of the
+    /// caller; new bytes are zero. Open files keep their offsets,
+    /// even beyond the new end. A later write there leaves a hole of zeros.
+    pub fn truncate_file(
+        &mut self,
+        caller: ProcessId,
+        path: &str,
+        len: usize,
+    ) -> Result<(), FsError> {
+        let path = self.resolve_symlinks(path, true)?;
+        if self.is_dir(caller, &path) {
+            return Err(FsError::IsDir);
+        }
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if len as u64 > self.file_limits(caller).max_file_size {
+            return Err(FsError::TooLarge);
+        }
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        let backend = self.backend_mut(mount)?;
+        let i_node = backend.lookup(relative_path)?;
+        backend.truncate(i_node, len)
+    }
+
+    /// Like [`Self::truncate_file`] but for an open file, like UNIX `ftruncate()`. The file
+    /// must be open for writing, otherwise it fails with [`FsError::InvalidArgument`], like
+    /// on Linux.
+    pub fn ftruncate_file(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        len: usize,
+    ) -> Result<(), FsError> {
+        let open_handle = self
+            .open_file_table
+            .lookup_handle(caller, fd)
+            .ok_or(FsError::BadFd)?;
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if !open_handle.flags().can_write() {
+            return Err(FsError::InvalidArgument);
+        }
+        let i_node = open_handle.i_node();
+        if len as u64 > self.file_limits(caller).max_file_size {
+            return Err(FsError::TooLarge);
+        }
+        let mount = open_handle.mount().ok_or(FsError::BadFd)?;
+        self.backend_mut(mount)?.truncate(i_node, len)
+    }
+
     /// Public interface to the file system management data structures to get the fstat data structure.
     ///
     /// This is not the public service API that gets exported via portals but the
     /// public service Portals will wrap around these functions.
     ///
     /// The interface is close to UNIX.
-    pub fn fstat(&mut self, caller: ProcessId, fd: FileDescriptor) -> Result<FileStat, ()> {
+    pub fn fstat(&mut self, caller: ProcessId, fd: FileDescriptor) -> Result<FileStat, FsError> {
         let open_handle = self
             .open_file_table
-            .lookup_handle_mut(caller, fd)
-            .ok_or(())?;
+            .lookup_handle(caller, fd)
+            .ok_or(FsError::BadFd)?;
+        self.stat_i_node(
+            open_handle.mount().ok_or(FsError::BadFd)?,
+            open_handle.i_node(),
+        )
+    }
+
+    /// Public interface to the file system management data structures to get the metadata
+    /// of a file by its path, i.e. without opening it.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `stat()`. There are no real directories: `/`, mount
+    /// points, and paths that are a prefix of other files followed by a slash are
+    /// directories, see [`Self::list_dir`]. Symbolic links are followed.
+    pub fn stat_path(&self, caller: ProcessId, path: &str) -> Result<FileStat, FsError> {
+        let path = self.resolve_symlinks(path, true)?;
+        self.stat_resolved_path(caller, &path)
+    }
+
+    /// Like [`Self::stat_path`] but returns the metadata of a symbolic link itself, like
+    /// UNIX `lstat()`.
+    pub fn lstat_path(&self, caller: ProcessId, path: &str) -> Result<FileStat, FsError> {
+        let path = self.resolve_symlinks(path, false)?;
+        self.stat_resolved_path(caller, &path)
+    }
+
+    /// Public interface to the file system management data structures to resolve all
+    /// symbolic links in a path.
+    ///
+    /// The interface is close to UNIX `realpath()`. The result is absolute and contains no
+    /// `.` and `..` components. The file itself doesn't have to exist.
+    pub fn canonicalize(&self, _caller: ProcessId, path: &str) -> Result<String, FsError> {
+        if !path.starts_with('/') {
+            return Err(FsError::InvalidArgument);
+        }
+        self.resolve_symlinks(path, true)
+    }
+
+    /// Checks if the resolved path is a directory, see [`Self::stat_path`].
+    fn is_dir(&self, caller: ProcessId, path: &str) -> bool {
+        self.stat_resolved_path(caller, path)
+            .map_or(false, |stat| stat.is_dir())
+    }
 
-        let file = self
-            .in_mem_fs
-            .get_file_by_inode(open_handle.i_node())
-            .ok_or(())?;
+    fn stat_resolved_path(&self, caller: ProcessId, path: &str) -> Result<FileStat, FsError> {
+        if !path.starts_with('/') {
+            return Err(FsError::InvalidArgument);
+        }
+        let (mount, relative_path) = self.mount_table.resolve(path);
+        if let Ok(i_node) = self.backend(mount)?.lookup(relative_path) {
+            return self.stat_i_node(mount, i_node);
+        }
 
-        Ok(FileStat::from(file))
+        let dir = match path.trim_end_matches('/') {
+            "" => "/",
+            dir => dir,
+        };
+        let is_dir = dir == "/"
+            || self.mount_table.lookup(dir).is_some()
+            || !self.list_dir(caller, dir).is_empty();
+        if is_dir {
+            let mut stat = FileStat::new(0, S_IFDIR | 0o755, 0);
+            stat.set_dev(mount.val());
+            Ok(stat)
+        } else {
+            Err(FsError::NotFound)
+        }
     }
 
     /// Public interface to the file system management data structures to close open files.
@@ -275,8 +778,16 @@ impl Filesystem {
     /// public service Portals will wrap around these functions.
     ///
     /// The interface is close to UNIX.
-    pub fn close_file(&mut self, caller: ProcessId, fd: FileDescriptor) -> Result<(), ()> {
-        self.open_file_table.close(caller, fd)
+    pub fn close_file(&mut self, caller: ProcessId, fd: FileDescriptor) -> Result<(), FsError> {
+        let i_node = self
+            .open_file_table
+            .lookup_handle(caller, fd)
+            .ok_or(FsError::BadFd)?
+            .i_node();
+        self.open_file_table.close(caller, fd)?;
+        // each socket is referenced by exactly one file descriptor
+        self.socket_table.close(i_node);
+        Ok(())
     }
 
     /// Public interface to the file system management data structures to unlink a file.
@@ -284,95 +795,879 @@ impl Filesystem {
     /// This is not the public service API that gets exported via portals but the
     /// public service Portals will wrap around these functions.
     ///
-    /// The interface is close to UNIX.
-    pub fn unlink_file(&mut self, _caller: ProcessId, file: &str) -> Result<(), ()> {
+    /// The interface is close to UNIX. A symbolic link gets removed, not its target. The
+    /// file is gone with its last hard link. Directories can't be unlinked.
+    pub fn unlink_file(&mut self, caller: ProcessId, file: &str) -> Result<(), FsError> {
         // TODO don't know yet how this interacts with files opened in the open file table
-        if self.in_mem_fs.delete_file_by_path(file) {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let file = self.resolve_symlinks(file, false)?;
+        let (mount, relative_path) = self.mount_table.resolve(&file);
+        let res = match self.backend_mut(mount)?.unlink(relative_path) {
+            Err(FsError::NotFound) if self.is_dir(caller, &file) => Err(FsError::IsDir),
+            res => res,
+        };
+        if res.is_ok() {
             log::trace!("deletion successful");
-            Ok(())
         } else {
             log::trace!("deletion failed");
-            Err(())
         }
+        res
     }
-}
-
-// caution: tests will share the state from the globally shared variables
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use libhrstd::time::Instant;
-    use std::vec::Vec;
-
-    #[test]
-    fn test_fs_basic() {
-        let mut fs = FILESYSTEM.lock();
-        let fd = fs
-            .open_or_create_file(
-                1,
-                "/foo/test1",
-                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
-                0o777,
-            )
-            .unwrap();
-        fs.write_file(1, fd, b"Hallo Welt!").unwrap();
-        fs.lseek_file(1, fd, "Hallo ".len()).unwrap();
-        let read = fs.read_file(1, fd, 100).unwrap();
-        let read = String::from_utf8_lossy(read);
-        // get rid of additional zeroes
-        let read = read.trim_matches('\0');
-        assert_eq!(read, "Welt!");
 
-        fs.lseek_file(1, fd, 0).unwrap();
-        let read = fs.read_file(1, fd, 100).unwrap();
-        let read = String::from_utf8_lossy(read);
-        // get rid of additional zeroes
-        let read = read.trim_matches('\0');
-        assert_eq!(read, "Hallo Welt!")
+    /// Public interface to the file system management data structures to create a
+    /// directory.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `mkdir()`. The directory gets the `umode` without the
+    /// bits of the umask of the caller. Its parent doesn't have to exist, because files can
+    /// be created at any path anyway. Fails with [`FsError::Exists`] if a file or a
+    /// directory, also an implicit one, exists at `path`.
+    pub fn mkdir(&mut self, caller: ProcessId, path: &str, umode: u16) -> Result<(), FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let path = self.resolve_symlinks(path, false)?;
+        if self.stat_resolved_path(caller, &path).is_ok() {
+            return Err(FsError::Exists);
+        }
+        let umode = umode & 0o777 & !self.umask(caller);
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        self.backend_mut(mount)?.mkdir(caller, relative_path, umode)
     }
 
-    #[test]
-    fn test_fs_lseek_write_size() {
-        let mut fs = FILESYSTEM.lock();
-        let payload = [0; 16384];
-        for i in 0..10 {
-            let fd = fs
-                .open_or_create_file(
-                    1,
-                    "/foo/test2",
-                    FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
-                    0o777,
-                )
-                .unwrap();
+    /// Public interface to the file system management data structures to remove a
+    /// directory.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `rmdir()`. Only empty directories can be removed;
+    /// implicit directories vanish with their last file. Mount points can't be removed
+    /// ([`FsError::Busy`]).
+    pub fn rmdir(&mut self, _caller: ProcessId, path: &str) -> Result<(), FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let path = self.resolve_symlinks(path, false)?;
+        if path == "/" || self.mount_table.lookup(&path).is_some() {
+            return Err(FsError::Busy);
+        }
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        self.backend_mut(mount)?.rmdir(relative_path)
+    }
 
-            // is first iteration
-            if i == 0 {
-                assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 0, "file size must be 0");
-            } else {
-                fs.lseek_file(1, fd, 0).unwrap();
-            }
+    /// Public interface to the file system management data structures to rename a file.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `rename()`: a file at `to` gets replaced and a
+    /// symbolic link gets renamed, not its target. Both paths must belong to the same mount.
+    pub fn rename_file(&mut self, _caller: ProcessId, from: &str, to: &str) -> Result<(), FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let from = self.resolve_symlinks(from, false)?;
+        let to = self.resolve_symlinks(to, false)?;
+        let (mount, relative_from) = self.mount_table.resolve(&from);
+        let (to_mount, relative_to) = self.mount_table.resolve(&to);
+        if mount != to_mount {
+            return Err(FsError::CrossMount);
+        }
+        self.backend_mut(mount)?.rename(relative_from, relative_to)
+    }
 
-            fs.write_file(1, fd, &payload).unwrap();
-            assert_eq!(
-                fs.fstat(1, fd).unwrap().st_size(),
-                16384,
-                "the file size must match the previous write"
-            );
-            fs.lseek_file(1, fd, 0).unwrap();
-            assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 16384, "the file size must match the previous write even if the file pointer was reset to the beginning");
-            fs.write_file(1, fd, &payload).unwrap();
-            assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 16384, "the file size must stay the same because the file offset was reset to the beginning.");
+    /// Returns true if both paths belong to the same mount, i.e. if [`Self::rename_file`]
+    /// and [`Self::link_file`] can move or link a file between them. Symbolic links in the
+    /// last component are not followed.
+    pub fn is_same_mount(&self, _caller: ProcessId, a: &str, b: &str) -> bool {
+        match (
+            self.resolve_symlinks(a, false),
+            self.resolve_symlinks(b, false),
+        ) {
+            (Ok(a), Ok(b)) => self.mount_table.resolve(&a).0 == self.mount_table.resolve(&b).0,
+            _ => false,
         }
     }
 
-    #[test]
-    fn test_fs_unlink() {
-        let mut fs = FILESYSTEM.lock();
-        let filename = "/foo/test3";
-        let fd = fs
-            .open_or_create_file(
-                1,
-                filename.clone(),
+    /// Public interface to the file system management data structures to create a hard
+    /// link.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `link()`: `new` becomes another path of the file at
+    /// `existing`, which keeps the file alive until both are unlinked. A symbolic link at
+    /// `existing` gets linked, not its target. Both paths must belong to the same mount.
+    /// Directories can't be linked ([`FsError::Unsupported`]).
+    pub fn link_file(
+        &mut self,
+        caller: ProcessId,
+        existing: &str,
+        new: &str,
+    ) -> Result<(), FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let existing = self.resolve_symlinks(existing, false)?;
+        if self.is_dir(caller, &existing) {
+            return Err(FsError::Unsupported);
+        }
+        let new = self.resolve_symlinks(new, false)?;
+        let (mount, relative_existing) = self.mount_table.resolve(&existing);
+        let (new_mount, relative_new) = self.mount_table.resolve(&new);
+        if mount != new_mount {
+            return Err(FsError::CrossMount);
+        }
+        self.backend_mut(mount)?
+            .link(relative_existing, relative_new)
+    }
+
+    /// Public interface to the file system management data structures to create a
+    /// symbolic link.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `symlink()`. `target` doesn't have to exist. A
+    /// relative target is relative to the directory of the link.
+    pub fn symlink(&mut self, caller: ProcessId, target: &str, path: &str) -> Result<(), FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        if target.is_empty() {
+            return Err(FsError::NotFound);
+        }
+        let path = self.resolve_symlinks(path, false)?;
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        self.backend_mut(mount)?
+            .symlink(caller, target, relative_path)
+    }
+
+    /// Public interface to the file system management data structures to read the target
+    /// of a symbolic link.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX `readlink()`. Fails with
+    /// [`FsError::InvalidArgument`] if the file is no symbolic link.
+    pub fn read_link(&self, _caller: ProcessId, path: &str) -> Result<String, FsError> {
+        let path = self.resolve_symlinks(path, false)?;
+        let (mount, relative_path) = self.mount_table.resolve(&path);
+        let backend = self.backend(mount)?;
+        // backends without symbolic links don't check if the file exists
+        backend.lookup(relative_path)?;
+        backend.readlink(relative_path)
+    }
+
+    /// Replaces the content of the file at `path` or adds the file, also in read-only
+    /// backends that support it, see [`FsBackend::replace`]. Files that are open see the
+    /// new content.
+    pub fn replace_file(&mut self, path: &str, data: Vec<u8>) -> Result<(), FsError> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+        let (mount, relative_path) = self.mount_table.resolve(path);
+        self.backend_mut(mount)?.replace(relative_path, data)
+    }
+
+    /// Public interface to the file system management data structures to list the files
+    /// inside a directory.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// There are no real directories. The result contains the full path of all files whose
+    /// path starts with `dir` followed by a slash, including files in subdirectories and in
+    /// other mounts below `dir`. The paths are sorted. If `dir` contains symbolic links, the
+    /// paths start with `dir` nevertheless.
+    pub fn list_dir(&self, caller: ProcessId, dir: &str) -> Vec<String> {
+        let resolved_dir = match self.resolve_symlinks(dir, true) {
+            Ok(resolved_dir) => resolved_dir,
+            Err(_) => return Vec::new(),
+        };
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let resolved_prefix = format!("{}/", resolved_dir.trim_end_matches('/'));
+        if resolved_prefix != prefix {
+            return self
+                .list_dir(caller, &resolved_dir)
+                .into_iter()
+                .map(|path| format!("{}{}", prefix, &path[resolved_prefix.len()..]))
+                .collect();
+        }
+
+        let (dir_mount, relative_dir) = self.mount_table.resolve(&prefix);
+        let mut paths = core::iter::once(MountId::ROOT)
+            .chain(self.mount_table.ids())
+            .flat_map(|mount| {
+                let mount_point = self.mount_table.mount_point(mount).unwrap();
+                let relative_paths = if mount == dir_mount {
+                    self.backend(mount).unwrap().readdir(relative_dir)
+                } else if format!("{}/", mount_point).starts_with(&prefix) {
+                    // the mount point is below the directory
+                    self.backend(mount).unwrap().readdir("/")
+                } else {
+                    Vec::new()
+                };
+                relative_paths
+                    .into_iter()
+                    .map(move |path| format!("{}{}", mount_point, path))
+                    // skip files that are hidden by a nested mount
+                    .filter(move |path| self.mount_table.resolve(path).0 == mount)
+            })
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        paths
+    }
+
+    /// Public interface to the local sockets. Checks if the file descriptor refers to a
+    /// socket. Socket file descriptors don't work with the file operations, except for
+    /// [`Self::close_file`].
+    pub fn is_socket(&self, caller: ProcessId, fd: FileDescriptor) -> bool {
+        self.socket_i_node(caller, fd).is_ok()
+    }
+
+    /// Public interface to the local sockets. Creates a new socket.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX. See [`crate::socket`] for the semantics.
+    pub fn socket(
+        &mut self,
+        caller: ProcessId,
+        kind: SocketKind,
+    ) -> Result<FileDescriptor, SocketError> {
+        let i_node = self.socket_table.create(caller, kind);
+        Ok(self.open_socket(caller, i_node))
+    }
+
+    /// Public interface to the local sockets. Creates two connected sockets.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX.
+    pub fn socketpair(
+        &mut self,
+        caller: ProcessId,
+        kind: SocketKind,
+    ) -> Result<(FileDescriptor, FileDescriptor), SocketError> {
+        let (a, b) = self.socket_table.create_pair(caller, kind);
+        Ok((self.open_socket(caller, a), self.open_socket(caller, b)))
+    }
+
+    /// Public interface to the local sockets. Binds a socket to a name.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX.
+    pub fn bind(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        name: &str,
+    ) -> Result<(), SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        self.socket_table.bind(i_node, name)
+    }
+
+    /// Public interface to the local sockets. Lets a stream socket accept connections.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX.
+    pub fn listen(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        backlog: usize,
+    ) -> Result<(), SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        self.socket_table.listen(i_node, backlog)
+    }
+
+    /// Public interface to the local sockets. Accepts the next pending connection.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX. On success, a new [`FileDescriptor`] for the
+    /// connection gets returned.
+    pub fn accept(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<FileDescriptor, SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        let connection = self.socket_table.accept(i_node)?;
+        Ok(self.open_socket(caller, connection))
+    }
+
+    /// Public interface to the local sockets. Connects a socket to the socket with the
+    /// given name.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX.
+    pub fn connect(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        name: &str,
+    ) -> Result<(), SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        self.socket_table.connect(caller, i_node, name)
+    }
+
+    /// Public interface to the local sockets. Returns the [`SocketKind`] of the socket.
+    pub fn socket_kind(
+        &self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<SocketKind, SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        self.socket_table.kind(i_node)
+    }
+
+    /// Public interface to the local sockets. Sends data to the peer or, for datagram
+    /// sockets, to `dest`.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX. On success, the number of sent bytes gets returned.
+    pub fn send(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        data: &[u8],
+        dest: Option<&str>,
+    ) -> Result<usize, SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        self.socket_table.send(i_node, data, dest)
+    }
+
+    /// Public interface to the local sockets. Receives at most `max_len` bytes.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// The interface is close to UNIX. On success, the received data and the name of
+    /// the sender gets returned. The name is only known for bound datagram sockets.
+    pub fn recv(
+        &mut self,
+        caller: ProcessId,
+        fd: FileDescriptor,
+        max_len: usize,
+    ) -> Result<(Vec<u8>, Option<String>), SocketError> {
+        let i_node = self.socket_i_node(caller, fd)?;
+        self.socket_table.recv(i_node, max_len)
+    }
+
+    /// Public interface to wait for I/O. Returns which operations on the file descriptor
+    /// currently don't block, like `poll()` on UNIX.
+    ///
+    /// This is not the public service API that gets exported via portals but the
+    /// public service Portals will wrap around these functions.
+    ///
+    /// Files are always readable and writable. Objects that live outside of the file
+    /// server (see [`Self::reserve_fd`]) are never ready; their owner knows better.
+    pub fn readiness(&self, caller: ProcessId, fd: FileDescriptor) -> Result<Readiness, FsError> {
+        let open_handle = self
+            .open_file_table
+            .lookup_handle(caller, fd)
+            .ok_or(FsError::BadFd)?;
+        if self.socket_table.is_socket(open_handle.i_node()) {
+            return self
+                .socket_table
+                .readiness(open_handle.i_node())
+                .map_err(|_| FsError::BadFd);
+        }
+        let is_file = open_handle.mount().is_some();
+        Ok(Readiness {
+            readable: is_file,
+            writable: is_file,
+            hang_up: false,
+        })
+    }
+
+    /// Public interface to reserve a file descriptor for an object that lives outside of
+    /// the file server, such as a socket of the network stack. The file descriptor
+    /// doesn't work with any operation except [`Self::close_file`].
+    pub fn reserve_fd(&mut self, caller: ProcessId) -> FileDescriptor {
+        let i_node = INODE_ALLOCATOR.lock().next(caller);
+        self.open_file_table
+            .open(caller, None, i_node, FsOpenFlags::O_RDWR)
+            .expect("opening a handle always succeeds")
+    }
+
+    /// Adds a socket to the open file table of the caller.
+    fn open_socket(&mut self, caller: ProcessId, i_node: INode) -> FileDescriptor {
+        self.open_file_table
+            .open(caller, None, i_node, FsOpenFlags::O_RDWR)
+            .expect("opening a handle always succeeds")
+    }
+
+    /// Resolves the symbolic links in an absolute path and removes `.`, `..`, and repeated
+    /// slashes. A symbolic link in the last component is only followed if `follow_last` is
+    /// set, e.g. `lstat()` doesn't follow it. Fails with [`FsError::Loop`] after
+    /// [`MAX_SYMLINKS`] links. Relative paths stay as they are.
+    fn resolve_symlinks(&self, path: &str, follow_last: bool) -> Result<String, FsError> {
+        if !path.starts_with('/') {
+            return Ok(String::from(path));
+        }
+        let mut path = String::from(path);
+        let mut followed_links = 0;
+        'restart: loop {
+            let components = path
+                .split('/')
+                .filter(|component| !component.is_empty() && *component != ".")
+                .collect::<Vec<_>>();
+            // without a trailing slash; contains no symbolic links
+            let mut resolved = String::new();
+            for (index, component) in components.iter().enumerate() {
+                if *component == ".." {
+                    resolved.truncate(resolved.rfind('/').unwrap_or(0));
+                    continue;
+                }
+                let candidate = format!("{}/{}", resolved, component);
+                let is_last = index + 1 == components.len();
+                let target = if is_last && !follow_last {
+                    Err(FsError::InvalidArgument)
+                } else {
+                    let (mount, relative_path) = self.mount_table.resolve(&candidate);
+                    self.backend(mount)
+                        .and_then(|backend| backend.readlink(relative_path))
+                };
+                match target {
+                    Ok(target) => {
+                        followed_links += 1;
+                        if followed_links > MAX_SYMLINKS {
+                            return Err(FsError::Loop);
+                        }
+                        // relative targets are relative to the directory of the link
+                        let base = if target.starts_with('/') {
+                            target
+                        } else {
+                            format!("{}/{}", resolved, target)
+                        };
+                        path = format!("{}/{}", base, components[index + 1..].join("/"));
+                        continue 'restart;
+                    }
+                    // no symbolic link or no file at all
+                    Err(_) => resolved = candidate,
+                }
+            }
+            if resolved.is_empty() {
+                resolved.push('/');
+            }
+            return Ok(resolved);
+        }
+    }
+
+    /// Returns the metadata of a file of a mount. Files of backends without timestamps
+    /// get the time of the mount.
+    fn stat_i_node(&self, mount: MountId, i_node: INode) -> Result<FileStat, FsError> {
+        let mut stat = self.backend(mount)?.stat(i_node)?;
+        stat.set_dev(mount.val());
+        if !stat.has_timestamps() {
+            let mounted_ns = self.mount_table.mounted_ns(mount).unwrap_or(0);
+            stat = stat.with_timestamps(mounted_ns, mounted_ns, mounted_ns);
+        }
+        Ok(stat)
+    }
+
+    fn backend(&self, mount: MountId) -> Result<&dyn FsBackend, FsError> {
+        match mount {
+            MountId::ROOT => Ok(&self.in_mem_fs),
+            mount => self.mount_table.backend(mount).ok_or(FsError::NotFound),
+        }
+    }
+
+    fn backend_mut(&mut self, mount: MountId) -> Result<&mut dyn FsBackend, FsError> {
+        match mount {
+            MountId::ROOT => Ok(&mut self.in_mem_fs),
+            mount => self.mount_table.backend_mut(mount).ok_or(FsError::NotFound),
+        }
+    }
+
+    fn socket_i_node(&self, caller: ProcessId, fd: FileDescriptor) -> Result<INode, SocketError> {
+        let i_node = self
+            .open_file_table
+            .lookup_handle(caller, fd)
+            .ok_or(SocketError::BadFd)?
+            .i_node();
+        if self.socket_table.is_socket(i_node) {
+            Ok(i_node)
+        } else {
+            Err(SocketError::NotASocket)
+        }
+    }
+}
+
+// caution: tests will share the state from the globally shared variables
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhrstd::time::Instant;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_fs_basic() {
+        let mut fs = FILESYSTEM.lock();
+        let fd = fs
+            .open_or_create_file(
+                1,
+                "/foo/test1",
+                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+                0o777,
+            )
+            .unwrap();
+        fs.write_file(1, fd, b"Hallo Welt!").unwrap();
+        fs.lseek_file(1, fd, "Hallo ".len()).unwrap();
+        let read = fs.read_file(1, fd, 100).unwrap();
+        let read = String::from_utf8_lossy(read);
+        // get rid of additional zeroes
+        let read = read.trim_matches('\0');
+        assert_eq!(read, "Welt!");
+
+        fs.lseek_file(1, fd, 0).unwrap();
+        let read = fs.read_file(1, fd, 100).unwrap();
+        let read = String::from_utf8_lossy(read);
+        // get rid of additional zeroes
+        let read = read.trim_matches('\0');
+        assert_eq!(read, "Hallo Welt!")
+    }
+
+    #[test]
+    fn test_fs_lseek_write_size() {
+        let mut fs = FILESYSTEM.lock();
+        let payload = [0; 16384];
+        for i in 0..10 {
+            let fd = fs
+                .open_or_create_file(
+                    1,
+                    "/foo/test2",
+                    FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+                    0o777,
+                )
+                .unwrap();
+
+            // is first iteration
+            if i == 0 {
+                assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 0, "file size must be 0");
+            } else {
+                fs.lseek_file(1, fd, 0).unwrap();
+            }
+
+            fs.write_file(1, fd, &payload).unwrap();
+            assert_eq!(
+                fs.fstat(1, fd).unwrap().st_size(),
+                16384,
+                "the file size must match the previous write"
+            );
+            fs.lseek_file(1, fd, 0).unwrap();
+            assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 16384, "the file size must match the previous write even if the file pointer was reset to the beginning");
+            fs.write_file(1, fd, &payload).unwrap();
+            assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 16384, "the file size must stay the same because the file offset was reset to the beginning.");
+        }
+    }
+
+    #[test]
+    fn test_pread() {
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs.open_or_create_file(1, "/pread", flags, 0o666).unwrap();
+        fs.write_file(1, fd, b"0123456789").unwrap();
+        fs.lseek_file(1, fd, 2).unwrap();
+        assert_eq!(fs.pread_file(1, fd, 6, 100).unwrap(), b"6789");
+        assert_eq!(fs.pread_file(1, fd, 10, 1).unwrap(), b"");
+        // the file offset stays
+        assert_eq!(fs.read_file(1, fd, 3).unwrap(), b"234");
+        assert_eq!(fs.pread_file(2, fd, 0, 1), Err(FsError::BadFd));
+    }
+
+    #[test]
+    fn test_open_exclusive() {
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_EXCL | FsOpenFlags::O_RDWR;
+        assert!(fs.open_or_create_file(1, "/excl", flags, 0o644).is_ok());
+        assert_eq!(
+            fs.open_or_create_file(1, "/excl", flags, 0o644),
+            Err(FsError::Exists)
+        );
+        // without O_CREAT, O_EXCL has no effect
+        let flags = FsOpenFlags::O_EXCL | FsOpenFlags::O_RDWR;
+        assert!(fs.open_or_create_file(1, "/excl", flags, 0o644).is_ok());
+    }
+
+    #[test]
+    fn test_write_vectored() {
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs.open_or_create_file(1, "/vec", flags, 0o644).unwrap();
+        let bufs: [&[u8]; 3] = [b"hello", b"", b" world"];
+        assert_eq!(fs.write_file_vectored(1, fd, None, &bufs), Ok(11));
+        // positional writes don't move the file offset
+        assert_eq!(
+            fs.write_file_vectored(1, fd, Some(0), &[b"HE", b"LLO"]),
+            Ok(5)
+        );
+        assert_eq!(fs.pread_file(1, fd, 0, 100).unwrap(), b"HELLO");
+        assert_eq!(fs.write_file(1, fd, b"!"), Ok(1));
+        assert_eq!(fs.pread_file(1, fd, 0, 100).unwrap(), b"HELLO\0\0\0\0\0\0!");
+        assert_eq!(fs.write_file_vectored(1, fd, Some(14), &[b"x"]), Ok(1));
+        assert_eq!(fs.pread_file(1, fd, 11, 100).unwrap(), b"!\0\0x");
+        assert_eq!(
+            fs.write_file_vectored(1, FileDescriptor::new(99), None, &bufs),
+            Err(FsError::BadFd)
+        );
+    }
+
+    #[test]
+    fn test_umask() {
+        let mut fs = FILESYSTEM.lock();
+        let (parent, child) = (40, 41);
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let mut create = |fs: &mut Filesystem, pid, path, umode| {
+            let fd = fs.open_or_create_file(pid, path, flags, umode).unwrap();
+            fs.fstat(pid, fd).unwrap().st_mode() & !S_IFMT
+        };
+
+        assert_eq!(fs.umask(parent), DEFAULT_UMASK);
+        assert_eq!(create(&mut fs, parent, "/umask/a", 0o666), 0o644);
+
+        assert_eq!(fs.set_umask(parent, 0o077), DEFAULT_UMASK);
+        assert_eq!(create(&mut fs, parent, "/umask/b", 0o777), 0o700);
+        // the mode of existing files doesn't change
+        assert_eq!(create(&mut fs, parent, "/umask/a", 0o777), 0o644);
+
+        // only the permission bits are used
+        assert_eq!(fs.set_umask(parent, 0o4777), 0o077);
+        assert_eq!(fs.umask(parent), 0o777);
+        assert_eq!(create(&mut fs, parent, "/umask/c", 0o777), 0);
+
+        fs.set_umask(parent, 0o027);
+        fs.inherit_umask(parent, child);
+        assert_eq!(fs.umask(child), 0o027);
+        assert_eq!(create(&mut fs, child, "/umask/d", 0o666), 0o640);
+        // changes of the child don't affect the parent
+        fs.set_umask(child, 0);
+        assert_eq!(fs.umask(parent), 0o027);
+    }
+
+    #[test]
+    fn test_file_limits() {
+        let mut fs = Filesystem::new();
+        let pid = 1;
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        assert_eq!(fs.file_limits(pid), FileLimits::UNLIMITED);
+        fs.set_file_limits(
+            pid,
+            FileLimits {
+                max_open_files: 4,
+                max_file_size: 8,
+            },
+        );
+
+        // file descriptors 0 to 2 are free but reserved for the standard streams
+        let fd = fs
+            .open_or_create_file(pid, "/limits/a", flags, 0o666)
+            .unwrap();
+        assert_eq!(fd, FileDescriptor::new(3));
+        assert_eq!(
+            fs.open_or_create_file(pid, "/limits/b", flags, 0o666),
+            Err(FsError::TooManyOpenFiles)
+        );
+        assert_eq!(
+            fs.access(pid, "/limits/b", FsAccessMode::F_OK),
+            Err(FsError::NotFound)
+        );
+
+        // the write that crosses the limit gets shortened
+        assert_eq!(fs.write_file(pid, fd, b"hello").unwrap(), 5);
+        assert_eq!(fs.write_file(pid, fd, b"world").unwrap(), 3);
+        assert_eq!(fs.write_file(pid, fd, b"!"), Err(FsError::TooLarge));
+        assert_eq!(
+            fs.write_file_vectored(pid, fd, Some(6), &[b"ab", b"cd"]),
+            Ok(2)
+        );
+        assert_eq!(fs.ftruncate_file(pid, fd, 9), Err(FsError::TooLarge));
+        assert_eq!(
+            fs.truncate_file(pid, "/limits/a", 9),
+            Err(FsError::TooLarge)
+        );
+        fs.ftruncate_file(pid, fd, 2).unwrap();
+
+        // other processes have no limits
+        let fd = fs
+            .open_or_create_file(2, "/limits/a", flags, 0o666)
+            .unwrap();
+        assert_eq!(fs.write_file(2, fd, b"hello world").unwrap(), 11);
+    }
+
+    #[test]
+    fn test_access() {
+        let mut fs = FILESYSTEM.lock();
+        let (owner, other) = (42, 43);
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        fs.set_umask(owner, 0);
+        fs.open_or_create_file(owner, "/access/a", flags, 0o754)
+            .unwrap();
+
+        let rwx = FsAccessMode::R_OK | FsAccessMode::W_OK | FsAccessMode::X_OK;
+        assert_eq!(fs.access(owner, "/access/a", rwx), Ok(()));
+        assert_eq!(fs.access(other, "/access/a", FsAccessMode::F_OK), Ok(()));
+        assert_eq!(fs.access(other, "/access/a", FsAccessMode::R_OK), Ok(()));
+        assert_eq!(
+            fs.access(other, "/access/a", FsAccessMode::R_OK | FsAccessMode::X_OK),
+            Err(FsError::Perm)
+        );
+        assert_eq!(
+            fs.access(other, "/access/b", FsAccessMode::F_OK),
+            Err(FsError::NotFound)
+        );
+        assert_eq!(
+            fs.access(owner, "", FsAccessMode::F_OK),
+            Err(FsError::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_read_only() {
+        // a separate instance; the global one is shared with the other tests
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs.open_or_create_file(1, "/ro/a", flags, 0o666).unwrap();
+        fs.write_file(1, fd, b"data").unwrap();
+
+        fs.set_read_only(true);
+        assert!(fs.is_read_only());
+        assert!(fs.write_file(1, fd, b"more").is_err());
+        assert!(fs.open_or_create_file(1, "/ro/a", flags, 0o666).is_err());
+        assert!(fs
+            .open_or_create_file(1, "/ro/b", FsOpenFlags::O_CREAT, 0o666)
+            .is_err());
+        assert!(fs.unlink_file(1, "/ro/a").is_err());
+        let fd = fs
+            .open_or_create_file(1, "/ro/a", FsOpenFlags::O_CREAT, 0o666)
+            .unwrap();
+        assert_eq!(fs.read_file(1, fd, 100).unwrap(), b"data");
+
+        fs.set_read_only(false);
+        fs.unlink_file(1, "/ro/a").unwrap();
+    }
+
+    #[test]
+    fn test_preopen_file() {
+        let mut fs = Filesystem::new();
+        let (launcher, child) = (1, 2);
+        let fd = FileDescriptor::new(4);
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY;
+        fs.preopen_file(launcher, child, fd, "/preopen/log", flags)
+            .unwrap();
+        // the file only belongs to the child
+        assert!(fs.write_file(launcher, fd, b"x").is_err());
+        assert_eq!(fs.write_file(child, fd, b"hello"), Ok(5));
+        assert!(fs
+            .open_file_table
+            .lookup_handle(launcher, FileDescriptor::new(3))
+            .is_none());
+
+        assert!(fs
+            .preopen_file(launcher, child, fd, "/preopen/log", flags)
+            .is_err());
+        assert!(fs
+            .preopen_file(
+                launcher,
+                child,
+                FileDescriptor::new(5),
+                "/preopen/none",
+                FsOpenFlags::O_RDONLY
+            )
+            .is_err());
+        // failed attempts don't leave open files behind
+        assert!(fs
+            .open_file_table
+            .lookup_handle(launcher, FileDescriptor::new(3))
+            .is_none());
+
+        let fd = fs
+            .open_or_create_file(launcher, "/preopen/log", FsOpenFlags::O_RDONLY, 0)
+            .unwrap();
+        assert_eq!(fs.read_file(launcher, fd, 100).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_inherit_and_swap_files() {
+        let mut fs = Filesystem::new();
+        let (parent, child) = (1, 2);
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs
+            .open_or_create_file(parent, "/spawn/file", flags, 0o666)
+            .unwrap();
+        fs.write_file(parent, fd, b"hello").unwrap();
+        fs.lseek_file(parent, fd, 1).unwrap();
+        let socket = fs.socket(parent, SocketKind::Stream).unwrap();
+
+        let child_fd = FileDescriptor::new(0);
+        fs.inherit_file(parent, fd, child, child_fd).unwrap();
+        // the copy starts at the offset of the original but has its own offset
+        assert_eq!(fs.read_file(child, child_fd, 2).unwrap(), b"el");
+        assert_eq!(fs.read_file(parent, fd, 100).unwrap(), b"ello");
+        assert_eq!(
+            fs.inherit_file(parent, fd, child, child_fd),
+            Err(FsError::Busy)
+        );
+        assert_eq!(
+            fs.inherit_file(parent, socket, child, FileDescriptor::new(5)),
+            Err(FsError::Unsupported)
+        );
+        assert_eq!(
+            fs.inherit_file(
+                parent,
+                FileDescriptor::new(42),
+                child,
+                FileDescriptor::new(5)
+            ),
+            Err(FsError::BadFd)
+        );
+        assert_eq!(fs.open_files(parent), vec![fd]);
+        assert_eq!(fs.open_files(child), vec![child_fd]);
+
+        // the socket stays with the parent
+        fs.swap_files(parent, child).unwrap();
+        assert_eq!(fs.open_files(parent), vec![child_fd]);
+        assert_eq!(fs.open_files(child), vec![fd]);
+        assert!(fs.is_socket(parent, socket));
+        assert!(!fs.is_socket(child, socket));
+
+        // a file can't replace the socket
+        let child_socket_fd = fs
+            .open_or_create_file(child, "/spawn/file", FsOpenFlags::O_RDONLY, 0)
+            .unwrap();
+        assert_eq!(child_socket_fd, socket);
+        assert_eq!(fs.swap_files(parent, child), Err(FsError::Busy));
+        assert_eq!(fs.open_files(child), vec![fd, child_socket_fd]);
+    }
+
+    #[test]
+    fn test_fs_unlink() {
+        let mut fs = FILESYSTEM.lock();
+        let filename = "/foo/test3";
+        let fd = fs
+            .open_or_create_file(
+                1,
+                filename.clone(),
                 FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
                 0o777,
             )
@@ -392,6 +1687,643 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fs_list_dir() {
+        let mut fs = FILESYSTEM.lock();
+        for path in ["/bar/b", "/bar/a", "/bar/sub/c", "/barfoo"] {
+            let fd = fs
+                .open_or_create_file(1, path, FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR, 0o777)
+                .unwrap();
+            fs.close_file(1, fd).unwrap();
+        }
+        assert_eq!(fs.list_dir(1, "/bar/"), ["/bar/a", "/bar/b", "/bar/sub/c"]);
+        assert!(fs.list_dir(1, "/nope").is_empty());
+    }
+
+    #[test]
+    fn test_socket_stream() {
+        let mut fs = FILESYSTEM.lock();
+        let server = fs.socket(1, SocketKind::Stream).unwrap();
+        fs.bind(1, server, "/tmp/stream.sock").unwrap();
+        fs.listen(1, server, 0).unwrap();
+        assert_eq!(fs.accept(1, server), Err(SocketError::WouldBlock));
+
+        let client = fs.socket(2, SocketKind::Stream).unwrap();
+        assert_eq!(
+            fs.send(2, client, b"early", None),
+            Err(SocketError::NotConnected)
+        );
+        fs.connect(2, client, "/tmp/stream.sock").unwrap();
+        let connection = fs.accept(1, server).unwrap();
+
+        assert_eq!(fs.send(2, client, b"Hallo ", None), Ok(6));
+        assert_eq!(fs.send(2, client, b"Welt!", None), Ok(5));
+        assert_eq!(fs.recv(1, connection, 8).unwrap().0, b"Hallo We");
+        assert_eq!(fs.recv(1, connection, 100).unwrap().0, b"lt!");
+        assert_eq!(fs.recv(1, connection, 100), Err(SocketError::WouldBlock));
+
+        fs.close_file(2, client).unwrap();
+        assert!(fs.recv(1, connection, 100).unwrap().0.is_empty(), "EOF");
+        assert_eq!(
+            fs.send(1, connection, b"x", None),
+            Err(SocketError::BrokenPipe)
+        );
+
+        fs.close_file(1, connection).unwrap();
+        fs.close_file(1, server).unwrap();
+        let fd = fs.socket(1, SocketKind::Stream).unwrap();
+        fs.bind(1, fd, "/tmp/stream.sock")
+            .expect("name must be free after close");
+        fs.close_file(1, fd).unwrap();
+    }
+
+    #[test]
+    fn test_socket_datagram() {
+        let mut fs = FILESYSTEM.lock();
+        let (a, b) = fs.socketpair(1, SocketKind::Datagram).unwrap();
+        assert!(fs.is_socket(1, a));
+        assert_eq!(fs.socket_kind(1, a), Ok(SocketKind::Datagram));
+        assert_eq!(fs.send(1, a, b"ping", None), Ok(4));
+        assert_eq!(fs.recv(1, b, 100).unwrap(), (Vec::from(*b"ping"), None));
+
+        let server = fs.socket(1, SocketKind::Datagram).unwrap();
+        fs.bind(1, server, "/tmp/dgram.sock").unwrap();
+        fs.bind(1, a, "/tmp/dgram.client").unwrap();
+        assert_eq!(
+            fs.bind(1, b, "/tmp/dgram.sock"),
+            Err(SocketError::AddressInUse)
+        );
+        fs.send(1, a, b"first", Some("/tmp/dgram.sock")).unwrap();
+        fs.send(1, a, b"second", Some("/tmp/dgram.sock")).unwrap();
+        // message boundaries are kept; the rest of a datagram gets discarded
+        let (data, from) = fs.recv(1, server, 3).unwrap();
+        assert_eq!(data, b"fir");
+        assert_eq!(from.as_deref(), Some("/tmp/dgram.client"));
+        assert_eq!(fs.recv(1, server, 100).unwrap().0, b"second");
+
+        assert_eq!(
+            fs.send(1, server, b"x", Some("/nope")),
+            Err(SocketError::NotFound)
+        );
+        assert_eq!(
+            fs.send(1, server, b"x", None),
+            Err(SocketError::DestinationRequired)
+        );
+        for fd in [a, b, server] {
+            fs.close_file(1, fd).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_socket_fds_are_no_files() {
+        let mut fs = FILESYSTEM.lock();
+        let file = fs
+            .open_or_create_file(
+                3,
+                "/foo/test4",
+                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+                0o777,
+            )
+            .unwrap();
+        assert!(!fs.is_socket(3, file));
+        assert_eq!(fs.recv(3, file, 1), Err(SocketError::NotASocket));
+        assert_eq!(fs.recv(3, 1000_u64.into(), 1), Err(SocketError::BadFd));
+
+        let socket = fs.socket(3, SocketKind::Stream).unwrap();
+        assert_ne!(socket, file, "sockets and files share the file descriptors");
+        assert!(fs.read_file(3, socket, 1).is_err());
+    }
+
+    #[test]
+    fn test_readiness() {
+        let mut fs = FILESYSTEM.lock();
+        let file = fs
+            .open_or_create_file(
+                1,
+                "/foo/readiness",
+                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+                0o777,
+            )
+            .unwrap();
+        let ready = Readiness {
+            readable: true,
+            writable: true,
+            hang_up: false,
+        };
+        assert_eq!(fs.readiness(1, file), Ok(ready));
+        assert_eq!(fs.readiness(1, 1000_u64.into()), Err(FsError::BadFd));
+
+        let (a, b) = fs.socketpair(1, SocketKind::Stream).unwrap();
+        assert!(!fs.readiness(1, b).unwrap().readable);
+        assert!(fs.readiness(1, b).unwrap().writable);
+        fs.send(1, a, b"ping", None).unwrap();
+        assert!(fs.readiness(1, b).unwrap().readable);
+        fs.recv(1, b, 100).unwrap();
+        assert!(!fs.readiness(1, b).unwrap().readable);
+        fs.close_file(1, a).unwrap();
+        assert_eq!(
+            fs.readiness(1, b),
+            Ok(Readiness {
+                hang_up: true,
+                ..ready
+            })
+        );
+
+        let server = fs.socket(1, SocketKind::Stream).unwrap();
+        fs.bind(1, server, "/tmp/readiness.sock").unwrap();
+        fs.listen(1, server, 1).unwrap();
+        assert!(!fs.readiness(1, server).unwrap().readable);
+        let client = fs.socket(1, SocketKind::Stream).unwrap();
+        assert!(fs.readiness(1, client).unwrap().hang_up);
+        fs.connect(1, client, "/tmp/readiness.sock").unwrap();
+        assert!(
+            fs.readiness(1, server).unwrap().readable,
+            "pending connection"
+        );
+        assert!(!fs.readiness(1, client).unwrap().hang_up);
+
+        let reserved = fs.reserve_fd(1);
+        assert_eq!(fs.readiness(1, reserved), Ok(Readiness::default()));
+        for fd in [file, b, server, client, reserved] {
+            fs.close_file(1, fd).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reserved_fd() {
+        let mut fs = FILESYSTEM.lock();
+        let fd = fs.reserve_fd(4);
+        let socket = fs.socket(4, SocketKind::Datagram).unwrap();
+        assert_ne!(fd, socket);
+        assert!(!fs.is_socket(4, fd));
+        assert!(fs.read_file(4, fd, 1).is_err());
+        fs.close_file(4, fd).unwrap();
+        assert!(fs.close_file(4, fd).is_err());
+    }
+
+    #[test]
+    fn test_release_process() {
+        let mut fs = Filesystem::new();
+        let pid = 1;
+        let file = fs
+            .open_or_create_file(
+                pid,
+                "/release",
+                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+                0o666,
+            )
+            .unwrap();
+        let (a, b) = fs.socketpair(2, SocketKind::Stream).unwrap();
+        let socket = fs.socket(pid, SocketKind::Stream).unwrap();
+        let reserved = fs.reserve_fd(pid);
+        fs.set_umask(pid, 0o077);
+        fs.set_file_limits(
+            pid,
+            FileLimits {
+                max_open_files: 8,
+                max_file_size: 8,
+            },
+        );
+
+        fs.release_process(pid);
+        for fd in [file, socket, reserved] {
+            assert_eq!(fs.close_file(pid, fd), Err(FsError::BadFd));
+        }
+        assert_eq!(fs.umask(pid), DEFAULT_UMASK);
+        assert_eq!(fs.file_limits(pid), FileLimits::UNLIMITED);
+        // the sockets of other processes stay
+        for fd in [a, b] {
+            fs.close_file(2, fd).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mount() {
+        // own instance: mounts would affect the other tests
+        let mut fs = Filesystem::new();
+        fs.mount("/mnt/", Box::new(InMemFilesystem::new())).unwrap();
+        fs.mount("/mnt/sub", Box::new(InMemFilesystem::new()))
+            .unwrap();
+        assert!(fs.mount("/mnt", Box::new(InMemFilesystem::new())).is_err());
+        assert!(fs.mount("/", Box::new(InMemFilesystem::new())).is_err());
+
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        for path in ["/a", "/mnt/b", "/mnt/sub/c", "/mntfoo"] {
+            let fd = fs.open_or_create_file(1, path, flags, 0o777).unwrap();
+            fs.write_file(1, fd, path.as_bytes()).unwrap();
+            fs.close_file(1, fd).unwrap();
+        }
+        // the backends only see paths relative to their mount point
+        assert!(fs.in_mem_fs.get_file_by_path("/a").is_some());
+        assert!(fs.in_mem_fs.get_file_by_path("/mnt/b").is_none());
+        assert_eq!(fs.mount_table.resolve("/mnt/sub/c").1, "/c");
+        assert_eq!(fs.mount_table.resolve("/mntfoo").0, MountId::ROOT);
+
+        assert_eq!(
+            fs.list_dir(1, "/"),
+            ["/a", "/mnt/b", "/mnt/sub/c", "/mntfoo"]
+        );
+        assert_eq!(fs.list_dir(1, "/mnt"), ["/mnt/b", "/mnt/sub/c"]);
+
+        let fd = fs
+            .open_or_create_file(1, "/mnt/sub/c", FsOpenFlags::O_RDWR, 0)
+            .unwrap();
+        assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 10);
+        assert_eq!(fs.read_file(1, fd, 100).unwrap(), b"/mnt/sub/c");
+        assert!(fs.unmount("/mnt/sub").is_err(), "file is still open");
+        fs.close_file(1, fd).unwrap();
+        fs.unmount("/mnt/sub").unwrap();
+        assert!(fs
+            .open_or_create_file(1, "/mnt/sub/c", FsOpenFlags::O_RDWR, 0)
+            .is_err());
+
+        fs.unlink_file(1, "/mnt/b").unwrap();
+        assert!(fs.list_dir(1, "/mnt").is_empty());
+
+        // the in-memory file system doesn't support it
+        assert!(fs.replace_file("/mnt/b", b"data".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_stat_path() {
+        use core::sync::atomic::{
+            AtomicU64,
+            Ordering,
+        };
+        static NOW_NS: AtomicU64 = AtomicU64::new(5_000_000_001);
+        set_clock(|| NOW_NS.load(Ordering::SeqCst));
+
+        // own instance: the mount would affect the other tests
+        let mut fs = Filesystem::new();
+        fs.mount("/mnt", Box::new(InMemFilesystem::new())).unwrap();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs
+            .open_or_create_file(1, "/mnt/dir/f", flags, 0o640)
+            .unwrap();
+        fs.write_file(1, fd, &[0; 513]).unwrap();
+
+        let stat = fs.stat_path(1, "/mnt/dir/f").unwrap();
+        assert_eq!(stat.st_mode(), S_IFREG | 0o640);
+        assert_eq!(stat.st_size(), 513);
+        assert_eq!(stat.st_blocks(), 2);
+        assert_eq!(stat.st_blksize(), 4096);
+        assert_eq!(stat.st_nlink(), 1);
+        assert_ne!(stat.st_dev(), 0, "the device identifies the mount");
+        assert_eq!((stat.st_mtime(), stat.st_mtime_nsec()), (5, 1));
+        assert_eq!(fs.fstat(1, fd).unwrap().st_ino(), stat.st_ino());
+
+        // writes update the modification time, reads the access time
+        NOW_NS.store(7_000_000_000, Ordering::SeqCst);
+        fs.write_file(1, fd, b"x").unwrap();
+        let stat = fs.stat_path(1, "/mnt/dir/f").unwrap();
+        assert_eq!(
+            (stat.st_atime(), stat.st_mtime(), stat.st_ctime()),
+            (5, 7, 7)
+        );
+        NOW_NS.store(9_000_000_000, Ordering::SeqCst);
+        fs.lseek_file(1, fd, 0).unwrap();
+        fs.read_file(1, fd, 1).unwrap();
+        let stat = fs.stat_path(1, "/mnt/dir/f").unwrap();
+        assert_eq!((stat.st_atime(), stat.st_mtime()), (9, 7));
+
+        for dir in ["/", "/mnt", "/mnt/", "/mnt/dir"] {
+            assert!(fs.stat_path(1, dir).unwrap().is_dir(), "{}", dir);
+        }
+        assert!(fs.stat_path(1, "/mnt/di").is_err());
+        assert!(fs.stat_path(1, "/missing").is_err());
+        assert!(fs.stat_path(1, "mnt/dir/f").is_err());
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut fs = Filesystem::new();
+        fs.mount("/mnt", Box::new(InMemFilesystem::new())).unwrap();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs.open_or_create_file(1, "/a", flags, 0o644).unwrap();
+        fs.write_file(1, fd, b"a").unwrap();
+        fs.close_file(1, fd).unwrap();
+        let fd = fs.open_or_create_file(1, "/b", flags, 0o644).unwrap();
+        fs.close_file(1, fd).unwrap();
+
+        // replaces the target
+        fs.rename_file(1, "/a", "/b").unwrap();
+        assert!(fs.stat_path(1, "/a").is_err());
+        assert_eq!(fs.stat_path(1, "/b").unwrap().st_size(), 1);
+        assert!(fs.rename_file(1, "/a", "/c").is_err());
+
+        // directories move with all their files
+        for path in ["/dir/x", "/dir/sub/y"] {
+            let fd = fs.open_or_create_file(1, path, flags, 0o644).unwrap();
+            fs.close_file(1, fd).unwrap();
+        }
+        fs.rename_file(1, "/dir", "/moved/").unwrap();
+        assert_eq!(fs.list_dir(1, "/moved"), ["/moved/sub/y", "/moved/x"]);
+        assert!(fs.list_dir(1, "/dir").is_empty());
+        assert!(fs.rename_file(1, "/moved", "/moved/sub/z").is_err());
+
+        // like POSIX, a file only replaces a file and a directory an empty directory
+        fs.mkdir(1, "/empty", 0o755).unwrap();
+        assert_eq!(fs.rename_file(1, "/b", "/moved"), Err(FsError::IsDir));
+        assert_eq!(fs.rename_file(1, "/b", "/empty"), Err(FsError::IsDir));
+        assert_eq!(fs.rename_file(1, "/moved", "/b"), Err(FsError::NotDir));
+        assert_eq!(
+            fs.rename_file(1, "/empty", "/moved"),
+            Err(FsError::NotEmpty)
+        );
+        fs.mkdir(1, "/full", 0o755).unwrap();
+        let fd = fs.open_or_create_file(1, "/full/f", flags, 0o644).unwrap();
+        fs.close_file(1, fd).unwrap();
+        assert_eq!(fs.rename_file(1, "/moved", "/full"), Err(FsError::NotEmpty));
+        // nothing moved
+        assert_eq!(fs.stat_path(1, "/b").unwrap().st_size(), 1);
+        assert_eq!(fs.list_dir(1, "/moved"), ["/moved/sub/y", "/moved/x"]);
+        assert_eq!(fs.list_dir(1, "/full"), ["/full/f"]);
+        fs.rename_file(1, "/moved", "/empty").unwrap();
+        assert_eq!(fs.list_dir(1, "/empty"), ["/empty/sub/y", "/empty/x"]);
+        assert!(fs.stat_path(1, "/moved").is_err());
+
+        assert!(!fs.is_same_mount(1, "/b", "/mnt/b"));
+        assert!(
+            fs.rename_file(1, "/b", "/mnt/b").is_err(),
+            "no moves across mounts"
+        );
+        fs.set_read_only(true);
+        assert!(fs.rename_file(1, "/b", "/c").is_err());
+    }
+
+    #[test]
+    fn test_directories() {
+        let mut fs = Filesystem::new();
+        fs.mkdir(1, "/d", 0o777).unwrap();
+        let stat = fs.stat_path(1, "/d/").unwrap();
+        assert!(stat.is_dir());
+        assert_eq!(stat.st_mode() & 0o777, 0o755, "umask applies");
+        assert_eq!(fs.mkdir(1, "/d", 0o755), Err(FsError::Exists));
+        assert_eq!(fs.list_dir(1, "/"), ["/d"]);
+
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        assert_eq!(
+            fs.open_or_create_file(1, "/d", flags, 0o644),
+            Err(FsError::IsDir)
+        );
+        assert_eq!(fs.unlink_file(1, "/d"), Err(FsError::IsDir));
+        let fd = fs.open_or_create_file(1, "/d/f", flags, 0o644).unwrap();
+        fs.close_file(1, fd).unwrap();
+        // implicit directories exist as well
+        assert_eq!(fs.mkdir(1, "/d", 0o755), Err(FsError::Exists));
+        assert_eq!(fs.rmdir(1, "/d"), Err(FsError::NotEmpty));
+        assert_eq!(fs.rmdir(1, "/d/f"), Err(FsError::NotDir));
+
+        fs.rename_file(1, "/d", "/e").unwrap();
+        assert_eq!(fs.list_dir(1, "/"), ["/e", "/e/f"]);
+        fs.unlink_file(1, "/e/f").unwrap();
+        fs.rmdir(1, "/e").unwrap();
+        assert!(fs.stat_path(1, "/e").is_err());
+        assert_eq!(fs.rmdir(1, "/e"), Err(FsError::NotFound));
+        assert_eq!(fs.rmdir(1, "/"), Err(FsError::Busy));
+
+        assert_eq!(absolute_path("/tmp/", "a/../b"), "/tmp/a/../b");
+        assert_eq!(absolute_path("/", "a"), "/a");
+        assert_eq!(absolute_path("/tmp", "/a"), "/a");
+        assert_eq!(absolute_path("/tmp", ""), "");
+        let fd = fs.open_or_create_file(1, &absolute_path("/x/y", "../z"), flags, 0o644);
+        fs.close_file(1, fd.unwrap()).unwrap();
+        assert!(fs.stat_path(1, "/x/z").is_ok());
+    }
+
+    #[test]
+    fn test_hard_links() {
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs.open_or_create_file(1, "/orig", flags, 0o644).unwrap();
+        fs.write_file(1, fd, b"shared").unwrap();
+
+        fs.link_file(1, "/orig", "/link").unwrap();
+        assert!(
+            fs.link_file(1, "/orig", "/link").is_err(),
+            "the path exists"
+        );
+        assert!(fs.link_file(1, "/missing", "/other").is_err());
+        let stat = fs.stat_path(1, "/link").unwrap();
+        assert_eq!(stat.st_nlink(), 2);
+        assert_eq!(stat.st_ino(), fs.stat_path(1, "/orig").unwrap().st_ino());
+
+        // the file lives until its last link is gone
+        fs.unlink_file(1, "/orig").unwrap();
+        assert_eq!(fs.stat_path(1, "/link").unwrap().st_nlink(), 1);
+        let link_fd = fs.open_or_create_file(1, "/link", flags, 0).unwrap();
+        assert_eq!(fs.read_file(1, link_fd, 100).unwrap(), b"shared");
+        fs.unlink_file(1, "/link").unwrap();
+        assert!(fs.stat_path(1, "/link").is_err());
+    }
+
+    #[test]
+    fn test_symlinks() {
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs
+            .open_or_create_file(1, "/data/file", flags, 0o600)
+            .unwrap();
+        fs.write_file(1, fd, b"content").unwrap();
+
+        fs.symlink(1, "/data/file", "/abs").unwrap();
+        fs.symlink(1, "file", "/data/rel").unwrap();
+        fs.symlink(1, "/data", "/dirlink").unwrap();
+        fs.symlink(1, "../data/./rel", "/dirlink/chain").unwrap();
+        assert!(
+            fs.symlink(1, "/elsewhere", "/abs").is_err(),
+            "the path exists"
+        );
+
+        for path in ["/abs", "/data/rel", "/dirlink/file", "/dirlink/chain"] {
+            let fd = fs.open_or_create_file(1, path, flags, 0).unwrap();
+            assert_eq!(fs.read_file(1, fd, 100).unwrap(), b"content", "{}", path);
+            assert_eq!(fs.stat_path(1, path).unwrap().st_size(), 7);
+        }
+        assert_eq!(fs.read_link(1, "/data/rel").unwrap(), "file");
+        assert_eq!(fs.read_link(1, "/dirlink/chain").unwrap(), "../data/./rel");
+        assert!(fs.read_link(1, "/data/file").is_err(), "no symbolic link");
+        assert_eq!(fs.canonicalize(1, "/dirlink/chain").unwrap(), "/data/file");
+        assert!(fs.stat_path(1, "/dirlink").unwrap().is_dir());
+        assert_eq!(
+            fs.list_dir(1, "/dirlink"),
+            ["/dirlink/chain", "/dirlink/file", "/dirlink/rel"]
+        );
+
+        let lstat = fs.lstat_path(1, "/abs").unwrap();
+        assert!(lstat.is_symlink());
+        assert_eq!(lstat.st_mode(), S_IFLNK | 0o777);
+        assert_eq!(lstat.st_size(), "/data/file".len() as i64);
+
+        // a dangling link creates its target
+        fs.symlink(1, "/data/new", "/dangling").unwrap();
+        assert!(fs.stat_path(1, "/dangling").is_err());
+        fs.open_or_create_file(1, "/dangling", flags, 0o600)
+            .unwrap();
+        assert!(fs.stat_path(1, "/data/new").is_ok());
+
+        // unlink and rename affect the link, not its target
+        fs.rename_file(1, "/abs", "/abs2").unwrap();
+        assert_eq!(fs.read_link(1, "/abs2").unwrap(), "/data/file");
+        fs.unlink_file(1, "/abs2").unwrap();
+        assert!(fs.stat_path(1, "/data/file").is_ok());
+
+        fs.symlink(1, "/loop_b", "/loop_a").unwrap();
+        fs.symlink(1, "/loop_a", "/loop_b").unwrap();
+        assert!(fs.open_or_create_file(1, "/loop_a", flags, 0).is_err());
+        assert!(fs.lstat_path(1, "/loop_a").unwrap().is_symlink());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let writer = fs.open_or_create_file(1, "/file", flags, 0o644).unwrap();
+        fs.write_file(1, writer, b"0123456789").unwrap();
+        let reader = fs
+            .open_or_create_file(1, "/file", FsOpenFlags::O_RDONLY, 0)
+            .unwrap();
+        assert_eq!(fs.read_file(1, reader, 4).unwrap(), b"0123");
+
+        // shrinking keeps the offsets of the open files, even beyond the new end
+        fs.truncate_file(1, "/file", 6).unwrap();
+        assert_eq!(fs.stat_path(1, "/file").unwrap().st_size(), 6);
+        assert_eq!(fs.read_file(1, reader, 100).unwrap(), b"45");
+        fs.ftruncate_file(1, writer, 2).unwrap();
+        assert!(fs.read_file(1, reader, 100).unwrap().is_empty());
+        // the writer still is at offset 10 and leaves a hole of zeros
+        fs.write_file(1, writer, b"x").unwrap();
+        fs.lseek_file(1, reader, 0).unwrap();
+        assert_eq!(
+            fs.read_file(1, reader, 100).unwrap(),
+            b"01\0\0\0\0\0\0\0\0x"
+        );
+
+        // growing fills with zeros
+        fs.truncate_file(1, "/file", 16).unwrap();
+        fs.lseek_file(1, reader, 11).unwrap();
+        assert_eq!(fs.read_file(1, reader, 100).unwrap(), &[0; 5]);
+        assert!(fs.truncate_file(1, "/file", MAX_FILE_SIZE + 1).is_err());
+
+        // O_TRUNC empties the file
+        let fd = fs
+            .open_or_create_file(1, "/file", FsOpenFlags::O_WRONLY | FsOpenFlags::O_TRUNC, 0)
+            .unwrap();
+        assert_eq!(fs.fstat(1, fd).unwrap().st_size(), 0);
+
+        assert!(
+            fs.ftruncate_file(1, reader, 0).is_err(),
+            "not open for writing"
+        );
+        assert!(fs.truncate_file(1, "/missing", 0).is_err());
+        fs.set_read_only(true);
+        assert!(fs.truncate_file(1, "/file", 0).is_err());
+        assert!(fs.ftruncate_file(1, writer, 0).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut fs = Filesystem::new();
+        fs.mount("/mnt", Box::new(InMemFilesystem::new())).unwrap();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs
+            .open_or_create_file(1, "/dir/file", flags, 0o644)
+            .unwrap();
+
+        assert_eq!(
+            fs.open_or_create_file(1, "/missing", FsOpenFlags::O_RDONLY, 0),
+            Err(FsError::NotFound)
+        );
+        assert_eq!(fs.symlink(1, "/x", "/dir/file"), Err(FsError::Exists));
+        assert_eq!(fs.read_link(1, "/dir/file"), Err(FsError::InvalidArgument));
+        assert_eq!(fs.unlink_file(1, "/dir"), Err(FsError::IsDir));
+        assert_eq!(fs.truncate_file(1, "/dir", 0), Err(FsError::IsDir));
+        assert_eq!(
+            fs.truncate_file(1, "/dir/file", MAX_FILE_SIZE + 1),
+            Err(FsError::TooLarge)
+        );
+        assert_eq!(
+            fs.rename_file(1, "/dir/file", "/mnt/file"),
+            Err(FsError::CrossMount)
+        );
+
+        fs.symlink(1, "/loop/b", "/loop/a").unwrap();
+        fs.symlink(1, "/loop/a", "/loop/b").unwrap();
+        assert_eq!(fs.stat_path(1, "/loop/a").err(), Some(FsError::Loop));
+
+        let mounted = fs
+            .open_or_create_file(1, "/mnt/file", flags, 0o644)
+            .unwrap();
+        assert_eq!(fs.unmount("/mnt").map(|_| ()), Err(FsError::Busy));
+        fs.close_file(1, mounted).unwrap();
+        assert_eq!(fs.unmount("/other").map(|_| ()), Err(FsError::NotFound));
+
+        fs.close_file(1, fd).unwrap();
+        assert_eq!(fs.read_file(1, fd, 1).map(|_| ()), Err(FsError::BadFd));
+        assert_eq!(fs.close_file(1, fd), Err(FsError::BadFd));
+
+        fs.set_read_only(true);
+        assert_eq!(
+            fs.open_or_create_file(1, "/new", flags, 0o644),
+            Err(FsError::ReadOnly)
+        );
+    }
+
+    #[test]
+    fn test_usage() {
+        // the accounting is global: a PID of its own keeps the other tests out
+        let pid = 2801;
+        let process_bytes = || usage().process_bytes.get(&pid).copied();
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let fd = fs.open_or_create_file(pid, "/file", flags, 0o644).unwrap();
+        assert_eq!(process_bytes(), None);
+        fs.write_file(pid, fd, b"0123456789").unwrap();
+        assert_eq!(process_bytes(), Some(10));
+        fs.truncate_file(pid, "/file", 4).unwrap();
+        assert_eq!(process_bytes(), Some(4));
+
+        fs.symlink(pid, "/file", "/link").unwrap();
+        assert_eq!(process_bytes(), Some(9), "the target is the data");
+        fs.link_file(pid, "/file", "/hard").unwrap();
+        fs.unlink_file(pid, "/file").unwrap();
+        assert_eq!(process_bytes(), Some(9), "the file still has a path");
+        fs.unlink_file(pid, "/hard").unwrap();
+        fs.unlink_file(pid, "/link").unwrap();
+        assert_eq!(process_bytes(), None);
+    }
+
+    #[test]
+    fn test_truncate_frees_memory() {
+        let pid = 28010;
+        let mut fs = Filesystem::new();
+        let flags = FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR;
+        let data = vec![0xaa; 4 * InMemFile::DEFAULT_CAPACITY];
+        let mut capacity = 0;
+        for i in 0..32 {
+            let path = format!("/file{}", i);
+            let fd = fs.open_or_create_file(pid, &path, flags, 0o644).unwrap();
+            fs.write_file(pid, fd, &data).unwrap();
+            let file = fs.in_mem_fs.get_file_by_path(&path).unwrap();
+            assert_eq!(file.inner_vec().capacity(), data.len(), "grows exactly");
+            // a write that ends before the old end shrinks the file, too
+            fs.lseek_file(pid, fd, 1).unwrap();
+            fs.write_file(pid, fd, b"x").unwrap();
+            let file = fs.in_mem_fs.get_file_by_path(&path).unwrap();
+            assert_eq!(file.inner_vec().capacity(), InMemFile::DEFAULT_CAPACITY);
+            fs.ftruncate_file(pid, fd, 0).unwrap();
+            fs.close_file(pid, fd).unwrap();
+            capacity += fs
+                .in_mem_fs
+                .get_file_by_path(&path)
+                .unwrap()
+                .inner_vec()
+                .capacity();
+        }
+        assert_eq!(usage().process_bytes.get(&pid), None);
+        assert_eq!(capacity, 0, "the empty files occupy no heap");
+    }
+
     /// The tests above do basic functionality of read and write. This test checks with random
     /// data if the data written is actually the data read. Furthermore, it splits read and
     /// write operation into multiple chunks.
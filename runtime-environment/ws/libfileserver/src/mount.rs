@@ -0,0 +1,139 @@
+//! Mount table of the [`crate::Filesystem`]. It routes paths by their longest matching
+//! mount point to a [`FsBackend`]. Paths that don't belong to any mount point go to the
+//! in-memory file system, which is always mounted at `/`.
+
+use crate::backend::FsBackend;
+use crate::now_ns;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use libhrstd::rt::services::fs::FsError;
+
+/// Identifies a mounted [`FsBackend`]. Open files remember the mount they belong to.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Hash, Ord, Eq)]
+pub(crate) struct MountId(u64);
+
+impl MountId {
+    /// The in-memory file system at `/`.
+    pub(crate) const ROOT: Self = Self(0);
+
+    pub(crate) const fn val(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+struct Mount {
+    /// Absolute path without a trailing slash.
+    mount_point: String,
+    backend: Box<dyn FsBackend>,
+    /// Time of the mount in nanoseconds since the Unix epoch.
+    mounted_ns: u64,
+}
+
+/// Holds all backends that are mounted in addition to the in-memory file system.
+#[derive(Debug)]
+pub(crate) struct MountTable {
+    mounts: BTreeMap<MountId, Mount>,
+    next_id: u64,
+}
+
+impl MountTable {
+    pub(crate) const fn new() -> Self {
+        Self {
+            mounts: BTreeMap::new(),
+            next_id: MountId::ROOT.0 + 1,
+        }
+    }
+
+    /// Mounts the backend at the given absolute path. Mount points may be nested but
+    /// must be unique. `/` can't be replaced.
+    pub(crate) fn mount(
+        &mut self,
+        mount_point: &str,
+        backend: Box<dyn FsBackend>,
+    ) -> Result<MountId, FsError> {
+        let mount_point = mount_point.trim_end_matches('/');
+        if !mount_point.starts_with('/') {
+            return Err(FsError::InvalidArgument);
+        }
+        if self.lookup(mount_point).is_some() {
+            return Err(FsError::Exists);
+        }
+        let id = MountId(self.next_id);
+        self.next_id += 1;
+        self.mounts.insert(
+            id,
+            Mount {
+                mount_point: String::from(mount_point),
+                backend,
+                mounted_ns: now_ns(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Removes a mount and returns its backend.
+    pub(crate) fn unmount(&mut self, id: MountId) -> Option<Box<dyn FsBackend>> {
+        self.mounts.remove(&id).map(|mount| mount.backend)
+    }
+
+    /// Returns the mount with exactly this mount point.
+    pub(crate) fn lookup(&self, mount_point: &str) -> Option<MountId> {
+        let mount_point = mount_point.trim_end_matches('/');
+        self.mounts
+            .iter()
+            .find(|(_, mount)| mount.mount_point == mount_point)
+            .map(|(id, _)| *id)
+    }
+
+    /// Finds the mount that is responsible for an absolute path. Returns the path
+    /// relative to the mount point, which always starts with a slash.
+    pub(crate) fn resolve<'a>(&self, path: &'a str) -> (MountId, &'a str) {
+        self.mounts
+            .iter()
+            .filter_map(|(id, mount)| {
+                let relative = path.strip_prefix(mount.mount_point.as_str())?;
+                match relative {
+                    "" => Some((*id, "/", mount.mount_point.len())),
+                    _ if relative.starts_with('/') => {
+                        Some((*id, relative, mount.mount_point.len()))
+                    }
+                    _ => None,
+                }
+            })
+            .max_by_key(|(_, _, mount_point_len)| *mount_point_len)
+            .map(|(id, relative, _)| (id, relative))
+            .unwrap_or((MountId::ROOT, path))
+    }
+
+    /// Returns the mount point of a mount. Empty for [`MountId::ROOT`], so that
+    /// concatenating the mount point and a relative path always results in the absolute
+    /// path.
+    pub(crate) fn mount_point(&self, id: MountId) -> Option<&str> {
+        if id == MountId::ROOT {
+            return Some("");
+        }
+        self.mounts.get(&id).map(|mount| mount.mount_point.as_str())
+    }
+
+    /// Returns the time of the mount in nanoseconds since the Unix epoch.
+    pub(crate) fn mounted_ns(&self, id: MountId) -> Option<u64> {
+        self.mounts.get(&id).map(|mount| mount.mounted_ns)
+    }
+
+    pub(crate) fn backend(&self, id: MountId) -> Option<&dyn FsBackend> {
+        self.mounts.get(&id).map(|mount| mount.backend.as_ref())
+    }
+
+    pub(crate) fn backend_mut(&mut self, id: MountId) -> Option<&mut dyn FsBackend> {
+        self.mounts
+            .get_mut(&id)
+            .map(|mount| mount.backend.as_mut() as &mut dyn FsBackend)
+    }
+
+    /// Returns the IDs of all mounts except [`MountId::ROOT`].
+    pub(crate) fn ids(&self) -> impl Iterator<Item = MountId> + '_ {
+        self.mounts.keys().copied()
+    }
+}
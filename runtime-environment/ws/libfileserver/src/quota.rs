@@ -0,0 +1,145 @@
+use alloc::collections::BTreeMap;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsError;
+
+/// Byte quotas of the in-memory file system. `None` means unlimited.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FsQuota {
+    /// Maximum number of bytes of all files together.
+    pub total_bytes: Option<usize>,
+    /// Maximum number of bytes of the files that a single process owns.
+    pub process_bytes: Option<usize>,
+}
+
+impl FsQuota {
+    pub const UNLIMITED: Self = Self {
+        total_bytes: None,
+        process_bytes: None,
+    };
+}
+
+/// Snapshot of the usage of the in-memory file system, see [`crate::usage`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsUsage {
+    pub quota: FsQuota,
+    /// Bytes of all files together.
+    pub total_bytes: usize,
+    /// Bytes of the files of each process that owns at least one byte.
+    pub process_bytes: BTreeMap<ProcessId, usize>,
+}
+
+/// Counts the bytes of the files of the in-memory file system and enforces the
+/// [`FsQuota`]. The owner of a file pays for its data, no matter who writes it, like the
+/// quotas of UNIX. Only the size of the data counts, not the capacity that the file
+/// reserves in advance. Files give memory beyond their default capacity back to the heap
+/// when they shrink, hence the usage also bounds the heap that the files occupy.
+#[derive(Debug)]
+pub(crate) struct Accounting {
+    quota: FsQuota,
+    total_bytes: usize,
+    process_bytes: BTreeMap<ProcessId, usize>,
+}
+
+impl Accounting {
+    pub(crate) const fn new() -> Self {
+        Self {
+            quota: FsQuota::UNLIMITED,
+            total_bytes: 0,
+            process_bytes: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the quota. Usage beyond the new quota stays, but can't grow.
+    pub(crate) fn set_quota(&mut self, quota: FsQuota) {
+        self.quota = quota;
+    }
+
+    /// Charges `owner` for `bytes` more bytes. Fails with [`FsError::NoSpace`] and charges
+    /// nothing if this exceeds a quota.
+    pub(crate) fn charge(&mut self, owner: ProcessId, bytes: usize) -> Result<(), FsError> {
+        if bytes == 0 {
+            return Ok(());
+        }
+        let process_bytes = self.process_bytes.get(&owner).copied().unwrap_or(0) + bytes;
+        let total_bytes = self.total_bytes + bytes;
+        let exceeds = |quota: Option<usize>, bytes| quota.map_or(false, |quota| bytes > quota);
+        if exceeds(self.quota.total_bytes, total_bytes)
+            || exceeds(self.quota.process_bytes, process_bytes)
+        {
+            return Err(FsError::NoSpace);
+        }
+        self.total_bytes = total_bytes;
+        self.process_bytes.insert(owner, process_bytes);
+        Ok(())
+    }
+
+    /// Returns `bytes` to `owner`, e.g. when a file shrinks or is gone.
+    pub(crate) fn release(&mut self, owner: ProcessId, bytes: usize) {
+        if let Some(process_bytes) = self.process_bytes.get_mut(&owner) {
+            let bytes = bytes.min(*process_bytes);
+            *process_bytes -= bytes;
+            self.total_bytes -= bytes;
+            if *process_bytes == 0 {
+                self.process_bytes.remove(&owner);
+            }
+        }
+    }
+
+    /// Charges or releases the difference between the old and the new size of a file.
+    pub(crate) fn resize(
+        &mut self,
+        owner: ProcessId,
+        old_len: usize,
+        new_len: usize,
+    ) -> Result<(), FsError> {
+        if new_len > old_len {
+            self.charge(owner, new_len - old_len)
+        } else {
+            self.release(owner, old_len - new_len);
+            Ok(())
+        }
+    }
+
+    pub(crate) fn usage(&self) -> FsUsage {
+        FsUsage {
+            quota: self.quota,
+            total_bytes: self.total_bytes,
+            process_bytes: self.process_bytes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounting() {
+        let mut accounting = Accounting::new();
+        accounting.set_quota(FsQuota {
+            total_bytes: Some(100),
+            process_bytes: Some(60),
+        });
+        accounting.charge(1, 60).unwrap();
+        assert_eq!(accounting.charge(1, 1), Err(FsError::NoSpace));
+        accounting.charge(2, 40).unwrap();
+        assert_eq!(
+            accounting.charge(3, 1),
+            Err(FsError::NoSpace),
+            "exceeds the total quota"
+        );
+
+        accounting.resize(1, 60, 10).unwrap();
+        accounting.charge(3, 50).unwrap();
+        assert_eq!(accounting.resize(2, 40, 41), Err(FsError::NoSpace));
+        accounting.release(3, 50);
+        let usage = accounting.usage();
+        assert_eq!(usage.total_bytes, 50);
+        assert_eq!(usage.process_bytes.get(&1), Some(&10));
+        assert_eq!(usage.process_bytes.get(&3), None, "owns no byte");
+
+        // releasing more than charged can't underflow
+        accounting.release(1, 1000);
+        assert_eq!(accounting.usage().total_bytes, 40);
+    }
+}
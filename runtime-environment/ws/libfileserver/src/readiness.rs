@@ -0,0 +1,14 @@
+/// Tells which operations on a file descriptor currently don't block, similar to the
+/// events that `poll()` reports on UNIX. See [`crate::Filesystem::readiness`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Readiness {
+    /// A read or `accept()` returns immediately instead of failing with
+    /// [`libhrstd::rt::services::fs::SocketError::WouldBlock`].
+    pub readable: bool,
+    /// A write returns immediately instead of failing with
+    /// [`libhrstd::rt::services::fs::SocketError::WouldBlock`].
+    pub writable: bool,
+    /// The stream socket has no peer, i.e. the peer closed the connection or the socket
+    /// was never connected.
+    pub hang_up: bool,
+}
@@ -0,0 +1,396 @@
+//! Local sockets (`AF_UNIX`) that live entirely inside the file server. Each socket has an
+//! [`INode`] like a file. Therefore, sockets share the [`crate::file_table::OpenFileTable`]
+//! with files and processes address them by ordinary file descriptors.
+//!
+//! The names of bound sockets form their own namespace; they don't show up as files.
+//! The file server can't block a caller. All operations fail with
+//! [`SocketError::WouldBlock`] instead of waiting; callers that implement blocking sockets
+//! retry them later, like the roottask does for `SOCK_NONBLOCK`-less sockets of Linux
+//! programs.
+
+use crate::inode::INode;
+use crate::readiness::Readiness;
+use crate::INODE_ALLOCATOR;
+use alloc::collections::{
+    BTreeMap,
+    VecDeque,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::min;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    SocketError,
+    SocketKind,
+};
+
+/// Maximum number of bytes that may wait in the receive queue of a socket.
+pub(crate) const SOCKET_BUFFER_CAPACITY: usize = 0x10000;
+
+/// Holds all sockets of the system and the names they are bound to.
+#[derive(Debug)]
+pub(crate) struct SocketTable {
+    sockets: BTreeMap<INode, Socket>,
+    names: BTreeMap<String, INode>,
+}
+
+impl SocketTable {
+    pub(crate) const fn new() -> Self {
+        Self {
+            sockets: BTreeMap::new(),
+            names: BTreeMap::new(),
+        }
+    }
+
+    /// Checks if the [`INode`] belongs to a socket rather than to a file.
+    pub(crate) fn is_socket(&self, i_node: INode) -> bool {
+        self.sockets.contains_key(&i_node)
+    }
+
+    /// Returns the kind of the socket.
+    pub(crate) fn kind(&self, i_node: INode) -> Result<SocketKind, SocketError> {
+        self.sockets
+            .get(&i_node)
+            .map(|socket| socket.kind)
+            .ok_or(SocketError::NotASocket)
+    }
+
+    /// Creates a new unnamed and unconnected socket.
+    pub(crate) fn create(&mut self, creator: ProcessId, kind: SocketKind) -> INode {
+        let i_node = INODE_ALLOCATOR.lock().next(creator);
+        self.sockets.insert(i_node, Socket::new(kind));
+        i_node
+    }
+
+    /// Creates two sockets that are connected with each other.
+    pub(crate) fn create_pair(&mut self, creator: ProcessId, kind: SocketKind) -> (INode, INode) {
+        let a = self.create(creator, kind);
+        let b = self.create(creator, kind);
+        self.socket_mut(a).unwrap().state = SocketState::Connected(b);
+        self.socket_mut(b).unwrap().state = SocketState::Connected(a);
+        (a, b)
+    }
+
+    pub(crate) fn bind(&mut self, i_node: INode, name: &str) -> Result<(), SocketError> {
+        if name.is_empty() || self.socket_mut(i_node)?.name.is_some() {
+            return Err(SocketError::InvalidArgument);
+        }
+        if self.names.contains_key(name) {
+            return Err(SocketError::AddressInUse);
+        }
+        self.socket_mut(i_node)?.name.replace(String::from(name));
+        self.names.insert(String::from(name), i_node);
+        Ok(())
+    }
+
+    /// Marks a stream socket as passive. A backlog of zero still allows one connection,
+    /// like on Linux.
+    pub(crate) fn listen(&mut self, i_node: INode, backlog: usize) -> Result<(), SocketError> {
+        let socket = self.socket_mut(i_node)?;
+        if socket.kind != SocketKind::Stream {
+            return Err(SocketError::Unsupported);
+        }
+        let backlog = backlog.max(1);
+        match &mut socket.state {
+            SocketState::Unconnected => {
+                socket.state = SocketState::Listening {
+                    backlog,
+                    pending: VecDeque::new(),
+                };
+                Ok(())
+            }
+            SocketState::Listening {
+                backlog: old_backlog,
+                ..
+            } => {
+                *old_backlog = backlog;
+                Ok(())
+            }
+            SocketState::Connected(_) => Err(SocketError::InvalidArgument),
+        }
+    }
+
+    /// Takes the next pending connection of a listening socket and returns the
+    /// server-side socket of it.
+    pub(crate) fn accept(&mut self, i_node: INode) -> Result<INode, SocketError> {
+        match &mut self.socket_mut(i_node)?.state {
+            SocketState::Listening { pending, .. } => {
+                pending.pop_front().ok_or(SocketError::WouldBlock)
+            }
+            _ => Err(SocketError::InvalidArgument),
+        }
+    }
+
+    /// Connects a socket to the socket with the given name. Stream sockets queue a new
+    /// connection at the listening peer. Datagram sockets only remember the peer as
+    /// default destination.
+    /// Connects the socket to the socket with the given name. `caller` creates the socket
+    /// of the server side of a stream connection.
+    pub(crate) fn connect(
+        &mut self,
+        caller: ProcessId,
+        i_node: INode,
+        name: &str,
+    ) -> Result<(), SocketError> {
+        let kind = self.socket_mut(i_node)?.kind;
+        let peer_i_node = self.lookup_name(name, kind)?;
+
+        if kind == SocketKind::Datagram {
+            self.socket_mut(i_node)?.state = SocketState::Connected(peer_i_node);
+            return Ok(());
+        }
+
+        match self.socket_mut(i_node)?.state {
+            SocketState::Unconnected => {}
+            SocketState::Connected(_) => return Err(SocketError::AlreadyConnected),
+            SocketState::Listening { .. } => return Err(SocketError::InvalidArgument),
+        }
+        match &self.socket_mut(peer_i_node)?.state {
+            SocketState::Listening { backlog, pending } if pending.len() >= *backlog => {
+                return Err(SocketError::WouldBlock)
+            }
+            SocketState::Listening { .. } => {}
+            _ => return Err(SocketError::ConnectionRefused),
+        }
+
+        let server_i_node = self.create(caller, kind);
+        self.socket_mut(server_i_node)?.state = SocketState::Connected(i_node);
+        self.socket_mut(i_node)?.state = SocketState::Connected(server_i_node);
+        if let SocketState::Listening { pending, .. } = &mut self.socket_mut(peer_i_node)?.state {
+            pending.push_back(server_i_node);
+        }
+        Ok(())
+    }
+
+    /// Sends data. Stream sockets send as many bytes as fit into the receive queue of
+    /// the peer. Datagram sockets send either the whole datagram or nothing.
+    pub(crate) fn send(
+        &mut self,
+        i_node: INode,
+        data: &[u8],
+        dest: Option<&str>,
+    ) -> Result<usize, SocketError> {
+        let socket = self.socket_mut(i_node)?;
+        let kind = socket.kind;
+        let from = socket.name.clone();
+        let peer_closed = socket.peer_closed;
+        let connected_peer = match socket.state {
+            SocketState::Connected(peer) => Some(peer),
+            _ => None,
+        };
+
+        match kind {
+            SocketKind::Stream => {
+                let peer_i_node = connected_peer.ok_or(SocketError::NotConnected)?;
+                if peer_closed {
+                    return Err(SocketError::BrokenPipe);
+                }
+                let peer = self
+                    .socket_mut(peer_i_node)
+                    .map_err(|_| SocketError::BrokenPipe)?;
+                let len = min(data.len(), peer.free_capacity());
+                if len == 0 && !data.is_empty() {
+                    return Err(SocketError::WouldBlock);
+                }
+                peer.enqueue(None, &data[..len]);
+                Ok(len)
+            }
+            SocketKind::Datagram => {
+                let peer_i_node = match dest {
+                    Some(dest) => self.lookup_name(dest, kind)?,
+                    None => connected_peer.ok_or(SocketError::DestinationRequired)?,
+                };
+                if data.len() > SOCKET_BUFFER_CAPACITY {
+                    return Err(SocketError::MessageTooLong);
+                }
+                let peer = self
+                    .socket_mut(peer_i_node)
+                    .map_err(|_| SocketError::ConnectionRefused)?;
+                if data.len() > peer.free_capacity() {
+                    return Err(SocketError::WouldBlock);
+                }
+                peer.enqueue(from, data);
+                Ok(data.len())
+            }
+        }
+    }
+
+    /// Receives at most `max_len` bytes. Stream sockets return empty data if the peer
+    /// closed the connection. Datagram sockets return one datagram and the name of its
+    /// sender; the part of the datagram that exceeds `max_len` gets discarded.
+    pub(crate) fn recv(
+        &mut self,
+        i_node: INode,
+        max_len: usize,
+    ) -> Result<(Vec<u8>, Option<String>), SocketError> {
+        let socket = self.socket_mut(i_node)?;
+        match socket.kind {
+            SocketKind::Stream => {
+                if socket.rx_queue.is_empty() {
+                    return match socket.state {
+                        SocketState::Connected(_) if socket.peer_closed => Ok((Vec::new(), None)),
+                        SocketState::Connected(_) => Err(SocketError::WouldBlock),
+                        _ => Err(SocketError::NotConnected),
+                    };
+                }
+                let mut data = Vec::new();
+                while data.len() < max_len {
+                    let message = match socket.rx_queue.front_mut() {
+                        Some(message) => message,
+                        None => break,
+                    };
+                    let len = min(max_len - data.len(), message.data.len());
+                    data.extend(message.data.drain(..len));
+                    if message.data.is_empty() {
+                        socket.rx_queue.pop_front();
+                    }
+                }
+                socket.rx_bytes -= data.len();
+                Ok((data, None))
+            }
+            SocketKind::Datagram => {
+                let mut message = socket.rx_queue.pop_front().ok_or(SocketError::WouldBlock)?;
+                socket.rx_bytes -= message.data.len();
+                message.data.truncate(max_len);
+                Ok((message.data, message.from))
+            }
+        }
+    }
+
+    /// Returns which operations on the socket currently don't block. Like on Linux, a
+    /// stream socket without a peer reports [`Readiness::hang_up`], and operations that
+    /// fail immediately, e.g. with [`SocketError::BrokenPipe`], count as not blocking.
+    pub(crate) fn readiness(&self, i_node: INode) -> Result<Readiness, SocketError> {
+        let socket = self.sockets.get(&i_node).ok_or(SocketError::NotASocket)?;
+        let has_data = !socket.rx_queue.is_empty();
+        let peer_has_capacity = |peer: &INode| {
+            self.sockets
+                .get(peer)
+                .map_or(true, |peer| peer.free_capacity() > 0)
+        };
+        let readiness = match (&socket.state, socket.kind) {
+            (SocketState::Listening { pending, .. }, _) => Readiness {
+                readable: !pending.is_empty(),
+                writable: false,
+                hang_up: false,
+            },
+            (SocketState::Connected(peer), SocketKind::Stream) => Readiness {
+                readable: has_data || socket.peer_closed,
+                writable: socket.peer_closed || peer_has_capacity(peer),
+                hang_up: socket.peer_closed,
+            },
+            (SocketState::Unconnected, SocketKind::Stream) => Readiness {
+                readable: false,
+                writable: true,
+                hang_up: true,
+            },
+            (SocketState::Connected(peer), SocketKind::Datagram) => Readiness {
+                readable: has_data,
+                writable: peer_has_capacity(peer),
+                hang_up: false,
+            },
+            (SocketState::Unconnected, SocketKind::Datagram) => Readiness {
+                readable: has_data,
+                writable: true,
+                hang_up: false,
+            },
+        };
+        Ok(readiness)
+    }
+
+    /// Destroys a socket. The peer of a stream connection sees the end of the stream.
+    /// Connections that wait to be accepted by a listening socket are closed as well.
+    pub(crate) fn close(&mut self, i_node: INode) {
+        let socket = match self.sockets.remove(&i_node) {
+            Some(socket) => socket,
+            None => return,
+        };
+        if let Some(name) = socket.name {
+            self.names.remove(&name);
+        }
+        match socket.state {
+            SocketState::Connected(peer) if socket.kind == SocketKind::Stream => {
+                if let Ok(peer) = self.socket_mut(peer) {
+                    peer.peer_closed = true;
+                }
+            }
+            SocketState::Listening { pending, .. } => {
+                pending.into_iter().for_each(|i_node| self.close(i_node));
+            }
+            _ => {}
+        }
+    }
+
+    fn socket_mut(&mut self, i_node: INode) -> Result<&mut Socket, SocketError> {
+        self.sockets.get_mut(&i_node).ok_or(SocketError::NotASocket)
+    }
+
+    /// Returns the socket that is bound to the name, if it has the expected kind.
+    fn lookup_name(&self, name: &str, kind: SocketKind) -> Result<INode, SocketError> {
+        let i_node = *self.names.get(name).ok_or(SocketError::NotFound)?;
+        if self.sockets[&i_node].kind == kind {
+            Ok(i_node)
+        } else {
+            Err(SocketError::WrongKind)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Socket {
+    kind: SocketKind,
+    name: Option<String>,
+    state: SocketState,
+    rx_queue: VecDeque<Message>,
+    /// Sum of the bytes of all messages in the receive queue.
+    rx_bytes: usize,
+    /// The peer of the stream connection was closed.
+    peer_closed: bool,
+}
+
+impl Socket {
+    const fn new(kind: SocketKind) -> Self {
+        Self {
+            kind,
+            name: None,
+            state: SocketState::Unconnected,
+            rx_queue: VecDeque::new(),
+            rx_bytes: 0,
+            peer_closed: false,
+        }
+    }
+
+    fn free_capacity(&self) -> usize {
+        SOCKET_BUFFER_CAPACITY - self.rx_bytes
+    }
+
+    fn enqueue(&mut self, from: Option<String>, data: &[u8]) {
+        self.rx_bytes += data.len();
+        self.rx_queue.push_back(Message {
+            from,
+            data: Vec::from(data),
+        });
+    }
+}
+
+#[derive(Debug)]
+enum SocketState {
+    Unconnected,
+    /// Stream socket that accepts connections. `pending` holds the server-side sockets
+    /// of connections that are not accepted yet.
+    Listening {
+        backlog: usize,
+        pending: VecDeque<INode>,
+    },
+    /// Stream socket with an established connection or datagram socket with a default
+    /// destination.
+    Connected(INode),
+}
+
+/// Data of a single send operation.
+#[derive(Debug)]
+struct Message {
+    /// Name of the sending datagram socket.
+    from: Option<String>,
+    data: Vec<u8>,
+}
@@ -1,4 +1,24 @@
-use crate::in_mem_fs::InMemFile;
+use crate::in_mem_fs::{
+    FileKind,
+    InMemFile,
+};
+
+/// Mask of the file type bits of `st_mode`.
+pub const S_IFMT: u32 = 0o170000;
+/// File type bits of a directory.
+pub const S_IFDIR: u32 = 0o040000;
+/// File type bits of a regular file.
+pub const S_IFREG: u32 = 0o100000;
+/// File type bits of a symbolic link.
+pub const S_IFLNK: u32 = 0o120000;
+
+/// Preferred size of reads and writes (`st_blksize`), i.e. the page size.
+const BLOCK_SIZE: i64 = 4096;
+
+/// Unit of `st_blocks`. It is 512 bytes, independent of [`BLOCK_SIZE`].
+const BLOCKS_UNIT: i64 = 512;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
 
 /// This is identical to the UNIX/libc stat type.
 #[repr(C)]
@@ -25,6 +45,82 @@ pub struct FileStat {
 }
 
 impl FileStat {
+    /// Creates the metadata of a file. A `st_mode` without file type bits describes a
+    /// regular file. Each file has a single link and occupies as many blocks as its size
+    /// requires. The timestamps are zero, see [`Self::with_timestamps`]. Used by
+    /// [`crate::FsBackend`] implementations.
+    pub const fn new(st_ino: u64, st_mode: u32, st_size: i64) -> Self {
+        let st_mode = if st_mode & S_IFMT == 0 {
+            st_mode | S_IFREG
+        } else {
+            st_mode
+        };
+        Self {
+            st_dev: 0,
+            st_ino,
+            st_nlink: 1,
+            st_mode,
+            st_uid: 0,
+            st_gid: 0,
+            __pad0: 0,
+            st_rdev: 0,
+            st_size,
+            st_blksize: BLOCK_SIZE,
+            st_blocks: (st_size + BLOCKS_UNIT - 1) / BLOCKS_UNIT,
+            st_atime: 0,
+            st_atime_nsec: 0,
+            st_mtime: 0,
+            st_mtime_nsec: 0,
+            st_ctime: 0,
+            st_ctime_nsec: 0,
+            __unused: [0; 3],
+        }
+    }
+
+    /// Sets the time of the last access, modification, and change of the file, each in
+    /// nanoseconds since the Unix epoch.
+    pub const fn with_timestamps(mut self, atime_ns: u64, mtime_ns: u64, ctime_ns: u64) -> Self {
+        self.st_atime = (atime_ns / NS_PER_SEC) as i64;
+        self.st_atime_nsec = (atime_ns % NS_PER_SEC) as i64;
+        self.st_mtime = (mtime_ns / NS_PER_SEC) as i64;
+        self.st_mtime_nsec = (mtime_ns % NS_PER_SEC) as i64;
+        self.st_ctime = (ctime_ns / NS_PER_SEC) as i64;
+        self.st_ctime_nsec = (ctime_ns % NS_PER_SEC) as i64;
+        self
+    }
+
+    /// Sets the number of paths that refer to the file, i.e. its hard links.
+    pub const fn with_nlink(mut self, st_nlink: u64) -> Self {
+        self.st_nlink = st_nlink;
+        self
+    }
+
+    /// Returns true if the backend didn't provide any timestamp.
+    pub(crate) const fn has_timestamps(&self) -> bool {
+        self.st_atime != 0 || self.st_mtime != 0 || self.st_ctime != 0
+    }
+
+    /// Sets the device, i.e. the mount, that the file belongs to. Inodes are only unique
+    /// within a device.
+    pub(crate) fn set_dev(&mut self, st_dev: u64) {
+        self.st_dev = st_dev;
+    }
+
+    /// Returns true if the file is a directory.
+    pub const fn is_dir(&self) -> bool {
+        self.st_mode & S_IFMT == S_IFDIR
+    }
+
+    /// Returns true if the file is a regular file.
+    pub const fn is_file(&self) -> bool {
+        self.st_mode & S_IFMT == S_IFREG
+    }
+
+    /// Returns true if the file is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.st_mode & S_IFMT == S_IFLNK
+    }
+
     pub fn st_dev(&self) -> u64 {
         self.st_dev
     }
@@ -77,25 +173,18 @@ impl FileStat {
 
 impl From<&InMemFile> for FileStat {
     fn from(file: &InMemFile) -> Self {
-        Self {
-            st_dev: 0,
-            st_ino: file.i_node().val(),
-            st_nlink: 0,
-            st_mode: file.meta().umode() as u32,
-            st_uid: 0,
-            st_gid: 0,
-            __pad0: 0,
-            st_rdev: 0,
-            st_size: file.data().len() as i64,
-            st_blksize: 0,
-            st_blocks: 0,
-            st_atime: 0,
-            st_atime_nsec: 0,
-            st_mtime: 0,
-            st_mtime_nsec: 0,
-            st_ctime: 0,
-            st_ctime_nsec: 0,
-            __unused: [0; 3],
-        }
+        let meta = file.meta();
+        let file_type = match file.kind() {
+            FileKind::Regular => S_IFREG,
+            FileKind::Symlink => S_IFLNK,
+            FileKind::Dir => S_IFDIR,
+        };
+        Self::new(
+            file.i_node().val(),
+            file_type | meta.umode() as u32,
+            file.data().len() as i64,
+        )
+        .with_nlink(file.links())
+        .with_timestamps(meta.atime_ns(), meta.mtime_ns(), meta.ctime_ns())
     }
 }
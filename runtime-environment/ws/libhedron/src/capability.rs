@@ -44,6 +44,10 @@ pub type CrdObjSM = Crd<SMCapPermissions, CrdTypeObject, CrdTypeObjectSM>;
 pub type CrdObjPD = Crd<PDCapPermissions, CrdTypeObject, CrdTypeObjectPD>;
 /// CRD used to refer to capabilities for PT objects. See [`Crd`] for generic details.
 pub type CrdObjPT = Crd<PTCapPermissions, CrdTypeObject, CrdTypeObjectPT>;
+/// CRD used to refer to capabilities for kernel objects of any kind, for example the one
+/// of a [`crate::TypedItem`]. The permission bits keep their meaning of the actual kind.
+/// See [`Crd`] for generic details.
+pub type CrdObj = Crd<NullCapPermissions, CrdTypeObject, ()>;
 
 /// Highest possible order for a [`Crd`]. A order has exactly 5 bits.
 pub const MAX_CRD_ORDER: u8 = 0x1f;
@@ -232,7 +236,7 @@ impl<Permissions, Specialization, ObjectSpecialization>
     const BASE_LEFT_SHIFT: u64 = 12;
 
     /// Constructs a new, unvalidated Crd from a u64 value.
-    fn new_from_val(val: u64) -> Self {
+    pub(crate) fn new_from_val(val: u64) -> Self {
         Self {
             val,
             _zst1: PhantomData::default(),
@@ -281,6 +285,16 @@ impl<Permissions, Specialization, ObjectSpecialization>
         (self.val & Self::BASE_BITMASK) >> Self::BASE_LEFT_SHIFT
     }
 
+    /// Returns a copy of this [`Crd`] with another base but the same kind, order, and
+    /// permissions.
+    pub fn with_base(self, base: u64) -> Self {
+        let base = UI52Bit::from(base);
+        Self::new_from_val(
+            (self.val & !Self::BASE_BITMASK)
+                | ((base.val() << Self::BASE_LEFT_SHIFT) & Self::BASE_BITMASK),
+        )
+    }
+
     /// Returns the generic permissions, i.e. untyped.
     /// Internal API.
     const fn gen_permissions(self) -> u8 {
@@ -549,6 +563,24 @@ impl Debug for CrdObjPT {
     }
 }
 
+impl CrdObj {
+    /// Creates a new CRD for object capabilities of any kind without permissions. This
+    /// CRD is of kind [`CrdKind::CrdKindObject`]. It is useful for operations that ignore
+    /// the permissions, such as revoking a range of selectors with mixed kinds of objects.
+    ///
+    /// # Parameters
+    /// - `base` - First capability selector of the range. Must be order-aligned!
+    /// - `order` - The range covers 2^order capability selectors.
+    pub fn new(base: CapSel, order: u8) -> Self {
+        Self::new_generic(
+            CrdKind::CrdKindObject,
+            base.into(),
+            order.into(),
+            0_u8.into(),
+        )
+    }
+}
+
 /// Shared trait for all permission implementations.
 pub trait CrdPermissions: From<u8> + Into<u8> + Default {
     /// Returns a raw unsigned integer with the permission bits.
@@ -760,6 +792,16 @@ mod tests {
         println!("{:?}", CrdObjPD::new(5, 0, PDCapPermissions::all()));
     }
 
+    #[test]
+    fn test_with_base() {
+        let crd = CrdObjSM::new(7, 2, SMCapPermissions::UP);
+        let moved = crd.with_base(MAX_CRD_BASE);
+        assert_eq!(moved.base(), MAX_CRD_BASE);
+        assert_eq!(moved.order(), 2);
+        assert_eq!(moved.kind(), CrdKind::CrdKindObject);
+        assert_eq!(moved.permissions(), SMCapPermissions::UP);
+    }
+
     #[test]
     fn test_bits() {
         let base = MAX_CRD_BASE;
@@ -20,5 +20,13 @@ pub const NUM_VM_EXC: usize = 256;
 /// Note that this number can be higher for memory capabilities!
 pub const NUM_CAP_SEL: CapSel = 67108864;
 
+/// API version that the forked Hedron of this project reports in the HIP. Hedron encodes
+/// the version as `major * 1000 + minor`. The fork adds foreign system calls on top of
+/// this version of upstream Hedron.
+pub const HEDRON_FORK_API_VERSION: u32 = 3000;
+
+/// Minimum API version that provides the `pd_ctrl_delegate` sub-operation.
+pub const HEDRON_PD_CTRL_DELEGATE_API_VERSION: u32 = 3000;
+
 #[cfg(test)]
 mod tests {}
@@ -10,6 +10,7 @@ use core::fmt::{
     Formatter,
 };
 use core::mem::size_of;
+use core::ops::Range;
 
 /// Hypervisor Information Page.
 #[repr(C)]
@@ -206,6 +207,76 @@ impl HIP {
         HipMemDescIterator::new(self)
     }
 
+    /// Returns an iterator of type [`HipCpuDescIterator`] over all CPU descriptors,
+    /// including the ones of CPUs that are not present.
+    pub fn cpu_desc_iterator(&self) -> HipCpuDescIterator {
+        assert_eq!(
+            size_of::<HipCpu>(),
+            self.cpu_size as usize,
+            "the struct must have an equal size to the struct in Hedron"
+        );
+        HipCpuDescIterator::new(self)
+    }
+
+    /// Returns an iterator over the CPU number and the descriptor of each online CPU.
+    pub fn online_cpu_iterator(&self) -> impl Iterator<Item = (u64, &HipCpu)> {
+        self.cpu_desc_iterator().filter(|(_, cpu)| cpu.is_online())
+    }
+
+    /// Returns the number of online CPUs.
+    pub fn online_cpu_count(&self) -> usize {
+        self.online_cpu_iterator().count()
+    }
+
+    /// Returns an iterator over all memory descriptors of type
+    /// [`HipMemType::AvailableMemory`]. Other descriptors, such as the ones of the
+    /// hypervisor and of the boot modules, may overlap with them.
+    pub fn usable_mem_iterator(&self) -> impl Iterator<Item = &HipMem> {
+        self.mem_desc_iterator().filter(|mem| mem.is_usable())
+    }
+
+    /// Returns an iterator over the memory descriptors of all Multiboot boot modules, in
+    /// the order of the boot loader configuration.
+    pub fn mb_module_iterator(&self) -> impl Iterator<Item = &HipMem> {
+        self.mem_desc_iterator()
+            .filter(|mem| mem.typ() == HipMemType::MbModule)
+    }
+
+    /// Returns an iterator over all physical memory ranges that are usable and that no
+    /// other memory descriptor (e.g. hypervisor, boot modules, reserved memory) claims.
+    /// This is the memory that is free during the handoff to the roottask. A range may
+    /// occur multiple times if multiple claimed ranges end at the same address.
+    pub fn free_mem_iterator(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        let claimed = move || self.mem_desc_iterator().filter(|mem| !mem.is_usable());
+        self.usable_mem_iterator().flat_map(move |usable| {
+            // a free range starts at the begin of the usable memory or right after a
+            // claimed range and ends at the next claimed range
+            core::iter::once(usable.addr())
+                .chain(claimed().map(HipMem::end))
+                .filter(move |start| usable.range().contains(start))
+                .filter(move |start| !claimed().any(|mem| mem.range().contains(start)))
+                .map(move |start| {
+                    let end = claimed()
+                        .map(HipMem::addr)
+                        .filter(|addr| *addr > start)
+                        .fold(usable.end(), u64::min);
+                    start..end
+                })
+        })
+    }
+
+    /// Returns the largest range of [`Self::free_mem_iterator`]. If multiple ranges have
+    /// the same size, the one with the lowest address wins.
+    pub fn largest_usable_region(&self) -> Option<Range<u64>> {
+        self.free_mem_iterator()
+            .fold(None, |largest: Option<Range<u64>>, range| match largest {
+                Some(largest) if largest.end - largest.start >= range.end - range.start => {
+                    Some(largest)
+                }
+                _ => Some(range),
+            })
+    }
+
     // The base port of the serial device.
     // If this is 0 the system may fall back to
     // the default port 0x3f8.
@@ -276,6 +347,9 @@ impl Debug for HIP {
     }
 }
 
+/// Bit in [`HipCpu::flags`] that is set for online CPUs.
+const HIP_CPU_FLAG_ONLINE: u8 = 1;
+
 #[derive(Debug, Default)]
 #[repr(C)]
 pub struct HipCpu {
@@ -310,6 +384,11 @@ impl HipCpu {
     pub const fn lapic_info(&self) -> &LapicInfo {
         &self.lapic_info
     }
+    /// Returns true if Hedron brought up the CPU. Syscalls such as `create_ec` only
+    /// accept online CPUs.
+    pub const fn is_online(&self) -> bool {
+        self.flags & HIP_CPU_FLAG_ONLINE != 0
+    }
 }
 
 /// Identifies all memory that is initially in use. From this, it can be derived
@@ -336,6 +415,18 @@ impl HipMem {
     pub const fn typ(&self) -> HipMemType {
         self.typ
     }
+    /// Returns the exclusive end address.
+    pub const fn end(&self) -> u64 {
+        self.addr + self.size
+    }
+    /// Returns the physical address range of the memory.
+    pub const fn range(&self) -> Range<u64> {
+        self.addr..self.end()
+    }
+    /// Returns true if the memory is of type [`HipMemType::AvailableMemory`].
+    pub fn is_usable(&self) -> bool {
+        self.typ == HipMemType::AvailableMemory
+    }
     /// Returns the pointer to the command line, it the memory type is `HipMemType::MbModule`
     /// and the `cmdline` pointer != 0.
     pub fn cmdline(&self) -> Option<*const u8> {
@@ -456,6 +547,29 @@ impl<'a> Iterator for HipMemDescIterator<'a> {
     }
 }
 
+/// Iterator over all [`HipCpu`]-descriptors of the [`HIP`]. Yields the number of each
+/// CPU, i.e. the value that syscalls such as `create_ec` expect, and its descriptor.
+#[derive(Debug)]
+pub struct HipCpuDescIterator<'a> {
+    iter: core::iter::Enumerate<core::slice::Iter<'a, HipCpu>>,
+}
+
+impl<'a> HipCpuDescIterator<'a> {
+    fn new(hip: &'a HIP) -> Self {
+        Self {
+            iter: hip.cpu_desc.iter().enumerate(),
+        }
+    }
+}
+
+impl<'a> Iterator for HipCpuDescIterator<'a> {
+    type Item = (u64, &'a HipCpu);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(cpu, desc)| (cpu as u64, desc))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::hip::{
@@ -551,5 +665,66 @@ mod tests {
         assert_eq!(mem_descs[3].typ, HipMemType::MbModule);
         assert_eq!(mem_descs[3].addr, 0xbadb001);
         assert_eq!(mem_descs[3].size, 0);
+
+        assert_eq!(hip.mb_module_iterator().count(), 2);
+        assert_eq!(hip.usable_mem_iterator().count(), 0);
+        assert_eq!(hip.largest_usable_region(), None);
+        assert_eq!(hip.free_mem_iterator().count(), 0);
+    }
+
+    #[test]
+    fn test_hip_cpu_desc_iter() {
+        let mut hip = unsafe { alloc::boxed::Box::<HIP>::new_zeroed().assume_init() };
+        hip.cpu_size = size_of::<HipCpu>() as u16;
+        hip.cpu_desc[0].flags = 1;
+        hip.cpu_desc[1].flags = 1;
+        hip.cpu_desc[3].flags = 1;
+
+        assert_eq!(hip.cpu_desc_iterator().count(), 64);
+        assert_eq!(hip.online_cpu_count(), 3);
+        let online = hip
+            .online_cpu_iterator()
+            .map(|(cpu, _)| cpu)
+            .collect::<Vec<_>>();
+        assert_eq!(online, [0, 1, 3]);
+    }
+
+    #[test]
+    fn test_hip_largest_usable_region() {
+        let mut bytes = [0_u8; size_of::<HIP>() + 5 * size_of::<HipMem>()];
+        let hip = unsafe { &mut *(bytes.as_mut_ptr() as *mut HIP) };
+        hip.length = bytes.len() as u16;
+        hip.mem_size = size_of::<HipMem>() as u16;
+
+        unsafe {
+            let arr = bytes.as_ptr().add(size_of::<HIP>()) as *mut HipMem;
+            let arr = core::slice::from_raw_parts_mut(arr, 5);
+            // low memory
+            arr[0].typ = HipMemType::AvailableMemory;
+            arr[0].addr = 0;
+            arr[0].size = 0x9f000;
+            // high memory with the hypervisor and two boot modules in it
+            arr[1].typ = HipMemType::AvailableMemory;
+            arr[1].addr = 0x100000;
+            arr[1].size = 0x7ff00000;
+            arr[2].typ = HipMemType::Hypervisor;
+            arr[2].addr = 0x100000;
+            arr[2].size = 0x1000000;
+            arr[3].typ = HipMemType::MbModule;
+            arr[3].addr = 0x2000000;
+            arr[3].size = 0x100000;
+            arr[4].typ = HipMemType::MbModule;
+            arr[4].addr = 0x2100000;
+            arr[4].size = 0x100000;
+        }
+
+        assert_eq!(hip.usable_mem_iterator().count(), 2);
+        assert_eq!(hip.mb_module_iterator().count(), 2);
+        // right after the last boot module up to the end of the usable memory
+        assert_eq!(hip.largest_usable_region(), Some(0x2200000..0x80000000));
+        assert_eq!(
+            hip.free_mem_iterator().collect::<Vec<_>>(),
+            [0..0x9f000, 0x1100000..0x2000000, 0x2200000..0x80000000]
+        );
     }
 }
@@ -0,0 +1,111 @@
+//! assign_gsi syscall
+
+use crate::capability::CapSel;
+use crate::consts::{
+    NUM_CAP_SEL,
+    NUM_CPUS,
+};
+use crate::syscall::{
+    hedron_syscall_4_out3,
+    SyscallError,
+    SyscallNum,
+};
+use alloc::string::ToString;
+
+/// Flag in ARG1: Hedron takes the trigger mode and the polarity from ARG4 instead of
+/// the defaults of the interrupt controller.
+const ASSIGN_GSI_OVERRIDE_CFG: u64 = 1 << 8;
+
+/// Trigger mode and polarity of an IOAPIC pin. Without it, Hedron uses the configuration
+/// from the ACPI tables or edge-triggered and active-high for ISA interrupts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GsiConfig {
+    pub level_triggered: bool,
+    pub active_low: bool,
+}
+
+impl GsiConfig {
+    const fn val(self) -> u64 {
+        self.level_triggered as u64 | (self.active_low as u64) << 1
+    }
+}
+
+/// The message that a device must send to raise a message signaled interrupt (MSI), i.e.
+/// the values for the MSI capability or the MSI-X table of the device.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MsiInfo {
+    pub address: u64,
+    pub data: u64,
+}
+
+/// Routes a global system interrupt (GSI) to a CPU. Afterwards, each interrupt performs
+/// an "up" operation on the interrupt semaphore of the GSI, on which ECs can wait. Hedron
+/// creates the interrupt semaphores and gives them to the roottask.
+///
+/// # Parameters
+/// - `sm_sel` Cap Sel of the interrupt semaphore of the GSI
+/// - `dev_cfg_addr` Address of the mapped PCI configuration space of the device for an MSI
+///   or `0` for a pin of an IOAPIC.
+/// - `cpu` CPU that receives the interrupt
+/// - `config` optionally overrides the trigger mode and polarity of the pin
+///
+/// Returns the MSI message. It is meaningless for pins of an IOAPIC.
+///
+/// # Safety
+/// * This function may change the systems functionality in an unintended way,
+///   if the arguments are illegal or wrong.
+/// * This function is not allowed to panic.
+/// * This function is strictly required to never produce any side effect system calls! Therefore,
+///   also no log::trace()-stuff or similar. Otherwise, the current implementation of hybrid
+///   foreign system calls will fail.
+#[inline]
+pub fn sys_assign_gsi(
+    sm_sel: CapSel,
+    dev_cfg_addr: u64,
+    cpu: u64,
+    config: Option<GsiConfig>,
+) -> Result<MsiInfo, SyscallError> {
+    if sm_sel >= NUM_CAP_SEL {
+        return Err(SyscallError::ClientArgumentError(
+            "Argument `sm_sel` is too big".to_string(),
+        ));
+    }
+    if cpu >= NUM_CPUS as u64 {
+        return Err(SyscallError::ClientArgumentError(
+            "Argument `cpu` is too big".to_string(),
+        ));
+    }
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::AssignGsi.val();
+    if config.is_some() {
+        arg1 |= ASSIGN_GSI_OVERRIDE_CFG;
+    }
+    arg1 |= sm_sel << 12;
+    let arg4 = config.map_or(0, GsiConfig::val);
+
+    unsafe {
+        hedron_syscall_4_out3(arg1, dev_cfg_addr, cpu, arg4)
+            .map(|(address, data)| MsiInfo { address, data })
+            .map_err(|e| SyscallError::HedronStatusError(e.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gsi_config() {
+        let config = GsiConfig {
+            level_triggered: true,
+            active_low: true,
+        };
+        assert_eq!(config.val(), 0b11);
+        let config = GsiConfig {
+            level_triggered: false,
+            active_low: true,
+        };
+        assert_eq!(config.val(), 0b10);
+    }
+}
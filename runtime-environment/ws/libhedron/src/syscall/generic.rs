@@ -97,6 +97,53 @@ pub(super) unsafe fn hedron_syscall_4(
     }
 }
 
+/// Like [`hedron_syscall_4`] but returns the "out2"- and the "out3"-value on success. Only
+/// a few syscalls, such as `assign_gsi`, report a second value.
+///
+/// This function never panics.
+///
+/// # Safety
+/// * This function may change the systems functionality in an unintended way,
+///   if the arguments are illegal or wrong.
+/// * This function is not allowed to panic.
+/// * This function is strictly required to never produce any side effect system calls! Therefore,
+///   also no log::trace()-stuff or similar. Otherwise, the current implementation of hybrid
+///   foreign system calls will fail.
+#[inline]
+pub(super) unsafe fn hedron_syscall_4_out3(
+    arg1: u64,
+    arg2: u64,
+    arg3: u64,
+    arg4: u64,
+) -> Result<(u64, u64), (SyscallStatus, u64)> {
+    let out1: u64;
+    let out2;
+    let out3;
+    asm!(
+        "syscall",
+        in("rdi") arg1,
+        in("rsi") arg2,
+        in("rdx") arg3,
+        in("rax") arg4,
+        lateout("rdi") out1,
+        lateout("rsi") out2,
+        lateout("rdx") out3,
+        // mark as clobbered
+        // https://doc.rust-lang.org/beta/unstable-book/library-features/asm.html
+        // NOVA/Hedron spec lists all registers that may be altered
+        lateout("r11") _,
+        lateout("rcx") _,
+        // Memory Clobber not necessary, because this is the default in Rust
+        options(nostack) // probably no effect, but strictly speaking correct
+    );
+    let (out1, out2) = (SyscallStatus::from(out1), out2);
+    if out1 == SyscallStatus::Success {
+        Ok((out2, out3))
+    } else {
+        Err((out1, out2))
+    }
+}
+
 /// Does a Hedron syscall with 3 arguments. On success, the "out2"-value is returned.
 /// On failure, the error code ("out1") is returned together with "out2".
 ///
@@ -247,7 +294,7 @@ impl SyscallNum {
 #[repr(u64)]
 pub enum PdCtrlSubSyscall {
     PdCtrlDelegate = 2,
-    PdCtrlMsgAccess = 3,
+    PdCtrlMsrAccess = 3,
 }
 
 impl PdCtrlSubSyscall {
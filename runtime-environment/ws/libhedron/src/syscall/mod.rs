@@ -10,6 +10,8 @@
 
 use alloc::string::String;
 
+mod assign_gsi;
+pub use assign_gsi::*;
 mod create_ec;
 pub use create_ec::*;
 mod create_pd;
@@ -25,7 +27,11 @@ pub use ipc::*;
 mod pd_ctrl;
 pub use pd_ctrl::*;
 mod pt_ctrl;
+mod revoke;
+mod sc_ctrl;
 pub use create_sm::*;
+pub use revoke::*;
+pub use sc_ctrl::*;
 mod create_sm;
 pub use sm_ctrl::*;
 mod sm_ctrl;
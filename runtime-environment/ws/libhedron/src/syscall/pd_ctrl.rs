@@ -6,6 +6,7 @@ use crate::capability::{
 };
 use crate::consts::NUM_CAP_SEL;
 use crate::syscall::{
+    hedron_syscall_2,
     hedron_syscall_5,
     PdCtrlSubSyscall,
     SyscallNum,
@@ -15,11 +16,15 @@ use crate::syscall::{
     SyscallResult,
 };
 use alloc::string::ToString;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
 /// Carries additional infos for a transfer or delegation call including some flags.
 /// Can also be understood typed item
 /// (Partly described by 4.6.2.2 Typed Items of original NOVA spec.)
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DelegateFlags(u64);
 
 impl DelegateFlags {
@@ -167,3 +172,57 @@ pub fn sys_pd_ctrl_delegate<Perm, Spec, ObjSpec>(
         }
     }
 }
+
+/// System call `pd_ctrl_msr_access` reads a model-specific register (MSR) of the CPU that
+/// executes the caller. Only passthrough PDs, such as the roottask, may access MSRs.
+///
+/// This function never panics.
+///
+/// # Safety
+/// * This function is not allowed to panic.
+/// * This function is strictly required to never produce any side effect system calls! Therefore,
+///   also no log::trace()-stuff or similar. Otherwise, the current implementation of hybrid
+///   foreign system calls will fail.
+#[inline]
+pub fn sys_pd_ctrl_msr_read(msr: u32) -> Result<u64, SyscallError> {
+    pd_ctrl_msr_access(msr, false, 0)
+}
+
+/// System call `pd_ctrl_msr_access` writes a model-specific register (MSR) of the CPU
+/// that executes the caller. Only passthrough PDs, such as the roottask, may access MSRs.
+/// Hedron only permits MSRs that don't compromise the kernel, e.g. the ones of the
+/// performance counters.
+///
+/// This function never panics.
+///
+/// # Safety
+/// * This function may change the systems functionality in an unintended way,
+///   if the arguments are illegal or wrong.
+/// * This function is not allowed to panic.
+/// * This function is strictly required to never produce any side effect system calls! Therefore,
+///   also no log::trace()-stuff or similar. Otherwise, the current implementation of hybrid
+///   foreign system calls will fail.
+#[inline]
+pub fn sys_pd_ctrl_msr_write(msr: u32, value: u64) -> SyscallResult {
+    pd_ctrl_msr_access(msr, true, value).map(|_x| ())
+}
+
+/// Reads or writes an MSR. Returns the value of the MSR after a read.
+#[inline]
+fn pd_ctrl_msr_access(msr: u32, write: bool, value: u64) -> Result<u64, SyscallError> {
+    const SYSCALL_BITMASK: u64 = 0xff;
+    const SUB_SYSCALL_BITMASK: u64 = 0x300;
+    const SUB_SYSCALL_BITSHIFT: u64 = 8;
+    const WRITE_BITSHIFT: u64 = 10;
+    const MSR_BITSHIFT: u64 = 12;
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::PdCtrl.val() & SYSCALL_BITMASK;
+    arg1 |= (PdCtrlSubSyscall::PdCtrlMsrAccess.val() << SUB_SYSCALL_BITSHIFT) & SUB_SYSCALL_BITMASK;
+    if write {
+        arg1 |= 1 << WRITE_BITSHIFT;
+    }
+    arg1 |= (msr as u64) << MSR_BITSHIFT;
+
+    unsafe { hedron_syscall_2(arg1, value).map_err(|e| SyscallError::HedronStatusError(e.0)) }
+}
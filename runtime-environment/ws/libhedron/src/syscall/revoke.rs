@@ -0,0 +1,48 @@
+//! revoke syscall
+
+use crate::capability::Crd;
+use crate::syscall::hedron_syscall_2;
+use crate::syscall::SyscallError;
+use crate::syscall::SyscallNum;
+use crate::syscall::SyscallResult;
+
+/// Revokes the capabilities that the [`Crd`] describes from all protection domains that
+/// received them from the caller, directly or indirectly. If `self_too` is set, the
+/// capabilities are also removed from the caller. Kernel objects get destroyed, once no
+/// capability refers to them anymore.
+///
+/// The permissions of the [`Crd`] are ignored. This wrapper always revokes all permissions,
+/// i.e. the capabilities get removed completely.
+///
+/// This function never panics.
+///
+/// # Safety
+/// * This function may change the systems functionality in an unintended way,
+///   if the arguments are illegal or wrong.
+/// * This function is not allowed to panic.
+/// * This function is strictly required to never produce any side effect system calls! Therefore,
+///   also no log::trace()-stuff or similar. Otherwise, the current implementation of hybrid
+///   foreign system calls will fail.
+#[inline]
+pub fn sys_revoke<Perm, Spec, ObjSpec>(
+    crd: Crd<Perm, Spec, ObjSpec>,
+    self_too: bool,
+) -> SyscallResult {
+    const SYSCALL_BITMASK: u64 = 0xff;
+    const SELF_FLAG: u64 = 1 << 8;
+    // all 5 permission bits of a Crd
+    const ALL_PERMISSIONS: u64 = 0b111_1100;
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::Revoke.val() & SYSCALL_BITMASK;
+    if self_too {
+        arg1 |= SELF_FLAG;
+    }
+    let arg2 = crd.val() | ALL_PERMISSIONS;
+
+    unsafe {
+        hedron_syscall_2(arg1, arg2)
+            .map(|_x| ())
+            .map_err(|e| SyscallError::HedronStatusError(e.0))
+    }
+}
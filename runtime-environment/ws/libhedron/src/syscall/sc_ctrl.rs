@@ -0,0 +1,33 @@
+//! SC CTRL-syscall.
+
+use crate::capability::CapSel;
+use crate::consts::NUM_CAP_SEL;
+use crate::syscall::{
+    hedron_syscall_1,
+    SyscallError,
+    SyscallNum,
+};
+use alloc::string::ToString;
+
+/// Returns the time that the SC consumed so far in microseconds. The time of an SC only
+/// grows while it runs, hence it is the CPU time of the EC that the SC is bound to.
+///
+/// # Safety
+/// * This function is not allowed to panic.
+/// * This function is strictly required to never produce any side effect system calls! Therefore,
+///   also no log::trace()-stuff or similar. Otherwise, the current implementation of hybrid
+///   foreign system calls will fail.
+#[inline]
+pub fn sys_sc_ctrl(sc_sel: CapSel) -> Result<u64, SyscallError> {
+    if sc_sel >= NUM_CAP_SEL {
+        return Err(SyscallError::ClientArgumentError(
+            "Argument `sc_sel` is too big".to_string(),
+        ));
+    }
+
+    let mut arg1 = 0;
+    arg1 |= SyscallNum::ScCtrl.val();
+    arg1 |= sc_sel << 12;
+
+    unsafe { hedron_syscall_1(arg1).map_err(|e| SyscallError::HedronStatusError(e.0)) }
+}
@@ -7,7 +7,12 @@
 //! avoid the chicken-egg problem!
 
 use crate::mem::PAGE_SIZE;
-use crate::Mtd;
+use crate::syscall::DelegateFlags;
+use crate::{
+    Crd,
+    CrdKind,
+    Mtd,
+};
 use arrayvec::ArrayString;
 use core::fmt::{
     Debug,
@@ -139,7 +144,7 @@ impl Utcb {
 
     /// Sets the number of typed items.
     fn set_number_typed_items(&mut self, count: u16) -> Result<(), UtcbError> {
-        if count as usize > UNTYPED_ITEM_CAPACITY {
+        if count as usize > TYPED_ITEM_CAPACITY {
             Err(UtcbError::TooManyTypedItems)
         } else {
             let untyped_items = self.untyped_items_count() as u32;
@@ -148,14 +153,33 @@ impl Utcb {
         }
     }
 
-    /// Returns all available typed items as slice.
-    /// The microhypervisor transfers typed items from the end of the UTCB data area downwards.
-    /// Each typed item occupies two words.
-    pub fn typed_items(&self) -> &[TypedItem] {
+    /// Removes all items. A reply afterwards carries no data, i.e. [`Self::load_data`]
+    /// fails with [`UtcbError::NoData`].
+    pub fn clear_data(&mut self) {
+        self.head.items = 0;
+    }
+
+    /// Returns all available typed items in the order of [`Self::push_typed_item`].
+    /// Typed items occupy the UTCB data area from the end downwards. Each typed item
+    /// occupies two words.
+    pub fn typed_items(&self) -> impl DoubleEndedIterator<Item = &TypedItem> {
         // typed items are at end of array
-        let end_i = UNTYPED_ITEM_CAPACITY - 1;
-        let begin_i = end_i - self.typed_items_count() as usize;
-        &self.data.typed_items()[begin_i..]
+        let begin_i = TYPED_ITEM_CAPACITY - self.typed_items_count() as usize;
+        self.data.typed_items()[begin_i..].iter().rev()
+    }
+
+    /// Adds a typed item behind the existing ones. The untyped items stay as they are,
+    /// hence typed items for a message go into the UTCB after [`Self::store_data`]. Fails
+    /// if the item would overlap with the untyped items.
+    pub fn push_typed_item(&mut self, item: TypedItem) -> Result<(), UtcbError> {
+        let count = self.typed_items_count() as usize + 1;
+        let occupied = self.untyped_items_count() as usize * size_of::<UntypedItem>()
+            + count * size_of::<TypedItem>();
+        if occupied > UTCB_DATA_CAPACITY {
+            return Err(UtcbError::TooManyTypedItems);
+        }
+        self.data.typed_items_mut()[TYPED_ITEM_CAPACITY - count] = item;
+        self.set_number_typed_items(count as u16)
     }
 
     /// Loads data from the UTCB, that was stored using [`Self::store_data`].
@@ -208,6 +232,60 @@ impl Utcb {
     pub fn exception_data_mut(&mut self) -> &mut UtcbDataException {
         self.data.exception_data_mut()
     }
+
+    /// Copies the head and the exception data. See [`UtcbSnapshot`].
+    pub fn snapshot(&self) -> UtcbSnapshot {
+        UtcbSnapshot {
+            head: self.head,
+            exception_data: *self.exception_data(),
+        }
+    }
+
+    /// Restores the state of a [`UtcbSnapshot`].
+    pub fn restore(&mut self, snapshot: &UtcbSnapshot) {
+        self.head = snapshot.head;
+        *self.exception_data_mut() = snapshot.exception_data;
+    }
+
+    /// Saves the state of the UTCB and restores it, when the returned guard gets dropped.
+    ///
+    /// A portal handler that performs its own IPC calls, i.e. nested portal calls, must use
+    /// this. The reply of the nested call gets written into the UTCB of the handler, which
+    /// still holds the exception state for the reply of the handler.
+    pub fn save(&mut self) -> UtcbSaveGuard<'_> {
+        UtcbSaveGuard {
+            snapshot: self.snapshot(),
+            utcb: self,
+        }
+    }
+}
+
+/// Copy of the part of a [`Utcb`] that the reply to an exception or a foreign system call
+/// depends on: the [`UtcbHead`] and the [`UtcbDataException`]. Untyped items beyond the
+/// size of the exception data are not part of it.
+#[derive(Debug, Copy, Clone)]
+pub struct UtcbSnapshot {
+    head: UtcbHead,
+    exception_data: UtcbDataException,
+}
+
+impl UtcbSnapshot {
+    pub const fn exception_data(&self) -> &UtcbDataException {
+        &self.exception_data
+    }
+}
+
+/// Restores the [`Utcb`] to the state of [`Utcb::save`] when it gets dropped.
+#[derive(Debug)]
+pub struct UtcbSaveGuard<'a> {
+    utcb: &'a mut Utcb,
+    snapshot: UtcbSnapshot,
+}
+
+impl Drop for UtcbSaveGuard<'_> {
+    fn drop(&mut self) {
+        self.utcb.restore(&self.snapshot);
+    }
 }
 
 impl Debug for Utcb {
@@ -383,6 +461,91 @@ pub struct UtcbDataException {
     pub tsc_timeout: u64,
 }
 
+impl UtcbDataException {
+    /// Returns the groups of registers, as they are selected by the [`Mtd`], that differ
+    /// between both states. The MTD itself is not compared.
+    pub fn changed_registers(&self, other: &Self) -> Mtd {
+        let groups = [
+            (
+                Mtd::GPR_ACDB,
+                [self.rax, self.rcx, self.rdx, self.rbx]
+                    != [other.rax, other.rcx, other.rdx, other.rbx],
+            ),
+            (
+                Mtd::GPR_BSD,
+                [self.rbp, self.rsi, self.rdi] != [other.rbp, other.rsi, other.rdi],
+            ),
+            (Mtd::RSP, self.rsp != other.rsp),
+            (
+                Mtd::RIP_LEN,
+                [self.rip, self.inst_len] != [other.rip, other.inst_len],
+            ),
+            (Mtd::RFLAGS, self.rflags != other.rflags),
+            (Mtd::DS_ES, [self.ds, self.es] != [other.ds, other.es]),
+            (Mtd::FS_GS, [self.fs, self.gs] != [other.fs, other.gs]),
+            (Mtd::CS_SS, [self.cs, self.ss] != [other.cs, other.ss]),
+            (Mtd::TR, self.tr != other.tr),
+            (Mtd::LDTR, self.ld != other.ld),
+            (Mtd::GDTR, self.gd != other.gd),
+            (Mtd::IDTR, self.id != other.id),
+            (
+                Mtd::CR,
+                [self.cr0, self.cr2, self.cr3, self.cr4, self.cr8, self.xrc0]
+                    != [
+                        other.cr0, other.cr2, other.cr3, other.cr4, other.cr8, other.xrc0,
+                    ],
+            ),
+            (Mtd::DR, self.dr7 != other.dr7),
+            (
+                Mtd::SYSENTER,
+                [self.sysenter_cs, self.sysenter_rsp, self.sysenter_rip]
+                    != [other.sysenter_cs, other.sysenter_rsp, other.sysenter_rip],
+            ),
+            (Mtd::QUAL, self.qual != other.qual),
+            (Mtd::CTRL, self.ctrl != other.ctrl),
+            (
+                Mtd::INJ,
+                [self.intr_info, self.intr_error] != [other.intr_info, other.intr_error],
+            ),
+            (
+                Mtd::STA,
+                [self.intr_state, self.actv_state] != [other.intr_state, other.actv_state],
+            ),
+            (
+                Mtd::TSC,
+                [self.tsc_val, self.tsc_off] != [other.tsc_val, other.tsc_off],
+            ),
+            (
+                Mtd::EFER_PAT,
+                [self.efer, self.pat] != [other.efer, other.pat],
+            ),
+            (Mtd::PDPTE, self.pdpte != other.pdpte),
+            (
+                Mtd::GPR_R8_R15,
+                [
+                    self.r8, self.r9, self.r10, self.r11, self.r12, self.r13, self.r14, self.r15,
+                ] != [
+                    other.r8, other.r9, other.r10, other.r11, other.r12, other.r13, other.r14,
+                    other.r15,
+                ],
+            ),
+            (
+                Mtd::SYSCALL_SWAPGS,
+                [self.star, self.lstar, self.fmask, self.kernel_gs_base]
+                    != [other.star, other.lstar, other.fmask, other.kernel_gs_base],
+            ),
+            (Mtd::TSC_TIMEOUT, self.tsc_timeout != other.tsc_timeout),
+            (Mtd::VINTR, self.vintr_status != other.vintr_status),
+            (Mtd::EOI, self.eoi_bitmap != other.eoi_bitmap),
+            (Mtd::TPR, self.tpr_threshold != other.tpr_threshold),
+        ];
+        groups
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .fold(Mtd::empty(), |mtd, (group, _)| mtd | group)
+    }
+}
+
 impl Debug for UtcbDataException {
     fn fmt(&self, f: &mut Formatter<'_>) -> serde::__private::fmt::Result {
         f.debug_struct("UtcbDataException")
@@ -467,7 +630,7 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 #[repr(C)]
 pub struct UtcbHead {
     flags: SystemCallFlags,
@@ -502,7 +665,7 @@ impl UtcbHead {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(C)]
 pub struct UtcbSegment {
     pub sel: u16,
@@ -513,18 +676,58 @@ pub struct UtcbSegment {
 
 pub type UntypedItem = u64;
 
-/// Typed item for delegation or translate capability operations in NOVA. Not used anymore
-/// in favor of more expressive, dedicated syscalls. Stands here only for completeness in typings.
-#[derive(Debug, Copy, Clone)]
+/// Typed item for delegation or translate capability operations in NOVA. Hedron doesn't
+/// delegate capabilities during IPC anymore in favor of the dedicated delegate syscall
+/// ([`crate::syscall::sys_pd_ctrl_delegate`]). Hence, a typed item only describes a
+/// capability that a message refers to: the party that holds the capabilities of both
+/// PDs, i.e. the roottask, performs the delegation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(C)]
 pub struct TypedItem {
-    a: u64,
-    b: u64,
+    /// Raw value of the [`Crd`] of the send window.
+    crd: u64,
+    flags: DelegateFlags,
+}
+
+impl TypedItem {
+    /// Creates a typed item that delegates the capabilities of `crd`.
+    pub fn delegate<Perm, Spec, ObjSpec>(
+        crd: Crd<Perm, Spec, ObjSpec>,
+        flags: DelegateFlags,
+    ) -> Self {
+        Self {
+            crd: crd.val(),
+            flags,
+        }
+    }
+
+    /// Returns the [`Crd`] of the send window. The caller chooses the specialisation that
+    /// fits to [`Self::kind`].
+    pub fn crd<Perm, Spec, ObjSpec>(&self) -> Crd<Perm, Spec, ObjSpec> {
+        Crd::new_from_val(self.crd)
+    }
+
+    /// Returns the [`CrdKind`] of the send window.
+    pub fn kind(&self) -> CrdKind {
+        CrdKind::from((self.crd & 0b11) as u8)
+    }
+
+    pub const fn flags(&self) -> DelegateFlags {
+        self.flags
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        CrdObjPT,
+        CrdObjSM,
+        CrdTypeObject,
+        CrdTypeObjectSM,
+        PTCapPermissions,
+        SMCapPermissions,
+    };
     use core::mem::size_of;
     use core::mem::size_of_val;
 
@@ -609,4 +812,79 @@ mod tests {
         let data = vec![0_u8; UTCB_DATA_CAPACITY - 1];
         assert!(utcb.store_data(&data).is_err());
     }
+
+    #[test]
+    fn test_typed_items() {
+        let mut utcb = Utcb::new();
+        utcb.store_data(&[7_u64; 4]).unwrap();
+        let items = [
+            TypedItem::delegate(
+                CrdObjPT::new(40, 0, PTCapPermissions::CALL),
+                DelegateFlags::default(),
+            ),
+            TypedItem::delegate(
+                CrdObjSM::new(128, 1, SMCapPermissions::UP),
+                DelegateFlags::default(),
+            ),
+        ];
+        for item in items {
+            utcb.push_typed_item(item).unwrap();
+        }
+        assert_eq!(utcb.typed_items_count(), 2);
+        assert!(utcb.typed_items().copied().eq(items));
+        assert_eq!(
+            utcb.typed_items().next().unwrap().kind(),
+            CrdKind::CrdKindObject
+        );
+        assert_eq!(
+            utcb.typed_items()
+                .nth(1)
+                .unwrap()
+                .crd::<SMCapPermissions, CrdTypeObject, CrdTypeObjectSM>()
+                .base(),
+            128
+        );
+        // the untyped items stay intact
+        assert_eq!(utcb.load_data::<[u64; 4]>().unwrap(), [7; 4]);
+
+        // a new message drops the typed items
+        utcb.store_data(&vec![0_u8; UTCB_DATA_CAPACITY - 32])
+            .unwrap();
+        assert_eq!(utcb.typed_items_count(), 0);
+        utcb.push_typed_item(items[0]).unwrap();
+        assert!(utcb.push_typed_item(items[1]).is_err());
+        assert_eq!(utcb.typed_items_count(), 1);
+    }
+
+    #[test]
+    fn test_save_guard() {
+        let mut utcb = Utcb::new();
+        utcb.exception_data_mut().mtd = Mtd::RIP_LEN;
+        utcb.exception_data_mut().rip = 0x1000;
+        {
+            let _guard = utcb.save();
+        }
+        let entry = utcb.snapshot();
+        {
+            let guard = utcb.save();
+            // what the reply of a nested portal call does
+            guard.utcb.store_data(&[0xff_u64; 8]).unwrap();
+            assert_ne!(guard.utcb.exception_data().rip, 0x1000);
+        }
+        assert_eq!(utcb.exception_data().mtd, Mtd::RIP_LEN);
+        assert_eq!(utcb.exception_data().rip, 0x1000);
+        assert_eq!(utcb.untyped_items_count(), 0);
+        assert!(utcb
+            .exception_data()
+            .changed_registers(entry.exception_data())
+            .is_empty());
+
+        utcb.exception_data_mut().rax = 42;
+        utcb.exception_data_mut().r15 = 42;
+        assert_eq!(
+            utcb.exception_data()
+                .changed_registers(entry.exception_data()),
+            Mtd::GPR_ACDB | Mtd::GPR_R8_R15
+        );
+    }
 }
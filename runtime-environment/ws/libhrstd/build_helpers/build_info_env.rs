@@ -0,0 +1,54 @@
+// Shared by the build scripts of all binaries via `include!()`. Emits the environment
+// variables that `libhrstd::build_info!()` needs. Build scripts can't depend on
+// libhrstd itself, because it is a `no_std` crate for the Hedron target.
+
+/// Emits `HRSTD_GIT_HASH`, `HRSTD_BUILD_TIMESTAMP`, and `HRSTD_BUILD_PROFILE` for the
+/// binary that is currently built. The git hash gets the suffix `-dirty` if the working
+/// tree has uncommitted changes. `SOURCE_DATE_EPOCH` overrides the build timestamp for
+/// reproducible builds.
+fn emit_build_info_env() {
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let git_hash = match git(&["rev-parse", "--short=12", "HEAD"]) {
+        Some(hash) => {
+            let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+                .map_or(false, |status| !status.is_empty());
+            if dirty {
+                format!("{}-dirty", hash)
+            } else {
+                hash
+            }
+        }
+        None => String::from("unknown"),
+    };
+
+    let build_timestamp = std::env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string()
+    });
+
+    println!("cargo:rustc-env=HRSTD_GIT_HASH={}", git_hash);
+    println!("cargo:rustc-env=HRSTD_BUILD_TIMESTAMP={}", build_timestamp);
+    println!(
+        "cargo:rustc-env=HRSTD_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap()
+    );
+
+    // rebuild the metadata after each commit or change to the index
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/logs/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/index", git_dir);
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
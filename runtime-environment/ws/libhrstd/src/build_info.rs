@@ -0,0 +1,130 @@
+//! Build metadata that every binary of the runtime environment embeds. It helps to
+//! detect if a booted image is stale, i.e. doesn't match the tested commit.
+//!
+//! The build script of each binary emits the required environment variables via
+//! `libhrstd/build_helpers/build_info_env.rs`. The binary then creates its
+//! [`BuildInfo`] with [`crate::build_info!`].
+
+use alloc::borrow::Cow;
+use core::fmt::{
+    Display,
+    Formatter,
+};
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Build metadata of a binary. Uses [`Cow`] so that it can be created from static strings
+/// at compile time but also be deserialized from a UTCB.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Name of the Cargo package of the binary.
+    pub pkg_name: Cow<'static, str>,
+    /// Version of the Cargo package of the binary.
+    pub pkg_version: Cow<'static, str>,
+    /// Abbreviated git commit hash, with the suffix `-dirty` if the working tree had
+    /// uncommitted changes, or `unknown`.
+    pub git_hash: Cow<'static, str>,
+    /// Seconds since the UNIX epoch.
+    pub build_timestamp: u64,
+    /// Cargo profile, i.e. `debug` or `release`.
+    pub profile: Cow<'static, str>,
+    /// Enabled features of libhrstd.
+    pub features: Cow<'static, str>,
+}
+
+impl BuildInfo {
+    /// Constructor. Use [`crate::build_info!`] instead.
+    pub fn new(
+        pkg_name: &'static str,
+        pkg_version: &'static str,
+        git_hash: &'static str,
+        build_timestamp: &'static str,
+        profile: &'static str,
+    ) -> Self {
+        Self {
+            pkg_name: Cow::Borrowed(pkg_name),
+            pkg_version: Cow::Borrowed(pkg_version),
+            git_hash: Cow::Borrowed(git_hash),
+            build_timestamp: build_timestamp.parse().unwrap_or(0),
+            profile: Cow::Borrowed(profile),
+            features: Cow::Borrowed(Self::libhrstd_features()),
+        }
+    }
+
+    /// Returns true if both binaries were built from the same commit. An unknown or
+    /// dirty commit never matches, because the source is ambiguous.
+    pub fn same_commit(&self, other: &Self) -> bool {
+        let is_exact = |hash: &str| hash != "unknown" && !hash.ends_with("-dirty");
+        is_exact(&self.git_hash) && self.git_hash == other.git_hash
+    }
+
+    /// Returns the enabled features of libhrstd in the current build.
+    const fn libhrstd_features() -> &'static str {
+        if cfg!(feature = "native_rust_rt") {
+            "native_rust_rt"
+        } else if cfg!(feature = "foreign_rust_rt") {
+            "foreign_rust_rt"
+        } else {
+            ""
+        }
+    }
+}
+
+impl Display for BuildInfo {
+    /// Formats the build info as single line. The integration test scripts rely on the
+    /// `git=` part.
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} {} (git={}, built={}, profile={}, features=[{}])",
+            self.pkg_name,
+            self.pkg_version,
+            self.git_hash,
+            self.build_timestamp,
+            self.profile,
+            self.features
+        )
+    }
+}
+
+/// Creates the [`BuildInfo`] of the binary that invokes this macro. The build script
+/// of the binary must call `emit_build_info_env()`.
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::build_info::BuildInfo::new(
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            env!("HRSTD_GIT_HASH"),
+            env!("HRSTD_BUILD_TIMESTAMP"),
+            env!("HRSTD_BUILD_PROFILE"),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_build_info() {
+        let info = BuildInfo::new("foo-bin", "0.1.0", "0123456789ab", "1337", "release");
+        // the test runs for each runtime flavor, see `cargo xtask feature-matrix`
+        assert_eq!(
+            info.to_string(),
+            format!(
+                "foo-bin 0.1.0 (git=0123456789ab, built=1337, profile=release, features=[{}])",
+                BuildInfo::libhrstd_features()
+            )
+        );
+        assert!(info.same_commit(&info));
+
+        let dirty = BuildInfo::new("foo-bin", "0.1.0", "0123456789ab-dirty", "x", "debug");
+        assert_eq!(dirty.build_timestamp, 0);
+        assert!(!dirty.same_commit(&dirty));
+        assert!(!info.same_commit(&dirty));
+    }
+}
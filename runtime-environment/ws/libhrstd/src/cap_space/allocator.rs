@@ -0,0 +1,155 @@
+//! See [`CapSelAllocator`].
+
+use crate::libhedron::CapSel;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Hands out ranges of free capability selectors from a fixed range of a capability space,
+/// such as the dynamic part of [`crate::cap_space::root::RootCapSpace`]. Ranges that get
+/// freed are reused by later allocations.
+///
+/// The allocator only manages numbers. The caller must make sure that no capability is
+/// left at the selectors before it frees them, for example by revoking them.
+#[derive(Debug)]
+pub struct CapSelAllocator {
+    /// Selectors at and above this one were never handed out.
+    next: CapSel,
+    /// End of the managed range (exclusive).
+    end: CapSel,
+    /// Freed ranges below [`Self::next`], sorted and not adjacent to each other.
+    freed: Vec<Range<CapSel>>,
+}
+
+impl CapSelAllocator {
+    /// Creates an allocator that manages the selectors in `range`.
+    pub const fn new(range: Range<CapSel>) -> Self {
+        Self {
+            next: range.start,
+            end: range.end,
+            freed: Vec::new(),
+        }
+    }
+
+    /// Returns the first of `count` consecutive free selectors and marks them as used.
+    /// Returns `None` if no such range is free.
+    pub fn alloc(&mut self, count: u64) -> Option<CapSel> {
+        assert!(count > 0, "count must be bigger than 0");
+        // first fit among the freed ranges
+        if let Some(index) = self
+            .freed
+            .iter()
+            .position(|range| range.end - range.start >= count)
+        {
+            let range = &mut self.freed[index];
+            let base = range.start;
+            range.start += count;
+            if range.is_empty() {
+                self.freed.remove(index);
+            }
+            return Some(base);
+        }
+        if self.end - self.next < count {
+            return None;
+        }
+        let base = self.next;
+        self.next += count;
+        Some(base)
+    }
+
+    /// Marks `count` selectors from `base` on as free again. Panics if any of them is
+    /// already free.
+    pub fn free(&mut self, base: CapSel, count: u64) {
+        let range = base..base + count;
+        assert!(
+            !range.is_empty() && range.end <= self.next,
+            "selectors {:?} were never allocated",
+            range
+        );
+        let index = self.freed.partition_point(|freed| freed.end <= range.start);
+        assert!(
+            self.freed
+                .get(index)
+                .map_or(true, |freed| range.end <= freed.start),
+            "selectors {:?} are already free",
+            range
+        );
+        self.freed.insert(index, range);
+
+        // merge with the neighbours
+        if index + 1 < self.freed.len() && self.freed[index].end == self.freed[index + 1].start {
+            self.freed[index].end = self.freed.remove(index + 1).end;
+        }
+        if index > 0 && self.freed[index - 1].end == self.freed[index].start {
+            self.freed[index - 1].end = self.freed.remove(index).end;
+        }
+        // give the top range back to the never used part
+        if self
+            .freed
+            .last()
+            .map_or(false, |last| last.end == self.next)
+        {
+            self.next = self.freed.pop().unwrap().start;
+        }
+    }
+
+    /// Returns the number of free selectors.
+    pub fn free_count(&self) -> u64 {
+        let freed = self
+            .freed
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum::<u64>();
+        freed + (self.end - self.next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alloc_and_free() {
+        let mut alloc = CapSelAllocator::new(100..110);
+        assert_eq!(alloc.alloc(4), Some(100));
+        assert_eq!(alloc.alloc(4), Some(104));
+        assert_eq!(alloc.alloc(4), None);
+        assert_eq!(alloc.free_count(), 2);
+
+        // freed selectors get reused
+        alloc.free(100, 4);
+        assert_eq!(alloc.alloc(2), Some(100));
+        assert_eq!(alloc.alloc(3), None);
+        assert_eq!(alloc.alloc(2), Some(102));
+        assert_eq!(alloc.free_count(), 2);
+
+        // the top range merges with the never used part
+        alloc.free(104, 4);
+        alloc.free(100, 2);
+        alloc.free(102, 2);
+        assert_eq!(alloc.free_count(), 10);
+        assert_eq!(alloc.alloc(10), Some(100));
+    }
+
+    #[test]
+    fn test_free_merges_ranges() {
+        let mut alloc = CapSelAllocator::new(0..100);
+        for i in 0..5 {
+            assert_eq!(alloc.alloc(10), Some(i * 10));
+        }
+        alloc.free(10, 10);
+        alloc.free(30, 10);
+        alloc.free(20, 10);
+        // 10..40 is one range now
+        assert_eq!(alloc.alloc(30), Some(10));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_double_free() {
+        let mut alloc = CapSelAllocator::new(0..100);
+        alloc.alloc(10);
+        alloc.alloc(10);
+        alloc.free(0, 10);
+        alloc.free(5, 2);
+    }
+}
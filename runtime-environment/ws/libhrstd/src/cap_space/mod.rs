@@ -1,2 +1,3 @@
+pub mod allocator;
 pub mod root;
 pub mod user;
@@ -1,4 +1,4 @@
-//! See [`CapabilitySpace`].
+//! See [`RootCapSpace`] and [`ProcessCapSels`].
 
 use crate::libhedron::consts::NUM_EXC;
 use crate::libhedron::CapSel;
@@ -6,34 +6,35 @@ use crate::process::consts::{
     ProcessId,
     NUM_PROCESSES,
 };
+use crate::rt::services::timer::{
+    TimerId,
+    MAX_TIMERS_PER_PROCESS,
+};
 use crate::service_ids::ServiceId;
 use enum_iterator::IntoEnumIterator;
 use libhedron::consts::NUM_CPUS;
 
-const PROCESS_PD_BASE: u64 = 100;
-const PROCESS_PD_END: u64 = RootCapSpace::calc_pd_sel(NUM_PROCESSES) - 1;
-const PROCESS_EC_BASE: u64 = PROCESS_PD_END + 1;
-const PROCESS_EC_END: u64 = RootCapSpace::calc_gl_ec_sel(NUM_PROCESSES) - 1;
-const PROCESS_SC_BASE: u64 = PROCESS_EC_END + 1;
-const PROCESS_SC_END: u64 = RootCapSpace::calc_sc_sel(NUM_PROCESSES) - 1;
-const PROCESS_EXC_PT_BASE: u64 = PROCESS_SC_END + 1;
-const PROCESS_EXC_PT_END: u64 =
-    RootCapSpace::calc_exc_pt_sel_base(NUM_PROCESSES) + NUM_EXC as u64 - 1;
-const PROCESS_SERVICE_PT_BASE: u64 = PROCESS_EXC_PT_END + 1;
-const PROCESS_SERVICE_PT_END: u64 =
-    RootCapSpace::calc_service_pt_sel_base(NUM_PROCESSES as u64) + ServiceId::count() - 1;
-const PROCESS_FOREIGN_SYSCALL_HANDLER_PT_BASE: u64 = PROCESS_SERVICE_PT_END + 1;
-const PROCESS_FOREIGN_SYSCALL_HANDLER_PT_END: u64 =
-    RootCapSpace::calc_foreign_syscall_pt_sel_base(NUM_PROCESSES as u64) - 1;
-
-/// Describes the capability space of the roottask. Party determinined by Hedron,
-/// the rest is a choice by me. Some of the capabilities stand also inside the HIP.
+const AP_EXCEPTION_LOCAL_EC_BASE: u64 = 100;
+const AP_EXCEPTION_LOCAL_EC_END: u64 = AP_EXCEPTION_LOCAL_EC_BASE + NUM_CPUS as u64 - 2;
+const AP_SERVICE_LOCAL_EC_BASE: u64 = AP_EXCEPTION_LOCAL_EC_END + 1;
+const AP_SERVICE_LOCAL_EC_END: u64 = AP_SERVICE_LOCAL_EC_BASE + NUM_CPUS as u64 - 2;
+const AP_RAW_ECHO_SERVICE_EC_BASE: u64 = AP_SERVICE_LOCAL_EC_END + 1;
+const AP_RAW_ECHO_SERVICE_EC_END: u64 = AP_RAW_ECHO_SERVICE_EC_BASE + NUM_CPUS as u64 - 2;
+const AP_RAW_ECHO_SERVICE_PT_BASE: u64 = AP_RAW_ECHO_SERVICE_EC_END + 1;
+const AP_RAW_ECHO_SERVICE_PT_END: u64 = AP_RAW_ECHO_SERVICE_PT_BASE + NUM_CPUS as u64 - 2;
+const DYNAMIC_BASE: u64 = AP_RAW_ECHO_SERVICE_PT_END + 1;
+
+/// Describes the static part of the capability space of the roottask. Party determinined
+/// by Hedron, the rest is a choice by me. Some of the capabilities stand also inside the HIP.
 /// Anyhow, we don't expect or support changing capability space layouts without recompilation.
 ///
 /// The variant value corresponds to the [`crate::libhrstd::libhedron::CapSel`]
 /// that refers to the given capability.
 ///
-/// TODO remove this because it extremely pollutes the cap space. Make a dynamic capability selector!
+/// Everything from [`RootCapSpace::DynamicBase`] on gets allocated at runtime, see
+/// [`crate::cap_space::allocator::CapSelAllocator`]. The kernel objects of a process live
+/// in a block of selectors that the roottask allocates when the process starts and frees
+/// when it stops, see [`ProcessCapSels`].
 #[repr(u64)]
 #[derive(Copy, Clone, Debug, IntoEnumIterator)]
 pub enum RootCapSpace {
@@ -68,36 +69,38 @@ pub enum RootCapSpace {
     /// The root task can call its own raw echo service PT for performance measurements.
     RootRawEchoServicePt,
 
-    /// Base CapSel for the PD of a process. This + PID => capability index offset
-    ProcessPdBase = PROCESS_PD_BASE,
-    /// Last inclusive index relative to [`ProcessPdBase`].
-    ProcessPdEnd = PROCESS_PD_END,
-
-    /// Base CapSel for the global EC of a process. This + PID + CPU => capability index offset
-    ProcessEcBase = PROCESS_EC_BASE,
-    /// Last inclusive index relative to [`ProcessEcBase`].
-    ProcessEcEnc = PROCESS_EC_END,
-
-    /// Base CapSel for the SC of a process. This + PID => capability index offset
-    ProcessScBase = PROCESS_SC_BASE,
-    /// Last inclusive index relative to [`ProcessScBase`].
-    ProcessScEnd = PROCESS_SC_END,
-
-    /// Base CapSel for the exception portals of a process. This + PID * CPU_NUM * NUM-EXC => cap index offset
-    ProcessExcPtBase = PROCESS_EXC_PT_BASE,
-    /// Last inclusive index relative to [`ProcessExcPtBase`].
-    ProcessExcPtEnd = PROCESS_EXC_PT_END,
-
-    /// Base CapSel for the service portals of a process. This + PID * SERVICE_ID => cap index
-    ServicePtBase = PROCESS_SERVICE_PT_BASE,
-    /// Last inclusive index relative to [`ProcessServicePtBase`].
-    ServicePtEnd = PROCESS_SERVICE_PT_END,
-
-    // TODO das is mittlerweile wirklich blöd. Lieber einen dynamischen CapSel-Allokator bauen
-    /// Base CapSel for the foreign syscall handler portals of a process. This + PID + CPU-Num => cap index
-    SyscallHandlerPtBase = PROCESS_FOREIGN_SYSCALL_HANDLER_PT_BASE,
-    /// Last inclusive index relative to [`SyscallHandlerPtBase`].
-    SyscallHandlerPtEnd = PROCESS_FOREIGN_SYSCALL_HANDLER_PT_END,
+    /// The SM object that the local EC of the foreign syscall handler uses to sleep
+    /// with a timeout on behalf of a process (i.e. `nanosleep`).
+    RootSmForeignSleep,
+
+    /// Base CapSel for the exception handling local ECs of the application processors
+    /// (all CPUs except CPU 0). This + CPU - 1 => cap index. CPU 0 uses
+    /// [`RootExceptionLocalEc`].
+    ApExceptionLocalEcBase = AP_EXCEPTION_LOCAL_EC_BASE,
+    /// Last inclusive index relative to [`ApExceptionLocalEcBase`].
+    ApExceptionLocalEcEnd = AP_EXCEPTION_LOCAL_EC_END,
+
+    /// Base CapSel for the service local ECs of the application processors.
+    /// This + CPU - 1 => cap index. CPU 0 uses [`RootServiceLocalEc`].
+    ApServiceLocalEcBase = AP_SERVICE_LOCAL_EC_BASE,
+    /// Last inclusive index relative to [`ApServiceLocalEcBase`].
+    ApServiceLocalEcEnd = AP_SERVICE_LOCAL_EC_END,
+
+    /// Base CapSel for the local ECs of the Raw Echo Service of the application processors.
+    /// This + CPU - 1 => cap index. CPU 0 uses [`RootRawEchoServiceEc`].
+    ApRawEchoServiceEcBase = AP_RAW_ECHO_SERVICE_EC_BASE,
+    /// Last inclusive index relative to [`ApRawEchoServiceEcBase`].
+    ApRawEchoServiceEcEnd = AP_RAW_ECHO_SERVICE_EC_END,
+
+    /// Base CapSel for the raw echo service PTs of the roottask on the application
+    /// processors. This + CPU - 1 => cap index. CPU 0 uses [`RootRawEchoServicePt`].
+    ApRawEchoServicePtBase = AP_RAW_ECHO_SERVICE_PT_BASE,
+    /// Last inclusive index relative to [`ApRawEchoServicePtBase`].
+    ApRawEchoServicePtEnd = AP_RAW_ECHO_SERVICE_PT_END,
+
+    /// First CapSel that the roottask hands out at runtime, e.g. for the kernel objects
+    /// of processes. See [`ProcessCapSels`] and [`crate::cap_space::allocator`].
+    DynamicBase = DYNAMIC_BASE,
     _Max,
 }
 
@@ -107,35 +110,128 @@ impl RootCapSpace {
         self as _
     }
 
-    /// Calcs the cap sel in the roottask for the PD for a given process.
-    pub const fn calc_pd_sel(pid: ProcessId) -> CapSel {
-        PROCESS_PD_BASE + pid
+    /// Calcs the cap sel in the roottask for the exception handling local EC of a CPU.
+    pub const fn calc_exception_local_ec_sel(cpu: u64) -> CapSel {
+        if cpu == 0 {
+            Self::RootExceptionLocalEc.val()
+        } else {
+            AP_EXCEPTION_LOCAL_EC_BASE + cpu - 1
+        }
+    }
+
+    /// Calcs the cap sel in the roottask for the service local EC of a CPU.
+    pub const fn calc_service_local_ec_sel(cpu: u64) -> CapSel {
+        if cpu == 0 {
+            Self::RootServiceLocalEc.val()
+        } else {
+            AP_SERVICE_LOCAL_EC_BASE + cpu - 1
+        }
+    }
+
+    /// Calcs the cap sel in the roottask for the local EC of the Raw Echo Service of a CPU.
+    pub const fn calc_raw_echo_service_ec_sel(cpu: u64) -> CapSel {
+        if cpu == 0 {
+            Self::RootRawEchoServiceEc.val()
+        } else {
+            AP_RAW_ECHO_SERVICE_EC_BASE + cpu - 1
+        }
+    }
+
+    /// Calcs the cap sel in the roottask for the raw echo service PT of the roottask that
+    /// can be called on a CPU.
+    pub const fn calc_raw_echo_service_pt_sel(cpu: u64) -> CapSel {
+        if cpu == 0 {
+            Self::RootRawEchoServicePt.val()
+        } else {
+            AP_RAW_ECHO_SERVICE_PT_BASE + cpu - 1
+        }
+    }
+}
+
+const PD_OFFSET: u64 = 0;
+const GL_EC_OFFSET: u64 = 1;
+const SC_OFFSET: u64 = 2;
+const FS_RING_SM_OFFSET: u64 = 3;
+const HOSTED_SERVICE_EC_OFFSET: u64 = 4;
+const EXC_PT_OFFSET: u64 = 5;
+const SERVICE_PT_OFFSET: u64 = EXC_PT_OFFSET + NUM_EXC as u64;
+const FOREIGN_SYSCALL_PT_OFFSET: u64 = SERVICE_PT_OFFSET + ServiceId::count();
+const TIMER_SM_OFFSET: u64 = FOREIGN_SYSCALL_PT_OFFSET + NUM_CPUS as u64;
+const HOSTED_SERVICE_PT_OFFSET: u64 = TIMER_SM_OFFSET + MAX_TIMERS_PER_PROCESS;
+
+/// The selectors of the kernel objects of a process in the capability space of the
+/// roottask. They form a block of [`ProcessCapSels::COUNT`] consecutive selectors in the
+/// dynamic part of the [`RootCapSpace`], which the roottask allocates for each process.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProcessCapSels {
+    base: CapSel,
+}
+
+impl ProcessCapSels {
+    /// Number of selectors of a process.
+    pub const COUNT: u64 = HOSTED_SERVICE_PT_OFFSET + NUM_PROCESSES;
+
+    /// Describes the block of selectors that begins at `base`.
+    pub const fn new(base: CapSel) -> Self {
+        Self { base }
+    }
+
+    /// Returns the first selector of the block.
+    pub const fn base(self) -> CapSel {
+        self.base
+    }
+
+    /// Returns the cap sel of the PD of the process.
+    pub const fn pd(self) -> CapSel {
+        self.base + PD_OFFSET
     }
 
-    /// Calcs the cap sel in the roottask for the global EC for a given process.
-    pub const fn calc_gl_ec_sel(pid: ProcessId) -> CapSel {
-        PROCESS_EC_BASE + pid
+    /// Returns the cap sel of the global EC of the process.
+    pub const fn gl_ec(self) -> CapSel {
+        self.base + GL_EC_OFFSET
     }
 
-    /// Calcs the cap sel in the roottask for the SC for a given process.
-    pub const fn calc_sc_sel(pid: ProcessId) -> CapSel {
-        PROCESS_SC_BASE + pid
+    /// Returns the cap sel of the SC of the process.
+    pub const fn sc(self) -> CapSel {
+        self.base + SC_OFFSET
     }
 
-    /// Calcs the cap sel base in the roottask for the exception PT for a given process.
-    pub const fn calc_exc_pt_sel_base(pid: ProcessId) -> CapSel {
-        PROCESS_EXC_PT_BASE + (pid * NUM_EXC as u64) + pid
+    /// Returns the cap sel base of the [`NUM_EXC`] exception PTs of the process.
+    pub const fn exc_pt_base(self) -> CapSel {
+        self.base + EXC_PT_OFFSET
     }
 
-    /// Calcs the cap sel base in the roottask for the service PT for a given process.
-    pub const fn calc_service_pt_sel_base(pid: ProcessId) -> CapSel {
-        PROCESS_SERVICE_PT_BASE + (pid * ServiceId::count()) + pid
+    /// Returns the cap sel base of the service PTs of the process. This + [`ServiceId`] =>
+    /// cap sel.
+    pub const fn service_pt_base(self) -> CapSel {
+        self.base + SERVICE_PT_OFFSET
     }
 
-    /// Calcs the cap sel base in the roottask for the syscall handler PTs for a given process.
-    pub const fn calc_foreign_syscall_pt_sel_base(pid: ProcessId) -> CapSel {
-        // -1: roottask is excluded here
-        PROCESS_FOREIGN_SYSCALL_HANDLER_PT_BASE + (NUM_CPUS as u64 * (pid - 1))
+    /// Returns the cap sel base of the foreign syscall handler PTs of the process. This +
+    /// CPU => cap sel.
+    pub const fn foreign_syscall_pt_base(self) -> CapSel {
+        self.base + FOREIGN_SYSCALL_PT_OFFSET
+    }
+
+    /// Returns the cap sel of the SM of a timer of the process.
+    pub const fn timer_sm(self, timer_id: TimerId) -> CapSel {
+        self.base + TIMER_SM_OFFSET + timer_id
+    }
+
+    /// Returns the cap sel of the completion SM of the file system ring of the process.
+    pub const fn fs_ring_sm(self) -> CapSel {
+        self.base + FS_RING_SM_OFFSET
+    }
+
+    /// Returns the cap sel of the local EC of the service that the process hosts.
+    pub const fn hosted_service_ec(self) -> CapSel {
+        self.base + HOSTED_SERVICE_EC_OFFSET
+    }
+
+    /// Returns the cap sel of the portal of the service that the process hosts, through
+    /// which `client` calls it.
+    pub const fn hosted_service_pt(self, client: ProcessId) -> CapSel {
+        self.base + HOSTED_SERVICE_PT_OFFSET + client
     }
 }
 
@@ -159,4 +255,60 @@ mod tests {
     fn test_assert_max_cap_sel() {
         assert!(RootCapSpace::_Max.val() <= NUM_CAP_SEL);
     }
+
+    #[test]
+    fn test_process_cap_sels() {
+        let sels = ProcessCapSels::new(RootCapSpace::DynamicBase.val());
+        let last_pid = NUM_PROCESSES - 1;
+        let mut all = vec![
+            sels.pd(),
+            sels.gl_ec(),
+            sels.sc(),
+            sels.fs_ring_sm(),
+            sels.hosted_service_ec(),
+        ];
+        all.extend((0..NUM_EXC as u64).map(|exc| sels.exc_pt_base() + exc));
+        all.extend((0..ServiceId::count()).map(|id| sels.service_pt_base() + id));
+        all.extend((0..NUM_CPUS as u64).map(|cpu| sels.foreign_syscall_pt_base() + cpu));
+        all.extend((0..MAX_TIMERS_PER_PROCESS).map(|id| sels.timer_sm(id)));
+        all.extend((0..NUM_PROCESSES).map(|client| sels.hosted_service_pt(client)));
+        // each selector of the block is used exactly once
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len() as u64, ProcessCapSels::COUNT);
+        assert_eq!(all.first(), Some(&sels.base()));
+        assert_eq!(
+            sels.hosted_service_pt(last_pid),
+            sels.base() + ProcessCapSels::COUNT - 1
+        );
+    }
+
+    #[test]
+    fn test_per_cpu_sels() {
+        let last_cpu = NUM_CPUS as u64 - 1;
+        assert_eq!(
+            RootCapSpace::calc_service_local_ec_sel(0),
+            RootCapSpace::RootServiceLocalEc.val()
+        );
+        assert_eq!(
+            RootCapSpace::calc_service_local_ec_sel(1),
+            RootCapSpace::ApServiceLocalEcBase.val()
+        );
+        assert_eq!(
+            RootCapSpace::calc_exception_local_ec_sel(last_cpu),
+            RootCapSpace::ApExceptionLocalEcEnd.val()
+        );
+        assert_eq!(
+            RootCapSpace::calc_service_local_ec_sel(last_cpu),
+            RootCapSpace::ApServiceLocalEcEnd.val()
+        );
+        assert_eq!(
+            RootCapSpace::calc_raw_echo_service_ec_sel(last_cpu),
+            RootCapSpace::ApRawEchoServiceEcEnd.val()
+        );
+        assert_eq!(
+            RootCapSpace::calc_raw_echo_service_pt_sel(last_cpu),
+            RootCapSpace::ApRawEchoServicePtEnd.val()
+        );
+    }
 }
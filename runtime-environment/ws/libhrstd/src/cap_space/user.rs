@@ -32,6 +32,63 @@ pub enum UserAppCapSpace {
     FsServicePT = 38,
     EchoServicePT,
     RawEchoServicePt,
+    /// CapSel for the timer service portal.
+    TimerServicePT,
+    /// CapSel for the build info service portal.
+    BuildInfoServicePT,
+    /// CapSel for the process signal service portal.
+    ProcessSignalServicePT,
+    /// CapSel for the network service portal.
+    NetworkServicePT,
+    /// CapSel for the scheduling service portal.
+    SchedulingServicePT,
+    /// CapSel for the process service portal.
+    ProcessServicePT,
+    /// CapSel for the system time service portal.
+    SystemTimeServicePT,
+    /// CapSel for the stdin service portal.
+    StdinServicePT,
+    /// CapSel for the name service portal.
+    NameServicePT,
+    /// CapSel for the serial transfer service portal.
+    SerialTransferServicePT,
+    /// CapSel for the log service portal.
+    LogServicePT,
+    /// CapSel for the performance counter service portal.
+    PerfServicePT,
+    /// CapSel for the semaphore service portal.
+    SemaphoreServicePT,
+    /// CapSel for the shared memory service portal.
+    ShmServicePT,
+    /// CapSel for the interrupt service portal.
+    IrqServicePT,
+    /// CapSel for the PCI service portal.
+    PciServicePT,
+    /// Base CapSel for the semaphores of timers. This + timer id => SM that gets
+    /// signaled by the roottask each time the timer fires.
+    TimerSmBase = 128,
+    /// Semaphore that wakes up the roottask to handle the submissions in the file system
+    /// ring. Follows the semaphores of the timers. See `fs_submit_batch`.
+    FsRingKickSm = 144,
+    /// Semaphore that the roottask signals after it completed submissions of the file
+    /// system ring.
+    FsRingCompletionSm,
+    /// First selector of the range where the roottask delegates the capabilities that the
+    /// process receives from other processes. See
+    /// [`crate::rt::services::process::ProcessServiceRequest::SendCap`].
+    ReceivedCapBase = 192,
+    /// Last inclusive selector of the received capabilities.
+    ReceivedCapEnd = 255,
+    /// First selector of the range where the roottask delegates the semaphores that the
+    /// process creates or opens. See [`crate::rt::services::semaphore`].
+    SemaphoreBase = 256,
+    /// Last inclusive selector of the semaphores.
+    SemaphoreEnd = 319,
+    /// First selector of the range where the roottask delegates the interrupt semaphores
+    /// of the interrupts that the process attaches. See [`crate::rt::services::irq`].
+    IrqSmBase = 320,
+    /// Last inclusive selector of the interrupt semaphores.
+    IrqSmEnd = 335,
 }
 
 impl UserAppCapSpace {
@@ -45,8 +102,9 @@ impl UserAppCapSpace {
 #[repr(u64)]
 #[derive(Copy, Clone, Debug)]
 pub enum ForeignUserAppCapSpace {
-    /// Begin value. This plus CPU_NUM equals the actual PT selector.
-    SyscallBasePt = 50,
+    /// Begin value. This plus CPU_NUM equals the actual PT selector. Follows the service
+    /// PTs; the last CPU ends right before [`UserAppCapSpace::TimerSmBase`].
+    SyscallBasePt = 64,
 }
 
 impl ForeignUserAppCapSpace {
@@ -61,6 +119,28 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_fs_ring_sms_follow_timer_sms() {
+        use crate::rt::services::timer::{
+            timer_sm_sel,
+            MAX_TIMERS_PER_PROCESS,
+        };
+        assert_eq!(
+            timer_sm_sel(MAX_TIMERS_PER_PROCESS - 1) + 1,
+            UserAppCapSpace::FsRingKickSm.val()
+        );
+    }
+
+    #[test]
+    fn test_syscall_pts_between_service_pts_and_timer_sms() {
+        use crate::libhedron::consts::NUM_CPUS;
+        assert!(UserAppCapSpace::PciServicePT.val() < ForeignUserAppCapSpace::SyscallBasePt.val());
+        assert_eq!(
+            ForeignUserAppCapSpace::SyscallBasePt.val() + NUM_CPUS as u64,
+            UserAppCapSpace::TimerSmBase.val()
+        );
+    }
+
     #[test]
     fn test_syscall_base_ot() {
         dbg!(ForeignUserAppCapSpace::SyscallBasePt.val());
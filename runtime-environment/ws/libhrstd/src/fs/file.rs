@@ -1,5 +1,4 @@
 use crate::mem::UserPtrOrEmbedded;
-use crate::rt::services::fs::FD;
 use crate::rt::services::fs::{
     fs_service_close,
     FsCloseRequest,
@@ -21,6 +20,10 @@ use crate::rt::services::fs::{
     fs_service_write,
     FsWriteRequest,
 };
+use crate::rt::services::fs::{
+    FsError,
+    FD,
+};
 use alloc::string::ToString;
 use alloc::vec::Vec;
 use libhedron::mem::PAGE_SIZE;
@@ -34,13 +37,19 @@ pub struct File {
 
 impl File {
     /// Opens a file.
-    pub fn open(path: &str, flags: FsOpenFlags, umode: u16) -> Self {
-        let fd = fs_service_open(FsOpenRequest::new(path.to_string(), flags, umode));
+    pub fn open(path: &str, flags: FsOpenFlags, umode: u16) -> Result<Self, FsError> {
+        let fd = fs_service_open(FsOpenRequest::new(path.to_string(), flags, umode))?;
+        Ok(Self { fd })
+    }
+
+    /// Takes a file that the process got at a fixed file descriptor when it started, see
+    /// [`crate::rt::services::process::PreopenedFile`].
+    pub fn from_fd(fd: FD) -> Self {
         Self { fd }
     }
 
     /// Writes all bytes to the file.
-    pub fn write_all(&mut self, bytes: &[u8]) -> usize {
+    pub fn write_all(&mut self, bytes: &[u8]) -> Result<usize, FsError> {
         fs_service_write(FsWriteRequest::new(
             self.fd,
             UserPtrOrEmbedded::EmbeddedSlice(bytes.to_vec()),
@@ -48,8 +57,18 @@ impl File {
         ))
     }
 
+    /// Reads at most `buf.len()` bytes at the file offset. Returns the number of read bytes,
+    /// which is zero at the end of the file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, FsError> {
+        fs_service_read(FsReadRequest::new(
+            self.fd,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+        ))
+    }
+
     /// This returns all bytes until the file system returns EOF.
-    pub fn read_to_vec(&mut self) -> Vec<u8> {
+    pub fn read_to_vec(&mut self) -> Result<Vec<u8>, FsError> {
         let mut data = Vec::<u8>::with_capacity(PAGE_SIZE);
         let mut tmp_data = Vec::<u8>::with_capacity(PAGE_SIZE);
         loop {
@@ -57,7 +76,7 @@ impl File {
                 self.fd,
                 tmp_data.as_mut_ptr() as usize,
                 data.capacity(),
-            ));
+            ))?;
             log::trace!("read_bytes = {}", read_bytes);
             if read_bytes == 0 {
                 break;
@@ -66,16 +85,16 @@ impl File {
                 data.extend_from_slice(tmp_data.as_slice());
             }
         }
-        data
+        Ok(data)
     }
 
     /// Updates the file offset of the opened file.
-    pub fn lseek(&mut self, offset: u64) {
-        fs_service_lseek(FsLseekRequest::new(self.fd, offset));
+    pub fn lseek(&mut self, offset: u64) -> Result<(), FsError> {
+        fs_service_lseek(FsLseekRequest::new(self.fd, offset))
     }
 
     /// Closes a file.
-    pub fn close(self) {
-        fs_service_close(FsCloseRequest::new(self.fd));
+    pub fn close(self) -> Result<(), FsError> {
+        fs_service_close(FsCloseRequest::new(self.fd))
     }
 }
@@ -1,3 +1,18 @@
 mod file;
 
 pub use file::File;
+
+use crate::rt::services::fs::{
+    fs_service_access,
+    FsAccessMode,
+    FsAccessRequest,
+    FsError,
+};
+use alloc::string::ToString;
+
+/// Checks if the calling process has the permissions of `mode` for the file at `path`,
+/// without opening the file. Like `access()` on UNIX; shells and build tools use it to
+/// check if a file is executable before they spawn it.
+pub fn access(path: &str, mode: FsAccessMode) -> Result<(), FsError> {
+    fs_service_access(FsAccessRequest::new(path.to_string(), mode))
+}
@@ -0,0 +1,138 @@
+use crate::libhedron::syscall::sys_revoke;
+use crate::libhedron::{
+    CrdMem,
+    CrdObj,
+    MemCapPermissions,
+};
+use crate::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Kind of the capabilities of a [`Delegation`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DelegatedKind {
+    /// Memory pages. The bases are page numbers.
+    Mem,
+    /// Kernel objects, such as portals or semaphores. The bases are capability selectors.
+    Obj,
+}
+
+/// Capabilities that a PD received. The range refers to the capability space of the PD
+/// that delegated them, i.e. of the roottask. Only that PD can revoke them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    pub kind: DelegatedKind,
+    pub range: Range<u64>,
+}
+
+/// All capabilities that a PD received, see [`crate::kobjects::PdObject::track_delegation`].
+#[derive(Debug, Default)]
+pub struct Delegations(Vec<Delegation>);
+
+impl Delegations {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Records the delegation of `range`.
+    pub fn insert(&mut self, kind: DelegatedKind, range: Range<u64>) {
+        if !range.is_empty() {
+            self.0.push(Delegation { kind, range });
+        }
+    }
+
+    /// Forgets all delegations of `kind` that lie in `range` and returns their ranges.
+    /// Delegations that are partly in `range` shrink or get split.
+    pub fn remove(&mut self, kind: DelegatedKind, range: Range<u64>) -> Vec<Range<u64>> {
+        let mut removed = Vec::new();
+        let mut kept = Vec::with_capacity(self.0.len());
+        for delegation in self.0.drain(..) {
+            let start = delegation.range.start.max(range.start);
+            let end = delegation.range.end.min(range.end);
+            if delegation.kind != kind || start >= end {
+                kept.push(delegation);
+                continue;
+            }
+            removed.push(start..end);
+            if delegation.range.start < start {
+                kept.push(Delegation {
+                    kind,
+                    range: delegation.range.start..start,
+                });
+            }
+            if end < delegation.range.end {
+                kept.push(Delegation {
+                    kind,
+                    range: end..delegation.range.end,
+                });
+            }
+        }
+        self.0 = kept;
+        removed
+    }
+
+    /// Forgets all delegations and returns them.
+    pub fn take_all(&mut self) -> Vec<Delegation> {
+        core::mem::take(&mut self.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Delegation> {
+        self.0.iter()
+    }
+}
+
+/// Revokes the capabilities of `range` in the capability space of the caller from all PDs
+/// that received them from the caller, directly or indirectly. The caller keeps its own
+/// capabilities. Errors are only logged, because the capabilities may be gone already.
+pub fn revoke_delegated(kind: DelegatedKind, range: Range<u64>) {
+    let count = (range.end - range.start) as usize;
+    CrdDelegateOptimizer::new(range.start, range.start, count).for_each(|step| {
+        let res = match kind {
+            DelegatedKind::Mem => sys_revoke(
+                CrdMem::new(step.src_base, step.order, MemCapPermissions::RWX),
+                false,
+            ),
+            DelegatedKind::Obj => sys_revoke(CrdObj::new(step.src_base, step.order), false),
+        };
+        if let Err(e) = res {
+            log::warn!(
+                "can't revoke {:?} capabilities at {}: {:?}",
+                kind,
+                step.src_base,
+                e
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_remove_delegations() {
+        let mut delegations = Delegations::new();
+        delegations.insert(DelegatedKind::Mem, 10..20);
+        delegations.insert(DelegatedKind::Obj, 10..20);
+        delegations.insert(DelegatedKind::Mem, 30..31);
+        delegations.insert(DelegatedKind::Mem, 40..40);
+
+        // splits the first delegation and keeps the objects
+        assert_eq!(delegations.remove(DelegatedKind::Mem, 12..15), vec![12..15]);
+        assert_eq!(
+            delegations.remove(DelegatedKind::Mem, 0..100),
+            vec![10..12, 15..20, 30..31]
+        );
+        assert_eq!(delegations.remove(DelegatedKind::Mem, 0..100), vec![]);
+
+        assert_eq!(
+            delegations.take_all(),
+            vec![Delegation {
+                kind: DelegatedKind::Obj,
+                range: 10..20
+            }]
+        );
+        assert_eq!(delegations.iter().count(), 0);
+    }
+}
@@ -40,6 +40,8 @@ pub struct LocalEcObject {
     ec_sel: CapSel,
     stack_top_ptr: u64,
     utcb_addr: u64,
+    /// CPU the EC is bound to. Portals of the EC can only be called from this CPU.
+    cpu: u64,
     // a local EC owns all its portals
     portals: RefCell<BTreeSet<Rc<PtObject>>>,
 }
@@ -51,8 +53,9 @@ impl LocalEcObject {
         pd_obj: &Rc<PdObject>,
         stack_top_ptr: u64,
         utcb_addr: u64,
+        cpu: u64,
     ) -> Rc<Self> {
-        let obj = Self::new(ec_sel, pd_obj, stack_top_ptr, utcb_addr);
+        let obj = Self::new(ec_sel, pd_obj, stack_top_ptr, utcb_addr, cpu);
 
         #[cfg(not(feature = "foreign_rust_rt"))]
         let syscall_fn = libhedron::syscall::sys_create_local_ec;
@@ -64,7 +67,7 @@ impl LocalEcObject {
             stack_top_ptr,
             // 0 is used as event base in all PDs by convention
             UserAppCapSpace::ExceptionEventBase.val(),
-            0,
+            cpu,
             obj.utcb_page_num(),
         )
         .unwrap();
@@ -80,6 +83,7 @@ impl LocalEcObject {
         pd_obj: &Rc<PdObject>,
         stack_top_ptr: u64,
         utcb_addr: u64,
+        cpu: u64,
     ) -> Rc<Self> {
         assert!(utcb_addr > 0);
         assert_eq!(utcb_addr % PAGE_SIZE as u64, 0);
@@ -90,6 +94,7 @@ impl LocalEcObject {
             ec_sel,
             stack_top_ptr,
             utcb_addr,
+            cpu,
             portals: RefCell::new(BTreeSet::new()),
         };
         let obj = Rc::new(obj);
@@ -118,6 +123,9 @@ impl LocalEcObject {
     pub fn utcb_page_num(&self) -> u64 {
         self.utcb_addr / PAGE_SIZE as u64
     }
+    pub fn cpu(&self) -> u64 {
+        self.cpu
+    }
 
     pub fn add_portal(&self, pt: Rc<PtObject>) {
         let _ = self.portals.borrow_mut().insert(pt);
@@ -178,6 +186,8 @@ pub struct GlobalEcObject {
     stack_top_ptr: u64,
     /// UTCB-addr in the address space of the targed PD.
     utcb_addr: u64,
+    /// CPU the EC runs on. ECs can't migrate to other CPUs.
+    cpu: u64,
 }
 
 impl GlobalEcObject {
@@ -188,8 +198,9 @@ impl GlobalEcObject {
         pd_obj: &Rc<PdObject>,
         utcb_addr: u64,
         stack_top_ptr: u64,
+        cpu: u64,
     ) -> Rc<Self> {
-        let obj = Self::new(ec_sel, pd_obj, utcb_addr, stack_top_ptr);
+        let obj = Self::new(ec_sel, pd_obj, utcb_addr, stack_top_ptr, cpu);
 
         #[cfg(not(feature = "foreign_rust_rt"))]
         let syscall_fn = libhedron::syscall::sys_create_global_ec;
@@ -200,7 +211,7 @@ impl GlobalEcObject {
             pd_obj.cap_sel(),
             // 0 is used as event base in all PDs by convention
             UserAppCapSpace::ExceptionEventBase.val(),
-            0,
+            cpu,
             obj.utcb_page_num(),
         )
         .unwrap();
@@ -229,6 +240,7 @@ impl GlobalEcObject {
         pd_obj: &Rc<PdObject>,
         utcb_addr: u64,
         stack_top_ptr: u64,
+        cpu: u64,
     ) -> Rc<Self> {
         assert!(utcb_addr > 0);
         assert_eq!(utcb_addr % PAGE_SIZE as u64, 0);
@@ -238,6 +250,7 @@ impl GlobalEcObject {
             utcb_addr,
             sc: RefCell::new(None),
             stack_top_ptr,
+            cpu,
         };
         let obj = Rc::new(obj);
         pd_obj.attach_global_ec(obj.clone());
@@ -257,6 +270,9 @@ impl GlobalEcObject {
     pub fn utcb_page_num(&self) -> u64 {
         self.utcb_addr / PAGE_SIZE as u64
     }
+    pub fn cpu(&self) -> u64 {
+        self.cpu
+    }
 
     /// Returns a reference to the owned scheduling context, if (already) present.
     pub fn sc(&self) -> Ref<'_, Option<Rc<ScObject>>> {
@@ -4,12 +4,14 @@
 //! PD owns SM and EC objects. Global EC objects own their corresponding SC and local EC
 //! objects own their corresponding PTs.
 
+mod delegations;
 mod ec;
 mod pd;
 mod pt;
 mod sc;
 mod sm;
 
+pub use delegations::*;
 pub use ec::*;
 pub use pd::*;
 pub use pt::*;
@@ -44,7 +46,7 @@ mod tests {
 
         assert!(pd.global_ec().is_none());
 
-        let gl_ec = GlobalEcObject::new(gl_ec_sel, &pd, 0xdeadbeef000, 0x1238);
+        let gl_ec = GlobalEcObject::new(gl_ec_sel, &pd, 0xdeadbeef000, 0x1238, 0);
 
         assert!(pd.global_ec().is_some());
         assert_eq!(
@@ -69,7 +71,7 @@ mod tests {
 
         // now create local ec
         assert!(pd.local_ecs().is_empty());
-        let local_ec_1 = LocalEcObject::new(local_ec_1_sel, &pd, 0xbadf00d, 0x1337000);
+        let local_ec_1 = LocalEcObject::new(local_ec_1_sel, &pd, 0xbadf00d, 0x1337000, 0);
         assert_eq!(local_ec_1.pd().cap_sel(), pd_sel);
 
         assert_eq!(local_ec_1.portals().len(), 0);
@@ -87,7 +89,7 @@ mod tests {
             // now attach 2 portals to the local EC
             let pd2 = PdObject::new(1, Some(&pd), pd_2_sel);
             assert_eq!(pd2.parent().unwrap().cap_sel(), pd_sel);
-            let local_ec_2 = LocalEcObject::new(local_ec_2_sel, &pd2, 0xabcdef, 0x1000);
+            let local_ec_2 = LocalEcObject::new(local_ec_2_sel, &pd2, 0xabcdef, 0x1000, 0);
             assert_eq!(local_ec_2.pd().cap_sel(), pd_2_sel);
         }
 
@@ -104,7 +106,7 @@ mod tests {
         let lec_0_sel = 3;
 
         let pd0 = PdObject::new(ROOTTASK_PROCESS_PID, None, pd_0_sel);
-        let lec0 = LocalEcObject::new(lec_0_sel, &pd0, 0xd000, 0xf000);
+        let lec0 = LocalEcObject::new(lec_0_sel, &pd0, 0xd000, 0xf000, 0);
         let pd1 = PdObject::new(1, None, pd_1_sel);
 
         let pt0 = PtObject::new(
@@ -1,5 +1,8 @@
 use crate::cap_space::user::UserAppCapSpace;
 use crate::kobjects::{
+    revoke_delegated,
+    DelegatedKind,
+    Delegations,
     GlobalEcObject,
     LocalEcObject,
     PortalIdentifier,
@@ -25,6 +28,7 @@ use core::cell::{
     RefCell,
     RefMut,
 };
+use core::ops::Range;
 use libhedron::CapSel;
 
 /// Object that wraps around a kernel PD object with convenient runtime
@@ -51,6 +55,9 @@ pub struct PdObject {
     // I think it's correct to use Rc here. Weak doesn't work (not `Ord`) and as long as
     // the Rc is not cyclic, everything is fine.
     delegated_pts: RefCell<BTreeSet<Rc<PtObject>>>,
+    // All capabilities that the PD received from the PD that created this object. See
+    // [`Self::track_delegation`].
+    delegations: RefCell<Delegations>,
 }
 
 impl PdObject {
@@ -118,6 +125,7 @@ impl PdObject {
             local_ecs: RefCell::new(BTreeSet::new()),
             global_ec: RefCell::new(None),
             delegated_pts: RefCell::new(BTreeSet::new()),
+            delegations: RefCell::new(Delegations::new()),
         })
     }
 
@@ -181,6 +189,27 @@ impl PdObject {
         self.delegated_pts.borrow_mut().insert(pt);
     }
 
+    /// Records that the PD received the capabilities `range` of the capability space of the
+    /// PD that created this object, so that they can be revoked later. Each range may only
+    /// be delegated to this PD, because revoking it removes it from all PDs.
+    pub fn track_delegation(&self, kind: DelegatedKind, range: Range<u64>) {
+        self.delegations.borrow_mut().insert(kind, range);
+    }
+
+    /// Revokes the tracked capabilities of `kind` in `range` from the PD. See
+    /// [`Self::track_delegation`].
+    pub fn revoke_delegations(&self, kind: DelegatedKind, range: Range<u64>) {
+        let removed = self.delegations.borrow_mut().remove(kind, range);
+        for range in removed {
+            revoke_delegated(kind, range);
+        }
+    }
+
+    /// Returns all tracked delegations into this PD.
+    pub fn delegations(&self) -> Ref<Delegations> {
+        self.delegations.borrow()
+    }
+
     /// Iterator over all portals from the PD.
     pub fn portals(&self) -> Vec<Rc<PtObject>> {
         let local_ecs = self.local_ecs.borrow();
@@ -206,6 +235,20 @@ impl PdObject {
     }
 }
 
+/// Revokes all capabilities from `pd` that it received from the PD that created the object,
+/// see [`PdObject::track_delegation`]. Used when the process of the PD stops.
+pub fn revoke_all_from(pd: &PdObject) {
+    let delegations = pd.delegations.borrow_mut().take_all();
+    log::debug!(
+        "revoking {} delegations from pid={}",
+        delegations.len(),
+        pd.pid()
+    );
+    for delegation in delegations {
+        revoke_delegated(delegation.kind, delegation.range);
+    }
+}
+
 impl Drop for PdObject {
     fn drop(&mut self) {
         if self.pid == ROOTTASK_PROCESS_PID {
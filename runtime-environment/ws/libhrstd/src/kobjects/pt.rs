@@ -1,4 +1,5 @@
 use crate::kobjects::{
+    DelegatedKind,
     LocalEcObject,
     PdObject,
 };
@@ -236,6 +237,7 @@ impl PtObject {
         )
         .unwrap();
 
+        target.track_delegation(DelegatedKind::Obj, this.cap_sel()..this.cap_sel() + 1);
         // create bi-directional references
         this.attach_delegated_to_pd(&target);
         target.attach_delegated_pt(this.clone());
@@ -1,10 +1,22 @@
-use crate::kobjects::PdObject;
+use crate::kobjects::{
+    DelegatedKind,
+    PdObject,
+};
 use alloc::rc::{
     Rc,
     Weak,
 };
-use libhedron::syscall::SmCtrlZeroCounterStrategy;
-use libhedron::CapSel;
+use libhedron::syscall::{
+    DelegateFlags,
+    SmCtrlZeroCounterStrategy,
+    SyscallError,
+    SyscallStatus,
+};
+use libhedron::{
+    CapSel,
+    CrdObjSM,
+    SMCapPermissions,
+};
 
 /// A convenient wrapper around the Semaphore (SM) kernel object.
 #[derive(Debug)]
@@ -62,6 +74,46 @@ impl SmObject {
         syscall_fn(self.sel, SmCtrlZeroCounterStrategy::Decrement, None).unwrap();
     }
 
+    /// Performs a "semaphore down" operation that gets aborted by the kernel, when
+    /// the TSC reaches `tsc_deadline`. Returns `true` if the semaphore was acquired
+    /// and `false` if the operation timed out.
+    pub fn sem_down_timeout(&self, tsc_deadline: u64) -> bool {
+        #[cfg(not(feature = "foreign_rust_rt"))]
+        let syscall_fn = crate::libhedron::syscall::sys_sm_down;
+        #[cfg(feature = "foreign_rust_rt")]
+        let syscall_fn = crate::rt::hybrid_rt::syscalls::sys_hybrid_sm_down;
+
+        match syscall_fn(
+            self.sel,
+            SmCtrlZeroCounterStrategy::Decrement,
+            Some(tsc_deadline),
+        ) {
+            Ok(_) => true,
+            Err(SyscallError::HedronStatusError(SyscallStatus::Timeout)) => false,
+            Err(e) => panic!("sm down failed: {:?}", e),
+        }
+    }
+
+    /// Delegates the SM to a given PD at the given selector. The target PD can perform
+    /// "semaphore down" operations on it afterwards.
+    pub fn delegate(&self, target: &Rc<PdObject>, sel: CapSel) {
+        #[cfg(not(feature = "foreign_rust_rt"))]
+        let syscall_fn = crate::libhedron::syscall::sys_pd_ctrl_delegate;
+        #[cfg(feature = "foreign_rust_rt")]
+        let syscall_fn = crate::rt::hybrid_rt::syscalls::sys_hybrid_pd_ctrl_delegate;
+
+        let owning_pd = self.owning_pd.upgrade().unwrap();
+        syscall_fn(
+            owning_pd.cap_sel(),
+            target.cap_sel(),
+            CrdObjSM::new(self.sel, 0, SMCapPermissions::DOWN),
+            CrdObjSM::new(sel, 0, SMCapPermissions::DOWN),
+            DelegateFlags::default(),
+        )
+        .unwrap();
+        target.track_delegation(DelegatedKind::Obj, self.sel..self.sel + 1);
+    }
+
     pub fn sel(&self) -> CapSel {
         self.sel
     }
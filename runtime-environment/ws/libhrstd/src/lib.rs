@@ -50,6 +50,7 @@ pub use libhedron;
 
 #[macro_use]
 pub mod util;
+pub mod build_info;
 pub mod cap_space;
 pub mod cstr;
 #[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
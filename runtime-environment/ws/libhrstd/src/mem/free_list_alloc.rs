@@ -0,0 +1,495 @@
+use arrayvec::ArrayVec;
+use core::alloc::Layout;
+use core::mem::size_of;
+use core::ops::Range;
+use core::ptr::NonNull;
+
+/// Size and alignment of all blocks of a [`FreeListAllocator`]. Each free block holds a
+/// [`FreeBlock`] header, hence this is the minimum size of a block.
+pub const FREE_LIST_BLOCK_ALIGN: usize = 16;
+
+/// Maximum number of regions that a [`FreeListAllocator`] remembers. Memory of further
+/// regions works as well but never gets released, see
+/// [`FreeListAllocator::release_free_region`].
+pub const FREE_LIST_MAX_REGIONS: usize = 64;
+
+/// Header at the begin of every free block.
+#[repr(C, align(16))]
+struct FreeBlock {
+    /// Size of the block in bytes, including this header.
+    size: usize,
+    /// The next free block at a higher address.
+    next: Option<NonNull<FreeBlock>>,
+}
+
+/// Allocator that manages the memory of one or more regions with a linked list of free
+/// blocks. The list is sorted by address; freed blocks merge with their neighbours. Takes
+/// the first free block that fits (first fit).
+///
+/// The headers of the free blocks live in the free memory itself. The allocator only
+/// touches memory when it hands it out or gets it back, hence regions can be backed by
+/// memory lazily, page by page.
+///
+/// All blocks are multiples of [`FREE_LIST_BLOCK_ALIGN`], so that the remainders of a
+/// block are always big enough for a header and every allocation can be freed without
+/// knowing more than its layout.
+///
+/// Regions that are completely free can be taken out of the allocator again, so that their
+/// owner can release the memory.
+#[derive(Debug)]
+pub struct FreeListAllocator {
+    head: Option<NonNull<FreeBlock>>,
+    /// The regions as passed to [`Self::add_region`].
+    regions: ArrayVec<Range<usize>, FREE_LIST_MAX_REGIONS>,
+    /// Bytes of all regions.
+    total_bytes: usize,
+    /// Bytes that are currently allocated, including the padding of the blocks.
+    used_bytes: usize,
+}
+
+// the allocator owns the memory of the regions
+unsafe impl Send for FreeListAllocator {}
+
+impl FreeListAllocator {
+    pub const fn new() -> Self {
+        Self {
+            head: None,
+            regions: ArrayVec::new_const(),
+            total_bytes: 0,
+            used_bytes: 0,
+        }
+    }
+
+    /// Adds the memory `start..start + size` to the allocator. Parts that don't fill a
+    /// whole block at the begin and at the end stay unused.
+    ///
+    /// # Safety
+    /// The memory must be valid for reads and writes, must not overlap other regions, and
+    /// must not be used by anyone else as long as the allocator lives.
+    pub unsafe fn add_region(&mut self, start: *mut u8, size: usize) {
+        let region = start as usize..start as usize + size;
+        let usable = Self::usable_range(&region);
+        if usable.is_empty() {
+            return;
+        }
+        // without space in the table, the region just never gets released
+        let _ = self.regions.try_push(region);
+        self.total_bytes += usable.len();
+        self.insert(usable.start, usable.len());
+    }
+
+    /// Returns the part of a region that consists of whole blocks.
+    fn usable_range(region: &Range<usize>) -> Range<usize> {
+        let begin = (region.start + FREE_LIST_BLOCK_ALIGN - 1) & !(FREE_LIST_BLOCK_ALIGN - 1);
+        let end = region.end & !(FREE_LIST_BLOCK_ALIGN - 1);
+        begin..end.max(begin)
+    }
+
+    /// Takes a region out of the allocator that contains no allocation anymore. Returns
+    /// the region as it was passed to [`Self::add_region`], or `None` if every region is
+    /// still in use. The caller owns the memory afterwards and can release it.
+    pub fn release_free_region(&mut self) -> Option<(NonNull<u8>, usize)> {
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut cur = self.head;
+        while let Some(block) = cur {
+            let (block_begin, block_size, next) = unsafe {
+                let block_ref = block.as_ref();
+                (block.as_ptr() as usize, block_ref.size, block_ref.next)
+            };
+            let block_end = block_begin + block_size;
+            // neighbouring regions may share a free block
+            let index = self.regions.iter().position(|region| {
+                let usable = Self::usable_range(region);
+                block_begin <= usable.start && usable.end <= block_end
+            });
+            let index = match index {
+                Some(index) => index,
+                None => {
+                    prev = cur;
+                    cur = next;
+                    continue;
+                }
+            };
+
+            let region = self.regions.swap_remove(index);
+            let usable = Self::usable_range(&region);
+            match prev {
+                None => self.head = next,
+                Some(mut prev) => unsafe { prev.as_mut().next = next },
+            }
+            if usable.start > block_begin {
+                self.insert(block_begin, usable.start - block_begin);
+            }
+            if block_end > usable.end {
+                self.insert(usable.end, block_end - usable.end);
+            }
+            self.total_bytes -= usable.len();
+            return NonNull::new(region.start as *mut u8).map(|ptr| (ptr, region.len()));
+        }
+        None
+    }
+
+    /// Returns the size of the block that an allocation with `layout` occupies.
+    fn block_size(layout: Layout) -> usize {
+        let size = layout.size().max(FREE_LIST_BLOCK_ALIGN);
+        (size + FREE_LIST_BLOCK_ALIGN - 1) & !(FREE_LIST_BLOCK_ALIGN - 1)
+    }
+
+    /// Allocates memory for `layout`. Returns `None` if no free block fits.
+    pub fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let size = Self::block_size(layout);
+        let align = layout.align().max(FREE_LIST_BLOCK_ALIGN);
+
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut cur = self.head;
+        while let Some(block) = cur {
+            let (block_begin, block_size, next) = unsafe {
+                let block_ref = block.as_ref();
+                (block.as_ptr() as usize, block_ref.size, block_ref.next)
+            };
+            let block_end = block_begin + block_size;
+            let alloc_begin = (block_begin + align - 1) & !(align - 1);
+            if alloc_begin + size > block_end {
+                prev = cur;
+                cur = next;
+                continue;
+            }
+
+            // unlink the block; the remainders before and after the allocation become new
+            // free blocks, both are multiples of the block alignment
+            match prev {
+                None => self.head = next,
+                Some(mut prev) => unsafe { prev.as_mut().next = next },
+            }
+            let alloc_end = alloc_begin + size;
+            if alloc_begin > block_begin {
+                self.insert(block_begin, alloc_begin - block_begin);
+            }
+            if block_end > alloc_end {
+                self.insert(alloc_end, block_end - alloc_end);
+            }
+            self.used_bytes += size;
+            return NonNull::new(alloc_begin as *mut u8);
+        }
+        None
+    }
+
+    /// Returns the memory of an allocation.
+    ///
+    /// # Safety
+    /// `ptr` must come from [`Self::allocate`] of this allocator with the same `layout`.
+    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        let size = Self::block_size(layout);
+        self.used_bytes -= size;
+        self.insert(ptr.as_ptr() as usize, size);
+    }
+
+    /// Resizes an allocation to `new_size` bytes without moving it. Shrinking always works;
+    /// growing only works if the memory right behind the allocation is free. Returns false
+    /// if the allocation must move.
+    ///
+    /// # Safety
+    /// `ptr` must come from [`Self::allocate`] of this allocator with the same `layout`. On
+    /// success, the allocation has the layout with `new_size` afterwards.
+    pub unsafe fn reallocate_in_place(
+        &mut self,
+        ptr: NonNull<u8>,
+        layout: Layout,
+        new_size: usize,
+    ) -> bool {
+        let begin = ptr.as_ptr() as usize;
+        let old_size = Self::block_size(layout);
+        let new_size = Self::block_size(Layout::from_size_align_unchecked(new_size, 1));
+        if new_size <= old_size {
+            if new_size < old_size {
+                self.used_bytes -= old_size - new_size;
+                self.insert(begin + new_size, old_size - new_size);
+            }
+            return true;
+        }
+
+        // look for a free block that starts at the end of the allocation
+        let end = begin + old_size;
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut cur = self.head;
+        while let Some(block) = cur {
+            if block.as_ptr() as usize >= end {
+                break;
+            }
+            prev = cur;
+            cur = block.as_ref().next;
+        }
+        let (block_size, next) = match cur {
+            Some(block) if block.as_ptr() as usize == end => {
+                (block.as_ref().size, block.as_ref().next)
+            }
+            _ => return false,
+        };
+        let missing = new_size - old_size;
+        if block_size < missing {
+            return false;
+        }
+
+        match prev {
+            None => self.head = next,
+            Some(mut prev) => prev.as_mut().next = next,
+        }
+        if block_size > missing {
+            self.insert(begin + new_size, block_size - missing);
+        }
+        self.used_bytes += missing;
+        true
+    }
+
+    /// Inserts a free block into the sorted list and merges it with its neighbours.
+    fn insert(&mut self, begin: usize, size: usize) {
+        debug_assert_eq!(begin % FREE_LIST_BLOCK_ALIGN, 0);
+        debug_assert_eq!(size % FREE_LIST_BLOCK_ALIGN, 0);
+        debug_assert!(size >= size_of::<FreeBlock>());
+
+        // the last free block below the new one
+        let mut prev: Option<NonNull<FreeBlock>> = None;
+        let mut next = self.head;
+        while let Some(block) = next {
+            if block.as_ptr() as usize > begin {
+                break;
+            }
+            prev = next;
+            next = unsafe { block.as_ref().next };
+        }
+
+        unsafe {
+            let mut block = NonNull::new(begin as *mut FreeBlock).unwrap();
+            block.as_ptr().write(FreeBlock { size, next });
+            // merge with the next block
+            if let Some(next) = next {
+                if begin + size == next.as_ptr() as usize {
+                    let next = next.as_ref();
+                    block.as_mut().size += next.size;
+                    block.as_mut().next = next.next;
+                }
+            }
+            // merge with the previous block
+            match prev {
+                Some(mut prev) if prev.as_ptr() as usize + prev.as_ref().size == begin => {
+                    prev.as_mut().size += block.as_ref().size;
+                    prev.as_mut().next = block.as_ref().next;
+                }
+                Some(mut prev) => prev.as_mut().next = Some(block),
+                None => self.head = Some(block),
+            }
+        }
+    }
+
+    /// Bytes of all regions.
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Bytes that are currently allocated.
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    /// Number of regions that the allocator remembers.
+    pub fn region_count(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Number of free blocks. High numbers mean high fragmentation.
+    pub fn free_block_count(&self) -> usize {
+        let mut count = 0;
+        let mut cur = self.head;
+        while let Some(block) = cur {
+            count += 1;
+            cur = unsafe { block.as_ref().next };
+        }
+        count
+    }
+}
+
+impl Default for FreeListAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+
+    #[derive(Copy, Clone)]
+    #[repr(align(16))]
+    struct Chunk([u8; FREE_LIST_BLOCK_ALIGN]);
+
+    /// A page-aligned region, so that the offsets of aligned allocations are predictable.
+    #[repr(align(4096))]
+    struct Page([u8; 4096]);
+
+    fn region(bytes: usize) -> Vec<Chunk> {
+        vec![Chunk([0; FREE_LIST_BLOCK_ALIGN]); bytes / FREE_LIST_BLOCK_ALIGN]
+    }
+
+    #[test]
+    fn test_free_list_alloc() {
+        let mut mem = Box::new(Page([0; 4096]));
+        let mut alloc = FreeListAllocator::new();
+        unsafe { alloc.add_region(mem.0.as_mut_ptr(), 4096) };
+        assert_eq!(alloc.total_bytes(), 4096);
+
+        let small = Layout::from_size_align(3, 1).unwrap();
+        let a = alloc.allocate(small).unwrap();
+        let b = alloc.allocate(small).unwrap();
+        assert_eq!(
+            b.as_ptr() as usize - a.as_ptr() as usize,
+            FREE_LIST_BLOCK_ALIGN
+        );
+        assert_eq!(alloc.used_bytes(), 2 * FREE_LIST_BLOCK_ALIGN);
+
+        let aligned = Layout::from_size_align(64, 256).unwrap();
+        let c = alloc.allocate(aligned).unwrap();
+        assert_eq!(c.as_ptr() as usize % 256, 0);
+        unsafe { c.as_ptr().write_bytes(0xff, 64) };
+
+        // the gap before the aligned block gets used first
+        let d = alloc.allocate(small).unwrap();
+        assert!(d.as_ptr() < c.as_ptr());
+
+        assert!(alloc
+            .allocate(Layout::from_size_align(8192, 8).unwrap())
+            .is_none());
+
+        unsafe {
+            alloc.deallocate(b, small);
+            alloc.deallocate(a, small);
+            alloc.deallocate(c, aligned);
+            alloc.deallocate(d, small);
+        }
+        assert_eq!(alloc.used_bytes(), 0);
+        assert_eq!(alloc.free_block_count(), 1, "all blocks merge again");
+        let all = Layout::from_size_align(4096, 16).unwrap();
+        let e = alloc.allocate(all).unwrap();
+        assert_eq!(e.as_ptr(), mem.0.as_mut_ptr());
+    }
+
+    #[test]
+    fn test_free_list_alloc_regions() {
+        // two regions with a gap in between
+        let mut mem = region(8192);
+        let first = mem.as_mut_ptr().cast::<u8>();
+        let second = unsafe { first.add(2048) };
+        let mut alloc = FreeListAllocator::new();
+        // unaligned parts get dropped
+        unsafe { alloc.add_region(first.add(1), 1023) };
+        assert_eq!(alloc.total_bytes(), 1024 - FREE_LIST_BLOCK_ALIGN);
+
+        let big = Layout::from_size_align(2048, 8).unwrap();
+        assert!(alloc.allocate(big).is_none());
+        unsafe { alloc.add_region(second, 4096) };
+        let ptr = alloc.allocate(big).unwrap();
+        assert_eq!(ptr.as_ptr(), second);
+
+        let layouts = (1..20)
+            .map(|i| Layout::from_size_align(i * 7, 8).unwrap())
+            .collect::<Vec<_>>();
+        let ptrs = layouts
+            .iter()
+            .map(|layout| alloc.allocate(*layout).unwrap())
+            .collect::<Vec<_>>();
+        // free every second block first to fragment the memory
+        for (i, (ptr, layout)) in ptrs.iter().zip(&layouts).enumerate() {
+            if i % 2 == 0 {
+                unsafe { alloc.deallocate(*ptr, *layout) };
+            }
+        }
+        assert!(alloc.free_block_count() > 2);
+        for (i, (ptr, layout)) in ptrs.iter().zip(&layouts).enumerate() {
+            if i % 2 == 1 {
+                unsafe { alloc.deallocate(*ptr, *layout) };
+            }
+        }
+        unsafe { alloc.deallocate(ptr, big) };
+        assert_eq!(alloc.used_bytes(), 0);
+        assert_eq!(alloc.free_block_count(), 2, "one block per region");
+    }
+
+    #[test]
+    fn test_free_list_alloc_realloc() {
+        let mut mem = region(1024);
+        let mut alloc = FreeListAllocator::new();
+        unsafe { alloc.add_region(mem.as_mut_ptr().cast(), 1024) };
+
+        let layout = Layout::from_size_align(100, 8).unwrap();
+        let a = alloc.allocate(layout).unwrap();
+        unsafe {
+            a.as_ptr().write_bytes(0xab, 100);
+            // the rest of the region follows the allocation
+            assert!(alloc.reallocate_in_place(a, layout, 500));
+            assert_eq!(alloc.used_bytes(), 512);
+            assert_eq!(*a.as_ptr().add(99), 0xab);
+            let layout = Layout::from_size_align(500, 8).unwrap();
+            assert!(alloc.reallocate_in_place(a, layout, 40));
+            assert_eq!(alloc.used_bytes(), 48);
+            assert_eq!(
+                alloc.free_block_count(),
+                1,
+                "the tail merges with the free block"
+            );
+
+            let layout = Layout::from_size_align(40, 8).unwrap();
+            let b = alloc.allocate(layout).unwrap();
+            assert_eq!(b.as_ptr(), a.as_ptr().add(48));
+            // blocked by b
+            assert!(!alloc.reallocate_in_place(a, layout, 64));
+            assert!(!alloc.reallocate_in_place(b, layout, 2048));
+            assert!(alloc.reallocate_in_place(b, layout, 900));
+            assert_eq!(alloc.free_block_count(), 1);
+
+            alloc.deallocate(a, layout);
+            alloc.deallocate(b, Layout::from_size_align(900, 8).unwrap());
+        }
+        assert_eq!(alloc.used_bytes(), 0);
+        assert_eq!(alloc.free_block_count(), 1);
+    }
+
+    #[test]
+    fn test_free_list_alloc_release_regions() {
+        // two adjacent regions share free blocks
+        let mut mem = region(4096);
+        let first = mem.as_mut_ptr().cast::<u8>();
+        let second = unsafe { first.add(2048) };
+        let mut alloc = FreeListAllocator::new();
+        unsafe {
+            alloc.add_region(first, 2048);
+            alloc.add_region(second, 2048);
+        }
+        assert_eq!(alloc.free_block_count(), 1);
+
+        // spans both regions
+        let big = Layout::from_size_align(3000, 8).unwrap();
+        let ptr = alloc.allocate(big).unwrap();
+        assert!(alloc.release_free_region().is_none());
+        unsafe { alloc.deallocate(ptr, big) };
+
+        let small = Layout::from_size_align(16, 8).unwrap();
+        let ptr = alloc.allocate(small).unwrap();
+        assert_eq!(ptr.as_ptr(), first);
+        let (released, size) = alloc.release_free_region().unwrap();
+        assert_eq!((released.as_ptr(), size), (second, 2048));
+        assert!(alloc.release_free_region().is_none());
+        assert_eq!(alloc.total_bytes(), 2048);
+        assert_eq!(alloc.region_count(), 1);
+        // the released memory is gone
+        assert!(alloc
+            .allocate(Layout::from_size_align(2048, 8).unwrap())
+            .is_none());
+
+        unsafe { alloc.deallocate(ptr, small) };
+        let (released, _) = alloc.release_free_region().unwrap();
+        assert_eq!(released.as_ptr(), first);
+        assert_eq!(alloc.total_bytes(), 0);
+        assert_eq!(alloc.free_block_count(), 0);
+    }
+}
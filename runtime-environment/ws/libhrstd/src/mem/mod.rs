@@ -1,7 +1,9 @@
 mod aligned;
+mod free_list_alloc;
 mod usr_ptr_or_embedded;
 
 pub use aligned::*;
+pub use free_list_alloc::*;
 use libhedron::mem::PAGE_SIZE;
 pub use usr_ptr_or_embedded::*;
 
@@ -0,0 +1,129 @@
+//! Startup protocol for the arguments and the environment variables of native Hedron
+//! apps. Linux apps get them on the initial stack instead.
+//!
+//! Before a native app starts, the roottask writes an [`ArgsBlock`] to
+//! [`crate::uaddress_space::USER_ARGS_ADDR`]. The layout is:
+//!
+//! | Offset | Content                                              |
+//! |--------|------------------------------------------------------|
+//! | 0      | [`ARGS_BLOCK_MAGIC`] (u64, LE)                       |
+//! | 8      | number of arguments (u64, LE)                        |
+//! | 16     | number of environment variables (u64, LE)            |
+//! | 24     | all arguments, then all variables; each null-terminated |
+
+use core::mem::size_of;
+
+/// Marks a valid [`ArgsBlock`].
+pub const ARGS_BLOCK_MAGIC: u64 = u64::from_le_bytes(*b"HRARGS\0\x01");
+
+/// Size of the header in front of the strings.
+const HEADER_SIZE: usize = 3 * size_of::<u64>();
+
+/// Read-only view of the arguments and environment variables in the memory of a native
+/// app. See the module description.
+#[derive(Debug, Copy, Clone)]
+pub struct ArgsBlock<'a> {
+    argc: usize,
+    envc: usize,
+    strings: &'a [u8],
+}
+
+impl<'a> ArgsBlock<'a> {
+    /// Writes the arguments and the environment variables into `buf`. Returns the number
+    /// of written bytes or an error if `buf` is too small. The strings must not contain
+    /// null bytes.
+    pub fn write<S: AsRef<str>>(buf: &mut [u8], argv: &[S], envp: &[S]) -> Result<usize, ()> {
+        if buf.len() < HEADER_SIZE {
+            return Err(());
+        }
+        let mut pos = HEADER_SIZE;
+        for string in argv.iter().chain(envp.iter()) {
+            let bytes = string.as_ref().as_bytes();
+            debug_assert!(!bytes.contains(&0), "null bytes are not allowed");
+            let end = pos + bytes.len() + 1;
+            if end > buf.len() {
+                return Err(());
+            }
+            buf[pos..end - 1].copy_from_slice(bytes);
+            buf[end - 1] = 0;
+            pos = end;
+        }
+        buf[0..8].copy_from_slice(&ARGS_BLOCK_MAGIC.to_le_bytes());
+        buf[8..16].copy_from_slice(&(argv.len() as u64).to_le_bytes());
+        buf[16..24].copy_from_slice(&(envp.len() as u64).to_le_bytes());
+        Ok(pos)
+    }
+
+    /// Parses the block at the beginning of `buf`. Returns `None` if there is no valid
+    /// block.
+    pub fn parse(buf: &'a [u8]) -> Option<Self> {
+        let read_u64 = |offset: usize| {
+            buf.get(offset..offset + 8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        };
+        if read_u64(0)? != ARGS_BLOCK_MAGIC {
+            return None;
+        }
+        let block = Self {
+            argc: read_u64(8)? as usize,
+            envc: read_u64(16)? as usize,
+            strings: &buf[HEADER_SIZE..],
+        };
+        // each string needs at least its null byte
+        let terminated = block.strings.iter().filter(|b| **b == 0).count();
+        (terminated >= block.argc + block.envc).then(|| block)
+    }
+
+    /// Returns the arguments. By convention, the first one is the program name.
+    pub fn args(&self) -> impl Iterator<Item = &'a str> {
+        self.strings().take(self.argc)
+    }
+
+    /// Returns the environment variables as pairs of key and value. Variables without
+    /// a `=` have an empty value.
+    pub fn vars(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.strings()
+            .skip(self.argc)
+            .take(self.envc)
+            .map(|var| var.split_once('=').unwrap_or((var, "")))
+    }
+
+    /// Returns all strings. Invalid UTF-8 becomes an empty string.
+    fn strings(&self) -> impl Iterator<Item = &'a str> {
+        self.strings
+            .split(|b| *b == 0)
+            .map(|bytes| core::str::from_utf8(bytes).unwrap_or(""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_args_block() {
+        let mut buf = [0; 128];
+        let len = ArgsBlock::write(&mut buf, &["/bin/foo", "-v"], &["FOO=BAR", "EMPTY"]).unwrap();
+        assert_eq!(len, HEADER_SIZE + "/bin/foo-vFOO=BAREMPTY".len() + 4);
+
+        let block = ArgsBlock::parse(&buf).unwrap();
+        assert_eq!(block.args().collect::<Vec<_>>(), ["/bin/foo", "-v"]);
+        assert_eq!(
+            block.vars().collect::<Vec<_>>(),
+            [("FOO", "BAR"), ("EMPTY", "")]
+        );
+
+        let empty: [&str; 0] = [];
+        let mut buf = [0; HEADER_SIZE];
+        ArgsBlock::write(&mut buf, &empty, &empty).unwrap();
+        let block = ArgsBlock::parse(&buf).unwrap();
+        assert_eq!(block.args().count(), 0);
+        assert_eq!(block.vars().count(), 0);
+
+        // too small
+        assert!(ArgsBlock::write(&mut [0; 30], &["/bin/foo"], &["FOO=BAR"]).is_err());
+        // no magic
+        assert!(ArgsBlock::parse(&[0; 64]).is_none());
+    }
+}
@@ -11,3 +11,12 @@ pub const ROOTTASK_PROCESS_PID: ProcessId = 0;
 
 /// Max number of supported processes.
 pub const NUM_PROCESSES: u64 = 2_u64.pow(6);
+
+/// Environment variable that the roottask sets for Linux programs. Hybrid apps check it
+/// to detect that they run under Hedron, see `hybrid_rt::runs_under_hedron`.
+pub const LINUX_UNDER_HEDRON_ENV_VAR: &str = "LINUX_UNDER_HEDRON";
+
+/// Value of the `AT_IGNORE` entry that the roottask adds to the aux vector of Linux
+/// programs. Unlike [`LINUX_UNDER_HEDRON_ENV_VAR`], the program can't clear it. The value
+/// is "HEDRON" in ASCII.
+pub const HEDRON_AUX_VECTOR_MAGIC: u64 = 0x4845_4452_4f4e;
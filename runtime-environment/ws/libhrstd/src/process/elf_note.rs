@@ -0,0 +1,136 @@
+//! ELF notes. The native runtime of libhrstd embeds a [`HedronAbiNote`] into each
+//! binary. The roottask looks for it to detect the syscall ABI of a program.
+//!
+//! Native binaries must keep the section [`HEDRON_NOTE_SECTION`] in their linker script.
+
+use core::mem::size_of;
+
+/// Name of the section that holds the [`HedronAbiNote`].
+pub const HEDRON_NOTE_SECTION: &str = ".note.hedron";
+
+/// Name (owner) of the [`HedronAbiNote`], including the terminating null byte.
+pub const HEDRON_NOTE_NAME: &[u8] = b"Hedron\0";
+
+/// Type of the [`HedronAbiNote`].
+pub const NT_HEDRON_NATIVE_ABI: u32 = 1;
+
+/// Version of the native syscall ABI. Descriptor of the [`HedronAbiNote`].
+pub const HEDRON_NATIVE_ABI_VERSION: u32 = 1;
+
+/// ELF note that marks a binary as native Hedron app. The layout follows the ELF
+/// specification: a header, followed by the name and the descriptor, each padded to
+/// four bytes.
+#[derive(Debug)]
+#[repr(C, align(4))]
+pub struct HedronAbiNote {
+    namesz: u32,
+    descsz: u32,
+    n_type: u32,
+    name: [u8; 8],
+    desc: u32,
+}
+
+impl HedronAbiNote {
+    pub const fn new() -> Self {
+        let mut name = [0; 8];
+        let mut i = 0;
+        while i < HEDRON_NOTE_NAME.len() {
+            name[i] = HEDRON_NOTE_NAME[i];
+            i += 1;
+        }
+        Self {
+            namesz: HEDRON_NOTE_NAME.len() as u32,
+            descsz: size_of::<u32>() as u32,
+            n_type: NT_HEDRON_NATIVE_ABI,
+            name,
+            desc: HEDRON_NATIVE_ABI_VERSION,
+        }
+    }
+}
+
+impl Default for HedronAbiNote {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single note of a `PT_NOTE` segment or a `SHT_NOTE` section.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ElfNote<'a> {
+    /// Name (owner) of the note, including the terminating null byte.
+    pub name: &'a [u8],
+    pub n_type: u32,
+    pub desc: &'a [u8],
+}
+
+impl<'a> ElfNote<'a> {
+    /// Checks if this is a [`HedronAbiNote`].
+    pub fn is_hedron_abi_note(&self) -> bool {
+        self.name == HEDRON_NOTE_NAME && self.n_type == NT_HEDRON_NATIVE_ABI
+    }
+
+    /// Parses all notes of a note segment or section. Stops at the first malformed note.
+    pub fn parse_all(bytes: &'a [u8]) -> impl Iterator<Item = ElfNote<'a>> {
+        let mut remaining = bytes;
+        core::iter::from_fn(move || {
+            let (note, len) = Self::parse(remaining)?;
+            remaining = &remaining[len..];
+            Some(note)
+        })
+    }
+
+    /// Parses a single note. Returns the note and the number of bytes it occupies.
+    fn parse(bytes: &'a [u8]) -> Option<(Self, usize)> {
+        const HEADER_SIZE: usize = 3 * size_of::<u32>();
+        let read_u32 = |index: usize| {
+            let bytes = bytes.get(index * size_of::<u32>()..(index + 1) * size_of::<u32>())?;
+            Some(u32::from_ne_bytes(bytes.try_into().unwrap()) as usize)
+        };
+        let align = |len: usize| (len + 3) & !3;
+
+        let namesz = read_u32(0)?;
+        let descsz = read_u32(1)?;
+        let n_type = read_u32(2)? as u32;
+        let desc_offset = HEADER_SIZE + align(namesz);
+        let len = desc_offset + align(descsz);
+        let note = Self {
+            name: bytes.get(HEADER_SIZE..HEADER_SIZE + namesz)?,
+            n_type,
+            desc: bytes.get(desc_offset..desc_offset + descsz)?,
+        };
+        Some((note, len.min(bytes.len())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hedron_abi_note() {
+        let note = HedronAbiNote::new();
+        let note_bytes = unsafe {
+            core::slice::from_raw_parts(
+                (&note as *const HedronAbiNote).cast::<u8>(),
+                size_of::<HedronAbiNote>(),
+            )
+        };
+        // a GNU ABI tag note in front of the Hedron note
+        let mut bytes = std::vec::Vec::new();
+        for word in [4_u32, 16, 1] {
+            bytes.extend_from_slice(&word.to_ne_bytes());
+        }
+        bytes.extend_from_slice(b"GNU\0");
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(note_bytes);
+
+        let notes = ElfNote::parse_all(&bytes).collect::<std::vec::Vec<_>>();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].name, b"GNU\0");
+        assert!(!notes[0].is_hedron_abi_note());
+        assert!(notes[1].is_hedron_abi_note());
+        assert_eq!(notes[1].desc, HEDRON_NATIVE_ABI_VERSION.to_ne_bytes());
+
+        assert_eq!(ElfNote::parse_all(&bytes[..20]).count(), 0, "truncated");
+    }
+}
@@ -1 +1,3 @@
+pub mod args_block;
 pub mod consts;
+pub mod elf_note;
@@ -0,0 +1,31 @@
+//! Arguments and environment variables of native Hedron apps, similar to `std::env`.
+//! The roottask provides them at [`USER_ARGS_ADDR`] before the app starts. See
+//! [`crate::process::args_block`].
+
+use crate::process::args_block::ArgsBlock;
+use crate::uaddress_space::{
+    USER_ARGS_ADDR,
+    USER_ARGS_SIZE,
+};
+
+/// Returns the arguments of the app. By convention, the first one is the program name.
+pub fn args() -> impl Iterator<Item = &'static str> {
+    args_block().into_iter().flat_map(|block| block.args())
+}
+
+/// Returns the environment variables of the app as pairs of key and value.
+pub fn vars() -> impl Iterator<Item = (&'static str, &'static str)> {
+    args_block().into_iter().flat_map(|block| block.vars())
+}
+
+/// Returns the value of the environment variable `key`.
+pub fn var(key: &str) -> Option<&'static str> {
+    vars().find(|(k, _)| *k == key).map(|(_, value)| value)
+}
+
+/// Returns the block that the roottask mapped into the address space. It stays mapped
+/// and unchanged during the whole lifetime of the app.
+fn args_block() -> Option<ArgsBlock<'static>> {
+    let mem = unsafe { core::slice::from_raw_parts(USER_ARGS_ADDR as *const u8, USER_ARGS_SIZE) };
+    ArgsBlock::parse(mem)
+}
@@ -0,0 +1,102 @@
+//! File I/O of hybrid apps on Linux file descriptors. Under Hedron, the Linux syscalls
+//! and the file system service share the file descriptors of the process, because the
+//! roottask serves both from the same file system. Hence, a file that the app opened with
+//! `std::fs::File` works on both paths and the app can switch between them per operation.
+//!
+//! The native path only works for files, not for sockets or the console.
+
+use crate::fs::File;
+use crate::rt::hybrid_rt::linux::{
+    linux_syscall3,
+    SYS_LSEEK,
+    SYS_READ,
+    SYS_WRITE,
+};
+use crate::rt::hybrid_rt::{
+    hybrid_path,
+    HybridPath,
+};
+use crate::rt::services::fs::{
+    FsError,
+    FD,
+};
+
+/// Same as `SEEK_SET` of Linux.
+const SEEK_SET: u64 = 0;
+
+/// Error of a hybrid file operation, depending on the path that it took.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HybridFsError {
+    /// The Linux syscall failed with the error number (`errno`).
+    Linux(i32),
+    /// The file system service failed.
+    Native(FsError),
+}
+
+/// Reads at most `buf.len()` bytes at the file offset, on the path of [`hybrid_path`].
+/// Returns the number of read bytes.
+pub fn read(fd: i32, buf: &mut [u8]) -> Result<usize, HybridFsError> {
+    read_via(hybrid_path(), fd, buf)
+}
+
+/// Like [`read`] but on the given path.
+pub fn read_via(path: HybridPath, fd: i32, buf: &mut [u8]) -> Result<usize, HybridFsError> {
+    match path {
+        HybridPath::Linux => unsafe {
+            linux_syscall3(
+                SYS_READ,
+                fd as u64,
+                buf.as_mut_ptr() as u64,
+                buf.len() as u64,
+            )
+        }
+        .map(|count| count as usize)
+        .map_err(HybridFsError::Linux),
+        HybridPath::Native => File::from_fd(FD::new(fd))
+            .read(buf)
+            .map_err(HybridFsError::Native),
+    }
+}
+
+/// Writes the bytes at the file offset, on the path of [`hybrid_path`]. Returns the
+/// number of written bytes.
+pub fn write(fd: i32, bytes: &[u8]) -> Result<usize, HybridFsError> {
+    write_via(hybrid_path(), fd, bytes)
+}
+
+/// Like [`write`] but on the given path.
+pub fn write_via(path: HybridPath, fd: i32, bytes: &[u8]) -> Result<usize, HybridFsError> {
+    match path {
+        HybridPath::Linux => unsafe {
+            linux_syscall3(
+                SYS_WRITE,
+                fd as u64,
+                bytes.as_ptr() as u64,
+                bytes.len() as u64,
+            )
+        }
+        .map(|count| count as usize)
+        .map_err(HybridFsError::Linux),
+        HybridPath::Native => File::from_fd(FD::new(fd))
+            .write_all(bytes)
+            .map_err(HybridFsError::Native),
+    }
+}
+
+/// Sets the file offset to `offset` bytes from the beginning of the file, on the path of
+/// [`hybrid_path`].
+pub fn seek(fd: i32, offset: u64) -> Result<(), HybridFsError> {
+    seek_via(hybrid_path(), fd, offset)
+}
+
+/// Like [`seek`] but on the given path.
+pub fn seek_via(path: HybridPath, fd: i32, offset: u64) -> Result<(), HybridFsError> {
+    match path {
+        HybridPath::Linux => unsafe { linux_syscall3(SYS_LSEEK, fd as u64, offset, SEEK_SET) }
+            .map(|_| ())
+            .map_err(HybridFsError::Linux),
+        HybridPath::Native => File::from_fd(FD::new(fd))
+            .lseek(offset)
+            .map_err(HybridFsError::Native),
+    }
+}
@@ -0,0 +1,38 @@
+//! Raw Linux syscalls for the Linux path of hybrid apps. Under Hedron, they are foreign
+//! syscalls that the roottask handles. The lib doesn't depend on a libc for them.
+
+use core::arch::asm;
+
+/// Linux syscall number of `read`.
+pub const SYS_READ: u64 = 0;
+/// Linux syscall number of `write`.
+pub const SYS_WRITE: u64 = 1;
+/// Linux syscall number of `lseek`.
+pub const SYS_LSEEK: u64 = 8;
+
+/// Performs a Linux syscall with three arguments. Returns the result or the error number
+/// (`errno`) of the failed syscall.
+///
+/// # Safety
+/// The arguments must be valid for the syscall, e.g. pointers must point to memory of the
+/// given length.
+pub unsafe fn linux_syscall3(num: u64, arg0: u64, arg1: u64, arg2: u64) -> Result<u64, i32> {
+    let ret: i64;
+    asm!(
+        "syscall",
+        inlateout("rax") num as i64 => ret,
+        in("rdi") arg0,
+        in("rsi") arg1,
+        in("rdx") arg2,
+        // the syscall instruction clobbers them
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
+    );
+    // Linux returns the negated errno in the range of -4095 to -1
+    if (-4095..0).contains(&ret) {
+        Err(-ret as i32)
+    } else {
+        Ok(ret as u64)
+    }
+}
@@ -1 +1,17 @@
+//! Runtime of hybrid foreign apps: Linux programs that run natively under Linux as well as
+//! under Hedron. They use Linux syscalls by default, but when they detect that they run
+//! under Hedron, they can take the Hedron-native path for selected hot paths, e.g. file
+//! I/O with [`fs`] or IPC with the service portals. The same binary works in both
+//! environments and can compare both paths in the same process.
+
+pub mod fs;
+mod linux;
+mod path;
 pub mod syscalls;
+
+pub use path::{
+    force_hybrid_path,
+    hybrid_path,
+    runs_under_hedron,
+    HybridPath,
+};
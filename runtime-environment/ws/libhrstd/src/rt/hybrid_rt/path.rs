@@ -0,0 +1,100 @@
+//! Detects at runtime if a hybrid app runs under Hedron and selects the path of the
+//! operations that hybrid apps can do either way.
+//!
+//! The roottask marks Linux programs with the [`LINUX_UNDER_HEDRON_ENV_VAR`] environment
+//! variable and an `AT_IGNORE` entry with [`HEDRON_AUX_VECTOR_MAGIC`] in the aux vector.
+//! The libc of the program gives access to both, hence hybrid apps must link a libc that
+//! provides `getauxval()` and `getenv()`, such as glibc or musl.
+
+use crate::process::consts::{
+    HEDRON_AUX_VECTOR_MAGIC,
+    LINUX_UNDER_HEDRON_ENV_VAR,
+};
+use alloc::vec::Vec;
+use core::sync::atomic::{
+    AtomicU8,
+    Ordering,
+};
+
+/// Type of the aux vector entry that programs should ignore.
+const AT_IGNORE: u64 = 1;
+
+const UNKNOWN: u8 = 0;
+const NO: u8 = 1;
+const YES: u8 = 2;
+
+/// Result of [`detect_hedron`], which [`runs_under_hedron`] caches.
+static UNDER_HEDRON: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+/// The path that [`force_hybrid_path`] selected, or [`UNKNOWN`] for the default path.
+static FORCED_PATH: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+extern "C" {
+    fn getauxval(typ: u64) -> u64;
+    fn getenv(name: *const u8) -> *const u8;
+}
+
+/// The way in which a hybrid app performs an operation that it can do either way.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HybridPath {
+    /// Linux syscalls. Under Hedron, the roottask handles them as foreign syscalls.
+    Linux,
+    /// Hedron-native calls of the service portals. Only available under Hedron.
+    Native,
+}
+
+impl HybridPath {
+    const fn val(self) -> u8 {
+        match self {
+            HybridPath::Linux => NO,
+            HybridPath::Native => YES,
+        }
+    }
+}
+
+/// Checks if the app runs under Hedron rather than under Linux. Detects it once with the
+/// aux vector or the environment and caches the result.
+pub fn runs_under_hedron() -> bool {
+    match UNDER_HEDRON.load(Ordering::SeqCst) {
+        UNKNOWN => {
+            let under_hedron = detect_hedron();
+            UNDER_HEDRON.store(if under_hedron { YES } else { NO }, Ordering::SeqCst);
+            under_hedron
+        }
+        val => val == YES,
+    }
+}
+
+/// Returns the path of hybrid operations: [`HybridPath::Native`] under Hedron and
+/// [`HybridPath::Linux`] otherwise, unless [`force_hybrid_path`] selected another one.
+pub fn hybrid_path() -> HybridPath {
+    match FORCED_PATH.load(Ordering::SeqCst) {
+        NO => HybridPath::Linux,
+        YES => HybridPath::Native,
+        _ if runs_under_hedron() => HybridPath::Native,
+        _ => HybridPath::Linux,
+    }
+}
+
+/// Forces the path of hybrid operations for the whole process, e.g. to compare both paths
+/// in a benchmark. `None` restores the default path.
+///
+/// # Panics
+/// If the native path is forced but the app doesn't run under Hedron.
+pub fn force_hybrid_path(path: Option<HybridPath>) {
+    assert!(
+        path != Some(HybridPath::Native) || runs_under_hedron(),
+        "the native path is only available under Hedron"
+    );
+    FORCED_PATH.store(path.map_or(UNKNOWN, HybridPath::val), Ordering::SeqCst);
+}
+
+/// Looks for the marks of the roottask in the aux vector and the environment.
+fn detect_hedron() -> bool {
+    if unsafe { getauxval(AT_IGNORE) } == HEDRON_AUX_VECTOR_MAGIC {
+        return true;
+    }
+    let mut name = Vec::from(LINUX_UNDER_HEDRON_ENV_VAR.as_bytes());
+    name.push(0);
+    !unsafe { getenv(name.as_ptr()) }.is_null()
+}
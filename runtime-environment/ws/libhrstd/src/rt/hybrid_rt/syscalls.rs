@@ -25,6 +25,7 @@ use libhedron::syscall::{
 };
 use libhedron::syscall::{
     sys_pt_ctrl,
+    sys_revoke,
     SmCtrlZeroCounterStrategy,
 };
 use libhedron::Mtd;
@@ -200,3 +201,13 @@ pub fn sys_hybrid_sm_down(
     log::trace!("Executing hybrid foreign syscall: sys_sm_down");
     wrap_hybrid_hedron_syscall(|| sys_sm_down(sm_sel, counter_strategy, tsc_timeout))
 }
+
+/// Like [`libhedron::syscall::sys_revoke`] but for usage in hybrid foreign applications.
+#[inline]
+pub fn sys_hybrid_revoke<Perm, Spec, ObjSpec>(
+    crd: Crd<Perm, Spec, ObjSpec>,
+    self_too: bool,
+) -> SyscallResult {
+    log::trace!("Executing hybrid foreign syscall: sys_revoke");
+    wrap_hybrid_hedron_syscall(|| sys_revoke(crd, self_too))
+}
@@ -1,3 +1,5 @@
+#[cfg(feature = "native_rust_rt")]
+pub mod env;
 // required for successful compilation ...
 #[cfg(all(not(test), feature = "native_rust_rt"))]
 pub mod rust_rt;
@@ -1,4 +1,13 @@
 //! Set's up the Rust runtime for native Hedron Rust apps, except the roottask.
 
+pub mod user_fault_handler;
 pub mod user_global_allocator;
 pub mod user_panic_handler;
+
+use crate::process::elf_note::HedronAbiNote;
+
+/// Marks the binary as native Hedron app, so that the roottask can detect its syscall
+/// ABI. See [`crate::process::elf_note`].
+#[used]
+#[link_section = ".note.hedron"]
+static HEDRON_ABI_NOTE: HedronAbiNote = HedronAbiNote::new();
@@ -0,0 +1,62 @@
+//! Handler for faults of Hedron-native apps that the roottask can't resolve, e.g. a page
+//! fault at an unmapped address. See
+//! [`crate::rt::services::process::ProcessServiceRequest::SetFaultHandler`].
+
+use crate::rt::services::process::process_service_set_fault_handler;
+use crate::sync::mutex::SimpleMutex;
+use alloc::vec::Vec;
+use libhedron::mem::PAGE_SIZE;
+
+/// Size of the stack that the fault handler runs on. Big enough for logging.
+const FAULT_STACK_SIZE: usize = 16 * PAGE_SIZE;
+
+/// The handler of the app, see [`set_fault_handler`].
+static FAULT_HANDLER: SimpleMutex<Option<fn(&Fault) -> !>> = SimpleMutex::new(None);
+
+/// A fault of the app as the roottask reports it.
+#[derive(Debug, Copy, Clone)]
+pub struct Fault {
+    /// Number of the exception, see [`libhedron::ExceptionEventOffset`].
+    pub exception: u64,
+    pub rip: u64,
+    pub rsp: u64,
+    /// Faulting address of page faults, otherwise zero.
+    pub fault_addr: u64,
+    pub error_code: u64,
+}
+
+/// Registers `handler` for faults of the app that the roottask can't resolve. Without a
+/// handler, such faults terminate the app. The handler runs on a stack of its own and can't
+/// continue the faulting code; usually, it reports the fault and exits, e.g. with
+/// [`crate::rt::services::process::process_service_exit`]. It runs at most once.
+///
+/// The fault may happen while the app holds a lock, e.g. the one of the logger. Handlers
+/// should do as little as possible.
+pub fn set_fault_handler(handler: fn(&Fault) -> !) {
+    let stack = Vec::<u8>::with_capacity(FAULT_STACK_SIZE)
+        .leak()
+        .as_mut_ptr();
+    let stack_top = stack as u64 + FAULT_STACK_SIZE as u64;
+    FAULT_HANDLER.lock().replace(handler);
+    process_service_set_fault_handler(Some(fault_entry), stack_top).unwrap();
+}
+
+/// Entry of the roottask into the app after a fault. Forwards the fault to the handler of
+/// [`set_fault_handler`].
+extern "C" fn fault_entry(
+    exception: u64,
+    rip: u64,
+    rsp: u64,
+    fault_addr: u64,
+    error_code: u64,
+) -> ! {
+    let fault = Fault {
+        exception,
+        rip,
+        rsp,
+        fault_addr,
+        error_code,
+    };
+    let handler = FAULT_HANDLER.lock().expect("no fault handler registered");
+    handler(&fault)
+}
@@ -1,36 +1,96 @@
+use crate::mem::FreeListAllocator;
 use crate::rt::services::allocate::{
-    alloc_service,
     dealloc_service,
+    heap_region_service,
+    HEAP_REGION_SIZE,
 };
+use crate::sync::mutex::SimpleMutex;
 use core::alloc::{
     GlobalAlloc,
     Layout,
 };
+use core::ptr::{
+    copy_nonoverlapping,
+    null_mut,
+    NonNull,
+};
+use libhedron::mem::PAGE_SIZE;
 
 #[global_allocator]
 static GLOBAL_ALLOC: UserGlobalAllocator = UserGlobalAllocator::new();
 
-/// Global Allocator for User Hedron-native User Apps. Currently it is dumb.
-/// It allocates whole portions of pages (minimum allocation). THis is really inefficient.
-struct UserGlobalAllocator {}
+/// Global Allocator for User Hedron-native User Apps. Manages heap regions from the
+/// roottask locally with a [`FreeListAllocator`]. Only calls the allocate service when
+/// all regions are exhausted; the roottask backs the regions lazily. See
+/// [`crate::rt::services::allocate::AllocRequest::HeapRegion`]. Unused regions go back to
+/// the roottask with [`release_unused_heap_regions`].
+struct UserGlobalAllocator {
+    heap: SimpleMutex<FreeListAllocator>,
+}
 
 impl UserGlobalAllocator {
     const fn new() -> Self {
-        Self {}
+        Self {
+            heap: SimpleMutex::new(FreeListAllocator::new()),
+        }
     }
 }
 
 unsafe impl GlobalAlloc for UserGlobalAllocator {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        let ptr = alloc_service(layout);
-        log::trace!("alloc: layout={:?} ptr={:?}", layout, ptr);
-        ptr
+        let mut heap = self.heap.lock();
+        if let Some(ptr) = heap.allocate(layout) {
+            return ptr.as_ptr();
+        }
+        // allocations bigger than a region get a region of their own
+        let size = (layout.size() + layout.align() + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let size = size.max(HEAP_REGION_SIZE);
+        // no logging here: the logger might allocate
+        let region = heap_region_service(size);
+        if region.is_null() {
+            return null_mut();
+        }
+        heap.add_region(region, size);
+        heap.allocate(layout).map_or(null_mut(), |ptr| ptr.as_ptr())
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        dealloc_service(ptr as u64, layout);
-        log::trace!("dealloc: layout={:?} ptr={:?}", layout, ptr);
+        if let Some(ptr) = NonNull::new(ptr) {
+            self.heap.lock().deallocate(ptr, layout);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if let Some(non_null) = NonNull::new(ptr) {
+            if self
+                .heap
+                .lock()
+                .reallocate_in_place(non_null, layout, new_size)
+            {
+                return ptr;
+            }
+        }
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() && !ptr.is_null() {
+            copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+/// Gives all heap regions that contain no allocation anymore back to the roottask, which
+/// unmaps them. Returns the number of released bytes.
+pub fn release_unused_heap_regions() -> usize {
+    let mut heap = GLOBAL_ALLOC.heap.lock();
+    let mut released = 0;
+    while let Some((region, size)) = heap.release_free_region() {
+        let layout = Layout::from_size_align(size, PAGE_SIZE).unwrap();
+        unsafe { dealloc_service(region.as_ptr() as u64, layout) };
+        released += size;
     }
+    released
 }
 
 #[alloc_error_handler]
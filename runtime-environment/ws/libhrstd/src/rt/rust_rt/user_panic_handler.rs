@@ -1,14 +1,62 @@
 use crate::libhedron::mem::PAGE_SIZE;
+use crate::uaddress_space::{
+    USER_STACK_BOTTOM_ADDR,
+    USER_STACK_VERY_TOP,
+};
+use crate::util::backtrace::{
+    current_frame_pointer,
+    write_backtrace,
+    EmbeddedSymbols,
+};
 use crate::util::panic_msg::generate_panic_msg;
+use arrayvec::ArrayString;
 use core::panic::PanicInfo;
 use core::sync::atomic::{
     compiler_fence,
     Ordering,
 };
 
+/// Filled with the functions of the app after linking, see [`crate::util::backtrace`].
+#[used]
+#[link_section = ".hedron_symbols"]
+static SYMBOLS: EmbeddedSymbols<{ 256 * 1024 }> = EmbeddedSymbols::new();
+
+/// Frames of stacks other than the main stack, e.g. the one of the fault handler, are only
+/// followed that far above the first frame.
+const OTHER_STACK_BACKTRACE_RANGE: u64 = 16 * PAGE_SIZE as u64;
+
 pub fn handle_panic(info: &PanicInfo) -> ! {
-    log::error!("{}", generate_panic_msg::<PAGE_SIZE>(info));
+    // the roottask prefixes the output with the PID; the name identifies the app
+    let program = crate::rt::env::args().next().unwrap_or("<unknown>");
+    log::error!(
+        "app '{}' panicked: {}",
+        program,
+        generate_panic_msg::<PAGE_SIZE>(info)
+    );
+    log_backtrace();
     loop {
         compiler_fence(Ordering::SeqCst)
     }
 }
+
+/// Logs the backtrace of the panicking thread. Only follows frames in the stack of the
+/// thread, which is mapped.
+fn log_backtrace() {
+    let rbp = current_frame_pointer();
+    let stack = if (USER_STACK_BOTTOM_ADDR..USER_STACK_VERY_TOP).contains(&rbp) {
+        USER_STACK_BOTTOM_ADDR..USER_STACK_VERY_TOP
+    } else {
+        rbp..rbp + OTHER_STACK_BACKTRACE_RANGE
+    };
+    let mut out = ArrayString::<PAGE_SIZE>::new();
+    // truncated output is still helpful
+    let _ = unsafe {
+        write_backtrace(
+            &mut out,
+            rbp,
+            |addr| stack.contains(&addr) && stack.contains(&(addr + 15)),
+            SYMBOLS.table(),
+        )
+    };
+    log::error!("{}", out);
+}
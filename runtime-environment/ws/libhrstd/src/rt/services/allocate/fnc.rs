@@ -1,35 +1,25 @@
-use crate::cap_space::user::UserAppCapSpace;
-#[cfg(feature = "foreign_rust_rt")]
-use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
-use crate::rt::services::allocate::AllocRequest;
-use crate::rt::user_load_utcb::user_load_utcb_mut;
+use crate::rt::services::allocate::{
+    AllocRequest,
+    AllocateService,
+};
+use crate::rt::services::rpc::rpc_call;
 use core::alloc::Layout;
-#[cfg(feature = "native_rust_rt")]
-use libhedron::syscall::sys_call;
 
 /// Allocates memory from the roottask allocator.
 #[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
 pub fn alloc_service(layout: Layout) -> *mut u8 {
-    let utcb = user_load_utcb_mut();
-    utcb.store_data(&AllocRequest::new_alloc(layout)).unwrap();
-
-    #[cfg(feature = "native_rust_rt")]
-    sys_call(UserAppCapSpace::AllocatorServicePT.val()).unwrap();
-    #[cfg(feature = "foreign_rust_rt")]
-    sys_hybrid_call(UserAppCapSpace::AllocatorServicePT.val()).unwrap();
+    rpc_call::<AllocateService, _>(AllocRequest::new_alloc(layout)).unwrap() as *mut u8
+}
 
-    utcb.load_data::<u64>().unwrap() as *mut u8
+/// Reserves a heap region of `size` bytes, see [`AllocRequest::HeapRegion`]. Returns null
+/// if the address space of the app is exhausted.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn heap_region_service(size: usize) -> *mut u8 {
+    rpc_call::<AllocateService, _>(AllocRequest::new_heap_region(size)).unwrap() as *mut u8
 }
 
 /// Allocates memory from the roottask allocator.
 #[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
 pub unsafe fn dealloc_service(ptr: u64, layout: Layout) {
-    let utcb = user_load_utcb_mut();
-    utcb.store_data(&AllocRequest::new_delloc(ptr, layout))
-        .unwrap();
-
-    #[cfg(feature = "native_rust_rt")]
-    sys_call(UserAppCapSpace::AllocatorServicePT.val()).unwrap();
-    #[cfg(feature = "foreign_rust_rt")]
-    sys_hybrid_call(UserAppCapSpace::AllocatorServicePT.val()).unwrap();
+    rpc_call::<AllocateService, _>(AllocRequest::new_delloc(ptr, layout)).unwrap();
 }
@@ -1,8 +1,19 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::rt::services::rpc::{
+    Protocol,
+    Rpc,
+    Service,
+};
 use core::alloc::Layout;
 use libhedron::ipc_serde::{
     Deserialize,
     Serialize,
 };
+use libhedron::mem::PAGE_SIZE;
+
+/// Size of the heap regions that the allocator of Hedron-native apps requests, see
+/// [`AllocRequest::HeapRegion`].
+pub const HEAP_REGION_SIZE: usize = 16 * 1024 * 1024;
 
 /// Describes an allocation request similar to mmap that Hedron-native
 /// apps can trigger.
@@ -10,8 +21,22 @@ use libhedron::ipc_serde::{
 /// Like "Layout" but serializable.
 #[derive(Copy, Clone, Serialize, Deserialize, Debug)]
 pub enum AllocRequest {
-    Alloc { size: usize, align: usize },
-    Dealloc { ptr: u64, size: usize, align: usize },
+    Alloc {
+        size: usize,
+        align: usize,
+    },
+    Dealloc {
+        ptr: u64,
+        size: usize,
+        align: usize,
+    },
+    /// Reserves a page-aligned region of `size` bytes for the heap of the app. The roottask
+    /// backs the pages lazily when the app touches them for the first time, hence large
+    /// regions cost nothing up front. The app manages the region itself and returns it
+    /// as a whole with [`AllocRequest::Dealloc`] once it is unused. See [`HEAP_REGION_SIZE`].
+    HeapRegion {
+        size: usize,
+    },
 }
 
 impl AllocRequest {
@@ -30,6 +55,10 @@ impl AllocRequest {
         }
     }
 
+    pub fn new_heap_region(size: usize) -> Self {
+        Self::HeapRegion { size }
+    }
+
     pub fn to_layout(self) -> Layout {
         Layout::from_size_align(self.size(), self.align()).unwrap()
     }
@@ -38,12 +67,14 @@ impl AllocRequest {
         match self {
             AllocRequest::Alloc { size, .. } => *size,
             AllocRequest::Dealloc { size, .. } => *size,
+            AllocRequest::HeapRegion { size } => *size,
         }
     }
     pub fn align(&self) -> usize {
         match self {
             AllocRequest::Alloc { align, .. } => *align,
             AllocRequest::Dealloc { align, .. } => *align,
+            AllocRequest::HeapRegion { .. } => PAGE_SIZE,
         }
     }
 
@@ -62,3 +93,24 @@ impl AllocRequest {
         matches!(self, Self::Dealloc { .. })
     }
 }
+
+/// The allocator service.
+#[derive(Debug)]
+pub struct AllocateService;
+
+impl Protocol for AllocateService {}
+
+impl Service for AllocateService {
+    const PORTAL: UserAppCapSpace = UserAppCapSpace::AllocatorServicePT;
+}
+
+/// Replies with the address of the allocation, the address of the freed memory, or the
+/// address of the heap region. Null if the memory is exhausted.
+impl Rpc<AllocateService> for AllocRequest {
+    type Message = Self;
+    type Response = u64;
+
+    fn into_message(self) -> Self {
+        self
+    }
+}
@@ -0,0 +1,19 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::build_info::BuildInfoServiceResponse;
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+
+/// Fetches the build metadata of the roottask and the API version of the running
+/// Hedron kernel. Compare it with the own [`crate::build_info!`] to detect a stale image.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn build_info_service() -> BuildInfoServiceResponse {
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::BuildInfoServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::BuildInfoServicePT.val()).unwrap();
+
+    user_load_utcb_mut().load_data().unwrap()
+}
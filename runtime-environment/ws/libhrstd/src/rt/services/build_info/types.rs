@@ -0,0 +1,57 @@
+use crate::build_info::BuildInfo;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Reply of the build info service. The service doesn't expect request data.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfoServiceResponse {
+    /// Build metadata of the roottask.
+    pub roottask: BuildInfo,
+    /// API version of the running Hedron kernel, as reported by the HIP.
+    pub hedron_api_ver: u32,
+    /// Hardware properties of the platform, as reported by the HIP.
+    pub platform: PlatformInfo,
+}
+
+/// Hardware properties of the platform that the roottask reads from the HIP.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlatformInfo {
+    /// Number of online CPUs.
+    pub online_cpus: u64,
+    /// Sum of the sizes of all available memory regions in bytes. Includes the memory
+    /// of the hypervisor and of the boot modules.
+    pub usable_mem: u64,
+    /// Start address and size of the largest available memory region that neither the
+    /// hypervisor nor a boot module occupies.
+    pub largest_usable_region: Option<(u64, u64)>,
+    /// Number of Multiboot boot modules.
+    pub boot_modules: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let response = BuildInfoServiceResponse {
+            roottask: BuildInfo::new("roottask-bin", "0.1.0", "0123456789ab", "1337", "debug"),
+            hedron_api_ver: 3000,
+            platform: PlatformInfo {
+                online_cpus: 4,
+                usable_mem: 0x7ff9f000,
+                largest_usable_region: Some((0x2200000, 0x7de00000)),
+                boot_modules: 2,
+            },
+        };
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<BuildInfoServiceResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
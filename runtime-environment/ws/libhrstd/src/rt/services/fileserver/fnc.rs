@@ -0,0 +1,80 @@
+use crate::rt::services::chunked_bulk_call;
+use crate::rt::services::fileserver::{
+    FileServer,
+    FileServerCloseRequest,
+    FileServerError,
+    FileServerLseekRequest,
+    FileServerOpenRequest,
+    FileServerReadRequest,
+    FileServerWriteRequest,
+    FILE_SERVER_MAX_IO_LEN,
+};
+use crate::rt::services::fs::{
+    FsOpenFlags,
+    FD,
+};
+use crate::rt::services::rpc::rpc_call_at;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use libhedron::CapSel;
+
+/// Opens a file of the file server whose portal is at `portal`, see
+/// [`crate::rt::services::name::name_service_lookup`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn file_server_open(
+    portal: CapSel,
+    path: &str,
+    flags: FsOpenFlags,
+    umode: u16,
+) -> Result<FD, FileServerError> {
+    let request = FileServerOpenRequest {
+        path: path.to_string(),
+        flags,
+        umode,
+    };
+    rpc_call_at::<FileServer, _>(portal, request).map_err(|_| FileServerError::TooLarge)?
+}
+
+/// Reads up to `count` bytes. Returns fewer bytes at the end of the file.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn file_server_read(portal: CapSel, fd: FD, count: usize) -> Result<Vec<u8>, FileServerError> {
+    let mut data = Vec::with_capacity(count);
+    while data.len() < count {
+        let chunk_len = (count - data.len()).min(FILE_SERVER_MAX_IO_LEN);
+        let request = FileServerReadRequest {
+            fd,
+            count: chunk_len,
+        };
+        let chunk = rpc_call_at::<FileServer, _>(portal, request).unwrap()?;
+        data.extend_from_slice(&chunk);
+        if chunk.len() < chunk_len {
+            break;
+        }
+    }
+    Ok(data)
+}
+
+/// Writes the data in chunks of at most [`FILE_SERVER_MAX_IO_LEN`] bytes and returns the
+/// number of written bytes.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn file_server_write(portal: CapSel, fd: FD, data: &[u8]) -> Result<usize, FileServerError> {
+    chunked_bulk_call(data, FILE_SERVER_MAX_IO_LEN, |chunk| {
+        let request = FileServerWriteRequest {
+            fd,
+            data: chunk.to_vec(),
+        };
+        rpc_call_at::<FileServer, _>(portal, request).unwrap()
+    })
+}
+
+/// Sets the offset of the file.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn file_server_lseek(portal: CapSel, fd: FD, offset: u64) -> Result<(), FileServerError> {
+    rpc_call_at::<FileServer, _>(portal, FileServerLseekRequest { fd, offset }).unwrap()
+}
+
+/// Closes the file.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn file_server_close(portal: CapSel, fd: FD) -> Result<(), FileServerError> {
+    rpc_call_at::<FileServer, _>(portal, FileServerCloseRequest { fd }).unwrap()
+}
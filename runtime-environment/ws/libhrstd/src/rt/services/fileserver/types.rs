@@ -0,0 +1,110 @@
+use crate::rt::services::fs::{
+    FsError,
+    FsOpenFlags,
+    FD,
+};
+use crate::service_protocol;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Name of the file server in the name service.
+pub const FILE_SERVER_NAME: &str = "fileserver";
+
+/// Maximum number of bytes that a single read or write request transfers. The data
+/// travels through the UTCB.
+pub const FILE_SERVER_MAX_IO_LEN: usize = 2048;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileServerOpenRequest {
+    pub path: String,
+    pub flags: FsOpenFlags,
+    pub umode: u16,
+}
+
+/// Reads up to `count` bytes, but at most [`FILE_SERVER_MAX_IO_LEN`].
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileServerReadRequest {
+    pub fd: FD,
+    pub count: usize,
+}
+
+/// Writes at most [`FILE_SERVER_MAX_IO_LEN`] bytes.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileServerWriteRequest {
+    pub fd: FD,
+    pub data: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileServerLseekRequest {
+    pub fd: FD,
+    pub offset: u64,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FileServerCloseRequest {
+    pub fd: FD,
+}
+
+/// Request that a user app sends to the portal of the file server. The file server is a
+/// user app itself; clients get its portal from the name service under
+/// [`FILE_SERVER_NAME`]. Unlike the file system service of the roottask, the data of reads
+/// and writes travels through the UTCB, because the file server can't map the memory of
+/// its clients.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum FileServerRequest {
+    Open(FileServerOpenRequest),
+    Read(FileServerReadRequest),
+    Write(FileServerWriteRequest),
+    LSeek(FileServerLseekRequest),
+    Close(FileServerCloseRequest),
+}
+
+/// Errors that the file server can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum FileServerError {
+    /// The file system rejected the operation.
+    Fs(FsError),
+    /// The request exceeds [`FILE_SERVER_MAX_IO_LEN`].
+    TooLarge,
+}
+
+service_protocol! {
+    /// The file server that runs as user app, see `fileserver-bin`.
+    pub service FileServer: FileServerRequest {
+        Open(FileServerOpenRequest) -> Result<FD, FileServerError>,
+        Read(FileServerReadRequest) -> Result<Vec<u8>, FileServerError>,
+        Write(FileServerWriteRequest) -> Result<usize, FileServerError>,
+        LSeek(FileServerLseekRequest) -> Result<(), FileServerError>,
+        Close(FileServerCloseRequest) -> Result<(), FileServerError>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_max_write_fits_into_utcb() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = FileServerWriteRequest {
+            fd: FD::new(3),
+            data: vec![0xff; FILE_SERVER_MAX_IO_LEN],
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<FileServerRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: Result<Vec<u8>, FileServerError> = Ok(vec![0xff; FILE_SERVER_MAX_IO_LEN]);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+    }
+}
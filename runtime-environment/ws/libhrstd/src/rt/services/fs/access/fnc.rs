@@ -0,0 +1,13 @@
+use crate::rt::services::fs::access::{
+    FsAccessRequest,
+    FsAccessResponse,
+};
+use crate::rt::services::fs::FsService;
+use crate::rt::services::rpc::rpc_call;
+
+/// Wrapper around the FS service portal to check the permissions of the calling process
+/// for a file, without opening it.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_service_access(request: FsAccessRequest) -> FsAccessResponse {
+    rpc_call::<FsService, _>(request).unwrap()
+}
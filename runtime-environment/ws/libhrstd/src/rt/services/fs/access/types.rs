@@ -0,0 +1,95 @@
+use crate::rt::services::fs::FsError;
+use alloc::string::String;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+bitflags::bitflags! {
+    /// Permissions that can be checked with the `access()` system call. The interface is
+    /// similar to the one by Linux.
+    ///
+    /// Source: <https://github.com/torvalds/linux/blob/master/include/linux/fs.h>
+    #[derive(Serialize, Deserialize)]
+    pub struct FsAccessMode: u32 {
+        /// Only checks if the file exists.
+        const F_OK = 0;
+        /// Execute permission.
+        const X_OK = 1;
+        /// Write permission.
+        const W_OK = 2;
+        /// Read permission.
+        const R_OK = 4;
+    }
+}
+
+impl FsAccessMode {
+    /// Returns the permissions of a single class of the permission bits of a `umode`,
+    /// e.g. `0o5` (`r-x`).
+    pub fn from_umode_class(bits: u16) -> Self {
+        Self::from_bits_truncate(bits as u32 & 0o7)
+    }
+}
+
+/// Data send via UTCB to Fs Access Portal. The reply is a [`FsAccessResponse`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FsAccessRequest {
+    path: String,
+    mode: FsAccessMode,
+}
+
+impl FsAccessRequest {
+    pub fn new(path: String, mode: FsAccessMode) -> Self {
+        FsAccessRequest { path, mode }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    pub fn mode(&self) -> FsAccessMode {
+        self.mode
+    }
+}
+
+/// Reply of the Fs Access Portal. If the file exists, but the caller lacks at least one of
+/// the permissions, the error is [`FsError::Perm`].
+pub type FsAccessResponse = Result<(), FsError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_from_umode_class() {
+        assert_eq!(FsAccessMode::from_umode_class(0), FsAccessMode::F_OK);
+        assert_eq!(
+            FsAccessMode::from_umode_class(0o5),
+            FsAccessMode::R_OK | FsAccessMode::X_OK
+        );
+        assert_eq!(
+            FsAccessMode::from_umode_class(0o754 >> 3),
+            FsAccessMode::R_OK | FsAccessMode::X_OK
+        );
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = FsAccessRequest::new(
+            String::from("/bin/sh"),
+            FsAccessMode::R_OK | FsAccessMode::X_OK,
+        );
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        let request = libhedron::ipc_postcard::from_bytes::<FsAccessRequest>(&buf).unwrap();
+        assert_eq!(request.path(), "/bin/sh");
+        assert_eq!(request.mode(), FsAccessMode::R_OK | FsAccessMode::X_OK);
+
+        let response: FsAccessResponse = Err(FsError::Perm);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<FsAccessResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use crate::rt::services::fs::FsService;
+use crate::rt::services::fs::{
+    FsBufferError,
+    FsBufferId,
+    FsBufferResponse,
+    FsRegisterBufferRequest,
+};
+use crate::rt::services::rpc::rpc_call;
+
+/// Registers `buf` as fixed buffer at the file system service. The roottask maps the buffer
+/// once and keeps the mapping, hence read and write requests that refer to the buffer
+/// via a [`crate::rt::services::fs::FsBufferRef`] need neither a new mapping nor a copy
+/// through the UTCB.
+///
+/// The memory of `buf` must stay valid until [`fs_unregister_buffer`] or the exit of the
+/// process, because the roottask accesses it on every request that refers to the buffer.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_register_buffer(buf: &mut [u8]) -> FsBufferResponse {
+    let request = FsRegisterBufferRequest::new(buf.as_mut_ptr() as usize, buf.len());
+    rpc_call::<FsService, _>(request).unwrap()
+}
+
+/// Removes a buffer that was registered with [`fs_register_buffer`]. Afterwards, the ID
+/// may be reused for another buffer.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_unregister_buffer(id: FsBufferId) -> Result<(), FsBufferError> {
+    rpc_call::<FsService, _>(id).unwrap()
+}
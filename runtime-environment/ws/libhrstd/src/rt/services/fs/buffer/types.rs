@@ -0,0 +1,112 @@
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Maximum number of buffers that a process can register at the same time.
+pub const FS_MAX_REGISTERED_BUFFERS: usize = 16;
+
+/// ID of a buffer that a process registered at the file system service. IDs are only
+/// unique per process.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq, Hash, Ord, Eq, Serialize, Deserialize)]
+pub struct FsBufferId(u32);
+
+impl FsBufferId {
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+}
+
+/// Refers to the memory of a registered buffer, beginning at `offset` bytes after the start
+/// of the buffer. Used by [`crate::rt::services::fs::FsReadRequest`] and
+/// [`crate::rt::services::fs::FsWriteRequest`] instead of a user pointer or embedded data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsBufferRef {
+    id: FsBufferId,
+    offset: usize,
+}
+
+impl FsBufferRef {
+    pub fn new(id: FsBufferId, offset: usize) -> Self {
+        FsBufferRef { id, offset }
+    }
+
+    pub fn id(&self) -> FsBufferId {
+        self.id
+    }
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Data send via UTCB to Fs Register Buffer Portal. The reply is a [`FsBufferResponse`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FsRegisterBufferRequest {
+    user_ptr: usize,
+    len: usize,
+}
+
+impl FsRegisterBufferRequest {
+    pub fn new(user_ptr: usize, len: usize) -> Self {
+        FsRegisterBufferRequest { user_ptr, len }
+    }
+
+    pub fn user_ptr(&self) -> usize {
+        self.user_ptr
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Errors of the operations on registered buffers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FsBufferError {
+    /// The process already registered [`FS_MAX_REGISTERED_BUFFERS`] buffers.
+    TooManyBuffers,
+    /// The buffer to register has a length of zero.
+    Empty,
+    /// The process has no registered buffer with the given ID.
+    UnknownBuffer,
+    /// The accessed range exceeds the end of the buffer.
+    OutOfBounds,
+}
+
+/// Reply of the Fs Register Buffer Portal.
+pub type FsBufferResponse = Result<FsBufferId, FsBufferError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = FsRegisterBufferRequest::new(0x1000_0123, 0x8000);
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        let request = libhedron::ipc_postcard::from_bytes::<FsRegisterBufferRequest>(&buf).unwrap();
+        assert_eq!(request.user_ptr(), 0x1000_0123);
+        assert_eq!(request.len(), 0x8000);
+
+        for response in [Ok(FsBufferId::new(7)), Err(FsBufferError::TooManyBuffers)] {
+            libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+            assert_eq!(
+                libhedron::ipc_postcard::from_bytes::<FsBufferResponse>(&buf).unwrap(),
+                response
+            );
+        }
+
+        let buffer = FsBufferRef::new(FsBufferId::new(3), 4096);
+        libhedron::ipc_postcard::to_slice(&buffer, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<FsBufferRef>(&buf).unwrap(),
+            buffer
+        );
+    }
+}
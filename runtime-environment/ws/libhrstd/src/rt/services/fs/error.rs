@@ -0,0 +1,65 @@
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Errors of the file system. The file system service reports them to its clients and
+/// the Linux personality maps them to the corresponding Linux error codes.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum FsError {
+    /// The file or mount point doesn't exist. (`ENOENT`)
+    NotFound,
+    /// A file already exists at the path. (`EEXIST`)
+    Exists,
+    /// The file descriptor is not open, refers to no file, or isn't open for the
+    /// operation. (`EBADF`)
+    BadFd,
+    /// The path is no directory but must be one. (`ENOTDIR`)
+    NotDir,
+    /// The operation doesn't work on directories. (`EISDIR`)
+    IsDir,
+    /// The backend has no space for more data. (`ENOSPC`)
+    NoSpace,
+    /// The permission bits of the file or the backend deny the operation. (`EACCES`)
+    Perm,
+    /// The file system is read-only. (`EROFS`)
+    ReadOnly,
+    /// The paths belong to different mounts. (`EXDEV`)
+    CrossMount,
+    /// The resolution of the path followed too many symbolic links. (`ELOOP`)
+    Loop,
+    /// The file would exceed the maximum file size. (`EFBIG`)
+    TooLarge,
+    /// A process still has an open file on the mount or the file descriptor is in use.
+    /// (`EBUSY`)
+    Busy,
+    /// Invalid argument, e.g. an empty or relative path or a file that is no symbolic
+    /// link. (`EINVAL`)
+    InvalidArgument,
+    /// The backend doesn't support the operation, e.g. hard links. (`EPERM`)
+    Unsupported,
+    /// The directory still contains files. (`ENOTEMPTY`)
+    NotEmpty,
+    /// The process has as many open files as its limit allows. (`EMFILE`)
+    TooManyOpenFiles,
+    /// The device behind the backend failed or didn't respond. (`EIO`)
+    Io,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        for response in [Ok(42), Err(FsError::NotFound), Err(FsError::Unsupported)] {
+            libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+            assert_eq!(
+                libhedron::ipc_postcard::from_bytes::<Result<usize, FsError>>(&buf).unwrap(),
+                response
+            );
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::rt::services::fs::FsListDirRequest;
+use crate::rt::services::fs::FsService;
+use crate::rt::services::rpc::rpc_call;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Wrapper around the FS service portal to list the files of a directory. If the list
+/// doesn't fit into the UTCB, it fetches the remaining paths with further requests.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_service_list_dir(request: FsListDirRequest) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut request = request;
+    loop {
+        let dir = String::from(request.dir());
+        let skip = request.skip();
+        let response = rpc_call::<FsService, _>(request).unwrap();
+        let more = response.more();
+        let page = response.into_paths();
+        // a single path that doesn't fit into the UTCB; give up
+        if page.is_empty() {
+            break;
+        }
+        request = FsListDirRequest::new(dir).with_skip(skip + page.len());
+        paths.extend(page);
+        if !more {
+            break;
+        }
+    }
+    paths
+}
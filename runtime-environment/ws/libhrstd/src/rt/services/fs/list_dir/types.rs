@@ -0,0 +1,102 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Data send via UTCB to Fs List Dir Portal. The reply contains the paths of all files
+/// inside the directory, including files in subdirectories.
+///
+/// The reply is a [`FsListDirResponse`]. Large directories need multiple requests;
+/// `skip` is the number of paths that the caller already received.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FsListDirRequest {
+    dir: String,
+    skip: usize,
+}
+
+impl FsListDirRequest {
+    pub fn new(dir: String) -> Self {
+        Self { dir, skip: 0 }
+    }
+
+    /// Requests the paths after the first `skip` paths of the directory.
+    pub fn with_skip(mut self, skip: usize) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+    pub fn skip(&self) -> usize {
+        self.skip
+    }
+}
+
+/// Reply of the Fs List Dir Portal. Contains as many paths as fit into the UTCB.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FsListDirResponse {
+    paths: Vec<String>,
+    more: bool,
+}
+
+impl FsListDirResponse {
+    /// Drops paths from the end of the list until the serialized response fits into
+    /// `capacity` bytes. Marks the response as incomplete if it dropped paths.
+    pub fn new_fitting(mut paths: Vec<String>, capacity: usize) -> Self {
+        let mut buf = vec![0; capacity];
+        let mut more = false;
+        loop {
+            let response = Self { paths, more };
+            if libhedron::ipc_postcard::to_slice(&response, &mut buf).is_ok() {
+                return response;
+            }
+            paths = response.paths;
+            paths.pop();
+            more = true;
+        }
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+    pub fn into_paths(self) -> Vec<String> {
+        self.paths
+    }
+    /// True if the directory contains further paths after the ones of this response.
+    pub fn more(&self) -> bool {
+        self.more
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    /// Paths with 99 characters; each one has 100 bytes serialized.
+    fn paths(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("/{:098}", i)).collect()
+    }
+
+    #[test]
+    fn test_new_fitting_at_utcb_boundary() {
+        // one byte for the length of the vector, one for the bool
+        let fitting = (UTCB_DATA_CAPACITY - 2) / 100;
+        let response = FsListDirResponse::new_fitting(paths(fitting), UTCB_DATA_CAPACITY);
+        assert_eq!(response.paths().len(), fitting);
+        assert!(!response.more());
+
+        let response = FsListDirResponse::new_fitting(paths(fitting + 1), UTCB_DATA_CAPACITY);
+        assert_eq!(response.paths(), &paths(fitting)[..]);
+        assert!(response.more());
+
+        let response = FsListDirResponse::new_fitting(Vec::new(), UTCB_DATA_CAPACITY);
+        assert!(response.paths().is_empty());
+        assert!(!response.more());
+    }
+}
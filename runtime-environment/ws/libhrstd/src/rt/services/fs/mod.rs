@@ -1,17 +1,51 @@
+mod access;
+mod buffer;
 mod close;
+mod error;
 mod fd;
+mod list_dir;
 mod lseek;
 mod open;
 mod read;
 mod request;
+mod ring;
+mod socket;
+mod umask;
 mod write;
 
 // types
 #[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use access::fs_service_access;
+pub use access::{
+    FsAccessMode,
+    FsAccessRequest,
+    FsAccessResponse,
+};
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use buffer::{
+    fs_register_buffer,
+    fs_unregister_buffer,
+};
+pub use buffer::{
+    FsBufferError,
+    FsBufferId,
+    FsBufferRef,
+    FsBufferResponse,
+    FsRegisterBufferRequest,
+    FS_MAX_REGISTERED_BUFFERS,
+};
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
 pub use close::fs_service_close;
 pub use close::FsCloseRequest;
+pub use error::FsError;
 pub use fd::FD;
 #[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use list_dir::fs_service_list_dir;
+pub use list_dir::{
+    FsListDirRequest,
+    FsListDirResponse,
+};
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
 pub use lseek::fs_service_lseek;
 pub use lseek::FsLseekRequest;
 #[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
@@ -22,8 +56,51 @@ pub use open::{
 };
 #[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
 pub use read::fs_service_read;
-pub use read::FsReadRequest;
-pub use request::FsServiceRequest;
+pub use read::{
+    FsReadDest,
+    FsReadRequest,
+};
+pub use request::{
+    FsService,
+    FsServiceRequest,
+};
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use ring::{
+    fs_ring_setup,
+    fs_submit_batch,
+    FsRingClient,
+};
+pub use ring::{
+    FsRing,
+    FsRingCompletion,
+    FsRingCqe,
+    FsRingError,
+    FsRingOp,
+    FsRingOpcode,
+    FsRingSetupRequest,
+    FsRingSetupResponse,
+    FsRingSqe,
+    FS_RING_DATA_SLOT_SIZE,
+    FS_RING_ENTRIES,
+    FS_RING_ERROR,
+};
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use socket::fs_service_socket;
+pub use socket::{
+    FsSocketReply,
+    FsSocketRequest,
+    FsSocketResponse,
+    SocketError,
+    SocketKind,
+    FS_SOCKET_MAX_IPC_DATA,
+};
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use umask::fs_service_umask;
+pub use umask::FsUmaskRequest;
 #[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
 pub use write::fs_service_write;
-pub use write::FsWriteRequest;
+pub use write::{
+    FsWriteRequest,
+    FsWriteSrc,
+    FS_WRITE_MAX_EMBEDDED,
+};
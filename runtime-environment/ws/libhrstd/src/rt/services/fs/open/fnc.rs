@@ -1,24 +1,11 @@
-use crate::cap_space::user::UserAppCapSpace;
-#[cfg(feature = "foreign_rust_rt")]
-use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::fs::FsError;
 use crate::rt::services::fs::FsOpenRequest;
-use crate::rt::services::fs::FsServiceRequest;
+use crate::rt::services::fs::FsService;
 use crate::rt::services::fs::FD;
-use crate::rt::user_load_utcb::user_load_utcb_mut;
-#[cfg(feature = "native_rust_rt")]
-use libhedron::syscall::sys_call;
+use crate::rt::services::rpc::rpc_call;
 
 /// Wrapper around the FS service portal to open files.
 #[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
-pub fn fs_service_open(request: FsOpenRequest) -> FD {
-    let utcb = user_load_utcb_mut();
-    let request = FsServiceRequest::Open(request);
-    utcb.store_data(&request).unwrap();
-
-    #[cfg(feature = "native_rust_rt")]
-    sys_call(UserAppCapSpace::FsServicePT.val()).unwrap();
-    #[cfg(feature = "foreign_rust_rt")]
-    sys_hybrid_call(UserAppCapSpace::FsServicePT.val()).unwrap();
-
-    utcb.load_data().unwrap()
+pub fn fs_service_open(request: FsOpenRequest) -> Result<FD, FsError> {
+    rpc_call::<FsService, _>(request).unwrap()
 }
@@ -1,4 +1,5 @@
 use super::super::FD;
+use crate::rt::services::fs::FsBufferRef;
 use libhedron::ipc_serde::{
     Deserialize,
     Serialize,
@@ -8,7 +9,7 @@ use libhedron::ipc_serde::{
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FsReadRequest {
     fd: FD,
-    user_ptr: usize,
+    dest: FsReadDest,
     count: usize,
 }
 
@@ -16,7 +17,17 @@ impl FsReadRequest {
     pub fn new(fd: FD, user_ptr: usize, count: usize) -> Self {
         FsReadRequest {
             fd,
-            user_ptr,
+            dest: FsReadDest::UserPtr(user_ptr),
+            count,
+        }
+    }
+
+    /// Reads into a buffer that was registered with
+    /// [`crate::rt::services::fs::fs_register_buffer`].
+    pub fn new_registered(fd: FD, buffer: FsBufferRef, count: usize) -> Self {
+        FsReadRequest {
+            fd,
+            dest: FsReadDest::Registered(buffer),
             count,
         }
     }
@@ -24,10 +35,38 @@ impl FsReadRequest {
     pub fn fd(&self) -> FD {
         self.fd
     }
-    pub fn user_ptr(&self) -> usize {
-        self.user_ptr
+    pub fn dest(&self) -> FsReadDest {
+        self.dest
     }
     pub fn count(&self) -> usize {
         self.count
     }
 }
+
+/// Destination of the data of a [`FsReadRequest`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FsReadDest {
+    /// Memory of the process that the roottask maps for this single request.
+    UserPtr(usize),
+    /// Memory of a registered buffer, which the roottask already mapped.
+    Registered(FsBufferRef),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::fs::FsBufferId;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let buffer = FsBufferRef::new(FsBufferId::new(1), 512);
+        let request = FsReadRequest::new_registered(FD::new(3), buffer, 1024);
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        let request = libhedron::ipc_postcard::from_bytes::<FsReadRequest>(&buf).unwrap();
+        assert_eq!(request.fd(), FD::new(3));
+        assert_eq!(request.dest(), FsReadDest::Registered(buffer));
+        assert_eq!(request.count(), 1024);
+    }
+}
@@ -1,8 +1,22 @@
+use crate::rt::services::fs::FsAccessRequest;
+use crate::rt::services::fs::FsAccessResponse;
+use crate::rt::services::fs::FsBufferError;
+use crate::rt::services::fs::FsBufferId;
+use crate::rt::services::fs::FsBufferResponse;
 use crate::rt::services::fs::FsCloseRequest;
+use crate::rt::services::fs::FsError;
+use crate::rt::services::fs::FsListDirRequest;
+use crate::rt::services::fs::FsListDirResponse;
 use crate::rt::services::fs::FsLseekRequest;
 use crate::rt::services::fs::FsOpenRequest;
 use crate::rt::services::fs::FsReadRequest;
+use crate::rt::services::fs::FsRegisterBufferRequest;
+use crate::rt::services::fs::FsSocketRequest;
+use crate::rt::services::fs::FsSocketResponse;
+use crate::rt::services::fs::FsUmaskRequest;
 use crate::rt::services::fs::FsWriteRequest;
+use crate::rt::services::fs::FD;
+use crate::service_protocol;
 use libhedron::ipc_serde::{
     Deserialize,
     Serialize,
@@ -16,6 +30,34 @@ pub enum FsServiceRequest {
     LSeek(FsLseekRequest),
     Write(FsWriteRequest),
     Close(FsCloseRequest),
+    ListDir(FsListDirRequest),
+    Socket(FsSocketRequest),
+    /// Maps the shared file system ring of the process into its address space. See
+    /// [`crate::rt::services::fs::FsRing`].
+    RingSetup,
+    Umask(FsUmaskRequest),
+    /// Registers a fixed buffer for read and write requests. See
+    /// [`crate::rt::services::fs::fs_register_buffer`].
+    RegisterBuffer(FsRegisterBufferRequest),
+    UnregisterBuffer(FsBufferId),
+    Access(FsAccessRequest),
+}
+
+service_protocol! {
+    /// The file system service. All requests go through [`FsServiceRequest`].
+    pub service FsService(FsServicePT): FsServiceRequest {
+        Open(FsOpenRequest) -> Result<FD, FsError>,
+        Read(FsReadRequest) -> Result<usize, FsError>,
+        LSeek(FsLseekRequest) -> Result<(), FsError>,
+        Write(FsWriteRequest) -> Result<usize, FsError>,
+        Close(FsCloseRequest) -> Result<(), FsError>,
+        ListDir(FsListDirRequest) -> FsListDirResponse,
+        Socket(FsSocketRequest) -> FsSocketResponse,
+        Umask(FsUmaskRequest) -> u16,
+        RegisterBuffer(FsRegisterBufferRequest) -> FsBufferResponse,
+        UnregisterBuffer(FsBufferId) -> Result<(), FsBufferError>,
+        Access(FsAccessRequest) -> FsAccessResponse,
+    }
 }
 
 #[cfg(test)]
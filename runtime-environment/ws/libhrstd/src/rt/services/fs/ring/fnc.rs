@@ -0,0 +1,102 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::{
+    sys_hybrid_sm_down,
+    sys_hybrid_sm_up,
+};
+use crate::rt::services::fs::ring::{
+    FsRing,
+    FsRingCompletion,
+    FsRingError,
+    FsRingOp,
+    FsRingSetupRequest,
+    FS_RING_ERROR,
+};
+use crate::rt::services::fs::FsService;
+use crate::rt::services::rpc::rpc_call;
+use alloc::vec::Vec;
+use libhedron::syscall::SmCtrlZeroCounterStrategy;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::{
+    sys_sm_down,
+    sys_sm_up,
+};
+
+/// Client side of the file system ring of the process. See [`FsRing`].
+#[derive(Debug)]
+pub struct FsRingClient {
+    ring: &'static FsRing,
+}
+
+impl FsRingClient {
+    /// Returns the shared ring.
+    pub fn ring(&self) -> &'static FsRing {
+        self.ring
+    }
+
+    /// Wakes up the file system service to process the submissions.
+    fn kick(&self) {
+        #[cfg(feature = "native_rust_rt")]
+        sys_sm_up(UserAppCapSpace::FsRingKickSm.val()).unwrap();
+        #[cfg(feature = "foreign_rust_rt")]
+        sys_hybrid_sm_up(UserAppCapSpace::FsRingKickSm.val()).unwrap();
+    }
+
+    /// Waits until the file system service completed submissions.
+    fn wait(&self) {
+        let sel = UserAppCapSpace::FsRingCompletionSm.val();
+        #[cfg(feature = "native_rust_rt")]
+        sys_sm_down(sel, SmCtrlZeroCounterStrategy::Decrement, None).unwrap();
+        #[cfg(feature = "foreign_rust_rt")]
+        sys_hybrid_sm_down(sel, SmCtrlZeroCounterStrategy::Decrement, None).unwrap();
+    }
+}
+
+/// Wrapper around the FS service portal that maps the file system ring into the address
+/// space of the process. Each process has a single ring; further calls return it again.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_ring_setup() -> FsRingClient {
+    let response = rpc_call::<FsService, _>(FsRingSetupRequest).unwrap();
+    FsRingClient {
+        ring: unsafe { &*(response.ring_addr as *const FsRing) },
+    }
+}
+
+/// Executes the operations via the file system ring and returns their completions in the
+/// order of the operations. Submits as many operations as the ring can hold at once,
+/// hence a batch costs a few system calls instead of one portal call per operation.
+/// Writes whose data exceed a data slot fail with [`FS_RING_ERROR`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_submit_batch(client: &FsRingClient, ops: &[FsRingOp]) -> Vec<FsRingCompletion> {
+    let ring = client.ring();
+    let mut completions = Vec::with_capacity(ops.len());
+    let mut next_op = 0;
+    while completions.len() < ops.len() {
+        while next_op < ops.len() {
+            let (sqe, data) = ops[next_op].to_sqe(next_op as u64);
+            match ring.submit(sqe, data) {
+                Ok(()) => {}
+                Err(FsRingError::Full) => break,
+                Err(FsRingError::DataTooLarge) => completions.push(FsRingCompletion {
+                    index: next_op,
+                    result: FS_RING_ERROR,
+                    data: Vec::new(),
+                }),
+            }
+            next_op += 1;
+        }
+        if ring.in_flight() > 0 {
+            client.kick();
+            client.wait();
+        }
+        while let Some(completion) = ring.reap_with(|cqe, data| FsRingCompletion {
+            index: cqe.user_data as usize,
+            result: cqe.result,
+            data: Vec::from(data),
+        }) {
+            completions.push(completion);
+        }
+    }
+    completions.sort_by_key(|completion| completion.index);
+    completions
+}
@@ -0,0 +1,380 @@
+use super::super::FD;
+use crate::rt::services::fs::{
+    FsService,
+    FsServiceRequest,
+};
+use crate::rt::services::rpc::Rpc;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering,
+};
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Number of entries of the submission queue and of the completion queue.
+pub const FS_RING_ENTRIES: u32 = 16;
+
+/// Size of the data slot of each entry. Reads and writes with more bytes transfer at
+/// most this many bytes, like a short read or write.
+pub const FS_RING_DATA_SLOT_SIZE: usize = 2048;
+
+/// Result of a failed operation in [`FsRingCqe::result`].
+pub const FS_RING_ERROR: i64 = -1;
+
+/// Operation of a submission. Stored as `u32` in [`FsRingSqe::opcode`].
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FsRingOpcode {
+    /// Reads up to [`FsRingSqe::arg`] bytes into the data slot.
+    Read = 0,
+    /// Writes [`FsRingSqe::arg`] bytes from the data slot.
+    Write = 1,
+    /// Sets the file offset to [`FsRingSqe::arg`].
+    LSeek = 2,
+}
+
+impl TryFrom<u32> for FsRingOpcode {
+    type Error = ();
+
+    fn try_from(val: u32) -> Result<Self, Self::Error> {
+        match val {
+            0 => Ok(Self::Read),
+            1 => Ok(Self::Write),
+            2 => Ok(Self::LSeek),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Submission queue entry.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FsRingSqe {
+    /// See [`FsRingOpcode`].
+    pub opcode: u32,
+    /// Raw value of the file descriptor.
+    pub fd: i32,
+    /// Byte count or file offset, depending on the opcode.
+    pub arg: u64,
+    /// Value of the client that the server copies into the completion.
+    pub user_data: u64,
+}
+
+/// Completion queue entry.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FsRingCqe {
+    /// See [`FsRingSqe::user_data`].
+    pub user_data: u64,
+    /// Transferred bytes or [`FS_RING_ERROR`].
+    pub result: i64,
+    /// Data slot of the submission.
+    pub slot: u32,
+    /// Number of valid bytes in the data slot, i.e. the read bytes.
+    pub data_len: u32,
+}
+
+/// Shared memory between a client and the file system service, similar to `io_uring` of
+/// Linux. The client enqueues submissions and their data, wakes up the service with the
+/// kick semaphore, and waits on the completion semaphore until the service completed the
+/// submissions. The service processes all submissions of a ring in one go, hence a batch
+/// of operations costs only a few system calls instead of one portal call per operation.
+///
+/// Both queues are single-producer single-consumer queues with free-running indices.
+/// The client keeps at most [`FS_RING_ENTRIES`] operations in flight, therefore the
+/// completion queue never overflows. Submission `n` uses the data slot
+/// `n % FS_RING_ENTRIES`, which belongs to the service until the client reaped the
+/// completion.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FsRing {
+    /// Next submission the service processes. Written by the service.
+    sq_head: AtomicU32,
+    /// Next free submission entry. Written by the client.
+    sq_tail: AtomicU32,
+    /// Next completion the client reaps. Written by the client.
+    cq_head: AtomicU32,
+    /// Next free completion entry. Written by the service.
+    cq_tail: AtomicU32,
+    sqes: [UnsafeCell<FsRingSqe>; FS_RING_ENTRIES as usize],
+    cqes: [UnsafeCell<FsRingCqe>; FS_RING_ENTRIES as usize],
+    data: [UnsafeCell<[u8; FS_RING_DATA_SLOT_SIZE]>; FS_RING_ENTRIES as usize],
+}
+
+// The indices synchronize the accesses to the entries and the data slots.
+unsafe impl Sync for FsRing {}
+
+impl FsRing {
+    /// Returns the number of operations that were submitted but not reaped yet.
+    pub fn in_flight(&self) -> u32 {
+        self.sq_tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.cq_head.load(Ordering::Acquire))
+    }
+
+    /// Enqueues a submission on the client side. Copies `data` into the data slot, for
+    /// example the data of a write. Fails if [`FS_RING_ENTRIES`] operations are in flight
+    /// or if `data` doesn't fit into a slot.
+    pub fn submit(&self, sqe: FsRingSqe, data: &[u8]) -> Result<(), FsRingError> {
+        if data.len() > FS_RING_DATA_SLOT_SIZE {
+            return Err(FsRingError::DataTooLarge);
+        }
+        if self.in_flight() >= FS_RING_ENTRIES {
+            return Err(FsRingError::Full);
+        }
+        let tail = self.sq_tail.load(Ordering::Relaxed);
+        let slot = Self::slot(tail);
+        // the slot belongs to the client until the index is published
+        unsafe {
+            let slot_data = &mut *self.data[slot].get();
+            slot_data[..data.len()].copy_from_slice(data);
+            *self.sqes[slot].get() = sqe;
+        }
+        self.sq_tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Handles all pending submissions on the service side. The handler gets the
+    /// submission and its data slot and returns the result and the number of valid bytes
+    /// in the data slot. Returns the number of handled submissions.
+    pub fn process_submissions(
+        &self,
+        mut handler: impl FnMut(&FsRingSqe, &mut [u8; FS_RING_DATA_SLOT_SIZE]) -> (i64, u32),
+    ) -> usize {
+        let mut count = 0;
+        loop {
+            let head = self.sq_head.load(Ordering::Relaxed);
+            if head == self.sq_tail.load(Ordering::Acquire) {
+                return count;
+            }
+            let slot = Self::slot(head);
+            // the client doesn't touch the entry and the slot until it reaped the completion
+            let (sqe, data) = unsafe { (*self.sqes[slot].get(), &mut *self.data[slot].get()) };
+            let (result, data_len) = handler(&sqe, data);
+            self.sq_head.store(head.wrapping_add(1), Ordering::Release);
+
+            let cq_tail = self.cq_tail.load(Ordering::Relaxed);
+            unsafe {
+                *self.cqes[Self::slot(cq_tail)].get() = FsRingCqe {
+                    user_data: sqe.user_data,
+                    result,
+                    slot: slot as u32,
+                    data_len: data_len.min(FS_RING_DATA_SLOT_SIZE as u32),
+                };
+            }
+            self.cq_tail
+                .store(cq_tail.wrapping_add(1), Ordering::Release);
+            count += 1;
+        }
+    }
+
+    /// Dequeues the next completion on the client side. Passes the valid bytes of the
+    /// data slot to `f`, before the slot becomes free again.
+    pub fn reap_with<R>(&self, f: impl FnOnce(&FsRingCqe, &[u8]) -> R) -> Option<R> {
+        let head = self.cq_head.load(Ordering::Relaxed);
+        if head == self.cq_tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let cqe = unsafe { *self.cqes[Self::slot(head)].get() };
+        let data = unsafe { &*self.data[cqe.slot as usize % FS_RING_ENTRIES as usize].get() };
+        let ret = f(&cqe, &data[..cqe.data_len as usize]);
+        self.cq_head.store(head.wrapping_add(1), Ordering::Release);
+        Some(ret)
+    }
+
+    fn slot(index: u32) -> usize {
+        (index % FS_RING_ENTRIES) as usize
+    }
+}
+
+/// Errors of [`FsRing::submit`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FsRingError {
+    /// [`FS_RING_ENTRIES`] operations are in flight.
+    Full,
+    /// The data exceeds [`FS_RING_DATA_SLOT_SIZE`].
+    DataTooLarge,
+}
+
+/// An operation for the file system ring. See `fs_submit_batch`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FsRingOp<'a> {
+    /// Reads up to `count` bytes, but at most [`FS_RING_DATA_SLOT_SIZE`].
+    Read { fd: FD, count: usize },
+    /// Writes `data`, which must not exceed [`FS_RING_DATA_SLOT_SIZE`].
+    Write { fd: FD, data: &'a [u8] },
+    /// Sets the file offset.
+    LSeek { fd: FD, offset: u64 },
+}
+
+impl<'a> FsRingOp<'a> {
+    /// Returns the submission for the operation and the data for its data slot.
+    pub fn to_sqe(&self, user_data: u64) -> (FsRingSqe, &'a [u8]) {
+        let (opcode, fd, arg, data): (_, _, _, &[u8]) = match *self {
+            Self::Read { fd, count } => (FsRingOpcode::Read, fd, count as u64, &[]),
+            Self::Write { fd, data } => (FsRingOpcode::Write, fd, data.len() as u64, data),
+            Self::LSeek { fd, offset } => (FsRingOpcode::LSeek, fd, offset, &[]),
+        };
+        let sqe = FsRingSqe {
+            opcode: opcode as u32,
+            fd: fd.raw(),
+            arg,
+            user_data,
+        };
+        (sqe, data)
+    }
+}
+
+/// Completion of an operation of `fs_submit_batch`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsRingCompletion {
+    /// Index of the operation in the batch.
+    pub index: usize,
+    /// Transferred bytes or [`FS_RING_ERROR`].
+    pub result: i64,
+    /// The read bytes. Empty for other operations.
+    pub data: Vec<u8>,
+}
+
+/// Request to map the file system ring of the process. Sent as
+/// [`FsServiceRequest::RingSetup`], which has no content.
+#[derive(Copy, Clone, Debug)]
+pub struct FsRingSetupRequest;
+
+impl Rpc<FsService> for FsRingSetupRequest {
+    type Message = FsServiceRequest;
+    type Response = FsRingSetupResponse;
+
+    fn into_message(self) -> FsServiceRequest {
+        FsServiceRequest::RingSetup
+    }
+}
+
+/// Reply of the file system service to [`crate::rt::services::fs::FsServiceRequest::RingSetup`].
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FsRingSetupResponse {
+    /// Address of the [`FsRing`] in the address space of the client.
+    pub ring_addr: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use core::mem::size_of;
+
+    fn new_ring() -> Box<FsRing> {
+        // the service hands out zeroed memory, too
+        unsafe { Box::new_zeroed().assume_init() }
+    }
+
+    /// Handler that acts like a file system with a single file that contains "Hallo Welt!".
+    fn handle(sqe: &FsRingSqe, data: &mut [u8; FS_RING_DATA_SLOT_SIZE]) -> (i64, u32) {
+        let content = b"Hallo Welt!";
+        match FsRingOpcode::try_from(sqe.opcode) {
+            Ok(FsRingOpcode::Read) => {
+                let len = content.len().min(sqe.arg as usize);
+                data[..len].copy_from_slice(&content[..len]);
+                (len as i64, len as u32)
+            }
+            Ok(FsRingOpcode::Write) => (sqe.arg as i64, 0),
+            Ok(FsRingOpcode::LSeek) => (0, 0),
+            Err(_) => (FS_RING_ERROR, 0),
+        }
+    }
+
+    #[test]
+    fn test_ring_size() {
+        assert!(size_of::<FsRing>() < 10 * 4096);
+    }
+
+    #[test]
+    fn test_ring_batch() {
+        let ring = new_ring();
+        let fd = FD::new(3);
+        let ops = [
+            FsRingOp::Write { fd, data: b"Hallo" },
+            FsRingOp::LSeek { fd, offset: 0 },
+            FsRingOp::Read { fd, count: 5 },
+        ];
+        for (i, op) in ops.iter().enumerate() {
+            let (sqe, data) = op.to_sqe(i as u64);
+            ring.submit(sqe, data).unwrap();
+        }
+        assert_eq!(ring.in_flight(), 3);
+        assert_eq!(ring.process_submissions(handle), 3);
+        assert_eq!(ring.process_submissions(handle), 0);
+
+        let completions = core::iter::from_fn(|| {
+            ring.reap_with(|cqe, data| (cqe.user_data, cqe.result, Vec::from(data)))
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(
+            completions,
+            [
+                (0, 5, Vec::new()),
+                (1, 0, Vec::new()),
+                (2, 5, Vec::from(*b"Hallo"))
+            ]
+        );
+        assert_eq!(ring.in_flight(), 0);
+    }
+
+    #[test]
+    fn test_ring_full_and_wrap_around() {
+        let ring = new_ring();
+        let (sqe, _) = FsRingOp::Read {
+            fd: FD::new(3),
+            count: 100,
+        }
+        .to_sqe(0);
+        // multiple rounds, so that the indices wrap around the entries
+        for _ in 0..5 {
+            for _ in 0..FS_RING_ENTRIES {
+                ring.submit(sqe, &[]).unwrap();
+            }
+            assert_eq!(ring.submit(sqe, &[]), Err(FsRingError::Full));
+            assert_eq!(ring.process_submissions(handle), FS_RING_ENTRIES as usize);
+            // still full until the client reaped the completions
+            assert_eq!(ring.submit(sqe, &[]), Err(FsRingError::Full));
+            for _ in 0..FS_RING_ENTRIES {
+                let data = ring.reap_with(|_, data| Vec::from(data)).unwrap();
+                assert_eq!(data, b"Hallo Welt!");
+            }
+            assert!(ring.reap_with(|_, _| ()).is_none());
+        }
+        assert_eq!(
+            ring.submit(sqe, &[0; FS_RING_DATA_SLOT_SIZE + 1]),
+            Err(FsRingError::DataTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_invalid_opcode() {
+        let ring = new_ring();
+        let sqe = FsRingSqe {
+            opcode: 42,
+            ..Default::default()
+        };
+        ring.submit(sqe, &[]).unwrap();
+        ring.process_submissions(handle);
+        assert_eq!(ring.reap_with(|cqe, _| cqe.result), Some(FS_RING_ERROR));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; 16];
+        let response = FsRingSetupResponse {
+            ring_addr: 0x1000_0000,
+        };
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<FsRingSetupResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use crate::rt::services::chunked_bulk_call;
+use crate::rt::services::fs::FsService;
+use crate::rt::services::fs::{
+    FsSocketReply,
+    FsSocketRequest,
+    FsSocketResponse,
+    FS_SOCKET_MAX_IPC_DATA,
+};
+use crate::rt::services::rpc::rpc_call;
+
+/// Wrapper around the FS service portal for operations on local sockets.
+///
+/// A [`FsSocketRequest::Send`] with more than [`FS_SOCKET_MAX_IPC_DATA`] bytes gets split
+/// into multiple partial sends. The reply contains the sum of the sent bytes.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_service_socket(request: FsSocketRequest) -> FsSocketResponse {
+    match request {
+        FsSocketRequest::Send {
+            fd,
+            data,
+            dest,
+            partial: _,
+        } if data.len() > FS_SOCKET_MAX_IPC_DATA => {
+            chunked_bulk_call(&data, FS_SOCKET_MAX_IPC_DATA, |chunk| {
+                let request = FsSocketRequest::Send {
+                    fd,
+                    data: chunk.to_vec(),
+                    dest: dest.clone(),
+                    partial: true,
+                };
+                match rpc_call::<FsService, _>(request).unwrap()? {
+                    FsSocketReply::Sent(count) => Ok(count),
+                    reply => panic!("unexpected reply: {:?}", reply),
+                }
+            })
+            .map(FsSocketReply::Sent)
+        }
+        request => rpc_call::<FsService, _>(request).unwrap(),
+    }
+}
@@ -0,0 +1,146 @@
+use super::super::FD;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Maximum number of payload bytes of a single [`FsSocketRequest::Send`] or
+/// [`FsSocketRequest::Recv`]. The data travels through the UTCB, therefore larger
+/// transfers must be split.
+pub const FS_SOCKET_MAX_IPC_DATA: usize = 2048;
+
+/// Type of a local socket. Corresponds to `SOCK_STREAM` and `SOCK_DGRAM` of `AF_UNIX`.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SocketKind {
+    /// Connection-oriented byte stream.
+    Stream,
+    /// Connectionless messages that keep their boundaries.
+    Datagram,
+}
+
+/// Socket operations that a user app sends to the Fs Portal. Sockets share the file
+/// descriptors with files, i.e. [`super::super::FsCloseRequest`] closes them.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum FsSocketRequest {
+    /// Creates an unnamed socket. Replies with [`FsSocketReply::Fd`].
+    Socket(SocketKind),
+    /// Creates two connected sockets. Replies with [`FsSocketReply::FdPair`].
+    SocketPair(SocketKind),
+    /// Gives a socket a name, so that others can connect or send to it.
+    Bind { fd: FD, name: String },
+    /// Makes a bound stream socket accept connections.
+    Listen { fd: FD, backlog: usize },
+    /// Takes the next pending connection. Replies with [`FsSocketReply::Fd`].
+    Accept(FD),
+    /// Connects to the socket with the given name.
+    Connect { fd: FD, name: String },
+    /// Sends data. `dest` is required for unconnected datagram sockets.
+    /// Replies with [`FsSocketReply::Sent`]. `partial` marks a part of a larger send that
+    /// [`super::fs_service_socket`] split, because it exceeds
+    /// [`FS_SOCKET_MAX_IPC_DATA`]. Datagram sockets reject partial sends with
+    /// [`SocketError::MessageTooLong`], as datagrams must stay intact.
+    Send {
+        fd: FD,
+        data: Vec<u8>,
+        dest: Option<String>,
+        partial: bool,
+    },
+    /// Receives at most `max_len` bytes. Replies with [`FsSocketReply::Received`].
+    Recv { fd: FD, max_len: usize },
+}
+
+/// Successful replies to a [`FsSocketRequest`].
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub enum FsSocketReply {
+    Fd(FD),
+    FdPair(FD, FD),
+    Done,
+    /// Number of bytes that were sent.
+    Sent(usize),
+    /// Received data and the name of the sender, if it is a bound datagram socket.
+    /// Empty data on a stream socket means that the peer closed the connection.
+    Received {
+        data: Vec<u8>,
+        from: Option<String>,
+    },
+}
+
+/// Errors of socket operations. They map to the corresponding Linux error codes.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SocketError {
+    /// The file descriptor is not open. (`EBADF`)
+    BadFd,
+    /// The file descriptor refers to a file. (`ENOTSOCK`)
+    NotASocket,
+    /// Invalid argument or operation in the current state. (`EINVAL`)
+    InvalidArgument,
+    /// Another socket is already bound to the name. (`EADDRINUSE`)
+    AddressInUse,
+    /// No socket is bound to the name. (`ENOENT`)
+    NotFound,
+    /// The socket of the peer has a different [`SocketKind`]. (`EPROTOTYPE`)
+    WrongKind,
+    /// The peer doesn't accept connections or doesn't exist anymore. (`ECONNREFUSED`)
+    ConnectionRefused,
+    /// The socket is not connected. (`ENOTCONN`)
+    NotConnected,
+    /// The socket is already connected. (`EISCONN`)
+    AlreadyConnected,
+    /// The datagram socket is not connected and no destination was given. (`EDESTADDRREQ`)
+    DestinationRequired,
+    /// The peer closed the connection. (`EPIPE`)
+    BrokenPipe,
+    /// The operation would block. The file server never blocks. (`EAGAIN`)
+    WouldBlock,
+    /// The datagram is larger than the receive buffer. (`EMSGSIZE`)
+    MessageTooLong,
+    /// The operation is not supported for this kind of socket. (`EOPNOTSUPP`)
+    Unsupported,
+}
+
+/// Response of the Fs Portal to a [`FsSocketRequest`].
+pub type FsSocketResponse = Result<FsSocketReply, SocketError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = FsSocketRequest::Send {
+            fd: FD::new(3),
+            data: vec![0xff; FS_SOCKET_MAX_IPC_DATA],
+            dest: Some(String::from("/tmp/socket")),
+            partial: true,
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        match libhedron::ipc_postcard::from_bytes::<FsSocketRequest>(&buf).unwrap() {
+            FsSocketRequest::Send {
+                fd,
+                data,
+                dest,
+                partial,
+            } => {
+                assert_eq!(fd, FD::new(3));
+                assert!(partial);
+                assert_eq!(data.len(), FS_SOCKET_MAX_IPC_DATA);
+                assert_eq!(dest.as_deref(), Some("/tmp/socket"));
+            }
+            request => panic!("unexpected request: {:?}", request),
+        }
+
+        let response: FsSocketResponse = Ok(FsSocketReply::Received {
+            data: vec![1, 2, 3],
+            from: None,
+        });
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<FsSocketResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
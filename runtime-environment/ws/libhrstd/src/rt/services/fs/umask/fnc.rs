@@ -0,0 +1,10 @@
+use crate::rt::services::fs::umask::FsUmaskRequest;
+use crate::rt::services::fs::FsService;
+use crate::rt::services::rpc::rpc_call;
+
+/// Wrapper around the FS service portal to set the umask of the process. Returns the
+/// previous umask.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn fs_service_umask(request: FsUmaskRequest) -> u16 {
+    rpc_call::<FsService, _>(request).unwrap()
+}
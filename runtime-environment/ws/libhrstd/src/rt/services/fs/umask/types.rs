@@ -0,0 +1,20 @@
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Data send via UTCB to Fs Umask Portal. The reply is the previous umask.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FsUmaskRequest {
+    umask: u16,
+}
+
+impl FsUmaskRequest {
+    pub fn new(umask: u16) -> Self {
+        FsUmaskRequest { umask }
+    }
+
+    pub fn umask(&self) -> u16 {
+        self.umask
+    }
+}
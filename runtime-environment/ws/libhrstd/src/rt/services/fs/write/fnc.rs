@@ -1,24 +1,34 @@
-use crate::cap_space::user::UserAppCapSpace;
-#[cfg(feature = "foreign_rust_rt")]
-use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
-use crate::rt::services::fs::FsServiceRequest;
-use crate::rt::services::fs::FsWriteRequest;
-use crate::rt::user_load_utcb::user_load_utcb_mut;
-#[cfg(feature = "native_rust_rt")]
-use libhedron::syscall::sys_call;
+use crate::mem::UserPtrOrEmbedded;
+use crate::rt::services::chunked_bulk_call;
+use crate::rt::services::fs::{
+    FsError,
+    FsService,
+    FsWriteRequest,
+    FsWriteSrc,
+    FS_WRITE_MAX_EMBEDDED,
+};
+use crate::rt::services::rpc::rpc_call;
 
 /// Wrapper around the FS service portal to write to files.
 /// Returns the number of written bytes.
+///
+/// Embedded data that doesn't fit into the UTCB gets written with multiple requests of
+/// at most [`FS_WRITE_MAX_EMBEDDED`] bytes. It fails only if the first request fails.
 #[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
-pub fn fs_service_write(request: FsWriteRequest) -> usize {
-    let utcb = user_load_utcb_mut();
-    let request = FsServiceRequest::Write(request);
-    utcb.store_data(&request).unwrap();
-
-    #[cfg(feature = "native_rust_rt")]
-    sys_call(UserAppCapSpace::FsServicePT.val()).unwrap();
-    #[cfg(feature = "foreign_rust_rt")]
-    sys_hybrid_call(UserAppCapSpace::FsServicePT.val()).unwrap();
-
-    utcb.load_data().unwrap()
+pub fn fs_service_write(request: FsWriteRequest) -> Result<usize, FsError> {
+    match request.src() {
+        FsWriteSrc::Data(UserPtrOrEmbedded::EmbeddedSlice(data))
+            if data.len() > FS_WRITE_MAX_EMBEDDED =>
+        {
+            chunked_bulk_call(data, FS_WRITE_MAX_EMBEDDED, |chunk| {
+                let chunk_request = FsWriteRequest::new(
+                    request.fd(),
+                    UserPtrOrEmbedded::EmbeddedSlice(chunk.to_vec()),
+                    chunk.len(),
+                );
+                rpc_call::<FsService, _>(chunk_request).unwrap()
+            })
+        }
+        _ => rpc_call::<FsService, _>(request).unwrap(),
+    }
 }
@@ -1,30 +1,98 @@
 use super::super::FD;
 use crate::mem::UserPtrOrEmbedded;
+use crate::rt::services::fs::FsBufferRef;
 use libhedron::ipc_serde::{
     Deserialize,
     Serialize,
 };
+use libhedron::UTCB_DATA_CAPACITY;
+
+/// Maximum number of bytes that a [`FsWriteRequest`] can embed, so that the request still
+/// fits into the UTCB. [`crate::rt::services::fs::fs_service_write`] splits larger
+/// embedded slices into multiple requests. Leaves room for the serialized request around
+/// the data.
+pub const FS_WRITE_MAX_EMBEDDED: usize = UTCB_DATA_CAPACITY - 64;
 
 /// Data send via UTCB to Fs Write Portal.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FsWriteRequest {
     fd: FD,
-    data: UserPtrOrEmbedded<u8>,
+    src: FsWriteSrc,
     count: usize,
 }
 
 impl FsWriteRequest {
     pub fn new(fd: FD, data: UserPtrOrEmbedded<u8>, count: usize) -> Self {
-        FsWriteRequest { fd, data, count }
+        FsWriteRequest {
+            fd,
+            src: FsWriteSrc::Data(data),
+            count,
+        }
+    }
+
+    /// Writes the data of a buffer that was registered with
+    /// [`crate::rt::services::fs::fs_register_buffer`].
+    pub fn new_registered(fd: FD, buffer: FsBufferRef, count: usize) -> Self {
+        FsWriteRequest {
+            fd,
+            src: FsWriteSrc::Registered(buffer),
+            count,
+        }
     }
 
     pub fn fd(&self) -> FD {
         self.fd
     }
-    pub fn data(&self) -> &UserPtrOrEmbedded<u8> {
-        &self.data
+    pub fn src(&self) -> &FsWriteSrc {
+        &self.src
     }
     pub fn count(&self) -> usize {
         self.count
     }
 }
+
+/// Source of the data of a [`FsWriteRequest`].
+#[derive(Debug, Serialize, Deserialize)]
+pub enum FsWriteSrc {
+    /// Data that is part of the request.
+    Data(UserPtrOrEmbedded<u8>),
+    /// Memory of a registered buffer, which the roottask already mapped.
+    Registered(FsBufferRef),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::fs::{
+        FsBufferId,
+        FsServiceRequest,
+    };
+    use alloc::vec;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let buffer = FsBufferRef::new(FsBufferId::new(2), 0);
+        let request = FsWriteRequest::new_registered(FD::new(4), buffer, 8192);
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        let request = libhedron::ipc_postcard::from_bytes::<FsWriteRequest>(&buf).unwrap();
+        assert_eq!(request.fd(), FD::new(4));
+        assert!(matches!(request.src(), FsWriteSrc::Registered(b) if *b == buffer));
+        assert_eq!(request.count(), 8192);
+    }
+
+    fn fits_into_utcb(data_len: usize) -> bool {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let data = UserPtrOrEmbedded::EmbeddedSlice(vec![0xff; data_len]);
+        let request = FsServiceRequest::Write(FsWriteRequest::new(FD::new(4), data, data_len));
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).is_ok()
+    }
+
+    #[test]
+    fn test_max_embedded_fits_into_utcb() {
+        assert!(fits_into_utcb(0));
+        assert!(fits_into_utcb(FS_WRITE_MAX_EMBEDDED - 1));
+        assert!(fits_into_utcb(FS_WRITE_MAX_EMBEDDED));
+        assert!(!fits_into_utcb(UTCB_DATA_CAPACITY));
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_sm_down;
+use crate::rt::services::irq::{
+    Irq,
+    IrqAttachRequest,
+    IrqAttachment,
+    IrqDetachRequest,
+    IrqService,
+    IrqServiceResponse,
+    IrqSource,
+};
+use crate::rt::services::rpc::rpc_call;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_sm_down;
+use libhedron::syscall::SmCtrlZeroCounterStrategy;
+
+/// Attaches an interrupt to the caller, see [`IrqAttachRequest`].
+pub fn irq_service_attach(source: IrqSource) -> IrqServiceResponse<IrqAttachment> {
+    rpc_call::<IrqService, _>(IrqAttachRequest { source }).unwrap()
+}
+
+/// Detaches an interrupt of [`irq_service_attach`].
+pub fn irq_service_detach(irq: Irq) -> IrqServiceResponse<()> {
+    rpc_call::<IrqService, _>(IrqDetachRequest { irq }).unwrap()
+}
+
+impl Irq {
+    /// Blocks until the interrupt occurs. Interrupts that occurred since the last call
+    /// are coalesced, i.e. the call returns only once for them.
+    pub fn wait(self) {
+        #[cfg(feature = "native_rust_rt")]
+        let syscall_fn = sys_sm_down;
+        #[cfg(feature = "foreign_rust_rt")]
+        let syscall_fn = sys_hybrid_sm_down;
+
+        syscall_fn(self.sel(), SmCtrlZeroCounterStrategy::SetToZero, None)
+            .unwrap_or_else(|e| panic!("waiting for GSI {} failed: {:?}", self.gsi(), e));
+    }
+}
@@ -0,0 +1,7 @@
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+mod fnc;
+mod types;
+
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use fnc::*;
+pub use types::*;
@@ -0,0 +1,175 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::service_protocol;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use libhedron::CapSel;
+
+/// Maximum number of interrupts that a process can have attached at the same time.
+pub const MAX_IRQS_PER_PROCESS: u64 =
+    UserAppCapSpace::IrqSmEnd as u64 - UserAppCapSpace::IrqSmBase as u64 + 1;
+
+/// Location of a PCI function.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PciFunction {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+/// The interrupt that a driver wants to receive.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum IrqSource {
+    /// A global system interrupt (GSI), i.e. a pin of an IOAPIC, with the trigger mode and
+    /// polarity that Hedron knows from the ACPI tables. ISA interrupts use the GSI with the
+    /// same number, unless the ACPI tables override it.
+    Gsi(u32),
+    /// The GSI of the interrupt pin of a PCI function. Unlike [`Self::Gsi`], it is
+    /// level-triggered and active-low.
+    PciIntx(u32),
+    /// A message signaled interrupt (MSI) of a PCI function. The roottask picks a free GSI
+    /// and programs the MSI capability of the function.
+    Msi(PciFunction),
+}
+
+/// The message that the function of an [`IrqSource::Msi`] sends. The roottask programs it
+/// into the MSI capability already; a driver only needs it for MSI-X.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct MsiMessage {
+    pub address: u64,
+    pub data: u64,
+}
+
+/// An interrupt that the caller attached. Each interrupt performs an "up" operation on the
+/// interrupt semaphore at [`Self::sel`], on which the process waits.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Irq {
+    gsi: u32,
+    sel: CapSel,
+}
+
+impl Irq {
+    pub const fn new(gsi: u32, sel: CapSel) -> Self {
+        Self { gsi, sel }
+    }
+
+    /// Returns the GSI of the interrupt.
+    pub const fn gsi(self) -> u32 {
+        self.gsi
+    }
+
+    /// Returns the selector of the interrupt semaphore in the capability space of the
+    /// process.
+    pub const fn sel(self) -> CapSel {
+        self.sel
+    }
+}
+
+/// Result of [`IrqAttachRequest`].
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct IrqAttachment {
+    pub irq: Irq,
+    /// `Some` for [`IrqSource::Msi`].
+    pub msi: Option<MsiMessage>,
+}
+
+/// Routes the interrupt to the CPU of the caller and delegates its interrupt semaphore to
+/// the caller. Each interrupt can only be attached to one process or driver at a time.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct IrqAttachRequest {
+    pub source: IrqSource,
+}
+
+/// Revokes the interrupt semaphore from the caller and frees the interrupt. The roottask
+/// disables the MSI capability of an [`IrqSource::Msi`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct IrqDetachRequest {
+    pub irq: Irq,
+}
+
+/// Request that a user app sends to the interrupt service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum IrqServiceRequest {
+    Attach(IrqAttachRequest),
+    Detach(IrqDetachRequest),
+}
+
+/// Errors that the interrupt service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum IrqServiceError {
+    /// The GSI doesn't exist.
+    InvalidIrq,
+    /// The interrupt is attached already or belongs to a device of the roottask.
+    Busy,
+    /// All GSIs are in use, hence there is none for an MSI.
+    NoFreeVector,
+    /// The caller has [`MAX_IRQS_PER_PROCESS`] interrupts attached already.
+    TooManyIrqs,
+    /// The PCI function doesn't exist, has no MSI capability, or the platform has no
+    /// memory-mapped PCI configuration space, which Hedron needs for MSIs.
+    MsiUnsupported,
+    /// The caller has no such interrupt attached.
+    UnknownIrq,
+    /// Hedron refused to route the interrupt.
+    AssignFailed,
+    /// Only drivers, i.e. the roottask and the programs it started itself, can attach
+    /// interrupts.
+    PermissionDenied,
+}
+
+/// Response of the interrupt service.
+pub type IrqServiceResponse<T> = Result<T, IrqServiceError>;
+
+service_protocol! {
+    /// The interrupt service. Driver processes attach interrupts of devices. The roottask
+    /// delegates the interrupt semaphore of Hedron to the process, which waits on it
+    /// without the roottask.
+    pub service IrqService(IrqServicePT): IrqServiceRequest {
+        Attach(IrqAttachRequest) -> IrqServiceResponse<IrqAttachment>,
+        Detach(IrqDetachRequest) -> IrqServiceResponse<()>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_max_irqs() {
+        assert_eq!(MAX_IRQS_PER_PROCESS, 16);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = IrqAttachRequest {
+            source: IrqSource::Msi(PciFunction {
+                bus: 0,
+                device: 3,
+                function: 0,
+            }),
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<IrqServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: IrqServiceResponse<IrqAttachment> = Ok(IrqAttachment {
+            irq: Irq::new(23, UserAppCapSpace::IrqSmBase.val()),
+            msi: Some(MsiMessage {
+                address: 0xfee0_0000,
+                data: 0x4041,
+            }),
+        });
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<IrqServiceResponse<IrqAttachment>>(&buf).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::process::consts::ProcessId;
+use crate::rt::services::logging::{
+    LogClearRequest,
+    LogEntry,
+    LogGetLevelRequest,
+    LogLevel,
+    LogReadRequest,
+    LogScope,
+    LogService,
+    LogServiceResponse,
+    LogSetConsoleRequest,
+    LogSetLevelRequest,
+    LogWriteRequest,
+};
+use crate::rt::services::rpc::rpc_call;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Adds a record of the calling process to the log of the roottask.
+pub fn log_service_write(level: LogLevel, target: String, msg: String) {
+    rpc_call::<LogService, _>(LogWriteRequest { level, target, msg }).unwrap()
+}
+
+/// Reads all records from the sequence number `from` on that match `pid` and `level`, see
+/// [`LogReadRequest`]. Returns them together with the sequence number after the last
+/// record of the log, e.g. for [`log_service_clear`].
+pub fn log_service_read(
+    from: u64,
+    pid: Option<ProcessId>,
+    level: LogLevel,
+) -> (Vec<LogEntry>, u64) {
+    let mut entries = Vec::new();
+    let mut from = from;
+    loop {
+        let response = rpc_call::<LogService, _>(LogReadRequest { from, pid, level }).unwrap();
+        entries.extend(response.entries);
+        from = response.next;
+        if !response.more {
+            return (entries, from);
+        }
+    }
+}
+
+/// Removes all records before the sequence number `until` from the log.
+pub fn log_service_clear(until: u64) -> LogServiceResponse {
+    rpc_call::<LogService, _>(LogClearRequest { until }).unwrap()
+}
+
+/// Sets the level of a filter of the log.
+pub fn log_service_set_level(scope: LogScope, level: LogLevel) -> LogServiceResponse {
+    rpc_call::<LogService, _>(LogSetLevelRequest { scope, level }).unwrap()
+}
+
+/// Returns the least severe level that the log takes from the process, or from the calling
+/// process with `None`.
+pub fn log_service_level(pid: Option<ProcessId>) -> LogLevel {
+    rpc_call::<LogService, _>(LogGetLevelRequest { pid }).unwrap()
+}
+
+/// Enables or disables the output of the log on the console.
+pub fn log_service_set_console(enabled: bool) -> LogServiceResponse {
+    rpc_call::<LogService, _>(LogSetConsoleRequest { enabled }).unwrap()
+}
@@ -0,0 +1,355 @@
+use crate::process::consts::ProcessId;
+use crate::service_protocol;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{
+    Display,
+    Formatter,
+};
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use log::{
+    Level,
+    LevelFilter,
+};
+
+/// Longest message of a log record. The roottask truncates longer messages.
+pub const LOG_MAX_MSG_LEN: usize = 1024;
+
+/// Longest target of a log record. The roottask truncates longer targets.
+pub const LOG_MAX_TARGET_LEN: usize = 128;
+
+/// Severity of log records and threshold of log filters. Like [`LevelFilter`], but it can
+/// travel through the UTCB. Records never have the level `Off`.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// Returns the name in lowercase, e.g. `info`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+
+    /// Parses the name of a level, as returned by [`Self::as_str`].
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Self::Off,
+            Self::Error,
+            Self::Warn,
+            Self::Info,
+            Self::Debug,
+            Self::Trace,
+        ]
+        .into_iter()
+        .find(|level| level.as_str().eq_ignore_ascii_case(name))
+    }
+
+    pub const fn level_filter(self) -> LevelFilter {
+        match self {
+            Self::Off => LevelFilter::Off,
+            Self::Error => LevelFilter::Error,
+            Self::Warn => LevelFilter::Warn,
+            Self::Info => LevelFilter::Info,
+            Self::Debug => LevelFilter::Debug,
+            Self::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<Level> for LogLevel {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Error => Self::Error,
+            Level::Warn => Self::Warn,
+            Level::Info => Self::Info,
+            Level::Debug => Self::Debug,
+            Level::Trace => Self::Trace,
+        }
+    }
+}
+
+impl From<LevelFilter> for LogLevel {
+    fn from(filter: LevelFilter) -> Self {
+        filter.to_level().map_or(Self::Off, Self::from)
+    }
+}
+
+/// A record in the log buffer of the roottask.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Position in the log. Increases by one with each record, hence gaps show that the
+    /// roottask dropped records, because the buffer was full.
+    pub seq: u64,
+    /// Nanoseconds since boot. `None` if the roottask has no timestamps, e.g. due to the
+    /// boot argument `log_timestamps=off`.
+    pub timestamp_ns: Option<u64>,
+    /// Process that created the record.
+    pub pid: ProcessId,
+    pub level: LogLevel,
+    /// Module path of the origin, e.g. `libroottask::process`.
+    pub target: String,
+    pub msg: String,
+}
+
+impl Display for LogEntry {
+    /// Formats the record as a line like `[    2.000417309] [ INFO] pid=0 roottask_bin: msg`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        const NS_PER_SEC: u64 = 1_000_000_000;
+        if let Some(ns) = self.timestamp_ns {
+            write!(f, "[{:>5}.{:09}] ", ns / NS_PER_SEC, ns % NS_PER_SEC)?;
+        }
+        write!(
+            f,
+            "[{:>5}] pid={} {}: {}",
+            self.level.as_str().to_ascii_uppercase(),
+            self.pid,
+            self.target,
+            self.msg
+        )
+    }
+}
+
+/// Which log records a filter applies to. See [`LogSetLevelRequest`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum LogScope {
+    /// All records that no other filter covers.
+    Default,
+    /// The records of a process. The roottask is the process with the PID
+    /// [`crate::process::consts::ROOTTASK_PROCESS_PID`].
+    Process(ProcessId),
+    /// The records whose target starts with the module path, e.g. `libroottask::mem`. The
+    /// longest matching module wins over the filter of the process.
+    Module(String),
+}
+
+/// Adds a record of the caller to the log. The roottask drops it if the filters exclude it.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogWriteRequest {
+    pub level: LogLevel,
+    pub target: String,
+    pub msg: String,
+}
+
+/// Reads the records in the log from the sequence number `from` on. Only records of the
+/// process `pid`, if given, and with the given level or a more severe one are included.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogReadRequest {
+    pub from: u64,
+    pub pid: Option<ProcessId>,
+    pub level: LogLevel,
+}
+
+/// Removes all records before the sequence number `until` from the log, e.g. the
+/// records that the caller read.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogClearRequest {
+    pub until: u64,
+}
+
+/// Sets the level of a filter. Records with a less severe level are neither stored nor
+/// printed.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogSetLevelRequest {
+    pub scope: LogScope,
+    pub level: LogLevel,
+}
+
+/// Returns the least severe level of the records that the filters let through for the
+/// process, or for the caller with `None`. Loggers can skip all other records.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogGetLevelRequest {
+    pub pid: Option<ProcessId>,
+}
+
+/// Enables or disables the output of the log on the serial port and debugcon. The log
+/// buffer keeps all records either way.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogSetConsoleRequest {
+    pub enabled: bool,
+}
+
+/// Request that a user app sends to the log service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum LogServiceRequest {
+    Write(LogWriteRequest),
+    Read(LogReadRequest),
+    Clear(LogClearRequest),
+    SetLevel(LogSetLevelRequest),
+    GetLevel(LogGetLevelRequest),
+    SetConsole(LogSetConsoleRequest),
+}
+
+/// Reply to [`LogReadRequest`]. Contains as many records as fit into the UTCB.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LogReadResponse {
+    pub entries: Vec<LogEntry>,
+    /// Sequence number for the next request.
+    pub next: u64,
+    /// True if further records match after the ones of this response.
+    pub more: bool,
+}
+
+impl LogReadResponse {
+    /// Drops records from the end until the serialized response fits into `capacity`
+    /// bytes. `next` and `more` describe the position after the last of the `entries`.
+    pub fn new_fitting(
+        mut entries: Vec<LogEntry>,
+        next: u64,
+        mut more: bool,
+        capacity: usize,
+    ) -> Self {
+        let mut buf = vec![0; capacity];
+        let mut dropped = false;
+        loop {
+            let response = Self {
+                next: match (dropped, entries.last()) {
+                    (true, Some(entry)) => entry.seq + 1,
+                    _ => next,
+                },
+                entries,
+                more,
+            };
+            if libhedron::ipc_postcard::to_slice(&response, &mut buf).is_ok() {
+                return response;
+            }
+            entries = response.entries;
+            entries.pop();
+            dropped = true;
+            more = true;
+        }
+    }
+}
+
+/// Errors that the log service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum LogServiceError {
+    /// Only privileged processes (the roottask and the programs it started itself) can
+    /// clear the log, change the default and module filters, and toggle the console.
+    /// Other processes can change the filters of themselves and of their children.
+    PermissionDenied,
+    /// There is no process with the given PID.
+    NoSuchProcess,
+    /// The module path of the filter is empty.
+    InvalidModule,
+}
+
+/// Response of the log service.
+pub type LogServiceResponse = Result<(), LogServiceError>;
+
+service_protocol! {
+    /// The log service. The roottask keeps the log records of itself and of all processes
+    /// that use the user logger of native apps in a buffer and prints them on the console,
+    /// unless it is disabled.
+    pub service LogService(LogServicePT): LogServiceRequest {
+        Write(LogWriteRequest) -> (),
+        Read(LogReadRequest) -> LogReadResponse,
+        Clear(LogClearRequest) -> LogServiceResponse,
+        SetLevel(LogSetLevelRequest) -> LogServiceResponse,
+        GetLevel(LogGetLevelRequest) -> LogLevel,
+        SetConsole(LogSetConsoleRequest) -> LogServiceResponse,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use alloc::format;
+    use alloc::string::ToString;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    fn entry(seq: u64, msg: String) -> LogEntry {
+        LogEntry {
+            seq,
+            timestamp_ns: Some(2_000_417_309),
+            pid: 3,
+            level: LogLevel::Warn,
+            target: String::from("hello"),
+            msg,
+        }
+    }
+
+    #[test]
+    fn test_log_level() {
+        assert_eq!(LogLevel::parse("DEBUG"), Some(LogLevel::Debug));
+        assert_eq!(LogLevel::parse("off"), Some(LogLevel::Off));
+        assert_eq!(LogLevel::parse("verbose"), None);
+        assert_eq!(LogLevel::from(LevelFilter::Off), LogLevel::Off);
+        assert_eq!(
+            LogLevel::from(Level::Trace).level_filter(),
+            LevelFilter::Trace
+        );
+        assert!(LogLevel::Error < LogLevel::Info);
+    }
+
+    #[test]
+    fn test_log_entry_fmt() {
+        assert_eq!(
+            entry(0, String::from("disk full")).to_string(),
+            "[    2.000417309] [ WARN] pid=3 hello: disk full"
+        );
+        let mut entry = entry(0, String::from("disk full"));
+        entry.timestamp_ns = None;
+        assert_eq!(entry.to_string(), "[ WARN] pid=3 hello: disk full");
+    }
+
+    #[test]
+    fn test_read_response_new_fitting() {
+        let entries = (0..10)
+            .map(|seq| entry(seq, format!("{:01000}", seq)))
+            .collect::<Vec<_>>();
+        let response = LogReadResponse::new_fitting(entries.clone(), 10, false, UTCB_DATA_CAPACITY);
+        assert!(response.more);
+        assert!(!response.entries.is_empty() && response.entries.len() < 10);
+        assert_eq!(response.next, response.entries.len() as u64);
+        assert_eq!(response.entries[..], entries[..response.entries.len()]);
+
+        let response =
+            LogReadResponse::new_fitting(entries[..1].to_vec(), 10, false, UTCB_DATA_CAPACITY);
+        assert!(!response.more);
+        assert_eq!(response.next, 10);
+        let response =
+            LogReadResponse::new_fitting(entries[..1].to_vec(), 1, true, UTCB_DATA_CAPACITY);
+        assert!(response.more);
+        assert_eq!(response.next, 1);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = LogSetLevelRequest {
+            scope: LogScope::Module(String::from("libroottask::mem")),
+            level: LogLevel::Debug,
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<LogServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: LogServiceResponse = Err(LogServiceError::PermissionDenied);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<LogServiceResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
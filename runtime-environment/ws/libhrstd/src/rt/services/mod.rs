@@ -1,5 +1,111 @@
 pub mod allocate;
+pub mod build_info;
 pub mod echo;
+pub mod fileserver;
 pub mod fs;
+pub mod irq;
+pub mod logging;
+pub mod name;
+pub mod network;
+pub mod pci;
+pub mod perf_counter;
+pub mod process;
+pub mod process_signal;
+pub mod rpc;
+pub mod scheduling;
+pub mod semaphore;
+pub mod serial_transfer;
+pub mod shm;
 pub mod stderr;
+pub mod stdin;
 pub mod stdout;
+pub mod system_time;
+pub mod timer;
+
+/// Splits bulk data of a request that doesn't fit into the UTCB into chunks of at most
+/// `chunk_size` bytes. `call` sends one request per chunk and returns the number of bytes
+/// that the service processed. Returns the sum of all chunks.
+///
+/// Stops after the first chunk that the service didn't process completely, i.e. a short
+/// write. If a later chunk fails, the bytes of the previous chunks are returned, like a
+/// short write; an error is only returned if the first chunk fails. Empty data results
+/// in a single call with an empty chunk.
+#[allow(unused)]
+pub(super) fn chunked_bulk_call<E>(
+    data: &[u8],
+    chunk_size: usize,
+    mut call: impl FnMut(&[u8]) -> Result<usize, E>,
+) -> Result<usize, E> {
+    if data.is_empty() {
+        return call(data);
+    }
+    let mut done = 0;
+    for chunk in data.chunks(chunk_size) {
+        match call(chunk) {
+            Ok(count) => {
+                done += count;
+                if count < chunk.len() {
+                    break;
+                }
+            }
+            Err(e) if done == 0 => return Err(e),
+            Err(_) => break,
+        }
+    }
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn chunk_lens(data_len: usize, chunk_size: usize) -> Vec<usize> {
+        let mut lens = Vec::new();
+        let done = chunked_bulk_call::<()>(&vec![0; data_len], chunk_size, |chunk| {
+            lens.push(chunk.len());
+            Ok(chunk.len())
+        })
+        .unwrap();
+        assert_eq!(done, data_len);
+        lens
+    }
+
+    #[test]
+    fn test_chunked_bulk_call_boundaries() {
+        assert_eq!(chunk_lens(0, 100), [0]);
+        assert_eq!(chunk_lens(99, 100), [99]);
+        assert_eq!(chunk_lens(100, 100), [100]);
+        assert_eq!(chunk_lens(101, 100), [100, 1]);
+        assert_eq!(chunk_lens(201, 100), [100, 100, 1]);
+    }
+
+    #[test]
+    fn test_chunked_bulk_call_short_write_and_errors() {
+        let data = [0; 250];
+        // the service only takes half of the second chunk
+        let mut calls = 0;
+        let done = chunked_bulk_call::<()>(&data, 100, |chunk| {
+            calls += 1;
+            Ok(if calls == 2 { 50 } else { chunk.len() })
+        });
+        assert_eq!(done, Ok(150));
+        assert_eq!(calls, 2);
+
+        assert_eq!(
+            chunked_bulk_call(&data, 100, |_| Err::<usize, _>("e")),
+            Err("e")
+        );
+
+        let mut calls = 0;
+        let done = chunked_bulk_call(&data, 100, |chunk| {
+            calls += 1;
+            if calls == 1 {
+                Ok(chunk.len())
+            } else {
+                Err("e")
+            }
+        });
+        assert_eq!(done, Ok(100));
+    }
+}
@@ -0,0 +1,147 @@
+#[cfg(feature = "native_rust_rt")]
+use crate::kobjects::PortalIdentifier;
+use crate::process::consts::ProcessId;
+#[cfg(feature = "native_rust_rt")]
+use crate::rt::services::name::NameHostRequest;
+use crate::rt::services::name::{
+    is_valid_service_name,
+    NameLookupRequest,
+    NameLookupResponse,
+    NameRegisterRequest,
+    NameService,
+    NameServiceError,
+    NameServiceResponse,
+    NameUnregisterRequest,
+    ServiceAccess,
+};
+use crate::rt::services::rpc::rpc_call;
+#[cfg(feature = "native_rust_rt")]
+use crate::rt::user_load_utcb::set_service_ec_stack;
+#[cfg(feature = "native_rust_rt")]
+use crate::sync::mutex::SimpleMutex;
+#[cfg(feature = "native_rust_rt")]
+use crate::uaddress_space::USER_SERVICE_UTCB_ADDR;
+use alloc::string::ToString;
+#[cfg(feature = "native_rust_rt")]
+use core::alloc::Layout;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::mem::PAGE_SIZE;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_reply;
+use libhedron::syscall::DelegateFlags;
+use libhedron::{
+    CapSel,
+    CrdObjPT,
+    PTCapPermissions,
+    TypedItem,
+    Utcb,
+};
+
+/// Size of the stack of the EC that handles the calls of a hosted service.
+#[cfg(feature = "native_rust_rt")]
+const SERVICE_EC_STACK_SIZE: usize = 16 * PAGE_SIZE;
+
+/// Handler of a hosted service; see [`name_service_host`].
+pub type HostedServiceHandler = fn(client: ProcessId, utcb: &mut Utcb);
+
+/// The handler of the service that the app hosts and the stack top of its EC.
+#[cfg(feature = "native_rust_rt")]
+static HOSTED_SERVICE: SimpleMutex<Option<(HostedServiceHandler, u64)>> = SimpleMutex::new(None);
+
+/// Registers the portal at `pt_sel` in the capability space of the caller under `name`.
+/// The name stays registered until the caller unregisters it or exits.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn name_service_register(
+    name: &str,
+    pt_sel: CapSel,
+    access: ServiceAccess,
+) -> NameServiceResponse {
+    check_name(name)?;
+    let item = TypedItem::delegate(
+        CrdObjPT::new(pt_sel, 0, PTCapPermissions::CALL),
+        DelegateFlags::default(),
+    );
+    rpc_call::<NameService, _>(NameRegisterRequest {
+        name: name.to_string(),
+        item,
+        access,
+    })
+    .unwrap()
+}
+
+/// Hosts a service under `name`: each call of a client runs `handler` with the PID of the
+/// client and the UTCB with the request. The handler stores the reply in the UTCB. It runs
+/// on its own EC, which can use all services, but shares the memory with the rest of the
+/// app. Each app can host a single service. See [`NameHostRequest`].
+#[cfg(feature = "native_rust_rt")]
+pub fn name_service_host(
+    name: &str,
+    access: ServiceAccess,
+    handler: HostedServiceHandler,
+) -> NameServiceResponse {
+    check_name(name)?;
+    let mut hosted_service = HOSTED_SERVICE.lock();
+    if hosted_service.is_some() {
+        return Err(NameServiceError::AlreadyHosting);
+    }
+    let layout = Layout::from_size_align(SERVICE_EC_STACK_SIZE, PAGE_SIZE).unwrap();
+    let stack_begin = unsafe { alloc::alloc::alloc(layout) } as u64;
+    let stack_end = stack_begin + SERVICE_EC_STACK_SIZE as u64;
+    // like the main stack, see USER_STACK_TOP
+    let stack_top = stack_end - 64 + 8;
+    *hosted_service = Some((handler, stack_top));
+    drop(hosted_service);
+    set_service_ec_stack(stack_begin, stack_end);
+
+    let response = rpc_call::<NameService, _>(NameHostRequest {
+        name: name.to_string(),
+        entry: hosted_service_entry as usize as u64,
+        stack_top,
+        access,
+    })
+    .unwrap();
+    if response.is_err() {
+        // the stack stays reserved
+        *HOSTED_SERVICE.lock() = None;
+    }
+    response
+}
+
+/// Looks up the portal of the service `name`, e.g. `"fs"`, and returns its selector in
+/// the capability space of the caller. Each lookup occupies another selector for
+/// received capabilities.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn name_service_lookup(name: &str) -> NameLookupResponse {
+    check_name(name)?;
+    rpc_call::<NameService, _>(NameLookupRequest {
+        name: name.to_string(),
+    })
+    .unwrap()
+}
+
+/// Removes the service `name` that the caller registered.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn name_service_unregister(name: &str) -> NameServiceResponse {
+    check_name(name)?;
+    rpc_call::<NameService, _>(NameUnregisterRequest {
+        name: name.to_string(),
+    })
+    .unwrap()
+}
+
+/// Rejects invalid names before they reach the UTCB, which also ensures that they fit.
+fn check_name(name: &str) -> Result<(), NameServiceError> {
+    is_valid_service_name(name)
+        .then(|| ())
+        .ok_or(NameServiceError::InvalidName)
+}
+
+/// Entry of the portals of the hosted service. Hedron passes the portal identifier, which is
+/// the PID of the client.
+#[cfg(feature = "native_rust_rt")]
+fn hosted_service_entry(client: PortalIdentifier) -> ! {
+    let (handler, stack_top) = HOSTED_SERVICE.lock().unwrap();
+    let utcb = unsafe { (USER_SERVICE_UTCB_ADDR as *mut Utcb).as_mut().unwrap() };
+    handler(client, utcb);
+    sys_reply(stack_top)
+}
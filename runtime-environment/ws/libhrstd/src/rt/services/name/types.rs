@@ -0,0 +1,207 @@
+use crate::process::consts::ProcessId;
+use crate::service_protocol;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use libhedron::{
+    CapSel,
+    TypedItem,
+};
+
+/// Maximum length of a service name in bytes.
+pub const MAX_SERVICE_NAME_LEN: usize = 32;
+
+/// Which processes may look up a service.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ServiceAccess {
+    /// All processes.
+    Everyone,
+    /// Only the processes that the owner of the service launched.
+    Children,
+    /// Only the given processes.
+    Processes(Vec<ProcessId>),
+}
+
+impl ServiceAccess {
+    /// Returns whether the service of `owner` is accessible for `client`. `client_parent`
+    /// is the parent of the client. The owner can always access its own service.
+    pub fn allows(
+        &self,
+        owner: ProcessId,
+        client: ProcessId,
+        client_parent: Option<ProcessId>,
+    ) -> bool {
+        client == owner
+            || match self {
+                Self::Everyone => true,
+                Self::Children => client_parent == Some(owner),
+                Self::Processes(pids) => pids.contains(&client),
+            }
+    }
+}
+
+/// Registers the portal of `item` under `name`. The portal stays in the capability space
+/// of the registering process; the roottask delegates it from there on each lookup.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct NameRegisterRequest {
+    pub name: String,
+    pub item: TypedItem,
+    pub access: ServiceAccess,
+}
+
+/// Lets the roottask create a local EC in the caller that handles the calls of a service
+/// and registers the service under `name`. The EC runs on the CPU of the caller; its UTCB
+/// is at [`crate::uaddress_space::USER_SERVICE_UTCB_ADDR`]. Each lookup creates a new
+/// portal to the EC, whose portal identifier is the PID of the client, and delegates it
+/// to the client. Hence, the service knows who calls it. Each process can host a single
+/// service.
+///
+/// Hedron calls `entry` with the PID of the client as first argument and the stack
+/// pointer at `stack_top`. The entry must reply with `sys_reply(stack_top)`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct NameHostRequest {
+    pub name: String,
+    pub entry: u64,
+    pub stack_top: u64,
+    pub access: ServiceAccess,
+}
+
+/// Looks up the portal of a service. The roottask delegates it to the caller.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct NameLookupRequest {
+    pub name: String,
+}
+
+/// Removes a service that the caller registered. Processes that looked it up before keep
+/// the portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct NameUnregisterRequest {
+    pub name: String,
+}
+
+/// Request that a user app sends to the name service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum NameServiceRequest {
+    Register(NameRegisterRequest),
+    Host(NameHostRequest),
+    Lookup(NameLookupRequest),
+    Unregister(NameUnregisterRequest),
+}
+
+/// Errors that the name service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum NameServiceError {
+    /// The name is empty, too long, contains other characters than ASCII letters, digits,
+    /// `-`, `_`, and `.`, or is reserved for a service of the roottask.
+    InvalidName,
+    /// Another running process registered the name.
+    NameTaken,
+    /// No running process registered the name.
+    NotFound,
+    /// Only the process that registered a name can unregister it. The access of the
+    /// service excludes the caller.
+    PermissionDenied,
+    /// The service runs on another CPU than the caller. Hedron only permits calls to
+    /// portals of local ECs on the same CPU.
+    OtherCpu,
+    /// The caller already hosts a service.
+    AlreadyHosting,
+    /// The typed item names no single object capability.
+    InvalidCapability,
+    /// The caller has no free selector for received capabilities left.
+    CapSpaceFull,
+}
+
+/// Response of the name service to [`NameRegisterRequest`], [`NameHostRequest`], and
+/// [`NameUnregisterRequest`].
+pub type NameServiceResponse = Result<(), NameServiceError>;
+
+/// Response of the name service to [`NameLookupRequest`]: the selector of the portal in
+/// the capability space of the caller.
+pub type NameLookupResponse = Result<CapSel, NameServiceError>;
+
+service_protocol! {
+    /// The name service. Maps names of services to their portals, so that services don't
+    /// need a fixed selector in [`crate::cap_space::user::UserAppCapSpace`].
+    pub service NameService(NameServicePT): NameServiceRequest {
+        Register(NameRegisterRequest) -> NameServiceResponse,
+        Host(NameHostRequest) -> NameServiceResponse,
+        Lookup(NameLookupRequest) -> NameLookupResponse,
+        Unregister(NameUnregisterRequest) -> NameServiceResponse,
+    }
+}
+
+/// Returns whether `name` is a syntactically valid service name. Doesn't check whether
+/// the roottask reserves it.
+pub fn is_valid_service_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_SERVICE_NAME_LEN
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use libhedron::syscall::DelegateFlags;
+    use libhedron::{
+        CrdObjPT,
+        PTCapPermissions,
+        UTCB_DATA_CAPACITY,
+    };
+
+    #[test]
+    fn test_is_valid_service_name() {
+        assert!(is_valid_service_name("fs"));
+        assert!(is_valid_service_name("net.udp-echo_2"));
+        assert!(is_valid_service_name(&"a".repeat(MAX_SERVICE_NAME_LEN)));
+        assert!(!is_valid_service_name(""));
+        assert!(!is_valid_service_name(
+            &"a".repeat(MAX_SERVICE_NAME_LEN + 1)
+        ));
+        assert!(!is_valid_service_name("my service"));
+        assert!(!is_valid_service_name("/fs"));
+    }
+
+    #[test]
+    fn test_service_access() {
+        assert!(ServiceAccess::Everyone.allows(1, 2, None));
+        assert!(ServiceAccess::Children.allows(1, 2, Some(1)));
+        assert!(!ServiceAccess::Children.allows(1, 2, Some(0)));
+        assert!(ServiceAccess::Processes(vec![2]).allows(1, 2, None));
+        assert!(!ServiceAccess::Processes(vec![3]).allows(1, 2, Some(1)));
+        // the owner itself
+        assert!(ServiceAccess::Processes(Vec::new()).allows(1, 1, None));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = NameRegisterRequest {
+            name: String::from("echo"),
+            item: TypedItem::delegate(
+                CrdObjPT::new(300, 0, PTCapPermissions::CALL),
+                DelegateFlags::default(),
+            ),
+            access: ServiceAccess::Processes(vec![2, 3]),
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<NameServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: NameLookupResponse = Err(NameServiceError::NotFound);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<NameLookupResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
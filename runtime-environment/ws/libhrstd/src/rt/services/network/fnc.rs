@@ -0,0 +1,28 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::network::{
+    NetworkError,
+    NetworkServiceRequest,
+    NetworkServiceResponse,
+};
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+
+/// Wrapper around the network service portal. Frames and datagrams can't be split, hence
+/// a request that doesn't fit into the UTCB fails with [`NetworkError::MessageTooLong`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn network_service(request: NetworkServiceRequest) -> NetworkServiceResponse {
+    let utcb = user_load_utcb_mut();
+    if utcb.store_data(&request).is_err() {
+        return Err(NetworkError::MessageTooLong);
+    }
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::NetworkServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::NetworkServicePT.val()).unwrap();
+
+    utcb.load_data().unwrap()
+}
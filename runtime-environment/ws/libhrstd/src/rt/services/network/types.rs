@@ -0,0 +1,264 @@
+use crate::rt::services::fs::FD;
+use alloc::vec::Vec;
+use core::fmt::{
+    Display,
+    Formatter,
+};
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Maximum number of payload bytes of a single request or reply. Large enough for a
+/// whole Ethernet frame. The data travels through the UTCB, therefore larger transfers
+/// must be split.
+pub const NETWORK_MAX_IPC_DATA: usize = 2048;
+
+/// Hardware address of an Ethernet device.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl MacAddress {
+    pub const BROADCAST: Self = Self([0xff; 6]);
+
+    pub fn is_broadcast(self) -> bool {
+        self == Self::BROADCAST
+    }
+}
+
+impl Display for MacAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+/// IPv4 address in network byte order.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv4Address(pub [u8; 4]);
+
+impl Ipv4Address {
+    pub const UNSPECIFIED: Self = Self([0; 4]);
+    pub const BROADCAST: Self = Self([0xff; 4]);
+
+    pub fn is_unspecified(self) -> bool {
+        self == Self::UNSPECIFIED
+    }
+
+    /// Checks if both addresses are in the same network with the given prefix length.
+    pub fn is_in_same_network(self, other: Self, prefix_len: u8) -> bool {
+        let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+        u32::from_be_bytes(self.0) & mask == u32::from_be_bytes(other.0) & mask
+    }
+}
+
+impl Display for Ipv4Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+/// IPv4 address and port of a socket.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketAddrV4 {
+    pub addr: Ipv4Address,
+    pub port: u16,
+}
+
+impl SocketAddrV4 {
+    pub const fn new(addr: Ipv4Address, port: u16) -> Self {
+        Self { addr, port }
+    }
+}
+
+impl Display for SocketAddrV4 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}:{}", self.addr, self.port)
+    }
+}
+
+/// Static IPv4 configuration of the network stack of the roottask.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct NetworkConfig {
+    pub mac: MacAddress,
+    pub addr: Ipv4Address,
+    pub prefix_len: u8,
+    pub gateway: Ipv4Address,
+}
+
+/// Request that a user app sends to the network service portal.
+///
+/// UDP and TCP sockets share the file descriptors with files and local sockets, i.e.
+/// [`crate::rt::services::fs::FsCloseRequest`] closes them. Closing a TCP socket sends
+/// the remaining data and closes the connection in the background.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum NetworkServiceRequest {
+    /// Replies with [`NetworkServiceReply::Config`].
+    Config,
+    /// Sends a raw Ethernet frame (without the frame check sequence).
+    SendFrame(Vec<u8>),
+    /// Takes the next received Ethernet frame that the network stack didn't consume
+    /// itself. Replies with [`NetworkServiceReply::Frame`].
+    ReceiveFrame,
+    /// Creates an unbound UDP socket. Replies with [`NetworkServiceReply::Fd`].
+    UdpSocket,
+    /// Binds a UDP socket to a local port. Port zero selects a free port.
+    UdpBind { fd: FD, addr: SocketAddrV4 },
+    /// Sets the default destination of a UDP socket.
+    UdpConnect { fd: FD, addr: SocketAddrV4 },
+    /// Sends a datagram. `dest` is required for unconnected sockets. Unbound sockets
+    /// get bound to a free port first. Replies with [`NetworkServiceReply::Sent`].
+    UdpSend {
+        fd: FD,
+        data: Vec<u8>,
+        dest: Option<SocketAddrV4>,
+    },
+    /// Receives one datagram. The part that exceeds `max_len` gets discarded.
+    /// Replies with [`NetworkServiceReply::Received`].
+    UdpRecv { fd: FD, max_len: usize },
+    /// Creates an unconnected TCP socket. Replies with [`NetworkServiceReply::Fd`].
+    TcpSocket,
+    /// Binds a TCP socket to a local port. Port zero selects a free port.
+    TcpBind { fd: FD, addr: SocketAddrV4 },
+    /// Lets a TCP socket accept connections. At most `backlog` connections wait for
+    /// [`NetworkServiceRequest::TcpAccept`]. Unbound sockets get bound to a free port
+    /// first.
+    TcpListen { fd: FD, backlog: usize },
+    /// Takes the next connection of a listening TCP socket. Replies with
+    /// [`NetworkServiceReply::Accepted`].
+    TcpAccept { fd: FD },
+    /// Connects a TCP socket to the peer. Fails with [`NetworkError::WouldBlock`] until
+    /// the handshake is done; the request must be repeated until it succeeds.
+    TcpConnect { fd: FD, addr: SocketAddrV4 },
+    /// Sends data via a connected TCP socket. Replies with [`NetworkServiceReply::Sent`],
+    /// which may be less than the data if the send buffer is full.
+    TcpSend { fd: FD, data: Vec<u8> },
+    /// Receives at most `max_len` bytes from a connected TCP socket. No data means that
+    /// the peer closed the connection. Replies with [`NetworkServiceReply::Received`].
+    TcpRecv { fd: FD, max_len: usize },
+}
+
+/// Successful replies to a [`NetworkServiceRequest`].
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub enum NetworkServiceReply {
+    Config(NetworkConfig),
+    Done,
+    Frame(Vec<u8>),
+    Fd(FD),
+    /// Number of bytes that were sent.
+    Sent(usize),
+    /// Received datagram and its sender, or received data of a TCP connection and the
+    /// peer.
+    Received {
+        data: Vec<u8>,
+        from: SocketAddrV4,
+    },
+    /// Socket of the accepted TCP connection and the peer.
+    Accepted {
+        fd: FD,
+        peer: SocketAddrV4,
+    },
+}
+
+/// Errors of the network service. They map to the corresponding Linux error codes.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum NetworkError {
+    /// The system has no supported network device. (`ENETDOWN`)
+    NoDevice,
+    /// The file descriptor is not open. (`EBADF`)
+    BadFd,
+    /// The file descriptor doesn't refer to a socket of the required protocol.
+    /// (`ENOTSOCK`)
+    NotASocket,
+    /// Invalid argument or operation in the current state. (`EINVAL`)
+    InvalidArgument,
+    /// Another socket is already bound to the port. (`EADDRINUSE`)
+    AddressInUse,
+    /// The local address doesn't belong to this host. (`EADDRNOTAVAIL`)
+    AddressNotAvailable,
+    /// The socket is not connected and no destination was given. (`EDESTADDRREQ`)
+    DestinationRequired,
+    /// The operation would block. The network service never blocks. (`EAGAIN`)
+    WouldBlock,
+    /// The datagram or frame doesn't fit into a single frame. (`EMSGSIZE`)
+    MessageTooLong,
+    /// The TCP socket is not connected. (`ENOTCONN`)
+    NotConnected,
+    /// The TCP socket is already connected to another peer. (`EISCONN`)
+    AlreadyConnected,
+    /// The peer refused the TCP connection. (`ECONNREFUSED`)
+    ConnectionRefused,
+    /// The peer reset the TCP connection. (`ECONNRESET`)
+    ConnectionReset,
+    /// The peer didn't acknowledge the data in time. (`ETIMEDOUT`)
+    TimedOut,
+    /// The TCP connection was closed and can't send anymore. (`EPIPE`)
+    BrokenPipe,
+}
+
+/// Response of the network service portal.
+pub type NetworkServiceResponse = Result<NetworkServiceReply, NetworkError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let dest = SocketAddrV4::new(Ipv4Address([10, 0, 2, 2]), 1337);
+        let request = NetworkServiceRequest::UdpSend {
+            fd: FD::new(3),
+            data: vec![0xff; NETWORK_MAX_IPC_DATA],
+            dest: Some(dest),
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        match libhedron::ipc_postcard::from_bytes::<NetworkServiceRequest>(&buf).unwrap() {
+            NetworkServiceRequest::UdpSend {
+                fd,
+                data,
+                dest: actual_dest,
+            } => {
+                assert_eq!(fd, FD::new(3));
+                assert_eq!(data.len(), NETWORK_MAX_IPC_DATA);
+                assert_eq!(actual_dest, Some(dest));
+            }
+            request => panic!("unexpected request: {:?}", request),
+        }
+
+        let response: NetworkServiceResponse = Ok(NetworkServiceReply::Received {
+            data: vec![1, 2, 3],
+            from: dest,
+        });
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<NetworkServiceResponse>(&buf).unwrap(),
+            response
+        );
+
+        let response: NetworkServiceResponse = Ok(NetworkServiceReply::Accepted {
+            fd: FD::new(4),
+            peer: dest,
+        });
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<NetworkServiceResponse>(&buf).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn test_ipv4_same_network() {
+        let addr = Ipv4Address([10, 0, 2, 15]);
+        assert!(addr.is_in_same_network(Ipv4Address([10, 0, 2, 2]), 24));
+        assert!(!addr.is_in_same_network(Ipv4Address([10, 0, 3, 2]), 24));
+        assert!(addr.is_in_same_network(Ipv4Address([1, 1, 1, 1]), 0));
+        assert_eq!(format!("{}", addr), "10.0.2.15");
+    }
+}
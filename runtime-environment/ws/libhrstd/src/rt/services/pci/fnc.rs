@@ -0,0 +1,50 @@
+use crate::rt::services::irq::{
+    IrqAttachment,
+    PciFunction,
+};
+use crate::rt::services::pci::{
+    PciAttachMsiRequest,
+    PciBarMapping,
+    PciDeviceInfo,
+    PciDeviceRequest,
+    PciMapBarRequest,
+    PciReleaseRequest,
+    PciService,
+    PciServiceResponse,
+};
+use crate::rt::services::rpc::rpc_call;
+use alloc::vec::Vec;
+
+/// Returns the PCI function with the index, see [`PciDeviceRequest`].
+pub fn pci_service_device(index: u32) -> PciServiceResponse<PciDeviceInfo> {
+    rpc_call::<PciService, _>(PciDeviceRequest { index }).unwrap()
+}
+
+/// Returns all PCI functions in the order of the enumeration.
+pub fn pci_service_devices() -> Vec<PciDeviceInfo> {
+    (0..)
+        .map_while(|index| pci_service_device(index).ok())
+        .collect()
+}
+
+/// Returns the first PCI function with the vendor and device ID.
+pub fn pci_service_find(vendor_id: u16, device_id: u16) -> Option<PciDeviceInfo> {
+    (0..)
+        .map_while(|index| pci_service_device(index).ok())
+        .find(|info| info.vendor_id == vendor_id && info.device_id == device_id)
+}
+
+/// Maps a BAR of the function into the caller, see [`PciMapBarRequest`].
+pub fn pci_service_map_bar(function: PciFunction, bar: u8) -> PciServiceResponse<PciBarMapping> {
+    rpc_call::<PciService, _>(PciMapBarRequest { function, bar }).unwrap()
+}
+
+/// Attaches the MSI of the function, see [`PciAttachMsiRequest`].
+pub fn pci_service_attach_msi(function: PciFunction) -> PciServiceResponse<IrqAttachment> {
+    rpc_call::<PciService, _>(PciAttachMsiRequest { function }).unwrap()
+}
+
+/// Releases a function that the caller claimed, see [`PciReleaseRequest`].
+pub fn pci_service_release(function: PciFunction) -> PciServiceResponse<()> {
+    rpc_call::<PciService, _>(PciReleaseRequest { function }).unwrap()
+}
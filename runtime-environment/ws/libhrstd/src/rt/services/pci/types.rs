@@ -0,0 +1,250 @@
+use crate::rt::services::irq::{
+    IrqAttachment,
+    IrqServiceError,
+    PciFunction,
+};
+use crate::service_protocol;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Number of base address registers (BARs) of a PCI function with a type 0 header. Bridges
+/// only have the first two.
+pub const PCI_BAR_COUNT: usize = 6;
+
+/// Kind of the resource that a base address register decodes.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum PciBarKind {
+    /// A range of I/O ports.
+    Io,
+    /// A range of memory-mapped registers. A 64-bit BAR occupies the next register too.
+    Memory { prefetchable: bool, is_64bit: bool },
+}
+
+/// A base address register that the firmware assigned.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciBarInfo {
+    pub kind: PciBarKind,
+    /// First I/O port or physical address.
+    pub base: u64,
+    /// Size in bytes or number of I/O ports. Always a power of two.
+    pub size: u64,
+}
+
+/// An entry of the capability list of a PCI function, e.g. the MSI capability.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciCapability {
+    pub id: u8,
+    /// Offset in the configuration space.
+    pub offset: u8,
+}
+
+/// A PCI function that the roottask found when it enumerated the buses.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciDeviceInfo {
+    pub function: PciFunction,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    /// Layout of the configuration space without the multi-function bit: `0` for devices,
+    /// `1` for PCI-to-PCI bridges.
+    pub header_type: u8,
+    /// `None` for unused registers and for the upper halves of 64-bit BARs.
+    pub bars: [Option<PciBarInfo>; PCI_BAR_COUNT],
+    /// Interrupt pin (`1` = INTA# to `4` = INTD#) or `0` if the function has none.
+    pub interrupt_pin: u8,
+    /// The IOAPIC pin that the firmware routed the interrupt pin to. Only meaningful if
+    /// the ACPI tables don't say otherwise.
+    pub interrupt_line: u8,
+    pub capabilities: Vec<PciCapability>,
+}
+
+impl PciDeviceInfo {
+    /// Returns the offset of the capability with the ID, if the function has one.
+    pub fn capability(&self, id: u8) -> Option<u8> {
+        self.capabilities
+            .iter()
+            .find(|cap| cap.id == id)
+            .map(|cap| cap.offset)
+    }
+}
+
+/// Where a base address register is accessible for the caller.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum PciBarMapping {
+    /// Virtual address of the registers in the address space of the caller. The
+    /// registers may start in the middle of a page.
+    Memory(u64),
+    /// First I/O port. The caller may access all ports of the BAR.
+    Io(u16),
+}
+
+/// Returns the PCI function with the index in the order of the enumeration. Indices are
+/// stable, because the roottask enumerates the buses only once.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciDeviceRequest {
+    pub index: u32,
+}
+
+/// Maps the memory or delegates the I/O ports of a base address register to the caller,
+/// and enables the decoding and DMA of the function. The first request for a function
+/// claims it for the caller: other processes can't get its resources until the caller
+/// releases it. Mapping a BAR twice returns the same mapping.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciMapBarRequest {
+    pub function: PciFunction,
+    pub bar: u8,
+}
+
+/// Claims the function like [`PciMapBarRequest`] and attaches its MSI, see
+/// [`crate::rt::services::irq::IrqSource::Msi`]. Releasing the function detaches it.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciAttachMsiRequest {
+    pub function: PciFunction,
+}
+
+/// Unmaps all BARs and detaches the MSI of a function that the caller claimed, disables
+/// DMA of the function, and frees it for other processes. The roottask releases the
+/// functions of a process when it exits.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PciReleaseRequest {
+    pub function: PciFunction,
+}
+
+/// Request that a user app sends to the PCI service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum PciServiceRequest {
+    Device(PciDeviceRequest),
+    MapBar(PciMapBarRequest),
+    AttachMsi(PciAttachMsiRequest),
+    Release(PciReleaseRequest),
+}
+
+/// Errors that the PCI service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum PciServiceError {
+    /// There is no function with the index or at the location.
+    NoSuchDevice,
+    /// The BAR doesn't exist or the firmware didn't assign it.
+    NoSuchBar,
+    /// The roottask or another process claimed the function.
+    Busy,
+    /// The caller didn't claim the function.
+    NotClaimed,
+    /// The address space of the caller has no room for the BAR.
+    OutOfMemory,
+    /// Hedron refused to delegate the resources of the BAR.
+    DelegationFailed,
+    /// The interrupt service refused the MSI.
+    Irq(IrqServiceError),
+    /// Only drivers, i.e. the roottask and the programs it started itself, can look up
+    /// and claim functions.
+    PermissionDenied,
+}
+
+/// Response of the PCI service.
+pub type PciServiceResponse<T> = Result<T, PciServiceError>;
+
+service_protocol! {
+    /// The PCI service. The roottask enumerates the PCI buses at boot. Driver processes
+    /// look up their device and get the registers and interrupts of it.
+    pub service PciService(PciServicePT): PciServiceRequest {
+        Device(PciDeviceRequest) -> PciServiceResponse<PciDeviceInfo>,
+        MapBar(PciMapBarRequest) -> PciServiceResponse<PciBarMapping>,
+        AttachMsi(PciAttachMsiRequest) -> PciServiceResponse<IrqAttachment>,
+        Release(PciReleaseRequest) -> PciServiceResponse<()>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    fn virtio_net() -> PciDeviceInfo {
+        let mut bars = [None; PCI_BAR_COUNT];
+        bars[0] = Some(PciBarInfo {
+            kind: PciBarKind::Io,
+            base: 0xc000,
+            size: 32,
+        });
+        bars[4] = Some(PciBarInfo {
+            kind: PciBarKind::Memory {
+                prefetchable: true,
+                is_64bit: true,
+            },
+            base: 0xfebf_0000,
+            size: 0x4000,
+        });
+        PciDeviceInfo {
+            function: PciFunction {
+                bus: 0,
+                device: 3,
+                function: 0,
+            },
+            vendor_id: 0x1af4,
+            device_id: 0x1000,
+            class: 0x02,
+            subclass: 0x00,
+            prog_if: 0,
+            revision: 0,
+            header_type: 0,
+            bars,
+            interrupt_pin: 1,
+            interrupt_line: 11,
+            capabilities: vec![
+                PciCapability {
+                    id: 0x11,
+                    offset: 0x98,
+                },
+                PciCapability {
+                    id: 0x09,
+                    offset: 0x84,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_capability() {
+        let info = virtio_net();
+        assert_eq!(info.capability(0x09), Some(0x84));
+        assert_eq!(info.capability(0x05), None);
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = PciMapBarRequest {
+            function: virtio_net().function,
+            bar: 4,
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<PciServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: PciServiceResponse<PciDeviceInfo> = Ok(virtio_net());
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<PciServiceResponse<PciDeviceInfo>>(&buf).unwrap(),
+            response
+        );
+
+        let response: PciServiceResponse<IrqAttachment> =
+            Err(PciServiceError::Irq(IrqServiceError::NoFreeVector));
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<PciServiceResponse<IrqAttachment>>(&buf).unwrap(),
+            response
+        );
+    }
+}
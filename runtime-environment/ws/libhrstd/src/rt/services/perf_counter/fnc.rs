@@ -0,0 +1,36 @@
+use crate::rt::services::perf_counter::{
+    PerfCounts,
+    PerfEvent,
+    PerfInfo,
+    PerfInfoRequest,
+    PerfReadRequest,
+    PerfService,
+    PerfServiceResponse,
+    PerfStartRequest,
+    PerfStopRequest,
+};
+use crate::rt::services::rpc::rpc_call;
+
+/// Returns the capabilities of the performance counters of the CPU of the caller.
+pub fn perf_service_info() -> PerfInfo {
+    rpc_call::<PerfService, _>(PerfInfoRequest).unwrap()
+}
+
+/// Resets the performance counters of the CPU of the caller and starts counting the
+/// events, see [`PerfStartRequest`].
+pub fn perf_service_start(events: &[PerfEvent]) -> PerfServiceResponse<()> {
+    rpc_call::<PerfService, _>(PerfStartRequest {
+        events: events.to_vec(),
+    })
+    .unwrap()
+}
+
+/// Returns the current values of the counters that the caller started.
+pub fn perf_service_read() -> PerfServiceResponse<PerfCounts> {
+    rpc_call::<PerfService, _>(PerfReadRequest).unwrap()
+}
+
+/// Stops the counters that the caller started and returns their final values.
+pub fn perf_service_stop() -> PerfServiceResponse<PerfCounts> {
+    rpc_call::<PerfService, _>(PerfStopRequest).unwrap()
+}
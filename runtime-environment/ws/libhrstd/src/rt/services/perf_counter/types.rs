@@ -0,0 +1,201 @@
+use crate::service_protocol;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Hardware events that the performance counters can count. These are architectural
+/// events of Intel CPUs, i.e. each CPU with architectural performance monitoring may
+/// support them; see [`PerfInfo::events`].
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PerfEvent {
+    /// Core cycles while the CPU isn't halted. Unlike the TSC, it follows the frequency
+    /// scaling of the core.
+    CoreCycles,
+    InstructionsRetired,
+    /// Misses of the last level cache.
+    LlcMisses,
+    /// Retired branch instructions that were mispredicted.
+    BranchMispredictions,
+}
+
+impl PerfEvent {
+    /// All events, e.g. for [`PerfStartRequest`].
+    pub const ALL: [Self; 4] = [
+        Self::CoreCycles,
+        Self::InstructionsRetired,
+        Self::LlcMisses,
+        Self::BranchMispredictions,
+    ];
+
+    /// Returns a short name, e.g. `instructions`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::CoreCycles => "cycles",
+            Self::InstructionsRetired => "instructions",
+            Self::LlcMisses => "llc-misses",
+            Self::BranchMispredictions => "branch-misses",
+        }
+    }
+}
+
+/// The values of the counters, one per event of the [`PerfStartRequest`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PerfCounts {
+    pub counts: Vec<(PerfEvent, u64)>,
+}
+
+impl PerfCounts {
+    /// Returns the value of the counter of the event, if it was counted.
+    pub fn get(&self, event: PerfEvent) -> Option<u64> {
+        self.counts
+            .iter()
+            .find(|(e, _)| *e == event)
+            .map(|(_, count)| *count)
+    }
+
+    /// Returns the counts since `earlier`, e.g. of a previous [`PerfReadRequest`].
+    pub fn since(&self, earlier: &Self) -> Self {
+        let counts = self
+            .counts
+            .iter()
+            .map(|(event, count)| {
+                let earlier = earlier.get(*event).unwrap_or(0);
+                (*event, count.saturating_sub(earlier))
+            })
+            .collect();
+        Self { counts }
+    }
+}
+
+/// The performance monitoring unit of the CPU of the caller.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PerfInfo {
+    /// Version of the architectural performance monitoring. 0 if the CPU has none, e.g. in
+    /// QEMU without KVM.
+    pub version: u8,
+    /// Number of general-purpose counters, i.e. how many events can be counted at once.
+    pub num_counters: u8,
+    /// Width of the counters in bits.
+    pub counter_width: u8,
+    /// Events that the CPU supports.
+    pub events: Vec<PerfEvent>,
+}
+
+/// Programs the counters of the CPU of the caller for the events, resets them, and starts
+/// counting. The counters count in user and kernel mode, i.e. also during the IPC to
+/// the roottask and in Hedron, and also when other processes run on the CPU. Only one
+/// process at a time may use the counters of a CPU; it owns them until it stops them or
+/// exits. Starting again restarts the counters of the owner.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PerfStartRequest {
+    pub events: Vec<PerfEvent>,
+}
+
+/// Reads the counters of the caller without stopping them.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PerfReadRequest;
+
+/// Stops the counters of the caller and returns their final values. Afterwards, other
+/// processes may use the counters.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PerfStopRequest;
+
+/// Returns the capabilities of the performance monitoring unit of the CPU of the caller.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PerfInfoRequest;
+
+/// Request that a user app sends to the performance counter service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum PerfServiceRequest {
+    Info(PerfInfoRequest),
+    Start(PerfStartRequest),
+    Read(PerfReadRequest),
+    Stop(PerfStopRequest),
+}
+
+/// Errors that the performance counter service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum PerfServiceError {
+    /// The CPU has no architectural performance monitoring or Hedron doesn't let the
+    /// roottask access the MSRs of the counters.
+    Unsupported,
+    /// The CPU can't count the event.
+    UnsupportedEvent(PerfEvent),
+    /// The request has no events, an event twice, or more events than the CPU has
+    /// counters.
+    InvalidEvents,
+    /// Another process uses the counters of the CPU.
+    Busy,
+    /// The caller didn't start the counters.
+    NotStarted,
+    /// Only privileged processes, i.e. the roottask and the programs it started itself,
+    /// can start the counters, because they count everything that runs on the CPU.
+    PermissionDenied,
+}
+
+/// Response of the performance counter service.
+pub type PerfServiceResponse<T> = Result<T, PerfServiceError>;
+
+service_protocol! {
+    /// The performance counter service. The roottask programs the performance counters
+    /// of the CPU via MSRs on behalf of user apps, e.g. benchmarks, because user apps
+    /// can't access MSRs.
+    pub service PerfService(PerfServicePT): PerfServiceRequest {
+        Info(PerfInfoRequest) -> PerfInfo,
+        Start(PerfStartRequest) -> PerfServiceResponse<()>,
+        Read(PerfReadRequest) -> PerfServiceResponse<PerfCounts>,
+        Stop(PerfStopRequest) -> PerfServiceResponse<PerfCounts>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_perf_counts() {
+        let earlier = PerfCounts {
+            counts: vec![
+                (PerfEvent::InstructionsRetired, 100),
+                (PerfEvent::LlcMisses, 7),
+            ],
+        };
+        let later = PerfCounts {
+            counts: vec![
+                (PerfEvent::InstructionsRetired, 350),
+                (PerfEvent::LlcMisses, 9),
+            ],
+        };
+        assert_eq!(later.get(PerfEvent::LlcMisses), Some(9));
+        assert_eq!(later.get(PerfEvent::CoreCycles), None);
+        let delta = later.since(&earlier);
+        assert_eq!(delta.get(PerfEvent::InstructionsRetired), Some(250));
+        assert_eq!(delta.get(PerfEvent::LlcMisses), Some(2));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = PerfStartRequest {
+            events: PerfEvent::ALL.to_vec(),
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<PerfServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: PerfServiceResponse<PerfCounts> =
+            Err(PerfServiceError::UnsupportedEvent(PerfEvent::LlcMisses));
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<PerfServiceResponse<PerfCounts>>(&buf).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::process::consts::ProcessId;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::{
+    sys_hybrid_call,
+    sys_hybrid_revoke,
+};
+use crate::rt::services::process::{
+    FaultHandlerEntry,
+    ProcessSendCapResponse,
+    ProcessServiceError,
+    ProcessServiceRequest,
+    ProcessServiceResponse,
+    ProcessStatusResponse,
+    SpawnFd,
+};
+use crate::rt::services::rpc::RpcError;
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+use libhedron::ipc_serde::de::DeserializeOwned;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::{
+    sys_call,
+    sys_revoke,
+};
+use libhedron::{
+    CapSel,
+    CrdObj,
+    TypedItem,
+};
+
+/// Sends a request to the process service, i.e. to start a program by its path. The
+/// new process starts asynchronously: it might not run yet when this returns.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service(request: ProcessServiceRequest) -> ProcessServiceResponse {
+    debug_assert!(matches!(request, ProcessServiceRequest::Launch { .. }));
+    process_service_call(&request).unwrap_or(Err(ProcessServiceError::ArgumentsTooLong))
+}
+
+/// Starts the program at `path` like `posix_spawn()`, see [`ProcessServiceRequest::Spawn`].
+/// The new process starts asynchronously: it might not run yet when this returns.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_spawn(
+    path: &str,
+    argv: Vec<String>,
+    envp: Vec<String>,
+    fds: Vec<SpawnFd>,
+) -> ProcessServiceResponse {
+    let request = ProcessServiceRequest::Spawn {
+        path: path.to_string(),
+        argv,
+        envp,
+        fds,
+    };
+    process_service_call(&request).unwrap_or(Err(ProcessServiceError::ArgumentsTooLong))
+}
+
+/// Returns whether the child process still runs or its exit status.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_status(pid: ProcessId) -> ProcessStatusResponse {
+    process_service_call(&ProcessServiceRequest::Status { pid }).unwrap()
+}
+
+/// Delegates the capability of `item` to the running process `to`. Returns the selector
+/// of the capability in the capability space of `to`.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_send_cap(item: TypedItem, to: ProcessId) -> ProcessSendCapResponse {
+    process_service_call(&ProcessServiceRequest::SendCap { item, to }).unwrap()
+}
+
+/// Revokes the capability at `sel` that the caller received via
+/// [`process_service_send_cap`] and frees the selector for the next transfer.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_release_cap(sel: CapSel) -> Result<(), ProcessServiceError> {
+    // an empty selector is fine; the roottask checks that it belongs to a received one
+    #[cfg(feature = "native_rust_rt")]
+    let _ = sys_revoke(CrdObj::new(sel, 0), true);
+    #[cfg(feature = "foreign_rust_rt")]
+    let _ = sys_hybrid_revoke(CrdObj::new(sel, 0), true);
+    process_service_call(&ProcessServiceRequest::ReleaseCap { sel }).unwrap()
+}
+
+/// Replaces the program at `path` with the ELF file at `source` until the next reboot.
+/// The next launch of `path` starts the new program.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_reload(path: &str, source: &str) -> Result<(), ProcessServiceError> {
+    let request = ProcessServiceRequest::Reload {
+        path: path.to_string(),
+        source: source.to_string(),
+    };
+    process_service_call(&request).unwrap_or(Err(ProcessServiceError::ArgumentsTooLong))
+}
+
+/// Terminates the calling process with the given status. Native apps call this instead
+/// of returning from their entry function.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_exit(status: i32) -> ! {
+    let _: Result<(), ProcessServiceError> =
+        process_service_call(&ProcessServiceRequest::Exit { status }).unwrap();
+    // the roottask stops the process shortly after the reply
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// Registers the handler for faults of the calling process that runs on the stack below
+/// `stack_top`, or removes it with `None`. See [`ProcessServiceRequest::SetFaultHandler`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_set_fault_handler(
+    entry: Option<FaultHandlerEntry>,
+    stack_top: u64,
+) -> Result<(), ProcessServiceError> {
+    let request = ProcessServiceRequest::SetFaultHandler {
+        entry: entry.map_or(0, |entry| entry as u64),
+        stack_top,
+    };
+    process_service_call(&request).unwrap()
+}
+
+/// Enables or disables the syscall trace of a child process, see
+/// [`ProcessServiceRequest::SetSyscallTrace`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_service_set_syscall_trace(
+    pid: ProcessId,
+    enabled: bool,
+) -> Result<(), ProcessServiceError> {
+    process_service_call(&ProcessServiceRequest::SetSyscallTrace { pid, enabled }).unwrap()
+}
+
+/// Fails if the request doesn't fit into the UTCB or the roottask denied the call, see
+/// [`RpcError`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+fn process_service_call<T: DeserializeOwned>(
+    request: &ProcessServiceRequest,
+) -> Result<T, RpcError> {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(request)?;
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::ProcessServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::ProcessServicePT.val()).unwrap();
+
+    if utcb.untyped_items_count() == 0 {
+        return Err(RpcError::Denied);
+    }
+    Ok(utcb.load_data()?)
+}
@@ -0,0 +1,360 @@
+use crate::process::consts::ProcessId;
+use crate::rt::services::fs::{
+    FsOpenFlags,
+    FD,
+};
+use crate::rt::services::scheduling::SchedulingParams;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use libhedron::{
+    CapSel,
+    TypedItem,
+};
+
+/// Request that a user app sends to the process service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ProcessServiceRequest {
+    /// Starts the ELF file at `path` in the file system, for example a program in `/bin`.
+    /// The syscall ABI gets detected from the ELF file. Returns the PID of the new process.
+    /// The calling process becomes the parent of the new process.
+    Launch {
+        path: String,
+        /// Arguments of the program. By convention, the first one is the program name. If
+        /// empty, the path becomes the only argument.
+        argv: Vec<String>,
+        /// Environment variables in the form `KEY=VALUE`.
+        envp: Vec<String>,
+        /// Priority and time quantum of the new process. If `None`,
+        /// [`SchedulingParams::DEFAULT`] applies.
+        sched_params: Option<SchedulingParams>,
+        /// CPU of the new process. The CPU must be online. If `None`, the roottask
+        /// distributes new processes round-robin across all online CPUs. A process can't
+        /// change its CPU afterwards.
+        cpu: Option<u64>,
+        /// Files that the service opens on behalf of the caller and hands to the new
+        /// process at fixed file descriptors, e.g. a configuration file at FD 3. The new
+        /// process uses them without opening any path itself.
+        preopened: Vec<PreopenedFile>,
+        /// Randomizes the address space layout of the new process: the begin of the heap,
+        /// the base of the mmap arena, and the initial stack pointer. Without it, the
+        /// layout only depends on the ELF file, which keeps benchmarks reproducible.
+        aslr: bool,
+        /// The new process waits before its first instruction until GDB attaches over the
+        /// serial port and lets it run. See the GDB stub of the roottask.
+        debug: bool,
+        /// Records the Linux syscalls of the new process in `/proc/<pid>/trace`, like
+        /// `strace`. See [`ProcessServiceRequest::SetSyscallTrace`].
+        trace: bool,
+    },
+    /// Starts the ELF file at `path` like `posix_spawn()`: the new process gets copies of
+    /// open files of the caller at the file descriptors of `fds` and the defaults of
+    /// [`ProcessServiceRequest::Launch`] for everything else. Unlike `fork()` and
+    /// `exec()`, nothing of the address space of the caller gets copied. Returns the PID of
+    /// the new process, whose parent is the caller.
+    Spawn {
+        path: String,
+        /// Arguments of the program. If empty, the path becomes the only argument.
+        argv: Vec<String>,
+        /// Environment variables in the form `KEY=VALUE`.
+        envp: Vec<String>,
+        /// Open files of the caller that the new process inherits, see [`SpawnFd`].
+        fds: Vec<SpawnFd>,
+    },
+    /// Returns the [`ProcessStatus`] of a child of the caller without blocking. Once the
+    /// parent got [`ProcessStatus::Exited`], the PID may belong to a new process.
+    Status { pid: ProcessId },
+    /// Delegates a capability of the caller, e.g. a portal or a semaphore, to the running
+    /// process `to`. `item` names a single object capability in the capability space of
+    /// the caller; its delegate flags are ignored. The capability lands at the next free
+    /// selector of the received capabilities of `to`, see
+    /// [`crate::cap_space::user::UserAppCapSpace::ReceivedCapBase`]. The caller tells the
+    /// receiver the selector of the [`ProcessSendCapResponse`] itself. Only the parent
+    /// and the children of `to` and privileged processes may send capabilities to it.
+    SendCap { item: TypedItem, to: ProcessId },
+    /// Replaces the program at `path`, e.g. a program in `/bin`, with the ELF file at
+    /// `source`, which gets opened on behalf of the caller. The next launch of `path`
+    /// starts the new program; running processes keep the old one. The replacement lasts
+    /// until the next reboot. Lets developers test a new version of a program without
+    /// rebooting. The response is `Result<(), ProcessServiceError>`.
+    Reload { path: String, source: String },
+    /// Terminates the calling process with the given status. The process stops shortly
+    /// after the call returns; it must not do anything else afterwards. The response is
+    /// `Result<(), ProcessServiceError>`.
+    Exit { status: i32 },
+    /// Registers a handler for exceptions of the calling Hedron-native process that the
+    /// roottask can't resolve, e.g. a page fault at an unmapped address or an invalid
+    /// opcode. Without a handler, such exceptions terminate the process. The roottask
+    /// continues the process in `entry` on the stack below `stack_top`, see
+    /// [`FaultHandlerEntry`]. The handler is used once: a fault inside the handler
+    /// terminates the process. An `entry` of zero removes the handler. Linux processes
+    /// catch signals instead. The response is `Result<(), ProcessServiceError>`.
+    SetFaultHandler { entry: u64, stack_top: u64 },
+    /// Enables or disables the recording of the Linux syscalls of the process `pid` in
+    /// `/proc/<pid>/trace`, like `strace`. Each line shows a syscall with its decoded
+    /// arguments and its return value; the file keeps the last few hundred syscalls and
+    /// stays after the process exited. Only the parent of the process and privileged
+    /// processes may do this. Hedron-native processes don't make Linux syscalls. The
+    /// response is `Result<(), ProcessServiceError>`.
+    SetSyscallTrace { pid: ProcessId, enabled: bool },
+    /// Frees the selector `sel` of a capability that the caller received via
+    /// [`Self::SendCap`], so that the next transfer can use it again. The caller revokes
+    /// the capability itself before. The response is `Result<(), ProcessServiceError>`.
+    ReleaseCap { sel: CapSel },
+}
+
+/// Function that handles the faults of a Hedron-native process, see
+/// [`ProcessServiceRequest::SetFaultHandler`]. Gets the number of the exception (see
+/// [`libhedron::ExceptionEventOffset`]), the instruction pointer and the stack pointer at
+/// the time of the fault, the faulting address of page faults, and the error code of the
+/// exception. The process can't continue where the fault happened, hence the handler
+/// never returns.
+pub type FaultHandlerEntry =
+    extern "C" fn(exception: u64, rip: u64, rsp: u64, fault_addr: u64, error_code: u64) -> !;
+
+/// A file that the process service opens for a new process before it starts. See
+/// [`ProcessServiceRequest::Launch`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PreopenedFile {
+    /// File descriptor of the file in the new process. Must be at least
+    /// [`PreopenedFile::MIN_FD`] and unique within a launch.
+    pub fd: FD,
+    pub path: String,
+    pub flags: FsOpenFlags,
+}
+
+impl PreopenedFile {
+    /// Lowest file descriptor for pre-opened files; 0 to 2 are the standard streams.
+    pub const MIN_FD: i32 = 3;
+
+    /// Returns true if the file descriptors are valid and unique.
+    pub fn are_valid(files: &[Self]) -> bool {
+        files.iter().enumerate().all(|(i, file)| {
+            file.fd.raw() >= Self::MIN_FD && files[..i].iter().all(|other| other.fd != file.fd)
+        })
+    }
+}
+
+/// An open file of the caller of [`ProcessServiceRequest::Spawn`] that the new process
+/// gets at `child_fd`. The new process has its own file offset, which starts at the
+/// offset of the file of the caller. Sockets can't be inherited.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SpawnFd {
+    pub parent_fd: FD,
+    pub child_fd: FD,
+}
+
+impl SpawnFd {
+    /// Returns true if the file descriptors are not negative and each file descriptor of
+    /// the new process is unique.
+    pub fn are_valid(fds: &[Self]) -> bool {
+        fds.iter().enumerate().all(|(i, fd)| {
+            fd.parent_fd.raw() >= 0
+                && fd.child_fd.raw() >= 0
+                && fds[..i].iter().all(|other| other.child_fd != fd.child_fd)
+        })
+    }
+}
+
+/// State of a process as the process service reports it.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ProcessStatus {
+    /// The process runs or is about to start.
+    Running,
+    /// The process exited with the given status.
+    Exited(i32),
+}
+
+/// Errors that the process service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ProcessServiceError {
+    /// The file doesn't exist or can't be read, a file to pre-open can't be opened, or a
+    /// file to inherit is not open.
+    NotFound,
+    /// Only the roottask and processes that the roottask started itself can launch or
+    /// reload programs. Only the parent of a process or a privileged process can query its
+    /// status. Only relatives of a process and privileged processes can send it
+    /// capabilities.
+    PermissionDenied,
+    /// The file is no ELF file or its syscall ABI is unknown.
+    InvalidElf,
+    /// An argument or an environment variable contains a null byte, the scheduling
+    /// parameters are out of range, or the file descriptors of the pre-opened or inherited
+    /// files are invalid.
+    InvalidArgument,
+    /// The file system of the program to reload doesn't support replacing files.
+    ReloadUnsupported,
+    /// The arguments and environment variables together don't fit into the UTCB.
+    /// (like `E2BIG`)
+    ArgumentsTooLong,
+    /// The running Hedron kernel can't run the program, i.e. it lacks support for
+    /// foreign system calls. Or a Linux process registers a fault handler, or a socket
+    /// should be inherited.
+    Unsupported,
+    /// All PIDs are in use or the roottask has no capability selectors for another
+    /// process left.
+    TooManyProcesses,
+    /// There is no process with the given PID.
+    NoSuchProcess,
+    /// The typed item names no single object capability, or the selector to release
+    /// holds no received capability.
+    InvalidCapability,
+    /// The receiving process has no free selector for received capabilities left.
+    CapSpaceFull,
+}
+
+/// Response of the process service to [`ProcessServiceRequest::Launch`] and
+/// [`ProcessServiceRequest::Spawn`].
+pub type ProcessServiceResponse = Result<ProcessId, ProcessServiceError>;
+
+/// Response of the process service to [`ProcessServiceRequest::Status`].
+pub type ProcessStatusResponse = Result<ProcessStatus, ProcessServiceError>;
+
+/// Response of the process service to [`ProcessServiceRequest::SendCap`]: the selector of
+/// the capability in the capability space of the receiver.
+pub type ProcessSendCapResponse = Result<CapSel, ProcessServiceError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::syscall::DelegateFlags;
+    use libhedron::{
+        CrdObjPT,
+        PTCapPermissions,
+        UTCB_DATA_CAPACITY,
+    };
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = ProcessServiceRequest::Launch {
+            path: String::from("/bin/linux_c_hello_world_musl"),
+            argv: vec![String::from("linux_c_hello_world_musl"), String::from("-v")],
+            envp: vec![String::from("FOO=BAR")],
+            sched_params: Some(SchedulingParams {
+                priority: 2,
+                quantum_us: 1000,
+            }),
+            cpu: Some(1),
+            preopened: vec![PreopenedFile {
+                fd: FD::new(3),
+                path: String::from("/etc/hello.conf"),
+                flags: FsOpenFlags::O_RDONLY,
+            }],
+            aslr: true,
+            debug: true,
+            trace: false,
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: ProcessServiceResponse = Err(ProcessServiceError::InvalidElf);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceResponse>(&buf).unwrap(),
+            response
+        );
+
+        let request = ProcessServiceRequest::Reload {
+            path: String::from("/bin/native-shell-bin"),
+            source: String::from("/tmp/native-shell-bin"),
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let request = ProcessServiceRequest::Spawn {
+            path: String::from("/bin/hello"),
+            argv: vec![String::from("hello"), String::from("-v")],
+            envp: vec![String::from("HOME=/")],
+            fds: vec![SpawnFd {
+                parent_fd: FD::new(5),
+                child_fd: FD::new(1),
+            }],
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let request = ProcessServiceRequest::Exit { status: -1 };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let request = ProcessServiceRequest::SetFaultHandler {
+            entry: 0x401000,
+            stack_top: 0x7000_0000_0000,
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let request = ProcessServiceRequest::SendCap {
+            item: TypedItem::delegate(
+                CrdObjPT::new(40, 0, PTCapPermissions::CALL),
+                DelegateFlags::default(),
+            ),
+            to: 7,
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let request = ProcessServiceRequest::ReleaseCap { sel: 192 };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: ProcessStatusResponse = Ok(ProcessStatus::Exited(42));
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessStatusResponse>(&buf).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn test_spawn_fds_are_valid() {
+        let fd = |parent_fd, child_fd| SpawnFd {
+            parent_fd: FD::new(parent_fd),
+            child_fd: FD::new(child_fd),
+        };
+        assert!(SpawnFd::are_valid(&[]));
+        assert!(SpawnFd::are_valid(&[fd(3, 0), fd(3, 1), fd(4, 3)]));
+        assert!(!SpawnFd::are_valid(&[fd(-1, 3)]));
+        assert!(!SpawnFd::are_valid(&[fd(3, -1)]));
+        assert!(!SpawnFd::are_valid(&[fd(3, 0), fd(4, 0)]));
+    }
+
+    #[test]
+    fn test_preopened_files_are_valid() {
+        let file = |fd| PreopenedFile {
+            fd: FD::new(fd),
+            path: String::from("/tmp/log"),
+            flags: FsOpenFlags::O_WRONLY,
+        };
+        assert!(PreopenedFile::are_valid(&[]));
+        assert!(PreopenedFile::are_valid(&[file(3), file(4)]));
+        assert!(!PreopenedFile::are_valid(&[file(2)]));
+        assert!(!PreopenedFile::are_valid(&[file(-1)]));
+        assert!(!PreopenedFile::are_valid(&[file(3), file(4), file(3)]));
+    }
+}
@@ -0,0 +1,30 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::process::consts::ProcessId;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::process_signal::{
+    ProcessSignal,
+    ProcessSignalServiceRequest,
+    ProcessSignalServiceResponse,
+};
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+
+/// Sends a signal to the process with the given PID. Only allowed for the parent of
+/// the process. The signal gets delivered the next time the target enters the roottask.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn process_signal_service(
+    pid: ProcessId,
+    signal: ProcessSignal,
+) -> ProcessSignalServiceResponse {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(&ProcessSignalServiceRequest { pid, signal }).unwrap();
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::ProcessSignalServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::ProcessSignalServicePT.val()).unwrap();
+
+    utcb.load_data().unwrap()
+}
@@ -0,0 +1,90 @@
+use crate::process::consts::ProcessId;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Signal that a process sends to another process via the process signal service.
+/// The roottask delivers it like the corresponding POSIX signal.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ProcessSignal {
+    /// `SIGINT`. The target can handle it, e.g. to abort its current work.
+    Interrupt,
+    /// `SIGSTOP`. Can't be caught.
+    Stop,
+    /// `SIGCONT`. Continues a stopped process.
+    Continue,
+    /// `SIGTERM`. The target can handle it to shut down gracefully.
+    Terminate,
+    /// `SIGKILL`. Can't be caught.
+    Kill,
+    /// Any other signal by its (Linux) number.
+    Other(u64),
+}
+
+impl ProcessSignal {
+    /// Returns the signal number. Numbering follows Linux on x86_64.
+    pub const fn signum(self) -> u64 {
+        match self {
+            Self::Interrupt => 2,
+            Self::Stop => 19,
+            Self::Continue => 18,
+            Self::Terminate => 15,
+            Self::Kill => 9,
+            Self::Other(sig) => sig,
+        }
+    }
+}
+
+/// Request that a user app sends to the process signal service portal.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub struct ProcessSignalServiceRequest {
+    /// Process that receives the signal.
+    pub pid: ProcessId,
+    pub signal: ProcessSignal,
+}
+
+/// Errors that the process signal service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ProcessSignalServiceError {
+    /// There is no process with the given PID.
+    NoSuchProcess,
+    /// Only the parent of a process and the roottask can signal it.
+    PermissionDenied,
+    /// The signal number is out of range.
+    InvalidSignal,
+    /// The target is the roottask, or it can't be stopped or continued, because the
+    /// roottask doesn't manage its SC.
+    Unsupported,
+}
+
+/// Response of the process signal service.
+pub type ProcessSignalServiceResponse = Result<(), ProcessSignalServiceError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = ProcessSignalServiceRequest {
+            pid: 3,
+            signal: ProcessSignal::Other(10),
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        let request =
+            libhedron::ipc_postcard::from_bytes::<ProcessSignalServiceRequest>(&buf).unwrap();
+        assert_eq!(request.pid, 3);
+        assert_eq!(request.signal.signum(), 10);
+
+        let response: ProcessSignalServiceResponse =
+            Err(ProcessSignalServiceError::PermissionDenied);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ProcessSignalServiceResponse>(&buf).unwrap(),
+            Err(ProcessSignalServiceError::PermissionDenied)
+        );
+    }
+}
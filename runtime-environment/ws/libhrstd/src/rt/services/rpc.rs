@@ -0,0 +1,222 @@
+//! Typed RPC over service portals. A [`Service`] names the portal in the capability space
+//! of user apps and each request type implements [`Rpc`] for its service. The request
+//! type fixes the message that travels through the UTCB and the type of the reply, hence
+//! the client stub [`rpc_call`] and the server glue [`rpc_serve`] can't disagree about
+//! the format.
+//!
+//! Services that user apps host have no fixed portal; clients get it from the name
+//! service, see [`crate::rt::services::name`]. They only implement [`Protocol`] and are
+//! called with [`rpc_call_at`].
+//!
+//! Services that multiplex multiple operations through a single portal wrap each request
+//! into a request enum; [`service_protocol!`] generates the [`Rpc`] implementations for
+//! them.
+
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+use core::mem::size_of;
+use libhedron::ipc_serde::de::DeserializeOwned;
+use libhedron::ipc_serde::Serialize;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+use libhedron::Utcb;
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+use libhedron::{
+    CapSel,
+    UtcbError,
+};
+
+/// The protocol of a service, i.e. the set of its requests.
+pub trait Protocol {}
+
+/// A service that user apps call via a portal at a fixed selector.
+pub trait Service: Protocol {
+    /// Portal of the service in the capability space of user apps.
+    const PORTAL: UserAppCapSpace;
+}
+
+/// A request to the service `S` with a typed reply.
+pub trait Rpc<S: Protocol> {
+    /// Message that travels through the UTCB. Either the request itself or the request
+    /// enum of the service.
+    type Message: Serialize;
+    /// Reply of the service. Replies without content, i.e. `()`, don't use the UTCB.
+    type Response: Serialize + DeserializeOwned;
+
+    /// Turns the request into the message for the portal.
+    fn into_message(self) -> Self::Message;
+}
+
+/// Errors of [`rpc_call`] and [`rpc_call_at`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+#[derive(Debug)]
+pub enum RpcError {
+    /// The message doesn't fit into the UTCB or the reply is malformed.
+    Utcb(UtcbError),
+    /// The service didn't handle the call, e.g. because the caller exceeded the rate limit
+    /// of the service in the roottask. Such a reply carries no data. Replies of handled
+    /// calls always do: the reply or, for replies without content, the request.
+    Denied,
+}
+
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+impl From<UtcbError> for RpcError {
+    fn from(err: UtcbError) -> Self {
+        Self::Utcb(err)
+    }
+}
+
+/// Sends the request to the portal of the service and returns the typed reply. Fails if
+/// the message doesn't fit into the UTCB or the service denied the call.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn rpc_call<S: Service, R: Rpc<S>>(request: R) -> Result<R::Response, RpcError> {
+    rpc_call_at::<S, R>(S::PORTAL.val(), request)
+}
+
+/// Like [`rpc_call`] but calls the portal at `portal`, i.e. a portal that the name service
+/// delegated to the caller.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn rpc_call_at<S: Protocol, R: Rpc<S>>(
+    portal: CapSel,
+    request: R,
+) -> Result<R::Response, RpcError> {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(&request.into_message())?;
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(portal).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(portal).unwrap();
+
+    if utcb.untyped_items_count() == 0 {
+        return Err(RpcError::Denied);
+    }
+    if size_of::<R::Response>() == 0 {
+        return Ok(libhedron::ipc_postcard::from_bytes(&[]).unwrap());
+    }
+    Ok(utcb.load_data()?)
+}
+
+/// Server side of [`rpc_call`]: passes the request to the handler and stores its reply
+/// in the UTCB. Replies without content leave the request in the UTCB, which tells the
+/// client that the call was handled, see [`RpcError::Denied`].
+pub fn rpc_serve<S: Protocol, R: Rpc<S>>(
+    request: R,
+    utcb: &mut Utcb,
+    handler: impl FnOnce(R) -> R::Response,
+) {
+    let response = handler(request);
+    if size_of::<R::Response>() != 0 {
+        utcb.store_data(&response).unwrap();
+    }
+}
+
+/// Defines a [`Service`] whose requests are variants of a request enum and implements
+/// [`Rpc`] for the type of each variant. Without the portal, it defines only the
+/// [`Protocol`] of a service that a user app hosts.
+///
+/// ```ignore
+/// service_protocol! {
+///     /// The file system service.
+///     pub service FsService(FsServicePT): FsServiceRequest {
+///         Open(FsOpenRequest) -> FD,
+///         Read(FsReadRequest) -> usize,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! service_protocol {
+    (
+        $(#[$meta:meta])*
+        $vis:vis service $service:ident($portal:ident): $message:ident {
+            $($variant:ident($request:ty) -> $response:ty),* $(,)?
+        }
+    ) => {
+        $crate::service_protocol! {
+            $(#[$meta])*
+            $vis service $service: $message {
+                $($variant($request) -> $response),*
+            }
+        }
+
+        impl $crate::rt::services::rpc::Service for $service {
+            const PORTAL: $crate::cap_space::user::UserAppCapSpace =
+                $crate::cap_space::user::UserAppCapSpace::$portal;
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis service $service:ident: $message:ident {
+            $($variant:ident($request:ty) -> $response:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        $vis struct $service;
+
+        impl $crate::rt::services::rpc::Protocol for $service {}
+
+        $(
+            impl $crate::rt::services::rpc::Rpc<$service> for $request {
+                type Message = $message;
+                type Response = $response;
+
+                fn into_message(self) -> $message {
+                    $message::$variant(self)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use libhedron::ipc_serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum TestRequest {
+        Greet(String),
+        Add((u32, u32)),
+    }
+
+    service_protocol! {
+        /// Service that only exists for this test.
+        service TestService(EchoServicePT): TestRequest {
+            Greet(String) -> String,
+            Add((u32, u32)) -> u32,
+        }
+    }
+
+    /// Sends the message through a buffer like through the UTCB.
+    fn transfer<R: Rpc<TestService>>(request: R) -> TestRequest {
+        let mut buf = [0; 64];
+        let bytes = libhedron::ipc_postcard::to_slice(&request.into_message(), &mut buf).unwrap();
+        libhedron::ipc_postcard::from_bytes(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_service_protocol() {
+        assert_eq!(
+            TestService::PORTAL.val(),
+            UserAppCapSpace::EchoServicePT.val()
+        );
+        assert_eq!(
+            transfer(String::from("Hallo")),
+            TestRequest::Greet(String::from("Hallo"))
+        );
+        assert_eq!(transfer((1, 2)), TestRequest::Add((1, 2)));
+
+        let response: <(u32, u32) as Rpc<TestService>>::Response = 3;
+        let mut buf = [0; 64];
+        let bytes = libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<u32>(bytes).unwrap(),
+            3
+        );
+    }
+}
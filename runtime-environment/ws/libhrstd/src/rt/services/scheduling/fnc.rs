@@ -0,0 +1,25 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::scheduling::{
+    SchedulingServiceRequest,
+    SchedulingServiceResponse,
+};
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+
+/// Queries or adjusts the scheduling parameters of a process. A process may adjust its
+/// own parameters and those of its children. The new parameters take effect immediately.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn scheduling_service(request: SchedulingServiceRequest) -> SchedulingServiceResponse {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(&request).unwrap();
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::SchedulingServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::SchedulingServicePT.val()).unwrap();
+
+    utcb.load_data().unwrap()
+}
@@ -0,0 +1,133 @@
+use crate::process::consts::ProcessId;
+use libhedron::consts::NUM_PRIORITIES;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use libhedron::Qpd;
+
+/// Scheduling parameters of the main SC of a process. Hedron schedules SCs of the same
+/// priority round-robin; each SC runs for its time quantum before it gets preempted.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SchedulingParams {
+    /// Priority between 1 and [`NUM_PRIORITIES`]. Higher is more important.
+    pub priority: u64,
+    /// Time quantum in microseconds.
+    pub quantum_us: u64,
+}
+
+impl SchedulingParams {
+    /// Parameters of new processes.
+    pub const DEFAULT: Self = Self {
+        priority: 1,
+        quantum_us: Qpd::DEFAULT_QUANTUM,
+    };
+
+    /// Returns true if Hedron accepts the parameters.
+    pub const fn is_valid(&self) -> bool {
+        self.priority >= 1 && self.priority <= NUM_PRIORITIES as u64 && self.quantum_us > 0
+    }
+
+    /// Returns the [`Qpd`] for `create_sc`. The parameters must be valid.
+    pub fn qpd(&self) -> Qpd {
+        Qpd::new(self.priority, Some(self.quantum_us))
+    }
+}
+
+impl From<Qpd> for SchedulingParams {
+    fn from(qpd: Qpd) -> Self {
+        Self {
+            priority: qpd.priority(),
+            quantum_us: qpd.quantum(),
+        }
+    }
+}
+
+/// Request that a user app sends to the scheduling service portal. A PID of `None`
+/// refers to the calling process.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum SchedulingServiceRequest {
+    /// Returns the current parameters of the process.
+    Get { pid: Option<ProcessId> },
+    /// Replaces the parameters of the process and returns the new parameters.
+    Set {
+        pid: Option<ProcessId>,
+        params: SchedulingParams,
+    },
+}
+
+/// Errors that the scheduling service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum SchedulingServiceError {
+    /// There is no process with the given PID or it has no SC that the roottask manages.
+    NoSuchProcess,
+    /// Only the process itself, its parent, and privileged processes (the roottask and the
+    /// programs it started itself) can adjust the parameters.
+    PermissionDenied,
+    /// The priority or the quantum is out of range.
+    InvalidParams,
+    /// Hedron rejected the new SC.
+    SyscallFailed,
+}
+
+/// Response of the scheduling service.
+pub type SchedulingServiceResponse = Result<SchedulingParams, SchedulingServiceError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_params() {
+        assert!(SchedulingParams::DEFAULT.is_valid());
+        let params = SchedulingParams {
+            priority: 3,
+            quantum_us: 1000,
+        };
+        assert_eq!(SchedulingParams::from(params.qpd()), params);
+        assert!(!SchedulingParams {
+            priority: 0,
+            quantum_us: 1000
+        }
+        .is_valid());
+        assert!(!SchedulingParams {
+            priority: 1,
+            quantum_us: 0
+        }
+        .is_valid());
+        assert!(!SchedulingParams {
+            priority: NUM_PRIORITIES as u64 + 1,
+            quantum_us: 1000
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let params = SchedulingParams {
+            priority: 2,
+            quantum_us: 500,
+        };
+        let request = SchedulingServiceRequest::Set {
+            pid: Some(3),
+            params,
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        match libhedron::ipc_postcard::from_bytes::<SchedulingServiceRequest>(&buf).unwrap() {
+            SchedulingServiceRequest::Set { pid, params: p } => {
+                assert_eq!(pid, Some(3));
+                assert_eq!(p, params);
+            }
+            request => panic!("unexpected request {:?}", request),
+        }
+
+        let response: SchedulingServiceResponse = Ok(params);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SchedulingServiceResponse>(&buf).unwrap(),
+            Ok(params)
+        );
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::{
+    sys_hybrid_sm_down,
+    sys_hybrid_sm_up,
+};
+use crate::rt::services::rpc::rpc_call;
+use crate::rt::services::semaphore::{
+    SemCloseRequest,
+    SemCreateRequest,
+    SemOpenRequest,
+    SemUnlinkRequest,
+    Semaphore,
+    SemaphoreService,
+    SemaphoreServiceResponse,
+};
+use alloc::string::ToString;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::{
+    sys_sm_down,
+    sys_sm_up,
+};
+use libhedron::syscall::{
+    SmCtrlZeroCounterStrategy,
+    SyscallError,
+    SyscallStatus,
+};
+
+/// Creates a semaphore with the counter `value`, see [`SemCreateRequest`].
+pub fn semaphore_service_create(
+    name: Option<&str>,
+    value: u64,
+    exclusive: bool,
+) -> SemaphoreServiceResponse<Semaphore> {
+    rpc_call::<SemaphoreService, _>(SemCreateRequest {
+        name: name.map(|name| name.to_string()),
+        value,
+        exclusive,
+    })
+    .unwrap()
+}
+
+/// Opens the semaphore with the name.
+pub fn semaphore_service_open(name: &str) -> SemaphoreServiceResponse<Semaphore> {
+    rpc_call::<SemaphoreService, _>(SemOpenRequest {
+        name: name.to_string(),
+    })
+    .unwrap()
+}
+
+/// Closes a semaphore, see [`SemCloseRequest`].
+pub fn semaphore_service_close(sem: Semaphore) -> SemaphoreServiceResponse<()> {
+    rpc_call::<SemaphoreService, _>(SemCloseRequest { sem }).unwrap()
+}
+
+/// Removes the name of a semaphore, see [`SemUnlinkRequest`].
+pub fn semaphore_service_unlink(name: &str) -> SemaphoreServiceResponse<()> {
+    rpc_call::<SemaphoreService, _>(SemUnlinkRequest {
+        name: name.to_string(),
+    })
+    .unwrap()
+}
+
+impl Semaphore {
+    /// Increments the counter or wakes up a process that waits for the semaphore.
+    pub fn up(self) {
+        #[cfg(feature = "native_rust_rt")]
+        sys_sm_up(self.sel()).unwrap();
+        #[cfg(feature = "foreign_rust_rt")]
+        sys_hybrid_sm_up(self.sel()).unwrap();
+    }
+
+    /// Blocks until the counter is positive and decrements it.
+    pub fn down(self) {
+        self.down_until(None);
+    }
+
+    /// Like [`Self::down`] but gives up when the TSC reaches `tsc_deadline`. Returns
+    /// `true` if the counter was decremented.
+    pub fn down_timeout(self, tsc_deadline: u64) -> bool {
+        // Hedron interprets a deadline of zero as no deadline
+        self.down_until(Some(tsc_deadline.max(1)))
+    }
+
+    /// Decrements the counter if it is positive. Never blocks. Returns `true` if the
+    /// counter was decremented.
+    pub fn try_down(self) -> bool {
+        // the deadline is in the past, hence Hedron doesn't block
+        self.down_timeout(1)
+    }
+
+    fn down_until(self, tsc_deadline: Option<u64>) -> bool {
+        #[cfg(feature = "native_rust_rt")]
+        let syscall_fn = sys_sm_down;
+        #[cfg(feature = "foreign_rust_rt")]
+        let syscall_fn = sys_hybrid_sm_down;
+
+        match syscall_fn(
+            self.sel(),
+            SmCtrlZeroCounterStrategy::Decrement,
+            tsc_deadline,
+        ) {
+            Ok(_) => true,
+            Err(SyscallError::HedronStatusError(SyscallStatus::Timeout)) => false,
+            Err(e) => panic!("sm down on semaphore {} failed: {:?}", self.sel(), e),
+        }
+    }
+}
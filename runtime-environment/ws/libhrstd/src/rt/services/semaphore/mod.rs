@@ -0,0 +1,11 @@
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+mod fnc;
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+mod posix;
+mod types;
+
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use fnc::*;
+#[cfg(any(feature = "native_rust_rt", feature = "foreign_rust_rt"))]
+pub use posix::*;
+pub use types::*;
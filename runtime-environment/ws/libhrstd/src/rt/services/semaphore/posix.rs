@@ -0,0 +1,82 @@
+//! POSIX-like named semaphores on top of the semaphore service. Native and hybrid foreign
+//! apps use them like `sem_open(3)` and friends, but the functions report errors as
+//! [`SemaphoreServiceError`] instead of `errno`. `sem_wait` and `sem_post` don't involve
+//! the roottask, they are plain `sm_ctrl` syscalls.
+
+use crate::rt::services::semaphore::{
+    semaphore_service_close,
+    semaphore_service_create,
+    semaphore_service_open,
+    semaphore_service_unlink,
+    Semaphore,
+    SemaphoreServiceError,
+    SemaphoreServiceResponse,
+};
+
+bitflags::bitflags! {
+    /// Flags of [`sem_open`]. The values are the ones of Linux.
+    pub struct SemOpenFlags: u32 {
+        /// Creates the semaphore if it doesn't exist.
+        const O_CREAT = 0o100;
+        /// Together with [`Self::O_CREAT`], fails if the semaphore exists.
+        const O_EXCL = 0o200;
+    }
+}
+
+/// Opens the semaphore with the name `name`, which may begin with a `/` like in POSIX.
+/// With [`SemOpenFlags::O_CREAT`], a missing semaphore is created with the counter
+/// `value`.
+pub fn sem_open(
+    name: &str,
+    flags: SemOpenFlags,
+    value: u64,
+) -> SemaphoreServiceResponse<Semaphore> {
+    let name = posix_name(name)?;
+    if flags.contains(SemOpenFlags::O_CREAT) {
+        semaphore_service_create(Some(name), value, flags.contains(SemOpenFlags::O_EXCL))
+    } else {
+        semaphore_service_open(name)
+    }
+}
+
+/// Blocks until the counter of the semaphore is positive and decrements it.
+pub fn sem_wait(sem: Semaphore) {
+    sem.down()
+}
+
+/// Like [`sem_wait`] but never blocks. Returns `false` if the counter is zero, i.e. where
+/// POSIX reports `EAGAIN`.
+pub fn sem_trywait(sem: Semaphore) -> bool {
+    sem.try_down()
+}
+
+/// Like [`sem_wait`] but gives up when the TSC reaches `tsc_deadline`. Returns `false` on
+/// timeout, i.e. where POSIX reports `ETIMEDOUT`.
+pub fn sem_timedwait(sem: Semaphore, tsc_deadline: u64) -> bool {
+    sem.down_timeout(tsc_deadline)
+}
+
+/// Increments the counter of the semaphore or wakes up a waiter.
+pub fn sem_post(sem: Semaphore) {
+    sem.up()
+}
+
+/// Closes the semaphore, see [`crate::rt::services::semaphore::SemCloseRequest`].
+pub fn sem_close(sem: Semaphore) -> SemaphoreServiceResponse<()> {
+    semaphore_service_close(sem)
+}
+
+/// Removes the name of the semaphore. Processes that have it open can still use it.
+pub fn sem_unlink(name: &str) -> SemaphoreServiceResponse<()> {
+    semaphore_service_unlink(posix_name(name)?)
+}
+
+/// Strips the optional leading `/` of a POSIX semaphore name.
+fn posix_name(name: &str) -> SemaphoreServiceResponse<&str> {
+    let name = name.strip_prefix('/').unwrap_or(name);
+    if name.contains('/') {
+        Err(SemaphoreServiceError::InvalidName)
+    } else {
+        Ok(name)
+    }
+}
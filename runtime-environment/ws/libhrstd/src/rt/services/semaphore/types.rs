@@ -0,0 +1,162 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::rt::services::name::is_valid_service_name;
+use crate::service_protocol;
+use alloc::string::String;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use libhedron::CapSel;
+
+/// Maximum number of semaphores that a process can create or open during its lifetime.
+/// Like the selectors of received capabilities, the selectors of semaphores are never
+/// reused.
+pub const MAX_SEMAPHORES_PER_PROCESS: u64 =
+    UserAppCapSpace::SemaphoreEnd as u64 - UserAppCapSpace::SemaphoreBase as u64 + 1;
+
+/// Highest initial value of a semaphore. Same as `SEM_VALUE_MAX` of Linux.
+pub const SEM_VALUE_MAX: u64 = i32::MAX as u64;
+
+/// A semaphore in the capability space of the process. The process can perform "up" and
+/// "down" operations on it directly via the `sm_ctrl` syscall, without the roottask.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Semaphore {
+    sel: CapSel,
+}
+
+impl Semaphore {
+    /// Refers to the semaphore at `sel`, e.g. one that the process received from another
+    /// process, see [`crate::rt::services::process::ProcessServiceRequest::SendCap`].
+    pub const fn from_sel(sel: CapSel) -> Self {
+        Self { sel }
+    }
+
+    /// Returns the selector of the semaphore.
+    pub const fn sel(self) -> CapSel {
+        self.sel
+    }
+}
+
+/// Returns whether `name` is a valid name of a semaphore. The rules are the same as for
+/// service names, see [`is_valid_service_name`].
+pub fn is_valid_semaphore_name(name: &str) -> bool {
+    is_valid_service_name(name)
+}
+
+/// Creates a semaphore with the counter `value`. A semaphore without name is only known
+/// to the caller, which can pass it to other processes. If a semaphore with the name
+/// exists already, the caller opens it instead and `value` is ignored, unless `exclusive`
+/// is set.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SemCreateRequest {
+    pub name: Option<String>,
+    pub value: u64,
+    pub exclusive: bool,
+}
+
+/// Opens the semaphore with the name. A process that opens a semaphore multiple times gets
+/// the same selector each time.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SemOpenRequest {
+    pub name: String,
+}
+
+/// Closes a semaphore of the caller. The roottask destroys the semaphore once no process
+/// has it open anymore and it has no name. Until then, the capability stays in the
+/// capability space of the caller.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SemCloseRequest {
+    pub sem: Semaphore,
+}
+
+/// Removes the name of a semaphore. Processes that have the semaphore open can still use
+/// it, but it can't be opened anymore and the name is free for a new semaphore.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SemUnlinkRequest {
+    pub name: String,
+}
+
+/// Request that a user app sends to the semaphore service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SemaphoreServiceRequest {
+    Create(SemCreateRequest),
+    Open(SemOpenRequest),
+    Close(SemCloseRequest),
+    Unlink(SemUnlinkRequest),
+}
+
+/// Errors that the semaphore service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SemaphoreServiceError {
+    /// The name is invalid, see [`is_valid_semaphore_name`]. (`EINVAL`)
+    InvalidName,
+    /// The initial value exceeds [`SEM_VALUE_MAX`]. (`EINVAL`)
+    InvalidValue,
+    /// A semaphore with the name exists already. (`EEXIST`)
+    Exists,
+    /// No semaphore has the name. (`ENOENT`)
+    NotFound,
+    /// The caller has no open semaphore at the selector. (`EINVAL`)
+    UnknownSemaphore,
+    /// The caller created or opened [`MAX_SEMAPHORES_PER_PROCESS`] semaphores already.
+    /// (`EMFILE`)
+    TooManySemaphores,
+    /// The capability space of the roottask is exhausted. (`ENOMEM`)
+    OutOfResources,
+}
+
+/// Response of the semaphore service.
+pub type SemaphoreServiceResponse<T> = Result<T, SemaphoreServiceError>;
+
+service_protocol! {
+    /// The semaphore service. Processes create named or anonymous semaphores, i.e. Hedron
+    /// SMs, and the roottask delegates the SM with the permissions for "up" and "down"
+    /// into the range of [`UserAppCapSpace::SemaphoreBase`]. Afterwards, the processes
+    /// synchronize without the roottask. User apps also find a POSIX-like interface, i.e.
+    /// `sem_open` and `sem_wait`, in this module.
+    pub service SemaphoreService(SemaphoreServicePT): SemaphoreServiceRequest {
+        Create(SemCreateRequest) -> SemaphoreServiceResponse<Semaphore>,
+        Open(SemOpenRequest) -> SemaphoreServiceResponse<Semaphore>,
+        Close(SemCloseRequest) -> SemaphoreServiceResponse<()>,
+        Unlink(SemUnlinkRequest) -> SemaphoreServiceResponse<()>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use alloc::string::ToString;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_max_semaphores() {
+        assert_eq!(MAX_SEMAPHORES_PER_PROCESS, 64);
+        assert!(UserAppCapSpace::SemaphoreBase.val() > UserAppCapSpace::ReceivedCapEnd.val());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = SemCreateRequest {
+            name: Some("jobs".to_string()),
+            value: 3,
+            exclusive: true,
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SemaphoreServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: SemaphoreServiceResponse<Semaphore> =
+            Ok(Semaphore::from_sel(UserAppCapSpace::SemaphoreBase.val()));
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SemaphoreServiceResponse<Semaphore>>(&buf)
+                .unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,26 @@
+use crate::rt::services::rpc::rpc_call;
+use crate::rt::services::serial_transfer::{
+    SerialRecvRequest,
+    SerialSendRequest,
+    SerialTransferResponse,
+    SerialTransferService,
+};
+use alloc::string::ToString;
+
+/// Sends the file at `path` to the host over the serial port. Returns the size of the file.
+/// Blocks until the transfer is done.
+pub fn serial_transfer_send(path: &str) -> SerialTransferResponse {
+    rpc_call::<SerialTransferService, _>(SerialSendRequest {
+        path: path.to_string(),
+    })
+    .unwrap()
+}
+
+/// Receives a file from the host over the serial port and stores it at `path`. Returns the
+/// size of the file. Blocks until the transfer is done.
+pub fn serial_transfer_recv(path: &str) -> SerialTransferResponse {
+    rpc_call::<SerialTransferService, _>(SerialRecvRequest {
+        path: path.to_string(),
+    })
+    .unwrap()
+}
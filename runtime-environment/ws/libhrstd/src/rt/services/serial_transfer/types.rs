@@ -0,0 +1,88 @@
+use crate::service_protocol;
+use alloc::string::String;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Largest file that the roottask receives.
+pub const SERIAL_TRANSFER_MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Sends the file at `path` to the host. The host receives it with
+/// `serialxfer-host recv`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SerialSendRequest {
+    pub path: String,
+}
+
+/// Receives a file from the host and stores it at `path`, replacing an existing file. The
+/// host sends it with `serialxfer-host send`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SerialRecvRequest {
+    pub path: String,
+}
+
+/// Request that a user app sends to the serial transfer service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SerialTransferRequest {
+    Send(SerialSendRequest),
+    Recv(SerialRecvRequest),
+}
+
+/// Errors that the serial transfer service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SerialTransferError {
+    /// Only the roottask and processes that the roottask started itself can transfer files.
+    PermissionDenied,
+    /// The file can't be opened, read, or written.
+    FileError,
+    /// The host didn't start or answer in time.
+    Timeout,
+    /// The host aborted the transfer.
+    Cancelled,
+    /// The transfer failed, e.g. because of too many broken packets.
+    TransferFailed,
+    /// The file is larger than [`SERIAL_TRANSFER_MAX_FILE_SIZE`].
+    TooLarge,
+}
+
+/// Response of the serial transfer service: the size of the transferred file.
+pub type SerialTransferResponse = Result<u64, SerialTransferError>;
+
+service_protocol! {
+    /// The serial transfer service. Transfers files between the file system and a host
+    /// over the serial port, see [`crate::util::serial_transfer`]. The console stalls
+    /// during a transfer.
+    pub service SerialTransferService(SerialTransferServicePT): SerialTransferRequest {
+        Send(SerialSendRequest) -> SerialTransferResponse,
+        Recv(SerialRecvRequest) -> SerialTransferResponse,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = SerialRecvRequest {
+            path: String::from("/tmp/app.elf"),
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SerialTransferRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: SerialTransferResponse = Err(SerialTransferError::Timeout);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SerialTransferResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use crate::rt::services::rpc::rpc_call;
+use crate::rt::services::shm::{
+    ShmAccess,
+    ShmCloseRequest,
+    ShmCreateRequest,
+    ShmId,
+    ShmInfo,
+    ShmMapRequest,
+    ShmOpenRequest,
+    ShmResizeRequest,
+    ShmService,
+    ShmServiceResponse,
+    ShmUnlinkRequest,
+    ShmUnmapRequest,
+};
+use alloc::string::ToString;
+
+/// Creates a segment of `size` bytes, see [`ShmCreateRequest`].
+pub fn shm_service_create(
+    name: Option<&str>,
+    size: u64,
+    exclusive: bool,
+    mode: u16,
+    access: ShmAccess,
+) -> ShmServiceResponse<ShmInfo> {
+    rpc_call::<ShmService, _>(ShmCreateRequest {
+        name: name.map(|name| name.to_string()),
+        size,
+        exclusive,
+        mode,
+        access,
+    })
+    .unwrap()
+}
+
+/// Opens the segment with the name, see [`ShmOpenRequest`].
+pub fn shm_service_open(name: &str, access: ShmAccess) -> ShmServiceResponse<ShmInfo> {
+    rpc_call::<ShmService, _>(ShmOpenRequest {
+        name: name.to_string(),
+        access,
+    })
+    .unwrap()
+}
+
+/// Changes the size of a segment, see [`ShmResizeRequest`].
+pub fn shm_service_resize(id: ShmId, size: u64) -> ShmServiceResponse<()> {
+    rpc_call::<ShmService, _>(ShmResizeRequest { id, size }).unwrap()
+}
+
+/// Maps a segment into the address space of the caller and returns the address.
+pub fn shm_service_map(id: ShmId, access: ShmAccess) -> ShmServiceResponse<u64> {
+    rpc_call::<ShmService, _>(ShmMapRequest { id, access }).unwrap()
+}
+
+/// Removes a mapping of [`shm_service_map`].
+pub fn shm_service_unmap(addr: u64) -> ShmServiceResponse<()> {
+    rpc_call::<ShmService, _>(ShmUnmapRequest { addr }).unwrap()
+}
+
+/// Closes a segment, see [`ShmCloseRequest`].
+pub fn shm_service_close(id: ShmId) -> ShmServiceResponse<()> {
+    rpc_call::<ShmService, _>(ShmCloseRequest { id }).unwrap()
+}
+
+/// Removes the name of a segment, see [`ShmUnlinkRequest`].
+pub fn shm_service_unlink(name: &str) -> ShmServiceResponse<()> {
+    rpc_call::<ShmService, _>(ShmUnlinkRequest {
+        name: name.to_string(),
+    })
+    .unwrap()
+}
@@ -0,0 +1,204 @@
+use crate::rt::services::name::is_valid_service_name;
+use crate::service_protocol;
+use alloc::string::String;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Largest size of a shared memory segment in bytes.
+pub const SHM_SIZE_MAX: u64 = 64 * 1024 * 1024;
+
+/// Largest total size in bytes of all segments that a process owns, i.e. that it created.
+pub const SHM_SIZE_MAX_PER_PROCESS: u64 = 4 * SHM_SIZE_MAX;
+
+/// Identifies a shared memory segment. The roottask never reuses the ids of removed
+/// segments.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ShmId(u64);
+
+impl ShmId {
+    pub const fn new(val: u64) -> Self {
+        Self(val)
+    }
+
+    pub const fn val(self) -> u64 {
+        self.0
+    }
+}
+
+/// Returns whether `name` is a valid name of a shared memory segment. The rules are the
+/// same as for service names, see [`is_valid_service_name`].
+pub fn is_valid_shm_name(name: &str) -> bool {
+    is_valid_service_name(name)
+}
+
+/// A segment that the caller created or opened.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmInfo {
+    pub id: ShmId,
+    /// Size of the segment in bytes.
+    pub size: u64,
+}
+
+/// How a process opens or maps a segment.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ShmAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Creates a segment of `size` zeroed bytes and opens it with `access`. The caller owns
+/// the segment and its size counts against [`SHM_SIZE_MAX_PER_PROCESS`]. A segment without
+/// name is only known to the caller. If a segment with the name exists already, the caller
+/// opens it instead and `size` and `mode` are ignored, unless `exclusive` is set.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmCreateRequest {
+    pub name: Option<String>,
+    pub size: u64,
+    pub exclusive: bool,
+    /// Permissions of the segment like the mode of a file, e.g. `0o600`. Each process
+    /// counts as its own user: the owner bits apply to the owner, the other bits to all
+    /// other processes. The group bits have no effect.
+    pub mode: u16,
+    pub access: ShmAccess,
+}
+
+/// Opens the segment with the name with `access`, if the mode of the segment permits it.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmOpenRequest {
+    pub name: String,
+    pub access: ShmAccess,
+}
+
+/// Changes the size of a segment that the caller opened for writing. New bytes are zero.
+/// Fails while a process has the segment mapped. Segments whose owner exited can't grow.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmResizeRequest {
+    pub id: ShmId,
+    pub size: u64,
+}
+
+/// Maps the whole segment into the mmap area of the caller and returns the address. Each
+/// request creates a new mapping. All mappings of a segment share the same memory. Writable
+/// mappings need a segment that the caller opened for writing.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmMapRequest {
+    pub id: ShmId,
+    pub access: ShmAccess,
+}
+
+/// Removes the mapping at the address that [`ShmMapRequest`] returned.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmUnmapRequest {
+    pub addr: u64,
+}
+
+/// Closes a segment of the caller. Mappings of the segment stay valid. The roottask frees
+/// the memory once no process has the segment open or mapped and it has no name.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmCloseRequest {
+    pub id: ShmId,
+}
+
+/// Removes the name of a segment. Processes that have the segment open or mapped can
+/// still use it, but it can't be opened anymore and the name is free for a new segment.
+/// Only the owner can unlink a segment. After the owner exited, every process that may
+/// write the segment can.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ShmUnlinkRequest {
+    pub name: String,
+}
+
+/// Request that a user app sends to the shared memory service portal.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ShmServiceRequest {
+    Create(ShmCreateRequest),
+    Open(ShmOpenRequest),
+    Resize(ShmResizeRequest),
+    Map(ShmMapRequest),
+    Unmap(ShmUnmapRequest),
+    Close(ShmCloseRequest),
+    Unlink(ShmUnlinkRequest),
+}
+
+/// Errors that the shared memory service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ShmServiceError {
+    /// The name is invalid, see [`is_valid_shm_name`]. (`EINVAL`)
+    InvalidName,
+    /// The size exceeds [`SHM_SIZE_MAX`] or the mapped range isn't part of the segment.
+    /// (`EINVAL`)
+    InvalidSize,
+    /// A segment with the name exists already. (`EEXIST`)
+    Exists,
+    /// No segment has the name. (`ENOENT`)
+    NotFound,
+    /// The caller has no such segment open or no mapping at the address. (`EINVAL`)
+    UnknownSegment,
+    /// The segment is mapped and can't change its size. (`EBUSY`)
+    Busy,
+    /// The physical memory or the mmap area of the caller is exhausted. (`ENOMEM`)
+    OutOfMemory,
+    /// The mode of the segment or the way the caller opened it doesn't permit the
+    /// operation, or the caller doesn't own the segment. (`EACCES`)
+    PermissionDenied,
+    /// The segments of the owner would exceed [`SHM_SIZE_MAX_PER_PROCESS`], or the owner
+    /// exited. (`ENOSPC`)
+    LimitExceeded,
+}
+
+/// Response of the shared memory service.
+pub type ShmServiceResponse<T> = Result<T, ShmServiceError>;
+
+service_protocol! {
+    /// The shared memory service. Processes create named or anonymous segments of memory
+    /// that the roottask owns. The roottask maps a segment into each process that asks for
+    /// it, with the permissions that the process chooses. Afterwards, the processes share
+    /// the memory without the roottask.
+    pub service ShmService(ShmServicePT): ShmServiceRequest {
+        Create(ShmCreateRequest) -> ShmServiceResponse<ShmInfo>,
+        Open(ShmOpenRequest) -> ShmServiceResponse<ShmInfo>,
+        Resize(ShmResizeRequest) -> ShmServiceResponse<()>,
+        Map(ShmMapRequest) -> ShmServiceResponse<u64>,
+        Unmap(ShmUnmapRequest) -> ShmServiceResponse<()>,
+        Close(ShmCloseRequest) -> ShmServiceResponse<()>,
+        Unlink(ShmUnlinkRequest) -> ShmServiceResponse<()>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::rpc::Rpc;
+    use alloc::string::ToString;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = ShmCreateRequest {
+            name: Some("frames".to_string()),
+            size: 8192,
+            exclusive: false,
+            mode: 0o600,
+            access: ShmAccess::ReadWrite,
+        }
+        .into_message();
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ShmServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: ShmServiceResponse<ShmInfo> = Ok(ShmInfo {
+            id: ShmId::new(3),
+            size: 8192,
+        });
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<ShmServiceResponse<ShmInfo>>(&buf).unwrap(),
+            response
+        );
+    }
+}
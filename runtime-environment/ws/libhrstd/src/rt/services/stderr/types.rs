@@ -1 +1,26 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::rt::services::rpc::{
+    Protocol,
+    Rpc,
+    Service,
+};
+use crate::rt::services::stdout::OutputChunk;
 
+/// The stderr service. Takes the same [`OutputChunk`]s as the stdout service.
+#[derive(Debug)]
+pub struct StderrService;
+
+impl Protocol for StderrService {}
+
+impl Service for StderrService {
+    const PORTAL: UserAppCapSpace = UserAppCapSpace::StderrServicePT;
+}
+
+impl<'a> Rpc<StderrService> for OutputChunk<'a> {
+    type Message = Self;
+    type Response = ();
+
+    fn into_message(self) -> Self {
+        self
+    }
+}
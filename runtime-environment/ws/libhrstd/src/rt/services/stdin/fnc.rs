@@ -0,0 +1,27 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::stdin::{
+    StdinServiceRequest,
+    StdinServiceResponse,
+};
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+
+/// Reads at most `max_len` bytes of input from STDIN. Returns immediately with the bytes
+/// that are available, which might be none. Callers that wait for input have to poll.
+/// If `echo` is set, the roottask echoes the bytes to the console.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn stdin_service(max_len: usize, echo: bool) -> StdinServiceResponse {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(&StdinServiceRequest { max_len, echo })
+        .unwrap();
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::StdinServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::StdinServicePT.val()).unwrap();
+
+    utcb.load_data().unwrap()
+}
@@ -0,0 +1,51 @@
+use alloc::vec::Vec;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Upper bound for the bytes of a single request. Keeps the response small enough for
+/// the UTCB.
+pub const STDIN_MAX_READ: usize = 1024;
+
+/// Request that a user app sends to the stdin service portal.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StdinServiceRequest {
+    /// Maximum number of bytes to read. Capped at [`STDIN_MAX_READ`].
+    pub max_len: usize,
+    /// Echo the bytes to the console, like a terminal does. Interactive programs need
+    /// this, because each write to STDOUT becomes a separate line.
+    pub echo: bool,
+}
+
+/// Response of the stdin service: the bytes that were available at the time of the call.
+/// Empty if there was no input. The service never blocks.
+pub type StdinServiceResponse = Vec<u8>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = StdinServiceRequest {
+            max_len: 64,
+            echo: true,
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<StdinServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        // the largest response must fit into the UTCB
+        let response: StdinServiceResponse = vec![b'x'; STDIN_MAX_READ];
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<StdinServiceResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
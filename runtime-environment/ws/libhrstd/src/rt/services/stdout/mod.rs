@@ -9,13 +9,39 @@ pub use types::*;
 use core::cmp::min;
 
 /// Splits a message into multiple chunks and applies the function step by step. This is useful
-/// because the message may be to large to fit into the UTCB.
+/// because the message may be to large to fit into the UTCB. See [`send_msg_chunked`].
+///
+/// Chunks never split a UTF-8 code point, i.e. a chunk may be a few bytes shorter than
+/// `step_size`. A chunk only exceeds `step_size` if a single code point is larger.
 #[allow(unused)]
-pub(super) fn msg_chunk_bulk_apply(msg: &str, step_size: usize, fnc: impl FnMut(&str) -> ()) {
-    (0..msg.len())
-        .step_by(step_size)
-        .map(|step| &msg[step..min(msg.len(), step + step_size)])
-        .for_each(fnc);
+pub(super) fn msg_chunk_bulk_apply(msg: &str, step_size: usize, mut fnc: impl FnMut(&str) -> ()) {
+    let mut remaining = msg;
+    while !remaining.is_empty() {
+        let mut chunk_len = min(remaining.len(), step_size);
+        while !remaining.is_char_boundary(chunk_len) {
+            chunk_len -= 1;
+        }
+        if chunk_len == 0 {
+            chunk_len = remaining.chars().next().unwrap().len_utf8();
+        }
+        let (chunk, rest) = remaining.split_at(chunk_len);
+        fnc(chunk);
+        remaining = rest;
+    }
+}
+
+/// Sends a message as [`OutputChunk`]s of at most [`OUTPUT_CHUNK_MAX_LEN`] bytes. Only
+/// the final chunk has [`OutputChunk::last`] set. Sends nothing for an empty message.
+#[allow(unused)]
+pub(super) fn send_msg_chunked(msg: &str, mut send: impl FnMut(&OutputChunk)) {
+    let mut sent = 0;
+    msg_chunk_bulk_apply(msg, OUTPUT_CHUNK_MAX_LEN, |chunk| {
+        sent += chunk.len();
+        send(&OutputChunk {
+            msg: chunk,
+            last: sent == msg.len(),
+        });
+    });
 }
 
 #[cfg(test)]
@@ -39,4 +65,60 @@ mod tests {
         assert_eq!(msgs.borrow()[1], " Welt");
         assert_eq!(msgs.borrow()[2], "!\n");
     }
+
+    fn collect_chunks(msg: &str, step_size: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        msg_chunk_bulk_apply(msg, step_size, |chunk| chunks.push(String::from(chunk)));
+        chunks
+    }
+
+    #[test]
+    fn test_msg_to_chunk_splitter_multi_byte() {
+        // 2, 3, and 4 byte code points
+        let msg = "Grüße: 10€ 😀!";
+        for step_size in 1..=msg.len() + 1 {
+            let chunks = collect_chunks(msg, step_size);
+            assert_eq!(chunks.concat(), msg, "step_size={}", step_size);
+            for chunk in &chunks {
+                assert!(!chunk.is_empty());
+                assert!(
+                    chunk.len() <= step_size || chunk.chars().count() == 1,
+                    "step_size={}, chunk={:?}",
+                    step_size,
+                    chunk
+                );
+            }
+        }
+
+        // "ü" crosses the boundary after 3 bytes
+        assert_eq!(collect_chunks("Grüße", 3), ["Gr", "ü", "ße"]);
+        // code points larger than the step size are not split
+        assert_eq!(collect_chunks("😀€", 2), ["😀", "€"]);
+        assert!(collect_chunks("", 4).is_empty());
+    }
+
+    #[test]
+    fn test_send_msg_chunked() {
+        let msg = "x".repeat(2 * OUTPUT_CHUNK_MAX_LEN + 1);
+        let mut chunks = Vec::new();
+        send_msg_chunked(&msg, |chunk| chunks.push((chunk.msg.len(), chunk.last)));
+        assert_eq!(
+            chunks,
+            [
+                (OUTPUT_CHUNK_MAX_LEN, false),
+                (OUTPUT_CHUNK_MAX_LEN, false),
+                (1, true)
+            ]
+        );
+
+        let mut chunks = Vec::new();
+        send_msg_chunked("Hallo", |chunk| {
+            chunks.push((String::from(chunk.msg), chunk.last))
+        });
+        assert_eq!(chunks, [(String::from("Hallo"), true)]);
+
+        let mut count = 0;
+        send_msg_chunked("", |_| count += 1);
+        assert_eq!(count, 0);
+    }
 }
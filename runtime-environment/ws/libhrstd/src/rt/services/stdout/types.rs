@@ -1 +1,86 @@
+use crate::cap_space::user::UserAppCapSpace;
+use crate::rt::services::rpc::{
+    Protocol,
+    Rpc,
+    Service,
+};
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
 
+/// Upper bound for the bytes of message text in a single [`OutputChunk`]. Leaves room for
+/// the serialization overhead in the UTCB.
+pub const OUTPUT_CHUNK_MAX_LEN: usize = 4000;
+
+/// Request that a user app sends to the stdout or the stderr service portal. Messages
+/// that don't fit into the UTCB are sent as multiple chunks. The service buffers the
+/// chunks per process and outputs the whole message at once after the last chunk, hence
+/// the output of other processes never splits the message.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct OutputChunk<'a> {
+    /// Part of the message. At most [`OUTPUT_CHUNK_MAX_LEN`] bytes, unless a single UTF-8
+    /// code point is larger.
+    pub msg: &'a str,
+    /// True for the last chunk of the message.
+    pub last: bool,
+}
+
+/// The stdout service. Replies to an [`OutputChunk`] without content.
+#[derive(Debug)]
+pub struct StdoutService;
+
+impl Protocol for StdoutService {}
+
+impl Service for StdoutService {
+    const PORTAL: UserAppCapSpace = UserAppCapSpace::StdoutServicePT;
+}
+
+impl<'a> Rpc<StdoutService> for OutputChunk<'a> {
+    type Message = Self;
+    type Response = ();
+
+    fn into_message(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rt::services::stdout::msg_chunk_bulk_apply;
+    use alloc::string::String;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let chunk = OutputChunk {
+            msg: "Hallo Welt!",
+            last: true,
+        };
+        libhedron::ipc_postcard::to_slice(&chunk, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<OutputChunk>(&buf).unwrap(),
+            chunk
+        );
+    }
+
+    /// Every chunk of a message that spans multiple pages must fit into the UTCB.
+    #[test]
+    fn test_multi_page_msg_chunks_fit_into_utcb() {
+        let msg = "Grüße 😀 ".repeat(3 * 4096 / 10);
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let mut reassembled = String::new();
+        let mut chunk_count = 0;
+        msg_chunk_bulk_apply(&msg, OUTPUT_CHUNK_MAX_LEN, |msg| {
+            let chunk = OutputChunk { msg, last: false };
+            libhedron::ipc_postcard::to_slice(&chunk, &mut buf).unwrap();
+            let chunk = libhedron::ipc_postcard::from_bytes::<OutputChunk>(&buf).unwrap();
+            reassembled.push_str(chunk.msg);
+            chunk_count += 1;
+        });
+        assert_eq!(reassembled, msg);
+        assert_eq!(chunk_count, 4);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::sys_hybrid_call;
+use crate::rt::services::system_time::{
+    SystemTimeServiceRequest,
+    SystemTimeServiceResponse,
+};
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::sys_call;
+
+/// Queries or adjusts the time of the system. Only privileged processes may adjust it.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn system_time_service(request: SystemTimeServiceRequest) -> SystemTimeServiceResponse {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(&request).unwrap();
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::SystemTimeServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::SystemTimeServicePT.val()).unwrap();
+
+    utcb.load_data().unwrap()
+}
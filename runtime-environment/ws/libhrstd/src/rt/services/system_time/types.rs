@@ -0,0 +1,85 @@
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The time of the system.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SystemTime {
+    /// Wall-clock time in nanoseconds since the Unix epoch (UTC). Might jump when the
+    /// time gets adjusted.
+    pub realtime_ns: u64,
+    /// Nanoseconds since boot. Never jumps.
+    pub monotonic_ns: u64,
+    /// Frequency of the TSC in kHz. Converts clock ticks, e.g. of
+    /// [`crate::time::Instant`], to wall-clock durations.
+    pub tsc_freq_khz: u64,
+}
+
+/// How the wall clock reaches a new time.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum TimeAdjustment {
+    /// The wall clock jumps to the new time at once.
+    Step,
+    /// The wall clock runs faster or slower until it reaches the new time after the
+    /// given duration. It never runs backwards if the duration is larger than the
+    /// difference to the new time.
+    Smear { duration_ns: u64 },
+}
+
+/// Request that a user app sends to the system time service portal.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum SystemTimeServiceRequest {
+    /// Returns the current time.
+    Get,
+    /// Adjusts the wall clock and returns the time afterwards. The new time is also
+    /// written to the real-time clock of the platform.
+    Set {
+        realtime_ns: u64,
+        adjustment: TimeAdjustment,
+    },
+}
+
+/// Errors that the system time service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum SystemTimeServiceError {
+    /// Only the roottask and processes that the roottask started itself can adjust the
+    /// time.
+    PermissionDenied,
+}
+
+/// Response of the system time service.
+pub type SystemTimeServiceResponse = Result<SystemTime, SystemTimeServiceError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = SystemTimeServiceRequest::Set {
+            realtime_ns: 1_700_000_000_000_000_000,
+            adjustment: TimeAdjustment::Smear {
+                duration_ns: 60_000_000_000,
+            },
+        };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SystemTimeServiceRequest>(&buf).unwrap(),
+            request
+        );
+
+        let response: SystemTimeServiceResponse = Ok(SystemTime {
+            realtime_ns: 1_700_000_000_000_000_000,
+            monotonic_ns: 42,
+            tsc_freq_khz: 3_000_000,
+        });
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<SystemTimeServiceResponse>(&buf).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use crate::cap_space::user::UserAppCapSpace;
+#[cfg(feature = "foreign_rust_rt")]
+use crate::rt::hybrid_rt::syscalls::{
+    sys_hybrid_call,
+    sys_hybrid_sm_down,
+};
+use crate::rt::services::timer::{
+    timer_sm_sel,
+    TimerId,
+    TimerServiceRequest,
+    TimerServiceResponse,
+};
+use crate::rt::user_load_utcb::user_load_utcb_mut;
+use libhedron::syscall::SmCtrlZeroCounterStrategy;
+#[cfg(feature = "native_rust_rt")]
+use libhedron::syscall::{
+    sys_call,
+    sys_sm_down,
+};
+
+/// Creates a timer that fires once after `delay_ns` nanoseconds.
+/// Wait for it with [`timer_service_wait`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn timer_service_create_one_shot(delay_ns: u64) -> TimerServiceResponse {
+    timer_service_call(TimerServiceRequest::CreateOneShot { delay_ns })
+}
+
+/// Creates a timer that fires every `period_ns` nanoseconds.
+/// Wait for it with [`timer_service_wait`].
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn timer_service_create_periodic(period_ns: u64) -> TimerServiceResponse {
+    timer_service_call(TimerServiceRequest::CreatePeriodic { period_ns })
+}
+
+/// Stops a timer. Its ID can be reused afterwards.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn timer_service_cancel(id: TimerId) -> TimerServiceResponse {
+    timer_service_call(TimerServiceRequest::Cancel(id))
+}
+
+/// Blocks until the given timer fires. If the timer fired multiple times since the last
+/// call, each call returns immediately once per missed expiration.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn timer_service_wait(id: TimerId) {
+    #[cfg(feature = "native_rust_rt")]
+    sys_sm_down(timer_sm_sel(id), SmCtrlZeroCounterStrategy::Decrement, None).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_sm_down(timer_sm_sel(id), SmCtrlZeroCounterStrategy::Decrement, None).unwrap();
+}
+
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+fn timer_service_call(request: TimerServiceRequest) -> TimerServiceResponse {
+    let utcb = user_load_utcb_mut();
+    utcb.store_data(&request).unwrap();
+
+    #[cfg(feature = "native_rust_rt")]
+    sys_call(UserAppCapSpace::TimerServicePT.val()).unwrap();
+    #[cfg(feature = "foreign_rust_rt")]
+    sys_hybrid_call(UserAppCapSpace::TimerServicePT.val()).unwrap();
+
+    utcb.load_data().unwrap()
+}
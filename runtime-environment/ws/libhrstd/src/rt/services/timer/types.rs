@@ -0,0 +1,69 @@
+use crate::cap_space::user::UserAppCapSpace;
+use libhedron::ipc_serde::{
+    Deserialize,
+    Serialize,
+};
+use libhedron::CapSel;
+
+/// Maximum number of timers a single process can have at the same time.
+pub const MAX_TIMERS_PER_PROCESS: u64 = 16;
+
+/// Identifies a timer of a process. In range `0..MAX_TIMERS_PER_PROCESS`.
+pub type TimerId = u64;
+
+/// Returns the capability selector of the semaphore that belongs to the given timer
+/// inside the capability space of the user app.
+pub const fn timer_sm_sel(id: TimerId) -> CapSel {
+    UserAppCapSpace::TimerSmBase as CapSel + id
+}
+
+/// Request that a user app sends to the timer service portal.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum TimerServiceRequest {
+    /// Creates a timer that fires once after `delay_ns` nanoseconds.
+    CreateOneShot { delay_ns: u64 },
+    /// Creates a timer that fires every `period_ns` nanoseconds.
+    CreatePeriodic { period_ns: u64 },
+    /// Stops the given timer and releases its ID.
+    Cancel(TimerId),
+}
+
+/// Errors that the timer service can report.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum TimerServiceError {
+    /// The process already has [`MAX_TIMERS_PER_PROCESS`] timers.
+    TooManyTimers,
+    /// There is no active timer with the given ID.
+    UnknownTimer,
+    /// A period of zero was requested.
+    InvalidPeriod,
+    /// The delay or the period is so large that the deadline of the timer overflows.
+    DelayTooLarge,
+}
+
+/// Response of the timer service. The roottask replies with the ID of the affected timer.
+pub type TimerServiceResponse = Result<TimerId, TimerServiceError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libhedron::UTCB_DATA_CAPACITY;
+
+    #[test]
+    fn test_serialization() {
+        let mut buf = [0; UTCB_DATA_CAPACITY];
+        let request = TimerServiceRequest::CreatePeriodic { period_ns: 1337 };
+        libhedron::ipc_postcard::to_slice(&request, &mut buf).unwrap();
+        match libhedron::ipc_postcard::from_bytes::<TimerServiceRequest>(&buf).unwrap() {
+            TimerServiceRequest::CreatePeriodic { period_ns } => assert_eq!(period_ns, 1337),
+            _ => panic!("wrong variant"),
+        }
+
+        let response: TimerServiceResponse = Err(TimerServiceError::TooManyTimers);
+        libhedron::ipc_postcard::to_slice(&response, &mut buf).unwrap();
+        assert_eq!(
+            libhedron::ipc_postcard::from_bytes::<TimerServiceResponse>(&buf).unwrap(),
+            Err(TimerServiceError::TooManyTimers)
+        );
+    }
+}
@@ -1,16 +1,50 @@
 //! Helper methods to load the UTCB in Hedron user apps.
 //! It is mapped at a well-known location.
+//!
+//! Apps that host a service have a second EC that handles the service calls, with its own
+//! UTCB at [`USER_SERVICE_UTCB_ADDR`]. The helpers return the UTCB of the EC that runs,
+//! which they recognize by the stack, see [`set_service_ec_stack`].
 
 use crate::libhedron::Utcb;
-use crate::uaddress_space::USER_UTCB_ADDR;
+use crate::uaddress_space::{
+    USER_SERVICE_UTCB_ADDR,
+    USER_UTCB_ADDR,
+};
+use core::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+
+/// Begin (inclusive) and end (exclusive) of the stack of the EC that handles the calls of
+/// the service that the app hosts. Both zero if the app hosts no service.
+static SERVICE_EC_STACK: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
 
 #[allow(unused)]
 pub fn user_load_utcb() -> &'static Utcb {
-    unsafe { (USER_UTCB_ADDR as *const Utcb).as_ref().unwrap() }
+    unsafe { (utcb_addr() as *const Utcb).as_ref().unwrap() }
 }
 
 /// Loads the UTCB from the well-known location in user apps.
 /// TODO currently this allows multiple mutuable references and ignores guarantees Rust wants to give.
 pub fn user_load_utcb_mut() -> &'static mut Utcb {
-    unsafe { (USER_UTCB_ADDR as *mut Utcb).as_mut().unwrap() }
+    unsafe { (utcb_addr() as *mut Utcb).as_mut().unwrap() }
+}
+
+/// Tells the helpers the stack of the EC that handles the calls of the service that the
+/// app hosts.
+pub fn set_service_ec_stack(begin: u64, end: u64) {
+    SERVICE_EC_STACK[0].store(begin, Ordering::SeqCst);
+    SERVICE_EC_STACK[1].store(end, Ordering::SeqCst);
+}
+
+fn utcb_addr() -> u64 {
+    let stack_ptr: u64;
+    unsafe { core::arch::asm!("mov {}, rsp", out(reg) stack_ptr) };
+    let service_ec_stack =
+        SERVICE_EC_STACK[0].load(Ordering::SeqCst)..SERVICE_EC_STACK[1].load(Ordering::SeqCst);
+    if service_ec_stack.contains(&stack_ptr) {
+        USER_SERVICE_UTCB_ADDR
+    } else {
+        USER_UTCB_ADDR
+    }
 }
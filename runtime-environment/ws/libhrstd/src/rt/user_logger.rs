@@ -1,15 +1,16 @@
-use crate::rt::services::stdout::stdout_service;
-use crate::util::ansi::{
-    AnsiStyle,
-    Color,
-    TextStyle,
+//! Logger of native apps. Sends each record to the log service of the roottask, which
+//! keeps it in its log buffer and prints it on the console, unless the console output is
+//! disabled. See [`crate::rt::services::logging`].
+
+use crate::rt::services::logging::{
+    log_service_level,
+    log_service_write,
+    LOG_MAX_MSG_LEN,
 };
+use alloc::string::String;
 use arrayvec::ArrayString;
 use core::fmt::Write;
-use libhedron::mem::PAGE_SIZE;
 use log::{
-    Level,
-    LevelFilter,
     Log,
     Metadata,
     Record,
@@ -21,97 +22,29 @@ static LOGGER: UserRustLogger = UserRustLogger;
 pub struct UserRustLogger;
 
 impl UserRustLogger {
+    /// Installs the logger. The app skips the records that the filters of the log service
+    /// exclude at this point. Filters that let fewer records through apply immediately;
+    /// filters that let more records through only apply to apps that start afterwards.
     pub fn init() {
         log::set_logger(&LOGGER).unwrap();
-        log::set_max_level(LevelFilter::Info);
-    }
-
-    /// Builds the formatted error message in a stack-allocated array.
-    /// Because we don't have nested logging, this is fine and cheap.
-    ///
-    /// Make sure that stack of roottask is big enough.
-    fn fmt_msg(record: &Record) -> ArrayString<PAGE_SIZE> {
-        let mut buf = ArrayString::new();
-
-        // "TRACE", " INFO", "ERROR"...
-        let mut level = ArrayString::<5>::new();
-        write!(&mut level, "{:>5}", record.level().as_str()).unwrap();
-
-        let crate_name = record
-            .module_path()
-            .map(|module| module.split_once("::").map(|x| x.0).unwrap_or(module))
-            .unwrap_or("<unknown mod>");
-
-        // file name: origin of logging msg
-        let file = record
-            .file()
-            // remove full system path, only keep file path in project
-            .map(|f| {
-                let index = f.find("/src");
-                if let Some(index) = index {
-                    // skip slash
-                    let index = index + 1;
-                    &f[index..]
-                } else {
-                    f
-                }
-            })
-            .unwrap_or("<unknown file>");
-
-        let mut line = ArrayString::<5>::new();
-        write!(&mut line, "{}", record.line().unwrap_or(0)).unwrap();
-
-        let res = writeln!(
-            &mut buf,
-            "[{level:>5}] {crate_name}:{file:>15}{at_sign}{line}{double_point} {msg}",
-            // level is padded to 5 chars and right-aligned
-            // style around
-            level = Self::style_for_level(record.level()).msg(level.as_str()),
-            crate_name = AnsiStyle::new()
-                .foreground_color(Color::Magenta)
-                .msg(crate_name),
-            file = AnsiStyle::new().msg(file).text_style(TextStyle::Dimmed),
-            at_sign = AnsiStyle::new().text_style(TextStyle::Dimmed).msg("@"),
-            line = AnsiStyle::new()
-                .msg(line.as_str())
-                .text_style(TextStyle::Dimmed),
-            double_point = AnsiStyle::new().text_style(TextStyle::Bold).msg(":"),
-            msg = record.args(),
-        );
-
-        if res.is_err() {
-            let msg_too_long = "<LOG MSG TOO LONG; TRUNCATED>\n";
-            unsafe { buf.set_len(buf.len() - msg_too_long.len()) };
-            let _ = buf.write_str(msg_too_long);
-        }
-
-        buf
-    }
-
-    /// Gets the style for "DEBUG", "ERROR" etc.
-    fn style_for_level<'a>(level: Level) -> AnsiStyle<'a> {
-        match level {
-            Level::Error => AnsiStyle::new()
-                .text_style(TextStyle::Bold)
-                .foreground_color(Color::Red),
-            Level::Warn => AnsiStyle::new()
-                .text_style(TextStyle::Bold)
-                .foreground_color(Color::Yellow),
-            Level::Info => AnsiStyle::new().foreground_color(Color::Green),
-            Level::Debug => AnsiStyle::new().foreground_color(Color::Yellow),
-            Level::Trace => AnsiStyle::new().foreground_color(Color::Green),
-        }
+        log::set_max_level(log_service_level(None).level_filter());
     }
 }
 
 impl Log for UserRustLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
     }
 
     fn log(&self, record: &Record) {
-        let msg = Self::fmt_msg(record);
-        stdout_service(msg.as_str());
+        // a too long message gets truncated, so that it fits into the UTCB
+        let mut msg = ArrayString::<LOG_MAX_MSG_LEN>::new();
+        let _ = write!(&mut msg, "{}", record.args());
+        log_service_write(
+            record.level().into(),
+            String::from(record.target()),
+            String::from(msg.as_str()),
+        );
     }
 
     fn flush(&self) {}
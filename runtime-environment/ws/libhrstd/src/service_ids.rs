@@ -13,6 +13,45 @@ pub enum ServiceId {
     /// Service to measure IPC costs without the portal multiplexing mechanism
     /// but a raw call instead.
     RawEchoService,
+    /// Service to create one-shot and periodic timers, that signal a semaphore.
+    TimerService,
+    /// Service that reports the build metadata of the roottask and the Hedron API version.
+    BuildInfoService,
+    /// Service to send a signal to another process, e.g. to interrupt or terminate a child.
+    ProcessSignalService,
+    /// Service to send and receive Ethernet frames and UDP datagrams.
+    NetworkService,
+    /// Service to query and adjust the scheduling parameters (priority and time quantum)
+    /// of a running process.
+    SchedulingService,
+    /// Service to start a program from the file system at runtime.
+    ProcessService,
+    /// Service to query and adjust the wall-clock time of the system.
+    SystemTimeService,
+    /// Service to read the input of the console, i.e. of the serial port.
+    StdinService,
+    /// Service that maps names of services to their portals, see
+    /// [`crate::rt::services::name`].
+    NameService,
+    /// Service to transfer files between the file system and a host over the serial port.
+    SerialTransferService,
+    /// Service to write log records and to query and filter the log of the roottask.
+    LogService,
+    /// Service to count hardware events, such as retired instructions, with the
+    /// performance counters of the CPU.
+    PerfService,
+    /// Service to create and open semaphores that processes share, see
+    /// [`crate::rt::services::semaphore`].
+    SemaphoreService,
+    /// Service to create shared memory segments and to map them into processes, see
+    /// [`crate::rt::services::shm`].
+    ShmService,
+    /// Service that forwards interrupts of devices to driver processes, see
+    /// [`crate::rt::services::irq`].
+    IrqService,
+    /// Service that lists the PCI functions and hands their resources to driver
+    /// processes, see [`crate::rt::services::pci`].
+    PciService,
     _Count,
 }
 
@@ -0,0 +1,349 @@
+//! Contention statistics of [`SimpleMutex`] and [`SimpleRwLock`]. With the feature
+//! `lock_stats`, each lock records in [`LOCK_STATS`] how often it was acquired, how often
+//! and how long callers waited for it, and where in the code its current owner acquired
+//! it. The table identifies locks by their address and names them by the code location of
+//! their first acquisition, hence no lock needs to be registered. A freed lock keeps its
+//! slot; a new lock at the same address shares it. Without the feature, the locks record
+//! nothing and the table stays empty.
+//!
+//! A caller that waits longer than [`WATCHDOG_TICKS`] for a lock dumps the table to the
+//! log once, which points to the owner of a lock in a deadlock. The roottask also dumps it
+//! when a portal call exceeds the same watchdog timeout and shows it in
+//! `/proc/roottask/locks`.
+//!
+//! [`SimpleMutex`]: crate::sync::mutex::SimpleMutex
+//! [`SimpleRwLock`]: crate::sync::rwlock::SimpleRwLock
+
+use crate::time::Instant;
+use alloc::vec::Vec;
+use core::panic::Location;
+use core::ptr::null_mut;
+use core::sync::atomic::{
+    AtomicBool,
+    AtomicPtr,
+    AtomicU64,
+    Ordering,
+};
+
+/// Maximum number of locks that [`LOCK_STATS`] tracks. Further locks stay untracked.
+pub const MAX_TRACKED_LOCKS: usize = 128;
+
+/// Ticks that a caller waits for a lock until it dumps the statistics of all locks.
+/// Roughly 5s on a 2 GHz CPU.
+pub const WATCHDOG_TICKS: u64 = 10_000_000_000;
+
+/// Whether the locks record statistics, i.e. if the feature `lock_stats` is enabled.
+pub const ENABLED: bool = cfg!(feature = "lock_stats");
+
+/// Statistics of all locks, see module description.
+pub static LOCK_STATS: LockStatsTable = LockStatsTable::new();
+
+/// Prevents that a dump waits for a lock that dumps again.
+static DUMPING: AtomicBool = AtomicBool::new(false);
+
+/// Fixed-size hash table of [`LockStats`], keyed by the address of the lock. All
+/// operations are lock-free, so that the locks themselves can use them.
+#[derive(Debug)]
+pub struct LockStatsTable {
+    slots: [LockStats; MAX_TRACKED_LOCKS],
+    /// Number of locks that found no free slot.
+    untracked: AtomicU64,
+}
+
+impl LockStatsTable {
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const FREE: LockStats = LockStats::new();
+        Self {
+            slots: [FREE; MAX_TRACKED_LOCKS],
+            untracked: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the statistics of the lock at `lock_addr`. Claims a free slot on the first
+    /// call, with `site` as name of the lock. Returns `None` if the table is full.
+    pub fn get(&self, lock_addr: u64, site: &'static Location<'static>) -> Option<&LockStats> {
+        assert_ne!(lock_addr, 0, "zero marks free slots");
+        let begin = (lock_addr as usize >> 3) % MAX_TRACKED_LOCKS;
+        for i in 0..MAX_TRACKED_LOCKS {
+            let slot = &self.slots[(begin + i) % MAX_TRACKED_LOCKS];
+            match slot
+                .lock_addr
+                .compare_exchange(0, lock_addr, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => {
+                    slot.site.store(location_ptr(site), Ordering::SeqCst);
+                    return Some(slot);
+                }
+                Err(addr) if addr == lock_addr => return Some(slot),
+                Err(_) => {}
+            }
+        }
+        self.untracked.fetch_add(1, Ordering::SeqCst);
+        None
+    }
+
+    /// Returns the statistics of all tracked locks, the ones with the longest total wait
+    /// first.
+    pub fn snapshots(&self) -> Vec<LockStatsSnapshot> {
+        let mut snapshots = self
+            .used_slots()
+            .map(LockStats::snapshot)
+            .collect::<Vec<_>>();
+        snapshots.sort_unstable_by_key(|snapshot| u64::MAX - snapshot.total_wait_ticks);
+        snapshots
+    }
+
+    /// Prints the statistics of all tracked locks to the log. Doesn't allocate memory, so
+    /// that it works while the heap is locked.
+    pub fn log_report(&self) {
+        if DUMPING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let mut tracked = 0;
+        for snapshot in self.used_slots().map(LockStats::snapshot) {
+            tracked += 1;
+            if snapshot.acquisitions > 0 {
+                log::info!("{}", snapshot);
+            }
+        }
+        log::info!(
+            "lock stats: {} locks tracked, {} untracked",
+            tracked,
+            self.untracked()
+        );
+        DUMPING.store(false, Ordering::SeqCst);
+    }
+
+    /// Number of locks that found no free slot.
+    pub fn untracked(&self) -> u64 {
+        self.untracked.load(Ordering::SeqCst)
+    }
+
+    fn used_slots(&self) -> impl Iterator<Item = &LockStats> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.lock_addr.load(Ordering::SeqCst) != 0)
+    }
+}
+
+impl Default for LockStatsTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Statistics of a single lock.
+#[derive(Debug)]
+pub struct LockStats {
+    /// Address of the lock or zero if the slot is free.
+    lock_addr: AtomicU64,
+    /// Code location of the first acquisition.
+    site: AtomicPtr<Location<'static>>,
+    /// Code location where the current owner acquired the lock, or null if it is free.
+    owner: AtomicPtr<Location<'static>>,
+    acquisitions: AtomicU64,
+    /// Acquisitions that had to wait.
+    contended: AtomicU64,
+    total_wait_ticks: AtomicU64,
+    max_wait_ticks: AtomicU64,
+}
+
+impl LockStats {
+    const fn new() -> Self {
+        Self {
+            lock_addr: AtomicU64::new(0),
+            site: AtomicPtr::new(null_mut()),
+            owner: AtomicPtr::new(null_mut()),
+            acquisitions: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+            total_wait_ticks: AtomicU64::new(0),
+            max_wait_ticks: AtomicU64::new(0),
+        }
+    }
+
+    /// Records an acquisition at `owner` after a wait of `wait_ticks`, if the caller had
+    /// to wait.
+    pub fn record_acquisition(&self, wait_ticks: Option<u64>, owner: &'static Location<'static>) {
+        self.owner.store(location_ptr(owner), Ordering::SeqCst);
+        self.acquisitions.fetch_add(1, Ordering::SeqCst);
+        if let Some(wait_ticks) = wait_ticks {
+            self.contended.fetch_add(1, Ordering::SeqCst);
+            self.total_wait_ticks
+                .fetch_add(wait_ticks, Ordering::SeqCst);
+            self.max_wait_ticks.fetch_max(wait_ticks, Ordering::SeqCst);
+        }
+    }
+
+    /// Records that the owner released the lock.
+    pub fn record_release(&self) {
+        self.owner.store(null_mut(), Ordering::SeqCst);
+    }
+
+    /// Returns a copy of the current values.
+    pub fn snapshot(&self) -> LockStatsSnapshot {
+        LockStatsSnapshot {
+            lock_addr: self.lock_addr.load(Ordering::SeqCst),
+            site: unsafe { self.site.load(Ordering::SeqCst).as_ref() },
+            owner: unsafe { self.owner.load(Ordering::SeqCst).as_ref() },
+            acquisitions: self.acquisitions.load(Ordering::SeqCst),
+            contended: self.contended.load(Ordering::SeqCst),
+            total_wait_ticks: self.total_wait_ticks.load(Ordering::SeqCst),
+            max_wait_ticks: self.max_wait_ticks.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Copy of the values of [`LockStats`] at a certain point in time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LockStatsSnapshot {
+    pub lock_addr: u64,
+    pub site: Option<&'static Location<'static>>,
+    pub owner: Option<&'static Location<'static>>,
+    pub acquisitions: u64,
+    pub contended: u64,
+    pub total_wait_ticks: u64,
+    pub max_wait_ticks: u64,
+}
+
+impl LockStatsSnapshot {
+    /// Average wait of the contended acquisitions.
+    pub fn avg_wait_ticks(&self) -> u64 {
+        self.total_wait_ticks
+            .checked_div(self.contended)
+            .unwrap_or(0)
+    }
+}
+
+/// A line of the report of [`LockStatsTable::log_report`].
+impl core::fmt::Display for LockStatsSnapshot {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "lock {:#x} ({}): {} acquisitions, {} contended, wait [ticks] total={} avg={} max={}, owner: {}",
+            self.lock_addr,
+            DisplayLocation(self.site),
+            self.acquisitions,
+            self.contended,
+            self.total_wait_ticks,
+            self.avg_wait_ticks(),
+            self.max_wait_ticks,
+            DisplayLocation(self.owner)
+        )
+    }
+}
+
+/// Measures a single acquisition of a lock. Used by the locks themselves.
+#[derive(Debug)]
+pub struct Acquisition {
+    stats: Option<&'static LockStats>,
+    site: &'static Location<'static>,
+    wait_begin: Option<u64>,
+    dumped: bool,
+}
+
+impl Acquisition {
+    /// Begins the acquisition of the lock at `lock_addr` by the caller of the lock.
+    #[track_caller]
+    pub fn begin(lock_addr: u64) -> Self {
+        let site = Location::caller();
+        Self {
+            stats: LOCK_STATS.get(lock_addr, site),
+            site,
+            wait_begin: None,
+            dumped: false,
+        }
+    }
+
+    /// Records that the lock is taken. Dumps the statistics once if the caller waits
+    /// longer than [`WATCHDOG_TICKS`].
+    pub fn wait(&mut self) {
+        let now = Instant::now().val();
+        let waited = now - *self.wait_begin.get_or_insert(now);
+        if waited > WATCHDOG_TICKS && !self.dumped {
+            self.dumped = true;
+            log::warn!(
+                "waiting for {} ticks for a lock at {}",
+                waited,
+                DisplayLocation(Some(self.site))
+            );
+            LOCK_STATS.log_report();
+        }
+    }
+
+    /// Records that the caller owns the lock now. Returns the statistics, so that the
+    /// guard can record the release.
+    pub fn finish(self) -> Option<&'static LockStats> {
+        let wait_ticks = self.wait_begin.map(|begin| Instant::now().val() - begin);
+        if let Some(stats) = self.stats {
+            stats.record_acquisition(wait_ticks, self.site);
+        }
+        self.stats
+    }
+}
+
+fn location_ptr(location: &'static Location<'static>) -> *mut Location<'static> {
+    location as *const _ as *mut _
+}
+
+/// Displays an optional code location as `file:line`.
+struct DisplayLocation(Option<&'static Location<'static>>);
+
+impl core::fmt::Display for DisplayLocation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            Some(location) => write!(f, "{}:{}", location.file(), location.line()),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_stats_table() {
+        let table = LockStatsTable::new();
+        let site = Location::caller();
+        let stats = table.get(0x1000, site).unwrap();
+        stats.record_acquisition(None, site);
+        assert_eq!(stats.snapshot().owner, Some(site));
+        stats.record_release();
+        stats.record_acquisition(Some(30), site);
+        stats.record_acquisition(Some(10), site);
+
+        let snapshot = table.get(0x1000, Location::caller()).unwrap().snapshot();
+        assert_eq!(snapshot.site, Some(site));
+        assert_eq!(snapshot.acquisitions, 3);
+        assert_eq!(snapshot.contended, 2);
+        assert_eq!(snapshot.total_wait_ticks, 40);
+        assert_eq!(snapshot.max_wait_ticks, 30);
+        assert_eq!(snapshot.avg_wait_ticks(), 20);
+
+        table
+            .get(0x2000, site)
+            .unwrap()
+            .record_acquisition(Some(100), site);
+        let snapshots = table.snapshots();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].lock_addr, 0x2000);
+        let line = format!("{}", snapshots[1]);
+        assert!(line.starts_with("lock 0x1000 ("));
+        assert!(line.contains("wait [ticks] total=40 avg=20 max=30, owner: libhrstd/"));
+    }
+
+    #[test]
+    fn test_lock_stats_table_full() {
+        let table = LockStatsTable::new();
+        let site = Location::caller();
+        // all addresses hash to the same slot
+        for i in 0..MAX_TRACKED_LOCKS as u64 {
+            assert!(table
+                .get((i + 1) * 8 * MAX_TRACKED_LOCKS as u64, site)
+                .is_some());
+        }
+        assert!(table.get(0x8, site).is_none());
+        assert_eq!(table.untracked(), 1);
+    }
+}
@@ -1,5 +1,6 @@
 //! Primitives for synchronization.
 pub mod fakelock;
+pub mod lock_stats;
 pub mod mutex;
 pub mod rwlock;
 pub mod static_global_ptr;
@@ -1,3 +1,8 @@
+#[cfg(feature = "lock_stats")]
+use crate::sync::lock_stats::{
+    Acquisition,
+    LockStats,
+};
 use core::cell::UnsafeCell;
 use core::ops::{
     Deref,
@@ -13,7 +18,8 @@ const UNLOCKED: bool = false;
 const LOCKED: bool = true;
 
 /// A simple mutex. The core library doesn't have this, therefore I have to build
-/// it by myself.
+/// it by myself. With the feature `lock_stats`, it records contention statistics, see
+/// [`crate::sync::lock_stats`].
 #[derive(Debug)]
 pub struct SimpleMutex<T> {
     data: UnsafeCell<T>,
@@ -39,7 +45,10 @@ impl<T> SimpleMutex<T> {
         self.data.into_inner()
     }
 
+    #[track_caller]
     pub fn lock(&self) -> SimpleMutexGuard<T> {
+        #[cfg(feature = "lock_stats")]
+        let mut acquisition = Acquisition::begin(self as *const _ as u64);
         loop {
             let lock_obtained =
                 self.lock
@@ -47,8 +56,14 @@ impl<T> SimpleMutex<T> {
             if lock_obtained.is_ok() {
                 break;
             }
+            #[cfg(feature = "lock_stats")]
+            acquisition.wait();
+        }
+        SimpleMutexGuard {
+            lock: &self,
+            #[cfg(feature = "lock_stats")]
+            stats: acquisition.finish(),
         }
-        SimpleMutexGuard { lock: &self }
     }
 }
 
@@ -61,6 +76,8 @@ impl<T: Default> Default for SimpleMutex<T> {
 #[derive(Debug)]
 pub struct SimpleMutexGuard<'a, T> {
     lock: &'a SimpleMutex<T>,
+    #[cfg(feature = "lock_stats")]
+    stats: Option<&'static LockStats>,
 }
 
 impl<'a, T> SimpleMutexGuard<'a, T> {
@@ -95,6 +112,10 @@ impl<T> DerefMut for SimpleMutexGuard<'_, T> {
 impl<T> Drop for SimpleMutexGuard<'_, T> {
     #[inline]
     fn drop(&mut self) {
+        #[cfg(feature = "lock_stats")]
+        if let Some(stats) = self.stats {
+            stats.record_release();
+        }
         self.lock.lock.store(UNLOCKED, Ordering::SeqCst);
     }
 }
@@ -1,4 +1,9 @@
 use super::mutex::SimpleMutex;
+#[cfg(feature = "lock_stats")]
+use crate::sync::lock_stats::{
+    Acquisition,
+    LockStats,
+};
 use core::cell::UnsafeCell;
 use core::ops::{
     Deref,
@@ -9,7 +14,9 @@ use core::sync::atomic::{
     Ordering,
 };
 
-/// A simple read write lock. Allows either n readers or one writer.
+/// A simple read write lock. Allows either n readers or one writer. With the feature
+/// `lock_stats`, it records contention statistics, see [`crate::sync::lock_stats`]. The
+/// owner in the statistics is the last reader or writer.
 #[derive(Debug)]
 pub struct SimpleRwLock<T> {
     data: UnsafeCell<T>,
@@ -38,6 +45,7 @@ impl<T> SimpleRwLock<T> {
         self.data.into_inner()
     }*/
 
+    #[track_caller]
     pub fn try_lock_read(&self) -> Result<SimpleRwLockReadGuard<T>, ()> {
         let lock = self.critical_section.lock();
         lock.execute_while_locked(&|| {
@@ -49,6 +57,7 @@ impl<T> SimpleRwLock<T> {
         })
     }
 
+    #[track_caller]
     pub fn try_lock_write(&self) -> Result<SimpleRwLockWriteGuard<T>, ()> {
         let lock = self.critical_section.lock();
         lock.execute_while_locked(&|| {
@@ -60,19 +69,39 @@ impl<T> SimpleRwLock<T> {
         })
     }
 
+    #[track_caller]
     pub fn lock_read(&self) -> SimpleRwLockReadGuard<T> {
+        #[cfg(feature = "lock_stats")]
+        let mut acquisition = Acquisition::begin(self as *const _ as u64);
         loop {
-            if let Ok(l) = self.try_lock_read() {
+            #[allow(unused_mut)]
+            if let Ok(mut l) = self.try_lock_read() {
+                #[cfg(feature = "lock_stats")]
+                {
+                    l.stats = acquisition.finish();
+                }
                 return l;
             }
+            #[cfg(feature = "lock_stats")]
+            acquisition.wait();
         }
     }
 
+    #[track_caller]
     pub fn lock_write(&self) -> SimpleRwLockWriteGuard<T> {
+        #[cfg(feature = "lock_stats")]
+        let mut acquisition = Acquisition::begin(self as *const _ as u64);
         loop {
-            if let Ok(l) = self.try_lock_write() {
+            #[allow(unused_mut)]
+            if let Ok(mut l) = self.try_lock_write() {
+                #[cfg(feature = "lock_stats")]
+                {
+                    l.stats = acquisition.finish();
+                }
                 return l;
             }
+            #[cfg(feature = "lock_stats")]
+            acquisition.wait();
         }
     }
 
@@ -90,12 +119,18 @@ impl<T> SimpleRwLock<T> {
 #[derive(Debug)]
 pub struct SimpleRwLockWriteGuard<'a, T> {
     lock: &'a SimpleRwLock<T>,
+    #[cfg(feature = "lock_stats")]
+    stats: Option<&'static LockStats>,
 }
 
 impl<'a, T> SimpleRwLockWriteGuard<'a, T> {
     fn new(lock: &'a SimpleRwLock<T>) -> Self {
         lock.write_count.fetch_add(1, Ordering::SeqCst);
-        Self { lock }
+        Self {
+            lock,
+            #[cfg(feature = "lock_stats")]
+            stats: None,
+        }
     }
 }
 
@@ -116,6 +151,10 @@ impl<T> DerefMut for SimpleRwLockWriteGuard<'_, T> {
 impl<T> Drop for SimpleRwLockWriteGuard<'_, T> {
     #[inline]
     fn drop(&mut self) {
+        #[cfg(feature = "lock_stats")]
+        if let Some(stats) = self.stats {
+            stats.record_release();
+        }
         self.lock.write_count.fetch_sub(1, Ordering::SeqCst);
     }
 }
@@ -123,12 +162,18 @@ impl<T> Drop for SimpleRwLockWriteGuard<'_, T> {
 #[derive(Debug)]
 pub struct SimpleRwLockReadGuard<'a, T> {
     lock: &'a SimpleRwLock<T>,
+    #[cfg(feature = "lock_stats")]
+    stats: Option<&'static LockStats>,
 }
 
 impl<'a, T> SimpleRwLockReadGuard<'a, T> {
     fn new(lock: &'a SimpleRwLock<T>) -> Self {
         lock.read_count.fetch_add(1, Ordering::SeqCst);
-        Self { lock }
+        Self {
+            lock,
+            #[cfg(feature = "lock_stats")]
+            stats: None,
+        }
     }
 }
 
@@ -143,6 +188,10 @@ impl<T> Deref for SimpleRwLockReadGuard<'_, T> {
 impl<T> Drop for SimpleRwLockReadGuard<'_, T> {
     #[inline]
     fn drop(&mut self) {
+        #[cfg(feature = "lock_stats")]
+        if let Some(stats) = self.stats {
+            stats.record_release();
+        }
         self.lock.read_count.fetch_sub(1, Ordering::SeqCst);
     }
 }
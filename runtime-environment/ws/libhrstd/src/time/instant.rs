@@ -2,8 +2,8 @@ use crate::time::Duration;
 use core::ops::Sub;
 
 /// Wrapper around `rdtscp` to measure performance
-/// in clock ticks. Currently, there is no way to convert
-/// this to wall clock time.
+/// in clock ticks. `tsc_freq_khz()` converts them to wall-clock
+/// durations; use [`super::SystemTime`] for wall-clock time.
 #[derive(Debug)]
 pub struct Instant {
     begin_time: u64,
@@ -22,6 +22,19 @@ impl Instant {
     }
 }
 
+/// Returns the frequency of the TSC in kHz, which the roottask calibrated during boot.
+/// Asks the system time service.
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub fn tsc_freq_khz() -> u64 {
+    use crate::rt::services::system_time::{
+        system_time_service,
+        SystemTimeServiceRequest,
+    };
+    system_time_service(SystemTimeServiceRequest::Get)
+        .unwrap()
+        .tsc_freq_khz
+}
+
 impl Sub for Instant {
     type Output = Duration;
 
@@ -1,5 +1,14 @@
 mod duration;
 mod instant;
+mod system_time;
 
 pub use duration::Duration;
+#[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+pub use instant::tsc_freq_khz;
 pub use instant::Instant;
+pub use system_time::{
+    civil_from_days,
+    days_from_civil,
+    SystemTime,
+    UNIX_EPOCH,
+};
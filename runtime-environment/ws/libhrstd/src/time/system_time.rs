@@ -0,0 +1,128 @@
+use core::fmt::{
+    Display,
+    Formatter,
+};
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Point in time of the wall clock (UTC) with nanosecond resolution. Unlike [`super::Instant`],
+/// it is independent of the TSC and comparable across processes and reboots. Processes
+/// get it from the system time service of the roottask, see [`SystemTime::now`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SystemTime {
+    unix_ns: u64,
+}
+
+/// 1970-01-01T00:00:00Z
+pub const UNIX_EPOCH: SystemTime = SystemTime::from_unix_ns(0);
+
+impl SystemTime {
+    /// Returns the current wall-clock time.
+    #[cfg(any(feature = "foreign_rust_rt", feature = "native_rust_rt"))]
+    pub fn now() -> Self {
+        use crate::rt::services::system_time::{
+            system_time_service,
+            SystemTimeServiceRequest,
+        };
+        let time = system_time_service(SystemTimeServiceRequest::Get).unwrap();
+        Self::from_unix_ns(time.realtime_ns)
+    }
+
+    /// Creates a point in time from nanoseconds since the Unix epoch.
+    pub const fn from_unix_ns(unix_ns: u64) -> Self {
+        Self { unix_ns }
+    }
+
+    /// Returns the nanoseconds since the Unix epoch.
+    pub const fn as_unix_ns(&self) -> u64 {
+        self.unix_ns
+    }
+
+    /// Returns the full seconds since the Unix epoch.
+    pub const fn as_unix_secs(&self) -> u64 {
+        self.unix_ns / NS_PER_SEC
+    }
+
+    /// Returns the nanoseconds that passed from `earlier` until this point in time, or
+    /// `None` if `earlier` is later, e.g. because the wall clock was adjusted.
+    pub const fn ns_since(&self, earlier: Self) -> Option<u64> {
+        self.unix_ns.checked_sub(earlier.unix_ns)
+    }
+}
+
+impl Display for SystemTime {
+    /// Formats the time in RFC 3339, e.g. `2024-02-29T13:37:42.000000000Z`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let secs = self.as_unix_secs();
+        let (year, month, day) = civil_from_days(secs / SECONDS_PER_DAY);
+        let secs_of_day = secs % SECONDS_PER_DAY;
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60,
+            self.unix_ns % NS_PER_SEC
+        )
+    }
+}
+
+/// Returns the days since the Unix epoch for a date of the proleptic Gregorian calendar
+/// since 1970. Algorithm by Howard Hinnant: <http://howardhinnant.github.io/date_algorithms.html>
+pub fn days_from_civil(year: u64, month: u8, day: u8) -> u64 {
+    // years start in March; makes the leap day the last day of a year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month = month as u64;
+    let day_of_year =
+        (153 * if month > 2 { month - 3 } else { month + 9 } + 2) / 5 + day as u64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Inverse of [`days_from_civil`]. Returns year, month, and day.
+pub fn civil_from_days(days: u64) -> (u64, u8, u8) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_system_time() {
+        assert_eq!(UNIX_EPOCH.to_string(), "1970-01-01T00:00:00.000000000Z");
+        let leap_day = SystemTime::from_unix_ns(1_709_213_862 * NS_PER_SEC + 5_000);
+        assert_eq!(leap_day.to_string(), "2024-02-29T13:37:42.000005000Z");
+        assert_eq!(leap_day.as_unix_secs(), 1_709_213_862);
+        assert_eq!(leap_day.ns_since(UNIX_EPOCH), Some(leap_day.as_unix_ns()));
+        assert_eq!(UNIX_EPOCH.ns_since(leap_day), None);
+        assert!(UNIX_EPOCH < leap_day);
+    }
+
+    #[test]
+    fn test_civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        for days in [0, 11016, 47482] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+}
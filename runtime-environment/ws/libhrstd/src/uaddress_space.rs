@@ -21,21 +21,55 @@ pub const USER_STACK_VERY_TOP: u64 = USER_UTCB_ADDR;
 /// + 8: stack offset for correct alignment of first argument.
 pub const USER_STACK_TOP: u64 = USER_STACK_VERY_TOP - 64 + 8;
 
-/// The page-aligned bottom address of the stack.
+/// The page-aligned bottom address of the virtual range that is reserved for the stack.
 pub const USER_STACK_BOTTOM_ADDR: u64 = USER_UTCB_ADDR - USER_STACK_SIZE as u64;
 
 /// The page number of [`USER_STACK_BOTTOM_ADDR`].
 pub const USER_STACK_BOTTOM_PAGE_NUM: u64 = USER_STACK_BOTTOM_ADDR / PAGE_SIZE as u64;
 
-/// 2 MiB stack size for all Hedron user apps. A multiple of [`PAGE_SIZE`].
-/// Linux default is 10MB, Windows default is 1MB. That big because I need to write large
-/// amounts of data in my FS micro benchmark.
-pub const USER_STACK_SIZE: usize = 512 * PAGE_SIZE;
+/// The lowest page of the reserved stack range stays unmapped. A page fault at this page is
+/// a stack overflow.
+pub const USER_STACK_GUARD_PAGE_ADDR: u64 = USER_STACK_BOTTOM_ADDR;
+
+/// 8 MiB of virtual memory are reserved for the stack of all Hedron user apps, like the
+/// default stack limit of Linux. A multiple of [`PAGE_SIZE`]. Only the top
+/// [`USER_STACK_INITIAL_SIZE`] bytes are mapped when the process starts; the stack grows on
+/// demand until it reaches the guard page. That big because I need to write large amounts
+/// of data in my FS micro benchmark.
+pub const USER_STACK_SIZE: usize = 2048 * PAGE_SIZE;
+
+/// Size of the top of the stack that is mapped when the process starts. Must hold the
+/// initial stack layout of Linux apps. A multiple of [`PAGE_SIZE`].
+pub const USER_STACK_INITIAL_SIZE: usize = 32 * PAGE_SIZE;
+
+/// The stack grows by at least this many bytes at once, to save page faults. A multiple of
+/// [`PAGE_SIZE`].
+pub const USER_STACK_GROW_MIN_SIZE: usize = 16 * PAGE_SIZE;
+
+/// A page fault below the mapped stack grows the stack only if the fault address is at most
+/// this many bytes below the stack pointer. Everything further below is a wild access
+/// rather than a push or a large stack frame. Same value as older Linux kernels.
+pub const USER_STACK_GROW_RSP_SLACK: u64 = 65536 + 32 * 8;
 
 /// Some libc implementation, such as musl, need to read the program headers of their
 /// ELF file. This is the user address where the ELF file program headers shall be
 /// mapped.
 pub const USER_ELF_ADDR: u64 = USER_STACK_BOTTOM_ADDR - PAGE_SIZE as u64;
 
+/// Size of the read-only area at [`USER_ARGS_ADDR`]. A multiple of [`PAGE_SIZE`].
+pub const USER_ARGS_SIZE: usize = 4 * PAGE_SIZE;
+
+/// Native Hedron apps find their arguments and environment variables at this address.
+/// See [`crate::process::args_block`].
+pub const USER_ARGS_ADDR: u64 = USER_ELF_ADDR - USER_ARGS_SIZE as u64;
+
+/// Virtual page-aligned address of the UTCB of the local EC that handles the calls of a
+/// service that the app hosts. See [`crate::rt::services::name::NameHostRequest`].
+pub const USER_SERVICE_UTCB_ADDR: u64 = USER_ARGS_ADDR - PAGE_SIZE as u64;
+
+/// Everything from this address up to [`USER_MAX_ADDR`] has a fixed purpose and is the same
+/// in all processes. The ELF segments, the heap, and mappings must stay below.
+pub const USER_FIXED_AREA_BEGIN: u64 = USER_SERVICE_UTCB_ADDR;
+
 /// Begin of the heap. No text or data segment is allowed to clash with this.
 pub const USER_HEAP_BEGIN: usize = 0x40000000;
@@ -0,0 +1,435 @@
+//! Backtraces for panics of the roottask and of native apps. All binaries are compiled with
+//! frame pointers (see `ws/.cargo/config.toml`), hence the return addresses of the active
+//! functions can be found by following the chain of saved RBP values on the stack, see
+//! [`walk_frame_pointers`].
+//!
+//! Each binary reserves a section for a table of the addresses and names of its functions
+//! ([`EmbeddedSymbols`]). The build fills it after linking with `cargo xtask embed-symbols`,
+//! which takes the symbols from the ELF symbol table and encodes them with
+//! [`encode_symbol_table`]. At runtime, [`SymbolTable`] resolves the return addresses. A
+//! binary without a filled table prints plain addresses.
+//!
+//! Format of the table, all numbers little endian:
+//! - header: magic [`SYMBOL_TABLE_MAGIC`], number of entries (u32), size of the name
+//!   pool (u32), reserved (u32)
+//! - entries sorted by address: address (u64), size (u32), offset of the name in the
+//!   pool (u32); the names are stored in the same order, hence each name ends where the
+//!   next one begins
+//! - the name pool: UTF-8 names without terminators
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// First bytes of an encoded symbol table.
+pub const SYMBOL_TABLE_MAGIC: [u8; 4] = *b"HSYM";
+
+/// Name of the section that holds the [`EmbeddedSymbols`] of a binary.
+pub const SYMBOL_SECTION_NAME: &str = ".hedron_symbols";
+
+/// Maximum number of frames that a backtrace shows. Protects against loops in corrupted
+/// frame pointer chains.
+pub const MAX_BACKTRACE_FRAMES: usize = 64;
+
+const HEADER_SIZE: usize = 16;
+const ENTRY_SIZE: usize = 16;
+
+/// A function of a binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub addr: u64,
+    pub size: u32,
+    pub name: String,
+}
+
+/// Encodes the symbols in the format of the module description. Sorts them by address;
+/// of multiple symbols at the same address, only the first one stays.
+pub fn encode_symbol_table(mut symbols: Vec<Symbol>) -> Vec<u8> {
+    symbols.sort_by_key(|symbol| symbol.addr);
+    symbols.dedup_by_key(|symbol| symbol.addr);
+    let pool_size = symbols
+        .iter()
+        .map(|symbol| symbol.name.len())
+        .sum::<usize>();
+
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + symbols.len() * ENTRY_SIZE + pool_size);
+    bytes.extend_from_slice(&SYMBOL_TABLE_MAGIC);
+    bytes.extend_from_slice(&(symbols.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(pool_size as u32).to_le_bytes());
+    bytes.extend_from_slice(&0_u32.to_le_bytes());
+    let mut name_offset = 0;
+    for symbol in &symbols {
+        bytes.extend_from_slice(&symbol.addr.to_le_bytes());
+        bytes.extend_from_slice(&symbol.size.to_le_bytes());
+        bytes.extend_from_slice(&(name_offset as u32).to_le_bytes());
+        name_offset += symbol.name.len();
+    }
+    for symbol in &symbols {
+        bytes.extend_from_slice(symbol.name.as_bytes());
+    }
+    bytes
+}
+
+/// Read-only view on an encoded symbol table. See the module description.
+#[derive(Debug, Copy, Clone)]
+pub struct SymbolTable<'a> {
+    entries: &'a [u8],
+    pool: &'a [u8],
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Returns `None` if the bytes contain no valid table, e.g. because the table of a
+    /// binary was never filled.
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != SYMBOL_TABLE_MAGIC {
+            return None;
+        }
+        let count = read_u32(bytes, 4) as usize;
+        let pool_size = read_u32(bytes, 8) as usize;
+        let entries_end = HEADER_SIZE.checked_add(count.checked_mul(ENTRY_SIZE)?)?;
+        let pool_end = entries_end.checked_add(pool_size)?;
+        if pool_end > bytes.len() {
+            return None;
+        }
+        Some(Self {
+            entries: &bytes[HEADER_SIZE..entries_end],
+            pool: &bytes[entries_end..pool_end],
+        })
+    }
+
+    /// Number of symbols.
+    pub fn len(&self) -> usize {
+        self.entries.len() / ENTRY_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the name of the function that contains `addr` and the offset of `addr`
+    /// within the function.
+    pub fn lookup(&self, addr: u64) -> Option<(&'a str, u64)> {
+        // index of the first symbol above the address
+        let mut low = 0;
+        let mut high = self.len();
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.addr(mid) <= addr {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let index = low.checked_sub(1)?;
+        let offset = addr - self.addr(index);
+        let size = read_u32(self.entries, index * ENTRY_SIZE + 8) as u64;
+        if offset >= size {
+            return None;
+        }
+        Some((self.name(index)?, offset))
+    }
+
+    fn addr(&self, index: usize) -> u64 {
+        let offset = index * ENTRY_SIZE;
+        u64::from_le_bytes(self.entries[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn name(&self, index: usize) -> Option<&'a str> {
+        let begin = read_u32(self.entries, index * ENTRY_SIZE + 12) as usize;
+        let end = if index + 1 < self.len() {
+            read_u32(self.entries, (index + 1) * ENTRY_SIZE + 12) as usize
+        } else {
+            self.pool.len()
+        };
+        core::str::from_utf8(self.pool.get(begin..end)?).ok()
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Space for the symbol table of a binary in the section [`SYMBOL_SECTION_NAME`]. The
+/// section must be part of a loaded segment; the linker scripts of the binaries take care
+/// of that. Each binary places one instance, e.g.:
+///
+/// ```ignore
+/// #[used]
+/// #[link_section = ".hedron_symbols"]
+/// static SYMBOLS: EmbeddedSymbols<{ 256 * 1024 }> = EmbeddedSymbols::new();
+/// ```
+#[derive(Debug)]
+#[repr(C, align(8))]
+pub struct EmbeddedSymbols<const N: usize>([u8; N]);
+
+impl<const N: usize> EmbeddedSymbols<N> {
+    pub const fn new() -> Self {
+        Self([0; N])
+    }
+
+    /// Returns the table that the build embedded, if there is one.
+    pub fn table(&'static self) -> Option<SymbolTable<'static>> {
+        // The compiler only knows the zeroes of the initializer; the build changes the
+        // bytes afterwards. Reading the address through a volatile load hides the origin
+        // of the memory, so that the compiler can't assume its content.
+        let ptr = self.0.as_ptr();
+        let ptr = unsafe { core::ptr::read_volatile(&ptr) };
+        SymbolTable::parse(unsafe { core::slice::from_raw_parts(ptr, N) })
+    }
+}
+
+impl<const N: usize> Default for EmbeddedSymbols<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the current frame pointer, i.e. the begin of the frame of the function that
+/// calls this.
+#[inline(always)]
+pub fn current_frame_pointer() -> u64 {
+    let rbp: u64;
+    unsafe { core::arch::asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack)) };
+    rbp
+}
+
+/// Follows the chain of frame pointers that starts at `rbp` and calls `f` with the return
+/// address of each frame, beginning with the innermost one. Each frame begins with the
+/// saved RBP of the caller followed by the return address.
+///
+/// Stops at a null frame pointer, after [`MAX_BACKTRACE_FRAMES`] frames, if the chain
+/// doesn't go up the stack, or if `is_readable` rejects the 16 bytes of a frame. Code that
+/// isn't compiled with frame pointers breaks the chain.
+///
+/// # Safety
+/// All frames that `is_readable` accepts must be readable memory.
+pub unsafe fn walk_frame_pointers(
+    mut rbp: u64,
+    is_readable: impl Fn(u64) -> bool,
+    mut f: impl FnMut(u64),
+) {
+    for _ in 0..MAX_BACKTRACE_FRAMES {
+        if rbp == 0 || rbp % 8 != 0 || !is_readable(rbp) || !is_readable(rbp + 8) {
+            return;
+        }
+        let frame = rbp as *const u64;
+        let caller_rbp = frame.read();
+        let return_addr = frame.add(1).read();
+        if return_addr == 0 {
+            return;
+        }
+        f(return_addr);
+        // the stack grows downwards
+        if caller_rbp <= rbp {
+            return;
+        }
+        rbp = caller_rbp;
+    }
+}
+
+/// Writes the backtrace that starts at `rbp` to `out`, one line per frame. See
+/// [`walk_frame_pointers`].
+///
+/// # Safety
+/// All frames that `is_readable` accepts must be readable memory.
+pub unsafe fn write_backtrace(
+    out: &mut impl Write,
+    rbp: u64,
+    is_readable: impl Fn(u64) -> bool,
+    symbols: Option<SymbolTable>,
+) -> core::fmt::Result {
+    let mut res = writeln!(out, "backtrace:");
+    let mut index = 0;
+    walk_frame_pointers(rbp, is_readable, |return_addr| {
+        // the call instruction is right before the return address
+        let call_addr = return_addr - 1;
+        let symbol = symbols.and_then(|symbols| symbols.lookup(call_addr));
+        res = res.and_then(|_| match symbol {
+            Some((name, offset)) => writeln!(
+                out,
+                "  #{:<2} {:#018x} {}+{:#x}",
+                index,
+                return_addr,
+                name,
+                offset + 1
+            ),
+            None => writeln!(out, "  #{:<2} {:#018x} <unknown>", index, return_addr),
+        });
+        index += 1;
+    });
+    res
+}
+
+/// Demangles the legacy symbol mangling of Rust, e.g. `_ZN4core3fmt5write17h0123E` becomes
+/// `core::fmt::write`. The hash at the end gets removed. Other names stay as they are.
+pub fn demangle(name: &str) -> String {
+    let mut rest = match name
+        .strip_prefix("_ZN")
+        .and_then(|rest| rest.strip_suffix('E'))
+    {
+        Some(rest) => rest,
+        None => return String::from(name),
+    };
+    let mut parts = Vec::new();
+    while !rest.is_empty() {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let len = match rest[..digits].parse::<usize>() {
+            Ok(len) if digits + len <= rest.len() => len,
+            _ => return String::from(name),
+        };
+        parts.push(&rest[digits..digits + len]);
+        rest = &rest[digits + len..];
+    }
+    if parts
+        .last()
+        .map_or(false, |last| last.len() == 17 && last.starts_with('h'))
+    {
+        parts.pop();
+    }
+
+    let mut demangled = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            demangled.push_str("::");
+        }
+        demangle_part(part, &mut demangled);
+    }
+    demangled
+}
+
+/// Replaces the escapes of special characters within a part of a path.
+fn demangle_part(mut part: &str, out: &mut String) {
+    // a leading underscore protects parts that begin with an escape
+    if part.starts_with("_$") {
+        part = &part[1..];
+    }
+    while !part.is_empty() {
+        if let Some(rest) = part.strip_prefix("..") {
+            out.push_str("::");
+            part = rest;
+            continue;
+        }
+        if part.starts_with('$') {
+            if let Some(end) = part[1..].find('$') {
+                let escape = &part[1..end + 1];
+                let replacement = match escape {
+                    "SP" => Some('@'),
+                    "BP" => Some('*'),
+                    "RF" => Some('&'),
+                    "LT" => Some('<'),
+                    "GT" => Some('>'),
+                    "LP" => Some('('),
+                    "RP" => Some(')'),
+                    "C" => Some(','),
+                    _ => escape
+                        .strip_prefix('u')
+                        .and_then(|code| u32::from_str_radix(code, 16).ok())
+                        .and_then(char::from_u32),
+                };
+                if let Some(replacement) = replacement {
+                    out.push(replacement);
+                    part = &part[end + 2..];
+                    continue;
+                }
+            }
+        }
+        let c = part.chars().next().unwrap();
+        out.push(c);
+        part = &part[c.len_utf8()..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn symbol(addr: u64, size: u32, name: &str) -> Symbol {
+        Symbol {
+            addr,
+            size,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_symbol_table() {
+        let bytes = encode_symbol_table(vec![
+            symbol(0x402000, 0x80, "second"),
+            symbol(0x401000, 0x100, "first"),
+            symbol(0x401000, 0x10, "alias"),
+            symbol(0x403000, 0x20, "third"),
+        ]);
+        let table = SymbolTable::parse(&bytes).unwrap();
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.lookup(0x401000), Some(("first", 0)));
+        assert_eq!(table.lookup(0x4010ff), Some(("first", 0xff)));
+        assert_eq!(table.lookup(0x401100), None, "gap between functions");
+        assert_eq!(table.lookup(0x402010), Some(("second", 0x10)));
+        assert_eq!(table.lookup(0x40301f), Some(("third", 0x1f)));
+        assert_eq!(table.lookup(0x403020), None);
+        assert_eq!(table.lookup(0x400fff), None);
+
+        // an unfilled section
+        assert!(SymbolTable::parse(&[0; 64]).is_none());
+        // truncated
+        assert!(SymbolTable::parse(&bytes[..bytes.len() - 1]).is_none());
+        let empty = encode_symbol_table(Vec::new());
+        assert!(SymbolTable::parse(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_walk_frame_pointers() {
+        // three frames on a fake stack; the outermost one ends the chain with a null RBP
+        let mut stack = [0_u64; 16];
+        let base = stack.as_ptr() as u64;
+        let frame = |index: usize| base + index as u64 * 8;
+        stack[2] = frame(6);
+        stack[3] = 0x401234;
+        stack[6] = frame(10);
+        stack[7] = 0x402345;
+        stack[10] = 0;
+        stack[11] = 0x403456;
+
+        let readable = |addr: u64| (base..base + 16 * 8).contains(&addr);
+        let mut addrs = Vec::new();
+        unsafe { walk_frame_pointers(frame(2), readable, |addr| addrs.push(addr)) };
+        assert_eq!(addrs, [0x401234, 0x402345, 0x403456]);
+
+        // a chain that points down the stack stops
+        stack[6] = frame(2);
+        addrs.clear();
+        unsafe { walk_frame_pointers(frame(2), readable, |addr| addrs.push(addr)) };
+        assert_eq!(addrs, [0x401234, 0x402345]);
+
+        // unreadable frames stop
+        addrs.clear();
+        unsafe { walk_frame_pointers(frame(2), |_| false, |addr| addrs.push(addr)) };
+        assert!(addrs.is_empty());
+
+        let bytes = encode_symbol_table(vec![symbol(0x401200, 0x100, "main")]);
+        let mut out = String::new();
+        unsafe {
+            write_backtrace(&mut out, frame(2), readable, SymbolTable::parse(&bytes)).unwrap()
+        };
+        assert_eq!(
+            out,
+            "backtrace:\n  #0  0x0000000000401234 main+0x34\n  #1  0x0000000000402345 <unknown>\n"
+        );
+    }
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(
+            demangle("_ZN4core3fmt5write17h6b1e3a4f0a2d7c9bE"),
+            "core::fmt::write"
+        );
+        assert_eq!(
+            demangle("_ZN72_$LT$libroottask..mem..MappedMemory$u20$as$u20$core..ops..drop..Drop$GT$4drop17h0123456789abcdefE"),
+            "<libroottask::mem::MappedMemory as core::ops::drop::Drop>::drop"
+        );
+        assert_eq!(demangle("start"), "start");
+        assert_eq!(demangle("_ZN3foo"), "_ZN3foo");
+        assert_eq!(demangle("_ZN99fooE"), "_ZN99fooE");
+    }
+}
@@ -2,6 +2,7 @@ use crate::time::{
     Duration,
     Instant,
 };
+use alloc::vec::Vec;
 use core::fmt::{
     Debug,
     Formatter,
@@ -9,6 +10,81 @@ use core::fmt::{
 
 pub type DurationPerIteration = Duration;
 
+/// Statistics over the durations of the single iterations of a benchmark. All values
+/// except `iterations` are in clock ticks or, after [`Self::to_ns`], in nanoseconds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BenchStats {
+    pub iterations: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+    pub median: u64,
+    pub p90: u64,
+    pub p99: u64,
+    /// Standard deviation of the population.
+    pub stddev: u64,
+}
+
+impl BenchStats {
+    /// Computes the statistics of the samples and sorts them on the way. Returns `None`
+    /// if there are no samples.
+    pub fn from_samples(samples: &mut [u64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let count = samples.len() as u128;
+        let sum = samples.iter().map(|sample| *sample as u128).sum::<u128>();
+        let mean = (sum / count) as u64;
+        let variance = samples
+            .iter()
+            .map(|sample| {
+                let diff = *sample as f64 - mean as f64;
+                diff * diff
+            })
+            .sum::<f64>()
+            / count as f64;
+        Some(Self {
+            iterations: samples.len() as u64,
+            min: samples[0],
+            max: samples[samples.len() - 1],
+            mean,
+            median: percentile(samples, 50),
+            p90: percentile(samples, 90),
+            p99: percentile(samples, 99),
+            stddev: libm::sqrt(variance) as u64,
+        })
+    }
+
+    /// Converts the statistics from clock ticks to nanoseconds, given the frequency of the
+    /// TSC, e.g. from `crate::time::tsc_freq_khz()`.
+    pub fn to_ns(&self, tsc_freq_khz: u64) -> Self {
+        let to_ns = |ticks| ticks_to_ns(ticks, tsc_freq_khz);
+        Self {
+            iterations: self.iterations,
+            min: to_ns(self.min),
+            max: to_ns(self.max),
+            mean: to_ns(self.mean),
+            median: to_ns(self.median),
+            p90: to_ns(self.p90),
+            p99: to_ns(self.p99),
+            stddev: to_ns(self.stddev),
+        }
+    }
+}
+
+/// Returns the value below which `percent` percent of the sorted samples are (nearest
+/// rank). The samples must not be empty.
+pub fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
+
+/// Converts clock ticks to nanoseconds, given the frequency of the TSC.
+pub const fn ticks_to_ns(ticks: u64, tsc_freq_khz: u64) -> u64 {
+    (ticks as u128 * 1_000_000 / tsc_freq_khz as u128) as u64
+}
+
 /// Helper script that benchmarks a workload [`BenchHelper::BENCH_ITERATIONS`] times.
 /// Beforehand, it warms up the caches etc. with [`BenchHelper::WARMUP_ITERATIONS`] iterations.
 pub struct BenchHelper<
@@ -80,6 +156,41 @@ impl<
         counter / BENCH_ITERATIONS
     }
 
+    /// Like [`Self::bench`] but measures each iteration on its own and returns statistics
+    /// over all iterations instead of the average. The measurement of each iteration
+    /// includes the overhead of reading the TSC.
+    pub fn bench_stats(&mut self) -> BenchStats {
+        let mut samples = Vec::with_capacity(BENCH_ITERATIONS as usize);
+        let mut single_bench_round = |samples: &mut Vec<u64>, iteration: u64| {
+            if let Some(fnc) = self.before_each_fn.as_mut() {
+                fnc();
+            }
+            let begin = Instant::now();
+            (self.bench_fn)(iteration);
+            samples.push(Instant::now() - begin);
+            if let Some(fnc) = self.after_each_fn.as_mut() {
+                fnc();
+            }
+        };
+
+        (0..WARMUP_ITERATIONS).for_each(|i| single_bench_round(&mut samples, i));
+        samples.clear();
+        (0..BENCH_ITERATIONS).for_each(|i| single_bench_round(&mut samples, i));
+        BenchStats::from_samples(&mut samples).expect("at least one bench iteration")
+    }
+
+    /// Like [`Self::bench_direct`] but returns statistics over all iterations. See
+    /// [`Self::bench_stats`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let stats = BenchHelper::<_, 100, 1000>::bench_direct_stats(|_| echo_pt.call().unwrap());
+    /// log::info!("median: {} ticks, p99: {} ticks", stats.median, stats.p99);
+    /// ```
+    pub fn bench_direct_stats(fnc: BenchFncT) -> BenchStats {
+        Self::new(fnc).bench_stats()
+    }
+
     /// Direct benchmark the function. For a more complex use with
     /// "before_each" and "after_each" hooks, please check [`Self::bench`].
     ///
@@ -133,6 +244,7 @@ impl<
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::time::Instant;
     use crate::util::BenchHelper;
     use std::println;
@@ -166,6 +278,40 @@ mod tests {
         let _ = BenchHelper::<_, 2, 3>::new(|i| println!("Bench Iteration #{}", i)).bench();
     }
 
+    #[test]
+    fn test_bench_stats() {
+        let mut counter = 0;
+        let stats = BenchHelper::<_, 2, 100>::bench_direct_stats(|i| counter += i);
+        assert_eq!(stats.iterations, 100);
+        assert!(stats.min <= stats.median && stats.median <= stats.p99);
+        assert!(stats.p99 <= stats.max);
+    }
+
+    #[test]
+    fn test_bench_stats_from_samples() {
+        assert_eq!(BenchStats::from_samples(&mut []), None);
+        let mut samples = (1..=100).rev().collect::<Vec<u64>>();
+        let stats = BenchStats::from_samples(&mut samples).unwrap();
+        assert_eq!(samples[0], 1);
+        assert_eq!(
+            stats,
+            BenchStats {
+                iterations: 100,
+                min: 1,
+                max: 100,
+                mean: 50,
+                median: 50,
+                p90: 90,
+                p99: 99,
+                stddev: 28,
+            }
+        );
+        // 2 GHz
+        let ns = stats.to_ns(2_000_000);
+        assert_eq!((ns.iterations, ns.max, ns.p90), (100, 50, 45));
+        assert_eq!(percentile(&[7], 99), 7);
+    }
+
     #[test]
     fn test_bench_const_generic_infer() {
         let mut counter = 0;
@@ -0,0 +1,630 @@
+//! Structured benchmark results that get persisted in the file system, so that measurement
+//! workflows don't depend on capturing serial output. Each run is stored as JSON in
+//! [`BENCH_RESULTS_DIR`]`/<run-id>.json` and optionally as CSV next to it, see
+//! [`BenchReport::to_csv`]. The serial transfer service sends them to a host, see
+//! [`crate::rt::services::serial_transfer`].
+//!
+//! The JSON format is simple and stable. `tsc_freq_khz` and `value_ns` are only present if
+//! the frequency of the TSC is known; `stats` only for results with [`BenchStats`]:
+//! ```text
+//! {
+//!   "run_id": "roottask-1337",
+//!   "source": "roottask",
+//!   "git_hash": "0123456789ab",
+//!   "timestamp": 1337,
+//!   "unit": "ticks",
+//!   "tsc_freq_khz": 3000000,
+//!   "results": [
+//!     { "name": "echo call", "value": 42, "value_ns": 14 },
+//!     { "name": "raw echo call", "value": 40, "value_ns": 13, "stats": { "iterations": 1000,
+//!       "min": 38, "max": 900, "mean": 40, "median": 39, "p90": 41, "p99": 60, "stddev": 5 } }
+//!   ]
+//! }
+//! ```
+
+use crate::util::{
+    ticks_to_ns,
+    BenchStats,
+};
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Directory in the file system that contains one file per benchmark run.
+pub const BENCH_RESULTS_DIR: &str = "/var/bench";
+
+/// Result of a single benchmark inside a run. All values are in clock ticks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    pub name: String,
+    pub value: u64,
+    /// Statistics over the iterations, if the benchmark measured them separately.
+    pub stats: Option<BenchStats>,
+}
+
+/// All results of a benchmark run of one program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchReport {
+    run_id: String,
+    source: String,
+    git_hash: String,
+    timestamp: u64,
+    tsc_freq_khz: Option<u64>,
+    results: Vec<BenchResult>,
+}
+
+/// Comparison of a benchmark that is part of two runs. See [`BenchReport::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchDiff {
+    pub name: String,
+    pub old: Option<u64>,
+    pub new: Option<u64>,
+}
+
+impl BenchDiff {
+    /// Relative change from the old to the new value in percent, if both runs contain
+    /// the benchmark.
+    pub fn change_percent(&self) -> Option<f64> {
+        match (self.old, self.new) {
+            (Some(old), Some(new)) if old != 0 => {
+                Some((new as f64 - old as f64) * 100.0 / old as f64)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Errors when a report is parsed from JSON.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BenchReportParseError {
+    /// The input is not valid JSON (or uses JSON features that are not supported).
+    InvalidJson,
+    /// A mandatory field is missing or has the wrong type.
+    MissingField(&'static str),
+}
+
+impl BenchReport {
+    /// Creates an empty report. The run ID is derived from the source and the given
+    /// timestamp (TSC value), so that multiple runs of the same program don't overwrite
+    /// each other.
+    pub fn new(source: &str, git_hash: &str, timestamp: u64) -> Self {
+        Self {
+            run_id: format!("{}-{}", source, timestamp),
+            source: source.to_string(),
+            git_hash: git_hash.to_string(),
+            timestamp,
+            tsc_freq_khz: None,
+            results: Vec::new(),
+        }
+    }
+
+    /// Sets the frequency of the TSC, so that the report also contains the values in
+    /// nanoseconds.
+    pub fn with_tsc_freq_khz(&mut self, tsc_freq_khz: u64) -> &mut Self {
+        self.tsc_freq_khz.replace(tsc_freq_khz);
+        self
+    }
+
+    /// Adds the result of a benchmark. `value` is the number of clock ticks.
+    pub fn add(&mut self, name: &str, value: u64) -> &mut Self {
+        self.results.push(BenchResult {
+            name: name.to_string(),
+            value,
+            stats: None,
+        });
+        self
+    }
+
+    /// Adds the result of a benchmark with statistics in clock ticks. The mean is the
+    /// value of the result.
+    pub fn add_stats(&mut self, name: &str, stats: BenchStats) -> &mut Self {
+        self.results.push(BenchResult {
+            name: name.to_string(),
+            value: stats.mean,
+            stats: Some(stats),
+        });
+        self
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Name of the program that performed the benchmarks.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn git_hash(&self) -> &str {
+        &self.git_hash
+    }
+
+    /// TSC value when the run started. Only comparable between runs of the same boot.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Frequency of the TSC during the run, if known.
+    pub fn tsc_freq_khz(&self) -> Option<u64> {
+        self.tsc_freq_khz
+    }
+
+    pub fn results(&self) -> &[BenchResult] {
+        &self.results
+    }
+
+    /// Returns the value of the benchmark with the given name.
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.results
+            .iter()
+            .find(|res| res.name == name)
+            .map(|res| res.value)
+    }
+
+    /// Path of the file in the file system where the report belongs to.
+    pub fn path(&self) -> String {
+        format!("{}/{}.json", BENCH_RESULTS_DIR, self.run_id)
+    }
+
+    /// Path of the file in the file system for [`Self::to_csv`].
+    pub fn csv_path(&self) -> String {
+        format!("{}/{}.csv", BENCH_RESULTS_DIR, self.run_id)
+    }
+
+    /// Compares `self` as old run with a newer run. Contains all benchmarks of both runs,
+    /// in the order of `self` followed by the ones that only exist in `new`.
+    pub fn diff(&self, new: &Self) -> Vec<BenchDiff> {
+        let mut diff = self
+            .results
+            .iter()
+            .map(|res| BenchDiff {
+                name: res.name.clone(),
+                old: Some(res.value),
+                new: new.get(&res.name),
+            })
+            .collect::<Vec<_>>();
+        new.results
+            .iter()
+            .filter(|res| self.get(&res.name).is_none())
+            .for_each(|res| {
+                diff.push(BenchDiff {
+                    name: res.name.clone(),
+                    old: None,
+                    new: Some(res.value),
+                })
+            });
+        diff
+    }
+
+    /// Serializes the report into the format described in the module description.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n");
+        let _ = writeln!(json, "  \"run_id\": {},", JsonStr(&self.run_id));
+        let _ = writeln!(json, "  \"source\": {},", JsonStr(&self.source));
+        let _ = writeln!(json, "  \"git_hash\": {},", JsonStr(&self.git_hash));
+        let _ = writeln!(json, "  \"timestamp\": {},", self.timestamp);
+        json.push_str("  \"unit\": \"ticks\",\n");
+        if let Some(tsc_freq_khz) = self.tsc_freq_khz {
+            let _ = writeln!(json, "  \"tsc_freq_khz\": {},", tsc_freq_khz);
+        }
+        json.push_str("  \"results\": [");
+        for (i, res) in self.results.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "\n    {{ \"name\": {}, \"value\": {}",
+                JsonStr(&res.name),
+                res.value
+            );
+            if let Some(tsc_freq_khz) = self.tsc_freq_khz {
+                let _ = write!(
+                    json,
+                    ", \"value_ns\": {}",
+                    ticks_to_ns(res.value, tsc_freq_khz)
+                );
+            }
+            if let Some(stats) = res.stats {
+                let _ = write!(
+                    json,
+                    ", \"stats\": {{ \"iterations\": {}, \"min\": {}, \"max\": {}, \"mean\": {}, \
+                     \"median\": {}, \"p90\": {}, \"p99\": {}, \"stddev\": {} }}",
+                    stats.iterations,
+                    stats.min,
+                    stats.max,
+                    stats.mean,
+                    stats.median,
+                    stats.p90,
+                    stats.p99,
+                    stats.stddev
+                );
+            }
+            json.push_str(" }");
+        }
+        if !self.results.is_empty() {
+            json.push_str("\n  ");
+        }
+        json.push_str("]\n}\n");
+        json
+    }
+
+    /// Serializes the results as CSV with a header line, e.g. for spreadsheets and plot
+    /// scripts. The statistics are in clock ticks and empty for results without them.
+    /// The column `ns` is empty if the frequency of the TSC is unknown.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("name,ticks,ns,iterations,min,max,mean,median,p90,p99,stddev\n");
+        for res in &self.results {
+            let _ = write!(csv, "\"{}\",{},", res.name.replace('"', "\"\""), res.value);
+            if let Some(tsc_freq_khz) = self.tsc_freq_khz {
+                let _ = write!(csv, "{}", ticks_to_ns(res.value, tsc_freq_khz));
+            }
+            match res.stats {
+                Some(stats) => {
+                    let _ = writeln!(
+                        csv,
+                        ",{},{},{},{},{},{},{},{}",
+                        stats.iterations,
+                        stats.min,
+                        stats.max,
+                        stats.mean,
+                        stats.median,
+                        stats.p90,
+                        stats.p99,
+                        stats.stddev
+                    );
+                }
+                None => csv.push_str(",,,,,,,,\n"),
+            }
+        }
+        csv
+    }
+
+    /// Parses a report that was created by [`Self::to_json`]. Unknown fields are ignored.
+    pub fn from_json(json: &str) -> Result<Self, BenchReportParseError> {
+        let mut parser = JsonParser {
+            input: json.as_bytes(),
+            pos: 0,
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.input.len() {
+            return Err(BenchReportParseError::InvalidJson);
+        }
+
+        let str_field = |name: &'static str| {
+            value
+                .field(name)
+                .and_then(JsonValue::as_str)
+                .map(ToString::to_string)
+                .ok_or(BenchReportParseError::MissingField(name))
+        };
+        let results = match value.field("results") {
+            Some(JsonValue::Array(results)) => results
+                .iter()
+                .map(|res| {
+                    let name = res
+                        .field("name")
+                        .and_then(JsonValue::as_str)
+                        .ok_or(BenchReportParseError::MissingField("name"))?;
+                    let value = match res.field("value") {
+                        Some(JsonValue::Number(value)) => *value,
+                        _ => return Err(BenchReportParseError::MissingField("value")),
+                    };
+                    let stats = match res.field("stats") {
+                        Some(stats) => Some(parse_stats(stats)?),
+                        None => None,
+                    };
+                    Ok(BenchResult {
+                        name: name.to_string(),
+                        value,
+                        stats,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(BenchReportParseError::MissingField("results")),
+        };
+
+        Ok(Self {
+            run_id: str_field("run_id")?,
+            source: str_field("source")?,
+            git_hash: str_field("git_hash")?,
+            timestamp: match value.field("timestamp") {
+                Some(JsonValue::Number(timestamp)) => *timestamp,
+                _ => return Err(BenchReportParseError::MissingField("timestamp")),
+            },
+            tsc_freq_khz: match value.field("tsc_freq_khz") {
+                Some(JsonValue::Number(tsc_freq_khz)) => Some(*tsc_freq_khz),
+                _ => None,
+            },
+            results,
+        })
+    }
+}
+
+fn parse_stats(stats: &JsonValue) -> Result<BenchStats, BenchReportParseError> {
+    let field = |name: &'static str| match stats.field(name) {
+        Some(JsonValue::Number(value)) => Ok(*value),
+        _ => Err(BenchReportParseError::MissingField(name)),
+    };
+    Ok(BenchStats {
+        iterations: field("iterations")?,
+        min: field("min")?,
+        max: field("max")?,
+        mean: field("mean")?,
+        median: field("median")?,
+        p90: field("p90")?,
+        p99: field("p99")?,
+        stddev: field("stddev")?,
+    })
+}
+
+/// Formats a string as JSON string literal including the quotes.
+struct JsonStr<'a>(&'a str);
+
+impl core::fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// The subset of JSON that is required for benchmark reports. Numbers are unsigned integers.
+#[derive(Debug)]
+enum JsonValue {
+    String(String),
+    Number(u64),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+    Other,
+}
+
+impl JsonValue {
+    fn field(&self, name: &str) -> Option<&Self> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(key, _)| key == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(str) => Some(str),
+            _ => None,
+        }
+    }
+}
+
+/// Minimal recursive descent parser for [`JsonValue`].
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.input.len() && self.input[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.input.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), BenchReportParseError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(BenchReportParseError::InvalidJson)
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<JsonValue, BenchReportParseError> {
+        if self.input[self.pos..].starts_with(keyword.as_bytes()) {
+            self.pos += keyword.len();
+            Ok(JsonValue::Other)
+        } else {
+            Err(BenchReportParseError::InvalidJson)
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, BenchReportParseError> {
+        match self.peek().ok_or(BenchReportParseError::InvalidJson)? {
+            b'{' => self.parse_object(),
+            b'[' => self.parse_array(),
+            b'"' => self.parse_string().map(JsonValue::String),
+            b'0'..=b'9' => self.parse_number(),
+            b't' => self.expect_keyword("true"),
+            b'f' => self.expect_keyword("false"),
+            b'n' => self.expect_keyword("null"),
+            _ => Err(BenchReportParseError::InvalidJson),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, BenchReportParseError> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(fields));
+                }
+                _ => return Err(BenchReportParseError::InvalidJson),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, BenchReportParseError> {
+        self.expect(b'[')?;
+        let mut elements = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(elements));
+        }
+        loop {
+            elements.push(self.parse_value()?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(elements));
+                }
+                _ => return Err(BenchReportParseError::InvalidJson),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, BenchReportParseError> {
+        let begin = self.pos;
+        while self.pos < self.input.len() && self.input[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        core::str::from_utf8(&self.input[begin..self.pos])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .map(JsonValue::Number)
+            .ok_or(BenchReportParseError::InvalidJson)
+    }
+
+    fn parse_string(&mut self) -> Result<String, BenchReportParseError> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self
+                .input
+                .get(self.pos)
+                .ok_or(BenchReportParseError::InvalidJson)?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self
+                        .input
+                        .get(self.pos)
+                        .ok_or(BenchReportParseError::InvalidJson)?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' | b'\\' | b'/' => bytes.push(escaped),
+                        b'n' => bytes.push(b'\n'),
+                        b't' => bytes.push(b'\t'),
+                        b'r' => bytes.push(b'\r'),
+                        b'u' => {
+                            let c = self
+                                .input
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|hex| core::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or(BenchReportParseError::InvalidJson)?;
+                            self.pos += 4;
+                            let mut buf = [0; 4];
+                            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                        }
+                        _ => return Err(BenchReportParseError::InvalidJson),
+                    }
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| BenchReportParseError::InvalidJson)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mut report = BenchReport::new("roottask", "0123456789ab", 1337);
+        report.add("echo call", 42).add("fs \"open\"\n", 7);
+        assert_eq!(report.path(), "/var/bench/roottask-1337.json");
+
+        let json = report.to_json();
+        assert_eq!(BenchReport::from_json(&json), Ok(report.clone()));
+
+        let empty = BenchReport::new("roottask", "unknown", 1);
+        assert_eq!(BenchReport::from_json(&empty.to_json()), Ok(empty));
+
+        assert_eq!(
+            BenchReport::from_json("{\"run_id\": \"a\"}"),
+            Err(BenchReportParseError::MissingField("results"))
+        );
+        assert_eq!(
+            BenchReport::from_json("{\"run_id\": "),
+            Err(BenchReportParseError::InvalidJson)
+        );
+    }
+
+    #[test]
+    fn test_stats_and_csv() {
+        let stats = BenchStats {
+            iterations: 1000,
+            min: 38,
+            max: 900,
+            mean: 40,
+            median: 39,
+            p90: 41,
+            p99: 60,
+            stddev: 5,
+        };
+        let mut report = BenchReport::new("roottask", "unknown", 1);
+        report
+            .with_tsc_freq_khz(3_000_000)
+            .add("echo call", 42)
+            .add_stats("raw, echo", stats);
+        assert_eq!(report.get("raw, echo"), Some(40));
+
+        let json = report.to_json();
+        assert!(json.contains("\"value_ns\": 14"));
+        assert_eq!(BenchReport::from_json(&json), Ok(report.clone()));
+
+        assert_eq!(
+            report.to_csv(),
+            "name,ticks,ns,iterations,min,max,mean,median,p90,p99,stddev\n\
+             \"echo call\",42,14,,,,,,,,\n\
+             \"raw, echo\",40,13,1000,38,900,40,39,41,60,5\n"
+        );
+        assert_eq!(report.csv_path(), "/var/bench/roottask-1.csv");
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut old = BenchReport::new("roottask", "unknown", 1);
+        old.add("a", 100).add("b", 50);
+        let mut new = BenchReport::new("roottask", "unknown", 2);
+        new.add("a", 110).add("c", 1);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.len(), 3);
+        assert_eq!(diff[0].change_percent(), Some(10.0));
+        assert_eq!(diff[1].new, None);
+        assert_eq!(diff[2].old, None);
+        assert_eq!(diff[2].name, "c");
+    }
+}
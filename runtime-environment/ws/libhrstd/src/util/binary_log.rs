@@ -0,0 +1,331 @@
+//! Compact binary encoding of log records. The roottask can emit it instead of formatted
+//! text to reduce the bandwidth of the serial port during benchmarks. Strings that are
+//! part of the image, i.e. the module path, the file, and messages without arguments,
+//! are only transferred as reference (address and length). A decoder on the host
+//! resolves them with the ELF file of the image.
+//!
+//! The format string of a record isn't accessible from [`log::Record`]. Hence, the call
+//! site (file and line) identifies the log statement, and a message with arguments gets
+//! transferred formatted.
+//!
+//! Layout of a record (little endian):
+//!
+//! | field     | size                                                                     |
+//! |-----------|--------------------------------------------------------------------------|
+//! | magic     | 2 bytes, [`BINARY_LOG_MAGIC`]                                            |
+//! | flags     | 1 byte, see [`BinaryLogFlags`]                                           |
+//! | level     | 1 byte, [`log::Level`] as number                                         |
+//! | timestamp | 8 bytes, only if [`BinaryLogFlags::TIMESTAMP`]                           |
+//! | target    | [`StrRef`]: 4 bytes address, 2 bytes length                              |
+//! | file      | [`StrRef`]                                                               |
+//! | line      | 4 bytes                                                                  |
+//! | message   | [`StrRef`], or 2 bytes length and the text if [`BinaryLogFlags::INLINE_MSG`] |
+//!
+//! Records may be interleaved with regular text output. The magic starts with a null byte,
+//! which never occurs in text.
+
+use arrayvec::ArrayVec;
+use log::Level;
+
+/// Start of each record.
+pub const BINARY_LOG_MAGIC: [u8; 2] = [0x00, 0xb1];
+
+/// Maximum length of a record without the inline message.
+pub const BINARY_LOG_MAX_HEADER_LEN: usize = 2 + 1 + 1 + 8 + 6 + 6 + 4 + 2;
+
+bitflags::bitflags! {
+    /// Flags of a record.
+    pub struct BinaryLogFlags: u8 {
+        /// The record carries a timestamp.
+        const TIMESTAMP = 1 << 0;
+        /// The timestamp is a raw TSC value instead of nanoseconds.
+        const TIMESTAMP_TICKS = 1 << 1;
+        /// The message follows as text instead of a [`StrRef`].
+        const INLINE_MSG = 1 << 2;
+    }
+}
+
+/// Reference to a string in the image. The null reference stands for an unknown string.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct StrRef {
+    pub addr: u32,
+    pub len: u16,
+}
+
+impl StrRef {
+    /// Returns a reference to the string, if its address and length fit into the record.
+    pub fn from_static(s: &'static str) -> Option<Self> {
+        Some(Self {
+            addr: u32::try_from(s.as_ptr() as usize).ok()?,
+            len: u16::try_from(s.len()).ok()?,
+        })
+    }
+
+    /// Returns a reference to the string or the null reference.
+    pub fn from_static_or_null(s: Option<&'static str>) -> Self {
+        s.and_then(Self::from_static).unwrap_or_default()
+    }
+
+    pub const fn is_null(self) -> bool {
+        self.addr == 0
+    }
+}
+
+/// Timestamp of a record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryLogTimestamp {
+    Ns(u64),
+    Ticks(u64),
+}
+
+/// Message of a record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryLogMsg<'a> {
+    Static(StrRef),
+    Inline(&'a str),
+}
+
+/// A decoded or to be encoded log record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BinaryLogRecord<'a> {
+    pub timestamp: Option<BinaryLogTimestamp>,
+    pub level: Level,
+    /// Module path of the call site.
+    pub target: StrRef,
+    pub file: StrRef,
+    pub line: u32,
+    pub msg: BinaryLogMsg<'a>,
+}
+
+/// Errors of [`BinaryLogRecord::decode`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinaryLogDecodeError {
+    /// The bytes don't start with [`BINARY_LOG_MAGIC`].
+    NoMagic,
+    /// More bytes are required to decode the record.
+    Incomplete,
+    /// Invalid level or unknown flags.
+    Invalid,
+}
+
+impl<'a> BinaryLogRecord<'a> {
+    /// Replaces the content of `buf` with the encoded record. A too long inline message
+    /// gets truncated.
+    pub fn encode<const N: usize>(&self, buf: &mut ArrayVec<u8, N>) {
+        assert!(N >= BINARY_LOG_MAX_HEADER_LEN, "buffer too small");
+        let mut flags = BinaryLogFlags::empty();
+        let timestamp = match self.timestamp {
+            Some(BinaryLogTimestamp::Ns(ns)) => {
+                flags |= BinaryLogFlags::TIMESTAMP;
+                Some(ns)
+            }
+            Some(BinaryLogTimestamp::Ticks(ticks)) => {
+                flags |= BinaryLogFlags::TIMESTAMP | BinaryLogFlags::TIMESTAMP_TICKS;
+                Some(ticks)
+            }
+            None => None,
+        };
+        if let BinaryLogMsg::Inline(_) = self.msg {
+            flags |= BinaryLogFlags::INLINE_MSG;
+        }
+
+        buf.clear();
+        buf.extend(BINARY_LOG_MAGIC);
+        buf.push(flags.bits());
+        buf.push(self.level as u8);
+        if let Some(timestamp) = timestamp {
+            buf.extend(timestamp.to_le_bytes());
+        }
+        Self::encode_str_ref(buf, self.target);
+        Self::encode_str_ref(buf, self.file);
+        buf.extend(self.line.to_le_bytes());
+        match self.msg {
+            BinaryLogMsg::Static(msg) => Self::encode_str_ref(buf, msg),
+            BinaryLogMsg::Inline(msg) => {
+                let mut len = msg.len().min(N - buf.len() - 2).min(u16::MAX as usize);
+                while !msg.is_char_boundary(len) {
+                    len -= 1;
+                }
+                let msg = &msg.as_bytes()[..len];
+                buf.extend((msg.len() as u16).to_le_bytes());
+                buf.try_extend_from_slice(msg).unwrap();
+            }
+        }
+    }
+
+    /// Decodes the record at the beginning of `bytes`. Returns the record and the number
+    /// of bytes it occupies. An inline message with invalid UTF-8 decodes to the empty
+    /// string.
+    pub fn decode(bytes: &'a [u8]) -> Result<(Self, usize), BinaryLogDecodeError> {
+        if bytes.len() < BINARY_LOG_MAGIC.len() {
+            return if BINARY_LOG_MAGIC.starts_with(bytes) {
+                Err(BinaryLogDecodeError::Incomplete)
+            } else {
+                Err(BinaryLogDecodeError::NoMagic)
+            };
+        }
+        if bytes[..2] != BINARY_LOG_MAGIC {
+            return Err(BinaryLogDecodeError::NoMagic);
+        }
+        let mut reader = Reader { bytes, pos: 2 };
+        let flags = BinaryLogFlags::from_bits(reader.u8()?).ok_or(BinaryLogDecodeError::Invalid)?;
+        let level = match reader.u8()? {
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            5 => Level::Trace,
+            _ => return Err(BinaryLogDecodeError::Invalid),
+        };
+        let timestamp = if flags.contains(BinaryLogFlags::TIMESTAMP) {
+            let timestamp = reader.u64()?;
+            if flags.contains(BinaryLogFlags::TIMESTAMP_TICKS) {
+                Some(BinaryLogTimestamp::Ticks(timestamp))
+            } else {
+                Some(BinaryLogTimestamp::Ns(timestamp))
+            }
+        } else {
+            None
+        };
+        let target = reader.str_ref()?;
+        let file = reader.str_ref()?;
+        let line = reader.u32()?;
+        let msg = if flags.contains(BinaryLogFlags::INLINE_MSG) {
+            let len = reader.u16()? as usize;
+            let msg = reader.take(len)?;
+            BinaryLogMsg::Inline(core::str::from_utf8(msg).unwrap_or(""))
+        } else {
+            BinaryLogMsg::Static(reader.str_ref()?)
+        };
+
+        let record = Self {
+            timestamp,
+            level,
+            target,
+            file,
+            line,
+            msg,
+        };
+        Ok((record, reader.pos))
+    }
+
+    fn encode_str_ref<const N: usize>(buf: &mut ArrayVec<u8, N>, str_ref: StrRef) {
+        buf.extend(str_ref.addr.to_le_bytes());
+        buf.extend(str_ref.len.to_le_bytes());
+    }
+}
+
+/// Reads little-endian values from a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BinaryLogDecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(BinaryLogDecodeError::Incomplete)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, BinaryLogDecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, BinaryLogDecodeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, BinaryLogDecodeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, BinaryLogDecodeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn str_ref(&mut self) -> Result<StrRef, BinaryLogDecodeError> {
+        Ok(StrRef {
+            addr: self.u32()?,
+            len: self.u16()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_log_roundtrip() {
+        let record = BinaryLogRecord {
+            timestamp: Some(BinaryLogTimestamp::Ns(2_000_417_309)),
+            level: Level::Warn,
+            target: StrRef {
+                addr: 0x401000,
+                len: 11,
+            },
+            file: StrRef {
+                addr: 0x402000,
+                len: 15,
+            },
+            line: 42,
+            msg: BinaryLogMsg::Inline("pid=3 exited"),
+        };
+        let mut buf = ArrayVec::<u8, 128>::new();
+        record.encode(&mut buf);
+        assert_eq!(buf.len(), BINARY_LOG_MAX_HEADER_LEN + "pid=3 exited".len());
+        assert_eq!(BinaryLogRecord::decode(&buf), Ok((record, buf.len())));
+
+        let record = BinaryLogRecord {
+            timestamp: None,
+            msg: BinaryLogMsg::Static(StrRef {
+                addr: 0x403000,
+                len: 5,
+            }),
+            ..record
+        };
+        record.encode(&mut buf);
+        assert_eq!(buf.len(), BINARY_LOG_MAX_HEADER_LEN - 8 + 4);
+        // trailing bytes belong to the next record
+        buf.extend([b'a', b'b']);
+        assert_eq!(BinaryLogRecord::decode(&buf), Ok((record, buf.len() - 2)));
+    }
+
+    #[test]
+    fn test_binary_log_decode_errors() {
+        assert_eq!(
+            BinaryLogRecord::decode(b"text"),
+            Err(BinaryLogDecodeError::NoMagic)
+        );
+        assert_eq!(
+            BinaryLogRecord::decode(&[0x00]),
+            Err(BinaryLogDecodeError::Incomplete)
+        );
+        assert_eq!(
+            BinaryLogRecord::decode(&[0x00, 0xb1, 0x00, 0x09]),
+            Err(BinaryLogDecodeError::Invalid)
+        );
+
+        let record = BinaryLogRecord {
+            timestamp: Some(BinaryLogTimestamp::Ticks(1337)),
+            level: Level::Info,
+            target: StrRef::default(),
+            file: StrRef::default(),
+            line: 0,
+            msg: BinaryLogMsg::Inline("a message that gets truncated"),
+        };
+        let mut buf = ArrayVec::<u8, { BINARY_LOG_MAX_HEADER_LEN + 9 }>::new();
+        record.encode(&mut buf);
+        let (decoded, len) = BinaryLogRecord::decode(&buf).unwrap();
+        assert_eq!(decoded.msg, BinaryLogMsg::Inline("a message"));
+        assert_eq!(len, buf.len());
+        assert_eq!(
+            BinaryLogRecord::decode(&buf[..len - 1]),
+            Err(BinaryLogDecodeError::Incomplete)
+        );
+    }
+}
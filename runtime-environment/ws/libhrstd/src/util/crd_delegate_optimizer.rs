@@ -1,19 +1,7 @@
-//! Module for [`OrderLoopOptimizerIterator`].
+//! Module for [`CrdDelegateOptimizer`].
 
-use crate::cap_space::root::RootCapSpace;
-use crate::libhedron::mem::PAGE_SIZE;
 use core::cmp::min;
-use libhedron::syscall::{
-    sys_pd_ctrl_delegate,
-    DelegateFlags,
-};
-use libhedron::{
-    CapSel,
-    CrdMem,
-    CrdObjPT,
-    MemCapPermissions,
-    PTCapPermissions,
-};
+use libhedron::MAX_CRD_ORDER;
 
 /// An iterator that helps to delegate multiple capabilities via
 /// [`crate::libhedron::Crd`] objects in a as optimal as it can be bulk operation.
@@ -37,6 +25,10 @@ use libhedron::{
 /// 4) base 29, order 1 (15/16 pages)
 /// 4) base 31, order 0 (16/16 pages)
 ///
+/// To delegate capabilities, use [`crate::util::delegation::DelegationBuilder`], which
+/// validates the range and performs the syscalls of all steps. Use the iterator directly
+/// only for other operations, such as revoking ranges.
+///
 /// # Notes
 /// For Multiboot-Modules the optimization is not applicable from my observations, because
 /// they are only page-aligned but often not more. Therefore a delegate syscall for each page.
@@ -51,7 +43,8 @@ pub struct CrdDelegateOptimizer {
 }
 
 impl CrdDelegateOptimizer {
-    const MAX_ORDER: u64 = u8::MAX as u64;
+    /// A [`libhedron::Crd`] can't describe more capabilities at once.
+    const MAX_ORDER: u64 = MAX_CRD_ORDER as u64;
 
     /// Creates a new [`CrdDelegateOptimizer`]. The base is either a page-num,
     /// a I/O port number or an index into the capability space.
@@ -66,7 +59,7 @@ impl CrdDelegateOptimizer {
     }
 
     /// Finds the highest order for a base (regarding power of 2), where the base
-    /// is aligned to. 64 is the maximum value.
+    /// is aligned to. [`Self::MAX_ORDER`] is the maximum value.
     fn find_highest_order_for_base_alignment(base: u64) -> u64 {
         for order in (1..=Self::MAX_ORDER).rev() {
             let power = libm::pow(2.0, order as f64) as u64;
@@ -76,68 +69,6 @@ impl CrdDelegateOptimizer {
         }
         0
     }
-
-    /// Iterates over all elements of [`Self`] and delegates memory capabilites
-    /// from the src Pd to the dest Pd. If SRC_PD = DEST_PD and SRC_PD == ROOTTASK_PD,
-    /// the Hypervisor-flag in the DelegateFlags gets true.
-    pub fn mmap(self, src_pd: CapSel, dest_pd: CapSel, perm: MemCapPermissions) {
-        let is_roottask = src_pd == RootCapSpace::RootPd.val();
-        let is_roottask_to_roottask_mapping = is_roottask && src_pd == dest_pd;
-
-        if is_roottask_to_roottask_mapping {
-            log::debug!("is roottask to roottask mapping (hypervisorflag true)");
-        }
-
-        self.for_each(|params| {
-            log::trace!(
-                "map page {} ({:?}) (pd={}) to page {} ({:?}) (pd={}), order={} (2^order={}, perm={:?})",
-                params.src_base,
-                (params.src_base as usize * PAGE_SIZE) as *const u64,
-                src_pd,
-                params.dest_base,
-                (params.dest_base as usize * PAGE_SIZE) as *const u64,
-                dest_pd,
-                params.order,
-                params.power,
-                perm,
-            );
-
-            // currently in Hedron: needs twice the same permissions (this will be removed soon)
-            let src_crd = CrdMem::new(params.src_base, params.order, perm);
-            let dest_crd = CrdMem::new(params.dest_base, params.order, perm);
-            sys_pd_ctrl_delegate(
-                src_pd,
-                dest_pd,
-                src_crd,
-                dest_crd,
-                DelegateFlags::new(true, false, false, is_roottask_to_roottask_mapping, 0),
-            )
-            .unwrap();
-        });
-    }
-
-    /// Map PTs to other PTs.
-    pub fn pts(self, src_pd: CapSel, dest_pd: CapSel) {
-        self.for_each(|params| {
-            log::trace!(
-                "map PT sel {} (pd={}) to PT sel {} (pd={}), order={} (2^order={})",
-                params.src_base,
-                src_pd,
-                params.dest_base,
-                dest_pd,
-                params.order,
-                params.power
-            );
-
-            let perm = PTCapPermissions::CALL;
-
-            // currently in Hedron: needs twice the same permissions (this will be removed soon)
-            let src_crd = CrdObjPT::new(params.src_base, params.order, perm);
-            let dest_crd = CrdObjPT::new(params.dest_base, params.order, perm);
-            sys_pd_ctrl_delegate(src_pd, dest_pd, src_crd, dest_crd, DelegateFlags::default())
-                .unwrap();
-        });
-    }
 }
 
 impl Iterator for CrdDelegateOptimizer {
@@ -0,0 +1,163 @@
+//! Cryptographically secure pseudo-random number generator on top of the ChaCha20 block
+//! function (RFC 8439). The roottask seeds it with RDRAND and TSC jitter and serves
+//! `/dev/urandom` with it.
+//!
+//! After each request, the generator replaces its key with fresh output ("fast key
+//! erasure"), hence a leaked state doesn't reveal earlier output.
+
+/// Size of a ChaCha20 key and of the seed of [`ChaChaRng`].
+pub const CHACHA_KEY_SIZE: usize = 32;
+
+/// Size of a ChaCha20 block.
+pub const CHACHA_BLOCK_SIZE: usize = 64;
+
+/// "expand 32-byte k"
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+/// Computes the ChaCha20 block for `key`, `counter`, and `nonce` (RFC 8439, 2.3).
+pub fn chacha20_block(
+    key: &[u8; CHACHA_KEY_SIZE],
+    counter: u32,
+    nonce: &[u8; 12],
+) -> [u8; CHACHA_BLOCK_SIZE] {
+    let mut state = [0_u32; 16];
+    state[..4].copy_from_slice(&CONSTANTS);
+    for (i, word) in key.chunks_exact(4).enumerate() {
+        state[4 + i] = u32::from_le_bytes(word.try_into().unwrap());
+    }
+    state[12] = counter;
+    for (i, word) in nonce.chunks_exact(4).enumerate() {
+        state[13 + i] = u32::from_le_bytes(word.try_into().unwrap());
+    }
+
+    let mut working = state;
+    for _ in 0..10 {
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+
+    let mut block = [0; CHACHA_BLOCK_SIZE];
+    for (i, word) in working.iter().enumerate() {
+        block[i * 4..][..4].copy_from_slice(&word.wrapping_add(state[i]).to_le_bytes());
+    }
+    block
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// CSPRNG, see module description.
+#[derive(Debug, Clone)]
+pub struct ChaChaRng {
+    key: [u8; CHACHA_KEY_SIZE],
+    /// Number of requests so far, used as nonce.
+    requests: u64,
+}
+
+impl ChaChaRng {
+    /// Creates a generator. The seed must come from a source of entropy.
+    pub const fn new(seed: [u8; CHACHA_KEY_SIZE]) -> Self {
+        Self {
+            key: seed,
+            requests: 0,
+        }
+    }
+
+    /// Mixes more entropy into the key.
+    pub fn reseed(&mut self, entropy: &[u8]) {
+        for chunk in entropy.chunks(CHACHA_KEY_SIZE) {
+            for (key, byte) in self.key.iter_mut().zip(chunk) {
+                *key ^= byte;
+            }
+            self.rekey();
+        }
+    }
+
+    /// Fills `buf` with random bytes.
+    pub fn fill_bytes(&mut self, buf: &mut [u8]) {
+        let nonce = self.nonce();
+        // the first block becomes the next key
+        for (i, chunk) in buf.chunks_mut(CHACHA_BLOCK_SIZE).enumerate() {
+            let block = chacha20_block(&self.key, i as u32 + 1, &nonce);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.rekey();
+    }
+
+    fn rekey(&mut self) {
+        let block = chacha20_block(&self.key, 0, &self.nonce());
+        self.key.copy_from_slice(&block[..CHACHA_KEY_SIZE]);
+        self.requests += 1;
+    }
+
+    fn nonce(&self) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[4..].copy_from_slice(&self.requests.to_le_bytes());
+        nonce
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chacha20_block() {
+        // RFC 8439, 2.3.2
+        let mut key = [0; CHACHA_KEY_SIZE];
+        key.iter_mut()
+            .enumerate()
+            .for_each(|(i, byte)| *byte = i as u8);
+        let nonce = [0, 0, 0, 0x09, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let block = chacha20_block(&key, 1, &nonce);
+        assert_eq!(
+            block[..16],
+            [
+                0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20,
+                0x71, 0xc4
+            ]
+        );
+        assert_eq!(
+            block[48..],
+            [
+                0xb5, 0x12, 0x9c, 0xd1, 0xde, 0x16, 0x4e, 0xb9, 0xcb, 0xd0, 0x83, 0xe8, 0xa2, 0x50,
+                0x3c, 0x4e
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chacha_rng() {
+        let mut rng = ChaChaRng::new([7; CHACHA_KEY_SIZE]);
+        let mut a = [0; 100];
+        let mut b = [0; 100];
+        rng.fill_bytes(&mut a);
+        rng.fill_bytes(&mut b);
+        assert_ne!(a, b);
+        assert_ne!(a, [0; 100]);
+
+        // deterministic for the same seed, different after a reseed
+        let mut same = ChaChaRng::new([7; CHACHA_KEY_SIZE]);
+        let mut c = [0; 100];
+        same.fill_bytes(&mut c);
+        assert_eq!(a, c);
+        let mut reseeded = ChaChaRng::new([7; CHACHA_KEY_SIZE]);
+        reseeded.reseed(&[1]);
+        reseeded.fill_bytes(&mut c);
+        assert_ne!(a, c);
+    }
+}
@@ -0,0 +1,304 @@
+//! Module for [`DelegationBuilder`].
+
+use crate::cap_space::root::RootCapSpace;
+use crate::kobjects::{
+    DelegatedKind,
+    PdObject,
+};
+use crate::libhedron::mem::PAGE_SIZE;
+use crate::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use core::fmt::Debug;
+use core::ops::Range;
+use libhedron::syscall::{
+    sys_pd_ctrl_delegate,
+    DelegateFlags,
+    SyscallError,
+};
+use libhedron::{
+    CapSel,
+    CrdMem,
+    CrdObjPT,
+    MemCapPermissions,
+    PTCapPermissions,
+    MAX_CRD_BASE,
+};
+
+/// Delegates a range of capabilities from one PD to another with as few `pd_ctrl_delegate`
+/// syscalls as possible. The caller describes the range with addresses or selectors and
+/// the builder computes the bases and orders of the [`crate::libhedron::Crd`]s, see
+/// [`CrdDelegateOptimizer`].
+///
+/// ```ignore
+/// DelegationBuilder::mem(r_addr..r_addr + len)
+///     .perms(MemCapPermissions::RW)
+///     .at(u_addr)
+///     .to(process.pd_obj().cap_sel())?;
+/// ```
+///
+/// The source PD is the roottask unless [`Self::from`] says otherwise. If a syscall
+/// fails, the capabilities of the steps before stay delegated. [`Self::to_pd`] also tracks
+/// the delegation, so that the roottask can revoke it later, see
+/// [`crate::kobjects::revoke_all_from`].
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct DelegationBuilder<P: DelegationPermissions> {
+    src: Range<u64>,
+    dest_start: Option<u64>,
+    src_pd: CapSel,
+    perms: P,
+}
+
+impl DelegationBuilder<MemCapPermissions> {
+    /// Delegates the memory at the page-aligned addresses `range`. Without
+    /// [`Self::perms`], the destination gets all permissions of the source. A roottask to
+    /// roottask delegation uses the hypervisor as the source, i.e. `range` contains
+    /// physical addresses then.
+    pub fn mem(range: Range<u64>) -> Self {
+        Self::new(range, MemCapPermissions::RWX)
+    }
+}
+
+impl DelegationBuilder<PTCapPermissions> {
+    /// Delegates the portals at the selectors `range`. Without [`Self::perms`], the
+    /// destination may only call them.
+    pub fn pts(range: Range<CapSel>) -> Self {
+        Self::new(range, PTCapPermissions::CALL)
+    }
+}
+
+impl<P: DelegationPermissions> DelegationBuilder<P> {
+    fn new(src: Range<u64>, perms: P) -> Self {
+        Self {
+            src,
+            dest_start: None,
+            src_pd: RootCapSpace::RootPd.val(),
+            perms,
+        }
+    }
+
+    /// Sets the permissions of the delegated capabilities.
+    pub fn perms(mut self, perms: P) -> Self {
+        self.perms = perms;
+        self
+    }
+
+    /// Sets the PD that owns the capabilities. Default is the roottask.
+    pub fn from(mut self, src_pd: CapSel) -> Self {
+        self.src_pd = src_pd;
+        self
+    }
+
+    /// Sets the address or selector of the first capability in the destination PD.
+    /// Default is the start of the source range.
+    pub fn at(mut self, dest_start: u64) -> Self {
+        self.dest_start = Some(dest_start);
+        self
+    }
+
+    /// Delegates the capabilities into the PD `dest_pd`.
+    pub fn to(self, dest_pd: CapSel) -> Result<(), DelegationError> {
+        // roottask to roottask only works with the hypervisor as the source
+        let from_hypervisor = self.src_pd == RootCapSpace::RootPd.val() && self.src_pd == dest_pd;
+        for step in self.steps()? {
+            log::trace!(
+                "delegate {} {} (pd={}) to {} (pd={}), order={}, perms={:?}",
+                P::NAME,
+                step.src_base,
+                self.src_pd,
+                step.dest_base,
+                dest_pd,
+                step.order,
+                self.perms,
+            );
+            P::delegate(
+                self.src_pd,
+                dest_pd,
+                step.src_base,
+                step.dest_base,
+                step.order,
+                self.perms,
+                from_hypervisor,
+            )
+            .map_err(|error| DelegationError::SyscallFailed {
+                base: step.src_base,
+                error,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Like [`Self::to`] but also records the delegation in `pd`, see
+    /// [`PdObject::track_delegation`]. The source must be the PD that created `pd`. The
+    /// capabilities that were delegated before a failing step are tracked, too.
+    pub fn to_pd(self, pd: &PdObject) -> Result<(), DelegationError> {
+        let src_base = self.src.start / P::UNIT;
+        let res = self.clone().to(pd.cap_sel());
+        let delegated = match &res {
+            Ok(()) => (self.src.end - self.src.start) / P::UNIT,
+            Err(DelegationError::SyscallFailed { base, .. }) => base - src_base,
+            Err(_) => 0,
+        };
+        pd.track_delegation(P::KIND, src_base..src_base + delegated);
+        res
+    }
+
+    /// Validates the range and splits it into naturally aligned steps.
+    fn steps(&self) -> Result<CrdDelegateOptimizer, DelegationError> {
+        let dest_start = self.dest_start.unwrap_or(self.src.start);
+        if self.src.is_empty() {
+            return Err(DelegationError::EmptyRange);
+        }
+        for addr in [self.src.start, self.src.end, dest_start] {
+            if addr % P::UNIT != 0 {
+                return Err(DelegationError::Unaligned(addr));
+            }
+        }
+        let count = (self.src.end - self.src.start) / P::UNIT;
+        let src_base = self.src.start / P::UNIT;
+        let dest_base = dest_start / P::UNIT;
+        if src_base.max(dest_base) + count - 1 > MAX_CRD_BASE {
+            return Err(DelegationError::OutOfRange);
+        }
+        Ok(CrdDelegateOptimizer::new(
+            src_base,
+            dest_base,
+            count as usize,
+        ))
+    }
+}
+
+/// Permissions of the capabilities that [`DelegationBuilder`] can delegate. Each
+/// implementation knows the [`crate::libhedron::Crd`] of its kind.
+pub trait DelegationPermissions: Copy + Debug {
+    /// Name of the kind for log messages.
+    const NAME: &'static str;
+    /// Number of addresses per capability, e.g. the size of a page.
+    const UNIT: u64;
+    /// Kind of the capabilities for [`PdObject::track_delegation`].
+    const KIND: DelegatedKind;
+
+    /// Delegates the `2^order` capabilities at `src_base` to `dest_base`.
+    fn delegate(
+        src_pd: CapSel,
+        dest_pd: CapSel,
+        src_base: u64,
+        dest_base: u64,
+        order: u8,
+        perms: Self,
+        from_hypervisor: bool,
+    ) -> Result<(), SyscallError>;
+}
+
+impl DelegationPermissions for MemCapPermissions {
+    const NAME: &'static str = "page";
+    const UNIT: u64 = PAGE_SIZE as u64;
+    const KIND: DelegatedKind = DelegatedKind::Mem;
+
+    fn delegate(
+        src_pd: CapSel,
+        dest_pd: CapSel,
+        src_base: u64,
+        dest_base: u64,
+        order: u8,
+        perms: Self,
+        from_hypervisor: bool,
+    ) -> Result<(), SyscallError> {
+        // currently in Hedron: needs twice the same permissions (this will be removed soon)
+        sys_pd_ctrl_delegate(
+            src_pd,
+            dest_pd,
+            CrdMem::new(src_base, order, perms),
+            CrdMem::new(dest_base, order, perms),
+            DelegateFlags::new(true, false, false, from_hypervisor, 0),
+        )
+    }
+}
+
+impl DelegationPermissions for PTCapPermissions {
+    const NAME: &'static str = "PT";
+    const UNIT: u64 = 1;
+    const KIND: DelegatedKind = DelegatedKind::Obj;
+
+    fn delegate(
+        src_pd: CapSel,
+        dest_pd: CapSel,
+        src_base: u64,
+        dest_base: u64,
+        order: u8,
+        perms: Self,
+        _from_hypervisor: bool,
+    ) -> Result<(), SyscallError> {
+        sys_pd_ctrl_delegate(
+            src_pd,
+            dest_pd,
+            CrdObjPT::new(src_base, order, perms),
+            CrdObjPT::new(dest_base, order, perms),
+            DelegateFlags::default(),
+        )
+    }
+}
+
+/// Errors of [`DelegationBuilder::to`].
+#[derive(Debug)]
+pub enum DelegationError {
+    /// The range contains no capability.
+    EmptyRange,
+    /// The address isn't a multiple of the size of a capability, e.g. of a page.
+    Unaligned(u64),
+    /// The range exceeds the bases that a [`crate::libhedron::Crd`] can describe.
+    OutOfRange,
+    /// Hedron refused the delegation of the step at the source base `base`.
+    SyscallFailed { base: u64, error: SyscallError },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn steps<P: DelegationPermissions>(
+        builder: DelegationBuilder<P>,
+    ) -> Result<Vec<(u64, u64, u8)>, DelegationError> {
+        Ok(builder
+            .steps()?
+            .map(|step| (step.src_base, step.dest_base, step.order))
+            .collect())
+    }
+
+    #[test]
+    fn test_mem_steps() {
+        let page = PAGE_SIZE as u64;
+        let builder = DelegationBuilder::mem(4 * page..9 * page).at(12 * page);
+        assert_eq!(steps(builder).unwrap(), [(4, 12, 2), (8, 16, 0)]);
+
+        let builder = DelegationBuilder::mem(0..page);
+        assert_eq!(steps(builder).unwrap(), [(0, 0, 0)]);
+    }
+
+    #[test]
+    fn test_pt_steps() {
+        let builder = DelegationBuilder::pts(32..40).at(0);
+        assert_eq!(steps(builder).unwrap(), [(32, 0, 3)]);
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        let page = PAGE_SIZE as u64;
+        assert!(matches!(
+            steps(DelegationBuilder::mem(page..page)),
+            Err(DelegationError::EmptyRange)
+        ));
+        assert!(matches!(
+            steps(DelegationBuilder::mem(page..page + 1)),
+            Err(DelegationError::Unaligned(addr)) if addr == page + 1
+        ));
+        assert!(matches!(
+            steps(DelegationBuilder::mem(0..page).at(100)),
+            Err(DelegationError::Unaligned(100))
+        ));
+        assert!(matches!(
+            steps(DelegationBuilder::pts(0..2).at(MAX_CRD_BASE)),
+            Err(DelegationError::OutOfRange)
+        ));
+    }
+}
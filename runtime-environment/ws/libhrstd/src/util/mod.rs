@@ -1,9 +1,21 @@
 pub mod ansi;
+pub mod backtrace;
+pub mod binary_log;
 pub mod crd_delegate_optimizer;
+pub mod csprng;
 #[macro_use]
 pub mod dbg;
 mod bench;
+pub mod bench_report;
+pub mod delegation;
 pub mod global_counter;
 pub mod panic_msg;
+pub mod serial_transfer;
+pub mod utf8_stream;
 
-pub use bench::BenchHelper;
+pub use bench::{
+    percentile,
+    ticks_to_ns,
+    BenchHelper,
+    BenchStats,
+};
@@ -0,0 +1,429 @@
+//! Simple file-transfer protocol over the serial port, similar to XMODEM-1K with CRC. It
+//! moves files between the runtime environment and a host, for example a new build of a
+//! program (see the process service's reload) or a core dump, also on real hardware
+//! where the serial port is the only connection. The roottask and the host tool
+//! `serialxfer-host` share this implementation.
+//!
+//! The receiver drives the transfer: it sends [`READY`] once per [`BYTE_TIMEOUT_MS`] until
+//! the first packet arrives. The sender then sends the packets one after another; the
+//! receiver answers each with [`ACK`] or, if the packet is broken, with [`NAK`], upon which
+//! the sender repeats it. [`EOT`] ends the transfer and [`CAN`] aborts it from either side.
+//!
+//! Layout of a packet (multi-byte numbers little endian, except the CRC):
+//!
+//! | field    | size                                                                 |
+//! |----------|----------------------------------------------------------------------|
+//! | start    | 1 byte, [`SOH`]                                                      |
+//! | sequence | 1 byte, packet number modulo 256                                     |
+//! | inverse  | 1 byte, `!sequence`                                                  |
+//! | length   | 2 bytes, length of the payload, at most [`MAX_PAYLOAD_LEN`]          |
+//! | payload  | `length` bytes                                                       |
+//! | CRC      | 2 bytes, big endian, CRC-16/XMODEM of the payload, see [`crc16`]     |
+//!
+//! Packet 0 is the header: the size of the file (8 bytes) followed by its name. The data
+//! of the file follows in packets 1, 2, and so on. The control bytes never occur in
+//! regular text output, hence the other side ignores output that precedes a transfer.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Starts a packet.
+pub const SOH: u8 = 0x01;
+/// Ends the transfer.
+pub const EOT: u8 = 0x04;
+/// The receiver is ready for the first packet.
+pub const READY: u8 = 0x05;
+/// The receiver got the packet.
+pub const ACK: u8 = 0x06;
+/// The packet was broken; the sender repeats it.
+pub const NAK: u8 = 0x15;
+/// Aborts the transfer.
+pub const CAN: u8 = 0x18;
+
+/// Maximum length of the payload of a packet.
+pub const MAX_PAYLOAD_LEN: usize = 1024;
+
+/// Maximum length of the file name in the header.
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Time after which [`SerialLink::read_byte`] gives up.
+pub const BYTE_TIMEOUT_MS: u64 = 1000;
+
+/// How often the receiver signals [`READY`] until it gives up, i.e. the time in seconds
+/// that the user has to start the other side.
+pub const MAX_READY_SIGNALS: usize = 60;
+
+/// How often a side repeats a packet or an answer until it gives up.
+pub const MAX_RETRIES: usize = 10;
+
+/// Byte-wise access to the serial port.
+pub trait SerialLink {
+    /// Writes all bytes.
+    fn write_bytes(&mut self, bytes: &[u8]);
+
+    /// Returns the next received byte or `None` if none arrives within
+    /// [`BYTE_TIMEOUT_MS`].
+    fn read_byte(&mut self) -> Option<u8>;
+}
+
+/// Errors of a transfer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransferError {
+    /// The other side didn't answer in time.
+    Timeout,
+    /// The other side aborted the transfer.
+    Cancelled,
+    /// Too many broken packets in a row.
+    TooManyErrors,
+    /// The header packet is malformed.
+    InvalidHeader,
+    /// The file is larger than the receiver accepts.
+    TooLarge,
+}
+
+/// A file that [`recv_file`] received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedFile {
+    /// Name that the sender gave the file.
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Computes the CRC-16/XMODEM (polynomial `0x1021`, initial value zero) of `data`.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, byte| {
+        let mut crc = crc ^ ((*byte as u16) << 8);
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Encodes a packet. `payload` must not be longer than [`MAX_PAYLOAD_LEN`].
+pub fn encode_packet(seq: u8, payload: &[u8]) -> Vec<u8> {
+    assert!(payload.len() <= MAX_PAYLOAD_LEN, "payload too long");
+    let mut packet = Vec::with_capacity(payload.len() + 7);
+    packet.extend_from_slice(&[SOH, seq, !seq]);
+    packet.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    packet.extend_from_slice(payload);
+    packet.extend_from_slice(&crc16(payload).to_be_bytes());
+    packet
+}
+
+/// Sends the file `data` under `name` to a receiver that runs [`recv_file`].
+pub fn send_file(link: &mut dyn SerialLink, name: &str, data: &[u8]) -> Result<(), TransferError> {
+    let name = &name.as_bytes()[..name.len().min(MAX_NAME_LEN)];
+    wait_for_ready(link)?;
+
+    let mut header = Vec::with_capacity(8 + name.len());
+    header.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    header.extend_from_slice(name);
+    send_packet(link, 0, &header)?;
+    for (i, chunk) in data.chunks(MAX_PAYLOAD_LEN).enumerate() {
+        send_packet(link, (i + 1) as u8, chunk)?;
+    }
+
+    for _ in 0..MAX_RETRIES {
+        link.write_bytes(&[EOT]);
+        match wait_for_answer(link) {
+            Ok(()) => return Ok(()),
+            Err(TransferError::Cancelled) => return Err(TransferError::Cancelled),
+            Err(_) => {}
+        }
+    }
+    Err(TransferError::TooManyErrors)
+}
+
+/// Receives a file from a sender that runs [`send_file`]. Aborts the transfer if the file
+/// is larger than `max_size`.
+pub fn recv_file(
+    link: &mut dyn SerialLink,
+    max_size: usize,
+) -> Result<ReceivedFile, TransferError> {
+    let header = recv_first_packet(link)?;
+    if header.seq != 0 || header.payload.len() < 8 {
+        link.write_bytes(&[CAN]);
+        return Err(TransferError::InvalidHeader);
+    }
+    let size = u64::from_le_bytes(header.payload[..8].try_into().unwrap()) as usize;
+    let name = match String::from_utf8(header.payload[8..].to_vec()) {
+        Ok(name) => name,
+        Err(_) => {
+            link.write_bytes(&[CAN]);
+            return Err(TransferError::InvalidHeader);
+        }
+    };
+    if size > max_size {
+        link.write_bytes(&[CAN]);
+        return Err(TransferError::TooLarge);
+    }
+    link.write_bytes(&[ACK]);
+
+    let mut data = Vec::with_capacity(size);
+    let mut last_seq = 0_u8;
+    let mut errors = 0;
+    loop {
+        match read_packet(link) {
+            Ok(Incoming::Packet(packet)) if packet.seq == last_seq.wrapping_add(1) => {
+                if data.len() + packet.payload.len() > size {
+                    link.write_bytes(&[CAN]);
+                    return Err(TransferError::TooLarge);
+                }
+                data.extend_from_slice(&packet.payload);
+                last_seq = packet.seq;
+                errors = 0;
+                link.write_bytes(&[ACK]);
+            }
+            // the sender missed our ACK and repeats the previous packet
+            Ok(Incoming::Packet(packet)) if packet.seq == last_seq => {
+                link.write_bytes(&[ACK]);
+            }
+            Ok(Incoming::Packet(_)) => {
+                link.write_bytes(&[CAN]);
+                return Err(TransferError::TooManyErrors);
+            }
+            Ok(Incoming::Eot) if data.len() == size => {
+                link.write_bytes(&[ACK]);
+                return Ok(ReceivedFile { name, data });
+            }
+            Ok(Incoming::Eot) => {
+                link.write_bytes(&[CAN]);
+                return Err(TransferError::InvalidHeader);
+            }
+            Ok(Incoming::Cancel) => return Err(TransferError::Cancelled),
+            Err(_) => {
+                errors += 1;
+                if errors > MAX_RETRIES {
+                    link.write_bytes(&[CAN]);
+                    return Err(TransferError::TooManyErrors);
+                }
+                link.write_bytes(&[NAK]);
+            }
+        }
+    }
+}
+
+/// A received packet.
+#[derive(Debug)]
+struct Packet {
+    seq: u8,
+    payload: Vec<u8>,
+}
+
+/// What the receiver got from the sender.
+#[derive(Debug)]
+enum Incoming {
+    Packet(Packet),
+    Eot,
+    Cancel,
+}
+
+/// Sends a packet until the receiver acknowledges it.
+fn send_packet(link: &mut dyn SerialLink, seq: u8, payload: &[u8]) -> Result<(), TransferError> {
+    let packet = encode_packet(seq, payload);
+    for _ in 0..MAX_RETRIES {
+        link.write_bytes(&packet);
+        match wait_for_answer(link) {
+            Ok(()) => return Ok(()),
+            Err(TransferError::Cancelled) => return Err(TransferError::Cancelled),
+            Err(_) => {}
+        }
+    }
+    link.write_bytes(&[CAN]);
+    Err(TransferError::TooManyErrors)
+}
+
+/// Waits for the [`READY`] signal of the receiver.
+fn wait_for_ready(link: &mut dyn SerialLink) -> Result<(), TransferError> {
+    // the receiver signals once per timeout; allow a little slack
+    for _ in 0..MAX_READY_SIGNALS + 1 {
+        match link.read_byte() {
+            Some(READY) => return Ok(()),
+            Some(CAN) => return Err(TransferError::Cancelled),
+            _ => {}
+        }
+    }
+    Err(TransferError::Timeout)
+}
+
+/// Waits for the answer to a packet. Returns [`TransferError::TooManyErrors`] on [`NAK`].
+fn wait_for_answer(link: &mut dyn SerialLink) -> Result<(), TransferError> {
+    loop {
+        match link.read_byte() {
+            Some(ACK) => return Ok(()),
+            Some(NAK) => return Err(TransferError::TooManyErrors),
+            Some(CAN) => return Err(TransferError::Cancelled),
+            // a late READY signal
+            Some(_) => {}
+            None => return Err(TransferError::Timeout),
+        }
+    }
+}
+
+/// Signals [`READY`] until the first packet arrives.
+fn recv_first_packet(link: &mut dyn SerialLink) -> Result<Packet, TransferError> {
+    let mut errors = 0;
+    let mut signal = READY;
+    for _ in 0..MAX_READY_SIGNALS {
+        link.write_bytes(&[signal]);
+        match read_packet(link) {
+            Ok(Incoming::Packet(packet)) => return Ok(packet),
+            Ok(Incoming::Cancel) => return Err(TransferError::Cancelled),
+            Ok(Incoming::Eot) => {}
+            // nothing yet
+            Err(TransferError::Timeout) => {}
+            Err(_) => {
+                errors += 1;
+                if errors > MAX_RETRIES {
+                    link.write_bytes(&[CAN]);
+                    return Err(TransferError::TooManyErrors);
+                }
+                // the sender already waits for an answer
+                signal = NAK;
+            }
+        }
+    }
+    Err(TransferError::Timeout)
+}
+
+/// Reads the next packet. Skips bytes before the start of the packet. Returns
+/// [`TransferError::Timeout`] if nothing arrives and [`TransferError::TooManyErrors`] if
+/// the packet is broken.
+fn read_packet(link: &mut dyn SerialLink) -> Result<Incoming, TransferError> {
+    loop {
+        match link.read_byte().ok_or(TransferError::Timeout)? {
+            SOH => break,
+            EOT => return Ok(Incoming::Eot),
+            CAN => return Ok(Incoming::Cancel),
+            _ => {}
+        }
+    }
+    let mut read = || link.read_byte().ok_or(TransferError::TooManyErrors);
+    let seq = read()?;
+    let inverse = read()?;
+    let len = u16::from_le_bytes([read()?, read()?]) as usize;
+    if inverse != !seq || len > MAX_PAYLOAD_LEN {
+        return Err(TransferError::TooManyErrors);
+    }
+    let payload = (0..len).map(|_| read()).collect::<Result<Vec<_>, _>>()?;
+    let crc = u16::from_be_bytes([read()?, read()?]);
+    if crc != crc16(&payload) {
+        return Err(TransferError::TooManyErrors);
+    }
+    Ok(Incoming::Packet(Packet { seq, payload }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{
+        channel,
+        Receiver,
+        Sender,
+    };
+    use std::time::Duration;
+
+    /// One end of a simulated serial line. Corrupts the `corrupt`-th byte that it writes.
+    struct TestLink {
+        tx: Sender<u8>,
+        rx: Receiver<u8>,
+        written: usize,
+        corrupt: Option<usize>,
+    }
+
+    impl SerialLink for TestLink {
+        fn write_bytes(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                let byte = if Some(self.written) == self.corrupt {
+                    !*byte
+                } else {
+                    *byte
+                };
+                self.written += 1;
+                // the other side may have finished already
+                let _ = self.tx.send(byte);
+            }
+        }
+
+        fn read_byte(&mut self) -> Option<u8> {
+            self.rx.recv_timeout(Duration::from_millis(100)).ok()
+        }
+    }
+
+    fn link_pair(corrupt_a: Option<usize>) -> (TestLink, TestLink) {
+        let (tx_a, rx_b) = channel();
+        let (tx_b, rx_a) = channel();
+        let a = TestLink {
+            tx: tx_a,
+            rx: rx_a,
+            written: 0,
+            corrupt: corrupt_a,
+        };
+        let b = TestLink {
+            tx: tx_b,
+            rx: rx_b,
+            written: 0,
+            corrupt: None,
+        };
+        (a, b)
+    }
+
+    fn transfer(
+        data: Vec<u8>,
+        corrupt: Option<usize>,
+        max_size: usize,
+    ) -> [Result<(), TransferError>; 2] {
+        let (mut sender, mut receiver) = link_pair(corrupt);
+        let expected = data.clone();
+        let sender = std::thread::spawn(move || send_file(&mut sender, "app.elf", &data));
+        let received = recv_file(&mut receiver, max_size).map(|file| {
+            assert_eq!(file.name, "app.elf");
+            assert_eq!(file.data, expected);
+        });
+        [sender.join().unwrap(), received]
+    }
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(crc16(&[]), 0);
+    }
+
+    #[test]
+    fn test_encode_packet() {
+        assert_eq!(
+            encode_packet(1, b"123456789"),
+            [&[SOH, 1, 0xfe, 9, 0][..], b"123456789", &[0x31, 0xc3][..]].concat()
+        );
+    }
+
+    #[test]
+    fn test_transfer() {
+        let data = (0..5000).map(|i| i as u8).collect::<Vec<_>>();
+        assert_eq!(transfer(data, None, 5000), [Ok(()), Ok(())]);
+        assert_eq!(transfer(Vec::new(), None, 0), [Ok(()), Ok(())]);
+        // more than 256 packets
+        let data = vec![0xaa; 300 * MAX_PAYLOAD_LEN];
+        assert_eq!(transfer(data, None, usize::MAX), [Ok(()), Ok(())]);
+    }
+
+    #[test]
+    fn test_transfer_repeats_broken_packet() {
+        let data = (0..3000).map(|i| i as u8).collect::<Vec<_>>();
+        // a byte of the payload of the first data packet (header: 7 + 8 + 7 bytes)
+        assert_eq!(transfer(data, Some(22 + 100), 3000), [Ok(()), Ok(())]);
+    }
+
+    #[test]
+    fn test_transfer_too_large() {
+        assert_eq!(
+            transfer(vec![0; 100], None, 99),
+            [Err(TransferError::Cancelled), Err(TransferError::TooLarge)]
+        );
+    }
+}
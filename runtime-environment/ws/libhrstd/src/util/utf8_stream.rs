@@ -0,0 +1,123 @@
+//! Incremental UTF-8 decoding of byte streams. Programs write their output in arbitrary
+//! portions, for example when the buffer of the libc is full. Hence, a multi-byte code
+//! point may be split across two writes.
+
+/// Decodes a UTF-8 byte stream that arrives in arbitrary portions. An incomplete code
+/// point at the end of a portion is kept until the next portion completes it. Invalid
+/// sequences become [`char::REPLACEMENT_CHARACTER`].
+#[derive(Debug, Default)]
+pub struct Utf8StreamDecoder {
+    /// Bytes of an incomplete but so far valid code point.
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+impl Utf8StreamDecoder {
+    const REPLACEMENT: &'static str = "\u{fffd}";
+
+    pub const fn new() -> Self {
+        Self {
+            pending: [0; 4],
+            pending_len: 0,
+        }
+    }
+
+    /// Decodes the next portion of the stream and applies `fnc` to each piece of text.
+    pub fn decode(&mut self, mut bytes: &[u8], mut fnc: impl FnMut(&str)) {
+        // first, complete the code point of the previous portion
+        while self.pending_len > 0 && !bytes.is_empty() {
+            self.pending[self.pending_len] = bytes[0];
+            match core::str::from_utf8(&self.pending[..self.pending_len + 1]) {
+                Ok(text) => {
+                    fnc(text);
+                    self.pending_len = 0;
+                    bytes = &bytes[1..];
+                }
+                // the byte doesn't continue the code point; decode it again below
+                Err(err) if err.error_len().is_some() => {
+                    fnc(Self::REPLACEMENT);
+                    self.pending_len = 0;
+                }
+                Err(_) => {
+                    self.pending_len += 1;
+                    bytes = &bytes[1..];
+                }
+            }
+        }
+
+        while !bytes.is_empty() {
+            let err = match core::str::from_utf8(bytes) {
+                Ok(text) => {
+                    fnc(text);
+                    return;
+                }
+                Err(err) => err,
+            };
+            let (valid, rest) = bytes.split_at(err.valid_up_to());
+            if !valid.is_empty() {
+                fnc(core::str::from_utf8(valid).unwrap());
+            }
+            match err.error_len() {
+                Some(invalid_len) => {
+                    fnc(Self::REPLACEMENT);
+                    bytes = &rest[invalid_len..];
+                }
+                // incomplete code point at the end
+                None => {
+                    self.pending[..rest.len()].copy_from_slice(rest);
+                    self.pending_len = rest.len();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Emits a pending incomplete code point as [`char::REPLACEMENT_CHARACTER`]. Useful when
+    /// the stream ends.
+    pub fn flush(&mut self, mut fnc: impl FnMut(&str)) {
+        if self.pending_len > 0 {
+            fnc(Self::REPLACEMENT);
+            self.pending_len = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    fn decode_in_portions(bytes: &[u8], portion_size: usize) -> String {
+        let mut decoder = Utf8StreamDecoder::new();
+        let mut text = String::new();
+        for portion in bytes.chunks(portion_size) {
+            decoder.decode(portion, |piece| text.push_str(piece));
+        }
+        decoder.flush(|piece| text.push_str(piece));
+        text
+    }
+
+    #[test]
+    fn test_split_code_points() {
+        let msg = "Grüße: 10€ 😀!";
+        for portion_size in 1..=msg.len() {
+            assert_eq!(
+                decode_in_portions(msg.as_bytes(), portion_size),
+                msg,
+                "portion_size={}",
+                portion_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_sequences() {
+        // stray continuation byte
+        assert_eq!(decode_in_portions(b"a\x80b", 1), "a\u{fffd}b");
+        // code point interrupted by an ASCII character in the next portion
+        assert_eq!(decode_in_portions(b"a\xe2\x82b", 1), "a\u{fffd}b");
+        assert_eq!(decode_in_portions(b"a\xe2\x82b", 2), "a\u{fffd}b");
+        // the stream ends within a code point
+        assert_eq!(decode_in_portions(&"a€".as_bytes()[..3], 2), "a\u{fffd}");
+    }
+}
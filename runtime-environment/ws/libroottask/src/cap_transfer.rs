@@ -0,0 +1,169 @@
+//! Transfer of capabilities between processes.
+//!
+//! Hedron doesn't delegate capabilities during IPC, see [`TypedItem`]. Instead, a service
+//! request names a capability by a typed item and the roottask, which holds the
+//! capabilities of all PDs, delegates it with `pd_ctrl_delegate` to the receiver. The
+//! capability lands at the next free selector in the range of received capabilities of the
+//! receiver, see [`UserAppCapSpace::ReceivedCapBase`]. The roottask can't revoke the
+//! capability from the receiver alone, hence the receiver revokes it itself and frees the
+//! selector for the next transfer with [`release_received_cap`].
+
+use crate::process::process_cap_sels;
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::libhedron::syscall::{
+    sys_pd_ctrl_delegate,
+    DelegateFlags,
+};
+use libhrstd::libhedron::{
+    CapSel,
+    CrdKind,
+    CrdObj,
+    TypedItem,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Number of selectors for received capabilities of each process.
+const RECEIVED_CAP_COUNT: u64 =
+    UserAppCapSpace::ReceivedCapEnd as u64 - UserAppCapSpace::ReceivedCapBase as u64 + 1;
+
+/// The selectors for received capabilities that each process uses, indexed by PID. Bit
+/// `i` stands for the selector `ReceivedCapBase + i`; all of them fit into a `u64`.
+static RECEIVED_CAPS: SimpleMutex<[u64; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([0; NUM_PROCESSES as usize]);
+
+/// Errors of [`transfer_cap`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CapTransferError {
+    /// The typed item names no single object capability.
+    InvalidItem,
+    /// All selectors for received capabilities of the receiver are in use.
+    CapSpaceFull,
+    /// The selector holds no received capability.
+    NotReceived,
+}
+
+/// Delegates the capability of `item` from the PD with the selector `from_pd` to the
+/// process `to` and returns its selector in the capability space of `to`. Services of the
+/// roottask pass [`libhrstd::cap_space::root::RootCapSpace::RootPd`] to hand out their own
+/// capabilities. The delegate flags of the item are ignored, because they could make the
+/// hypervisor the source.
+///
+/// The caller must make sure that `to` still runs and that the source may hand out
+/// capabilities to it, e.g. because `to` asked for them.
+pub fn transfer_cap(
+    from_pd: CapSel,
+    item: &TypedItem,
+    to: ProcessId,
+) -> Result<CapSel, CapTransferError> {
+    let crd = check_item(item)?;
+    // a process that stopped in the meantime can't receive anything
+    let to_pd = process_cap_sels(to)
+        .ok_or(CapTransferError::InvalidItem)?
+        .pd();
+    let sel = next_received_cap_sel(&mut RECEIVED_CAPS.lock()[to as usize])
+        .ok_or(CapTransferError::CapSpaceFull)?;
+    // if the selector of the sender is empty, the one of the receiver stays empty
+    sys_pd_ctrl_delegate(
+        from_pd,
+        to_pd,
+        crd,
+        crd.with_base(sel),
+        DelegateFlags::default(),
+    )
+    .map_err(|_| {
+        free_received_cap_sel(&mut RECEIVED_CAPS.lock()[to as usize], sel);
+        CapTransferError::InvalidItem
+    })?;
+    log::debug!(
+        "delegated capability {} of PD {} to pid={} at {}",
+        crd.base(),
+        from_pd,
+        to,
+        sel
+    );
+    Ok(sel)
+}
+
+/// Frees the selector of a capability that the process received, after the process
+/// revoked the capability itself.
+pub fn release_received_cap(pid: ProcessId, sel: CapSel) -> Result<(), CapTransferError> {
+    if free_received_cap_sel(&mut RECEIVED_CAPS.lock()[pid as usize], sel) {
+        log::debug!("pid={} released the received capability at {}", pid, sel);
+        Ok(())
+    } else {
+        Err(CapTransferError::NotReceived)
+    }
+}
+
+/// Frees the selectors for received capabilities of a stopped process for the next process
+/// with its PID. The capabilities are gone with the PD of the process.
+pub fn forget_received_caps(pid: ProcessId) {
+    RECEIVED_CAPS.lock()[pid as usize] = 0;
+}
+
+/// Returns the send window of `item` if it names a single object capability.
+pub fn check_item(item: &TypedItem) -> Result<CrdObj, CapTransferError> {
+    let crd: CrdObj = item.crd();
+    if item.kind() == CrdKind::CrdKindObject && crd.order() == 0 {
+        Ok(crd)
+    } else {
+        Err(CapTransferError::InvalidItem)
+    }
+}
+
+/// Returns the lowest free selector for received capabilities and marks it as used.
+fn next_received_cap_sel(used: &mut u64) -> Option<CapSel> {
+    let index = (0..RECEIVED_CAP_COUNT).find(|index| *used & (1 << index) == 0)?;
+    *used |= 1 << index;
+    Some(UserAppCapSpace::ReceivedCapBase.val() + index)
+}
+
+/// Marks the selector as free. Returns `false` if it isn't a used selector for received
+/// capabilities.
+fn free_received_cap_sel(used: &mut u64, sel: CapSel) -> bool {
+    let index = match sel.checked_sub(UserAppCapSpace::ReceivedCapBase.val()) {
+        Some(index) if index < RECEIVED_CAP_COUNT => index,
+        _ => return false,
+    };
+    let was_used = *used & (1 << index) != 0;
+    *used &= !(1 << index);
+    was_used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_received_cap_sel() {
+        let base = UserAppCapSpace::ReceivedCapBase.val();
+        let mut used = 0;
+        assert_eq!(next_received_cap_sel(&mut used), Some(base));
+        let sels = (1..RECEIVED_CAP_COUNT)
+            .map(|_| next_received_cap_sel(&mut used).unwrap())
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(
+            sels.last().copied(),
+            Some(UserAppCapSpace::ReceivedCapEnd.val())
+        );
+        assert_eq!(next_received_cap_sel(&mut used), None);
+        assert_eq!(used, !0);
+
+        // released selectors get reused, the lowest first
+        assert!(free_received_cap_sel(&mut used, base + 5));
+        assert!(free_received_cap_sel(&mut used, base + 2));
+        assert!(!free_received_cap_sel(&mut used, base + 2));
+        assert!(!free_received_cap_sel(&mut used, base - 1));
+        assert!(!free_received_cap_sel(
+            &mut used,
+            UserAppCapSpace::ReceivedCapEnd.val() + 1
+        ));
+        assert_eq!(next_received_cap_sel(&mut used), Some(base + 2));
+        assert_eq!(next_received_cap_sel(&mut used), Some(base + 5));
+        assert_eq!(next_received_cap_sel(&mut used), None);
+    }
+}
@@ -0,0 +1,34 @@
+//! Deterministic mode of the roottask, enabled by the boot argument `deterministic=on`
+//! (see [`crate::rt::boot_args`]). Two runs of the same workload then produce
+//! byte-comparable logs and traces, which makes it easy to diff them across builds.
+//!
+//! PIDs, and the selectors that derive from them, already count up from fixed values in
+//! the order of the launches. In deterministic mode, additionally:
+//! - log lines carry no timestamps, see [`crate::log_timestamp`]
+//! - all processes run on CPU 0, unless they request another CPU explicitly. Hence,
+//!   processes can't interleave differently on multiple CPUs, see [`crate::smp::next_cpu`]
+//! - the inodes of a process only depend on the files and sockets that the process itself
+//!   created before, see [`libfileserver::set_deterministic_inodes`]
+
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables the deterministic mode. Must be called before the roottask starts
+/// the userland. A later boot argument `log_timestamps=on` still enables the timestamps.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    libfileserver::set_deterministic_inodes(enabled);
+    if enabled {
+        crate::log_timestamp::set_enabled(false);
+        log::info!("deterministic mode: no timestamps, all processes on CPU 0");
+    }
+}
+
+/// Returns true if the roottask runs in deterministic mode.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,230 @@
+//! Byte quotas of the in-memory file system, see [`libfileserver::set_quota`]. The boot
+//! arguments `fs_quota=<size>` and `fs_process_quota=<size>` (see [`crate::rt::boot_args`])
+//! limit all files together and the files of each process. The size is a number of bytes
+//! with an optional suffix `K`, `M`, or `G`, e.g. `fs_process_quota=64M`, or `off`.
+//! Writes beyond a quota fail with `ENOSPC`.
+//!
+//! The current usage is readable at `/proc/fs/usage`, see [`FS_MOUNT_POINT`]. Each line is
+//! a key followed by values, separated by spaces: `quota_bytes` and `process_quota_bytes`
+//! (`off` without a quota), `total_bytes`, and one line `process <pid> <bytes>` per
+//! process that owns data.
+
+use alloc::boxed::Box;
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use libfileserver::{
+    FileStat,
+    FsBackend,
+    FsError,
+    FsQuota,
+    FsUsage,
+    INode,
+    FILESYSTEM,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+
+/// Where the usage appears in the file system.
+pub const FS_MOUNT_POINT: &str = "/proc/fs";
+
+/// The only file below [`FS_MOUNT_POINT`].
+const USAGE_FILE: &str = "/usage";
+
+/// [`INode`] of [`USAGE_FILE`].
+const USAGE_INODE: INode = INode::new(1);
+
+/// The file is a read-only regular file.
+const FILE_MODE: u32 = 0o100444;
+
+/// Mounts the usage file at [`FS_MOUNT_POINT`]. The heap must be initialized.
+pub fn init() {
+    FILESYSTEM
+        .lock()
+        .mount(FS_MOUNT_POINT, Box::new(FsUsageFs::new()))
+        .expect("the mount point of the file system usage must be free");
+}
+
+/// Sets the quota of all files together from the value of a boot argument. Returns false
+/// if the value is invalid.
+pub fn set_total_quota(value: &str) -> bool {
+    update_quota(value, |quota, bytes| quota.total_bytes = bytes)
+}
+
+/// Sets the quota of the files of each process from the value of a boot argument. Returns
+/// false if the value is invalid.
+pub fn set_process_quota(value: &str) -> bool {
+    update_quota(value, |quota, bytes| quota.process_bytes = bytes)
+}
+
+fn update_quota(value: &str, update: impl FnOnce(&mut FsQuota, Option<usize>)) -> bool {
+    let bytes = match parse_size(value) {
+        Some(bytes) => bytes,
+        None => return false,
+    };
+    let mut quota = libfileserver::usage().quota;
+    update(&mut quota, bytes);
+    libfileserver::set_quota(quota);
+    log::info!("file system quota: {:?}", quota);
+    true
+}
+
+/// Parses the value of a quota boot argument, see module description. `Some(None)` means
+/// no quota.
+fn parse_size(value: &str) -> Option<Option<usize>> {
+    if value == "off" {
+        return Some(None);
+    }
+    let (number, factor) = match value.as_bytes().last()? {
+        b'K' => (&value[..value.len() - 1], 1 << 10),
+        b'M' => (&value[..value.len() - 1], 1 << 20),
+        b'G' => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| number.checked_mul(factor))
+        .map(Some)
+}
+
+/// Returns the content of the usage file, see module description.
+fn render(usage: &FsUsage) -> String {
+    let quota = |bytes: Option<usize>| bytes.map_or_else(|| String::from("off"), |b| b.to_string());
+    let mut out = String::new();
+    writeln!(out, "quota_bytes {}", quota(usage.quota.total_bytes)).unwrap();
+    writeln!(
+        out,
+        "process_quota_bytes {}",
+        quota(usage.quota.process_bytes)
+    )
+    .unwrap();
+    writeln!(out, "total_bytes {}", usage.total_bytes).unwrap();
+    for (pid, bytes) in &usage.process_bytes {
+        writeln!(out, "process {} {}", pid, bytes).unwrap();
+    }
+    out
+}
+
+/// Read-only [`FsBackend`] with the usage file, see module description. Each open takes
+/// a new snapshot of the usage; all open handles share it.
+#[derive(Debug)]
+pub struct FsUsageFs {
+    /// Content of the usage file, taken during the last open.
+    snapshot: Vec<u8>,
+}
+
+impl FsUsageFs {
+    pub const fn new() -> Self {
+        Self {
+            snapshot: Vec::new(),
+        }
+    }
+}
+
+impl Default for FsUsageFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsBackend for FsUsageFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        _umode: u16,
+    ) -> Result<INode, FsError> {
+        if flags.can_write() {
+            return Err(FsError::Perm);
+        }
+        let i_node = self.lookup(path)?;
+        // the accounting has its own lock, hence this works while the file system is locked
+        self.snapshot = render(&libfileserver::usage()).into_bytes();
+        Ok(i_node)
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        if path == USAGE_FILE {
+            Ok(USAGE_INODE)
+        } else {
+            Err(FsError::NotFound)
+        }
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        // the file belongs to no process
+        self.stat(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        self.stat(i_node)?;
+        let from_index = offset.min(self.snapshot.len());
+        let to_index = (from_index + count).min(self.snapshot.len());
+        Ok(&self.snapshot[from_index..to_index])
+    }
+
+    fn write(&mut self, _i_node: INode, _offset: usize, _data: &[u8]) -> Result<usize, FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        if i_node != USAGE_INODE {
+            return Err(FsError::NotFound);
+        }
+        Ok(FileStat::new(
+            i_node.val(),
+            FILE_MODE,
+            self.snapshot.len() as i64,
+        ))
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        if USAGE_FILE.starts_with(dir) {
+            vec![String::from(USAGE_FILE)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Some(Some(4096)));
+        assert_eq!(parse_size("64K"), Some(Some(64 << 10)));
+        assert_eq!(parse_size("2G"), Some(Some(2 << 30)));
+        assert_eq!(parse_size("off"), Some(None));
+        assert_eq!(parse_size("M"), None);
+        assert_eq!(parse_size("-1"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let usage = FsUsage {
+            quota: FsQuota {
+                total_bytes: Some(1 << 20),
+                process_bytes: None,
+            },
+            total_bytes: 30,
+            process_bytes: BTreeMap::from([(3, 10), (5, 20)]),
+        };
+        assert_eq!(
+            render(&usage),
+            "quota_bytes 1048576\nprocess_quota_bytes off\ntotal_bytes 30\nprocess 3 10\nprocess 5 20\n"
+        );
+    }
+}
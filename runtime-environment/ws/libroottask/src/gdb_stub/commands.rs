@@ -0,0 +1,461 @@
+//! The commands of the GDB remote serial protocol that the stub supports. They work on a
+//! [`Target`], i.e. a stopped process, hence they are independent of Hedron.
+//!
+//! Supported: `?`, `g`/`G` (registers), `m`/`M` (memory), `c`/`s` (continue and step, with
+//! an optional address), `Z0`/`z0` (software breakpoints), `D` (detach), `k` (kill), and
+//! the queries that GDB sends when it connects. Everything else gets the empty reply,
+//! which tells GDB that the command is unsupported; GDB then falls back to the commands
+//! above, e.g. from `vCont` to `c` and `s`.
+
+use super::protocol::{
+    decode_hex,
+    parse_hex,
+    push_hex,
+    MAX_PACKET_SIZE,
+};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::vec::Vec;
+
+/// The `int3` instruction that replaces the first byte of the instruction at a software
+/// breakpoint.
+pub const INT3: u8 = 0xcc;
+
+/// Trap flag of RFLAGS; the CPU raises a debug exception after the next instruction.
+pub const RFLAGS_TF: u64 = 1 << 8;
+
+/// The registers in the order of the `g` packet of GDB for x86_64: the general purpose
+/// registers and RIP with 8 bytes each, followed by EFLAGS and the segment selectors with
+/// 4 bytes each. The registers of the FPU aren't transferred; GDB shows them as unavailable.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Registers {
+    /// RAX, RBX, RCX, RDX, RSI, RDI, RBP, RSP, R8 to R15, and RIP.
+    pub gprs: [u64; 17],
+    pub rflags: u64,
+    /// CS, SS, DS, ES, FS, and GS.
+    pub segments: [u32; 6],
+}
+
+impl Registers {
+    pub const RIP: usize = 16;
+
+    /// Size of the encoded registers in bytes.
+    const SIZE: usize = 17 * 8 + 7 * 4;
+
+    pub fn rip(&self) -> u64 {
+        self.gprs[Self::RIP]
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        for gpr in &self.gprs {
+            push_hex(out, &gpr.to_le_bytes());
+        }
+        push_hex(out, &(self.rflags as u32).to_le_bytes());
+        for segment in &self.segments {
+            push_hex(out, &segment.to_le_bytes());
+        }
+    }
+
+    /// Decodes the registers of a `G` packet. Keeps the upper half of RFLAGS.
+    fn decode(&mut self, hex: &[u8]) -> Option<()> {
+        let bytes = decode_hex(hex)?;
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        let (gprs, rest) = bytes.split_at(17 * 8);
+        for (gpr, bytes) in self.gprs.iter_mut().zip(gprs.chunks(8)) {
+            *gpr = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        let mut words = rest
+            .chunks(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+        self.rflags = (self.rflags & !0xffff_ffff) | words.next()? as u64;
+        for segment in &mut self.segments {
+            *segment = words.next()?;
+        }
+        Some(())
+    }
+}
+
+/// A stopped process.
+pub trait Target {
+    fn registers(&self) -> Registers;
+    fn set_registers(&mut self, regs: Registers);
+    /// Reads the memory at the user address or returns `None` if some of it isn't mapped.
+    fn read_memory(&self, u_addr: u64, len: usize) -> Option<Vec<u8>>;
+    /// Writes the memory at the user address, also if the process can only read it, e.g.
+    /// code. Returns false if some of it isn't mapped.
+    fn write_memory(&mut self, u_addr: u64, data: &[u8]) -> bool;
+}
+
+/// State of the debugging of a process that outlives its stops.
+#[derive(Debug, Default)]
+pub struct DebugState {
+    /// Software breakpoints by their address with the original byte of the instruction.
+    pub breakpoints: BTreeMap<u64, u8>,
+    /// The process executes a single instruction with the trap flag, see [`RFLAGS_TF`].
+    pub stepping: bool,
+    /// Address of the last fault that GDB saw. The process faults there again after the
+    /// stop, which then takes its usual course, see [`super::handle_exception`].
+    pub reported_fault: Option<u64>,
+}
+
+/// What the stub does after a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Sends the reply and waits for the next command.
+    Reply(Vec<u8>),
+    /// Lets the process run again. With `step`, it stops again after one instruction.
+    Resume { step: bool },
+    /// Ends the debugging; the process continues without breakpoints.
+    Detach,
+    /// Terminates the process.
+    Kill,
+}
+
+/// Executes a command of GDB on the stopped target. `signal` is the signal that
+/// stopped it.
+pub fn handle_command(
+    packet: &[u8],
+    target: &mut dyn Target,
+    state: &mut DebugState,
+    signal: u8,
+) -> Action {
+    let (&command, args) = match packet.split_first() {
+        Some(split) => split,
+        None => return Action::Reply(Vec::new()),
+    };
+    let reply = match command {
+        b'?' => Some(stop_reply(signal)),
+        b'g' => {
+            let mut reply = Vec::with_capacity(Registers::SIZE * 2);
+            target.registers().encode(&mut reply);
+            Some(reply)
+        }
+        b'G' => {
+            let mut regs = target.registers();
+            regs.decode(args).map(|_| {
+                target.set_registers(regs);
+                ok()
+            })
+        }
+        b'm' => parse_addr_len(args).and_then(|(addr, len)| {
+            let len = len.min(MAX_PACKET_SIZE / 2);
+            let data = read_memory(target, state, addr, len)?;
+            let mut reply = Vec::with_capacity(len * 2);
+            push_hex(&mut reply, &data);
+            Some(reply)
+        }),
+        b'M' => split_once(args, b':').and_then(|(range, hex)| {
+            let (addr, len) = parse_addr_len(range)?;
+            let data = decode_hex(hex).filter(|data| data.len() == len)?;
+            write_memory(target, state, addr, &data).then(ok)
+        }),
+        b'c' | b's' => {
+            if !args.is_empty() {
+                let addr = match parse_hex(args) {
+                    Some(addr) => addr,
+                    None => return Action::Reply(error()),
+                };
+                let mut regs = target.registers();
+                regs.gprs[Registers::RIP] = addr;
+                target.set_registers(regs);
+            }
+            return Action::Resume {
+                step: command == b's',
+            };
+        }
+        b'Z' | b'z' => match parse_breakpoint(args) {
+            // only software breakpoints; GDB falls back to writing the int3 itself
+            Some((0, addr)) if command == b'Z' => insert_breakpoint(target, state, addr),
+            Some((0, addr)) => remove_breakpoint(target, state, addr),
+            Some(_) => Some(Vec::new()),
+            None => None,
+        },
+        b'D' => return Action::Detach,
+        b'k' => return Action::Kill,
+        b'q' => Some(query(args)),
+        // all commands apply to the only thread
+        b'H' => Some(ok()),
+        b'T' => Some(ok()),
+        _ => Some(Vec::new()),
+    };
+    Action::Reply(reply.unwrap_or_else(error))
+}
+
+/// The reply when the target stopped because of the signal.
+pub fn stop_reply(signal: u8) -> Vec<u8> {
+    let mut reply = vec![b'S'];
+    push_hex(&mut reply, &[signal]);
+    reply
+}
+
+/// Removes all breakpoints from the memory of the target, e.g. before GDB detaches.
+pub fn remove_all_breakpoints(target: &mut dyn Target, state: &mut DebugState) {
+    for (addr, original) in core::mem::take(&mut state.breakpoints) {
+        target.write_memory(addr, &[original]);
+    }
+}
+
+fn query(args: &[u8]) -> Vec<u8> {
+    let name = split_once(args, b':').map_or(args, |(name, _)| name);
+    match name {
+        b"Supported" => format!("PacketSize={:x}", MAX_PACKET_SIZE).into_bytes(),
+        // the process existed before GDB attached; GDB detaches instead of killing it
+        b"Attached" => b"1".to_vec(),
+        b"C" => b"QC1".to_vec(),
+        b"fThreadInfo" => b"m1".to_vec(),
+        b"sThreadInfo" => b"l".to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// Reads memory as GDB expects it: without the int3 of the breakpoints of the stub.
+fn read_memory(target: &dyn Target, state: &DebugState, addr: u64, len: usize) -> Option<Vec<u8>> {
+    let mut data = target.read_memory(addr, len)?;
+    for (&bp_addr, &original) in state.breakpoints.range(addr..addr + len as u64) {
+        data[(bp_addr - addr) as usize] = original;
+    }
+    Some(data)
+}
+
+/// Writes memory and keeps the breakpoints of the stub in the written range.
+fn write_memory(target: &mut dyn Target, state: &mut DebugState, addr: u64, data: &[u8]) -> bool {
+    let mut data = data.to_vec();
+    for (&bp_addr, original) in state.breakpoints.range_mut(addr..addr + data.len() as u64) {
+        let byte = &mut data[(bp_addr - addr) as usize];
+        *original = *byte;
+        *byte = INT3;
+    }
+    target.write_memory(addr, &data)
+}
+
+fn insert_breakpoint(
+    target: &mut dyn Target,
+    state: &mut DebugState,
+    addr: u64,
+) -> Option<Vec<u8>> {
+    if !state.breakpoints.contains_key(&addr) {
+        let original = target.read_memory(addr, 1)?[0];
+        if !target.write_memory(addr, &[INT3]) {
+            return None;
+        }
+        state.breakpoints.insert(addr, original);
+    }
+    Some(ok())
+}
+
+fn remove_breakpoint(
+    target: &mut dyn Target,
+    state: &mut DebugState,
+    addr: u64,
+) -> Option<Vec<u8>> {
+    if let Some(original) = state.breakpoints.remove(&addr) {
+        target.write_memory(addr, &[original]).then(ok)
+    } else {
+        Some(ok())
+    }
+}
+
+/// Parses `<type>,<addr>,<kind>` of the breakpoint packets.
+fn parse_breakpoint(args: &[u8]) -> Option<(u64, u64)> {
+    let mut parts = args.split(|&byte| byte == b',');
+    let ty = parse_hex(parts.next()?)?;
+    let addr = parse_hex(parts.next()?)?;
+    Some((ty, addr))
+}
+
+/// Parses `<addr>,<len>`.
+fn parse_addr_len(args: &[u8]) -> Option<(u64, usize)> {
+    let (addr, len) = split_once(args, b',')?;
+    Some((parse_hex(addr)?, parse_hex(len)? as usize))
+}
+
+fn split_once(bytes: &[u8], separator: u8) -> Option<(&[u8], &[u8])> {
+    let index = bytes.iter().position(|&byte| byte == separator)?;
+    Some((&bytes[..index], &bytes[index + 1..]))
+}
+
+fn ok() -> Vec<u8> {
+    b"OK".to_vec()
+}
+
+/// The reply to a command that failed. GDB doesn't interpret the number.
+fn error() -> Vec<u8> {
+    b"E01".to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Memory of one page at 0x1000 and registers.
+    struct FakeTarget {
+        regs: Registers,
+        memory: Vec<u8>,
+    }
+
+    impl FakeTarget {
+        const BASE: u64 = 0x1000;
+
+        fn new() -> Self {
+            let mut regs = Registers::default();
+            regs.gprs[Registers::RIP] = 0x1010;
+            regs.rflags = 0x202;
+            Self {
+                regs,
+                memory: (0..4096).map(|i| i as u8).collect(),
+            }
+        }
+
+        fn range(&self, u_addr: u64, len: usize) -> Option<core::ops::Range<usize>> {
+            let begin = u_addr.checked_sub(Self::BASE)? as usize;
+            (begin + len <= self.memory.len()).then(|| begin..begin + len)
+        }
+    }
+
+    impl Target for FakeTarget {
+        fn registers(&self) -> Registers {
+            self.regs
+        }
+        fn set_registers(&mut self, regs: Registers) {
+            self.regs = regs;
+        }
+        fn read_memory(&self, u_addr: u64, len: usize) -> Option<Vec<u8>> {
+            Some(self.memory[self.range(u_addr, len)?].to_vec())
+        }
+        fn write_memory(&mut self, u_addr: u64, data: &[u8]) -> bool {
+            match self.range(u_addr, data.len()) {
+                Some(range) => {
+                    self.memory[range].copy_from_slice(data);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    fn reply(action: Action) -> Vec<u8> {
+        match action {
+            Action::Reply(reply) => reply,
+            action => panic!("unexpected {:?}", action),
+        }
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut target = FakeTarget::new();
+        let mut state = DebugState::default();
+        let regs = reply(handle_command(b"g", &mut target, &mut state, 5));
+        assert_eq!(regs.len(), Registers::SIZE * 2);
+        // RIP and EFLAGS
+        assert_eq!(&regs[16 * 16..17 * 16], b"1010000000000000");
+        assert_eq!(&regs[17 * 16..17 * 16 + 8], b"02020000");
+
+        let mut regs = regs;
+        regs[0..2].copy_from_slice(b"2a");
+        let packet = [b"G".as_slice(), &regs].concat();
+        assert_eq!(
+            reply(handle_command(&packet, &mut target, &mut state, 5)),
+            b"OK"
+        );
+        assert_eq!(target.regs.gprs[0], 0x2a);
+        assert_eq!(target.regs.rip(), 0x1010);
+        assert_eq!(
+            reply(handle_command(b"G00", &mut target, &mut state, 5)),
+            b"E01"
+        );
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut target = FakeTarget::new();
+        let mut state = DebugState::default();
+        assert_eq!(
+            reply(handle_command(b"m1004,3", &mut target, &mut state, 5)),
+            b"040506"
+        );
+        assert_eq!(
+            reply(handle_command(b"M1004,2:aabb", &mut target, &mut state, 5)),
+            b"OK"
+        );
+        assert_eq!(&target.memory[4..6], &[0xaa, 0xbb]);
+        // not mapped
+        assert_eq!(
+            reply(handle_command(b"m0,4", &mut target, &mut state, 5)),
+            b"E01"
+        );
+        // length and data differ
+        assert_eq!(
+            reply(handle_command(b"M1004,3:aabb", &mut target, &mut state, 5)),
+            b"E01"
+        );
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut target = FakeTarget::new();
+        let mut state = DebugState::default();
+        assert_eq!(
+            reply(handle_command(b"Z0,1010,1", &mut target, &mut state, 5)),
+            b"OK"
+        );
+        assert_eq!(target.memory[0x10], INT3);
+        // GDB sees the original instruction
+        assert_eq!(
+            reply(handle_command(b"m100f,2", &mut target, &mut state, 5)),
+            b"0f10"
+        );
+        // hardware breakpoints aren't supported
+        assert_eq!(
+            reply(handle_command(b"Z1,1010,1", &mut target, &mut state, 5)),
+            b""
+        );
+        assert_eq!(
+            reply(handle_command(b"z0,1010,1", &mut target, &mut state, 5)),
+            b"OK"
+        );
+        assert_eq!(target.memory[0x10], 0x10);
+
+        handle_command(b"Z0,1020,1", &mut target, &mut state, 5);
+        remove_all_breakpoints(&mut target, &mut state);
+        assert_eq!(target.memory[0x20], 0x20);
+        assert!(state.breakpoints.is_empty());
+    }
+
+    #[test]
+    fn test_execution() {
+        let mut target = FakeTarget::new();
+        let mut state = DebugState::default();
+        assert_eq!(
+            reply(handle_command(b"?", &mut target, &mut state, 5)),
+            b"S05"
+        );
+        assert_eq!(
+            handle_command(b"c", &mut target, &mut state, 5),
+            Action::Resume { step: false }
+        );
+        assert_eq!(
+            handle_command(b"s1020", &mut target, &mut state, 5),
+            Action::Resume { step: true }
+        );
+        assert_eq!(target.regs.rip(), 0x1020);
+        assert_eq!(
+            handle_command(b"D", &mut target, &mut state, 5),
+            Action::Detach
+        );
+        assert_eq!(
+            reply(handle_command(
+                b"qSupported:multiprocess+",
+                &mut target,
+                &mut state,
+                5
+            )),
+            b"PacketSize=1000"
+        );
+        assert_eq!(
+            reply(handle_command(b"vCont?", &mut target, &mut state, 5)),
+            b""
+        );
+    }
+}
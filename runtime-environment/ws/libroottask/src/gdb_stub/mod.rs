@@ -0,0 +1,434 @@
+//! Stub for the GDB remote serial protocol over the serial port. GDB on the host debugs a
+//! user process that was launched for debugging, see the `debug` flag of
+//! [`libhrstd::rt::services::process::ProcessServiceRequest::Launch`]; the shell does
+//! this for `gdb PROG [ARG...]`. GDB connects to the serial port of the machine, e.g.
+//! `target remote /dev/ttyUSB0` or, with QEMU, to its `-serial pty`.
+//!
+//! The stub runs on the local EC that handles the exception. A debugged process stops
+//! before its first instruction (see [`handle_startup`]), at breakpoints and after single
+//! steps, and at faults (see [`handle_exception`]). While the process is stopped, the stub
+//! executes the commands of GDB (see [`commands`]) on the registers in the UTCB of the
+//! exception and on the memory of the process; when GDB continues, the roottask replies
+//! to the exception with the changed registers. Breakpoints are `int3` instructions in the
+//! memory of the process. Single steps use the trap flag.
+//!
+//! The exception handlers only defer the stop. The local EC runs the session after it
+//! released the lock of the process manager, see [`run_deferred_stop`], and takes the lock
+//! again only for each command. Between the polls of the serial port, it sleeps without
+//! any lock, hence the other processes and services go on while GDB takes its time. Only
+//! the stub reads from the serial port during a session. The output of the processes and
+//! the log of the roottask go over the same serial port; GDB ignores what isn't a packet.
+//! Because Hedron can't interrupt a running process, GDB can't stop it with Ctrl+C; use
+//! breakpoints instead.
+
+mod commands;
+mod protocol;
+
+pub use commands::*;
+pub use protocol::*;
+
+use crate::process::{
+    kill_process,
+    Process,
+    SigNum,
+    PROCESS_MNG,
+    SIGKILL,
+    SIGTRAP,
+};
+use crate::services::foreign_syscall::sleep_until;
+use crate::services::stdout;
+use crate::time;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use libhrstd::kobjects::PtObject;
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::ExceptionEventOffset;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Time between two polls of the serial port while a process is stopped.
+const POLL_INTERVAL_NS: u64 = 1_000_000;
+
+/// The processes that are debugged. The state of a process is taken out while it is
+/// stopped.
+static DEBUGGED: SimpleMutex<BTreeMap<ProcessId, DebugState>> = SimpleMutex::new(BTreeMap::new());
+
+/// Stops that wait until the local EC released the lock of the process manager, see
+/// [`run_deferred_stop`]. Key is the capability selector of the local EC.
+static DEFERRED_STOPS: SimpleMutex<BTreeMap<CapSel, DeferredStop>> =
+    SimpleMutex::new(BTreeMap::new());
+
+/// Whether a session reads from the serial port, see [`Link`].
+static SERIAL_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// A stop of a process whose session didn't start yet.
+struct DeferredStop {
+    pid: ProcessId,
+    /// The registers of the exception in the UTCB of the local EC. The UTCB stays valid,
+    /// because the local EC handles no other call until it replied to the exception.
+    utcb_exc: *mut UtcbDataException,
+    state: DebugState,
+    sig: SigNum,
+    report_stop: bool,
+}
+
+/// Debugs the process from its first instruction on. Must be called before it starts.
+pub fn register(pid: ProcessId) {
+    log::debug!("pid={} will wait for GDB when it starts", pid);
+    DEBUGGED.lock().insert(pid, DebugState::default());
+}
+
+/// Tells GDB that the process exited, if it is debugged. Called when the process stops.
+pub fn process_exited(pid: ProcessId, status: i32) {
+    if DEBUGGED.lock().remove(&pid).is_some() {
+        let mut reply = vec![b'W'];
+        push_hex(&mut reply, &[status as u8]);
+        // never waits for a session, because the main EC calls this
+        stdout::writer_mut().write_serial_bytes(&encode_packet(&reply));
+    }
+}
+
+/// Returns true while a stopped process waits for GDB. Only the stub reads from the serial
+/// port in the meantime.
+pub fn serial_claimed() -> bool {
+    SERIAL_CLAIMED.load(Ordering::Acquire)
+}
+
+/// Lets GDB take control of the process before its first instruction, if the process is
+/// debugged. Called by the handler of the startup exception after it prepared the UTCB.
+/// The session runs before the reply, see [`run_deferred_stop`].
+pub fn handle_startup(pt: &PtObject, process: &Process, utcb_exc: &mut UtcbDataException) {
+    let state = match DEBUGGED.lock().remove(&process.pid()) {
+        Some(state) => state,
+        None => return,
+    };
+    log::info!("pid={} waits for GDB", process.pid());
+    // GDB asks for the reason of the stop when it connects
+    defer_stop(pt, process, utcb_exc, state, SIGTRAP, false);
+}
+
+/// Stops the process at the exception, if the process is debugged, and lets GDB take
+/// control before the reply, see [`run_deferred_stop`]. Returns true if the exception is
+/// handled.
+///
+/// After a fault, the process continues as it would without GDB: the reply leaves the
+/// registers as they are, unless GDB changes them, hence the process faults again at the
+/// same address. Then, this returns false and the fault takes its usual course, i.e. the
+/// process gets the signal of the fault or is terminated.
+pub fn handle_exception(
+    pt: &PtObject,
+    exc: ExceptionEventOffset,
+    process: &Process,
+    utcb_exc: &mut UtcbDataException,
+    sig: SigNum,
+) -> bool {
+    let mut state = match DEBUGGED.lock().remove(&process.pid()) {
+        Some(state) => state,
+        None => return false,
+    };
+    match exc {
+        ExceptionEventOffset::BreakpointTrap => {
+            // continues at the original instruction when the breakpoint is removed
+            if state.breakpoints.contains_key(&(utcb_exc.rip - 1)) {
+                utcb_exc.rip -= 1;
+            }
+        }
+        ExceptionEventOffset::DebugTrap if state.stepping => {
+            utcb_exc.rflags &= !RFLAGS_TF;
+            state.stepping = false;
+        }
+        _ if state.reported_fault == Some(utcb_exc.rip) => {
+            state.reported_fault = None;
+            DEBUGGED.lock().insert(process.pid(), state);
+            return false;
+        }
+        _ => state.reported_fault = Some(utcb_exc.rip),
+    }
+    log::debug!("pid={} stops at {:?} for GDB", process.pid(), exc);
+    utcb_exc.mtd = Mtd::empty();
+    defer_stop(pt, process, utcb_exc, state, sig, true);
+    true
+}
+
+/// Runs the session of the stop that [`handle_startup`] or [`handle_exception`] deferred
+/// on the local EC, if there is one, and prepares the UTCB for the reply. Must be called
+/// after the local EC released the lock of the process manager and before it replies.
+pub fn run_deferred_stop(local_ec_sel: CapSel) {
+    let stop = match DEFERRED_STOPS.lock().remove(&local_ec_sel) {
+        Some(stop) => stop,
+        None => return,
+    };
+    let utcb_exc = unsafe { &mut *stop.utcb_exc };
+    let mut state = stop.state;
+    let action = run_session(stop.pid, utcb_exc, &mut state, stop.sig, stop.report_stop);
+    let mng = PROCESS_MNG.lock();
+    match mng.lookup_process(stop.pid) {
+        Some(process) => finish_stop(process, utcb_exc, state, action),
+        // killed while it was stopped; it never runs again
+        None => utcb_exc.mtd = Mtd::empty(),
+    }
+}
+
+/// Lets the local EC of the portal run the session before it replies to the exception.
+fn defer_stop(
+    pt: &PtObject,
+    process: &Process,
+    utcb_exc: &mut UtcbDataException,
+    state: DebugState,
+    sig: SigNum,
+    report_stop: bool,
+) {
+    let stop = DeferredStop {
+        pid: process.pid(),
+        utcb_exc,
+        state,
+        sig,
+        report_stop,
+    };
+    DEFERRED_STOPS.lock().insert(pt.local_ec().ec_sel(), stop);
+}
+
+/// Prepares the UTCB for the reply to the exception according to the last command.
+fn finish_stop(
+    process: &Process,
+    utcb_exc: &mut UtcbDataException,
+    mut state: DebugState,
+    action: Action,
+) {
+    utcb_exc.mtd |=
+        Mtd::GPR_ACDB | Mtd::GPR_BSD | Mtd::GPR_R8_R15 | Mtd::RSP | Mtd::RIP_LEN | Mtd::RFLAGS;
+    match action {
+        Action::Resume { step } => {
+            if step {
+                utcb_exc.rflags |= RFLAGS_TF;
+            } else {
+                utcb_exc.rflags &= !RFLAGS_TF;
+            }
+            state.stepping = step;
+            DEBUGGED.lock().insert(process.pid(), state);
+        }
+        Action::Detach => {
+            remove_all_breakpoints(&mut ProcessTarget { process, utcb_exc }, &mut state);
+            utcb_exc.rflags &= !RFLAGS_TF;
+            log::info!("GDB detached from pid={}", process.pid());
+        }
+        Action::Kill => {
+            kill_process(process.pid(), SIGKILL);
+            // the process faults again until it is stopped
+            utcb_exc.mtd = Mtd::empty();
+        }
+        Action::Reply(_) => unreachable!("replies don't end a stop"),
+    }
+}
+
+/// Executes the commands of GDB until it resumes, detaches, or kills the process. Sends the
+/// reason of the stop first, if GDB waits for it. Holds the lock of the process manager
+/// only while it executes a command.
+fn run_session(
+    pid: ProcessId,
+    utcb_exc: &mut UtcbDataException,
+    state: &mut DebugState,
+    sig: SigNum,
+    report_stop: bool,
+) -> Action {
+    let mut link = Link::new();
+    if report_stop {
+        link.send(&stop_reply(sig as u8));
+    }
+    loop {
+        match link.next_event() {
+            Event::Packet(packet) => {
+                link.write(b"+");
+                let action = {
+                    let mng = PROCESS_MNG.lock();
+                    mng.lookup_process(pid).map(|process| {
+                        let mut target = ProcessTarget {
+                            process,
+                            utcb_exc: &mut *utcb_exc,
+                        };
+                        handle_command(&packet, &mut target, state, sig as u8)
+                    })
+                };
+                let action = match action {
+                    Some(action) => action,
+                    None => {
+                        // killed while it was stopped
+                        let mut reply = vec![b'X'];
+                        push_hex(&mut reply, &[SIGKILL as u8]);
+                        link.send(&reply);
+                        return Action::Kill;
+                    }
+                };
+                match action {
+                    Action::Reply(reply) => link.send(&reply),
+                    Action::Detach => {
+                        link.send(b"OK");
+                        return Action::Detach;
+                    }
+                    // GDB expects no reply to these
+                    action => return action,
+                }
+            }
+            Event::BadChecksum => link.write(b"-"),
+            Event::Nack => link.write(&link.last_packet),
+            // the process is stopped already
+            Event::Ack | Event::Interrupt => {}
+        }
+    }
+}
+
+/// The serial port, claimed while the process is stopped. Takes the lock of the stdout
+/// writer only for each access. Sessions of other stopped processes wait until the claim
+/// ends.
+struct Link {
+    reader: PacketReader,
+    /// Sent again if GDB didn't receive it correctly.
+    last_packet: Vec<u8>,
+}
+
+impl Link {
+    fn new() -> Self {
+        while SERIAL_CLAIMED.swap(true, Ordering::Acquire) {
+            sleep_until(time::tsc_now() + time::ns_to_ticks(POLL_INTERVAL_NS));
+        }
+        Self {
+            reader: PacketReader::new(),
+            last_packet: Vec::new(),
+        }
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        stdout::writer_mut().write_serial_bytes(bytes);
+    }
+
+    fn send(&mut self, data: &[u8]) {
+        self.last_packet = encode_packet(data);
+        self.write(&self.last_packet);
+    }
+
+    /// Blocks until the next event; GDB may take its time. Sleeps between the polls.
+    fn next_event(&mut self) -> Event {
+        loop {
+            let byte = stdout::writer_mut().try_read_byte();
+            match byte {
+                Some(byte) => {
+                    if let Some(event) = self.reader.feed(byte) {
+                        return event;
+                    }
+                }
+                None => sleep_until(time::tsc_now() + time::ns_to_ticks(POLL_INTERVAL_NS)),
+            }
+        }
+    }
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        SERIAL_CLAIMED.store(false, Ordering::Release);
+    }
+}
+
+/// A stopped process with the registers of its exception.
+struct ProcessTarget<'a> {
+    process: &'a Process,
+    utcb_exc: &'a mut UtcbDataException,
+}
+
+impl<'a> ProcessTarget<'a> {
+    /// Copies between the memory of the process and a buffer of `len` bytes. `f` gets the
+    /// memory of the process and the offset in the buffer for each mapping in the range.
+    fn access_memory(&self, u_addr: u64, len: usize, mut f: impl FnMut(&mut [u8], usize)) -> bool {
+        let mut done = 0;
+        while done < len {
+            let chunk = with_user_memory(self.process, u_addr + done as u64, |memory| {
+                let chunk = memory.len().min(len - done);
+                f(&mut memory[..chunk], done);
+                chunk
+            });
+            match chunk {
+                Some(chunk) => done += chunk,
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+impl<'a> Target for ProcessTarget<'a> {
+    fn registers(&self) -> Registers {
+        let utcb = &self.utcb_exc;
+        Registers {
+            gprs: [
+                utcb.rax, utcb.rbx, utcb.rcx, utcb.rdx, utcb.rsi, utcb.rdi, utcb.rbp, utcb.rsp,
+                utcb.r8, utcb.r9, utcb.r10, utcb.r11, utcb.r12, utcb.r13, utcb.r14, utcb.r15,
+                utcb.rip,
+            ],
+            rflags: utcb.rflags,
+            segments: [
+                utcb.cs.sel as u32,
+                utcb.ss.sel as u32,
+                utcb.ds.sel as u32,
+                utcb.es.sel as u32,
+                utcb.fs.sel as u32,
+                utcb.gs.sel as u32,
+            ],
+        }
+    }
+
+    /// The segment selectors can't be changed.
+    fn set_registers(&mut self, regs: Registers) {
+        let utcb = &mut self.utcb_exc;
+        let [rax, rbx, rcx, rdx, rsi, rdi, rbp, rsp, r8, r9, r10, r11, r12, r13, r14, r15, rip] =
+            regs.gprs;
+        utcb.rax = rax;
+        utcb.rbx = rbx;
+        utcb.rcx = rcx;
+        utcb.rdx = rdx;
+        utcb.rsi = rsi;
+        utcb.rdi = rdi;
+        utcb.rbp = rbp;
+        utcb.rsp = rsp;
+        utcb.r8 = r8;
+        utcb.r9 = r9;
+        utcb.r10 = r10;
+        utcb.r11 = r11;
+        utcb.r12 = r12;
+        utcb.r13 = r13;
+        utcb.r14 = r14;
+        utcb.r15 = r15;
+        utcb.rip = rip;
+        utcb.rflags = regs.rflags;
+    }
+
+    fn read_memory(&self, u_addr: u64, len: usize) -> Option<Vec<u8>> {
+        let mut data = vec![0; len];
+        self.access_memory(u_addr, len, |memory, offset| {
+            data[offset..][..memory.len()].copy_from_slice(memory)
+        })
+        .then(|| data)
+    }
+
+    fn write_memory(&mut self, u_addr: u64, data: &[u8]) -> bool {
+        self.access_memory(u_addr, data.len(), |memory, offset| {
+            let len = memory.len();
+            memory.copy_from_slice(&data[offset..][..len])
+        })
+    }
+}
+
+/// Calls `f` with the memory from the user address up to the end of its mapping, as the
+/// roottask accesses it. Returns `None` if the address isn't mapped.
+fn with_user_memory<R>(
+    process: &Process,
+    u_addr: u64,
+    f: impl FnOnce(&mut [u8]) -> R,
+) -> Option<R> {
+    let mut memory_manager = process.memory_manager_mut();
+    memory_manager.user_memory_mut(u_addr).map(f)
+}
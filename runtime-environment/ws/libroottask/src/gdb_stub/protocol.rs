@@ -0,0 +1,220 @@
+//! Framing of the GDB remote serial protocol. A packet is `$<data>#<checksum>`, where the
+//! checksum is the sum of the data bytes modulo 256 as two hex digits. The receiver
+//! acknowledges each packet with `+` or requests it again with `-`. The bytes `$`, `#`,
+//! `}`, and `*` within the data are escaped by `}` followed by the byte XOR 0x20.
+
+use alloc::vec::Vec;
+
+/// Byte that GDB sends to interrupt the running program (Ctrl+C).
+pub const INTERRUPT: u8 = 0x03;
+
+/// Maximum size of the data of a packet. The stub announces it via `qSupported`.
+pub const MAX_PACKET_SIZE: usize = 4096;
+
+const ESCAPE: u8 = b'}';
+
+/// Something that [`PacketReader`] received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A packet with a valid checksum. The data is unescaped.
+    Packet(Vec<u8>),
+    /// A packet with a wrong checksum; the sender must repeat it.
+    BadChecksum,
+    /// The acknowledgement of the last sent packet.
+    Ack,
+    /// The request to send the last packet again.
+    Nack,
+    Interrupt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ReadState {
+    /// Waits for the begin of a packet; all other bytes are ignored.
+    Idle,
+    Data,
+    /// The last byte was an [`ESCAPE`].
+    Escape,
+    Checksum1,
+    Checksum2(u8),
+}
+
+/// Assembles the [`Event`]s from the received bytes.
+#[derive(Debug)]
+pub struct PacketReader {
+    state: ReadState,
+    data: Vec<u8>,
+    /// Sum of the bytes of the packet as they arrive, i.e. before unescaping.
+    sum: u8,
+}
+
+impl PacketReader {
+    pub const fn new() -> Self {
+        Self {
+            state: ReadState::Idle,
+            data: Vec::new(),
+            sum: 0,
+        }
+    }
+
+    /// Processes the next received byte. Returns an event if the byte completes one.
+    pub fn feed(&mut self, byte: u8) -> Option<Event> {
+        match self.state {
+            ReadState::Idle => match byte {
+                b'$' => {
+                    self.data.clear();
+                    self.sum = 0;
+                    self.state = ReadState::Data;
+                    None
+                }
+                b'+' => Some(Event::Ack),
+                b'-' => Some(Event::Nack),
+                INTERRUPT => Some(Event::Interrupt),
+                _ => None,
+            },
+            ReadState::Data => {
+                match byte {
+                    b'#' => {
+                        self.state = ReadState::Checksum1;
+                        return None;
+                    }
+                    ESCAPE => self.state = ReadState::Escape,
+                    _ => self.push(byte),
+                }
+                self.sum = self.sum.wrapping_add(byte);
+                None
+            }
+            ReadState::Escape => {
+                self.sum = self.sum.wrapping_add(byte);
+                self.state = ReadState::Data;
+                self.push(byte ^ 0x20);
+                None
+            }
+            ReadState::Checksum1 => {
+                self.state = ReadState::Checksum2(byte);
+                None
+            }
+            ReadState::Checksum2(high) => {
+                self.state = ReadState::Idle;
+                let checksum = parse_hex(&[high, byte]).map(|val| val as u8);
+                if checksum == Some(self.sum) {
+                    Some(Event::Packet(core::mem::take(&mut self.data)))
+                } else {
+                    Some(Event::BadChecksum)
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        // GDB respects the announced size; drop the excess of broken packets
+        if self.data.len() < MAX_PACKET_SIZE {
+            self.data.push(byte);
+        }
+    }
+}
+
+impl Default for PacketReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frames the data as packet, including the escapes and the checksum.
+pub fn encode_packet(data: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(data.len() + 4);
+    packet.push(b'$');
+    let mut sum = 0_u8;
+    for &byte in data {
+        if matches!(byte, b'$' | b'#' | ESCAPE | b'*') {
+            packet.push(ESCAPE);
+            packet.push(byte ^ 0x20);
+            sum = sum.wrapping_add(ESCAPE).wrapping_add(byte ^ 0x20);
+        } else {
+            packet.push(byte);
+            sum = sum.wrapping_add(byte);
+        }
+    }
+    packet.push(b'#');
+    push_hex(&mut packet, &[sum]);
+    packet
+}
+
+/// Appends the bytes as pairs of lowercase hex digits.
+pub fn push_hex(out: &mut Vec<u8>, bytes: &[u8]) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for byte in bytes {
+        out.push(DIGITS[(byte >> 4) as usize]);
+        out.push(DIGITS[(byte & 0xf) as usize]);
+    }
+}
+
+/// Decodes pairs of hex digits into bytes.
+pub fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| parse_hex(pair).map(|val| val as u8))
+        .collect()
+}
+
+/// Parses a big-endian hex number, like the addresses and lengths in packets.
+pub fn parse_hex(hex: &[u8]) -> Option<u64> {
+    if hex.is_empty() || hex.len() > 16 {
+        return None;
+    }
+    hex.iter().try_fold(0, |val, &digit| {
+        let digit = (digit as char).to_digit(16)?;
+        Some(val << 4 | digit as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(reader: &mut PacketReader, bytes: &[u8]) -> Vec<Event> {
+        bytes.iter().filter_map(|byte| reader.feed(*byte)).collect()
+    }
+
+    #[test]
+    fn test_encode_packet() {
+        assert_eq!(encode_packet(b"OK"), b"$OK#9a");
+        assert_eq!(encode_packet(b""), b"$#00");
+        // '#' is escaped as "}\x03"
+        assert_eq!(encode_packet(b"a#"), b"$a}\x03#e1");
+    }
+
+    #[test]
+    fn test_packet_reader() {
+        let mut reader = PacketReader::new();
+        assert_eq!(
+            feed_all(&mut reader, b"junk+$OK#9a-\x03"),
+            vec![
+                Event::Ack,
+                Event::Packet(b"OK".to_vec()),
+                Event::Nack,
+                Event::Interrupt
+            ]
+        );
+        assert_eq!(feed_all(&mut reader, b"$OK#00"), vec![Event::BadChecksum]);
+        // the escapes of encode_packet are undone
+        assert_eq!(
+            feed_all(&mut reader, &encode_packet(b"M0,1:}*$#")),
+            vec![Event::Packet(b"M0,1:}*$#".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(parse_hex(b"401a2f"), Some(0x401a2f));
+        assert_eq!(parse_hex(b"FF"), Some(0xff));
+        assert_eq!(parse_hex(b""), None);
+        assert_eq!(parse_hex(b"xy"), None);
+        assert_eq!(decode_hex(b"00ff10"), Some(vec![0, 0xff, 0x10]));
+        assert_eq!(decode_hex(b"0"), None);
+        let mut out = Vec::new();
+        push_hex(&mut out, &[0xde, 0xad, 0x01]);
+        assert_eq!(out, b"dead01");
+    }
+}
@@ -0,0 +1,131 @@
+//! Boot-time check of the API of the running Hedron kernel. The roottask relies on a
+//! forked Hedron. Instead of failing deep inside a syscall wrapper with a cryptic status
+//! code, the roottask checks the HIP once during boot against a capability matrix.
+//! Missing required features stop the boot with a clear message. Missing optional
+//! features get disabled and logged.
+
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering,
+};
+use libhrstd::libhedron::consts::{
+    HEDRON_FORK_API_VERSION,
+    HEDRON_PD_CTRL_DELEGATE_API_VERSION,
+    NUM_EXC,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::HipFeatureFlags;
+use libhrstd::libhedron::HIP;
+
+/// Features of the running kernel. Set once by [`init`].
+static FEATURES: AtomicU32 = AtomicU32::new(0);
+
+bitflags::bitflags! {
+    /// Features of the running Hedron kernel that the roottask cares about.
+    pub struct HedronFeatures: u32 {
+        /// `pd_ctrl_delegate` sub-operation. Required.
+        const PD_CTRL_DELEGATE = 1 << 0;
+        /// Foreign system calls of the forked Hedron. Required to run Linux processes.
+        const FOREIGN_SYSCALLS = 1 << 1;
+        /// vCPUs with Intel VMX.
+        const VCPU_VMX = 1 << 2;
+        /// vCPUs with AMD SVM.
+        const VCPU_SVM = 1 << 3;
+        /// IOMMU is active.
+        const IOMMU = 1 << 4;
+    }
+}
+
+impl HedronFeatures {
+    /// Features without which the roottask can't work at all.
+    pub const REQUIRED: Self = Self::PD_CTRL_DELEGATE;
+
+    /// Calculates the capability matrix from the values of the HIP.
+    pub fn detect(api_ver: u32, api_flg: HipFeatureFlags) -> Self {
+        let mut features = Self::empty();
+        features.set(
+            Self::PD_CTRL_DELEGATE,
+            api_ver >= HEDRON_PD_CTRL_DELEGATE_API_VERSION,
+        );
+        features.set(Self::FOREIGN_SYSCALLS, api_ver >= HEDRON_FORK_API_VERSION);
+        features.set(Self::VCPU_VMX, api_flg.contains(HipFeatureFlags::VMX));
+        features.set(Self::VCPU_SVM, api_flg.contains(HipFeatureFlags::SVM));
+        features.set(Self::IOMMU, api_flg.contains(HipFeatureFlags::IOM));
+        features
+    }
+}
+
+/// Checks the HIP and stores the features of the running kernel. Panics with a clear
+/// message if the kernel can't run the roottask.
+pub fn init(hip: &HIP) {
+    let api_ver = hip.api_ver();
+    log::info!(
+        "Hedron API version {}.{} (roottask built for {}.{})",
+        api_ver / 1000,
+        api_ver % 1000,
+        HEDRON_FORK_API_VERSION / 1000,
+        HEDRON_FORK_API_VERSION % 1000
+    );
+    if api_ver / 1000 > HEDRON_FORK_API_VERSION / 1000 {
+        log::warn!("newer major API version of Hedron; this combination is untested");
+    }
+
+    // these are compile time constants of the roottask; mismatches break things silently
+    assert!(
+        hip.num_exc_sel() as usize >= NUM_EXC,
+        "Hedron provides {} exception selectors but the roottask needs {}",
+        hip.num_exc_sel(),
+        NUM_EXC
+    );
+    // bit n stands for UTCBs with a size of 2^n bytes
+    assert_ne!(
+        hip.cfg_utcb() & PAGE_SIZE as u32,
+        0,
+        "Hedron doesn't support UTCBs with a size of {} bytes",
+        PAGE_SIZE
+    );
+
+    let features = HedronFeatures::detect(api_ver, hip.api_flg());
+    let missing = HedronFeatures::REQUIRED - features;
+    assert!(
+        missing.is_empty(),
+        "the running Hedron lacks required features: {:?}",
+        missing
+    );
+
+    for feature in [
+        HedronFeatures::FOREIGN_SYSCALLS,
+        HedronFeatures::VCPU_VMX,
+        HedronFeatures::VCPU_SVM,
+        HedronFeatures::IOMMU,
+    ] {
+        if features.contains(feature) {
+            log::debug!("Hedron feature {:?}: available", feature);
+        } else {
+            log::info!("Hedron feature {:?}: not available - disabled", feature);
+        }
+    }
+
+    FEATURES.store(features.bits(), Ordering::SeqCst);
+}
+
+/// Returns true if the running kernel supports all given features. Only valid after [`init`].
+pub fn is_supported(features: HedronFeatures) -> bool {
+    HedronFeatures::from_bits_truncate(FEATURES.load(Ordering::SeqCst)).contains(features)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let features = HedronFeatures::detect(HEDRON_FORK_API_VERSION, HipFeatureFlags::VMX);
+        assert!(features.contains(HedronFeatures::REQUIRED | HedronFeatures::FOREIGN_SYSCALLS));
+        assert!(features.contains(HedronFeatures::VCPU_VMX));
+        assert!(!features.contains(HedronFeatures::VCPU_SVM));
+
+        let features = HedronFeatures::detect(0, HipFeatureFlags::empty());
+        assert!(features.is_empty());
+    }
+}
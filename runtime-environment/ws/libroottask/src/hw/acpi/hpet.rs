@@ -0,0 +1,94 @@
+//! The table of the high precision event timer (HPET, signature "HPET").
+
+use super::tables::{
+    read_u16,
+    read_u32,
+    read_u64,
+    AcpiError,
+    SdtHeader,
+    SDT_HEADER_SIZE,
+};
+
+/// Address space ID of memory in a generic address structure.
+const GAS_SYSTEM_MEMORY: u8 = 0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Hpet {
+    pub hardware_revision: u8,
+    /// Number of comparators, i.e. timers, of the first block.
+    pub comparator_count: u8,
+    pub counter_64bit: bool,
+    /// The HPET can replace the PIT and the RTC interrupt.
+    pub legacy_replacement: bool,
+    pub pci_vendor_id: u16,
+    /// Physical address of the registers.
+    pub address: u64,
+    /// Sequence number of the HPET if the system has more than one.
+    pub number: u8,
+    /// Minimum number of ticks of a periodic timer without lost interrupts.
+    pub minimum_tick: u16,
+}
+
+impl Hpet {
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        let table = SdtHeader::table(bytes, b"HPET")?;
+        let block_id = read_u32(table, SDT_HEADER_SIZE).ok_or(AcpiError::Truncated)?;
+        // generic address structure of the registers
+        let gas = table
+            .get(SDT_HEADER_SIZE + 4..SDT_HEADER_SIZE + 16)
+            .ok_or(AcpiError::Truncated)?;
+        if gas[0] != GAS_SYSTEM_MEMORY {
+            log::warn!("HPET registers are in address space {}", gas[0]);
+        }
+        Ok(Self {
+            hardware_revision: block_id as u8,
+            comparator_count: ((block_id >> 8) & 0x1f) as u8 + 1,
+            counter_64bit: block_id & (1 << 13) != 0,
+            legacy_replacement: block_id & (1 << 15) != 0,
+            pci_vendor_id: (block_id >> 16) as u16,
+            address: read_u64(gas, 4).unwrap(),
+            number: *table
+                .get(SDT_HEADER_SIZE + 16)
+                .ok_or(AcpiError::Truncated)?,
+            minimum_tick: read_u16(table, SDT_HEADER_SIZE + 17).ok_or(AcpiError::Truncated)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tables::test_utils::build_table;
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_hpet() {
+        let mut body = Vec::new();
+        // QEMU: revision 1, 3 comparators, 64-bit counter, legacy replacement, Intel
+        body.extend_from_slice(&0x8086_a201_u32.to_le_bytes());
+        body.extend_from_slice(&[GAS_SYSTEM_MEMORY, 0, 0, 0]);
+        body.extend_from_slice(&0xfed0_0000_u64.to_le_bytes());
+        body.push(0);
+        body.extend_from_slice(&128_u16.to_le_bytes());
+        // page protection
+        body.push(0);
+        let table = build_table(b"HPET", &body);
+        assert_eq!(
+            Hpet::parse(&table),
+            Ok(Hpet {
+                hardware_revision: 1,
+                comparator_count: 3,
+                counter_64bit: true,
+                legacy_replacement: true,
+                pci_vendor_id: 0x8086,
+                address: 0xfed0_0000,
+                number: 0,
+                minimum_tick: 128,
+            })
+        );
+        assert_eq!(
+            Hpet::parse(&build_table(b"HPET", &body[..16])),
+            Err(AcpiError::Truncated)
+        );
+    }
+}
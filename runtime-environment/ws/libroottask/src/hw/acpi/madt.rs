@@ -0,0 +1,223 @@
+//! The multiple APIC description table (MADT, signature "APIC"). It lists the local APICs,
+//! i.e. the CPUs, the IOAPICs, and how the ISA interrupts map to GSIs.
+
+use super::tables::{
+    read_u16,
+    read_u32,
+    read_u64,
+    AcpiError,
+    SdtHeader,
+    SDT_HEADER_SIZE,
+};
+use alloc::vec::Vec;
+
+const ENTRY_LOCAL_APIC: u8 = 0;
+const ENTRY_IO_APIC: u8 = 1;
+const ENTRY_INTERRUPT_OVERRIDE: u8 = 2;
+const ENTRY_LOCAL_APIC_ADDRESS_OVERRIDE: u8 = 5;
+const ENTRY_LOCAL_X2APIC: u8 = 9;
+
+/// Flag of a local APIC: the CPU is usable.
+const LOCAL_APIC_ENABLED: u32 = 1 << 0;
+/// Flag of a local APIC: the firmware can enable the CPU at runtime.
+const LOCAL_APIC_ONLINE_CAPABLE: u32 = 1 << 1;
+
+/// A CPU.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LocalApic {
+    /// The ID of the processor object in the ACPI namespace.
+    pub processor_id: u32,
+    pub apic_id: u32,
+    pub enabled: bool,
+    pub online_capable: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IoApic {
+    pub id: u8,
+    /// Physical address of the registers.
+    pub address: u32,
+    /// GSI of the first pin.
+    pub gsi_base: u32,
+}
+
+/// An ISA interrupt that isn't identity-mapped to a GSI or that has a different trigger
+/// mode or polarity than ISA interrupts usually have.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InterruptOverride {
+    pub source: u8,
+    pub gsi: u32,
+    /// `None` if the interrupt conforms to the bus, i.e. active-high for ISA.
+    pub active_low: Option<bool>,
+    /// `None` if the interrupt conforms to the bus, i.e. edge-triggered for ISA.
+    pub level_triggered: Option<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Madt {
+    /// Physical address of the local APICs.
+    pub local_apic_address: u64,
+    /// The system has dual 8259 PICs too.
+    pub pcat_compat: bool,
+    pub local_apics: Vec<LocalApic>,
+    pub io_apics: Vec<IoApic>,
+    pub interrupt_overrides: Vec<InterruptOverride>,
+}
+
+impl Madt {
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        let table = SdtHeader::table(bytes, b"APIC")?;
+        let mut madt = Self {
+            local_apic_address: read_u32(table, SDT_HEADER_SIZE).ok_or(AcpiError::Truncated)?
+                as u64,
+            pcat_compat: read_u32(table, SDT_HEADER_SIZE + 4).ok_or(AcpiError::Truncated)? & 1 != 0,
+            local_apics: Vec::new(),
+            io_apics: Vec::new(),
+            interrupt_overrides: Vec::new(),
+        };
+
+        let mut entries = &table[SDT_HEADER_SIZE + 8..];
+        while !entries.is_empty() {
+            let (entry_type, len) = match entries {
+                [entry_type, len, ..] if *len >= 2 && *len as usize <= entries.len() => {
+                    (*entry_type, *len as usize)
+                }
+                _ => return Err(AcpiError::Truncated),
+            };
+            madt.add_entry(entry_type, &entries[..len])?;
+            entries = &entries[len..];
+        }
+        Ok(madt)
+    }
+
+    /// Returns the local APICs of the usable CPUs.
+    pub fn enabled_cpus(&self) -> impl Iterator<Item = &LocalApic> {
+        self.local_apics.iter().filter(|apic| apic.enabled)
+    }
+
+    /// Returns the GSI of the ISA interrupt.
+    pub fn isa_gsi(&self, isa_irq: u8) -> u32 {
+        self.interrupt_overrides
+            .iter()
+            .find(|o| o.source == isa_irq)
+            .map_or(isa_irq as u32, |o| o.gsi)
+    }
+
+    fn add_entry(&mut self, entry_type: u8, entry: &[u8]) -> Result<(), AcpiError> {
+        let truncated = || AcpiError::Truncated;
+        let byte = |offset: usize| entry.get(offset).copied().ok_or_else(truncated);
+        match entry_type {
+            ENTRY_LOCAL_APIC => {
+                let flags = read_u32(entry, 4).ok_or_else(truncated)?;
+                self.local_apics.push(LocalApic {
+                    processor_id: byte(2)? as u32,
+                    apic_id: byte(3)? as u32,
+                    enabled: flags & LOCAL_APIC_ENABLED != 0,
+                    online_capable: flags & LOCAL_APIC_ONLINE_CAPABLE != 0,
+                });
+            }
+            ENTRY_LOCAL_X2APIC => {
+                let flags = read_u32(entry, 8).ok_or_else(truncated)?;
+                self.local_apics.push(LocalApic {
+                    processor_id: read_u32(entry, 12).ok_or_else(truncated)?,
+                    apic_id: read_u32(entry, 4).unwrap(),
+                    enabled: flags & LOCAL_APIC_ENABLED != 0,
+                    online_capable: flags & LOCAL_APIC_ONLINE_CAPABLE != 0,
+                });
+            }
+            ENTRY_IO_APIC => self.io_apics.push(IoApic {
+                id: byte(2)?,
+                address: read_u32(entry, 4).ok_or_else(truncated)?,
+                gsi_base: read_u32(entry, 8).ok_or_else(truncated)?,
+            }),
+            ENTRY_INTERRUPT_OVERRIDE => {
+                let flags = read_u16(entry, 8).ok_or_else(truncated)?;
+                self.interrupt_overrides.push(InterruptOverride {
+                    source: byte(3)?,
+                    gsi: read_u32(entry, 4).unwrap(),
+                    // 0b00: conforms to the bus, 0b01: high or edge, 0b11: low or level
+                    active_low: match flags & 0x3 {
+                        0b01 => Some(false),
+                        0b11 => Some(true),
+                        _ => None,
+                    },
+                    level_triggered: match (flags >> 2) & 0x3 {
+                        0b01 => Some(false),
+                        0b11 => Some(true),
+                        _ => None,
+                    },
+                });
+            }
+            ENTRY_LOCAL_APIC_ADDRESS_OVERRIDE => {
+                self.local_apic_address = read_u64(entry, 4).ok_or_else(truncated)?;
+            }
+            // NMI sources, SAPICs, GIC entries, ...
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tables::test_utils::build_table;
+    use super::*;
+
+    #[test]
+    fn test_madt() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0xfee0_0000_u32.to_le_bytes());
+        body.extend_from_slice(&1_u32.to_le_bytes());
+        // CPU 0 and 1 enabled, CPU 2 hot-pluggable
+        body.extend_from_slice(&[ENTRY_LOCAL_APIC, 8, 0, 0, 1, 0, 0, 0]);
+        body.extend_from_slice(&[ENTRY_LOCAL_APIC, 8, 1, 1, 1, 0, 0, 0]);
+        body.extend_from_slice(&[ENTRY_LOCAL_APIC, 8, 2, 2, 2, 0, 0, 0]);
+        body.extend_from_slice(&[ENTRY_IO_APIC, 12, 0, 0]);
+        body.extend_from_slice(&0xfec0_0000_u32.to_le_bytes());
+        body.extend_from_slice(&0_u32.to_le_bytes());
+        // the PIT (ISA IRQ 0) is at GSI 2
+        body.extend_from_slice(&[ENTRY_INTERRUPT_OVERRIDE, 10, 0, 0, 2, 0, 0, 0, 0, 0]);
+        // the SCI (ISA IRQ 9) is level-triggered and active-high
+        body.extend_from_slice(&[ENTRY_INTERRUPT_OVERRIDE, 10, 0, 9, 9, 0, 0, 0, 0b1101, 0]);
+        // unknown entry: local APIC NMI
+        body.extend_from_slice(&[4, 6, 0xff, 0, 0, 1]);
+        let madt = Madt::parse(&build_table(b"APIC", &body)).unwrap();
+
+        assert_eq!(madt.local_apic_address, 0xfee0_0000);
+        assert!(madt.pcat_compat);
+        assert_eq!(madt.local_apics.len(), 3);
+        assert_eq!(
+            madt.enabled_cpus()
+                .map(|apic| apic.apic_id)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert!(madt.local_apics[2].online_capable);
+        assert_eq!(
+            madt.io_apics,
+            [IoApic {
+                id: 0,
+                address: 0xfec0_0000,
+                gsi_base: 0,
+            }]
+        );
+        assert_eq!(madt.isa_gsi(0), 2);
+        assert_eq!(madt.isa_gsi(1), 1);
+        assert_eq!(
+            madt.interrupt_overrides[1],
+            InterruptOverride {
+                source: 9,
+                gsi: 9,
+                active_low: Some(false),
+                level_triggered: Some(true),
+            }
+        );
+
+        // an entry that claims to be longer than the table
+        body.extend_from_slice(&[ENTRY_LOCAL_APIC, 8, 3, 3]);
+        assert_eq!(
+            Madt::parse(&build_table(b"APIC", &body)),
+            Err(AcpiError::Truncated)
+        );
+    }
+}
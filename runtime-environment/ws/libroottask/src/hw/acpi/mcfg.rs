@@ -0,0 +1,105 @@
+//! The table of the memory-mapped PCI configuration space (ECAM, signature "MCFG").
+
+use super::tables::{
+    read_u16,
+    read_u64,
+    AcpiError,
+    SdtHeader,
+    SDT_HEADER_SIZE,
+};
+use alloc::vec::Vec;
+
+/// Size of an entry in bytes.
+const ENTRY_SIZE: usize = 16;
+
+/// The ECAM region of a range of buses of a PCI segment.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct McfgEntry {
+    /// Physical address of the configuration space of bus 0, even if `bus_start` is
+    /// higher.
+    pub base: u64,
+    pub segment: u16,
+    pub bus_start: u8,
+    /// Inclusive.
+    pub bus_end: u8,
+}
+
+impl McfgEntry {
+    /// Physical address of the configuration space of `bus_start`.
+    pub const fn bus_start_address(&self) -> u64 {
+        self.base + ((self.bus_start as u64) << 20)
+    }
+
+    pub const fn bus_count(&self) -> u64 {
+        self.bus_end as u64 + 1 - self.bus_start as u64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mcfg {
+    pub entries: Vec<McfgEntry>,
+}
+
+impl Mcfg {
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        let table = SdtHeader::table(bytes, b"MCFG")?;
+        // 8 reserved bytes before the entries
+        let entries = table
+            .get(SDT_HEADER_SIZE + 8..)
+            .ok_or(AcpiError::Truncated)?
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| McfgEntry {
+                base: read_u64(entry, 0).unwrap(),
+                segment: read_u16(entry, 8).unwrap(),
+                bus_start: entry[10],
+                bus_end: entry[11],
+            })
+            .filter(|entry| entry.bus_start <= entry.bus_end)
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// Returns the entry of the first bus of PCI segment 0, which is the only segment
+    /// that the legacy I/O ports reach too.
+    pub fn segment_0(&self) -> Option<&McfgEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.segment == 0)
+            .min_by_key(|entry| entry.bus_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tables::test_utils::build_table;
+    use super::*;
+
+    fn entry(base: u64, segment: u16, bus_start: u8, bus_end: u8) -> [u8; ENTRY_SIZE] {
+        let mut entry = [0; ENTRY_SIZE];
+        entry[..8].copy_from_slice(&base.to_le_bytes());
+        entry[8..10].copy_from_slice(&segment.to_le_bytes());
+        entry[10] = bus_start;
+        entry[11] = bus_end;
+        entry
+    }
+
+    #[test]
+    fn test_mcfg() {
+        let mut body = vec![0; 8];
+        body.extend_from_slice(&entry(0xc000_0000, 1, 0, 0xff));
+        body.extend_from_slice(&entry(0xb000_0000, 0, 0x10, 0x1f));
+        // invalid bus range
+        body.extend_from_slice(&entry(0xa000_0000, 0, 0x20, 0x1f));
+        let mcfg = Mcfg::parse(&build_table(b"MCFG", &body)).unwrap();
+        assert_eq!(mcfg.entries.len(), 2);
+        let segment_0 = mcfg.segment_0().unwrap();
+        assert_eq!(segment_0.bus_start_address(), 0xb100_0000);
+        assert_eq!(segment_0.bus_count(), 16);
+        assert_eq!(mcfg.entries[0].bus_count(), 256);
+
+        assert_eq!(
+            Mcfg::parse(&build_table(b"MCFG", &[])),
+            Err(AcpiError::Truncated)
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! ACPI tables. Hedron parses the tables itself, but the HIP only has the parts that Hedron
+//! needs, e.g. the online CPUs and one ECAM region. [`init`] parses the tables that the
+//! drivers of the roottask need: the MADT (CPUs, IOAPICs, and ISA interrupt overrides),
+//! the HPET table, and the MCFG (ECAM regions of PCI). The roottask never modifies the
+//! tables and keeps copies of them.
+//!
+//! The HIP has the address of the XSDT or the RSDT. Without it, the roottask searches the
+//! RSDP in the BIOS memory, like a legacy OS.
+
+mod hpet;
+mod madt;
+mod mcfg;
+mod tables;
+
+pub use hpet::*;
+pub use madt::*;
+pub use mcfg::*;
+pub use tables::{
+    AcpiError,
+    Rsdp,
+    SdtHeader,
+};
+
+use crate::mem::{
+    revoke_from_roottask,
+    PhysAddr,
+    VIRT_MEM_ALLOC,
+};
+use alloc::alloc::Layout;
+use alloc::vec::Vec;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::HIP;
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::delegation::DelegationBuilder;
+use tables::{
+    parse_root_table,
+    read_u16,
+    SDT_HEADER_SIZE,
+};
+
+/// Address of the pointer to the extended BIOS data area (EBDA) in the BIOS data area. It
+/// holds the real mode segment.
+const EBDA_POINTER_ADDR: PhysAddr = 0x40e;
+/// The RSDP is in the first KiB of the EBDA ...
+const EBDA_SEARCH_SIZE: usize = 1024;
+/// ... or in the BIOS ROM.
+const BIOS_ROM: core::ops::Range<PhysAddr> = 0xe0000..0x100000;
+
+/// Upper bound for the length of a table. Protects against broken headers.
+const MAX_TABLE_SIZE: usize = 1024 * 1024;
+
+/// The parsed tables. `None` until [`init`] ran.
+static ACPI: SimpleMutex<Option<AcpiTables>> = SimpleMutex::new(None);
+
+/// The tables that the roottask knows. Each one is `None` if the firmware doesn't provide
+/// it or it is broken.
+#[derive(Clone, Debug, Default)]
+pub struct AcpiTables {
+    /// Physical address of the XSDT or RSDT.
+    pub root_table_address: PhysAddr,
+    /// Signatures and physical addresses of all tables that the root table lists.
+    pub tables: Vec<([u8; 4], PhysAddr)>,
+    pub madt: Option<Madt>,
+    pub hpet: Option<Hpet>,
+    pub mcfg: Option<Mcfg>,
+}
+
+/// Locates and parses the ACPI tables.
+pub fn init(hip: &HIP) {
+    let root_table_address = match hip.xsdt_rdst_table() {
+        0 => match find_rsdp() {
+            Some(rsdp) => rsdp.root_table_address(),
+            None => {
+                log::warn!("no ACPI tables found");
+                return;
+            }
+        },
+        address => address,
+    };
+    let tables = match read_table(root_table_address).map(|bytes| parse_root_table(&bytes)) {
+        Some(Ok(tables)) => tables,
+        e => {
+            log::warn!(
+                "can't read the ACPI root table at {:#x}: {:?}",
+                root_table_address,
+                e
+            );
+            return;
+        }
+    };
+
+    let mut acpi = AcpiTables {
+        root_table_address,
+        ..AcpiTables::default()
+    };
+    for address in tables {
+        let bytes = match read_table(address) {
+            Some(bytes) => bytes,
+            None => continue,
+        };
+        let header = SdtHeader::parse(&bytes).unwrap();
+        let result = match &header.signature {
+            b"APIC" => Madt::parse(&bytes).map(|madt| acpi.madt = Some(madt)),
+            b"HPET" => Hpet::parse(&bytes).map(|hpet| acpi.hpet = Some(hpet)),
+            b"MCFG" => Mcfg::parse(&bytes).map(|mcfg| acpi.mcfg = Some(mcfg)),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            log::warn!(
+                "broken ACPI table {} at {:#x}: {:?}",
+                core::str::from_utf8(&header.signature).unwrap_or("????"),
+                address,
+                e
+            );
+        }
+        acpi.tables.push((header.signature, address));
+    }
+
+    log::info!(
+        "ACPI tables: {:?}",
+        acpi.tables
+            .iter()
+            .map(|(signature, _)| core::str::from_utf8(signature).unwrap_or("????"))
+            .collect::<Vec<_>>()
+    );
+    if let Some(madt) = &acpi.madt {
+        log::info!(
+            "MADT: {} CPUs ({} usable), {} IOAPICs",
+            madt.local_apics.len(),
+            madt.enabled_cpus().count(),
+            madt.io_apics.len()
+        );
+    }
+    ACPI.lock().replace(acpi);
+}
+
+/// Returns a copy of the parsed tables. `None` if the system has no ACPI tables.
+pub fn tables() -> Option<AcpiTables> {
+    ACPI.lock().clone()
+}
+
+/// Returns the MADT, if the system has one.
+pub fn madt() -> Option<Madt> {
+    ACPI.lock().as_ref().and_then(|acpi| acpi.madt.clone())
+}
+
+/// Returns the HPET table, if the system has one.
+pub fn hpet() -> Option<Hpet> {
+    ACPI.lock().as_ref().and_then(|acpi| acpi.hpet)
+}
+
+/// Returns the MCFG, if the system has one.
+pub fn mcfg() -> Option<Mcfg> {
+    ACPI.lock().as_ref().and_then(|acpi| acpi.mcfg.clone())
+}
+
+/// Searches the RSDP in the EBDA and in the BIOS ROM.
+fn find_rsdp() -> Option<Rsdp> {
+    let ebda_segment = read_phys(EBDA_POINTER_ADDR, 2).and_then(|bytes| read_u16(&bytes, 0));
+    let ebda = ebda_segment
+        .filter(|segment| *segment != 0)
+        .map(|segment| (segment as PhysAddr) << 4);
+    let bios_rom_size = (BIOS_ROM.end - BIOS_ROM.start) as usize;
+    [
+        (ebda, EBDA_SEARCH_SIZE),
+        (Some(BIOS_ROM.start), bios_rom_size),
+    ]
+    .into_iter()
+    .filter_map(|(start, len)| Some((start?, read_phys(start?, len)?)))
+    .find_map(|(start, area)| {
+        let offset = Rsdp::find(&area)?;
+        log::debug!("found the RSDP at {:#x}", start + offset as PhysAddr);
+        Rsdp::parse(&area[offset..]).ok()
+    })
+}
+
+/// Returns a copy of the table at the physical address.
+fn read_table(address: PhysAddr) -> Option<Vec<u8>> {
+    let header = SdtHeader::parse(&read_phys(address, SDT_HEADER_SIZE)?).ok()?;
+    let len = header.length as usize;
+    if !(SDT_HEADER_SIZE..=MAX_TABLE_SIZE).contains(&len) {
+        log::warn!("ACPI table at {:#x} has an invalid length {}", address, len);
+        return None;
+    }
+    read_phys(address, len)
+}
+
+/// Returns a copy of the physical memory. The roottask maps the memory only temporarily.
+fn read_phys(address: PhysAddr, len: usize) -> Option<Vec<u8>> {
+    let page_offset = (address % PAGE_SIZE as u64) as usize;
+    let phys_start = address - page_offset as u64;
+    let page_count = (page_offset + len + PAGE_SIZE - 1) / PAGE_SIZE;
+    let size = page_count * PAGE_SIZE;
+    let r_address = VIRT_MEM_ALLOC
+        .lock()
+        .next_addr(Layout::from_size_align(size, PAGE_SIZE).unwrap());
+    // roottask to roottask: the source is the physical address
+    if let Err(e) = DelegationBuilder::mem(phys_start..phys_start + size as u64)
+        .at(r_address)
+        .to(RootCapSpace::RootPd.val())
+    {
+        log::warn!("can't map physical memory at {:#x}: {:?}", address, e);
+        return None;
+    }
+    let bytes = unsafe {
+        core::slice::from_raw_parts((r_address as usize + page_offset) as *const u8, len)
+    }
+    .to_vec();
+    revoke_from_roottask(r_address, page_count);
+    Some(bytes)
+}
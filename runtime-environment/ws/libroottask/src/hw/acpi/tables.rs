@@ -0,0 +1,273 @@
+//! The common parts of all ACPI tables: the RSDP, the header of the system description
+//! tables (SDTs), and the root tables (RSDT and XSDT) that point to the other tables.
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// Size of [`SdtHeader`] in bytes. The fields of a table follow it.
+pub const SDT_HEADER_SIZE: usize = 36;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// Size of the RSDP of ACPI 1.0, which the first checksum covers.
+const RSDP_V1_SIZE: usize = 20;
+/// Size of the RSDP of ACPI 2.0 and later, which the extended checksum covers.
+pub const RSDP_V2_SIZE: usize = 36;
+
+/// Errors of the parsers of ACPI tables.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AcpiError {
+    /// The table doesn't start with the expected signature.
+    InvalidSignature,
+    /// The bytes of the table don't sum up to zero.
+    InvalidChecksum,
+    /// The table is shorter than its header or its entries say.
+    Truncated,
+}
+
+/// The root system description pointer, which the firmware places in the BIOS memory.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rsdp {
+    pub revision: u8,
+    pub rsdt_address: u32,
+    /// Only ACPI 2.0 and later have an XSDT.
+    pub xsdt_address: Option<u64>,
+}
+
+impl Rsdp {
+    /// Parses the RSDP at the beginning of `bytes`. `bytes` must contain
+    /// [`RSDP_V2_SIZE`] bytes if the RSDP is of ACPI 2.0 or later.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        if bytes.get(..RSDP_SIGNATURE.len()) != Some(RSDP_SIGNATURE) {
+            return Err(AcpiError::InvalidSignature);
+        }
+        let v1 = bytes.get(..RSDP_V1_SIZE).ok_or(AcpiError::Truncated)?;
+        if !is_checksum_valid(v1) {
+            return Err(AcpiError::InvalidChecksum);
+        }
+        let revision = v1[15];
+        let rsdt_address = read_u32(v1, 16).unwrap();
+        let xsdt_address = if revision >= 2 {
+            let v2 = bytes.get(..RSDP_V2_SIZE).ok_or(AcpiError::Truncated)?;
+            if !is_checksum_valid(v2) {
+                return Err(AcpiError::InvalidChecksum);
+            }
+            Some(read_u64(v2, 24).unwrap())
+        } else {
+            None
+        };
+        Ok(Self {
+            revision,
+            rsdt_address,
+            xsdt_address,
+        })
+    }
+
+    /// Returns the offset of the first valid RSDP in `area`. The RSDP is on a 16-byte
+    /// boundary.
+    pub fn find(area: &[u8]) -> Option<usize> {
+        (0..area.len())
+            .step_by(16)
+            .find(|offset| Self::parse(&area[*offset..]).is_ok())
+    }
+
+    /// Returns the address of the XSDT, or of the RSDT for ACPI 1.0.
+    pub fn root_table_address(&self) -> u64 {
+        self.xsdt_address
+            .filter(|address| *address != 0)
+            .unwrap_or(self.rsdt_address as u64)
+    }
+}
+
+/// The header of each system description table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    /// Length of the table including the header.
+    pub length: u32,
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+}
+
+impl SdtHeader {
+    /// Parses the header at the beginning of `bytes`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        let header = bytes.get(..SDT_HEADER_SIZE).ok_or(AcpiError::Truncated)?;
+        Ok(Self {
+            signature: header[0..4].try_into().unwrap(),
+            length: read_u32(header, 4).unwrap(),
+            revision: header[8],
+            oem_id: header[10..16].try_into().unwrap(),
+            oem_table_id: header[16..24].try_into().unwrap(),
+        })
+    }
+
+    /// Returns the complete table with the signature at the beginning of `bytes`. Checks
+    /// the length and the checksum.
+    pub fn table<'a>(bytes: &'a [u8], signature: &[u8; 4]) -> Result<&'a [u8], AcpiError> {
+        let header = Self::parse(bytes)?;
+        if &header.signature != signature {
+            return Err(AcpiError::InvalidSignature);
+        }
+        let table = bytes
+            .get(..header.length as usize)
+            .filter(|table| table.len() >= SDT_HEADER_SIZE)
+            .ok_or(AcpiError::Truncated)?;
+        if is_checksum_valid(table) {
+            Ok(table)
+        } else {
+            Err(AcpiError::InvalidChecksum)
+        }
+    }
+}
+
+/// Returns the addresses of the tables that the RSDT (with 32-bit entries) or the XSDT
+/// (with 64-bit entries) at the beginning of `bytes` points to.
+pub fn parse_root_table(bytes: &[u8]) -> Result<Vec<u64>, AcpiError> {
+    let (table, entry_size) = match SdtHeader::table(bytes, b"XSDT") {
+        Ok(table) => (table, 8),
+        Err(AcpiError::InvalidSignature) => (SdtHeader::table(bytes, b"RSDT")?, 4),
+        Err(e) => return Err(e),
+    };
+    Ok(table[SDT_HEADER_SIZE..]
+        .chunks_exact(entry_size)
+        .map(|entry| {
+            if entry_size == 8 {
+                read_u64(entry, 0).unwrap()
+            } else {
+                read_u32(entry, 0).unwrap() as u64
+            }
+        })
+        .collect())
+}
+
+/// Returns whether all bytes sum up to zero (modulo 256).
+pub fn is_checksum_valid(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+pub fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+pub fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+pub fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Helpers for the tests of the parsers.
+#[cfg(test)]
+pub mod test_utils {
+    use super::*;
+
+    /// Builds a table with a valid header and checksum around `body`.
+    pub fn build_table(signature: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut table = Vec::new();
+        table.extend_from_slice(signature);
+        table.extend_from_slice(&((SDT_HEADER_SIZE + body.len()) as u32).to_le_bytes());
+        table.push(1);
+        // checksum
+        table.push(0);
+        table.extend_from_slice(b"BOCHS ");
+        table.extend_from_slice(b"BXPC    ");
+        table.resize(SDT_HEADER_SIZE, 0);
+        table.extend_from_slice(body);
+        fix_checksum(&mut table, 9);
+        table
+    }
+
+    /// Sets the byte at `index` so that all bytes sum up to zero.
+    pub fn fix_checksum(bytes: &mut [u8], index: usize) {
+        bytes[index] = 0;
+        let sum = bytes.iter().fold(0_u8, |sum, byte| sum.wrapping_add(*byte));
+        bytes[index] = 0_u8.wrapping_sub(sum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::*;
+    use super::*;
+
+    fn rsdp_v2(xsdt_address: u64) -> Vec<u8> {
+        let mut rsdp = Vec::new();
+        rsdp.extend_from_slice(RSDP_SIGNATURE);
+        // checksum, OEM ID, revision
+        rsdp.push(0);
+        rsdp.extend_from_slice(b"BOCHS ");
+        rsdp.push(2);
+        rsdp.extend_from_slice(&0x7fe_1000_u32.to_le_bytes());
+        rsdp.extend_from_slice(&(RSDP_V2_SIZE as u32).to_le_bytes());
+        rsdp.extend_from_slice(&xsdt_address.to_le_bytes());
+        // extended checksum, reserved
+        rsdp.extend_from_slice(&[0; 4]);
+        fix_checksum(&mut rsdp[..RSDP_V1_SIZE], 8);
+        fix_checksum(&mut rsdp, 32);
+        rsdp
+    }
+
+    #[test]
+    fn test_rsdp() {
+        let rsdp = rsdp_v2(0x7fe_2000);
+        assert_eq!(
+            Rsdp::parse(&rsdp),
+            Ok(Rsdp {
+                revision: 2,
+                rsdt_address: 0x7fe_1000,
+                xsdt_address: Some(0x7fe_2000),
+            })
+        );
+        assert_eq!(Rsdp::parse(&rsdp).unwrap().root_table_address(), 0x7fe_2000);
+        assert_eq!(
+            Rsdp::parse(&rsdp_v2(0)).unwrap().root_table_address(),
+            0x7fe_1000
+        );
+        assert_eq!(
+            Rsdp::parse(&rsdp[..RSDP_V1_SIZE]),
+            Err(AcpiError::Truncated)
+        );
+
+        let mut broken = rsdp.clone();
+        broken[16] += 1;
+        assert_eq!(Rsdp::parse(&broken), Err(AcpiError::InvalidChecksum));
+
+        let mut area = vec![0; 64];
+        area.extend_from_slice(&rsdp);
+        area.resize(256, 0);
+        assert_eq!(Rsdp::find(&area), Some(64));
+        assert_eq!(Rsdp::find(&area[..64]), None);
+    }
+
+    #[test]
+    fn test_root_table() {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0x7fe_3000_u64.to_le_bytes());
+        body.extend_from_slice(&0x7fe_4000_u64.to_le_bytes());
+        let xsdt = build_table(b"XSDT", &body);
+        assert_eq!(parse_root_table(&xsdt), Ok(vec![0x7fe_3000, 0x7fe_4000]));
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&0x7fe_3000_u32.to_le_bytes());
+        let rsdt = build_table(b"RSDT", &body);
+        assert_eq!(parse_root_table(&rsdt), Ok(vec![0x7fe_3000]));
+        assert_eq!(SdtHeader::parse(&rsdt).unwrap().oem_table_id, *b"BXPC    ");
+
+        assert_eq!(
+            parse_root_table(&build_table(b"APIC", &[])),
+            Err(AcpiError::InvalidSignature)
+        );
+        assert_eq!(
+            parse_root_table(&xsdt[..xsdt.len() - 1]),
+            Err(AcpiError::Truncated)
+        );
+        let mut broken = xsdt;
+        broken[SDT_HEADER_SIZE] ^= 1;
+        assert_eq!(parse_root_table(&broken), Err(AcpiError::InvalidChecksum));
+    }
+}
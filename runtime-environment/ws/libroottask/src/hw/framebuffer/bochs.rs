@@ -0,0 +1,93 @@
+//! Minimal driver for the VBE extensions ("DISPI") of the standard VGA of QEMU (`-vga std`)
+//! and Bochs. The extensions set a graphics mode without the VBE BIOS; BAR0 of the PCI
+//! function is the linear framebuffer.
+
+use super::{
+    Framebuffer,
+    BYTES_PER_PIXEL,
+};
+use crate::hw::pci::{
+    self,
+    PciBar,
+    PciConfigSpace,
+    PciOwner,
+};
+use crate::io_port::request_io_ports;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::libhedron::CrdPortIO;
+use x86::io::{
+    inw,
+    outw,
+};
+
+const VENDOR_ID: u16 = 0x1234;
+const DEVICE_ID: u16 = 0x1111;
+
+/// I/O port that selects the register.
+const INDEX_PORT: u16 = 0x1ce;
+/// I/O port that accesses the selected register.
+const DATA_PORT: u16 = 0x1cf;
+
+const REG_ID: u16 = 0;
+const REG_XRES: u16 = 1;
+const REG_YRES: u16 = 2;
+const REG_BPP: u16 = 3;
+const REG_ENABLE: u16 = 4;
+
+/// Oldest version of the extensions in the ID register; higher values are newer versions.
+const ID_MIN: u16 = 0xb0c0;
+const ENABLE_ENABLED: u16 = 1 << 0;
+const ENABLE_LFB: u16 = 1 << 6;
+
+/// Searches the standard VGA on the PCI bus and sets the mode. Returns `None` if there is
+/// no such device.
+pub fn init(width: u32, height: u32) -> Option<Framebuffer> {
+    let pci = PciConfigSpace::new(RootCapSpace::RootPd.val()).ok()?;
+    let device = pci.find_device(VENDOR_ID, &[DEVICE_ID])?;
+    if let Err(e) = pci::claim(device.addr.into(), PciOwner::Roottask) {
+        log::warn!("Bochs VBE: can't claim the device: {:?}", e);
+        return None;
+    }
+    let base = match pci.bar(device.addr, 0) {
+        PciBar::Memory(base) => base,
+        bar => {
+            log::warn!("Bochs VBE: BAR0 is not a memory BAR: {:?}", bar);
+            return None;
+        }
+    };
+    // 2 consecutive ports: index and data
+    request_io_ports(RootCapSpace::RootPd.val(), CrdPortIO::new(INDEX_PORT, 1)).ok()?;
+    let id = read_reg(REG_ID);
+    if id < ID_MIN {
+        log::warn!("Bochs VBE: unknown version {:#x}", id);
+        return None;
+    }
+    pci.enable_device(device.addr);
+
+    // the mode can only change while the extensions are disabled
+    write_reg(REG_ENABLE, 0);
+    write_reg(REG_XRES, width as u16);
+    write_reg(REG_YRES, height as u16);
+    write_reg(REG_BPP, (BYTES_PER_PIXEL * 8) as u16);
+    write_reg(REG_ENABLE, ENABLE_ENABLED | ENABLE_LFB);
+    Some(Framebuffer::map(
+        base,
+        width,
+        height,
+        width * BYTES_PER_PIXEL,
+    ))
+}
+
+fn read_reg(index: u16) -> u16 {
+    unsafe {
+        outw(INDEX_PORT, index);
+        inw(DATA_PORT)
+    }
+}
+
+fn write_reg(index: u16, value: u16) {
+    unsafe {
+        outw(INDEX_PORT, index);
+        outw(DATA_PORT, value);
+    }
+}
@@ -0,0 +1,262 @@
+//! Linear framebuffers for the text console of the roottask, see
+//! [`crate::services::stdout`]. The boot argument `fb_console` selects the source of the
+//! framebuffer:
+//!
+//! - `fb_console=on`: the roottask sets a mode with the VBE extensions of the standard VGA
+//!   of QEMU and Bochs itself, see [`bochs`].
+//! - `fb_console=<address>,<width>x<height>[,<pitch>]`: the boot loader already set a mode,
+//!   e.g. GRUB with `set gfxpayload=keep`. Hedron doesn't pass the framebuffer of the
+//!   Multiboot information on to the roottask, therefore the argument describes it.
+//!
+//! Only modes with 32 bits per pixel (XRGB) are supported.
+
+pub mod bochs;
+pub mod font;
+
+use crate::mem::{
+    map_phys_into_roottask,
+    PhysAddr,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Bytes per pixel.
+pub const BYTES_PER_PIXEL: u32 = 4;
+
+/// Resolution of the mode that the roottask sets itself.
+const DEFAULT_WIDTH: u32 = 1024;
+const DEFAULT_HEIGHT: u32 = 768;
+
+/// The source that the boot argument selected.
+static SOURCE: SimpleMutex<FramebufferSource> = SimpleMutex::new(FramebufferSource::Off);
+
+/// Where the framebuffer comes from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FramebufferSource {
+    /// No framebuffer console, the default.
+    Off,
+    /// The roottask sets a mode via the Bochs VBE extensions.
+    BochsVbe,
+    /// The boot loader set a mode.
+    BootLoader {
+        address: PhysAddr,
+        width: u32,
+        height: u32,
+        /// Bytes per line.
+        pitch: u32,
+    },
+}
+
+impl FramebufferSource {
+    /// Parses the value of the boot argument `fb_console`.
+    pub fn parse(arg: &str) -> Option<Self> {
+        match arg {
+            "off" => return Some(Self::Off),
+            "on" => return Some(Self::BochsVbe),
+            _ => {}
+        }
+        let mut parts = arg.split(',');
+        let address = parts.next()?.strip_prefix("0x")?;
+        let address = PhysAddr::from_str_radix(address, 16).ok()?;
+        let (width, height) = parts.next()?.split_once('x')?;
+        let width = width.parse::<u32>().ok().filter(|width| *width > 0)?;
+        let height = height.parse::<u32>().ok().filter(|height| *height > 0)?;
+        let pitch = match parts.next() {
+            Some(pitch) => pitch.parse().ok()?,
+            None => width * BYTES_PER_PIXEL,
+        };
+        if parts.next().is_some() || pitch < width * BYTES_PER_PIXEL {
+            return None;
+        }
+        Some(Self::BootLoader {
+            address,
+            width,
+            height,
+            pitch,
+        })
+    }
+}
+
+/// Sets the source from the boot argument. Returns false if the argument is invalid.
+pub fn set_source(arg: &str) -> bool {
+    match FramebufferSource::parse(arg) {
+        Some(source) => {
+            *SOURCE.lock() = source;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sets up the framebuffer of the selected source and maps it into the roottask. Returns
+/// `None` if the console is off or the framebuffer is unavailable. Needs the PCI devices.
+pub fn init() -> Option<Framebuffer> {
+    let source = *SOURCE.lock();
+    let framebuffer = match source {
+        FramebufferSource::Off => return None,
+        FramebufferSource::BochsVbe => bochs::init(DEFAULT_WIDTH, DEFAULT_HEIGHT),
+        FramebufferSource::BootLoader {
+            address,
+            width,
+            height,
+            pitch,
+        } => Some(Framebuffer::map(address, width, height, pitch)),
+    };
+    match &framebuffer {
+        Some(fb) => log::info!(
+            "framebuffer: {}x{} pixels at {:#x}",
+            fb.width,
+            fb.height,
+            fb.r_addr
+        ),
+        None => log::warn!("no framebuffer for the console ({:?})", source),
+    }
+    framebuffer
+}
+
+/// A mapped framebuffer with 32 bits per pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    /// Address of the first pixel in the roottask.
+    pub r_addr: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Bytes per line.
+    pub pitch: u32,
+}
+
+impl Framebuffer {
+    /// Maps the framebuffer at the physical address into the roottask.
+    pub fn map(address: PhysAddr, width: u32, height: u32, pitch: u32) -> Self {
+        let page_offset = address % PAGE_SIZE as u64;
+        let size = page_offset as usize + (pitch * height) as usize;
+        let page_count = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let r_addr = map_phys_into_roottask(address - page_offset, page_count) + page_offset;
+        Self {
+            r_addr,
+            width,
+            height,
+            pitch,
+        }
+    }
+
+    /// Fills the rectangle with the color. The rectangle must be inside the framebuffer.
+    pub fn fill_rect(&self, x: u32, y: u32, width: u32, height: u32, color: u32) {
+        debug_assert!(x + width <= self.width && y + height <= self.height);
+        for row in y..y + height {
+            for column in x..x + width {
+                self.put_pixel(column, row, color);
+            }
+        }
+    }
+
+    /// Draws the glyph of [`font`] with its top left corner at the position. Each row of
+    /// the glyph is `scale_y` pixels high.
+    pub fn draw_glyph(
+        &self,
+        x: u32,
+        y: u32,
+        glyph: &[u8; 8],
+        scale_y: u32,
+        foreground: u32,
+        background: u32,
+    ) {
+        for (glyph_row, bits) in glyph.iter().enumerate() {
+            for row in 0..scale_y {
+                for column in 0..font::GLYPH_WIDTH {
+                    let color = if bits & (1 << column) != 0 {
+                        foreground
+                    } else {
+                        background
+                    };
+                    self.put_pixel(x + column, y + glyph_row as u32 * scale_y + row, color);
+                }
+            }
+        }
+    }
+
+    /// Moves the content up by `lines` pixel lines and fills the freed lines at the bottom
+    /// with the color.
+    pub fn scroll_up(&self, lines: u32, color: u32) {
+        let lines = lines.min(self.height);
+        let moved = ((self.height - lines) * self.pitch) as usize;
+        unsafe {
+            core::ptr::copy(
+                (self.r_addr + (lines * self.pitch) as u64) as *const u8,
+                self.r_addr as *mut u8,
+                moved,
+            )
+        };
+        self.fill_rect(0, self.height - lines, self.width, lines, color);
+    }
+
+    fn put_pixel(&self, x: u32, y: u32, color: u32) {
+        let address = self.r_addr + (y * self.pitch + x * BYTES_PER_PIXEL) as u64;
+        unsafe { core::ptr::write_volatile(address as *mut u32, color) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            FramebufferSource::parse("on"),
+            Some(FramebufferSource::BochsVbe)
+        );
+        assert_eq!(
+            FramebufferSource::parse("off"),
+            Some(FramebufferSource::Off)
+        );
+        assert_eq!(
+            FramebufferSource::parse("0xfd000000,800x600"),
+            Some(FramebufferSource::BootLoader {
+                address: 0xfd00_0000,
+                width: 800,
+                height: 600,
+                pitch: 3200,
+            })
+        );
+        assert_eq!(
+            FramebufferSource::parse("0xe0000000,1366x768,5504"),
+            Some(FramebufferSource::BootLoader {
+                address: 0xe000_0000,
+                width: 1366,
+                height: 768,
+                pitch: 5504,
+            })
+        );
+        assert_eq!(FramebufferSource::parse("fd000000,800x600"), None);
+        assert_eq!(FramebufferSource::parse("0xfd000000,800"), None);
+        assert_eq!(FramebufferSource::parse("0xfd000000,0x600"), None);
+        // the pitch is smaller than a line
+        assert_eq!(FramebufferSource::parse("0xfd000000,800x600,800"), None);
+        assert_eq!(FramebufferSource::parse("0xfd000000,800x600,3200,1"), None);
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        // 16x4 pixels with a padding of 4 pixels per line
+        let mut pixels = vec![0_u32; 20 * 4];
+        let fb = Framebuffer {
+            r_addr: pixels.as_mut_ptr() as u64,
+            width: 16,
+            height: 4,
+            pitch: 20 * BYTES_PER_PIXEL,
+        };
+        fb.fill_rect(1, 1, 2, 2, 7);
+        assert_eq!(pixels[20..24], [0, 7, 7, 0]);
+        assert_eq!(pixels[40..44], [0, 7, 7, 0]);
+
+        fb.scroll_up(1, 9);
+        assert_eq!(pixels[0..4], [0, 7, 7, 0]);
+        assert_eq!(pixels[20..24], [0, 7, 7, 0]);
+        assert_eq!(pixels[40..44], [0; 4]);
+        assert_eq!(pixels[60..76], [9; 16]);
+        // the padding is untouched
+        assert_eq!(pixels[76..80], [0; 4]);
+    }
+}
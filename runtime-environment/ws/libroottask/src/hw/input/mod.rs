@@ -0,0 +1,133 @@
+//! Input of the console. The drivers of the input devices feed the [`InputQueue`], which
+//! the stdin service and `read` of Linux programs on their standard input consume, see
+//! [`crate::services::stdin`]. The devices are the serial port, e.g. `-serial stdio` of
+//! QEMU, or the virtio-console that replaces it (see [`crate::hw::virtio_console`]), and
+//! the PS/2 keyboard, e.g. the emulated keyboard in the window of QEMU. All processes
+//! share the input.
+//!
+//! The PS/2 keyboard signals each key with an interrupt. The serial port and the
+//! virtio-console are polled before each read instead, because the serial transfer service
+//! and the GDB stub read raw bytes from them too, which an interrupt handler would steal.
+
+pub mod ps2;
+
+use crate::gdb_stub;
+use crate::services::stdout;
+use alloc::vec::Vec;
+use libhrstd::libhedron::CapSel;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Capacity of the input queue in bytes, like the input buffer of a Linux terminal.
+/// Further input is dropped until a program reads.
+pub const INPUT_QUEUE_CAPACITY: usize = 4096;
+
+/// The input of the console that no program read yet.
+static INPUT: SimpleMutex<InputQueue> = SimpleMutex::new(InputQueue::new());
+
+/// Initializes the input devices that need a driver. The serial port is the output of
+/// the console too and was set up with it, see [`crate::services::stdout`].
+pub fn init(root_pd_sel: CapSel) {
+    if !ps2::init(root_pd_sel) {
+        log::info!("no PS/2 keyboard; input only from the serial port");
+    }
+}
+
+/// Appends input to the queue. Called by the drivers.
+pub fn push(bytes: &[u8]) {
+    INPUT.lock().push(bytes);
+}
+
+/// Checks if the console has input that [`read`] returns immediately.
+pub fn has_input() -> bool {
+    poll();
+    INPUT.lock().has_input()
+}
+
+/// Returns at most `max_len` bytes of the input. Never blocks. See [`InputQueue::read`].
+pub fn read(max_len: usize) -> Vec<u8> {
+    poll();
+    INPUT.lock().read(max_len)
+}
+
+/// Moves the bytes that the serial port received into the queue. Also fetches the keys
+/// that the PS/2 keyboard has, in case its interrupt isn't available.
+fn poll() {
+    let mut bytes = Vec::new();
+    // GDB talks to a stopped process over the serial port
+    if !gdb_stub::serial_claimed() {
+        let mut writer = stdout::writer_mut();
+        while bytes.len() < INPUT_QUEUE_CAPACITY {
+            match writer.try_read_byte() {
+                Some(byte) => bytes.push(byte),
+                None => break,
+            }
+        }
+    }
+    push(&bytes);
+    ps2::poll();
+}
+
+/// Line-buffered queue of the input. A read returns the bytes that are there already but
+/// never more than one line, so that each line of the user arrives in its own read, like
+/// on a terminal. Unlike a terminal in canonical mode, the queue delivers an incomplete
+/// line as well; the programs edit their lines themselves.
+#[derive(Debug, Default)]
+pub struct InputQueue {
+    bytes: Vec<u8>,
+}
+
+impl InputQueue {
+    pub const fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    /// Appends the bytes. Drops the bytes that exceed [`INPUT_QUEUE_CAPACITY`].
+    pub fn push(&mut self, bytes: &[u8]) {
+        let free = INPUT_QUEUE_CAPACITY - self.bytes.len();
+        if bytes.len() > free {
+            log::debug!("input queue is full; dropped {} bytes", bytes.len() - free);
+        }
+        self.bytes
+            .extend_from_slice(&bytes[..bytes.len().min(free)]);
+    }
+
+    pub fn has_input(&self) -> bool {
+        !self.bytes.is_empty()
+    }
+
+    /// Removes and returns at most `max_len` bytes, up to and including the first line
+    /// break.
+    pub fn read(&mut self, max_len: usize) -> Vec<u8> {
+        let line_len = self
+            .bytes
+            .iter()
+            .position(|byte| matches!(byte, b'\r' | b'\n'))
+            .map_or(self.bytes.len(), |index| index + 1);
+        self.bytes.drain(..line_len.min(max_len)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_queue() {
+        let mut queue = InputQueue::new();
+        assert!(!queue.has_input());
+        assert_eq!(queue.read(10), b"");
+
+        queue.push(b"ls\rcd /bin\r\npw");
+        assert_eq!(queue.read(10), b"ls\r");
+        assert_eq!(queue.read(3), b"cd ");
+        assert_eq!(queue.read(10), b"/bin\r");
+        assert_eq!(queue.read(10), b"\n");
+        assert_eq!(queue.read(10), b"pw");
+        assert!(!queue.has_input());
+
+        queue.push(&[b'x'; INPUT_QUEUE_CAPACITY + 1]);
+        queue.push(b"\r");
+        assert_eq!(queue.read(usize::MAX).len(), INPUT_QUEUE_CAPACITY);
+        assert!(!queue.has_input());
+    }
+}
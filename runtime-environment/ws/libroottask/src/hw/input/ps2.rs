@@ -0,0 +1,348 @@
+//! Driver for the PS/2 keyboard at the i8042 controller of PC platforms. The controller
+//! translates the scancodes of the keyboard to scancode set 1, which [`Keyboard`] turns
+//! into the bytes that a terminal with a US layout sends: ASCII characters, control
+//! characters with Ctrl, and escape sequences for the cursor keys. Key repeat happens in
+//! the keyboard. The mouse at the second port of the controller is ignored.
+
+use crate::hw::acpi;
+use crate::io_port::request_io_port;
+use crate::irq;
+use alloc::vec::Vec;
+use libhrstd::libhedron::CapSel;
+use libhrstd::rt::services::irq::IrqSource;
+use libhrstd::sync::mutex::SimpleMutex;
+use x86::io::{
+    inb,
+    outb,
+};
+
+/// I/O port of the data of the keyboard and of the controller.
+const DATA_PORT: u16 = 0x60;
+/// I/O port of the status register (read) and the command register (write).
+const STATUS_COMMAND_PORT: u16 = 0x64;
+
+/// Set in the status register if the data port has a byte for the CPU.
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+/// Set in the status register while the controller didn't take the last byte of the CPU.
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+/// Set in the status register if the byte in the data port comes from the mouse.
+const STATUS_AUX_DATA: u8 = 1 << 5;
+
+const COMMAND_READ_CONFIG: u8 = 0x20;
+const COMMAND_WRITE_CONFIG: u8 = 0x60;
+
+/// Bit of the configuration byte: the keyboard raises ISA IRQ 1.
+const CONFIG_KEYBOARD_IRQ: u8 = 1 << 0;
+/// Bit of the configuration byte: the clock of the keyboard is off.
+const CONFIG_KEYBOARD_DISABLED: u8 = 1 << 4;
+/// Bit of the configuration byte: the controller translates to scancode set 1.
+const CONFIG_TRANSLATION: u8 = 1 << 6;
+
+const KEYBOARD_ISA_IRQ: u8 = 1;
+
+/// Upper bound of the status polls while waiting for the controller. A missing controller
+/// never becomes ready.
+const MAX_STATUS_POLLS: usize = 100_000;
+
+/// Set in the scancode of a released key.
+const RELEASED: u8 = 0x80;
+/// Prefix of the scancodes of the keys that the original XT keyboard didn't have.
+const PREFIX_EXTENDED: u8 = 0xe0;
+/// Prefix of the Pause key, which sends two sequences of three bytes without a release.
+const PREFIX_PAUSE: u8 = 0xe1;
+
+const KEY_CTRL: u8 = 0x1d;
+const KEY_LEFT_SHIFT: u8 = 0x2a;
+const KEY_RIGHT_SHIFT: u8 = 0x36;
+const KEY_ALT: u8 = 0x38;
+const KEY_CAPS_LOCK: u8 = 0x3a;
+const KEY_KEYPAD_7: u8 = 0x47;
+const KEY_KEYPAD_DOT: u8 = 0x53;
+
+/// Bytes of the keys of the main block without Shift, indexed by scancode. Backspace sends
+/// DEL, like most terminals do. 0 for the modifiers.
+const KEYS: &[u8; 58] = b"\0\x1b1234567890-=\x7f\tqwertyuiop[]\r\0asdfghjkl;'`\0\\zxcvbnm,./\0*\0 ";
+/// Same as [`KEYS`], but with Shift.
+const KEYS_SHIFT: &[u8; 58] =
+    b"\0\x1b!@#$%^&*()_+\x7f\tQWERTYUIOP{}\r\0ASDFGHJKL:\"~\0|ZXCVBNM<>?\0*\0 ";
+/// Bytes of the keypad from [`KEY_KEYPAD_7`] to [`KEY_KEYPAD_DOT`]. Num Lock is ignored.
+const KEYPAD: &[u8; 13] = b"789-456+1230.";
+
+/// The keyboard, once [`init`] found the controller.
+static KEYBOARD: SimpleMutex<Option<Keyboard>> = SimpleMutex::new(None);
+
+/// Sets up the controller and registers the interrupt handler of the keyboard. Returns
+/// false if the platform has no PS/2 controller.
+pub fn init(root_pd_sel: CapSel) -> bool {
+    if request_io_port(root_pd_sel, DATA_PORT).is_err()
+        || request_io_port(root_pd_sel, STATUS_COMMAND_PORT).is_err()
+    {
+        log::warn!("can't get the I/O ports of the PS/2 controller");
+        return false;
+    }
+    // the bus returns all ones without a controller
+    if unsafe { inb(STATUS_COMMAND_PORT) } == 0xff {
+        return false;
+    }
+    // discards what the keyboard sent during boot
+    for _ in 0..MAX_STATUS_POLLS {
+        if unsafe { inb(STATUS_COMMAND_PORT) } & STATUS_OUTPUT_FULL == 0 {
+            break;
+        }
+        unsafe { inb(DATA_PORT) };
+    }
+    let config = match write_command(COMMAND_READ_CONFIG).and_then(|_| read_data()) {
+        Ok(config) => config,
+        Err(()) => {
+            log::warn!("PS/2 controller doesn't respond");
+            return false;
+        }
+    };
+    let config = (config | CONFIG_KEYBOARD_IRQ | CONFIG_TRANSLATION) & !CONFIG_KEYBOARD_DISABLED;
+    if write_command(COMMAND_WRITE_CONFIG)
+        .and_then(|_| write_data(config))
+        .is_err()
+    {
+        log::warn!("can't configure the PS/2 controller");
+        return false;
+    }
+    KEYBOARD.lock().replace(Keyboard::new());
+
+    let gsi = acpi::madt().map_or(KEYBOARD_ISA_IRQ as u32, |madt| {
+        madt.isa_gsi(KEYBOARD_ISA_IRQ)
+    });
+    match irq::register_handler(IrqSource::Gsi(gsi), keyboard_interrupt) {
+        Ok(_) => log::info!("PS/2 keyboard uses GSI {}", gsi),
+        Err(e) => log::warn!("no interrupt for the PS/2 keyboard ({:?}); polling", e),
+    }
+    true
+}
+
+/// Fetches the pending scancodes without an interrupt.
+pub fn poll() {
+    read_scancodes();
+}
+
+/// Interrupt handler of the keyboard.
+fn keyboard_interrupt(_gsi: u32) {
+    read_scancodes();
+}
+
+/// Reads all pending scancodes and adds their bytes to the input. The lock of the
+/// keyboard keeps the order of the scancodes if the interrupt thread and a poll race.
+fn read_scancodes() {
+    let mut keyboard = KEYBOARD.lock();
+    let keyboard = match keyboard.as_mut() {
+        Some(keyboard) => keyboard,
+        None => return,
+    };
+    let mut bytes = Vec::new();
+    loop {
+        let status = unsafe { inb(STATUS_COMMAND_PORT) };
+        if status & STATUS_OUTPUT_FULL == 0 {
+            break;
+        }
+        let data = unsafe { inb(DATA_PORT) };
+        if status & STATUS_AUX_DATA == 0 {
+            keyboard.handle_scancode(data, &mut bytes);
+        }
+    }
+    super::push(&bytes);
+}
+
+fn write_command(command: u8) -> Result<(), ()> {
+    wait_for_status(STATUS_INPUT_FULL, false)?;
+    unsafe { outb(STATUS_COMMAND_PORT, command) };
+    Ok(())
+}
+
+fn write_data(byte: u8) -> Result<(), ()> {
+    wait_for_status(STATUS_INPUT_FULL, false)?;
+    unsafe { outb(DATA_PORT, byte) };
+    Ok(())
+}
+
+fn read_data() -> Result<u8, ()> {
+    wait_for_status(STATUS_OUTPUT_FULL, true)?;
+    Ok(unsafe { inb(DATA_PORT) })
+}
+
+/// Polls the status register until the bit has the value.
+fn wait_for_status(bit: u8, set: bool) -> Result<(), ()> {
+    (0..MAX_STATUS_POLLS)
+        .find(|_| (unsafe { inb(STATUS_COMMAND_PORT) } & bit != 0) == set)
+        .map(|_| ())
+        .ok_or(())
+}
+
+/// State of the modifier keys and of multi-byte scancodes.
+#[derive(Debug, Default)]
+pub struct Keyboard {
+    /// The previous byte was [`PREFIX_EXTENDED`].
+    extended: bool,
+    /// Remaining bytes of the Pause key.
+    skip: u8,
+    left_shift: bool,
+    right_shift: bool,
+    left_ctrl: bool,
+    right_ctrl: bool,
+    alt: bool,
+    caps_lock: bool,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a scancode of set 1 and appends the bytes of the key, if any, to `out`.
+    pub fn handle_scancode(&mut self, scancode: u8, out: &mut Vec<u8>) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        match scancode {
+            PREFIX_EXTENDED => {
+                self.extended = true;
+                return;
+            }
+            PREFIX_PAUSE => {
+                self.skip = 2;
+                return;
+            }
+            _ => {}
+        }
+        let extended = core::mem::replace(&mut self.extended, false);
+        let pressed = scancode & RELEASED == 0;
+        match (extended, scancode & !RELEASED) {
+            (false, KEY_LEFT_SHIFT) => self.left_shift = pressed,
+            (false, KEY_RIGHT_SHIFT) => self.right_shift = pressed,
+            (false, KEY_CTRL) => self.left_ctrl = pressed,
+            (true, KEY_CTRL) => self.right_ctrl = pressed,
+            // the right Alt key is AltGr, which the US layout doesn't use
+            (_, KEY_ALT) => self.alt = pressed,
+            (false, KEY_CAPS_LOCK) if pressed => self.caps_lock = !self.caps_lock,
+            _ if !pressed => {}
+            (true, key) => out.extend_from_slice(extended_key(key)),
+            (false, key) => self.push_key(key, out),
+        }
+    }
+
+    /// Appends the bytes of a key that has no prefix.
+    fn push_key(&self, key: u8, out: &mut Vec<u8>) {
+        let shift = self.left_shift || self.right_shift;
+        let byte = match key {
+            KEY_KEYPAD_7..=KEY_KEYPAD_DOT => KEYPAD[(key - KEY_KEYPAD_7) as usize],
+            key if (key as usize) < KEYS.len() => {
+                let byte = KEYS[key as usize];
+                if byte.is_ascii_lowercase() {
+                    if shift != self.caps_lock {
+                        byte.to_ascii_uppercase()
+                    } else {
+                        byte
+                    }
+                } else if shift {
+                    KEYS_SHIFT[key as usize]
+                } else {
+                    byte
+                }
+            }
+            // function keys etc.
+            _ => 0,
+        };
+        if byte == 0 {
+            return;
+        }
+        // Alt sends an escape first, like the meta key of xterm
+        if self.alt {
+            out.push(0x1b);
+        }
+        if (self.left_ctrl || self.right_ctrl) && matches!(byte, b'@'..=b'_' | b'a'..=b'z') {
+            out.push(byte & 0x1f);
+        } else {
+            out.push(byte);
+        }
+    }
+}
+
+/// Returns the bytes of a key with [`PREFIX_EXTENDED`]: the escape sequences of xterm for
+/// the cursor keys and the block above them.
+fn extended_key(key: u8) -> &'static [u8] {
+    match key {
+        // keypad
+        0x1c => b"\r",
+        0x35 => b"/",
+        0x47 => b"\x1b[H",
+        0x48 => b"\x1b[A",
+        0x49 => b"\x1b[5~",
+        0x4b => b"\x1b[D",
+        0x4d => b"\x1b[C",
+        0x4f => b"\x1b[F",
+        0x50 => b"\x1b[B",
+        0x51 => b"\x1b[6~",
+        0x52 => b"\x1b[2~",
+        0x53 => b"\x1b[3~",
+        _ => b"",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_scancodes(keyboard: &mut Keyboard, scancodes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        scancodes
+            .iter()
+            .for_each(|scancode| keyboard.handle_scancode(*scancode, &mut out));
+        out
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let mut keyboard = Keyboard::new();
+        // "ls /", Enter; key releases send nothing
+        assert_eq!(
+            type_scancodes(
+                &mut keyboard,
+                &[0x26, 0xa6, 0x1f, 0x9f, 0x39, 0xb9, 0x35, 0xb5, 0x1c, 0x9c]
+            ),
+            b"ls /\r"
+        );
+        // Shift+a, Shift+1, right Shift+/
+        assert_eq!(
+            type_scancodes(&mut keyboard, &[0x2a, 0x1e, 0x02, 0xaa, 0x36, 0x35, 0xb6]),
+            b"A!?"
+        );
+        // Caps Lock affects only letters and Shift inverts it
+        assert_eq!(
+            type_scancodes(&mut keyboard, &[0x3a, 0xba, 0x1e, 0x02, 0x2a, 0x1e, 0xaa]),
+            b"A1a"
+        );
+        type_scancodes(&mut keyboard, &[0x3a, 0xba]);
+        // Ctrl+C, right Ctrl+D, Alt+b, Backspace
+        assert_eq!(
+            type_scancodes(
+                &mut keyboard,
+                &[0x1d, 0x2e, 0x9d, 0xe0, 0x1d, 0x20, 0xe0, 0x9d, 0x38, 0x30, 0xb8, 0x0e]
+            ),
+            b"\x03\x04\x1bb\x7f"
+        );
+        // cursor up and left, keypad Enter, keypad 5, F1
+        assert_eq!(
+            type_scancodes(
+                &mut keyboard,
+                &[0xe0, 0x48, 0xe0, 0xc8, 0xe0, 0x4b, 0xe0, 0x1c, 0x4c, 0x3b]
+            ),
+            b"\x1b[A\x1b[D\r5"
+        );
+        // Pause, then the fake Shift of Print Screen doesn't change the case
+        assert_eq!(
+            type_scancodes(
+                &mut keyboard,
+                &[0xe1, 0x1d, 0x45, 0xe1, 0x9d, 0xc5, 0xe0, 0x2a, 0x1e]
+            ),
+            b"a"
+        );
+    }
+}
@@ -0,0 +1,17 @@
+//! Abstractions over hardware that the roottask uses. Hedron owns most of the platform
+//! devices (e.g. the local APIC), therefore some of these abstractions drive the hardware
+//! indirectly via Hedron system calls.
+
+pub mod acpi;
+pub mod framebuffer;
+pub mod input;
+pub mod net;
+pub mod pci;
+pub mod pit;
+pub mod pmu;
+pub mod rtc;
+pub mod timer;
+pub mod virtio;
+pub mod virtio_9p;
+pub mod virtio_console;
+pub mod virtio_net;
@@ -0,0 +1,21 @@
+//! Module for [`NetDevice`].
+
+use alloc::vec::Vec;
+use libhrstd::rt::services::network::{
+    MacAddress,
+    NetworkError,
+};
+
+/// Ethernet device that the network stack uses to send and receive frames. All
+/// operations are non-blocking.
+pub trait NetDevice {
+    /// The hardware address of the device.
+    fn mac(&self) -> MacAddress;
+
+    /// Sends a single frame (without the frame check sequence). Fails with
+    /// [`NetworkError::WouldBlock`] if all transmit buffers are in use.
+    fn transmit(&mut self, frame: &[u8]) -> Result<(), NetworkError>;
+
+    /// Takes the next received frame, if there is one.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
@@ -0,0 +1,344 @@
+//! The PCI functions of the system. The roottask enumerates the buses once at boot and
+//! keeps the result, because sizing the BARs disturbs drivers that use the device. Each
+//! function has at most one owner, i.e. the roottask or a driver process, see [`claim`].
+
+use super::{
+    PciAddress,
+    PciConfigSpace,
+    COMMAND_IO_SPACE,
+    COMMAND_MEMORY_SPACE,
+    REG_BAR0,
+    REG_CLASS,
+    REG_COMMAND,
+    REG_HEADER_TYPE,
+    REG_INTERRUPT,
+    REG_VENDOR_DEVICE,
+};
+use alloc::vec::Vec;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::irq::PciFunction;
+use libhrstd::rt::services::pci::{
+    PciBarInfo,
+    PciBarKind,
+    PciDeviceInfo,
+    PciServiceError,
+    PciServiceResponse,
+    PCI_BAR_COUNT,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// All PCI functions in the order of the enumeration.
+static DEVICES: SimpleMutex<Vec<PciDeviceEntry>> = SimpleMutex::new(Vec::new());
+
+/// Who uses a PCI function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PciOwner {
+    /// A driver of the roottask, e.g. the network driver.
+    Roottask,
+    Process(ProcessId),
+}
+
+#[derive(Debug)]
+struct PciDeviceEntry {
+    info: PciDeviceInfo,
+    owner: Option<PciOwner>,
+}
+
+/// Reads the configuration of all functions. See [`super::init`].
+pub fn enumerate(pci: &PciConfigSpace) {
+    let devices = pci
+        .devices()
+        .map(|device| PciDeviceEntry {
+            info: pci.device_info(device.addr),
+            owner: None,
+        })
+        .collect::<Vec<_>>();
+    for entry in &devices {
+        let info = &entry.info;
+        log::info!(
+            "PCI {:02x}:{:02x}.{}: {:04x}:{:04x} class {:02x}.{:02x}",
+            info.function.bus,
+            info.function.device,
+            info.function.function,
+            info.vendor_id,
+            info.device_id,
+            info.class,
+            info.subclass
+        );
+    }
+    *DEVICES.lock() = devices;
+}
+
+/// Returns the function with the index in the order of the enumeration.
+pub fn device(index: usize) -> Option<PciDeviceInfo> {
+    DEVICES.lock().get(index).map(|entry| entry.info.clone())
+}
+
+/// Makes `owner` the owner of the function, unless somebody else owns it. Claiming a
+/// function twice is fine.
+pub fn claim(function: PciFunction, owner: PciOwner) -> PciServiceResponse<PciDeviceInfo> {
+    let mut devices = DEVICES.lock();
+    let entry = entry_mut(&mut devices, function)?;
+    match entry.owner {
+        Some(current) if current != owner => Err(PciServiceError::Busy),
+        _ => {
+            entry.owner = Some(owner);
+            Ok(entry.info.clone())
+        }
+    }
+}
+
+/// Frees a function that `owner` claimed.
+pub fn release(function: PciFunction, owner: PciOwner) -> PciServiceResponse<()> {
+    let mut devices = DEVICES.lock();
+    let entry = entry_mut(&mut devices, function)?;
+    if entry.owner != Some(owner) {
+        return Err(PciServiceError::NotClaimed);
+    }
+    entry.owner = None;
+    Ok(())
+}
+
+/// Returns all functions that `owner` claimed.
+pub fn claimed_by(owner: PciOwner) -> Vec<PciFunction> {
+    DEVICES
+        .lock()
+        .iter()
+        .filter(|entry| entry.owner == Some(owner))
+        .map(|entry| entry.info.function)
+        .collect()
+}
+
+/// Returns the owner of the function, if somebody claimed it.
+pub fn owner(function: PciFunction) -> Option<PciOwner> {
+    DEVICES
+        .lock()
+        .iter()
+        .find(|entry| entry.info.function == function)
+        .and_then(|entry| entry.owner)
+}
+
+/// Checks if a function that `owner` claimed raises its interrupt pin on the GSI, see
+/// [`PciDeviceInfo::interrupt_line`].
+pub fn uses_interrupt_line(owner: PciOwner, gsi: u32) -> bool {
+    DEVICES.lock().iter().any(|entry| {
+        entry.owner == Some(owner)
+            && entry.info.interrupt_pin != 0
+            && entry.info.interrupt_line as u32 == gsi
+    })
+}
+
+fn entry_mut(
+    devices: &mut [PciDeviceEntry],
+    function: PciFunction,
+) -> PciServiceResponse<&mut PciDeviceEntry> {
+    devices
+        .iter_mut()
+        .find(|entry| entry.info.function == function)
+        .ok_or(PciServiceError::NoSuchDevice)
+}
+
+impl PciConfigSpace {
+    /// Reads the IDs, the class, the BARs, the interrupt pin, and the capabilities of the
+    /// function. Sizes the BARs, therefore the device must not be in use.
+    pub fn device_info(&self, addr: PciAddress) -> PciDeviceInfo {
+        let ids = self.read(addr, REG_VENDOR_DEVICE);
+        let class = self.read(addr, REG_CLASS);
+        let header_type = (self.read(addr, REG_HEADER_TYPE) >> 16) as u8 & 0x7f;
+        let interrupt = self.read(addr, REG_INTERRUPT);
+        let bar_count = match header_type {
+            0 => PCI_BAR_COUNT,
+            1 => 2,
+            _ => 0,
+        };
+        PciDeviceInfo {
+            function: addr.into(),
+            vendor_id: ids as u16,
+            device_id: (ids >> 16) as u16,
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            prog_if: (class >> 8) as u8,
+            revision: class as u8,
+            header_type,
+            bars: self.size_bars(addr, bar_count),
+            interrupt_pin: (interrupt >> 8) as u8,
+            interrupt_line: interrupt as u8,
+            capabilities: self.capabilities(addr),
+        }
+    }
+
+    /// Determines the sizes of the BARs: the bits that stay zero after writing all ones
+    /// are the size. The decoding is off meanwhile, because the BARs point to nonsense.
+    fn size_bars(&self, addr: PciAddress, bar_count: usize) -> [Option<PciBarInfo>; PCI_BAR_COUNT] {
+        let mut bars = [None; PCI_BAR_COUNT];
+        let command = self.read(addr, REG_COMMAND);
+        self.write(
+            addr,
+            REG_COMMAND,
+            command & !(COMMAND_IO_SPACE | COMMAND_MEMORY_SPACE),
+        );
+        let mut index = 0;
+        while index < bar_count {
+            let offset = REG_BAR0 + index as u8 * 4;
+            let (low, low_mask) = self.probe_register(addr, offset);
+            let is_64bit = is_64bit_bar(low) && index + 1 < bar_count;
+            let (high, high_mask) = if is_64bit {
+                self.probe_register(addr, offset + 4)
+            } else {
+                (0, u32::MAX)
+            };
+            bars[index] = decode_bar(low, high, low_mask, high_mask);
+            index += if is_64bit { 2 } else { 1 };
+        }
+        self.write(addr, REG_COMMAND, command);
+        bars
+    }
+
+    /// Returns the value of the register and the value after writing all ones. Restores
+    /// the value afterwards.
+    fn probe_register(&self, addr: PciAddress, offset: u8) -> (u32, u32) {
+        let val = self.read(addr, offset);
+        self.write(addr, offset, u32::MAX);
+        let mask = self.read(addr, offset);
+        self.write(addr, offset, val);
+        (val, mask)
+    }
+}
+
+const fn is_64bit_bar(low: u32) -> bool {
+    low & 1 == 0 && (low >> 1) & 0x3 == 0x2
+}
+
+/// Decodes a BAR from its value and the value after writing all ones. `high` and
+/// `high_mask` are the upper register of a 64-bit BAR. Returns `None` for unused BARs and
+/// BARs that the firmware didn't assign.
+fn decode_bar(low: u32, high: u32, low_mask: u32, high_mask: u32) -> Option<PciBarInfo> {
+    let (kind, base, size) = if low & 1 != 0 {
+        let mask = low_mask & !0x3;
+        if mask == 0 {
+            return None;
+        }
+        // I/O BARs decode at most 16 bits; the upper bits may read back as zero
+        let size = (!(mask | 0xffff_0000)).wrapping_add(1);
+        (PciBarKind::Io, (low & !0x3) as u64, size as u64)
+    } else {
+        let is_64bit = is_64bit_bar(low);
+        let high_mask = if is_64bit { high_mask } else { u32::MAX };
+        let mask = (high_mask as u64) << 32 | (low_mask & !0xf) as u64;
+        if mask == 0xffff_ffff_0000_0000 || mask == 0 {
+            return None;
+        }
+        let kind = PciBarKind::Memory {
+            prefetchable: low & (1 << 3) != 0,
+            is_64bit,
+        };
+        let base = (high as u64) << 32 | (low & !0xf) as u64;
+        (kind, base, (!mask).wrapping_add(1))
+    };
+    if base == 0 {
+        return None;
+    }
+    Some(PciBarInfo { kind, base, size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_bar() {
+        // unused
+        assert_eq!(decode_bar(0, 0, 0, 0), None);
+        // 32 I/O ports
+        assert_eq!(
+            decode_bar(0xc001, 0, 0xffff_ffe1, 0),
+            Some(PciBarInfo {
+                kind: PciBarKind::Io,
+                base: 0xc000,
+                size: 32,
+            })
+        );
+        // the upper 16 bits of an I/O BAR read back as zero
+        assert_eq!(decode_bar(0xc041, 0, 0xffc1, 0).unwrap().size, 64);
+        // 4 KiB of 32-bit memory
+        assert_eq!(
+            decode_bar(0xfebd_1000, 0, 0xffff_f000, 0),
+            Some(PciBarInfo {
+                kind: PciBarKind::Memory {
+                    prefetchable: false,
+                    is_64bit: false,
+                },
+                base: 0xfebd_1000,
+                size: 0x1000,
+            })
+        );
+        // 16 KiB of prefetchable 64-bit memory above 4 GiB
+        assert_eq!(
+            decode_bar(0x0000_400c, 0x8, 0xffff_c00c, 0xffff_ffff),
+            Some(PciBarInfo {
+                kind: PciBarKind::Memory {
+                    prefetchable: true,
+                    is_64bit: true,
+                },
+                base: 0x8_0000_4000,
+                size: 0x4000,
+            })
+        );
+        // 8 GiB of 64-bit memory: the size is in the upper register
+        assert_eq!(
+            decode_bar(0x4, 0x4, 0x4, 0xffff_fffe).unwrap().size,
+            0x2_0000_0000
+        );
+        // not assigned by the firmware
+        assert_eq!(decode_bar(0x0, 0, 0xffff_f000, 0), None);
+    }
+
+    #[test]
+    fn test_owner_and_interrupt_line() {
+        let function = |device| PciFunction {
+            bus: 0,
+            device,
+            function: 0,
+        };
+        let entry = |device, interrupt_pin, owner| PciDeviceEntry {
+            info: PciDeviceInfo {
+                function: function(device),
+                vendor_id: 0x1af4,
+                device_id: 0x1000,
+                class: 2,
+                subclass: 0,
+                prog_if: 0,
+                revision: 0,
+                header_type: 0,
+                bars: [None; PCI_BAR_COUNT],
+                interrupt_pin,
+                interrupt_line: 11,
+                capabilities: Vec::new(),
+            },
+            owner,
+        };
+        *DEVICES.lock() = alloc::vec![
+            entry(1, 0, Some(PciOwner::Roottask)),
+            entry(2, 1, Some(PciOwner::Process(5))),
+        ];
+        assert_eq!(owner(function(1)), Some(PciOwner::Roottask));
+        assert_eq!(owner(function(3)), None);
+        // the function of the roottask has no interrupt pin
+        assert!(!uses_interrupt_line(PciOwner::Roottask, 11));
+        assert!(uses_interrupt_line(PciOwner::Process(5), 11));
+        assert!(!uses_interrupt_line(PciOwner::Process(5), 10));
+
+        claim(function(1), PciOwner::Process(5)).unwrap_err();
+        release(function(1), PciOwner::Roottask).unwrap();
+        claim(function(1), PciOwner::Process(5)).unwrap();
+        assert_eq!(claimed_by(PciOwner::Process(5)).len(), 2);
+        DEVICES.lock().clear();
+    }
+
+    #[test]
+    fn test_is_64bit_bar() {
+        assert!(is_64bit_bar(0xfebf_000c));
+        assert!(!is_64bit_bar(0xfebf_0008));
+        assert!(!is_64bit_bar(0xc005));
+    }
+}
@@ -0,0 +1,378 @@
+//! Access to the configuration space of PCI devices. Hedron doesn't drive PCI devices
+//! itself, therefore the roottask can take them over. The roottask uses the memory-mapped
+//! configuration space (ECAM) that the ACPI MCFG table describes, if the platform has one,
+//! see [`super::acpi::Mcfg`], and the legacy I/O ports (configuration mechanism #1) otherwise. Unlike the I/O ports,
+//! ECAM accesses are a single instruction and therefore safe on all CPUs at the same time.
+//!
+//! [`init`] enumerates all functions once, see [`devices`].
+
+mod devices;
+
+pub use devices::*;
+
+use super::acpi;
+use crate::io_port::request_io_ports;
+use crate::mem::map_phys_into_roottask;
+use alloc::vec::Vec;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::{
+    CapSel,
+    CrdPortIO,
+    HIP,
+};
+use libhrstd::rt::services::irq::PciFunction;
+use libhrstd::rt::services::pci::PciCapability;
+use libhrstd::sync::mutex::SimpleMutex;
+use x86::io::{
+    inl,
+    outl,
+};
+
+/// I/O port that selects the register of the configuration space.
+const CONFIG_ADDRESS_PORT: u16 = 0xcf8;
+/// I/O port that accesses the selected register.
+const CONFIG_DATA_PORT: u16 = 0xcfc;
+
+const VENDOR_ID_INVALID: u16 = 0xffff;
+
+const REG_VENDOR_DEVICE: u8 = 0x00;
+const REG_COMMAND: u8 = 0x04;
+const REG_CLASS: u8 = 0x08;
+const REG_HEADER_TYPE: u8 = 0x0c;
+const REG_BAR0: u8 = 0x10;
+const REG_CAPABILITIES: u8 = 0x34;
+const REG_INTERRUPT: u8 = 0x3c;
+
+const COMMAND_IO_SPACE: u32 = 1 << 0;
+const COMMAND_MEMORY_SPACE: u32 = 1 << 1;
+const COMMAND_BUS_MASTER: u32 = 1 << 2;
+/// Bit of the status register (upper half of [`REG_COMMAND`]): the function has a list of
+/// capabilities.
+const STATUS_CAPABILITIES_LIST: u32 = 1 << 20;
+
+/// Size of the configuration space of a function in ECAM.
+const ECAM_FUNCTION_SIZE: u64 = PAGE_SIZE as u64;
+/// Size of the configuration space of a bus in ECAM.
+const ECAM_BUS_SIZE: u64 = 1 << 20;
+
+const CAP_ID_MSI: u8 = 0x05;
+/// Bits of the message control register (upper half of the first register of the MSI
+/// capability).
+const MSI_CONTROL_ENABLE: u32 = 1 << 16;
+const MSI_CONTROL_MULTIPLE_MESSAGE_ENABLE: u32 = 0x7 << 20;
+const MSI_CONTROL_64_BIT: u32 = 1 << 23;
+
+/// The mapped ECAM region. `None` if the platform has none or [`init`] didn't run yet.
+static ECAM: SimpleMutex<Option<Ecam>> = SimpleMutex::new(None);
+
+/// Maps the memory-mapped configuration space, if the HIP or the ACPI MCFG table report
+/// one, and enumerates all PCI functions. Requires [`super::acpi::init`].
+pub fn init(hip: &HIP) {
+    // physical address of the first bus, first bus, and number of buses
+    let ecam_region = if hip.mcfg_base() != 0 {
+        let bus_start = hip.pci_bus_start() as u8;
+        let bus_count = (hip.msfg_size() / ECAM_BUS_SIZE).clamp(1, 256 - bus_start as u64);
+        Some((hip.mcfg_base(), bus_start, bus_count))
+    } else {
+        acpi::mcfg()
+            .and_then(|mcfg| mcfg.segment_0().copied())
+            .map(|entry| {
+                (
+                    entry.bus_start_address(),
+                    entry.bus_start,
+                    entry.bus_count(),
+                )
+            })
+    };
+    if let Some((phys_base, bus_start, bus_count)) = ecam_region {
+        let page_count = (bus_count * ECAM_BUS_SIZE) as usize / PAGE_SIZE;
+        let r_base = map_phys_into_roottask(phys_base, page_count);
+        ECAM.lock().replace(Ecam {
+            r_base,
+            bus_start,
+            bus_count: bus_count as u16,
+        });
+        log::info!(
+            "PCI ECAM at {:#x} for buses {}..{}",
+            phys_base,
+            bus_start,
+            bus_start as u64 + bus_count
+        );
+    }
+    match PciConfigSpace::new(RootCapSpace::RootPd.val()) {
+        Ok(pci) => enumerate(&pci),
+        Err(_) => log::warn!("can't access the PCI configuration space"),
+    }
+}
+
+/// Returns the address of the mapped page of the configuration space of the function, as
+/// Hedron needs it for MSIs. `None` without ECAM.
+pub fn config_page(addr: PciAddress) -> Option<u64> {
+    ECAM.lock().and_then(|ecam| ecam.register(addr, 0))
+}
+
+/// Location of a PCI function.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PciAddress {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl From<PciFunction> for PciAddress {
+    fn from(function: PciFunction) -> Self {
+        Self {
+            bus: function.bus,
+            device: function.device,
+            function: function.function,
+        }
+    }
+}
+
+impl From<PciAddress> for PciFunction {
+    fn from(addr: PciAddress) -> Self {
+        Self {
+            bus: addr.bus,
+            device: addr.device,
+            function: addr.function,
+        }
+    }
+}
+
+/// The mapping of the memory-mapped configuration space of the buses
+/// `bus_start..bus_start + bus_count`.
+#[derive(Copy, Clone, Debug)]
+struct Ecam {
+    /// Address of the configuration space of `bus_start` in the roottask.
+    r_base: u64,
+    bus_start: u8,
+    bus_count: u16,
+}
+
+impl Ecam {
+    /// Returns the address of the register, if the bus is in the mapped range.
+    fn register(self, addr: PciAddress, offset: u8) -> Option<u64> {
+        let bus = addr.bus.checked_sub(self.bus_start)? as u64;
+        if bus >= self.bus_count as u64 {
+            return None;
+        }
+        let function = (addr.device as u64) << 3 | addr.function as u64;
+        Some(
+            self.r_base
+                + bus * ECAM_BUS_SIZE
+                + function * ECAM_FUNCTION_SIZE
+                + (offset & 0xfc) as u64,
+        )
+    }
+}
+
+/// A PCI function that was found during the enumeration.
+#[derive(Copy, Clone, Debug)]
+pub struct PciDevice {
+    pub addr: PciAddress,
+    pub vendor_id: u16,
+    pub device_id: u16,
+}
+
+/// Decoded base address register.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PciBar {
+    /// Base of a range of I/O ports.
+    Io(u16),
+    /// Physical base address of a memory range.
+    Memory(u64),
+}
+
+/// Accessor for the PCI configuration space. Only one instance should exist.
+#[derive(Debug)]
+pub struct PciConfigSpace;
+
+impl PciConfigSpace {
+    /// Requests the I/O ports of the configuration mechanism from the kern PD.
+    pub fn new(root_pd_sel: CapSel) -> Result<Self, ()> {
+        // 8 consecutive ports: CONFIG_ADDRESS and CONFIG_DATA
+        request_io_ports(root_pd_sel, CrdPortIO::new(CONFIG_ADDRESS_PORT, 3)).map_err(|_| ())?;
+        Ok(Self)
+    }
+
+    /// Reads a 32-bit register. `offset` must be 4-byte aligned.
+    pub fn read(&self, addr: PciAddress, offset: u8) -> u32 {
+        if let Some(register) = Self::ecam_register(addr, offset) {
+            return unsafe { core::ptr::read_volatile(register as *const u32) };
+        }
+        unsafe {
+            outl(CONFIG_ADDRESS_PORT, Self::config_address(addr, offset));
+            inl(CONFIG_DATA_PORT)
+        }
+    }
+
+    /// Writes a 32-bit register. `offset` must be 4-byte aligned.
+    pub fn write(&self, addr: PciAddress, offset: u8, val: u32) {
+        if let Some(register) = Self::ecam_register(addr, offset) {
+            return unsafe { core::ptr::write_volatile(register as *mut u32, val) };
+        }
+        unsafe {
+            outl(CONFIG_ADDRESS_PORT, Self::config_address(addr, offset));
+            outl(CONFIG_DATA_PORT, val);
+        }
+    }
+
+    /// Enumerates all functions on all buses by brute force.
+    pub fn devices(&self) -> impl Iterator<Item = PciDevice> + '_ {
+        (0..=u8::MAX)
+            .flat_map(|bus| (0..32).map(move |device| (bus, device)))
+            .flat_map(move |(bus, device)| {
+                let addr = PciAddress {
+                    bus,
+                    device,
+                    function: 0,
+                };
+                let function_count = match self.probe(addr) {
+                    None => 0,
+                    // bit 7 of the header type: multi-function device
+                    Some(_) if self.read(addr, REG_HEADER_TYPE) & (1 << 23) != 0 => 8,
+                    Some(_) => 1,
+                };
+                (0..function_count).filter_map(move |function| {
+                    self.probe(PciAddress {
+                        bus,
+                        device,
+                        function,
+                    })
+                })
+            })
+    }
+
+    /// Returns the first function with the given vendor and one of the given device IDs.
+    pub fn find_device(&self, vendor_id: u16, device_ids: &[u16]) -> Option<PciDevice> {
+        self.devices()
+            .find(|dev| dev.vendor_id == vendor_id && device_ids.contains(&dev.device_id))
+    }
+
+    /// Decodes the base address register with the given index (0-5).
+    pub fn bar(&self, addr: PciAddress, index: u8) -> PciBar {
+        let offset = REG_BAR0 + index * 4;
+        let bar = self.read(addr, offset);
+        if bar & 1 != 0 {
+            PciBar::Io((bar & !0x3) as u16)
+        } else if (bar >> 1) & 0x3 == 0x2 {
+            // 64-bit BAR: the next register holds the upper half
+            let upper = self.read(addr, offset + 4) as u64;
+            PciBar::Memory(upper << 32 | (bar & !0xf) as u64)
+        } else {
+            PciBar::Memory((bar & !0xf) as u64)
+        }
+    }
+
+    /// Enables the decoding of I/O and memory accesses and allows the device to perform DMA.
+    pub fn enable_device(&self, addr: PciAddress) {
+        let command = self.read(addr, REG_COMMAND);
+        self.write(
+            addr,
+            REG_COMMAND,
+            command | COMMAND_IO_SPACE | COMMAND_MEMORY_SPACE | COMMAND_BUS_MASTER,
+        );
+    }
+
+    /// Disables the decoding of I/O and memory accesses and stops DMA of the device.
+    pub fn disable_device(&self, addr: PciAddress) {
+        let command = self.read(addr, REG_COMMAND);
+        self.write(
+            addr,
+            REG_COMMAND,
+            command & !(COMMAND_IO_SPACE | COMMAND_MEMORY_SPACE | COMMAND_BUS_MASTER),
+        );
+    }
+
+    /// Returns the capability list of the function.
+    pub fn capabilities(&self, addr: PciAddress) -> Vec<PciCapability> {
+        let mut capabilities = Vec::new();
+        if self.read(addr, REG_COMMAND) & STATUS_CAPABILITIES_LIST == 0 {
+            return capabilities;
+        }
+        let mut offset = self.read(addr, REG_CAPABILITIES) as u8 & 0xfc;
+        // at most 48 capabilities fit into the configuration space; protects against loops
+        while offset != 0 && capabilities.len() < 48 {
+            let header = self.read(addr, offset);
+            capabilities.push(PciCapability {
+                id: header as u8,
+                offset,
+            });
+            offset = (header >> 8) as u8 & 0xfc;
+        }
+        capabilities
+    }
+
+    /// Returns the offset of the capability with the ID in the capability list of the
+    /// function, if it has one.
+    pub fn capability(&self, addr: PciAddress, id: u8) -> Option<u8> {
+        self.capabilities(addr)
+            .into_iter()
+            .find(|cap| cap.id == id)
+            .map(|cap| cap.offset)
+    }
+
+    /// Returns whether the function has an MSI capability.
+    pub fn has_msi(&self, addr: PciAddress) -> bool {
+        self.capability(addr, CAP_ID_MSI).is_some()
+    }
+
+    /// Programs the MSI capability of the function with a single message and enables it.
+    /// Afterwards, the function signals interrupts with the message instead of its
+    /// interrupt pin. Returns `false` if the function has no MSI capability.
+    pub fn enable_msi(&self, addr: PciAddress, address: u64, data: u16) -> bool {
+        let offset = match self.capability(addr, CAP_ID_MSI) {
+            Some(offset) => offset,
+            None => return false,
+        };
+        let control = self.read(addr, offset);
+        self.write(addr, offset + 4, address as u32);
+        let data_offset = if control & MSI_CONTROL_64_BIT != 0 {
+            self.write(addr, offset + 8, (address >> 32) as u32);
+            offset + 12
+        } else {
+            offset + 8
+        };
+        // the upper half is reserved or the extended message data
+        let data_reg = self.read(addr, data_offset);
+        self.write(addr, data_offset, data_reg & 0xffff_0000 | data as u32);
+        let control = control & !MSI_CONTROL_MULTIPLE_MESSAGE_ENABLE | MSI_CONTROL_ENABLE;
+        self.write(addr, offset, control);
+        true
+    }
+
+    /// Disables the MSI capability of the function, if it has one.
+    pub fn disable_msi(&self, addr: PciAddress) {
+        if let Some(offset) = self.capability(addr, CAP_ID_MSI) {
+            let control = self.read(addr, offset);
+            self.write(addr, offset, control & !MSI_CONTROL_ENABLE);
+        }
+    }
+
+    fn probe(&self, addr: PciAddress) -> Option<PciDevice> {
+        let ids = self.read(addr, REG_VENDOR_DEVICE);
+        let vendor_id = ids as u16;
+        if vendor_id == VENDOR_ID_INVALID {
+            None
+        } else {
+            Some(PciDevice {
+                addr,
+                vendor_id,
+                device_id: (ids >> 16) as u16,
+            })
+        }
+    }
+
+    fn ecam_register(addr: PciAddress, offset: u8) -> Option<u64> {
+        ECAM.lock().and_then(|ecam| ecam.register(addr, offset))
+    }
+
+    const fn config_address(addr: PciAddress, offset: u8) -> u32 {
+        1 << 31
+            | (addr.bus as u32) << 16
+            | (addr.device as u32) << 11
+            | (addr.function as u32) << 8
+            | (offset & 0xfc) as u32
+    }
+}
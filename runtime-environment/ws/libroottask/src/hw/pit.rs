@@ -0,0 +1,94 @@
+//! Minimal driver for the programmable interval timer (PIT, Intel 8253/8254) of PC
+//! platforms. The roottask uses it only to measure the frequency of the TSC if Hedron
+//! doesn't report it, see [`crate::time::init`]. The PIT runs with a fixed frequency of
+//! [`PIT_FREQ_HZ`]. Channel 2 can be gated and polled via the NMI status and control port
+//! without interrupts.
+
+use crate::io_port::{
+    request_io_port,
+    request_io_ports,
+};
+use libhrstd::libhedron::{
+    CapSel,
+    CrdPortIO,
+};
+use x86::io::{
+    inb,
+    outb,
+};
+
+/// Fixed input frequency of the PIT.
+pub const PIT_FREQ_HZ: u64 = 1_193_182;
+
+/// I/O port of the counter of channel 2.
+const CHANNEL_2_PORT: u16 = 0x42;
+/// I/O port of the mode/command register.
+const COMMAND_PORT: u16 = 0x43;
+/// NMI status and control port. Bit 0 gates channel 2, bit 1 connects it to the speaker.
+const CONTROL_PORT: u16 = 0x61;
+
+/// Channel 2, low and high byte, mode 0 (interrupt on terminal count), binary.
+const COMMAND_CHANNEL_2_ONESHOT: u8 = 0b1011_0000;
+const CONTROL_GATE_2: u8 = 1 << 0;
+const CONTROL_SPEAKER: u8 = 1 << 1;
+/// Set in the control port once channel 2 reached its terminal count.
+const CONTROL_OUT_2: u8 = 1 << 5;
+
+/// Duration of the calibration in PIT ticks: about 10 ms.
+const CALIBRATION_TICKS: u16 = 11932;
+
+/// Accessor for the PIT. Only one instance should exist.
+#[derive(Debug)]
+pub struct Pit;
+
+impl Pit {
+    /// Requests the I/O ports of channel 2 from the kern PD.
+    pub fn new(root_pd_sel: CapSel) -> Result<Self, ()> {
+        // 2 consecutive ports: channel 2 and command
+        request_io_ports(root_pd_sel, CrdPortIO::new(CHANNEL_2_PORT, 1)).map_err(|_| ())?;
+        request_io_port(root_pd_sel, CONTROL_PORT).map_err(|_| ())?;
+        Ok(Self)
+    }
+
+    /// Measures the frequency of the TSC in kHz against the PIT. Busy-waits for about
+    /// 10 ms.
+    pub fn measure_tsc_freq_khz(&self) -> u64 {
+        unsafe {
+            // gate on, speaker off
+            let control = inb(CONTROL_PORT);
+            outb(CONTROL_PORT, (control & !CONTROL_SPEAKER) | CONTROL_GATE_2);
+
+            outb(COMMAND_PORT, COMMAND_CHANNEL_2_ONESHOT);
+            outb(CHANNEL_2_PORT, CALIBRATION_TICKS as u8);
+            outb(CHANNEL_2_PORT, (CALIBRATION_TICKS >> 8) as u8);
+
+            let begin = x86::time::rdtscp();
+            while inb(CONTROL_PORT) & CONTROL_OUT_2 == 0 {
+                core::hint::spin_loop();
+            }
+            let end = x86::time::rdtscp();
+
+            outb(CONTROL_PORT, control);
+            tsc_freq_khz(end - begin, CALIBRATION_TICKS as u64)
+        }
+    }
+}
+
+/// Returns the TSC frequency in kHz if the TSC advanced by `tsc_ticks` during `pit_ticks`.
+fn tsc_freq_khz(tsc_ticks: u64, pit_ticks: u64) -> u64 {
+    (tsc_ticks as u128 * PIT_FREQ_HZ as u128 / (pit_ticks as u128 * 1000)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsc_freq_khz() {
+        // a 3 GHz TSC during the calibration
+        let tsc_ticks = CALIBRATION_TICKS as u64 * 3_000_000_000 / PIT_FREQ_HZ;
+        let freq_khz = tsc_freq_khz(tsc_ticks, CALIBRATION_TICKS as u64);
+        assert!((2_999_999..=3_000_000).contains(&freq_khz));
+        assert_eq!(tsc_freq_khz(PIT_FREQ_HZ, PIT_FREQ_HZ), PIT_FREQ_HZ / 1000);
+    }
+}
@@ -0,0 +1,187 @@
+//! Driver for the architectural performance monitoring unit (PMU) of Intel CPUs. Each CPU
+//! has a few general-purpose counters; the event select MSR of a counter chooses the
+//! event that it counts. CPUID leaf `0xa` reports the number and the width of the counters
+//! and the architectural events that the CPU supports.
+//!
+//! The roottask can't execute `rdmsr` and `wrmsr` itself but accesses the MSRs via
+//! Hedron, see [`sys_pd_ctrl_msr_read`]. Hedron accesses the MSRs of the CPU that executes
+//! the system call, hence all functions operate on the counters of the current CPU.
+
+use alloc::vec::Vec;
+use libhrstd::libhedron::syscall::{
+    sys_pd_ctrl_msr_read,
+    sys_pd_ctrl_msr_write,
+    SyscallError,
+};
+use libhrstd::rt::services::perf_counter::PerfEvent;
+use x86::msr::{
+    IA32_PERFEVTSEL0,
+    IA32_PERF_GLOBAL_CTRL,
+    IA32_PMC0,
+};
+
+/// CPUID leaf of the architectural performance monitoring.
+const CPUID_LEAF_PMU: u32 = 0xa;
+
+/// Bits of the event select MSRs.
+const PERFEVTSEL_USR: u64 = 1 << 16;
+const PERFEVTSEL_OS: u64 = 1 << 17;
+const PERFEVTSEL_EN: u64 = 1 << 22;
+
+/// Capabilities of the PMU of a CPU, as reported by CPUID leaf `0xa`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PmuInfo {
+    /// Version of the architectural performance monitoring. 0 if there is none.
+    pub version: u8,
+    pub num_counters: u8,
+    pub counter_width: u8,
+    /// Number of valid bits in `unavailable_events`.
+    events_len: u8,
+    /// A set bit means that the CPU doesn't support the architectural event with this
+    /// index.
+    unavailable_events: u32,
+}
+
+impl PmuInfo {
+    /// Returns the PMU of the current CPU.
+    pub fn current() -> Self {
+        let res = x86::cpuid::native_cpuid::cpuid_count(CPUID_LEAF_PMU, 0);
+        Self::from_cpuid(res.eax, res.ebx)
+    }
+
+    /// Decodes EAX and EBX of CPUID leaf `0xa`.
+    pub const fn from_cpuid(eax: u32, ebx: u32) -> Self {
+        Self {
+            version: eax as u8,
+            num_counters: (eax >> 8) as u8,
+            counter_width: (eax >> 16) as u8,
+            events_len: (eax >> 24) as u8,
+            unavailable_events: ebx,
+        }
+    }
+
+    /// Returns true if the CPU can count the event.
+    pub const fn supports(&self, event: PerfEvent) -> bool {
+        let index = event_index(event);
+        self.version > 0 && index < self.events_len && self.unavailable_events & (1 << index) == 0
+    }
+
+    /// Returns all events that the CPU can count.
+    pub fn events(&self) -> Vec<PerfEvent> {
+        PerfEvent::ALL
+            .into_iter()
+            .filter(|event| self.supports(*event))
+            .collect()
+    }
+
+    /// Masks the bits of a counter value that the counter implements.
+    const fn mask(&self, value: u64) -> u64 {
+        if self.counter_width >= 64 {
+            value
+        } else {
+            value & ((1 << self.counter_width) - 1)
+        }
+    }
+}
+
+/// Index of the event in EBX of CPUID leaf `0xa`.
+const fn event_index(event: PerfEvent) -> u8 {
+    match event {
+        PerfEvent::CoreCycles => 0,
+        PerfEvent::InstructionsRetired => 1,
+        PerfEvent::LlcMisses => 4,
+        PerfEvent::BranchMispredictions => 6,
+    }
+}
+
+/// Returns the value of an event select MSR that counts the event in user and kernel mode.
+pub const fn event_select(event: PerfEvent) -> u64 {
+    // event number and unit mask of the architectural events
+    let (event_num, umask) = match event {
+        PerfEvent::CoreCycles => (0x3c, 0x00),
+        PerfEvent::InstructionsRetired => (0xc0, 0x00),
+        PerfEvent::LlcMisses => (0x2e, 0x41),
+        PerfEvent::BranchMispredictions => (0xc5, 0x00),
+    };
+    event_num | umask << 8 | PERFEVTSEL_USR | PERFEVTSEL_OS | PERFEVTSEL_EN
+}
+
+/// Bits of `IA32_PERF_GLOBAL_CTRL` that enable the first `num` general-purpose counters.
+const fn global_enable_bits(num: usize) -> u64 {
+    (1 << num) - 1
+}
+
+/// Resets the first counters and lets counter `i` count `events[i]`. The caller checks
+/// that the PMU supports the events and has enough counters.
+pub fn start(info: &PmuInfo, events: &[PerfEvent]) -> Result<(), SyscallError> {
+    disable(info, events.len())?;
+    for (i, event) in events.iter().enumerate() {
+        sys_pd_ctrl_msr_write(IA32_PMC0 + i as u32, 0)?;
+        sys_pd_ctrl_msr_write(IA32_PERFEVTSEL0 + i as u32, event_select(*event))?;
+    }
+    // since version 2, the counters only count if they are enabled globally, too
+    if info.version >= 2 {
+        let global_ctrl = sys_pd_ctrl_msr_read(IA32_PERF_GLOBAL_CTRL)?;
+        sys_pd_ctrl_msr_write(
+            IA32_PERF_GLOBAL_CTRL,
+            global_ctrl | global_enable_bits(events.len()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the values of the first `num` counters.
+pub fn read(info: &PmuInfo, num: usize) -> Result<Vec<u64>, SyscallError> {
+    (0..num)
+        .map(|i| sys_pd_ctrl_msr_read(IA32_PMC0 + i as u32).map(|value| info.mask(value)))
+        .collect()
+}
+
+/// Stops the first `num` counters. Their values stay readable until the next [`start`].
+pub fn disable(info: &PmuInfo, num: usize) -> Result<(), SyscallError> {
+    if info.version >= 2 {
+        let global_ctrl = sys_pd_ctrl_msr_read(IA32_PERF_GLOBAL_CTRL)?;
+        sys_pd_ctrl_msr_write(
+            IA32_PERF_GLOBAL_CTRL,
+            global_ctrl & !global_enable_bits(num),
+        )?;
+    }
+    for i in 0..num {
+        sys_pd_ctrl_msr_write(IA32_PERFEVTSEL0 + i as u32, 0)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pmu_info_from_cpuid() {
+        // Skylake: version 4, 4 counters with 48 bits, 7 events, all supported
+        let info = PmuInfo::from_cpuid(0x0730_0404, 0);
+        assert_eq!(
+            (info.version, info.num_counters, info.counter_width),
+            (4, 4, 48)
+        );
+        assert_eq!(info.events(), PerfEvent::ALL);
+        assert_eq!(info.mask(u64::MAX), (1 << 48) - 1);
+
+        // LLC misses unavailable; only 2 events listed
+        let info = PmuInfo::from_cpuid(0x0230_0402, 1 << 4);
+        assert_eq!(
+            info.events(),
+            [PerfEvent::CoreCycles, PerfEvent::InstructionsRetired]
+        );
+
+        // no architectural performance monitoring, e.g. QEMU without KVM
+        assert!(PmuInfo::from_cpuid(0, 0).events().is_empty());
+    }
+
+    #[test]
+    fn test_event_select() {
+        assert_eq!(event_select(PerfEvent::InstructionsRetired), 0x4300c0);
+        assert_eq!(event_select(PerfEvent::LlcMisses), 0x43412e);
+        assert_eq!(global_enable_bits(3), 0b111);
+    }
+}
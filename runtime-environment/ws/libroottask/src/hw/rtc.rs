@@ -0,0 +1,228 @@
+//! Minimal driver for the real-time clock (RTC) in the CMOS of PC platforms. The RTC
+//! keeps the wall-clock time while the machine is powered off. It has a resolution of one
+//! second and knows neither time zones nor the century; this driver assumes UTC and the
+//! years 2000 to 2099.
+
+use crate::io_port::request_io_ports;
+use libhrstd::libhedron::{
+    CapSel,
+    CrdPortIO,
+};
+use libhrstd::time::{
+    civil_from_days,
+    days_from_civil,
+};
+use x86::io::{
+    inb,
+    outb,
+};
+
+/// I/O port that selects the CMOS register. Bit 7 disables NMIs.
+const CMOS_ADDRESS_PORT: u16 = 0x70;
+/// I/O port that accesses the selected CMOS register.
+const CMOS_DATA_PORT: u16 = 0x71;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0a;
+const REG_STATUS_B: u8 = 0x0b;
+
+/// Set in status register A while the RTC updates its registers.
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+/// Set in status register B if the RTC uses the 24 hour format.
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+/// Set in status register B if the RTC uses binary values instead of BCD.
+const STATUS_B_BINARY: u8 = 1 << 2;
+/// Set in status register B to stop updates, e.g. while the registers are written.
+const STATUS_B_SET: u8 = 1 << 7;
+/// Set in the hours register in the 12 hour format for PM.
+const HOURS_PM: u8 = 1 << 7;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Accessor for the CMOS RTC. Only one instance should exist.
+#[derive(Debug)]
+pub struct CmosRtc;
+
+impl CmosRtc {
+    /// Requests the I/O ports of the CMOS from the kern PD.
+    pub fn new(root_pd_sel: CapSel) -> Result<Self, ()> {
+        // 2 consecutive ports: address and data
+        request_io_ports(root_pd_sel, CrdPortIO::new(CMOS_ADDRESS_PORT, 1)).map_err(|_| ())?;
+        Ok(Self)
+    }
+
+    /// Returns the current time as seconds since the Unix epoch.
+    pub fn read(&self) -> u64 {
+        // the registers are consistent if two reads outside of an update are equal
+        let mut time = self.read_once();
+        loop {
+            let next = self.read_once();
+            if next == time {
+                break;
+            }
+            time = next;
+        }
+        time.to_unix_secs()
+    }
+
+    /// Sets the time of the RTC. `unix_secs` are seconds since the Unix epoch.
+    pub fn write(&self, unix_secs: u64) {
+        let time = RtcTime::from_unix_secs(unix_secs);
+        let status_b = self.read_reg(REG_STATUS_B);
+        let encode = |val: u8| {
+            if status_b & STATUS_B_BINARY != 0 {
+                val
+            } else {
+                bin_to_bcd(val)
+            }
+        };
+        let hours = if status_b & STATUS_B_24_HOUR != 0 {
+            encode(time.hours)
+        } else {
+            // 12 hour format: 12 AM is midnight, 12 PM is noon
+            let hours_12 = (time.hours + 11) % 12 + 1;
+            encode(hours_12) | if time.hours >= 12 { HOURS_PM } else { 0 }
+        };
+
+        self.write_reg(REG_STATUS_B, status_b | STATUS_B_SET);
+        self.write_reg(REG_SECONDS, encode(time.seconds));
+        self.write_reg(REG_MINUTES, encode(time.minutes));
+        self.write_reg(REG_HOURS, hours);
+        self.write_reg(REG_DAY, encode(time.day));
+        self.write_reg(REG_MONTH, encode(time.month));
+        self.write_reg(REG_YEAR, encode((time.year % 100) as u8));
+        self.write_reg(REG_STATUS_B, status_b & !STATUS_B_SET);
+    }
+
+    /// Reads all time registers once, after a running update finished.
+    fn read_once(&self) -> RtcTime {
+        while self.read_reg(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {
+            core::hint::spin_loop();
+        }
+        let status_b = self.read_reg(REG_STATUS_B);
+        let decode = |val: u8| {
+            if status_b & STATUS_B_BINARY != 0 {
+                val
+            } else {
+                bcd_to_bin(val)
+            }
+        };
+        let hours_raw = self.read_reg(REG_HOURS);
+        let mut hours = decode(hours_raw & !HOURS_PM);
+        if status_b & STATUS_B_24_HOUR == 0 {
+            hours %= 12;
+            if hours_raw & HOURS_PM != 0 {
+                hours += 12;
+            }
+        }
+        RtcTime {
+            year: 2000 + decode(self.read_reg(REG_YEAR)) as u64,
+            month: decode(self.read_reg(REG_MONTH)),
+            day: decode(self.read_reg(REG_DAY)),
+            hours,
+            minutes: decode(self.read_reg(REG_MINUTES)),
+            seconds: decode(self.read_reg(REG_SECONDS)),
+        }
+    }
+
+    fn read_reg(&self, reg: u8) -> u8 {
+        unsafe {
+            outb(CMOS_ADDRESS_PORT, reg);
+            inb(CMOS_DATA_PORT)
+        }
+    }
+
+    fn write_reg(&self, reg: u8, val: u8) {
+        unsafe {
+            outb(CMOS_ADDRESS_PORT, reg);
+            outb(CMOS_DATA_PORT, val);
+        }
+    }
+}
+
+/// Broken-down UTC time as the RTC stores it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct RtcTime {
+    year: u64,
+    month: u8,
+    day: u8,
+    hours: u8,
+    minutes: u8,
+    seconds: u8,
+}
+
+impl RtcTime {
+    fn to_unix_secs(self) -> u64 {
+        days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+            + self.hours as u64 * 3600
+            + self.minutes as u64 * 60
+            + self.seconds as u64
+    }
+
+    fn from_unix_secs(unix_secs: u64) -> Self {
+        let (year, month, day) = civil_from_days(unix_secs / SECONDS_PER_DAY);
+        let secs_of_day = unix_secs % SECONDS_PER_DAY;
+        Self {
+            year,
+            month,
+            day,
+            hours: (secs_of_day / 3600) as u8,
+            minutes: (secs_of_day % 3600 / 60) as u8,
+            seconds: (secs_of_day % 60) as u8,
+        }
+    }
+}
+
+const fn bcd_to_bin(val: u8) -> u8 {
+    (val >> 4) * 10 + (val & 0xf)
+}
+
+const fn bin_to_bcd(val: u8) -> u8 {
+    ((val / 10) << 4) | (val % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bcd() {
+        assert_eq!(bcd_to_bin(0x59), 59);
+        assert_eq!(bin_to_bcd(59), 0x59);
+        assert_eq!(bcd_to_bin(bin_to_bcd(7)), 7);
+    }
+
+    #[test]
+    fn test_unix_time() {
+        let epoch = RtcTime {
+            year: 1970,
+            month: 1,
+            day: 1,
+            hours: 0,
+            minutes: 0,
+            seconds: 0,
+        };
+        assert_eq!(epoch.to_unix_secs(), 0);
+
+        // 2024-02-29T13:37:42Z
+        let leap_day = RtcTime {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hours: 13,
+            minutes: 37,
+            seconds: 42,
+        };
+        assert_eq!(leap_day.to_unix_secs(), 1_709_213_862);
+        assert_eq!(RtcTime::from_unix_secs(1_709_213_862), leap_day);
+
+        for secs in [0, 951_782_400, 1_000_000_000, 4_102_444_799] {
+            assert_eq!(RtcTime::from_unix_secs(secs).to_unix_secs(), secs);
+        }
+    }
+}
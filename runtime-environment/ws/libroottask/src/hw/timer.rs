@@ -0,0 +1,52 @@
+//! Module for [`TscDeadlineTimer`].
+
+use libhrstd::libhedron::syscall::{
+    sys_sm_down,
+    sys_sm_up,
+    SmCtrlZeroCounterStrategy,
+    SyscallError,
+    SyscallStatus,
+};
+use libhrstd::libhedron::CapSel;
+
+/// One-shot timer based on the TSC deadline mode of the local APIC. Hedron owns the local
+/// APIC, but it programs the TSC deadline for us, when we perform a "semaphore down"
+/// operation with a timeout. This type wraps a semaphore for exactly this purpose.
+///
+/// The timer only uses raw system calls and no reference counted kernel objects. This
+/// way it can be used from any EC of the roottask.
+#[derive(Debug)]
+pub struct TscDeadlineTimer {
+    sm_sel: CapSel,
+}
+
+impl TscDeadlineTimer {
+    /// Creates a new timer on top of an existing semaphore in the capability space of
+    /// the roottask. Nobody except [`Self::kick`] should up this semaphore.
+    pub const fn new(sm_sel: CapSel) -> Self {
+        Self { sm_sel }
+    }
+
+    /// Blocks the calling EC until the TSC reaches `tsc_deadline` or someone calls
+    /// [`Self::kick`]. Without a deadline, it only waits for a kick. Returns `true`
+    /// if the timer got kicked and `false` if the deadline was reached.
+    pub fn wait(&self, tsc_deadline: Option<u64>) -> bool {
+        // a deadline of zero has the meaning "no timeout" for Hedron
+        let tsc_deadline = tsc_deadline.map(|deadline| deadline.max(1));
+        match sys_sm_down(
+            self.sm_sel,
+            SmCtrlZeroCounterStrategy::SetToZero,
+            tsc_deadline,
+        ) {
+            Ok(_) => true,
+            Err(SyscallError::HedronStatusError(SyscallStatus::Timeout)) => false,
+            Err(e) => panic!("sm down failed: {:?}", e),
+        }
+    }
+
+    /// Wakes up the EC that is currently waiting in [`Self::wait`]. If there is none,
+    /// the next call to [`Self::wait`] returns immediately.
+    pub fn kick(&self) {
+        sys_sm_up(self.sm_sel).unwrap();
+    }
+}
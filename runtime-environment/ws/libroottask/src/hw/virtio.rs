@@ -0,0 +1,383 @@
+//! Common parts of the drivers for virtio devices of QEMU, see [`crate::hw::virtio_net`],
+//! [`crate::hw::virtio_console`], and [`crate::hw::virtio_9p`]. They use the legacy interface via I/O ports, because it
+//! only needs the PCI configuration mechanism and a few I/O ports. The drivers poll; they
+//! don't use interrupts.
+//!
+//! The device accesses the virtqueues and the buffers via DMA with physical addresses. The
+//! roottask identity-maps physical memory for them, see [`alloc_dma_mem`]. This only works
+//! without an IOMMU, which is the default for QEMU.
+
+use crate::hw::pci::{
+    self,
+    PciBar,
+    PciConfigSpace,
+    PciOwner,
+};
+use crate::io_port::request_io_ports;
+use crate::mem::{
+    MappedMemory,
+    PHYS_FRAME_ALLOC,
+    ROOT_MEM_MAPPER,
+};
+use crate::process::Process;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::sync::atomic::{
+    fence,
+    Ordering,
+};
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::{
+    CrdPortIO,
+    MemCapPermissions,
+};
+use libhrstd::mem::calc_page_count;
+use x86::io::{
+    inb,
+    inl,
+    inw,
+    outb,
+    outl,
+    outw,
+};
+
+const VIRTIO_VENDOR_ID: u16 = 0x1af4;
+
+// registers of the legacy interface, relative to the I/O base
+const REG_DEVICE_FEATURES: u16 = 0x00;
+const REG_GUEST_FEATURES: u16 = 0x04;
+const REG_QUEUE_ADDRESS: u16 = 0x08;
+const REG_QUEUE_SIZE: u16 = 0x0c;
+const REG_QUEUE_SELECT: u16 = 0x0e;
+const REG_QUEUE_NOTIFY: u16 = 0x10;
+const REG_DEVICE_STATUS: u16 = 0x12;
+/// Device-specific configuration without MSI-X.
+const REG_DEVICE_CONFIG: u16 = 0x14;
+/// Order of the number of I/O ports of the legacy interface (32 ports).
+const IO_PORT_ORDER: u8 = 5;
+
+const STATUS_ACKNOWLEDGE: u8 = 1;
+const STATUS_DRIVER: u8 = 2;
+const STATUS_DRIVER_OK: u8 = 4;
+const STATUS_FAILED: u8 = 0x80;
+
+/// The descriptor continues in the descriptor of `next`.
+const VIRTQ_DESC_F_NEXT: u16 = 1;
+/// The device only writes to the buffer of the descriptor.
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+/// Element of the descriptor table of a virtqueue.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct VirtqDesc {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+/// Element of the used ring of a virtqueue.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct VirtqUsedElem {
+    id: u32,
+    len: u32,
+}
+
+/// The registers of a device with the legacy interface.
+#[derive(Debug)]
+pub struct LegacyDevice {
+    io_base: u16,
+}
+
+impl LegacyDevice {
+    /// Searches the transitional device with the device ID on the PCI bus, claims it for
+    /// the roottask, and resets it. The driver accepts the features of `features` that the
+    /// device offers. Returns the device and the accepted features. `name` is for the log.
+    pub fn init(
+        pci: &PciConfigSpace,
+        device_id: u16,
+        name: &str,
+        features: u32,
+    ) -> Option<(Self, u32)> {
+        let device = pci.find_device(VIRTIO_VENDOR_ID, &[device_id])?;
+        if let Err(e) = pci::claim(device.addr.into(), PciOwner::Roottask) {
+            log::warn!("{}: can't claim the device: {:?}", name, e);
+            return None;
+        }
+        let io_base = match pci.bar(device.addr, 0) {
+            PciBar::Io(io_base) => io_base,
+            bar => {
+                log::warn!("{}: BAR0 is not an I/O BAR: {:?}", name, bar);
+                return None;
+            }
+        };
+        request_io_ports(
+            RootCapSpace::RootPd.val(),
+            CrdPortIO::new(io_base, IO_PORT_ORDER),
+        )
+        .ok()?;
+        pci.enable_device(device.addr);
+
+        unsafe {
+            // reset
+            outb(io_base + REG_DEVICE_STATUS, 0);
+            outb(io_base + REG_DEVICE_STATUS, STATUS_ACKNOWLEDGE);
+            outb(
+                io_base + REG_DEVICE_STATUS,
+                STATUS_ACKNOWLEDGE | STATUS_DRIVER,
+            );
+        }
+
+        let device_features = unsafe { inl(io_base + REG_DEVICE_FEATURES) };
+        let features = device_features & features;
+        unsafe { outl(io_base + REG_GUEST_FEATURES, features) };
+        log::debug!("{}: device at {:?}", name, device.addr);
+        Some((Self { io_base }, features))
+    }
+
+    /// Tells the device that the driver is ready.
+    pub fn set_driver_ok(&self) {
+        unsafe {
+            outb(
+                self.io_base + REG_DEVICE_STATUS,
+                STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_DRIVER_OK,
+            )
+        };
+    }
+
+    /// Tells the device that the driver gave up.
+    pub fn set_failed(&self) {
+        unsafe { outb(self.io_base + REG_DEVICE_STATUS, STATUS_FAILED) };
+    }
+
+    /// Tells the device that the queue has new buffers.
+    pub fn notify(&self, queue_index: u16) {
+        unsafe { outw(self.io_base + REG_QUEUE_NOTIFY, queue_index) };
+    }
+
+    /// Reads a byte of the device-specific configuration.
+    pub fn config_u8(&self, offset: u16) -> u8 {
+        unsafe { inb(self.io_base + REG_DEVICE_CONFIG + offset) }
+    }
+}
+
+/// Split virtqueue in the memory layout of the legacy interface. Descriptor `i` always
+/// refers to buffer `i`; only [`Self::push_request`] chains two of them.
+#[derive(Debug)]
+pub struct Virtqueue {
+    /// Number of descriptors, as dictated by the device.
+    size: u16,
+    /// Descriptor table, available ring, and used ring.
+    rings: MappedMemory,
+    buffer_size: usize,
+    buffers: MappedMemory,
+    /// Next free index in the available ring.
+    avail_idx: u16,
+    /// Next index in the used ring that the driver didn't process yet.
+    last_used_idx: u16,
+    /// Descriptors (= buffers) that are not owned by the device.
+    free_ids: Vec<u16>,
+}
+
+impl Virtqueue {
+    /// Allocates the queue with the given index and `buffer_count` buffers and tells the
+    /// device its location. Fails if the queue of the device has less descriptors.
+    pub fn new(
+        device: &LegacyDevice,
+        index: u16,
+        buffer_count: u16,
+        buffer_size: usize,
+        root: &Rc<Process>,
+    ) -> Option<Self> {
+        let io_base = device.io_base;
+        let size = unsafe {
+            outw(io_base + REG_QUEUE_SELECT, index);
+            inw(io_base + REG_QUEUE_SIZE)
+        };
+        if size < buffer_count {
+            return None;
+        }
+
+        let rings = alloc_dma_mem(
+            root,
+            Self::used_ring_offset(size) + Self::used_ring_size(size),
+        );
+        let buffers = alloc_dma_mem(root, buffer_count as usize * buffer_size);
+        let queue = Self {
+            size,
+            rings,
+            buffer_size,
+            buffers,
+            avail_idx: 0,
+            last_used_idx: 0,
+            free_ids: (0..buffer_count).collect(),
+        };
+        for id in 0..buffer_count {
+            let desc = VirtqDesc {
+                addr: queue.buffers.original_addr() + id as u64 * buffer_size as u64,
+                len: buffer_size as u32,
+                flags: 0,
+                next: 0,
+            };
+            unsafe { queue.desc_ptr(id).write_volatile(desc) };
+        }
+
+        // the legacy interface expects the page frame number of the queue
+        unsafe {
+            outl(
+                io_base + REG_QUEUE_ADDRESS,
+                (queue.rings.original_addr() / PAGE_SIZE as u64) as u32,
+            )
+        };
+        Some(queue)
+    }
+
+    pub const fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Takes a buffer that the device doesn't own.
+    pub fn alloc_id(&mut self) -> Option<u16> {
+        self.free_ids.pop()
+    }
+
+    /// Returns a buffer that the device gave back and the driver doesn't need anymore.
+    pub fn free_id(&mut self, id: u16) {
+        self.free_ids.push(id);
+    }
+
+    /// Hands the buffer with the given ID over to the device.
+    pub fn push(&mut self, id: u16, len: u32, device_writes: bool) {
+        unsafe {
+            let desc = self.desc_ptr(id);
+            (*desc).len = len;
+            (*desc).flags = if device_writes { VIRTQ_DESC_F_WRITE } else { 0 };
+        }
+        self.make_available(id);
+    }
+
+    /// Hands a request and a buffer for the response over to the device, for devices that
+    /// answer requests, like virtio-9p. The device reads `len` bytes of the buffer
+    /// `request_id` and writes into the buffer `response_id`. [`Self::pop_used`] returns
+    /// `request_id` afterwards.
+    pub fn push_request(&mut self, request_id: u16, len: u32, response_id: u16) {
+        unsafe {
+            let desc = self.desc_ptr(request_id);
+            (*desc).len = len;
+            (*desc).flags = VIRTQ_DESC_F_NEXT;
+            (*desc).next = response_id;
+            let desc = self.desc_ptr(response_id);
+            (*desc).len = self.buffer_size as u32;
+            (*desc).flags = VIRTQ_DESC_F_WRITE;
+        }
+        self.make_available(request_id);
+    }
+
+    /// Takes the next buffer that the device returned. Returns its ID and the number of
+    /// bytes the device wrote into it.
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        let used_offset = Self::used_ring_offset(self.size);
+        let used_idx = unsafe {
+            self.rings
+                .begin_ptr_mut()
+                .add(used_offset)
+                .cast::<u16>()
+                .add(1)
+                .read_volatile()
+        };
+        if used_idx == self.last_used_idx {
+            return None;
+        }
+        fence(Ordering::SeqCst);
+        let elem = unsafe {
+            self.rings
+                .begin_ptr_mut()
+                .add(used_offset + 2 * size_of::<u16>())
+                .cast::<VirtqUsedElem>()
+                .add((self.last_used_idx % self.size) as usize)
+                .read_volatile()
+        };
+        self.last_used_idx = self.last_used_idx.wrapping_add(1);
+        Some((elem.id as u16, elem.len))
+    }
+
+    pub fn buffer_mut(&mut self, id: u16) -> &mut [u8] {
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self.buffers
+                    .begin_ptr_mut()
+                    .add(id as usize * self.buffer_size),
+                self.buffer_size,
+            )
+        }
+    }
+
+    /// Puts the descriptor (chain) into the available ring.
+    fn make_available(&mut self, head: u16) {
+        unsafe {
+            self.avail_ptr(2 + self.avail_idx % self.size)
+                .write_volatile(head);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            // the device must see the descriptor before the new index
+            fence(Ordering::SeqCst);
+            self.avail_ptr(1).write_volatile(self.avail_idx);
+            fence(Ordering::SeqCst);
+        }
+    }
+
+    fn desc_ptr(&self, id: u16) -> *mut VirtqDesc {
+        unsafe {
+            self.rings
+                .begin_ptr_mut()
+                .cast::<VirtqDesc>()
+                .add(id as usize)
+        }
+    }
+
+    /// Pointer to the n-th `u16` of the available ring (flags, idx, ring entries).
+    fn avail_ptr(&self, n: u16) -> *mut u16 {
+        unsafe {
+            self.rings
+                .begin_ptr_mut()
+                .add(size_of::<VirtqDesc>() * self.size as usize)
+                .cast::<u16>()
+                .add(n as usize)
+        }
+    }
+
+    /// The legacy interface places the used ring at the next page boundary after the
+    /// descriptor table and the available ring.
+    fn used_ring_offset(size: u16) -> usize {
+        let size = size as usize;
+        let len = size_of::<VirtqDesc>() * size + size_of::<u16>() * (3 + size);
+        calc_page_count(len) * PAGE_SIZE
+    }
+
+    fn used_ring_size(size: u16) -> usize {
+        size_of::<u16>() * 3 + size_of::<VirtqUsedElem>() * size as usize
+    }
+}
+
+/// Allocates zeroed memory that the device can access via DMA. The physical address is
+/// [`MappedMemory::original_addr`]. The memory comes from [`PHYS_FRAME_ALLOC`] and is
+/// never freed.
+fn alloc_dma_mem(root: &Rc<Process>, size: usize) -> MappedMemory {
+    let page_count = calc_page_count(size);
+    let phys_addr = PHYS_FRAME_ALLOC
+        .lock()
+        .alloc(page_count)
+        .expect("out of physical memory");
+    let mem = ROOT_MEM_MAPPER.lock().mmap(
+        root,
+        root,
+        phys_addr,
+        None,
+        page_count as u64,
+        MemCapPermissions::RW,
+    );
+    unsafe { core::ptr::write_bytes(mem.begin_ptr_mut(), 0, mem.size() as usize) };
+    mem
+}
@@ -0,0 +1,123 @@
+//! Driver for the virtio-9p device of QEMU (`-fsdev local,id=...,path=... -device
+//! virtio-9p-pci,fsdev=...,mount_tag=...`). It transports the 9P messages of the client in
+//! [`crate::rt::hostfs`] to the server in QEMU, which shares a directory of the host. See
+//! [`crate::hw::virtio`] for the interface and the DMA memory.
+//!
+//! The device has a single queue. The driver sends one request at a time and waits for
+//! the response.
+
+use crate::hw::pci::PciConfigSpace;
+use crate::hw::virtio::{
+    LegacyDevice,
+    Virtqueue,
+};
+use crate::process::Process;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Device ID of the transitional virtio-9p device, which offers the legacy interface.
+const VIRTIO_9P_LEGACY_DEVICE_ID: u16 = 0x1009;
+
+/// The device provides the mount tag in the device-specific configuration.
+const VIRTIO_9P_F_MOUNT_TAG: u32 = 1 << 0;
+/// Offset of the length of the mount tag in the device-specific configuration. The tag
+/// follows it.
+const CONFIG_TAG_LEN: u16 = 0;
+
+const REQUEST_QUEUE_INDEX: u16 = 0;
+
+/// Maximum size of a 9P message, i.e. the `msize` that the client proposes.
+pub const MAX_MSG_SIZE: usize = 64 * 1024;
+
+/// Buffer of the request and buffer of the response.
+const REQUEST_ID: u16 = 0;
+const RESPONSE_ID: u16 = 1;
+
+/// Upper bound of the polls for a response, i.e. several seconds. The device gives up
+/// afterwards, because it still owns the buffers.
+const MAX_POLLS: usize = 1_000_000_000;
+
+/// Driver for a legacy virtio-9p device.
+#[derive(Debug)]
+pub struct Virtio9p {
+    device: LegacyDevice,
+    queue: Virtqueue,
+    mount_tag: String,
+    /// Set when the device didn't respond. All further requests fail.
+    failed: bool,
+}
+
+impl Virtio9p {
+    /// Searches a virtio-9p device on the PCI bus and initializes it. Returns `None` if
+    /// there is no such device or the initialization fails.
+    pub fn init(pci: &PciConfigSpace, root: &Rc<Process>) -> Option<Self> {
+        let (device, features) = LegacyDevice::init(
+            pci,
+            VIRTIO_9P_LEGACY_DEVICE_ID,
+            "virtio-9p",
+            VIRTIO_9P_F_MOUNT_TAG,
+        )?;
+        let queue = match Virtqueue::new(&device, REQUEST_QUEUE_INDEX, 2, MAX_MSG_SIZE, root) {
+            Some(queue) => queue,
+            None => {
+                log::warn!("virtio-9p: can't set up the virtqueue");
+                device.set_failed();
+                return None;
+            }
+        };
+
+        let mount_tag = if features & VIRTIO_9P_F_MOUNT_TAG != 0 {
+            let len = u16::from_le_bytes([
+                device.config_u8(CONFIG_TAG_LEN),
+                device.config_u8(CONFIG_TAG_LEN + 1),
+            ]);
+            let tag = (0..len)
+                .map(|i| device.config_u8(CONFIG_TAG_LEN + 2 + i))
+                .collect::<Vec<_>>();
+            String::from_utf8_lossy(&tag).into_owned()
+        } else {
+            String::new()
+        };
+
+        device.set_driver_ok();
+        log::info!(
+            "virtio-9p: initialized device with mount tag {:?}",
+            mount_tag
+        );
+        Some(Self {
+            device,
+            queue,
+            mount_tag,
+            failed: false,
+        })
+    }
+
+    /// The name under which QEMU exports the shared directory.
+    pub fn mount_tag(&self) -> &str {
+        &self.mount_tag
+    }
+
+    /// Sends the request and waits for the response. Returns the response buffer, which
+    /// starts with the response and may contain stale bytes after it. Returns `None` if
+    /// the device doesn't respond.
+    pub fn request(&mut self, request: &[u8]) -> Option<&[u8]> {
+        if self.failed || request.len() > MAX_MSG_SIZE {
+            return None;
+        }
+        self.queue.buffer_mut(REQUEST_ID)[..request.len()].copy_from_slice(request);
+        self.queue
+            .push_request(REQUEST_ID, request.len() as u32, RESPONSE_ID);
+        self.device.notify(REQUEST_QUEUE_INDEX);
+
+        for _ in 0..MAX_POLLS {
+            if self.queue.pop_used().is_some() {
+                return Some(self.queue.buffer_mut(RESPONSE_ID));
+            }
+            core::hint::spin_loop();
+        }
+        log::error!("virtio-9p: the device doesn't respond; giving up");
+        self.failed = true;
+        None
+    }
+}
@@ -0,0 +1,167 @@
+//! Driver for the console of a virtio-serial device of QEMU (`-device virtio-serial-pci
+//! -device virtconsole,chardev=...`). With the boot argument `virtio_console=on`, it
+//! replaces the serial port as the transport of the console, see
+//! [`crate::services::stdout`]: it carries the output of the stdout and stderr services
+//! and of the log, the input of the stdin service, and the raw bytes of the GDB stub and
+//! the serial transfer service. Unlike the serial port, it doesn't lose bytes, and unlike
+//! debugcon, it works in both directions.
+//!
+//! The driver only uses port 0, the console, without the multiport feature. See
+//! [`crate::hw::virtio`] for the interface and the DMA memory.
+
+use crate::hw::pci::PciConfigSpace;
+use crate::hw::virtio::{
+    LegacyDevice,
+    Virtqueue,
+};
+use crate::process::Process;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use core::fmt::Write;
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use libhrstd::cap_space::root::RootCapSpace;
+
+/// Device ID of the transitional virtio-serial device, which offers the legacy interface.
+const VIRTIO_CONSOLE_LEGACY_DEVICE_ID: u16 = 0x1003;
+
+/// Queues of port 0.
+const RX_QUEUE_INDEX: u16 = 0;
+const TX_QUEUE_INDEX: u16 = 1;
+
+/// Number of buffers per queue.
+const BUFFER_COUNT: u16 = 16;
+const BUFFER_SIZE: usize = 4096;
+
+/// Upper bound of the polls of the transmit queue while all buffers are in flight. Only a
+/// broken device takes that long; the driver drops the output then.
+const MAX_TX_POLLS: usize = 10_000_000;
+
+/// Set by the boot argument.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables the virtio-console as the transport of the console. Off by default.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Driver for port 0 of a legacy virtio-serial device.
+#[derive(Debug)]
+pub struct VirtioConsole {
+    device: LegacyDevice,
+    rx_queue: Virtqueue,
+    tx_queue: Virtqueue,
+    /// Bytes that the device delivered and nobody read yet.
+    received: VecDeque<u8>,
+}
+
+impl VirtioConsole {
+    /// Searches a virtio-serial device on the PCI bus and initializes it. Returns `None`
+    /// if the boot argument didn't enable it, there is no such device, or the
+    /// initialization fails.
+    pub fn init(root: &Rc<Process>) -> Option<Self> {
+        if !is_enabled() {
+            return None;
+        }
+        let pci = PciConfigSpace::new(RootCapSpace::RootPd.val()).ok()?;
+        let device =
+            match LegacyDevice::init(&pci, VIRTIO_CONSOLE_LEGACY_DEVICE_ID, "virtio-console", 0) {
+                Some((device, _)) => device,
+                None => {
+                    log::warn!("virtio-console: no device; the console stays on the serial port");
+                    return None;
+                }
+            };
+
+        let new_queue = |index| Virtqueue::new(&device, index, BUFFER_COUNT, BUFFER_SIZE, root);
+        let (mut rx_queue, tx_queue) =
+            match new_queue(RX_QUEUE_INDEX).zip(new_queue(TX_QUEUE_INDEX)) {
+                Some(queues) => queues,
+                None => {
+                    log::warn!("virtio-console: can't set up the virtqueues");
+                    device.set_failed();
+                    return None;
+                }
+            };
+        while let Some(id) = rx_queue.alloc_id() {
+            rx_queue.push(id, BUFFER_SIZE as u32, true);
+        }
+
+        device.set_driver_ok();
+        device.notify(RX_QUEUE_INDEX);
+        log::info!("virtio-console: initialized device");
+        Some(Self {
+            device,
+            rx_queue,
+            tx_queue,
+            received: VecDeque::new(),
+        })
+    }
+
+    /// Sends the bytes. Waits for free buffers instead of dropping output.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(BUFFER_SIZE) {
+            let id = match self.wait_for_tx_buffer() {
+                Some(id) => id,
+                None => return,
+            };
+            self.tx_queue.buffer_mut(id)[..chunk.len()].copy_from_slice(chunk);
+            self.tx_queue.push(id, chunk.len() as u32, false);
+            self.device.notify(TX_QUEUE_INDEX);
+        }
+    }
+
+    /// Checks if the device received a byte that wasn't read yet.
+    pub fn has_input(&mut self) -> bool {
+        self.receive();
+        !self.received.is_empty()
+    }
+
+    /// Returns the next received byte, if there is one. Never blocks.
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        if self.received.is_empty() {
+            self.receive();
+        }
+        self.received.pop_front()
+    }
+
+    /// Moves the bytes of the buffers that the device filled to [`Self::received`] and
+    /// gives the buffers back.
+    fn receive(&mut self) {
+        while let Some((id, len)) = self.rx_queue.pop_used() {
+            let len = (len as usize).min(self.rx_queue.buffer_size());
+            self.received
+                .extend(self.rx_queue.buffer_mut(id)[..len].iter());
+            self.rx_queue.push(id, BUFFER_SIZE as u32, true);
+            self.device.notify(RX_QUEUE_INDEX);
+        }
+    }
+
+    /// Returns a free transmit buffer. Reclaims the buffers that the device sent.
+    fn wait_for_tx_buffer(&mut self) -> Option<u16> {
+        for _ in 0..MAX_TX_POLLS {
+            while let Some((id, _)) = self.tx_queue.pop_used() {
+                self.tx_queue.free_id(id);
+            }
+            if let Some(id) = self.tx_queue.alloc_id() {
+                return Some(id);
+            }
+            core::hint::spin_loop();
+        }
+        None
+    }
+}
+
+impl Write for VirtioConsole {
+    /// Sends the text unmodified.
+    fn write_str(&mut self, msg: &str) -> core::fmt::Result {
+        self.write_bytes(msg.as_bytes());
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Driver for the virtio-net device of QEMU (`-device virtio-net-pci`). See
+//! [`crate::hw::virtio`] for the interface and the DMA memory.
+
+use crate::hw::net::NetDevice;
+use crate::hw::pci::PciConfigSpace;
+use crate::hw::virtio::{
+    LegacyDevice,
+    Virtqueue,
+};
+use crate::process::Process;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use libhrstd::rt::services::network::{
+    MacAddress,
+    NetworkError,
+};
+
+/// Device ID of the transitional virtio-net device, which offers the legacy interface.
+const VIRTIO_NET_LEGACY_DEVICE_ID: u16 = 0x1000;
+
+/// Offset of the MAC address in the device-specific configuration.
+const CONFIG_MAC: u16 = 0;
+
+/// The device provides its MAC address in the device-specific configuration.
+const VIRTIO_NET_F_MAC: u32 = 1 << 5;
+
+const RX_QUEUE_INDEX: u16 = 0;
+const TX_QUEUE_INDEX: u16 = 1;
+
+/// Number of packet buffers per queue.
+const BUFFER_COUNT: u16 = 32;
+/// Size of a packet buffer. Holds the virtio header and a whole Ethernet frame.
+const BUFFER_SIZE: usize = 2048;
+/// Size of `struct virtio_net_hdr` without `VIRTIO_NET_F_MRG_RXBUF`. Precedes each frame.
+const NET_HDR_SIZE: usize = 10;
+/// Maximum size of an Ethernet frame without the frame check sequence.
+const MAX_FRAME_SIZE: usize = 1514;
+
+/// Driver for a legacy virtio-net device.
+#[derive(Debug)]
+pub struct VirtioNet {
+    device: LegacyDevice,
+    mac: MacAddress,
+    rx_queue: Virtqueue,
+    tx_queue: Virtqueue,
+}
+
+impl VirtioNet {
+    /// Searches a virtio-net device on the PCI bus and initializes it. Returns `None`
+    /// if there is no such device or the initialization fails.
+    pub fn init(pci: &PciConfigSpace, root: &Rc<Process>) -> Option<Self> {
+        let (device, features) = LegacyDevice::init(
+            pci,
+            VIRTIO_NET_LEGACY_DEVICE_ID,
+            "virtio-net",
+            VIRTIO_NET_F_MAC,
+        )?;
+
+        let new_queue = |index| Virtqueue::new(&device, index, BUFFER_COUNT, BUFFER_SIZE, root);
+        let (mut rx_queue, tx_queue) =
+            match new_queue(RX_QUEUE_INDEX).zip(new_queue(TX_QUEUE_INDEX)) {
+                Some(queues) => queues,
+                None => {
+                    log::warn!("virtio-net: can't set up the virtqueues");
+                    device.set_failed();
+                    return None;
+                }
+            };
+
+        let mac = if features & VIRTIO_NET_F_MAC != 0 {
+            let mut mac = [0; 6];
+            for (i, byte) in mac.iter_mut().enumerate() {
+                *byte = device.config_u8(CONFIG_MAC + i as u16);
+            }
+            MacAddress(mac)
+        } else {
+            // locally administered address, same default as QEMU
+            MacAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56])
+        };
+
+        // the device may use all receive buffers
+        while let Some(id) = rx_queue.alloc_id() {
+            rx_queue.push(id, BUFFER_SIZE as u32, true);
+        }
+
+        device.set_driver_ok();
+        device.notify(RX_QUEUE_INDEX);
+        log::info!("virtio-net: initialized device with MAC {}", mac);
+        Some(Self {
+            device,
+            mac,
+            rx_queue,
+            tx_queue,
+        })
+    }
+}
+
+impl NetDevice for VirtioNet {
+    fn mac(&self) -> MacAddress {
+        self.mac
+    }
+
+    fn transmit(&mut self, frame: &[u8]) -> Result<(), NetworkError> {
+        if frame.len() > MAX_FRAME_SIZE {
+            return Err(NetworkError::MessageTooLong);
+        }
+        // reclaim the buffers of frames that the device sent in the meantime
+        while let Some((id, _)) = self.tx_queue.pop_used() {
+            self.tx_queue.free_id(id);
+        }
+        let id = self.tx_queue.alloc_id().ok_or(NetworkError::WouldBlock)?;
+
+        let buffer = self.tx_queue.buffer_mut(id);
+        // all fields zero: no checksum offloading and no segmentation offloading
+        buffer[..NET_HDR_SIZE].fill(0);
+        buffer[NET_HDR_SIZE..][..frame.len()].copy_from_slice(frame);
+        self.tx_queue
+            .push(id, (NET_HDR_SIZE + frame.len()) as u32, false);
+        self.device.notify(TX_QUEUE_INDEX);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        let (id, len) = self.rx_queue.pop_used()?;
+        let len = (len as usize).clamp(NET_HDR_SIZE, BUFFER_SIZE);
+        let frame = Vec::from(&self.rx_queue.buffer_mut(id)[NET_HDR_SIZE..len]);
+        // give the buffer back to the device
+        self.rx_queue.push(id, BUFFER_SIZE as u32, true);
+        self.device.notify(RX_QUEUE_INDEX);
+        Some(frame)
+    }
+}
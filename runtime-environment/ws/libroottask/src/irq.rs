@@ -0,0 +1,535 @@
+//! Interrupts of devices. Hedron owns the interrupt controllers and signals each global
+//! system interrupt (GSI) with an "up" operation on its interrupt semaphore. Hedron hands
+//! these semaphores to the roottask; [`sys_assign_gsi`] routes a GSI to a CPU. Message
+//! signaled interrupts (MSIs) of PCI functions use GSIs too: the roottask takes a free GSI
+//! from the top of the range, where no pin of an IOAPIC is, and programs the MSI
+//! capability of the function with the message that Hedron returns.
+//!
+//! Drivers of the roottask register a handler with [`register_handler`]. Each registered
+//! interrupt gets a dedicated interrupt thread, i.e. a global EC with an SC, because only
+//! ECs with an SC can block on a semaphore. The thread waits on the semaphore and calls the
+//! handler for each interrupt. Like all global ECs of the roottask, the threads run on
+//! CPU 0, where the exception portals of the roottask are, see
+//! [`crate::roottask_exception::init`]. Driver processes attach interrupts via the
+//! interrupt service instead, see [`crate::services::irq`]: the roottask delegates the
+//! semaphore to the process, which waits on it itself.
+//!
+//! Hedron can't withdraw a GSI from a CPU. A GSI that a process detached stays routed, and
+//! the roottask drains its semaphore before it hands it out again.
+
+use crate::hw::pci::{
+    self,
+    PciConfigSpace,
+    PciOwner,
+};
+use crate::mem::VIRT_MEM_ALLOC;
+use crate::process::{
+    alloc_cap_sels,
+    process_cap_sels,
+    Process,
+};
+use crate::stack::StaticStack;
+use alloc::alloc::Layout;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::syscall::{
+    sys_assign_gsi,
+    sys_create_global_ec,
+    sys_create_sc,
+    sys_pd_ctrl_delegate,
+    sys_revoke,
+    sys_sm_down,
+    DelegateFlags,
+    GsiConfig,
+    MsiInfo,
+    SmCtrlZeroCounterStrategy,
+    SyscallError,
+    SyscallStatus,
+};
+use libhrstd::libhedron::{
+    CapSel,
+    CrdObjSM,
+    Mtd,
+    SMCapPermissions,
+    Utcb,
+    HIP,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::irq::{
+    Irq,
+    IrqAttachment,
+    IrqServiceError,
+    IrqServiceResponse,
+    IrqSource,
+    MsiMessage,
+    PciFunction,
+    MAX_IRQS_PER_PROCESS,
+};
+use libhrstd::rt::services::scheduling::SchedulingParams;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// CPU of the interrupt threads.
+const INTERRUPT_THREAD_CPU: u64 = 0;
+
+/// Stack size of an interrupt thread in pages. Handlers run on it.
+const INTERRUPT_STACK_PAGES: usize = 4;
+
+/// Handler of an interrupt in the roottask. Gets the GSI of the interrupt. Runs on the
+/// interrupt thread of the GSI; it must not wait for other interrupts.
+pub type IrqHandler = fn(gsi: u32);
+
+/// All GSIs and their users.
+static IRQS: SimpleMutex<IrqTable> = SimpleMutex::new(IrqTable::new());
+
+/// Interrupt threads that were created but didn't start yet. See [`handle_thread_startup`].
+static PENDING_THREADS: SimpleMutex<Vec<InterruptThread>> = SimpleMutex::new(Vec::new());
+
+/// Reads the interrupt semaphores from the HIP. Hedron places the interrupt semaphores of
+/// all GSIs at the end of the capability space of the roottask.
+pub fn init(hip: &HIP) {
+    let mut irqs = IRQS.lock();
+    irqs.gsi_count = hip.num_gsi_sel();
+    irqs.gsi_sm_base = (hip.sel_num() - hip.num_gsi_sel()) as CapSel;
+    log::info!(
+        "{} GSIs; interrupt semaphores from selector {}",
+        irqs.gsi_count,
+        irqs.gsi_sm_base
+    );
+}
+
+/// Routes the interrupt to CPU 0 and starts an interrupt thread that calls the handler for
+/// each interrupt. Returns the GSI. The registration is permanent.
+pub fn register_handler(source: IrqSource, handler: IrqHandler) -> IrqServiceResponse<u32> {
+    let (gsi, _) = claim_and_assign(source, User::Roottask, INTERRUPT_THREAD_CPU)?;
+    let sm_sel = IRQS.lock().sm_sel(gsi);
+    spawn_thread(InterruptThread {
+        gsi,
+        sm_sel,
+        handler,
+        stack_top: 0,
+    });
+    log::info!("registered handler for GSI {} ({:?})", gsi, source);
+    Ok(gsi)
+}
+
+/// Routes the interrupt to the CPU of the process and delegates the interrupt semaphore to
+/// it. See [`libhrstd::rt::services::irq::IrqAttachRequest`].
+pub fn attach_to_process(
+    process: &Process,
+    source: IrqSource,
+) -> IrqServiceResponse<IrqAttachment> {
+    let pid = process.pid();
+    if belongs_to_roottask(source) {
+        log::debug!("pid={} can't attach {:?} of the roottask", pid, source);
+        return Err(IrqServiceError::Busy);
+    }
+    let sel = IRQS.lock().free_process_sel(pid)?;
+    let (gsi, msi) = claim_and_assign(source, User::Process { pid, sel }, process.cpu())?;
+    let sm_sel = IRQS.lock().sm_sel(gsi);
+    sys_pd_ctrl_delegate(
+        RootCapSpace::RootPd.val(),
+        process_cap_sels(pid)
+            .expect("running processes have capability selectors")
+            .pd(),
+        CrdObjSM::new(sm_sel, 0, SMCapPermissions::DOWN),
+        CrdObjSM::new(sel, 0, SMCapPermissions::DOWN),
+        DelegateFlags::default(),
+    )
+    .unwrap();
+    log::debug!("pid={} attached GSI {} ({:?})", pid, gsi, source);
+    Ok(IrqAttachment {
+        irq: Irq::new(gsi, sel),
+        msi: msi.map(|msi| MsiMessage {
+            address: msi.address,
+            data: msi.data,
+        }),
+    })
+}
+
+/// Checks if the interrupt comes from a PCI function that a driver of the roottask claimed.
+/// Interrupts that the roottask handles itself are busy anyway, see [`register_handler`].
+fn belongs_to_roottask(source: IrqSource) -> bool {
+    match source {
+        IrqSource::Gsi(gsi) | IrqSource::PciIntx(gsi) => {
+            pci::uses_interrupt_line(PciOwner::Roottask, gsi)
+        }
+        IrqSource::Msi(function) => pci::owner(function) == Some(PciOwner::Roottask),
+    }
+}
+
+/// Revokes the interrupt semaphore from the process and frees the GSI.
+pub fn detach_from_process(pid: ProcessId, irq: Irq) -> IrqServiceResponse<()> {
+    let (sm_sel, msi_function) = IRQS.lock().release(pid, irq)?;
+    // keeps the semaphore of the roottask
+    if let Err(e) = sys_revoke(CrdObjSM::new(sm_sel, 0, SMCapPermissions::DOWN), false) {
+        log::warn!("can't revoke GSI {} from pid={}: {:?}", irq.gsi(), pid, e);
+    }
+    if let Some(function) = msi_function {
+        pci_config_space().disable_msi(function.into());
+    }
+    log::debug!("pid={} detached GSI {}", pid, irq.gsi());
+    Ok(())
+}
+
+/// Detaches all interrupts of a stopped process.
+pub fn release_process(pid: ProcessId) {
+    let irqs = IRQS.lock().process_irqs(pid);
+    for irq in irqs {
+        detach_from_process(pid, irq).unwrap();
+    }
+}
+
+/// Prepares the UTCB of the startup exception of a new interrupt thread, see
+/// [`crate::process::ProcessManager::startup_exception_handler`]. The thread gets the
+/// oldest pending entry. Which thread gets which entry doesn't matter, because an entry
+/// doesn't depend on the EC.
+pub fn handle_thread_startup(utcb: &mut Utcb) {
+    let thread = {
+        let mut pending = PENDING_THREADS.lock();
+        assert!(
+            !pending.is_empty(),
+            "startup of an unknown global EC of the roottask"
+        );
+        pending.remove(0)
+    };
+    log::debug!("interrupt thread of GSI {} starts", thread.gsi);
+    let utcb = utcb.exception_data_mut();
+    utcb.mtd = Mtd::RIP_LEN | Mtd::RSP | Mtd::GPR_BSD;
+    let entry: extern "C" fn(*mut InterruptThread) -> ! = interrupt_thread_entry;
+    utcb.rip = entry as u64;
+    utcb.rsp = thread.stack_top;
+    utcb.rdi = Box::into_raw(Box::new(thread)) as u64;
+}
+
+/// Who receives the interrupts of a GSI.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum User {
+    /// An interrupt thread of the roottask.
+    Roottask,
+    /// A process that has the interrupt semaphore at `sel` of its capability space.
+    Process { pid: ProcessId, sel: CapSel },
+}
+
+/// A GSI that is in use.
+#[derive(Copy, Clone, Debug)]
+struct IrqLine {
+    user: User,
+    /// The PCI function whose MSI uses the GSI.
+    msi_function: Option<PciFunction>,
+}
+
+/// Claims a GSI for the interrupt, routes it to the CPU, and programs the MSI capability of
+/// the function of an [`IrqSource::Msi`]. Returns the GSI and the MSI message.
+fn claim_and_assign(
+    source: IrqSource,
+    user: User,
+    cpu: u64,
+) -> IrqServiceResponse<(u32, Option<MsiInfo>)> {
+    // fail before the GSI is claimed
+    let msi_cfg_addr = match source {
+        IrqSource::Msi(function) => Some(pci_config_page(function)?),
+        _ => None,
+    };
+
+    let (gsi, sm_sel) = {
+        let mut irqs = IRQS.lock();
+        let gsi = irqs.claim(source, user)?;
+        (gsi, irqs.sm_sel(gsi))
+    };
+    let config = match source {
+        IrqSource::PciIntx(_) => Some(GsiConfig {
+            level_triggered: true,
+            active_low: true,
+        }),
+        _ => None,
+    };
+    let msi = match sys_assign_gsi(sm_sel, msi_cfg_addr.unwrap_or(0), cpu, config) {
+        Ok(msi) => msi,
+        Err(e) => {
+            log::warn!("can't assign GSI {} to CPU {}: {:?}", gsi, cpu, e);
+            IRQS.lock().lines.remove(&gsi);
+            return Err(IrqServiceError::AssignFailed);
+        }
+    };
+    drain(sm_sel);
+
+    if let IrqSource::Msi(function) = source {
+        let programmed =
+            pci_config_space().enable_msi(function.into(), msi.address, msi.data as u16);
+        assert!(programmed, "checked before");
+        Ok((gsi, Some(msi)))
+    } else {
+        Ok((gsi, None))
+    }
+}
+
+/// Resets the counter of an interrupt semaphore, i.e. forgets interrupts of a former user.
+fn drain(sm_sel: CapSel) {
+    // the deadline is in the past, hence Hedron doesn't block
+    match sys_sm_down(sm_sel, SmCtrlZeroCounterStrategy::SetToZero, Some(1)) {
+        Ok(_) | Err(SyscallError::HedronStatusError(SyscallStatus::Timeout)) => {}
+        Err(e) => panic!("can't drain interrupt semaphore {}: {:?}", sm_sel, e),
+    }
+}
+
+/// Returns the address of the mapped page of the configuration space of the function, as
+/// needed by [`sys_assign_gsi`]. Fails if the function has no MSI capability or the
+/// platform has no memory-mapped configuration space.
+fn pci_config_page(function: PciFunction) -> IrqServiceResponse<u64> {
+    if !pci_config_space().has_msi(function.into()) {
+        return Err(IrqServiceError::MsiUnsupported);
+    }
+    pci::config_page(function.into()).ok_or(IrqServiceError::MsiUnsupported)
+}
+
+fn pci_config_space() -> PciConfigSpace {
+    PciConfigSpace::new(RootCapSpace::RootPd.val())
+        .expect("the roottask can access the PCI configuration space")
+}
+
+/// What an interrupt thread needs to know. It lives on the heap once the thread runs.
+#[derive(Debug)]
+struct InterruptThread {
+    gsi: u32,
+    sm_sel: CapSel,
+    handler: IrqHandler,
+    stack_top: u64,
+}
+
+/// Creates the global EC and the SC of a new interrupt thread. The thread starts with a
+/// startup exception, see [`handle_thread_startup`].
+fn spawn_thread(mut thread: InterruptThread) {
+    let ec_sel = alloc_cap_sels(2).expect("capability space of the roottask is exhausted");
+    let sc_sel = ec_sel + 1;
+    let stack = StaticStack::<INTERRUPT_STACK_PAGES>::new_leaked();
+    unsafe {
+        stack.activate_guard_page(
+            RootCapSpace::RootPd.val(),
+            format!("interrupt thread of GSI {}", thread.gsi),
+        )
+    };
+    thread.stack_top = stack.get_stack_top_ptr() as u64;
+    let utcb_addr = VIRT_MEM_ALLOC
+        .lock()
+        .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
+    PENDING_THREADS.lock().push(thread);
+
+    sys_create_global_ec(
+        ec_sel,
+        RootCapSpace::RootPd.val(),
+        RootCapSpace::ExceptionEventBase.val(),
+        INTERRUPT_THREAD_CPU,
+        utcb_addr / PAGE_SIZE as u64,
+    )
+    .unwrap();
+    // the locks of the roottask are spin locks; a higher priority could starve the holder
+    sys_create_sc(
+        sc_sel,
+        RootCapSpace::RootPd.val(),
+        ec_sel,
+        SchedulingParams::DEFAULT.qpd(),
+    )
+    .unwrap();
+}
+
+/// Main loop of an interrupt thread.
+extern "C" fn interrupt_thread_entry(thread: *mut InterruptThread) -> ! {
+    let thread = unsafe { Box::from_raw(thread) };
+    loop {
+        sys_sm_down(thread.sm_sel, SmCtrlZeroCounterStrategy::Decrement, None).unwrap();
+        (thread.handler)(thread.gsi);
+    }
+}
+
+/// The GSIs that are in use. Contains only plain data; the caller performs the syscalls.
+#[derive(Debug)]
+struct IrqTable {
+    gsi_count: u32,
+    /// Selector of the interrupt semaphore of GSI 0 in the capability space of the
+    /// roottask.
+    gsi_sm_base: CapSel,
+    lines: BTreeMap<u32, IrqLine>,
+}
+
+impl IrqTable {
+    const fn new() -> Self {
+        Self {
+            gsi_count: 0,
+            gsi_sm_base: 0,
+            lines: BTreeMap::new(),
+        }
+    }
+
+    const fn sm_sel(&self, gsi: u32) -> CapSel {
+        self.gsi_sm_base + gsi as CapSel
+    }
+
+    /// Marks the GSI of the interrupt as used. For an MSI, it picks the highest free GSI.
+    fn claim(&mut self, source: IrqSource, user: User) -> IrqServiceResponse<u32> {
+        let (gsi, msi_function) = match source {
+            IrqSource::Gsi(gsi) | IrqSource::PciIntx(gsi) => {
+                if gsi >= self.gsi_count {
+                    return Err(IrqServiceError::InvalidIrq);
+                }
+                if self.lines.contains_key(&gsi) {
+                    return Err(IrqServiceError::Busy);
+                }
+                (gsi, None)
+            }
+            IrqSource::Msi(function) => {
+                if self
+                    .lines
+                    .values()
+                    .any(|line| line.msi_function == Some(function))
+                {
+                    return Err(IrqServiceError::Busy);
+                }
+                let gsi = (0..self.gsi_count)
+                    .rev()
+                    .find(|gsi| !self.lines.contains_key(gsi))
+                    .ok_or(IrqServiceError::NoFreeVector)?;
+                (gsi, Some(function))
+            }
+        };
+        self.lines.insert(gsi, IrqLine { user, msi_function });
+        Ok(gsi)
+    }
+
+    /// Returns the lowest selector in the range of interrupt semaphores of the process
+    /// that no attached interrupt uses.
+    fn free_process_sel(&self, pid: ProcessId) -> IrqServiceResponse<CapSel> {
+        let base = UserAppCapSpace::IrqSmBase.val();
+        (base..base + MAX_IRQS_PER_PROCESS)
+            .find(|sel| {
+                !self
+                    .lines
+                    .values()
+                    .any(|line| line.user == User::Process { pid, sel: *sel })
+            })
+            .ok_or(IrqServiceError::TooManyIrqs)
+    }
+
+    /// Frees the GSI of the interrupt of the process. Returns the selector of the interrupt
+    /// semaphore in the roottask and the PCI function if the interrupt is an MSI.
+    fn release(
+        &mut self,
+        pid: ProcessId,
+        irq: Irq,
+    ) -> IrqServiceResponse<(CapSel, Option<PciFunction>)> {
+        let line = self
+            .lines
+            .get(&irq.gsi())
+            .filter(|line| {
+                line.user
+                    == User::Process {
+                        pid,
+                        sel: irq.sel(),
+                    }
+            })
+            .ok_or(IrqServiceError::UnknownIrq)?;
+        let msi_function = line.msi_function;
+        self.lines.remove(&irq.gsi());
+        Ok((self.sm_sel(irq.gsi()), msi_function))
+    }
+
+    /// Returns all interrupts that the process attached.
+    fn process_irqs(&self, pid: ProcessId) -> Vec<Irq> {
+        self.lines
+            .iter()
+            .filter_map(|(gsi, line)| match line.user {
+                User::Process { pid: line_pid, sel } if line_pid == pid => {
+                    Some(Irq::new(*gsi, sel))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_irq_table() {
+        let mut table = IrqTable::new();
+        table.gsi_count = 24;
+        table.gsi_sm_base = 1000;
+        let base = UserAppCapSpace::IrqSmBase.val();
+        let function = PciFunction {
+            bus: 0,
+            device: 3,
+            function: 0,
+        };
+
+        assert_eq!(
+            table.claim(IrqSource::Gsi(24), User::Roottask),
+            Err(IrqServiceError::InvalidIrq)
+        );
+        assert_eq!(table.claim(IrqSource::Gsi(4), User::Roottask), Ok(4));
+        assert_eq!(
+            table.claim(IrqSource::PciIntx(4), User::Roottask),
+            Err(IrqServiceError::Busy)
+        );
+        assert_eq!(table.sm_sel(4), 1004);
+
+        // MSIs take the GSIs from the top
+        assert_eq!(table.free_process_sel(1), Ok(base));
+        let user = User::Process { pid: 1, sel: base };
+        assert_eq!(table.claim(IrqSource::Msi(function), user), Ok(23));
+        assert_eq!(
+            table.claim(IrqSource::Msi(function), User::Roottask),
+            Err(IrqServiceError::Busy)
+        );
+        assert_eq!(table.free_process_sel(1), Ok(base + 1));
+        assert_eq!(table.free_process_sel(2), Ok(base));
+        let user = User::Process {
+            pid: 1,
+            sel: base + 1,
+        };
+        assert_eq!(table.claim(IrqSource::Gsi(9), user), Ok(9));
+        assert_eq!(
+            table.process_irqs(1),
+            [Irq::new(9, base + 1), Irq::new(23, base)]
+        );
+
+        assert_eq!(
+            table.release(2, Irq::new(23, base)),
+            Err(IrqServiceError::UnknownIrq)
+        );
+        assert_eq!(
+            table.release(1, Irq::new(23, base)),
+            Ok((1023, Some(function)))
+        );
+        assert_eq!(table.release(1, Irq::new(9, base + 1)), Ok((1009, None)));
+        assert!(table.process_irqs(1).is_empty());
+        assert_eq!(
+            table.claim(IrqSource::Msi(function), User::Roottask),
+            Ok(23)
+        );
+    }
+
+    #[test]
+    fn test_no_free_vector() {
+        let mut table = IrqTable::new();
+        table.gsi_count = 2;
+        let function = |device| PciFunction {
+            bus: 0,
+            device,
+            function: 0,
+        };
+        assert_eq!(
+            table.claim(IrqSource::Msi(function(1)), User::Roottask),
+            Ok(1)
+        );
+        assert_eq!(table.claim(IrqSource::Gsi(0), User::Roottask), Ok(0));
+        assert_eq!(
+            table.claim(IrqSource::Msi(function(2)), User::Roottask),
+            Err(IrqServiceError::NoFreeVector)
+        );
+    }
+}
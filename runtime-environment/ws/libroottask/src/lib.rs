@@ -41,11 +41,29 @@ extern crate alloc;
 #[macro_use]
 extern crate libhrstd;
 
+pub mod cap_transfer;
+pub mod deterministic;
+pub mod fs_quota;
+pub mod gdb_stub;
+pub mod hedron_features;
+pub mod hw;
 pub mod io_port;
+pub mod irq;
+pub mod log_buffer;
+pub mod log_format;
+pub mod log_timestamp;
 pub mod mem;
 pub mod process;
 pub mod pt_multiplex;
+pub mod rate_limit;
 pub mod roottask_exception;
 pub mod rt;
+pub mod safe_mode;
+pub mod selfcheck;
+pub mod service_stats;
 pub mod services;
+pub mod smp;
 pub mod stack;
+pub mod static_alloc;
+pub mod time;
+pub mod uname;
@@ -0,0 +1,323 @@
+//! In-memory log of the roottask and of all processes that log via the log service (see
+//! [`crate::services::log`]). The last [`LOG_BUFFER_CAPACITY`] records stay in a ring
+//! buffer, where `dmesg` and other programs can query them, independent of the console.
+//!
+//! Filters decide which records the log takes: a default level, a level per process, and
+//! a level per module path. The longest module path that matches the target of a record
+//! wins over the level of the process, which wins over the default level. The boot
+//! argument `log_level=<level>` sets the default level, see [`crate::rt::boot_args`].
+//!
+//! The roottask prints each record that the log takes on the serial port and debugcon,
+//! unless the console output is disabled, e.g. with the boot argument `log_serial=off`.
+//! Output on the serial port perturbs benchmarks, because it is slow.
+
+use crate::log_timestamp;
+use crate::log_timestamp::LogTimestamp;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::logging::{
+    LogEntry,
+    LogLevel,
+    LogScope,
+    LOG_MAX_MSG_LEN,
+    LOG_MAX_TARGET_LEN,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Number of records that the log keeps. Older ones are dropped.
+pub const LOG_BUFFER_CAPACITY: usize = 2048;
+
+/// Rough size of the fields of a serialized [`LogEntry`] besides the target and the message.
+const ENTRY_OVERHEAD: usize = 32;
+
+static BUFFER: SimpleMutex<LogBuffer> = SimpleMutex::new(LogBuffer::new());
+
+static FILTERS: SimpleMutex<LogFilters> = SimpleMutex::new(LogFilters::new());
+
+static CONSOLE: AtomicBool = AtomicBool::new(true);
+
+/// The records by sequence number.
+#[derive(Debug)]
+struct LogBuffer {
+    entries: BTreeMap<u64, LogEntry>,
+    next_seq: u64,
+}
+
+impl LogBuffer {
+    const fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    fn push(&mut self, mut entry: LogEntry) {
+        if self.entries.len() == LOG_BUFFER_CAPACITY {
+            let oldest = *self.entries.keys().next().unwrap();
+            self.entries.remove(&oldest);
+        }
+        entry.seq = self.next_seq;
+        self.next_seq += 1;
+        self.entries.insert(entry.seq, entry);
+    }
+
+    /// Collects the matching records from `from` on until they occupy about `max_bytes`
+    /// serialized. Returns them, the sequence number to continue from, and whether
+    /// further records may match.
+    fn read(
+        &self,
+        from: u64,
+        pid: Option<ProcessId>,
+        level: LogLevel,
+        max_bytes: usize,
+    ) -> (Vec<LogEntry>, u64, bool) {
+        let mut entries = Vec::new();
+        let mut bytes = 0;
+        let matching = self
+            .entries
+            .range(from..)
+            .map(|(_, entry)| entry)
+            .filter(|entry| pid.map_or(true, |pid| entry.pid == pid) && entry.level <= level);
+        for entry in matching {
+            bytes += entry.target.len() + entry.msg.len() + ENTRY_OVERHEAD;
+            entries.push(entry.clone());
+            if bytes >= max_bytes {
+                return (entries, entry.seq + 1, true);
+            }
+        }
+        (entries, self.next_seq, false)
+    }
+}
+
+#[derive(Debug)]
+struct LogFilters {
+    default: LogLevel,
+    processes: BTreeMap<ProcessId, LogLevel>,
+    modules: BTreeMap<String, LogLevel>,
+}
+
+impl LogFilters {
+    const fn new() -> Self {
+        Self {
+            default: LogLevel::Info,
+            processes: BTreeMap::new(),
+            modules: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the least severe level that the log takes from the target of the process.
+    fn level(&self, pid: ProcessId, target: &str) -> LogLevel {
+        self.modules
+            .iter()
+            .filter(|(module, _)| is_in_module(target, module))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or_else(|| self.process_level(pid))
+    }
+
+    fn process_level(&self, pid: ProcessId) -> LogLevel {
+        self.processes.get(&pid).copied().unwrap_or(self.default)
+    }
+
+    /// Returns the least severe level that the log takes from any target of the process.
+    fn max_level(&self, pid: ProcessId) -> LogLevel {
+        self.modules
+            .values()
+            .copied()
+            .fold(self.process_level(pid), LogLevel::max)
+    }
+
+    fn set(&mut self, scope: LogScope, level: LogLevel) {
+        match scope {
+            LogScope::Default => self.default = level,
+            LogScope::Process(pid) => {
+                self.processes.insert(pid, level);
+            }
+            LogScope::Module(module) => {
+                self.modules.insert(module, level);
+            }
+        }
+    }
+}
+
+/// Returns true if the target is the module or one of its submodules.
+fn is_in_module(target: &str, module: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .map_or(false, |rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Shortens the string to at most `max_len` bytes at a character boundary.
+fn truncate(text: &mut String, max_len: usize) {
+    if text.len() > max_len {
+        let len = (0..=max_len)
+            .rev()
+            .find(|len| text.is_char_boundary(*len))
+            .unwrap();
+        text.truncate(len);
+    }
+}
+
+/// Returns true if the log takes records of the level from the target of the process.
+pub fn is_enabled(pid: ProcessId, target: &str, level: LogLevel) -> bool {
+    level != LogLevel::Off && level <= FILTERS.lock().level(pid, target)
+}
+
+/// Returns the least severe level that the log takes from the process.
+pub fn level(pid: ProcessId) -> LogLevel {
+    FILTERS.lock().max_level(pid)
+}
+
+/// Sets the level of a filter. Also adjusts the maximum level of the logger of the
+/// roottask, see [`log::set_max_level`].
+pub fn set_level(scope: LogScope, level: LogLevel) {
+    log::debug!("log level of {:?}: {}", scope, level.as_str());
+    let max_level = {
+        let mut filters = FILTERS.lock();
+        filters.set(scope, level);
+        filters.max_level(ROOTTASK_PROCESS_PID)
+    };
+    log::set_max_level(max_level.level_filter());
+}
+
+/// Drops the filter of a process, i.e. when the process exits. The next process with its
+/// PID starts with the default level.
+pub fn forget_process(pid: ProcessId) {
+    FILTERS.lock().processes.remove(&pid);
+}
+
+/// Adds a record to the log, regardless of the filters; see [`is_enabled`]. Truncates the
+/// target and the message. Returns the record.
+pub fn record(pid: ProcessId, level: LogLevel, target: &str, msg: String) -> LogEntry {
+    let mut target = String::from(target);
+    truncate(&mut target, LOG_MAX_TARGET_LEN);
+    let mut msg = msg;
+    truncate(&mut msg, LOG_MAX_MSG_LEN);
+    let timestamp_ns = match log_timestamp::now() {
+        Some(LogTimestamp::Ns(ns)) => Some(ns),
+        _ => None,
+    };
+    let mut entry = LogEntry {
+        seq: 0,
+        timestamp_ns,
+        pid,
+        level,
+        target,
+        msg,
+    };
+    let mut buffer = BUFFER.lock();
+    entry.seq = buffer.next_seq;
+    buffer.push(entry.clone());
+    entry
+}
+
+/// Returns the records from the sequence number `from` on that match `pid` and `level`,
+/// until they occupy about `max_bytes` serialized. Also returns the sequence number to
+/// continue from and whether further records may match.
+pub fn read(
+    from: u64,
+    pid: Option<ProcessId>,
+    level: LogLevel,
+    max_bytes: usize,
+) -> (Vec<LogEntry>, u64, bool) {
+    BUFFER.lock().read(from, pid, level, max_bytes)
+}
+
+/// Removes all records before the sequence number `until`.
+pub fn clear(until: u64) {
+    let mut buffer = BUFFER.lock();
+    buffer.entries = buffer.entries.split_off(&until);
+}
+
+/// Enables or disables the output of the log on the console.
+pub fn set_console_enabled(enabled: bool) {
+    CONSOLE.store(enabled, Ordering::SeqCst);
+}
+
+/// Returns true if the records of the log are printed on the console.
+pub fn is_console_enabled() -> bool {
+    CONSOLE.load(Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pid: ProcessId, level: LogLevel, msg: &str) -> LogEntry {
+        LogEntry {
+            seq: 0,
+            timestamp_ns: None,
+            pid,
+            level,
+            target: String::from("app"),
+            msg: String::from(msg),
+        }
+    }
+
+    #[test]
+    fn test_log_buffer() {
+        let mut buffer = LogBuffer::new();
+        for i in 0..LOG_BUFFER_CAPACITY + 2 {
+            let pid = if i % 2 == 0 { 1 } else { 2 };
+            buffer.push(entry(pid, LogLevel::Info, "hello"));
+        }
+        buffer.push(entry(1, LogLevel::Error, "oops"));
+        assert_eq!(buffer.entries.len(), LOG_BUFFER_CAPACITY);
+        assert_eq!(*buffer.entries.keys().next().unwrap(), 3);
+
+        let (entries, next, more) = buffer.read(0, Some(1), LogLevel::Warn, usize::MAX);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].msg, "oops");
+        assert_eq!(entries[0].seq, LOG_BUFFER_CAPACITY as u64 + 2);
+        assert_eq!((next, more), (buffer.next_seq, false));
+
+        // stops after the record that exceeds the size
+        let (entries, next, more) = buffer.read(10, None, LogLevel::Info, 1);
+        assert_eq!(entries.len(), 1);
+        assert_eq!((next, more), (11, true));
+    }
+
+    #[test]
+    fn test_log_filters() {
+        let mut filters = LogFilters::new();
+        assert_eq!(filters.level(3, "app"), LogLevel::Info);
+        filters.set(LogScope::Process(3), LogLevel::Warn);
+        filters.set(
+            LogScope::Module(String::from("libroottask")),
+            LogLevel::Error,
+        );
+        filters.set(
+            LogScope::Module(String::from("libroottask::mem")),
+            LogLevel::Trace,
+        );
+        assert_eq!(filters.level(3, "app"), LogLevel::Warn);
+        assert_eq!(filters.level(4, "app"), LogLevel::Info);
+        assert_eq!(filters.level(0, "libroottask::process"), LogLevel::Error);
+        assert_eq!(
+            filters.level(0, "libroottask::mem::pagefault"),
+            LogLevel::Trace
+        );
+        // no module boundary
+        assert_eq!(filters.level(0, "libroottask_extra"), LogLevel::Info);
+        assert_eq!(filters.max_level(3), LogLevel::Trace);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut text = String::from("aä");
+        truncate(&mut text, 2);
+        assert_eq!(text, "a");
+        let mut text = String::from("abc");
+        truncate(&mut text, 3);
+        assert_eq!(text, "abc");
+    }
+}
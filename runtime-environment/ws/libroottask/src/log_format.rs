@@ -0,0 +1,36 @@
+//! Format of the log output of the roottask. Besides formatted text, the roottask can emit
+//! compact binary records (see [`libhrstd::util::binary_log`]) to reduce the bandwidth of
+//! the serial port during benchmarks. The boot argument `log_format=binary` selects it,
+//! see [`crate::rt::boot_args`]. The host tool `logdecoder-host` turns the output back
+//! into text.
+//!
+//! Only the log of the roottask is affected. The output of the STDOUT and STDERR
+//! services, and hence the log of other processes, stays text.
+
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+
+static BINARY: AtomicBool = AtomicBool::new(false);
+
+/// Format of the log output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Binary,
+}
+
+/// Sets the format of all following log output.
+pub fn set(format: LogFormat) {
+    BINARY.store(format == LogFormat::Binary, Ordering::SeqCst);
+}
+
+/// Returns the current format of the log output.
+pub fn get() -> LogFormat {
+    if BINARY.load(Ordering::SeqCst) {
+        LogFormat::Binary
+    } else {
+        LogFormat::Text
+    }
+}
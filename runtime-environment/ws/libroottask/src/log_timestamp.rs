@@ -0,0 +1,89 @@
+//! Timestamps for the log output of the roottask and for the output that processes send
+//! via the STDOUT and STDERR services. Each line starts with the monotonic time at which
+//! the roottask formatted it, e.g. `[    2.000417309]`. Before [`crate::time::init`]
+//! calibrated the TSC, lines carry raw TSC ticks instead, e.g. `[tsc=37591811]`.
+//!
+//! Golden-output tests need byte-identical output; the boot argument
+//! `log_timestamps=off` disables the timestamps, see [`crate::rt::boot_args`].
+
+use crate::time;
+use core::fmt::{
+    Display,
+    Formatter,
+};
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use libhrstd::util::binary_log::BinaryLogTimestamp;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enables or disables the timestamps of all following lines.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Returns the timestamp for a line that gets written now, or `None` if timestamps
+/// are disabled.
+pub fn now() -> Option<LogTimestamp> {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return None;
+    }
+    let tsc = time::tsc_now();
+    if time::is_calibrated() {
+        Some(LogTimestamp::Ns(time::ticks_to_ns(tsc)))
+    } else {
+        Some(LogTimestamp::Ticks(tsc))
+    }
+}
+
+/// Point in time of a log line. Formats itself including the surrounding brackets and a
+/// trailing space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogTimestamp {
+    /// Nanoseconds since the TSC started.
+    Ns(u64),
+    /// Raw TSC value; the frequency of the TSC is unknown yet.
+    Ticks(u64),
+}
+
+impl Display for LogTimestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Ns(ns) => write!(f, "[{:>5}.{:09}] ", ns / NS_PER_SEC, ns % NS_PER_SEC),
+            Self::Ticks(ticks) => write!(f, "[tsc={}] ", ticks),
+        }
+    }
+}
+
+impl From<LogTimestamp> for BinaryLogTimestamp {
+    fn from(timestamp: LogTimestamp) -> Self {
+        match timestamp {
+            LogTimestamp::Ns(ns) => Self::Ns(ns),
+            LogTimestamp::Ticks(ticks) => Self::Ticks(ticks),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_log_timestamp_fmt() {
+        assert_eq!(LogTimestamp::Ns(0).to_string(), "[    0.000000000] ");
+        assert_eq!(
+            LogTimestamp::Ns(2_000_417_309).to_string(),
+            "[    2.000417309] "
+        );
+        assert_eq!(
+            LogTimestamp::Ns(123_456 * NS_PER_SEC + 7).to_string(),
+            "[123456.000000007] "
+        );
+        assert_eq!(LogTimestamp::Ticks(37591811).to_string(), "[tsc=37591811] ");
+    }
+}
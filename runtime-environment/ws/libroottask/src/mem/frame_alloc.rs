@@ -0,0 +1,189 @@
+//! Module for [`PhysFrameAllocator`].
+
+use alloc::vec::Vec;
+use core::ops::Range;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::HIP;
+use libhrstd::sync::mutex::SimpleMutex;
+
+pub type PhysAddr = u64;
+
+/// Physical memory below this address is never handed out. It contains the real mode
+/// structures of the BIOS, the VGA memory, and the option ROMs, even if the memory map
+/// reports parts of it as usable.
+const MIN_PHYS_ADDR: PhysAddr = 0x100000;
+
+/// Public instance that manages all physical memory that is free after the handoff to
+/// the roottask. [`PhysFrameAllocator::init`] must be called once the heap works.
+pub static PHYS_FRAME_ALLOC: SimpleMutex<PhysFrameAllocator> =
+    SimpleMutex::new(PhysFrameAllocator::new());
+
+/// Hands out contiguous physical page frames from the free memory of the HIP memory map.
+/// The roottask uses them for memory that isn't part of its own heap, i.e. the memory of
+/// user processes and DMA buffers of devices. Only hands out addresses; mapping the frames
+/// somewhere is up to the caller.
+///
+/// Keeps a list of free ranges, sorted by address, and allocates the first range that is
+/// big enough (first fit). Freed frames get merged with their neighbours.
+#[derive(Debug)]
+pub struct PhysFrameAllocator {
+    /// Free, page-aligned ranges. Sorted, disjoint, and never adjacent to each other.
+    free: Vec<Range<PhysAddr>>,
+    /// Number of pages that the allocator manages in total.
+    total_pages: usize,
+}
+
+impl PhysFrameAllocator {
+    const fn new() -> Self {
+        Self {
+            free: Vec::new(),
+            total_pages: 0,
+        }
+    }
+
+    /// Adds all free memory of the HIP memory map, see [`HIP::free_mem_iterator`].
+    pub fn init(&mut self, hip: &HIP) {
+        assert_eq!(self.total_pages, 0, "init only permitted once!");
+        hip.free_mem_iterator()
+            .for_each(|range| self.add_region(range));
+        log::info!(
+            "physical frame allocator: {} MiB free in {} ranges",
+            self.total_pages * PAGE_SIZE / (1024 * 1024),
+            self.free.len()
+        );
+    }
+
+    /// Adds a region of free physical memory. Shrinks it to whole pages above
+    /// [`MIN_PHYS_ADDR`]. Parts that the allocator already knows are fine.
+    fn add_region(&mut self, range: Range<PhysAddr>) {
+        let page_size = PAGE_SIZE as u64;
+        let start = range.start.max(MIN_PHYS_ADDR);
+        let start = (start + page_size - 1) / page_size * page_size;
+        let end = range.end / page_size * page_size;
+        if start >= end {
+            return;
+        }
+        let known_pages = self
+            .free
+            .iter()
+            .map(|free| free.end.min(end).saturating_sub(free.start.max(start)))
+            .sum::<u64>() as usize
+            / PAGE_SIZE;
+        self.insert(start..end);
+        self.total_pages += (end - start) as usize / PAGE_SIZE - known_pages;
+    }
+
+    /// Allocates `page_count` physically contiguous frames. Returns the physical address
+    /// of the first frame or `None` if no free range is big enough. The frames keep
+    /// their old content.
+    pub fn alloc(&mut self, page_count: usize) -> Option<PhysAddr> {
+        assert!(page_count > 0, "page_count must be not null");
+        let size = (page_count * PAGE_SIZE) as u64;
+        let index = self
+            .free
+            .iter()
+            .position(|range| range.end - range.start >= size)?;
+        let addr = self.free[index].start;
+        self.free[index].start += size;
+        if self.free[index].is_empty() {
+            self.free.remove(index);
+        }
+        Some(addr)
+    }
+
+    /// Returns frames that [`Self::alloc`] handed out.
+    pub fn free(&mut self, addr: PhysAddr, page_count: usize) {
+        assert_eq!(addr % PAGE_SIZE as u64, 0, "must be page address");
+        let range = addr..addr + (page_count * PAGE_SIZE) as u64;
+        assert!(
+            !self
+                .free
+                .iter()
+                .any(|free| free.start < range.end && range.start < free.end),
+            "frames {:x?} are already free",
+            range
+        );
+        self.insert(range);
+    }
+
+    /// Inserts a range into the free list and merges it with all ranges that overlap or
+    /// touch it.
+    fn insert(&mut self, mut range: Range<PhysAddr>) {
+        let first = self
+            .free
+            .iter()
+            .position(|free| free.end >= range.start)
+            .unwrap_or(self.free.len());
+        let last = self.free[first..]
+            .iter()
+            .position(|free| free.start > range.end)
+            .map_or(self.free.len(), |count| first + count);
+        if first < last {
+            range.start = range.start.min(self.free[first].start);
+            range.end = range.end.max(self.free[last - 1].end);
+        }
+        self.free.splice(first..last, core::iter::once(range));
+    }
+
+    /// Number of pages that the allocator manages in total.
+    pub fn total_pages(&self) -> usize {
+        self.total_pages
+    }
+
+    /// Number of pages that are currently free.
+    pub fn free_pages(&self) -> usize {
+        self.free
+            .iter()
+            .map(|range| (range.end - range.start) as usize / PAGE_SIZE)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phys_frame_alloc() {
+        let mut alloc = PhysFrameAllocator::new();
+        // low memory and partial pages get dropped
+        alloc.add_region(0..0x9f000);
+        alloc.add_region(0x100800..0x105000);
+        alloc.add_region(0x200000..0x204000);
+        // already known memory isn't counted twice
+        alloc.add_region(0x200000..0x204000);
+        assert_eq!(alloc.free, [0x101000..0x105000, 0x200000..0x204000]);
+        assert_eq!(alloc.total_pages(), 8);
+
+        let first = alloc.alloc(3).unwrap();
+        assert_eq!(first, 0x101000);
+        // first fit: the rest of the first range is too small
+        let second = alloc.alloc(2).unwrap();
+        assert_eq!(second, 0x200000);
+        assert_eq!(alloc.alloc(5), None);
+        assert_eq!(alloc.free_pages(), 3);
+
+        alloc.free(first, 3);
+        assert_eq!(alloc.free, [0x101000..0x105000, 0x202000..0x204000]);
+        alloc.free(second, 2);
+        assert_eq!(alloc.free, [0x101000..0x105000, 0x200000..0x204000]);
+        assert_eq!(alloc.free_pages(), alloc.total_pages());
+
+        // frees in the middle merge both neighbours
+        let all = alloc.alloc(4).unwrap();
+        alloc.free(all + PAGE_SIZE as u64, 2);
+        alloc.free(all, 1);
+        alloc.free(all + 3 * PAGE_SIZE as u64, 1);
+        assert_eq!(alloc.free, [0x101000..0x105000, 0x200000..0x204000]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_phys_frame_alloc_double_free() {
+        let mut alloc = PhysFrameAllocator::new();
+        alloc.add_region(0x100000..0x200000);
+        let addr = alloc.alloc(1).unwrap();
+        alloc.free(addr, 1);
+        alloc.free(addr, 1);
+    }
+}
@@ -0,0 +1,189 @@
+//! Module for [`HeapStats`].
+
+use core::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+
+/// Public instance that tracks the utilization of the roottask heap. It gets updated by
+/// the global allocator of the roottask binary. Other parts of the roottask can query it
+/// via [`HeapStats::snapshot`].
+pub static ROOTTASK_HEAP_STATS: HeapStats = HeapStats::new();
+
+/// Continuously tracks the utilization of a heap whose allocator hands out blocks that
+/// may be larger than requested, e.g. the power-of-two blocks of the buddy allocator in
+/// [`crate::static_alloc`]. The difference between the requested bytes and the bytes in
+/// occupied blocks is a good estimate for the internal fragmentation of the heap.
+///
+/// All operations are lock-free, so that they can be used inside the global allocator.
+#[derive(Debug)]
+pub struct HeapStats {
+    /// Size of the heap in bytes.
+    capacity: AtomicU64,
+    /// Bytes requested by all currently active allocations.
+    current_bytes: AtomicU64,
+    /// Highest value of `current_bytes` ever observed.
+    peak_bytes: AtomicU64,
+    /// Bytes in the blocks occupied by all currently active allocations.
+    current_block_bytes: AtomicU64,
+    /// Highest value of `current_block_bytes` ever observed.
+    peak_block_bytes: AtomicU64,
+    /// Total number of allocations.
+    alloc_count: AtomicU64,
+    /// Total number of deallocations.
+    dealloc_count: AtomicU64,
+}
+
+impl HeapStats {
+    /// Constructor. Call [`Self::init`] before allocations get recorded.
+    pub const fn new() -> Self {
+        Self {
+            capacity: AtomicU64::new(0),
+            current_bytes: AtomicU64::new(0),
+            peak_bytes: AtomicU64::new(0),
+            current_block_bytes: AtomicU64::new(0),
+            peak_block_bytes: AtomicU64::new(0),
+            alloc_count: AtomicU64::new(0),
+            dealloc_count: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the size of the tracked heap.
+    pub fn init(&self, capacity: usize) {
+        self.capacity.store(capacity as u64, Ordering::SeqCst);
+    }
+
+    /// Records a successful allocation of `size` bytes that occupies a block of
+    /// `block_size` bytes.
+    pub fn record_alloc(&self, size: usize, block_size: usize) {
+        let size = size as u64;
+        let block_size = block_size as u64;
+        let current_bytes = self.current_bytes.fetch_add(size, Ordering::SeqCst) + size;
+        let current_block_bytes = self
+            .current_block_bytes
+            .fetch_add(block_size, Ordering::SeqCst)
+            + block_size;
+        self.peak_bytes.fetch_max(current_bytes, Ordering::SeqCst);
+        self.peak_block_bytes
+            .fetch_max(current_block_bytes, Ordering::SeqCst);
+        self.alloc_count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Records the deallocation of `size` bytes in a block of `block_size` bytes.
+    pub fn record_dealloc(&self, size: usize, block_size: usize) {
+        self.current_bytes.fetch_sub(size as u64, Ordering::SeqCst);
+        self.current_block_bytes
+            .fetch_sub(block_size as u64, Ordering::SeqCst);
+        self.dealloc_count.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Returns a copy of the current values.
+    pub fn snapshot(&self) -> HeapStatsSnapshot {
+        HeapStatsSnapshot {
+            capacity: self.capacity.load(Ordering::SeqCst),
+            current_bytes: self.current_bytes.load(Ordering::SeqCst),
+            peak_bytes: self.peak_bytes.load(Ordering::SeqCst),
+            current_block_bytes: self.current_block_bytes.load(Ordering::SeqCst),
+            peak_block_bytes: self.peak_block_bytes.load(Ordering::SeqCst),
+            alloc_count: self.alloc_count.load(Ordering::SeqCst),
+            dealloc_count: self.dealloc_count.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Prints a report of the heap utilization to the log.
+    pub fn log_report(&self) {
+        let snapshot = self.snapshot();
+        log::info!("heap capacity        : {:>10} bytes", snapshot.capacity);
+        log::info!(
+            "heap usage (current) : {:>10} bytes ({:>10} bytes in blocks, {:.2}%)",
+            snapshot.current_bytes,
+            snapshot.current_block_bytes,
+            snapshot.current_usage() * 100.0
+        );
+        log::info!(
+            "heap usage (peak)    : {:>10} bytes ({:>10} bytes in blocks, {:.2}%)",
+            snapshot.peak_bytes,
+            snapshot.peak_block_bytes,
+            snapshot.peak_usage() * 100.0
+        );
+        log::info!(
+            "heap fragmentation   : {:.2}% (estimated; unused bytes in occupied blocks)",
+            snapshot.fragmentation() * 100.0
+        );
+        log::info!(
+            "heap allocations     : {:>10} allocs, {:>10} deallocs",
+            snapshot.alloc_count,
+            snapshot.dealloc_count
+        );
+    }
+}
+
+/// Copy of the values of [`HeapStats`] at a certain point in time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HeapStatsSnapshot {
+    pub capacity: u64,
+    pub current_bytes: u64,
+    pub peak_bytes: u64,
+    pub current_block_bytes: u64,
+    pub peak_block_bytes: u64,
+    pub alloc_count: u64,
+    pub dealloc_count: u64,
+}
+
+impl HeapStatsSnapshot {
+    /// Current utilization of the heap in blocks, in range `0.0..=1.0`.
+    pub fn current_usage(&self) -> f32 {
+        Self::ratio(self.current_block_bytes, self.capacity)
+    }
+
+    /// Peak utilization of the heap in blocks, in range `0.0..=1.0`.
+    pub fn peak_usage(&self) -> f32 {
+        Self::ratio(self.peak_block_bytes, self.capacity)
+    }
+
+    /// Estimated internal fragmentation, i.e. the share of bytes in occupied blocks that
+    /// are not used by allocations. In range `0.0..=1.0`.
+    pub fn fragmentation(&self) -> f32 {
+        Self::ratio(
+            self.current_block_bytes - self.current_bytes,
+            self.current_block_bytes,
+        )
+    }
+
+    fn ratio(a: u64, b: u64) -> f32 {
+        if b == 0 {
+            0.0
+        } else {
+            a as f32 / b as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heap_stats() {
+        let stats = HeapStats::new();
+        stats.init(1024);
+
+        stats.record_alloc(100, 256);
+        stats.record_alloc(256, 256);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.current_bytes, 356);
+        assert_eq!(snapshot.current_block_bytes, 512);
+        assert_eq!(snapshot.current_usage(), 0.5);
+
+        stats.record_dealloc(256, 256);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.current_bytes, 100);
+        assert_eq!(snapshot.current_block_bytes, 256);
+        assert_eq!(snapshot.peak_bytes, 356);
+        assert_eq!(snapshot.peak_block_bytes, 512);
+        assert_eq!(snapshot.peak_usage(), 0.5);
+        assert_eq!(snapshot.fragmentation(), 156.0 / 256.0);
+        assert_eq!(snapshot.alloc_count, 2);
+        assert_eq!(snapshot.dealloc_count, 1);
+    }
+}
@@ -1,7 +1,13 @@
+mod frame_alloc;
+mod heap_stats;
 mod mem_location;
+mod phys_mapping;
 mod root_mem_mapper;
 mod virt_mem_alloc;
 
+pub use frame_alloc::*;
+pub use heap_stats::*;
 pub use mem_location::*;
+pub use phys_mapping::*;
 pub use root_mem_mapper::*;
 pub use virt_mem_alloc::*;
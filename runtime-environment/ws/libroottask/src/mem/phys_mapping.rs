@@ -0,0 +1,48 @@
+//! Maps physical memory, i.e. page frames or the registers of devices, into the address
+//! space of the roottask.
+
+use crate::mem::{
+    PhysAddr,
+    VIRT_MEM_ALLOC,
+};
+use alloc::alloc::Layout;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::syscall::sys_revoke;
+use libhrstd::libhedron::{
+    CrdMem,
+    MemCapPermissions,
+};
+use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use libhrstd::util::delegation::DelegationBuilder;
+
+/// Maps the pages at `phys_address` to a new address of the roottask and returns it.
+/// Each call creates a new mapping, which the roottask can delegate and revoke on its own.
+pub fn map_phys_into_roottask(phys_address: PhysAddr, page_count: usize) -> u64 {
+    // optimize alignment for faster delegate calls (use Crd order optimization)
+    let size = page_count * PAGE_SIZE;
+    let r_address = VIRT_MEM_ALLOC
+        .lock()
+        .next_addr(Layout::from_size_align(size, size.next_power_of_two()).unwrap());
+    // roottask to roottask: the source is the physical address
+    DelegationBuilder::mem(phys_address..phys_address + size as u64)
+        .at(r_address)
+        .to(RootCapSpace::RootPd.val())
+        .unwrap();
+    r_address
+}
+
+/// Revokes the pages at `r_address` from the roottask and from all processes that got them
+/// from there. Returns `false` if Hedron refused a part.
+pub fn revoke_from_roottask(r_address: u64, page_count: usize) -> bool {
+    let r_page_num = r_address / PAGE_SIZE as u64;
+    let mut revoked = true;
+    CrdDelegateOptimizer::new(r_page_num, r_page_num, page_count).for_each(|params| {
+        let crd = CrdMem::new(params.src_base, params.order, MemCapPermissions::RWX);
+        if let Err(e) = sys_revoke(crd, true) {
+            log::warn!("can't revoke page {}: {:?}", params.src_base, e);
+            revoked = false;
+        }
+    });
+    revoked
+}
@@ -9,7 +9,7 @@ use core::mem::size_of;
 use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::MemCapPermissions;
 use libhrstd::sync::mutex::SimpleMutex;
-use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use libhrstd::util::delegation::DelegationBuilder;
 
 /// Public instance of the root task memory mapper.
 pub static ROOT_MEM_MAPPER: SimpleMutex<RootMemMapper> = SimpleMutex::new(RootMemMapper);
@@ -212,7 +212,7 @@ impl MappedMemory {
 pub struct RootMemMapper;
 
 impl RootMemMapper {
-    /// High level wrapper around [`CrdDelegateOptimizer::mmap`]. Maps memory from the origin
+    /// High level wrapper around [`DelegationBuilder`]. Maps memory from the origin
     /// process to the caller process. If origin==to==Roottask, then the Hypervisor
     /// flag will be set and all addresses will be treated as physical addresses, because
     /// the memory will be identity mapped.
@@ -259,14 +259,12 @@ impl RootMemMapper {
             );
         }
 
-        let src_page_num = src_addr / PAGE_SIZE as u64;
-        let dest_page_num = dest_addr / PAGE_SIZE as u64;
-
-        CrdDelegateOptimizer::new(src_page_num, dest_page_num, page_count as usize).mmap(
-            src_process.pd_obj().cap_sel(),
-            dest_process.pd_obj().cap_sel(),
-            perm,
-        );
+        DelegationBuilder::mem(src_addr..src_addr + page_count * PAGE_SIZE as u64)
+            .perms(perm)
+            .from(src_process.pd_obj().cap_sel())
+            .at(dest_addr)
+            .to(dest_process.pd_obj().cap_sel())
+            .unwrap();
 
         MappedMemory {
             origin_process: Rc::downgrade(src_process),
@@ -1,12 +1,27 @@
+use crate::gdb_stub;
+use crate::hedron_features::{
+    self,
+    HedronFeatures,
+};
+use crate::irq;
 use crate::mem::MappedMemory;
 use crate::process::{
+    assign_process_cap_sels,
+    inherit_resource_limits,
+    register_signal_target,
+    signal_target,
+    AddressSpaceLayout,
     Process,
+    SignalTarget,
     SyscallAbi,
 };
 use crate::roottask_exception;
+use crate::rt::procfs;
+use crate::smp;
 use alloc::collections::BTreeMap;
 use alloc::rc::Rc;
 use alloc::string::String;
+use alloc::vec::Vec;
 use elf_rs::ElfFile;
 
 use libhrstd::kobjects::{
@@ -18,14 +33,126 @@ use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
 use libhrstd::process::consts::{
     ProcessId,
+    NUM_PROCESSES,
     ROOTTASK_PROCESS_PID,
 };
+use libhrstd::rt::services::process::ProcessServiceError;
+use libhrstd::rt::services::scheduling::SchedulingParams;
 use libhrstd::sync::mutex::SimpleMutex;
-use libhrstd::uaddress_space::USER_STACK_TOP;
+use libhrstd::uaddress_space::{
+    USER_STACK_TOP,
+    USER_STACK_VERY_TOP,
+};
 
 /// The global instance for the roottask to manage all processes.
 pub static PROCESS_MNG: SimpleMutex<ProcessManager> = SimpleMutex::new(ProcessManager::new());
 
+/// The PIDs that are in use. Lives outside of [`PROCESS_MNG`], because portal handlers need
+/// PIDs while the process manager is locked.
+static PIDS: SimpleMutex<PidAllocator> = SimpleMutex::new(PidAllocator::new());
+
+/// Reserves the PID for a new process. Returns `None` if all PIDs are in use. A PID stays
+/// in use until the process exited, was stopped, and its parent collected the exit status,
+/// see [`crate::process::reap_process`].
+pub fn allocate_pid() -> Option<ProcessId> {
+    PIDS.lock().allocate()
+}
+
+/// Makes the PID available for new processes. Nothing of the previous process with this PID
+/// may be left. Can be called from every EC of the roottask.
+pub fn free_pid(pid: ProcessId) {
+    PIDS.lock().free(pid);
+}
+
+/// Hands out the PIDs in increasing order and wraps around at [`NUM_PROCESSES`], like Linux.
+/// Hence, a PID gets reused as late as possible.
+#[derive(Debug)]
+struct PidAllocator {
+    used: [bool; NUM_PROCESSES as usize],
+    /// Where the search for a free PID starts.
+    next: ProcessId,
+}
+
+impl PidAllocator {
+    /// The roottask has its PID from the beginning.
+    const FIRST_PID: ProcessId = ROOTTASK_PROCESS_PID + 1;
+
+    const fn new() -> Self {
+        Self {
+            used: [false; NUM_PROCESSES as usize],
+            next: Self::FIRST_PID,
+        }
+    }
+
+    fn allocate(&mut self) -> Option<ProcessId> {
+        let count = NUM_PROCESSES - Self::FIRST_PID;
+        let pid = (0..count)
+            .map(|i| Self::FIRST_PID + (self.next - Self::FIRST_PID + i) % count)
+            .find(|pid| !self.used[*pid as usize])?;
+        self.used[pid as usize] = true;
+        self.next = pid + 1;
+        Some(pid)
+    }
+
+    fn free(&mut self, pid: ProcessId) {
+        assert!(self.used[pid as usize], "pid={} is not in use", pid);
+        self.used[pid as usize] = false;
+    }
+}
+
+/// Returns true if the process may perform system-wide operations, such as launching
+/// programs or adjusting the time. These are the roottask and the processes that the
+/// roottask started itself, i.e. the programs of the bootstrap. Processes that were
+/// launched at runtime are not privileged.
+pub fn is_privileged(pid: ProcessId) -> bool {
+    pid == ROOTTASK_PROCESS_PID
+        || signal_target(pid).map_or(false, |target| target.parent == Some(ROOTTASK_PROCESS_PID))
+}
+
+/// Determines the syscall ABI of a program from its ELF file, see [`SyscallAbi::detect`].
+/// Only if that fails, `fallback_abi` is used. Fails if both are unknown, if the running
+/// Hedron kernel can't run the program, or if the ELF segments don't fit into the address
+/// space, see [`AddressSpaceLayout`].
+pub fn select_syscall_abi(
+    elf_bytes: &[u8],
+    program_name: &str,
+    fallback_abi: Option<SyscallAbi>,
+) -> Result<SyscallAbi, ProcessServiceError> {
+    let syscall_abi = match (SyscallAbi::detect(elf_bytes), fallback_abi) {
+        (Some(detected), Some(fallback)) if detected != fallback => {
+            log::warn!(
+                "program '{}' has the syscall ABI {:?} and not {:?}",
+                program_name,
+                detected,
+                fallback
+            );
+            detected
+        }
+        (Some(detected), _) => detected,
+        (None, Some(fallback)) => fallback,
+        (None, None) => {
+            log::error!(
+                "can't start program '{}': unknown syscall ABI",
+                program_name
+            );
+            return Err(ProcessServiceError::InvalidElf);
+        }
+    };
+    if syscall_abi.is_foreign() && !hedron_features::is_supported(HedronFeatures::FOREIGN_SYSCALLS)
+    {
+        log::error!(
+            "can't start program '{}': the running Hedron doesn't support foreign system calls",
+            program_name
+        );
+        return Err(ProcessServiceError::Unsupported);
+    }
+    if let Err(e) = AddressSpaceLayout::from_elf(elf_bytes) {
+        log::error!("can't start program '{}': {:?}", program_name, e);
+        return Err(ProcessServiceError::InvalidElf);
+    }
+    Ok(syscall_abi)
+}
+
 /// Manager that holds information about all processes that are
 /// started by the current PD. Can be used in the roottask or by
 /// user-apps, that start other apps.
@@ -34,7 +161,6 @@ pub static PROCESS_MNG: SimpleMutex<ProcessManager> = SimpleMutex::new(ProcessMa
 #[derive(Debug)]
 pub struct ProcessManager {
     processes: BTreeMap<ProcessId, Rc<Process>>,
-    pid_counter: u64,
     init: bool,
 }
 
@@ -43,7 +169,6 @@ impl ProcessManager {
     pub const fn new() -> Self {
         ProcessManager {
             processes: BTreeMap::new(),
-            pid_counter: ROOTTASK_PROCESS_PID,
             init: false,
         }
     }
@@ -53,7 +178,13 @@ impl ProcessManager {
         assert!(!self.init);
         // only creates the struct, without syscalls or so
         let process = Process::root(utcb_addr, stack_btm_addr);
-        self.pid_counter += 1;
+        register_signal_target(
+            process.pid(),
+            SignalTarget {
+                parent: None,
+                receives_signals: false,
+            },
+        );
         self.processes.insert(process.pid(), process);
         self.init = true;
     }
@@ -64,30 +195,115 @@ impl ProcessManager {
         self.processes.get(&ROOTTASK_PROCESS_PID).unwrap()
     }
 
-    /// Starts a new process. Will trigger a STARTUP exception.
+    /// Starts a new process. Will trigger a STARTUP exception. Returns `None` if the
+    /// running Hedron kernel can't run the process.
+    ///
+    /// The syscall ABI gets detected from the ELF file, see [`select_syscall_abi`].
+    /// `argv` and `envp` get passed to the program, see [`Process::new`]. `sched_params`
+    /// are the initial scheduling parameters of the main SC of the process. The process
+    /// runs on `cpu`, which must be online, or on the next CPU in round-robin order if
+    /// `None`, see [`smp::next_cpu`]. `aslr` randomizes the address space layout of the
+    /// process, see [`crate::process::AddressSpaceLayout::randomize`].
+    #[allow(clippy::too_many_arguments)]
     pub fn start_process(
         &mut self,
         elf_file: MappedMemory,
         program_name: String,
+        fallback_abi: Option<SyscallAbi>,
+        argv: Vec<String>,
+        envp: Vec<String>,
+        sched_params: SchedulingParams,
+        cpu: Option<u64>,
+        aslr: bool,
+    ) -> Option<ProcessId> {
+        let elf_bytes = elf_file.mem_as_slice::<u8>(elf_file.size() as usize);
+        let syscall_abi = select_syscall_abi(elf_bytes, &program_name, fallback_abi).ok()?;
+        let pid = allocate_pid()?;
+        if assign_process_cap_sels(pid).is_none() {
+            free_pid(pid);
+            return None;
+        }
+        self.start_process_with_pid(
+            pid,
+            elf_file,
+            program_name,
+            syscall_abi,
+            ROOTTASK_PROCESS_PID,
+            argv,
+            envp,
+            sched_params,
+            cpu,
+            aslr,
+        );
+        Some(pid)
+    }
+
+    /// Like [`Self::start_process`] but for a process whose PID and syscall ABI were
+    /// already determined, see [`allocate_pid`] and [`select_syscall_abi`], and whose
+    /// capability selectors were assigned, see [`assign_process_cap_sels`]. `parent` is the
+    /// process that may signal the new process. The roottask always owns the resources of
+    /// the new process, independent of `parent`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start_process_with_pid(
+        &mut self,
+        pid: ProcessId,
+        elf_file: MappedMemory,
+        program_name: String,
         syscall_abi: SyscallAbi,
-    ) -> ProcessId {
+        parent: ProcessId,
+        argv: Vec<String>,
+        envp: Vec<String>,
+        sched_params: SchedulingParams,
+        cpu: Option<u64>,
+        aslr: bool,
+    ) {
         if !self.init {
             panic!("call init() first!");
         }
-        log::info!("starting program '{}'", program_name);
-
-        let pid = self.pid_counter;
-        self.pid_counter += 1;
+        let cpu = cpu.unwrap_or_else(smp::next_cpu);
+        log::info!(
+            "starting program '{}' on CPU {} (aslr={})",
+            program_name,
+            cpu,
+            aslr
+        );
 
         // the process starts itself. the Mng just keeps track of it.
-        let mut process = Process::new(pid, elf_file, program_name, self.root(), syscall_abi);
+        let mut process = Process::new(
+            pid,
+            elf_file,
+            program_name,
+            self.root(),
+            syscall_abi,
+            argv,
+            envp,
+            sched_params,
+            cpu,
+            aslr,
+        );
         process.init();
+        // like after fork on Linux
+        libfileserver::FILESYSTEM.lock().inherit_umask(parent, pid);
+        inherit_resource_limits(parent, pid);
+        procfs::mount(pid, process.argv());
+        register_signal_target(
+            pid,
+            SignalTarget {
+                parent: Some(parent),
+                receives_signals: syscall_abi.is_foreign(),
+            },
+        );
 
         log::debug!("process init done!");
 
         let _ = self.processes.insert(pid, Rc::new(process));
+    }
 
-        pid
+    /// Forgets a stopped process whose PID gets reused. Dropping the process frees its
+    /// memory.
+    pub fn remove_process(&mut self, pid: ProcessId) -> Option<Rc<Process>> {
+        assert_ne!(pid, ROOTTASK_PROCESS_PID);
+        self.processes.remove(&pid)
     }
 
     pub fn terminate_prog(&mut self, _id: ProcessId) -> Result<(), ()> {
@@ -130,26 +346,68 @@ impl ProcessManager {
     /// Prepares the UTCB of the calling portal with the initial machine state to startup
     /// the thread.
     pub fn startup_exception_handler(
-        _pt: &Rc<PtObject>,
+        pt: &Rc<PtObject>,
         process: &Rc<Process>,
         utcb: &mut Utcb,
         do_reply: &mut bool,
     ) {
         log::debug!("startup exception handler");
 
+        // the only global ECs of the roottask that start this way are interrupt threads
+        if process.pid() == ROOTTASK_PROCESS_PID {
+            irq::handle_thread_startup(utcb);
+            *do_reply = true;
+            return;
+        }
+
         let elf = elf_rs::Elf::from_bytes(process.elf_file_bytes()).unwrap();
 
         let utcb = utcb.exception_data_mut();
         utcb.mtd = Mtd::RIP_LEN | Mtd::RSP;
         // todo future work: figure out what global EC triggered this (multithreading, multiple stacks)
-        utcb.rip = elf.entry_point();
+        // dynamically linked programs start in their program interpreter
+        utcb.rip = match process.memory_manager().interpreter() {
+            Some(interpreter) => interpreter.entry,
+            None => elf.entry_point() + process.memory_manager().layout().load_bias(),
+        };
 
         if matches!(process.syscall_abi(), SyscallAbi::Linux) {
             utcb.rsp = process.init_stack_libc_aux_vector() as u64;
         } else {
-            utcb.rsp = USER_STACK_TOP;
+            // like USER_STACK_TOP, but below the very top with ASLR
+            utcb.rsp = process.memory_manager().layout().stack_top()
+                - (USER_STACK_VERY_TOP - USER_STACK_TOP);
         }
 
+        gdb_stub::handle_startup(pt, process, utcb);
         *do_reply = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_allocator() {
+        let mut pids = PidAllocator::new();
+        let all = (0..NUM_PROCESSES - 1)
+            .map(|_| pids.allocate().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(all.first(), Some(&1));
+        assert_eq!(all.last(), Some(&(NUM_PROCESSES - 1)));
+        assert_eq!(pids.allocate(), None);
+
+        pids.free(7);
+        pids.free(3);
+        assert_eq!(pids.allocate(), Some(3));
+        assert_eq!(pids.allocate(), Some(7));
+        assert_eq!(pids.allocate(), None);
+
+        // the search continues after the last PID instead of taking the lowest free one
+        pids.free(2);
+        pids.free(9);
+        assert_eq!(pids.allocate(), Some(9));
+        assert_eq!(pids.allocate(), Some(2));
+    }
+}
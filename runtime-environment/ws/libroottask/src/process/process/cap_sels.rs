@@ -0,0 +1,101 @@
+//! Capability selectors of the roottask for the kernel objects of processes. Each process
+//! gets a block of [`ProcessCapSels::COUNT`] selectors from the dynamic part of the
+//! [`RootCapSpace`] when it starts, see [`assign_process_cap_sels`]. When it stops, the
+//! roottask revokes all capabilities of the block and reuses the selectors for later
+//! processes, see [`release_process_cap_sels`]. Other parts of the roottask can allocate
+//! selectors at runtime with [`alloc_cap_sels`].
+//!
+//! Like the signal targets, the blocks live in a global table of plain data, because
+//! portal handlers can't look up other processes while the process manager is locked.
+
+use libhrstd::cap_space::allocator::CapSelAllocator;
+use libhrstd::cap_space::root::{
+    ProcessCapSels,
+    RootCapSpace,
+};
+use libhrstd::libhedron::consts::NUM_CAP_SEL;
+use libhrstd::libhedron::syscall::sys_revoke;
+use libhrstd::libhedron::{
+    CapSel,
+    CrdObj,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+
+/// The selectors of the dynamic part of the capability space of the roottask.
+static CAP_SEL_ALLOC: SimpleMutex<CapSelAllocator> = SimpleMutex::new(CapSelAllocator::new(
+    RootCapSpace::DynamicBase.val()..NUM_CAP_SEL,
+));
+
+/// Selectors of each process, indexed by PID. `None` if the process doesn't run.
+static PROCESS_CAP_SELS: SimpleMutex<[Option<ProcessCapSels>; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([None; NUM_PROCESSES as usize]);
+
+/// Allocates `count` consecutive selectors in the capability space of the roottask.
+/// Returns `None` if the capability space is exhausted.
+pub fn alloc_cap_sels(count: u64) -> Option<CapSel> {
+    let base = CAP_SEL_ALLOC.lock().alloc(count);
+    if base.is_none() {
+        log::warn!("capability space of the roottask is exhausted");
+    }
+    base
+}
+
+/// Frees selectors of [`alloc_cap_sels`]. No capability may be left at them.
+pub fn free_cap_sels(base: CapSel, count: u64) {
+    CAP_SEL_ALLOC.lock().free(base, count);
+}
+
+/// Allocates the selectors for the kernel objects of a new process. Returns `None` if the
+/// capability space is exhausted.
+pub fn assign_process_cap_sels(pid: ProcessId) -> Option<ProcessCapSels> {
+    let mut table = PROCESS_CAP_SELS.lock();
+    assert!(
+        table[pid as usize].is_none(),
+        "pid={} already has capability selectors",
+        pid
+    );
+    let sels = ProcessCapSels::new(alloc_cap_sels(ProcessCapSels::COUNT)?);
+    table[pid as usize] = Some(sels);
+    log::debug!(
+        "pid={} gets the capability selectors from {}",
+        pid,
+        sels.base()
+    );
+    Some(sels)
+}
+
+/// Returns the selectors of the kernel objects of a running process.
+/// Can be called from every EC of the roottask.
+pub fn process_cap_sels(pid: ProcessId) -> Option<ProcessCapSels> {
+    PROCESS_CAP_SELS.lock().get(pid as usize).copied().flatten()
+}
+
+/// Revokes all capabilities of the kernel objects of a stopped process, including the ones
+/// that were delegated to other processes, and frees the selectors for later processes.
+/// Does nothing if the process has no selectors.
+pub fn release_process_cap_sels(pid: ProcessId) {
+    let sels = match PROCESS_CAP_SELS.lock()[pid as usize].take() {
+        Some(sels) => sels,
+        None => return,
+    };
+    // the block is not aligned, hence split it into naturally aligned ranges
+    CrdDelegateOptimizer::new(sels.base(), sels.base(), ProcessCapSels::COUNT as usize).for_each(
+        |params| {
+            if let Err(e) = sys_revoke(CrdObj::new(params.src_base, params.order), true) {
+                log::error!(
+                    "can't revoke capabilities of pid={} at {}: {:?}",
+                    pid,
+                    params.src_base,
+                    e
+                );
+            }
+        },
+    );
+    free_cap_sels(sels.base(), ProcessCapSels::COUNT);
+    log::debug!("released the capability selectors of pid={}", pid);
+}
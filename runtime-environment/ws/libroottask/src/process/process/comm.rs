@@ -0,0 +1,76 @@
+//! Command names ("comm") of processes, like on Linux. A process starts with the file name
+//! of its program and can rename itself via `prctl(PR_SET_NAME)`, e.g. to name its main
+//! thread. The names show up in `/proc/<pid>/comm`, see [`crate::rt::procfs`].
+//!
+//! [`super::Process`] holds the name of each process. Like the signal targets, the names
+//! also live in a global table, because the file system can't look up processes while the
+//! process manager is locked.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Size of a command name including the null byte (`TASK_COMM_LEN` of Linux).
+pub const COMM_LEN: usize = 16;
+
+/// Command name of each running process by PID.
+static COMMS: SimpleMutex<BTreeMap<ProcessId, String>> = SimpleMutex::new(BTreeMap::new());
+
+/// Derives the initial command name from the name of the program, i.e. its file name,
+/// truncated like on Linux.
+pub fn comm_from_program_name(program_name: &str) -> String {
+    let file_name = program_name.rsplit('/').next().unwrap_or(program_name);
+    truncate_comm(file_name)
+}
+
+/// Truncates `name` to at most [`COMM_LEN`] - 1 bytes without splitting a character.
+pub fn truncate_comm(name: &str) -> String {
+    let mut len = name.len().min(COMM_LEN - 1);
+    while !name.is_char_boundary(len) {
+        len -= 1;
+    }
+    String::from(&name[..len])
+}
+
+/// Publishes the command name of a process. Called when the process starts and when it
+/// renames itself.
+pub fn register_comm(pid: ProcessId, comm: &str) {
+    COMMS.lock().insert(pid, String::from(comm));
+}
+
+/// Forgets the command name of a process, i.e. when the process exits.
+pub fn unregister_comm(pid: ProcessId) {
+    COMMS.lock().remove(&pid);
+}
+
+/// Returns the command name of a running process. Can be called from every EC of the
+/// roottask.
+pub fn process_comm(pid: ProcessId) -> Option<String> {
+    COMMS.lock().get(&pid).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comm_from_program_name() {
+        assert_eq!(comm_from_program_name("/bin/shell"), "shell");
+        assert_eq!(comm_from_program_name("shell"), "shell");
+        assert_eq!(
+            comm_from_program_name("/usr/bin/a_very_long_program_name"),
+            "a_very_long_pro"
+        );
+        // 14 ASCII bytes and a two-byte character
+        assert_eq!(truncate_comm("abcdefghijklmnä"), "abcdefghijklmn");
+    }
+
+    #[test]
+    fn test_comm_table() {
+        register_comm(42, "worker");
+        assert_eq!(process_comm(42).as_deref(), Some("worker"));
+        unregister_comm(42);
+        assert_eq!(process_comm(42), None);
+    }
+}
@@ -0,0 +1,546 @@
+//! ELF core dumps of crashed processes. When a fault or a signal with the default action
+//! [`SigDefaultAction::Core`] terminates a process, the roottask writes its registers and
+//! its memory to `/cores/<pid>.core` in the in-memory file system, see
+//! [`write_core_dump`]. The format is the one of Linux, hence gdb on the host understands
+//! it: `gdb <ELF> <pid>.core`. To get the file to the host, use `send /cores/<pid>.core` in
+//! the shell and `serialxfer-host`.
+//!
+//! The dump contains all memory that the roottask mapped for the process: the writable
+//! ELF segments, the heap, the mappings, the stack, and the arguments. ELF segments that are
+//! mapped directly from the file are left out, like Linux does by default, because gdb
+//! takes them from the ELF file. Dumps bigger than [`MAX_CORE_DUMP_SIZE`] are skipped.
+//!
+//! The boot argument `core_dumps=off` disables them, see [`crate::rt::boot_args`].
+
+use crate::process::{
+    Process,
+    SigDefaultAction,
+    SigNum,
+};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use libfileserver::FILESYSTEM;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::{
+    MemCapPermissions,
+    UtcbDataException,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsOpenFlags,
+};
+
+/// Directory of the core dumps in the file system.
+pub const CORE_DUMP_DIR: &str = "/cores";
+
+/// Core dumps that would be bigger are skipped. The file system keeps them in the heap of
+/// the roottask.
+pub const MAX_CORE_DUMP_SIZE: usize = 16 * 1024 * 1024;
+
+/// ELF type of core files.
+const ET_CORE: u16 = 4;
+
+/// ELF machine type of x86_64.
+const EM_X86_64: u16 = 62;
+
+/// ELF program header types.
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+
+/// ELF segment permissions.
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+/// Types of the notes of Linux core files.
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+/// Size of `struct elf_prstatus` of Linux on x86_64.
+const PRSTATUS_SIZE: usize = 336;
+
+/// Offset of the registers in `struct elf_prstatus`.
+const PRSTATUS_REGS_OFFSET: usize = 112;
+
+/// Size of `struct elf_prpsinfo` of Linux on x86_64.
+const PRPSINFO_SIZE: usize = 136;
+
+/// Name of the notes of Linux core files, with the terminator and the padding to 4 bytes.
+const NOTE_NAME: &[u8; 8] = b"CORE\0\0\0\0";
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enables or disables core dumps.
+pub fn set_core_dumps_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Returns the path of the core dump of the process.
+pub fn core_dump_path(pid: ProcessId) -> String {
+    format!("{}/{}.core", CORE_DUMP_DIR, pid)
+}
+
+/// Returns true if the signal creates a core dump when it terminates a process.
+pub fn signal_dumps_core(sig: SigNum) -> bool {
+    SigDefaultAction::of(sig) == SigDefaultAction::Core
+}
+
+/// Registers of the process in the order of `struct user_regs_struct` of Linux on x86_64.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CoreRegisters {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+impl CoreRegisters {
+    /// Takes the registers from the UTCB of an exception of the process.
+    pub fn from_utcb(utcb_exc: &UtcbDataException) -> Self {
+        Self {
+            r15: utcb_exc.r15,
+            r14: utcb_exc.r14,
+            r13: utcb_exc.r13,
+            r12: utcb_exc.r12,
+            rbp: utcb_exc.rbp,
+            rbx: utcb_exc.rbx,
+            r11: utcb_exc.r11,
+            r10: utcb_exc.r10,
+            r9: utcb_exc.r9,
+            r8: utcb_exc.r8,
+            rax: utcb_exc.rax,
+            rcx: utcb_exc.rcx,
+            rdx: utcb_exc.rdx,
+            rsi: utcb_exc.rsi,
+            rdi: utcb_exc.rdi,
+            // not a syscall
+            orig_rax: u64::MAX,
+            rip: utcb_exc.rip,
+            cs: utcb_exc.cs.sel as u64,
+            rflags: utcb_exc.rflags,
+            rsp: utcb_exc.rsp,
+            ss: utcb_exc.ss.sel as u64,
+            fs_base: utcb_exc.fs.base,
+            gs_base: utcb_exc.gs.base,
+            ds: utcb_exc.ds.sel as u64,
+            es: utcb_exc.es.sel as u64,
+            fs: utcb_exc.fs.sel as u64,
+            gs: utcb_exc.gs.sel as u64,
+        }
+    }
+
+    fn to_array(self) -> [u64; 27] {
+        [
+            self.r15,
+            self.r14,
+            self.r13,
+            self.r12,
+            self.rbp,
+            self.rbx,
+            self.r11,
+            self.r10,
+            self.r9,
+            self.r8,
+            self.rax,
+            self.rcx,
+            self.rdx,
+            self.rsi,
+            self.rdi,
+            self.orig_rax,
+            self.rip,
+            self.cs,
+            self.rflags,
+            self.rsp,
+            self.ss,
+            self.fs_base,
+            self.gs_base,
+            self.ds,
+            self.es,
+            self.fs,
+            self.gs,
+        ]
+    }
+}
+
+/// Describes the crashed process in the notes of the core file.
+#[derive(Debug, Clone)]
+pub struct CoreProcessInfo<'a> {
+    pub pid: ProcessId,
+    pub ppid: ProcessId,
+    /// The signal that terminated the process.
+    pub signal: SigNum,
+    /// Command name; gdb shows it as the program that crashed.
+    pub comm: &'a str,
+    pub argv: &'a [String],
+    pub regs: CoreRegisters,
+}
+
+/// A range of the memory of the process in the core file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CoreSegment {
+    /// Page-aligned address in the address space of the process.
+    pub u_address: u64,
+    /// Size in bytes; a multiple of [`PAGE_SIZE`].
+    pub len: usize,
+    pub perm: MemCapPermissions,
+}
+
+/// Encodes the ELF header, the program headers, and the notes of a core file. The content of
+/// the segments follows in the given order, beginning at the next page-aligned offset of
+/// the file, i.e. at the size of the result. Hence, the caller can write the memory
+/// directly after the header without copying it.
+pub fn encode_core_header(info: &CoreProcessInfo, segments: &[CoreSegment]) -> Vec<u8> {
+    let notes = encode_notes(info);
+    let phnum = 1 + segments.len();
+    let notes_offset = EHDR_SIZE + phnum * PHDR_SIZE;
+    let data_offset = align_up(notes_offset + notes.len(), PAGE_SIZE);
+
+    let mut bytes = Vec::with_capacity(data_offset);
+    // e_ident: 64 bit, little endian, version 1, System V ABI
+    bytes.extend_from_slice(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+    push_u16(&mut bytes, ET_CORE);
+    push_u16(&mut bytes, EM_X86_64);
+    push_u32(&mut bytes, 1);
+    // entry, program header offset, section header offset
+    push_u64(&mut bytes, 0);
+    push_u64(&mut bytes, EHDR_SIZE as u64);
+    push_u64(&mut bytes, 0);
+    // flags, header sizes, and numbers; there are no sections
+    push_u32(&mut bytes, 0);
+    push_u16(&mut bytes, EHDR_SIZE as u16);
+    push_u16(&mut bytes, PHDR_SIZE as u16);
+    push_u16(&mut bytes, phnum as u16);
+    push_u16(&mut bytes, 0);
+    push_u16(&mut bytes, 0);
+    push_u16(&mut bytes, 0);
+
+    push_phdr(
+        &mut bytes,
+        PT_NOTE,
+        0,
+        notes_offset as u64,
+        0,
+        notes.len() as u64,
+        1,
+    );
+    let mut offset = data_offset as u64;
+    for segment in segments {
+        push_phdr(
+            &mut bytes,
+            PT_LOAD,
+            segment_flags(segment.perm),
+            offset,
+            segment.u_address,
+            segment.len as u64,
+            PAGE_SIZE as u64,
+        );
+        offset += segment.len as u64;
+    }
+    bytes.extend_from_slice(&notes);
+    bytes.resize(data_offset, 0);
+    bytes
+}
+
+/// Writes the core dump of the process, which the exception in `utcb_exc` or the signal
+/// `sig` terminates, to [`core_dump_path`]. Must be called before the process exits, i.e.
+/// while its memory still exists. Does nothing if core dumps are disabled.
+pub fn write_core_dump(process: &Process, utcb_exc: &UtcbDataException, sig: SigNum) {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    let path = core_dump_path(process.pid());
+    match try_write_core_dump(process, utcb_exc, sig, &path) {
+        Ok(size) => log::info!(
+            "pid={} dumped core to {} ({} bytes)",
+            process.pid(),
+            path,
+            size
+        ),
+        Err(e) => log::warn!("pid={} can't dump core to {}: {:?}", process.pid(), path, e),
+    }
+}
+
+fn try_write_core_dump(
+    process: &Process,
+    utcb_exc: &UtcbDataException,
+    sig: SigNum,
+    path: &str,
+) -> Result<usize, FsError> {
+    let comm = process.comm();
+    let info = CoreProcessInfo {
+        pid: process.pid(),
+        ppid: process.parent().map_or(0, |parent| parent.pid()),
+        signal: sig,
+        comm: &comm,
+        argv: process.argv(),
+        regs: CoreRegisters::from_utcb(utcb_exc),
+    };
+    let memory_manager = process.memory_manager();
+    let mappings = memory_manager.mappings().collect::<Vec<_>>();
+    let segments = mappings
+        .iter()
+        .map(|mapping| CoreSegment {
+            u_address: mapping.address().val(),
+            len: mapping.len(),
+            perm: mapping.perm(),
+        })
+        .collect::<Vec<_>>();
+    let header = encode_core_header(&info, &segments);
+    let size = header.len() + segments.iter().map(|segment| segment.len).sum::<usize>();
+    if size > MAX_CORE_DUMP_SIZE {
+        return Err(FsError::NoSpace);
+    }
+
+    let mut fs = FILESYSTEM.lock();
+    let fd = fs.open_or_create_file(
+        ROOTTASK_PROCESS_PID,
+        path,
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY | FsOpenFlags::O_TRUNC,
+        0o600,
+    )?;
+    let res = core::iter::once(header.as_slice())
+        .chain(mappings.iter().map(|mapping| mapping.mem_as_ref()))
+        .try_for_each(|data| fs.write_file(ROOTTASK_PROCESS_PID, fd, data).map(|_| ()));
+    fs.close_file(ROOTTASK_PROCESS_PID, fd).unwrap();
+    if res.is_err() {
+        // a partial dump only confuses gdb
+        let _ = fs.unlink_file(ROOTTASK_PROCESS_PID, path);
+    }
+    res.map(|_| size)
+}
+
+/// Encodes the notes `NT_PRSTATUS` and `NT_PRPSINFO`.
+fn encode_notes(info: &CoreProcessInfo) -> Vec<u8> {
+    let mut prstatus = [0; PRSTATUS_SIZE];
+    // pr_info.si_signo and pr_cursig
+    prstatus[0..4].copy_from_slice(&(info.signal as i32).to_le_bytes());
+    prstatus[12..14].copy_from_slice(&(info.signal as i16).to_le_bytes());
+    // pr_pid, pr_ppid, pr_pgrp, pr_sid
+    prstatus[32..36].copy_from_slice(&(info.pid as i32).to_le_bytes());
+    prstatus[36..40].copy_from_slice(&(info.ppid as i32).to_le_bytes());
+    prstatus[40..44].copy_from_slice(&(info.pid as i32).to_le_bytes());
+    prstatus[44..48].copy_from_slice(&(info.pid as i32).to_le_bytes());
+    for (i, reg) in info.regs.to_array().iter().enumerate() {
+        let offset = PRSTATUS_REGS_OFFSET + i * 8;
+        prstatus[offset..offset + 8].copy_from_slice(&reg.to_le_bytes());
+    }
+
+    let mut prpsinfo = [0; PRPSINFO_SIZE];
+    // pr_state 'R', pr_sname
+    prpsinfo[1] = b'R';
+    // pr_pid, pr_ppid, pr_pgrp, pr_sid
+    prpsinfo[24..28].copy_from_slice(&(info.pid as i32).to_le_bytes());
+    prpsinfo[28..32].copy_from_slice(&(info.ppid as i32).to_le_bytes());
+    prpsinfo[32..36].copy_from_slice(&(info.pid as i32).to_le_bytes());
+    prpsinfo[36..40].copy_from_slice(&(info.pid as i32).to_le_bytes());
+    // pr_fname and pr_psargs; both null-terminated and truncated
+    copy_truncated(&mut prpsinfo[40..56], info.comm.as_bytes());
+    copy_truncated(&mut prpsinfo[56..136], info.argv.join(" ").as_bytes());
+
+    let mut notes = Vec::new();
+    push_note(&mut notes, NT_PRSTATUS, &prstatus);
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo);
+    notes
+}
+
+/// Copies as much of `src` to `dest` as fits with a terminating null byte.
+fn copy_truncated(dest: &mut [u8], src: &[u8]) {
+    let len = src.len().min(dest.len() - 1);
+    dest[..len].copy_from_slice(&src[..len]);
+}
+
+fn push_note(bytes: &mut Vec<u8>, ty: u32, desc: &[u8]) {
+    // the name size counts the terminator but not the padding
+    push_u32(bytes, 5);
+    push_u32(bytes, desc.len() as u32);
+    push_u32(bytes, ty);
+    bytes.extend_from_slice(NOTE_NAME);
+    bytes.extend_from_slice(desc);
+    bytes.resize(align_up(bytes.len(), 4), 0);
+}
+
+fn push_phdr(
+    bytes: &mut Vec<u8>,
+    ty: u32,
+    flags: u32,
+    offset: u64,
+    vaddr: u64,
+    size: u64,
+    align: u64,
+) {
+    push_u32(bytes, ty);
+    push_u32(bytes, flags);
+    push_u64(bytes, offset);
+    push_u64(bytes, vaddr);
+    // physical address
+    push_u64(bytes, 0);
+    // size in the file and in memory
+    push_u64(bytes, size);
+    push_u64(bytes, size);
+    push_u64(bytes, align);
+}
+
+fn segment_flags(perm: MemCapPermissions) -> u32 {
+    let mut flags = 0;
+    if perm.contains(MemCapPermissions::READ) {
+        flags |= PF_R;
+    }
+    if perm.contains(MemCapPermissions::WRITE) {
+        flags |= PF_W;
+    }
+    if perm.contains(MemCapPermissions::EXECUTE) {
+        flags |= PF_X;
+    }
+    flags
+}
+
+const fn align_up(val: usize, align: usize) -> usize {
+    (val + align - 1) & !(align - 1)
+}
+
+fn push_u16(bytes: &mut Vec<u8>, val: u16) {
+    bytes.extend_from_slice(&val.to_le_bytes());
+}
+
+fn push_u32(bytes: &mut Vec<u8>, val: u32) {
+    bytes.extend_from_slice(&val.to_le_bytes());
+}
+
+fn push_u64(bytes: &mut Vec<u8>, val: u64) {
+    bytes.extend_from_slice(&val.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{
+        SIGABRT,
+        SIGSEGV,
+        SIGTERM,
+    };
+    use alloc::vec;
+
+    fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_encode_core_header() {
+        let argv = vec![String::from("/bin/app"), String::from("-v")];
+        let info = CoreProcessInfo {
+            pid: 3,
+            ppid: 1,
+            signal: SIGSEGV,
+            comm: "app",
+            argv: &argv,
+            regs: CoreRegisters {
+                rip: 0x401000,
+                rsp: 0x7fff0000,
+                ..CoreRegisters::default()
+            },
+        };
+        let segments = [
+            CoreSegment {
+                u_address: 0x600000,
+                len: 2 * PAGE_SIZE,
+                perm: MemCapPermissions::READ | MemCapPermissions::WRITE,
+            },
+            CoreSegment {
+                u_address: 0x7ffe0000,
+                len: PAGE_SIZE,
+                perm: MemCapPermissions::READ | MemCapPermissions::WRITE,
+            },
+        ];
+        let bytes = encode_core_header(&info, &segments);
+
+        assert_eq!(&bytes[..5], b"\x7fELF\x02");
+        assert_eq!(bytes.len() % PAGE_SIZE, 0);
+        assert_eq!(read_u16(&bytes, 0x10), ET_CORE);
+        assert_eq!(read_u16(&bytes, 0x12), EM_X86_64);
+        assert_eq!(read_u16(&bytes, 0x38), 3);
+
+        // the notes
+        let note_phdr = EHDR_SIZE;
+        assert_eq!(read_u32(&bytes, note_phdr), PT_NOTE);
+        let notes_offset = read_u64(&bytes, note_phdr + 8) as usize;
+        assert_eq!(read_u32(&bytes, notes_offset + 8), NT_PRSTATUS);
+        assert_eq!(&bytes[notes_offset + 12..notes_offset + 17], b"CORE\0");
+        let prstatus = notes_offset + 20;
+        assert_eq!(read_u32(&bytes, prstatus), SIGSEGV as u32);
+        assert_eq!(read_u32(&bytes, prstatus + 32), 3);
+        // rip is the 17th register
+        assert_eq!(
+            read_u64(&bytes, prstatus + PRSTATUS_REGS_OFFSET + 16 * 8),
+            0x401000
+        );
+        let prpsinfo_note = prstatus + PRSTATUS_SIZE;
+        assert_eq!(read_u32(&bytes, prpsinfo_note + 8), NT_PRPSINFO);
+        let prpsinfo = prpsinfo_note + 20;
+        assert_eq!(&bytes[prpsinfo + 40..prpsinfo + 44], b"app\0");
+        assert_eq!(&bytes[prpsinfo + 56..prpsinfo + 68], b"/bin/app -v\0");
+
+        // the segments follow each other after the header
+        let load_phdr = EHDR_SIZE + PHDR_SIZE;
+        assert_eq!(read_u32(&bytes, load_phdr), PT_LOAD);
+        assert_eq!(read_u32(&bytes, load_phdr + 4), PF_R | PF_W);
+        assert_eq!(read_u64(&bytes, load_phdr + 8), bytes.len() as u64);
+        assert_eq!(read_u64(&bytes, load_phdr + 16), 0x600000);
+        assert_eq!(read_u64(&bytes, load_phdr + 32), 2 * PAGE_SIZE as u64);
+        let load_phdr = load_phdr + PHDR_SIZE;
+        assert_eq!(
+            read_u64(&bytes, load_phdr + 8),
+            (bytes.len() + 2 * PAGE_SIZE) as u64
+        );
+        assert_eq!(read_u64(&bytes, load_phdr + 16), 0x7ffe0000);
+    }
+
+    #[test]
+    fn test_signal_dumps_core() {
+        assert!(signal_dumps_core(SIGSEGV));
+        assert!(signal_dumps_core(SIGABRT));
+        assert!(!signal_dumps_core(SIGTERM));
+    }
+}
@@ -0,0 +1,235 @@
+//! Exit of processes. An exited process stops running and its parent can query the exit
+//! status. The roottask takes back all capabilities that it delegated to the process, see
+//! [`revoke_all_from`], revokes the kernel objects of the process, and reuses their
+//! selectors, see [`release_process_cap_sels`].
+//!
+//! Like on Linux, the process stays a zombie until its parent collected the exit status,
+//! see [`reap_process`]. Nobody collects the exit status of orphans and of the processes
+//! that the roottask started itself. Then, the roottask frees the memory of the process
+//! and reuses its PID, see [`crate::process::free_pid`]. Files that the process created
+//! belong to the next process with the PID afterwards.
+//!
+//! Like the signal targets, the exit statuses live in a global table of plain data,
+//! because portal handlers can't look up other processes while the process manager is
+//! locked. The portal handler that handles the exit runs on the SC of the exiting process.
+//! Hence, it only records the status and the main global EC of the roottask revokes the
+//! SC and the other kernel objects afterwards in [`stop_exited_processes`].
+
+use crate::cap_transfer::forget_received_caps;
+use crate::gdb_stub;
+use crate::irq;
+use crate::log_buffer;
+use crate::process::{
+    forget_syscall_trace,
+    free_pid,
+    has_syscall_trace,
+    orphan_children,
+    release_process_cap_sels,
+    signal_target,
+    unregister_comm,
+    unregister_process_cpu,
+    unregister_scheduling_params,
+    unregister_signal_target,
+    ProcessManager,
+    SigNum,
+    PROCESS_MNG,
+};
+use crate::rate_limit;
+use crate::rt::procfs;
+use crate::services::forget_all_mapped_areas;
+use crate::services::timer::wake_main_ec;
+use crate::services::{
+    foreign_syscall,
+    fs,
+    name,
+    network,
+    pci,
+    perf_counter,
+    semaphore,
+    shm,
+    stderr,
+    stdout,
+    timer,
+};
+use alloc::vec::Vec;
+use libhrstd::kobjects::revoke_all_from;
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Exit status of each process, indexed by PID. `None` while the process runs.
+static EXIT_STATUS: SimpleMutex<[Option<i32>; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([None; NUM_PROCESSES as usize]);
+
+/// Exited processes whose SC is not revoked yet.
+static PENDING_STOPS: SimpleMutex<Vec<ProcessId>> = SimpleMutex::new(Vec::new());
+
+/// What keeps the PID of each exited process in use, indexed by PID.
+static ZOMBIES: SimpleMutex<[Zombie; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([Zombie::NONE; NUM_PROCESSES as usize]);
+
+/// Reaped processes whose PID the main EC frees next.
+static PENDING_RELEASES: SimpleMutex<Vec<ProcessId>> = SimpleMutex::new(Vec::new());
+
+/// An exited process can be released once it was stopped and reaped.
+#[derive(Debug, Copy, Clone)]
+struct Zombie {
+    stopped: bool,
+    /// Nobody waits for the exit status anymore.
+    reaped: bool,
+}
+
+impl Zombie {
+    const NONE: Self = Self {
+        stopped: false,
+        reaped: false,
+    };
+}
+
+/// Records the exit status of the process and lets the main EC stop it. The process
+/// keeps running until then; further calls are ignored. Can be called from every EC of
+/// the roottask.
+pub fn exit_process(pid: ProcessId, status: i32) {
+    let mut table = EXIT_STATUS.lock();
+    if table[pid as usize].is_some() {
+        // i.e. libc calls `exit` after `exit_group` until the process stops
+        return;
+    }
+    table[pid as usize] = Some(status);
+    log::info!("pid={} exited with status {}", pid, status);
+    PENDING_STOPS.lock().push(pid);
+    wake_main_ec();
+}
+
+/// Handles the exit of a process that never ran on its own SC, i.e. the child of a
+/// `vfork()` that exits before `execve()`. There is nothing to stop and nobody can collect
+/// the exit status, hence the PID is free again right away. The files of the process must
+/// be released already. Can be called from every EC of the roottask.
+pub fn exit_unstarted_process(pid: ProcessId, status: i32) {
+    log::info!("pid={} exited with status {}", pid, status);
+    free_pid(pid);
+}
+
+/// Terminates the process because of the signal, e.g. after a fault that the process
+/// doesn't handle. Like shells, the exit status is 128 plus the number of the signal.
+/// Can be called from every EC of the roottask.
+pub fn kill_process(pid: ProcessId, sig: SigNum) {
+    log::info!("pid={} gets terminated by signal {}", pid, sig);
+    exit_process(pid, 128 + sig as i32);
+}
+
+/// Returns the exit status of the process or `None` if it didn't exit.
+/// Can be called from every EC of the roottask.
+pub fn exit_status(pid: ProcessId) -> Option<i32> {
+    EXIT_STATUS.lock().get(pid as usize).copied().flatten()
+}
+
+/// Tells that the parent collected the exit status of the process. Its PID gets reused
+/// once the process was stopped as well. Can be called from every EC of the roottask.
+pub fn reap_process(pid: ProcessId) {
+    let mut zombies = ZOMBIES.lock();
+    let zombie = &mut zombies[pid as usize];
+    if zombie.reaped || exit_status(pid).is_none() {
+        return;
+    }
+    zombie.reaped = true;
+    if zombie.stopped {
+        PENDING_RELEASES.lock().push(pid);
+        wake_main_ec();
+    }
+}
+
+/// Revokes the SCs and all other kernel objects of all exited processes, so that they never
+/// run again, and releases the processes that were reaped, see [`reap_process`]. Must be
+/// called by the main global EC of the roottask, which doesn't hold the lock of the process
+/// manager.
+pub fn stop_exited_processes() {
+    let pids = core::mem::take(&mut *PENDING_STOPS.lock());
+    for pid in pids {
+        // no portal handler runs while the lock is held, i.e. none on the SC of the process
+        let mut mng = PROCESS_MNG.lock();
+        // prevents that the scheduling service creates a new SC for the process
+        unregister_scheduling_params(pid);
+        unregister_process_cpu(pid);
+        unregister_comm(pid);
+        // the trace stays readable after the exit
+        if !has_syscall_trace(pid) {
+            procfs::unmount(pid);
+        }
+        stdout::discard_pending_msg(pid);
+        stderr::discard_pending_msg(pid);
+        foreign_syscall::release_process(pid);
+        libfileserver::FILESYSTEM.lock().release_process(pid);
+        network::release_process(pid);
+        log_buffer::forget_process(pid);
+        fs::unregister_fs_ring(pid);
+        fs::unregister_fs_buffers(pid);
+        name::unregister_services(pid);
+        perf_counter::unregister_process(pid);
+        rate_limit::release_process(pid);
+        semaphore::close_semaphores(pid);
+        shm::release_process(pid);
+        // detaches the MSIs of the functions first
+        pci::release_process(pid);
+        irq::release_process(pid);
+        gdb_stub::process_exited(pid, exit_status(pid).unwrap_or(0));
+        // periodic timers would otherwise use selectors of the next processes
+        timer::cancel_timers(pid);
+        // takes back the memory and the capabilities that the process received
+        if let Some(process) = mng.processes().get(&pid) {
+            revoke_all_from(&process.pd_obj());
+        }
+        // revokes the SC, PD, and all other kernel objects of the process
+        release_process_cap_sels(pid);
+        log::debug!("stopped pid={}", pid);
+
+        let parent = signal_target(pid).and_then(|target| target.parent);
+        let mut zombies = ZOMBIES.lock();
+        zombies[pid as usize].stopped = true;
+        // nobody waits for orphans and for the processes that the roottask started itself
+        if parent.map_or(true, |parent| parent == ROOTTASK_PROCESS_PID) {
+            zombies[pid as usize].reaped = true;
+        }
+        let mut releases = Vec::from([pid]);
+        for child in orphan_children(pid) {
+            zombies[child as usize].reaped = true;
+            releases.push(child);
+        }
+        drop(zombies);
+        releases
+            .into_iter()
+            .for_each(|pid| release_if_reaped(&mut mng, pid));
+    }
+
+    let pids = core::mem::take(&mut *PENDING_RELEASES.lock());
+    let mut mng = PROCESS_MNG.lock();
+    pids.into_iter()
+        .for_each(|pid| release_if_reaped(&mut mng, pid));
+}
+
+/// Forgets everything about the process that is left after it was stopped and frees its
+/// PID, if the process was stopped and reaped.
+fn release_if_reaped(mng: &mut ProcessManager, pid: ProcessId) {
+    {
+        let mut zombies = ZOMBIES.lock();
+        let zombie = &mut zombies[pid as usize];
+        if !zombie.stopped || !zombie.reaped {
+            return;
+        }
+        *zombie = Zombie::NONE;
+    }
+    if forget_syscall_trace(pid) {
+        procfs::unmount(pid);
+    }
+    forget_received_caps(pid);
+    forget_all_mapped_areas(pid);
+    unregister_signal_target(pid);
+    // frees the memory of the process
+    mng.remove_process(pid);
+    EXIT_STATUS.lock()[pid as usize] = None;
+    free_pid(pid);
+    log::debug!("released pid={}", pid);
+}
@@ -0,0 +1,61 @@
+//! Faults of user processes, i.e. exceptions that the roottask can't resolve by mapping
+//! memory, like page faults at unmapped addresses. Linux processes receive the signal of
+//! the exception (see [`exception_signal`]), Hedron-native processes enter their
+//! [`FaultHandler`]. If the process doesn't handle the fault, the roottask terminates it as
+//! if the signal killed it. Only faults of the roottask itself are fatal for the system.
+//! See [`crate::roottask_exception::handle_unresolved_fault`].
+
+use crate::process::{
+    SigNum,
+    SIGBUS,
+    SIGFPE,
+    SIGILL,
+    SIGSEGV,
+    SIGTRAP,
+};
+use libhrstd::libhedron::ExceptionEventOffset;
+
+/// Function of a Hedron-native process that handles its faults. See
+/// [`libhrstd::rt::services::process::ProcessServiceRequest::SetFaultHandler`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FaultHandler {
+    /// Address of the function.
+    pub entry: u64,
+    /// Top of the stack that the function runs on.
+    pub stack_top: u64,
+}
+
+/// Returns the signal that Linux sends to a process that caused the exception.
+pub fn exception_signal(exc: ExceptionEventOffset) -> SigNum {
+    match exc {
+        ExceptionEventOffset::DivideByZeroFault
+        | ExceptionEventOffset::X87FloatingPointFault
+        | ExceptionEventOffset::SimdFloatingPointFault => SIGFPE,
+        ExceptionEventOffset::DebugTrap | ExceptionEventOffset::BreakpointTrap => SIGTRAP,
+        ExceptionEventOffset::InvalidOpcodeFault => SIGILL,
+        ExceptionEventOffset::AlignmentCheckFault => SIGBUS,
+        _ => SIGSEGV,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exception_signal() {
+        assert_eq!(
+            exception_signal(ExceptionEventOffset::DivideByZeroFault),
+            SIGFPE
+        );
+        assert_eq!(
+            exception_signal(ExceptionEventOffset::InvalidOpcodeFault),
+            SIGILL
+        );
+        assert_eq!(exception_signal(ExceptionEventOffset::PageFault), SIGSEGV);
+        assert_eq!(
+            exception_signal(ExceptionEventOffset::GeneralProtectionFault),
+            SIGSEGV
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use elf_rs::{
+    Elf,
+    ElfFile,
+    ProgramType,
+};
+
+/// The program interpreter of a dynamically linked Linux program, i.e. the dynamic linker
+/// such as `/lib/ld-musl-x86_64.so.1`, as loaded into the address space of the process.
+/// The process starts in the interpreter, which maps the shared libraries, relocates the
+/// program, and jumps to the entry of the program, see [`AuxVar::Entry`].
+///
+/// [`AuxVar::Entry`]: linux_libc_auxv::AuxVar::Entry
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Interpreter {
+    /// Address where the interpreter got loaded, i.e. its load bias. Passed as `AT_BASE`.
+    pub base: u64,
+    /// Entry point of the interpreter in the address space.
+    pub entry: u64,
+}
+
+/// Returns the path of the program interpreter of the ELF file (`PT_INTERP`), if it has one.
+pub fn interpreter_path(elf_bytes: &[u8]) -> Option<&str> {
+    let elf = Elf::from_bytes(elf_bytes).ok()?;
+    let (offset, size) = elf
+        .program_header_iter()
+        .find(|hdr| hdr.ph_type() == ProgramType::INTERP)
+        .map(|hdr| (hdr.offset() as usize, hdr.filesz() as usize))?;
+    let content = elf_bytes.get(offset..offset.checked_add(size)?)?;
+    let path = content.split(|b| *b == 0).next().unwrap_or_default();
+    core::str::from_utf8(path)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    /// Creates an ELF64 file with a `PT_INTERP` segment, if `interp` is set.
+    fn create_elf(interp: Option<&[u8]>) -> Vec<u8> {
+        let interp = interp.unwrap_or_default();
+        let phnum = (!interp.is_empty()) as u16;
+        let mut elf = Vec::new();
+        elf.extend_from_slice(b"\x7fELF");
+        elf.extend_from_slice(&[2, 1, 1, 0]);
+        elf.resize(16, 0);
+        elf.extend_from_slice(&2_u16.to_ne_bytes()); // e_type: ET_EXEC
+        elf.extend_from_slice(&0x3e_u16.to_ne_bytes()); // e_machine: x86_64
+        elf.extend_from_slice(&1_u32.to_ne_bytes()); // e_version
+        elf.extend_from_slice(&0x400000_u64.to_ne_bytes()); // e_entry
+        elf.extend_from_slice(&64_u64.to_ne_bytes()); // e_phoff
+        elf.extend_from_slice(&0_u64.to_ne_bytes()); // e_shoff
+        elf.extend_from_slice(&0_u32.to_ne_bytes()); // e_flags
+        elf.extend_from_slice(&64_u16.to_ne_bytes());
+        elf.extend_from_slice(&56_u16.to_ne_bytes());
+        elf.extend_from_slice(&phnum.to_ne_bytes());
+        elf.extend_from_slice(&[0; 6]); // no section headers
+        if phnum > 0 {
+            elf.extend_from_slice(&3_u32.to_ne_bytes()); // p_type: PT_INTERP
+            elf.extend_from_slice(&4_u32.to_ne_bytes()); // p_flags
+            elf.extend_from_slice(&(64_u64 + 56).to_ne_bytes());
+            elf.extend_from_slice(&[0; 16]); // p_vaddr, p_paddr
+            elf.extend_from_slice(&(interp.len() as u64).to_ne_bytes());
+            elf.extend_from_slice(&(interp.len() as u64).to_ne_bytes());
+            elf.extend_from_slice(&1_u64.to_ne_bytes());
+            elf.extend_from_slice(interp);
+        }
+        elf
+    }
+
+    #[test]
+    fn test_interpreter_path() {
+        assert_eq!(
+            interpreter_path(&create_elf(Some(b"/lib/ld-musl-x86_64.so.1\0"))),
+            Some("/lib/ld-musl-x86_64.so.1")
+        );
+        assert_eq!(
+            interpreter_path(&create_elf(Some(b"/lib64/ld-linux-x86-64.so.2"))),
+            Some("/lib64/ld-linux-x86-64.so.2")
+        );
+        assert_eq!(interpreter_path(&create_elf(Some(b"\0"))), None);
+        assert_eq!(interpreter_path(&create_elf(None)), None);
+        assert_eq!(interpreter_path(b"no elf"), None);
+    }
+}
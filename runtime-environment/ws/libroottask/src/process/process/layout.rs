@@ -0,0 +1,452 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+use elf_rs::{
+    Elf,
+    ElfFile,
+    ElfType,
+    ProgramType,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::MemCapPermissions;
+use libhrstd::uaddress_space::{
+    USER_FIXED_AREA_BEGIN,
+    USER_STACK_BOTTOM_ADDR,
+    USER_STACK_INITIAL_SIZE,
+    USER_STACK_VERY_TOP,
+    USER_UTCB_ADDR,
+};
+
+/// The regions of the address space of a user process, see [`AddressSpaceLayout`]. Ordered
+/// from low to high addresses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RegionKind {
+    /// The load segments of the ELF file.
+    Elf,
+    /// The program break, i.e. the heap of `brk`. Begins right after the ELF segments,
+    /// unless ASLR moves it.
+    Heap,
+    /// The arena for `mmap`-like mappings and for memory that the roottask shares with
+    /// the process.
+    Mmap,
+    /// Fixed pages of the runtime between the arena and the stack, such as the arguments,
+    /// the ELF program headers, and the UTCB of a hosted service.
+    Runtime,
+    /// The reserved range of the stack, including its guard page.
+    Stack,
+    /// The UTCB of the main EC.
+    Utcb,
+}
+
+/// Errors of [`AddressSpaceLayout`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The ELF file is invalid or has no load segment, or a load segment exceeds the file
+    /// or has more bytes in the file than in memory.
+    InvalidElf,
+    /// The ELF segments overlap the null page or leave no room for the heap and the arena
+    /// below the fixed regions at the top of the address space.
+    ElfCollision,
+    /// A region has no space left for a mapping of the requested size.
+    OutOfSpace(RegionKind),
+}
+
+/// The layout of the virtual address space of a user process. Places every region at a
+/// deterministic address that only depends on the ELF file and guarantees that the regions
+/// never overlap:
+///
+/// ```text
+/// 0x1000 <= ELF < heap (MAX_HEAP_SIZE) < mmap arena < runtime < stack < UTCB
+/// ```
+///
+/// The first page stays unmapped, so that null pointers fault. The regions at the top of
+/// the address space are the same for all processes, see [`libhrstd::uaddress_space`].
+///
+/// Position-independent executables (`ET_DYN`) get loaded at [`Self::PIE_LOAD_BASE`]; all
+/// addresses of the ELF file then move by [`Self::load_bias`].
+///
+/// [`Self::randomize`] moves the heap, the mmap arena, and the stack top by random
+/// offsets (ASLR). The guarantees stay the same.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddressSpaceLayout {
+    /// All regions, sorted by address and disjoint. Gaps between the ELF, the heap, and the
+    /// arena exist only with ASLR.
+    regions: [(RegionKind, Range<u64>); 6],
+    /// Exclusive top of the stack where the initial stack frame begins.
+    stack_top: u64,
+    /// Offset between the addresses in the ELF file and in the address space.
+    load_bias: u64,
+}
+
+impl AddressSpaceLayout {
+    /// The program break can grow by at most 1 GiB.
+    pub const MAX_HEAP_SIZE: u64 = 0x40000000;
+
+    /// The mmap arena must provide at least this many bytes.
+    pub const MIN_MMAP_SIZE: u64 = 0x40000000;
+
+    /// ASLR moves the begin of the heap by up to 32 MiB, like Linux does.
+    pub const ASLR_HEAP_RANGE: u64 = 0x2000000;
+
+    /// ASLR moves the base of the mmap arena by up to 1 TiB.
+    pub const ASLR_MMAP_RANGE: u64 = 0x10000000000;
+
+    /// ASLR moves the stack top down by up to a quarter of the initially mapped stack.
+    pub const ASLR_STACK_RANGE: u64 = USER_STACK_INITIAL_SIZE as u64 / 4;
+
+    /// The stack top stays aligned to this, as the ABI requires for the initial stack frame.
+    const STACK_TOP_ALIGN: u64 = 64;
+
+    /// Where the lowest load segment of a position-independent executable begins, like on
+    /// Linux without ASLR.
+    pub const PIE_LOAD_BASE: u64 = 0x555555554000;
+
+    /// Calculates the layout for the load segments of an ELF file. Checks that the load
+    /// segments lie within the file.
+    pub fn from_elf(elf_bytes: &[u8]) -> Result<Self, LayoutError> {
+        let elf = Elf::from_bytes(elf_bytes).map_err(|_| LayoutError::InvalidElf)?;
+        let elf_range = elf_load_range(&elf, elf_bytes.len())?;
+        let load_bias = if elf.elf_header().elftype() == ElfType::ET_DYN {
+            Self::PIE_LOAD_BASE
+                .checked_sub(elf_range.start / PAGE_SIZE as u64 * PAGE_SIZE as u64)
+                .ok_or(LayoutError::ElfCollision)?
+        } else {
+            0
+        };
+        let mut layout = Self::from_elf_range(
+            elf_range.start + load_bias..elf_range.end.saturating_add(load_bias),
+        )?;
+        layout.load_bias = load_bias;
+        Ok(layout)
+    }
+
+    /// Calculates the layout for ELF segments that span `elf_range`.
+    pub fn from_elf_range(elf_range: Range<u64>) -> Result<Self, LayoutError> {
+        let page_size = PAGE_SIZE as u64;
+        let elf_start = elf_range.start / page_size * page_size;
+        let elf_end = elf_range
+            .end
+            .checked_add(page_size - 1)
+            .ok_or(LayoutError::ElfCollision)?
+            / page_size
+            * page_size;
+        let heap_end = elf_end + Self::MAX_HEAP_SIZE;
+        if elf_start < page_size || heap_end + Self::MIN_MMAP_SIZE > USER_FIXED_AREA_BEGIN {
+            return Err(LayoutError::ElfCollision);
+        }
+        Ok(Self {
+            regions: [
+                (RegionKind::Elf, elf_start..elf_end),
+                (RegionKind::Heap, elf_end..heap_end),
+                (RegionKind::Mmap, heap_end..USER_FIXED_AREA_BEGIN),
+                (
+                    RegionKind::Runtime,
+                    USER_FIXED_AREA_BEGIN..USER_STACK_BOTTOM_ADDR,
+                ),
+                (RegionKind::Stack, USER_STACK_BOTTOM_ADDR..USER_UTCB_ADDR),
+                (RegionKind::Utcb, USER_UTCB_ADDR..USER_UTCB_ADDR + page_size),
+            ],
+            stack_top: USER_STACK_VERY_TOP,
+            load_bias: 0,
+        })
+    }
+
+    /// Moves the begin of the heap, the base of the mmap arena, and the stack top by
+    /// offsets that derive from the three random numbers, see [`Self::ASLR_HEAP_RANGE`],
+    /// [`Self::ASLR_MMAP_RANGE`], and [`Self::ASLR_STACK_RANGE`]. The heap keeps its
+    /// maximum size and the arena keeps at least [`Self::MIN_MMAP_SIZE`] bytes. Must be
+    /// called at most once, before the first mapping.
+    pub fn randomize(&mut self, random: [u64; 3]) {
+        let page_size = PAGE_SIZE as u64;
+        let random_offset = |random: u64, range: u64, align: u64| random % (range / align) * align;
+
+        let elf_end = self.region(RegionKind::Elf).end;
+        let mmap_end = self.region(RegionKind::Mmap).end;
+        // the layout is valid without offsets; shrink the ranges if the ELF is that big
+        let space = mmap_end - (elf_end + Self::MAX_HEAP_SIZE + Self::MIN_MMAP_SIZE);
+        let heap_range = Self::ASLR_HEAP_RANGE.min(space).max(page_size);
+        let heap_start = elf_end + random_offset(random[0], heap_range, page_size);
+        let heap_end = heap_start + Self::MAX_HEAP_SIZE;
+        let space = mmap_end - (heap_end + Self::MIN_MMAP_SIZE);
+        let mmap_range = Self::ASLR_MMAP_RANGE.min(space).max(page_size);
+        let mmap_start = heap_end + random_offset(random[1], mmap_range, page_size);
+
+        self.regions[1].1 = heap_start..heap_end;
+        self.regions[2].1 = mmap_start..mmap_end;
+        self.stack_top = USER_STACK_VERY_TOP
+            - random_offset(random[2], Self::ASLR_STACK_RANGE, Self::STACK_TOP_ALIGN);
+    }
+
+    /// Returns the exclusive top of the stack where the initial stack frame begins. Below
+    /// [`USER_STACK_VERY_TOP`] with ASLR.
+    pub fn stack_top(&self) -> u64 {
+        self.stack_top
+    }
+
+    /// Returns the offset that the load segments and the entry point of the ELF file move
+    /// by in the address space. Non-zero for position-independent executables only.
+    pub fn load_bias(&self) -> u64 {
+        self.load_bias
+    }
+
+    /// Returns the address range of a region.
+    pub fn region(&self, kind: RegionKind) -> Range<u64> {
+        self.regions
+            .iter()
+            .find(|(region_kind, _)| *region_kind == kind)
+            .map(|(_, range)| range.clone())
+            .unwrap()
+    }
+
+    /// Returns the region that contains `addr`, if any.
+    pub fn region_of(&self, addr: u64) -> Option<RegionKind> {
+        self.regions
+            .iter()
+            .find(|(_, range)| range.contains(&addr))
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Checks that the mapping `start..start + size` lies completely within the region
+    /// `kind`. Fails with [`LayoutError::OutOfSpace`] otherwise.
+    pub fn check(&self, kind: RegionKind, start: u64, size: u64) -> Result<(), LayoutError> {
+        let region = self.region(kind);
+        let end = start
+            .checked_add(size)
+            .ok_or(LayoutError::OutOfSpace(kind))?;
+        if start >= region.start && end <= region.end {
+            Ok(())
+        } else {
+            Err(LayoutError::OutOfSpace(kind))
+        }
+    }
+
+    /// Returns all regions, sorted by address.
+    pub fn regions(&self) -> impl Iterator<Item = (RegionKind, Range<u64>)> + '_ {
+        self.regions.iter().cloned()
+    }
+}
+
+/// Returns the address range that the load segments of the ELF file span, without a load
+/// bias. Checks that the load segments lie within the file of `elf_len` bytes.
+pub fn elf_load_range(elf: &Elf, elf_len: usize) -> Result<Range<u64>, LayoutError> {
+    let mut elf_range: Option<Range<u64>> = None;
+    for hdr in elf
+        .program_header_iter()
+        .filter(|hdr| hdr.ph_type() == ProgramType::LOAD)
+    {
+        let file_end = hdr.offset().checked_add(hdr.filesz());
+        if file_end.map_or(true, |end| end > elf_len as u64) || hdr.filesz() > hdr.memsz() {
+            return Err(LayoutError::InvalidElf);
+        }
+        let range = hdr.vaddr()..hdr.vaddr().saturating_add(hdr.memsz());
+        elf_range = Some(match elf_range {
+            Some(elf_range) => elf_range.start.min(range.start)..elf_range.end.max(range.end),
+            None => range,
+        });
+    }
+    elf_range.ok_or(LayoutError::InvalidElf)
+}
+
+/// Returns the page ranges of the memory that backs the load segments, each with the
+/// union of the permissions of its segments. The segments are in the address space, i.e.
+/// moved by [`AddressSpaceLayout::load_bias`]. Segments that share a page, because they
+/// aren't page-aligned, share a range. Empty segments need no memory.
+pub fn elf_backing_pages(
+    mut segments: Vec<(Range<u64>, MemCapPermissions)>,
+) -> Vec<(Range<u64>, MemCapPermissions)> {
+    let page_size = PAGE_SIZE as u64;
+    segments.sort_by_key(|(range, _)| range.start);
+    let mut pages: Vec<(Range<u64>, MemCapPermissions)> = Vec::new();
+    for (range, perm) in segments.into_iter().filter(|(range, _)| !range.is_empty()) {
+        let range = range.start / page_size * page_size
+            ..(range.end + page_size - 1) / page_size * page_size;
+        match pages.last_mut() {
+            Some((last, last_perm)) if last.end > range.start => {
+                last.end = last.end.max(range.end);
+                *last_perm |= perm;
+            }
+            _ => pages.push((range, perm)),
+        }
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ET_EXEC: u16 = 2;
+    const ET_DYN: u16 = 3;
+
+    /// Creates an ELF64 file of 8 KiB with load segments `(vaddr, offset, filesz, memsz)`.
+    fn create_elf(e_type: u16, segments: &[(u64, u64, u64, u64)]) -> Vec<u8> {
+        const EHDR_SIZE: usize = 64;
+        const PHDR_SIZE: usize = 56;
+        let mut elf = Vec::new();
+        elf.extend_from_slice(b"\x7fELF");
+        elf.extend_from_slice(&[2, 1, 1, 0]);
+        elf.resize(16, 0);
+        elf.extend_from_slice(&e_type.to_ne_bytes());
+        elf.extend_from_slice(&0x3e_u16.to_ne_bytes()); // e_machine: x86_64
+        elf.extend_from_slice(&1_u32.to_ne_bytes()); // e_version
+        elf.extend_from_slice(&0x1000_u64.to_ne_bytes()); // e_entry
+        elf.extend_from_slice(&(EHDR_SIZE as u64).to_ne_bytes()); // e_phoff
+        elf.extend_from_slice(&0_u64.to_ne_bytes()); // e_shoff
+        elf.extend_from_slice(&0_u32.to_ne_bytes()); // e_flags
+        elf.extend_from_slice(&(EHDR_SIZE as u16).to_ne_bytes());
+        elf.extend_from_slice(&(PHDR_SIZE as u16).to_ne_bytes());
+        elf.extend_from_slice(&(segments.len() as u16).to_ne_bytes());
+        elf.extend_from_slice(&[0; 6]); // no section headers
+        for (vaddr, offset, filesz, memsz) in segments {
+            elf.extend_from_slice(&1_u32.to_ne_bytes()); // p_type: PT_LOAD
+            elf.extend_from_slice(&6_u32.to_ne_bytes()); // p_flags: RW
+            elf.extend_from_slice(&offset.to_ne_bytes());
+            elf.extend_from_slice(&vaddr.to_ne_bytes());
+            elf.extend_from_slice(&vaddr.to_ne_bytes());
+            elf.extend_from_slice(&filesz.to_ne_bytes());
+            elf.extend_from_slice(&memsz.to_ne_bytes());
+            elf.extend_from_slice(&0x1000_u64.to_ne_bytes());
+        }
+        elf.resize(0x2000, 0);
+        elf
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = AddressSpaceLayout::from_elf_range(0x400000..0x412345).unwrap();
+        assert_eq!(layout.region(RegionKind::Elf), 0x400000..0x413000);
+        assert_eq!(
+            layout.region(RegionKind::Heap),
+            0x413000..0x413000 + AddressSpaceLayout::MAX_HEAP_SIZE
+        );
+        assert_eq!(layout.region(RegionKind::Mmap).end, USER_FIXED_AREA_BEGIN);
+        // sorted, disjoint, and without gaps above the ELF
+        let regions = layout.regions().collect::<alloc::vec::Vec<_>>();
+        for pair in regions.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+            assert_eq!(pair[0].1.end, pair[1].1.start);
+        }
+
+        assert_eq!(layout.region_of(0x400000), Some(RegionKind::Elf));
+        assert_eq!(layout.region_of(USER_UTCB_ADDR), Some(RegionKind::Utcb));
+        assert_eq!(layout.region_of(0), None);
+
+        let heap = layout.region(RegionKind::Heap);
+        assert_eq!(layout.check(RegionKind::Heap, heap.start, 0x1000), Ok(()));
+        assert_eq!(
+            layout.check(RegionKind::Heap, heap.end - 0x1000, 0x2000),
+            Err(LayoutError::OutOfSpace(RegionKind::Heap))
+        );
+        assert_eq!(
+            layout.check(RegionKind::Mmap, u64::MAX, 1),
+            Err(LayoutError::OutOfSpace(RegionKind::Mmap))
+        );
+    }
+
+    #[test]
+    fn test_layout_randomize() {
+        let deterministic = AddressSpaceLayout::from_elf_range(0x400000..0x412345).unwrap();
+        let mut layout = deterministic.clone();
+        layout.randomize([0, 0, 0]);
+        assert_eq!(layout, deterministic, "zero offsets change nothing");
+
+        layout.randomize([u64::MAX; 3]);
+        let elf = layout.region(RegionKind::Elf);
+        let heap = layout.region(RegionKind::Heap);
+        let mmap = layout.region(RegionKind::Mmap);
+        assert_eq!(elf, deterministic.region(RegionKind::Elf));
+        assert!(
+            heap.start >= elf.end && heap.start < elf.end + AddressSpaceLayout::ASLR_HEAP_RANGE
+        );
+        assert_eq!(heap.start % PAGE_SIZE as u64, 0);
+        assert_eq!(heap.end - heap.start, AddressSpaceLayout::MAX_HEAP_SIZE);
+        assert!(mmap.start >= heap.end);
+        assert_eq!(mmap.start % PAGE_SIZE as u64, 0);
+        assert!(mmap.end - mmap.start >= AddressSpaceLayout::MIN_MMAP_SIZE);
+        assert_eq!(mmap.end, USER_FIXED_AREA_BEGIN);
+        assert!(layout.stack_top() < USER_STACK_VERY_TOP);
+        assert!(layout.stack_top() > USER_STACK_VERY_TOP - AddressSpaceLayout::ASLR_STACK_RANGE);
+        assert_eq!(layout.stack_top() % 64, 0);
+        assert_eq!(layout.region_of(elf.end), None, "gap between ELF and heap");
+
+        // the offsets shrink if the ELF leaves little room
+        let elf_end = USER_FIXED_AREA_BEGIN
+            - AddressSpaceLayout::MAX_HEAP_SIZE
+            - AddressSpaceLayout::MIN_MMAP_SIZE;
+        let mut layout = AddressSpaceLayout::from_elf_range(0x400000..elf_end).unwrap();
+        layout.randomize([u64::MAX; 3]);
+        assert_eq!(layout.region(RegionKind::Heap).start, elf_end);
+        assert_eq!(
+            layout.region(RegionKind::Mmap).end - layout.region(RegionKind::Mmap).start,
+            AddressSpaceLayout::MIN_MMAP_SIZE
+        );
+    }
+
+    #[test]
+    fn test_layout_from_elf() {
+        // code and data with BSS, not page-aligned in the file
+        let segments = [
+            (0x401000, 0x0, 0x1234, 0x1234),
+            (0x403234, 0x1234, 0x100, 0x5000),
+        ];
+        let layout = AddressSpaceLayout::from_elf(&create_elf(ET_EXEC, &segments)).unwrap();
+        assert_eq!(layout.load_bias(), 0);
+        assert_eq!(layout.region(RegionKind::Elf), 0x401000..0x409000);
+
+        let segments = [(0x0, 0x0, 0x1234, 0x1234), (0x2234, 0x1234, 0x100, 0x200)];
+        let layout = AddressSpaceLayout::from_elf(&create_elf(ET_DYN, &segments)).unwrap();
+        assert_eq!(layout.load_bias(), AddressSpaceLayout::PIE_LOAD_BASE);
+        assert_eq!(
+            layout.region(RegionKind::Elf),
+            AddressSpaceLayout::PIE_LOAD_BASE..AddressSpaceLayout::PIE_LOAD_BASE + 0x3000
+        );
+
+        // beyond the end of the file
+        let segments = [(0x401000, 0x1000, 0x1001, 0x1001)];
+        assert_eq!(
+            AddressSpaceLayout::from_elf(&create_elf(ET_EXEC, &segments)),
+            Err(LayoutError::InvalidElf)
+        );
+        // more bytes in the file than in memory
+        let segments = [(0x401000, 0x0, 0x100, 0x10)];
+        assert_eq!(
+            AddressSpaceLayout::from_elf(&create_elf(ET_EXEC, &segments)),
+            Err(LayoutError::InvalidElf)
+        );
+    }
+
+    #[test]
+    fn test_elf_backing_pages() {
+        let pages = elf_backing_pages(vec![
+            // data and BSS, sharing a page with the end of the code
+            (0x401234..0x403010, MemCapPermissions::RW),
+            (0x400000..0x401234, MemCapPermissions::RX),
+            (0x500000..0x500000, MemCapPermissions::READ),
+            (0x600010..0x600020, MemCapPermissions::READ),
+        ]);
+        assert_eq!(
+            pages,
+            [
+                (0x400000..0x404000, MemCapPermissions::RWX),
+                (0x600000..0x601000, MemCapPermissions::READ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_layout_collision() {
+        // null page
+        assert_eq!(
+            AddressSpaceLayout::from_elf_range(0..0x1000),
+            Err(LayoutError::ElfCollision)
+        );
+        // no room for heap and arena below the fixed regions
+        assert_eq!(
+            AddressSpaceLayout::from_elf_range(0x400000..USER_FIXED_AREA_BEGIN - 0x1000),
+            Err(LayoutError::ElfCollision)
+        );
+        assert_eq!(
+            AddressSpaceLayout::from_elf_range(0x400000..u64::MAX),
+            Err(LayoutError::ElfCollision)
+        );
+    }
+}
@@ -1,28 +1,56 @@
-use crate::process::Process;
-use alloc::alloc::{
-    Allocator,
-    Global,
-    Layout,
+use crate::mem::{
+    PhysAddr,
+    PHYS_FRAME_ALLOC,
+    VIRT_MEM_ALLOC,
 };
+use crate::process::{
+    elf_backing_pages,
+    elf_load_range,
+    interpreter_path,
+    resource_limit,
+    AddressSpaceLayout,
+    Interpreter,
+    LayoutError,
+    Process,
+    RegionKind,
+    RLIMIT_DATA,
+};
+use crate::rt::devfs;
+use crate::rt::userland::with_file;
+use crate::services;
+use alloc::alloc::Layout;
 use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::ptr::NonNull;
 use elf_rs::{
     Elf,
     ElfFile,
-    ProgramHeaderWrapper,
+    ElfType,
     ProgramType,
 };
 use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::kobjects::DelegatedKind;
 use libhrstd::libhedron::mem::PAGE_SIZE;
-use libhrstd::libhedron::MemCapPermissions;
+use libhrstd::libhedron::syscall::sys_revoke;
+use libhrstd::libhedron::{
+    CrdMem,
+    MemCapPermissions,
+};
 use libhrstd::mem::calc_page_count;
+use libhrstd::process::args_block::ArgsBlock;
+use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
 use libhrstd::uaddress_space::{
-    USER_STACK_BOTTOM_ADDR,
-    USER_STACK_BOTTOM_PAGE_NUM,
+    USER_ARGS_ADDR,
+    USER_ARGS_SIZE,
+    USER_STACK_GROW_MIN_SIZE,
+    USER_STACK_GROW_RSP_SLACK,
+    USER_STACK_INITIAL_SIZE,
     USER_STACK_SIZE,
+    USER_STACK_VERY_TOP,
 };
 use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use libhrstd::util::delegation::DelegationBuilder;
 
 /// Wrapper around `u64` that ensures that the inner value is a page address.
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
@@ -41,13 +69,16 @@ impl PageAddress {
 }
 
 /// Structure that knows about the memory usage/layout of a process. This helps to identify
-/// and manage the heap. Performs memory mappings.
+/// and manage the heap. Performs memory mappings. All mappings stay within their region of
+/// the [`AddressSpaceLayout`].
 ///
 ///
 /// TODO unify with the MappedMemory struct used in the roottask mapping mechanism
 #[derive(Debug)]
 pub struct ProcessMemoryManager {
     init: bool,
+    /// Where the ELF segments, the heap, the mappings, and the stack live.
+    layout: AddressSpaceLayout,
     /// Used for program break heap mechanism. Tells the beginning of the program break
     /// in the address space of the user.
     u_program_break_begin: PageAddress,
@@ -57,53 +88,66 @@ pub struct ProcessMemoryManager {
     u_program_break_current: PageAddress,
     /// Contains memory mappings for the ELF segments.
     elf_mappings: BTreeMap<PageAddress, MemoryMapping>,
-    /// Contains the memory mappings for the stack.
+    /// Contains the memory mapping for the top of the stack that is mapped when the
+    /// process starts.
     stack: Option<MemoryMapping>,
+    /// Contains the memory mappings that extend the stack downwards, see
+    /// [`Self::grow_stack`]. The last one is the lowest.
+    stack_growth: Vec<MemoryMapping>,
+    /// Contains the memory mapping for the arguments of native apps.
+    args: Option<MemoryMapping>,
     /// Contains all additional memory mappings  This includes heap mappings from mmap() calls for
     /// example from Linux programs.
     memory_mappings: BTreeMap<PageAddress, MemoryMapping>,
-    /// The next virtual memory address for a mmap mapping. Right now this grows until
-    /// infinity (TODO!).
+    /// Heap regions that get backed on demand by their begin and their size in bytes, see
+    /// [`Self::reserve_lazy_region`].
+    lazy_regions: BTreeMap<PageAddress, usize>,
+    /// The program interpreter of a dynamically linked program.
+    interpreter: Option<Interpreter>,
+    /// The next virtual memory address for a mmap mapping. Grows until the end of the mmap
+    /// arena; freed ranges aren't reused (TODO!).
     u_next_mmap_addr: u64,
 }
 
 impl ProcessMemoryManager {
     /// The maximum memory break.
-    pub const MEMORY_BREAK_MAX: usize = 0x40000000;
+    pub const MEMORY_BREAK_MAX: usize = AddressSpaceLayout::MAX_HEAP_SIZE as usize;
+
+    /// Lazy heap regions get backed in chunks of this size. A multiple of [`PAGE_SIZE`].
+    pub const LAZY_CHUNK_SIZE: usize = 16 * PAGE_SIZE;
 
     /// Constructor. Saves the area used for the stack and the program break inside the structure.
+    ///
+    /// The ELF file must have a valid layout, see [`crate::process::select_syscall_abi`].
     pub fn new(process: &Process) -> Self {
-        let u_program_break_begin = Self::get_program_break_begin(process.elf_file_bytes());
+        let mut layout = AddressSpaceLayout::from_elf(process.elf_file_bytes())
+            .expect("the ELF file must have a valid address space layout");
+        if process.aslr() {
+            let mut bytes = [0; 24];
+            devfs::fill_random(&mut bytes);
+            let random =
+                [0, 1, 2].map(|i| u64::from_le_bytes(bytes[i * 8..][..8].try_into().unwrap()));
+            layout.randomize(random);
+            log::debug!("randomized layout of pid={}: {:x?}", process.pid(), layout);
+        }
+        let u_program_break_begin = PageAddress::new(layout.region(RegionKind::Heap).start);
 
         Self {
             init: false,
             u_program_break_begin,
             u_program_break_current: u_program_break_begin,
-            u_next_mmap_addr: u_program_break_begin.val() + Self::MEMORY_BREAK_MAX as u64,
+            u_next_mmap_addr: layout.region(RegionKind::Mmap).start,
+            layout,
             elf_mappings: Default::default(),
             stack: None,
+            stack_growth: Vec::new(),
+            args: None,
             memory_mappings: BTreeMap::new(),
+            lazy_regions: BTreeMap::new(),
+            interpreter: None,
         }
     }
 
-    /// Determines the page-aligned begin of the program break used for the heap.
-    fn get_program_break_begin(elf_bytes: &[u8]) -> PageAddress {
-        let elf = elf_rs::Elf::from_bytes(elf_bytes).unwrap();
-
-        // the maximum virtual address used by a program
-        let elf_max_addr = elf
-            .program_header_iter()
-            .map(|hdr| hdr.vaddr() + hdr.memsz())
-            .max()
-            .unwrap();
-
-        let page_offset = elf_max_addr & 0xfff;
-
-        let program_break_begin = elf_max_addr + PAGE_SIZE as u64 - page_offset;
-
-        PageAddress(program_break_begin)
-    }
-
     /// Initializes the stack, the elf segments, and the heap for an application. Performs
     /// memory mappings/page table manipulations.
     pub fn init(&mut self, process: &Process) -> Result<(), ()> {
@@ -112,178 +156,194 @@ impl ProcessMemoryManager {
 
         self.init_stack(process).unwrap();
         self.init_elf_load_segments(process).unwrap();
+        if process.syscall_abi().is_foreign() {
+            self.init_interpreter(process);
+        }
+        // Linux apps get their arguments on the stack during the startup exception
+        if !process.syscall_abi().is_foreign() {
+            self.init_args(process).unwrap();
+        }
 
         Ok(())
     }
 
-    /// Initializes the stack and maps it to the user address space.
+    /// Initializes the top of the stack and maps it to the user address space. The rest of
+    /// the reserved stack range gets mapped on demand, see [`Self::grow_stack`].
     fn init_stack(&mut self, process: &Process) -> Result<(), ()> {
         assert_eq!(
-            USER_STACK_SIZE % PAGE_SIZE,
+            USER_STACK_INITIAL_SIZE % PAGE_SIZE,
             0,
             "STACK-Size must be a multiple of PAGE_SIZE."
         );
-        let r_layout = Layout::from_size_align(USER_STACK_SIZE, PAGE_SIZE).unwrap();
-        let r_stack: NonNull<[u8]> = Global.allocate_zeroed(r_layout).unwrap();
-        let r_stack = r_stack.as_ptr().as_mut_ptr() as u64;
-        let stack_page_count = USER_STACK_SIZE / PAGE_SIZE;
-
-        let r_stack_bottom_page_num = r_stack / PAGE_SIZE as u64;
-
-        CrdDelegateOptimizer::new(
-            r_stack_bottom_page_num,
-            USER_STACK_BOTTOM_PAGE_NUM,
-            stack_page_count,
-        )
-        .mmap(
-            process.parent().unwrap().pd_obj().cap_sel(),
-            process.pd_obj().cap_sel(),
-            MemCapPermissions::READ | MemCapPermissions::WRITE,
-        );
-
+        // the lowest page of the reserved range is the guard page and never gets mapped
+        assert!(USER_STACK_INITIAL_SIZE < USER_STACK_SIZE);
+        let stack_page_count = USER_STACK_INITIAL_SIZE / PAGE_SIZE;
+        let u_stack_bottom = USER_STACK_VERY_TOP - USER_STACK_INITIAL_SIZE as u64;
         let stack = MemoryMapping::new(
-            PageAddress::new(r_stack),
-            r_layout,
-            PageAddress::new(USER_STACK_BOTTOM_ADDR),
+            PageAddress::new(u_stack_bottom),
             stack_page_count,
             MemoryKind::Stack,
             MemCapPermissions::RW,
         );
 
-        self.stack.replace(stack);
+        stack
+            .delegation()
+            .perms(MemCapPermissions::RW)
+            .at(u_stack_bottom)
+            .to_pd(&process.pd_obj())
+            .unwrap();
 
-        // TODO last stack page without read or write permissions! => detect page fault
+        self.stack.replace(stack);
 
         Ok(())
     }
 
-    /// Maps the load elf segments to the user address space. If necessary,
-    /// allocates additional memory from the heap for BSS (filesize != memsize in elf)
-    fn init_elf_load_segments(&mut self, process: &Process) -> Result<(), ()> {
-        let elf = Elf::from_bytes(process.elf_file_bytes()).unwrap();
-
-        // log::debug!("ELF: {:#?}", elf64.header());
-        log::debug!("mapping mem for all load segments to new PD");
-        for segment in elf
-            .program_header_iter()
-            .filter(|pr_hrd| pr_hrd.ph_type() == ProgramType::LOAD)
-        {
-            log::trace!("next segment");
-            assert_eq!(
-                segment.align() as usize,
-                PAGE_SIZE,
-                "expects that all segments are page aligned inside the file!!"
+    /// Writes the arguments and environment variables of a native app into a new
+    /// [`ArgsBlock`] and maps it read-only to [`USER_ARGS_ADDR`].
+    fn init_args(&mut self, process: &Process) -> Result<(), ()> {
+        let page_count = USER_ARGS_SIZE / PAGE_SIZE;
+        let mut args = MemoryMapping::new(
+            PageAddress::new(USER_ARGS_ADDR),
+            page_count,
+            MemoryKind::Args,
+            MemCapPermissions::READ,
+        );
+        if ArgsBlock::write(args.mem_as_mut(), process.argv(), process.envp()).is_err() {
+            log::error!(
+                "arguments of pid={} exceed {} bytes; the app gets none",
+                process.pid(),
+                USER_ARGS_SIZE
             );
-            if segment.memsz() == segment.filesz() {
-                self.init_elf_load_segments__direct(&segment, process)?;
-            } else {
-                self.init_elf_load_segments__indirect(&segment, process)?;
-            }
+            let empty: [&str; 0] = [];
+            ArgsBlock::write(args.mem_as_mut(), &empty, &empty).unwrap();
         }
 
+        args.delegation()
+            .perms(MemCapPermissions::READ)
+            .at(USER_ARGS_ADDR)
+            .to_pd(&process.pd_obj())
+            .unwrap();
+
+        self.args.replace(args);
         Ok(())
     }
 
-    /// Maps a single load segment directly into the user address space.
-    #[allow(non_snake_case)]
-    fn init_elf_load_segments__direct(
-        &mut self,
-        segment: &ProgramHeaderWrapper,
-        process: &Process,
-    ) -> Result<(), ()> {
-        assert_eq!(segment.offset() % PAGE_SIZE as u64, 0);
-        // mem in roottask: pointer/page into address space of the roottask
-        let load_segment_src_page_num = segment.content().as_ptr() as usize / PAGE_SIZE;
-        // virt mem in dest PD / address space
-        let load_segment_dest_page_num = segment.vaddr() as usize / PAGE_SIZE;
-
-        // number of pages to map
-        let num_pages = calc_page_count(segment.filesz() as usize);
-
-        CrdDelegateOptimizer::new(
-            load_segment_src_page_num as u64,
-            load_segment_dest_page_num as u64,
-            num_pages,
-        )
-        .mmap(
-            RootCapSpace::RootPd.val(),
-            process.pd_obj().cap_sel(),
-            // works because Hedron and ELF use the same bits for RWX
-            MemCapPermissions::from_elf_segment_permissions(segment.flags().bits() as u8),
-        );
-
+    /// Copies the load segments of the ELF file into fresh memory and maps it to the user
+    /// address space, moved by the [`AddressSpaceLayout::load_bias`].
+    fn init_elf_load_segments(&mut self, process: &Process) -> Result<(), ()> {
+        let elf = Elf::from_bytes(process.elf_file_bytes()).map_err(|_| ())?;
+        log::debug!("mapping mem for all load segments to new PD");
+        self.map_elf_segments(&elf, self.layout.load_bias(), process);
         Ok(())
     }
 
-    /// Maps a single load segment indirectly into the user address space.
-    /// This means, it allocates additional memory on the roottask heap
-    /// and this is what gets mapped to the user.
-    #[allow(non_snake_case)]
-    fn init_elf_load_segments__indirect(
+    /// Loads the program interpreter of a dynamically linked program into the mmap arena,
+    /// see [`Interpreter`]. The roottask opens the interpreter, e.g. below
+    /// [`crate::rt::userland::LIB_MOUNT_POINT`]; it must be position-independent. If it
+    /// can't be loaded, the process starts at its own entry point and crashes soon.
+    fn init_interpreter(&mut self, process: &Process) {
+        let path = match interpreter_path(process.elf_file_bytes()) {
+            Some(path) => path,
+            None => return,
+        };
+        let res = with_file(ROOTTASK_PROCESS_PID, path, |data| {
+            self.load_interpreter(data, process)
+        });
+        match res {
+            Some(Ok(interpreter)) => {
+                log::debug!(
+                    "loaded program interpreter '{}' of pid={} at {:#x}",
+                    path,
+                    process.pid(),
+                    interpreter.base
+                );
+                self.interpreter.replace(interpreter);
+            }
+            Some(Err(e)) => log::error!(
+                "can't load program interpreter '{}' of pid={}: {:?}",
+                path,
+                process.pid(),
+                e
+            ),
+            None => log::error!(
+                "program interpreter '{}' of pid={} not found",
+                path,
+                process.pid()
+            ),
+        }
+    }
+
+    fn load_interpreter(
         &mut self,
-        segment: &ProgramHeaderWrapper,
+        elf_bytes: &[u8],
         process: &Process,
-    ) -> Result<(), ()> {
-        // memsize != file size
-        // I can't map the ELF load segment directly
-
-        // offset of load segment in first page (segment might not start at page aligned address)
-        let first_page_offset = segment.offset() & 0xfff;
-        // the total number we need in bytes (we always need to start at a page)
-        let total_size = first_page_offset + segment.memsz();
-        // how many pages we need
-        let page_count = calc_page_count(total_size as usize);
-
-        // TODO this will never be freed.. Q&D
-        // roottask pointer that holds the elf segment (page aligned)
-        let r_elf_segment_layout =
-            Layout::from_size_align(page_count as usize * PAGE_SIZE, PAGE_SIZE).unwrap();
-        let r_elf_segment_ptr: NonNull<[u8]> =
-            Global.allocate_zeroed(r_elf_segment_layout).unwrap();
-
-        let u_mem_permissions =
-            MemCapPermissions::from_elf_segment_permissions(segment.flags().bits() as u8);
-
-        let memory_mapping = MemoryMapping::new(
-            PageAddress::new(r_elf_segment_ptr.as_mut_ptr() as u64),
-            r_elf_segment_layout,
-            PageAddress::new(segment.vaddr() & !0xfff),
-            page_count,
-            MemoryKind::Elf,
-            u_mem_permissions,
-        );
-        self.elf_mappings
-            .insert(memory_mapping.u_address, memory_mapping);
-
-        // copy everything from the ELF file to the new memory
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                segment.content().as_ptr(),
-                r_elf_segment_ptr
-                    .as_ptr()
-                    .cast::<u8>()
-                    .add(first_page_offset as usize),
-                segment.filesz() as usize,
-            );
+    ) -> Result<Interpreter, LayoutError> {
+        let elf = Elf::from_bytes(elf_bytes).map_err(|_| LayoutError::InvalidElf)?;
+        if elf.elf_header().elftype() != ElfType::ET_DYN {
+            return Err(LayoutError::InvalidElf);
         }
+        let elf_range = elf_load_range(&elf, elf_bytes.len())?;
+        let elf_start = elf_range.start & !(PAGE_SIZE as u64 - 1);
+        let size = calc_page_count((elf_range.end - elf_start) as usize) * PAGE_SIZE;
+        let u_base = self.alloc_mmap_area(Layout::from_size_align(size, PAGE_SIZE).unwrap())?;
+        let load_bias = u_base - elf_start;
+        self.map_elf_segments(&elf, load_bias, process);
+        Ok(Interpreter {
+            base: load_bias,
+            entry: elf.entry_point() + load_bias,
+        })
+    }
 
-        // mem in roottask: pointer/page into address space of the roottask
-        let load_segment_src_page_num = r_elf_segment_ptr.as_mut_ptr() as usize / PAGE_SIZE;
-        // virt mem in dest PD / address space
-        let load_segment_dest_page_num = segment.vaddr() as usize / PAGE_SIZE;
-
-        CrdDelegateOptimizer::new(
-            load_segment_src_page_num as u64,
-            load_segment_dest_page_num as u64,
-            page_count as usize,
-        )
-        .mmap(
-            RootCapSpace::RootPd.val(),
-            process.pd_obj().cap_sel(),
-            u_mem_permissions,
+    /// Copies the load segments of a valid ELF file into fresh memory and maps it to the
+    /// user address space, moved by `load_bias`. The segments don't need to be page-aligned
+    /// in the file. The memory beyond the file size of a segment, i.e. the BSS, is zero.
+    /// The permissions follow the flags of the segments; a page that two segments share
+    /// gets the permissions of both, see [`elf_backing_pages`].
+    fn map_elf_segments(&mut self, elf: &Elf, load_bias: u64, process: &Process) {
+        let segments = elf
+            .program_header_iter()
+            .filter(|hdr| hdr.ph_type() == ProgramType::LOAD)
+            .map(|hdr| {
+                let u_addr = hdr.vaddr() + load_bias;
+                // works because Hedron and ELF use the same bits for RWX
+                let perm =
+                    MemCapPermissions::from_elf_segment_permissions(hdr.flags().bits() as u8);
+                (u_addr..u_addr + hdr.memsz(), perm, hdr.content())
+            })
+            .collect::<Vec<_>>();
+
+        let backing_pages = elf_backing_pages(
+            segments
+                .iter()
+                .map(|(range, perm, _)| (range.clone(), *perm))
+                .collect(),
         );
+        for (pages, perm) in backing_pages {
+            let page_count = ((pages.end - pages.start) / PAGE_SIZE as u64) as usize;
+            let mut memory_mapping = MemoryMapping::new(
+                PageAddress::new(pages.start),
+                page_count,
+                MemoryKind::Elf,
+                perm,
+            );
+            // the memory is zeroed, hence only the content from the file is left to copy
+            for (range, _, content) in segments
+                .iter()
+                .filter(|(range, ..)| !range.is_empty() && pages.contains(&range.start))
+            {
+                let offset = (range.start - pages.start) as usize;
+                memory_mapping.mem_as_mut()[offset..][..content.len()].copy_from_slice(content);
+            }
 
-        Ok(())
+            memory_mapping
+                .delegation()
+                .perms(perm)
+                .at(pages.start)
+                .to_pd(&process.pd_obj())
+                .unwrap();
+            self.elf_mappings
+                .insert(memory_mapping.u_address, memory_mapping);
+        }
     }
 
     /// Increases the program break by providing either null or an address. This is similar to
@@ -296,7 +356,9 @@ impl ProcessMemoryManager {
     /// Address must be a page address.
     ///
     /// Returns the new current break on success. Returns the begin of the break if
-    /// the provided address is zero.
+    /// the provided address is zero. Returns the unchanged break if the address is beyond
+    /// the heap region of the [`AddressSpaceLayout`] or if the growth exceeds the
+    /// [`RLIMIT_DATA`] of the process, like Linux does on failure.
     pub fn increase_break(&mut self, address: u64, process: &Process) -> u64 {
         if address == 0 {
             return self.u_program_break_current.val();
@@ -308,6 +370,19 @@ impl ProcessMemoryManager {
         );
         let address = PageAddress::new(address);
         let growth = address.val() - self.u_program_break_current.val();
+        if let Err(e) = self
+            .layout
+            .check(RegionKind::Heap, self.u_program_break_current.val(), growth)
+            .and_then(|_| self.check_data_limit(growth, RegionKind::Heap, process))
+        {
+            log::warn!(
+                "can't increase break of pid={} to 0x{:x}: {:?}",
+                process.pid(),
+                address.val(),
+                e
+            );
+            return self.u_program_break_current.val();
+        }
         log::trace!(
             "increase_break: old_brk=0x{old_brk:x}, address=0x{address:x}, growth={growth:x}",
             old_brk = self.u_program_break_current.val(),
@@ -315,32 +390,22 @@ impl ProcessMemoryManager {
         );
         let page_count = calc_page_count(growth as usize);
 
-        let layout = Layout::from_size_align(page_count * PAGE_SIZE, PAGE_SIZE).unwrap();
-        let r_mapping_ptr: NonNull<[u8]> = Global.allocate_zeroed(layout).unwrap();
-        let r_mapping_addr = r_mapping_ptr.as_mut_ptr() as u64;
         let perm = MemCapPermissions::RW;
 
         let mapping = MemoryMapping::new(
-            PageAddress::new(r_mapping_addr),
-            layout,
             self.u_program_break_current,
             page_count,
             MemoryKind::Heap,
             perm,
         );
+        mapping
+            .delegation()
+            .perms(perm)
+            .at(self.u_program_break_current.val() as u64)
+            .to_pd(&process.pd_obj())
+            .unwrap();
         self.memory_mappings.insert(mapping.u_address, mapping);
 
-        CrdDelegateOptimizer::new(
-            r_mapping_addr / PAGE_SIZE as u64,
-            self.u_program_break_current.val() as u64 / PAGE_SIZE as u64,
-            page_count,
-        )
-        .mmap(
-            process.parent().unwrap().pd_obj().cap_sel(),
-            process.pd_obj().cap_sel(),
-            perm,
-        );
-
         let _old_break = self.u_program_break_current;
         self.u_program_break_current =
             PageAddress::new(self.u_program_break_current.val() + growth);
@@ -366,73 +431,389 @@ impl ProcessMemoryManager {
         self.increase_break(new_brk_addr, process)
     }
 
-    /// Maps a memory area to the user (for heap usage). The heap is
-    pub fn mmap(&mut self, layout: Layout, process: &Process) -> u64 {
+    /// Maps a memory area to the user (for heap usage). The mapping lives in the mmap arena of
+    /// the [`AddressSpaceLayout`] and fails if the arena has no space left or if the
+    /// mapping exceeds the [`RLIMIT_DATA`] of the process.
+    pub fn mmap(&mut self, layout: Layout, process: &Process) -> Result<u64, LayoutError> {
+        self.mmap_with_perm(layout, MemCapPermissions::RW, process)
+    }
+
+    /// Like [`Self::mmap`] but with the permissions `perm` for the process.
+    fn mmap_with_perm(
+        &mut self,
+        layout: Layout,
+        perm: MemCapPermissions,
+        process: &Process,
+    ) -> Result<u64, LayoutError> {
         let layout = layout.align_to(PAGE_SIZE).unwrap();
 
         // upround to next multiple of page size
         let size = calc_page_count(layout.size()) * PAGE_SIZE;
         let layout = Layout::from_size_align(size, layout.align()).unwrap();
 
-        let r_ptr: NonNull<[u8]> = Global.allocate_zeroed(layout).unwrap();
-        let r_ptr = r_ptr.as_non_null_ptr().as_ptr();
-        let r_addr = r_ptr as u64;
-        let r_addr_page_num = r_addr / PAGE_SIZE as u64;
-
         let page_count = calc_page_count(layout.size());
-
-        let perm = MemCapPermissions::RW;
-        let mapping = MemoryMapping::new(
-            PageAddress::new(r_addr),
-            layout,
-            PageAddress::new(self.u_next_mmap_addr),
-            page_count,
-            MemoryKind::Heap,
-            perm,
-        );
+        self.check_data_limit(size as u64, RegionKind::Mmap, process)?;
+        let u_addr = self.alloc_mmap_area(layout)?;
+
+        let mapping =
+            MemoryMapping::new(PageAddress::new(u_addr), page_count, MemoryKind::Heap, perm);
+        mapping
+            .delegation()
+            .perms(perm)
+            .at(u_addr)
+            .to_pd(&process.pd_obj())
+            .unwrap();
         self.memory_mappings.insert(mapping.u_address, mapping);
 
-        CrdDelegateOptimizer::new(
-            r_addr_page_num,
-            self.u_next_mmap_addr / PAGE_SIZE as u64,
-            page_count,
-        )
-        .mmap(
-            process.parent().unwrap().pd_obj().cap_sel(),
-            process.pd_obj().cap_sel(),
-            perm,
-        );
+        Ok(u_addr)
+    }
+
+    /// Maps `len` bytes of fresh, zeroed memory with the permissions `perm` for the process,
+    /// like an anonymous `mmap()`. Returns the address of the memory. Without `u_addr`, the
+    /// memory comes from the mmap arena, see [`Self::mmap`]. Otherwise, the memory begins
+    /// at the page address `u_addr`, like with `MAP_FIXED`: pages of the ELF segments and
+    /// of earlier calls that are already mapped there get zeroed and get the new
+    /// permissions. The other pages must lie above all memory that the mmap arena gave
+    /// out so far.
+    pub fn mmap_at(
+        &mut self,
+        u_addr: Option<u64>,
+        len: usize,
+        perm: MemCapPermissions,
+        process: &Process,
+    ) -> Result<u64, LayoutError> {
+        let u_addr = match u_addr {
+            Some(u_addr) => u_addr,
+            None => {
+                return self.mmap_with_perm(
+                    Layout::from_size_align(len, PAGE_SIZE).unwrap(),
+                    perm,
+                    process,
+                )
+            }
+        };
+        let page_size = PAGE_SIZE as u64;
+        let size = calc_page_count(len) as u64 * page_size;
+        let u_end = u_addr
+            .checked_add(size)
+            .ok_or(LayoutError::OutOfSpace(RegionKind::Mmap))?;
+
+        // the pages that nothing maps yet, i.e. the pages after the last mapped page
+        let mut u_new = u_addr;
+        while u_new < u_end && self.owned_mapping_mut(u_new).is_some() {
+            u_new += page_size;
+        }
+        if u_new < u_end {
+            if u_new < self.u_next_mmap_addr
+                || (u_new..u_end)
+                    .step_by(PAGE_SIZE)
+                    .any(|u_page| self.owned_mapping_mut(u_page).is_some())
+            {
+                return Err(LayoutError::OutOfSpace(RegionKind::Mmap));
+            }
+            self.layout.check(RegionKind::Mmap, u_new, u_end - u_new)?;
+            self.check_data_limit(u_end - u_new, RegionKind::Mmap, process)?;
+            self.u_next_mmap_addr = u_end;
+            let page_count = ((u_end - u_new) / page_size) as usize;
+            let mapping =
+                MemoryMapping::new(PageAddress::new(u_new), page_count, MemoryKind::Heap, perm);
+            mapping
+                .delegation()
+                .perms(perm)
+                .at(u_new)
+                .to_pd(&process.pd_obj())
+                .unwrap();
+            self.memory_mappings.insert(mapping.u_address, mapping);
+        }
+        if u_new > u_addr {
+            let mut u_page = u_addr;
+            while u_page < u_new {
+                let memory = self.user_memory_mut(u_page).unwrap();
+                let len = memory.len().min((u_new - u_page) as usize);
+                memory[..len].fill(0);
+                u_page += len as u64;
+            }
+            self.mprotect(u_addr, u_new - u_addr, perm, process)
+                .map_err(|_| LayoutError::OutOfSpace(RegionKind::Mmap))?;
+        }
+        Ok(u_addr)
+    }
 
-        let addr = self.u_next_mmap_addr;
-        self.u_next_mmap_addr += layout.size() as u64;
-        addr
+    /// Changes the permissions of the process for the pages in `u_addr..u_addr + len` to
+    /// `perm`, like `mprotect()`. Only the memory of the ELF segments and of `mmap` can
+    /// change. Fails without changes if another page of the range isn't such memory.
+    pub fn mprotect(
+        &mut self,
+        u_addr: u64,
+        len: u64,
+        perm: MemCapPermissions,
+        process: &Process,
+    ) -> Result<(), ()> {
+        let page_size = PAGE_SIZE as u64;
+        let u_addr = u_addr & !(page_size - 1);
+        let u_end = u_addr
+            .checked_add(len)
+            .and_then(|end| end.checked_add(page_size - 1))
+            .ok_or(())?
+            / page_size
+            * page_size;
+        if (u_addr..u_end)
+            .step_by(PAGE_SIZE)
+            .any(|u_page| self.owned_mapping_mut(u_page).is_none())
+        {
+            return Err(());
+        }
+        let mut u_page = u_addr;
+        while u_page < u_end {
+            let mapping = self.owned_mapping_mut(u_page).unwrap();
+            let first_page = ((u_page - mapping.u_address.val()) / page_size) as usize;
+            let page_count =
+                (mapping.page_count - first_page).min(((u_end - u_page) / page_size) as usize);
+            mapping.protect(first_page, page_count, perm, process);
+            u_page += page_count as u64 * page_size;
+        }
+        // the roottask's own mappings of the memory are gone, too
+        services::forget_mapped_areas(process.pid(), u_addr..u_end);
+        Ok(())
     }
 
-    pub fn munmap(&mut self, u_addr: u64, process: &Process) {
-        let mapping = self
+    /// Returns the mapping of the ELF segments or of `mmap` that contains `u_addr`.
+    fn owned_mapping_mut(&mut self, u_addr: u64) -> Option<&mut MemoryMapping> {
+        let u_page = PageAddress(u_addr & !(PAGE_SIZE as u64 - 1));
+        [&mut self.elf_mappings, &mut self.memory_mappings]
+            .into_iter()
+            .find_map(|mappings| {
+                mappings
+                    .range_mut(..=u_page)
+                    .next_back()
+                    .map(|(_, mapping)| mapping)
+                    .filter(|mapping| u_addr < mapping.u_address.val() + mapping.len() as u64)
+            })
+    }
+
+    /// Returns the program interpreter of a dynamically linked program, once loaded.
+    pub fn interpreter(&self) -> Option<Interpreter> {
+        self.interpreter
+    }
+
+    /// Reserves `page_count` pages in the mmap area of the address space of the process
+    /// for memory that the caller maps itself and that this manager doesn't own, for
+    /// example memory that the process shares with the roottask.
+    pub fn reserve_mmap_area(&mut self, page_count: usize) -> Result<u64, LayoutError> {
+        self.alloc_mmap_area(Layout::from_size_align(page_count * PAGE_SIZE, PAGE_SIZE).unwrap())
+    }
+
+    /// Reserves a heap region of `size` bytes in the mmap arena without backing it. The pages
+    /// get mapped in chunks of [`Self::LAZY_CHUNK_SIZE`] bytes when the process touches them
+    /// for the first time, see [`Self::handle_page_fault`]. [`Self::munmap`] releases the
+    /// whole region. The whole region counts for the [`RLIMIT_DATA`] of the process.
+    pub fn reserve_lazy_region(
+        &mut self,
+        size: usize,
+        process: &Process,
+    ) -> Result<u64, LayoutError> {
+        let size = calc_page_count(size) * PAGE_SIZE;
+        self.check_data_limit(size as u64, RegionKind::Mmap, process)?;
+        let u_addr = self.alloc_mmap_area(Layout::from_size_align(size, PAGE_SIZE).unwrap())?;
+        self.lazy_regions.insert(PageAddress::new(u_addr), size);
+        Ok(u_addr)
+    }
+
+    /// Handles a page fault of the process at `u_fault_addr` if it belongs to memory that
+    /// gets mapped on demand: the stack (see [`Self::grow_stack`]) and the lazy heap regions
+    /// (see [`Self::reserve_lazy_region`]).
+    ///
+    /// Returns true if memory got mapped, i.e. if the process can retry the faulting access.
+    pub fn handle_page_fault(&mut self, u_fault_addr: u64, u_rsp: u64, process: &Process) -> bool {
+        self.grow_stack(u_fault_addr, u_rsp, process)
+            || self.back_lazy_region(u_fault_addr, process)
+    }
+
+    /// Maps all memory in `u_addr..u_addr + len` that gets mapped on demand but wasn't
+    /// touched yet, see [`Self::handle_page_fault`]. Must be called before the roottask
+    /// maps user memory into its own address space, e.g. a buffer of a read call, because
+    /// the process might not have touched the memory yet.
+    pub fn map_on_demand_memory(&mut self, u_addr: u64, len: u64, process: &Process) {
+        // the stack grows downwards; growing to the lowest address maps everything above
+        self.grow_stack(u_addr, u_addr, process);
+        let u_end = u_addr.saturating_add(len);
+        let mut u_page = u_addr & !(PAGE_SIZE as u64 - 1);
+        while u_page < u_end {
+            self.back_lazy_region(u_page, process);
+            u_page += PAGE_SIZE as u64;
+        }
+    }
+
+    /// Maps the chunk of a lazy heap region that contains `u_fault_addr`. Returns false if the
+    /// address belongs to no lazy region or if the chunk is already mapped.
+    fn back_lazy_region(&mut self, u_fault_addr: u64, process: &Process) -> bool {
+        let (u_region, size) = match self
+            .lazy_regions
+            .range(..=PageAddress(u_fault_addr))
+            .next_back()
+        {
+            Some((u_region, size)) if u_fault_addr < u_region.val() + *size as u64 => {
+                (u_region.val(), *size as u64)
+            }
+            _ => return false,
+        };
+        let chunk_size = Self::LAZY_CHUNK_SIZE as u64;
+        let u_chunk = u_region + (u_fault_addr - u_region) / chunk_size * chunk_size;
+        let u_chunk_addr = PageAddress::new(u_chunk);
+        // another access to a mapped chunk isn't a missing mapping but a wrong access
+        if self.memory_mappings.contains_key(&u_chunk_addr) {
+            return false;
+        }
+        let page_count = (chunk_size.min(u_region + size - u_chunk)) as usize / PAGE_SIZE;
+
+        let perm = MemCapPermissions::RW;
+        let mapping = MemoryMapping::new(u_chunk_addr, page_count, MemoryKind::Heap, perm);
+        mapping
+            .delegation()
+            .perms(perm)
+            .at(u_chunk)
+            .to_pd(&process.pd_obj())
+            .unwrap();
+        self.memory_mappings.insert(u_chunk_addr, mapping);
+        true
+    }
+
+    /// Removes all chunks of the lazy heap region `u_region..u_region + size` that are
+    /// backed already. The chunks get revoked from the process. Dropping the mappings frees
+    /// the frames.
+    fn release_lazy_region(&mut self, u_region: u64, size: u64, process: &Process) {
+        let u_chunks = self
+            .memory_mappings
+            .range(PageAddress(u_region)..PageAddress(u_region + size))
+            .map(|(u_addr, _mapping)| *u_addr)
+            .collect::<Vec<_>>();
+        for u_chunk in u_chunks {
+            let mapping = self.memory_mappings.remove(&u_chunk).unwrap();
+            mapping.revoke(0, mapping.page_count, process);
+        }
+    }
+
+    /// Returns the size of the heap memory of the process in bytes: the program break, the
+    /// memory of `mmap`, and the lazy heap regions, whether backed or not.
+    pub fn data_size(&self) -> u64 {
+        let in_lazy_region = |u_addr: &PageAddress| {
+            self.lazy_regions
+                .range(..=*u_addr)
+                .next_back()
+                .map_or(false, |(u_region, size)| {
+                    u_addr.val() < u_region.val() + *size as u64
+                })
+        };
+        let mapped = self
             .memory_mappings
             .iter()
-            .find(|(mapping_u_addr, _mapping)| mapping_u_addr.val() == u_addr)
-            .unwrap();
+            .filter(|(u_addr, _mapping)| !in_lazy_region(u_addr))
+            .map(|(_u_addr, mapping)| mapping.len() as u64)
+            .sum::<u64>();
+        let reserved = self
+            .lazy_regions
+            .values()
+            .map(|size| *size as u64)
+            .sum::<u64>();
+        mapped + reserved
+    }
 
-        let (u_addr, page_count) = (*mapping.0, mapping.1.page_count);
-        drop(mapping);
+    /// Fails with an error for the region `kind` if `growth` more bytes of heap memory
+    /// exceed the [`RLIMIT_DATA`] of the process.
+    fn check_data_limit(
+        &self,
+        growth: u64,
+        kind: RegionKind,
+        process: &Process,
+    ) -> Result<(), LayoutError> {
+        let limit = resource_limit(process.pid(), RLIMIT_DATA).unwrap().cur;
+        if self.data_size().saturating_add(growth) > limit {
+            log::debug!(
+                "pid={} exceeds its data limit of {} bytes",
+                process.pid(),
+                limit
+            );
+            return Err(LayoutError::OutOfSpace(kind));
+        }
+        Ok(())
+    }
+
+    /// Takes the next free range with the size and alignment of `layout` from the mmap arena.
+    fn alloc_mmap_area(&mut self, layout: Layout) -> Result<u64, LayoutError> {
+        let align = layout.align().max(PAGE_SIZE) as u64;
+        let u_addr = (self.u_next_mmap_addr + align - 1) / align * align;
+        self.layout
+            .check(RegionKind::Mmap, u_addr, layout.size() as u64)?;
+        self.u_next_mmap_addr = u_addr + layout.size() as u64;
+        Ok(u_addr)
+    }
+
+    /// Removes the mapping or the lazy heap region that starts at `u_addr`.
+    pub fn munmap(&mut self, u_addr: u64, process: &Process) {
+        if let Some(size) = self.lazy_regions.remove(&PageAddress(u_addr)) {
+            self.release_lazy_region(u_addr, size as u64, process);
+            return;
+        }
 
-        // downgrade rights
-        CrdDelegateOptimizer::new(
-            u_addr.val() / PAGE_SIZE as u64,
-            u_addr.val() / PAGE_SIZE as u64,
+        let mapping = self.memory_mappings.remove(&PageAddress(u_addr)).unwrap();
+        mapping.revoke(0, mapping.page_count, process);
+    }
+
+    /// Grows the stack on demand after a page fault of the process at `u_fault_addr`. The
+    /// stack grows if the fault address is below the mapped stack but above the guard page
+    /// and at most [`USER_STACK_GROW_RSP_SLACK`] bytes below the stack pointer `u_rsp`. Maps
+    /// at least [`USER_STACK_GROW_MIN_SIZE`] bytes at once.
+    ///
+    /// Returns true if the stack grew, i.e. if the process can retry the faulting access.
+    pub fn grow_stack(&mut self, u_fault_addr: u64, u_rsp: u64, process: &Process) -> bool {
+        let u_stack_bottom = self.u_stack_bottom().val();
+        // the lowest page of the region is the guard page
+        let u_stack_limit = self.layout.region(RegionKind::Stack).start + PAGE_SIZE as u64;
+        if u_fault_addr < u_stack_limit
+            || u_fault_addr >= u_stack_bottom
+            || u_fault_addr + USER_STACK_GROW_RSP_SLACK < u_rsp
+        {
+            return false;
+        }
+        let u_fault_page = u_fault_addr & !(PAGE_SIZE as u64 - 1);
+        let u_new_bottom = u_fault_page
+            .min(u_stack_bottom - USER_STACK_GROW_MIN_SIZE as u64)
+            .max(u_stack_limit);
+        let page_count = (u_stack_bottom - u_new_bottom) as usize / PAGE_SIZE;
+        log::debug!(
+            "growing stack of pid={} by {} pages to bottom=0x{:x}",
+            process.pid(),
             page_count,
-        )
-        .mmap(
-            process.pd_obj().cap_sel(),
-            process.pd_obj().cap_sel(),
-            MemCapPermissions::empty(),
+            u_new_bottom
         );
 
-        self.memory_mappings.remove(&u_addr);
+        let perm = MemCapPermissions::RW;
+        let mapping = MemoryMapping::new(
+            PageAddress::new(u_new_bottom),
+            page_count,
+            MemoryKind::Stack,
+            perm,
+        );
+        mapping
+            .delegation()
+            .perms(perm)
+            .at(u_new_bottom)
+            .to_pd(&process.pd_obj())
+            .unwrap();
+        self.stack_growth.push(mapping);
+        true
     }
 
+    /// Returns the lowest address of the stack that is currently mapped.
+    pub fn u_stack_bottom(&self) -> PageAddress {
+        self.stack_growth
+            .last()
+            .unwrap_or_else(|| self.stack())
+            .address()
+    }
+
+    /// Returns the mapping of the top of the stack that was mapped when the process
+    /// started. See [`USER_STACK_INITIAL_SIZE`].
     pub fn stack(&self) -> &MemoryMapping {
         self.stack.as_ref().unwrap()
     }
@@ -441,6 +822,11 @@ impl ProcessMemoryManager {
         self.stack.as_mut().unwrap()
     }
 
+    /// Returns the layout of the address space of the process.
+    pub fn layout(&self) -> &AddressSpaceLayout {
+        &self.layout
+    }
+
     /// Returns the current program break in user address space.
     pub fn u_program_break_current(&self) -> PageAddress {
         self.u_program_break_current
@@ -449,17 +835,47 @@ impl ProcessMemoryManager {
     pub fn u_program_break_begin(&self) -> PageAddress {
         self.u_program_break_begin
     }
+
+    /// Returns all memory that the roottask allocated for the process, including the copies
+    /// of the ELF segments. Not sorted by address.
+    pub fn mappings(&self) -> impl Iterator<Item = &MemoryMapping> {
+        self.elf_mappings
+            .values()
+            .chain(self.memory_mappings.values())
+            .chain(self.stack.iter())
+            .chain(self.stack_growth.iter())
+            .chain(self.args.iter())
+    }
+
+    /// Returns the memory from the user address up to the end of its mapping, as the
+    /// roottask accesses it. The roottask can write it, also if the process can't, e.g.
+    /// to place breakpoints in code. See [`Self::mappings`] for what memory is covered.
+    pub fn user_memory_mut(&mut self, u_addr: u64) -> Option<&mut [u8]> {
+        self.elf_mappings
+            .values_mut()
+            .chain(self.memory_mappings.values_mut())
+            .chain(self.stack.iter_mut())
+            .chain(self.stack_growth.iter_mut())
+            .chain(self.args.iter_mut())
+            .find_map(|mapping| {
+                let offset = u_addr.checked_sub(mapping.address().val())? as usize;
+                (offset < mapping.len()).then(|| &mut mapping.mem_as_mut()[offset..])
+            })
+    }
 }
 
 /// Describes a memory mapping for a process. Allows access to it in roottask address space.
+///
+/// The memory consists of physical frames from [`PHYS_FRAME_ALLOC`] and not of memory
+/// from the heap of the roottask. Dropping the mapping revokes it from the roottask and the
+/// user and returns the frames.
 #[derive(Debug)]
 pub struct MemoryMapping {
-    /// The address of the mapping in the address space of the roottask. This points into the
-    /// heap of the roottask and must be used to free heap usage of the roottask afterwards.
+    /// The address of the mapping in the address space of the roottask. The roottask maps
+    /// the frames here to access the memory.
     r_address: PageAddress,
-    /// The layout used for the allocation of this memory mapping inside the heap of the roottask.
-    /// Required to free the memory properly afterwards.
-    r_layout: core::alloc::Layout,
+    /// The physical address of the frames.
+    phys_address: PhysAddr,
     /// The address of the mapping in the address space of the user app.
     u_address: PageAddress,
     /// Amount of pages that were mapped.
@@ -470,22 +886,83 @@ pub struct MemoryMapping {
 }
 
 impl MemoryMapping {
-    const fn new(
-        r_address: PageAddress,
-        r_layout: Layout,
+    /// Allocates `page_count` frames, maps them into the roottask, and zeroes them. Mapping
+    /// them into the address space of the user at `u_address` is up to the caller.
+    fn new(
         u_address: PageAddress,
         page_count: usize,
         kind: MemoryKind,
         u_perm: MemCapPermissions,
     ) -> Self {
-        // TODO invoke allocator here?!
-        Self {
-            r_address,
-            r_layout,
+        let phys_address = PHYS_FRAME_ALLOC
+            .lock()
+            .alloc(page_count)
+            .expect("out of physical memory");
+        // optimize alignment for faster delegate calls (use Crd order optimization)
+        let size = page_count * PAGE_SIZE;
+        let r_address = VIRT_MEM_ALLOC
+            .lock()
+            .next_addr(Layout::from_size_align(size, size.next_power_of_two()).unwrap());
+        // roottask to roottask: the source is the physical address; the roottask keeps all
+        // rights, so that the user can get any of them
+        DelegationBuilder::mem(phys_address..phys_address + (page_count * PAGE_SIZE) as u64)
+            .at(r_address)
+            .to(RootCapSpace::RootPd.val())
+            .unwrap();
+
+        let mut mapping = Self {
+            r_address: PageAddress::new(r_address),
+            phys_address,
             u_address,
             page_count,
             kind,
             u_perm,
+        };
+        // frames keep the data of their previous owner
+        mapping.mem_as_mut().fill(0);
+        mapping
+    }
+
+    /// Returns a delegation of the whole mapping from the roottask, e.g. into the address
+    /// space of the user.
+    fn delegation(&self) -> DelegationBuilder<MemCapPermissions> {
+        let r_address = self.r_address.val();
+        DelegationBuilder::mem(r_address..r_address + self.len() as u64)
+    }
+
+    /// Revokes `page_count` pages from page `first_page` of the mapping on from the process.
+    /// The roottask keeps its own mapping.
+    fn revoke(&self, first_page: usize, page_count: usize, process: &Process) {
+        let r_page_num = self.r_address.val() / PAGE_SIZE as u64 + first_page as u64;
+        process.pd_obj().revoke_delegations(
+            DelegatedKind::Mem,
+            r_page_num..r_page_num + page_count as u64,
+        );
+    }
+
+    /// Changes the permissions of the process for `page_count` pages from page `first_page`
+    /// of the mapping on. The permissions of the mapping change only if all of its pages
+    /// change.
+    fn protect(
+        &mut self,
+        first_page: usize,
+        page_count: usize,
+        perm: MemCapPermissions,
+        process: &Process,
+    ) {
+        // Hedron can't downgrade mappings; remove the ones that derive from the roottask
+        self.revoke(first_page, page_count, process);
+        if !perm.is_empty() {
+            let offset = (first_page * PAGE_SIZE) as u64;
+            let r_addr = self.r_address.val() + offset;
+            DelegationBuilder::mem(r_addr..r_addr + (page_count * PAGE_SIZE) as u64)
+                .perms(perm)
+                .at(self.u_address.val() + offset)
+                .to_pd(&process.pd_obj())
+                .unwrap();
+        }
+        if first_page == 0 && page_count == self.page_count {
+            self.u_perm = perm;
         }
     }
 
@@ -525,7 +1002,23 @@ impl MemoryMapping {
 
 impl Drop for MemoryMapping {
     fn drop(&mut self) {
-        unsafe { Global.deallocate(self.r_address_as_non_null(), self.r_layout) }
+        // revokes the mapping of the roottask and all mappings derived from it, i.e. the
+        // one of the user
+        let r_page_num = self.r_address.val() / PAGE_SIZE as u64;
+        let mut revoked = true;
+        CrdDelegateOptimizer::new(r_page_num, r_page_num, self.page_count).for_each(|params| {
+            let crd = CrdMem::new(params.src_base, params.order, MemCapPermissions::RWX);
+            if let Err(e) = sys_revoke(crd, true) {
+                log::warn!("can't revoke memory at page {}: {:?}", params.src_base, e);
+                revoked = false;
+            }
+        });
+        // frames that someone may still access must never get a new owner
+        if revoked {
+            PHYS_FRAME_ALLOC
+                .lock()
+                .free(self.phys_address, self.page_count);
+        }
     }
 }
 
@@ -558,4 +1051,6 @@ pub enum MemoryKind {
     Heap,
     /// Memory is used as stack.
     Stack,
+    /// Memory holds the arguments and environment variables of a native app.
+    Args,
 }
@@ -1,8 +1,30 @@
+mod cap_sels;
+mod comm;
+mod core_dump;
+mod exit;
+mod fault;
+mod interp;
+mod layout;
 mod memory;
+mod rlimit;
+mod scheduling;
+mod signal;
 mod syscall_abi;
-
+mod syscall_trace;
+
+pub use cap_sels::*;
+pub use comm::*;
+pub use core_dump::*;
+pub use exit::*;
+pub use fault::*;
+pub use interp::*;
+pub use layout::*;
 pub use memory::*;
+pub use rlimit::*;
+pub use scheduling::*;
+pub use signal::*;
 pub use syscall_abi::*;
+pub use syscall_trace::*;
 
 use crate::mem::MappedMemory;
 use crate::roottask_exception;
@@ -30,7 +52,10 @@ use core::hash::{
     Hash,
     Hasher,
 };
-use elf_rs::ElfFile;
+use elf_rs::{
+    ElfFile,
+    ProgramType,
+};
 use libhrstd::cap_space::root::RootCapSpace;
 use libhrstd::cap_space::user::{
     ForeignUserAppCapSpace,
@@ -45,22 +70,23 @@ use libhrstd::kobjects::{
 };
 use libhrstd::libhedron::consts::NUM_EXC;
 use libhrstd::libhedron::mem::PAGE_SIZE;
-use libhrstd::libhedron::Qpd;
 use libhrstd::libhedron::{
     CapSel,
     MemCapPermissions,
 };
 use libhrstd::process::consts::{
     ProcessId,
+    HEDRON_AUX_VECTOR_MAGIC,
+    LINUX_UNDER_HEDRON_ENV_VAR,
     ROOTTASK_PROCESS_PID,
 };
+use libhrstd::rt::services::scheduling::SchedulingParams;
 use libhrstd::uaddress_space::{
     USER_ELF_ADDR,
-    USER_STACK_BOTTOM_ADDR,
-    USER_STACK_SIZE,
     USER_UTCB_ADDR,
 };
-use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use libhrstd::util::delegation::DelegationBuilder;
+use libhrstd::util::utf8_stream::Utf8StreamDecoder;
 use linux_libc_auxv::{
     AuxVar,
     InitialLinuxLibcStackLayoutBuilder,
@@ -80,19 +106,50 @@ pub enum ProcessState {
 pub struct Process {
     pid: ProcessId,
     name: String,
+    /// Command name, like on Linux. See [`process_comm`].
+    comm: RefCell<String>,
     state: Cell<ProcessState>,
     parent: Option<Weak<Self>>,
     pd_obj: RefCell<Option<Rc<PdObject>>>,
     // todo theoretically I could remove the option, because I have the memory for the mapped
     //  roottask too from the hip
     elf_file: Option<MappedMemory>,
-    // stack with size USER_STACK_SIZE for the main global EC
     /// Currently the process memory manager is only available for user processes
     /// but not the roottask.
     memory_manager: Option<RefCell<ProcessMemoryManager>>,
 
     /// Syscall ABI used by this process.
     syscall_abi: SyscallAbi,
+
+    /// Arguments of the program. By convention, the first one is the program name.
+    argv: Vec<String>,
+    /// Environment variables of the program in the form `KEY=VALUE`.
+    envp: Vec<String>,
+
+    /// Current working directory; an absolute path without symbolic links. Relative paths
+    /// of the process are relative to it. A new process starts in the one of its parent.
+    cwd: RefCell<String>,
+
+    /// Initial scheduling parameters of the main SC. The scheduling service can change
+    /// them at runtime, see [`scheduling_params`].
+    sched_params: SchedulingParams,
+
+    /// CPU of the main global EC. The process stays on this CPU; all its portals are bound
+    /// to the local ECs of the roottask on this CPU. See [`crate::smp`].
+    cpu: u64,
+
+    /// Whether the address space layout of the process is randomized, see
+    /// [`AddressSpaceLayout::randomize`].
+    aslr: bool,
+
+    /// Signal actions and blocked signals. Pending signals are managed by [`raise_signal`].
+    signal_state: RefCell<SignalState>,
+
+    /// Handler of faults of a Hedron-native process, see [`FaultHandler`].
+    fault_handler: Cell<Option<FaultHandler>>,
+
+    /// Decoders of the UTF-8 output to stdout and stderr, in that order.
+    console_decoders: RefCell<[Utf8StreamDecoder; 2]>,
 }
 
 impl Process {
@@ -105,6 +162,8 @@ impl Process {
             &root_pd_obj,
             utcb_addr,
             stack_top_addr,
+            // Hedron starts the roottask on the boot CPU
+            0,
         );
         let _ = ScObject::new(RootCapSpace::RootSc.val(), &root_ec_obj, None);
 
@@ -113,24 +172,48 @@ impl Process {
             pd_obj: RefCell::new(Some(root_pd_obj)),
             elf_file: None,
             name: "roottask".to_string(),
+            comm: RefCell::new("roottask".to_string()),
             state: Cell::new(ProcessState::Created),
             parent: None,
             syscall_abi: SyscallAbi::NativeHedron,
+            argv: Vec::new(),
+            envp: Vec::new(),
+            cwd: RefCell::new("/".to_string()),
+            // Hedron creates the SC of the roottask; this only documents the default
+            sched_params: SchedulingParams::DEFAULT,
+            cpu: 0,
+            aslr: false,
             memory_manager: None,
+            signal_state: RefCell::new(SignalState::new()),
+            fault_handler: Cell::new(None),
+            console_decoders: RefCell::new([Utf8StreamDecoder::new(), Utf8StreamDecoder::new()]),
         })
     }
 
     /// Creates a new process object. Doesn't create kernel objects or trigger syscalls.
     /// Already allcoates memory for UTCB and stack.
     ///
+    /// `argv` and `envp` are passed to the program when it starts. Must not contain null
+    /// bytes. `sched_params` must be valid, see [`SchedulingParams::is_valid`]. `cpu` must
+    /// be online, see [`crate::smp::is_online`]. `aslr` randomizes the address space layout;
+    /// without it, the layout is deterministic, e.g. for benchmarks.
+    ///
     /// Invoke [`Self::init`] next.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         pid: u64,
         elf_file: MappedMemory,
         program_name: String,
         parent: &Rc<Self>,
         syscall_abi: SyscallAbi,
+        argv: Vec<String>,
+        envp: Vec<String>,
+        sched_params: SchedulingParams,
+        cpu: u64,
+        aslr: bool,
     ) -> Self {
+        assert!(sched_params.is_valid(), "invalid scheduling params");
+        assert!(crate::smp::is_online(cpu), "CPU {} is not online", cpu);
         assert_eq!(
             elf_file.perm(),
             MemCapPermissions::all(),
@@ -140,11 +223,21 @@ impl Process {
             pid,
             pd_obj: RefCell::new(None),
             elf_file: Some(elf_file),
+            comm: RefCell::new(comm_from_program_name(&program_name)),
             name: program_name,
             state: Cell::new(ProcessState::Created),
             parent: Some(Rc::downgrade(parent)),
             syscall_abi,
+            argv,
+            envp,
+            cwd: RefCell::new(parent.cwd()),
+            sched_params,
+            cpu,
+            aslr,
             memory_manager: None,
+            signal_state: RefCell::new(SignalState::new()),
+            fault_handler: Cell::new(None),
+            console_decoders: RefCell::new([Utf8StreamDecoder::new(), Utf8StreamDecoder::new()]),
         }
     }
 
@@ -152,19 +245,22 @@ impl Process {
     /// - trigger syscalls for new PDs, ECs and SCs
     /// - map UTCB, STACK, and the LOAD segments from the ELF into the new process.
     ///
-    /// This will result in a STARTUP exception.
+    /// This will result in a STARTUP exception. The selectors of the process must be
+    /// assigned before, see [`assign_process_cap_sels`].
     pub fn init(&mut self) {
         // state will be altered by the startup exception handler
         assert_eq!(self.state.get(), ProcessState::Created);
         log::debug!(
-            "Create new process: pid={}, program_name={}",
+            "Create new process: pid={}, program_name={}, cpu={}",
             self.pid,
-            self.name
+            self.name,
+            self.cpu
         );
 
-        let pd_cap_in_root = RootCapSpace::calc_pd_sel(self.pid);
-        let ec_cap_in_root = RootCapSpace::calc_gl_ec_sel(self.pid);
-        let sc_cap_in_root = RootCapSpace::calc_sc_sel(self.pid);
+        let cap_sels = process_cap_sels(self.pid).expect("selectors must be assigned first");
+        let pd_cap_in_root = cap_sels.pd();
+        let ec_cap_in_root = cap_sels.gl_ec();
+        let sc_cap_in_root = cap_sels.sc();
 
         let foreign_syscall_base = if self.syscall_abi.is_foreign() {
             Some(ForeignUserAppCapSpace::SyscallBasePt.val())
@@ -186,10 +282,11 @@ impl Process {
             USER_UTCB_ADDR,
             // set in Startup-Exception anyway
             0,
+            self.cpu,
         );
         log::trace!("created global EC for PID={}", self.pid);
 
-        self.init_exc_portals(RootCapSpace::calc_exc_pt_sel_base(self.pid));
+        self.init_exc_portals(cap_sels.exc_pt_base());
 
         let mut memory_manager = ProcessMemoryManager::new(self);
         memory_manager.init(self).unwrap();
@@ -202,7 +299,10 @@ impl Process {
 
         // create SC-Object at the very end! Otherwise Hedron might schedule the new PD too early
         // (i.e.: before startup exception portal is set)
-        let _ = ScObject::create(sc_cap_in_root, &ec, Qpd::new(1, None));
+        let _ = ScObject::create(sc_cap_in_root, &ec, self.sched_params.qpd());
+        register_scheduling_params(self.pid, self.sched_params);
+        register_process_cpu(self.pid, self.cpu);
+        register_comm(self.pid, &self.comm.borrow());
 
         log::trace!(
             "Init process done: PID={}, name={}, utcb_addr={:x?}",
@@ -214,7 +314,7 @@ impl Process {
 
     /// Creates [`NUM_EXC`] new portals inside the roottask, let them point
     /// to the common generic exception handler and delegate them to
-    /// the new protection domain.
+    /// the new protection domain. The portals are bound to the CPU of the process.
     ///
     /// # Parameters
     /// * `base_cap_sel_in_root`: Base cap sel into the roottask for the exception
@@ -223,7 +323,8 @@ impl Process {
     fn init_exc_portals(&self, base_cap_sel_in_root: CapSel) {
         for exc_i in 0..NUM_EXC as u64 {
             let roottask_pt_sel = base_cap_sel_in_root + exc_i;
-            let pt = roottask_exception::create_exc_pt_for_process(exc_i, roottask_pt_sel);
+            let pt =
+                roottask_exception::create_exc_pt_for_process(exc_i, roottask_pt_sel, self.cpu);
 
             // delegate each exception portal to the pd of the new process
             PtObject::delegate(
@@ -246,43 +347,89 @@ impl Process {
         let pr_hdr_off = elf.elf_header().program_header_offset();
         dbg!(pr_hdr_off);
 
+        let load_bias = self.memory_manager().layout().load_bias();
+        // 0 tells the libc that there is no program interpreter
+        let u_interpreter_base = self
+            .memory_manager()
+            .interpreter()
+            .map_or(0, |interpreter| interpreter.base);
+        // the program headers as loaded, if a load segment covers them; static PIEs
+        // derive their load bias from it
+        let u_phdr_addr = elf
+            .program_header_iter()
+            .filter(|hdr| hdr.ph_type() == ProgramType::LOAD)
+            .find(|hdr| (hdr.offset()..hdr.offset() + hdr.filesz()).contains(&pr_hdr_off))
+            .map(|hdr| hdr.vaddr() + load_bias + (pr_hdr_off - hdr.offset()))
+            .unwrap_or(USER_ELF_ADDR + pr_hdr_off);
+
         // page aligned
         let elf_bytes_addr = elf_bytes.as_ptr() as u64;
 
         // map program header
-        CrdDelegateOptimizer::new(
-            elf_bytes_addr / PAGE_SIZE as u64,
-            USER_ELF_ADDR / PAGE_SIZE as u64,
-            1,
-        )
-        .mmap(
-            self.parent().unwrap().pd_obj().cap_sel(),
-            self.pd_obj().cap_sel(),
-            MemCapPermissions::READ,
-        );
-
-        let stack_layout = InitialLinuxLibcStackLayoutBuilder::new()
-            .add_arg_v("./executable")
-            .add_arg_v("10.123")
-            .add_arg_v("first")
-            .add_arg_v("second")
-            .add_env_v("FOO=BAR")
+        DelegationBuilder::mem(elf_bytes_addr..elf_bytes_addr + PAGE_SIZE as u64)
+            .perms(MemCapPermissions::READ)
+            .at(USER_ELF_ADDR)
+            .to_pd(&self.pd_obj())
+            .unwrap();
+
+        // glibc derives the stack protector canary and the pointer guard from it
+        let mut random = [0; 16];
+        crate::rt::devfs::fill_random(&mut random);
+        // like Linux, passes the features of CPUID leaf 1 in EDX
+        let hwcap = x86::cpuid::native_cpuid::cpuid_count(1, 0).edx;
+
+        let under_hedron_env_v = format!("{}=true", LINUX_UNDER_HEDRON_ENV_VAR);
+        let mut stack_layout = InitialLinuxLibcStackLayoutBuilder::new();
+        for arg in &self.argv {
+            stack_layout = stack_layout.add_arg_v(arg);
+        }
+        for env in &self.envp {
+            stack_layout = stack_layout.add_env_v(env);
+        }
+        let stack_layout = stack_layout
             // application can use this to check if it runs under hedron
-            .add_env_v("LINUX_UNDER_HEDRON=true")
-            .add_aux_v(AuxVar::ExecFn("./executable"))
+            .add_env_v(&under_hedron_env_v)
+            // same, but the application can't change it; libc ignores it
+            .add_aux_v(AuxVar::Ignore(HEDRON_AUX_VECTOR_MAGIC as usize))
+            .add_aux_v(AuxVar::ExecFn(
+                self.argv.first().map(String::as_str).unwrap_or(&self.name),
+            ))
             .add_aux_v(AuxVar::Platform("x86_64"))
             // libc (at least musl) expects all of this values to be present
-            .add_aux_v(AuxVar::Phdr((USER_ELF_ADDR + pr_hdr_off) as *const u8))
+            .add_aux_v(AuxVar::Phdr(u_phdr_addr as *const u8))
             .add_aux_v(AuxVar::Phnum(
                 elf.elf_header().program_header_entry_num() as usize
             ))
             .add_aux_v(AuxVar::Phent(
                 elf.elf_header().program_header_entry_size() as usize
             ))
-            .add_aux_v(AuxVar::Pagesz(PAGE_SIZE));
+            .add_aux_v(AuxVar::Pagesz(PAGE_SIZE))
+            .add_aux_v(AuxVar::Base(u_interpreter_base as *const u8))
+            // glibc expects the remaining values, too
+            .add_aux_v(AuxVar::Entry(
+                (elf.elf_header().entry_point() + load_bias) as *const u8,
+            ))
+            .add_aux_v(AuxVar::Random(random))
+            .add_aux_v(AuxVar::HwCap(hwcap as usize))
+            .add_aux_v(AuxVar::HwCap2(0))
+            .add_aux_v(AuxVar::Clktck(100))
+            .add_aux_v(AuxVar::Secure(false))
+            .add_aux_v(AuxVar::Uid(0))
+            .add_aux_v(AuxVar::EUid(0))
+            .add_aux_v(AuxVar::Gid(0))
+            .add_aux_v(AuxVar::EGid(0));
 
         let mut memory_manager = self.memory_manager_mut();
+        // below the very top with ASLR
+        let u_stack_top = memory_manager.layout().stack_top();
         let stack = memory_manager.stack_mut();
+        // the initially mapped top of the stack; the crt0 data must fit into it
+        let u_addr_stack_btm_inc = stack.address().val();
+        let stack_len = (u_stack_top - u_addr_stack_btm_inc) as usize;
+        assert!(
+            stack_layout.total_size() + 64 + 8 < stack_len,
+            "the initial stack layout exceeds the initial stack"
+        );
         // whole memory that is stack for user; in roottask address space
         let r_mem_stack = stack.mem_as_mut();
 
@@ -290,7 +437,7 @@ impl Process {
         // "u_addr": user address
 
         let r_addr_stack_btm_inc = r_mem_stack.as_ptr() as usize;
-        let r_addr_stack_top_excl = r_addr_stack_btm_inc + USER_STACK_SIZE;
+        let r_addr_stack_top_excl = r_addr_stack_btm_inc + stack_len;
 
         // - 1: to inclusive addr; - 8 because later we might need to add + 8 for correct alignment
         let mut r_addr_crt0_layout_btm = r_addr_stack_top_excl - 1 - stack_layout.total_size() - 8;
@@ -304,7 +451,7 @@ impl Process {
         let r_offset_crt0_layout = r_addr_crt0_layout_btm - r_addr_stack_btm_inc;
 
         // RSP of user
-        let u_addr_crt0_btm = USER_STACK_BOTTOM_ADDR + r_offset_crt0_layout as u64;
+        let u_addr_crt0_btm = u_addr_stack_btm_inc + r_offset_crt0_layout as u64;
 
         let r_mem_crt0 = &mut r_mem_stack[r_offset_crt0_layout..];
 
@@ -323,6 +470,40 @@ impl Process {
         &self.name
     }
 
+    /// Returns the command name of the process, see [`process_comm`].
+    pub fn comm(&self) -> String {
+        self.comm.borrow().clone()
+    }
+
+    /// Renames the process. Truncates the name to [`COMM_LEN`] - 1 bytes.
+    pub fn set_comm(&self, comm: &str) {
+        let comm = truncate_comm(comm);
+        register_comm(self.pid, &comm);
+        self.comm.replace(comm);
+    }
+
+    /// Current working directory of the process.
+    pub fn cwd(&self) -> String {
+        self.cwd.borrow().clone()
+    }
+
+    /// Changes the current working directory. `cwd` must be an absolute path without
+    /// symbolic links.
+    pub fn set_cwd(&self, cwd: String) {
+        debug_assert!(cwd.starts_with('/'));
+        self.cwd.replace(cwd);
+    }
+
+    /// Arguments of the program.
+    pub fn argv(&self) -> &[String] {
+        &self.argv
+    }
+
+    /// Environment variables of the program.
+    pub fn envp(&self) -> &[String] {
+        &self.envp
+    }
+
     /// Getter for [`PdObject`].
     pub fn pd_obj(&self) -> Rc<PdObject> {
         self.pd_obj
@@ -375,6 +556,16 @@ impl Process {
         self.syscall_abi
     }
 
+    /// Returns the CPU of the process.
+    pub fn cpu(&self) -> u64 {
+        self.cpu
+    }
+
+    /// Returns true if the address space layout of the process is randomized.
+    pub fn aslr(&self) -> bool {
+        self.aslr
+    }
+
     pub fn elf_file(&self) -> &Option<MappedMemory> {
         &self.elf_file
     }
@@ -386,6 +577,44 @@ impl Process {
     pub fn memory_manager_mut(&self) -> RefMut<ProcessMemoryManager> {
         self.memory_manager.as_ref().unwrap().borrow_mut()
     }
+
+    pub fn signal_state(&self) -> Ref<SignalState> {
+        self.signal_state.borrow()
+    }
+
+    pub fn signal_state_mut(&self) -> RefMut<SignalState> {
+        self.signal_state.borrow_mut()
+    }
+
+    /// Returns the decoder for the output of the process to stdout (`fd=1`) or stderr (`fd=2`).
+    /// Keeps code points that the process splits across multiple writes intact.
+    pub fn console_decoder_mut(&self, fd: u64) -> RefMut<Utf8StreamDecoder> {
+        assert!(fd == 1 || fd == 2, "fd={} is neither stdout nor stderr", fd);
+        RefMut::map(self.console_decoders.borrow_mut(), |decoders| {
+            &mut decoders[fd as usize - 1]
+        })
+    }
+
+    /// Registers the fault handler or removes it with `None`.
+    pub fn set_fault_handler(&self, handler: Option<FaultHandler>) {
+        self.fault_handler.set(handler);
+    }
+
+    /// Removes the fault handler and returns it. The roottask enters a fault handler only
+    /// once, so that a fault inside the handler terminates the process.
+    pub fn take_fault_handler(&self) -> Option<FaultHandler> {
+        self.fault_handler.take()
+    }
+
+    /// Wrapper around [`has_pending_signal`] that respects the blocked signals of the process.
+    pub fn has_pending_signal(&self) -> bool {
+        has_pending_signal(self.pid, self.signal_state().blocked())
+    }
+
+    /// Wrapper around [`take_pending_signal`] that respects the blocked signals of the process.
+    pub fn take_pending_signal(&self) -> Option<SigNum> {
+        take_pending_signal(self.pid, self.signal_state().blocked())
+    }
 }
 
 impl PartialEq for Process {
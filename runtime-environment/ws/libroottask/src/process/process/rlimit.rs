@@ -0,0 +1,229 @@
+//! Resource limits of processes, like `getrlimit()` and `setrlimit()` of Linux. Each limit
+//! has a soft limit, which takes effect, and a hard limit, which caps the soft limit. A new
+//! process inherits the limits of its parent. These limits take effect:
+//! - [`RLIMIT_CPU`]: the main global EC checks the CPU time each second, see
+//!   [`check_cpu_limit`]. Beyond the soft limit, the process gets `SIGXCPU` each second;
+//!   at the hard limit, it gets killed.
+//! - [`RLIMIT_FSIZE`] and [`RLIMIT_NOFILE`]: the file system enforces them, see
+//!   [`libfileserver::FileLimits`].
+//! - [`RLIMIT_DATA`]: the heap memory of the process, i.e. the program break, `mmap()`,
+//!   and the allocate service. See [`crate::process::ProcessMemoryManager::data_size`].
+//!
+//! [`RLIMIT_STACK`] reports the fixed size of the stack; all other limits have no effect.
+//!
+//! Like the signal targets, the limits live in a global table of plain data, because
+//! portal handlers can't look up other processes while the process manager is locked.
+
+use crate::process::{
+    cpu_time_us,
+    exit_status,
+    kill_process,
+    raise_signal,
+    SIGKILL,
+    SIGXCPU,
+};
+use crate::services::timer::watch_cpu_limit;
+use libfileserver::FileLimits;
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::uaddress_space::USER_STACK_SIZE;
+
+/// CPU time in seconds.
+pub const RLIMIT_CPU: u64 = 0;
+/// Size of files in bytes.
+pub const RLIMIT_FSIZE: u64 = 1;
+/// Size of the heap memory in bytes.
+pub const RLIMIT_DATA: u64 = 2;
+/// Size of the stack in bytes.
+pub const RLIMIT_STACK: u64 = 3;
+/// Number of open files, i.e. the lowest file descriptor that can't be opened.
+pub const RLIMIT_NOFILE: u64 = 7;
+/// Number of resources of Linux.
+pub const RLIM_NLIMITS: u64 = 16;
+/// No limit.
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// Limits of processes that didn't inherit any, i.e. the programs of the bootstrap. Like
+/// the defaults of Linux.
+const DEFAULT_LIMITS: [ResourceLimit; RLIM_NLIMITS as usize] = {
+    let mut limits = [ResourceLimit::UNLIMITED; RLIM_NLIMITS as usize];
+    limits[RLIMIT_STACK as usize] = ResourceLimit {
+        cur: USER_STACK_SIZE as u64,
+        max: USER_STACK_SIZE as u64,
+    };
+    limits[RLIMIT_NOFILE as usize] = ResourceLimit {
+        cur: 1024,
+        max: 4096,
+    };
+    limits
+};
+
+/// Limits of each process, indexed by PID.
+static RESOURCE_LIMITS: SimpleMutex<
+    [[ResourceLimit; RLIM_NLIMITS as usize]; NUM_PROCESSES as usize],
+> = SimpleMutex::new([DEFAULT_LIMITS; NUM_PROCESSES as usize]);
+
+/// Soft and hard limit of a resource. Same as `struct rlimit` of Linux.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C)]
+pub struct ResourceLimit {
+    pub cur: u64,
+    pub max: u64,
+}
+
+impl ResourceLimit {
+    pub const UNLIMITED: Self = Self {
+        cur: RLIM_INFINITY,
+        max: RLIM_INFINITY,
+    };
+}
+
+/// Errors of [`set_resource_limit`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResourceLimitError {
+    /// The resource is unknown or the soft limit exceeds the hard limit.
+    InvalidArgument,
+    /// Only privileged processes can raise hard limits.
+    PermissionDenied,
+}
+
+/// Returns the limit of the resource of the process or `None` if the resource is unknown.
+/// Can be called from every EC of the roottask.
+pub fn resource_limit(pid: ProcessId, resource: u64) -> Option<ResourceLimit> {
+    (resource < RLIM_NLIMITS).then(|| RESOURCE_LIMITS.lock()[pid as usize][resource as usize])
+}
+
+/// Sets the limit of the resource of the process and returns the previous one. Raising the
+/// hard limit needs a `privileged` caller, see [`crate::process::is_privileged`]. Can be
+/// called from every EC of the roottask.
+pub fn set_resource_limit(
+    pid: ProcessId,
+    resource: u64,
+    limit: ResourceLimit,
+    privileged: bool,
+) -> Result<ResourceLimit, ResourceLimitError> {
+    if resource >= RLIM_NLIMITS || limit.cur > limit.max {
+        return Err(ResourceLimitError::InvalidArgument);
+    }
+    let mut table = RESOURCE_LIMITS.lock();
+    let old = table[pid as usize][resource as usize];
+    if limit.max > old.max && !privileged {
+        return Err(ResourceLimitError::PermissionDenied);
+    }
+    table[pid as usize][resource as usize] = limit;
+    drop(table);
+
+    log::debug!(
+        "pid={} sets limit of resource {}: {:?} -> {:?}",
+        pid,
+        resource,
+        old,
+        limit
+    );
+    apply_limit(pid, resource);
+    Ok(old)
+}
+
+/// Gives a new process the limits of its parent, like after `fork()` on Linux. Called
+/// when the process starts.
+pub fn inherit_resource_limits(parent: ProcessId, child: ProcessId) {
+    let mut table = RESOURCE_LIMITS.lock();
+    table[child as usize] = table[parent as usize];
+    drop(table);
+    for resource in [RLIMIT_CPU, RLIMIT_FSIZE, RLIMIT_NOFILE] {
+        apply_limit(child, resource);
+    }
+}
+
+/// Checks the CPU time of the process against its [`RLIMIT_CPU`]. Returns false if there is
+/// nothing to check anymore, because the process exited or has no limit. Called by the
+/// main global EC of the roottask each second.
+pub fn check_cpu_limit(pid: ProcessId) -> bool {
+    let limit = RESOURCE_LIMITS.lock()[pid as usize][RLIMIT_CPU as usize];
+    if limit == ResourceLimit::UNLIMITED || exit_status(pid).is_some() {
+        return false;
+    }
+    let seconds = match cpu_time_us(pid) {
+        Some(time) => time / 1_000_000,
+        // the process doesn't run yet
+        None => return true,
+    };
+    if seconds >= limit.max {
+        kill_process(pid, SIGKILL);
+        false
+    } else {
+        if seconds >= limit.cur {
+            raise_signal(pid, SIGXCPU);
+        }
+        true
+    }
+}
+
+/// Lets the changed limit take effect outside of this module.
+fn apply_limit(pid: ProcessId, resource: u64) {
+    let limits = RESOURCE_LIMITS.lock()[pid as usize];
+    match resource {
+        RLIMIT_CPU => watch_cpu_limit(pid, limits[RLIMIT_CPU as usize] != ResourceLimit::UNLIMITED),
+        RLIMIT_FSIZE | RLIMIT_NOFILE => libfileserver::FILESYSTEM.lock().set_file_limits(
+            pid,
+            FileLimits {
+                max_open_files: limits[RLIMIT_NOFILE as usize].cur,
+                max_file_size: limits[RLIMIT_FSIZE as usize].cur,
+            },
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_limits() {
+        let stack = resource_limit(42, RLIMIT_STACK).unwrap();
+        assert_eq!(stack.cur, USER_STACK_SIZE as u64);
+        assert_eq!(
+            resource_limit(42, RLIMIT_DATA),
+            Some(ResourceLimit::UNLIMITED)
+        );
+        assert_eq!(resource_limit(42, RLIM_NLIMITS), None);
+
+        let limit = ResourceLimit {
+            cur: 4096,
+            max: 8192,
+        };
+        assert_eq!(
+            set_resource_limit(42, RLIM_NLIMITS, limit, true),
+            Err(ResourceLimitError::InvalidArgument)
+        );
+        assert_eq!(
+            set_resource_limit(42, RLIMIT_DATA, ResourceLimit { cur: 2, max: 1 }, true),
+            Err(ResourceLimitError::InvalidArgument)
+        );
+        assert_eq!(
+            set_resource_limit(42, RLIMIT_DATA, limit, false),
+            Ok(ResourceLimit::UNLIMITED)
+        );
+        assert_eq!(resource_limit(42, RLIMIT_DATA), Some(limit));
+
+        // raising the hard limit needs privileges, raising the soft limit doesn't
+        let raised = ResourceLimit {
+            cur: 8192,
+            max: 16384,
+        };
+        assert_eq!(
+            set_resource_limit(42, RLIMIT_DATA, raised, false),
+            Err(ResourceLimitError::PermissionDenied)
+        );
+        let soft = ResourceLimit {
+            cur: 8192,
+            max: 8192,
+        };
+        assert_eq!(set_resource_limit(42, RLIMIT_DATA, soft, false), Ok(limit));
+        assert_eq!(set_resource_limit(42, RLIMIT_DATA, raised, true), Ok(soft));
+    }
+}
@@ -0,0 +1,237 @@
+//! Scheduling parameters of processes. Each process has a single SC for its main global
+//! EC. Hedron can't change the parameters of an existing SC. Hence, the roottask revokes
+//! the SC and creates a new one with the new parameters.
+//!
+//! Like the signal targets, the parameters live in a global table of plain data, because
+//! portal handlers can't look up other processes while the process manager is locked. The
+//! same holds for the CPU of each process and its CPU time.
+//!
+//! Stopping a process (`SIGSTOP` and friends) works the same way: the roottask revokes
+//! the SC without creating a new one until the process continues.
+
+use crate::process::process_cap_sels;
+use libhrstd::cap_space::root::{
+    ProcessCapSels,
+    RootCapSpace,
+};
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::libhedron::syscall::{
+    sys_create_sc,
+    sys_pd_ctrl_delegate,
+    sys_revoke,
+    sys_sc_ctrl,
+    DelegateFlags,
+};
+use libhrstd::libhedron::{
+    CapSel,
+    CrdObjSC,
+    SCCapPermissions,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::rt::services::scheduling::{
+    SchedulingParams,
+    SchedulingServiceError,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Scheduling parameters of the SC of each process, indexed by PID. `None` for processes
+/// whose SC is not managed by the roottask, including the roottask itself.
+static SCHEDULING_PARAMS: SimpleMutex<[Option<SchedulingParams>; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([None; NUM_PROCESSES as usize]);
+
+/// CPU of the main global EC of each process, indexed by PID. `None` for processes that
+/// are not started yet or that exited.
+static PROCESS_CPUS: SimpleMutex<[Option<u64>; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([None; NUM_PROCESSES as usize]);
+
+/// CPU time in microseconds of the SCs that each process had before its current one,
+/// indexed by PID. See [`set_scheduling_params`].
+static RETIRED_CPU_TIME: SimpleMutex<[u64; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([0; NUM_PROCESSES as usize]);
+
+/// Processes whose SC is revoked by [`stop_process`], indexed by PID.
+static STOPPED: SimpleMutex<[bool; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([false; NUM_PROCESSES as usize]);
+
+/// Remembers the parameters of the SC of a new process. Called once when the SC gets
+/// created.
+pub fn register_scheduling_params(pid: ProcessId, params: SchedulingParams) {
+    SCHEDULING_PARAMS.lock()[pid as usize] = Some(params);
+}
+
+/// Forgets the parameters and the CPU time of the SC of a process, i.e. when the process
+/// exits. Afterwards, its parameters can't be changed anymore.
+pub fn unregister_scheduling_params(pid: ProcessId) {
+    SCHEDULING_PARAMS.lock()[pid as usize] = None;
+    RETIRED_CPU_TIME.lock()[pid as usize] = 0;
+    STOPPED.lock()[pid as usize] = false;
+}
+
+/// Returns the current scheduling parameters of the process. Can be called from every EC
+/// of the roottask.
+pub fn scheduling_params(pid: ProcessId) -> Option<SchedulingParams> {
+    SCHEDULING_PARAMS
+        .lock()
+        .get(pid as usize)
+        .copied()
+        .flatten()
+}
+
+/// Remembers the CPU of a new process. Called once when its global EC gets created.
+pub fn register_process_cpu(pid: ProcessId, cpu: u64) {
+    PROCESS_CPUS.lock()[pid as usize] = Some(cpu);
+}
+
+/// Forgets the CPU of a process, i.e. when the process exits.
+pub fn unregister_process_cpu(pid: ProcessId) {
+    PROCESS_CPUS.lock()[pid as usize] = None;
+}
+
+/// Returns the CPU of the process. Can be called from every EC of the roottask.
+pub fn process_cpu(pid: ProcessId) -> Option<u64> {
+    PROCESS_CPUS.lock().get(pid as usize).copied().flatten()
+}
+
+/// Returns the CPU time of the process in microseconds, i.e. the time that its SCs
+/// consumed. `None` for processes whose SC is not managed by the roottask or that exited.
+/// Can be called from every EC of the roottask.
+pub fn cpu_time_us(pid: ProcessId) -> Option<u64> {
+    scheduling_params(pid)?;
+    let retired = RETIRED_CPU_TIME.lock()[pid as usize];
+    if is_stopped(pid) {
+        return Some(retired);
+    }
+    let sc_time = sys_sc_ctrl(process_cap_sels(pid)?.sc()).ok()?;
+    Some(retired + sc_time)
+}
+
+/// Returns true if the process is stopped by [`stop_process`]. Can be called from every EC
+/// of the roottask.
+pub fn is_stopped(pid: ProcessId) -> bool {
+    STOPPED.lock().get(pid as usize).copied().unwrap_or(false)
+}
+
+/// Stops the process by revoking its SC. Its global EC doesn't run until
+/// [`continue_process`]. Returns false if the SC of the process is not managed by the
+/// roottask. Stopping a stopped process does nothing.
+pub fn stop_process(pid: ProcessId) -> bool {
+    let table = SCHEDULING_PARAMS.lock();
+    if table.get(pid as usize).copied().flatten().is_none() {
+        return false;
+    }
+    let mut stopped = STOPPED.lock();
+    if stopped[pid as usize] {
+        return true;
+    }
+    let cap_sels = match process_cap_sels(pid) {
+        Some(cap_sels) => cap_sels,
+        None => return false,
+    };
+    if let Err(e) = revoke_sc(pid, cap_sels.sc()) {
+        log::error!("can't stop pid={}: {:?}", pid, e);
+        return false;
+    }
+    stopped[pid as usize] = true;
+    log::debug!("stopped pid={}", pid);
+    true
+}
+
+/// Continues a process that was stopped by [`stop_process`] with a new SC with its current
+/// scheduling parameters. Returns false if the SC of the process is not managed by the
+/// roottask. Continuing a running process does nothing.
+pub fn continue_process(pid: ProcessId) -> bool {
+    let table = SCHEDULING_PARAMS.lock();
+    let params = match table.get(pid as usize).copied().flatten() {
+        Some(params) => params,
+        None => return false,
+    };
+    let mut stopped = STOPPED.lock();
+    if !stopped[pid as usize] {
+        return true;
+    }
+    let cap_sels = match process_cap_sels(pid) {
+        Some(cap_sels) => cap_sels,
+        None => return false,
+    };
+    if let Err(e) = install_sc(pid, cap_sels, params) {
+        log::error!("can't continue pid={}: {:?}", pid, e);
+        return false;
+    }
+    stopped[pid as usize] = false;
+    log::debug!("continued pid={}", pid);
+    true
+}
+
+/// Accounts the CPU time of the SC of the process and revokes the SC from the roottask and
+/// the process, which destroys it.
+fn revoke_sc(pid: ProcessId, sc_sel: CapSel) -> Result<(), SchedulingServiceError> {
+    // the CPU time of the process survives its SC
+    if let Ok(sc_time) = sys_sc_ctrl(sc_sel) {
+        RETIRED_CPU_TIME.lock()[pid as usize] += sc_time;
+    }
+    sys_revoke(CrdObjSC::new(sc_sel, 0, SCCapPermissions::empty()), true)
+        .map_err(|_| SchedulingServiceError::SyscallFailed)
+}
+
+/// Creates a new SC with the given parameters for the global EC of the process and
+/// installs it in the process at the well-known place.
+fn install_sc(
+    pid: ProcessId,
+    cap_sels: ProcessCapSels,
+    params: SchedulingParams,
+) -> Result<(), SchedulingServiceError> {
+    let root_pd_sel = RootCapSpace::RootPd.val();
+    let sc_sel = cap_sels.sc();
+    sys_create_sc(sc_sel, root_pd_sel, cap_sels.gl_ec(), params.qpd()).map_err(|e| {
+        log::error!("can't create new SC for pid={}: {:?}", pid, e);
+        SchedulingServiceError::SyscallFailed
+    })?;
+    sys_pd_ctrl_delegate(
+        root_pd_sel,
+        cap_sels.pd(),
+        CrdObjSC::new(sc_sel, 0, SCCapPermissions::empty()),
+        CrdObjSC::new(UserAppCapSpace::Sc.val(), 0, SCCapPermissions::empty()),
+        DelegateFlags::new(false, false, false, false, 0),
+    )
+    .unwrap();
+    Ok(())
+}
+
+/// Replaces the SC of the process by a new SC with the given parameters. The process
+/// continues to run with the new parameters once Hedron schedules the new SC. The new SC
+/// runs on the CPU of the global EC, i.e. the process keeps its CPU.
+///
+/// The [`libhrstd::kobjects::ScObject`] of the process still reports the initial
+/// parameters afterwards; this table is the source of truth.
+pub fn set_scheduling_params(
+    pid: ProcessId,
+    params: SchedulingParams,
+) -> Result<(), SchedulingServiceError> {
+    if !params.is_valid() {
+        return Err(SchedulingServiceError::InvalidParams);
+    }
+    let mut table = SCHEDULING_PARAMS.lock();
+    let entry = table
+        .get_mut(pid as usize)
+        .and_then(|entry| entry.as_mut())
+        .ok_or(SchedulingServiceError::NoSuchProcess)?;
+
+    // a stopped process gets an SC with the new parameters when it continues
+    if !is_stopped(pid) {
+        let cap_sels = process_cap_sels(pid).ok_or(SchedulingServiceError::NoSuchProcess)?;
+        revoke_sc(pid, cap_sels.sc())?;
+        install_sc(pid, cap_sels, params)?;
+    }
+
+    log::debug!(
+        "scheduling params of pid={}: {:?} -> {:?}",
+        pid,
+        entry,
+        params
+    );
+    *entry = params;
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! Module for POSIX signals of processes. Numbering and semantics follow Linux.
+//!
+//! The state of a process is split into two parts. The actions and the blocked mask live
+//! inside [`SignalState`] of the [`super::Process`]. The pending signals live in a global
+//! table of plain data, because signals can be raised from every EC of the roottask,
+//! including the main EC that handles timers and must not touch reference counted objects.
+//! The same applies to [`SignalTarget`], because portal handlers can't look up other
+//! processes while the process manager is locked.
+//!
+//! Stop and continue are implemented by revoking and recreating the SC of the process,
+//! see [`super::stop_process`].
+
+use super::{
+    continue_process,
+    kill_process,
+    stop_process,
+};
+use alloc::vec::Vec;
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Number of a signal. Valid signals are in range `1..=NSIG`.
+pub type SigNum = u64;
+
+/// Bitmap of signals. Bit `n - 1` stands for signal `n`.
+pub type SigSet = u64;
+
+/// Highest signal number (including real-time signals).
+pub const NSIG: SigNum = 64;
+
+pub const SIGHUP: SigNum = 1;
+pub const SIGINT: SigNum = 2;
+pub const SIGQUIT: SigNum = 3;
+pub const SIGILL: SigNum = 4;
+pub const SIGTRAP: SigNum = 5;
+pub const SIGABRT: SigNum = 6;
+pub const SIGBUS: SigNum = 7;
+pub const SIGFPE: SigNum = 8;
+pub const SIGKILL: SigNum = 9;
+pub const SIGUSR1: SigNum = 10;
+pub const SIGSEGV: SigNum = 11;
+pub const SIGUSR2: SigNum = 12;
+pub const SIGPIPE: SigNum = 13;
+pub const SIGALRM: SigNum = 14;
+pub const SIGTERM: SigNum = 15;
+pub const SIGCHLD: SigNum = 17;
+pub const SIGCONT: SigNum = 18;
+pub const SIGSTOP: SigNum = 19;
+pub const SIGTSTP: SigNum = 20;
+pub const SIGTTIN: SigNum = 21;
+pub const SIGTTOU: SigNum = 22;
+pub const SIGURG: SigNum = 23;
+pub const SIGXCPU: SigNum = 24;
+pub const SIGXFSZ: SigNum = 25;
+pub const SIGWINCH: SigNum = 28;
+
+/// `sa_handler` value for the default action.
+pub const SIG_DFL: u64 = 0;
+/// `sa_handler` value to ignore a signal.
+pub const SIG_IGN: u64 = 1;
+
+/// The handler gets three arguments (signal number, siginfo, ucontext).
+pub const SA_SIGINFO: u64 = 0x4;
+/// `sa_restorer` is valid. libc always sets this.
+pub const SA_RESTORER: u64 = 0x0400_0000;
+/// The handler should run on the alternate signal stack. Not supported yet.
+pub const SA_ONSTACK: u64 = 0x0800_0000;
+/// Don't block the signal while its handler runs.
+pub const SA_NODEFER: u64 = 0x4000_0000;
+/// Reset the action to [`SIG_DFL`] when the signal gets delivered.
+pub const SA_RESETHAND: u64 = 0x8000_0000;
+
+/// Pending signals of all processes, indexed by PID.
+static PENDING_SIGNALS: SimpleMutex<[SigSet; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([0; NUM_PROCESSES as usize]);
+
+/// Information about each process that is relevant for senders of signals, indexed by PID.
+static SIGNAL_TARGETS: SimpleMutex<[Option<SignalTarget>; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([None; NUM_PROCESSES as usize]);
+
+/// Describes a process as receiver of signals. See [`signal_target`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SignalTarget {
+    /// PID of the parent. The roottask and orphans, whose parent was stopped, have none.
+    pub parent: Option<ProcessId>,
+    /// Only processes with the Linux syscall ABI receive signals.
+    pub receives_signals: bool,
+}
+
+/// Returns the bit of the signal inside a [`SigSet`].
+pub const fn sig_bit(sig: SigNum) -> SigSet {
+    1 << (sig - 1)
+}
+
+/// Returns true if `sig` is a valid signal number.
+pub const fn sig_valid(sig: SigNum) -> bool {
+    sig >= 1 && sig <= NSIG
+}
+
+/// Marks the signal as pending for the given process. It gets delivered the next time
+/// the process enters the roottask and doesn't block the signal. Can be called from
+/// every EC of the roottask.
+pub fn raise_signal(pid: ProcessId, sig: SigNum) {
+    assert!(sig_valid(sig), "invalid signal {}", sig);
+    log::debug!("raise signal {} for pid={}", sig, pid);
+    PENDING_SIGNALS.lock()[pid as usize] |= sig_bit(sig);
+}
+
+/// Signals whose default action stops the process.
+const STOP_SIGNALS: SigSet =
+    sig_bit(SIGSTOP) | sig_bit(SIGTSTP) | sig_bit(SIGTTIN) | sig_bit(SIGTTOU);
+
+/// Sends the signal to the process. Other than [`raise_signal`], this also does what
+/// needs no help of the target: `SIGKILL` terminates it and `SIGSTOP` stops it right away,
+/// `SIGCONT` continues it and discards its pending stop signals. Processes that don't
+/// receive signals get the default action of the signal right away, without a core dump.
+///
+/// Returns false if the process doesn't exist or is the roottask, or if it can't be
+/// stopped or continued, because its SC is not managed by the roottask. Can be called from
+/// every EC of the roottask.
+pub fn post_signal(pid: ProcessId, sig: SigNum) -> bool {
+    let target = match signal_target(pid) {
+        Some(target) if pid != ROOTTASK_PROCESS_PID => target,
+        _ => return false,
+    };
+    match (sig, SigDefaultAction::of(sig)) {
+        (SIGKILL, _) => {
+            kill_process(pid, sig);
+            true
+        }
+        (SIGSTOP, _) => stop_process(pid),
+        (SIGCONT, _) => {
+            PENDING_SIGNALS.lock()[pid as usize] &= !STOP_SIGNALS;
+            if target.receives_signals {
+                // the handler of SIGCONT runs once the process continues
+                raise_signal(pid, sig);
+            }
+            continue_process(pid)
+        }
+        (_, _) if target.receives_signals => {
+            raise_signal(pid, sig);
+            true
+        }
+        (_, SigDefaultAction::Terminate | SigDefaultAction::Core) => {
+            kill_process(pid, sig);
+            true
+        }
+        (_, SigDefaultAction::Stop) => stop_process(pid),
+        (_, SigDefaultAction::Ignore | SigDefaultAction::Continue) => true,
+    }
+}
+
+/// Makes a process known as target of signals. Called once when the process gets created.
+pub fn register_signal_target(pid: ProcessId, target: SignalTarget) {
+    let mut targets = SIGNAL_TARGETS.lock();
+    assert!(
+        targets[pid as usize].is_none(),
+        "pid={} already registered",
+        pid
+    );
+    targets[pid as usize] = Some(target);
+    // a new process never inherits signals of a previous process with the same PID
+    PENDING_SIGNALS.lock()[pid as usize] = 0;
+}
+
+/// Forgets a process as target of signals, i.e. when its PID gets reused.
+pub fn unregister_signal_target(pid: ProcessId) {
+    SIGNAL_TARGETS.lock()[pid as usize] = None;
+}
+
+/// Makes the children of a stopped process orphans and returns their PIDs.
+pub fn orphan_children(parent: ProcessId) -> Vec<ProcessId> {
+    let mut orphans = Vec::new();
+    for (pid, target) in SIGNAL_TARGETS.lock().iter_mut().enumerate() {
+        if let Some(target) = target.as_mut().filter(|t| t.parent == Some(parent)) {
+            target.parent = None;
+            orphans.push(pid as ProcessId);
+        }
+    }
+    orphans
+}
+
+/// Returns information about the process with the given PID, if it exists.
+/// Can be called from every EC of the roottask.
+pub fn signal_target(pid: ProcessId) -> Option<SignalTarget> {
+    SIGNAL_TARGETS.lock().get(pid as usize).copied().flatten()
+}
+
+/// Returns the PIDs of all processes that receive signals.
+pub fn signal_receivers() -> impl Iterator<Item = ProcessId> {
+    let targets = *SIGNAL_TARGETS.lock();
+    (0..NUM_PROCESSES)
+        .filter(move |pid| targets[*pid as usize].map_or(false, |target| target.receives_signals))
+}
+
+/// Checks if the process has a pending signal that is not in `blocked`, without removing it.
+pub fn has_pending_signal(pid: ProcessId, blocked: SigSet) -> bool {
+    PENDING_SIGNALS.lock()[pid as usize] & !blocked != 0
+}
+
+/// Removes the lowest pending signal of the process that is not in `blocked` and returns it.
+pub fn take_pending_signal(pid: ProcessId, blocked: SigSet) -> Option<SigNum> {
+    let mut pending = PENDING_SIGNALS.lock();
+    let deliverable = pending[pid as usize] & !blocked;
+    if deliverable == 0 {
+        None
+    } else {
+        let sig = deliverable.trailing_zeros() as SigNum + 1;
+        pending[pid as usize] &= !sig_bit(sig);
+        Some(sig)
+    }
+}
+
+/// Action of a signal. Same layout as `struct kernel_sigaction` of Linux on x86_64,
+/// i.e. what libc passes to the `rt_sigaction` syscall.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[repr(C)]
+pub struct SigAction {
+    /// Address of the handler, [`SIG_DFL`], or [`SIG_IGN`].
+    pub handler: u64,
+    /// `SA_*` flags.
+    pub flags: u64,
+    /// Address of the function that performs the `rt_sigreturn` syscall.
+    pub restorer: u64,
+    /// Signals that are blocked additionally while the handler runs.
+    pub mask: SigSet,
+}
+
+/// What happens with a signal if its action is [`SIG_DFL`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SigDefaultAction {
+    Terminate,
+    /// Terminate and dump core.
+    Core,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+impl SigDefaultAction {
+    /// Returns the default action of a signal as described in `man 7 signal`.
+    pub fn of(sig: SigNum) -> Self {
+        match sig {
+            SIGCHLD | SIGURG | SIGWINCH => Self::Ignore,
+            SIGCONT => Self::Continue,
+            SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU => Self::Stop,
+            SIGQUIT | SIGILL | SIGTRAP | SIGABRT | SIGBUS | SIGFPE | SIGSEGV | SIGXCPU
+            | SIGXFSZ => Self::Core,
+            _ => Self::Terminate,
+        }
+    }
+}
+
+/// Signal state of a process that is owned by the process itself.
+#[derive(Debug)]
+pub struct SignalState {
+    actions: [SigAction; NSIG as usize],
+    blocked: SigSet,
+}
+
+impl SignalState {
+    /// Signals that can't be caught, ignored, or blocked.
+    pub const UNBLOCKABLE: SigSet = sig_bit(SIGKILL) | sig_bit(SIGSTOP);
+
+    pub fn new() -> Self {
+        Self {
+            actions: [SigAction::default(); NSIG as usize],
+            blocked: 0,
+        }
+    }
+
+    /// Returns the action of a valid signal.
+    pub fn action(&self, sig: SigNum) -> SigAction {
+        self.actions[sig as usize - 1]
+    }
+
+    /// Replaces the action of a valid signal and returns the old one.
+    pub fn set_action(&mut self, sig: SigNum, action: SigAction) -> SigAction {
+        core::mem::replace(&mut self.actions[sig as usize - 1], action)
+    }
+
+    /// Returns the set of blocked signals.
+    pub fn blocked(&self) -> SigSet {
+        self.blocked
+    }
+
+    /// Replaces the set of blocked signals. [`Self::UNBLOCKABLE`] signals are silently removed.
+    pub fn set_blocked(&mut self, blocked: SigSet) {
+        self.blocked = blocked & !Self::UNBLOCKABLE;
+    }
+}
+
+impl Default for SignalState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_state() {
+        let mut state = SignalState::new();
+        state.set_blocked(sig_bit(SIGKILL) | sig_bit(SIGALRM));
+        assert_eq!(state.blocked(), sig_bit(SIGALRM));
+
+        let action = SigAction {
+            handler: 0x1000,
+            flags: SA_RESTORER,
+            restorer: 0x2000,
+            mask: 0,
+        };
+        assert_eq!(state.set_action(SIGUSR1, action), SigAction::default());
+        assert_eq!(state.action(SIGUSR1), action);
+
+        raise_signal(7, SIGALRM);
+        raise_signal(7, SIGUSR2);
+        assert_eq!(take_pending_signal(7, state.blocked()), Some(SIGUSR2));
+        assert_eq!(take_pending_signal(7, state.blocked()), None);
+        assert_eq!(take_pending_signal(7, 0), Some(SIGALRM));
+
+        assert_eq!(signal_target(9), None);
+        assert_eq!(signal_target(NUM_PROCESSES + 1), None);
+        let target = SignalTarget {
+            parent: Some(0),
+            receives_signals: true,
+        };
+        register_signal_target(9, target);
+        assert_eq!(signal_target(9), Some(target));
+        assert!(signal_receivers().eq([9].into_iter()));
+        assert_eq!(orphan_children(0), [9]);
+        assert_eq!(signal_target(9).unwrap().parent, None);
+        unregister_signal_target(9);
+        assert_eq!(signal_target(9), None);
+
+        // SIGCONT discards pending stop signals; the roottask can't continue processes
+        // whose SC it doesn't manage
+        assert!(!post_signal(9, SIGUSR1));
+        register_signal_target(9, target);
+        assert!(post_signal(9, SIGUSR1));
+        raise_signal(9, SIGTSTP);
+        assert!(!post_signal(9, SIGCONT));
+        assert_eq!(take_pending_signal(9, 0), Some(SIGUSR1));
+        assert_eq!(take_pending_signal(9, 0), Some(SIGCONT));
+        assert_eq!(take_pending_signal(9, 0), None);
+        unregister_signal_target(9);
+        assert!(!post_signal(ROOTTASK_PROCESS_PID, SIGTERM));
+
+        assert_eq!(SigDefaultAction::of(SIGSEGV), SigDefaultAction::Core);
+        assert_eq!(SigDefaultAction::of(SIGCHLD), SigDefaultAction::Ignore);
+        assert_eq!(SigDefaultAction::of(SIGXFSZ), SigDefaultAction::Core);
+    }
+}
@@ -1,3 +1,24 @@
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use elf_rs::{
+    ElfAbi,
+    ElfFile,
+    ProgramType,
+    SectionType,
+};
+use libhrstd::process::elf_note::ElfNote;
+
+/// Owner of the notes of the GNU toolchain.
+const GNU_NOTE_NAME: &[u8] = b"GNU\0";
+/// Type of the note in `.note.ABI-tag` that names the OS of the program.
+const NT_GNU_ABI_TAG: u32 = 1;
+/// OS in the descriptor of a `NT_GNU_ABI_TAG` note.
+const ELF_NOTE_OS_LINUX: u32 = 0;
+/// `PT_GNU_STACK`. Linkers emit it for Linux targets, also for static musl binaries.
+const PT_GNU_STACK: u32 = 0x6474e551;
+/// Dynamic tags of the GNU extensions, such as `DT_GNU_HASH` and symbol versioning.
+const DT_GNU_TAGS: RangeInclusive<u64> = 0x6ffffef5..=0x6fffffff;
+
 /// Syscall ABI or OS Personality of a [`super::process::Process`].
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SyscallAbi {
@@ -13,6 +34,68 @@ impl SyscallAbi {
     pub fn is_foreign(self) -> bool {
         !self.is_native()
     }
+
+    /// Detects the syscall ABI of a program from its ELF file. Native Hedron apps carry
+    /// the note of [`libhrstd::process::elf_note`]. A program is a Linux program if
+    /// - the OSABI of the ELF header is Linux,
+    /// - it has a `.note.ABI-tag` with OS Linux,
+    /// - it has a program interpreter or dynamic tags of the GNU extensions, or
+    /// - it has a `PT_GNU_STACK` segment.
+    ///
+    /// Returns `None` if there is no hint. Then, the caller must decide.
+    pub fn detect(elf_bytes: &[u8]) -> Option<Self> {
+        let elf = elf_rs::Elf::from_bytes(elf_bytes).ok()?;
+        let segments = elf.program_header_iter().collect::<Vec<_>>();
+        let note_contents = segments
+            .iter()
+            .filter(|ph| ph.ph_type() == ProgramType::NOTE)
+            .map(|ph| ph.content())
+            .chain(
+                elf.section_header_iter()
+                    .filter(|sh| sh.sh_type() == SectionType::SHT_NOTE)
+                    .map(|sh| sh.content()),
+            )
+            .collect::<Vec<_>>();
+        let notes = note_contents
+            .iter()
+            .flat_map(|content| ElfNote::parse_all(content))
+            .collect::<Vec<_>>();
+
+        if notes.iter().any(|note| note.is_hedron_abi_note()) {
+            log::debug!("detected native Hedron app: Hedron ABI note");
+            return Some(Self::NativeHedron);
+        }
+
+        let reason = if elf.elf_header().abi() == ElfAbi::Linux {
+            "OSABI"
+        } else if notes.iter().any(|note| {
+            note.name == GNU_NOTE_NAME
+                && note.n_type == NT_GNU_ABI_TAG
+                && note.desc.get(0..4) == Some(&ELF_NOTE_OS_LINUX.to_ne_bytes())
+        }) {
+            "GNU ABI tag note"
+        } else if segments
+            .iter()
+            .any(|ph| ph.ph_type() == ProgramType::INTERP)
+        {
+            "program interpreter"
+        } else if segments
+            .iter()
+            .filter(|ph| ph.ph_type() == ProgramType::DYNAMIC)
+            .any(|ph| has_gnu_dynamic_tags(ph.content()))
+        {
+            "GNU dynamic tags"
+        } else if segments
+            .iter()
+            .any(|ph| ph.ph_type() == ProgramType::OsSpecific(PT_GNU_STACK))
+        {
+            "PT_GNU_STACK"
+        } else {
+            return None;
+        };
+        log::debug!("detected Linux program: {}", reason);
+        Some(Self::Linux)
+    }
 }
 
 impl Default for SyscallAbi {
@@ -20,3 +103,115 @@ impl Default for SyscallAbi {
         Self::NativeHedron
     }
 }
+
+/// Checks the entries (`struct Elf64_Dyn`) of a dynamic segment for GNU specific tags.
+fn has_gnu_dynamic_tags(dynamic: &[u8]) -> bool {
+    dynamic
+        .chunks_exact(2 * core::mem::size_of::<u64>())
+        .map(|entry| u64::from_ne_bytes(entry[..8].try_into().unwrap()))
+        .any(|tag| DT_GNU_TAGS.contains(&tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::size_of;
+    use libhrstd::process::elf_note::HedronAbiNote;
+    use std::vec::Vec;
+
+    const ELFOSABI_SYSV: u8 = 0;
+    const ELFOSABI_LINUX: u8 = 3;
+    const PT_INTERP: u32 = 3;
+    const PT_NOTE: u32 = 4;
+
+    /// Creates a minimal ELF64 file with the given program headers and their content.
+    fn create_elf(osabi: u8, segments: &[(u32, &[u8])]) -> Vec<u8> {
+        const EHDR_SIZE: usize = 64;
+        const PHDR_SIZE: usize = 56;
+        let mut elf = Vec::new();
+        elf.extend_from_slice(b"\x7fELF");
+        elf.extend_from_slice(&[2, 1, 1, osabi]);
+        elf.resize(16, 0);
+        elf.extend_from_slice(&2_u16.to_ne_bytes()); // e_type: ET_EXEC
+        elf.extend_from_slice(&0x3e_u16.to_ne_bytes()); // e_machine: x86_64
+        elf.extend_from_slice(&1_u32.to_ne_bytes()); // e_version
+        elf.extend_from_slice(&0x400000_u64.to_ne_bytes()); // e_entry
+        elf.extend_from_slice(&(EHDR_SIZE as u64).to_ne_bytes()); // e_phoff
+        elf.extend_from_slice(&0_u64.to_ne_bytes()); // e_shoff
+        elf.extend_from_slice(&0_u32.to_ne_bytes()); // e_flags
+        elf.extend_from_slice(&(EHDR_SIZE as u16).to_ne_bytes());
+        elf.extend_from_slice(&(PHDR_SIZE as u16).to_ne_bytes());
+        elf.extend_from_slice(&(segments.len() as u16).to_ne_bytes());
+        elf.extend_from_slice(&[0; 6]); // no section headers
+        assert_eq!(elf.len(), EHDR_SIZE);
+
+        let mut content_offset = EHDR_SIZE + segments.len() * PHDR_SIZE;
+        for (p_type, content) in segments {
+            elf.extend_from_slice(&p_type.to_ne_bytes());
+            elf.extend_from_slice(&4_u32.to_ne_bytes()); // p_flags
+            elf.extend_from_slice(&(content_offset as u64).to_ne_bytes());
+            elf.extend_from_slice(&[0; 16]); // p_vaddr, p_paddr
+            elf.extend_from_slice(&(content.len() as u64).to_ne_bytes());
+            elf.extend_from_slice(&(content.len() as u64).to_ne_bytes());
+            elf.extend_from_slice(&4_u64.to_ne_bytes());
+            content_offset += content.len();
+        }
+        segments
+            .iter()
+            .for_each(|(_, content)| elf.extend_from_slice(content));
+        elf
+    }
+
+    #[test]
+    fn test_detect() {
+        let note = HedronAbiNote::new();
+        let hedron_note = unsafe {
+            core::slice::from_raw_parts(
+                (&note as *const HedronAbiNote).cast::<u8>(),
+                size_of::<HedronAbiNote>(),
+            )
+        };
+        let mut abi_tag_note = Vec::new();
+        for word in [
+            4_u32,
+            16,
+            NT_GNU_ABI_TAG,
+            0x00554e47,
+            ELF_NOTE_OS_LINUX,
+            3,
+            2,
+            0,
+        ] {
+            abi_tag_note.extend_from_slice(&word.to_ne_bytes());
+        }
+        let mut gnu_dynamic = Vec::new();
+        for word in [0x6ffffef5_u64, 0x1000, 0, 0] {
+            gnu_dynamic.extend_from_slice(&word.to_ne_bytes());
+        }
+
+        let native = create_elf(ELFOSABI_SYSV, &[(PT_NOTE, hedron_note)]);
+        assert_eq!(SyscallAbi::detect(&native), Some(SyscallAbi::NativeHedron));
+        let native = create_elf(
+            ELFOSABI_SYSV,
+            &[(PT_GNU_STACK, &[]), (PT_NOTE, hedron_note)],
+        );
+        assert_eq!(
+            SyscallAbi::detect(&native),
+            Some(SyscallAbi::NativeHedron),
+            "the Hedron note has priority"
+        );
+
+        for linux in [
+            create_elf(ELFOSABI_LINUX, &[]),
+            create_elf(ELFOSABI_SYSV, &[(PT_NOTE, &abi_tag_note)]),
+            create_elf(ELFOSABI_SYSV, &[(PT_INTERP, b"/lib/ld-musl-x86_64.so.1\0")]),
+            create_elf(ELFOSABI_SYSV, &[(2, &gnu_dynamic)]),
+            create_elf(ELFOSABI_SYSV, &[(PT_GNU_STACK, &[])]),
+        ] {
+            assert_eq!(SyscallAbi::detect(&linux), Some(SyscallAbi::Linux));
+        }
+
+        assert_eq!(SyscallAbi::detect(&create_elf(ELFOSABI_SYSV, &[])), None);
+        assert_eq!(SyscallAbi::detect(b"no elf"), None);
+    }
+}
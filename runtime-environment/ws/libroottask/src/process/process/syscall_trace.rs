@@ -0,0 +1,154 @@
+//! Syscall traces of Linux processes, like `strace`. If tracing is enabled for a process,
+//! the foreign syscall handler records each Linux syscall with its decoded arguments and
+//! its return value, see [`record_syscall`]. The last [`SYSCALL_TRACE_CAPACITY`] syscalls
+//! show up in `/proc/<pid>/trace`, see [`crate::rt::procfs`].
+//!
+//! Tracing is enabled when the process is launched or later via the process service. See
+//! [`libhrstd::rt::services::process::ProcessServiceRequest::SetSyscallTrace`]. The trace
+//! of a process outlives the process, so that short-lived programs can be traced, too;
+//! PIDs aren't reused.
+//!
+//! Like the command names, the traces live in a global table, because the file system
+//! can't look up processes while the process manager is locked.
+
+use alloc::collections::{
+    BTreeMap,
+    VecDeque,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Number of syscalls that the trace of a process keeps. Older ones are dropped.
+pub const SYSCALL_TRACE_CAPACITY: usize = 256;
+
+/// Whether the syscalls of a process are traced, by PID. Checked for every syscall, hence
+/// not part of [`TRACES`].
+static TRACED: [AtomicBool; NUM_PROCESSES as usize] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const NOT_TRACED: AtomicBool = AtomicBool::new(false);
+    [NOT_TRACED; NUM_PROCESSES as usize]
+};
+
+/// The trace of each process that was traced at some point.
+static TRACES: SimpleMutex<BTreeMap<ProcessId, SyscallTrace>> = SimpleMutex::new(BTreeMap::new());
+
+/// The last syscalls of a process, one line each.
+#[derive(Debug, Default)]
+struct SyscallTrace {
+    lines: VecDeque<String>,
+    /// Number of lines that were dropped, because the trace was full.
+    dropped: u64,
+}
+
+impl SyscallTrace {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == SYSCALL_TRACE_CAPACITY {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    fn render(&self) -> Vec<u8> {
+        let mut text = String::new();
+        if self.dropped > 0 {
+            text.push_str(&format!("... {} earlier syscalls dropped\n", self.dropped));
+        }
+        for line in &self.lines {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.into_bytes()
+    }
+}
+
+/// Enables or disables the tracing of the syscalls of a process. The recorded syscalls
+/// stay when tracing is disabled.
+pub fn set_syscall_trace(pid: ProcessId, enabled: bool) {
+    log::debug!("syscall trace of pid={}: {}", pid, enabled);
+    if enabled {
+        TRACES.lock().entry(pid).or_default();
+    }
+    TRACED[pid as usize].store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if the syscalls of the process are traced.
+pub fn is_syscall_traced(pid: ProcessId) -> bool {
+    TRACED
+        .get(pid as usize)
+        .map_or(false, |traced| traced.load(Ordering::Relaxed))
+}
+
+/// Appends a syscall to the trace of the process, if the process is traced. `line` is the
+/// decoded syscall, e.g. `close(3) = 0`.
+pub fn record_syscall(pid: ProcessId, line: String) {
+    if is_syscall_traced(pid) {
+        if let Some(trace) = TRACES.lock().get_mut(&pid) {
+            trace.push(line);
+        }
+    }
+}
+
+/// Returns true if the process was traced at some point, i.e. it has a trace.
+pub fn has_syscall_trace(pid: ProcessId) -> bool {
+    TRACES.lock().contains_key(&pid)
+}
+
+/// Drops the trace of the process, i.e. when its PID gets reused. Returns true if there
+/// was one.
+pub fn forget_syscall_trace(pid: ProcessId) -> bool {
+    TRACED[pid as usize].store(false, Ordering::Relaxed);
+    TRACES.lock().remove(&pid).is_some()
+}
+
+/// Returns the trace of the process as text or `None` if it was never traced. Can be
+/// called from every EC of the roottask.
+pub fn syscall_trace(pid: ProcessId) -> Option<Vec<u8>> {
+    TRACES.lock().get(&pid).map(SyscallTrace::render)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syscall_trace() {
+        let pid = 33;
+        assert!(!has_syscall_trace(pid));
+        record_syscall(pid, String::from("close(3) = 0"));
+        assert!(syscall_trace(pid).is_none());
+
+        set_syscall_trace(pid, true);
+        assert!(is_syscall_traced(pid) && has_syscall_trace(pid));
+        record_syscall(pid, String::from("close(3) = 0"));
+        assert_eq!(syscall_trace(pid).unwrap(), b"close(3) = 0\n");
+
+        // the trace stays readable
+        set_syscall_trace(pid, false);
+        record_syscall(pid, String::from("close(4) = 0"));
+        assert_eq!(syscall_trace(pid).unwrap(), b"close(3) = 0\n");
+        assert!(!is_syscall_traced(NUM_PROCESSES));
+    }
+
+    #[test]
+    fn test_syscall_trace_capacity() {
+        let mut trace = SyscallTrace::default();
+        for fd in 0..SYSCALL_TRACE_CAPACITY + 2 {
+            trace.push(format!("close({}) = 0", fd));
+        }
+        let text = String::from_utf8(trace.render()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("... 2 earlier syscalls dropped"));
+        assert_eq!(lines.next(), Some("close(2) = 0"));
+        assert_eq!(lines.count(), SYSCALL_TRACE_CAPACITY - 1);
+    }
+}
@@ -2,13 +2,36 @@
 
 use crate::process::Process;
 use crate::process::PROCESS_MNG;
+use crate::rate_limit::RateLimitVerdict;
+use crate::services::foreign_syscall::sleep_until;
+use crate::{
+    gdb_stub,
+    rate_limit,
+    service_stats,
+    time,
+};
+use alloc::collections::BTreeMap;
 use alloc::rc::Rc;
+use core::mem::size_of;
 use libhrstd::kobjects::{
     PortalIdentifier,
     PtObject,
 };
 use libhrstd::libhedron::syscall::sys_reply;
+use libhrstd::libhedron::CapSel;
 use libhrstd::libhedron::Utcb;
+#[cfg(debug_assertions)]
+use libhrstd::libhedron::{
+    Mtd,
+    UtcbSnapshot,
+};
+use libhrstd::sync::lock_stats;
+use libhrstd::sync::lock_stats::LOCK_STATS;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// TSC values until which the local ECs wait before they reply to the current call, see
+/// [`delay_reply`]. Key is the capability selector of the local EC.
+static REPLY_DELAYS: SimpleMutex<BTreeMap<CapSel, u64>> = SimpleMutex::new(BTreeMap::new());
 
 /// Describes a function, that handles a specific portal call.
 /// # Parameters
@@ -25,6 +48,7 @@ pub fn roottask_generic_portal_callback(id: PortalIdentifier) -> ! {
     // log::trace!("generic portal callback called with argument: {}", id);
 
     let stack_top;
+    let local_ec_sel;
     let mut do_reply = false;
 
     // drop lock before reply()!
@@ -50,6 +74,7 @@ pub fn roottask_generic_portal_callback(id: PortalIdentifier) -> ! {
 
         // stack_top of the local EC that handles the call. Important for reply() syscall
         stack_top = pt.stack_top();
+        local_ec_sel = pt.local_ec().ec_sel();
         // +++++++++++++++++++++++++++++++++++
         // here goes portal-specific handling
 
@@ -63,12 +88,44 @@ pub fn roottask_generic_portal_callback(id: PortalIdentifier) -> ! {
             panic!("no portal callback handler known for given PT ctx");
         };
 
-        cb(
-            &pt,
-            &calling_process,
-            pt.local_ec().utcb_mut(),
-            &mut do_reply,
-        );
+        // exception state at the entry; see `debug_check_exception_state`
+        #[cfg(debug_assertions)]
+        let entry_snapshot = (!pt.ctx().is_service_pt()).then(|| pt.local_ec().utcb().snapshot());
+
+        let verdict = if pt.ctx().is_service_pt() {
+            rate_limit::admit(calling_process.pid(), pt.ctx().service_id())
+        } else {
+            RateLimitVerdict::Admit
+        };
+        if let RateLimitVerdict::Delay(ns) = verdict {
+            delay_reply(&pt, time::tsc_now().saturating_add(time::ns_to_ticks(ns)));
+        }
+        if verdict != RateLimitVerdict::Deny {
+            let begin = time::tsc_now();
+            let request_bytes = pt.local_ec().utcb().untyped_items().len() * size_of::<u64>();
+            cb(
+                &pt,
+                &calling_process,
+                pt.local_ec().utcb_mut(),
+                &mut do_reply,
+            );
+            let duration_ticks = time::tsc_now() - begin;
+            if pt.ctx().is_service_pt() {
+                service_stats::record(pt.ctx().service_id(), request_bytes as u64, duration_ticks);
+            }
+            if duration_ticks > lock_stats::WATCHDOG_TICKS {
+                watchdog_timeout(&pt, &calling_process, duration_ticks);
+            }
+        } else {
+            // denied by the rate limiter; reply without data
+            pt.local_ec().utcb_mut().clear_data();
+            do_reply = true;
+        }
+
+        #[cfg(debug_assertions)]
+        if let Some(entry_snapshot) = entry_snapshot.filter(|_| do_reply) {
+            debug_check_exception_state(&entry_snapshot, pt.local_ec().utcb());
+        }
 
         // log::debug!("specialized PT handler done");
         // +++++++++++++++++++++++++++++++++++
@@ -76,6 +133,14 @@ pub fn roottask_generic_portal_callback(id: PortalIdentifier) -> ! {
 
     // important that all locks are dropped now!
 
+    // not inside the `if let`; the lock would be held during the sleep
+    let reply_delay = REPLY_DELAYS.lock().remove(&local_ec_sel);
+    if let Some(tsc_deadline) = reply_delay {
+        sleep_until(tsc_deadline);
+    }
+    // a debugged process stays stopped until GDB continues it
+    gdb_stub::run_deferred_stop(local_ec_sel);
+
     // not a convenient method in the PtObj itself, because the lock needs to be relased first!
     if do_reply {
         // log::debug!("reply now!");
@@ -85,3 +150,47 @@ pub fn roottask_generic_portal_callback(id: PortalIdentifier) -> ! {
         panic!("panic without reply, end of game");
     }
 }
+
+/// Lets the local EC of the portal wait until the TSC reaches `tsc_deadline` before it
+/// replies to the current call. Handlers use this instead of waiting themselves: the wait
+/// happens after [`PROCESS_MNG`] was released, hence calls on the local ECs of other CPUs
+/// proceed in the meantime. Calls to the same local EC still queue up until the reply.
+pub fn delay_reply(pt: &PtObject, tsc_deadline: u64) {
+    REPLY_DELAYS
+        .lock()
+        .insert(pt.local_ec().ec_sel(), tsc_deadline);
+}
+
+/// Reports a portal call whose handler ran longer than [`lock_stats::WATCHDOG_TICKS`]. All
+/// other calls waited for [`PROCESS_MNG`] in the meantime, hence this dumps the statistics
+/// of the locks, which show where the time went.
+fn watchdog_timeout(pt: &PtObject, process: &Process, duration_ticks: u64) {
+    log::warn!(
+        "watchdog: portal call {:?} of process {} took {} ticks",
+        pt.ctx(),
+        process.pid(),
+        duration_ticks
+    );
+    if lock_stats::ENABLED {
+        LOCK_STATS.log_report();
+    }
+}
+
+/// Detects handlers of exceptions and foreign system calls that modify the exception state
+/// unintentionally, for example by a nested portal call without [`Utcb::save`]. Handlers
+/// must announce each group of registers that they change in the MTD of the reply.
+#[cfg(debug_assertions)]
+fn debug_check_exception_state(entry_snapshot: &UtcbSnapshot, utcb: &Utcb) {
+    let utcb_exc = utcb.exception_data();
+    assert!(
+        Mtd::from_bits(utcb_exc.mtd.bits()).is_some(),
+        "the MTD of the reply is corrupted: {:#x}",
+        utcb_exc.mtd.bits()
+    );
+    let unannounced = utcb_exc.changed_registers(entry_snapshot.exception_data()) - utcb_exc.mtd;
+    assert!(
+        unannounced.is_empty(),
+        "the handler changed registers that are not part of the MTD of the reply: {:?}",
+        unannounced
+    );
+}
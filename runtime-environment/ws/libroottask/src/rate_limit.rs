@@ -0,0 +1,420 @@
+//! Rate limiting of service calls. The roottask handles one service call at a time.
+//! Hence, a process that calls a service in a tight loop slows down the services for all
+//! other processes.
+//!
+//! The [`crate::pt_multiplex`] admits each service call via [`admit`]. Each process has
+//! a token bucket per service. A call consumes one token; tokens refill at a constant
+//! rate up to the burst size. Calls without tokens get a [`RateLimitPenalty`]. The boot
+//! argument `rate_limit` replaces the limits of a service, see [`configure_from_arg`].
+
+use crate::time;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use enum_iterator::IntoEnumIterator;
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+static RATE_LIMITER: SimpleMutex<RateLimiter> = SimpleMutex::new(RateLimiter::new());
+
+/// What happens to a call that exceeds the rate limit.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitPenalty {
+    /// The call gets handled as usual; the violation is only logged.
+    LogOnly,
+    /// The call gets handled as usual, but the reply is delayed by the given number of
+    /// nanoseconds, see [`crate::pt_multiplex::delay_reply`]. The caller donates its
+    /// scheduling context to the portal call, hence the caller pays for the delay.
+    Delay { ns: u64 },
+    /// The roottask replies without handling the call. The reply carries no data, i.e.
+    /// the caller sees [`libhrstd::rt::services::rpc::RpcError::Denied`].
+    Deny,
+}
+
+/// Thresholds of the rate limit of a service.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Number of calls that are admitted in a burst. The capacity of the bucket.
+    pub burst: u64,
+    /// Number of calls per second that are admitted in the long run. The refill rate of
+    /// the bucket.
+    pub calls_per_sec: u64,
+    pub penalty: RateLimitPenalty,
+}
+
+impl RateLimitConfig {
+    /// Generous limits that only throttle processes that call a service in a loop.
+    pub const DEFAULT: Self = Self {
+        burst: 1000,
+        calls_per_sec: 10000,
+        penalty: RateLimitPenalty::Delay { ns: 100_000 },
+    };
+}
+
+/// Verdict of the rate limiter for a single call.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitVerdict {
+    Admit,
+    /// Handle the call but delay the reply by the given number of nanoseconds.
+    Delay(u64),
+    Deny,
+}
+
+/// Replaces the rate limit of a service for all processes. `None` disables rate limiting
+/// for the service.
+pub fn configure(service: ServiceId, config: Option<RateLimitConfig>) {
+    RATE_LIMITER.lock().configure(service, config)
+}
+
+/// Applies the value of the boot argument `rate_limit`, i.e. `<service>:off` or
+/// `<service>:<calls per second>:<burst>:<penalty>`, where the service is the name of a
+/// [`ServiceId`] and the penalty is `log`, `deny`, or a delay like `100us`. Returns false
+/// if the value is invalid.
+pub fn configure_from_arg(value: &str) -> bool {
+    match parse_config(value) {
+        Some((service, config)) => {
+            log::info!("rate limit of {:?}: {:?}", service, config);
+            configure(service, config);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Checks if the service call of the process is within the limits and returns the
+/// penalty otherwise. Calls of the roottask itself are always admitted.
+pub fn admit(pid: ProcessId, service: ServiceId) -> RateLimitVerdict {
+    if pid == ROOTTASK_PROCESS_PID {
+        return RateLimitVerdict::Admit;
+    }
+    let ticks_per_sec = time::tsc_freq_khz() * 1000;
+    RATE_LIMITER
+        .lock()
+        .check(pid, service, time::tsc_now(), ticks_per_sec)
+}
+
+/// Drops the token buckets of an exited process.
+pub fn release_process(pid: ProcessId) {
+    RATE_LIMITER.lock().release_process(pid);
+}
+
+/// Parses the value of the boot argument, see [`configure_from_arg`].
+fn parse_config(value: &str) -> Option<(ServiceId, Option<RateLimitConfig>)> {
+    let (name, limits) = value.split_once(':')?;
+    let service = ServiceId::into_enum_iter().find(|service| format!("{:?}", service) == name)?;
+    if limits == "off" {
+        return Some((service, None));
+    }
+    let mut limits = limits.split(':');
+    let calls_per_sec = limits.next()?.parse().ok()?;
+    let burst = limits.next()?.parse().ok()?;
+    let penalty = match limits.next()? {
+        "log" => RateLimitPenalty::LogOnly,
+        "deny" => RateLimitPenalty::Deny,
+        delay => {
+            let us = delay.strip_suffix("us")?.parse::<u64>().ok()?;
+            RateLimitPenalty::Delay {
+                ns: us.checked_mul(1000)?,
+            }
+        }
+    };
+    if limits.next().is_some() || calls_per_sec == 0 || burst == 0 {
+        return None;
+    }
+    let config = RateLimitConfig {
+        burst,
+        calls_per_sec,
+        penalty,
+    };
+    Some((service, Some(config)))
+}
+
+/// Limits that apply if no other limits were configured via [`configure`].
+fn default_config(service: ServiceId) -> Option<RateLimitConfig> {
+    match service {
+        // the benchmarks call them in tight loops on purpose
+        ServiceId::EchoService | ServiceId::RawEchoService | ServiceId::FileSystemService => None,
+        _ => Some(RateLimitConfig::DEFAULT),
+    }
+}
+
+/// Token bucket of a process for a single service.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: u64,
+    /// TSC value of the last refill.
+    last_refill: u64,
+    /// Number of calls that exceeded the limit since the bucket ran empty.
+    limited_calls: u64,
+}
+
+impl TokenBucket {
+    const fn new(config: &RateLimitConfig, now: u64) -> Self {
+        Self {
+            tokens: config.burst,
+            last_refill: now,
+            limited_calls: 0,
+        }
+    }
+
+    /// Adds the tokens for the time since the last refill. Keeps the fraction of a
+    /// token for the next refill.
+    fn refill(&mut self, config: &RateLimitConfig, now: u64, ticks_per_sec: u64) {
+        let elapsed = now.saturating_sub(self.last_refill) as u128;
+        let new_tokens = elapsed * config.calls_per_sec as u128 / ticks_per_sec as u128;
+        if new_tokens == 0 {
+            return;
+        }
+        if self.tokens + new_tokens as u64 >= config.burst {
+            self.tokens = config.burst;
+            self.last_refill = now;
+        } else {
+            self.tokens += new_tokens as u64;
+            self.last_refill +=
+                (new_tokens * ticks_per_sec as u128 / config.calls_per_sec as u128) as u64;
+        }
+    }
+
+    /// Takes a token. Returns `false` if the bucket is empty.
+    fn take(&mut self) -> bool {
+        if self.tokens == 0 {
+            return false;
+        }
+        self.tokens -= 1;
+        true
+    }
+}
+
+#[derive(Debug)]
+struct RateLimiter {
+    /// Configured limits that replace the [`default_config`]. Key is the ID of the service.
+    configs: BTreeMap<u64, Option<RateLimitConfig>>,
+    /// Key is the process and the ID of the service.
+    buckets: BTreeMap<(ProcessId, u64), TokenBucket>,
+}
+
+impl RateLimiter {
+    const fn new() -> Self {
+        Self {
+            configs: BTreeMap::new(),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    fn configure(&mut self, service: ServiceId, config: Option<RateLimitConfig>) {
+        self.configs.insert(service.val(), config);
+        // the buckets may be larger than the new burst size
+        self.buckets.retain(|(_, id), _| *id != service.val());
+    }
+
+    fn release_process(&mut self, pid: ProcessId) {
+        self.buckets.retain(|(bucket_pid, _), _| *bucket_pid != pid);
+    }
+
+    fn config(&self, service: ServiceId) -> Option<RateLimitConfig> {
+        self.configs
+            .get(&service.val())
+            .copied()
+            .unwrap_or_else(|| default_config(service))
+    }
+
+    fn check(
+        &mut self,
+        pid: ProcessId,
+        service: ServiceId,
+        now: u64,
+        ticks_per_sec: u64,
+    ) -> RateLimitVerdict {
+        let config = match self.config(service) {
+            Some(config) if config.calls_per_sec > 0 => config,
+            _ => return RateLimitVerdict::Admit,
+        };
+        let bucket = self
+            .buckets
+            .entry((pid, service.val()))
+            .or_insert_with(|| TokenBucket::new(&config, now));
+        bucket.refill(&config, now, ticks_per_sec);
+
+        if bucket.take() {
+            if bucket.limited_calls > 0 {
+                log::info!(
+                    "process {} is within the rate limit of {:?} again ({} calls were limited)",
+                    pid,
+                    service,
+                    bucket.limited_calls
+                );
+                bucket.limited_calls = 0;
+            }
+            return RateLimitVerdict::Admit;
+        }
+
+        if bucket.limited_calls == 0 {
+            log::warn!(
+                "process {} exceeds the rate limit of {:?} ({} calls/s, burst {}); penalty: {:?}",
+                pid,
+                service,
+                config.calls_per_sec,
+                config.burst,
+                config.penalty
+            );
+        }
+        bucket.limited_calls = bucket.limited_calls.saturating_add(1);
+        match config.penalty {
+            RateLimitPenalty::LogOnly => RateLimitVerdict::Admit,
+            RateLimitPenalty::Delay { ns } => RateLimitVerdict::Delay(ns),
+            RateLimitPenalty::Deny => RateLimitVerdict::Deny,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 GHz: one tick per nanosecond.
+    const TICKS_PER_SEC: u64 = 1_000_000_000;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut limiter = RateLimiter::new();
+        let config = RateLimitConfig {
+            burst: 3,
+            calls_per_sec: 1000,
+            penalty: RateLimitPenalty::Deny,
+        };
+        limiter.configure(ServiceId::TimerService, Some(config));
+
+        // burst
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.check(1, ServiceId::TimerService, 0, TICKS_PER_SEC),
+                RateLimitVerdict::Admit
+            );
+        }
+        assert_eq!(
+            limiter.check(1, ServiceId::TimerService, 0, TICKS_PER_SEC),
+            RateLimitVerdict::Deny
+        );
+        // other processes and services have their own buckets
+        assert_eq!(
+            limiter.check(2, ServiceId::TimerService, 0, TICKS_PER_SEC),
+            RateLimitVerdict::Admit
+        );
+        assert_eq!(
+            limiter.check(1, ServiceId::StdoutService, 0, TICKS_PER_SEC),
+            RateLimitVerdict::Admit
+        );
+
+        // one token per millisecond; the fraction of a token is kept
+        assert_eq!(
+            limiter.check(1, ServiceId::TimerService, 1_500_000, TICKS_PER_SEC),
+            RateLimitVerdict::Admit
+        );
+        assert_eq!(
+            limiter.check(1, ServiceId::TimerService, 1_500_000, TICKS_PER_SEC),
+            RateLimitVerdict::Deny
+        );
+        assert_eq!(
+            limiter.check(1, ServiceId::TimerService, 2_000_000, TICKS_PER_SEC),
+            RateLimitVerdict::Admit
+        );
+
+        // the bucket never holds more than the burst size
+        for _ in 0..3 {
+            assert_eq!(
+                limiter.check(1, ServiceId::TimerService, 1_000_000_000, TICKS_PER_SEC),
+                RateLimitVerdict::Admit
+            );
+        }
+        assert_eq!(
+            limiter.check(1, ServiceId::TimerService, 1_000_000_000, TICKS_PER_SEC),
+            RateLimitVerdict::Deny
+        );
+    }
+
+    #[test]
+    fn test_penalties_and_defaults() {
+        let mut limiter = RateLimiter::new();
+        for _ in 0..1_000_000 {
+            assert_eq!(
+                limiter.check(1, ServiceId::EchoService, 0, TICKS_PER_SEC),
+                RateLimitVerdict::Admit,
+                "the echo service is not limited by default"
+            );
+        }
+
+        limiter.configure(
+            ServiceId::EchoService,
+            Some(RateLimitConfig {
+                burst: 1,
+                calls_per_sec: 1,
+                penalty: RateLimitPenalty::Delay { ns: 42 },
+            }),
+        );
+        assert_eq!(
+            limiter.check(1, ServiceId::EchoService, 0, TICKS_PER_SEC),
+            RateLimitVerdict::Admit
+        );
+        assert_eq!(
+            limiter.check(1, ServiceId::EchoService, 0, TICKS_PER_SEC),
+            RateLimitVerdict::Delay(42)
+        );
+
+        limiter.configure(ServiceId::StdoutService, None);
+        for _ in 0..2 * RateLimitConfig::DEFAULT.burst {
+            assert_eq!(
+                limiter.check(1, ServiceId::StdoutService, 0, TICKS_PER_SEC),
+                RateLimitVerdict::Admit
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let (service, config) = parse_config("TimerService:100:10:deny").unwrap();
+        assert_eq!(service.val(), ServiceId::TimerService.val());
+        assert_eq!(
+            config,
+            Some(RateLimitConfig {
+                burst: 10,
+                calls_per_sec: 100,
+                penalty: RateLimitPenalty::Deny,
+            })
+        );
+        assert_eq!(
+            parse_config("StdoutService:5000:500:250us").map(|(_, config)| config),
+            Some(Some(RateLimitConfig {
+                burst: 500,
+                calls_per_sec: 5000,
+                penalty: RateLimitPenalty::Delay { ns: 250_000 },
+            }))
+        );
+        assert_eq!(
+            parse_config("EchoService:off").map(|(_, config)| config),
+            Some(None)
+        );
+        for invalid in [
+            "NoSuchService:off",
+            "TimerService:100:10",
+            "TimerService:0:10:log",
+            "TimerService:100:10:250",
+        ] {
+            assert!(parse_config(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_release_process() {
+        let mut limiter = RateLimiter::new();
+        limiter.check(1, ServiceId::TimerService, 0, TICKS_PER_SEC);
+        limiter.check(2, ServiceId::TimerService, 0, TICKS_PER_SEC);
+        limiter.release_process(1);
+        assert_eq!(limiter.buckets.len(), 1);
+        assert!(limiter
+            .buckets
+            .contains_key(&(2, ServiceId::TimerService.val())));
+    }
+}
@@ -8,16 +8,31 @@
 //! to delegate the call to an even more specialized handler (e.g. startup exception).
 
 use crate::mem::VIRT_MEM_ALLOC;
-use crate::process::Process;
+use crate::process::{
+    exception_signal,
+    exit_status,
+    kill_process,
+    write_core_dump,
+    FaultHandler,
+    Process,
+    SyscallAbi,
+};
 use crate::pt_multiplex::{
     roottask_generic_portal_callback,
     PTCallHandler,
 };
+use crate::smp;
+use crate::stack;
 use crate::stack::StaticStack;
+use alloc::collections::BTreeMap;
 use alloc::rc::{
     Rc,
     Weak,
 };
+use alloc::string::{
+    String,
+    ToString,
+};
 use core::alloc::Layout;
 use core::convert::TryFrom;
 use libhrstd::cap_space::root::RootCapSpace;
@@ -32,9 +47,10 @@ use libhrstd::libhedron::CapSel;
 use libhrstd::libhedron::ExceptionEventOffset;
 use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
+use libhrstd::libhedron::UtcbDataException;
 use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
 use libhrstd::sync::mutex::SimpleMutex;
-use libhrstd::sync::static_global_ptr::StaticGlobalPtr;
+use libhrstd::uaddress_space::USER_STACK_GUARD_PAGE_ADDR;
 
 /// Used as stack for the exception handler callback function. Must be either mutable
 /// or manually placed in a writeable section in the file. Otherwise we get a page fault.
@@ -50,16 +66,16 @@ use libhrstd::sync::static_global_ptr::StaticGlobalPtr;
 ///           reduces stack usage by Rust. Without it, even stacks that seem large
 ///           enough lead to memory corruptions.
 ///
+/// This is the stack of CPU 0. The local ECs of the other CPUs get stacks of the same size
+/// from the heap.
+///
 // #[link_section = ".data"] (=rw) with "static VARNAME" or "static mut"
 static mut CALLBACK_STACK: StaticStack<16> = StaticStack::new();
 
-/// The stack top of the local EC that handles all exception calls.
-pub static LOCAL_EXC_EC_STACK_TOP: StaticGlobalPtr<u8> =
-    StaticGlobalPtr::new(unsafe { CALLBACK_STACK.get_stack_top_ptr() });
-
-/// Holds a weak reference to the local EC object used for handling exceptions inside
-/// the roottask.
-static EXCEPTION_LOCAL_EC: SimpleMutex<Option<Weak<LocalEcObject>>> = SimpleMutex::new(None);
+/// Holds weak references to the local EC objects used for handling exceptions inside
+/// the roottask, indexed by CPU.
+static EXCEPTION_LOCAL_ECS: SimpleMutex<BTreeMap<u64, Weak<LocalEcObject>>> =
+    SimpleMutex::new(BTreeMap::new());
 
 /// Map that helps to forward certain exceptions to specialized exception handlers, if are available.
 /// The generic PT entry callback sends all exceptions to the callback of this module. This module
@@ -67,32 +83,51 @@ static EXCEPTION_LOCAL_EC: SimpleMutex<Option<Weak<LocalEcObject>>> = SimpleMute
 static SPECIALIZES_EXCEPTION_HANDLER_MAP: SimpleMutex<[Option<PTCallHandler>; NUM_EXC]> =
     SimpleMutex::new([None; NUM_EXC]);
 
-/// Initializes a local EC and N portals to cover N exceptions for the roottask.
+/// Initializes a local EC per online CPU and N portals to cover N exceptions for the
+/// roottask. The exception portals of the roottask are bound to the local EC of CPU 0,
+/// because all global ECs of the roottask run there. Exceptions of the local ECs of the
+/// roottask on other CPUs can't be handled; they are fatal anyway.
 pub fn init(root_process: &Process) {
-    // make sure we reserve enough from virtual address space for the UTCB
-    let utcb_addr = VIRT_MEM_ALLOC
-        .lock()
-        .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
-
-    // adds itself to the root process
-    let exception_local_ec = LocalEcObject::create(
-        RootCapSpace::RootExceptionLocalEc.val(),
-        &root_process.pd_obj(),
-        LOCAL_EXC_EC_STACK_TOP.val(),
-        utcb_addr,
-    );
-    EXCEPTION_LOCAL_EC
-        .lock()
-        .replace(Rc::downgrade(&exception_local_ec));
-    unsafe {
-        CALLBACK_STACK.activate_guard_page(RootCapSpace::RootPd.val());
-    }
+    for cpu in smp::online_cpus_iter() {
+        // the static stack serves CPU 0; it is available before the heap
+        let stack = if cpu == 0 {
+            unsafe { &CALLBACK_STACK }
+        } else {
+            StaticStack::new_leaked()
+        };
 
-    log::debug!("created local ec for exception handling; guard page is active");
-    log::trace!(
-        "local exception handler ec stack top  (incl): {:016x?}",
-        unsafe { CALLBACK_STACK.get_stack_top_ptr() as u64 }
-    );
+        // make sure we reserve enough from virtual address space for the UTCB
+        let utcb_addr = VIRT_MEM_ALLOC
+            .lock()
+            .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
+
+        // adds itself to the root process
+        let exception_local_ec = LocalEcObject::create(
+            RootCapSpace::calc_exception_local_ec_sel(cpu),
+            &root_process.pd_obj(),
+            stack.get_stack_top_ptr() as u64,
+            utcb_addr,
+            cpu,
+        );
+        EXCEPTION_LOCAL_ECS
+            .lock()
+            .insert(cpu, Rc::downgrade(&exception_local_ec));
+        unsafe {
+            stack.activate_guard_page(
+                RootCapSpace::RootPd.val(),
+                format!("exception local EC of CPU {}", cpu),
+            );
+        }
+
+        log::debug!(
+            "created local ec for exception handling on CPU {}; guard page is active",
+            cpu
+        );
+        log::trace!(
+            "local exception handler ec stack top  (incl): {:016x?}",
+            stack.get_stack_top_ptr() as u64
+        );
+    }
 
     // I iterate here over all available/reserved capability selectors for exceptionss.
     // This is relative to the event base selector. For the roottask/root protection domain,
@@ -104,7 +139,7 @@ pub fn init(root_process: &Process) {
         // TODO maybe this should not register the startup exception?!
         //  or the roottask_exception module offers to register custom hooks too.. maybe the nicer way!
         let portal_cap_sel = RootCapSpace::ExceptionEventBase.val() + exc_offset as CapSel;
-        create_exc_pt_for_process(exc_offset as u64, portal_cap_sel);
+        create_exc_pt_for_process(exc_offset as u64, portal_cap_sel, 0);
     }
 }
 
@@ -121,20 +156,24 @@ pub fn register_specialized_exc_handler(excp_id: ExceptionEventOffset, fnc: PTCa
     map[excp_id.val() as usize] = Some(fnc);
 }
 
-/// Creates a new exception portal, that is bound to the local EC defined in this module.
-/// It needs to know the target process/PID, so that the roottask exception handler knows
-/// what process triggered a specific exception.
+/// Creates a new exception portal, that is bound to the local EC of the given CPU defined
+/// in this module. It needs to know the target process/PID, so that the roottask exception
+/// handler knows what process triggered a specific exception.
 ///
 /// Makes sure that the correct callback hook gets called for this portal too.
 ///
 /// # Parameters
 /// * `portal_cap_sel` Capability selector for portal in root PD
-/// * `process_id` Process ID, where this exception portal gets installed/delegated.
-pub fn create_exc_pt_for_process(exc_offset: u64, portal_cap_sel: CapSel) -> Rc<PtObject> {
-    let ec = EXCEPTION_LOCAL_EC
+/// * `cpu` CPU of the ECs that trigger the exceptions
+pub fn create_exc_pt_for_process(
+    exc_offset: u64,
+    portal_cap_sel: CapSel,
+    cpu: u64,
+) -> Rc<PtObject> {
+    let ec = EXCEPTION_LOCAL_ECS
         .lock()
-        .as_ref()
-        .expect("call init first")
+        .get(&cpu)
+        .expect("call init first; CPU must be online")
         .upgrade()
         .unwrap();
     let pt = PtObject::create(
@@ -148,7 +187,8 @@ pub fn create_exc_pt_for_process(exc_offset: u64, portal_cap_sel: CapSel) -> Rc<
 }
 
 /// Handler that handles all error exceptions that Hedron can trigger, both from the roottask or
-/// other processes.
+/// other processes. Exceptions of user processes never take down the roottask, see
+/// [`handle_unresolved_fault`].
 ///
 /// Doesn't reply, because this is done a layer above.
 pub fn generic_error_exception_handler(
@@ -161,6 +201,14 @@ pub fn generic_error_exception_handler(
     // Therefore we need to get the target PID (the process that triggered an exception) from the context.
     let is_roottask = process.pid() == ROOTTASK_PROCESS_PID;
     let exc = ExceptionEventOffset::try_from(pt.ctx().exc()).unwrap();
+
+    // a terminated process faults again and again until the main EC stops it
+    if !is_roottask && exit_status(process.pid()).is_some() {
+        utcb.exception_data_mut().mtd = Mtd::empty();
+        *do_reply = true;
+        return;
+    }
+
     if is_roottask {
         log::debug!(
             "caught exception {:?} from roottask via pt={}",
@@ -176,20 +224,147 @@ pub fn generic_error_exception_handler(
         );
     }
 
-    let map = SPECIALIZES_EXCEPTION_HANDLER_MAP.lock();
-    if let Some(handler) = map[exc.val() as usize] {
+    // the stack and the heap regions of user processes get mapped on demand; the process
+    // retries the access
+    if exc == ExceptionEventOffset::PageFault && !is_roottask {
+        let utcb_exc = utcb.exception_data_mut();
+        let grown =
+            process
+                .memory_manager_mut()
+                .handle_page_fault(utcb_exc.qual[1], utcb_exc.rsp, process);
+        if grown {
+            utcb_exc.mtd = Mtd::empty();
+            *do_reply = true;
+            return;
+        }
+    }
+
+    // the lock isn't held while the handler runs; it may wait for GDB, see crate::gdb_stub
+    let handler = SPECIALIZES_EXCEPTION_HANDLER_MAP.lock()[exc.val() as usize];
+    if let Some(handler) = handler {
         log::debug!("use specialized exception handler");
         handler(pt, process, utcb, do_reply);
     } else {
-        log::debug!("use generic (=panic) exception handler");
+        log::debug!("use generic exception handler");
+        handle_unresolved_fault(exc, process, utcb, do_reply);
+    }
+}
+
+/// Handles an exception that neither the roottask nor the process resolves. Specialized
+/// exception handlers use this as fallback, e.g. if a Linux process doesn't catch the
+/// signal of the exception.
+///
+/// Exceptions of the roottask are fatal for the system. A Hedron-native process enters
+/// its [`FaultHandler`], if it has one. Otherwise, the process gets terminated as if the
+/// signal of the exception killed it, see [`exception_signal`], and leaves a core dump, see
+/// [`write_core_dump`].
+pub fn handle_unresolved_fault(
+    exc: ExceptionEventOffset,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    if process.pid() == ROOTTASK_PROCESS_PID {
         *do_reply = false;
-        panic!(
-            "can't handle exception {:?} at rip={:?} from process {} ({}) currently - game over\n{:#?}",
+        panic_unhandled_exception(exc, process, utcb);
+    }
+
+    let fault_handler = if process.syscall_abi() == SyscallAbi::NativeHedron {
+        process.take_fault_handler()
+    } else {
+        None
+    };
+    if let Some(handler) = fault_handler {
+        log::info!(
+            "pid={} enters its fault handler after exception {:?} at rip={:?}",
+            process.pid(),
             exc,
-            utcb.exception_data().rip as *const u8,
+            utcb.exception_data().rip as *const u8
+        );
+        enter_fault_handler(utcb.exception_data_mut(), exc, handler);
+    } else {
+        log::error!("{}", describe_fault(exc, process, utcb));
+        let sig = exception_signal(exc);
+        write_core_dump(process, utcb.exception_data(), sig);
+        kill_process(process.pid(), sig);
+        // the process faults again until it is stopped; see above
+        utcb.exception_data_mut().mtd = Mtd::empty();
+    }
+    *do_reply = true;
+}
+
+/// Prepares the UTCB, so that the process continues in its fault handler. The arguments
+/// follow [`libhrstd::rt::services::process::FaultHandlerEntry`].
+fn enter_fault_handler(
+    utcb_exc: &mut UtcbDataException,
+    exc: ExceptionEventOffset,
+    handler: FaultHandler,
+) {
+    utcb_exc.rdi = exc.val();
+    utcb_exc.rsi = utcb_exc.rip;
+    utcb_exc.rdx = utcb_exc.rsp;
+    // Hedron reports the faulting address of page faults in the second qualification
+    utcb_exc.rcx = if exc == ExceptionEventOffset::PageFault {
+        utcb_exc.qual[1]
+    } else {
+        0
+    };
+    utcb_exc.r8 = utcb_exc.qual[0];
+    utcb_exc.rip = handler.entry;
+    // as if the handler was called by a `call` instruction
+    utcb_exc.rsp = (handler.stack_top & !0xf) - 8;
+    utcb_exc.mtd = Mtd::GPR_ACDB | Mtd::GPR_BSD | Mtd::GPR_R8_R15 | Mtd::RSP | Mtd::RIP_LEN;
+}
+
+/// Panics with a description of the exception. Used for exceptions of the roottask, which
+/// can't be handled.
+pub fn panic_unhandled_exception(exc: ExceptionEventOffset, process: &Process, utcb: &Utcb) -> ! {
+    panic!("{} - game over", describe_fault(exc, process, utcb));
+}
+
+/// Describes an exception that can't be handled. Page faults at a guard page are reported
+/// as stack overflow of the corresponding thread.
+fn describe_fault(exc: ExceptionEventOffset, process: &Process, utcb: &Utcb) -> String {
+    if let Some(thread) = overflowed_stack_thread(exc, process, utcb) {
+        return format!(
+            "stack overflow in PID {} (thread {}) at rip={:?}, rsp={:?}, fault address={:?}\n{:#?}",
             process.pid(),
-            process.name(),
+            thread,
+            utcb.exception_data().rip as *const u8,
+            utcb.exception_data().rsp as *const u8,
+            utcb.exception_data().qual[1] as *const u8,
             utcb.exception_data(),
         );
     }
+    format!(
+        "can't handle exception {:?} at rip={:?} from process {} ({})\n{:#?}",
+        exc,
+        utcb.exception_data().rip as *const u8,
+        process.pid(),
+        process.name(),
+        utcb.exception_data(),
+    )
+}
+
+/// Returns the name of the thread whose stack overflowed, if the exception is a page fault
+/// at a guard page. The roottask knows the names of its threads, see
+/// [`stack::guard_page_owner`]. User processes have a single thread whose ID equals the PID.
+fn overflowed_stack_thread(
+    exc: ExceptionEventOffset,
+    process: &Process,
+    utcb: &Utcb,
+) -> Option<String> {
+    if exc != ExceptionEventOffset::PageFault {
+        return None;
+    }
+    let fault_addr = utcb.exception_data().qual[1];
+    if process.pid() == ROOTTASK_PROCESS_PID {
+        stack::guard_page_owner(fault_addr)
+    } else if (USER_STACK_GUARD_PAGE_ADDR..USER_STACK_GUARD_PAGE_ADDR + PAGE_SIZE as u64)
+        .contains(&fault_addr)
+    {
+        Some(process.pid().to_string())
+    } else {
+        None
+    }
 }
@@ -0,0 +1,106 @@
+//! Boot arguments of the roottask. They follow the name in the cmdline string of the
+//! Multiboot boot module of the roottask, e.g. `roottask log_timestamps=off`.
+//!
+//! Supported arguments:
+//! - `core_dumps=off`: crashed processes leave no core dumps in `/cores`, see
+//!   [`crate::process::write_core_dump`]
+//! - `deterministic=on`: two runs of the same workload produce the same logs, see
+//!   [`crate::deterministic`]
+//! - `fb_console=on` or `fb_console=<address>,<width>x<height>[,<pitch>]`: the console
+//!   output appears on a framebuffer too, see [`crate::hw::framebuffer`]
+//! - `fs_quota=<size>` and `fs_process_quota=<size>`: the files of the in-memory file
+//!   system occupy at most `size` bytes in total or per process, see [`crate::fs_quota`]
+//! - `hostname=<name>`: the name of the host that `uname()` reports, see [`crate::uname`]
+//! - `log_format=binary`: the roottask logs compact binary records instead of text, see
+//!   [`crate::log_format`]
+//! - `log_level=<level>`: the log takes records of the level (`off`, `error`, `warn`,
+//!   `info`, `debug`, or `trace`) and more severe ones by default, see
+//!   [`crate::log_buffer`]
+//! - `log_serial=off`: the log is only kept in memory and not printed on the serial port
+//!   and debugcon, see [`crate::log_buffer`]
+//! - `log_timestamps=off`: lines of the log output carry no timestamps, see
+//!   [`crate::log_timestamp`]
+//! - `rate_limit=<service>:off` or `rate_limit=<service>:<calls/s>:<burst>:<penalty>`:
+//!   replaces the rate limit of a service, see [`crate::rate_limit::configure_from_arg`]
+//! - `safe_mode=on`: the roottask boots into a recovery environment, see
+//!   [`crate::safe_mode`]
+//! - `selfcheck=on`: the roottask checks the health of its services after boot, see
+//!   [`crate::selfcheck`]
+//! - `uname_sysname=<name>`, `uname_release=<release>`, and `uname_machine=<machine>`:
+//!   the system that `uname()` and `/etc/os-release` report, see [`crate::uname`]
+//! - `virtio_console=on`: the console uses a virtio-console instead of the serial port,
+//!   see [`crate::hw::virtio_console`]
+
+use crate::hw::framebuffer;
+use crate::hw::virtio_console;
+use crate::log_format::LogFormat;
+use crate::process;
+use crate::process::Process;
+use crate::rt::userland::InitialUserland;
+use crate::{
+    deterministic,
+    fs_quota,
+    log_buffer,
+    log_format,
+    log_timestamp,
+    rate_limit,
+    safe_mode,
+    selfcheck,
+    uname,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::HIP;
+use libhrstd::rt::services::logging::{
+    LogLevel,
+    LogScope,
+};
+
+/// Name of the roottask in the cmdline string of its boot module.
+const ROOTTASK_MB_CMDLINE_ARGUMENT: &str = "roottask";
+
+/// Finds the boot module of the roottask and applies its boot arguments. Does nothing if
+/// the boot loader passes no cmdline string, as GRUB does for the roottask by default.
+pub fn init(hip: &HIP, root: &Rc<Process>) {
+    let args = hip
+        .mb_module_iterator()
+        .filter_map(|hipmem| InitialUserland::hip_mem_mb_cmd_str(hipmem, root))
+        .map(|cmdline| cmdline.split_whitespace())
+        .find_map(|mut args| (args.next() == Some(ROOTTASK_MB_CMDLINE_ARGUMENT)).then(|| args));
+    for arg in args.into_iter().flatten() {
+        apply(arg);
+    }
+}
+
+/// Applies a single boot argument.
+fn apply(arg: &str) {
+    match arg.split_once('=') {
+        Some(("core_dumps", "on")) => process::set_core_dumps_enabled(true),
+        Some(("core_dumps", "off")) => process::set_core_dumps_enabled(false),
+        Some(("deterministic", "on")) => deterministic::set_enabled(true),
+        Some(("deterministic", "off")) => deterministic::set_enabled(false),
+        Some(("fb_console", source)) if framebuffer::set_source(source) => {}
+        Some(("fs_quota", size)) if fs_quota::set_total_quota(size) => {}
+        Some(("fs_process_quota", size)) if fs_quota::set_process_quota(size) => {}
+        Some(("hostname", name)) if uname::set_nodename(name) => {}
+        Some(("log_format", "text")) => log_format::set(LogFormat::Text),
+        Some(("log_format", "binary")) => log_format::set(LogFormat::Binary),
+        Some(("log_level", level)) if LogLevel::parse(level).is_some() => {
+            log_buffer::set_level(LogScope::Default, LogLevel::parse(level).unwrap())
+        }
+        Some(("log_serial", "on")) => log_buffer::set_console_enabled(true),
+        Some(("log_serial", "off")) => log_buffer::set_console_enabled(false),
+        Some(("log_timestamps", "on")) => log_timestamp::set_enabled(true),
+        Some(("log_timestamps", "off")) => log_timestamp::set_enabled(false),
+        Some(("rate_limit", value)) if rate_limit::configure_from_arg(value) => {}
+        Some(("safe_mode", "on")) => safe_mode::set_enabled(true),
+        Some(("safe_mode", "off")) => safe_mode::set_enabled(false),
+        Some(("selfcheck", "on")) => selfcheck::set_enabled(true),
+        Some(("selfcheck", "off")) => selfcheck::set_enabled(false),
+        Some(("uname_sysname", name)) if uname::set_sysname(name) => {}
+        Some(("uname_release", release)) if uname::set_release(release) => {}
+        Some(("uname_machine", machine)) if uname::set_machine(machine) => {}
+        Some(("virtio_console", "on")) => virtio_console::set_enabled(true),
+        Some(("virtio_console", "off")) => virtio_console::set_enabled(false),
+        _ => log::warn!("ignoring unknown boot argument: {}", arg),
+    }
+}
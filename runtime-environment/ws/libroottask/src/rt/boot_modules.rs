@@ -0,0 +1,199 @@
+//! Programs as Multiboot boot modules. Besides the roottask and the userland tarball (see
+//! [`super::userland`]), each boot module is a program that the roottask starts as a
+//! process during boot. Hence, the boot loader configuration alone can decide what runs,
+//! e.g. with GRUB:
+//!
+//! ```text
+//! module2 /linux_c_hello_world_musl hello abi=linux arg=--verbose env=FOO=BAR
+//! ```
+//!
+//! The cmdline string of a module is `[NAME ...] [KEY=VALUE ...]`. The last word before the
+//! first key, without the directory, names the module; some boot loaders put the file
+//! name of the module first. The keys are:
+//! - `abi=linux|native`: syscall ABI of the program, if it can't be detected from the ELF
+//! - `name=NAME`: name and first argument of the process instead of the name of the module
+//! - `arg=ARG`: next argument of the process; may appear multiple times
+//! - `env=KEY=VALUE`: environment variable of the process; may appear multiple times
+//!
+//! Arguments can't contain spaces. The first module is the roottask itself. Modules named
+//! `roottask` or `userland` are not started either.
+
+use super::userland::{
+    copy_to_page_aligned_dest,
+    InitialUserland,
+};
+use crate::mem::{
+    MappedMemory,
+    ROOT_MEM_MAPPER,
+};
+use crate::process::{
+    Process,
+    SyscallAbi,
+    PROCESS_MNG,
+};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::libhedron::{
+    MemCapPermissions,
+    HIP,
+};
+use libhrstd::mem::calc_page_count;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::scheduling::SchedulingParams;
+
+/// Names of the modules that aren't programs.
+const RESERVED_MODULE_NAMES: [&str; 2] = ["roottask", "userland"];
+
+/// A program in a boot module.
+#[derive(Debug)]
+pub struct BootModule {
+    elf_file: MappedMemory,
+    cmdline: ModuleCmdline,
+}
+
+impl BootModule {
+    /// Starts the program as a process. Returns the ID of the new process.
+    pub fn start(&self) -> Option<ProcessId> {
+        let name = self.cmdline.process_name();
+        let mut argv = Vec::with_capacity(self.cmdline.args.len() + 1);
+        argv.push(String::from(name));
+        argv.extend(self.cmdline.args.iter().cloned());
+        log::info!("starting boot module '{}'", name);
+        PROCESS_MNG.lock().start_process(
+            self.elf_file.clone(),
+            String::from(name),
+            self.cmdline.abi,
+            argv,
+            self.cmdline.envp.clone(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
+        )
+    }
+}
+
+/// The parsed cmdline string of a boot module.
+#[derive(Debug, Default, PartialEq)]
+struct ModuleCmdline {
+    /// Name of the module, i.e. usually its file name.
+    module_name: Option<String>,
+    /// Value of `name=`.
+    name: Option<String>,
+    abi: Option<SyscallAbi>,
+    args: Vec<String>,
+    envp: Vec<String>,
+}
+
+impl ModuleCmdline {
+    /// Parses the cmdline string of a boot module.
+    fn parse(cmdline: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut words = cmdline.split_whitespace().peekable();
+        while let Some(word) = words.next_if(|word| !word.contains('=')) {
+            let module_name = word.rsplit('/').next().unwrap_or(word);
+            parsed.module_name = Some(String::from(module_name));
+        }
+        for word in words {
+            match word.split_once('=') {
+                Some(("abi", "linux")) => parsed.abi = Some(SyscallAbi::Linux),
+                Some(("abi", "native")) => parsed.abi = Some(SyscallAbi::NativeHedron),
+                Some(("name", name)) if !name.is_empty() => parsed.name = Some(String::from(name)),
+                Some(("arg", arg)) => parsed.args.push(String::from(arg)),
+                Some(("env", env)) if env.contains('=') => parsed.envp.push(String::from(env)),
+                _ => return Err(format!("invalid argument: {}", word)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns true if the module is the roottask or the userland tarball.
+    fn is_reserved(&self) -> bool {
+        self.module_name
+            .as_deref()
+            .map_or(false, |name| RESERVED_MODULE_NAMES.contains(&name))
+    }
+
+    fn process_name(&self) -> &str {
+        self.name
+            .as_deref()
+            .or(self.module_name.as_deref())
+            .unwrap_or("boot-module")
+    }
+}
+
+/// Finds the boot modules with programs and copies each program to page-aligned memory.
+/// Modules with an invalid cmdline string are skipped.
+pub fn load(hip: &HIP, root: &Rc<Process>) -> Vec<BootModule> {
+    hip.mb_module_iterator()
+        .enumerate()
+        // the roottask
+        .skip(1)
+        .filter_map(|(index, hip_mem)| {
+            let cmdline = InitialUserland::hip_mem_mb_cmdline(hip_mem, root).unwrap_or_default();
+            let cmdline = match ModuleCmdline::parse(cmdline) {
+                Ok(cmdline) => cmdline,
+                Err(e) => {
+                    log::error!("ignoring boot module {}: {}", index, e);
+                    return None;
+                }
+            };
+            if cmdline.is_reserved() {
+                return None;
+            }
+            let offset = hip_mem.addr() & 0xfff;
+            let mapped_mem = ROOT_MEM_MAPPER.lock().mmap(
+                root,
+                root,
+                hip_mem.addr() - offset,
+                None,
+                calc_page_count((offset + hip_mem.size()) as usize) as u64,
+                MemCapPermissions::READ,
+            );
+            let data =
+                mapped_mem.mem_with_offset_as_slice(hip_mem.size() as usize, offset as usize);
+            log::debug!(
+                "boot module {} '{}': {} bytes",
+                index,
+                cmdline.process_name(),
+                data.len()
+            );
+            Some(BootModule {
+                elf_file: copy_to_page_aligned_dest(data, root),
+                cmdline,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module_cmdline() {
+        let cmdline =
+            ModuleCmdline::parse("./build/ls.elf abi=linux name=ls arg=-l arg=/bin env=FOO=BAR")
+                .unwrap();
+        assert_eq!(cmdline.module_name.as_deref(), Some("ls.elf"));
+        assert_eq!(cmdline.process_name(), "ls");
+        assert_eq!(cmdline.abi, Some(SyscallAbi::Linux));
+        assert_eq!(cmdline.args, vec!["-l", "/bin"]);
+        assert_eq!(cmdline.envp, vec!["FOO=BAR"]);
+        assert!(!cmdline.is_reserved());
+
+        // file name first, then the name of the module
+        let cmdline = ModuleCmdline::parse("./build/roottask-bin.elf roottask").unwrap();
+        assert!(cmdline.is_reserved());
+        assert!(ModuleCmdline::parse("userland").unwrap().is_reserved());
+
+        let cmdline = ModuleCmdline::parse("").unwrap();
+        assert_eq!(cmdline, ModuleCmdline::default());
+        assert_eq!(cmdline.process_name(), "boot-module");
+
+        assert!(ModuleCmdline::parse("ls abi=windows").is_err());
+        assert!(ModuleCmdline::parse("ls env=FOO").is_err());
+        assert!(ModuleCmdline::parse("abi=linux ls").is_err());
+    }
+}
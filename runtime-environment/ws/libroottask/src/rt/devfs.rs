@@ -0,0 +1,238 @@
+//! Device file system backend with the standard device nodes that musl and many programs
+//! open during startup. The roottask mounts it at [`DEV_MOUNT_POINT`].
+//!
+//! - `null` discards all writes; reads return the end of the file.
+//! - `zero` returns zeros.
+//! - `urandom` returns the output of a [`ChaChaRng`] that is seeded with RDRAND, if the
+//!   CPU supports it, and the jitter of the TSC. The generator also serves the `getrandom`
+//!   syscall and `AT_RANDOM` of new processes, see [`fill_random`].
+//! - `console` is the console of the stdout and stdin services: writes go to the output,
+//!   reads return the input that already arrived and never block, see
+//!   [`crate::hw::input`].
+
+use crate::hw::input;
+use crate::services::stdout;
+use crate::time;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use libfileserver::{
+    FileStat,
+    FsBackend,
+    FsError,
+    INode,
+    FILESYSTEM,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::csprng::{
+    ChaChaRng,
+    CHACHA_KEY_SIZE,
+};
+use x86::cpuid::CpuId;
+
+/// Where the device nodes appear in the file system.
+pub const DEV_MOUNT_POINT: &str = "/dev";
+
+/// Maximum number of bytes that a single read of an endless device returns.
+const MAX_READ: usize = 4096;
+
+/// Number of TSC measurements that contribute to the seed of `urandom`.
+const JITTER_SAMPLES: usize = 256;
+
+/// Character devices that everyone can read and write.
+const DEVICE_MODE: u32 = 0o020666;
+
+/// The device nodes. The [`INode`] of a node is its index plus one.
+const DEVICES: [(&str, Device); 4] = [
+    ("/null", Device::Null),
+    ("/zero", Device::Zero),
+    ("/urandom", Device::Urandom),
+    ("/console", Device::Console),
+];
+
+/// The generator behind `urandom`. [`init`] seeds it.
+static URANDOM: SimpleMutex<ChaChaRng> = SimpleMutex::new(ChaChaRng::new([0; CHACHA_KEY_SIZE]));
+
+/// Seeds the generator and mounts the device nodes at [`DEV_MOUNT_POINT`]. The heap and
+/// [`time`] must be initialized.
+pub fn init() {
+    URANDOM.lock().reseed(&DevFs::collect_seed());
+    FILESYSTEM
+        .lock()
+        .mount(DEV_MOUNT_POINT, Box::new(DevFs::new()))
+        .expect("the mount point of the device nodes must be free");
+}
+
+/// Fills `buf` with the output of the generator behind `urandom`. Can be called from every
+/// EC of the roottask.
+pub fn fill_random(buf: &mut [u8]) {
+    URANDOM.lock().fill_bytes(buf);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Device {
+    Null,
+    Zero,
+    Urandom,
+    Console,
+}
+
+/// [`FsBackend`] with the device nodes, see module description.
+#[derive(Debug)]
+pub struct DevFs {
+    /// The data of the last read.
+    buf: Vec<u8>,
+}
+
+impl DevFs {
+    pub const fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    /// Collects a seed for `urandom` from RDRAND and the jitter of the TSC.
+    pub fn collect_seed() -> [u8; CHACHA_KEY_SIZE] {
+        let mut rng = ChaChaRng::new([0; CHACHA_KEY_SIZE]);
+        let has_rdrand = CpuId::new()
+            .get_feature_info()
+            .map_or(false, |info| info.has_rdrand());
+        if has_rdrand {
+            for _ in 0..CHACHA_KEY_SIZE / 8 {
+                let mut val = 0;
+                if unsafe { x86::random::rdrand64(&mut val) } {
+                    rng.reseed(&val.to_le_bytes());
+                }
+            }
+        } else {
+            log::warn!("CPU has no RDRAND; the seed of /dev/urandom relies on TSC jitter");
+        }
+        // the duration of the same work varies a little because of caches, interrupts,
+        // and other CPUs
+        let mut scratch = [0; 64];
+        for _ in 0..JITTER_SAMPLES {
+            let begin = time::tsc_now();
+            rng.fill_bytes(&mut scratch);
+            rng.reseed(&(time::tsc_now() - begin).to_le_bytes());
+        }
+        let mut seed = [0; CHACHA_KEY_SIZE];
+        rng.fill_bytes(&mut seed);
+        seed
+    }
+
+    fn device(i_node: INode) -> Result<Device, FsError> {
+        (i_node.val() as usize)
+            .checked_sub(1)
+            .and_then(|index| DEVICES.get(index))
+            .map(|(_, device)| *device)
+            .ok_or(FsError::NotFound)
+    }
+}
+
+impl Default for DevFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsBackend for DevFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        _flags: FsOpenFlags,
+        _umode: u16,
+    ) -> Result<INode, FsError> {
+        // O_CREAT and O_TRUNC are fine as long as the node exists
+        self.lookup(path)
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        DEVICES
+            .iter()
+            .position(|(device_path, _)| *device_path == path)
+            .map(|index| INode::new(index as u64 + 1))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        // the nodes belong to no process
+        Self::device(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, _offset: usize, count: usize) -> Result<&[u8], FsError> {
+        let count = count.min(MAX_READ);
+        self.buf.clear();
+        match Self::device(i_node)? {
+            Device::Null => {}
+            Device::Zero => self.buf.resize(count, 0),
+            Device::Urandom => {
+                self.buf.resize(count, 0);
+                fill_random(&mut self.buf);
+            }
+            Device::Console => self.buf = input::read(count),
+        }
+        Ok(&self.buf)
+    }
+
+    fn write(&mut self, i_node: INode, _offset: usize, data: &[u8]) -> Result<usize, FsError> {
+        match Self::device(i_node)? {
+            Device::Null | Device::Zero => {}
+            // additional entropy can't hurt, like on Linux
+            Device::Urandom => URANDOM.lock().reseed(data),
+            Device::Console => {
+                let _ = stdout::writer_mut().write_str(&String::from_utf8_lossy(data));
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        Self::device(i_node)?;
+        Ok(FileStat::new(i_node.val(), DEVICE_MODE, 0))
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        DEVICES
+            .iter()
+            .map(|(path, _)| String::from(*path))
+            .filter(|path| path.starts_with(dir))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devfs() {
+        let mut devfs = DevFs::new();
+
+        let null = devfs.open(1, "/null", FsOpenFlags::O_RDWR, 0).unwrap();
+        assert_eq!(devfs.write(null, 0, b"gone"), Ok(4));
+        assert_eq!(devfs.read(null, 0, 100).unwrap(), b"");
+
+        let zero = devfs.open(1, "/zero", FsOpenFlags::O_RDONLY, 0).unwrap();
+        assert_eq!(devfs.read(zero, 0, 100).unwrap(), &[0; 100]);
+        assert_eq!(devfs.read(zero, 100, 1 << 20).unwrap().len(), MAX_READ);
+
+        let urandom = devfs.open(1, "/urandom", FsOpenFlags::O_RDONLY, 0).unwrap();
+        let first = devfs.read(urandom, 0, 32).unwrap().to_vec();
+        let second = devfs.read(urandom, 32, 32).unwrap().to_vec();
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, second);
+        assert_ne!(first, [0; 32]);
+
+        assert!(devfs.open(1, "/sda", FsOpenFlags::O_CREAT, 0).is_err());
+        assert_eq!(devfs.stat(zero).unwrap().st_mode(), DEVICE_MODE);
+        assert!(devfs.unlink("/null").is_err());
+        assert_eq!(devfs.readdir("/").len(), DEVICES.len());
+        assert_eq!(devfs.lookup("/console"), Ok(INode::new(4)));
+    }
+}
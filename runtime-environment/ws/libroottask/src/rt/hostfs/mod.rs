@@ -0,0 +1,676 @@
+//! File system backend that shares a directory of the host under QEMU. The roottask is a
+//! 9P2000.L client of the server in QEMU and talks to it via virtio-9p, see
+//! [`crate::hw::virtio_9p`]. If QEMU provides the device, e.g. `-fsdev
+//! local,id=host0,path=<dir>,security_model=none -device
+//! virtio-9p-pci,fsdev=host0,mount_tag=host`, the roottask mounts the directory at
+//! [`HOST_MOUNT_POINT`]. Programs read their input from it, e.g. binaries that the
+//! process service starts, and write their results to it, without a rebuild of the boot
+//! image.
+//!
+//! - The server checks the permissions as the user of QEMU. The files belong to no
+//!   process.
+//! - The [`INode`] of a file is the unique path of its qid on the host.
+//! - The facade expects [`FsBackend::readdir`] to return all files below a directory,
+//!   hence the backend walks the whole tree below it, up to [`MAX_READDIR_ENTRIES`]
+//!   files.
+
+mod protocol;
+
+use crate::hw::pci::PciConfigSpace;
+use crate::hw::virtio_9p::{
+    Virtio9p,
+    MAX_MSG_SIZE,
+};
+use crate::process::Process;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use libfileserver::{
+    FileStat,
+    FsBackend,
+    FsError,
+    INode,
+    FILESYSTEM,
+};
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use protocol::{
+    DirEntry,
+    MsgBuilder,
+    MsgReader,
+    Qid,
+};
+
+/// Where the directory of the host appears in the file system.
+pub const HOST_MOUNT_POINT: &str = "/host";
+
+/// Maximum number of files that a single [`FsBackend::readdir`] returns.
+pub const MAX_READDIR_ENTRIES: usize = 4096;
+
+/// The fid of the shared directory.
+const ROOT_FID: u32 = 0;
+/// The client has only one outstanding request.
+const TAG: u16 = 0;
+
+/// Mounts the directory of the host at [`HOST_MOUNT_POINT`], if QEMU provides a
+/// virtio-9p device.
+pub fn init(root: &Rc<Process>) {
+    let pci = match PciConfigSpace::new(RootCapSpace::RootPd.val()) {
+        Ok(pci) => pci,
+        Err(_) => {
+            log::warn!("can't access the PCI configuration space; no host directory");
+            return;
+        }
+    };
+    let device = match Virtio9p::init(&pci, root) {
+        Some(device) => device,
+        None => {
+            log::info!("no virtio-9p device; {} isn't mounted", HOST_MOUNT_POINT);
+            return;
+        }
+    };
+    let mount_tag = String::from(device.mount_tag());
+    let fs = match HostFs::new(device) {
+        Ok(fs) => fs,
+        Err(err) => {
+            log::warn!(
+                "can't attach to the 9P server of {:?}: {:?}",
+                mount_tag,
+                err
+            );
+            return;
+        }
+    };
+    match FILESYSTEM.lock().mount(HOST_MOUNT_POINT, Box::new(fs)) {
+        Ok(()) => log::info!(
+            "mounted {:?} of the host at {}",
+            mount_tag,
+            HOST_MOUNT_POINT
+        ),
+        Err(err) => log::warn!("can't mount {}: {:?}", HOST_MOUNT_POINT, err),
+    }
+}
+
+/// Splits a path into the path of the parent directory and the name of the file.
+fn split_path(path: &str) -> Result<(&str, &str), FsError> {
+    let path = path.trim_end_matches('/');
+    match path.rsplit_once('/') {
+        Some((_, "")) | None => Err(FsError::InvalidArgument),
+        Some(("", name)) => Ok(("/", name)),
+        Some((parent, name)) => Ok((parent, name)),
+    }
+}
+
+/// 9P2000.L client that sends one request at a time via the device.
+#[derive(Debug)]
+struct Client {
+    device: Virtio9p,
+    /// Negotiated maximum size of a message.
+    msize: usize,
+    root_qid: Qid,
+    next_fid: u32,
+    free_fids: Vec<u32>,
+}
+
+impl Client {
+    /// Negotiates the protocol version and attaches [`ROOT_FID`] to the shared directory.
+    fn new(device: Virtio9p) -> Result<Self, FsError> {
+        let mut client = Self {
+            device,
+            msize: MAX_MSG_SIZE,
+            root_qid: Qid {
+                kind: 0,
+                version: 0,
+                path: 0,
+            },
+            next_fid: ROOT_FID + 1,
+            free_fids: Vec::new(),
+        };
+
+        let request = MsgBuilder::new(protocol::TVERSION, protocol::NOTAG)
+            .u32(MAX_MSG_SIZE as u32)
+            .str(protocol::VERSION)
+            .finish();
+        let (msize, version) = client.transact(&request, protocol::TVERSION, |reader| {
+            Ok((reader.u32()?, reader.str()?))
+        })?;
+        if version != protocol::VERSION {
+            return Err(FsError::Unsupported);
+        }
+        client.msize = (msize as usize).min(MAX_MSG_SIZE);
+
+        let request = MsgBuilder::new(protocol::TATTACH, TAG)
+            .u32(ROOT_FID)
+            .u32(protocol::NOFID)
+            .str("root")
+            .str("")
+            .u32(0)
+            .finish();
+        client.root_qid = client.transact(&request, protocol::TATTACH, |reader| reader.qid())?;
+        Ok(client)
+    }
+
+    /// Sends the request and parses the response of the expected type.
+    fn transact<T>(
+        &mut self,
+        request: &[u8],
+        kind: u8,
+        parse: impl FnOnce(&mut MsgReader) -> Result<T, FsError>,
+    ) -> Result<T, FsError> {
+        let response = self.device.request(request).ok_or(FsError::Io)?;
+        parse(&mut MsgReader::new(response, kind)?)
+    }
+
+    /// Maximum number of bytes of a single read or write.
+    fn max_io_size(&self) -> usize {
+        self.msize - protocol::IO_HEADER_SIZE
+    }
+
+    fn alloc_fid(&mut self) -> u32 {
+        self.free_fids.pop().unwrap_or_else(|| {
+            self.next_fid += 1;
+            self.next_fid - 1
+        })
+    }
+
+    /// Releases the fid on the server.
+    fn clunk(&mut self, fid: u32) {
+        let request = MsgBuilder::new(protocol::TCLUNK, TAG).u32(fid).finish();
+        // the fid is gone even if the server reports an error
+        let _ = self.transact(&request, protocol::TCLUNK, |_| Ok(()));
+        self.free_fids.push(fid);
+    }
+
+    /// Returns a new fid for the file at the path and its qid.
+    fn walk(&mut self, path: &str) -> Result<(u32, Qid), FsError> {
+        let names = path
+            .split('/')
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        // a walk without names clones the fid
+        let chunks = if names.is_empty() {
+            Vec::from([&names[..]])
+        } else {
+            names.chunks(protocol::MAX_WALK_NAMES).collect()
+        };
+        let fid = self.alloc_fid();
+        let mut qid = self.root_qid;
+        let mut from = ROOT_FID;
+        for chunk in chunks {
+            let request = chunk
+                .iter()
+                .fold(
+                    MsgBuilder::new(protocol::TWALK, TAG)
+                        .u32(from)
+                        .u32(fid)
+                        .u16(chunk.len() as u16),
+                    |request, name| request.str(name),
+                )
+                .finish();
+            let qids = self.transact(&request, protocol::TWALK, |reader| {
+                (0..reader.u16()?)
+                    .map(|_| reader.qid())
+                    .collect::<Result<Vec<_>, _>>()
+            });
+            match qids {
+                Ok(qids) if qids.len() == chunk.len() => {
+                    if let Some(last) = qids.last() {
+                        qid = *last;
+                    }
+                }
+                res => {
+                    // the fid only exists after the first successful walk
+                    if from == fid {
+                        self.clunk(fid);
+                    } else {
+                        self.free_fids.push(fid);
+                    }
+                    return Err(res.err().unwrap_or(FsError::NotFound));
+                }
+            }
+            from = fid;
+        }
+        Ok((fid, qid))
+    }
+
+    /// Walks to the file at the path, runs `f` with its fid, and releases the fid.
+    fn with_fid<T>(
+        &mut self,
+        path: &str,
+        f: impl FnOnce(&mut Self, u32, Qid) -> Result<T, FsError>,
+    ) -> Result<T, FsError> {
+        let (fid, qid) = self.walk(path)?;
+        let res = f(self, fid, qid);
+        self.clunk(fid);
+        res
+    }
+
+    fn getattr(&mut self, fid: u32) -> Result<protocol::Attr, FsError> {
+        let request = MsgBuilder::new(protocol::TGETATTR, TAG)
+            .u32(fid)
+            .u64(protocol::GETATTR_BASIC)
+            .finish();
+        self.transact(&request, protocol::TGETATTR, |reader| reader.attr())
+    }
+
+    fn lopen(&mut self, fid: u32, flags: u32) -> Result<(), FsError> {
+        let request = MsgBuilder::new(protocol::TLOPEN, TAG)
+            .u32(fid)
+            .u32(flags)
+            .finish();
+        self.transact(&request, protocol::TLOPEN, |_| Ok(()))
+    }
+
+    /// Creates the file in the directory of `fid` and opens it. Afterwards, `fid` refers
+    /// to the new file.
+    fn lcreate(&mut self, fid: u32, name: &str, flags: u32, mode: u32) -> Result<Qid, FsError> {
+        let request = MsgBuilder::new(protocol::TLCREATE, TAG)
+            .u32(fid)
+            .str(name)
+            .u32(flags)
+            .u32(mode)
+            .u32(0)
+            .finish();
+        self.transact(&request, protocol::TLCREATE, |reader| reader.qid())
+    }
+
+    /// Reads at most `count` bytes into `buf`.
+    fn read(
+        &mut self,
+        fid: u32,
+        offset: usize,
+        count: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), FsError> {
+        let request = MsgBuilder::new(protocol::TREAD, TAG)
+            .u32(fid)
+            .u64(offset as u64)
+            .u32(count.min(self.max_io_size()) as u32)
+            .finish();
+        self.transact(&request, protocol::TREAD, |reader| {
+            let count = reader.u32()? as usize;
+            buf.clear();
+            buf.extend_from_slice(reader.bytes(count)?);
+            Ok(())
+        })
+    }
+
+    /// Writes the data. Returns the number of written bytes.
+    fn write(&mut self, fid: u32, offset: usize, data: &[u8]) -> Result<usize, FsError> {
+        let mut written = 0;
+        for chunk in data.chunks(self.max_io_size()) {
+            let request = MsgBuilder::new(protocol::TWRITE, TAG)
+                .u32(fid)
+                .u64((offset + written) as u64)
+                .u32(chunk.len() as u32)
+                .bytes(chunk)
+                .finish();
+            let count = self.transact(&request, protocol::TWRITE, |reader| reader.u32())? as usize;
+            // a broken server; the offset of the next chunk would be wrong
+            if count > chunk.len() {
+                return Err(FsError::Io);
+            }
+            written += count;
+            if count < chunk.len() {
+                break;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Returns the entries of the directory of `fid`, which must be open.
+    fn readdir(&mut self, fid: u32) -> Result<Vec<DirEntry>, FsError> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        loop {
+            let request = MsgBuilder::new(protocol::TREADDIR, TAG)
+                .u32(fid)
+                .u64(offset)
+                .u32(self.max_io_size() as u32)
+                .finish();
+            let chunk =
+                self.transact(&request, protocol::TREADDIR, |reader| reader.dir_entries())?;
+            match chunk.last() {
+                Some(last) => offset = last.offset,
+                None => return Ok(entries),
+            }
+            entries.extend(chunk);
+        }
+    }
+
+    /// Sends a request that refers to the directory of the path, e.g. `Tmkdir`, and
+    /// ignores the content of the response. `build` adds the fields after the fid of the
+    /// directory.
+    fn dir_request(
+        &mut self,
+        dir: &str,
+        kind: u8,
+        build: impl FnOnce(MsgBuilder) -> MsgBuilder,
+    ) -> Result<(), FsError> {
+        self.with_fid(dir, |client, fid, _| {
+            let request = build(MsgBuilder::new(kind, TAG).u32(fid)).finish();
+            client.transact(&request, kind, |_| Ok(()))
+        })
+    }
+}
+
+/// A fid of a file that is open for reads and writes.
+#[derive(Debug, Copy, Clone)]
+struct OpenFid {
+    fid: u32,
+    /// False if the host only permits reads.
+    writable: bool,
+}
+
+/// [`FsBackend`] for the directory of the host, see module description.
+#[derive(Debug)]
+pub struct HostFs {
+    /// Lookups need the client too, hence the interior mutability.
+    client: RefCell<Client>,
+    /// The paths of the files that were looked up.
+    paths: RefCell<BTreeMap<INode, String>>,
+    /// Files are opened on the first read or write and stay open.
+    open_fids: BTreeMap<INode, OpenFid>,
+    /// The data of the last read.
+    buf: Vec<u8>,
+}
+
+impl HostFs {
+    /// Attaches to the shared directory of the server behind the device.
+    pub fn new(device: Virtio9p) -> Result<Self, FsError> {
+        Ok(Self {
+            client: RefCell::new(Client::new(device)?),
+            paths: RefCell::new(BTreeMap::new()),
+            open_fids: BTreeMap::new(),
+            buf: Vec::new(),
+        })
+    }
+
+    fn path(&self, i_node: INode) -> Result<String, FsError> {
+        self.paths
+            .borrow()
+            .get(&i_node)
+            .cloned()
+            .ok_or(FsError::NotFound)
+    }
+
+    /// Returns the fid of the opened file. Opens the file if necessary.
+    fn open_fid(&mut self, i_node: INode) -> Result<OpenFid, FsError> {
+        if let Some(open_fid) = self.open_fids.get(&i_node) {
+            return Ok(*open_fid);
+        }
+        let path = self.path(i_node)?;
+        let client = self.client.get_mut();
+        let (fid, _) = client.walk(&path)?;
+        let open_fid = match client.lopen(fid, protocol::L_O_RDWR) {
+            Ok(()) => OpenFid {
+                fid,
+                writable: true,
+            },
+            Err(FsError::Perm | FsError::ReadOnly) => {
+                // a fid can only be opened once, hence a new one
+                client.clunk(fid);
+                let (fid, _) = client.walk(&path)?;
+                if let Err(err) = client.lopen(fid, protocol::L_O_RDONLY) {
+                    client.clunk(fid);
+                    return Err(err);
+                }
+                OpenFid {
+                    fid,
+                    writable: false,
+                }
+            }
+            Err(err) => {
+                client.clunk(fid);
+                return Err(err);
+            }
+        };
+        self.open_fids.insert(i_node, open_fid);
+        Ok(open_fid)
+    }
+
+    /// Forgets the file at the path and the files below it, e.g. after they were removed
+    /// or renamed. Moves them to `new_path`, if there is one.
+    fn forget_path(&mut self, path: &str, new_path: Option<&str>) {
+        let dir = format!("{}/", path);
+        let paths = self.paths.get_mut();
+        let i_nodes = paths
+            .iter()
+            .filter(|(_, file)| *file == path || file.starts_with(&dir))
+            .map(|(i_node, _)| *i_node)
+            .collect::<Vec<_>>();
+        for i_node in i_nodes {
+            let file = paths.remove(&i_node).unwrap();
+            match new_path {
+                Some(new_path) => {
+                    paths.insert(i_node, format!("{}{}", new_path, &file[path.len()..]));
+                }
+                None => {
+                    if let Some(open_fid) = self.open_fids.remove(&i_node) {
+                        self.client.get_mut().clunk(open_fid.fid);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FsBackend for HostFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        umode: u16,
+    ) -> Result<INode, FsError> {
+        match self.lookup(path) {
+            Ok(i_node) if flags.can_write() && self.stat(i_node)?.is_dir() => Err(FsError::IsDir),
+            Err(FsError::NotFound) if flags.can_create() => {
+                let (dir, name) = split_path(path)?;
+                let client = self.client.get_mut();
+                let (fid, _) = client.walk(dir)?;
+                let qid = match client.lcreate(fid, name, protocol::L_O_RDWR, umode as u32) {
+                    Ok(qid) => qid,
+                    Err(err) => {
+                        client.clunk(fid);
+                        return Err(err);
+                    }
+                };
+                let i_node = INode::new(qid.path);
+                self.paths.get_mut().insert(i_node, String::from(path));
+                // the fid refers to the new file, which is open already
+                let open_fid = OpenFid {
+                    fid,
+                    writable: true,
+                };
+                if let Some(old) = self.open_fids.insert(i_node, open_fid) {
+                    self.client.get_mut().clunk(old.fid);
+                }
+                Ok(i_node)
+            }
+            res => res,
+        }
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        let path = match path.trim_end_matches('/') {
+            "" => "/",
+            path => path,
+        };
+        let (fid, qid) = self.client.borrow_mut().walk(path)?;
+        self.client.borrow_mut().clunk(fid);
+        let i_node = INode::new(qid.path);
+        self.paths.borrow_mut().insert(i_node, String::from(path));
+        Ok(i_node)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        // the files belong to the user of QEMU
+        self.path(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        let open_fid = self.open_fid(i_node)?;
+        self.client
+            .get_mut()
+            .read(open_fid.fid, offset, count, &mut self.buf)?;
+        Ok(&self.buf)
+    }
+
+    fn write(&mut self, i_node: INode, offset: usize, data: &[u8]) -> Result<usize, FsError> {
+        match self.open_fid(i_node)? {
+            OpenFid {
+                fid,
+                writable: true,
+            } => self.client.get_mut().write(fid, offset, data),
+            _ => Err(FsError::Perm),
+        }
+    }
+
+    fn truncate(&mut self, i_node: INode, len: usize) -> Result<(), FsError> {
+        let path = self.path(i_node)?;
+        self.client.get_mut().with_fid(&path, |client, fid, _| {
+            let request = MsgBuilder::new(protocol::TSETATTR, TAG)
+                .u32(fid)
+                .u32(protocol::SETATTR_SIZE)
+                // mode, uid, gid
+                .u32(0)
+                .u32(0)
+                .u32(0)
+                .u64(len as u64)
+                // atime and mtime
+                .u64(0)
+                .u64(0)
+                .u64(0)
+                .u64(0)
+                .finish();
+            client.transact(&request, protocol::TSETATTR, |_| Ok(()))
+        })
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        let path = self.path(i_node)?;
+        let attr = self
+            .client
+            .borrow_mut()
+            .with_fid(&path, |client, fid, _| client.getattr(fid))?;
+        Ok(FileStat::new(i_node.val(), attr.mode, attr.size as i64)
+            .with_timestamps(attr.atime_ns, attr.mtime_ns, attr.ctime_ns)
+            .with_nlink(attr.nlink))
+    }
+
+    fn unlink(&mut self, path: &str) -> Result<(), FsError> {
+        let (dir, name) = split_path(path)?;
+        self.client
+            .get_mut()
+            .dir_request(dir, protocol::TUNLINKAT, |request| request.str(name).u32(0))?;
+        self.forget_path(path, None);
+        Ok(())
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        let mut client = self.client.borrow_mut();
+        let mut paths = Vec::new();
+        let mut dirs = Vec::from([String::from(dir.trim_end_matches('/'))]);
+        while let Some(dir) = dirs.pop() {
+            let entries = client.with_fid(&dir, |client, fid, _| {
+                client.lopen(fid, protocol::L_O_RDONLY)?;
+                client.readdir(fid)
+            });
+            for entry in entries.unwrap_or_default() {
+                if entry.name == "." || entry.name == ".." {
+                    continue;
+                }
+                if paths.len() == MAX_READDIR_ENTRIES {
+                    log::warn!("{}{} has too many files to list", HOST_MOUNT_POINT, dir);
+                    return paths;
+                }
+                let path = format!("{}/{}", dir, entry.name);
+                if entry.qid.is_dir() {
+                    dirs.push(path.clone());
+                }
+                paths.push(path);
+            }
+        }
+        paths
+    }
+
+    fn rename(&mut self, from: &str, to: &str) -> Result<(), FsError> {
+        let (from_dir, from_name) = split_path(from)?;
+        let (to_dir, to_name) = split_path(to)?;
+        let client = self.client.get_mut();
+        client.with_fid(to_dir, |client, to_fid, _| {
+            client.dir_request(from_dir, protocol::TRENAMEAT, |request| {
+                request.str(from_name).u32(to_fid).str(to_name)
+            })
+        })?;
+        // a replaced file is gone
+        self.forget_path(to, None);
+        self.forget_path(from, Some(to));
+        Ok(())
+    }
+
+    fn link(&mut self, existing: &str, new: &str) -> Result<(), FsError> {
+        let (dir, name) = split_path(new)?;
+        self.client.get_mut().with_fid(existing, |client, fid, _| {
+            client.dir_request(dir, protocol::TLINK, |request| request.u32(fid).str(name))
+        })
+    }
+
+    fn mkdir(&mut self, _caller: ProcessId, path: &str, umode: u16) -> Result<(), FsError> {
+        let (dir, name) = split_path(path)?;
+        self.client
+            .get_mut()
+            .dir_request(dir, protocol::TMKDIR, |request| {
+                request.str(name).u32(umode as u32).u32(0)
+            })
+    }
+
+    fn rmdir(&mut self, path: &str) -> Result<(), FsError> {
+        let (dir, name) = split_path(path)?;
+        self.client
+            .get_mut()
+            .dir_request(dir, protocol::TUNLINKAT, |request| {
+                request.str(name).u32(protocol::AT_REMOVEDIR)
+            })?;
+        self.forget_path(path, None);
+        Ok(())
+    }
+
+    fn symlink(&mut self, _caller: ProcessId, target: &str, path: &str) -> Result<(), FsError> {
+        let (dir, name) = split_path(path)?;
+        self.client
+            .get_mut()
+            .dir_request(dir, protocol::TSYMLINK, |request| {
+                request.str(name).str(target).u32(0)
+            })
+    }
+
+    fn readlink(&self, path: &str) -> Result<String, FsError> {
+        self.client.borrow_mut().with_fid(path, |client, fid, qid| {
+            if !qid.is_symlink() {
+                return Err(FsError::InvalidArgument);
+            }
+            let request = MsgBuilder::new(protocol::TREADLINK, TAG).u32(fid).finish();
+            client.transact(&request, protocol::TREADLINK, |reader| reader.str())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(split_path("/a"), Ok(("/", "a")));
+        assert_eq!(split_path("/a/b/"), Ok(("/a", "b")));
+        assert_eq!(split_path("/a/b/c"), Ok(("/a/b", "c")));
+        assert_eq!(split_path("/"), Err(FsError::InvalidArgument));
+        assert_eq!(split_path("a"), Err(FsError::InvalidArgument));
+    }
+}
@@ -0,0 +1,375 @@
+//! Messages of the 9P2000.L protocol, see
+//! <https://github.com/chaos/diod/blob/master/protocol.md>. All numbers are little-endian,
+//! strings carry a 16-bit length and no null byte. Each message starts with its size,
+//! including the size field, its type, and a tag.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use libfileserver::FsError;
+
+/// The protocol version that the client negotiates.
+pub const VERSION: &str = "9P2000.L";
+/// "No fid", e.g. as the authentication fid of `Tattach`.
+pub const NOFID: u32 = !0;
+/// The tag of `Tversion`.
+pub const NOTAG: u16 = !0;
+/// Size of the header: size, type, and tag.
+pub const HEADER_SIZE: usize = 7;
+/// Size of the header of `Rread` and `Twrite` in front of the data.
+pub const IO_HEADER_SIZE: usize = HEADER_SIZE + 4 + 8 + 4;
+
+// message types; the response is always the request plus one
+pub const RLERROR: u8 = 7;
+pub const TLOPEN: u8 = 12;
+pub const TLCREATE: u8 = 14;
+pub const TSYMLINK: u8 = 16;
+pub const TREADLINK: u8 = 22;
+pub const TGETATTR: u8 = 24;
+pub const TSETATTR: u8 = 26;
+pub const TREADDIR: u8 = 40;
+pub const TLINK: u8 = 70;
+pub const TMKDIR: u8 = 72;
+pub const TRENAMEAT: u8 = 74;
+pub const TUNLINKAT: u8 = 76;
+pub const TVERSION: u8 = 100;
+pub const TATTACH: u8 = 104;
+pub const TWALK: u8 = 110;
+pub const TREAD: u8 = 116;
+pub const TWRITE: u8 = 118;
+pub const TCLUNK: u8 = 120;
+
+/// Maximum number of names in a single `Twalk`.
+pub const MAX_WALK_NAMES: usize = 16;
+
+/// `Tgetattr`: the basic fields, i.e. everything up to the number of blocks.
+pub const GETATTR_BASIC: u64 = 0x7ff;
+/// `Tsetattr`: the size is valid.
+pub const SETATTR_SIZE: u32 = 0x8;
+/// `Tunlinkat`: the name is a directory.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+// flags of `Tlopen` and `Tlcreate`, the same as on Linux
+pub const L_O_RDONLY: u32 = 0o0;
+pub const L_O_RDWR: u32 = 0o2;
+
+/// Type of a [`Qid`]: a directory.
+const QTDIR: u8 = 0x80;
+/// Type of a [`Qid`]: a symbolic link.
+const QTSYMLINK: u8 = 0x02;
+
+// error numbers of `Rlerror`, the same as on Linux
+const EPERM: u32 = 1;
+const ENOENT: u32 = 2;
+const EACCES: u32 = 13;
+const EEXIST: u32 = 17;
+const EXDEV: u32 = 18;
+const ENOTDIR: u32 = 20;
+const EISDIR: u32 = 21;
+const EINVAL: u32 = 22;
+const EFBIG: u32 = 27;
+const ENOSPC: u32 = 28;
+const EROFS: u32 = 30;
+const ENOTEMPTY: u32 = 39;
+const ELOOP: u32 = 40;
+
+/// Unique identification of a file on the server.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Qid {
+    pub kind: u8,
+    pub version: u32,
+    /// Unique for each file, like an inode.
+    pub path: u64,
+}
+
+impl Qid {
+    pub const fn is_dir(&self) -> bool {
+        self.kind & QTDIR != 0
+    }
+
+    pub const fn is_symlink(&self) -> bool {
+        self.kind & QTSYMLINK != 0
+    }
+}
+
+/// The basic fields of `Rgetattr`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Attr {
+    pub qid: Qid,
+    pub mode: u32,
+    pub nlink: u64,
+    pub size: u64,
+    pub atime_ns: u64,
+    pub mtime_ns: u64,
+    pub ctime_ns: u64,
+}
+
+/// An entry of the data of `Rreaddir`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub qid: Qid,
+    /// Offset of the next entry, for the next `Treaddir`.
+    pub offset: u64,
+    pub name: String,
+}
+
+/// Builds a T-message.
+#[derive(Debug)]
+pub struct MsgBuilder {
+    buf: Vec<u8>,
+}
+
+impl MsgBuilder {
+    /// Starts a message with the header. [`Self::finish`] fills in the size.
+    pub fn new(kind: u8, tag: u16) -> Self {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(&[0; 4]);
+        buf.push(kind);
+        buf.extend_from_slice(&tag.to_le_bytes());
+        Self { buf }
+    }
+
+    pub fn u16(mut self, val: u16) -> Self {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, val: u32) -> Self {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, val: u64) -> Self {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+        self
+    }
+
+    pub fn str(self, val: &str) -> Self {
+        let mut builder = self.u16(val.len() as u16);
+        builder.buf.extend_from_slice(val.as_bytes());
+        builder
+    }
+
+    /// Appends the data without a length, e.g. after the count of `Twrite`.
+    pub fn bytes(mut self, data: &[u8]) -> Self {
+        self.buf.extend_from_slice(data);
+        self
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let size = self.buf.len() as u32;
+        self.buf[0..4].copy_from_slice(&size.to_le_bytes());
+        self.buf
+    }
+}
+
+/// Parses an R-message. Truncated messages fail with [`FsError::Io`].
+#[derive(Debug)]
+pub struct MsgReader<'a> {
+    msg: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MsgReader<'a> {
+    /// Checks the header of the response to a request of the type `request`. An `Rlerror`
+    /// fails with the error of the server.
+    pub fn new(msg: &'a [u8], request: u8) -> Result<Self, FsError> {
+        let mut reader = Self { msg, pos: 0 };
+        let size = reader.u32()? as usize;
+        if size < HEADER_SIZE || size > msg.len() {
+            return Err(FsError::Io);
+        }
+        reader.msg = &msg[..size];
+        let kind = reader.u8()?;
+        let _tag = reader.u16()?;
+        match kind {
+            RLERROR => Err(errno_to_fs_error(reader.u32()?)),
+            kind if kind == request + 1 => Ok(reader),
+            _ => Err(FsError::Io),
+        }
+    }
+
+    pub fn u8(&mut self) -> Result<u8, FsError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, FsError> {
+        let mut val = [0; 2];
+        val.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_le_bytes(val))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, FsError> {
+        let mut val = [0; 4];
+        val.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(val))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, FsError> {
+        let mut val = [0; 8];
+        val.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(val))
+    }
+
+    pub fn str(&mut self) -> Result<String, FsError> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    pub fn qid(&mut self) -> Result<Qid, FsError> {
+        Ok(Qid {
+            kind: self.u8()?,
+            version: self.u32()?,
+            path: self.u64()?,
+        })
+    }
+
+    /// Parses the basic fields of `Rgetattr`.
+    pub fn attr(&mut self) -> Result<Attr, FsError> {
+        let _valid = self.u64()?;
+        let qid = self.qid()?;
+        let mode = self.u32()?;
+        let _uid = self.u32()?;
+        let _gid = self.u32()?;
+        let nlink = self.u64()?;
+        let _rdev = self.u64()?;
+        let size = self.u64()?;
+        let _blksize = self.u64()?;
+        let _blocks = self.u64()?;
+        let mut time_ns = || -> Result<u64, FsError> {
+            let sec = self.u64()?;
+            let nsec = self.u64()?;
+            Ok(sec.saturating_mul(1_000_000_000).saturating_add(nsec))
+        };
+        let atime_ns = time_ns()?;
+        let mtime_ns = time_ns()?;
+        let ctime_ns = time_ns()?;
+        Ok(Attr {
+            qid,
+            mode,
+            nlink,
+            size,
+            atime_ns,
+            mtime_ns,
+            ctime_ns,
+        })
+    }
+
+    /// Parses the entries of `Rreaddir`.
+    pub fn dir_entries(&mut self) -> Result<Vec<DirEntry>, FsError> {
+        let count = self.u32()? as usize;
+        let mut data = MsgReader {
+            msg: self.bytes(count)?,
+            pos: 0,
+        };
+        let mut entries = Vec::new();
+        while data.pos < data.msg.len() {
+            let qid = data.qid()?;
+            let offset = data.u64()?;
+            let _kind = data.u8()?;
+            let name = data.str()?;
+            entries.push(DirEntry { qid, offset, name });
+        }
+        Ok(entries)
+    }
+
+    /// Takes the next `len` bytes.
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], FsError> {
+        let bytes = self.msg.get(self.pos..self.pos + len).ok_or(FsError::Io)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+}
+
+/// Translates the error number of `Rlerror`.
+pub fn errno_to_fs_error(errno: u32) -> FsError {
+    match errno {
+        ENOENT => FsError::NotFound,
+        EEXIST => FsError::Exists,
+        ENOTDIR => FsError::NotDir,
+        EISDIR => FsError::IsDir,
+        ENOSPC => FsError::NoSpace,
+        EACCES => FsError::Perm,
+        EROFS => FsError::ReadOnly,
+        EXDEV => FsError::CrossMount,
+        ELOOP => FsError::Loop,
+        EFBIG => FsError::TooLarge,
+        EINVAL => FsError::InvalidArgument,
+        EPERM => FsError::Unsupported,
+        ENOTEMPTY => FsError::NotEmpty,
+        _ => FsError::Io,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_msg_builder() {
+        let msg = MsgBuilder::new(TWALK, 1)
+            .u32(0)
+            .u32(1)
+            .u16(2)
+            .str("bin")
+            .str("ls")
+            .finish();
+        assert_eq!(
+            msg,
+            [
+                26, 0, 0, 0, TWALK, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 3, 0, b'b', b'i', b'n', 2,
+                0, b'l', b's'
+            ]
+        );
+    }
+
+    #[test]
+    fn test_msg_reader() {
+        let mut msg = vec![0, 0, 0, 0, TWALK + 1, 1, 0, 1, 0];
+        msg.extend_from_slice(&[QTDIR, 7, 0, 0, 0, 42, 0, 0, 0, 0, 0, 0, 0]);
+        // trailing bytes of the buffer don't belong to the message
+        let size = msg.len() as u32;
+        msg[0..4].copy_from_slice(&size.to_le_bytes());
+        msg.extend_from_slice(&[0xff; 8]);
+
+        let mut reader = MsgReader::new(&msg, TWALK).unwrap();
+        assert_eq!(reader.u16(), Ok(1));
+        let qid = reader.qid().unwrap();
+        assert_eq!(qid.path, 42);
+        assert!(qid.is_dir());
+        assert_eq!(reader.u8(), Err(FsError::Io));
+
+        assert_eq!(MsgReader::new(&msg, TREAD).err(), Some(FsError::Io));
+        let error = [11, 0, 0, 0, RLERROR, 1, 0, ENOENT as u8, 0, 0, 0];
+        assert_eq!(MsgReader::new(&error, TWALK).err(), Some(FsError::NotFound));
+        assert_eq!(MsgReader::new(&error[..6], TWALK).err(), Some(FsError::Io));
+    }
+
+    #[test]
+    fn test_dir_entries() {
+        let mut data = Vec::new();
+        for (path, name) in [(2_u64, "a"), (3, "bc")] {
+            data.push(0);
+            data.extend_from_slice(&0_u32.to_le_bytes());
+            data.extend_from_slice(&path.to_le_bytes());
+            data.extend_from_slice(&(path * 10).to_le_bytes());
+            data.push(8);
+            data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            data.extend_from_slice(name.as_bytes());
+        }
+        let msg = MsgBuilder::new(TREADDIR + 1, 1)
+            .u32(data.len() as u32)
+            .bytes(&data)
+            .finish();
+
+        let entries = MsgReader::new(&msg, TREADDIR)
+            .unwrap()
+            .dir_entries()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "a");
+        assert_eq!(entries[1].qid.path, 3);
+        assert_eq!(entries[1].offset, 30);
+        assert_eq!(entries[1].name, "bc");
+    }
+}
@@ -1,3 +1,9 @@
 //! Everything related to the runtime environment that the roottask sets up under Hedron.
 
+pub mod boot_args;
+pub mod boot_modules;
+pub mod devfs;
+pub mod hostfs;
+pub mod procfs;
+pub mod tarfs;
 pub mod userland;
@@ -0,0 +1,364 @@
+//! Per-process directories below [`PROC_MOUNT_POINT`], like on Linux. The roottask mounts
+//! a [`ProcessFs`] at `/proc/<pid>` when a process starts and unmounts it when the
+//! process stops. The Linux syscalls resolve `/proc/self` to the directory of the calling
+//! process, see [`resolve_self`].
+//!
+//! - `comm` contains the command name of the process and a newline, see
+//!   [`crate::process::process_comm`]. Processes rename themselves via
+//!   `prctl(PR_SET_NAME)`, hence the file is read-only.
+//! - `cmdline` contains the arguments of the program, each terminated by a null byte.
+//! - `maps` lists the stack of the main thread in the format of Linux. glibc reads it to
+//!   find the bounds of the stack, e.g. in `pthread_getattr_np`.
+//! - `trace` contains the last syscalls of the process, if they are traced, see
+//!   [`crate::process::syscall_trace`]. The directory of a traced process stays after the
+//!   process stopped, so that the trace can be read.
+//!
+//! [`init`] also mounts a [`RoottaskFs`] at [`ROOTTASK_MOUNT_POINT`] with files about the
+//! roottask itself:
+//!
+//! - `heap` contains the utilization of the heap of the roottask, one `name: value` pair
+//!   per line, see [`ROOTTASK_HEAP_STATS`].
+//! - `locks` contains the contention statistics of the locks, one lock per line, the ones
+//!   with the longest total wait first, see [`LOCK_STATS`]. Only the build with the
+//!   feature `lock_stats` records them.
+
+use crate::mem::ROOTTASK_HEAP_STATS;
+use crate::process::{
+    process_comm,
+    syscall_trace,
+};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libfileserver::{
+    FileStat,
+    FsBackend,
+    FsError,
+    INode,
+    FILESYSTEM,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::sync::lock_stats;
+use libhrstd::sync::lock_stats::LOCK_STATS;
+use libhrstd::uaddress_space::{
+    USER_STACK_GUARD_PAGE_ADDR,
+    USER_UTCB_ADDR,
+};
+
+/// Parent directory of the per-process directories.
+pub const PROC_MOUNT_POINT: &str = "/proc";
+
+/// Directory with the files about the roottask.
+pub const ROOTTASK_MOUNT_POINT: &str = "/proc/roottask";
+
+/// The files are read-only regular files.
+const FILE_MODE: u32 = 0o100444;
+
+/// The files of a process directory. The [`INode`] of a file is its index plus one.
+const FILES: [&str; 4] = ["/comm", "/cmdline", "/maps", "/trace"];
+
+/// The files of the roottask directory. The [`INode`] of a file is its index plus one.
+const ROOTTASK_FILES: [&str; 2] = ["/heap", "/locks"];
+
+/// Mounts the directory of the roottask at [`ROOTTASK_MOUNT_POINT`]. The heap must be
+/// initialized.
+pub fn init() {
+    FILESYSTEM
+        .lock()
+        .mount(ROOTTASK_MOUNT_POINT, Box::new(RoottaskFs::new()))
+        .expect("the mount point of the roottask directory must be free");
+}
+
+/// Mounts the directory of a new process with the arguments of its program.
+pub fn mount(pid: ProcessId, argv: &[String]) {
+    let res = FILESYSTEM
+        .lock()
+        .mount(&mount_point(pid), Box::new(ProcessFs::new(pid, argv)));
+    if res.is_err() {
+        log::warn!("can't mount {}", mount_point(pid));
+    }
+}
+
+/// Unmounts the directory of a process, i.e. when it stops.
+pub fn unmount(pid: ProcessId) {
+    let _ = FILESYSTEM.lock().unmount(&mount_point(pid));
+}
+
+/// Replaces a leading `/proc/self` of `path` with the directory of `pid`.
+pub fn resolve_self(path: &str, pid: ProcessId) -> Cow<'_, str> {
+    let self_dir = "/proc/self";
+    match path.strip_prefix(self_dir) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            Cow::Owned(format!("{}{}", mount_point(pid), rest))
+        }
+        _ => Cow::Borrowed(path),
+    }
+}
+
+fn mount_point(pid: ProcessId) -> String {
+    format!("{}/{}", PROC_MOUNT_POINT, pid)
+}
+
+/// Read-only [`FsBackend`] with the files of a single process, see module description.
+#[derive(Debug)]
+pub struct ProcessFs {
+    pid: ProcessId,
+    /// Content of `cmdline`; the arguments never change.
+    cmdline: Vec<u8>,
+    /// The content of the last read.
+    buf: Vec<u8>,
+}
+
+impl ProcessFs {
+    pub fn new(pid: ProcessId, argv: &[String]) -> Self {
+        let cmdline = argv
+            .iter()
+            .flat_map(|arg| arg.bytes().chain(core::iter::once(0)))
+            .collect();
+        Self {
+            pid,
+            cmdline,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns the current content of a file.
+    fn render(&self, i_node: INode) -> Result<Vec<u8>, FsError> {
+        match i_node.val() {
+            1 => process_comm(self.pid)
+                .map(|comm| format!("{}\n", comm).into_bytes())
+                .ok_or(FsError::NotFound),
+            2 => Ok(self.cmdline.clone()),
+            3 => Ok(format!(
+                "{:08x}-{:08x} rw-p 00000000 00:00 0 [stack]\n",
+                USER_STACK_GUARD_PAGE_ADDR + PAGE_SIZE as u64,
+                USER_UTCB_ADDR
+            )
+            .into_bytes()),
+            4 => Ok(syscall_trace(self.pid).unwrap_or_default()),
+            _ => Err(FsError::NotFound),
+        }
+    }
+}
+
+impl FsBackend for ProcessFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        _umode: u16,
+    ) -> Result<INode, FsError> {
+        if flags.can_write() {
+            return Err(FsError::Perm);
+        }
+        self.lookup(path)
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        FILES
+            .iter()
+            .position(|file| *file == path)
+            .map(|index| INode::new(index as u64 + 1))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        self.render(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        self.buf = self.render(i_node)?;
+        let from_index = offset.min(self.buf.len());
+        let to_index = (from_index + count).min(self.buf.len());
+        Ok(&self.buf[from_index..to_index])
+    }
+
+    fn write(&mut self, _i_node: INode, _offset: usize, _data: &[u8]) -> Result<usize, FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        let size = self.render(i_node)?.len();
+        Ok(FileStat::new(i_node.val(), FILE_MODE, size as i64))
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        FILES
+            .iter()
+            .map(|file| String::from(*file))
+            .filter(|path| path.starts_with(dir))
+            .collect()
+    }
+}
+
+/// Read-only [`FsBackend`] with the files about the roottask, see module description.
+#[derive(Debug)]
+pub struct RoottaskFs {
+    /// The content of the last read.
+    buf: Vec<u8>,
+}
+
+impl RoottaskFs {
+    pub const fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    /// Returns the current content of a file.
+    fn render(&self, i_node: INode) -> Result<Vec<u8>, FsError> {
+        match i_node.val() {
+            1 => {
+                let heap = ROOTTASK_HEAP_STATS.snapshot();
+                Ok(format!(
+                    "capacity: {}\ncurrent: {}\ncurrent_blocks: {}\npeak: {}\n\
+                     peak_blocks: {}\nallocs: {}\ndeallocs: {}\n",
+                    heap.capacity,
+                    heap.current_bytes,
+                    heap.current_block_bytes,
+                    heap.peak_bytes,
+                    heap.peak_block_bytes,
+                    heap.alloc_count,
+                    heap.dealloc_count
+                )
+                .into_bytes())
+            }
+            2 if !lock_stats::ENABLED => {
+                Ok(b"disabled; build the roottask with the feature lock_stats\n".to_vec())
+            }
+            2 => {
+                let mut report = String::new();
+                for snapshot in LOCK_STATS.snapshots() {
+                    report += &format!("{}\n", snapshot);
+                }
+                report += &format!("untracked locks: {}\n", LOCK_STATS.untracked());
+                Ok(report.into_bytes())
+            }
+            _ => Err(FsError::NotFound),
+        }
+    }
+}
+
+impl Default for RoottaskFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsBackend for RoottaskFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        _umode: u16,
+    ) -> Result<INode, FsError> {
+        if flags.can_write() {
+            return Err(FsError::Perm);
+        }
+        self.lookup(path)
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        ROOTTASK_FILES
+            .iter()
+            .position(|file| *file == path)
+            .map(|index| INode::new(index as u64 + 1))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        self.render(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        self.buf = self.render(i_node)?;
+        let from_index = offset.min(self.buf.len());
+        let to_index = (from_index + count).min(self.buf.len());
+        Ok(&self.buf[from_index..to_index])
+    }
+
+    fn write(&mut self, _i_node: INode, _offset: usize, _data: &[u8]) -> Result<usize, FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        let size = self.render(i_node)?.len();
+        Ok(FileStat::new(i_node.val(), FILE_MODE, size as i64))
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        ROOTTASK_FILES
+            .iter()
+            .map(|file| String::from(*file))
+            .filter(|path| path.starts_with(dir))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{
+        register_comm,
+        unregister_comm,
+    };
+
+    #[test]
+    fn test_process_fs() {
+        register_comm(7, "worker");
+        let mut fs = ProcessFs::new(7, &[String::from("/bin/worker"), String::from("-v")]);
+        assert!(fs.open(1, "/comm", FsOpenFlags::O_WRONLY, 0).is_err());
+        let comm = fs.open(1, "/comm", FsOpenFlags::O_RDONLY, 0).unwrap();
+        assert_eq!(fs.read(comm, 0, 100).unwrap(), b"worker\n");
+        assert_eq!(fs.read(comm, 3, 2).unwrap(), b"ke");
+        assert_eq!(fs.stat(comm).unwrap().st_size(), 7);
+        assert_eq!(fs.readdir("/").len(), FILES.len());
+
+        let cmdline = fs.lookup("/cmdline").unwrap();
+        assert_eq!(fs.read(cmdline, 0, 100).unwrap(), b"/bin/worker\0-v\0");
+        let maps = fs.lookup("/maps").unwrap();
+        assert!(fs.read(maps, 0, 100).unwrap().ends_with(b" [stack]\n"));
+        let trace = fs.lookup("/trace").unwrap();
+        assert_eq!(fs.read(trace, 0, 100).unwrap(), b"");
+
+        // the file follows the renames
+        register_comm(7, "renamed");
+        assert_eq!(fs.read(comm, 0, 100).unwrap(), b"renamed\n");
+        unregister_comm(7);
+        assert!(fs.read(comm, 0, 100).is_err());
+    }
+
+    #[test]
+    fn test_roottask_fs() {
+        let mut fs = RoottaskFs::new();
+        assert!(fs.open(1, "/heap", FsOpenFlags::O_RDWR, 0).is_err());
+        let heap = fs.open(1, "/heap", FsOpenFlags::O_RDONLY, 0).unwrap();
+        let content = String::from_utf8(fs.read(heap, 0, 4096).unwrap().to_vec()).unwrap();
+        assert!(content.starts_with("capacity: "));
+        assert!(content.contains("\npeak_blocks: "));
+        assert_eq!(content.lines().count(), 7);
+        assert_eq!(fs.readdir("/"), ["/heap", "/locks"]);
+        let locks = fs.lookup("/locks").unwrap();
+        assert!(fs.read(locks, 0, 4096).unwrap().starts_with(b"disabled;"));
+        assert!(fs.lookup("/comm").is_err());
+    }
+
+    #[test]
+    fn test_resolve_self() {
+        assert_eq!(resolve_self("/proc/self/comm", 3), "/proc/3/comm");
+        assert_eq!(resolve_self("/proc/self", 3), "/proc/3");
+        assert_eq!(resolve_self("/proc/selfish", 3), "/proc/selfish");
+        assert_eq!(resolve_self("/tmp/proc/self", 3), "/tmp/proc/self");
+    }
+}
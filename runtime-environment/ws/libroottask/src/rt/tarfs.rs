@@ -0,0 +1,217 @@
+//! Read-only file system backend for Tar archives. The roottask mounts the userland
+//! tarball from the Multiboot boot module with it, so that each program in the tarball
+//! is accessible by its path. During development, files can be replaced at runtime, see
+//! [`FsBackend::replace`].
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::min;
+use libfileserver::{
+    FileStat,
+    FsBackend,
+    FsError,
+    INode,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use tar_no_std::ArchiveIterator;
+
+/// Size of a Tar block. Tar archives are a multiple of it.
+const BLOCKSIZE: usize = 512;
+
+/// The Tar header doesn't get exposed by the parser. Hence, all files are readable and
+/// executable regular files.
+const FILE_MODE: u32 = 0o100555;
+
+#[derive(Debug)]
+struct TarFsFile {
+    /// Absolute path inside the backend, i.e. it starts with a slash.
+    path: String,
+    /// Points into the archive, unless the file got replaced.
+    data: Cow<'static, [u8]>,
+}
+
+/// Read-only [`FsBackend`] on top of a Tar archive in memory. The files are not copied;
+/// the backend reads directly from the archive. Only regular files are supported. Replaced
+/// files live on the heap.
+#[derive(Debug)]
+pub struct TarFs {
+    /// The index plus one is the [`INode`] of a file.
+    files: Vec<TarFsFile>,
+}
+
+impl TarFs {
+    /// Parses the Tar archive. The data must outlive the backend, which is the case for
+    /// memory of boot modules, because the roottask never unmaps it.
+    pub fn new(archive: &'static [u8]) -> Self {
+        assert_eq!(archive.len() % BLOCKSIZE, 0, "invalid Tar archive");
+        // `TarArchiveRef::entries` would bind the entries to the lifetime of the wrapper
+        let files = ArchiveIterator::new(archive)
+            .map(|entry| TarFsFile {
+                path: Self::normalize_path(entry.filename().as_str()),
+                data: Cow::Borrowed(entry.data()),
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Turns a path of the archive, such as `./foo` or `bar/baz`, into an absolute path.
+    fn normalize_path(path: &str) -> String {
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        format!("/{}", path)
+    }
+
+    fn file(&self, i_node: INode) -> Result<&TarFsFile, FsError> {
+        (i_node.val() as usize)
+            .checked_sub(1)
+            .and_then(|index| self.files.get(index))
+            .ok_or(FsError::NotFound)
+    }
+}
+
+impl FsBackend for TarFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        _umode: u16,
+    ) -> Result<INode, FsError> {
+        if flags.can_write() {
+            log::debug!("tarfs is read-only: path={}, flags={:?}", path, flags);
+            return Err(FsError::ReadOnly);
+        }
+        // O_CREAT is fine as long as the file exists
+        self.lookup(path)
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        self.files
+            .iter()
+            .position(|file| file.path == path)
+            .map(|index| INode::new(index as u64 + 1))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        // the archive is part of the boot image and belongs to no process
+        self.file(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        let data = &self.file(i_node)?.data;
+        let from_index = min(offset, data.len());
+        let to_index = min(from_index + count, data.len());
+        Ok(&data[from_index..to_index])
+    }
+
+    fn write(&mut self, _i_node: INode, _offset: usize, _data: &[u8]) -> Result<usize, FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        let file = self.file(i_node)?;
+        Ok(FileStat::new(
+            i_node.val(),
+            FILE_MODE,
+            file.data.len() as i64,
+        ))
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        self.files
+            .iter()
+            .map(|file| &file.path)
+            .filter(|path| path.starts_with(dir))
+            .cloned()
+            .collect()
+    }
+
+    fn replace(&mut self, path: &str, data: Vec<u8>) -> Result<(), FsError> {
+        match self.files.iter_mut().find(|file| file.path == path) {
+            Some(file) => file.data = Cow::Owned(data),
+            None => self.files.push(TarFsFile {
+                path: String::from(path),
+                data: Cow::Owned(data),
+            }),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+
+    /// Creates a Tar archive in the ustar format with the given files. The memory is
+    /// leaked to get the `'static` lifetime that [`TarFs`] requires.
+    fn create_archive(files: &[(&str, &[u8])]) -> &'static [u8] {
+        let mut archive = Vec::new();
+        for (name, data) in files {
+            let mut hdr = [0_u8; BLOCKSIZE];
+            hdr[..name.len()].copy_from_slice(name.as_bytes());
+            hdr[100..107].copy_from_slice(b"0000555");
+            hdr[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+            hdr[156] = b'0';
+            hdr[257..263].copy_from_slice(b"ustar\0");
+            archive.extend_from_slice(&hdr);
+            archive.extend_from_slice(data);
+            let padding = (BLOCKSIZE - data.len() % BLOCKSIZE) % BLOCKSIZE;
+            archive.resize(archive.len() + padding, 0);
+        }
+        // end of archive
+        archive.resize(archive.len() + 2 * BLOCKSIZE, 0);
+        Box::leak(archive.into_boxed_slice())
+    }
+
+    #[test]
+    fn test_tarfs() {
+        let mut tarfs = TarFs::new(create_archive(&[
+            ("./hello", b"hello world"),
+            ("sub/big", &[0xab; 1000]),
+        ]));
+
+        let i_node = tarfs.open(1, "/hello", FsOpenFlags::O_RDONLY, 0).unwrap();
+        assert_eq!(tarfs.read(i_node, 0, 100).unwrap(), b"hello world");
+        assert_eq!(tarfs.read(i_node, 6, 3).unwrap(), b"wor");
+        assert_eq!(tarfs.read(i_node, 100, 3).unwrap(), b"");
+        assert_eq!(tarfs.stat(i_node).unwrap().st_size(), 11);
+
+        let i_node = tarfs.open(1, "/sub/big", FsOpenFlags::O_CREAT, 0).unwrap();
+        assert_eq!(tarfs.read(i_node, 0, 2000).unwrap(), &[0xab; 1000]);
+
+        let mut paths = tarfs.readdir("/");
+        paths.sort_unstable();
+        assert_eq!(paths, ["/hello", "/sub/big"]);
+        assert_eq!(tarfs.readdir("/sub/"), ["/sub/big"]);
+
+        assert_eq!(tarfs.lookup("/sub/big"), Ok(i_node));
+        assert_eq!(tarfs.owner(i_node), Ok(None));
+        assert!(tarfs.lookup("/sub").is_err());
+
+        // read-only
+        assert!(tarfs.open(1, "/hello", FsOpenFlags::O_RDWR, 0).is_err());
+        assert!(tarfs
+            .open(1, "/new", FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY, 0)
+            .is_err());
+        assert!(tarfs.open(1, "/new", FsOpenFlags::O_CREAT, 0).is_err());
+        assert!(tarfs.write(i_node, 0, b"data").is_err());
+        assert!(tarfs.unlink("/hello").is_err());
+
+        tarfs
+            .replace("/hello", b"hello new world".to_vec())
+            .unwrap();
+        let i_node = tarfs.lookup("/hello").unwrap();
+        assert_eq!(tarfs.read(i_node, 6, 3).unwrap(), b"new");
+        assert_eq!(tarfs.stat(i_node).unwrap().st_size(), 15);
+        tarfs.replace("/new", b"new file".to_vec()).unwrap();
+        assert_eq!(tarfs.readdir("/").len(), 3);
+    }
+}
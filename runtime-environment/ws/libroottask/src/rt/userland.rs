@@ -1,19 +1,35 @@
 //! Everything related to extract the runtime environment from the Tar file which is provided
-//! in a Multiboot boot module.
+//! in a Multiboot boot module. Further boot modules can hold programs, see
+//! [`super::boot_modules`]. The Tar file gets mounted read-only at [`USERLAND_MOUNT_POINT`],
+//! hence, each program in it can be started by its path via [`start_program`]. It also gets
+//! mounted at [`LIB_MOUNT_POINT`] for dynamically linked programs.
+//!
+//! During development, programs of the tarball can be replaced at runtime with a new
+//! version from the file system, for example with the `reload` command of the shell. The
+//! next start of the program uses the new version; no reboot is necessary. See
+//! [`libhrstd::rt::services::process::ProcessServiceRequest::Reload`].
 
 use crate::mem::{
     MappedMemory,
+    PHYS_FRAME_ALLOC,
     ROOT_MEM_MAPPER,
-    VIRT_MEM_ALLOC,
 };
 use crate::process::Process;
 use crate::process::SyscallAbi;
 use crate::process::PROCESS_MNG;
+use crate::rt::boot_modules;
+use crate::rt::boot_modules::BootModule;
+use crate::rt::tarfs::TarFs;
+use crate::{
+    safe_mode,
+    selfcheck,
+};
+use alloc::boxed::Box;
 use alloc::rc::Rc;
 use alloc::string::String;
-use core::alloc::Layout;
+use alloc::vec::Vec;
+use libfileserver::FILESYSTEM;
 use libhrstd::cstr::CStr;
-use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::MemCapPermissions;
 use libhrstd::libhedron::{
     HipMem,
@@ -21,6 +37,12 @@ use libhrstd::libhedron::{
     HIP,
 };
 use libhrstd::mem::calc_page_count;
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::scheduling::SchedulingParams;
 use tar_no_std::TarArchiveRef;
 
 /// Contains all files of the userland (runtime services + user applications) that
@@ -33,6 +55,8 @@ use tar_no_std::TarArchiveRef;
 pub struct InitialUserland {
     /// Release-version (=maximum optimized + fancy CPU features) of `hedron_native_hello_world_rust_debug_elf`
     hedron_native_hello_world_rust_elf: MappedMemory,
+    /// Native tool that lists and compares the benchmark results in the file system.
+    hedron_native_benchtool_elf: MappedMemory,
     /// Statically compiled Hello World for Linux (C + musl/gcc)
     linux_c_hello_world_elf: MappedMemory,
     /// Statically compiled Hello World for Linux (Rust + musl/LLVM)
@@ -47,6 +71,8 @@ pub struct InitialUserland {
     linux_c_matrix_mult_elf: MappedMemory,
     // Statically compiled AUX Vec Dump tool.
     linux_c_aux_dump_elf: MappedMemory,
+    /// Programs in the other boot modules, see [`boot_modules`].
+    boot_modules: Vec<BootModule>,
 }
 
 impl InitialUserland {
@@ -73,6 +99,17 @@ impl InitialUserland {
             .entries()
             .for_each(|e| log::trace!("    {} ({} bytes)", e.filename(), e.size()));
 
+        // the memory of the boot module stays mapped forever
+        let tar_data =
+            unsafe { core::slice::from_raw_parts(mapped_mem.begin_ptr(), hip_mem.size() as usize) };
+        let mut fs = FILESYSTEM.lock();
+        for mount_point in [USERLAND_MOUNT_POINT, LIB_MOUNT_POINT] {
+            fs.mount(mount_point, Box::new(TarFs::new(tar_data)))
+                .expect("the userland mount point must be free");
+            log::info!("mounted userland tar at {}", mount_point);
+        }
+        drop(fs);
+
         Self {
             hedron_native_hello_world_rust_elf: Self::map_tar_entry_to_page_aligned_dest(
                 &tar_file,
@@ -80,6 +117,12 @@ impl InitialUserland {
                 root,
             )
             .unwrap(),
+            hedron_native_benchtool_elf: Self::map_tar_entry_to_page_aligned_dest(
+                &tar_file,
+                "native-benchtool-bin",
+                root,
+            )
+            .unwrap(),
             linux_c_hello_world_elf: Self::map_tar_entry_to_page_aligned_dest(
                 &tar_file,
                 "linux_c_hello_world_musl",
@@ -116,12 +159,13 @@ impl InitialUserland {
                 root,
             )
             .unwrap(),
+            boot_modules: boot_modules::load(hip, root),
         }
     }
 
     /// Finds the HipMem descriptor that holds the Tar file with the userland.
     fn find_userland_tar_mem_desc<'a>(hip: &'a HIP, root: &Rc<Process>) -> Option<&'a HipMem> {
-        hip.mem_desc_iterator()
+        hip.mb_module_iterator()
             .map(|hipmem| (hipmem, Self::hip_mem_mb_cmd_str(hipmem, root)))
             .filter(|(_, cmdline)| cmdline.is_some())
             .map(|(hipmem, cmdline)| (hipmem, cmdline.unwrap()))
@@ -132,7 +176,31 @@ impl InitialUserland {
 
     /// Takes a hip mem object of type multiboot and returns the cmdline string
     /// if available.
-    fn hip_mem_mb_cmd_str<'a>(hip_mem_mb: &'a HipMem, root: &Rc<Process>) -> Option<&'a str> {
+    pub fn hip_mem_mb_cmd_str<'a>(hip_mem_mb: &'a HipMem, root: &Rc<Process>) -> Option<&'a str> {
+        let cmdline = Self::hip_mem_mb_cmdline(hip_mem_mb, root)?;
+
+        // the cmdline arg describes the payload, i.e. "userland"
+        let cmdline_arg = if cmdline.contains(' ') {
+            // multiboot boot loaders put something like
+            // './build/roottask-bin--release.elf roottask'
+            // ==> 'roottask'
+            cmdline
+                .split_once(' ')
+                .map(|(_file, first_arg)| first_arg)
+                .unwrap()
+        } else {
+            // SVP UEFI loader put something like
+            // 'roottask'
+            // ==> 'roottask'
+            cmdline
+        };
+
+        Some(cmdline_arg)
+    }
+
+    /// Like [`Self::hip_mem_mb_cmd_str`] but returns the whole cmdline string as the boot
+    /// loader passed it, i.e. maybe with the file name of the module.
+    pub fn hip_mem_mb_cmdline<'a>(hip_mem_mb: &'a HipMem, root: &Rc<Process>) -> Option<&'a str> {
         if hip_mem_mb.typ() != HipMemType::MbModule {
             return None;
         }
@@ -153,28 +221,11 @@ impl InitialUserland {
         let cmdline = cmdline.as_str();
         if cmdline.is_empty() {
             log::debug!("cmdline string is empty");
-            return None;
+            None
         } else {
             log::debug!("cmdline string: {}", cmdline);
+            Some(cmdline)
         }
-
-        // the cmdline arg describes the payload, i.e. "userland"
-        let cmdline_arg = if cmdline.contains(' ') {
-            // multiboot boot loaders put something like
-            // './build/roottask-bin--release.elf roottask'
-            // ==> 'roottask'
-            cmdline
-                .split_once(' ')
-                .map(|(_file, first_arg)| first_arg)
-                .unwrap()
-        } else {
-            // SVP UEFI loader put something like
-            // 'roottask'
-            // ==> 'roottask'
-            cmdline
-        };
-
-        Some(cmdline_arg)
     }
 
     /// Extracts an ELF from the TarArchive and maps it to a page-aligned destination with
@@ -185,61 +236,136 @@ impl InitialUserland {
         root: &Rc<Process>,
     ) -> Option<MappedMemory> {
         let entry = tar.entries().find(|e| e.filename().contains(filename))?;
-        // looks a bit weird, but is fine for a quick & dirty solution. I need some destination, where I can map the new memory too!
-        let phys_src = VIRT_MEM_ALLOC
-            .lock()
-            .next_addr(Layout::from_size_align(entry.size(), PAGE_SIZE).unwrap());
-
         log::debug!("mapping memory for Userland file: {}", filename);
-        let mut mapped_mem = ROOT_MEM_MAPPER.lock().mmap(
-            root,
-            root,
-            phys_src,
-            None,
-            calc_page_count(entry.size()) as u64,
-            MemCapPermissions::all(),
-        );
-
-        // copy data to mapped mem
-        unsafe {
-            let src_ptr = entry.data().as_ptr();
-            let dest_ptr = mapped_mem.mem_as_ptr_mut();
-            core::ptr::copy_nonoverlapping(src_ptr, dest_ptr, entry.size());
-        }
-
-        Some(mapped_mem)
+        Some(copy_to_page_aligned_dest(entry.data(), root))
     }
 
-    /// Bootstraps the userland. Starts processes in the process manager.
+    /// Bootstraps the userland. Starts processes in the process manager: first the
+    /// programs in the boot modules (see [`boot_modules`]), then the programs listed in the
+    /// [`AUTOSTART_FILE`] of the tarball, if it exists. Without both, the hard-coded
+    /// default programs get started. In safe mode, only the
+    /// [`safe_mode::RECOVERY_SHELL`] gets started. The self-test, if enabled, runs
+    /// alongside, see [`selfcheck`].
     pub fn bootstrap(&self) {
+        if selfcheck::is_enabled() {
+            let argv = vec![
+                String::from(selfcheck::SELFCHECK_SHELL),
+                String::from("selfcheck"),
+            ];
+            if start_program(selfcheck::SELFCHECK_SHELL, argv, Vec::new()).is_none() {
+                log::warn!("selfcheck: can't start {}", selfcheck::SELFCHECK_SHELL);
+            }
+        }
+        if safe_mode::is_enabled() {
+            if start_program(safe_mode::RECOVERY_SHELL, Vec::new(), Vec::new()).is_none() {
+                log::warn!("safe mode: can't start {}", safe_mode::RECOVERY_SHELL);
+            }
+            return;
+        }
+        let started_modules = self
+            .boot_modules
+            .iter()
+            .filter(|module| module.start().is_some())
+            .count();
+        let autostart = with_file(ROOTTASK_PROCESS_PID, AUTOSTART_FILE, |data| {
+            String::from(core::str::from_utf8(data).expect("autostart file must be UTF-8"))
+        });
+        if let Some(autostart) = autostart {
+            autostart
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .for_each(|line| {
+                    let (path, argv, envp) = parse_autostart_line(line);
+                    if start_program(path, argv, envp).is_none() {
+                        log::error!("can't start program '{}' from {}", path, AUTOSTART_FILE);
+                    }
+                });
+            return;
+        }
+        if started_modules > 0 {
+            return;
+        }
+
         /*PROCESS_MNG.lock().start_process(
             self.hedron_native_hello_world_rust_elf.clone(),
             String::from("Hedron-native Hello World Rust+libhrstd [RELEASE]"),
-            SyscallAbi::NativeHedron,
+            Some(SyscallAbi::NativeHedron),
+            vec![String::from("native-hello-world-rust-bin")],
+            Vec::new(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
         );*/
 
         /*PROCESS_MNG.lock().start_process(
             self.linux_c_hello_world_elf.clone(),
             String::from("Linux C Hello World Musl"),
-            SyscallAbi::Linux,
+            Some(SyscallAbi::Linux),
+            vec![String::from("linux_c_hello_world_musl")],
+            Vec::new(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
         );*/
 
         /*PROCESS_MNG.lock().start_process(
             self.linux_rust_hello_world_elf.clone(),
             String::from("Linux Hello World Hybrid (Rust + musl) [RELEASE]"),
-            SyscallAbi::Linux,
+            Some(SyscallAbi::Linux),
+            vec![String::from("linux_rust_hello_world_musl")],
+            Vec::new(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
         );*/
 
         PROCESS_MNG.lock().start_process(
             self.linux_rust_hybrid_benchmark_elf.clone(),
             String::from("My Diplom thesis evaluation benchmark. [RELEASE]"),
-            SyscallAbi::Linux,
+            Some(SyscallAbi::Linux),
+            vec![String::from("linux_rust_hybrid_benchmark")],
+            Vec::new(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
         );
 
+        // lists and compares the runs in /var/bench; start it once the benchmarks are done
+        /*PROCESS_MNG.lock().start_process(
+            self.hedron_native_benchtool_elf.clone(),
+            String::from("Bench Tool"),
+            Some(SyscallAbi::NativeHedron),
+            vec![String::from("native-benchtool-bin")],
+            Vec::new(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
+        );*/
+
+        // measures the scheduling latency under different time quanta; the CPU hog and the
+        // probe must run at the same time
+        /*start_program("/bin/native-sched-hog-bin", Vec::new(), Vec::new());
+        start_program("/bin/native-sched-probe-bin", Vec::new(), Vec::new());*/
+
+        // measures how the file system service scales with concurrent worker processes
+        // start_program("/bin/native-fsbench-bin", Vec::new(), Vec::new());
+
+        // file server as user app; other apps find it under "fileserver" in the name service
+        // start_program("/bin/native-fileserver-bin", Vec::new(), Vec::new());
+
+        // interactive shell on the serial console; launches further programs at runtime
+        // start_program("/bin/native-shell-bin", Vec::new(), Vec::new());
+
         /*PROCESS_MNG.lock().start_process(
             self.linux_c_matrix_mult_elf.clone(),
             String::from("C Matrix Multiplication"),
-            SyscallAbi::Linux,
+            Some(SyscallAbi::Linux),
+            vec![String::from("linux_c_matrix_mult_musl")],
+            Vec::new(),
+            SchedulingParams::DEFAULT,
+            None,
+            false,
         );*/
     }
 }
@@ -251,3 +377,114 @@ pub enum HedronUserlandError {
 
 /// The first argument describing the given payload as userland file.
 const USERLAND_MB_CMDLINE_ARGUMENT: &str = "userland";
+
+/// Path where the userland tarball gets mounted in the file system.
+pub const USERLAND_MOUNT_POINT: &str = "/bin";
+
+/// Path where the userland tarball gets mounted a second time, so that dynamically linked
+/// programs find their program interpreter and shared libraries under the usual paths, such
+/// as `/lib/ld-musl-x86_64.so.1`.
+pub const LIB_MOUNT_POINT: &str = "/lib";
+
+/// Optional file in the userland tarball that lists the programs to start: one program
+/// per line in the form `[KEY=VALUE ...] PATH [ARG ...]`, such as
+/// `FOO=BAR /bin/linux_c_hello_world_musl --verbose`. Lines starting with `#` are
+/// comments. Arguments and variables can't contain spaces.
+pub const AUTOSTART_FILE: &str = "/bin/autostart";
+
+/// Starts the program at the given path of the file system, for example a program of the
+/// userland tarball below [`USERLAND_MOUNT_POINT`]. The syscall ABI gets detected from
+/// the ELF file. If `argv` is empty, the path becomes the only argument. Returns the ID
+/// of the new process.
+pub fn start_program(path: &str, mut argv: Vec<String>, envp: Vec<String>) -> Option<ProcessId> {
+    let root = PROCESS_MNG.lock().root().clone();
+    let elf_file = with_file(ROOTTASK_PROCESS_PID, path, |data| {
+        copy_to_page_aligned_dest(data, &root)
+    })?;
+    if argv.is_empty() {
+        argv.push(String::from(path));
+    }
+    PROCESS_MNG.lock().start_process(
+        elf_file,
+        String::from(path),
+        None,
+        argv,
+        envp,
+        SchedulingParams::DEFAULT,
+        None,
+        false,
+    )
+}
+
+/// Splits a line of the [`AUTOSTART_FILE`] into the path, the arguments, and the
+/// environment variables of the program. The path is the first argument.
+fn parse_autostart_line(line: &str) -> (&str, Vec<String>, Vec<String>) {
+    let mut words = line.split_whitespace().peekable();
+    let mut envp = Vec::new();
+    while let Some(env) = words.next_if(|word| word.contains('=')) {
+        envp.push(String::from(env));
+    }
+    let path = words.peek().copied().unwrap_or_default();
+    let argv = words.map(String::from).collect();
+    (path, argv, envp)
+}
+
+/// Applies `fnc` to the content of the file at `path`, which gets opened on behalf of
+/// process `pid`. Returns `None` if the file can't be read.
+pub fn with_file<T>(pid: ProcessId, path: &str, fnc: impl FnOnce(&[u8]) -> T) -> Option<T> {
+    let mut fs = FILESYSTEM.lock();
+    let fd = fs
+        .open_or_create_file(pid, path, FsOpenFlags::O_RDONLY, 0)
+        .ok()?;
+    let res = fs
+        .fstat(pid, fd)
+        .and_then(|stat| fs.read_file(pid, fd, stat.st_size() as usize))
+        .map(fnc)
+        .ok();
+    fs.close_file(pid, fd).unwrap();
+    res
+}
+
+/// Copies the data (i.e. an ELF file) to a page-aligned destination with RWX rights.
+pub fn copy_to_page_aligned_dest(data: &[u8], root: &Rc<Process>) -> MappedMemory {
+    // TODO this will never be freed.. Q&D
+    let phys_src = PHYS_FRAME_ALLOC
+        .lock()
+        .alloc(calc_page_count(data.len()))
+        .expect("out of physical memory");
+
+    let mut mapped_mem = ROOT_MEM_MAPPER.lock().mmap(
+        root,
+        root,
+        phys_src,
+        None,
+        calc_page_count(data.len()) as u64,
+        MemCapPermissions::all(),
+    );
+
+    // copy data to mapped mem
+    unsafe {
+        let dest_ptr = mapped_mem.mem_as_ptr_mut();
+        core::ptr::copy_nonoverlapping(data.as_ptr(), dest_ptr, data.len());
+    }
+
+    mapped_mem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_autostart_line() {
+        let (path, argv, envp) = parse_autostart_line("/bin/foo");
+        assert_eq!(path, "/bin/foo");
+        assert_eq!(argv, vec!["/bin/foo"]);
+        assert!(envp.is_empty());
+
+        let (path, argv, envp) = parse_autostart_line("FOO=BAR  A=1 /bin/foo -v  x=y");
+        assert_eq!(path, "/bin/foo");
+        assert_eq!(argv, vec!["/bin/foo", "-v", "x=y"]);
+        assert_eq!(envp, vec!["FOO=BAR", "A=1"]);
+    }
+}
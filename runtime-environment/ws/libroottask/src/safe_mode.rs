@@ -0,0 +1,33 @@
+//! Safe mode of the roottask: a recovery environment for diagnosing boot-time
+//! regressions, enabled by the boot argument `safe_mode=on` (see [`crate::rt::boot_args`]).
+//!
+//! In safe mode, the roottask only provides its core services: the console, the debug
+//! console, and a read-only file system with the userland tarball at
+//! [`crate::rt::userland::USERLAND_MOUNT_POINT`]. It skips the benchmarks, the device
+//! drivers, and the programs of [`crate::rt::userland::AUTOSTART_FILE`]. Instead, it
+//! starts [`RECOVERY_SHELL`], if the userland contains it.
+
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+
+/// Program that the roottask starts in safe mode instead of the autostart programs.
+pub const RECOVERY_SHELL: &str = "/bin/native-shell-bin";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables the safe mode. Must be called before the roottask initializes the
+/// drivers and starts the userland. The file system becomes read-only in safe mode.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+    libfileserver::FILESYSTEM.lock().set_read_only(enabled);
+    if enabled {
+        log::warn!("safe mode: only core services, read-only file system, no autostart");
+    }
+}
+
+/// Returns true if the roottask runs in safe mode.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
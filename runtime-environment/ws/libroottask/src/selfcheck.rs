@@ -0,0 +1,26 @@
+//! Self-test of the services during boot, enabled by the boot argument `selfcheck=on` (see
+//! [`crate::rt::boot_args`]). The roottask then starts [`SELFCHECK_SHELL`] with the
+//! built-in command `selfcheck`, which sends a canary request to each service and prints a
+//! health report, also in safe mode. Useful after porting the system to new hardware or
+//! to another revision of Hedron.
+
+use core::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+
+/// The shell that runs the self-test.
+pub const SELFCHECK_SHELL: &str = "/bin/native-shell-bin";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables the self-test. Must be called before the roottask starts the
+/// userland.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Returns true if the roottask runs the self-test during boot.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
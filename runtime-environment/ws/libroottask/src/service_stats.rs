@@ -0,0 +1,307 @@
+//! Statistics of the service calls. [`crate::pt_multiplex`] records the size of the
+//! request and the handling latency of each call in a histogram per service. They are
+//! readable as files below [`STATS_MOUNT_POINT`], e.g. `/proc/services/fs/stats`, hence
+//! performance investigations need no rebuild with extra counters.
+//!
+//! Each line of a file is a key followed by values, separated by spaces. A histogram has
+//! one line per bucket: the name of the histogram, the inclusive upper bound of the bucket
+//! (`inf` for the last one), and the number of values in the bucket, e.g.
+//! `latency_ns 2000 17`. The buckets are not cumulative.
+
+use crate::services::name::builtin_services;
+use crate::time;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use libfileserver::{
+    FileStat,
+    FsBackend,
+    FsError,
+    INode,
+    FILESYSTEM,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Where the statistics appear in the file system.
+pub const STATS_MOUNT_POINT: &str = "/proc/services";
+
+/// Number of buckets of a [`Histogram`].
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// Upper bound of the first bucket of the request sizes. The UTCB transfers whole words.
+const REQUEST_BYTES_FIRST_BOUND: u64 = 8;
+
+/// Upper bound of the first bucket of the latencies.
+const LATENCY_NS_FIRST_BOUND: u64 = 1000;
+
+/// The files are read-only regular files.
+const FILE_MODE: u32 = 0o100444;
+
+static STATS: SimpleMutex<[ServiceStats; ServiceId::count() as usize]> =
+    SimpleMutex::new([ServiceStats::new(); ServiceId::count() as usize]);
+
+/// Mounts the statistics at [`STATS_MOUNT_POINT`]. The heap must be initialized.
+pub fn init() {
+    FILESYSTEM
+        .lock()
+        .mount(STATS_MOUNT_POINT, Box::new(ServiceStatsFs::new()))
+        .expect("the mount point of the service statistics must be free");
+}
+
+/// Records a call of `service` with a request of `request_bytes` that took `ticks` to
+/// handle.
+pub fn record(service: ServiceId, request_bytes: u64, ticks: u64) {
+    STATS.lock()[service.val() as usize].record(request_bytes, time::ticks_to_ns(ticks));
+}
+
+/// Histogram with buckets whose bounds double, starting at a given bound. The last bucket
+/// counts all larger values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    first_bound: u64,
+    counts: [u64; HISTOGRAM_BUCKETS],
+}
+
+impl Histogram {
+    pub const fn new(first_bound: u64) -> Self {
+        Self {
+            first_bound,
+            counts: [0; HISTOGRAM_BUCKETS],
+        }
+    }
+
+    /// Returns the inclusive upper bound of the bucket or `None` for the last bucket.
+    pub fn bound(&self, bucket: usize) -> Option<u64> {
+        (bucket < HISTOGRAM_BUCKETS - 1).then(|| self.first_bound << bucket)
+    }
+
+    pub fn record(&mut self, value: u64) {
+        let bucket = (0..HISTOGRAM_BUCKETS)
+            .find(|bucket| self.bound(*bucket).map_or(true, |bound| value <= bound))
+            .unwrap();
+        self.counts[bucket] += 1;
+    }
+
+    /// Returns the number of values in each bucket.
+    pub fn counts(&self) -> &[u64; HISTOGRAM_BUCKETS] {
+        &self.counts
+    }
+
+    /// Appends one line per bucket to `out`, see module description.
+    fn render(&self, name: &str, out: &mut String) {
+        for (bucket, count) in self.counts.iter().enumerate() {
+            match self.bound(bucket) {
+                Some(bound) => writeln!(out, "{} {} {}", name, bound, count),
+                None => writeln!(out, "{} inf {}", name, count),
+            }
+            .unwrap();
+        }
+    }
+}
+
+/// Statistics of a single service.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ServiceStats {
+    pub calls: u64,
+    pub request_bytes: Histogram,
+    pub latency_ns: Histogram,
+    pub total_latency_ns: u64,
+    pub max_latency_ns: u64,
+}
+
+impl ServiceStats {
+    const fn new() -> Self {
+        Self {
+            calls: 0,
+            request_bytes: Histogram::new(REQUEST_BYTES_FIRST_BOUND),
+            latency_ns: Histogram::new(LATENCY_NS_FIRST_BOUND),
+            total_latency_ns: 0,
+            max_latency_ns: 0,
+        }
+    }
+
+    fn record(&mut self, request_bytes: u64, latency_ns: u64) {
+        self.calls += 1;
+        self.request_bytes.record(request_bytes);
+        self.latency_ns.record(latency_ns);
+        self.total_latency_ns += latency_ns;
+        self.max_latency_ns = self.max_latency_ns.max(latency_ns);
+    }
+
+    /// Returns the content of the stats file, see module description.
+    pub fn render(&self, name: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "service {}", name).unwrap();
+        writeln!(out, "calls {}", self.calls).unwrap();
+        writeln!(out, "latency_ns_total {}", self.total_latency_ns).unwrap();
+        writeln!(out, "latency_ns_max {}", self.max_latency_ns).unwrap();
+        self.request_bytes.render("request_bytes", &mut out);
+        self.latency_ns.render("latency_ns", &mut out);
+        out
+    }
+}
+
+/// Read-only [`FsBackend`] with a file `/<name>/stats` per service of the roottask. Each
+/// open takes a new snapshot of the statistics; all open handles of a file share it.
+#[derive(Debug)]
+pub struct ServiceStatsFs {
+    /// Content of the files by [`INode`], taken during the last open.
+    snapshots: BTreeMap<INode, Vec<u8>>,
+}
+
+impl ServiceStatsFs {
+    pub const fn new() -> Self {
+        Self {
+            snapshots: BTreeMap::new(),
+        }
+    }
+
+    /// The [`INode`] of a file is the number of the service plus one.
+    fn service(i_node: INode) -> Result<(&'static str, ServiceId), FsError> {
+        builtin_services()
+            .iter()
+            .copied()
+            .find(|(_, service)| service.val() + 1 == i_node.val())
+            .ok_or(FsError::NotFound)
+    }
+}
+
+impl Default for ServiceStatsFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FsBackend for ServiceStatsFs {
+    fn open(
+        &mut self,
+        _caller: ProcessId,
+        path: &str,
+        flags: FsOpenFlags,
+        _umode: u16,
+    ) -> Result<INode, FsError> {
+        if flags.can_write() {
+            return Err(FsError::Perm);
+        }
+        let i_node = self.lookup(path)?;
+        let (name, service) = Self::service(i_node)?;
+        let snapshot = STATS.lock()[service.val() as usize];
+        self.snapshots
+            .insert(i_node, snapshot.render(name).into_bytes());
+        Ok(i_node)
+    }
+
+    fn lookup(&self, path: &str) -> Result<INode, FsError> {
+        let name = path
+            .strip_prefix('/')
+            .and_then(|path| path.strip_suffix("/stats"))
+            .ok_or(FsError::NotFound)?;
+        builtin_services()
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, service)| INode::new(service.val() + 1))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn owner(&self, i_node: INode) -> Result<Option<ProcessId>, FsError> {
+        // the files belong to no process
+        Self::service(i_node).map(|_| None)
+    }
+
+    fn read(&mut self, i_node: INode, offset: usize, count: usize) -> Result<&[u8], FsError> {
+        let data = self.snapshots.get(&i_node).ok_or(FsError::NotFound)?;
+        let from_index = offset.min(data.len());
+        let to_index = (from_index + count).min(data.len());
+        Ok(&data[from_index..to_index])
+    }
+
+    fn write(&mut self, _i_node: INode, _offset: usize, _data: &[u8]) -> Result<usize, FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn stat(&self, i_node: INode) -> Result<FileStat, FsError> {
+        Self::service(i_node)?;
+        let size = self.snapshots.get(&i_node).map_or(0, Vec::len);
+        Ok(FileStat::new(i_node.val(), FILE_MODE, size as i64))
+    }
+
+    fn unlink(&mut self, _path: &str) -> Result<(), FsError> {
+        Err(FsError::Perm)
+    }
+
+    fn readdir(&self, dir: &str) -> Vec<String> {
+        builtin_services()
+            .iter()
+            .map(|(name, _)| format!("/{}/stats", name))
+            .filter(|path| path.starts_with(dir))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::new(8);
+        assert_eq!(histogram.bound(0), Some(8));
+        assert_eq!(histogram.bound(2), Some(32));
+        assert_eq!(histogram.bound(HISTOGRAM_BUCKETS - 1), None);
+        histogram.record(0);
+        histogram.record(8);
+        histogram.record(9);
+        histogram.record(u64::MAX);
+        assert_eq!(histogram.counts()[0], 2);
+        assert_eq!(histogram.counts()[1], 1);
+        assert_eq!(histogram.counts()[HISTOGRAM_BUCKETS - 1], 1);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut stats = ServiceStats::new();
+        stats.record(16, 1500);
+        stats.record(24, 500);
+        let text = stats.render("fs");
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[..4],
+            [
+                "service fs",
+                "calls 2",
+                "latency_ns_total 2000",
+                "latency_ns_max 1500"
+            ]
+        );
+        assert!(lines.contains(&"request_bytes 16 1"));
+        assert!(lines.contains(&"request_bytes 32 1"));
+        assert!(lines.contains(&"request_bytes inf 0"));
+        assert!(lines.contains(&"latency_ns 1000 1"));
+        assert!(lines.contains(&"latency_ns 2000 1"));
+        assert_eq!(lines.len(), 4 + 2 * HISTOGRAM_BUCKETS);
+    }
+
+    #[test]
+    fn test_service_stats_fs() {
+        let mut fs = ServiceStatsFs::new();
+        assert!(fs.lookup("/fs/stats").is_ok());
+        assert!(fs.lookup("/fs").is_err());
+        assert!(fs.lookup("/unknown/stats").is_err());
+        assert!(fs.open(1, "/fs/stats", FsOpenFlags::O_RDWR, 0).is_err());
+
+        let i_node = fs.open(1, "/fs/stats", FsOpenFlags::O_RDONLY, 0).unwrap();
+        let size = fs.stat(i_node).unwrap().st_size() as usize;
+        assert!(size > 0);
+        let content = fs.read(i_node, 0, size).unwrap();
+        assert!(content.starts_with(b"service fs\ncalls "));
+        assert_eq!(fs.owner(i_node), Ok(None));
+
+        assert_eq!(fs.readdir("/fs/"), ["/fs/stats"]);
+        assert_eq!(fs.readdir("/").len(), builtin_services().len());
+    }
+}
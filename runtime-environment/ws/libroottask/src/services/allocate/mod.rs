@@ -9,7 +9,11 @@ use libhrstd::kobjects::{
 use libhrstd::libhedron::CapSel;
 use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
-use libhrstd::rt::services::allocate::AllocRequest;
+use libhrstd::rt::services::allocate::{
+    AllocRequest,
+    AllocateService,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
 use libhrstd::service_ids::ServiceId;
 
 /// Creates a new ALLOCATOR service PT, which can be delegated to a new process.
@@ -36,15 +40,25 @@ pub fn allocate_service_handler(
 
     log::trace!("alloc_request: {alloc_request:?}");
 
-    if alloc_request.is_allocation() {
-        let addr = process
-            .memory_manager_mut()
-            .mmap(alloc_request.to_layout(), process);
-        utcb.store_data(&addr).unwrap();
-    } else {
-        let addr = alloc_request.ptr().unwrap();
-        process.memory_manager_mut().munmap(addr, process);
-    }
+    rpc_serve::<AllocateService, _>(alloc_request, utcb, |alloc_request| {
+        if let AllocRequest::HeapRegion { size } = alloc_request {
+            // null tells the allocator of the app that the address space is exhausted
+            process
+                .memory_manager_mut()
+                .reserve_lazy_region(size, process)
+                .unwrap_or(0)
+        } else if alloc_request.is_allocation() {
+            // null tells the allocator of the app that the memory is exhausted
+            process
+                .memory_manager_mut()
+                .mmap(alloc_request.to_layout(), process)
+                .unwrap_or(0)
+        } else {
+            let addr = alloc_request.ptr().unwrap();
+            process.memory_manager_mut().munmap(addr, process);
+            addr
+        }
+    });
 
     /*let brk = process
         .memory_manager_mut()
@@ -0,0 +1,105 @@
+//! Build info service. Reports the build metadata of the roottask, the API version
+//! of the running Hedron kernel, and the platform properties from the HIP. The same information is available to Linux processes
+//! in the file `/proc/version`.
+
+use crate::process::Process;
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::format;
+use alloc::rc::Rc;
+use libhrstd::build_info::BuildInfo;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::libhedron::HIP;
+use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
+use libhrstd::rt::services::build_info::{
+    BuildInfoServiceResponse,
+    PlatformInfo,
+};
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Path of the file that contains the version line.
+const PROC_VERSION_PATH: &str = "/proc/version";
+
+/// The reply of the service. Initialized once during startup.
+static BUILD_INFO: SimpleMutex<Option<BuildInfoServiceResponse>> = SimpleMutex::new(None);
+
+/// Stores the build info of the roottask, logs it, and creates `/proc/version`.
+/// The heap must be initialized.
+pub fn init(roottask: BuildInfo, hip: &HIP) {
+    let response = BuildInfoServiceResponse {
+        roottask,
+        hedron_api_ver: hip.api_ver(),
+        platform: platform_info(hip),
+    };
+    // the integration test scripts grep for this line
+    log::info!("build info: {}", response.roottask);
+    log::info!("platform: {:x?}", response.platform);
+
+    let version_line = format!(
+        "Hedron (API version {}) {}\n",
+        response.hedron_api_ver, response.roottask
+    );
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    let fd = fs
+        .open_or_create_file(
+            ROOTTASK_PROCESS_PID,
+            PROC_VERSION_PATH,
+            FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+            0o444,
+        )
+        .unwrap();
+    fs.write_file(ROOTTASK_PROCESS_PID, fd, version_line.as_bytes())
+        .unwrap();
+    fs.close_file(ROOTTASK_PROCESS_PID, fd).unwrap();
+    drop(fs);
+
+    let mut lock = BUILD_INFO.lock();
+    assert!(lock.is_none(), "init only allowed once!");
+    lock.replace(response);
+}
+
+/// Collects the platform properties from the typed HIP queries.
+fn platform_info(hip: &HIP) -> PlatformInfo {
+    PlatformInfo {
+        online_cpus: hip.online_cpu_count() as u64,
+        usable_mem: hip.usable_mem_iterator().map(|mem| mem.size()).sum(),
+        largest_usable_region: hip
+            .largest_usable_region()
+            .map(|region| (region.start, region.end - region.start)),
+        boot_modules: hip.mb_module_iterator().count() as u64,
+    }
+}
+
+/// Creates a new BUILD INFO service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::BuildInfoService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the BUILD INFO Portal.
+pub fn build_info_service_handler(
+    _pt: &Rc<PtObject>,
+    _process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let lock = BUILD_INFO.lock();
+    let response = lock.as_ref().expect("call init first");
+    utcb.store_data(response).unwrap();
+    *do_reply = true;
+}
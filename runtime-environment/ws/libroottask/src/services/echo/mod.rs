@@ -3,9 +3,15 @@
 use crate::mem::VIRT_MEM_ALLOC;
 use crate::process::Process;
 use crate::pt_multiplex::roottask_generic_portal_callback;
-use crate::stack::StaticStack;
+use crate::smp;
+use crate::stack::{
+    stack_top_of_top_page,
+    StaticStack,
+};
+use alloc::collections::BTreeMap;
 use alloc::rc::Rc;
 use core::alloc::Layout;
+use core::arch::asm;
 use libhrstd::cap_space::root::RootCapSpace;
 use libhrstd::kobjects::{
     LocalEcObject,
@@ -23,31 +29,60 @@ use libhrstd::libhedron::{
 use libhrstd::service_ids::ServiceId;
 use libhrstd::sync::mutex::SimpleMutex;
 
+/// Stack of the local EC of the raw echo service on CPU 0. The local ECs of the other CPUs
+/// get stacks of the same size from the heap.
 static mut RAW_ECHO_SERVICE_STACK: StaticStack<4> = StaticStack::new();
 
-static RAW_ECHO_SERVICE_LOCAL_EC: SimpleMutex<Option<Rc<LocalEcObject>>> = SimpleMutex::new(None);
+/// Local ECs of the raw echo service, indexed by CPU.
+static RAW_ECHO_SERVICE_LOCAL_ECS: SimpleMutex<BTreeMap<u64, Rc<LocalEcObject>>> =
+    SimpleMutex::new(BTreeMap::new());
 
-/// Creates a local EC
+/// Creates a local EC per online CPU and a raw echo service PT for the roottask on each
+/// CPU. The foreign syscall handler calls the PT of its CPU, see
+/// [`RootCapSpace::calc_raw_echo_service_pt_sel`].
 pub fn init_echo_raw_service(root: &Process) {
-    let mut lock = RAW_ECHO_SERVICE_LOCAL_EC.lock();
-    assert!(lock.is_none(), "init only permitted once!");
+    let mut lock = RAW_ECHO_SERVICE_LOCAL_ECS.lock();
+    assert!(lock.is_empty(), "init only permitted once!");
 
-    // make sure we reserve enough from virtual address space for the UTCB
-    let utcb_addr = VIRT_MEM_ALLOC
-        .lock()
-        .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
-    let echo_ec = LocalEcObject::create(
-        RootCapSpace::RootRawEchoServiceEc.val(),
-        &root.pd_obj(),
-        unsafe { RAW_ECHO_SERVICE_STACK.get_stack_top_ptr() } as u64,
-        utcb_addr,
-    );
+    for cpu in smp::online_cpus_iter() {
+        let stack = if cpu == 0 {
+            unsafe { &RAW_ECHO_SERVICE_STACK }
+        } else {
+            StaticStack::new_leaked()
+        };
+        unsafe {
+            stack.activate_guard_page(
+                RootCapSpace::RootPd.val(),
+                format!("raw echo local EC of CPU {}", cpu),
+            )
+        };
+        // make sure we reserve enough from virtual address space for the UTCB
+        let utcb_addr = VIRT_MEM_ALLOC
+            .lock()
+            .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
+        let echo_ec = LocalEcObject::create(
+            RootCapSpace::calc_raw_echo_service_ec_sel(cpu),
+            &root.pd_obj(),
+            stack.get_stack_top_ptr() as u64,
+            utcb_addr,
+            cpu,
+        );
 
-    lock.replace(echo_ec);
+        // adds itself to the local EC
+        let _ = PtObject::create(
+            RootCapSpace::calc_raw_echo_service_pt_sel(cpu),
+            &echo_ec,
+            Mtd::empty(),
+            raw_echo_pt_cb,
+            PtCtx::Service(ServiceId::RawEchoService),
+        );
+
+        lock.insert(cpu, echo_ec);
+    }
 }
 
-/// Creates the service PTs for the ECHO service and the RAW ECHO service for the roottask
-/// itself.
+/// Creates the service PT for the ECHO service for the roottask itself and returns it
+/// together with the RAW ECHO service PT of the roottask. Both can be called on CPU 0.
 pub(super) fn create_service_pts_fot_roottask(
     service_ec: &Rc<LocalEcObject>,
 ) -> (Rc<PtObject>, Rc<PtObject>) {
@@ -60,22 +95,27 @@ pub(super) fn create_service_pts_fot_roottask(
         PtCtx::Service(ServiceId::EchoService),
     );
 
-    let raw_echo_service_pt = PtObject::create(
-        RootCapSpace::RootRawEchoServicePt.val(),
-        &RAW_ECHO_SERVICE_LOCAL_EC.lock().as_ref().unwrap(),
-        Mtd::empty(),
-        raw_echo_pt_cb,
-        PtCtx::Service(ServiceId::RawEchoService),
-    );
+    let raw_echo_service_pt = RAW_ECHO_SERVICE_LOCAL_ECS
+        .lock()
+        .get(&0)
+        .expect("call init_echo_raw_service first!")
+        .portals()
+        .iter()
+        .find(|pt| pt.cap_sel() == RootCapSpace::RootRawEchoServicePt.val())
+        .unwrap()
+        .clone();
 
     (echo_service_pt, raw_echo_service_pt)
 }
 
 /// Creates the service PTs for the ECHO service and the RAW ECHO service.
 /// Only returns the service PT used by my PT multiplexing mechanism.
+///
+/// The raw echo service PT is bound to the local EC of the raw echo service on `cpu`.
 pub fn create_service_pts(
     base_cap_sel: CapSel,
     service_ec: &Rc<LocalEcObject>,
+    cpu: u64,
 ) -> (Rc<PtObject>, Rc<PtObject>) {
     // adds itself to the local EC
     let echo_service_pt = PtObject::create(
@@ -88,7 +128,7 @@ pub fn create_service_pts(
 
     let raw_echo_service_pt = PtObject::create(
         base_cap_sel + ServiceId::RawEchoService.val(),
-        &RAW_ECHO_SERVICE_LOCAL_EC.lock().as_ref().unwrap(),
+        RAW_ECHO_SERVICE_LOCAL_ECS.lock().get(&cpu).unwrap(),
         Mtd::empty(),
         raw_echo_pt_cb,
         PtCtx::Service(ServiceId::RawEchoService),
@@ -107,8 +147,14 @@ pub fn echo_service_handler(
     *do_reply = true;
 }
 
-/// Cheap handler for the raw echo service PT.
+/// Cheap handler for the raw echo service PTs of all CPUs.
+///
+/// Hedron enters the handler with the stack pointer of the previous reply, i.e. the stack
+/// top of the local EC. The handler recovers the stack top from the current stack pointer
+/// instead of looking up its local EC, which would require a lock.
 fn raw_echo_pt_cb(_: PortalIdentifier) -> ! {
     // log::trace!("raw echo pt called!");
-    sys_reply(unsafe { RAW_ECHO_SERVICE_STACK.get_stack_top_ptr() } as u64)
+    let stack_ptr: u64;
+    unsafe { asm!("mov {}, rsp", out(reg) stack_ptr) };
+    sys_reply(stack_top_of_top_page(stack_ptr))
 }
@@ -0,0 +1,85 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_accept,
+    is_inet_socket,
+    write_sockaddr_in,
+};
+use crate::services::foreign_syscall::linux::unix_socket::{
+    set_nonblocking,
+    wait_if_blocking,
+    write_sockaddr_un,
+    SOCK_NONBLOCK,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/accept.2.html>.
+/// Also handles `accept4`, of whose flags only `SOCK_NONBLOCK` has an effect. Waits until
+/// a connection is pending, unless the listening socket is non-blocking. The address of
+/// the peer of a local socket is always reported as unnamed.
+#[derive(Debug)]
+pub struct AcceptSyscall {
+    fd: FileDescriptor,
+    u_addr: u64,
+    u_addr_len: u64,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for AcceptSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_addr: syscall.arg1(),
+            u_addr_len: syscall.arg2(),
+            flags: 0,
+        }
+    }
+}
+
+impl AcceptSyscall {
+    /// `accept4`, which has the flags as additional argument.
+    pub(super) fn new_accept4(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            flags: syscall.arg3(),
+            ..Self::from(syscall)
+        }
+    }
+}
+
+impl LinuxSyscallImpl for AcceptSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if is_inet_socket(process, self.fd) {
+            return match inet_accept(process, self.fd) {
+                Ok((fd, peer)) => {
+                    set_nonblocking(process, fd, self.flags & SOCK_NONBLOCK != 0);
+                    write_sockaddr_in(process, self.u_addr, self.u_addr_len, peer);
+                    LinuxSyscallResult::new_success(fd.val())
+                }
+                Err(err) => LinuxSyscallResult::new_error(err),
+            };
+        }
+        let res = libfileserver::FILESYSTEM
+            .lock()
+            .accept(process.pid(), self.fd);
+        match res {
+            Ok(fd) => {
+                set_nonblocking(process, fd, self.flags & SOCK_NONBLOCK != 0);
+                write_sockaddr_un(process, self.u_addr, self.u_addr_len, None);
+                LinuxSyscallResult::new_success(fd.val())
+            }
+            Err(err) => {
+                LinuxSyscallResult::new_error(wait_if_blocking(process, self.fd, err.into()))
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_PATH_MAX;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::resolve_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::rt::services::fs::FsAccessMode;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/access.2.html>.
+#[derive(Debug)]
+pub struct AccessSyscall {
+    // null terminated path name
+    pathname: *const u8,
+    mode: u64,
+}
+
+impl From<&GenericLinuxSyscall> for AccessSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+            mode: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for AccessSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, self.pathname as u64, LINUX_PATH_MAX as u64)
+            .clone();
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        check_access(process, pathname, self.mode)
+    }
+}
+
+/// Checks the permissions of the process for a file. Shared by `access()` and
+/// `faccessat()`.
+pub(super) fn check_access(process: &Process, pathname: &str, mode: u64) -> LinuxSyscallResult {
+    let mode = match u32::try_from(mode).ok().and_then(FsAccessMode::from_bits) {
+        Some(mode) => mode,
+        None => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+    };
+    let pathname = resolve_path(process, pathname);
+    match libfileserver::FILESYSTEM
+        .lock()
+        .access(process.pid(), &pathname, mode)
+    {
+        Ok(()) => LinuxSyscallResult::new_success(0),
+        Err(err) => LinuxSyscallResult::new_error(err.into()),
+    }
+}
@@ -0,0 +1,40 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::timer;
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/alarm.2.html>.
+/// The timer service raises SIGALRM for the process when the alarm expires.
+#[derive(Debug)]
+pub struct AlarmSyscall {
+    seconds: u64,
+}
+
+impl From<&GenericLinuxSyscall> for AlarmSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            seconds: syscall.arg0(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for AlarmSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        const NSEC_PER_SEC: u64 = 1_000_000_000;
+        let remaining_ns =
+            timer::set_alarm(process.pid(), self.seconds.saturating_mul(NSEC_PER_SEC));
+        // Linux never reports zero seconds for an active alarm
+        let remaining_secs =
+            remaining_ns.map_or(0, |ns| ((ns + NSEC_PER_SEC - 1) / NSEC_PER_SEC).max(1));
+        LinuxSyscallResult::new_success(remaining_secs)
+    }
+}
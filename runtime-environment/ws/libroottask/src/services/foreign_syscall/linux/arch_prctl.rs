@@ -1,4 +1,5 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
@@ -18,7 +19,9 @@ use libhrstd::libhedron::UtcbDataException;
 /// operations, or as an unsigned long *, for the "get" operations.
 #[derive(Debug)]
 pub struct ArchPrctlSyscall {
-    subfunction: ArchPrctlSubfunction,
+    /// `Err` with the raw code for subfunctions that are unknown, such as the CET
+    /// subfunctions that glibc probes.
+    subfunction: Result<ArchPrctlSubfunction, u64>,
     /// integer for the set operations or pointer for get operations.
     addr: *const u8,
 }
@@ -26,7 +29,7 @@ pub struct ArchPrctlSyscall {
 impl From<&GenericLinuxSyscall> for ArchPrctlSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            subfunction: ArchPrctlSubfunction::try_from(syscall.arg0()).unwrap(),
+            subfunction: ArchPrctlSubfunction::try_from(syscall.arg0()).map_err(|_| syscall.arg0()),
             addr: syscall.arg1() as _,
         }
     }
@@ -38,9 +41,16 @@ impl LinuxSyscallImpl for ArchPrctlSyscall {
         utcb_exc: &mut UtcbDataException,
         _process: &Rc<Process>,
     ) -> LinuxSyscallResult {
+        let subfunction = match self.subfunction {
+            Ok(subfunction) => subfunction,
+            Err(code) => {
+                log::debug!("arch_prctl subfunction {:#x} is not supported", code);
+                return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+            }
+        };
         utcb_exc.mtd |= Mtd::FS_GS;
 
-        match self.subfunction {
+        match subfunction {
             ArchPrctlSubfunction::ArchSetGs => utcb_exc.gs.base = self.addr as _,
             ArchPrctlSubfunction::ArchSetFs => utcb_exc.fs.base = self.addr as _,
             ArchPrctlSubfunction::ArchGetFs => {
@@ -0,0 +1,61 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_bind,
+    is_inet_socket,
+    read_sockaddr_in,
+};
+use crate::services::foreign_syscall::linux::unix_socket::read_sockaddr_un;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/bind.2.html>.
+#[derive(Debug)]
+pub struct BindSyscall {
+    fd: FileDescriptor,
+    u_addr: u64,
+    addr_len: u64,
+}
+
+impl From<&GenericLinuxSyscall> for BindSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_addr: syscall.arg1(),
+            addr_len: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for BindSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if is_inet_socket(process, self.fd) {
+            let res = read_sockaddr_in(process, self.u_addr, self.addr_len)
+                .and_then(|addr| inet_bind(process, self.fd, addr));
+            return match res {
+                Ok(_) => LinuxSyscallResult::new_success(0),
+                Err(err) => LinuxSyscallResult::new_error(err),
+            };
+        }
+        let name = match read_sockaddr_un(process, self.u_addr, self.addr_len) {
+            Ok(name) => name,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        match libfileserver::FILESYSTEM
+            .lock()
+            .bind(process.pid(), self.fd, &name)
+        {
+            Ok(_) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
+    }
+}
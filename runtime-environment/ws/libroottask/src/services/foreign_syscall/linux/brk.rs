@@ -27,11 +27,9 @@ impl LinuxSyscallImpl for BrkSyscall {
         _utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        log::trace!("BRK  in={:?}", self.addr);
         let brk = process
             .memory_manager_mut()
             .increase_break(self.addr as u64, process);
-        log::trace!("BRK  out={:?}", brk as *const u8);
         LinuxSyscallResult::new_success(brk)
     }
 }
@@ -0,0 +1,53 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/chdir.2.html>. The roottask
+/// keeps the current working directory of each process and resolves its relative paths.
+/// The directory is stored without symbolic links, like `getcwd()` reports it on Linux.
+#[derive(Debug)]
+pub struct ChdirSyscall {
+    // null terminated path name
+    path: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for ChdirSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            path: syscall.arg0() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ChdirSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let path = read_path(process, self.path);
+        if path.is_empty() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ENOENT);
+        }
+        let fs = libfileserver::FILESYSTEM.lock();
+        let res = fs
+            .canonicalize(process.pid(), &path)
+            .and_then(|path| fs.stat_path(process.pid(), &path).map(|stat| (path, stat)));
+        drop(fs);
+        match res {
+            Ok((path, stat)) if stat.is_dir() => {
+                process.set_cwd(path);
+                LinuxSyscallResult::new_success(0)
+            }
+            Ok(_) => LinuxSyscallResult::new_error(LinuxErrorCode::ENOTDIR),
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
+    }
+}
@@ -1,24 +1,31 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::MAPPED_AREAS;
+use crate::time;
 use alloc::rc::Rc;
 use core::mem::size_of;
 use libhrstd::libhedron::UtcbDataException;
 
+/// Implementation of <https://man7.org/linux/man-pages/man2/clock_gettime.2.html>.
+/// The realtime clocks return the wall clock of the roottask, the monotonic and boot
+/// clocks the time since boot, see [`time`]. There is no CPU time accounting, hence
+/// the CPU-time clocks fail with `EINVAL`.
 #[derive(Debug)]
 pub struct ClockGetTimeSyscall {
-    _clk_id: ClockId,
-    timespec: *mut timespec,
+    clk_id: u64,
+    u_ptr_tp: u64,
 }
 
 impl From<&GenericLinuxSyscall> for ClockGetTimeSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            _clk_id: unsafe { core::mem::transmute(syscall.arg0()) },
-            timespec: syscall.arg1() as *mut _,
+            clk_id: syscall.arg0(),
+            u_ptr_tp: syscall.arg1(),
         }
     }
 }
@@ -27,27 +34,87 @@ impl LinuxSyscallImpl for ClockGetTimeSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        log::trace!("ClockGetTime: {:?}", self);
-        unsafe { core::ptr::write_bytes(self.timespec.cast::<u8>(), 0, size_of::<timespec>()) };
-        LinuxSyscallResult::new_success(0)
+        let ns = match ClockId::from_raw(self.clk_id) {
+            Some(ClockId::Realtime | ClockId::RealtimeCoarse | ClockId::Realtimealarm) => {
+                time::realtime_ns()
+            }
+            Some(
+                ClockId::Monotonic
+                | ClockId::MonotonicRaw
+                | ClockId::MonotonicCoarse
+                | ClockId::Boottime
+                | ClockId::BoottimeAlarm,
+            ) => time::monotonic_ns(),
+            _ => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+        };
+        match write_user_timespec(process, self.u_ptr_tp, ns) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Writes nanoseconds as a `struct timespec` into the address space of the process.
+pub(super) fn write_user_timespec(
+    process: &Rc<Process>,
+    u_ptr: u64,
+    ns: u64,
+) -> Result<(), LinuxErrorCode> {
+    if u_ptr == 0 {
+        return Err(LinuxErrorCode::EFAULT);
     }
+
+    let u_page_offset = u_ptr & 0xfff;
+    let mut mapping =
+        MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, u_ptr, size_of::<timespec>() as u64);
+    *mapping.mem_with_offset_as_mut::<timespec>(u_page_offset as usize) = timespec::from_nanos(ns);
+    Ok(())
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
 #[repr(C)]
-struct timespec {
+pub(super) struct timespec {
     /// seconds
-    tv_sec: usize,
+    pub(super) tv_sec: usize,
     /// nanoseconds
-    tv_nsec: u64,
+    pub(super) tv_nsec: u64,
+}
+
+impl timespec {
+    const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+    /// Returns the total amount of nanoseconds or `None`, if the value is invalid
+    /// in the sense of POSIX, i.e. `tv_sec` is negative or `tv_nsec` is not in range
+    /// `0..1_000_000_000`. Saturates at `u64::MAX`, i.e. after about 584 years.
+    pub(super) fn as_nanos(&self) -> Option<u64> {
+        if (self.tv_sec as i64) < 0 || self.tv_nsec >= Self::NSEC_PER_SEC {
+            None
+        } else {
+            Some(
+                (self.tv_sec as u64)
+                    .saturating_mul(Self::NSEC_PER_SEC)
+                    .saturating_add(self.tv_nsec),
+            )
+        }
+    }
+
+    pub(super) const fn from_nanos(ns: u64) -> Self {
+        Self {
+            tv_sec: (ns / Self::NSEC_PER_SEC) as usize,
+            tv_nsec: ns % Self::NSEC_PER_SEC,
+        }
+    }
 }
 
 #[allow(unused)]
 #[repr(u64)]
 #[derive(Debug)]
-enum ClockId {
+pub(super) enum ClockId {
     Realtime = 0,
     Monotonic = 1,
     ProcessCpuTimeId = 2,
@@ -59,3 +126,39 @@ enum ClockId {
     Realtimealarm = 8,
     BoottimeAlarm = 9,
 }
+
+impl ClockId {
+    /// Returns the clock with the ID that a process passed, if it exists.
+    pub(super) const fn from_raw(clk_id: u64) -> Option<Self> {
+        Some(match clk_id {
+            0 => Self::Realtime,
+            1 => Self::Monotonic,
+            2 => Self::ProcessCpuTimeId,
+            3 => Self::ThreadCpuTimeId,
+            4 => Self::MonotonicRaw,
+            5 => Self::RealtimeCoarse,
+            6 => Self::MonotonicCoarse,
+            7 => Self::Boottime,
+            8 => Self::Realtimealarm,
+            9 => Self::BoottimeAlarm,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timespec_as_nanos() {
+        let ts = |tv_sec, tv_nsec| timespec { tv_sec, tv_nsec };
+        assert_eq!(ts(2, 5).as_nanos(), Some(2_000_000_005));
+        assert_eq!(ts(0, 1_000_000_000).as_nanos(), None);
+        assert_eq!(ts(-1_i64 as usize, 0).as_nanos(), None);
+        assert_eq!(
+            ts(i64::MAX as usize, 999_999_999).as_nanos(),
+            Some(u64::MAX)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::clock_gettime::ClockId;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::nanosleep::{
+    read_user_timespec,
+    sleep_until,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::time;
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// The `clock_nanosleep` syscall. Works like [`super::nanosleep::NanoSleepSyscall`] but
+/// supports absolute deadlines via `TIMER_ABSTIME`. The monotonic clocks use the TSC as
+/// time base, i.e. their absolute values are nanoseconds since the TSC was reset. Absolute
+/// values of `CLOCK_REALTIME` are nanoseconds since the Unix epoch; they are converted to
+/// the monotonic clock once, i.e. a later change of the wall clock doesn't affect the sleep.
+/// Like on Linux, `rem` is only written if a signal interrupts a relative sleep.
+#[derive(Debug)]
+pub struct ClockNanoSleepSyscall {
+    clk_id: u64,
+    flags: u64,
+    u_ptr_req: u64,
+    u_ptr_rem: u64,
+}
+
+impl ClockNanoSleepSyscall {
+    /// Flag that marks the requested time as absolute deadline.
+    const TIMER_ABSTIME: u64 = 1;
+
+    /// Converts the absolute value `req_ns` of the clock to nanoseconds of the monotonic
+    /// clock. Deadlines in the past are reached immediately.
+    fn monotonic_deadline_ns(&self, req_ns: u64, monotonic_ns: u64, realtime_ns: u64) -> u64 {
+        if self.clk_id == ClockId::Realtime as u64 {
+            monotonic_ns.saturating_add(req_ns.saturating_sub(realtime_ns))
+        } else {
+            req_ns
+        }
+    }
+
+    fn is_supported_clock(&self) -> bool {
+        self.clk_id == ClockId::Realtime as u64
+            || self.clk_id == ClockId::Monotonic as u64
+            || self.clk_id == ClockId::Boottime as u64
+    }
+}
+
+impl From<&GenericLinuxSyscall> for ClockNanoSleepSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            clk_id: syscall.arg0(),
+            flags: syscall.arg1(),
+            u_ptr_req: syscall.arg2(),
+            u_ptr_rem: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ClockNanoSleepSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if !self.is_supported_clock() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+
+        let req_ns = match read_user_timespec(process, self.u_ptr_req) {
+            Ok(ns) => ns,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+
+        if self.flags & Self::TIMER_ABSTIME != 0 {
+            let deadline_ns =
+                self.monotonic_deadline_ns(req_ns, time::monotonic_ns(), time::realtime_ns());
+            sleep_until(process, time::ns_to_ticks(deadline_ns), 0)
+        } else {
+            let tsc_deadline = time::tsc_now().saturating_add(time::ns_to_ticks(req_ns));
+            sleep_until(process, tsc_deadline, self.u_ptr_rem)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic_deadline() {
+        let syscall = |clk_id: ClockId| ClockNanoSleepSyscall {
+            clk_id: clk_id as u64,
+            flags: ClockNanoSleepSyscall::TIMER_ABSTIME,
+            u_ptr_req: 0,
+            u_ptr_rem: 0,
+        };
+        // the wall clock says 2022-01-01
+        let realtime_ns = 1_640_995_200_000_000_000;
+        let realtime = syscall(ClockId::Realtime);
+        assert_eq!(
+            realtime.monotonic_deadline_ns(realtime_ns + 1_000_000_000, 5_000, realtime_ns),
+            1_000_005_000
+        );
+        assert_eq!(
+            realtime.monotonic_deadline_ns(realtime_ns - 1, 5_000, realtime_ns),
+            5_000,
+            "in the past"
+        );
+        assert_eq!(
+            syscall(ClockId::Monotonic).monotonic_deadline_ns(7_000, 5_000, realtime_ns),
+            7_000
+        );
+    }
+}
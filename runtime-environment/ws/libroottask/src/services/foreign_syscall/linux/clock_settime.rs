@@ -0,0 +1,56 @@
+use crate::process::{
+    is_privileged,
+    Process,
+};
+use crate::services::foreign_syscall::linux::clock_gettime::ClockId;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::nanosleep::read_user_timespec;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::time;
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::rt::services::system_time::TimeAdjustment;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/clock_settime.2.html>.
+/// Only `CLOCK_REALTIME` can be set. The wall clock jumps to the new time. Only
+/// privileged processes may set it, see [`is_privileged`].
+#[derive(Debug)]
+pub struct ClockSetTimeSyscall {
+    clk_id: u64,
+    u_ptr_tp: u64,
+}
+
+impl From<&GenericLinuxSyscall> for ClockSetTimeSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            clk_id: syscall.arg0(),
+            u_ptr_tp: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ClockSetTimeSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.clk_id != ClockId::Realtime as u64 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let realtime_ns = match read_user_timespec(process, self.u_ptr_tp) {
+            Ok(ns) => ns,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        if !is_privileged(process.pid()) {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EPERM);
+        }
+
+        time::set_realtime_ns(process.pid(), realtime_ns, TimeAdjustment::Step);
+        LinuxSyscallResult::new_success(0)
+    }
+}
@@ -1,4 +1,5 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::spawn::vfork;
 use crate::services::foreign_syscall::linux::{
     GenericLinuxSyscall,
     LinuxSyscallImpl,
@@ -10,29 +11,29 @@ use alloc::rc::Rc;
 use libhrstd::libhedron::UtcbDataException;
 //use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
 
+/// Implementation of <https://man7.org/linux/man-pages/man2/clone.2.html>. Processes have
+/// a single thread, hence only the `clone()` of `vfork()` and `posix_spawn()` is supported,
+/// i.e. `CLONE_VM | CLONE_VFORK` without `CLONE_THREAD`. See
+/// [`crate::services::foreign_syscall::linux::spawn`].
 #[derive(Debug)]
 pub struct CloneSyscall {
-    // poorly documented ... I took this from the musl code
-    // maybe this is the right linux code: https://elixir.bootlin.com/linux/v5.16.10/source/kernel/fork.c#L2677
-    _fnc_ptr: u64,
-    _child_stack: u64,
-    _flags: u64,
-    // flags: CloneFlags,
-    _args: *const u8,
+    // Order of the raw syscall on x86_64, which differs from the `clone()` of libc.
+    // See https://elixir.bootlin.com/linux/v5.16.10/source/kernel/fork.c#L2677
+    flags: CloneFlags,
+    child_stack: u64,
     _ptid: u64,
+    _ctid: u64,
     _tls: u64,
 }
 
 impl From<&GenericLinuxSyscall> for CloneSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            _fnc_ptr: syscall.arg0(),
-            _child_stack: syscall.arg1(),
-            // flags: CloneFlags::from_bits(syscall.arg2()).unwrap(),
-            _flags: syscall.arg2(),
-            _args: syscall.arg3() as *const _,
-            _ptid: syscall.arg4(),
-            _tls: syscall.arg5(),
+            flags: CloneFlags::from_bits_truncate(syscall.arg0()),
+            child_stack: syscall.arg1(),
+            _ptid: syscall.arg2(),
+            _ctid: syscall.arg3(),
+            _tls: syscall.arg4(),
         }
     }
 }
@@ -40,10 +41,14 @@ impl From<&GenericLinuxSyscall> for CloneSyscall {
 impl LinuxSyscallImpl for CloneSyscall {
     fn handle(
         &self,
-        _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        log::trace!("Clone: {:#?}", self);
+        if self.flags.contains(CloneFlags::VM | CloneFlags::VFORK)
+            && !self.flags.contains(CloneFlags::THREAD)
+        {
+            return vfork(utcb_exc, process, self.child_stack);
+        }
 
         // Quick and dirty: afterwards, the Haskell binary wants to access
         // the memory behind the TLS address
@@ -1,9 +1,15 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll;
+use crate::services::foreign_syscall::linux::event_fd;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::shm_fd;
+use crate::services::foreign_syscall::linux::timer_fd;
+use crate::services::foreign_syscall::linux::unix_socket;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::network;
 use alloc::rc::Rc;
 use libfileserver::FileDescriptor;
 use libhrstd::libhedron::UtcbDataException;
@@ -28,11 +34,19 @@ impl LinuxSyscallImpl for CloseSyscall {
         _utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        libfileserver::FILESYSTEM
+        let res = libfileserver::FILESYSTEM
             .lock()
-            .close_file(process.pid(), self.fd)
-            .unwrap();
+            .close_file(process.pid(), self.fd);
+        network::close_socket(process.pid(), self.fd);
+        epoll::close_fd(process, self.fd);
+        event_fd::close(process, self.fd);
+        timer_fd::close(process, self.fd);
+        shm_fd::close(process, self.fd);
+        unix_socket::close(process, self.fd);
 
-        LinuxSyscallResult::new_success(0)
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
     }
 }
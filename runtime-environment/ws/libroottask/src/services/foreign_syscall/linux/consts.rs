@@ -9,3 +9,41 @@ pub const LINUX_NAME_MAX: usize = 255;
 ///
 /// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/limits.h#L13>
 pub const LINUX_PATH_MAX: usize = 4096;
+/// Maximum number of `struct iovec` of the vectored I/O system calls, such as `writev()`.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/uio.h#L27>
+pub const LINUX_IOV_MAX: u64 = 1024;
+/// Maximum number of file descriptors in a `fd_set` of `select()`. `poll()` accepts the same
+/// number of file descriptors, which is the default limit of open files on Linux.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/posix_types.h#L27>
+pub const LINUX_FD_SETSIZE: u64 = 1024;
+/// Special value for the `dirfd` argument of the `*at()` system calls: relative paths
+/// are relative to the current working directory.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L94>
+pub const LINUX_AT_FDCWD: i32 = -100;
+/// Flag of the `*at()` system calls: don't follow a symbolic link at the end of the path.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L98>
+pub const LINUX_AT_SYMLINK_NOFOLLOW: u64 = 0x100;
+/// Flag of `unlinkat()`: remove a directory instead of a file, like `rmdir()`.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L104>
+pub const LINUX_AT_REMOVEDIR: u64 = 0x200;
+/// Flag of `linkat()`: follow a symbolic link at the end of the path.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L106>
+pub const LINUX_AT_SYMLINK_FOLLOW: u64 = 0x400;
+/// Flag of the `*at()` system calls: don't trigger the automount of the last component.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L108>
+pub const LINUX_AT_NO_AUTOMOUNT: u64 = 0x800;
+/// Flag of the `*at()` system calls: an empty path refers to the file of `dirfd` itself.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L110>
+pub const LINUX_AT_EMPTY_PATH: u64 = 0x1000;
+/// Flags of `statx()` that select how to synchronize with remote file systems.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fcntl.h#L112>
+pub const LINUX_AT_STATX_SYNC_TYPE: u64 = 0x6000;
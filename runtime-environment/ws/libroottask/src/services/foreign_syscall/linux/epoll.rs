@@ -0,0 +1,187 @@
+//! Common functionality of the epoll syscalls. An epoll instance is a set of file
+//! descriptors of interest (the interest list) that `epoll_wait` checks with
+//! [`fd_events::ready_events`]. The file server reserves the file descriptor of the
+//! instance, similar to UDP sockets.
+//!
+//! All file descriptors are level-triggered. `EPOLLET` is accepted but has no effect;
+//! programs that use edge-triggered notifications read until `EAGAIN` anyway and cope
+//! with additional notifications. Epoll instances can't be nested.
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events;
+use crate::services::foreign_syscall::linux::fd_events::{
+    FdKind,
+    POLL_ALWAYS,
+};
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use libfileserver::FileDescriptor;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Adds a file descriptor to the interest list.
+pub(super) const EPOLL_CTL_ADD: u64 = 1;
+/// Removes a file descriptor from the interest list.
+pub(super) const EPOLL_CTL_DEL: u64 = 2;
+/// Changes the events of a file descriptor in the interest list.
+pub(super) const EPOLL_CTL_MOD: u64 = 3;
+
+/// Disables the file descriptor after its first notification until `EPOLL_CTL_MOD`.
+const EPOLLONESHOT: u32 = 1 << 30;
+
+/// All epoll instances of all processes.
+static INSTANCES: SimpleMutex<BTreeMap<(ProcessId, FileDescriptor), EpollInstance>> =
+    SimpleMutex::new(BTreeMap::new());
+
+/// Same as `struct epoll_event` of Linux, which is packed on x86_64.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C, packed)]
+pub(super) struct EpollEvent {
+    pub(super) events: u32,
+    pub(super) data: u64,
+}
+
+#[derive(Debug, Default)]
+struct EpollInstance {
+    interests: BTreeMap<FileDescriptor, EpollEvent>,
+}
+
+/// Creates a new epoll instance and returns its file descriptor.
+pub(super) fn create(process: &Rc<Process>) -> FileDescriptor {
+    let fd = libfileserver::FILESYSTEM.lock().reserve_fd(process.pid());
+    INSTANCES
+        .lock()
+        .insert((process.pid(), fd), EpollInstance::default());
+    fd
+}
+
+/// Checks if the file descriptor refers to an epoll instance.
+pub(super) fn is_epoll(process: &Rc<Process>, fd: FileDescriptor) -> bool {
+    INSTANCES.lock().contains_key(&(process.pid(), fd))
+}
+
+/// Implements `epoll_ctl`. Like on Linux, files can't be added, because they are always
+/// ready.
+pub(super) fn ctl(
+    process: &Rc<Process>,
+    epfd: FileDescriptor,
+    op: u64,
+    fd: FileDescriptor,
+    event: Option<EpollEvent>,
+) -> Result<(), LinuxErrorCode> {
+    match fd_events::fd_kind(process, epfd) {
+        Some(FdKind::Epoll) => {}
+        Some(_) => return Err(LinuxErrorCode::EINVAL),
+        None => return Err(LinuxErrorCode::EBADF),
+    }
+    match fd_events::fd_kind(process, fd).ok_or(LinuxErrorCode::EBADF)? {
+        FdKind::File | FdKind::Shm => return Err(LinuxErrorCode::EPERM),
+        FdKind::Epoll => return Err(LinuxErrorCode::EINVAL),
+        _ => {}
+    }
+
+    let mut instances = INSTANCES.lock();
+    let interests = &mut instances.get_mut(&(process.pid(), epfd)).unwrap().interests;
+    match (op, event) {
+        (EPOLL_CTL_ADD, Some(event)) if !interests.contains_key(&fd) => {
+            interests.insert(fd, event);
+        }
+        (EPOLL_CTL_ADD, Some(_)) => return Err(LinuxErrorCode::EEXIST),
+        (EPOLL_CTL_MOD, Some(event)) => {
+            *interests.get_mut(&fd).ok_or(LinuxErrorCode::ENOENT)? = event;
+        }
+        (EPOLL_CTL_DEL, _) => {
+            interests.remove(&fd).ok_or(LinuxErrorCode::ENOENT)?;
+        }
+        (EPOLL_CTL_ADD | EPOLL_CTL_MOD, None) => return Err(LinuxErrorCode::EFAULT),
+        _ => return Err(LinuxErrorCode::EINVAL),
+    }
+    Ok(())
+}
+
+/// Returns at most `max_events` events of file descriptors in the interest list that are
+/// ready. File descriptors with `EPOLLONESHOT` get disabled by this.
+pub(super) fn ready_events(
+    process: &Rc<Process>,
+    epfd: FileDescriptor,
+    max_events: usize,
+) -> Vec<EpollEvent> {
+    let mut ready = Vec::new();
+    for (fd, interest) in interests(process, epfd) {
+        if ready.len() == max_events {
+            break;
+        }
+        let events = events_of(process, fd, interest);
+        if events == 0 {
+            continue;
+        }
+        ready.push(EpollEvent {
+            events,
+            data: interest.data,
+        });
+        if interest.events & EPOLLONESHOT != 0 {
+            if let Some(instance) = INSTANCES.lock().get_mut(&(process.pid(), epfd)) {
+                if let Some(interest) = instance.interests.get_mut(&fd) {
+                    interest.events = 0;
+                }
+            }
+        }
+    }
+    ready
+}
+
+/// Checks if a file descriptor in the interest list is ready. Doesn't disable file
+/// descriptors with `EPOLLONESHOT`.
+pub(super) fn has_ready_events(process: &Rc<Process>, epfd: FileDescriptor) -> bool {
+    interests(process, epfd)
+        .into_iter()
+        .any(|(fd, interest)| events_of(process, fd, interest) != 0)
+}
+
+/// Destroys the epoll instance, if the file descriptor refers to one, and removes the
+/// file descriptor from the interest lists of the process. Called when the file
+/// descriptor gets closed.
+pub(super) fn close_fd(process: &Rc<Process>, fd: FileDescriptor) {
+    let mut instances = INSTANCES.lock();
+    instances.remove(&(process.pid(), fd));
+    instances
+        .iter_mut()
+        .filter(|((pid, _), _)| *pid == process.pid())
+        .for_each(|(_, instance)| {
+            instance.interests.remove(&fd);
+        });
+}
+
+/// Destroys all epoll instances of a process that exited.
+pub(super) fn release_process(pid: ProcessId) {
+    INSTANCES.lock().retain(|(fd_pid, _), _| *fd_pid != pid);
+}
+
+/// Returns a copy of the interest list. The lock must not be held while checking the file
+/// descriptors.
+fn interests(process: &Rc<Process>, epfd: FileDescriptor) -> Vec<(FileDescriptor, EpollEvent)> {
+    INSTANCES
+        .lock()
+        .get(&(process.pid(), epfd))
+        .map(|instance| {
+            instance
+                .interests
+                .iter()
+                .map(|(fd, interest)| (*fd, *interest))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the events of the file descriptor that are ready and of interest. Like on Linux,
+/// errors and hang-ups are always of interest, unless the file descriptor is disabled.
+fn events_of(process: &Rc<Process>, fd: FileDescriptor, interest: EpollEvent) -> u32 {
+    if interest.events == 0 {
+        return 0;
+    }
+    // the bits of the epoll events are the same as of the poll events
+    let ready = fd_events::ready_events(process, fd).unwrap_or(0);
+    (interest.events | u32::from(POLL_ALWAYS)) & u32::from(ready)
+}
@@ -0,0 +1,38 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/epoll_create.2.html>. The size
+/// is only a hint, but must be positive. See [`super::epoll`].
+#[derive(Debug)]
+pub struct EpollCreateSyscall {
+    size: i32,
+}
+
+impl From<&GenericLinuxSyscall> for EpollCreateSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            size: syscall.arg0() as i32,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EpollCreateSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.size <= 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        LinuxSyscallResult::new_success(epoll::create(process).val())
+    }
+}
@@ -0,0 +1,42 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Close the file descriptor during `execve()`. Accepted, but has no effect, see
+/// [`super::spawn`].
+const EPOLL_CLOEXEC: u64 = 0o2000000;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/epoll_create1.2.html>. The only
+/// flag is `EPOLL_CLOEXEC`. See [`super::epoll`].
+#[derive(Debug)]
+pub struct EpollCreate1Syscall {
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for EpollCreate1Syscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            flags: syscall.arg0(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EpollCreate1Syscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.flags & !EPOLL_CLOEXEC != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        LinuxSyscallResult::new_success(epoll::create(process).val())
+    }
+}
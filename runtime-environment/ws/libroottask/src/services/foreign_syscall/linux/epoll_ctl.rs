@@ -0,0 +1,50 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll;
+use crate::services::foreign_syscall::linux::epoll::EpollEvent;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::read_from_user;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/epoll_ctl.2.html>.
+/// See [`super::epoll`].
+#[derive(Debug)]
+pub struct EpollCtlSyscall {
+    epfd: FileDescriptor,
+    op: u64,
+    fd: FileDescriptor,
+    u_event: u64,
+}
+
+impl From<&GenericLinuxSyscall> for EpollCtlSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            epfd: FileDescriptor::new(syscall.arg0()),
+            op: syscall.arg1(),
+            fd: FileDescriptor::new(syscall.arg2()),
+            u_event: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EpollCtlSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let event = match self.u_event {
+            0 => None,
+            u_event => Some(read_from_user::<EpollEvent>(process, u_event)),
+        };
+        match epoll::ctl(process, self.epfd, self.op, self.fd, event) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
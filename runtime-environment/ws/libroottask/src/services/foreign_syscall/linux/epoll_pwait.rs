@@ -0,0 +1,48 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll_wait::epoll_wait;
+use crate::services::foreign_syscall::linux::fd_events::deadline_after_ms;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/epoll_pwait.2.html>. Same as
+/// `epoll_wait`; the signal mask has no effect while waiting.
+#[derive(Debug)]
+pub struct EpollPWaitSyscall {
+    epfd: FileDescriptor,
+    u_events: u64,
+    max_events: i32,
+    timeout_ms: i32,
+}
+
+impl From<&GenericLinuxSyscall> for EpollPWaitSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            epfd: FileDescriptor::new(syscall.arg0()),
+            u_events: syscall.arg1(),
+            max_events: syscall.arg2() as i32,
+            timeout_ms: syscall.arg3() as i32,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EpollPWaitSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        epoll_wait(
+            process,
+            self.epfd,
+            self.u_events,
+            self.max_events,
+            deadline_after_ms(self.timeout_ms),
+        )
+    }
+}
@@ -0,0 +1,97 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll;
+use crate::services::foreign_syscall::linux::epoll::EpollEvent;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events::{
+    deadline_after_ms,
+    fd_kind,
+    wait_for_events,
+    FdKind,
+};
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::write_to_user;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/epoll_wait.2.html>. Blocks
+/// like `poll`; a negative timeout in milliseconds waits forever. See [`super::epoll`].
+#[derive(Debug)]
+pub struct EpollWaitSyscall {
+    epfd: FileDescriptor,
+    u_events: u64,
+    max_events: i32,
+    timeout_ms: i32,
+}
+
+impl From<&GenericLinuxSyscall> for EpollWaitSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            epfd: FileDescriptor::new(syscall.arg0()),
+            u_events: syscall.arg1(),
+            max_events: syscall.arg2() as i32,
+            timeout_ms: syscall.arg3() as i32,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EpollWaitSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        epoll_wait(
+            process,
+            self.epfd,
+            self.u_events,
+            self.max_events,
+            deadline_after_ms(self.timeout_ms),
+        )
+    }
+}
+
+/// Waits for the epoll instance of `epoll_wait` and `epoll_pwait` until the TSC reaches
+/// `tsc_deadline` and writes at most `max_events` events to user memory.
+pub(super) fn epoll_wait(
+    process: &Rc<Process>,
+    epfd: FileDescriptor,
+    u_events: u64,
+    max_events: i32,
+    tsc_deadline: Option<u64>,
+) -> LinuxSyscallResult {
+    let max_events = match usize::try_from(max_events) {
+        Ok(0) | Err(_) => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+        Ok(max_events) => max_events,
+    };
+    match fd_kind(process, epfd) {
+        Some(FdKind::Epoll) => {}
+        Some(_) => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+        None => return LinuxSyscallResult::new_error(LinuxErrorCode::EBADF),
+    }
+
+    let mut events = Vec::new();
+    let res = wait_for_events(process, tsc_deadline, || {
+        events = epoll::ready_events(process, epfd, max_events);
+        events.len()
+    });
+    match res {
+        Ok(count) => {
+            for (i, event) in events.into_iter().enumerate() {
+                write_to_user(
+                    process,
+                    u_events + (i * size_of::<EpollEvent>()) as u64,
+                    event,
+                );
+            }
+            LinuxSyscallResult::new_success(count as u64)
+        }
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
@@ -1,5 +1,9 @@
+use enum_iterator::IntoEnumIterator;
+use libhrstd::rt::services::fs::FsError;
+use libhrstd::rt::services::shm::ShmServiceError;
+
 /// <https://elixir.bootlin.com/linux/latest/source/include/uapi/asm-generic/errno-base.h#L5>
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, IntoEnumIterator)]
 #[repr(u64)]
 #[allow(unused)]
 pub enum LinuxErrorCode {
@@ -71,10 +75,99 @@ pub enum LinuxErrorCode {
     EDOM = 33,
     /// Math result not representable
     ERANGE = 34,
+    // <https://elixir.bootlin.com/linux/latest/source/include/uapi/asm-generic/errno.h>
+    /// Invalid system call number
+    ENOSYS = 38,
+    /// Directory not empty
+    ENOTEMPTY = 39,
+    /// Too many symbolic links encountered
+    ELOOP = 40,
+    /// Socket operation on non-socket
+    ENOTSOCK = 88,
+    /// Destination address required
+    EDESTADDRREQ = 89,
+    /// Message too long
+    EMSGSIZE = 90,
+    /// Protocol wrong type for socket
+    EPROTOTYPE = 91,
+    /// Protocol not supported
+    EPROTONOSUPPORT = 93,
+    /// Socket type not supported
+    ESOCKTNOSUPPORT = 94,
+    /// Operation not supported on transport endpoint
+    EOPNOTSUPP = 95,
+    /// Address family not supported by protocol
+    EAFNOSUPPORT = 97,
+    /// Address already in use
+    EADDRINUSE = 98,
+    /// Cannot assign requested address
+    EADDRNOTAVAIL = 99,
+    /// Network is down
+    ENETDOWN = 100,
+    /// Connection reset by peer
+    ECONNRESET = 104,
+    /// Transport endpoint is already connected
+    EISCONN = 106,
+    /// Transport endpoint is not connected
+    ENOTCONN = 107,
+    /// Connection timed out
+    ETIMEDOUT = 110,
+    /// Connection refused
+    ECONNREFUSED = 111,
+    /// Operation now in progress
+    EINPROGRESS = 115,
+    /// Restart the syscall. Only used inside the roottask and never visible to processes,
+    /// like on Linux; see [`super::restart`].
+    ERESTARTSYS = 512,
 }
 
 impl LinuxErrorCode {
     pub fn val(self) -> u64 {
         self as _
     }
+
+    /// Returns the error code with the given number, if it is known.
+    pub fn from_val(val: u64) -> Option<Self> {
+        Self::into_enum_iter().find(|code| code.val() == val)
+    }
+}
+
+impl From<FsError> for LinuxErrorCode {
+    fn from(err: FsError) -> Self {
+        match err {
+            FsError::NotFound => Self::ENOENT,
+            FsError::Exists => Self::EEXIST,
+            FsError::BadFd => Self::EBADF,
+            FsError::NotDir => Self::ENOTDIR,
+            FsError::IsDir => Self::EISDIR,
+            FsError::NoSpace => Self::ENOSPC,
+            FsError::Perm => Self::EACCES,
+            FsError::ReadOnly => Self::EROFS,
+            FsError::CrossMount => Self::EXDEV,
+            FsError::Loop => Self::ELOOP,
+            FsError::TooLarge => Self::EFBIG,
+            FsError::Busy => Self::EBUSY,
+            FsError::InvalidArgument => Self::EINVAL,
+            FsError::Unsupported => Self::EPERM,
+            FsError::NotEmpty => Self::ENOTEMPTY,
+            FsError::TooManyOpenFiles => Self::EMFILE,
+            FsError::Io => Self::EIO,
+        }
+    }
+}
+
+impl From<ShmServiceError> for LinuxErrorCode {
+    fn from(err: ShmServiceError) -> Self {
+        match err {
+            ShmServiceError::InvalidName => Self::EINVAL,
+            ShmServiceError::InvalidSize => Self::EINVAL,
+            ShmServiceError::Exists => Self::EEXIST,
+            ShmServiceError::NotFound => Self::ENOENT,
+            ShmServiceError::UnknownSegment => Self::EBADF,
+            ShmServiceError::Busy => Self::EBUSY,
+            ShmServiceError::OutOfMemory => Self::ENOMEM,
+            ShmServiceError::PermissionDenied => Self::EACCES,
+            ShmServiceError::LimitExceeded => Self::ENOSPC,
+        }
+    }
 }
@@ -0,0 +1,185 @@
+//! Common functionality of `eventfd` and `eventfd2`. An eventfd is a 64-bit counter that
+//! `write` increases and `read` takes. The file server reserves the file descriptor, similar
+//! to UDP sockets and epoll instances. Readers and writers block like on Linux, unless
+//! `EFD_NONBLOCK` was set: the syscall gets restarted until the counter allows it, see
+//! [`fd_events::wait_for_events`].
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    write_to_user,
+};
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libfileserver::{
+    FileDescriptor,
+    Readiness,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// `read` decreases the counter by one instead of resetting it.
+const EFD_SEMAPHORE: u64 = 1;
+/// Same as `O_NONBLOCK`.
+const EFD_NONBLOCK: u64 = 0o4000;
+/// Same as `O_CLOEXEC`. Accepted, but has no effect on `execve()`, see [`super::spawn`].
+const EFD_CLOEXEC: u64 = 0o2000000;
+
+/// The counter never reaches this value.
+const COUNTER_MAX: u64 = u64::MAX - 1;
+
+/// All eventfds of all processes.
+static EVENT_FDS: SimpleMutex<BTreeMap<(ProcessId, FileDescriptor), EventFd>> =
+    SimpleMutex::new(BTreeMap::new());
+
+#[derive(Debug)]
+struct EventFd {
+    counter: u64,
+    semaphore: bool,
+    nonblocking: bool,
+}
+
+/// Creates a new eventfd with the initial value of the counter and returns its file
+/// descriptor. Fails with `EINVAL` for unknown flags.
+pub(super) fn create(
+    process: &Rc<Process>,
+    initval: u32,
+    flags: u64,
+) -> Result<FileDescriptor, LinuxErrorCode> {
+    if flags & !(EFD_SEMAPHORE | EFD_NONBLOCK | EFD_CLOEXEC) != 0 {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    let fd = libfileserver::FILESYSTEM.lock().reserve_fd(process.pid());
+    let event_fd = EventFd {
+        counter: initval as u64,
+        semaphore: flags & EFD_SEMAPHORE != 0,
+        nonblocking: flags & EFD_NONBLOCK != 0,
+    };
+    EVENT_FDS.lock().insert((process.pid(), fd), event_fd);
+    Ok(fd)
+}
+
+/// Checks if the file descriptor refers to an eventfd.
+pub(super) fn is_event_fd(process: &Rc<Process>, fd: FileDescriptor) -> bool {
+    EVENT_FDS.lock().contains_key(&(process.pid(), fd))
+}
+
+/// Returns if a read and a write would return immediately.
+pub(super) fn readiness(process: &Rc<Process>, fd: FileDescriptor) -> Readiness {
+    EVENT_FDS
+        .lock()
+        .get(&(process.pid(), fd))
+        .map(|event_fd| Readiness {
+            readable: event_fd.counter > 0,
+            writable: event_fd.counter < COUNTER_MAX,
+            hang_up: false,
+        })
+        .unwrap_or_default()
+}
+
+/// Implements `read` on an eventfd. Waits until the counter is not zero and writes the
+/// counter, or one in semaphore mode, as 8-byte integer to user memory.
+pub(super) fn read(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    u_buf: u64,
+    count: usize,
+) -> LinuxSyscallResult {
+    if count < size_of::<u64>() {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+    }
+    let value = wait(process, fd, |event_fd| {
+        if event_fd.counter == 0 {
+            return None;
+        }
+        let value = if event_fd.semaphore {
+            1
+        } else {
+            event_fd.counter
+        };
+        event_fd.counter -= value;
+        Some(value)
+    });
+    match value {
+        Ok(value) => {
+            write_to_user(process, u_buf, value);
+            LinuxSyscallResult::new_success(size_of::<u64>() as u64)
+        }
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
+
+/// Implements `write` on an eventfd. Reads an 8-byte integer from user memory and adds it to
+/// the counter. Waits until the counter can take the value without reaching
+/// `u64::MAX`.
+pub(super) fn write(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    u_buf: u64,
+    count: usize,
+) -> LinuxSyscallResult {
+    if count < size_of::<u64>() {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+    }
+    let value = read_from_user::<u64>(process, u_buf);
+    if value == u64::MAX {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+    }
+    let res = wait(process, fd, |event_fd| {
+        if COUNTER_MAX - event_fd.counter < value {
+            return None;
+        }
+        event_fd.counter += value;
+        Some(())
+    });
+    match res {
+        Ok(()) => LinuxSyscallResult::new_success(size_of::<u64>() as u64),
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
+
+/// Destroys the eventfd, if the file descriptor refers to one.
+pub(super) fn close(process: &Rc<Process>, fd: FileDescriptor) {
+    EVENT_FDS.lock().remove(&(process.pid(), fd));
+}
+
+/// Destroys all eventfds of a process that exited.
+pub(super) fn release_process(pid: ProcessId) {
+    EVENT_FDS.lock().retain(|(fd_pid, _), _| *fd_pid != pid);
+}
+
+/// Calls `f`, which returns `None` if the eventfd is not ready. Then, the syscall waits
+/// and the handler must return the error, i.e. [`LinuxErrorCode::ERESTARTSYS`]. Fails
+/// with `EAGAIN` instead of waiting if the eventfd is non-blocking.
+fn wait<T>(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    f: impl FnOnce(&mut EventFd) -> Option<T>,
+) -> Result<T, LinuxErrorCode> {
+    let mut result = Err(LinuxErrorCode::EAGAIN);
+    // the eventfd is the only file descriptor to wait for; one means done
+    fd_events::wait_for_events(process, None, || {
+        let mut event_fds = EVENT_FDS.lock();
+        let event_fd = match event_fds.get_mut(&(process.pid(), fd)) {
+            Some(event_fd) => event_fd,
+            // closed by another thread in the meantime
+            None => {
+                result = Err(LinuxErrorCode::EBADF);
+                return 1;
+            }
+        };
+        match f(event_fd) {
+            Some(value) => {
+                result = Ok(value);
+                1
+            }
+            None if event_fd.nonblocking => 1,
+            None => 0,
+        }
+    })?;
+    result
+}
@@ -0,0 +1,37 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::event_fd;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/eventfd.2.html>. Same as
+/// `eventfd2` without flags. See [`super::event_fd`].
+#[derive(Debug)]
+pub struct EventFdSyscall {
+    initval: u32,
+}
+
+impl From<&GenericLinuxSyscall> for EventFdSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            initval: syscall.arg0() as u32,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EventFdSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        match event_fd::create(process, self.initval, 0) {
+            Ok(fd) => LinuxSyscallResult::new_success(fd.val()),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
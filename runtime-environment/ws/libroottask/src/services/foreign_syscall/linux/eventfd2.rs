@@ -0,0 +1,40 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::event_fd;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/eventfd2.2.html>, which libc
+/// uses for `eventfd`. Supports `EFD_SEMAPHORE`, `EFD_NONBLOCK`, and `EFD_CLOEXEC`.
+/// See [`super::event_fd`].
+#[derive(Debug)]
+pub struct EventFd2Syscall {
+    initval: u32,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for EventFd2Syscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            initval: syscall.arg0() as u32,
+            flags: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for EventFd2Syscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        match event_fd::create(process, self.initval, self.flags) {
+            Ok(fd) => LinuxSyscallResult::new_success(fd.val()),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::{
+    read_c_str,
+    read_path,
+};
+use crate::services::foreign_syscall::linux::spawn::execve;
+use crate::services::foreign_syscall::linux::unix_socket::read_from_user;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Maximum number of arguments or environment variables. The process service can't
+/// transfer more anyway, because all of them must fit into a UTCB.
+const MAX_STRINGS: usize = 1024;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/execve.2.html>. Only works in
+/// the child of a `vfork()`, where it starts the program as a new process, see
+/// [`crate::services::foreign_syscall::linux::spawn`]. Otherwise, it fails with `ENOSYS`.
+#[derive(Debug)]
+pub struct ExecveSyscall {
+    // null terminated path name
+    pathname: *const u8,
+    // null terminated array of pointers to null terminated strings
+    argv: u64,
+    envp: u64,
+}
+
+impl From<&GenericLinuxSyscall> for ExecveSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+            argv: syscall.arg1(),
+            envp: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ExecveSyscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let path = read_path(process, self.pathname);
+        if path.is_empty() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ENOENT);
+        }
+        let argv = match read_strings(process, self.argv) {
+            Ok(argv) => argv,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        let envp = match read_strings(process, self.envp) {
+            Ok(envp) => envp,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        execve(utcb_exc, process, path, argv, envp)
+    }
+}
+
+/// Reads a null terminated array of strings, such as `argv`, from user memory. A null
+/// pointer is an empty array, like on Linux.
+fn read_strings(process: &Rc<Process>, u_array: u64) -> Result<Vec<String>, LinuxErrorCode> {
+    let mut strings = Vec::new();
+    if u_array == 0 {
+        return Ok(strings);
+    }
+    loop {
+        let u_str =
+            read_from_user::<u64>(process, u_array + (strings.len() * size_of::<u64>()) as u64);
+        if u_str == 0 {
+            return Ok(strings);
+        }
+        if strings.len() == MAX_STRINGS {
+            return Err(LinuxErrorCode::E2BIG);
+        }
+        strings.push(read_c_str(process, u_str as *const _));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::process::{
+    exit_process,
+    Process,
+};
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::spawn::exit_vfork_child;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/exit.2.html> and
+/// <https://man7.org/linux/man-pages/man2/exit_group.2.html>. Processes have a single
+/// thread, hence both terminate the whole process.
+///
+/// The process stops shortly after the syscall returns, see [`exit_process`]. Until then,
+/// libc calls `exit` in a loop. In the child of a `vfork()`, only the child exits and the
+/// parent continues, see [`crate::services::foreign_syscall::linux::spawn`].
+#[derive(Debug)]
+pub struct ExitSyscall {
+    status: i32,
+}
+
+impl From<&GenericLinuxSyscall> for ExitSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            // Linux only keeps the lowest 8 bits
+            status: (syscall.arg0() & 0xff) as i32,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ExitSyscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if let Some(res) = exit_vfork_child(utcb_exc, process, self.status) {
+            return res;
+        }
+        exit_process(process.pid(), self.status);
+        LinuxSyscallResult::new_success(0)
+    }
+}
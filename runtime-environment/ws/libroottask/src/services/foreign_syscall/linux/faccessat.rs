@@ -0,0 +1,62 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::access::check_access;
+use crate::services::foreign_syscall::linux::consts::{
+    LINUX_AT_FDCWD,
+    LINUX_PATH_MAX,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/faccessat.2.html>. There
+/// are no directories that a file descriptor could refer to. Hence, relative paths are
+/// only supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct FaccessAtSyscall {
+    dirfd: i32,
+    // null terminated path name
+    pathname: *const u8,
+    mode: u64,
+}
+
+impl From<&GenericLinuxSyscall> for FaccessAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            pathname: syscall.arg1() as *const _,
+            mode: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for FaccessAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, self.pathname as u64, LINUX_PATH_MAX as u64)
+            .clone();
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        // absolute paths ignore the dirfd
+        if !pathname.starts_with('/') && self.dirfd != LINUX_AT_FDCWD {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ENOTDIR);
+        }
+        check_access(process, pathname, self.mode)
+    }
+}
@@ -0,0 +1,175 @@
+//! Readiness of file descriptors, the common base of `poll`, `select`, and `epoll`. Covers
+//! all kinds of file descriptors of Linux programs: the console, files and local sockets of
+//! the file server, UDP and TCP sockets of the network stack, epoll instances, eventfds, and
+//! timerfds.
+//!
+//! Nothing notifies the roottask when a file descriptor becomes ready. Therefore,
+//! [`wait_for_events`] checks the file descriptors and restarts the syscall if none is
+//! ready, i.e. the process executes it again after a short delay, see [`restart`]. The
+//! handler doesn't block; other processes can use the roottask in the meantime.
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::epoll;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::event_fd;
+use crate::services::foreign_syscall::linux::inet_socket::is_inet_socket;
+use crate::services::foreign_syscall::linux::restart;
+use crate::services::foreign_syscall::linux::shm_fd;
+use crate::services::foreign_syscall::linux::timer_fd;
+use crate::services::{
+    network,
+    stdin,
+};
+use crate::time;
+use alloc::rc::Rc;
+use libfileserver::{
+    FileDescriptor,
+    Readiness,
+};
+
+/// There is data to read.
+pub(super) const POLLIN: u16 = 0x1;
+/// There is urgent data to read. Never reported.
+pub(super) const POLLPRI: u16 = 0x2;
+/// Writing is possible.
+pub(super) const POLLOUT: u16 = 0x4;
+/// Error condition. Always reported, even if not requested.
+pub(super) const POLLERR: u16 = 0x8;
+/// The peer closed the connection. Always reported, even if not requested.
+pub(super) const POLLHUP: u16 = 0x10;
+/// The file descriptor is not open. Only reported by `poll`.
+pub(super) const POLLNVAL: u16 = 0x20;
+/// Same as [`POLLIN`].
+pub(super) const POLLRDNORM: u16 = 0x40;
+/// Same as [`POLLOUT`].
+pub(super) const POLLWRNORM: u16 = 0x100;
+
+/// Events that are reported even if they were not requested.
+pub(super) const POLL_ALWAYS: u16 = POLLERR | POLLHUP;
+
+const READABLE: u16 = POLLIN | POLLRDNORM;
+const WRITABLE: u16 = POLLOUT | POLLWRNORM;
+
+/// The different kinds of file descriptors of Linux programs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum FdKind {
+    /// Standard input, output, or error, unless the program replaced them.
+    Console,
+    /// File of the file server.
+    File,
+    /// Local socket of the file server.
+    LocalSocket,
+    /// UDP or TCP socket of the network stack.
+    InetSocket,
+    /// Instance of [`epoll`].
+    Epoll,
+    /// Counter of [`event_fd`].
+    EventFd,
+    /// Timer of [`timer_fd`].
+    TimerFd,
+    /// Segment of the shared memory service, see [`shm_fd`].
+    Shm,
+}
+
+/// Returns the kind of the file descriptor or `None` if the process didn't open it.
+pub(super) fn fd_kind(process: &Rc<Process>, fd: FileDescriptor) -> Option<FdKind> {
+    if epoll::is_epoll(process, fd) {
+        return Some(FdKind::Epoll);
+    }
+    if event_fd::is_event_fd(process, fd) {
+        return Some(FdKind::EventFd);
+    }
+    if timer_fd::is_timer_fd(process, fd) {
+        return Some(FdKind::TimerFd);
+    }
+    if shm_fd::get(process, fd).is_some() {
+        return Some(FdKind::Shm);
+    }
+    // don't hold the lock of the file system while checking for sockets of the network stack
+    if is_inet_socket(process, fd) {
+        return Some(FdKind::InetSocket);
+    }
+    let fs_lock = libfileserver::FILESYSTEM.lock();
+    if fs_lock.is_socket(process.pid(), fd) {
+        Some(FdKind::LocalSocket)
+    } else if fs_lock.readiness(process.pid(), fd).is_ok() {
+        Some(FdKind::File)
+    } else if fd.val() <= 2 {
+        Some(FdKind::Console)
+    } else {
+        None
+    }
+}
+
+/// Returns the `poll` events of the file descriptor that are ready right now or `None` if
+/// the process didn't open it. The result may contain events that were not requested.
+pub(super) fn ready_events(process: &Rc<Process>, fd: FileDescriptor) -> Option<u16> {
+    let events = match fd_kind(process, fd)? {
+        FdKind::Console if fd.val() == 0 && stdin::input_available() => READABLE,
+        FdKind::Console if fd.val() == 0 => 0,
+        FdKind::Console => WRITABLE,
+        FdKind::File | FdKind::LocalSocket => {
+            let readiness = libfileserver::FILESYSTEM
+                .lock()
+                .readiness(process.pid(), fd)
+                .ok()?;
+            readiness_events(readiness)
+        }
+        FdKind::InetSocket => readiness_events(network::socket_readiness(process.pid(), fd)?),
+        FdKind::Epoll if epoll::has_ready_events(process, fd) => READABLE,
+        FdKind::Epoll => 0,
+        FdKind::EventFd => readiness_events(event_fd::readiness(process, fd)),
+        FdKind::TimerFd => readiness_events(timer_fd::readiness(process, fd)),
+        // like regular files
+        FdKind::Shm => READABLE | WRITABLE,
+    };
+    Some(events)
+}
+
+/// Translates the [`Readiness`] of the file server into `poll` events.
+fn readiness_events(readiness: Readiness) -> u16 {
+    let mut events = 0;
+    if readiness.readable {
+        events |= READABLE;
+    }
+    if readiness.writable {
+        events |= WRITABLE;
+    }
+    if readiness.hang_up {
+        events |= POLLHUP;
+    }
+    events
+}
+
+/// Returns the TSC value after the timeout in nanoseconds, as deadline for
+/// [`wait_for_events`].
+pub(super) fn deadline_after_ns(timeout_ns: u64) -> u64 {
+    time::tsc_now().saturating_add(time::ns_to_ticks(timeout_ns))
+}
+
+/// Returns the TSC value after the timeout in milliseconds, as deadline for
+/// [`wait_for_events`]. A negative timeout means no deadline, like in `poll`.
+pub(super) fn deadline_after_ms(timeout_ms: i32) -> Option<u64> {
+    u64::try_from(timeout_ms)
+        .ok()
+        .map(|ms| deadline_after_ns(ms * 1_000_000))
+}
+
+/// Calls `check` and returns its result if it reports a non-zero number of ready file
+/// descriptors or if the TSC reached `tsc_deadline`, i.e. zero after the timeout.
+/// Otherwise, the syscall waits: this returns [`LinuxErrorCode::ERESTARTSYS`], which the
+/// handler must return, and the process executes the syscall again later. Without a
+/// deadline, the syscall waits forever. After a restart, the deadline of the first attempt
+/// applies, see [`restart::deadline`]. A pending signal ends the wait with `EINTR`.
+pub(super) fn wait_for_events(
+    process: &Rc<Process>,
+    tsc_deadline: Option<u64>,
+    check: impl FnOnce() -> usize,
+) -> Result<usize, LinuxErrorCode> {
+    let tsc_deadline = restart::deadline(process, tsc_deadline);
+    let ready = check();
+    if ready > 0 || tsc_deadline.map_or(false, |deadline| time::tsc_now() >= deadline) {
+        return Ok(ready);
+    }
+    Err(restart::restart(process, tsc_deadline))
+}
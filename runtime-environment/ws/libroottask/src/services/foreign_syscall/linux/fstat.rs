@@ -1,16 +1,14 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::shm_fd;
+use crate::services::foreign_syscall::linux::stat::write_stat;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
-use crate::services::MAPPED_AREAS;
 use alloc::rc::Rc;
-use core::mem::size_of;
-use libfileserver::{
-    FileDescriptor,
-    FileStat,
-};
+use libfileserver::FileDescriptor;
 use libhrstd::libhedron::UtcbDataException;
 
 #[derive(Debug)]
@@ -34,22 +32,19 @@ impl LinuxSyscallImpl for FstatSyscall {
         _utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        let fstat = libfileserver::FILESYSTEM
-            .lock()
-            .fstat(process.pid(), self.fd)
-            .unwrap();
-
-        let u_page_offset = self.u_ptr_statbuf & 0xfff;
-        let mut mapping = MAPPED_AREAS
-            .lock()
-            .create_or_get_mapping(process, self.u_ptr_statbuf, size_of::<FileStat>() as u64)
-            .clone();
-
-        let r_write_ptr = mapping.mem_with_offset_as_ptr_mut(u_page_offset as usize);
-        unsafe {
-            core::ptr::write(r_write_ptr as *mut _, fstat);
+        let fstat = match shm_fd::get(process, self.fd) {
+            Some(shm_fd) => shm_fd::stat(process, shm_fd),
+            None => libfileserver::FILESYSTEM
+                .lock()
+                .fstat(process.pid(), self.fd)
+                .map_err(LinuxErrorCode::from),
+        };
+        match fstat {
+            Ok(fstat) => {
+                write_stat(process, self.u_ptr_statbuf, fstat);
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
         }
-
-        LinuxSyscallResult::new_success(0)
     }
 }
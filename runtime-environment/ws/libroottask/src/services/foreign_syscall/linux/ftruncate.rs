@@ -0,0 +1,52 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::shm_fd;
+use crate::services::foreign_syscall::linux::truncate::check_length;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::shm;
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/ftruncate.2.html>.
+#[derive(Debug)]
+pub struct FtruncateSyscall {
+    fd: FileDescriptor,
+    length: i64,
+}
+
+impl From<&GenericLinuxSyscall> for FtruncateSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            length: syscall.arg1() as i64,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for FtruncateSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let res = check_length(self.length).and_then(|len| match shm_fd::get(process, self.fd) {
+            Some(shm_fd) if !shm_fd.writable => Err(LinuxErrorCode::EINVAL),
+            Some(shm_fd) => {
+                shm::resize(process.pid(), shm_fd.id, len as u64).map_err(LinuxErrorCode::from)
+            }
+            None => libfileserver::FILESYSTEM
+                .lock()
+                .ftruncate_file(process.pid(), self.fd, len)
+                .map_err(LinuxErrorCode::from),
+        });
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -1,28 +1,113 @@
-use crate::process::Process;
+use crate::process::{
+    raise_signal,
+    resource_limit,
+    Process,
+    RLIMIT_FSIZE,
+    RLIM_INFINITY,
+    SIGXFSZ,
+};
+use crate::services::foreign_syscall::linux::accept::AcceptSyscall;
+use crate::services::foreign_syscall::linux::access::AccessSyscall;
+use crate::services::foreign_syscall::linux::alarm::AlarmSyscall;
 use crate::services::foreign_syscall::linux::arch_prctl::ArchPrctlSyscall;
+use crate::services::foreign_syscall::linux::bind::BindSyscall;
 use crate::services::foreign_syscall::linux::brk::BrkSyscall;
+use crate::services::foreign_syscall::linux::chdir::ChdirSyscall;
 use crate::services::foreign_syscall::linux::clock_gettime::ClockGetTimeSyscall;
+use crate::services::foreign_syscall::linux::clock_nanosleep::ClockNanoSleepSyscall;
+use crate::services::foreign_syscall::linux::clock_settime::ClockSetTimeSyscall;
 use crate::services::foreign_syscall::linux::clone::CloneSyscall;
 use crate::services::foreign_syscall::linux::close::CloseSyscall;
+use crate::services::foreign_syscall::linux::connect::ConnectSyscall;
+use crate::services::foreign_syscall::linux::epoll_create::EpollCreateSyscall;
+use crate::services::foreign_syscall::linux::epoll_create1::EpollCreate1Syscall;
+use crate::services::foreign_syscall::linux::epoll_ctl::EpollCtlSyscall;
+use crate::services::foreign_syscall::linux::epoll_pwait::EpollPWaitSyscall;
+use crate::services::foreign_syscall::linux::epoll_wait::EpollWaitSyscall;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::eventfd::EventFdSyscall;
+use crate::services::foreign_syscall::linux::eventfd2::EventFd2Syscall;
+use crate::services::foreign_syscall::linux::execve::ExecveSyscall;
+use crate::services::foreign_syscall::linux::exit::ExitSyscall;
+use crate::services::foreign_syscall::linux::faccessat::FaccessAtSyscall;
 use crate::services::foreign_syscall::linux::fcntl::FcntlSyscall;
 use crate::services::foreign_syscall::linux::fstat::FstatSyscall;
+use crate::services::foreign_syscall::linux::ftruncate::FtruncateSyscall;
+use crate::services::foreign_syscall::linux::getcwd::GetCwdSyscall;
+use crate::services::foreign_syscall::linux::getrandom::GetRandomSyscall;
+use crate::services::foreign_syscall::linux::getrlimit::GetRLimitSyscall;
+use crate::services::foreign_syscall::linux::gettid::GetTidSyscall;
+use crate::services::foreign_syscall::linux::gettimeofday::GetTimeOfDaySyscall;
 use crate::services::foreign_syscall::linux::ioctl::IoctlSyscall;
+use crate::services::foreign_syscall::linux::kill::KillSyscall;
+use crate::services::foreign_syscall::linux::link::LinkSyscall;
+use crate::services::foreign_syscall::linux::linkat::LinkAtSyscall;
+use crate::services::foreign_syscall::linux::listen::ListenSyscall;
 use crate::services::foreign_syscall::linux::lseek::LSeekSyscall;
+use crate::services::foreign_syscall::linux::lstat::LStatSyscall;
 use crate::services::foreign_syscall::linux::madvise::MAdviseSyscall;
+use crate::services::foreign_syscall::linux::memfd_create::MemfdCreateSyscall;
+use crate::services::foreign_syscall::linux::mkdir::MkdirSyscall;
+use crate::services::foreign_syscall::linux::mkdirat::MkdirAtSyscall;
 use crate::services::foreign_syscall::linux::mmap::MMapSyscall;
 use crate::services::foreign_syscall::linux::mprotect::MProtectSyscall;
 use crate::services::foreign_syscall::linux::munmap::MUnMapSyscall;
+use crate::services::foreign_syscall::linux::nanosleep::NanoSleepSyscall;
+use crate::services::foreign_syscall::linux::newfstatat::NewFstatAtSyscall;
 use crate::services::foreign_syscall::linux::open::OpenSyscall;
+use crate::services::foreign_syscall::linux::openat::OpenAtSyscall;
 use crate::services::foreign_syscall::linux::poll::PollSyscall;
+use crate::services::foreign_syscall::linux::ppoll::PPollSyscall;
+use crate::services::foreign_syscall::linux::prctl::PrctlSyscall;
+use crate::services::foreign_syscall::linux::pread64::PRead64Syscall;
+use crate::services::foreign_syscall::linux::preadv::PReadVSyscall;
+use crate::services::foreign_syscall::linux::prlimit64::PrLimit64Syscall;
+use crate::services::foreign_syscall::linux::pselect6::PSelect6Syscall;
+use crate::services::foreign_syscall::linux::pwrite64::PWrite64Syscall;
+use crate::services::foreign_syscall::linux::pwritev::PWriteVSyscall;
 use crate::services::foreign_syscall::linux::read::ReadSyscall;
+use crate::services::foreign_syscall::linux::read_v::ReadVSyscall;
+use crate::services::foreign_syscall::linux::readlink::ReadLinkSyscall;
+use crate::services::foreign_syscall::linux::readlinkat::ReadLinkAtSyscall;
+use crate::services::foreign_syscall::linux::recvfrom::RecvFromSyscall;
+use crate::services::foreign_syscall::linux::recvmsg::RecvMsgSyscall;
+use crate::services::foreign_syscall::linux::rename::RenameSyscall;
+use crate::services::foreign_syscall::linux::renameat::RenameAtSyscall;
+use crate::services::foreign_syscall::linux::renameat2::RenameAt2Syscall;
+use crate::services::foreign_syscall::linux::rmdir::RmdirSyscall;
+use crate::services::foreign_syscall::linux::rseq::RseqSyscall;
+use crate::services::foreign_syscall::linux::rt_sigreturn::RtSigreturnSyscall;
 use crate::services::foreign_syscall::linux::rtsigaction::RtSigactionSyscall;
 use crate::services::foreign_syscall::linux::rtsigprocmask::RtSigProcMaskSyscall;
 use crate::services::foreign_syscall::linux::sched_getaffinity::SchedGetAffinitySyscall;
+use crate::services::foreign_syscall::linux::sched_setaffinity::SchedSetAffinitySyscall;
+use crate::services::foreign_syscall::linux::select::SelectSyscall;
+use crate::services::foreign_syscall::linux::sendmsg::SendMsgSyscall;
+use crate::services::foreign_syscall::linux::sendto::SendToSyscall;
+use crate::services::foreign_syscall::linux::set_robust_list::SetRobustListSyscall;
 use crate::services::foreign_syscall::linux::set_tid_address::SetTidAddressSyscall;
+use crate::services::foreign_syscall::linux::setrlimit::SetRLimitSyscall;
+use crate::services::foreign_syscall::linux::settimeofday::SetTimeOfDaySyscall;
 use crate::services::foreign_syscall::linux::signalstack::SignalStackSyscall;
+use crate::services::foreign_syscall::linux::socket::SocketSyscall;
+use crate::services::foreign_syscall::linux::socketpair::SocketPairSyscall;
+use crate::services::foreign_syscall::linux::stat::StatSyscall;
+use crate::services::foreign_syscall::linux::statx::StatxSyscall;
+use crate::services::foreign_syscall::linux::symlink::SymlinkSyscall;
+use crate::services::foreign_syscall::linux::symlinkat::SymlinkAtSyscall;
 use crate::services::foreign_syscall::linux::syscall_num::LinuxSyscallNum;
 use crate::services::foreign_syscall::linux::sysinfo::SysinfoSyscall;
+use crate::services::foreign_syscall::linux::tgkill::TgKillSyscall;
+use crate::services::foreign_syscall::linux::time::TimeSyscall;
+use crate::services::foreign_syscall::linux::timerfd_create::TimerFdCreateSyscall;
+use crate::services::foreign_syscall::linux::timerfd_gettime::TimerFdGetTimeSyscall;
+use crate::services::foreign_syscall::linux::timerfd_settime::TimerFdSetTimeSyscall;
+use crate::services::foreign_syscall::linux::truncate::TruncateSyscall;
+use crate::services::foreign_syscall::linux::umask::UmaskSyscall;
+use crate::services::foreign_syscall::linux::uname::UnameSyscall;
 use crate::services::foreign_syscall::linux::unlink::UnlinkSyscall;
+use crate::services::foreign_syscall::linux::unlinkat::UnlinkAtSyscall;
+use crate::services::foreign_syscall::linux::vfork::VforkSyscall;
 use crate::services::foreign_syscall::linux::write::WriteSyscall;
 use crate::services::foreign_syscall::linux::write_v::WriteVSyscall;
 use crate::services::foreign_syscall::linux::{
@@ -83,7 +168,9 @@ impl GenericLinuxSyscall {
             LinuxSyscallNum::Write => WriteSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Open => OpenSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Close => CloseSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Stat => StatSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Fstat => FstatSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::LStat => LStatSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Poll => PollSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::LSeek => LSeekSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::MMap => MMapSyscall::from(self).handle(utcb_exc, process),
@@ -92,24 +179,105 @@ impl GenericLinuxSyscall {
             LinuxSyscallNum::Brk => BrkSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::RtSigaction => RtSigactionSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::RtSigprocmask => RtSigProcMaskSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::RtSigreturn => RtSigreturnSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Ioctl => IoctlSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PRead64 => PRead64Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PWrite64 => PWrite64Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::ReadV => ReadVSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::NanoSleep => NanoSleepSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Alarm => AlarmSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::MAdvise => MAdviseSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::WriteV => WriteVSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Socket => SocketSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Connect => ConnectSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Accept => AcceptSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SendTo => SendToSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::RecvFrom => RecvFromSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SendMsg => SendMsgSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::RecvMsg => RecvMsgSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Bind => BindSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Listen => ListenSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SocketPair => SocketPairSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Clone => CloneSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Vfork => VforkSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Execve => ExecveSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Exit => ExitSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Kill => KillSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Uname => UnameSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Fcntl => FcntlSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Truncate => TruncateSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Ftruncate => FtruncateSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::GetCwd => GetCwdSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Chdir => ChdirSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Rename => RenameSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Mkdir => MkdirSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Rmdir => RmdirSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Link => LinkSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Unlink => UnlinkSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Symlink => SymlinkSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::ReadLink => ReadLinkSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Umask => UmaskSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::GetTimeOfDay => GetTimeOfDaySyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::GetRLimit => GetRLimitSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Access => AccessSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::FaccessAt => FaccessAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PSelect6 => PSelect6Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PPoll => PPollSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Select => SelectSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Sysinfo => SysinfoSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SetTimeOfDay => SetTimeOfDaySyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::SigAltStack => SignalStackSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Prctl => PrctlSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SetRLimit => SetRLimitSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::ArchPrctl => ArchPrctlSyscall::from(self).handle(utcb_exc, process),
-            LinuxSyscallNum::Gettid => todo!("LinuxSyscallNum::Gettid"),
+            LinuxSyscallNum::Gettid => GetTidSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Time => TimeSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::Futex => todo!("LinuxSyscallNum::Futex"),
+            LinuxSyscallNum::SchedSetAffinity => SchedSetAffinitySyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::SchedGetAffinity => SchedGetAffinitySyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EpollCreate => EpollCreateSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::SetTidAddress => SetTidAddressSyscall::from(self).handle(utcb_exc, process),
-            LinuxSyscallNum::ExitGroup => todo!("LinuxSyscallNum::ExitGroup"),
-            LinuxSyscallNum::ReadLinkAt => todo!("LinuxSyscallNum::ReadLinkAt"),
+            LinuxSyscallNum::ExitGroup => ExitSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EpollWait => EpollWaitSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EpollCtl => EpollCtlSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::OpenAt => OpenAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::MkdirAt => MkdirAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::NewFstatAt => NewFstatAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::UnlinkAt => UnlinkAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::RenameAt => RenameAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::LinkAt => LinkAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SymlinkAt => SymlinkAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::ReadLinkAt => ReadLinkAtSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::ClockSetTime => ClockSetTimeSyscall::from(self).handle(utcb_exc, process),
             LinuxSyscallNum::ClockGetTime => ClockGetTimeSyscall::from(self).handle(utcb_exc, process),
-            LinuxSyscallNum::PrLimit64 => todo!("LinuxSyscallNum::PrLimit64"),
+            LinuxSyscallNum::ClockNanoSleep => ClockNanoSleepSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::TgKill => TgKillSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Accept4 => AcceptSyscall::new_accept4(self).handle(utcb_exc, process),
+            LinuxSyscallNum::SetRobustList => SetRobustListSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EpollPWait => EpollPWaitSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::TimerFdCreate => TimerFdCreateSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EventFd => EventFdSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::TimerFdSetTime => TimerFdSetTimeSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::TimerFdGetTime => TimerFdGetTimeSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EventFd2 => EventFd2Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::EpollCreate1 => EpollCreate1Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PReadV => PReadVSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PWriteV => PWriteVSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::PrLimit64 => PrLimit64Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::RenameAt2 => RenameAt2Syscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::GetRandom => GetRandomSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::MemfdCreate => {
+                MemfdCreateSyscall::from(self).handle(utcb_exc, process)
+            }
+            LinuxSyscallNum::Statx => StatxSyscall::from(self).handle(utcb_exc, process),
+            LinuxSyscallNum::Rseq => RseqSyscall::from(self).handle(utcb_exc, process),
         };
+        // like on Linux, writes beyond the file size limit also raise SIGXFSZ
+        if res.0 == -(LinuxErrorCode::EFBIG.val() as i64)
+            && resource_limit(process.pid(), RLIMIT_FSIZE).unwrap().cur != RLIM_INFINITY
+        {
+            raise_signal(process.pid(), SIGXFSZ);
+        }
         utcb_exc.rax = res.val();
     }
 }
@@ -0,0 +1,48 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/getcwd.2.html>. Like the
+/// Linux syscall, it returns the length of the path including the null byte.
+#[derive(Debug)]
+pub struct GetCwdSyscall {
+    u_buf: u64,
+    size: u64,
+}
+
+impl From<&GenericLinuxSyscall> for GetCwdSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_buf: syscall.arg0(),
+            size: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for GetCwdSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mut cwd = process.cwd().into_bytes();
+        cwd.push(0);
+        if (self.size as usize) < cwd.len() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ERANGE);
+        }
+        let mapping =
+            MAPPED_AREAS
+                .lock()
+                .create_or_get_mapping(process, self.u_buf, cwd.len() as u64);
+        let r_buf = mapping.old_to_new_ptr_mut(self.u_buf as *mut u8);
+        unsafe { core::ptr::copy_nonoverlapping(cwd.as_ptr(), r_buf, cwd.len()) };
+        LinuxSyscallResult::new_success(cwd.len() as u64)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::process::Process;
+use crate::rt::devfs;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// `GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE`
+const GRND_FLAGS: u64 = 0x7;
+
+/// Maximum number of bytes of a single call. Linux returns less than requested for large
+/// requests, too.
+const GETRANDOM_MAX: u64 = 0x10000;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/getrandom.2.html>. Returns
+/// the output of the generator behind `/dev/urandom`, see [`devfs::fill_random`]. The
+/// generator is seeded during boot, hence the call never blocks and all flags behave
+/// the same. glibc uses it, e.g. to initialize the keys of malloc.
+#[derive(Debug)]
+pub struct GetRandomSyscall {
+    u_buf: u64,
+    len: u64,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for GetRandomSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_buf: syscall.arg0(),
+            len: syscall.arg1(),
+            flags: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for GetRandomSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.flags & !GRND_FLAGS != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let len = self.len.min(GETRANDOM_MAX);
+        if len == 0 {
+            return LinuxSyscallResult::new_success(0);
+        }
+
+        let mapping = MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, self.u_buf, len);
+        let r_buf = mapping.old_to_new_ptr_mut(self.u_buf as *mut u8);
+        devfs::fill_random(unsafe { core::slice::from_raw_parts_mut(r_buf, len as usize) });
+        LinuxSyscallResult::new_success(len)
+    }
+}
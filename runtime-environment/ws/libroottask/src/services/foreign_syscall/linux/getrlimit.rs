@@ -0,0 +1,39 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::prlimit64::prlimit;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/getrlimit.2.html>.
+/// Same as `prlimit64()` for the calling process without a new limit.
+#[derive(Debug)]
+pub struct GetRLimitSyscall {
+    resource: u64,
+    u_limit: u64,
+}
+
+impl From<&GenericLinuxSyscall> for GetRLimitSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            resource: syscall.arg0(),
+            u_limit: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for GetRLimitSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        match prlimit(process, process.pid(), self.resource, 0, self.u_limit) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/gettid.2.html>. A process
+/// has a single thread, whose ID equals the PID like for the main thread on Linux.
+#[derive(Debug)]
+pub struct GetTidSyscall;
+
+impl From<&GenericLinuxSyscall> for GetTidSyscall {
+    fn from(_syscall: &GenericLinuxSyscall) -> Self {
+        Self
+    }
+}
+
+impl LinuxSyscallImpl for GetTidSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        LinuxSyscallResult::new_success(process.pid())
+    }
+}
@@ -0,0 +1,71 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::settimeofday::timeval;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use crate::time;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/gettimeofday.2.html>.
+/// Returns the wall clock, like `clock_gettime(CLOCK_REALTIME)` but with microsecond
+/// resolution. The time zone is always UTC without daylight saving time.
+#[derive(Debug)]
+pub struct GetTimeOfDaySyscall {
+    u_ptr_tv: u64,
+    u_ptr_tz: u64,
+}
+
+impl From<&GenericLinuxSyscall> for GetTimeOfDaySyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_ptr_tv: syscall.arg0(),
+            u_ptr_tz: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for GetTimeOfDaySyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.u_ptr_tv != 0 {
+            let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_tv,
+                size_of::<timeval>() as u64,
+            );
+            *mapping.mem_with_offset_as_mut::<timeval>((self.u_ptr_tv & 0xfff) as usize) =
+                timeval::from_nanos(time::realtime_ns());
+        }
+        if self.u_ptr_tz != 0 {
+            let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_tz,
+                size_of::<timezone>() as u64,
+            );
+            *mapping.mem_with_offset_as_mut::<timezone>((self.u_ptr_tz & 0xfff) as usize) =
+                timezone {
+                    tz_minuteswest: 0,
+                    tz_dsttime: 0,
+                };
+        }
+        LinuxSyscallResult::new_success(0)
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct timezone {
+    /// minutes west of Greenwich
+    tz_minuteswest: i32,
+    /// type of the daylight saving time correction
+    tz_dsttime: i32,
+}
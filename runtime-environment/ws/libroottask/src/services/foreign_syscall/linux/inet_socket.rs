@@ -0,0 +1,275 @@
+//! Common functionality of the socket syscalls for `AF_INET`, i.e. UDP and TCP. The
+//! sockets are implemented by the network stack, see [`crate::services::network`].
+//!
+//! UDP sockets never block. They behave as if `O_NONBLOCK` was set. TCP sockets block
+//! like local sockets, unless they are non-blocking: the syscall gets restarted until
+//! the operation succeeds, see [`wait_if_blocking`].
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    copy_to_user,
+    read_from_user,
+    set_nonblocking,
+    wait_if_blocking,
+    write_to_user,
+    SOCK_NONBLOCK,
+};
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use crate::services::network;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::mem::size_of;
+use libfileserver::FileDescriptor;
+use libhrstd::rt::services::network::{
+    Ipv4Address,
+    NetworkError,
+    SocketAddrV4,
+};
+
+/// Address family of IPv4 sockets.
+pub(super) const AF_INET: u64 = 2;
+const SOCK_STREAM: u64 = 1;
+const SOCK_DGRAM: u64 = 2;
+/// The type argument of `socket` also holds flags, such as `SOCK_NONBLOCK`.
+const SOCK_TYPE_MASK: u64 = 0xf;
+const IPPROTO_TCP: u64 = 6;
+const IPPROTO_UDP: u64 = 17;
+
+/// Same as `struct sockaddr_in` of Linux. Port and address are in network byte order.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SockAddrIn {
+    sin_family: u16,
+    sin_port: [u8; 2],
+    sin_addr: [u8; 4],
+    sin_zero: [u8; 8],
+}
+
+impl From<NetworkError> for LinuxErrorCode {
+    fn from(err: NetworkError) -> Self {
+        match err {
+            NetworkError::NoDevice => Self::ENETDOWN,
+            NetworkError::BadFd => Self::EBADF,
+            NetworkError::NotASocket => Self::ENOTSOCK,
+            NetworkError::InvalidArgument => Self::EINVAL,
+            NetworkError::AddressInUse => Self::EADDRINUSE,
+            NetworkError::AddressNotAvailable => Self::EADDRNOTAVAIL,
+            NetworkError::DestinationRequired => Self::EDESTADDRREQ,
+            NetworkError::WouldBlock => Self::EAGAIN,
+            NetworkError::MessageTooLong => Self::EMSGSIZE,
+            NetworkError::NotConnected => Self::ENOTCONN,
+            NetworkError::AlreadyConnected => Self::EISCONN,
+            NetworkError::ConnectionRefused => Self::ECONNREFUSED,
+            NetworkError::ConnectionReset => Self::ECONNRESET,
+            NetworkError::TimedOut => Self::ETIMEDOUT,
+            NetworkError::BrokenPipe => Self::EPIPE,
+        }
+    }
+}
+
+/// Checks if the file descriptor refers to a UDP or TCP socket.
+pub(super) fn is_inet_socket(process: &Rc<Process>, fd: FileDescriptor) -> bool {
+    network::is_udp_socket(process.pid(), fd) || is_tcp_socket(process, fd)
+}
+
+/// Checks if the file descriptor refers to a TCP socket.
+fn is_tcp_socket(process: &Rc<Process>, fd: FileDescriptor) -> bool {
+    network::is_tcp_socket(process.pid(), fd)
+}
+
+/// Creates a UDP or TCP socket. Checks the `type` and `protocol` arguments of `socket`.
+pub(super) fn inet_socket(
+    process: &Rc<Process>,
+    sock_type: u64,
+    protocol: u64,
+) -> LinuxSyscallResult {
+    let res = match (sock_type & SOCK_TYPE_MASK, protocol) {
+        (SOCK_DGRAM, 0 | IPPROTO_UDP) => network::udp_socket(process.pid()),
+        (SOCK_STREAM, 0 | IPPROTO_TCP) => network::tcp_socket(process.pid()),
+        (SOCK_DGRAM | SOCK_STREAM, _) => {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EPROTONOSUPPORT)
+        }
+        (sock_type, _) => {
+            log::debug!("socket type {} not supported", sock_type);
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ESOCKTNOSUPPORT);
+        }
+    };
+    match res {
+        Ok(fd) => {
+            set_nonblocking(process, fd, sock_type & SOCK_NONBLOCK != 0);
+            LinuxSyscallResult::new_success(fd.val())
+        }
+        Err(err) => LinuxSyscallResult::new_error(err.into()),
+    }
+}
+
+/// Binds the UDP or TCP socket behind `fd` to a local address.
+pub(super) fn inet_bind(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    addr: SocketAddrV4,
+) -> Result<(), LinuxErrorCode> {
+    let pid = process.pid();
+    let tcp = is_tcp_socket(process, fd);
+    network::with_network_stack(|stack| match tcp {
+        true => stack.tcp_bind(pid, fd, addr),
+        false => stack.udp_bind(pid, fd, addr),
+    })
+    .map_err(|err| err.into())
+}
+
+/// Connects the socket behind `fd`. UDP sockets only get a default destination. TCP
+/// sockets wait for the handshake, unless they are non-blocking; then, the syscall fails
+/// with `EINPROGRESS` until the connection is established.
+pub(super) fn inet_connect(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    addr: SocketAddrV4,
+) -> LinuxSyscallResult {
+    let pid = process.pid();
+    let res = if is_tcp_socket(process, fd) {
+        network::with_network_stack(|stack| stack.tcp_connect(pid, fd, addr)).map_err(|err| {
+            match wait_if_blocking(process, fd, err.into()) {
+                LinuxErrorCode::EAGAIN => LinuxErrorCode::EINPROGRESS,
+                err => err,
+            }
+        })
+    } else {
+        network::with_network_stack(|stack| stack.udp_connect(pid, fd, addr))
+            .map_err(|err| err.into())
+    };
+    match res {
+        Ok(_) => LinuxSyscallResult::new_success(0),
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
+
+/// Lets the TCP socket behind `fd` accept connections. UDP sockets are connectionless.
+pub(super) fn inet_listen(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    backlog: usize,
+) -> LinuxSyscallResult {
+    if !is_tcp_socket(process, fd) {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EOPNOTSUPP);
+    }
+    match network::with_network_stack(|stack| stack.tcp_listen(process.pid(), fd, backlog)) {
+        Ok(_) => LinuxSyscallResult::new_success(0),
+        Err(err) => LinuxSyscallResult::new_error(err.into()),
+    }
+}
+
+/// Takes the next connection of the listening TCP socket behind `fd` and returns its file
+/// descriptor and the peer. Waits for a connection, unless the socket is non-blocking.
+pub(super) fn inet_accept(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+) -> Result<(FileDescriptor, SocketAddrV4), LinuxErrorCode> {
+    if !is_tcp_socket(process, fd) {
+        return Err(LinuxErrorCode::EOPNOTSUPP);
+    }
+    network::tcp_accept(process.pid(), fd).map_err(|err| wait_if_blocking(process, fd, err.into()))
+}
+
+/// Reads a `struct sockaddr_in` from user memory.
+pub(super) fn read_sockaddr_in(
+    process: &Rc<Process>,
+    u_addr: u64,
+    len: u64,
+) -> Result<SocketAddrV4, LinuxErrorCode> {
+    if u_addr == 0 || (len as usize) < size_of::<SockAddrIn>() {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    let addr = read_from_user::<SockAddrIn>(process, u_addr);
+    if addr.sin_family as u64 != AF_INET {
+        return Err(LinuxErrorCode::EAFNOSUPPORT);
+    }
+    Ok(SocketAddrV4::new(
+        Ipv4Address(addr.sin_addr),
+        u16::from_be_bytes(addr.sin_port),
+    ))
+}
+
+/// Writes an address as `struct sockaddr_in` to user memory. `u_len_ptr` points to the
+/// size of the user buffer and receives the size of the address. Does nothing if
+/// `u_addr` is null.
+pub(super) fn write_sockaddr_in(
+    process: &Rc<Process>,
+    u_addr: u64,
+    u_len_ptr: u64,
+    addr: SocketAddrV4,
+) {
+    if u_addr == 0 || u_len_ptr == 0 {
+        return;
+    }
+    let u_buf_len = read_from_user::<u32>(process, u_len_ptr);
+    let addr_len = copy_sockaddr_in_to_user(process, u_addr, u_buf_len, addr);
+    write_to_user(process, u_len_ptr, addr_len);
+}
+
+/// Writes an address as `struct sockaddr_in` into the user buffer of `buf_len` bytes and
+/// truncates it if necessary. Returns the size of the address.
+pub(super) fn copy_sockaddr_in_to_user(
+    process: &Rc<Process>,
+    u_addr: u64,
+    buf_len: u32,
+    addr: SocketAddrV4,
+) -> u32 {
+    let addr = SockAddrIn {
+        sin_family: AF_INET as u16,
+        sin_port: addr.port.to_be_bytes(),
+        sin_addr: addr.addr.0,
+        sin_zero: [0; 8],
+    };
+    let addr_len = size_of::<SockAddrIn>();
+    let addr_bytes =
+        unsafe { core::slice::from_raw_parts((&addr as *const SockAddrIn).cast::<u8>(), addr_len) };
+    copy_to_user(
+        process,
+        u_addr,
+        &addr_bytes[..min(addr_len, buf_len as usize)],
+    );
+    addr_len as u32
+}
+
+/// Sends a datagram via the UDP socket or data via the TCP socket behind `fd`. TCP
+/// sockets ignore `dest`. Used by all syscalls that send data.
+pub(super) fn inet_send(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    data: &[u8],
+    dest: Option<SocketAddrV4>,
+) -> LinuxSyscallResult {
+    let pid = process.pid();
+    let res = if is_tcp_socket(process, fd) {
+        network::with_network_stack(|stack| stack.tcp_send(pid, fd, data))
+            .map_err(|err| wait_if_blocking(process, fd, err.into()))
+    } else {
+        network::with_network_stack(|stack| stack.udp_send(pid, fd, data, dest))
+            .map_err(|err| err.into())
+    };
+    match res {
+        Ok(len) => LinuxSyscallResult::new_success(len as u64),
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
+
+/// Receives a datagram of at most `max_len` bytes from the UDP socket behind `fd`, or at
+/// most `max_len` bytes from the TCP socket. Returns the data and the sender, i.e. the
+/// peer for TCP. Used by all syscalls that receive data.
+pub(super) fn inet_recv(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    max_len: usize,
+) -> Result<(Vec<u8>, SocketAddrV4), LinuxErrorCode> {
+    let pid = process.pid();
+    if is_tcp_socket(process, fd) {
+        network::with_network_stack(|stack| stack.tcp_recv(pid, fd, max_len))
+            .map_err(|err| wait_if_blocking(process, fd, err.into()))
+    } else {
+        network::with_network_stack(|stack| stack.udp_recv(pid, fd, max_len))
+            .map_err(|err| err.into())
+    }
+}
@@ -1,24 +1,46 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events::{
+    fd_kind,
+    FdKind,
+};
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::termios;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    set_nonblocking,
+};
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
 use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
 use libhrstd::libhedron::UtcbDataException;
 
+/// Sets or clears `O_NONBLOCK`.
+const FIONBIO: u64 = 0x5421;
+/// Clears `FD_CLOEXEC`.
+const FIONCLEX: u64 = 0x5450;
+/// Sets `FD_CLOEXEC`.
+const FIOCLEX: u64 = 0x5451;
+
+/// Dispatches the request by the kind of the file descriptor. Only the console is a
+/// terminal; other file descriptors fail with `ENOTTY`, which `isatty()` relies on.
 #[derive(Debug)]
-#[allow(unused)]
 pub struct IoctlSyscall {
-    fd: u64,
+    fd: FileDescriptor,
     request: u64,
+    u_arg: u64,
 }
 
 impl From<&GenericLinuxSyscall> for IoctlSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            fd: syscall.arg0(),
-            request: syscall.arg1(),
+            fd: FileDescriptor::new(syscall.arg0()),
+            // the request is an unsigned int
+            request: syscall.arg1() & 0xffff_ffff,
+            u_arg: syscall.arg2(),
         }
     }
 }
@@ -27,10 +49,26 @@ impl LinuxSyscallImpl for IoctlSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        // do nothing; it's okay for simple Linux programs
-
-        LinuxSyscallResult::new_success(0)
+        let kind = match fd_kind(process, self.fd) {
+            Some(kind) => kind,
+            None => return LinuxSyscallResult::new_error(LinuxErrorCode::EBADF),
+        };
+        match (kind, self.request) {
+            (FdKind::LocalSocket | FdKind::InetSocket, FIONBIO) => {
+                let nonblocking = read_from_user::<i32>(process, self.u_arg) != 0;
+                set_nonblocking(process, self.fd, nonblocking);
+                LinuxSyscallResult::new_success(0)
+            }
+            // requests of all file descriptors; they have no effect, because processes
+            // can't exec and only the flags at creation set the blocking mode of the others
+            (_, FIONBIO | FIONCLEX | FIOCLEX) => LinuxSyscallResult::new_success(0),
+            (FdKind::Console, request) => termios::console_ioctl(process, request, self.u_arg),
+            (kind, request) => {
+                log::debug!("ioctl request {:#x} not supported for {:?}", request, kind);
+                LinuxSyscallResult::new_error(LinuxErrorCode::ENOTTY)
+            }
+        }
     }
 }
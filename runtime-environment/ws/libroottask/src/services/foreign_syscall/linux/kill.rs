@@ -0,0 +1,85 @@
+use crate::process::{
+    post_signal,
+    sig_valid,
+    signal_receivers,
+    signal_target,
+    Process,
+    SigNum,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::process::consts::ProcessId;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/kill.2.html>.
+/// There are no process groups: each process is the only member of its own group.
+/// All processes run as the same user, hence every process may signal every other
+/// Linux process.
+#[derive(Debug)]
+pub struct KillSyscall {
+    pid: i64,
+    sig: SigNum,
+}
+
+impl From<&GenericLinuxSyscall> for KillSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pid: syscall.arg0() as i32 as i64,
+            sig: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for KillSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.sig != 0 && !sig_valid(self.sig) {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+
+        let res = match self.pid {
+            // the own process group
+            0 => send_signal(process.pid(), self.sig),
+            // all processes except the caller
+            -1 => {
+                let mut receivers = signal_receivers()
+                    .filter(|pid| *pid != process.pid())
+                    .peekable();
+                if receivers.peek().is_none() {
+                    Err(LinuxErrorCode::ESRCH)
+                } else {
+                    receivers.try_for_each(|pid| send_signal(pid, self.sig))
+                }
+            }
+            // the process group with the ID -pid
+            pid if pid < 0 => send_signal(pid.unsigned_abs(), self.sig),
+            pid => send_signal(pid as ProcessId, self.sig),
+        };
+
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Sends the signal to the given process, see [`post_signal`]. A signal number of zero
+/// only checks if the process exists.
+pub(super) fn send_signal(pid: ProcessId, sig: SigNum) -> Result<(), LinuxErrorCode> {
+    let target = signal_target(pid).ok_or(LinuxErrorCode::ESRCH)?;
+    if !target.receives_signals {
+        return Err(LinuxErrorCode::EPERM);
+    }
+    if sig != 0 && !post_signal(pid, sig) {
+        return Err(LinuxErrorCode::EPERM);
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/link.2.html>. Like on Linux,
+/// a symbolic link at `oldpath` gets linked, not its target.
+#[derive(Debug)]
+pub struct LinkSyscall {
+    // null terminated path names
+    oldpath: *const u8,
+    newpath: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for LinkSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            oldpath: syscall.arg0() as *const _,
+            newpath: syscall.arg1() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for LinkSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let oldpath = read_path(process, self.oldpath);
+        let newpath = read_path(process, self.newpath);
+        match link(process, &oldpath, &newpath) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Adds `newpath` as another path of the file at `oldpath`. Shared by `link()` and
+/// `linkat()`.
+pub(super) fn link(process: &Process, oldpath: &str, newpath: &str) -> Result<(), LinuxErrorCode> {
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    fs.lstat_path(process.pid(), oldpath)?;
+    if fs.lstat_path(process.pid(), newpath).is_ok() {
+        return Err(LinuxErrorCode::EEXIST);
+    }
+    if !fs.is_same_mount(process.pid(), oldpath, newpath) {
+        return Err(LinuxErrorCode::EXDEV);
+    }
+    if fs.is_read_only() {
+        return Err(LinuxErrorCode::EROFS);
+    }
+    // e.g. a directory or a backend without hard links
+    fs.link_file(process.pid(), oldpath, newpath)
+        .map_err(LinuxErrorCode::from)
+}
@@ -0,0 +1,70 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_AT_SYMLINK_FOLLOW;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::link::link;
+use crate::services::foreign_syscall::linux::path::read_path_at;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/linkat.2.html>. musl
+/// implements `link()` with it. Like for `faccessat()`, relative paths are only supported
+/// together with `AT_FDCWD`. `AT_EMPTY_PATH` isn't supported.
+#[derive(Debug)]
+pub struct LinkAtSyscall {
+    olddirfd: i32,
+    // null terminated path names
+    oldpath: *const u8,
+    newdirfd: i32,
+    newpath: *const u8,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for LinkAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            olddirfd: syscall.arg0() as i32,
+            oldpath: syscall.arg1() as *const _,
+            newdirfd: syscall.arg2() as i32,
+            newpath: syscall.arg3() as *const _,
+            flags: syscall.arg4(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for LinkAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.flags & !LINUX_AT_SYMLINK_FOLLOW != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let paths = read_path_at(process, self.olddirfd, self.oldpath).and_then(|oldpath| {
+            read_path_at(process, self.newdirfd, self.newpath).map(|newpath| (oldpath, newpath))
+        });
+        let (mut oldpath, newpath) = match paths {
+            Ok(paths) => paths,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        if self.flags & LINUX_AT_SYMLINK_FOLLOW != 0 {
+            // links the target of a symbolic link
+            match libfileserver::FILESYSTEM
+                .lock()
+                .canonicalize(process.pid(), &oldpath)
+            {
+                Ok(target) => oldpath = target,
+                Err(err) => return LinuxSyscallResult::new_error(err.into()),
+            }
+        }
+        match link(process, &oldpath, &newpath) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
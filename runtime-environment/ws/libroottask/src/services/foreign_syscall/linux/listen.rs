@@ -0,0 +1,50 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_listen,
+    is_inet_socket,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/listen.2.html>.
+#[derive(Debug)]
+pub struct ListenSyscall {
+    fd: FileDescriptor,
+    backlog: i32,
+}
+
+impl From<&GenericLinuxSyscall> for ListenSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            backlog: syscall.arg1() as i32,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ListenSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        // negative values are treated as zero, like on Linux
+        let backlog = self.backlog.max(0) as usize;
+        if is_inet_socket(process, self.fd) {
+            return inet_listen(process, self.fd, backlog);
+        }
+        match libfileserver::FILESYSTEM
+            .lock()
+            .listen(process.pid(), self.fd, backlog)
+        {
+            Ok(_) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
+    }
+}
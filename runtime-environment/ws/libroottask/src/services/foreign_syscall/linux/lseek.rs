@@ -32,12 +32,14 @@ impl LinuxSyscallImpl for LSeekSyscall {
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
         // TODO whence not considered yet
-        libfileserver::FILESYSTEM
-            .lock()
-            .lseek_file(process.pid(), self.fd, self.offset as usize)
-            .unwrap();
-
-        LinuxSyscallResult::new_success(0)
+        match libfileserver::FILESYSTEM.lock().lseek_file(
+            process.pid(),
+            self.fd,
+            self.offset as usize,
+        ) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
     }
 }
 
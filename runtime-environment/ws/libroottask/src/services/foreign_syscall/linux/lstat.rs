@@ -0,0 +1,61 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_PATH_MAX;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::stat::{
+    stat_path,
+    write_stat,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/lstat.2.html>. Like
+/// [`super::stat::StatSyscall`] but returns the metadata of a symbolic link itself.
+#[derive(Debug)]
+pub struct LStatSyscall {
+    // null terminated path name
+    pathname: *const u8,
+    u_ptr_statbuf: u64,
+}
+
+impl From<&GenericLinuxSyscall> for LStatSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+            u_ptr_statbuf: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for LStatSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.pathname as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        match stat_path(process, pathname, false) {
+            Ok(stat) => {
+                write_stat(process, self.u_ptr_statbuf, stat);
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
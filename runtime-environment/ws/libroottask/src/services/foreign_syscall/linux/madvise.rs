@@ -30,7 +30,6 @@ impl LinuxSyscallImpl for MAdviseSyscall {
         _utcb_exc: &mut UtcbDataException,
         _process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        log::trace!("{:#?}", self);
         LinuxSyscallResult::new_success(0)
     }
 }
@@ -0,0 +1,58 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_c_str;
+use crate::services::foreign_syscall::linux::shm_fd;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Same as `O_CLOEXEC`. Accepted, but has no effect on `execve()`, see [`super::spawn`].
+const MFD_CLOEXEC: u64 = 0x1;
+/// Allows seals. Has no effect, because seals aren't supported.
+const MFD_ALLOW_SEALING: u64 = 0x2;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/memfd_create.2.html>. The
+/// file descriptor refers to an anonymous segment of the shared memory service, see
+/// [`super::shm_fd`]. The name only shows up in the log.
+#[derive(Debug)]
+pub struct MemfdCreateSyscall {
+    u_name: *const u8,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for MemfdCreateSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_name: syscall.arg0() as *const _,
+            flags: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for MemfdCreateSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING) != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        match shm_fd::create_anonymous(process) {
+            Ok(fd) => {
+                log::debug!(
+                    "pid={} created memfd {:?} as fd={}",
+                    process.pid(),
+                    read_c_str(process, self.u_name),
+                    fd.val()
+                );
+                LinuxSyscallResult::new_success(fd.val())
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
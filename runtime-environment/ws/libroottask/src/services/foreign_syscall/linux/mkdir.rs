@@ -0,0 +1,53 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/mkdir.2.html>. The umask of
+/// the process applies to the mode.
+#[derive(Debug)]
+pub struct MkdirSyscall {
+    // null terminated path name
+    pathname: *const u8,
+    mode: u64,
+}
+
+impl From<&GenericLinuxSyscall> for MkdirSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+            mode: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for MkdirSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let pathname = read_path(process, self.pathname);
+        match mkdir(process, &pathname, self.mode) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Creates the directory at `pathname`. Shared by `mkdir()` and `mkdirat()`.
+pub(super) fn mkdir(process: &Process, pathname: &str, mode: u64) -> Result<(), LinuxErrorCode> {
+    if pathname.is_empty() {
+        return Err(LinuxErrorCode::ENOENT);
+    }
+    libfileserver::FILESYSTEM
+        .lock()
+        .mkdir(process.pid(), pathname, (mode & 0o777) as u16)
+        .map_err(LinuxErrorCode::from)
+}
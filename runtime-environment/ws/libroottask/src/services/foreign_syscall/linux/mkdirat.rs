@@ -0,0 +1,46 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::mkdir::mkdir;
+use crate::services::foreign_syscall::linux::path::read_path_at;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/mkdirat.2.html>. See
+/// [`super::mkdir::MkdirSyscall`]. Like for `faccessat()`, relative paths are only
+/// supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct MkdirAtSyscall {
+    dirfd: i32,
+    // null terminated path name
+    pathname: *const u8,
+    mode: u64,
+}
+
+impl From<&GenericLinuxSyscall> for MkdirAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            pathname: syscall.arg1() as *const _,
+            mode: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for MkdirAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let res = read_path_at(process, self.dirfd, self.pathname)
+            .and_then(|pathname| mkdir(process, &pathname, self.mode));
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
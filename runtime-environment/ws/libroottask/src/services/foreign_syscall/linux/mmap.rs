@@ -1,13 +1,19 @@
 use crate::process::Process;
 use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::shm_fd::{
+    self,
+    ShmFd,
+};
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::shm;
 use alloc::rc::Rc;
-use core::alloc::Layout;
+use libfileserver::FileDescriptor;
 use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::MemCapPermissions;
 use libhrstd::libhedron::UtcbDataException;
 
 /// * <https://man7.org/linux/man-pages/man2/mmap.2.html>
@@ -41,44 +47,123 @@ impl LinuxSyscallImpl for MMapSyscall {
         _utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        log::trace!("Mmap: addr={:?}, len={}", self.addr, self.len);
-
-        if self.addr.is_null() {
-            // two most popular combinations
-
-            if (self.flags.contains(MMapFlags::ANONYMOUS)
-                && self.flags.contains(MMapFlags::PRIVATE))
-                || (self.flags.contains(MMapFlags::ANONYMOUS)
-                    && self.flags.contains(MMapFlags::SHARED))
-            {
-                let ptr = process.memory_manager_mut().mmap(
-                    Layout::from_size_align(self.len as usize, PAGE_SIZE).unwrap(),
-                    process,
-                );
-                log::trace!("Mmap: ptr={:?}", ptr as *const u8);
-                LinuxSyscallResult::new_success(ptr)
-            } else {
-                LinuxSyscallResult::new_error(LinuxErrorCode::ENOMEM)
+        let anonymous = self.flags.contains(MMapFlags::ANONYMOUS);
+        if self.len == 0 || (!anonymous && self.offset % PAGE_SIZE as u64 != 0) {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let u_addr = if self.flags.contains(MMapFlags::FIXED) {
+            if self.addr as u64 % PAGE_SIZE as u64 != 0 {
+                return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
             }
-        } else if self.addr as u64 <= process.memory_manager().u_program_break_current().val()
+            Some(self.addr as u64)
+        } else if !self.addr.is_null()
+            && self.addr as u64 <= process.memory_manager().u_program_break_current().val()
             && self.addr as u64 >= process.memory_manager().u_program_break_begin().val()
         {
             // das hab ich bisher nur beobachtet, dass nach ein erhöhen der Program Break
             // der Bereich gemappt werden soll. Aber das mache ich ja bereits.. daher muss ich
             // in dem Fall nichts machen
 
-            LinuxSyscallResult::new_success(self.addr as u64)
+            return LinuxSyscallResult::new_success(self.addr as u64);
         } else {
-            log::error!("not implemented yet!");
-            LinuxSyscallResult::new_error(LinuxErrorCode::ENOMEM)
+            // without MAP_FIXED, the address is only a hint
+            None
+        };
+
+        if !anonymous {
+            let fd = FileDescriptor::new(self.fd);
+            if let Some(shm_fd) = shm_fd::get(process, fd) {
+                if self.flags.contains(MMapFlags::SHARED) {
+                    return self.map_shared(shm_fd, u_addr, process);
+                }
+            } else if let Err(e) = libfileserver::FILESYSTEM.lock().fstat(process.pid(), fd) {
+                return LinuxSyscallResult::new_error(e.into());
+            }
+        }
+
+        let res = process.memory_manager_mut().mmap_at(
+            u_addr,
+            self.len as usize,
+            self.prot.to_mem_cap_permissions(),
+            process,
+        );
+        let u_addr = match res {
+            Ok(u_addr) => u_addr,
+            Err(e) => {
+                log::debug!("Mmap: {:?}", e);
+                return LinuxSyscallResult::new_error(LinuxErrorCode::ENOMEM);
+            }
+        };
+        if !anonymous {
+            if let Err(e) = self.copy_file(u_addr, process) {
+                return LinuxSyscallResult::new_error(e);
+            }
         }
+        LinuxSyscallResult::new_success(u_addr)
     }
 }
 
+impl MMapSyscall {
+    /// Maps the shared memory segment of the file descriptor, see [`shm_fd`]. All
+    /// `MAP_SHARED` mappings of the segment share the same memory. Fixed addresses aren't
+    /// supported.
+    fn map_shared(
+        &self,
+        shm_fd: ShmFd,
+        u_addr: Option<u64>,
+        process: &Process,
+    ) -> LinuxSyscallResult {
+        if u_addr.is_some() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        if self.prot.contains(MMapProt::WRITE) && !shm_fd.writable {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EACCES);
+        }
+        let perm = self.prot.to_mem_cap_permissions();
+        match shm::map(process, shm_fd.id, self.offset, self.len, perm) {
+            Ok(u_addr) => LinuxSyscallResult::new_success(u_addr),
+            Err(e) => LinuxSyscallResult::new_error(e.into()),
+        }
+    }
+
+    /// Copies the mapped part of the file into the new memory at `u_addr`. The rest of the
+    /// memory stays zero. Changes to the memory don't reach the file, i.e. the mapping is
+    /// private in any case. The same applies to shared memory segments without
+    /// `MAP_SHARED`.
+    fn copy_file(&self, u_addr: u64, process: &Process) -> Result<(), LinuxErrorCode> {
+        let fd = FileDescriptor::new(self.fd);
+        if let Some(shm_fd) = shm_fd::get(process, fd) {
+            let data = shm::read(process.pid(), shm_fd.id, self.offset, self.len)?;
+            return copy_to_user_memory(u_addr, &data, process);
+        }
+        let mut fs = libfileserver::FILESYSTEM.lock();
+        let data = fs
+            .pread_file(process.pid(), fd, self.offset as usize, self.len as usize)
+            .map_err(LinuxErrorCode::from)?;
+        copy_to_user_memory(u_addr, data, process)
+    }
+}
+
+/// Copies `data` into the memory of the process at `u_addr`, which the memory manager of
+/// the process owns.
+fn copy_to_user_memory(u_addr: u64, data: &[u8], process: &Process) -> Result<(), LinuxErrorCode> {
+    let mut memory_manager = process.memory_manager_mut();
+    let mut copied = 0;
+    while copied < data.len() {
+        let memory = memory_manager
+            .user_memory_mut(u_addr + copied as u64)
+            .ok_or(LinuxErrorCode::ENOMEM)?;
+        let len = memory.len().min(data.len() - copied);
+        memory[..len].copy_from_slice(&data[copied..][..len]);
+        copied += len;
+    }
+    Ok(())
+}
+
 bitflags::bitflags! {
     /// Don't know why iti s called PROT but it describes the permissions.
     /// <https://elixir.bootlin.com/linux/latest/source/include/uapi/asm-generic/mman-common.h#L12>
-    struct MMapProt: u64 {
+    pub struct MMapProt: u64 {
         /// page can be read
         const READ = 0x1;
         /// page can be written
@@ -88,6 +173,21 @@ bitflags::bitflags! {
     }
 }
 
+impl MMapProt {
+    /// Translates the protection into the permissions of a Hedron mapping. Like on x86
+    /// under Linux, writable pages are also readable.
+    pub fn to_mem_cap_permissions(self) -> MemCapPermissions {
+        let mut perm = MemCapPermissions::empty();
+        perm.set(
+            MemCapPermissions::READ,
+            self.intersects(Self::READ | Self::WRITE),
+        );
+        perm.set(MemCapPermissions::WRITE, self.contains(Self::WRITE));
+        perm.set(MemCapPermissions::EXECUTE, self.contains(Self::EXEC));
+        perm
+    }
+}
+
 bitflags::bitflags! {
     /// * <https://elixir.bootlin.com/linux/latest/source/include/uapi/asm-generic/mman-common.h#L12>
     /// * <https://elixir.bootlin.com/linux/latest/source/include/uapi/asm-generic/mman-common.h#L22>
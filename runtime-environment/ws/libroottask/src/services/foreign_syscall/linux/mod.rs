@@ -1,30 +1,120 @@
+mod accept;
+mod access;
+mod alarm;
 mod arch_prctl;
+mod bind;
 mod brk;
+mod chdir;
 mod clock_gettime;
+mod clock_nanosleep;
+mod clock_settime;
 mod clone;
 mod close;
+mod connect;
 mod consts;
+mod epoll;
+mod epoll_create;
+mod epoll_create1;
+mod epoll_ctl;
+mod epoll_pwait;
+mod epoll_wait;
 mod error_code;
+mod event_fd;
+mod eventfd;
+mod eventfd2;
+mod execve;
+mod exit;
+mod faccessat;
 mod fcntl;
+mod fd_events;
 mod fstat;
+mod ftruncate;
 mod generic;
+mod getcwd;
+mod getrandom;
+mod getrlimit;
+mod gettid;
+mod gettimeofday;
+mod inet_socket;
 mod ioctl;
+mod kill;
+mod link;
+mod linkat;
+mod listen;
 mod lseek;
+mod lstat;
 mod madvise;
+mod memfd_create;
+mod mkdir;
+mod mkdirat;
 mod mmap;
 mod mprotect;
 mod munmap;
+mod nanosleep;
+mod newfstatat;
 mod open;
+mod openat;
+mod path;
 mod poll;
+mod ppoll;
+mod prctl;
+mod pread64;
+mod preadv;
+mod prlimit64;
+mod pselect6;
+mod pwrite64;
+mod pwritev;
 mod read;
+mod read_v;
+mod readlink;
+mod readlinkat;
+mod recvfrom;
+mod recvmsg;
+mod rename;
+mod renameat;
+mod renameat2;
+mod restart;
+mod rmdir;
+mod rseq;
+mod rt_sigreturn;
 mod rtsigaction;
 mod rtsigprocmask;
 mod sched_getaffinity;
+mod sched_setaffinity;
+mod select;
+mod sendmsg;
+mod sendto;
+mod set_robust_list;
 mod set_tid_address;
+mod setrlimit;
+mod settimeofday;
+mod shm_fd;
+mod signal;
 mod signalstack;
+mod socket;
+mod socketpair;
+mod spawn;
+mod stat;
+mod statx;
+mod symlink;
+mod symlinkat;
 mod syscall_num;
 mod sysinfo;
+mod termios;
+mod tgkill;
+mod time;
+mod timer_fd;
+mod timerfd_create;
+mod timerfd_gettime;
+mod timerfd_settime;
+mod trace;
+mod truncate;
+mod umask;
+mod uname;
+mod unix_socket;
 mod unlink;
+mod unlinkat;
+mod vfork;
 mod write;
 mod write_v;
 
@@ -33,7 +123,21 @@ use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use alloc::rc::Rc;
 use core::fmt::Debug;
 pub use generic::GenericLinuxSyscall;
-use libhrstd::libhedron::UtcbDataException;
+use libhrstd::libhedron::{
+    Mtd,
+    UtcbDataException,
+};
+use libhrstd::process::consts::ProcessId;
+pub use restart::restart_if_waiting;
+pub use signal::{
+    deliver_pending_signal,
+    register_signal_exc_handlers,
+};
+pub use trace::{
+    format_call,
+    format_result,
+    format_unknown_call,
+};
 
 pub struct LinuxSyscallResult(i64);
 
@@ -47,12 +151,38 @@ impl LinuxSyscallResult {
         Self(-(error.val() as i64))
     }
 
+    /// Takes the value for RAX as it is. Only for syscalls that restore a previous state
+    /// of the process, such as `rt_sigreturn`, or that forward the result of another one.
+    fn new_raw(rax: u64) -> Self {
+        Self(rax as i64)
+    }
+
     /// Returns the value for the RAX register, which holds the syscall return code.
     pub fn val(self) -> u64 {
         self.0 as _
     }
 }
 
+/// Lets a syscall that the roottask doesn't know fail with `ENOSYS`, like on a Linux kernel
+/// that lacks the syscall. libc and language runtimes probe for optional syscalls, such as
+/// `rseq`, and cope with that.
+pub fn reject_unknown_syscall(utcb_exc: &mut UtcbDataException) {
+    utcb_exc.mtd |= Mtd::GPR_ACDB;
+    utcb_exc.rax = LinuxSyscallResult::new_error(LinuxErrorCode::ENOSYS).val();
+}
+
+/// Forgets everything that the Linux emulation keeps about a process that exited: a wait
+/// in progress, the objects behind its file descriptors, and the child of a `vfork()` that
+/// didn't start yet.
+pub fn release_process(pid: ProcessId) {
+    restart::forget_wait(pid);
+    epoll::release_process(pid);
+    event_fd::release_process(pid);
+    timer_fd::release_process(pid);
+    shm_fd::release_process(pid);
+    spawn::release_process(pid);
+}
+
 pub trait LinuxSyscallImpl: Debug {
     /// Must make sure, that the handler sets the correct return code in the correct register.
     fn handle(&self, utcb_exc: &mut UtcbDataException, process: &Rc<Process>)
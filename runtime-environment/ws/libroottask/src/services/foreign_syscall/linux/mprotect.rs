@@ -1,26 +1,31 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::mmap::MMapProt;
 use crate::services::foreign_syscall::linux::{
     GenericLinuxSyscall,
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
 use alloc::rc::Rc;
+use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::UtcbDataException;
 
-/// set protection on a region of memory
+/// set protection on a region of memory. Works for the memory of the ELF segments, of the
+/// program interpreter, and of `mmap`, see [`crate::process::ProcessMemoryManager::mprotect`].
+/// Other memory, such as the stack, keeps its permissions.
 #[derive(Debug)]
 pub struct MProtectSyscall {
-    _addr: u64,
-    _len: u64,
-    _prot: MProtect,
+    addr: u64,
+    len: u64,
+    prot: MProtect,
 }
 
 impl From<&GenericLinuxSyscall> for MProtectSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            _addr: syscall.arg0(),
-            _len: syscall.arg1(),
-            _prot: MProtect::from_bits(syscall.arg2()).unwrap(),
+            addr: syscall.arg0(),
+            len: syscall.arg1(),
+            prot: MProtect::from_bits(syscall.arg2()).unwrap(),
         }
     }
 }
@@ -29,9 +34,23 @@ impl LinuxSyscallImpl for MProtectSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        log::trace!("MProtect: {:#?}", self);
+        if self.addr % PAGE_SIZE as u64 != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let perm = MMapProt::from_bits_truncate(self.prot.bits()).to_mem_cap_permissions();
+        let res = process
+            .memory_manager_mut()
+            .mprotect(self.addr, self.len, perm, process);
+        if res.is_err() {
+            // e.g. the stack; the permissions stay as they are, as they always did
+            log::debug!(
+                "mprotect({:#x}, {:#x}): not only ELF or mmap memory, ignored",
+                self.addr,
+                self.len
+            );
+        }
         LinuxSyscallResult::new_success(0)
     }
 }
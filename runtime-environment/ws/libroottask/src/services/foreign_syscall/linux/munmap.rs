@@ -4,6 +4,7 @@ use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::shm;
 use alloc::rc::Rc;
 use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::UtcbDataException;
@@ -37,7 +38,10 @@ impl LinuxSyscallImpl for MUnMapSyscall {
         if self.addr % PAGE_SIZE as u64 != 0 {
             log::debug!("Linux app did not send page aligned address. This is with high certainty illegal! How does Linux get that address?! Mappings with mmap should all be page aligned..");
         }
-        process.memory_manager_mut().munmap(self.addr, process);
+        // mappings of shared memory segments don't belong to the memory manager
+        if !shm::unmap(process.pid(), self.addr) {
+            process.memory_manager_mut().munmap(self.addr, process);
+        }
         LinuxSyscallResult::new_success(0)
     }
 }
@@ -0,0 +1,91 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::clock_gettime::{
+    timespec,
+    write_user_timespec,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::restart;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use crate::time;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libhrstd::libhedron::UtcbDataException;
+
+/// The `nanosleep` syscall. The roottask restarts the syscall until the deadline passed,
+/// see [`restart`]; the handler itself never blocks. Like on Linux, a signal interrupts
+/// the sleep with `EINTR` and the remaining time is written to `rem`.
+#[derive(Debug)]
+pub struct NanoSleepSyscall {
+    u_ptr_req: u64,
+    u_ptr_rem: u64,
+}
+
+impl From<&GenericLinuxSyscall> for NanoSleepSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_ptr_req: syscall.arg0(),
+            u_ptr_rem: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for NanoSleepSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let duration_ns = match read_user_timespec(process, self.u_ptr_req) {
+            Ok(ns) => ns,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+
+        let tsc_deadline = time::tsc_now().saturating_add(time::ns_to_ticks(duration_ns));
+        sleep_until(process, tsc_deadline, self.u_ptr_rem)
+    }
+}
+
+/// Lets the syscall wait until the TSC reaches `tsc_deadline`; after a restart, the
+/// deadline of the first attempt applies. If a signal interrupts the sleep, the remaining
+/// time is written to `u_ptr_rem`, unless it is null.
+pub(super) fn sleep_until(
+    process: &Rc<Process>,
+    tsc_deadline: u64,
+    u_ptr_rem: u64,
+) -> LinuxSyscallResult {
+    let tsc_deadline = restart::deadline(process, Some(tsc_deadline)).unwrap_or(tsc_deadline);
+    let now = time::tsc_now();
+    if now >= tsc_deadline {
+        return LinuxSyscallResult::new_success(0);
+    }
+    let err = restart::restart(process, Some(tsc_deadline));
+    if matches!(err, LinuxErrorCode::EINTR) && u_ptr_rem != 0 {
+        let remaining_ns = time::ticks_to_ns(tsc_deadline - now);
+        if let Err(err) = write_user_timespec(process, u_ptr_rem, remaining_ns) {
+            return LinuxSyscallResult::new_error(err);
+        }
+    }
+    LinuxSyscallResult::new_error(err)
+}
+
+/// Reads a `struct timespec` from the address space of the process and returns its
+/// value in nanoseconds.
+pub(super) fn read_user_timespec(process: &Rc<Process>, u_ptr: u64) -> Result<u64, LinuxErrorCode> {
+    if u_ptr == 0 {
+        return Err(LinuxErrorCode::EFAULT);
+    }
+
+    let u_page_offset = u_ptr & 0xfff;
+    let mapping =
+        MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, u_ptr, size_of::<timespec>() as u64);
+    let ts = *mapping.mem_with_offset_as::<timespec>(u_page_offset as usize);
+
+    ts.as_nanos().ok_or(LinuxErrorCode::EINVAL)
+}
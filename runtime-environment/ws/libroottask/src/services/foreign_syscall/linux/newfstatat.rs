@@ -0,0 +1,107 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::{
+    LINUX_AT_EMPTY_PATH,
+    LINUX_AT_FDCWD,
+    LINUX_AT_NO_AUTOMOUNT,
+    LINUX_AT_SYMLINK_NOFOLLOW,
+    LINUX_PATH_MAX,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::stat::{
+    stat_path,
+    write_stat,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libfileserver::{
+    FileDescriptor,
+    FileStat,
+};
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/newfstatat.2.html>. glibc
+/// implements `stat()`, `lstat()`, and `fstat()` with it. Like for `faccessat()`,
+/// relative paths are only supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct NewFstatAtSyscall {
+    dirfd: i32,
+    // null terminated path name
+    pathname: *const u8,
+    u_ptr_statbuf: u64,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for NewFstatAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            pathname: syscall.arg1() as *const _,
+            u_ptr_statbuf: syscall.arg2(),
+            flags: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for NewFstatAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.pathname as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        if self.flags & !(LINUX_AT_SYMLINK_NOFOLLOW | LINUX_AT_NO_AUTOMOUNT | LINUX_AT_EMPTY_PATH)
+            != 0
+        {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        match stat_at(process, self.dirfd, pathname, self.flags) {
+            Ok(stat) => {
+                write_stat(process, self.u_ptr_statbuf, stat);
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Returns the metadata of the file at `pathname` or, with `AT_EMPTY_PATH` and an empty
+/// path, of the open file `dirfd`. Shared by `newfstatat()` and `statx()`.
+pub(super) fn stat_at(
+    process: &Process,
+    dirfd: i32,
+    pathname: &str,
+    flags: u64,
+) -> Result<FileStat, LinuxErrorCode> {
+    if pathname.is_empty() {
+        if flags & LINUX_AT_EMPTY_PATH == 0 {
+            return Err(LinuxErrorCode::ENOENT);
+        }
+        let fd = u64::try_from(dirfd).map_err(|_| LinuxErrorCode::EBADF)?;
+        return libfileserver::FILESYSTEM
+            .lock()
+            .fstat(process.pid(), FileDescriptor::new(fd))
+            .map_err(LinuxErrorCode::from);
+    }
+    // absolute paths ignore the dirfd
+    if !pathname.starts_with('/') && dirfd != LINUX_AT_FDCWD {
+        return Err(LinuxErrorCode::ENOTDIR);
+    }
+    stat_path(process, pathname, flags & LINUX_AT_SYMLINK_NOFOLLOW == 0)
+}
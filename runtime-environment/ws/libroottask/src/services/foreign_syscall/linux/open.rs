@@ -1,7 +1,8 @@
 use crate::process::Process;
 use crate::services::foreign_syscall::linux::consts::LINUX_PATH_MAX;
-use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::resolve_path;
+use crate::services::foreign_syscall::linux::shm_fd;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
@@ -47,17 +48,34 @@ impl LinuxSyscallImpl for OpenSyscall {
         // remove null bytes
         let filename = filename.as_str().trim_matches('\0');
 
-        let fd = libfileserver::FILESYSTEM.lock().open_or_create_file(
-            process.pid(),
-            filename,
-            self.flags,
-            self.umode as u16,
-        );
+        open_file(process, filename, self.flags, self.umode)
+    }
+}
 
-        if let Ok(fd) = fd {
-            LinuxSyscallResult::new_success(fd.val())
-        } else {
-            LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL)
-        }
+/// Opens or creates a file on behalf of the process. Shared by `open()` and `openat()`.
+/// Paths of named shared memory segments go to [`shm_fd::open`].
+pub(super) fn open_file(
+    process: &Process,
+    filename: &str,
+    flags: FsOpenFlags,
+    umode: u64,
+) -> LinuxSyscallResult {
+    let filename = resolve_path(process, filename);
+    if let Some(name) = shm_fd::shm_name(&filename) {
+        return match shm_fd::open(process, name, flags, umode as u16) {
+            Ok(fd) => LinuxSyscallResult::new_success(fd.val()),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        };
+    }
+    let fd = libfileserver::FILESYSTEM.lock().open_or_create_file(
+        process.pid(),
+        &filename,
+        flags,
+        umode as u16,
+    );
+
+    match fd {
+        Ok(fd) => LinuxSyscallResult::new_success(fd.val()),
+        Err(err) => LinuxSyscallResult::new_error(err.into()),
     }
 }
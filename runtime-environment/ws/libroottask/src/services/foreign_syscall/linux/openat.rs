@@ -0,0 +1,67 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::{
+    LINUX_AT_FDCWD,
+    LINUX_PATH_MAX,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::open::open_file;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::rt::services::fs::FsOpenFlags;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/openat.2.html>. glibc
+/// implements `open()` with it. Like for `faccessat()`, relative paths are only supported
+/// together with `AT_FDCWD`. Flags without meaning for the file system, such as
+/// `O_NOCTTY`, are ignored.
+#[derive(Debug)]
+pub struct OpenAtSyscall {
+    dirfd: i32,
+    // null terminated file name
+    filename: *const u8,
+    flags: FsOpenFlags,
+    umode: u64,
+}
+
+impl From<&GenericLinuxSyscall> for OpenAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            filename: syscall.arg1() as *const _,
+            flags: FsOpenFlags::from_bits_truncate(syscall.arg2() as u32),
+            umode: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for OpenAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.filename as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.filename as usize & 0xfff;
+        let filename = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let filename = CStr::try_from(filename).unwrap();
+        // remove null bytes
+        let filename = filename.as_str().trim_matches('\0');
+
+        // absolute paths ignore the dirfd
+        if !filename.starts_with('/') && self.dirfd != LINUX_AT_FDCWD {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ENOTDIR);
+        }
+        open_file(process, filename, self.flags, self.umode)
+    }
+}
@@ -0,0 +1,63 @@
+//! Path arguments of the Linux syscalls, read from the address space of the process.
+
+use crate::process::Process;
+use crate::rt::procfs;
+use crate::services::foreign_syscall::linux::consts::{
+    LINUX_AT_FDCWD,
+    LINUX_PATH_MAX,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use alloc::string::String;
+use libhrstd::cstr::CStr;
+
+/// Reads the null terminated path at `u_pathname` from the address space of the process
+/// and resolves it, see [`resolve_path`].
+pub(super) fn read_path(process: &Rc<Process>, u_pathname: *const u8) -> String {
+    resolve_path(process, &read_c_str(process, u_pathname))
+}
+
+/// Like [`read_path`] for the `*at()` syscalls, see [`check_dirfd`].
+pub(super) fn read_path_at(
+    process: &Rc<Process>,
+    dirfd: i32,
+    u_pathname: *const u8,
+) -> Result<String, LinuxErrorCode> {
+    let pathname = read_c_str(process, u_pathname);
+    check_dirfd(dirfd, &pathname)?;
+    Ok(resolve_path(process, &pathname))
+}
+
+/// Turns a path of the process into an absolute path of the file system: relative paths
+/// are relative to the current working directory of the process and `/proc/self` is the
+/// directory of the process. Empty paths stay empty.
+pub(super) fn resolve_path(process: &Process, pathname: &str) -> String {
+    let pathname = libfileserver::absolute_path(&process.cwd(), pathname);
+    procfs::resolve_self(&pathname, process.pid()).into_owned()
+}
+
+/// Reads a null terminated string of at most [`LINUX_PATH_MAX`] bytes from the address
+/// space of the process, e.g. the target of a new symbolic link.
+pub(super) fn read_c_str(process: &Rc<Process>, u_ptr: *const u8) -> String {
+    let mapping =
+        MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, u_ptr as u64, LINUX_PATH_MAX as u64);
+
+    let u_page_offset = u_ptr as usize & 0xfff;
+    let c_str = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+    let c_str = CStr::try_from(c_str).unwrap();
+    // remove null bytes
+    String::from(c_str.as_str().trim_matches('\0'))
+}
+
+/// Like for `faccessat()`, relative paths are only supported together with `AT_FDCWD`,
+/// i.e. relative to the current working directory. Absolute paths ignore the dirfd.
+pub(super) fn check_dirfd(dirfd: i32, pathname: &str) -> Result<(), LinuxErrorCode> {
+    if !pathname.starts_with('/') && dirfd != LINUX_AT_FDCWD {
+        Err(LinuxErrorCode::ENOTDIR)
+    } else {
+        Ok(())
+    }
+}
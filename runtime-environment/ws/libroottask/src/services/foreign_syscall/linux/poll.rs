@@ -1,25 +1,44 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_FD_SETSIZE;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events::{
+    deadline_after_ms,
+    ready_events,
+    wait_for_events,
+    POLLNVAL,
+    POLL_ALWAYS,
+};
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    write_to_user,
+};
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
 use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::mem::size_of;
+use libfileserver::FileDescriptor;
 use libhrstd::libhedron::UtcbDataException;
 
-/// Implementation of <https://man7.org/linux/man-pages/man2/poll.2.html>.
+/// Implementation of <https://man7.org/linux/man-pages/man2/poll.2.html>. Blocks until a
+/// file descriptor is ready, the timeout in milliseconds expires, or a signal arrives.
+/// A negative timeout waits forever. See [`super::fd_events`].
 #[derive(Debug)]
-#[allow(unused)]
 pub struct PollSyscall {
-    fds: *const *const PollFd,
-    count: usize,
+    u_fds: u64,
+    nfds: u64,
+    timeout_ms: i32,
 }
 
 impl From<&GenericLinuxSyscall> for PollSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            fds: syscall.arg0() as *const _,
-            count: syscall.arg1() as usize,
+            u_fds: syscall.arg0(),
+            nfds: syscall.arg1(),
+            timeout_ms: syscall.arg2() as i32,
         }
     }
 }
@@ -28,20 +47,76 @@ impl LinuxSyscallImpl for PollSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        // do nothing; it's okay for simple Linux programs
-
-        LinuxSyscallResult::new_success(0)
+        poll(
+            process,
+            self.u_fds,
+            self.nfds,
+            deadline_after_ms(self.timeout_ms),
+        )
     }
 }
 
+/// Same as `struct pollfd` of Linux.
+#[derive(Debug, Copy, Clone)]
 #[repr(C)]
 struct PollFd {
-    /* file descriptor */
-    fd: u32,
-    /* requested events */
+    /// file descriptor; negative values are ignored
+    fd: i32,
+    /// requested events
     events: u16,
-    /* returned events */
+    /// returned events
     revents: u16,
 }
+
+/// Waits for the `struct pollfd` array of `poll` and `ppoll` until the TSC reaches
+/// `tsc_deadline`. Writes the returned events back, unless the wait was interrupted.
+pub(super) fn poll(
+    process: &Rc<Process>,
+    u_fds: u64,
+    nfds: u64,
+    tsc_deadline: Option<u64>,
+) -> LinuxSyscallResult {
+    if nfds > LINUX_FD_SETSIZE {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+    }
+    if nfds > 0 && u_fds == 0 {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EFAULT);
+    }
+    let u_addr = |i: u64| u_fds + i * size_of::<PollFd>() as u64;
+    let mut fds = (0..nfds)
+        .map(|i| read_from_user::<PollFd>(process, u_addr(i)))
+        .collect::<Vec<_>>();
+
+    let res = wait_for_events(process, tsc_deadline, || {
+        fds.iter_mut()
+            .map(|poll_fd| {
+                poll_fd.revents = returned_events(process, poll_fd);
+                poll_fd.revents
+            })
+            .filter(|revents| *revents != 0)
+            .count()
+    });
+    match res {
+        Ok(count) => {
+            for (i, poll_fd) in fds.into_iter().enumerate() {
+                write_to_user(process, u_addr(i as u64), poll_fd);
+            }
+            LinuxSyscallResult::new_success(count as u64)
+        }
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
+
+/// Returns the requested events of the file descriptor that are ready, plus the events that
+/// are always reported.
+fn returned_events(process: &Rc<Process>, poll_fd: &PollFd) -> u16 {
+    if poll_fd.fd < 0 {
+        return 0;
+    }
+    match ready_events(process, FileDescriptor::new(poll_fd.fd as u64)) {
+        Some(ready) => ready & (poll_fd.events | POLL_ALWAYS),
+        None => POLLNVAL,
+    }
+}
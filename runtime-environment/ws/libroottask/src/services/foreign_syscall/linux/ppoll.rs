@@ -0,0 +1,49 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::fd_events::deadline_after_ns;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::nanosleep::read_user_timespec;
+use crate::services::foreign_syscall::linux::poll::poll;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/ppoll.2.html>. Same as `poll`,
+/// but with a `struct timespec` as timeout; null waits forever. The signal mask has no
+/// effect while waiting.
+#[derive(Debug)]
+pub struct PPollSyscall {
+    u_fds: u64,
+    nfds: u64,
+    u_timeout: u64,
+}
+
+impl From<&GenericLinuxSyscall> for PPollSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_fds: syscall.arg0(),
+            nfds: syscall.arg1(),
+            u_timeout: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for PPollSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let tsc_deadline = if self.u_timeout == 0 {
+            None
+        } else {
+            match read_user_timespec(process, self.u_timeout) {
+                Ok(ns) => Some(deadline_after_ns(ns)),
+                Err(err) => return LinuxSyscallResult::new_error(err),
+            }
+        };
+        poll(process, self.u_fds, self.nfds, tsc_deadline)
+    }
+}
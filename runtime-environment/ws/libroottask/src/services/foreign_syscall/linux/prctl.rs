@@ -0,0 +1,79 @@
+use crate::process::{
+    Process,
+    COMM_LEN,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use alloc::string::String;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/prctl.2.html>.
+///
+/// Supports renaming the process, i.e. `PR_SET_NAME` and `PR_GET_NAME`. A process has a
+/// single thread, hence the name of the thread is the name of the process. All other
+/// options fail with `EINVAL`, like on a Linux kernel that doesn't know the option. libc
+/// and language runtimes call `prctl` early and cope with that.
+#[derive(Debug)]
+pub struct PrctlSyscall {
+    option: u64,
+    arg2: u64,
+}
+
+impl From<&GenericLinuxSyscall> for PrctlSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            option: syscall.arg0(),
+            arg2: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for PrctlSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        match self.option {
+            PR_SET_NAME => {
+                let mapping =
+                    MAPPED_AREAS
+                        .lock()
+                        .create_or_get_mapping(process, self.arg2, COMM_LEN as u64);
+                let u_page_offset = self.arg2 as usize & 0xfff;
+                let name = mapping.mem_with_offset_as_slice::<u8>(COMM_LEN, u_page_offset);
+                // the name doesn't need a null byte if it has the maximum length
+                let len = name.iter().position(|byte| *byte == 0).unwrap_or(COMM_LEN);
+                process.set_comm(&String::from_utf8_lossy(&name[..len]));
+                LinuxSyscallResult::new_success(0)
+            }
+            PR_GET_NAME => {
+                let mut buf = [0_u8; COMM_LEN];
+                let comm = process.comm();
+                buf[..comm.len()].copy_from_slice(comm.as_bytes());
+                let mut mapping =
+                    MAPPED_AREAS
+                        .lock()
+                        .create_or_get_mapping(process, self.arg2, COMM_LEN as u64);
+                let r_buf =
+                    mapping.mem_with_offset_as_mut::<[u8; COMM_LEN]>((self.arg2 & 0xfff) as usize);
+                *r_buf = buf;
+                LinuxSyscallResult::new_success(0)
+            }
+            option => {
+                log::debug!("prctl option {} is not supported", option);
+                LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL)
+            }
+        }
+    }
+}
+
+/// <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/prctl.h#L56>
+const PR_SET_NAME: u64 = 15;
+const PR_GET_NAME: u64 = 16;
@@ -0,0 +1,55 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::read_v::read_vectored;
+use crate::services::foreign_syscall::linux::write_v::LinuxIoVec;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/pread64.2.html>. Reads at
+/// `offset` without changing the file offset.
+#[derive(Debug)]
+pub struct PRead64Syscall {
+    fd: u64,
+    u_buf: u64,
+    count: u64,
+    offset: i64,
+}
+
+impl From<&GenericLinuxSyscall> for PRead64Syscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: syscall.arg0(),
+            u_buf: syscall.arg1(),
+            count: syscall.arg2(),
+            offset: syscall.arg3() as i64,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for PRead64Syscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.offset < 0 || self.count > isize::MAX as u64 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let iovec = LinuxIoVec {
+            u_iov_base: self.u_buf as *const u8,
+            len: self.count,
+        };
+        read_vectored(
+            utcb_exc,
+            process,
+            self.fd,
+            Some(self.offset as usize),
+            &[iovec],
+        )
+    }
+}
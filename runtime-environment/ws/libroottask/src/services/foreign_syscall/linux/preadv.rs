@@ -0,0 +1,56 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::read_v::read_vectored;
+use crate::services::foreign_syscall::linux::write_v::checked_iovecs;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/preadv.2.html>. Like
+/// `readv()` but reads at `offset` without changing the file offset. On x86_64, the
+/// offset is a single register.
+#[derive(Debug)]
+pub struct PReadVSyscall {
+    fd: u64,
+    u_iov: u64,
+    // number of io vecs
+    count: u64,
+    offset: i64,
+}
+
+impl From<&GenericLinuxSyscall> for PReadVSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: syscall.arg0(),
+            u_iov: syscall.arg1(),
+            count: syscall.arg2(),
+            offset: syscall.arg3() as i64,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for PReadVSyscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.offset < 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        match checked_iovecs(process, self.u_iov, self.count) {
+            Ok(iovecs) => read_vectored(
+                utcb_exc,
+                process,
+                self.fd,
+                Some(self.offset as usize),
+                &iovecs,
+            ),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::process::{
+    is_privileged,
+    resource_limit,
+    set_resource_limit,
+    signal_target,
+    Process,
+    ResourceLimit,
+    ResourceLimitError,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    write_to_user,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::process::consts::ProcessId;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/prlimit64.2.html>, which
+/// libc uses for `getrlimit` and `setrlimit`. See [`crate::process::set_resource_limit`]
+/// for the limits that take effect. All processes run as the same user, hence every
+/// process may query and change the limits of every other process. Only privileged
+/// processes may raise hard limits.
+#[derive(Debug)]
+pub struct PrLimit64Syscall {
+    pid: ProcessId,
+    resource: u64,
+    u_new_limit: u64,
+    u_old_limit: u64,
+}
+
+impl From<&GenericLinuxSyscall> for PrLimit64Syscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pid: syscall.arg0(),
+            resource: syscall.arg1(),
+            u_new_limit: syscall.arg2(),
+            u_old_limit: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for PrLimit64Syscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let pid = if self.pid == 0 {
+            process.pid()
+        } else {
+            self.pid
+        };
+        if pid != process.pid() && signal_target(pid).is_none() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ESRCH);
+        }
+        match prlimit(
+            process,
+            pid,
+            self.resource,
+            self.u_new_limit,
+            self.u_old_limit,
+        ) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Reads the new limit of the resource of `pid` from `u_new_limit` and writes the old limit
+/// to `u_old_limit`, like `prlimit()`. Each pointer may be null.
+pub(super) fn prlimit(
+    process: &Rc<Process>,
+    pid: ProcessId,
+    resource: u64,
+    u_new_limit: u64,
+    u_old_limit: u64,
+) -> Result<(), LinuxErrorCode> {
+    let old = match u_new_limit {
+        0 => resource_limit(pid, resource).ok_or(LinuxErrorCode::EINVAL)?,
+        _ => {
+            let limit = read_from_user::<ResourceLimit>(process, u_new_limit);
+            set_resource_limit(pid, resource, limit, is_privileged(process.pid())).map_err(
+                |err| match err {
+                    ResourceLimitError::InvalidArgument => LinuxErrorCode::EINVAL,
+                    ResourceLimitError::PermissionDenied => LinuxErrorCode::EPERM,
+                },
+            )?
+        }
+    };
+    if u_old_limit != 0 {
+        write_to_user(process, u_old_limit, old);
+    }
+    Ok(())
+}
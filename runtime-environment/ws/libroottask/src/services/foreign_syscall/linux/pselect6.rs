@@ -0,0 +1,62 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::fd_events::deadline_after_ns;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::nanosleep::read_user_timespec;
+use crate::services::foreign_syscall::linux::select::select;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/pselect6.2.html>. Same as
+/// `select`, but with a `struct timespec` as timeout, which is not updated. The signal
+/// mask has no effect while waiting.
+#[derive(Debug)]
+pub struct PSelect6Syscall {
+    nfds: u64,
+    u_readfds: u64,
+    u_writefds: u64,
+    u_exceptfds: u64,
+    u_timeout: u64,
+}
+
+impl From<&GenericLinuxSyscall> for PSelect6Syscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            nfds: syscall.arg0(),
+            u_readfds: syscall.arg1(),
+            u_writefds: syscall.arg2(),
+            u_exceptfds: syscall.arg3(),
+            u_timeout: syscall.arg4(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for PSelect6Syscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let tsc_deadline = if self.u_timeout == 0 {
+            None
+        } else {
+            match read_user_timespec(process, self.u_timeout) {
+                Ok(ns) => Some(deadline_after_ns(ns)),
+                Err(err) => return LinuxSyscallResult::new_error(err),
+            }
+        };
+        let res = select(
+            process,
+            self.nfds,
+            [self.u_readfds, self.u_writefds, self.u_exceptfds],
+            tsc_deadline,
+        );
+        match res {
+            Ok(count) => LinuxSyscallResult::new_success(count as u64),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -1,15 +1,31 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::event_fd;
+use crate::services::foreign_syscall::linux::fd_events::{
+    fd_kind,
+    wait_for_events,
+    FdKind,
+};
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::is_inet_socket;
+use crate::services::foreign_syscall::linux::recvfrom::RecvFromSyscall;
+use crate::services::foreign_syscall::linux::termios;
+use crate::services::foreign_syscall::linux::timer_fd;
+use crate::services::foreign_syscall::linux::unix_socket::copy_to_user;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
-use crate::services::MAPPED_AREAS;
+use crate::services::{
+    stdin,
+    MAPPED_AREAS,
+};
 use alloc::rc::Rc;
 use core::cmp::min;
 use libfileserver::FileDescriptor;
 use libhrstd::libhedron::UtcbDataException;
 use libhrstd::mem::PageAlignedBuf;
+use libhrstd::rt::services::stdin::STDIN_MAX_READ;
 
 // Nils: for the evaluation I should simulate a more realistic scenario.
 // This is that the Linux OS Personality and the FS-Service use an
@@ -36,16 +52,45 @@ impl From<&GenericLinuxSyscall> for ReadSyscall {
     }
 }
 
+impl ReadSyscall {
+    pub(super) fn new(fd: FileDescriptor, user_buf: *mut u8, count: usize) -> Self {
+        Self {
+            fd,
+            user_buf,
+            count,
+        }
+    }
+}
+
 impl LinuxSyscallImpl for ReadSyscall {
     fn handle(
         &self,
-        _utcb_exc: &mut UtcbDataException,
+        utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
+        let u_buf = self.user_buf as u64;
+        match fd_kind(process, self.fd) {
+            Some(FdKind::Console) if self.fd.val() == 0 => {
+                return read_stdin(process, u_buf, self.count)
+            }
+            Some(FdKind::EventFd) => return event_fd::read(process, self.fd, u_buf, self.count),
+            Some(FdKind::TimerFd) => return timer_fd::read(process, self.fd, u_buf, self.count),
+            Some(FdKind::Shm) => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+            _ => {}
+        }
+
+        // don't hold the lock of the file system while checking for sockets of the network stack
+        let is_udp_socket = is_inet_socket(process, self.fd);
         let mut fs_lock = libfileserver::FILESYSTEM.lock();
-        let data = fs_lock
-            .read_file(process.pid(), self.fd, self.count)
-            .unwrap();
+        if is_udp_socket || fs_lock.is_socket(process.pid(), self.fd) {
+            drop(fs_lock);
+            return RecvFromSyscall::new(self.fd, self.user_buf as u64, self.count)
+                .handle(utcb_exc, process);
+        }
+        let data = match fs_lock.read_file(process.pid(), self.fd, self.count) {
+            Ok(data) => data,
+            Err(err) => return LinuxSyscallResult::new_error(err.into()),
+        };
 
         let bytes_read = min(self.count, data.len());
 
@@ -73,3 +118,18 @@ impl LinuxSyscallImpl for ReadSyscall {
         LinuxSyscallResult::new_success(bytes_read as u64)
     }
 }
+
+/// Reads from the console, like the stdin service does for native processes. Waits until
+/// the console has input, but returns only what's there already. The console echoes the
+/// input.
+fn read_stdin(process: &Rc<Process>, u_buf: u64, count: usize) -> LinuxSyscallResult {
+    if count == 0 {
+        return LinuxSyscallResult::new_success(0);
+    }
+    if let Err(err) = wait_for_events(process, None, || stdin::input_available() as usize) {
+        return LinuxSyscallResult::new_error(err);
+    }
+    let data = stdin::read_available(min(count, STDIN_MAX_READ), termios::echo_enabled());
+    copy_to_user(process, u_buf, &data);
+    LinuxSyscallResult::new_success(data.len() as u64)
+}
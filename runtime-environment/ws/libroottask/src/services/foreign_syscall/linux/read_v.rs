@@ -0,0 +1,124 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::is_inet_socket;
+use crate::services::foreign_syscall::linux::read::ReadSyscall;
+use crate::services::foreign_syscall::linux::write_v::{
+    checked_iovecs,
+    LinuxIoVec,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use core::cmp::min;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/readv.2.html>. See
+/// [`read_vectored`].
+#[derive(Debug)]
+pub struct ReadVSyscall {
+    fd: u64,
+    u_iov: u64,
+    // number of io vecs
+    count: u64,
+}
+
+impl From<&GenericLinuxSyscall> for ReadVSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: syscall.arg0(),
+            u_iov: syscall.arg1(),
+            count: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ReadVSyscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        match checked_iovecs(process, self.u_iov, self.count) {
+            Ok(iovecs) => read_vectored(utcb_exc, process, self.fd, None, &iovecs),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Fills the buffers one after another, from `offset` if set. Shared by `readv()`,
+/// `preadv()`, and `pread64()`. A file gets read with a single request to the file system
+/// for the total length of all buffers. Sockets, which have no offset (`ESPIPE`), fill
+/// each buffer on its own.
+pub(super) fn read_vectored(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    fd: u64,
+    offset: Option<usize>,
+    iovecs: &[LinuxIoVec],
+) -> LinuxSyscallResult {
+    let fd = FileDescriptor::new(fd);
+    // don't hold the lock of the file system while checking for sockets of the network stack
+    let is_socket = is_inet_socket(process, fd)
+        || libfileserver::FILESYSTEM
+            .lock()
+            .is_socket(process.pid(), fd);
+    if is_socket {
+        if offset.is_some() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ESPIPE);
+        }
+        let mut bytes_read = 0;
+        for iovec in iovecs {
+            let res = ReadSyscall::new(fd, iovec.u_iov_base as *mut u8, iovec.len as usize)
+                .handle(utcb_exc, process);
+            let rax = res.val();
+            let count = rax as i64;
+            if count < 0 {
+                return if bytes_read > 0 {
+                    LinuxSyscallResult::new_success(bytes_read)
+                } else {
+                    LinuxSyscallResult::new_raw(rax)
+                };
+            }
+            bytes_read += count as u64;
+            if (count as u64) < iovec.len {
+                break;
+            }
+        }
+        return LinuxSyscallResult::new_success(bytes_read);
+    }
+
+    let total_len = iovecs.iter().map(|iovec| iovec.len as usize).sum();
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    let data = match offset {
+        None => fs.read_file(process.pid(), fd, total_len),
+        Some(offset) => fs.pread_file(process.pid(), fd, offset, total_len),
+    };
+    let data = match data {
+        Ok(data) => data,
+        Err(err) => return LinuxSyscallResult::new_error(err.into()),
+    };
+
+    let mut remaining = data;
+    for iovec in iovecs {
+        if remaining.is_empty() {
+            break;
+        }
+        let (chunk, rest) = remaining.split_at(min(iovec.len as usize, remaining.len()));
+        if !chunk.is_empty() {
+            let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                iovec.u_iov_base as u64,
+                chunk.len() as u64,
+            );
+            let r_write_ptr = mapping.old_to_new_ptr_mut(iovec.u_iov_base as *mut u8);
+            unsafe { core::ptr::copy_nonoverlapping(chunk.as_ptr(), r_write_ptr, chunk.len()) };
+        }
+        remaining = rest;
+    }
+    LinuxSyscallResult::new_success(data.len() as u64)
+}
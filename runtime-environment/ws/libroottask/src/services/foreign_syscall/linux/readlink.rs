@@ -0,0 +1,95 @@
+use crate::process::Process;
+use crate::rt::procfs;
+use crate::services::foreign_syscall::linux::consts::LINUX_PATH_MAX;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::resolve_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use alloc::string::String;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/readlink.2.html>. Besides
+/// the symbolic links of the file system, there is `/proc/self/exe`, which points to the
+/// program of the process; glibc reads it during startup to find the origin of the
+/// program.
+#[derive(Debug)]
+pub struct ReadLinkSyscall {
+    // null terminated path name
+    pathname: *const u8,
+    u_buf: u64,
+    bufsiz: i64,
+}
+
+impl From<&GenericLinuxSyscall> for ReadLinkSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+            u_buf: syscall.arg1(),
+            bufsiz: syscall.arg2() as i64,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ReadLinkSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.pathname as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        read_link(process, pathname, self.u_buf, self.bufsiz)
+    }
+}
+
+/// Writes the target of the link at `pathname` into the buffer of the process without a
+/// null byte. Shared by `readlink()` and `readlinkat()`.
+pub(super) fn read_link(
+    process: &Rc<Process>,
+    pathname: &str,
+    u_buf: u64,
+    bufsiz: i64,
+) -> LinuxSyscallResult {
+    if bufsiz <= 0 {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+    }
+    let pathname = resolve_path(process, pathname);
+    let exe = format!("{}/{}/exe", procfs::PROC_MOUNT_POINT, process.pid());
+    let target = if pathname == exe {
+        String::from(process.name())
+    } else {
+        let target = libfileserver::FILESYSTEM
+            .lock()
+            .read_link(process.pid(), &pathname);
+        match target {
+            Ok(target) => target,
+            Err(err) => return LinuxSyscallResult::new_error(err.into()),
+        }
+    };
+
+    let target = target.as_bytes();
+    // like Linux, silently truncates the target
+    let len = target.len().min(bufsiz as usize);
+    let mapping = MAPPED_AREAS
+        .lock()
+        .create_or_get_mapping(process, u_buf, len as u64);
+    let r_buf = mapping.old_to_new_ptr_mut(u_buf as *mut u8);
+    unsafe { core::ptr::copy_nonoverlapping(target.as_ptr(), r_buf, len) };
+    LinuxSyscallResult::new_success(len as u64)
+}
@@ -0,0 +1,65 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::{
+    LINUX_AT_FDCWD,
+    LINUX_PATH_MAX,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::readlink::read_link;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/readlinkat.2.html>. See
+/// [`super::readlink::ReadLinkSyscall`]. Like for `faccessat()`, relative paths are only
+/// supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct ReadLinkAtSyscall {
+    dirfd: i32,
+    // null terminated path name
+    pathname: *const u8,
+    u_buf: u64,
+    bufsiz: i64,
+}
+
+impl From<&GenericLinuxSyscall> for ReadLinkAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            pathname: syscall.arg1() as *const _,
+            u_buf: syscall.arg2(),
+            bufsiz: syscall.arg3() as i64,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for ReadLinkAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.pathname as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        // absolute paths ignore the dirfd
+        if !pathname.starts_with('/') && self.dirfd != LINUX_AT_FDCWD {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ENOTDIR);
+        }
+        read_link(process, pathname, self.u_buf, self.bufsiz)
+    }
+}
@@ -0,0 +1,83 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_recv,
+    is_inet_socket,
+    write_sockaddr_in,
+};
+use crate::services::foreign_syscall::linux::unix_socket::{
+    copy_to_user,
+    socket_recv,
+    write_sockaddr_un,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/recvfrom.2.html>.
+/// The flags have no effect. Waits for data on blocking local sockets; otherwise, fails
+/// with `EAGAIN` if no data is available.
+#[derive(Debug)]
+pub struct RecvFromSyscall {
+    fd: FileDescriptor,
+    u_buf: u64,
+    len: usize,
+    u_src_addr: u64,
+    u_addr_len: u64,
+}
+
+impl From<&GenericLinuxSyscall> for RecvFromSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_buf: syscall.arg1(),
+            len: syscall.arg2() as usize,
+            u_src_addr: syscall.arg4(),
+            u_addr_len: syscall.arg5(),
+        }
+    }
+}
+
+impl RecvFromSyscall {
+    /// Receive without a source address. Used by `read` on sockets.
+    pub(super) fn new(fd: FileDescriptor, u_buf: u64, len: usize) -> Self {
+        Self {
+            fd,
+            u_buf,
+            len,
+            u_src_addr: 0,
+            u_addr_len: 0,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for RecvFromSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if is_inet_socket(process, self.fd) {
+            return match inet_recv(process, self.fd, self.len) {
+                Ok((data, from)) => {
+                    copy_to_user(process, self.u_buf, &data);
+                    write_sockaddr_in(process, self.u_src_addr, self.u_addr_len, from);
+                    LinuxSyscallResult::new_success(data.len() as u64)
+                }
+                Err(err) => LinuxSyscallResult::new_error(err),
+            };
+        }
+        match socket_recv(process, self.fd, self.len) {
+            Ok((data, from)) => {
+                copy_to_user(process, self.u_buf, &data);
+                write_sockaddr_un(process, self.u_src_addr, self.u_addr_len, from.as_deref());
+                LinuxSyscallResult::new_success(data.len() as u64)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    copy_sockaddr_in_to_user,
+    inet_recv,
+    is_inet_socket,
+};
+use crate::services::foreign_syscall::linux::unix_socket::{
+    copy_sockaddr_un_to_user,
+    copy_to_user,
+    read_from_user,
+    read_iovecs,
+    socket_recv,
+    write_to_user,
+    MsgHdr,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use core::cmp::min;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/recvmsg.2.html>.
+/// The flags have no effect. Never returns ancillary data. Waits for data on blocking
+/// local sockets; otherwise, fails with `EAGAIN` if no data is available.
+#[derive(Debug)]
+pub struct RecvMsgSyscall {
+    fd: FileDescriptor,
+    u_msg: u64,
+}
+
+impl From<&GenericLinuxSyscall> for RecvMsgSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_msg: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for RecvMsgSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mut msg = read_from_user::<MsgHdr>(process, self.u_msg);
+        let iovecs = read_iovecs(process, msg.msg_iov, msg.msg_iovlen);
+        let max_len = iovecs.iter().map(|iov| iov.len as usize).sum();
+
+        // the sender is either the name of a local socket or an IPv4 address
+        let res = if is_inet_socket(process, self.fd) {
+            inet_recv(process, self.fd, max_len).map(|(data, from)| (data, None, Some(from)))
+        } else {
+            socket_recv(process, self.fd, max_len).map(|(data, from)| (data, from, None))
+        };
+        let (data, from, inet_from) = match res {
+            Ok(res) => res,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+
+        // scatter the data across the io vecs
+        let mut remaining = data.as_slice();
+        for iov in iovecs {
+            let len = min(iov.len as usize, remaining.len());
+            copy_to_user(process, iov.u_iov_base as u64, &remaining[..len]);
+            remaining = &remaining[len..];
+        }
+
+        if msg.msg_name != 0 {
+            msg.msg_namelen = match inet_from {
+                Some(addr) => {
+                    copy_sockaddr_in_to_user(process, msg.msg_name, msg.msg_namelen, addr)
+                }
+                None => copy_sockaddr_un_to_user(
+                    process,
+                    msg.msg_name,
+                    msg.msg_namelen,
+                    from.as_deref(),
+                ),
+            };
+        }
+        msg.msg_controllen = 0;
+        msg.msg_flags = 0;
+        write_to_user(process, self.u_msg, msg);
+
+        LinuxSyscallResult::new_success(data.len() as u64)
+    }
+}
@@ -0,0 +1,66 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/rename.2.html>.
+#[derive(Debug)]
+pub struct RenameSyscall {
+    // null terminated path names
+    oldpath: *const u8,
+    newpath: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for RenameSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            oldpath: syscall.arg0() as *const _,
+            newpath: syscall.arg1() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for RenameSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let oldpath = read_path(process, self.oldpath);
+        let newpath = read_path(process, self.newpath);
+        match rename(process, &oldpath, &newpath, false) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Moves the file at `oldpath` to `newpath`. Replaces a file at `newpath` unless
+/// `no_replace` is set. Shared by the `rename()` family.
+pub(super) fn rename(
+    process: &Process,
+    oldpath: &str,
+    newpath: &str,
+    no_replace: bool,
+) -> Result<(), LinuxErrorCode> {
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    fs.lstat_path(process.pid(), oldpath)?;
+    if no_replace && fs.lstat_path(process.pid(), newpath).is_ok() {
+        return Err(LinuxErrorCode::EEXIST);
+    }
+    if !fs.is_same_mount(process.pid(), oldpath, newpath) {
+        return Err(LinuxErrorCode::EXDEV);
+    }
+    if fs.is_read_only() {
+        return Err(LinuxErrorCode::EROFS);
+    }
+    // e.g. a directory into itself or a backend without renames
+    fs.rename_file(process.pid(), oldpath, newpath)
+        .map_err(LinuxErrorCode::from)
+}
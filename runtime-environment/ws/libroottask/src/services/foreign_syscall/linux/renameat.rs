@@ -0,0 +1,49 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path_at;
+use crate::services::foreign_syscall::linux::rename::rename;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/renameat.2.html>. Like for
+/// `faccessat()`, relative paths are only supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct RenameAtSyscall {
+    olddirfd: i32,
+    // null terminated path names
+    oldpath: *const u8,
+    newdirfd: i32,
+    newpath: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for RenameAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            olddirfd: syscall.arg0() as i32,
+            oldpath: syscall.arg1() as *const _,
+            newdirfd: syscall.arg2() as i32,
+            newpath: syscall.arg3() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for RenameAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let res = read_path_at(process, self.olddirfd, self.oldpath).and_then(|oldpath| {
+            let newpath = read_path_at(process, self.newdirfd, self.newpath)?;
+            rename(process, &oldpath, &newpath, false)
+        });
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path_at;
+use crate::services::foreign_syscall::linux::rename::rename;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/renameat2.2.html>. See
+/// [`super::renameat::RenameAtSyscall`]. Of the flags, only `RENAME_NOREPLACE` is
+/// supported. glibc uses it for `renameat2()` and tools like `mv -n`.
+#[derive(Debug)]
+pub struct RenameAt2Syscall {
+    olddirfd: i32,
+    // null terminated path names
+    oldpath: *const u8,
+    newdirfd: i32,
+    newpath: *const u8,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for RenameAt2Syscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            olddirfd: syscall.arg0() as i32,
+            oldpath: syscall.arg1() as *const _,
+            newdirfd: syscall.arg2() as i32,
+            newpath: syscall.arg3() as *const _,
+            flags: syscall.arg4(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for RenameAt2Syscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        // RENAME_EXCHANGE and RENAME_WHITEOUT aren't supported
+        if self.flags & !RENAME_NOREPLACE != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let no_replace = self.flags & RENAME_NOREPLACE != 0;
+        let res = read_path_at(process, self.olddirfd, self.oldpath).and_then(|oldpath| {
+            let newpath = read_path_at(process, self.newdirfd, self.newpath)?;
+            rename(process, &oldpath, &newpath, no_replace)
+        });
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/fs.h#L50>
+const RENAME_NOREPLACE: u64 = 1;
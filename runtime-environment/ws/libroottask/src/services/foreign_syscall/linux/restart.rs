@@ -0,0 +1,95 @@
+//! Restart of syscalls that wait, e.g. `poll`, `nanosleep`, or a blocking `read`.
+//!
+//! The handlers of foreign syscalls run while the lock of the process manager is held
+//! (see [`crate::pt_multiplex`]), hence they must never block. A handler that has to wait
+//! returns [`LinuxErrorCode::ERESTARTSYS`] via [`restart`] instead. Afterwards,
+//! [`restart_if_waiting`] sets the instruction pointer of the process back to the
+//! `syscall` instruction and the reply is delayed until the next check, after the lock
+//! was released (see [`crate::pt_multiplex::delay_reply`]). The process then executes the
+//! syscall again, until it doesn't need to wait anymore.
+//!
+//! The deadline of the first attempt stays valid for all restarts, see [`deadline`].
+//! Processes have a single thread and are blocked in the portal call until the reply,
+//! hence the next syscall of a process after a restart is always the restarted one.
+
+use crate::process::{
+    exit_status,
+    Process,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use crate::time;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::cmp::min;
+use libhrstd::libhedron::{
+    Mtd,
+    UtcbDataException,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Interval in which a waiting syscall gets executed again. Nothing notifies the roottask
+/// about new events, hence a shorter interval reduces the latency but costs more CPU time.
+const CHECK_INTERVAL_NS: u64 = 1_000_000;
+
+/// Length of the `syscall` instruction.
+const SYSCALL_INSN_LEN: u64 = 2;
+
+/// Deadlines of the syscalls that wait, by process. `None` means no deadline. Only
+/// holds entries between a restart and the next attempt.
+static WAITS: SimpleMutex<BTreeMap<ProcessId, Option<u64>>> = SimpleMutex::new(BTreeMap::new());
+
+/// Returns the TSC deadline of a syscall that waits: the one of the first attempt if the
+/// process executes the syscall again after a restart, otherwise `tsc_deadline`.
+pub(super) fn deadline(process: &Process, tsc_deadline: Option<u64>) -> Option<u64> {
+    WAITS
+        .lock()
+        .get(&process.pid())
+        .copied()
+        .unwrap_or(tsc_deadline)
+}
+
+/// Lets the syscall wait until the next check. Returns the error code that the handler
+/// must return. Like on Linux, the wait ends with `EINTR` if a signal is pending. The wait
+/// also ends if the process exited in the meantime, e.g. because another thread called
+/// `exit_group`.
+pub(super) fn restart(process: &Process, tsc_deadline: Option<u64>) -> LinuxErrorCode {
+    if process.has_pending_signal() || exit_status(process.pid()).is_some() {
+        return LinuxErrorCode::EINTR;
+    }
+    WAITS.lock().insert(process.pid(), tsc_deadline);
+    LinuxErrorCode::ERESTARTSYS
+}
+
+/// Prepares the restart of the syscall if its handler returned
+/// [`LinuxErrorCode::ERESTARTSYS`]: restores the syscall number and points the
+/// instruction pointer to the `syscall` instruction again. Returns the TSC value until
+/// which the reply must be delayed. Otherwise, the syscall is done and this returns `None`.
+/// `syscall_num` is the original value of RAX.
+pub fn restart_if_waiting(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    syscall_num: u64,
+) -> Option<u64> {
+    let restart = LinuxSyscallResult::new_error(LinuxErrorCode::ERESTARTSYS).val();
+    let mut waits = WAITS.lock();
+    if utcb_exc.rax != restart {
+        waits.remove(&process.pid());
+        return None;
+    }
+    let tsc_deadline = *waits
+        .get(&process.pid())
+        .expect("handlers restart syscalls via restart()");
+    utcb_exc.mtd |= Mtd::RIP_LEN | Mtd::GPR_ACDB;
+    utcb_exc.rip -= SYSCALL_INSN_LEN;
+    utcb_exc.rax = syscall_num;
+
+    let next_check = time::tsc_now().saturating_add(time::ns_to_ticks(CHECK_INTERVAL_NS));
+    Some(tsc_deadline.map_or(next_check, |deadline| min(deadline, next_check)))
+}
+
+/// Forgets the wait of an exited process.
+pub(super) fn forget_wait(pid: ProcessId) {
+    WAITS.lock().remove(&pid);
+}
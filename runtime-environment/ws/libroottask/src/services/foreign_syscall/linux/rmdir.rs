@@ -0,0 +1,52 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/rmdir.2.html>. Only empty
+/// directories can be removed.
+#[derive(Debug)]
+pub struct RmdirSyscall {
+    // null terminated path name
+    pathname: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for RmdirSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for RmdirSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let pathname = read_path(process, self.pathname);
+        match rmdir(process, &pathname) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Removes the empty directory at `pathname`. Shared by `rmdir()` and `unlinkat()`.
+pub(super) fn rmdir(process: &Process, pathname: &str) -> Result<(), LinuxErrorCode> {
+    // like Linux, the current working directory itself can't be removed
+    if pathname.trim_end_matches('/') == process.cwd().trim_end_matches('/') {
+        return Err(LinuxErrorCode::EBUSY);
+    }
+    libfileserver::FILESYSTEM
+        .lock()
+        .rmdir(process.pid(), pathname)
+        .map_err(LinuxErrorCode::from)
+}
@@ -0,0 +1,33 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/rseq.2.html>. glibc 2.35 and
+/// newer registers a restartable sequence area for each thread during startup. The
+/// roottask can't abort restartable sequences on preemption, because Hedron doesn't tell
+/// it about preemptions. Hence, the registration fails with `ENOSYS`, like on Linux
+/// kernels without rseq, and glibc continues without.
+#[derive(Debug)]
+pub struct RseqSyscall;
+
+impl From<&GenericLinuxSyscall> for RseqSyscall {
+    fn from(_syscall: &GenericLinuxSyscall) -> Self {
+        Self
+    }
+}
+
+impl LinuxSyscallImpl for RseqSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        _process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        LinuxSyscallResult::new_error(LinuxErrorCode::ENOSYS)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::signal::restore_signal_frame;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/sigreturn.2.html>.
+/// The `sa_restorer` of libc invokes it after the signal handler returned. At this point,
+/// the `ret` of the handler consumed the return address of the signal frame, therefore
+/// the stack pointer points to the `ucontext` of the frame.
+#[derive(Debug)]
+pub struct RtSigreturnSyscall;
+
+impl From<&GenericLinuxSyscall> for RtSigreturnSyscall {
+    fn from(_syscall: &GenericLinuxSyscall) -> Self {
+        Self
+    }
+}
+
+impl LinuxSyscallImpl for RtSigreturnSyscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let u_ptr_uc = utcb_exc.rsp;
+        let rax = restore_signal_frame(utcb_exc, process, u_ptr_uc);
+        log::debug!("rt_sigreturn: continue at rip={:#x}", utcb_exc.rip);
+        // the process continues with the state before the signal, including RAX
+        LinuxSyscallResult::new_raw(rax)
+    }
+}
@@ -1,27 +1,41 @@
-use crate::process::Process;
+use crate::process::{
+    sig_valid,
+    Process,
+    SigAction,
+    SigNum,
+    SigSet,
+    SIGKILL,
+    SIGSTOP,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::MAPPED_AREAS;
 use alloc::rc::Rc;
+use core::mem::size_of;
 use libhrstd::libhedron::UtcbDataException;
 
 /// Implementation of <https://man7.org/linux/man-pages/man2/sigaction.2.html>.
+/// The actions are stored in the signal state of the process. See
+/// [`super::signal`] for the delivery of signals.
 #[derive(Debug)]
-#[allow(unused)]
 pub struct RtSigactionSyscall {
-    signum: u64,
-    new_action: *const Sigaction,
-    old_action: *const Sigaction,
+    signum: SigNum,
+    u_ptr_new_action: u64,
+    u_ptr_old_action: u64,
+    sigsetsize: u64,
 }
 
 impl From<&GenericLinuxSyscall> for RtSigactionSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
             signum: syscall.arg0(),
-            new_action: syscall.arg1() as *const _,
-            old_action: syscall.arg2() as *const _,
+            u_ptr_new_action: syscall.arg1(),
+            u_ptr_old_action: syscall.arg2(),
+            sigsetsize: syscall.arg3(),
         }
     }
 }
@@ -30,19 +44,43 @@ impl LinuxSyscallImpl for RtSigactionSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        // do nothing; it's okay for simple Linux programs
+        if self.sigsetsize != size_of::<SigSet>() as u64 || !sig_valid(self.signum) {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+
+        let old_action = process.signal_state().action(self.signum);
+
+        if self.u_ptr_new_action != 0 {
+            if self.signum == SIGKILL || self.signum == SIGSTOP {
+                return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+            }
+            let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_new_action,
+                size_of::<SigAction>() as u64,
+            );
+            let r_ptr = mapping
+                .mem_with_offset_as_ptr::<SigAction>((self.u_ptr_new_action & 0xfff) as usize);
+            let new_action = unsafe { core::ptr::read_unaligned(r_ptr) };
+            log::debug!("rt_sigaction: signal={}, {:x?}", self.signum, new_action);
+            process
+                .signal_state_mut()
+                .set_action(self.signum, new_action);
+        }
+
+        if self.u_ptr_old_action != 0 {
+            let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_old_action,
+                size_of::<SigAction>() as u64,
+            );
+            let r_ptr = mapping
+                .mem_with_offset_as_ptr_mut::<SigAction>((self.u_ptr_old_action & 0xfff) as usize);
+            unsafe { core::ptr::write_unaligned(r_ptr, old_action) };
+        }
 
         LinuxSyscallResult::new_success(0)
     }
 }
-
-#[repr(C)]
-struct Sigaction {
-    sa_handler: *const u8,
-    sig_mask: usize,
-    flags: u32,
-    sa_sigaction: *const u8,
-    sa_restorer: *const u8,
-}
@@ -1,19 +1,41 @@
-use crate::process::Process;
+use crate::process::{
+    Process,
+    SigSet,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::MAPPED_AREAS;
 use alloc::rc::Rc;
+use core::mem::size_of;
 use libhrstd::libhedron::UtcbDataException;
 
 /// Implementation of <https://man7.org/linux/man-pages/man2/sigprocmask.2.html>.
 #[derive(Debug)]
-pub struct RtSigProcMaskSyscall {}
+pub struct RtSigProcMaskSyscall {
+    how: u64,
+    u_ptr_set: u64,
+    u_ptr_old_set: u64,
+    sigsetsize: u64,
+}
+
+impl RtSigProcMaskSyscall {
+    const SIG_BLOCK: u64 = 0;
+    const SIG_UNBLOCK: u64 = 1;
+    const SIG_SETMASK: u64 = 2;
+}
 
 impl From<&GenericLinuxSyscall> for RtSigProcMaskSyscall {
-    fn from(_syscall: &GenericLinuxSyscall) -> Self {
-        Self {}
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            how: syscall.arg0(),
+            u_ptr_set: syscall.arg1(),
+            u_ptr_old_set: syscall.arg2(),
+            sigsetsize: syscall.arg3(),
+        }
     }
 }
 
@@ -21,9 +43,41 @@ impl LinuxSyscallImpl for RtSigProcMaskSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        // do nothing; it's okay for simple Linux programs
+        if self.sigsetsize != size_of::<SigSet>() as u64 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+
+        let old_blocked = process.signal_state().blocked();
+
+        if self.u_ptr_set != 0 {
+            let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_set,
+                size_of::<SigSet>() as u64,
+            );
+            let r_ptr = mapping.mem_with_offset_as_ptr::<SigSet>((self.u_ptr_set & 0xfff) as usize);
+            let set = unsafe { core::ptr::read_unaligned(r_ptr) };
+            let new_blocked = match self.how {
+                Self::SIG_BLOCK => old_blocked | set,
+                Self::SIG_UNBLOCK => old_blocked & !set,
+                Self::SIG_SETMASK => set,
+                _ => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+            };
+            process.signal_state_mut().set_blocked(new_blocked);
+        }
+
+        if self.u_ptr_old_set != 0 {
+            let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_old_set,
+                size_of::<SigSet>() as u64,
+            );
+            let r_ptr =
+                mapping.mem_with_offset_as_ptr_mut::<SigSet>((self.u_ptr_old_set & 0xfff) as usize);
+            unsafe { core::ptr::write_unaligned(r_ptr, old_blocked) };
+        }
 
         LinuxSyscallResult::new_success(0)
     }
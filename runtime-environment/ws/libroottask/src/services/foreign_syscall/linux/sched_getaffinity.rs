@@ -1,26 +1,38 @@
-use crate::process::Process;
+use crate::process::{
+    process_cpu,
+    Process,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::MAPPED_AREAS;
 use alloc::rc::Rc;
+use core::mem::size_of;
 use libhrstd::libhedron::UtcbDataException;
 use libhrstd::process::consts::ProcessId;
 
+/// Size of the CPU mask in bytes. Hedron supports up to 64 CPUs.
+pub(super) const CPU_MASK_SIZE: u64 = size_of::<u64>() as u64;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/sched_getaffinity.2.html>.
+/// A process runs on a single CPU for its whole lifetime, hence the mask has exactly
+/// one bit set. See [`crate::smp`].
 #[derive(Debug)]
 pub struct SchedGetAffinitySyscall {
-    _pid: ProcessId,
-    _len: usize,
-    user_mask_ptr: *mut u64,
+    pid: ProcessId,
+    len: u64,
+    u_mask_ptr: u64,
 }
 
 impl From<&GenericLinuxSyscall> for SchedGetAffinitySyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            _pid: syscall.arg0(),
-            _len: syscall.arg1() as usize,
-            user_mask_ptr: syscall.arg2() as *mut _,
+            pid: syscall.arg0(),
+            len: syscall.arg1(),
+            u_mask_ptr: syscall.arg2(),
         }
     }
 }
@@ -29,9 +41,35 @@ impl LinuxSyscallImpl for SchedGetAffinitySyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        unsafe { core::ptr::write(self.user_mask_ptr, !0) };
-        LinuxSyscallResult::new_success(0)
+        // the mask must cover all CPUs and consist of whole `long`s
+        if self.len < CPU_MASK_SIZE || self.len % size_of::<u64>() as u64 != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let cpu = match affinity_cpu(self.pid, process) {
+            Ok(cpu) => cpu,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+
+        let mut mapping =
+            MAPPED_AREAS
+                .lock()
+                .create_or_get_mapping(process, self.u_mask_ptr, CPU_MASK_SIZE);
+        let r_ptr = mapping.mem_with_offset_as_ptr_mut::<u64>((self.u_mask_ptr & 0xfff) as usize);
+        unsafe { core::ptr::write_unaligned(r_ptr, 1 << cpu) };
+
+        // the raw syscall returns the size of the mask; libc zeroes the rest of the buffer
+        LinuxSyscallResult::new_success(CPU_MASK_SIZE)
+    }
+}
+
+/// Returns the CPU of the process that the affinity syscalls refer to. A PID of 0 refers
+/// to the calling process.
+pub(super) fn affinity_cpu(pid: ProcessId, caller: &Process) -> Result<u64, LinuxErrorCode> {
+    if pid == 0 || pid == caller.pid() {
+        Ok(caller.cpu())
+    } else {
+        process_cpu(pid).ok_or(LinuxErrorCode::ESRCH)
     }
 }
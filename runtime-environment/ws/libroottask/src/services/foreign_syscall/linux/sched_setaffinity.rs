@@ -0,0 +1,71 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::sched_getaffinity::{
+    affinity_cpu,
+    CPU_MASK_SIZE,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use crate::smp;
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::process::consts::ProcessId;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/sched_setaffinity.2.html>.
+/// Hedron can't migrate the EC of a process to another CPU. Hence, the syscall only
+/// succeeds if the mask contains the current CPU of the process and the affinity stays
+/// this single CPU. Other masks fail with `EINVAL`, as if they contained no online CPU.
+#[derive(Debug)]
+pub struct SchedSetAffinitySyscall {
+    pid: ProcessId,
+    len: u64,
+    u_mask_ptr: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SchedSetAffinitySyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pid: syscall.arg0(),
+            len: syscall.arg1(),
+            u_mask_ptr: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SchedSetAffinitySyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let cpu = match affinity_cpu(self.pid, process) {
+            Ok(cpu) => cpu,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+
+        // bits beyond the supported CPUs are ignored; a shorter mask is zero-extended
+        let len = self.len.min(CPU_MASK_SIZE);
+        let mapping = MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, self.u_mask_ptr, len);
+        let r_ptr = mapping.mem_with_offset_as_ptr::<u8>((self.u_mask_ptr & 0xfff) as usize);
+        let mut mask_bytes = [0_u8; CPU_MASK_SIZE as usize];
+        unsafe { core::ptr::copy_nonoverlapping(r_ptr, mask_bytes.as_mut_ptr(), len as usize) };
+        let mask = u64::from_le_bytes(mask_bytes) & smp::online_cpus();
+
+        if mask & (1 << cpu) == 0 {
+            log::debug!(
+                "pid={} can't move to the CPUs {:#x}; it stays on CPU {}",
+                process.pid(),
+                mask,
+                cpu
+            );
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        LinuxSyscallResult::new_success(0)
+    }
+}
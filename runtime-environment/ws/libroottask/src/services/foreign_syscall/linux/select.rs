@@ -0,0 +1,157 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_FD_SETSIZE;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events::{
+    deadline_after_ns,
+    ready_events,
+    wait_for_events,
+    POLLERR,
+    POLLHUP,
+    POLLIN,
+    POLLOUT,
+    POLLPRI,
+};
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::restart;
+use crate::services::foreign_syscall::linux::settimeofday::timeval;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    write_to_user,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::time;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::mem::size_of;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Number of file descriptors per word of a `fd_set`.
+const FDS_PER_WORD: u64 = u64::BITS as u64;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/select.2.html>. Blocks like
+/// `poll`; a null timeout waits forever. Like on Linux, the remaining time gets written
+/// back into the timeout.
+#[derive(Debug)]
+pub struct SelectSyscall {
+    nfds: u64,
+    u_readfds: u64,
+    u_writefds: u64,
+    u_exceptfds: u64,
+    u_timeout: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SelectSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            nfds: syscall.arg0(),
+            u_readfds: syscall.arg1(),
+            u_writefds: syscall.arg2(),
+            u_exceptfds: syscall.arg3(),
+            u_timeout: syscall.arg4(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SelectSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let tsc_deadline = if self.u_timeout == 0 {
+            None
+        } else {
+            match read_from_user::<timeval>(process, self.u_timeout).as_nanos() {
+                Some(ns) => Some(deadline_after_ns(ns)),
+                None => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+            }
+        };
+        // the remaining time refers to the first attempt
+        let tsc_deadline = restart::deadline(process, tsc_deadline);
+        let res = select(
+            process,
+            self.nfds,
+            [self.u_readfds, self.u_writefds, self.u_exceptfds],
+            tsc_deadline,
+        );
+        if let Some(tsc_deadline) = tsc_deadline {
+            let remaining_ns = time::ticks_to_ns(tsc_deadline.saturating_sub(time::tsc_now()));
+            write_to_user(process, self.u_timeout, timeval::from_nanos(remaining_ns));
+        }
+        match res {
+            Ok(count) => LinuxSyscallResult::new_success(count as u64),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Waits for the three `fd_set`s of `select` and `pselect6`, i.e. the file descriptors that
+/// should become readable, writable, or have an exceptional condition. Null pointers stand
+/// for empty sets. Overwrites the sets with the ready file descriptors, unless the wait was
+/// interrupted, and returns their total count.
+pub(super) fn select(
+    process: &Rc<Process>,
+    nfds: u64,
+    u_fd_sets: [u64; 3],
+    tsc_deadline: Option<u64>,
+) -> Result<usize, LinuxErrorCode> {
+    if (nfds as i32) < 0 {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    // like Linux, ignore file descriptors above the limit
+    let nfds = min(nfds, LINUX_FD_SETSIZE);
+    let words = ((nfds + FDS_PER_WORD - 1) / FDS_PER_WORD) as usize;
+    let u_word_addr = |u_fd_set: u64, i: usize| u_fd_set + (i * size_of::<u64>()) as u64;
+    let fd_sets = u_fd_sets.map(|u_fd_set| {
+        (0..words)
+            .map(|i| match u_fd_set {
+                0 => 0,
+                u_fd_set => read_from_user::<u64>(process, u_word_addr(u_fd_set, i)),
+            })
+            .collect::<Vec<_>>()
+    });
+    let is_member = |fd_set: &[u64], fd: u64| {
+        fd_set[(fd / FDS_PER_WORD) as usize] >> (fd % FDS_PER_WORD) & 1 != 0
+    };
+    let fds = (0..nfds)
+        .filter(|fd| fd_sets.iter().any(|fd_set| is_member(fd_set, *fd)))
+        .collect::<Vec<_>>();
+    if fds
+        .iter()
+        .any(|fd| ready_events(process, FileDescriptor::new(*fd)).is_none())
+    {
+        return Err(LinuxErrorCode::EBADF);
+    }
+
+    // events that make a file descriptor a member of the resulting sets, like on Linux
+    let set_events = [POLLIN | POLLHUP | POLLERR, POLLOUT | POLLERR, POLLPRI];
+    let mut ready_sets = [(); 3].map(|_| vec![0_u64; words]);
+    let count = wait_for_events(process, tsc_deadline, || {
+        ready_sets.iter_mut().for_each(|set| set.fill(0));
+        let mut count = 0;
+        for fd in fds.iter().copied() {
+            let ready = ready_events(process, FileDescriptor::new(fd)).unwrap_or(POLLERR);
+            for i in 0..3 {
+                if is_member(&fd_sets[i], fd) && ready & set_events[i] != 0 {
+                    ready_sets[i][(fd / FDS_PER_WORD) as usize] |= 1 << (fd % FDS_PER_WORD);
+                    count += 1;
+                }
+            }
+        }
+        count
+    })?;
+
+    for (u_fd_set, ready_set) in u_fd_sets.into_iter().zip(ready_sets) {
+        if u_fd_set != 0 {
+            for (i, word) in ready_set.into_iter().enumerate() {
+                write_to_user(process, u_word_addr(u_fd_set, i), word);
+            }
+        }
+    }
+    Ok(count)
+}
@@ -0,0 +1,82 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_send,
+    is_inet_socket,
+    read_sockaddr_in,
+};
+use crate::services::foreign_syscall::linux::unix_socket::{
+    copy_from_user,
+    read_from_user,
+    read_iovecs,
+    read_sockaddr_un,
+    socket_send,
+    MsgHdr,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/sendmsg.2.html>.
+/// The flags have no effect. Ancillary data is not supported.
+#[derive(Debug)]
+pub struct SendMsgSyscall {
+    fd: FileDescriptor,
+    u_msg: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SendMsgSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_msg: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SendMsgSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let msg = read_from_user::<MsgHdr>(process, self.u_msg);
+        if msg.msg_controllen != 0 {
+            log::debug!("sendmsg: ancillary data not supported");
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EOPNOTSUPP);
+        }
+
+        // the message must be sent at once; otherwise datagrams get split
+        let data = read_iovecs(process, msg.msg_iov, msg.msg_iovlen)
+            .into_iter()
+            .flat_map(|iov| copy_from_user(process, iov.u_iov_base as u64, iov.len as usize))
+            .collect::<Vec<u8>>();
+
+        if is_inet_socket(process, self.fd) {
+            let dest = if msg.msg_name == 0 {
+                None
+            } else {
+                match read_sockaddr_in(process, msg.msg_name, msg.msg_namelen as u64) {
+                    Ok(addr) => Some(addr),
+                    Err(err) => return LinuxSyscallResult::new_error(err),
+                }
+            };
+            return inet_send(process, self.fd, &data, dest);
+        }
+        let dest = if msg.msg_name == 0 {
+            None
+        } else {
+            match read_sockaddr_un(process, msg.msg_name, msg.msg_namelen as u64) {
+                Ok(name) => Some(name),
+                Err(err) => return LinuxSyscallResult::new_error(err),
+            }
+        };
+        socket_send(process, self.fd, &data, dest.as_deref())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_send,
+    is_inet_socket,
+    read_sockaddr_in,
+};
+use crate::services::foreign_syscall::linux::unix_socket::{
+    copy_from_user,
+    read_sockaddr_un,
+    socket_send,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/sendto.2.html>.
+/// The flags have no effect.
+#[derive(Debug)]
+pub struct SendToSyscall {
+    fd: FileDescriptor,
+    u_buf: u64,
+    len: usize,
+    u_dest_addr: u64,
+    addr_len: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SendToSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_buf: syscall.arg1(),
+            len: syscall.arg2() as usize,
+            u_dest_addr: syscall.arg4(),
+            addr_len: syscall.arg5(),
+        }
+    }
+}
+
+impl SendToSyscall {
+    /// Send without a destination address. Used by `write` on sockets.
+    pub(super) fn new(fd: FileDescriptor, u_buf: u64, len: usize) -> Self {
+        Self {
+            fd,
+            u_buf,
+            len,
+            u_dest_addr: 0,
+            addr_len: 0,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SendToSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if is_inet_socket(process, self.fd) {
+            let dest = if self.u_dest_addr == 0 {
+                None
+            } else {
+                match read_sockaddr_in(process, self.u_dest_addr, self.addr_len) {
+                    Ok(addr) => Some(addr),
+                    Err(err) => return LinuxSyscallResult::new_error(err),
+                }
+            };
+            let data = copy_from_user(process, self.u_buf, self.len);
+            return inet_send(process, self.fd, &data, dest);
+        }
+        let dest = if self.u_dest_addr == 0 {
+            None
+        } else {
+            match read_sockaddr_un(process, self.u_dest_addr, self.addr_len) {
+                Ok(name) => Some(name),
+                Err(err) => return LinuxSyscallResult::new_error(err),
+            }
+        };
+        let data = copy_from_user(process, self.u_buf, self.len);
+        socket_send(process, self.fd, &data, dest.as_deref())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Size of `struct robust_list_head` on x86_64.
+const ROBUST_LIST_HEAD_SIZE: u64 = 24;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/set_robust_list.2.html>.
+/// glibc registers the list of robust futexes of each thread during startup. Linux wakes
+/// the waiters of these futexes when the thread dies. A process has a single thread and
+/// no other process shares its futexes, hence the list isn't needed and the syscall only
+/// validates the size.
+#[derive(Debug)]
+pub struct SetRobustListSyscall {
+    _head: *const u8,
+    len: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SetRobustListSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            _head: syscall.arg0() as *const _,
+            len: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SetRobustListSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        _process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.len != ROBUST_LIST_HEAD_SIZE {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        LinuxSyscallResult::new_success(0)
+    }
+}
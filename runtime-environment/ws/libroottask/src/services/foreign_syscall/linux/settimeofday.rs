@@ -0,0 +1,99 @@
+use crate::process::{
+    is_privileged,
+    Process,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use crate::time;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::rt::services::system_time::TimeAdjustment;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/settimeofday.2.html>.
+/// The wall clock jumps to the new time. The time zone is ignored, like on Linux. Only
+/// privileged processes may set the time, see [`is_privileged`].
+#[derive(Debug)]
+pub struct SetTimeOfDaySyscall {
+    u_ptr_tv: u64,
+    _u_ptr_tz: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SetTimeOfDaySyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_ptr_tv: syscall.arg0(),
+            _u_ptr_tz: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SetTimeOfDaySyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if !is_privileged(process.pid()) {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EPERM);
+        }
+        // only the time zone would be set; it is ignored anyway
+        if self.u_ptr_tv == 0 {
+            return LinuxSyscallResult::new_success(0);
+        }
+
+        let u_page_offset = self.u_ptr_tv & 0xfff;
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.u_ptr_tv,
+            size_of::<timeval>() as u64,
+        );
+        let tv = *mapping.mem_with_offset_as::<timeval>(u_page_offset as usize);
+        let realtime_ns = match tv.as_nanos() {
+            Some(ns) => ns,
+            None => return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL),
+        };
+
+        time::set_realtime_ns(process.pid(), realtime_ns, TimeAdjustment::Step);
+        LinuxSyscallResult::new_success(0)
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct timeval {
+    /// seconds
+    tv_sec: u64,
+    /// microseconds
+    tv_usec: u64,
+}
+
+impl timeval {
+    /// Returns the total amount of nanoseconds or `None`, if `tv_sec` is negative or
+    /// `tv_usec` is not in range `0..1_000_000`. Saturates at `u64::MAX`.
+    pub(super) fn as_nanos(&self) -> Option<u64> {
+        if (self.tv_sec as i64) < 0 || self.tv_usec >= 1_000_000 {
+            None
+        } else {
+            Some(
+                self.tv_sec
+                    .saturating_mul(1_000_000_000)
+                    .saturating_add(self.tv_usec * 1000),
+            )
+        }
+    }
+
+    /// Converts nanoseconds; truncates them to microseconds.
+    pub(super) const fn from_nanos(ns: u64) -> Self {
+        Self {
+            tv_sec: ns / 1_000_000_000,
+            tv_usec: ns % 1_000_000_000 / 1000,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! File descriptors that refer to segments of the shared memory service, see
+//! [`crate::services::shm`]. `memfd_create` creates an anonymous segment and `open` of a
+//! path in [`SHM_DIR`], which is what `shm_open` of libc does, a named one. `ftruncate`
+//! sets the size and `mmap` with `MAP_SHARED` maps the segment. The file server reserves
+//! the file descriptor, similar to eventfds. `read` and `write` aren't supported.
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::shm;
+use alloc::collections::BTreeMap;
+use libfileserver::{
+    FileDescriptor,
+    FileStat,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::shm::{
+    ShmAccess,
+    ShmId,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Directory of the named segments. It only exists for `open` and `unlink`.
+const SHM_DIR: &str = "/dev/shm/";
+
+/// All file descriptors of segments of all processes.
+static SHM_FDS: SimpleMutex<BTreeMap<(ProcessId, FileDescriptor), ShmFd>> =
+    SimpleMutex::new(BTreeMap::new());
+
+#[derive(Debug, Copy, Clone)]
+pub(super) struct ShmFd {
+    pub id: ShmId,
+    /// Whether the file descriptor was opened for writing, i.e. `mmap` can map it
+    /// writable.
+    pub writable: bool,
+}
+
+/// Returns the name of the segment if the absolute `path` lies in [`SHM_DIR`].
+pub(super) fn shm_name(path: &str) -> Option<&str> {
+    path.strip_prefix(SHM_DIR).filter(|name| !name.is_empty())
+}
+
+/// Opens the segment with the name and returns a new file descriptor for it. Creates the
+/// segment with [`FsOpenFlags::O_CREAT`] and the permissions `umode` without the umask of
+/// the process, and empties it with [`FsOpenFlags::O_TRUNC`].
+pub(super) fn open(
+    process: &Process,
+    name: &str,
+    flags: FsOpenFlags,
+    umode: u16,
+) -> Result<FileDescriptor, LinuxErrorCode> {
+    let pid = process.pid();
+    let access = if flags.can_write() {
+        ShmAccess::ReadWrite
+    } else {
+        ShmAccess::ReadOnly
+    };
+    let info = if flags.can_create() {
+        let mode = umode & !libfileserver::FILESYSTEM.lock().umask(pid);
+        let exclusive = flags.contains(FsOpenFlags::O_EXCL);
+        shm::create(pid, Some(name), 0, exclusive, mode, access)
+    } else {
+        shm::open(pid, name, access)
+    }?;
+    if flags.contains(FsOpenFlags::O_TRUNC) && flags.can_write() {
+        if let Err(err) = shm::resize(pid, info.id, 0) {
+            shm::close(pid, info.id).unwrap();
+            return Err(err.into());
+        }
+    }
+    Ok(insert(process, info.id, flags.can_write()))
+}
+
+/// Creates an empty anonymous segment and returns a writable file descriptor for it.
+pub(super) fn create_anonymous(process: &Process) -> Result<FileDescriptor, LinuxErrorCode> {
+    let info = shm::create(process.pid(), None, 0, false, 0o600, ShmAccess::ReadWrite)?;
+    Ok(insert(process, info.id, true))
+}
+
+fn insert(process: &Process, id: ShmId, writable: bool) -> FileDescriptor {
+    let fd = libfileserver::FILESYSTEM.lock().reserve_fd(process.pid());
+    SHM_FDS
+        .lock()
+        .insert((process.pid(), fd), ShmFd { id, writable });
+    fd
+}
+
+/// Returns the segment of the file descriptor or `None` if it refers to none.
+pub(super) fn get(process: &Process, fd: FileDescriptor) -> Option<ShmFd> {
+    SHM_FDS.lock().get(&(process.pid(), fd)).copied()
+}
+
+/// Returns the metadata of the segment, like for a regular file.
+pub(super) fn stat(process: &Process, shm_fd: ShmFd) -> Result<FileStat, LinuxErrorCode> {
+    let size = shm::size(process.pid(), shm_fd.id)?;
+    let mode = shm::mode(process.pid(), shm_fd.id)?;
+    Ok(FileStat::new(shm_fd.id.val(), u32::from(mode), size as i64))
+}
+
+/// Closes the segment of the file descriptor, if it refers to one.
+pub(super) fn close(process: &Process, fd: FileDescriptor) {
+    if let Some(shm_fd) = SHM_FDS.lock().remove(&(process.pid(), fd)) {
+        shm::close(process.pid(), shm_fd.id).unwrap();
+    }
+}
+
+/// Forgets the file descriptors of segments of a process that exited. The shared memory
+/// service closes the segments themselves, see [`shm::release_process`].
+pub(super) fn release_process(pid: ProcessId) {
+    SHM_FDS.lock().retain(|(fd_pid, _), _| *fd_pid != pid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shm_name() {
+        assert_eq!(shm_name("/dev/shm/frames"), Some("frames"));
+        assert_eq!(shm_name("/dev/shm/"), None);
+        assert_eq!(shm_name("/dev/null"), None);
+    }
+}
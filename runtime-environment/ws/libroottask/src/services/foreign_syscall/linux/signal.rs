@@ -0,0 +1,447 @@
+//! Delivery of signals to Linux processes. The roottask builds the same signal frame
+//! on the user stack as Linux does (`struct rt_sigframe` on x86_64) and redirects the
+//! process to the registered handler. When the handler returns, it lands in the
+//! `sa_restorer` of libc, which performs the `rt_sigreturn` syscall. See
+//! [`super::rt_sigreturn`].
+//!
+//! Hedron can't interrupt a running process on behalf of the roottask. Therefore, pending
+//! signals are delivered when the process enters the roottask the next time, i.e. on the
+//! next syscall or exception. Synchronous signals caused by exceptions are delivered
+//! immediately. Signals whose default action terminates the process do so, see
+//! [`kill_process`], and leave a core dump if the action is to dump core, see
+//! [`write_core_dump`]. Signals whose default action stops the process stop it, see
+//! [`stop_process`].
+//!
+//! The FPU state is not part of the frame, because the exception portals don't transfer it.
+
+use crate::gdb_stub;
+use crate::process::{
+    exception_signal,
+    kill_process,
+    sig_bit,
+    stop_process,
+    write_core_dump,
+    Process,
+    SigDefaultAction,
+    SigNum,
+    SyscallAbi,
+    SA_NODEFER,
+    SA_RESETHAND,
+    SA_SIGINFO,
+    SIGSEGV,
+    SIG_DFL,
+    SIG_IGN,
+};
+use crate::roottask_exception;
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use core::convert::TryFrom;
+use core::mem::size_of;
+use libhrstd::kobjects::PtObject;
+use libhrstd::libhedron::ExceptionEventOffset;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::libhedron::UtcbDataException;
+
+/// `si_code` for signals sent by the kernel.
+const SI_KERNEL: i32 = 0x80;
+
+/// Size of the red zone below the stack pointer of the System V ABI that must not be touched.
+const RED_ZONE_SIZE: u64 = 128;
+
+/// Flags of RFLAGS that a process may change via `rt_sigreturn`.
+/// Same as `FIX_EFLAGS` in Linux.
+const RFLAGS_USER_MASK: u64 = 0x50dd5;
+
+/// Same as `struct sigcontext` of Linux on x86_64.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub(super) struct SigContext {
+    r8: u64,
+    r9: u64,
+    r10: u64,
+    r11: u64,
+    r12: u64,
+    r13: u64,
+    r14: u64,
+    r15: u64,
+    rdi: u64,
+    rsi: u64,
+    rbp: u64,
+    rbx: u64,
+    rdx: u64,
+    rax: u64,
+    rcx: u64,
+    rsp: u64,
+    rip: u64,
+    rflags: u64,
+    cs: u16,
+    gs: u16,
+    fs: u16,
+    ss: u16,
+    err: u64,
+    trapno: u64,
+    oldmask: u64,
+    cr2: u64,
+    fpstate: u64,
+    reserved: [u64; 8],
+}
+
+/// Same as `stack_t` of Linux on x86_64.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+struct SigStack {
+    ss_sp: u64,
+    ss_flags: u32,
+    ss_size: u64,
+}
+
+/// Same as `struct ucontext` of Linux on x86_64.
+#[derive(Debug, Copy, Clone, Default)]
+#[repr(C)]
+pub(super) struct UContext {
+    uc_flags: u64,
+    uc_link: u64,
+    uc_stack: SigStack,
+    uc_mcontext: SigContext,
+    uc_sigmask: u64,
+}
+
+/// Same as `siginfo_t` of Linux. Only the fields used by the roottask are typed.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SigInfo {
+    si_signo: i32,
+    si_errno: i32,
+    si_code: i32,
+    _pad: i32,
+    /// Faulting address for SIGSEGV, SIGBUS, SIGILL, SIGFPE, and SIGTRAP.
+    si_addr: u64,
+    _rest: [u64; 13],
+}
+
+/// Same as `struct rt_sigframe` of Linux on x86_64. The stack pointer points to it
+/// when the handler gets called.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct RtSigFrame {
+    /// Return address of the handler.
+    pretcode: u64,
+    uc: UContext,
+    info: SigInfo,
+}
+
+/// Offset of [`RtSigFrame::uc`].
+const FRAME_UC_OFFSET: u64 = size_of::<u64>() as u64;
+
+/// Offset of [`RtSigFrame::info`].
+const FRAME_INFO_OFFSET: u64 = FRAME_UC_OFFSET + size_of::<UContext>() as u64;
+
+/// Additional information about a signal caused by an exception.
+#[derive(Debug, Copy, Clone, Default)]
+struct FaultInfo {
+    trapno: u64,
+    err: u64,
+    addr: u64,
+}
+
+/// Delivers the next pending and not blocked signal of the process, if there is one.
+/// The UTCB must hold the state of the process that it has when the roottask replies.
+/// Signals with [`SIG_IGN`] and signals whose default action is to be ignored are
+/// discarded.
+pub fn deliver_pending_signal(utcb_exc: &mut UtcbDataException, process: &Rc<Process>) {
+    while let Some(sig) = process.take_pending_signal() {
+        let action = process.signal_state().action(sig);
+        match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL => {
+                if !apply_default_action(utcb_exc, process, sig) {
+                    return;
+                }
+            }
+            _ => {
+                deliver_signal(utcb_exc, process, sig, None);
+                return;
+            }
+        }
+    }
+}
+
+/// Applies the default action for a signal. Returns false if the signal terminated the
+/// process. `utcb_exc` holds the registers for a core dump.
+fn apply_default_action(utcb_exc: &UtcbDataException, process: &Process, sig: SigNum) -> bool {
+    match SigDefaultAction::of(sig) {
+        SigDefaultAction::Ignore => true,
+        // the process runs again after SIGCONT; the remaining signals wait until then
+        SigDefaultAction::Stop => {
+            if !stop_process(process.pid()) {
+                log::warn!("can't stop pid={} for signal {}", process.pid(), sig);
+            }
+            true
+        }
+        // the sender already continued the process, see `post_signal`
+        SigDefaultAction::Continue => true,
+        SigDefaultAction::Terminate => {
+            kill_process(process.pid(), sig);
+            false
+        }
+        SigDefaultAction::Core => {
+            write_core_dump(process, utcb_exc, sig);
+            kill_process(process.pid(), sig);
+            false
+        }
+    }
+}
+
+/// Writes the signal frame onto the stack of the process and prepares the UTCB, so that
+/// the process continues in the handler of the signal. Updates the blocked signals
+/// according to the action.
+fn deliver_signal(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    sig: SigNum,
+    fault: Option<FaultInfo>,
+) {
+    let action = process.signal_state().action(sig);
+    let old_blocked = process.signal_state().blocked();
+    log::debug!(
+        "deliver signal {} to pid={}, handler={:#x}",
+        sig,
+        process.pid(),
+        action.handler
+    );
+
+    let fault_info = fault.unwrap_or_default();
+    let frame = RtSigFrame {
+        pretcode: action.restorer,
+        uc: UContext {
+            uc_mcontext: SigContext {
+                err: fault_info.err,
+                trapno: fault_info.trapno,
+                oldmask: old_blocked,
+                cr2: fault_info.addr,
+                ..SigContext::from_utcb(utcb_exc)
+            },
+            uc_sigmask: old_blocked,
+            ..UContext::default()
+        },
+        info: SigInfo {
+            si_signo: sig as i32,
+            si_errno: 0,
+            si_code: SI_KERNEL,
+            _pad: 0,
+            si_addr: fault_info.addr,
+            _rest: [0; 13],
+        },
+    };
+
+    let u_frame_addr = match signal_frame_addr(utcb_exc.rsp) {
+        Some(addr) => addr,
+        None => {
+            // like `force_sigsegv()` of Linux
+            log::debug!(
+                "signal frame of pid={} doesn't fit below rsp={:#x}",
+                process.pid(),
+                utcb_exc.rsp
+            );
+            write_core_dump(process, utcb_exc, SIGSEGV);
+            kill_process(process.pid(), SIGSEGV);
+            return;
+        }
+    };
+    let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+        process,
+        u_frame_addr,
+        size_of::<RtSigFrame>() as u64,
+    );
+    let r_frame_ptr =
+        mapping.mem_with_offset_as_ptr_mut::<RtSigFrame>((u_frame_addr & 0xfff) as usize);
+    unsafe {
+        core::ptr::write_unaligned(r_frame_ptr, frame);
+    }
+
+    let mut signal_state = process.signal_state_mut();
+    let mut blocked = old_blocked | action.mask;
+    if action.flags & SA_NODEFER == 0 {
+        blocked |= sig_bit(sig);
+    }
+    signal_state.set_blocked(blocked);
+    if action.flags & SA_RESETHAND != 0 {
+        signal_state.set_action(sig, Default::default());
+    }
+
+    utcb_exc.rip = action.handler;
+    utcb_exc.rsp = u_frame_addr;
+    utcb_exc.rdi = sig;
+    // the arguments are harmless for handlers without SA_SIGINFO
+    if action.flags & SA_SIGINFO != 0 || fault.is_some() {
+        utcb_exc.rsi = u_frame_addr + FRAME_INFO_OFFSET;
+        utcb_exc.rdx = u_frame_addr + FRAME_UC_OFFSET;
+    }
+    // required by the ABI for variadic functions
+    utcb_exc.rax = 0;
+    utcb_exc.mtd |= Mtd::GPR_ACDB | Mtd::GPR_BSD | Mtd::RSP | Mtd::RIP_LEN;
+}
+
+/// Returns the address of the signal frame below the red zone of the stack pointer `rsp`,
+/// or `None` if the frame doesn't fit into the address space.
+fn signal_frame_addr(rsp: u64) -> Option<u64> {
+    let addr = rsp
+        .checked_sub(RED_ZONE_SIZE)?
+        .checked_sub(size_of::<RtSigFrame>() as u64)?;
+    // Same alignment as Linux: `(rsp + 8) % 16 == 0` at the entry of the handler,
+    // as if the handler was called by a `call` instruction.
+    (addr & !0xf).checked_sub(8)
+}
+
+/// Reads the [`UContext`] of the signal frame at `u_addr` in the address space of the
+/// process, and restores the state of the process from it. This is the core of
+/// `rt_sigreturn`. Returns the value for RAX.
+pub(super) fn restore_signal_frame(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    u_addr: u64,
+) -> u64 {
+    let mapping =
+        MAPPED_AREAS
+            .lock()
+            .create_or_get_mapping(process, u_addr, size_of::<UContext>() as u64);
+    let r_uc_ptr = mapping.mem_with_offset_as_ptr::<UContext>((u_addr & 0xfff) as usize);
+    let uc = unsafe { core::ptr::read_unaligned(r_uc_ptr) };
+
+    process.signal_state_mut().set_blocked(uc.uc_sigmask);
+    uc.uc_mcontext.restore_to_utcb(utcb_exc);
+    uc.uc_mcontext.rax
+}
+
+impl SigContext {
+    /// Captures the general purpose registers from the UTCB.
+    pub(super) fn from_utcb(utcb_exc: &UtcbDataException) -> Self {
+        Self {
+            r8: utcb_exc.r8,
+            r9: utcb_exc.r9,
+            r10: utcb_exc.r10,
+            r11: utcb_exc.r11,
+            r12: utcb_exc.r12,
+            r13: utcb_exc.r13,
+            r14: utcb_exc.r14,
+            r15: utcb_exc.r15,
+            rdi: utcb_exc.rdi,
+            rsi: utcb_exc.rsi,
+            rbp: utcb_exc.rbp,
+            rbx: utcb_exc.rbx,
+            rdx: utcb_exc.rdx,
+            rax: utcb_exc.rax,
+            rcx: utcb_exc.rcx,
+            rsp: utcb_exc.rsp,
+            rip: utcb_exc.rip,
+            rflags: utcb_exc.rflags,
+            ..Self::default()
+        }
+    }
+
+    /// Writes the general purpose registers back to the UTCB and sets the MTD accordingly.
+    /// The process can only change the flags in [`RFLAGS_USER_MASK`].
+    pub(super) fn restore_to_utcb(&self, utcb_exc: &mut UtcbDataException) {
+        utcb_exc.r8 = self.r8;
+        utcb_exc.r9 = self.r9;
+        utcb_exc.r10 = self.r10;
+        utcb_exc.r11 = self.r11;
+        utcb_exc.r12 = self.r12;
+        utcb_exc.r13 = self.r13;
+        utcb_exc.r14 = self.r14;
+        utcb_exc.r15 = self.r15;
+        utcb_exc.rdi = self.rdi;
+        utcb_exc.rsi = self.rsi;
+        utcb_exc.rbp = self.rbp;
+        utcb_exc.rbx = self.rbx;
+        utcb_exc.rdx = self.rdx;
+        utcb_exc.rax = self.rax;
+        utcb_exc.rcx = self.rcx;
+        utcb_exc.rsp = self.rsp;
+        utcb_exc.rip = self.rip;
+        utcb_exc.rflags = (utcb_exc.rflags & !RFLAGS_USER_MASK) | (self.rflags & RFLAGS_USER_MASK);
+        utcb_exc.mtd |=
+            Mtd::GPR_ACDB | Mtd::GPR_BSD | Mtd::GPR_R8_R15 | Mtd::RSP | Mtd::RIP_LEN | Mtd::RFLAGS;
+    }
+}
+
+/// Registers the exception handlers that translate exceptions of Linux processes to
+/// signals. See [`signal_exc_handler`].
+pub fn register_signal_exc_handlers() {
+    for exc in [
+        ExceptionEventOffset::DivideByZeroFault,
+        ExceptionEventOffset::DebugTrap,
+        ExceptionEventOffset::BreakpointTrap,
+        ExceptionEventOffset::OverflowTrap,
+        ExceptionEventOffset::BoundRangeExceededFault,
+        ExceptionEventOffset::InvalidOpcodeFault,
+        ExceptionEventOffset::StackSegmentFault,
+        ExceptionEventOffset::GeneralProtectionFault,
+        ExceptionEventOffset::PageFault,
+        ExceptionEventOffset::X87FloatingPointFault,
+        ExceptionEventOffset::AlignmentCheckFault,
+        ExceptionEventOffset::SimdFloatingPointFault,
+    ] {
+        roottask_exception::register_specialized_exc_handler(exc, signal_exc_handler);
+    }
+}
+
+/// Delivers the signal that belongs to the exception to the process, if the process is a
+/// Linux process and handles the signal. A debugged process stops for GDB first, see
+/// [`gdb_stub::handle_exception`]. Otherwise, the exception is unresolved, see
+/// [`roottask_exception::handle_unresolved_fault`].
+fn signal_exc_handler(
+    pt: &Rc<PtObject>,
+    process: &Rc<Process>,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let exc = ExceptionEventOffset::try_from(pt.ctx().exc()).unwrap();
+    let sig = exception_signal(exc);
+
+    if gdb_stub::handle_exception(pt, exc, process, utcb.exception_data_mut(), sig) {
+        *do_reply = true;
+        return;
+    }
+
+    let handler = process.signal_state().action(sig).handler;
+    let blocked = process.signal_state().blocked() & sig_bit(sig) != 0;
+    if process.syscall_abi() != SyscallAbi::Linux
+        || blocked
+        || handler == SIG_DFL
+        || handler == SIG_IGN
+    {
+        roottask_exception::handle_unresolved_fault(exc, process, utcb, do_reply);
+        return;
+    }
+
+    let utcb_exc = utcb.exception_data_mut();
+    let fault = FaultInfo {
+        trapno: exc.val(),
+        err: utcb_exc.qual[0],
+        // Hedron reports the faulting address of page faults in the second qualification
+        addr: if exc == ExceptionEventOffset::PageFault {
+            utcb_exc.qual[1]
+        } else {
+            utcb_exc.rip
+        },
+    };
+    utcb_exc.mtd = Mtd::empty();
+    deliver_signal(utcb_exc, process, sig, Some(fault));
+    *do_reply = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_frame_addr() {
+        let addr = signal_frame_addr(0x7fff_f000).unwrap();
+        assert_eq!((addr + 8) % 16, 0);
+        assert!(addr + size_of::<RtSigFrame>() as u64 <= 0x7fff_f000 - RED_ZONE_SIZE);
+        assert_eq!(signal_frame_addr(0), None);
+        assert_eq!(signal_frame_addr(RED_ZONE_SIZE), None);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::{
+    inet_socket,
+    AF_INET,
+};
+use crate::services::foreign_syscall::linux::unix_socket::{
+    set_nonblocking,
+    socket_kind,
+    SOCK_NONBLOCK,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/socket.2.html>.
+/// Only supports `AF_UNIX`, and UDP and TCP sockets of `AF_INET`. See [`super::unix_socket`] and
+/// [`super::inet_socket`].
+#[derive(Debug)]
+pub struct SocketSyscall {
+    domain: u64,
+    sock_type: u64,
+    protocol: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SocketSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            domain: syscall.arg0(),
+            sock_type: syscall.arg1(),
+            protocol: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SocketSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.domain == AF_INET {
+            return inet_socket(process, self.sock_type, self.protocol);
+        }
+        let kind = match socket_kind(self.domain, self.sock_type, self.protocol) {
+            Ok(kind) => kind,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        let res = libfileserver::FILESYSTEM.lock().socket(process.pid(), kind);
+        match res {
+            Ok(fd) => {
+                set_nonblocking(process, fd, self.sock_type & SOCK_NONBLOCK != 0);
+                LinuxSyscallResult::new_success(fd.val())
+            }
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    set_nonblocking,
+    socket_kind,
+    write_to_user,
+    SOCK_NONBLOCK,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/socketpair.2.html>.
+#[derive(Debug)]
+pub struct SocketPairSyscall {
+    domain: u64,
+    sock_type: u64,
+    protocol: u64,
+    /// User pointer to `int sv[2]`.
+    u_sv: u64,
+}
+
+impl From<&GenericLinuxSyscall> for SocketPairSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            domain: syscall.arg0(),
+            sock_type: syscall.arg1(),
+            protocol: syscall.arg2(),
+            u_sv: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SocketPairSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let kind = match socket_kind(self.domain, self.sock_type, self.protocol) {
+            Ok(kind) => kind,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+        match libfileserver::FILESYSTEM
+            .lock()
+            .socketpair(process.pid(), kind)
+        {
+            Ok((a, b)) => {
+                let nonblocking = self.sock_type & SOCK_NONBLOCK != 0;
+                set_nonblocking(process, a, nonblocking);
+                set_nonblocking(process, b, nonblocking);
+                write_to_user(process, self.u_sv, [a.val() as i32, b.val() as i32]);
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err.into()),
+        }
+    }
+}
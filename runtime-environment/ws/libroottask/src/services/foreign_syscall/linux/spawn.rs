@@ -0,0 +1,202 @@
+//! `vfork()` and `execve()` of Linux processes, which together start programs like
+//! `posix_spawn()`. The roottask can't duplicate the address space of a process. Hence,
+//! it only supports the pattern of `vfork()` where the child calls `execve()` or `_exit()`
+//! right away, as shells and `posix_spawn()` of glibc do.
+//!
+//! Like on Linux, the child runs in the memory of the parent and the parent waits until
+//! the child calls `execve()` or exits. The roottask doesn't create a child at all: the
+//! thread of the parent plays the child. `vfork()` saves the registers of the parent,
+//! reserves the PID of the child, and returns 0. When the child calls `execve()`, the
+//! roottask starts the program directly from the ELF file as a new process with the
+//! reserved PID, see [`spawn_with_pid`]. Then it restores the registers of the parent, so
+//! that `vfork()` returns the PID of the child in the parent.
+//!
+//! The open files are copied to the PID of the child in `vfork()`. While the child runs,
+//! its file operations change the files of the PID of the parent. `execve()` exchanges the
+//! files of both PIDs, so that the new process gets the files as the child left them and
+//! the parent gets back its own files. This has some limitations:
+//! - Sockets and reserved file descriptors stay with the parent. If the child closes one,
+//!   the parent loses it.
+//! - Each copy of a file has its own file offset.
+//! - `O_CLOEXEC` has no effect.
+//!
+//! Also, `getpid()` returns the PID of the parent in the child. Like launches of the process
+//! service, only privileged processes can use `vfork()`, see [`is_privileged`].
+
+use crate::process::{
+    allocate_pid,
+    exit_unstarted_process,
+    free_pid,
+    is_privileged,
+    Process,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::signal::SigContext;
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use crate::services::process::spawn_with_pid;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::libhedron::UtcbDataException;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::process::ProcessServiceError;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Processes that currently play the child of a `vfork()`, by the PID of the parent.
+static VFORKS: SimpleMutex<BTreeMap<ProcessId, Vfork>> = SimpleMutex::new(BTreeMap::new());
+
+#[derive(Debug)]
+struct Vfork {
+    /// The reserved PID of the child.
+    child: ProcessId,
+    /// The registers of the parent when it called `vfork()`.
+    parent_regs: SigContext,
+}
+
+/// Lets the process continue as the child of a `vfork()`. If `child_stack` is not null,
+/// the child runs on that stack, like after `clone()`. Fails with `EAGAIN` if the process
+/// isn't privileged, already plays a child, or if all PIDs are in use.
+pub(super) fn vfork(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    child_stack: u64,
+) -> LinuxSyscallResult {
+    let parent = process.pid();
+    if !is_privileged(parent) {
+        log::debug!("pid={} isn't allowed to vfork", parent);
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EAGAIN);
+    }
+    let mut vforks = VFORKS.lock();
+    if vforks.contains_key(&parent) {
+        log::debug!("pid={} already plays a vfork child", parent);
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EAGAIN);
+    }
+    let child = match allocate_pid() {
+        Some(child) => child,
+        None => return LinuxSyscallResult::new_error(LinuxErrorCode::EAGAIN),
+    };
+
+    {
+        let mut fs = libfileserver::FILESYSTEM.lock();
+        for fd in fs.open_files(parent) {
+            if let Err(err) = fs.inherit_file(parent, fd, child, fd) {
+                log::warn!(
+                    "pid={} can't pass fd={} to pid={}: {:?}",
+                    parent,
+                    fd.val(),
+                    child,
+                    err
+                );
+            }
+        }
+    }
+    vforks.insert(
+        parent,
+        Vfork {
+            child,
+            parent_regs: SigContext::from_utcb(utcb_exc),
+        },
+    );
+    if child_stack != 0 {
+        utcb_exc.rsp = child_stack;
+    }
+    log::debug!("pid={} vforks pid={}", parent, child);
+    LinuxSyscallResult::new_success(0)
+}
+
+/// Starts the program at `path` as the child of the `vfork()` and lets the parent continue.
+/// Fails with `ENOSYS` if the process doesn't play a child, because the roottask can't
+/// replace the program of a running process.
+pub(super) fn execve(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    path: String,
+    argv: Vec<String>,
+    envp: Vec<String>,
+) -> LinuxSyscallResult {
+    let parent = process.pid();
+    let mut vforks = VFORKS.lock();
+    let child = match vforks.get(&parent) {
+        Some(vfork) => vfork.child,
+        None => {
+            log::debug!("pid={} calls execve without vfork", parent);
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ENOSYS);
+        }
+    };
+
+    // the new process can't start before the syscall returns, because the process
+    // manager is locked
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    if let Err(err) = fs.swap_files(parent, child) {
+        log::debug!(
+            "pid={} can't pass its files to pid={}: {:?}",
+            parent,
+            child,
+            err
+        );
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EBUSY);
+    }
+    if let Err(err) = spawn_with_pid(process, child, path, argv, envp) {
+        // the child continues after a failed execve; undoing a swap never fails
+        fs.swap_files(parent, child).unwrap();
+        return LinuxSyscallResult::new_error(execve_error_code(err));
+    }
+    drop(fs);
+
+    let vfork = vforks.remove(&parent).unwrap();
+    vfork.parent_regs.restore_to_utcb(utcb_exc);
+    LinuxSyscallResult::new_success(child)
+}
+
+/// Handles the exit of the child of a `vfork()` before `execve()`: discards the files of
+/// the child and lets the parent continue. Returns `None` if the process doesn't play a
+/// child.
+pub(super) fn exit_vfork_child(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    status: i32,
+) -> Option<LinuxSyscallResult> {
+    let parent = process.pid();
+    let vfork = VFORKS.lock().remove(&parent)?;
+    {
+        let mut fs = libfileserver::FILESYSTEM.lock();
+        if let Err(err) = fs.swap_files(parent, vfork.child) {
+            log::warn!(
+                "pid={} can't get back its files from pid={}: {:?}",
+                parent,
+                vfork.child,
+                err
+            );
+        }
+        fs.release_process(vfork.child);
+    }
+    exit_unstarted_process(vfork.child, status);
+    vfork.parent_regs.restore_to_utcb(utcb_exc);
+    Some(LinuxSyscallResult::new_success(vfork.child))
+}
+
+/// Frees the reserved PID of the child if the process exited while it played the child of
+/// a `vfork()`.
+pub(super) fn release_process(parent: ProcessId) {
+    if let Some(vfork) = VFORKS.lock().remove(&parent) {
+        libfileserver::FILESYSTEM
+            .lock()
+            .release_process(vfork.child);
+        free_pid(vfork.child);
+    }
+}
+
+/// Same errors as `execve()` of Linux.
+fn execve_error_code(err: ProcessServiceError) -> LinuxErrorCode {
+    match err {
+        ProcessServiceError::NotFound => LinuxErrorCode::ENOENT,
+        ProcessServiceError::InvalidElf | ProcessServiceError::Unsupported => {
+            LinuxErrorCode::ENOEXEC
+        }
+        ProcessServiceError::PermissionDenied => LinuxErrorCode::EACCES,
+        ProcessServiceError::ArgumentsTooLong => LinuxErrorCode::E2BIG,
+        ProcessServiceError::InvalidArgument => LinuxErrorCode::EINVAL,
+        _ => LinuxErrorCode::EAGAIN,
+    }
+}
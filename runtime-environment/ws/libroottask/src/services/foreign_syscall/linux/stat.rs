@@ -0,0 +1,94 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_PATH_MAX;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::resolve_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libfileserver::FileStat;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/stat.2.html>. See
+/// [`super::lstat::LStatSyscall`] for `lstat()`.
+#[derive(Debug)]
+pub struct StatSyscall {
+    // null terminated path name
+    pathname: *const u8,
+    u_ptr_statbuf: u64,
+}
+
+impl From<&GenericLinuxSyscall> for StatSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            pathname: syscall.arg0() as *const _,
+            u_ptr_statbuf: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for StatSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.pathname as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        match stat_path(process, pathname, true) {
+            Ok(stat) => {
+                write_stat(process, self.u_ptr_statbuf, stat);
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Returns the metadata of the file at `pathname`. Without `follow_symlinks`, the
+/// metadata of a symbolic link itself. Shared by the `stat()` family.
+pub(super) fn stat_path(
+    process: &Process,
+    pathname: &str,
+    follow_symlinks: bool,
+) -> Result<FileStat, LinuxErrorCode> {
+    let pathname = resolve_path(process, pathname);
+    let fs = libfileserver::FILESYSTEM.lock();
+    let stat = if follow_symlinks {
+        fs.stat_path(process.pid(), &pathname)
+    } else {
+        fs.lstat_path(process.pid(), &pathname)
+    };
+    stat.map_err(LinuxErrorCode::from)
+}
+
+/// Writes the metadata to the `struct stat` at `u_ptr_statbuf` in the address space of
+/// the process.
+pub(super) fn write_stat(process: &Rc<Process>, u_ptr_statbuf: u64, stat: FileStat) {
+    let u_page_offset = u_ptr_statbuf & 0xfff;
+    let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+        process,
+        u_ptr_statbuf,
+        size_of::<FileStat>() as u64,
+    );
+
+    let r_write_ptr = mapping.mem_with_offset_as_ptr_mut(u_page_offset as usize);
+    unsafe {
+        core::ptr::write(r_write_ptr as *mut _, stat);
+    }
+}
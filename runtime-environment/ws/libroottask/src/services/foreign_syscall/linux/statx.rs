@@ -0,0 +1,178 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::{
+    LINUX_AT_EMPTY_PATH,
+    LINUX_AT_NO_AUTOMOUNT,
+    LINUX_AT_STATX_SYNC_TYPE,
+    LINUX_AT_SYMLINK_NOFOLLOW,
+    LINUX_PATH_MAX,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::newfstatat::stat_at;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libfileserver::FileStat;
+use libhrstd::cstr::CStr;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/statx.2.html>. Newer glibc
+/// versions and Rust's `std` use it. Always returns the basic fields, independent of the
+/// requested mask, like the Linux kernel does for most file systems. There is no birth
+/// time. Like for `faccessat()`, relative paths are only supported together with
+/// `AT_FDCWD`.
+#[derive(Debug)]
+pub struct StatxSyscall {
+    dirfd: i32,
+    // null terminated path name
+    pathname: *const u8,
+    flags: u64,
+    mask: u32,
+    u_ptr_statxbuf: u64,
+}
+
+impl From<&GenericLinuxSyscall> for StatxSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            pathname: syscall.arg1() as *const _,
+            flags: syscall.arg2(),
+            mask: syscall.arg3() as u32,
+            u_ptr_statxbuf: syscall.arg4(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for StatxSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let known_flags = LINUX_AT_SYMLINK_NOFOLLOW
+            | LINUX_AT_NO_AUTOMOUNT
+            | LINUX_AT_EMPTY_PATH
+            | LINUX_AT_STATX_SYNC_TYPE;
+        // the sync types exclude each other; STATX__RESERVED is invalid
+        if self.flags & !known_flags != 0
+            || self.flags & LINUX_AT_STATX_SYNC_TYPE == LINUX_AT_STATX_SYNC_TYPE
+            || self.mask & STATX_RESERVED != 0
+        {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+
+        let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.pathname as u64,
+            LINUX_PATH_MAX as u64,
+        );
+
+        let u_page_offset = self.pathname as usize & 0xfff;
+        let pathname = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
+        let pathname = CStr::try_from(pathname).unwrap();
+        // remove null bytes
+        let pathname = pathname.as_str().trim_matches('\0');
+
+        let stat = match stat_at(process, self.dirfd, pathname, self.flags) {
+            Ok(stat) => stat,
+            Err(err) => return LinuxSyscallResult::new_error(err),
+        };
+
+        let u_page_offset = self.u_ptr_statxbuf & 0xfff;
+        let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+            process,
+            self.u_ptr_statxbuf,
+            size_of::<Statx>() as u64,
+        );
+        let r_write_ptr = mapping.mem_with_offset_as_ptr_mut(u_page_offset as usize);
+        unsafe {
+            core::ptr::write(r_write_ptr as *mut _, Statx::from(&stat));
+        }
+        LinuxSyscallResult::new_success(0)
+    }
+}
+
+/// All fields of `stat()`.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/stat.h#L160>
+const STATX_BASIC_STATS: u32 = 0x7ff;
+/// Reserved for a future extension of [`Statx`].
+const STATX_RESERVED: u32 = 0x8000_0000;
+
+/// `struct statx_timestamp` of Linux.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct StatxTimestamp {
+    tv_sec: i64,
+    tv_nsec: u32,
+    __reserved: i32,
+}
+
+impl StatxTimestamp {
+    fn new(sec: i64, nsec: i64) -> Self {
+        Self {
+            tv_sec: sec,
+            tv_nsec: nsec as u32,
+            __reserved: 0,
+        }
+    }
+}
+
+/// `struct statx` of Linux.
+///
+/// Source: <https://elixir.bootlin.com/linux/latest/source/include/uapi/linux/stat.h#L99>
+#[repr(C)]
+#[derive(Debug, Default)]
+struct Statx {
+    stx_mask: u32,
+    stx_blksize: u32,
+    stx_attributes: u64,
+    stx_nlink: u32,
+    stx_uid: u32,
+    stx_gid: u32,
+    stx_mode: u16,
+    __spare0: u16,
+    stx_ino: u64,
+    stx_size: u64,
+    stx_blocks: u64,
+    stx_attributes_mask: u64,
+    stx_atime: StatxTimestamp,
+    stx_btime: StatxTimestamp,
+    stx_ctime: StatxTimestamp,
+    stx_mtime: StatxTimestamp,
+    stx_rdev_major: u32,
+    stx_rdev_minor: u32,
+    stx_dev_major: u32,
+    stx_dev_minor: u32,
+    stx_mnt_id: u64,
+    stx_dio_mem_align: u32,
+    stx_dio_offset_align: u32,
+    __spare3: [u64; 12],
+}
+
+impl From<&FileStat> for Statx {
+    fn from(stat: &FileStat) -> Self {
+        Self {
+            stx_mask: STATX_BASIC_STATS,
+            stx_blksize: stat.st_blksize() as u32,
+            stx_nlink: stat.st_nlink() as u32,
+            stx_uid: stat.st_uid(),
+            stx_gid: stat.st_gid(),
+            stx_mode: stat.st_mode() as u16,
+            stx_ino: stat.st_ino(),
+            stx_size: stat.st_size() as u64,
+            stx_blocks: stat.st_blocks() as u64,
+            stx_atime: StatxTimestamp::new(stat.st_atime(), stat.st_atime_nsec()),
+            stx_ctime: StatxTimestamp::new(stat.st_ctime(), stat.st_ctime_nsec()),
+            stx_mtime: StatxTimestamp::new(stat.st_mtime(), stat.st_mtime_nsec()),
+            // the device is the mount; there are no major numbers
+            stx_dev_minor: stat.st_dev() as u32,
+            stx_mnt_id: stat.st_dev(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::{
+    read_c_str,
+    read_path,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/symlink.2.html>. The target
+/// doesn't have to exist. A relative target is relative to the directory of the link.
+#[derive(Debug)]
+pub struct SymlinkSyscall {
+    // null terminated path names
+    target: *const u8,
+    linkpath: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for SymlinkSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            target: syscall.arg0() as *const _,
+            linkpath: syscall.arg1() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SymlinkSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        // the link may be used by other processes, hence it keeps `/proc/self`
+        let target = read_c_str(process, self.target);
+        let linkpath = read_path(process, self.linkpath);
+        match symlink(process, &target, &linkpath) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Creates a symbolic link at `linkpath` that points to `target`. Shared by `symlink()`
+/// and `symlinkat()`.
+pub(super) fn symlink(
+    process: &Process,
+    target: &str,
+    linkpath: &str,
+) -> Result<(), LinuxErrorCode> {
+    if target.is_empty() {
+        return Err(LinuxErrorCode::ENOENT);
+    }
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    if fs.lstat_path(process.pid(), linkpath).is_ok() {
+        return Err(LinuxErrorCode::EEXIST);
+    }
+    if fs.is_read_only() {
+        return Err(LinuxErrorCode::EROFS);
+    }
+    // e.g. a backend without symbolic links
+    fs.symlink(process.pid(), target, linkpath)
+        .map_err(LinuxErrorCode::from)
+}
@@ -0,0 +1,50 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::{
+    read_c_str,
+    read_path_at,
+};
+use crate::services::foreign_syscall::linux::symlink::symlink;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/symlinkat.2.html>. See
+/// [`super::symlink::SymlinkSyscall`]. Like for `faccessat()`, relative paths are only
+/// supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct SymlinkAtSyscall {
+    // null terminated path names
+    target: *const u8,
+    newdirfd: i32,
+    linkpath: *const u8,
+}
+
+impl From<&GenericLinuxSyscall> for SymlinkAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            target: syscall.arg0() as *const _,
+            newdirfd: syscall.arg1() as i32,
+            linkpath: syscall.arg2() as *const _,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for SymlinkAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let target = read_c_str(process, self.target);
+        let res = read_path_at(process, self.newdirfd, self.linkpath)
+            .and_then(|linkpath| symlink(process, &target, &linkpath));
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -7,7 +7,9 @@ pub enum LinuxSyscallNum {
     Write = 1,
     Open = 2,
     Close = 3,
+    Stat = 4,
     Fstat = 5,
+    LStat = 6,
     Poll = 7,
     LSeek = 8,
     MMap = 9,
@@ -16,23 +18,96 @@ pub enum LinuxSyscallNum {
     Brk = 12,
     RtSigaction = 13,
     RtSigprocmask = 14,
+    RtSigreturn = 15,
     Ioctl = 16,
+    PRead64 = 17,
+    PWrite64 = 18,
+    ReadV = 19,
+    NanoSleep = 35,
+    Alarm = 37,
     MAdvise = 28,
     WriteV = 20,
+    Access = 21,
+    Select = 23,
+    Socket = 41,
+    Connect = 42,
+    Accept = 43,
+    SendTo = 44,
+    RecvFrom = 45,
+    SendMsg = 46,
+    RecvMsg = 47,
+    Bind = 49,
+    Listen = 50,
+    SocketPair = 53,
     Clone = 56,
+    Vfork = 58,
+    Execve = 59,
+    Exit = 60,
+    Kill = 62,
+    Uname = 63,
     Fcntl = 72,
+    Truncate = 76,
+    Ftruncate = 77,
+    GetCwd = 79,
+    Chdir = 80,
+    Rename = 82,
+    Mkdir = 83,
+    Rmdir = 84,
+    Link = 86,
     Unlink = 87,
+    Symlink = 88,
+    ReadLink = 89,
+    Umask = 95,
+    GetTimeOfDay = 96,
+    GetRLimit = 97,
     Sysinfo = 99,
+    SetTimeOfDay = 164,
     SigAltStack = 131,
+    Prctl = 157,
+    SetRLimit = 160,
     ArchPrctl = 158,
     Gettid = 186,
+    Time = 201,
     Futex = 202,
+    SchedSetAffinity = 203,
     SchedGetAffinity = 204,
+    EpollCreate = 213,
     SetTidAddress = 218,
     ExitGroup = 231,
+    EpollWait = 232,
+    EpollCtl = 233,
+    OpenAt = 257,
+    MkdirAt = 258,
+    NewFstatAt = 262,
+    UnlinkAt = 263,
+    RenameAt = 264,
+    LinkAt = 265,
+    SymlinkAt = 266,
     ReadLinkAt = 267,
+    FaccessAt = 269,
+    PSelect6 = 270,
+    PPoll = 271,
+    ClockSetTime = 227,
     ClockGetTime = 228,
+    ClockNanoSleep = 230,
+    TgKill = 234,
+    Accept4 = 288,
+    SetRobustList = 273,
+    EpollPWait = 281,
+    TimerFdCreate = 283,
+    EventFd = 284,
+    TimerFdSetTime = 286,
+    TimerFdGetTime = 287,
+    EventFd2 = 290,
+    EpollCreate1 = 291,
+    PReadV = 295,
+    PWriteV = 296,
     PrLimit64 = 302,
+    RenameAt2 = 316,
+    GetRandom = 318,
+    MemfdCreate = 319,
+    Statx = 332,
+    Rseq = 334,
 }
 
 impl LinuxSyscallNum {
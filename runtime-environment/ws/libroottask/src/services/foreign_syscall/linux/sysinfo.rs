@@ -1,22 +1,34 @@
-use crate::process::Process;
+use crate::mem::PHYS_FRAME_ALLOC;
+use crate::process::{
+    exit_status,
+    signal_target,
+    Process,
+};
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::write_to_user;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::time;
 use alloc::rc::Rc;
-use core::mem::size_of;
+use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::UtcbDataException;
+use libhrstd::process::consts::NUM_PROCESSES;
 
+/// Implementation of <https://man7.org/linux/man-pages/man2/sysinfo.2.html>.
+/// The memory is the user memory of the physical frame allocator, see
+/// [`crate::mem::PhysFrameAllocator`]. There is no swap, no shared memory that is
+/// accounted separately, and no load average.
 #[derive(Debug)]
 pub struct SysinfoSyscall {
-    sysinfo: *mut sysinfo,
+    u_info: u64,
 }
 
 impl From<&GenericLinuxSyscall> for SysinfoSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
-            sysinfo: syscall.arg0() as *mut _,
+            u_info: syscall.arg0(),
         }
     }
 }
@@ -25,34 +37,63 @@ impl LinuxSyscallImpl for SysinfoSyscall {
     fn handle(
         &self,
         _utcb_exc: &mut UtcbDataException,
-        _process: &Rc<Process>,
+        process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        unsafe { core::ptr::write_bytes(self.sysinfo.cast::<u8>(), 0, size_of::<sysinfo>()) };
+        let (total_pages, free_pages) = {
+            let frames = PHYS_FRAME_ALLOC.lock();
+            (frames.total_pages(), frames.free_pages())
+        };
+        // the process manager is locked; the global tables know all running processes
+        let procs = (0..NUM_PROCESSES)
+            .filter(|pid| signal_target(*pid).is_some() && exit_status(*pid).is_none())
+            .count();
+        let info = sysinfo {
+            uptime: time::monotonic_ns() / 1_000_000_000,
+            loads: [0; 3],
+            totalram: (total_pages * PAGE_SIZE) as u64,
+            freeram: (free_pages * PAGE_SIZE) as u64,
+            sharedram: 0,
+            bufferram: 0,
+            totalswap: 0,
+            freeswap: 0,
+            procs: procs as u16,
+            _pad: 0,
+            totalhigh: 0,
+            freehigh: 0,
+            mem_unit: 1,
+        };
+        write_to_user(process, self.u_info, info);
         LinuxSyscallResult::new_success(0)
     }
 }
 
 #[allow(non_camel_case_types)]
+#[derive(Copy, Clone)]
 #[repr(C)]
 struct sysinfo {
     /// Seconds since boot
-    uptime: usize,
+    uptime: u64,
     /// 1, 5, and 15 minute load averages
-    loads: [usize; 3],
+    loads: [u64; 3],
     /// Total usable main memory size
-    totalram: usize,
+    totalram: u64,
     /// Available memory size
-    freeram: usize,
+    freeram: u64,
     /// Amount of shared memory
-    sharedram: usize,
+    sharedram: u64,
     /// Memory used by buffers
-    bufferram: usize,
+    bufferram: u64,
     /// Total swap space size
-    totalswap: usize,
+    totalswap: u64,
     /// Swap space still available
-    freeswap: usize,
+    freeswap: u64,
     /// Number of current processes
     procs: u16,
-    /// Pads structure to 64 bytes
-    _pad: [u8; 22],
+    _pad: u16,
+    /// Total high memory size
+    totalhigh: u64,
+    /// Available high memory size
+    freehigh: u64,
+    /// Memory unit size in bytes
+    mem_unit: u32,
 }
@@ -0,0 +1,127 @@
+//! Terminal settings of the console. All Linux programs share the console like a single
+//! terminal. The settings start with the defaults of a Linux terminal, so that libc
+//! detects a terminal in `isatty()` and uses line buffering for `stdout`.
+//!
+//! Only `ECHO` has an effect: `read` on standard input echoes the input if it is set. The
+//! console always delivers input as it arrives, as if `ICANON` was cleared.
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    write_to_user,
+};
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use alloc::rc::Rc;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Gets the terminal settings.
+const TCGETS: u64 = 0x5401;
+/// Sets the terminal settings immediately.
+const TCSETS: u64 = 0x5402;
+/// Sets the terminal settings after all output was written.
+const TCSETSW: u64 = 0x5403;
+/// Sets the terminal settings after all output was written and discards pending input.
+const TCSETSF: u64 = 0x5404;
+/// Gets the window size.
+const TIOCGWINSZ: u64 = 0x5413;
+/// Sets the window size.
+const TIOCSWINSZ: u64 = 0x5414;
+
+/// Flag of `c_lflag`: echo input characters.
+const ECHO: u32 = 0o10;
+
+/// Settings of the console.
+static CONSOLE: SimpleMutex<Console> = SimpleMutex::new(Console::new());
+
+/// Same as `struct termios` of the Linux kernel, which `TCGETS` and `TCSETS` use. libc has
+/// a larger variant with the speeds as separate fields.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct termios {
+    c_iflag: u32,
+    c_oflag: u32,
+    c_cflag: u32,
+    c_lflag: u32,
+    c_line: u8,
+    c_cc: [u8; 19],
+}
+
+/// Same as `struct winsize` of Linux.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct winsize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
+#[derive(Debug)]
+struct Console {
+    termios: termios,
+    winsize: winsize,
+}
+
+impl Console {
+    /// The settings of a new terminal on Linux and the classic size of 80x24 characters.
+    const fn new() -> Self {
+        Self {
+            termios: termios {
+                // ICRNL | IXON
+                c_iflag: 0o2400,
+                // OPOST | ONLCR
+                c_oflag: 0o5,
+                // B38400 | CS8 | CREAD | HUPCL
+                c_cflag: 0o2277,
+                // ISIG | ICANON | ECHO | ECHOE | ECHOK | ECHOCTL | ECHOKE | IEXTEN
+                c_lflag: 0o105073,
+                c_line: 0,
+                // ^C, ^\, DEL, ^U, ^D, VTIME=0, VMIN=1, -, ^Q, ^S, ^Z, -, ^R, ^O, ^W, ^V, -
+                c_cc: [
+                    0x03, 0x1c, 0x7f, 0x15, 0x04, 0, 1, 0, 0x11, 0x13, 0x1a, 0, 0x12, 0x0f, 0x17,
+                    0x16, 0, 0, 0,
+                ],
+            },
+            winsize: winsize {
+                ws_row: 24,
+                ws_col: 80,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            },
+        }
+    }
+}
+
+/// Handles the terminal `ioctl` requests on the console. Fails with `ENOTTY` for other
+/// requests, like Linux does for requests that a terminal doesn't know.
+pub(super) fn console_ioctl(process: &Rc<Process>, request: u64, u_arg: u64) -> LinuxSyscallResult {
+    let is_known = matches!(
+        request,
+        TCGETS | TCSETS | TCSETSW | TCSETSF | TIOCGWINSZ | TIOCSWINSZ
+    );
+    if !is_known {
+        log::debug!("ioctl request {:#x} not supported for the console", request);
+        return LinuxSyscallResult::new_error(LinuxErrorCode::ENOTTY);
+    }
+    if u_arg == 0 {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EFAULT);
+    }
+
+    let mut console = CONSOLE.lock();
+    match request {
+        TCGETS => write_to_user(process, u_arg, console.termios),
+        TCSETS | TCSETSW | TCSETSF => console.termios = read_from_user::<termios>(process, u_arg),
+        TIOCGWINSZ => write_to_user(process, u_arg, console.winsize),
+        TIOCSWINSZ => console.winsize = read_from_user::<winsize>(process, u_arg),
+        _ => unreachable!(),
+    }
+    LinuxSyscallResult::new_success(0)
+}
+
+/// Checks if the console echoes the input that programs read.
+pub(super) fn echo_enabled() -> bool {
+    CONSOLE.lock().termios.c_lflag & ECHO != 0
+}
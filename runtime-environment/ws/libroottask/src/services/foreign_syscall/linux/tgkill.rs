@@ -0,0 +1,53 @@
+use crate::process::{
+    sig_valid,
+    Process,
+    SigNum,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::kill::send_signal;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/tgkill.2.html>.
+/// Processes are single-threaded, hence the only thread of a process has the
+/// thread ID that equals the PID.
+#[derive(Debug)]
+pub struct TgKillSyscall {
+    tgid: i64,
+    tid: i64,
+    sig: SigNum,
+}
+
+impl From<&GenericLinuxSyscall> for TgKillSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            tgid: syscall.arg0() as i32 as i64,
+            tid: syscall.arg1() as i32 as i64,
+            sig: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for TgKillSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        _process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.tgid <= 0 || self.tid <= 0 || (self.sig != 0 && !sig_valid(self.sig)) {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        if self.tgid != self.tid {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ESRCH);
+        }
+        match send_signal(self.tid as u64, self.sig) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::services::MAPPED_AREAS;
+use crate::time;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libhrstd::libhedron::UtcbDataException;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/time.2.html>. Returns the
+/// seconds of the wall clock since the Unix epoch and also stores them in `tloc`, if
+/// the pointer is not null.
+#[derive(Debug)]
+pub struct TimeSyscall {
+    u_ptr_tloc: u64,
+}
+
+impl From<&GenericLinuxSyscall> for TimeSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_ptr_tloc: syscall.arg0(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for TimeSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let secs = time::realtime_ns() / NS_PER_SEC;
+        if self.u_ptr_tloc != 0 {
+            let mut mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                self.u_ptr_tloc,
+                size_of::<u64>() as u64,
+            );
+            *mapping.mem_with_offset_as_mut::<u64>((self.u_ptr_tloc & 0xfff) as usize) = secs;
+        }
+        LinuxSyscallResult::new_success(secs)
+    }
+}
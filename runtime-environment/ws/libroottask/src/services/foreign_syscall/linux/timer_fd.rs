@@ -0,0 +1,342 @@
+//! Common functionality of the timerfd syscalls. A timerfd counts the expirations of a
+//! one-shot or periodic timer and `read` takes the count. The file server reserves the file
+//! descriptor, similar to eventfds.
+//!
+//! Unlike the timers of the timer service, a timerfd needs no semaphore: all clocks share
+//! the TSC as time base (see [`time`]), so the expirations follow from the TSC whenever
+//! the timerfd is used. Readers sleep until the timer expires like in `poll`, see
+//! [`fd_events::wait_for_events`]. Absolute expirations of the realtime clocks are
+//! converted once; a later change of the wall clock doesn't affect the timer.
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::clock_gettime::{
+    timespec,
+    ClockId,
+};
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::fd_events;
+use crate::services::foreign_syscall::linux::fd_events::FdKind;
+use crate::services::foreign_syscall::linux::unix_socket::write_to_user;
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use crate::time;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::mem::size_of;
+use libfileserver::{
+    FileDescriptor,
+    Readiness,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Same as `O_NONBLOCK`.
+const TFD_NONBLOCK: u64 = 0o4000;
+/// Same as `O_CLOEXEC`. Accepted, but has no effect on `execve()`, see [`super::spawn`].
+const TFD_CLOEXEC: u64 = 0o2000000;
+/// The expiration is an absolute value of the clock.
+const TFD_TIMER_ABSTIME: u64 = 1;
+/// Cancel the timer when the wall clock changes. Accepted but has no effect.
+const TFD_TIMER_CANCEL_ON_SET: u64 = 2;
+
+/// All timerfds of all processes.
+static TIMER_FDS: SimpleMutex<BTreeMap<(ProcessId, FileDescriptor), TimerFd>> =
+    SimpleMutex::new(BTreeMap::new());
+
+/// Same as `struct itimerspec` of Linux.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct itimerspec {
+    /// period of the timer; zero for one-shot timers
+    it_interval: timespec,
+    /// (remaining) time until the next expiration; zero if disarmed
+    it_value: timespec,
+}
+
+#[derive(Debug)]
+struct TimerFd {
+    realtime: bool,
+    nonblocking: bool,
+    /// Next expiration in nanoseconds of the monotonic clock. `None` if disarmed.
+    next_ns: Option<u64>,
+    interval_ns: u64,
+}
+
+impl TimerFd {
+    const fn new(realtime: bool, nonblocking: bool) -> Self {
+        Self {
+            realtime,
+            nonblocking,
+            next_ns: None,
+            interval_ns: 0,
+        }
+    }
+
+    /// Returns the number of expirations until `now_ns` that were not read yet.
+    fn pending_expirations(&self, now_ns: u64) -> u64 {
+        match self.next_ns {
+            Some(next_ns) if now_ns >= next_ns && self.interval_ns == 0 => 1,
+            Some(next_ns) if now_ns >= next_ns => 1 + (now_ns - next_ns) / self.interval_ns,
+            _ => 0,
+        }
+    }
+
+    /// Like [`Self::pending_expirations`], but also moves the timer to the next expiration
+    /// after `now_ns`. One-shot timers get disarmed.
+    fn take_expirations(&mut self, now_ns: u64) -> u64 {
+        let count = self.pending_expirations(now_ns);
+        if count > 0 {
+            self.next_ns = match self.interval_ns {
+                0 => None,
+                // saturates for huge intervals, i.e. the timer doesn't expire again
+                interval_ns => self
+                    .next_ns
+                    .map(|next_ns| next_ns.saturating_add(count.saturating_mul(interval_ns))),
+            };
+        }
+        count
+    }
+
+    /// Arms the timer with the first expiration after `value_ns` or, if `absolute`, at the
+    /// value `value_ns` of the clock. Zero disarms the timer. Values that are too large to
+    /// be reached saturate, so the timer never expires.
+    fn arm(
+        &mut self,
+        value_ns: u64,
+        interval_ns: u64,
+        absolute: bool,
+        now_ns: u64,
+        realtime_ns: u64,
+    ) {
+        self.interval_ns = interval_ns;
+        self.next_ns = match value_ns {
+            0 => None,
+            value_ns if !absolute => Some(now_ns.saturating_add(value_ns)),
+            value_ns if self.realtime => {
+                Some(now_ns.saturating_add(value_ns.saturating_sub(realtime_ns)))
+            }
+            value_ns => Some(value_ns),
+        };
+    }
+
+    /// Returns the current setting, i.e. the time until the next expiration after `now_ns`.
+    fn setting(&self, now_ns: u64) -> itimerspec {
+        let value_ns = match self.next_ns {
+            Some(next_ns) if next_ns > now_ns => next_ns - now_ns,
+            Some(next_ns) if self.interval_ns > 0 => {
+                let elapsed_ns = now_ns - next_ns;
+                self.interval_ns - elapsed_ns % self.interval_ns
+            }
+            // an expired one-shot timer that wasn't read yet counts as disarmed, too
+            _ => 0,
+        };
+        itimerspec {
+            it_interval: timespec::from_nanos(self.interval_ns),
+            it_value: timespec::from_nanos(value_ns),
+        }
+    }
+}
+
+/// Creates a new disarmed timerfd and returns its file descriptor. Fails with `EINVAL` for
+/// unknown flags and clocks that can't be used for timers.
+pub(super) fn create(
+    process: &Rc<Process>,
+    clk_id: u64,
+    flags: u64,
+) -> Result<FileDescriptor, LinuxErrorCode> {
+    let realtime = match ClockId::from_raw(clk_id) {
+        Some(ClockId::Realtime | ClockId::Realtimealarm) => true,
+        Some(ClockId::Monotonic | ClockId::Boottime | ClockId::BoottimeAlarm) => false,
+        _ => return Err(LinuxErrorCode::EINVAL),
+    };
+    if flags & !(TFD_NONBLOCK | TFD_CLOEXEC) != 0 {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    let fd = libfileserver::FILESYSTEM.lock().reserve_fd(process.pid());
+    let timer_fd = TimerFd::new(realtime, flags & TFD_NONBLOCK != 0);
+    TIMER_FDS.lock().insert((process.pid(), fd), timer_fd);
+    Ok(fd)
+}
+
+/// Checks if the file descriptor refers to a timerfd.
+pub(super) fn is_timer_fd(process: &Rc<Process>, fd: FileDescriptor) -> bool {
+    TIMER_FDS.lock().contains_key(&(process.pid(), fd))
+}
+
+/// A timerfd is readable if it expired since the last read. It is never writable.
+pub(super) fn readiness(process: &Rc<Process>, fd: FileDescriptor) -> Readiness {
+    let now_ns = time::monotonic_ns();
+    Readiness {
+        readable: TIMER_FDS
+            .lock()
+            .get(&(process.pid(), fd))
+            .map_or(false, |timer_fd| timer_fd.pending_expirations(now_ns) > 0),
+        writable: false,
+        hang_up: false,
+    }
+}
+
+/// Implements `timerfd_settime`. Arms the timer with `new`, or disarms it if the value is
+/// zero, and returns the previous setting.
+pub(super) fn set_time(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    flags: u64,
+    new: itimerspec,
+) -> Result<itimerspec, LinuxErrorCode> {
+    if flags & !(TFD_TIMER_ABSTIME | TFD_TIMER_CANCEL_ON_SET) != 0 {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    let value_ns = new.it_value.as_nanos().ok_or(LinuxErrorCode::EINVAL)?;
+    let interval_ns = new.it_interval.as_nanos().ok_or(LinuxErrorCode::EINVAL)?;
+    check_timer_fd(process, fd)?;
+
+    let mut timer_fds = TIMER_FDS.lock();
+    let timer_fd = timer_fds
+        .get_mut(&(process.pid(), fd))
+        .ok_or(LinuxErrorCode::EBADF)?;
+    let now_ns = time::monotonic_ns();
+    let old = timer_fd.setting(now_ns);
+    timer_fd.arm(
+        value_ns,
+        interval_ns,
+        flags & TFD_TIMER_ABSTIME != 0,
+        now_ns,
+        time::realtime_ns(),
+    );
+    Ok(old)
+}
+
+/// Implements `timerfd_gettime`.
+pub(super) fn get_time(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+) -> Result<itimerspec, LinuxErrorCode> {
+    check_timer_fd(process, fd)?;
+    TIMER_FDS
+        .lock()
+        .get(&(process.pid(), fd))
+        .map(|timer_fd| timer_fd.setting(time::monotonic_ns()))
+        .ok_or(LinuxErrorCode::EBADF)
+}
+
+/// Implements `read` on a timerfd. Waits until the timer expired and writes the number of
+/// expirations since the last read as 8-byte integer to user memory.
+pub(super) fn read(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    u_buf: u64,
+    count: usize,
+) -> LinuxSyscallResult {
+    if count < size_of::<u64>() {
+        return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+    }
+    let mut result = Err(LinuxErrorCode::EAGAIN);
+    // the timerfd is the only file descriptor to wait for; one means done
+    let res = fd_events::wait_for_events(process, None, || {
+        let mut timer_fds = TIMER_FDS.lock();
+        let timer_fd = match timer_fds.get_mut(&(process.pid(), fd)) {
+            Some(timer_fd) => timer_fd,
+            // closed by another thread in the meantime
+            None => {
+                result = Err(LinuxErrorCode::EBADF);
+                return 1;
+            }
+        };
+        match timer_fd.take_expirations(time::monotonic_ns()) {
+            0 => timer_fd.nonblocking as usize,
+            expirations => {
+                result = Ok(expirations);
+                1
+            }
+        }
+    });
+    match res.and(result) {
+        Ok(expirations) => {
+            write_to_user(process, u_buf, expirations);
+            LinuxSyscallResult::new_success(size_of::<u64>() as u64)
+        }
+        Err(err) => LinuxSyscallResult::new_error(err),
+    }
+}
+
+/// Destroys the timerfd, if the file descriptor refers to one.
+pub(super) fn close(process: &Rc<Process>, fd: FileDescriptor) {
+    TIMER_FDS.lock().remove(&(process.pid(), fd));
+}
+
+/// Destroys all timerfds of a process that exited.
+pub(super) fn release_process(pid: ProcessId) {
+    TIMER_FDS.lock().retain(|(fd_pid, _), _| *fd_pid != pid);
+}
+
+/// Fails with `EBADF` if the file descriptor is not open and with `EINVAL` if it doesn't
+/// refer to a timerfd.
+fn check_timer_fd(process: &Rc<Process>, fd: FileDescriptor) -> Result<(), LinuxErrorCode> {
+    match fd_events::fd_kind(process, fd) {
+        Some(FdKind::TimerFd) => Ok(()),
+        Some(_) => Err(LinuxErrorCode::EINVAL),
+        None => Err(LinuxErrorCode::EBADF),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_fd_expirations() {
+        let mut timer_fd = TimerFd::new(false, false);
+        assert_eq!(timer_fd.take_expirations(1000), 0, "disarmed");
+
+        // one-shot timer
+        timer_fd.next_ns = Some(100);
+        assert_eq!(timer_fd.pending_expirations(99), 0);
+        assert_eq!(timer_fd.setting(40).it_value.as_nanos(), Some(60));
+        assert_eq!(timer_fd.take_expirations(500), 1);
+        assert_eq!(timer_fd.take_expirations(1000), 0);
+        assert_eq!(timer_fd.next_ns, None);
+
+        // periodic timer
+        timer_fd.next_ns = Some(100);
+        timer_fd.interval_ns = 50;
+        assert_eq!(timer_fd.pending_expirations(220), 3);
+        assert_eq!(timer_fd.setting(220).it_value.as_nanos(), Some(30));
+        assert_eq!(timer_fd.take_expirations(220), 3);
+        assert_eq!(timer_fd.next_ns, Some(250));
+        assert_eq!(timer_fd.take_expirations(249), 0);
+        assert_eq!(timer_fd.take_expirations(250), 1);
+    }
+
+    #[test]
+    fn test_timer_fd_huge_values() {
+        let huge = timespec {
+            tv_sec: i64::MAX as usize,
+            tv_nsec: 0,
+        };
+        let huge_ns = huge.as_nanos().unwrap();
+
+        // relative and absolute expirations that can't be reached
+        let mut timer_fd = TimerFd::new(true, false);
+        timer_fd.arm(huge_ns, huge_ns, false, 1000, 5000);
+        assert_eq!(timer_fd.next_ns, Some(u64::MAX));
+        assert_eq!(timer_fd.take_expirations(u64::MAX - 1), 0);
+        timer_fd.arm(huge_ns, 0, true, 1000, 5000);
+        assert_eq!(timer_fd.next_ns, Some(u64::MAX - 4000));
+        assert_eq!(
+            timer_fd.setting(1000).it_value.as_nanos(),
+            Some(u64::MAX - 5000)
+        );
+
+        // an interval timer that runs for a long time
+        let mut timer_fd = TimerFd::new(false, false);
+        timer_fd.arm(100, u64::MAX / 2, false, 0, 0);
+        assert_eq!(timer_fd.take_expirations(u64::MAX / 2), 1);
+        assert_eq!(timer_fd.take_expirations(u64::MAX - 1), 1);
+        assert_eq!(timer_fd.next_ns, Some(u64::MAX));
+        assert_eq!(timer_fd.take_expirations(u64::MAX - 1), 0);
+        timer_fd.arm(1, 1, false, 0, 0);
+        assert_eq!(timer_fd.take_expirations(u64::MAX), u64::MAX);
+        assert_eq!(timer_fd.next_ns, Some(u64::MAX));
+    }
+}
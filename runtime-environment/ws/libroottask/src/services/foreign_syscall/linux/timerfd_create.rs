@@ -0,0 +1,39 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::timer_fd;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/timerfd_create.2.html>.
+/// Supports the realtime, monotonic, and boot clocks. See [`super::timer_fd`].
+#[derive(Debug)]
+pub struct TimerFdCreateSyscall {
+    clk_id: u64,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for TimerFdCreateSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            clk_id: syscall.arg0(),
+            flags: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for TimerFdCreateSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        match timer_fd::create(process, self.clk_id, self.flags) {
+            Ok(fd) => LinuxSyscallResult::new_success(fd.val()),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
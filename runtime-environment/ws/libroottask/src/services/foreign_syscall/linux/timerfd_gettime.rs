@@ -0,0 +1,48 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::timer_fd;
+use crate::services::foreign_syscall::linux::unix_socket::write_to_user;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/timerfd_gettime.2.html>.
+/// See [`super::timer_fd`].
+#[derive(Debug)]
+pub struct TimerFdGetTimeSyscall {
+    fd: FileDescriptor,
+    u_curr_value: u64,
+}
+
+impl From<&GenericLinuxSyscall> for TimerFdGetTimeSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            u_curr_value: syscall.arg1(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for TimerFdGetTimeSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.u_curr_value == 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EFAULT);
+        }
+        match timer_fd::get_time(process, self.fd) {
+            Ok(curr_value) => {
+                write_to_user(process, self.u_curr_value, curr_value);
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::timer_fd;
+use crate::services::foreign_syscall::linux::timer_fd::itimerspec;
+use crate::services::foreign_syscall::linux::unix_socket::{
+    read_from_user,
+    write_to_user,
+};
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libfileserver::FileDescriptor;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/timerfd_settime.2.html>.
+/// See [`super::timer_fd`].
+#[derive(Debug)]
+pub struct TimerFdSetTimeSyscall {
+    fd: FileDescriptor,
+    flags: u64,
+    u_new_value: u64,
+    u_old_value: u64,
+}
+
+impl From<&GenericLinuxSyscall> for TimerFdSetTimeSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            fd: FileDescriptor::new(syscall.arg0()),
+            flags: syscall.arg1(),
+            u_new_value: syscall.arg2(),
+            u_old_value: syscall.arg3(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for TimerFdSetTimeSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.u_new_value == 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EFAULT);
+        }
+        let new_value = read_from_user::<itimerspec>(process, self.u_new_value);
+        match timer_fd::set_time(process, self.fd, self.flags, new_value) {
+            Ok(old_value) => {
+                if self.u_old_value != 0 {
+                    write_to_user(process, self.u_old_value, old_value);
+                }
+                LinuxSyscallResult::new_success(0)
+            }
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Decodes Linux syscalls for the syscall trace of a process, like `strace` prints them,
+//! e.g. `openat(AT_FDCWD, "/etc/hosts", 0x80000, 0o0) = 3`. See
+//! [`crate::process::record_syscall`].
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_AT_FDCWD;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::path::read_c_str;
+use crate::services::foreign_syscall::linux::syscall_num::LinuxSyscallNum;
+use crate::services::foreign_syscall::linux::GenericLinuxSyscall;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Return values from -4095 to -1 are error codes, like in Linux.
+const MAX_ERRNO: u64 = 4095;
+
+/// How an argument of a syscall is shown.
+#[derive(Debug, Copy, Clone)]
+enum Arg {
+    /// Signed decimal number.
+    Int,
+    /// Flags and masks.
+    Hex,
+    /// File permissions.
+    Oct,
+    /// Address in the address space of the process; `NULL` if zero.
+    Ptr,
+    /// File descriptor; shows `AT_FDCWD`.
+    Fd,
+    /// Null-terminated string, e.g. a path, that is read from the process.
+    Str,
+}
+
+/// Formats the call of the syscall. Reads the strings from the process, hence it must be
+/// called before the syscall is handled; e.g. `execve` would replace them.
+pub fn format_call(syscall: &GenericLinuxSyscall, process: &Rc<Process>) -> String {
+    let (name, args) = signature(syscall.syscall_num());
+    let values = [
+        syscall.arg0(),
+        syscall.arg1(),
+        syscall.arg2(),
+        syscall.arg3(),
+        syscall.arg4(),
+        syscall.arg5(),
+    ];
+    format_args(name, args, &values, |u_ptr| {
+        read_c_str(process, u_ptr as *const u8)
+    })
+}
+
+/// Formats a syscall that the roottask doesn't know with all six argument registers.
+pub fn format_unknown_call(utcb_exc: &UtcbDataException) -> String {
+    let values = [
+        utcb_exc.rdi,
+        utcb_exc.rsi,
+        utcb_exc.rdx,
+        utcb_exc.r10,
+        utcb_exc.r8,
+        utcb_exc.r9,
+    ];
+    let name = format!("syscall_{}", utcb_exc.rax);
+    format_args(&name, &[Arg::Hex; 6], &values, |_| String::new())
+}
+
+/// Appends the return value of the syscall to its call. `syscall_num` is `None` for
+/// unknown syscalls.
+pub fn format_result(call: String, syscall_num: Option<LinuxSyscallNum>, rax: u64) -> String {
+    let result = match syscall_num {
+        // the process is gone
+        Some(LinuxSyscallNum::Exit | LinuxSyscallNum::ExitGroup) => String::from("?"),
+        _ if rax.wrapping_neg() <= MAX_ERRNO && rax != 0 => {
+            let errno = rax.wrapping_neg();
+            match LinuxErrorCode::from_val(errno) {
+                Some(code) => format!("-1 {:?}", code),
+                None => format!("-1 errno {}", errno),
+            }
+        }
+        Some(LinuxSyscallNum::MMap | LinuxSyscallNum::Brk) => format!("{:#x}", rax),
+        _ => format!("{}", rax as i64),
+    };
+    format!("{} = {}", call, result)
+}
+
+fn format_args(
+    name: &str,
+    args: &[Arg],
+    values: &[u64; 6],
+    read_str: impl Fn(u64) -> String,
+) -> String {
+    let args = args
+        .iter()
+        .zip(values)
+        .map(|(arg, &value)| match arg {
+            Arg::Int => format!("{}", value as i64),
+            Arg::Hex => format!("{:#x}", value),
+            Arg::Oct => format!("{:#o}", value),
+            Arg::Ptr | Arg::Str if value == 0 => String::from("NULL"),
+            Arg::Ptr => format!("{:#x}", value),
+            Arg::Fd if value as i32 == LINUX_AT_FDCWD => String::from("AT_FDCWD"),
+            Arg::Fd => format!("{}", value as i32),
+            Arg::Str => format!("{:?}", read_str(value)),
+        })
+        .collect::<Vec<_>>();
+    format!("{}({})", name, args.join(", "))
+}
+
+/// Returns the name of the syscall as in Linux and how its arguments are shown.
+fn signature(syscall_num: LinuxSyscallNum) -> (&'static str, &'static [Arg]) {
+    use Arg::*;
+    match syscall_num {
+        LinuxSyscallNum::Read => ("read", &[Fd, Ptr, Int]),
+        LinuxSyscallNum::Write => ("write", &[Fd, Ptr, Int]),
+        LinuxSyscallNum::Open => ("open", &[Str, Hex, Oct]),
+        LinuxSyscallNum::Close => ("close", &[Fd]),
+        LinuxSyscallNum::Stat => ("stat", &[Str, Ptr]),
+        LinuxSyscallNum::Fstat => ("fstat", &[Fd, Ptr]),
+        LinuxSyscallNum::LStat => ("lstat", &[Str, Ptr]),
+        LinuxSyscallNum::Poll => ("poll", &[Ptr, Int, Int]),
+        LinuxSyscallNum::LSeek => ("lseek", &[Fd, Int, Int]),
+        LinuxSyscallNum::MMap => ("mmap", &[Ptr, Int, Hex, Hex, Fd, Int]),
+        LinuxSyscallNum::MProtect => ("mprotect", &[Ptr, Int, Hex]),
+        LinuxSyscallNum::MUnmap => ("munmap", &[Ptr, Int]),
+        LinuxSyscallNum::Brk => ("brk", &[Ptr]),
+        LinuxSyscallNum::RtSigaction => ("rt_sigaction", &[Int, Ptr, Ptr, Int]),
+        LinuxSyscallNum::RtSigprocmask => ("rt_sigprocmask", &[Int, Ptr, Ptr, Int]),
+        LinuxSyscallNum::RtSigreturn => ("rt_sigreturn", &[]),
+        LinuxSyscallNum::Ioctl => ("ioctl", &[Fd, Hex, Hex]),
+        LinuxSyscallNum::PRead64 => ("pread64", &[Fd, Ptr, Int, Int]),
+        LinuxSyscallNum::PWrite64 => ("pwrite64", &[Fd, Ptr, Int, Int]),
+        LinuxSyscallNum::ReadV => ("readv", &[Fd, Ptr, Int]),
+        LinuxSyscallNum::NanoSleep => ("nanosleep", &[Ptr, Ptr]),
+        LinuxSyscallNum::Alarm => ("alarm", &[Int]),
+        LinuxSyscallNum::MAdvise => ("madvise", &[Ptr, Int, Int]),
+        LinuxSyscallNum::WriteV => ("writev", &[Fd, Ptr, Int]),
+        LinuxSyscallNum::Access => ("access", &[Str, Oct]),
+        LinuxSyscallNum::Select => ("select", &[Int, Ptr, Ptr, Ptr, Ptr]),
+        LinuxSyscallNum::Socket => ("socket", &[Int, Hex, Int]),
+        LinuxSyscallNum::Connect => ("connect", &[Fd, Ptr, Int]),
+        LinuxSyscallNum::Accept => ("accept", &[Fd, Ptr, Ptr]),
+        LinuxSyscallNum::SendTo => ("sendto", &[Fd, Ptr, Int, Hex, Ptr, Int]),
+        LinuxSyscallNum::RecvFrom => ("recvfrom", &[Fd, Ptr, Int, Hex, Ptr, Ptr]),
+        LinuxSyscallNum::SendMsg => ("sendmsg", &[Fd, Ptr, Hex]),
+        LinuxSyscallNum::RecvMsg => ("recvmsg", &[Fd, Ptr, Hex]),
+        LinuxSyscallNum::Bind => ("bind", &[Fd, Ptr, Int]),
+        LinuxSyscallNum::Listen => ("listen", &[Fd, Int]),
+        LinuxSyscallNum::SocketPair => ("socketpair", &[Int, Hex, Int, Ptr]),
+        LinuxSyscallNum::Clone => ("clone", &[Hex, Ptr, Ptr, Ptr, Hex]),
+        LinuxSyscallNum::Vfork => ("vfork", &[]),
+        LinuxSyscallNum::Execve => ("execve", &[Str, Ptr, Ptr]),
+        LinuxSyscallNum::Exit => ("exit", &[Int]),
+        LinuxSyscallNum::Kill => ("kill", &[Int, Int]),
+        LinuxSyscallNum::Uname => ("uname", &[Ptr]),
+        LinuxSyscallNum::Fcntl => ("fcntl", &[Fd, Int, Hex]),
+        LinuxSyscallNum::Truncate => ("truncate", &[Str, Int]),
+        LinuxSyscallNum::Ftruncate => ("ftruncate", &[Fd, Int]),
+        LinuxSyscallNum::GetCwd => ("getcwd", &[Ptr, Int]),
+        LinuxSyscallNum::Chdir => ("chdir", &[Str]),
+        LinuxSyscallNum::Rename => ("rename", &[Str, Str]),
+        LinuxSyscallNum::Mkdir => ("mkdir", &[Str, Oct]),
+        LinuxSyscallNum::Rmdir => ("rmdir", &[Str]),
+        LinuxSyscallNum::Link => ("link", &[Str, Str]),
+        LinuxSyscallNum::Unlink => ("unlink", &[Str]),
+        LinuxSyscallNum::Symlink => ("symlink", &[Str, Str]),
+        LinuxSyscallNum::ReadLink => ("readlink", &[Str, Ptr, Int]),
+        LinuxSyscallNum::Umask => ("umask", &[Oct]),
+        LinuxSyscallNum::GetTimeOfDay => ("gettimeofday", &[Ptr, Ptr]),
+        LinuxSyscallNum::GetRLimit => ("getrlimit", &[Int, Ptr]),
+        LinuxSyscallNum::Sysinfo => ("sysinfo", &[Ptr]),
+        LinuxSyscallNum::SetTimeOfDay => ("settimeofday", &[Ptr, Ptr]),
+        LinuxSyscallNum::SigAltStack => ("sigaltstack", &[Ptr, Ptr]),
+        LinuxSyscallNum::Prctl => ("prctl", &[Int, Hex, Hex, Hex, Hex]),
+        LinuxSyscallNum::SetRLimit => ("setrlimit", &[Int, Ptr]),
+        LinuxSyscallNum::ArchPrctl => ("arch_prctl", &[Hex, Hex]),
+        LinuxSyscallNum::Gettid => ("gettid", &[]),
+        LinuxSyscallNum::Time => ("time", &[Ptr]),
+        LinuxSyscallNum::Futex => ("futex", &[Ptr, Int, Int, Ptr, Ptr, Int]),
+        LinuxSyscallNum::SchedSetAffinity => ("sched_setaffinity", &[Int, Int, Ptr]),
+        LinuxSyscallNum::SchedGetAffinity => ("sched_getaffinity", &[Int, Int, Ptr]),
+        LinuxSyscallNum::EpollCreate => ("epoll_create", &[Int]),
+        LinuxSyscallNum::SetTidAddress => ("set_tid_address", &[Ptr]),
+        LinuxSyscallNum::ExitGroup => ("exit_group", &[Int]),
+        LinuxSyscallNum::EpollWait => ("epoll_wait", &[Fd, Ptr, Int, Int]),
+        LinuxSyscallNum::EpollCtl => ("epoll_ctl", &[Fd, Int, Fd, Ptr]),
+        LinuxSyscallNum::OpenAt => ("openat", &[Fd, Str, Hex, Oct]),
+        LinuxSyscallNum::MkdirAt => ("mkdirat", &[Fd, Str, Oct]),
+        LinuxSyscallNum::NewFstatAt => ("newfstatat", &[Fd, Str, Ptr, Hex]),
+        LinuxSyscallNum::UnlinkAt => ("unlinkat", &[Fd, Str, Hex]),
+        LinuxSyscallNum::RenameAt => ("renameat", &[Fd, Str, Fd, Str]),
+        LinuxSyscallNum::LinkAt => ("linkat", &[Fd, Str, Fd, Str, Hex]),
+        LinuxSyscallNum::SymlinkAt => ("symlinkat", &[Str, Fd, Str]),
+        LinuxSyscallNum::ReadLinkAt => ("readlinkat", &[Fd, Str, Ptr, Int]),
+        LinuxSyscallNum::FaccessAt => ("faccessat", &[Fd, Str, Oct]),
+        LinuxSyscallNum::PSelect6 => ("pselect6", &[Int, Ptr, Ptr, Ptr, Ptr, Ptr]),
+        LinuxSyscallNum::PPoll => ("ppoll", &[Ptr, Int, Ptr, Ptr, Int]),
+        LinuxSyscallNum::ClockSetTime => ("clock_settime", &[Int, Ptr]),
+        LinuxSyscallNum::ClockGetTime => ("clock_gettime", &[Int, Ptr]),
+        LinuxSyscallNum::ClockNanoSleep => ("clock_nanosleep", &[Int, Hex, Ptr, Ptr]),
+        LinuxSyscallNum::TgKill => ("tgkill", &[Int, Int, Int]),
+        LinuxSyscallNum::Accept4 => ("accept4", &[Fd, Ptr, Ptr, Hex]),
+        LinuxSyscallNum::SetRobustList => ("set_robust_list", &[Ptr, Int]),
+        LinuxSyscallNum::EpollPWait => ("epoll_pwait", &[Fd, Ptr, Int, Int, Ptr, Int]),
+        LinuxSyscallNum::TimerFdCreate => ("timerfd_create", &[Int, Hex]),
+        LinuxSyscallNum::EventFd => ("eventfd", &[Int]),
+        LinuxSyscallNum::TimerFdSetTime => ("timerfd_settime", &[Fd, Hex, Ptr, Ptr]),
+        LinuxSyscallNum::TimerFdGetTime => ("timerfd_gettime", &[Fd, Ptr]),
+        LinuxSyscallNum::EventFd2 => ("eventfd2", &[Int, Hex]),
+        LinuxSyscallNum::EpollCreate1 => ("epoll_create1", &[Hex]),
+        LinuxSyscallNum::PReadV => ("preadv", &[Fd, Ptr, Int, Int]),
+        LinuxSyscallNum::PWriteV => ("pwritev", &[Fd, Ptr, Int, Int]),
+        LinuxSyscallNum::PrLimit64 => ("prlimit64", &[Int, Int, Ptr, Ptr]),
+        LinuxSyscallNum::RenameAt2 => ("renameat2", &[Fd, Str, Fd, Str, Hex]),
+        LinuxSyscallNum::GetRandom => ("getrandom", &[Ptr, Int, Hex]),
+        LinuxSyscallNum::MemfdCreate => ("memfd_create", &[Str, Hex]),
+        LinuxSyscallNum::Statx => ("statx", &[Fd, Str, Hex, Hex, Ptr]),
+        LinuxSyscallNum::Rseq => ("rseq", &[Ptr, Int, Hex, Hex]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_args() {
+        let (name, args) = signature(LinuxSyscallNum::OpenAt);
+        let values = [LINUX_AT_FDCWD as u64, 0x1000, 0x80000, 0o644, 0, 0];
+        let call = format_args(name, args, &values, |u_ptr| {
+            assert_eq!(u_ptr, 0x1000);
+            String::from("/etc/\"hosts\"")
+        });
+        assert_eq!(
+            call,
+            "openat(AT_FDCWD, \"/etc/\\\"hosts\\\"\", 0x80000, 0o644)"
+        );
+
+        let (name, args) = signature(LinuxSyscallNum::Read);
+        let values = [3, 0, 10, 0, 0, 0];
+        assert_eq!(
+            format_args(name, args, &values, |_| unreachable!()),
+            "read(3, NULL, 10)"
+        );
+    }
+
+    #[test]
+    fn test_format_result() {
+        let call = String::from("close(3)");
+        assert_eq!(
+            format_result(call.clone(), Some(LinuxSyscallNum::Close), 0),
+            "close(3) = 0"
+        );
+        assert_eq!(
+            format_result(call.clone(), Some(LinuxSyscallNum::Close), -9_i64 as u64),
+            "close(3) = -1 EBADF"
+        );
+        assert_eq!(
+            format_result(call, None, -38_i64 as u64),
+            "close(3) = -1 ENOSYS"
+        );
+        assert_eq!(
+            format_result(
+                String::from("brk(NULL)"),
+                Some(LinuxSyscallNum::Brk),
+                0x4000
+            ),
+            "brk(NULL) = 0x4000"
+        );
+        assert_eq!(
+            format_result(
+                String::from("exit_group(0)"),
+                Some(LinuxSyscallNum::ExitGroup),
+                0
+            ),
+            "exit_group(0) = ?"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/truncate.2.html>.
+#[derive(Debug)]
+pub struct TruncateSyscall {
+    // null terminated path name
+    path: *const u8,
+    length: i64,
+}
+
+impl From<&GenericLinuxSyscall> for TruncateSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            path: syscall.arg0() as *const _,
+            length: syscall.arg1() as i64,
+        }
+    }
+}
+
+impl LinuxSyscallImpl for TruncateSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let path = read_path(process, self.path);
+        let res = check_length(self.length).and_then(|len| {
+            libfileserver::FILESYSTEM
+                .lock()
+                .truncate_file(process.pid(), &path, len)
+                .map_err(LinuxErrorCode::from)
+        });
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
+
+/// Validates the new length of a file. Shared by `truncate()` and `ftruncate()`.
+pub(super) fn check_length(length: i64) -> Result<usize, LinuxErrorCode> {
+    if length < 0 {
+        Err(LinuxErrorCode::EINVAL)
+    } else if length as u64 > libfileserver::MAX_FILE_SIZE as u64 {
+        Err(LinuxErrorCode::EFBIG)
+    } else {
+        Ok(length as usize)
+    }
+}
@@ -0,0 +1,38 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/umask.2.html>.
+/// The file system service applies the umask when the process creates files.
+#[derive(Debug)]
+pub struct UmaskSyscall {
+    mask: u64,
+}
+
+impl From<&GenericLinuxSyscall> for UmaskSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            mask: syscall.arg0(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for UmaskSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        // mode_t is 32 bits wide, but only the permission bits matter
+        let old = libfileserver::FILESYSTEM
+            .lock()
+            .set_umask(process.pid(), (self.mask & 0o777) as u16);
+        // never fails
+        LinuxSyscallResult::new_success(old as u64)
+    }
+}
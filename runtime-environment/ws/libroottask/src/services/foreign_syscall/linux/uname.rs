@@ -0,0 +1,68 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::unix_socket::write_to_user;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use crate::uname::{
+    uname,
+    MAX_FIELD_LEN,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/uname.2.html>.
+/// See [`crate::uname`] for the reported values.
+#[derive(Debug)]
+pub struct UnameSyscall {
+    u_buf: u64,
+}
+
+impl From<&GenericLinuxSyscall> for UnameSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            u_buf: syscall.arg0(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for UnameSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        let uname = uname();
+        let utsname = utsname {
+            sysname: to_field(&uname.sysname),
+            nodename: to_field(&uname.nodename),
+            release: to_field(&uname.release),
+            version: to_field(&uname.version),
+            machine: to_field(&uname.machine),
+            domainname: to_field("(none)"),
+        };
+        write_to_user(process, self.u_buf, utsname);
+        LinuxSyscallResult::new_success(0)
+    }
+}
+
+/// Copies the value into a null-terminated field. Longer values get truncated.
+fn to_field(value: &str) -> [u8; MAX_FIELD_LEN + 1] {
+    let mut field = [0; MAX_FIELD_LEN + 1];
+    let len = value.len().min(MAX_FIELD_LEN);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+    field
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone)]
+#[repr(C)]
+struct utsname {
+    sysname: [u8; MAX_FIELD_LEN + 1],
+    nodename: [u8; MAX_FIELD_LEN + 1],
+    release: [u8; MAX_FIELD_LEN + 1],
+    version: [u8; MAX_FIELD_LEN + 1],
+    machine: [u8; MAX_FIELD_LEN + 1],
+    domainname: [u8; MAX_FIELD_LEN + 1],
+}
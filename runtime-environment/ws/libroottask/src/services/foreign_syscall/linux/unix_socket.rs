@@ -0,0 +1,308 @@
+//! Common functionality of the socket syscalls. Only local sockets (`AF_UNIX`) are
+//! supported. They are implemented by the file server, see [`libfileserver::Filesystem::socket`].
+//!
+//! The file server never blocks. If an operation on a blocking socket would block, the
+//! syscall gets restarted until it succeeds, see [`restart`]. Sockets are non-blocking if
+//! they were created with `SOCK_NONBLOCK` or switched via `ioctl(FIONBIO)`. Passing file
+//! descriptors via ancillary data (`SCM_RIGHTS`) is not supported.
+
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::restart;
+use crate::services::foreign_syscall::linux::write_v::LinuxIoVec;
+use crate::services::foreign_syscall::linux::LinuxSyscallResult;
+use crate::services::MAPPED_AREAS;
+use alloc::collections::BTreeSet;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::mem::size_of;
+use libfileserver::FileDescriptor;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    SocketError,
+    SocketKind,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Address family of local sockets.
+pub(super) const AF_UNIX: u64 = 1;
+const SOCK_STREAM: u64 = 1;
+const SOCK_DGRAM: u64 = 2;
+/// The type argument of `socket` also holds flags, such as `SOCK_NONBLOCK`.
+const SOCK_TYPE_MASK: u64 = 0xf;
+/// Flag of `socket`, `socketpair`, and `accept4` for a non-blocking socket.
+pub(super) const SOCK_NONBLOCK: u64 = 0o4000;
+
+/// Local and TCP sockets of all processes that are non-blocking.
+static NONBLOCKING: SimpleMutex<BTreeSet<(ProcessId, FileDescriptor)>> =
+    SimpleMutex::new(BTreeSet::new());
+
+/// Maximum length of `sun_path` in `struct sockaddr_un`.
+const UNIX_PATH_MAX: usize = 108;
+
+/// Same as `struct sockaddr_un` of Linux.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct SockAddrUn {
+    sun_family: u16,
+    sun_path: [u8; UNIX_PATH_MAX],
+}
+
+/// Same as `struct msghdr` of Linux on x86_64.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub(super) struct MsgHdr {
+    pub(super) msg_name: u64,
+    pub(super) msg_namelen: u32,
+    pub(super) msg_iov: u64,
+    pub(super) msg_iovlen: u64,
+    pub(super) msg_control: u64,
+    pub(super) msg_controllen: u64,
+    pub(super) msg_flags: i32,
+}
+
+impl From<SocketError> for LinuxErrorCode {
+    fn from(err: SocketError) -> Self {
+        match err {
+            SocketError::BadFd => Self::EBADF,
+            SocketError::NotASocket => Self::ENOTSOCK,
+            SocketError::InvalidArgument => Self::EINVAL,
+            SocketError::AddressInUse => Self::EADDRINUSE,
+            SocketError::NotFound => Self::ENOENT,
+            SocketError::WrongKind => Self::EPROTOTYPE,
+            SocketError::ConnectionRefused => Self::ECONNREFUSED,
+            SocketError::NotConnected => Self::ENOTCONN,
+            SocketError::AlreadyConnected => Self::EISCONN,
+            SocketError::DestinationRequired => Self::EDESTADDRREQ,
+            SocketError::BrokenPipe => Self::EPIPE,
+            SocketError::WouldBlock => Self::EAGAIN,
+            SocketError::MessageTooLong => Self::EMSGSIZE,
+            SocketError::Unsupported => Self::EOPNOTSUPP,
+        }
+    }
+}
+
+/// Checks the `domain`, `type`, and `protocol` arguments of `socket` and `socketpair`.
+pub(super) fn socket_kind(
+    domain: u64,
+    sock_type: u64,
+    protocol: u64,
+) -> Result<SocketKind, LinuxErrorCode> {
+    if domain != AF_UNIX {
+        log::debug!("socket domain {} not supported", domain);
+        return Err(LinuxErrorCode::EAFNOSUPPORT);
+    }
+    if protocol != 0 {
+        return Err(LinuxErrorCode::EPROTONOSUPPORT);
+    }
+    match sock_type & SOCK_TYPE_MASK {
+        SOCK_STREAM => Ok(SocketKind::Stream),
+        SOCK_DGRAM => Ok(SocketKind::Datagram),
+        sock_type => {
+            log::debug!("socket type {} not supported", sock_type);
+            Err(LinuxErrorCode::ESOCKTNOSUPPORT)
+        }
+    }
+}
+
+/// Reads a `struct sockaddr_un` from user memory and returns the name of the socket.
+/// Names in the abstract namespace (leading null byte) get an `@` prefix instead.
+pub(super) fn read_sockaddr_un(
+    process: &Rc<Process>,
+    u_addr: u64,
+    len: u64,
+) -> Result<String, LinuxErrorCode> {
+    let len = len as usize;
+    if u_addr == 0 || len <= size_of::<u16>() || len > size_of::<SockAddrUn>() {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    let addr = read_from_user::<SockAddrUn>(process, u_addr);
+    if addr.sun_family as u64 != AF_UNIX {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+
+    let path = &addr.sun_path[..len - size_of::<u16>()];
+    let name = match path.split_first() {
+        Some((0, abstract_name)) => format!("@{}", String::from_utf8_lossy(abstract_name)),
+        _ => {
+            let len = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+            String::from_utf8_lossy(&path[..len]).into_owned()
+        }
+    };
+    if name.is_empty() {
+        // no autobind
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    Ok(name)
+}
+
+/// Writes the address of a socket as `struct sockaddr_un` to user memory. `u_len_ptr`
+/// points to the size of the user buffer and receives the size of the address. Does
+/// nothing if `u_addr` is null.
+pub(super) fn write_sockaddr_un(
+    process: &Rc<Process>,
+    u_addr: u64,
+    u_len_ptr: u64,
+    name: Option<&str>,
+) {
+    if u_addr == 0 || u_len_ptr == 0 {
+        return;
+    }
+    let u_buf_len = read_from_user::<u32>(process, u_len_ptr);
+    let addr_len = copy_sockaddr_un_to_user(process, u_addr, u_buf_len, name);
+    write_to_user(process, u_len_ptr, addr_len);
+}
+
+/// Writes the address of a socket as `struct sockaddr_un` into the user buffer of
+/// `buf_len` bytes and truncates it if necessary. Returns the size of the address.
+pub(super) fn copy_sockaddr_un_to_user(
+    process: &Rc<Process>,
+    u_addr: u64,
+    buf_len: u32,
+    name: Option<&str>,
+) -> u32 {
+    let mut addr = SockAddrUn {
+        sun_family: AF_UNIX as u16,
+        sun_path: [0; UNIX_PATH_MAX],
+    };
+    let addr_len = match name {
+        // unnamed socket
+        None => size_of::<u16>(),
+        Some(name) => match name.strip_prefix('@') {
+            // abstract names start with a null byte and are not null-terminated
+            Some(abstract_name) => {
+                let len = min(abstract_name.len(), UNIX_PATH_MAX - 1);
+                addr.sun_path[1..][..len].copy_from_slice(&abstract_name.as_bytes()[..len]);
+                size_of::<u16>() + 1 + len
+            }
+            None => {
+                let len = min(name.len(), UNIX_PATH_MAX - 1);
+                addr.sun_path[..len].copy_from_slice(&name.as_bytes()[..len]);
+                size_of::<u16>() + len + 1
+            }
+        },
+    };
+
+    let addr_bytes =
+        unsafe { core::slice::from_raw_parts((&addr as *const SockAddrUn).cast::<u8>(), addr_len) };
+    copy_to_user(
+        process,
+        u_addr,
+        &addr_bytes[..min(addr_len, buf_len as usize)],
+    );
+    addr_len as u32
+}
+
+/// Sets or clears the non-blocking mode of the local or TCP socket.
+pub(super) fn set_nonblocking(process: &Rc<Process>, fd: FileDescriptor, nonblocking: bool) {
+    let mut sockets = NONBLOCKING.lock();
+    if nonblocking {
+        sockets.insert((process.pid(), fd));
+    } else {
+        sockets.remove(&(process.pid(), fd));
+    }
+}
+
+/// Forgets the mode of the socket, if the file descriptor refers to one.
+pub(super) fn close(process: &Rc<Process>, fd: FileDescriptor) {
+    NONBLOCKING.lock().remove(&(process.pid(), fd));
+}
+
+/// Lets the syscall wait if the operation on the socket would block and the socket is
+/// blocking, i.e. returns [`LinuxErrorCode::ERESTARTSYS`], see [`restart`]. Otherwise,
+/// returns the error as it is.
+pub(super) fn wait_if_blocking(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    err: LinuxErrorCode,
+) -> LinuxErrorCode {
+    match err {
+        LinuxErrorCode::EAGAIN if !NONBLOCKING.lock().contains(&(process.pid(), fd)) => {
+            restart::restart(process, None)
+        }
+        err => err,
+    }
+}
+
+/// Sends data via the socket behind `fd`. Used by all syscalls that send data.
+pub(super) fn socket_send(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    data: &[u8],
+    dest: Option<&str>,
+) -> LinuxSyscallResult {
+    let res = libfileserver::FILESYSTEM
+        .lock()
+        .send(process.pid(), fd, data, dest);
+    match res {
+        Ok(len) => LinuxSyscallResult::new_success(len as u64),
+        Err(err) => LinuxSyscallResult::new_error(wait_if_blocking(process, fd, err.into())),
+    }
+}
+
+/// Receives at most `max_len` bytes from the socket behind `fd`. Used by all syscalls
+/// that receive data.
+pub(super) fn socket_recv(
+    process: &Rc<Process>,
+    fd: FileDescriptor,
+    max_len: usize,
+) -> Result<(Vec<u8>, Option<String>), LinuxErrorCode> {
+    let res = libfileserver::FILESYSTEM
+        .lock()
+        .recv(process.pid(), fd, max_len);
+    res.map_err(|err| wait_if_blocking(process, fd, err.into()))
+}
+
+/// Copies `len` bytes from user memory.
+pub(super) fn copy_from_user(process: &Rc<Process>, u_addr: u64, len: usize) -> Vec<u8> {
+    if len == 0 {
+        return Vec::new();
+    }
+    let mapping = MAPPED_AREAS
+        .lock()
+        .create_or_get_mapping(process, u_addr, len as u64);
+    Vec::from(mapping.mem_with_offset_as_slice::<u8>(len, (u_addr & 0xfff) as usize))
+}
+
+/// Copies data into user memory.
+pub(super) fn copy_to_user(process: &Rc<Process>, u_addr: u64, data: &[u8]) {
+    if data.is_empty() {
+        return;
+    }
+    let mapping = MAPPED_AREAS
+        .lock()
+        .create_or_get_mapping(process, u_addr, data.len() as u64);
+    let r_write_ptr = mapping.old_to_new_ptr_mut(u_addr as *mut u8);
+    unsafe {
+        core::ptr::copy_nonoverlapping(data.as_ptr(), r_write_ptr, data.len());
+    }
+}
+
+/// Reads a value of type `T` from user memory.
+pub(super) fn read_from_user<T: Copy>(process: &Rc<Process>, u_addr: u64) -> T {
+    let mapping = MAPPED_AREAS
+        .lock()
+        .create_or_get_mapping(process, u_addr, size_of::<T>() as u64);
+    let r_ptr = mapping.old_to_new_ptr(u_addr as *const u8).cast::<T>();
+    unsafe { core::ptr::read_unaligned(r_ptr) }
+}
+
+/// Writes a value of type `T` to user memory.
+pub(super) fn write_to_user<T: Copy>(process: &Rc<Process>, u_addr: u64, val: T) {
+    let mapping = MAPPED_AREAS
+        .lock()
+        .create_or_get_mapping(process, u_addr, size_of::<T>() as u64);
+    let r_ptr = mapping.old_to_new_ptr_mut(u_addr as *mut u8).cast::<T>();
+    unsafe { core::ptr::write_unaligned(r_ptr, val) }
+}
+
+/// Reads the array of `struct iovec` of `sendmsg`, `recvmsg`, and the vectored I/O
+/// syscalls from user memory.
+pub(super) fn read_iovecs(process: &Rc<Process>, u_iov: u64, count: u64) -> Vec<LinuxIoVec> {
+    (0..count)
+        .map(|i| u_iov + i * size_of::<LinuxIoVec>() as u64)
+        .map(|u_addr| read_from_user::<LinuxIoVec>(process, u_addr))
+        .collect()
+}
@@ -1,17 +1,17 @@
 use crate::process::Process;
-use crate::services::foreign_syscall::linux::consts::LINUX_PATH_MAX;
 use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path;
+use crate::services::foreign_syscall::linux::shm_fd;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
-use crate::services::MAPPED_AREAS;
+use crate::services::shm;
 use alloc::rc::Rc;
-use alloc::string::ToString;
-use libhrstd::cstr::CStr;
 use libhrstd::libhedron::UtcbDataException;
 
+/// Implementation of <https://man7.org/linux/man-pages/man2/unlink.2.html>.
 #[derive(Debug)]
 pub struct UnlinkSyscall {
     u_filename: *const u8,
@@ -31,25 +31,22 @@ impl LinuxSyscallImpl for UnlinkSyscall {
         _utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        let mapping = MAPPED_AREAS
-            .lock()
-            .create_or_get_mapping(process, self.u_filename as u64, LINUX_PATH_MAX as u64)
-            .clone();
-
-        let u_page_offset = self.u_filename as usize & 0xfff;
-        let filename = mapping.mem_with_offset_as_slice::<u8>(LINUX_PATH_MAX, u_page_offset);
-        let filename = CStr::try_from(filename).unwrap();
-        // remove null bytes
-        let filename = filename.as_str().trim_matches('\0').to_string();
-
-        if libfileserver::FILESYSTEM
-            .lock()
-            .unlink_file(process.pid(), &filename)
-            .is_ok()
-        {
-            LinuxSyscallResult::new_success(0)
-        } else {
-            LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL)
+        let filename = read_path(process, self.u_filename);
+        match unlink(process, &filename) {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
         }
     }
 }
+
+/// Removes the file at `pathname`. Shared by `unlink()` and `unlinkat()`. For the path of
+/// a named shared memory segment, see [`shm_fd`], this removes the name of the segment.
+pub(super) fn unlink(process: &Process, pathname: &str) -> Result<(), LinuxErrorCode> {
+    if let Some(name) = shm_fd::shm_name(pathname) {
+        return shm::unlink(process.pid(), name).map_err(LinuxErrorCode::from);
+    }
+    libfileserver::FILESYSTEM
+        .lock()
+        .unlink_file(process.pid(), pathname)
+        .map_err(LinuxErrorCode::from)
+}
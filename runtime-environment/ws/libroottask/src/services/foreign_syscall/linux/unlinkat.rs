@@ -0,0 +1,57 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_AT_REMOVEDIR;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::path::read_path_at;
+use crate::services::foreign_syscall::linux::rmdir::rmdir;
+use crate::services::foreign_syscall::linux::unlink::unlink;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/unlinkat.2.html>. musl
+/// implements `remove()` with it. With `AT_REMOVEDIR`, it is `rmdir()`. Like for
+/// `faccessat()`, relative paths are only supported together with `AT_FDCWD`.
+#[derive(Debug)]
+pub struct UnlinkAtSyscall {
+    dirfd: i32,
+    // null terminated path name
+    pathname: *const u8,
+    flags: u64,
+}
+
+impl From<&GenericLinuxSyscall> for UnlinkAtSyscall {
+    fn from(syscall: &GenericLinuxSyscall) -> Self {
+        Self {
+            dirfd: syscall.arg0() as i32,
+            pathname: syscall.arg1() as *const _,
+            flags: syscall.arg2(),
+        }
+    }
+}
+
+impl LinuxSyscallImpl for UnlinkAtSyscall {
+    fn handle(
+        &self,
+        _utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        if self.flags & !LINUX_AT_REMOVEDIR != 0 {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL);
+        }
+        let res = read_path_at(process, self.dirfd, self.pathname).and_then(|pathname| {
+            if self.flags & LINUX_AT_REMOVEDIR != 0 {
+                rmdir(process, &pathname)
+            } else {
+                unlink(process, &pathname)
+            }
+        });
+        match res {
+            Ok(()) => LinuxSyscallResult::new_success(0),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
+    }
+}
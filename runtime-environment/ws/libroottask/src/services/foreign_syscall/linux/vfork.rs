@@ -0,0 +1,30 @@
+use crate::process::Process;
+use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::spawn::vfork;
+use crate::services::foreign_syscall::linux::{
+    LinuxSyscallImpl,
+    LinuxSyscallResult,
+};
+use alloc::rc::Rc;
+use libhrstd::libhedron::UtcbDataException;
+
+/// Implementation of <https://man7.org/linux/man-pages/man2/vfork.2.html>. The child must
+/// call `execve()` or `_exit()` right away, see [`crate::services::foreign_syscall::linux::spawn`].
+#[derive(Debug)]
+pub struct VforkSyscall;
+
+impl From<&GenericLinuxSyscall> for VforkSyscall {
+    fn from(_syscall: &GenericLinuxSyscall) -> Self {
+        Self
+    }
+}
+
+impl LinuxSyscallImpl for VforkSyscall {
+    fn handle(
+        &self,
+        utcb_exc: &mut UtcbDataException,
+        process: &Rc<Process>,
+    ) -> LinuxSyscallResult {
+        vfork(utcb_exc, process, 0)
+    }
+}
@@ -1,5 +1,13 @@
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
+use crate::services::foreign_syscall::linux::event_fd;
+use crate::services::foreign_syscall::linux::fd_events::{
+    fd_kind,
+    FdKind,
+};
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::is_inet_socket;
+use crate::services::foreign_syscall::linux::sendto::SendToSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
@@ -7,7 +15,6 @@ use crate::services::foreign_syscall::linux::{
 use crate::services::MAPPED_AREAS;
 use alloc::rc::Rc;
 use core::fmt::Write;
-use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::UtcbDataException;
 use libhrstd::mem::PageAlignedBuf;
 
@@ -51,9 +58,28 @@ impl WriteSyscall {
 impl LinuxSyscallImpl for WriteSyscall {
     fn handle(
         &self,
-        _utcb_exc: &mut UtcbDataException,
+        utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
+        match fd_kind(process, self.fd.into()) {
+            Some(FdKind::EventFd) => {
+                return event_fd::write(process, self.fd.into(), self.usr_ptr as u64, self.count)
+            }
+            Some(FdKind::TimerFd | FdKind::Shm) => {
+                return LinuxSyscallResult::new_error(LinuxErrorCode::EINVAL)
+            }
+            _ => {}
+        }
+        if self.fd > 2
+            && (is_inet_socket(process, self.fd.into())
+                || libfileserver::FILESYSTEM
+                    .lock()
+                    .is_socket(process.pid(), self.fd.into()))
+        {
+            return SendToSyscall::new(self.fd.into(), self.usr_ptr as u64, self.count)
+                .handle(utcb_exc, process);
+        }
+
         // either create mapping or re-use if the page is already mapped
         let mapping = MAPPED_AREAS
             .lock()
@@ -62,30 +88,17 @@ impl LinuxSyscallImpl for WriteSyscall {
         let u_page_offset = self.usr_ptr as usize & 0xfff;
         let u_write_data = mapping.mem_with_offset_as_slice::<u8>(self.count, u_page_offset);
 
-        log::trace!(
-            "write: fd={}, u_page_offset={}, count={}, page_count={}",
-            self.fd,
-            u_page_offset,
-            self.count,
-            if self.count % PAGE_SIZE == 0 {
-                self.count / PAGE_SIZE
-            } else {
-                (self.count / PAGE_SIZE) + 1
-            }
-        );
-
         match self.fd {
             0 => panic!("write to stdin currently not supported"),
             1 | 2 => {
-                let r_cstr = core::str::from_utf8(u_write_data).unwrap();
+                // the program may split a code point across multiple writes
+                let mut decoder = process.console_decoder_mut(self.fd);
                 if self.fd == 1 {
-                    crate::services::stdout::writer_mut()
-                        .write_str(r_cstr)
-                        .unwrap();
+                    let mut writer = crate::services::stdout::writer_mut();
+                    decoder.decode(u_write_data, |text| writer.write_str(text).unwrap());
                 } else {
-                    crate::services::stderr::writer_mut()
-                        .write_str(r_cstr)
-                        .unwrap();
+                    let mut writer = crate::services::stderr::writer_mut();
+                    decoder.decode(u_write_data, |text| writer.write_str(text).unwrap());
                 }
 
                 LinuxSyscallResult::new_success(self.count as u64)
@@ -101,13 +114,15 @@ impl LinuxSyscallImpl for WriteSyscall {
                     let _ = core::ptr::read_volatile(SIMULATED_WRITE_WINDOW.as_ptr());
                 }
 
-                let written_bytes = libfileserver::FILESYSTEM
-                    .lock()
-                    .write_file(process.pid(), (fd as u64).into(), unsafe {
-                        &SIMULATED_WRITE_WINDOW[0..u_write_data.len()]
-                    })
-                    .unwrap();
-                LinuxSyscallResult::new_success(written_bytes as u64)
+                let written_bytes = libfileserver::FILESYSTEM.lock().write_file(
+                    process.pid(),
+                    (fd as u64).into(),
+                    unsafe { &SIMULATED_WRITE_WINDOW[0..u_write_data.len()] },
+                );
+                match written_bytes {
+                    Ok(written_bytes) => LinuxSyscallResult::new_success(written_bytes as u64),
+                    Err(err) => LinuxSyscallResult::new_error(err.into()),
+                }
             }
         }
     }
@@ -1,33 +1,37 @@
-use crate::mem::VIRT_MEM_ALLOC;
 use crate::process::Process;
+use crate::services::foreign_syscall::linux::consts::LINUX_IOV_MAX;
+use crate::services::foreign_syscall::linux::error_code::LinuxErrorCode;
 use crate::services::foreign_syscall::linux::generic::GenericLinuxSyscall;
+use crate::services::foreign_syscall::linux::inet_socket::is_inet_socket;
+use crate::services::foreign_syscall::linux::unix_socket::read_iovecs;
 use crate::services::foreign_syscall::linux::write::WriteSyscall;
 use crate::services::foreign_syscall::linux::{
     LinuxSyscallImpl,
     LinuxSyscallResult,
 };
+use crate::services::MAPPED_AREAS;
 use alloc::rc::Rc;
-use core::alloc::Layout;
-use libhrstd::libhedron::mem::PAGE_SIZE;
-use libhrstd::libhedron::MemCapPermissions;
+use alloc::vec::Vec;
+use libfileserver::FileDescriptor;
 use libhrstd::libhedron::UtcbDataException;
-use libhrstd::mem::calc_page_count;
-use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
 
+/// Implementation of <https://man7.org/linux/man-pages/man2/writev.2.html>. musl flushes
+/// the buffers of stdio with it. Files get all buffers in a single request to the file
+/// system, see [`write_vectored`].
 #[derive(Debug)]
 pub struct WriteVSyscall {
     fd: u64,
-    usr_ptr: *const LinuxIoVec,
+    u_iov: u64,
     // number of io vecs
-    count: usize,
+    count: u64,
 }
 
 impl From<&GenericLinuxSyscall> for WriteVSyscall {
     fn from(syscall: &GenericLinuxSyscall) -> Self {
         Self {
             fd: syscall.arg0(),
-            usr_ptr: syscall.arg1() as _,
-            count: syscall.arg2() as _,
+            u_iov: syscall.arg1(),
+            count: syscall.arg2(),
         }
     }
 }
@@ -38,50 +42,109 @@ impl LinuxSyscallImpl for WriteVSyscall {
         utcb_exc: &mut UtcbDataException,
         process: &Rc<Process>,
     ) -> LinuxSyscallResult {
-        // first: map the iovec itself
-        let u_iovec_page_offset = self.usr_ptr as usize & 0xfff;
-        let u_iovec_total_len = core::mem::size_of::<LinuxIoVec>() * self.count;
-        let r_mapping_size = u_iovec_page_offset + u_iovec_total_len;
-        let r_mapping_pages = calc_page_count(r_mapping_size);
-
-        let r_iovec_mapping_dest = VIRT_MEM_ALLOC
-            .lock()
-            .next_addr(Layout::from_size_align(r_mapping_size, PAGE_SIZE).unwrap());
-        CrdDelegateOptimizer::new(
-            self.usr_ptr as u64 / PAGE_SIZE as u64,
-            r_iovec_mapping_dest / PAGE_SIZE as u64,
-            r_mapping_pages,
-        )
-        .mmap(
-            process.pd_obj().cap_sel(),
-            process.parent().unwrap().pd_obj().cap_sel(),
-            MemCapPermissions::READ,
-        );
-
-        let r_mapping_begin_ptr = r_iovec_mapping_dest as *const u8;
-        let r_iovec_begin_ptr = unsafe {
-            r_mapping_begin_ptr
-                .add(u_iovec_page_offset)
-                .cast::<LinuxIoVec>()
-        };
-        let r_iovec = unsafe { core::slice::from_raw_parts(r_iovec_begin_ptr, self.count) };
-
-        // I reuse the functionality of the write system call for every IO VEC
-        let bytes_written = r_iovec
-            .iter()
-            .map(|x| WriteSyscall::new(self.fd, x.u_iov_base, x.len as usize))
-            .map(|x| x.handle(utcb_exc, process))
-            .map(|x| x.val())
-            .sum();
-
-        LinuxSyscallResult::new_success(bytes_written)
+        match checked_iovecs(process, self.u_iov, self.count) {
+            Ok(iovecs) => write_vectored(utcb_exc, process, self.fd, None, &iovecs),
+            Err(err) => LinuxSyscallResult::new_error(err),
+        }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 #[repr(C)]
-struct LinuxIoVec {
+pub(super) struct LinuxIoVec {
     /// User address.
-    u_iov_base: *const u8,
-    len: u64,
+    pub(super) u_iov_base: *const u8,
+    pub(super) len: u64,
+}
+
+/// Reads the array of `struct iovec` of the vectored I/O syscalls from user memory. Fails
+/// with `EINVAL` if there are more than [`LINUX_IOV_MAX`] or if the total length
+/// overflows, like on Linux.
+pub(super) fn checked_iovecs(
+    process: &Rc<Process>,
+    u_iov: u64,
+    count: u64,
+) -> Result<Vec<LinuxIoVec>, LinuxErrorCode> {
+    if count > LINUX_IOV_MAX {
+        return Err(LinuxErrorCode::EINVAL);
+    }
+    let iovecs = read_iovecs(process, u_iov, count);
+    iovecs
+        .iter()
+        .try_fold(0_u64, |total, iovec| total.checked_add(iovec.len))
+        .filter(|total| *total <= isize::MAX as u64)
+        .ok_or(LinuxErrorCode::EINVAL)?;
+    Ok(iovecs)
+}
+
+/// Writes the buffers one after another, at `offset` if set. Shared by `writev()`,
+/// `pwritev()`, and `pwrite64()`. A file gets all buffers in a single request to the file
+/// system, which writes them without concatenating them first. The console and sockets,
+/// which have no offset (`ESPIPE`), get each buffer on its own.
+pub(super) fn write_vectored(
+    utcb_exc: &mut UtcbDataException,
+    process: &Rc<Process>,
+    fd: u64,
+    offset: Option<usize>,
+    iovecs: &[LinuxIoVec],
+) -> LinuxSyscallResult {
+    let fd_obj = FileDescriptor::new(fd);
+    let is_stream = fd <= 2
+        || is_inet_socket(process, fd_obj)
+        || libfileserver::FILESYSTEM
+            .lock()
+            .is_socket(process.pid(), fd_obj);
+    if is_stream {
+        if offset.is_some() {
+            return LinuxSyscallResult::new_error(LinuxErrorCode::ESPIPE);
+        }
+        let mut bytes_written = 0;
+        for iovec in iovecs {
+            let res = WriteSyscall::new(fd, iovec.u_iov_base, iovec.len as usize)
+                .handle(utcb_exc, process);
+            let rax = res.val();
+            let count = rax as i64;
+            if count < 0 {
+                // like Linux, an error after some written bytes only shortens the write
+                return if bytes_written > 0 {
+                    LinuxSyscallResult::new_success(bytes_written)
+                } else {
+                    LinuxSyscallResult::new_raw(rax)
+                };
+            }
+            bytes_written += count as u64;
+            if (count as u64) < iovec.len {
+                break;
+            }
+        }
+        return LinuxSyscallResult::new_success(bytes_written);
+    }
+
+    let mappings = iovecs
+        .iter()
+        .filter(|iovec| iovec.len > 0)
+        .map(|iovec| {
+            let mapping = MAPPED_AREAS.lock().create_or_get_mapping(
+                process,
+                iovec.u_iov_base as u64,
+                iovec.len,
+            );
+            (mapping, iovec)
+        })
+        .collect::<Vec<_>>();
+    let bufs = mappings
+        .iter()
+        .map(|(mapping, iovec)| {
+            let u_page_offset = iovec.u_iov_base as usize & 0xfff;
+            mapping.mem_with_offset_as_slice::<u8>(iovec.len as usize, u_page_offset)
+        })
+        .collect::<Vec<_>>();
+    let res =
+        libfileserver::FILESYSTEM
+            .lock()
+            .write_file_vectored(process.pid(), fd_obj, offset, &bufs);
+    match res {
+        Ok(bytes_written) => LinuxSyscallResult::new_success(bytes_written as u64),
+        Err(err) => LinuxSyscallResult::new_error(err.into()),
+    }
 }
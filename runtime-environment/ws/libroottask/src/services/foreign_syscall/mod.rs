@@ -1,24 +1,65 @@
 //! Module is responsible for providing the service to handle foreign syscalls.
-use crate::process::Process;
-use crate::process::SyscallAbi;
-use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::process::{
+    is_syscall_traced,
+    process_cap_sels,
+    record_syscall,
+    Process,
+    SyscallAbi,
+};
+use crate::pt_multiplex::{
+    delay_reply,
+    roottask_generic_portal_callback,
+};
 use crate::services::foreign_syscall::linux::GenericLinuxSyscall;
-use crate::services::LOCAL_EC;
+use crate::services::LOCAL_ECS;
+use crate::smp;
 use alloc::rc::Rc;
 use libhrstd::cap_space::root::RootCapSpace;
 use libhrstd::cap_space::user::ForeignUserAppCapSpace;
 use libhrstd::kobjects::{
     PtCtx,
     PtObject,
+    SmObject,
 };
-use libhrstd::libhedron::consts::NUM_CPUS;
 use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
+use libhrstd::sync::mutex::SimpleMutex;
 
 mod linux;
 
+pub use linux::release_process;
+
+/// Semaphore that is never signaled. Down operations with a timeout on it put the local EC
+/// of the foreign syscall handler to sleep without burning CPU cycles.
+static SLEEP_SM: SimpleMutex<Option<Rc<SmObject>>> = SimpleMutex::new(None);
+
+/// Creates the semaphore used by [`sleep_until`] and registers the exception handlers
+/// that deliver signals. Call once during initialization of the services.
+pub fn init(root: &Process) {
+    linux::register_signal_exc_handlers();
+
+    let mut sm_lock = SLEEP_SM.lock();
+    assert!(sm_lock.is_none(), "init only allowed once!");
+    sm_lock.replace(SmObject::create(
+        RootCapSpace::RootSmForeignSleep.val(),
+        &root.pd_obj(),
+    ));
+}
+
+/// Blocks the current EC until the TSC reaches `tsc_deadline`. Returns immediately if
+/// the deadline is already in the past. Must not be called while the lock of the process
+/// manager is held; handlers use [`crate::pt_multiplex::delay_reply`] instead.
+pub fn sleep_until(tsc_deadline: u64) {
+    if tsc_deadline <= crate::time::tsc_now() {
+        return;
+    }
+    let sm = SLEEP_SM.lock().as_ref().expect("call init first").clone();
+    let acquired = sm.sem_down_timeout(tsc_deadline);
+    debug_assert!(!acquired, "nobody should up the sleep semaphore");
+}
+
 pub fn handle_foreign_syscall(
-    _pt: &Rc<PtObject>,
+    pt: &Rc<PtObject>,
     process: &Rc<Process>,
     utcb: &mut Utcb,
     do_reply: &mut bool,
@@ -35,33 +76,65 @@ pub fn handle_foreign_syscall(
     let original_rsp = utcb.exception_data().r11;
     // ####################################################
 
+    // Set before the syscall gets handled, because some syscalls (i.e. `rt_sigreturn`)
+    // restore a different state of the process.
+    utcb.exception_data_mut().rip = next_rip;
+    utcb.exception_data_mut().rsp = original_rsp;
+    // ####################################################
+
     match process.syscall_abi() {
-        // syscall implementations may not change these values
         SyscallAbi::Linux => {
             // EMULATE COSTS OF AN ADDITIONAL CHEAP IPC CALL AS DISCUSSED WITH NILS
             // THIS IS SIMILAR TO A MEDIATOR LIBRARY LINKED NEXT TO FOREIGN APPLICATIONS
             // DURING RUNTIME.
-            libhrstd::libhedron::syscall::sys_call(RootCapSpace::RootRawEchoServicePt.val())
-                .unwrap();
+            {
+                // the reply of the nested call overwrites the UTCB
+                let _utcb_guard = utcb.save();
+                // portals can only be called on the CPU of their local EC
+                let raw_echo_pt_sel =
+                    RootCapSpace::calc_raw_echo_service_pt_sel(pt.local_ec().cpu());
+                libhrstd::libhedron::syscall::sys_call(raw_echo_pt_sel).unwrap();
+            }
             // EMULATE COSTS END.
-            let syscall = GenericLinuxSyscall::try_from(utcb.exception_data()).unwrap();
-            log::trace!("linux syscall: {:?}", syscall.syscall_num());
-            syscall.handle(utcb.exception_data_mut(), process);
+            let traced = is_syscall_traced(process.pid());
+            let syscall_num = utcb.exception_data().rax;
+            match GenericLinuxSyscall::try_from(utcb.exception_data()) {
+                Ok(syscall) => {
+                    let call = traced.then(|| linux::format_call(&syscall, process));
+                    syscall.handle(utcb.exception_data_mut(), process);
+                    let restart =
+                        linux::restart_if_waiting(utcb.exception_data_mut(), process, syscall_num);
+                    if let Some(tsc_deadline) = restart {
+                        // the trace shows the syscall once it's done
+                        delay_reply(pt, tsc_deadline);
+                    } else if let Some(call) = call {
+                        let rax = utcb.exception_data().rax;
+                        let line = linux::format_result(call, Some(syscall.syscall_num()), rax);
+                        record_syscall(process.pid(), line);
+                    }
+                }
+                Err(()) => {
+                    let call = traced.then(|| linux::format_unknown_call(utcb.exception_data()));
+                    linux::reject_unknown_syscall(utcb.exception_data_mut());
+                    if let Some(call) = call {
+                        let rax = utcb.exception_data().rax;
+                        record_syscall(process.pid(), linux::format_result(call, None, rax));
+                    }
+                }
+            }
+            linux::deliver_pending_signal(utcb.exception_data_mut(), process);
         }
         _ => panic!("not implemented syscall ABI {:?}", process.syscall_abi()),
     }
 
-    // ### GENERIC PART: same for all foreign syscalls ###
-    utcb.exception_data_mut().rip = next_rip;
-    utcb.exception_data_mut().rsp = original_rsp;
-    // ####################################################
-
     log::trace!("outgoing MTD: {:?}", utcb.exception_data().mtd);
 
     *do_reply = true;
 }
 
-/// Creates the syscall handler PTs. The PD of a process gets `NUM_CPU` PTs.
+/// Creates the syscall handler PTs. The PD of a process gets one PT per online CPU at
+/// `SyscallBasePt + CPU`; Hedron uses the PT of the CPU where the syscall happens. Each PT
+/// is bound to the service local EC of its CPU.
 pub fn create_and_delegate_syscall_handler_pts(process: &Process) {
     log::debug!(
         "creating syscall handler PTs for process {}, {}",
@@ -69,17 +142,18 @@ pub fn create_and_delegate_syscall_handler_pts(process: &Process) {
         process.name()
     );
 
-    let base_sel = RootCapSpace::calc_foreign_syscall_pt_sel_base(process.pid());
+    let base_sel = process_cap_sels(process.pid())
+        .expect("running processes have capability selectors")
+        .foreign_syscall_pt_base();
 
-    // local EC for all service calls
-    let ec_lock = LOCAL_EC.lock();
-    let ec_lock = ec_lock.as_ref().unwrap();
+    // local ECs for all service calls
+    let ec_lock = LOCAL_ECS.lock();
 
-    for cpu in 0..NUM_CPUS as u64 {
+    for cpu in smp::online_cpus_iter() {
         let cap_sel = base_sel + cpu;
         let pt = PtObject::create(
             cap_sel,
-            ec_lock,
+            ec_lock.get(&cpu).unwrap(),
             // Julian: Niemals FPU hier; viel schneller und das wird nur für vCPUs benötigt
             Mtd::DEFAULT,
             roottask_generic_portal_callback,
@@ -0,0 +1,15 @@
+use crate::process::Process;
+use libhrstd::rt::services::fs::{
+    FsAccessRequest,
+    FsAccessResponse,
+};
+
+/// Implements the fs access service functionality that is accessible via the FS portal.
+pub(super) fn fs_service_impl_access(
+    request: &FsAccessRequest,
+    process: &Process,
+) -> FsAccessResponse {
+    libfileserver::FILESYSTEM
+        .lock()
+        .access(process.pid(), request.path(), request.mode())
+}
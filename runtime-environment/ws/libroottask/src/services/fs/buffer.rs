@@ -0,0 +1,141 @@
+//! Fixed buffers that processes register at the file system service, see
+//! [`libhrstd::rt::services::fs::fs_register_buffer`]. The roottask maps each buffer once
+//! into its address space and keeps the mapping. Read and write requests that refer to a
+//! registered buffer access this mapping directly.
+
+use crate::mem::VIRT_MEM_ALLOC;
+use crate::process::Process;
+use alloc::collections::BTreeMap;
+use core::alloc::Layout;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::MemCapPermissions;
+use libhrstd::mem::calc_page_count;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    FsBufferError,
+    FsBufferId,
+    FsBufferRef,
+    FsBufferResponse,
+    FsRegisterBufferRequest,
+    FS_MAX_REGISTERED_BUFFERS,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::delegation::DelegationBuilder;
+
+/// The registered buffers of all processes.
+static FS_BUFFERS: SimpleMutex<BTreeMap<ProcessId, BTreeMap<FsBufferId, RegisteredBuffer>>> =
+    SimpleMutex::new(BTreeMap::new());
+
+/// A registered buffer, mapped into the roottask.
+#[derive(Debug)]
+struct RegisteredBuffer {
+    /// Address of the first byte of the buffer in the address space of the roottask.
+    r_addr: u64,
+    /// Length of the buffer in bytes.
+    len: usize,
+}
+
+/// Implements the fs register buffer functionality that is accessible via the FS portal.
+pub(super) fn fs_service_impl_register_buffer(
+    request: &FsRegisterBufferRequest,
+    process: &Process,
+) -> FsBufferResponse {
+    register_buffer(request, process)
+}
+
+/// Implements the fs unregister buffer functionality that is accessible via the FS portal.
+pub(super) fn fs_service_impl_unregister_buffer(
+    id: FsBufferId,
+    process: &Process,
+) -> Result<(), FsBufferError> {
+    FS_BUFFERS
+        .lock()
+        .get_mut(&process.pid())
+        .and_then(|buffers| buffers.remove(&id))
+        .map(|_| ())
+        .ok_or(FsBufferError::UnknownBuffer)
+}
+
+/// Maps the buffer of the request into the roottask and assigns it the lowest free ID.
+fn register_buffer(
+    request: &FsRegisterBufferRequest,
+    process: &Process,
+) -> Result<FsBufferId, FsBufferError> {
+    if request.len() == 0 {
+        return Err(FsBufferError::Empty);
+    }
+    let mut all_buffers = FS_BUFFERS.lock();
+    let buffers = all_buffers.entry(process.pid()).or_default();
+    let id = (0..FS_MAX_REGISTERED_BUFFERS as u32)
+        .map(FsBufferId::new)
+        .find(|id| !buffers.contains_key(id))
+        .ok_or(FsBufferError::TooManyBuffers)?;
+
+    let u_addr = request.user_ptr();
+    let u_addr_page_offset = u_addr & 0xfff;
+    let u_page_num = u_addr / PAGE_SIZE;
+    let required_bytes = u_addr_page_offset + request.len();
+    let page_count = calc_page_count(required_bytes);
+
+    // get virt address to map the user memory into the roottask
+    let r_mapping_addr = VIRT_MEM_ALLOC
+        .lock()
+        .next_addr(Layout::from_size_align(required_bytes, PAGE_SIZE).unwrap());
+
+    // map memory from user app into root task
+    let u_page_addr = (u_page_num * PAGE_SIZE) as u64;
+    DelegationBuilder::mem(u_page_addr..u_page_addr + (page_count * PAGE_SIZE) as u64)
+        .perms(MemCapPermissions::RW)
+        .from(process.pd_obj().cap_sel())
+        .at(r_mapping_addr)
+        .to(process.parent().unwrap().pd_obj().cap_sel())
+        .unwrap();
+
+    log::debug!(
+        "pid={} registered fs buffer {} with {} bytes at {:#x}",
+        process.pid(),
+        id.raw(),
+        request.len(),
+        u_addr
+    );
+    buffers.insert(
+        id,
+        RegisteredBuffer {
+            r_addr: r_mapping_addr + u_addr_page_offset as u64,
+            len: request.len(),
+        },
+    );
+    Ok(id)
+}
+
+/// Calls `f` with the `count` bytes of a registered buffer of the process that `buffer`
+/// refers to.
+pub(super) fn with_registered_buffer<R>(
+    pid: ProcessId,
+    buffer: FsBufferRef,
+    count: usize,
+    f: impl FnOnce(&mut [u8]) -> R,
+) -> Result<R, FsBufferError> {
+    let buffers = FS_BUFFERS.lock();
+    let registered = buffers
+        .get(&pid)
+        .and_then(|buffers| buffers.get(&buffer.id()))
+        .ok_or(FsBufferError::UnknownBuffer)?;
+    let end = buffer
+        .offset()
+        .checked_add(count)
+        .ok_or(FsBufferError::OutOfBounds)?;
+    if end > registered.len {
+        return Err(FsBufferError::OutOfBounds);
+    }
+    let data = unsafe {
+        core::slice::from_raw_parts_mut((registered.r_addr as *mut u8).add(buffer.offset()), count)
+    };
+    Ok(f(data))
+}
+
+/// Removes the registered buffers of an exited process. Like the mappings of single
+/// requests, the mappings in the roottask stay.
+pub fn unregister_fs_buffers(pid: ProcessId) {
+    FS_BUFFERS.lock().remove(&pid);
+}
@@ -1,11 +1,20 @@
 use crate::process::Process;
-use libhrstd::libhedron::Utcb;
-use libhrstd::rt::services::fs::FsCloseRequest;
+use crate::services::network;
+use libhrstd::rt::services::fs::{
+    FsCloseRequest,
+    FsError,
+};
 
 /// Implements the fs close service functionality that is accessible via the FS portal.
-pub(super) fn fs_service_impl_close(request: &FsCloseRequest, _utcb: &mut Utcb, process: &Process) {
-    libfileserver::FILESYSTEM
+pub(super) fn fs_service_impl_close(
+    request: &FsCloseRequest,
+    process: &Process,
+) -> Result<(), FsError> {
+    let fd = (request.fd().raw() as u64).into();
+    let res = libfileserver::FILESYSTEM
         .lock()
-        .close_file(process.pid(), (request.fd().raw() as u64).into())
-        .unwrap();
+        .close_file(process.pid(), fd);
+    // the file descriptor may belong to a UDP or TCP socket
+    network::close_socket(process.pid(), fd);
+    res
 }
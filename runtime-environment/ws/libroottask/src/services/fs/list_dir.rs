@@ -0,0 +1,20 @@
+use crate::process::Process;
+use libhrstd::libhedron::UTCB_DATA_CAPACITY;
+use libhrstd::rt::services::fs::{
+    FsListDirRequest,
+    FsListDirResponse,
+};
+
+/// Implements the fs list dir service functionality that is accessible via the FS portal.
+/// Skips the paths that the caller already received and replies with as many of the
+/// remaining paths as fit into the UTCB.
+pub(super) fn fs_service_impl_list_dir(
+    request: &FsListDirRequest,
+    process: &Process,
+) -> FsListDirResponse {
+    let mut paths = libfileserver::FILESYSTEM
+        .lock()
+        .list_dir(process.pid(), request.dir());
+    paths.drain(..request.skip().min(paths.len()));
+    FsListDirResponse::new_fitting(paths, UTCB_DATA_CAPACITY)
+}
@@ -1,9 +1,14 @@
 use crate::process::Process;
-use libhrstd::libhedron::Utcb;
-use libhrstd::rt::services::fs::FsLseekRequest;
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsLseekRequest,
+};
 
 /// Implements the fs lseek service functionality that is accessible via the FS portal.
-pub(super) fn fs_service_impl_lseek(request: &FsLseekRequest, _utcb: &mut Utcb, process: &Process) {
+pub(super) fn fs_service_impl_lseek(
+    request: &FsLseekRequest,
+    process: &Process,
+) -> Result<(), FsError> {
     libfileserver::FILESYSTEM
         .lock()
         .lseek_file(
@@ -11,5 +16,5 @@ pub(super) fn fs_service_impl_lseek(request: &FsLseekRequest, _utcb: &mut Utcb,
             (request.fd().raw() as u64).into(),
             request.offset() as usize,
         )
-        .unwrap();
+        .map(|_| ())
 }
@@ -1,18 +1,33 @@
 //! The in memory file system service currently lives inside the roottask.
 //! This module connects the callable service portal with the actual functionality.
 
+mod access;
+mod buffer;
 mod close;
+mod list_dir;
 mod lseek;
 mod open;
 mod read;
+mod ring;
+mod socket;
+mod umask;
 mod write;
 
 use crate::process::Process;
 use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::services::fs::access::fs_service_impl_access;
+use crate::services::fs::buffer::{
+    fs_service_impl_register_buffer,
+    fs_service_impl_unregister_buffer,
+};
 use crate::services::fs::close::fs_service_impl_close;
+use crate::services::fs::list_dir::fs_service_impl_list_dir;
 use crate::services::fs::lseek::fs_service_impl_lseek;
 use crate::services::fs::open::fs_service_impl_open;
 use crate::services::fs::read::fs_service_impl_read;
+use crate::services::fs::ring::fs_service_impl_ring_setup;
+use crate::services::fs::socket::fs_service_impl_socket;
+use crate::services::fs::umask::fs_service_impl_umask;
 use crate::services::fs::write::fs_service_impl_write;
 use alloc::rc::Rc;
 use libhrstd::kobjects::{
@@ -23,9 +38,20 @@ use libhrstd::kobjects::{
 use libhrstd::libhedron::CapSel;
 use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
-use libhrstd::rt::services::fs::FsServiceRequest;
+use libhrstd::rt::services::fs::{
+    FsRingSetupRequest,
+    FsService,
+    FsServiceRequest,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
 use libhrstd::service_ids::ServiceId;
 
+pub use buffer::unregister_fs_buffers;
+pub use ring::{
+    process_fs_rings,
+    unregister_fs_ring,
+};
+
 /// Creates a new FILE SYSTEM service PT, which can be delegated to a new process.
 pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
     let service = ServiceId::FileSystemService;
@@ -48,11 +74,44 @@ pub fn fs_service_handler(
 ) {
     let file_server_request = utcb.load_data::<FsServiceRequest>().unwrap();
     match file_server_request {
-        FsServiceRequest::Open(request) => fs_service_impl_open(&request, utcb, process),
-        FsServiceRequest::Read(request) => fs_service_impl_read(&request, utcb, process),
-        FsServiceRequest::Write(request) => fs_service_impl_write(&request, utcb, process),
-        FsServiceRequest::Close(request) => fs_service_impl_close(&request, utcb, process),
-        FsServiceRequest::LSeek(request) => fs_service_impl_lseek(&request, utcb, process),
+        FsServiceRequest::Open(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_open(&r, process))
+        }
+        FsServiceRequest::Read(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_read(&r, process))
+        }
+        FsServiceRequest::Write(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_write(&r, process))
+        }
+        FsServiceRequest::Close(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_close(&r, process))
+        }
+        FsServiceRequest::LSeek(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_lseek(&r, process))
+        }
+        FsServiceRequest::ListDir(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_list_dir(&r, process))
+        }
+        FsServiceRequest::Socket(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_socket(&r, process))
+        }
+        FsServiceRequest::RingSetup => rpc_serve::<FsService, _>(FsRingSetupRequest, utcb, |_| {
+            fs_service_impl_ring_setup(process)
+        }),
+        FsServiceRequest::Umask(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_umask(&r, process))
+        }
+        FsServiceRequest::RegisterBuffer(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| {
+                fs_service_impl_register_buffer(&r, process)
+            })
+        }
+        FsServiceRequest::UnregisterBuffer(id) => rpc_serve::<FsService, _>(id, utcb, |id| {
+            fs_service_impl_unregister_buffer(id, process)
+        }),
+        FsServiceRequest::Access(request) => {
+            rpc_serve::<FsService, _>(request, utcb, |r| fs_service_impl_access(&r, process))
+        }
     }
 
     *do_reply = true;
@@ -1,35 +1,45 @@
 use crate::mem::VIRT_MEM_ALLOC;
 use crate::process::Process;
+use crate::services::fs::buffer::with_registered_buffer;
 use core::alloc::Layout;
 use libhrstd::libhedron::mem::PAGE_SIZE;
-use libhrstd::libhedron::{
-    MemCapPermissions,
-    Utcb,
-};
+use libhrstd::libhedron::MemCapPermissions;
 use libhrstd::mem::calc_page_count;
-use libhrstd::rt::services::fs::FsReadRequest;
-use libhrstd::util::crd_delegate_optimizer::CrdDelegateOptimizer;
+use libhrstd::rt::services::fs::{
+    FsBufferRef,
+    FsError,
+    FsReadDest,
+    FsReadRequest,
+};
+use libhrstd::util::delegation::DelegationBuilder;
 
 /// Implements the fs read service functionality that is accessible via the FS portal.
-pub(super) fn fs_service_impl_read(request: &FsReadRequest, utcb: &mut Utcb, process: &Process) {
+/// Replies with the number of read bytes.
+pub(super) fn fs_service_impl_read(
+    request: &FsReadRequest,
+    process: &Process,
+) -> Result<usize, FsError> {
+    let u_addr = match request.dest() {
+        FsReadDest::UserPtr(u_addr) => u_addr,
+        FsReadDest::Registered(buffer) => {
+            return read_into_registered_buffer(request, buffer, process);
+        }
+    };
+
     let mut fs_lock = libfileserver::FILESYSTEM.lock();
     // data from the file system
-    let read_bytes = fs_lock
-        .read_file(
-            process.pid(),
-            (request.fd().raw() as u64).into(),
-            request.count(),
-        )
-        .unwrap();
+    let read_bytes = fs_lock.read_file(
+        process.pid(),
+        (request.fd().raw() as u64).into(),
+        request.count(),
+    )?;
 
     // early return if EOF reached
     if read_bytes.len() == 0 {
-        utcb.store_data(&read_bytes.len()).unwrap();
-        return;
+        return Ok(0);
     }
 
     // now map the data to a user destination
-    let u_addr = request.user_ptr();
     let u_addr_page_offset = u_addr & 0xfff;
     let u_page_num = u_addr / PAGE_SIZE;
     let required_bytes = u_addr_page_offset + request.count();
@@ -41,20 +51,42 @@ pub(super) fn fs_service_impl_read(request: &FsReadRequest, utcb: &mut Utcb, pro
     let r_mapping_addr = VIRT_MEM_ALLOC
         .lock()
         .next_addr(Layout::from_size_align(required_bytes, PAGE_SIZE).unwrap());
-    let r_mapping_page_num = r_mapping_addr / PAGE_SIZE as u64;
 
     // map memory from user app into root task
-    CrdDelegateOptimizer::new(u_page_num as u64, r_mapping_page_num, page_count).mmap(
-        process.pd_obj().cap_sel(),
-        process.parent().unwrap().pd_obj().cap_sel(),
-        MemCapPermissions::READ | MemCapPermissions::WRITE,
-    );
+    let u_page_addr = (u_page_num * PAGE_SIZE) as u64;
+    DelegationBuilder::mem(u_page_addr..u_page_addr + (page_count * PAGE_SIZE) as u64)
+        .perms(MemCapPermissions::RW)
+        .from(process.pd_obj().cap_sel())
+        .at(r_mapping_addr)
+        .to(process.parent().unwrap().pd_obj().cap_sel())
+        .unwrap();
     // memory in roottask where I mapped the user memory
     let r_dest_ptr = (r_mapping_addr + u_addr_page_offset as u64) as *mut u8;
     unsafe {
-        core::ptr::copy_nonoverlapping(read_bytes.as_ptr(), r_dest_ptr, request.count());
+        core::ptr::copy_nonoverlapping(read_bytes.as_ptr(), r_dest_ptr, read_bytes.len());
     }
 
-    // read bytes
-    utcb.store_data(&read_bytes.len()).unwrap();
+    Ok(read_bytes.len())
+}
+
+/// Reads directly into the mapping of a registered buffer. Returns the number of read bytes.
+fn read_into_registered_buffer(
+    request: &FsReadRequest,
+    buffer: FsBufferRef,
+    process: &Process,
+) -> Result<usize, FsError> {
+    with_registered_buffer(process.pid(), buffer, request.count(), |dest| {
+        let mut fs_lock = libfileserver::FILESYSTEM.lock();
+        let read_bytes = fs_lock.read_file(
+            process.pid(),
+            (request.fd().raw() as u64).into(),
+            request.count(),
+        )?;
+        dest[..read_bytes.len()].copy_from_slice(read_bytes);
+        Ok(read_bytes.len())
+    })
+    .unwrap_or_else(|e| {
+        log::warn!("pid={} can't read into fs buffer: {:?}", process.pid(), e);
+        Err(FsError::InvalidArgument)
+    })
 }
@@ -0,0 +1,166 @@
+//! Service side of the file system ring, see [`FsRing`]. The roottask maps one ring per
+//! process into its address space. The kick semaphore of each process is the semaphore of
+//! the main global EC of the roottask, hence a kick wakes up
+//! [`crate::services::timer::timer_loop`], which processes the submissions of all rings
+//! with [`process_fs_rings`] and signals the completion semaphores.
+
+use crate::process::{
+    process_cap_sels,
+    Process,
+};
+use alloc::alloc::alloc_zeroed;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::alloc::Layout;
+use libfileserver::Filesystem;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::kobjects::SmObject;
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::syscall::{
+    sys_pd_ctrl_delegate,
+    DelegateFlags,
+};
+use libhrstd::libhedron::{
+    CrdObjSM,
+    MemCapPermissions,
+    SMCapPermissions,
+};
+use libhrstd::mem::calc_page_count;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsRing,
+    FsRingOpcode,
+    FsRingSetupResponse,
+    FsRingSqe,
+    FS_RING_DATA_SLOT_SIZE,
+    FS_RING_ERROR,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::delegation::DelegationBuilder;
+
+/// The rings of all processes. Shared between the service ECs, which create the rings,
+/// and the main EC of the roottask, which processes them.
+static FS_RINGS: SimpleMutex<BTreeMap<ProcessId, ProcessFsRing>> =
+    SimpleMutex::new(BTreeMap::new());
+
+/// The ring of a process.
+#[derive(Debug)]
+struct ProcessFsRing {
+    /// The ring in the address space of the roottask. Like the other resources of a
+    /// process, the memory stays allocated after the process exited.
+    ring: &'static FsRing,
+    /// Address of the ring in the address space of the process.
+    u_addr: u64,
+    completion_sm: Rc<SmObject>,
+}
+
+/// Implements the fs ring setup functionality that is accessible via the FS portal.
+pub(super) fn fs_service_impl_ring_setup(process: &Process) -> FsRingSetupResponse {
+    let u_addr = FS_RINGS
+        .lock()
+        .entry(process.pid())
+        .or_insert_with(|| create_ring(process))
+        .u_addr;
+    FsRingSetupResponse { ring_addr: u_addr }
+}
+
+/// Allocates the ring, maps it into the process, and delegates the semaphores.
+fn create_ring(process: &Process) -> ProcessFsRing {
+    let root = process.parent().unwrap();
+    let layout = Layout::new::<FsRing>()
+        .align_to(PAGE_SIZE)
+        .unwrap()
+        .pad_to_align();
+    let page_count = calc_page_count(layout.size());
+    // zeroed memory is an empty ring
+    let r_addr = unsafe { alloc_zeroed(layout) } as u64;
+    assert_ne!(r_addr, 0, "out of memory");
+
+    let u_addr = process
+        .memory_manager_mut()
+        .reserve_mmap_area(page_count)
+        .expect("the mmap arena of the process must have space for the ring");
+    DelegationBuilder::mem(r_addr..r_addr + layout.size() as u64)
+        .perms(MemCapPermissions::RW)
+        .at(u_addr)
+        .to_pd(&process.pd_obj())
+        .unwrap();
+
+    // the process may only kick, but not wait on the SM of the main EC
+    sys_pd_ctrl_delegate(
+        root.pd_obj().cap_sel(),
+        process.pd_obj().cap_sel(),
+        CrdObjSM::new(RootCapSpace::RootSmSleep.val(), 0, SMCapPermissions::UP),
+        CrdObjSM::new(UserAppCapSpace::FsRingKickSm.val(), 0, SMCapPermissions::UP),
+        DelegateFlags::default(),
+    )
+    .unwrap();
+    let completion_sm = SmObject::create(
+        process_cap_sels(process.pid())
+            .expect("running processes have capability selectors")
+            .fs_ring_sm(),
+        &root.pd_obj(),
+    );
+    completion_sm.delegate(&process.pd_obj(), UserAppCapSpace::FsRingCompletionSm.val());
+
+    log::debug!(
+        "fs ring of pid={}: {} pages at {:#x}",
+        process.pid(),
+        page_count,
+        u_addr
+    );
+    ProcessFsRing {
+        ring: unsafe { &*(r_addr as *const FsRing) },
+        u_addr,
+        completion_sm,
+    }
+}
+
+/// Processes the submissions of all rings and signals the completion semaphores of the
+/// rings with new completions. Must be called by the main global EC of the roottask.
+pub fn process_fs_rings() {
+    let rings = FS_RINGS.lock();
+    for (pid, ring) in rings.iter() {
+        let mut fs = libfileserver::FILESYSTEM.lock();
+        let count = ring
+            .ring
+            .process_submissions(|sqe, data| handle_submission(&mut fs, *pid, sqe, data));
+        drop(fs);
+        if count > 0 {
+            ring.completion_sm.sem_up();
+        }
+    }
+}
+
+/// Removes the ring of an exited process. Its memory stays allocated.
+pub fn unregister_fs_ring(pid: ProcessId) {
+    FS_RINGS.lock().remove(&pid);
+}
+
+/// Executes a single submission. Returns the result and the number of valid bytes in the
+/// data slot.
+fn handle_submission(
+    fs: &mut Filesystem,
+    pid: ProcessId,
+    sqe: &FsRingSqe,
+    data: &mut [u8; FS_RING_DATA_SLOT_SIZE],
+) -> (i64, u32) {
+    let fd = (sqe.fd as u64).into();
+    let count = (sqe.arg as usize).min(FS_RING_DATA_SLOT_SIZE);
+    let result = match FsRingOpcode::try_from(sqe.opcode) {
+        Ok(FsRingOpcode::Read) => fs.read_file(pid, fd, count).map(|bytes| {
+            data[..bytes.len()].copy_from_slice(bytes);
+            bytes.len()
+        }),
+        Ok(FsRingOpcode::Write) => fs.write_file(pid, fd, &data[..count]),
+        Ok(FsRingOpcode::LSeek) => fs.lseek_file(pid, fd, sqe.arg as usize).map(|_| 0),
+        Err(_) => Err(FsError::InvalidArgument),
+    };
+    match (result, sqe.opcode == FsRingOpcode::Read as u32) {
+        (Ok(bytes), true) => (bytes as i64, bytes as u32),
+        (Ok(bytes), false) => (bytes as i64, 0),
+        (Err(_), _) => (FS_RING_ERROR, 0),
+    }
+}
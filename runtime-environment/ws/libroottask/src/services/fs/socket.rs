@@ -0,0 +1,78 @@
+use crate::process::Process;
+use core::cmp::min;
+use libfileserver::{
+    FileDescriptor,
+    Filesystem,
+};
+use libhrstd::rt::services::fs::{
+    FsSocketReply,
+    FsSocketRequest,
+    FsSocketResponse,
+    SocketError,
+    SocketKind,
+    FD,
+    FS_SOCKET_MAX_IPC_DATA,
+};
+
+/// Implements the fs socket service functionality that is accessible via the FS portal.
+pub(super) fn fs_service_impl_socket(
+    request: &FsSocketRequest,
+    process: &Process,
+) -> FsSocketResponse {
+    let mut fs_lock = libfileserver::FILESYSTEM.lock();
+    handle_request(&mut fs_lock, request, process)
+}
+
+fn handle_request(
+    fs: &mut Filesystem,
+    request: &FsSocketRequest,
+    process: &Process,
+) -> FsSocketResponse {
+    let pid = process.pid();
+    let reply = match request {
+        FsSocketRequest::Socket(kind) => FsSocketReply::Fd(to_fd(fs.socket(pid, *kind)?)),
+        FsSocketRequest::SocketPair(kind) => {
+            let (a, b) = fs.socketpair(pid, *kind)?;
+            FsSocketReply::FdPair(to_fd(a), to_fd(b))
+        }
+        FsSocketRequest::Bind { fd, name } => {
+            fs.bind(pid, from_fd(*fd), name)?;
+            FsSocketReply::Done
+        }
+        FsSocketRequest::Listen { fd, backlog } => {
+            fs.listen(pid, from_fd(*fd), *backlog)?;
+            FsSocketReply::Done
+        }
+        FsSocketRequest::Accept(fd) => FsSocketReply::Fd(to_fd(fs.accept(pid, from_fd(*fd))?)),
+        FsSocketRequest::Connect { fd, name } => {
+            fs.connect(pid, from_fd(*fd), name)?;
+            FsSocketReply::Done
+        }
+        FsSocketRequest::Send {
+            fd,
+            data,
+            dest,
+            partial,
+        } => {
+            if *partial && fs.socket_kind(pid, from_fd(*fd))? == SocketKind::Datagram {
+                return Err(SocketError::MessageTooLong);
+            }
+            FsSocketReply::Sent(fs.send(pid, from_fd(*fd), data, dest.as_deref())?)
+        }
+        FsSocketRequest::Recv { fd, max_len } => {
+            // the reply must fit into the UTCB
+            let max_len = min(*max_len, FS_SOCKET_MAX_IPC_DATA);
+            let (data, from) = fs.recv(pid, from_fd(*fd), max_len)?;
+            FsSocketReply::Received { data, from }
+        }
+    };
+    Ok(reply)
+}
+
+fn from_fd(fd: FD) -> FileDescriptor {
+    (fd.raw() as u64).into()
+}
+
+fn to_fd(fd: FileDescriptor) -> FD {
+    FD::new(fd.val() as i32)
+}
@@ -0,0 +1,10 @@
+use crate::process::Process;
+use libhrstd::rt::services::fs::FsUmaskRequest;
+
+/// Implements the fs umask service functionality that is accessible via the FS portal.
+/// Replies with the previous umask.
+pub(super) fn fs_service_impl_umask(request: &FsUmaskRequest, process: &Process) -> u16 {
+    libfileserver::FILESYSTEM
+        .lock()
+        .set_umask(process.pid(), request.umask())
+}
@@ -1,19 +1,36 @@
 use crate::process::Process;
-use libhrstd::libhedron::Utcb;
-use libhrstd::rt::services::fs::FsWriteRequest;
+use crate::services::fs::buffer::with_registered_buffer;
+use libhrstd::rt::services::fs::{
+    FsError,
+    FsWriteRequest,
+    FsWriteSrc,
+};
 
 /// Implements the fs write service functionality that is accessible via the FS portal.
-pub(super) fn fs_service_impl_write(request: &FsWriteRequest, utcb: &mut Utcb, process: &Process) {
-    libfileserver::FILESYSTEM
-        .lock()
-        .write_file(
+/// Replies with the number of written bytes.
+pub(super) fn fs_service_impl_write(
+    request: &FsWriteRequest,
+    process: &Process,
+) -> Result<usize, FsError> {
+    match request.src() {
+        FsWriteSrc::Data(data) => libfileserver::FILESYSTEM.lock().write_file(
             process.pid(),
             (request.fd().raw() as u64).into(),
             // currently don't support user ptr read
-            request.data().embedded_slice(),
-        )
-        .unwrap();
-
-    utcb.store_data(&request.data().embedded_slice().len())
-        .unwrap();
+            data.embedded_slice(),
+        ),
+        FsWriteSrc::Registered(buffer) => {
+            with_registered_buffer(process.pid(), *buffer, request.count(), |src| {
+                libfileserver::FILESYSTEM.lock().write_file(
+                    process.pid(),
+                    (request.fd().raw() as u64).into(),
+                    src,
+                )
+            })
+            .unwrap_or_else(|e| {
+                log::warn!("pid={} can't write from fs buffer: {:?}", process.pid(), e);
+                Err(FsError::InvalidArgument)
+            })
+        }
+    }
 }
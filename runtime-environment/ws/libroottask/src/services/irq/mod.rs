@@ -0,0 +1,65 @@
+//! Interrupt service. Driver processes attach interrupts of devices; the roottask routes
+//! them to the CPU of the process and delegates the interrupt semaphore of Hedron, see
+//! [`crate::irq`]. Afterwards, the process waits for interrupts without the roottask.
+//! Only privileged processes (see [`is_privileged`]) are drivers; the service refuses to
+//! attach interrupts to all other callers.
+
+use crate::irq;
+use crate::process::{
+    is_privileged,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::rc::Rc;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::rt::services::irq::{
+    IrqService,
+    IrqServiceError,
+    IrqServiceRequest,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::service_ids::ServiceId;
+
+/// Creates a new interrupt service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::IrqService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the interrupt Portal.
+pub fn irq_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<IrqServiceRequest>().unwrap();
+    log::trace!("irq request from pid={}: {:?}", process.pid(), request);
+    match request {
+        IrqServiceRequest::Attach(request) => rpc_serve::<IrqService, _>(request, utcb, |r| {
+            if !is_privileged(process.pid()) {
+                log::debug!("pid={} isn't allowed to attach interrupts", process.pid());
+                return Err(IrqServiceError::PermissionDenied);
+            }
+            irq::attach_to_process(process, r.source)
+        }),
+        IrqServiceRequest::Detach(request) => rpc_serve::<IrqService, _>(request, utcb, |r| {
+            irq::detach_from_process(process.pid(), r.irq)
+        }),
+    }
+    *do_reply = true;
+}
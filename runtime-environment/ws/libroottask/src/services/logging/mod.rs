@@ -0,0 +1,163 @@
+//! Log service. Processes add their log records to the log of the roottask (see
+//! [`crate::log_buffer`]), query the log, and adjust its filters. The user logger of
+//! native apps sends each record here instead of formatting it on STDOUT, hence the
+//! filters and the console setting of the roottask apply to all processes. The tool
+//! `native-dmesg-bin` prints the log.
+
+use crate::log_buffer;
+use crate::log_timestamp;
+use crate::process::{
+    is_privileged,
+    signal_target,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::services::stderr;
+use alloc::rc::Rc;
+use core::fmt::Write;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::libhedron::UTCB_DATA_CAPACITY;
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::logging::{
+    LogEntry,
+    LogReadRequest,
+    LogReadResponse,
+    LogScope,
+    LogService,
+    LogServiceError,
+    LogServiceRequest,
+    LogServiceResponse,
+    LogWriteRequest,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::service_ids::ServiceId;
+
+/// Creates a new log service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::LogService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the log Portal.
+pub fn log_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<LogServiceRequest>().unwrap();
+    match request {
+        LogServiceRequest::Write(request) => {
+            rpc_serve::<LogService, _>(request, utcb, |r| write(process, r))
+        }
+        LogServiceRequest::Read(request) => rpc_serve::<LogService, _>(request, utcb, read),
+        LogServiceRequest::Clear(request) => rpc_serve::<LogService, _>(request, utcb, |r| {
+            check_privileged(process)?;
+            log_buffer::clear(r.until);
+            Ok(())
+        }),
+        LogServiceRequest::SetLevel(request) => rpc_serve::<LogService, _>(request, utcb, |r| {
+            check_scope(process, &r.scope)?;
+            log_buffer::set_level(r.scope, r.level);
+            Ok(())
+        }),
+        LogServiceRequest::GetLevel(request) => rpc_serve::<LogService, _>(request, utcb, |r| {
+            log_buffer::level(r.pid.unwrap_or_else(|| process.pid()))
+        }),
+        LogServiceRequest::SetConsole(request) => rpc_serve::<LogService, _>(request, utcb, |r| {
+            check_privileged(process)?;
+            log::info!(
+                "pid={} {} the console output of the log",
+                process.pid(),
+                if r.enabled { "enabled" } else { "disabled" }
+            );
+            log_buffer::set_console_enabled(r.enabled);
+            Ok(())
+        }),
+    }
+    *do_reply = true;
+}
+
+/// Adds the record of the process to the log and prints it, if the filters let it through.
+fn write(process: &Process, request: LogWriteRequest) {
+    if !log_buffer::is_enabled(process.pid(), &request.target, request.level) {
+        return;
+    }
+    let entry = log_buffer::record(process.pid(), request.level, &request.target, request.msg);
+    if log_buffer::is_console_enabled() {
+        print(&entry);
+    }
+}
+
+/// Prints the record of a process on the console, like the output of the STDERR service.
+fn print(entry: &LogEntry) {
+    let mut writer = stderr::writer_mut();
+    if let Some(timestamp) = log_timestamp::now() {
+        let _ = write!(&mut writer, "{}", timestamp);
+    }
+    let _ = writeln!(
+        &mut writer,
+        "[LOG PID={}] [{:>5}] {}: {}",
+        entry.pid,
+        entry.level.as_str().to_ascii_uppercase(),
+        entry.target,
+        entry.msg
+    );
+}
+
+fn read(request: LogReadRequest) -> LogReadResponse {
+    let (entries, next, more) =
+        log_buffer::read(request.from, request.pid, request.level, UTCB_DATA_CAPACITY);
+    LogReadResponse::new_fitting(entries, next, more, UTCB_DATA_CAPACITY)
+}
+
+/// Only privileged processes (see [`is_privileged`]) may clear the log, toggle the
+/// console, and change the default and module filters.
+fn check_privileged(caller: &Process) -> LogServiceResponse {
+    if is_privileged(caller.pid()) {
+        Ok(())
+    } else {
+        log::debug!("pid={} isn't allowed to configure the log", caller.pid());
+        Err(LogServiceError::PermissionDenied)
+    }
+}
+
+/// The filter of a process may also be changed by the process itself and by its parent.
+fn check_scope(caller: &Process, scope: &LogScope) -> LogServiceResponse {
+    match scope {
+        LogScope::Default => check_privileged(caller),
+        LogScope::Module(module) if module.is_empty() => Err(LogServiceError::InvalidModule),
+        LogScope::Module(_) => check_privileged(caller),
+        LogScope::Process(pid) => check_process(caller, *pid),
+    }
+}
+
+fn check_process(caller: &Process, pid: ProcessId) -> LogServiceResponse {
+    let parent = match signal_target(pid) {
+        Some(target) => target.parent,
+        None if pid == ROOTTASK_PROCESS_PID => None,
+        None => return Err(LogServiceError::NoSuchProcess),
+    };
+    if caller.pid() == pid || parent == Some(caller.pid()) {
+        Ok(())
+    } else {
+        check_privileged(caller)
+    }
+}
@@ -5,11 +5,16 @@ use crate::mem::{
     ROOT_MEM_MAPPER,
     VIRT_MEM_ALLOC,
 };
-use crate::process::Process;
+use crate::process::{
+    process_cap_sels,
+    Process,
+};
+use crate::smp;
 use crate::stack::StaticStack;
 use alloc::collections::BTreeMap;
 use alloc::rc::Rc;
 use core::alloc::Layout;
+use core::ops::Range;
 use libhrstd::cap_space::root::RootCapSpace;
 use libhrstd::cap_space::user::UserAppCapSpace;
 use libhrstd::kobjects::{
@@ -24,23 +29,37 @@ use libhrstd::mem::calc_page_count;
 use libhrstd::process::consts::ProcessId;
 use libhrstd::service_ids::ServiceId;
 use libhrstd::sync::mutex::SimpleMutex;
-use libhrstd::sync::static_global_ptr::StaticGlobalPtr;
 
 pub mod allocate;
+pub mod build_info;
 pub mod echo;
 pub mod foreign_syscall;
 pub mod fs;
+pub mod irq;
+pub mod logging;
+pub mod name;
+pub mod network;
+pub mod pci;
+pub mod perf_counter;
+pub mod process;
+pub mod process_signal;
+pub mod scheduling;
+pub mod semaphore;
+pub mod serial_transfer;
+pub mod shm;
 pub mod stderr;
+pub mod stdin;
 pub mod stdout;
+pub mod system_time;
+pub mod timer;
 
+/// Stack of the service local EC of CPU 0. The local ECs of the other CPUs get stacks of
+/// the same size from the heap.
 static mut LOCAL_EC_STACK: StaticStack<16> = StaticStack::new();
 
-/// The stack top of the local EC that handles all exception calls.
-pub static LOCAL_EC_STACK_TOP: StaticGlobalPtr<u8> =
-    StaticGlobalPtr::new(unsafe { LOCAL_EC_STACK.get_stack_top_ptr() });
-
-/// Holds a weak reference to the local EC object used for handling service calls the roottask.
-static LOCAL_EC: SimpleMutex<Option<Rc<LocalEcObject>>> = SimpleMutex::new(None);
+/// Holds the local EC objects used for handling service calls the roottask, indexed by CPU.
+/// The service PTs of a process are bound to the local EC of the CPU of the process.
+static LOCAL_ECS: SimpleMutex<BTreeMap<u64, Rc<LocalEcObject>>> = SimpleMutex::new(BTreeMap::new());
 
 /// Helps to keep knowledge about mapped areas. This accelerates reads and writes if certain user
 /// memory pages are mapped already. For example, Linux read and write calls require memory
@@ -59,6 +78,16 @@ impl MappedAreas {
         Self(BTreeMap::new())
     }
 
+    /// Removes the cached mappings of the process that overlap `u_range`.
+    fn forget(&mut self, pid: ProcessId, u_range: Range<u64>) {
+        if let Some(process_mappings) = self.0.get_mut(&pid) {
+            process_mappings.retain(|u_page_addr, mapping| {
+                let u_end = u_page_addr + mapping.size_in_pages() * PAGE_SIZE as u64;
+                u_end <= u_range.start || *u_page_addr >= u_range.end
+            });
+        }
+    }
+
     /// Convenient wrapper that service functions should use if they need access to certain
     /// user memory. It creates a mapping with an appropriate size.
     fn create_or_get_mapping(
@@ -109,6 +138,12 @@ impl MappedAreas {
         u_page_addr: u64,
         page_count: u64,
     ) -> MappedMemory {
+        // the process might not have touched memory that gets mapped on demand yet
+        process.memory_manager_mut().map_on_demand_memory(
+            u_page_addr,
+            page_count * PAGE_SIZE as u64,
+            process,
+        );
         let mut mapper = ROOT_MEM_MAPPER.lock();
         let root_process = process.parent().unwrap();
 
@@ -130,33 +165,49 @@ pub fn init_writers(hip: &HIP) {
     stderr::init_writer(hip);
 }
 
-/// Inits the local EC used by the service portals. Now [`create_and_delegate_service_pts`] can be called.
+/// Inits the local ECs used by the service portals, one per online CPU. Now
+/// [`create_and_delegate_service_pts`] can be called.
 pub fn init_services(root: &Process) {
-    let mut ec_lock = LOCAL_EC.lock();
-    assert!(ec_lock.is_none(), "init only allowed once!");
-
-    let utcb_addr = VIRT_MEM_ALLOC
-        .lock()
-        .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
-
-    unsafe { LOCAL_EC_STACK.activate_guard_page(RootCapSpace::RootPd.val()) };
-    // adds itself to the root process
-    let ec = LocalEcObject::create(
-        RootCapSpace::RootServiceLocalEc.val(),
-        &root.pd_obj(),
-        LOCAL_EC_STACK_TOP.val(),
-        utcb_addr,
-    );
-    log::trace!(
-        "Created local EC for all service calls (UTCB={:016x})",
-        ec.utcb_addr()
-    );
+    let mut ec_lock = LOCAL_ECS.lock();
+    assert!(ec_lock.is_empty(), "init only allowed once!");
 
-    ec_lock.replace(ec);
+    for cpu in smp::online_cpus_iter() {
+        let stack = if cpu == 0 {
+            unsafe { &LOCAL_EC_STACK }
+        } else {
+            StaticStack::new_leaked()
+        };
+        let utcb_addr = VIRT_MEM_ALLOC
+            .lock()
+            .next_addr(Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap());
+
+        unsafe {
+            stack.activate_guard_page(
+                RootCapSpace::RootPd.val(),
+                format!("service local EC of CPU {}", cpu),
+            )
+        };
+        // adds itself to the root process
+        let ec = LocalEcObject::create(
+            RootCapSpace::calc_service_local_ec_sel(cpu),
+            &root.pd_obj(),
+            stack.get_stack_top_ptr() as u64,
+            utcb_addr,
+            cpu,
+        );
+        log::trace!(
+            "Created local EC for all service calls on CPU {} (UTCB={:016x})",
+            cpu,
+            ec.utcb_addr()
+        );
+
+        ec_lock.insert(cpu, ec);
+    }
 
     // Additional setup out of the loop for the regular service PTs that gets multiplexed
     // via the shared PT entry.
     echo::init_echo_raw_service(root);
+    foreign_syscall::init(root);
 }
 
 /// Entry for all services of the roottask.
@@ -178,12 +229,39 @@ pub fn handle_service_call(
         ServiceId::AllocateService => allocate::allocate_service_handler,
         ServiceId::FileSystemService => fs::fs_service_handler,
         ServiceId::EchoService => echo::echo_service_handler,
+        ServiceId::TimerService => timer::timer_service_handler,
+        ServiceId::BuildInfoService => build_info::build_info_service_handler,
+        ServiceId::ProcessSignalService => process_signal::process_signal_service_handler,
+        ServiceId::NetworkService => network::network_service_handler,
+        ServiceId::SchedulingService => scheduling::scheduling_service_handler,
+        ServiceId::ProcessService => process::process_service_handler,
+        ServiceId::SystemTimeService => system_time::system_time_service_handler,
+        ServiceId::StdinService => stdin::stdin_service_handler,
+        ServiceId::NameService => name::name_service_handler,
+        ServiceId::SerialTransferService => serial_transfer::serial_transfer_service_handler,
+        ServiceId::LogService => logging::log_service_handler,
+        ServiceId::PerfService => perf_counter::perf_service_handler,
+        ServiceId::SemaphoreService => semaphore::semaphore_service_handler,
+        ServiceId::ShmService => shm::shm_service_handler,
+        ServiceId::IrqService => irq::irq_service_handler,
+        ServiceId::PciService => pci::pci_service_handler,
         ServiceId::RawEchoService => panic!("the raw echo service is not covered by the PT multiplexing mechanism; has a dedicated entry"),
         _ => panic!("service not supported yet"),
     };
     cb(pt, process, utcb, do_reply);
 }
 
+/// Drops the cached mappings of user memory of the process in `u_range`, e.g. after the
+/// memory got revoked from the process, which also revokes the mappings of the roottask.
+pub fn forget_mapped_areas(pid: ProcessId, u_range: Range<u64>) {
+    MAPPED_AREAS.lock().forget(pid, u_range);
+}
+
+/// Drops all cached mappings of user memory of a stopped process.
+pub fn forget_all_mapped_areas(pid: ProcessId) {
+    MAPPED_AREAS.lock().0.remove(&pid);
+}
+
 /// Creates the service PTs for a process inside the roottask. Install the PTs in the
 /// target PD at well-known locations.
 ///
@@ -195,11 +273,13 @@ pub fn create_and_delegate_service_pts(process: &Process) {
         process.name()
     );
 
-    let cap_base_sel = RootCapSpace::calc_service_pt_sel_base(process.pid());
+    let cap_base_sel = process_cap_sels(process.pid())
+        .expect("running processes have capability selectors")
+        .service_pt_base();
 
-    // local EC for all service calls
-    let ec_lock = LOCAL_EC.lock();
-    let ec_lock = ec_lock.as_ref().unwrap();
+    // local EC for all service calls on the CPU of the process
+    let ec_lock = LOCAL_ECS.lock();
+    let ec_lock = ec_lock.get(&process.cpu()).unwrap();
 
     // Stdout Service PT
     {
@@ -244,10 +324,186 @@ pub fn create_and_delegate_service_pts(process: &Process) {
         log::trace!("delegated fs service pt");
     }
 
+    // Timer Service PT
+    {
+        let timer_pt = timer::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &timer_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::TimerServicePT.val(),
+        );
+        log::trace!("delegated timer service pt");
+    }
+
+    // Build Info Service PT
+    {
+        let build_info_pt = build_info::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &build_info_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::BuildInfoServicePT.val(),
+        );
+        log::trace!("delegated build info service pt");
+    }
+
+    // Process Signal Service PT
+    {
+        let process_signal_pt = process_signal::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &process_signal_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::ProcessSignalServicePT.val(),
+        );
+        log::trace!("delegated process signal service pt");
+    }
+
+    // Network Service PT
+    {
+        let network_pt = network::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &network_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::NetworkServicePT.val(),
+        );
+        log::trace!("delegated network service pt");
+    }
+
+    // Scheduling Service PT
+    {
+        let scheduling_pt = scheduling::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &scheduling_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::SchedulingServicePT.val(),
+        );
+        log::trace!("delegated scheduling service pt");
+    }
+
+    // Process Service PT
+    {
+        let process_pt = process::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &process_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::ProcessServicePT.val(),
+        );
+        log::trace!("delegated process service pt");
+    }
+
+    // System Time Service PT
+    {
+        let system_time_pt = system_time::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &system_time_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::SystemTimeServicePT.val(),
+        );
+        log::trace!("delegated system time service pt");
+    }
+
+    // Stdin Service PT
+    {
+        let stdin_pt = stdin::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &stdin_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::StdinServicePT.val(),
+        );
+        log::trace!("delegated stdin service pt");
+    }
+
+    // Name Service PT
+    {
+        let name_pt = name::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &name_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::NameServicePT.val(),
+        );
+        log::trace!("delegated name service pt");
+    }
+
+    // Serial Transfer Service PT
+    {
+        let serial_transfer_pt = serial_transfer::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &serial_transfer_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::SerialTransferServicePT.val(),
+        );
+        log::trace!("delegated serial transfer service pt");
+    }
+
+    // Log Service PT
+    {
+        let log_pt = logging::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &log_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::LogServicePT.val(),
+        );
+        log::trace!("delegated log service pt");
+    }
+
+    // Performance Counter Service PT
+    {
+        let perf_pt = perf_counter::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &perf_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::PerfServicePT.val(),
+        );
+        log::trace!("delegated performance counter service pt");
+    }
+
+    // Semaphore Service PT
+    {
+        let semaphore_pt = semaphore::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &semaphore_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::SemaphoreServicePT.val(),
+        );
+        log::trace!("delegated semaphore service pt");
+    }
+
+    // Shared Memory Service PT
+    {
+        let shm_pt = shm::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &shm_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::ShmServicePT.val(),
+        );
+        log::trace!("delegated shm service pt");
+    }
+
+    // Interrupt Service PT
+    {
+        let irq_pt = irq::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &irq_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::IrqServicePT.val(),
+        );
+        log::trace!("delegated irq service pt");
+    }
+
+    // PCI Service PT
+    {
+        let pci_pt = pci::create_service_pt(cap_base_sel, ec_lock);
+        PtObject::delegate(
+            &pci_pt,
+            &process.pd_obj(),
+            UserAppCapSpace::PciServicePT.val(),
+        );
+        log::trace!("delegated pci service pt");
+    }
+
     // ECHO Service PT & RAW ECHO Service PT
     {
         let (echo_service_pt, raw_echo_service_pt) =
-            echo::create_service_pts(cap_base_sel, ec_lock);
+            echo::create_service_pts(cap_base_sel, ec_lock, process.cpu());
         PtObject::delegate(
             &echo_service_pt,
             &process.pd_obj(),
@@ -263,9 +519,10 @@ pub fn create_and_delegate_service_pts(process: &Process) {
 }
 
 /// The roottask can use this to create and get the pair of (echo pt, raw echo pt).
-/// Useful for benchmarking of PD-internal IPC costs.
+/// Useful for benchmarking of PD-internal IPC costs. The PTs can be called on CPU 0, where
+/// the main global EC of the roottask runs.
 pub fn init_roottask_echo_pts() -> (Rc<PtObject>, Rc<PtObject>) {
-    let ec_lock = LOCAL_EC.lock();
-    let ec_lock = ec_lock.as_ref().expect("call init_services first!");
+    let ec_lock = LOCAL_ECS.lock();
+    let ec_lock = ec_lock.get(&0).expect("call init_services first!");
     echo::create_service_pts_fot_roottask(ec_lock)
 }
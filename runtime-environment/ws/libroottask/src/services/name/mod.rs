@@ -0,0 +1,488 @@
+//! Name service. Maps names of services to their portals, so that services of user apps
+//! don't need a fixed selector in
+//! [`libhrstd::cap_space::user::UserAppCapSpace`]. A process registers a portal in its
+//! capability space under a name; other processes look up the name and the roottask
+//! delegates the portal into their range of received capabilities, see
+//! [`crate::cap_transfer`].
+//!
+//! The services of the roottask are reserved names, see [`BUILTIN_SERVICES`]. A lookup of
+//! one of them delegates the service portal of the caller once more, i.e. `lookup("fs")`
+//! returns a selector that behaves like
+//! [`libhrstd::cap_space::user::UserAppCapSpace::FsServicePT`].
+//!
+//! User apps can host services in two ways. Either they create a portal themselves and
+//! register it, or they let the roottask create a local EC in their PD that handles the
+//! calls ([`NameHostRequest`]). Then, each client gets its own portal to the EC whose
+//! portal identifier is the PID of the client, hence the service knows its clients.
+//!
+//! Each registration carries a [`ServiceAccess`] that limits which processes may look up
+//! the service. Because Hedron permits calls to portals of local ECs only from the CPU of
+//! the EC, lookups from another CPU than the one of the owner fail.
+//!
+//! The roottask doesn't check whether the registered selector actually holds a portal. If
+//! it doesn't, the selector that a lookup returns stays empty.
+
+use crate::cap_transfer::{
+    check_item,
+    transfer_cap,
+    CapTransferError,
+};
+use crate::process::{
+    exit_status,
+    process_cap_sels,
+    process_cpu,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::cap_space::root::{
+    ProcessCapSels,
+    RootCapSpace,
+};
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::syscall::{
+    sys_create_local_ec,
+    sys_create_pt,
+    sys_pt_ctrl,
+    sys_revoke,
+    DelegateFlags,
+};
+use libhrstd::libhedron::{
+    CapSel,
+    CrdObjPT,
+    Mtd,
+    PTCapPermissions,
+    TypedItem,
+    Utcb,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::rt::services::name::{
+    is_valid_service_name,
+    NameHostRequest,
+    NameLookupResponse,
+    NameRegisterRequest,
+    NameService,
+    NameServiceError,
+    NameServiceRequest,
+    NameServiceResponse,
+    ServiceAccess,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::uaddress_space::USER_SERVICE_UTCB_ADDR;
+
+/// Names of the services of the roottask.
+const BUILTIN_SERVICES: [(&str, ServiceId); 20] = [
+    ("stdout", ServiceId::StdoutService),
+    ("stderr", ServiceId::StderrService),
+    ("stdin", ServiceId::StdinService),
+    ("allocate", ServiceId::AllocateService),
+    ("fs", ServiceId::FileSystemService),
+    ("echo", ServiceId::EchoService),
+    ("timer", ServiceId::TimerService),
+    ("build_info", ServiceId::BuildInfoService),
+    ("process_signal", ServiceId::ProcessSignalService),
+    ("network", ServiceId::NetworkService),
+    ("scheduling", ServiceId::SchedulingService),
+    ("process", ServiceId::ProcessService),
+    ("system_time", ServiceId::SystemTimeService),
+    ("serial_transfer", ServiceId::SerialTransferService),
+    ("log", ServiceId::LogService),
+    ("perf", ServiceId::PerfService),
+    ("semaphore", ServiceId::SemaphoreService),
+    ("shm", ServiceId::ShmService),
+    ("irq", ServiceId::IrqService),
+    ("pci", ServiceId::PciService),
+];
+
+/// Services that user apps registered.
+static REGISTRY: SimpleMutex<Registry> = SimpleMutex::new(Registry::new());
+
+/// Whether the process hosts a service, i.e. has a local EC for it, indexed by PID.
+static HOSTING: SimpleMutex<[bool; NUM_PROCESSES as usize]> =
+    SimpleMutex::new([false; NUM_PROCESSES as usize]);
+
+/// Portals to hosted services that the roottask created, as pairs of owner and client.
+static HOSTED_PORTALS: SimpleMutex<BTreeSet<(ProcessId, ProcessId)>> =
+    SimpleMutex::new(BTreeSet::new());
+
+/// Where the portal of a registered service comes from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Portal {
+    /// The typed item of the portal in the capability space of the owner.
+    Item(TypedItem),
+    /// The local EC of the owner that the roottask created, with the entry of its portals.
+    Hosted { entry: u64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Registration {
+    portal: Portal,
+    access: ServiceAccess,
+}
+
+/// Maps names to the process that registered them and their registration.
+#[derive(Debug)]
+struct Registry(BTreeMap<String, (ProcessId, Registration)>);
+
+impl Registry {
+    const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Fails if another process that is still running registered the name; `is_running`
+    /// decides that.
+    fn check_available(
+        &self,
+        owner: ProcessId,
+        name: &str,
+        is_running: impl Fn(ProcessId) -> bool,
+    ) -> NameServiceResponse {
+        match self.0.get(name) {
+            Some((other, _)) if *other != owner && is_running(*other) => {
+                Err(NameServiceError::NameTaken)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Registers the name, if it is available, see [`Self::check_available`].
+    fn register(
+        &mut self,
+        owner: ProcessId,
+        name: String,
+        registration: Registration,
+        is_running: impl Fn(ProcessId) -> bool,
+    ) -> NameServiceResponse {
+        self.check_available(owner, &name, is_running)?;
+        self.0.insert(name, (owner, registration));
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Option<(ProcessId, Registration)> {
+        self.0.get(name).cloned()
+    }
+
+    fn unregister(&mut self, caller: ProcessId, name: &str) -> NameServiceResponse {
+        match self.0.get(name) {
+            None => Err(NameServiceError::NotFound),
+            Some((owner, _)) if *owner != caller => Err(NameServiceError::PermissionDenied),
+            Some(_) => {
+                self.0.remove(name);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes all names of the process.
+    fn unregister_all(&mut self, owner: ProcessId) {
+        self.0.retain(|_, (pid, _)| *pid != owner);
+    }
+}
+
+/// Creates a new NAME service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::NameService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the NAME Portal.
+pub fn name_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<NameServiceRequest>().unwrap();
+    match request {
+        NameServiceRequest::Register(request) => {
+            rpc_serve::<NameService, _>(request, utcb, |r| register(process, r))
+        }
+        NameServiceRequest::Host(request) => {
+            rpc_serve::<NameService, _>(request, utcb, |r| host(process, r))
+        }
+        NameServiceRequest::Lookup(request) => {
+            rpc_serve::<NameService, _>(request, utcb, |r| lookup(process, &r.name))
+        }
+        NameServiceRequest::Unregister(request) => {
+            rpc_serve::<NameService, _>(request, utcb, |r| {
+                REGISTRY.lock().unregister(process.pid(), &r.name)
+            })
+        }
+    }
+    *do_reply = true;
+}
+
+/// Removes all names that the process registered and revokes the portals to the service
+/// that it hosts as well as the portals that it got to the services of others. Called when
+/// the process exits.
+pub fn unregister_services(pid: ProcessId) {
+    REGISTRY.lock().unregister_all(pid);
+    HOSTING.lock()[pid as usize] = false;
+    let mut portals = HOSTED_PORTALS.lock();
+    let pairs = portals
+        .iter()
+        .filter(|(owner, client)| *owner == pid || *client == pid)
+        .copied()
+        .collect::<Vec<_>>();
+    for (owner, client) in pairs {
+        portals.remove(&(owner, client));
+        // the portal of a client whose PID gets reused must not lead to the service anymore
+        let cap_sels = match process_cap_sels(owner) {
+            Some(cap_sels) => cap_sels,
+            None => continue,
+        };
+        let pt_sel = cap_sels.hosted_service_pt(client);
+        if let Err(e) = sys_revoke(CrdObjPT::new(pt_sel, 0, PTCapPermissions::all()), true) {
+            log::error!(
+                "can't revoke portal to the service of pid={}: {:?}",
+                owner,
+                e
+            );
+        }
+    }
+}
+
+fn register(caller: &Process, request: NameRegisterRequest) -> NameServiceResponse {
+    check_name(&request.name)?;
+    check_item(&request.item).map_err(|_| NameServiceError::InvalidCapability)?;
+    log::debug!("pid={} registers service '{}'", caller.pid(), request.name);
+    let registration = Registration {
+        portal: Portal::Item(request.item),
+        access: request.access,
+    };
+    REGISTRY
+        .lock()
+        .register(caller.pid(), request.name, registration, is_running)
+}
+
+/// Creates the local EC of the service in the PD of the caller and registers the service.
+fn host(caller: &Process, request: NameHostRequest) -> NameServiceResponse {
+    check_name(&request.name)?;
+    let mut hosting = HOSTING.lock();
+    if hosting[caller.pid() as usize] {
+        return Err(NameServiceError::AlreadyHosting);
+    }
+    let mut registry = REGISTRY.lock();
+    registry.check_available(caller.pid(), &request.name, is_running)?;
+
+    sys_create_local_ec(
+        process_cap_sels(caller.pid())
+            .expect("running processes have capability selectors")
+            .hosted_service_ec(),
+        caller.pd_obj().cap_sel(),
+        request.stack_top,
+        UserAppCapSpace::ExceptionEventBase.val(),
+        caller.cpu(),
+        USER_SERVICE_UTCB_ADDR / PAGE_SIZE as u64,
+    )
+    .unwrap();
+    hosting[caller.pid() as usize] = true;
+    log::debug!(
+        "pid={} hosts service '{}' on CPU {}",
+        caller.pid(),
+        request.name,
+        caller.cpu()
+    );
+
+    let registration = Registration {
+        portal: Portal::Hosted {
+            entry: request.entry,
+        },
+        access: request.access,
+    };
+    registry.register(caller.pid(), request.name, registration, is_running)
+}
+
+fn lookup(caller: &Process, name: &str) -> NameLookupResponse {
+    let (from_pd, item) = if let Some(service) = builtin_service(name) {
+        let pt_sel = process_cap_sels(caller.pid())
+            .expect("running processes have capability selectors")
+            .service_pt_base()
+            + service.val();
+        (RootCapSpace::RootPd.val(), pt_item(pt_sel))
+    } else {
+        let (owner, registration) = REGISTRY
+            .lock()
+            .lookup(name)
+            .filter(|(owner, _)| is_running(*owner))
+            .ok_or(NameServiceError::NotFound)?;
+        let client_parent = caller.parent().map(|parent| parent.pid());
+        if !registration
+            .access
+            .allows(owner, caller.pid(), client_parent)
+        {
+            log::debug!("pid={} may not look up service '{}'", caller.pid(), name);
+            return Err(NameServiceError::PermissionDenied);
+        }
+        if process_cpu(owner) != Some(caller.cpu()) {
+            return Err(NameServiceError::OtherCpu);
+        }
+        let owner_sels = process_cap_sels(owner).ok_or(NameServiceError::NotFound)?;
+        match registration.portal {
+            Portal::Item(item) => (owner_sels.pd(), item),
+            Portal::Hosted { entry } => {
+                let pt_sel = hosted_portal(owner, owner_sels, caller.pid(), entry);
+                (RootCapSpace::RootPd.val(), pt_item(pt_sel))
+            }
+        }
+    };
+    transfer_cap(from_pd, &item, caller.pid()).map_err(|err| match err {
+        CapTransferError::InvalidItem | CapTransferError::NotReceived => {
+            NameServiceError::InvalidCapability
+        }
+        CapTransferError::CapSpaceFull => NameServiceError::CapSpaceFull,
+    })
+}
+
+/// Returns the portal to the service of `owner` for `client`. Creates it on the first
+/// lookup.
+fn hosted_portal(
+    owner: ProcessId,
+    owner_sels: ProcessCapSels,
+    client: ProcessId,
+    entry: u64,
+) -> CapSel {
+    let pt_sel = owner_sels.hosted_service_pt(client);
+    if HOSTED_PORTALS.lock().insert((owner, client)) {
+        sys_create_pt(
+            pt_sel,
+            owner_sels.pd(),
+            owner_sels.hosted_service_ec(),
+            Mtd::empty(),
+            entry as *const u64,
+        )
+        .unwrap();
+        // the service recognizes its clients by the portal identifier
+        sys_pt_ctrl(pt_sel, client).unwrap();
+    }
+    pt_sel
+}
+
+fn pt_item(pt_sel: CapSel) -> TypedItem {
+    TypedItem::delegate(
+        CrdObjPT::new(pt_sel, 0, PTCapPermissions::CALL),
+        DelegateFlags::default(),
+    )
+}
+
+/// User apps can't register the names of the roottask services.
+fn check_name(name: &str) -> NameServiceResponse {
+    if is_valid_service_name(name) && builtin_service(name).is_none() {
+        Ok(())
+    } else {
+        Err(NameServiceError::InvalidName)
+    }
+}
+
+fn is_running(pid: ProcessId) -> bool {
+    exit_status(pid).is_none()
+}
+
+/// Returns the names of the services of the roottask.
+pub fn builtin_services() -> &'static [(&'static str, ServiceId)] {
+    &BUILTIN_SERVICES
+}
+
+fn builtin_service(name: &str) -> Option<ServiceId> {
+    BUILTIN_SERVICES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, service)| *service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(sel: CapSel) -> Registration {
+        Registration {
+            portal: Portal::Item(pt_item(sel)),
+            access: ServiceAccess::Everyone,
+        }
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::new();
+        let running = |_| true;
+        registry
+            .register(1, String::from("kv-store"), item(300), running)
+            .unwrap();
+        assert_eq!(
+            registry.register(2, String::from("kv-store"), item(301), running),
+            Err(NameServiceError::NameTaken)
+        );
+        // the owner may replace its own portal
+        registry
+            .register(1, String::from("kv-store"), item(302), running)
+            .unwrap();
+        assert_eq!(registry.lookup("kv-store"), Some((1, item(302))));
+        assert_eq!(registry.lookup("other"), None);
+
+        assert_eq!(
+            registry.unregister(2, "kv-store"),
+            Err(NameServiceError::PermissionDenied)
+        );
+        registry.unregister(1, "kv-store").unwrap();
+        assert_eq!(
+            registry.unregister(1, "kv-store"),
+            Err(NameServiceError::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_registry_exited_owner() {
+        let mut registry = Registry::new();
+        registry
+            .register(1, String::from("a"), item(300), |_| true)
+            .unwrap();
+        registry
+            .register(1, String::from("b"), item(301), |_| true)
+            .unwrap();
+        // the name of an exited process can be taken over
+        registry
+            .register(2, String::from("a"), item(400), |pid| pid != 1)
+            .unwrap();
+        registry.unregister_all(1);
+        assert_eq!(registry.lookup("a"), Some((2, item(400))));
+        assert_eq!(registry.lookup("b"), None);
+    }
+
+    #[test]
+    fn test_builtin_services_are_valid_names() {
+        assert!(BUILTIN_SERVICES
+            .iter()
+            .all(|(name, _)| is_valid_service_name(name)));
+        assert!(matches!(
+            builtin_service("fs"),
+            Some(ServiceId::FileSystemService)
+        ));
+        assert!(builtin_service("name").is_none());
+        assert_eq!(check_name("fs"), Err(NameServiceError::InvalidName));
+        assert_eq!(check_name("fileserver"), Ok(()));
+    }
+}
@@ -0,0 +1,275 @@
+//! Network service. User processes can send and receive raw Ethernet frames and use UDP
+//! and TCP sockets. The roottask drives a virtio-net device (see [`VirtioNet`]) and runs a
+//! minimal IPv4 network stack on top of it (see [`NetworkStack`]).
+//!
+//! The network stack has a static configuration that matches the user mode network of
+//! QEMU (`-netdev user`). Linux processes reach the sockets via the regular socket system
+//! calls with `AF_INET`.
+
+mod stack;
+mod tcp;
+mod wire;
+
+use crate::hw::pci::PciConfigSpace;
+use crate::hw::virtio_net::VirtioNet;
+use crate::process::Process;
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::time;
+use alloc::rc::Rc;
+use core::cmp::min;
+use libfileserver::{
+    FileDescriptor,
+    Readiness,
+};
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FD;
+use libhrstd::rt::services::network::{
+    Ipv4Address,
+    NetworkError,
+    NetworkServiceReply,
+    NetworkServiceRequest,
+    NetworkServiceResponse,
+    SocketAddrV4,
+    NETWORK_MAX_IPC_DATA,
+};
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+pub use stack::{
+    NetworkStack,
+    MAX_UDP_PAYLOAD,
+};
+
+/// Address of the roottask in the user mode network of QEMU.
+const HOST_ADDR: Ipv4Address = Ipv4Address([10, 0, 2, 15]);
+const PREFIX_LEN: u8 = 24;
+/// The gateway of the user mode network of QEMU, which forwards traffic to the host.
+const GATEWAY_ADDR: Ipv4Address = Ipv4Address([10, 0, 2, 2]);
+
+/// The network stack. `None` if the system has no supported network device.
+///
+/// If both are required, lock this before [`libfileserver::FILESYSTEM`].
+static NETWORK: SimpleMutex<Option<NetworkStack<VirtioNet>>> = SimpleMutex::new(None);
+
+/// Searches a network device and sets up the network stack. Without a device, the
+/// network service replies with [`NetworkError::NoDevice`] to all requests.
+pub fn init(root: &Rc<Process>) {
+    let pci = match PciConfigSpace::new(RootCapSpace::RootPd.val()) {
+        Ok(pci) => pci,
+        Err(_) => {
+            log::warn!("can't access the PCI configuration space; no network");
+            return;
+        }
+    };
+    match VirtioNet::init(&pci, root) {
+        Some(device) => {
+            let stack = NetworkStack::new(device, HOST_ADDR, PREFIX_LEN, GATEWAY_ADDR);
+            log::info!("network is up: {:?}", stack.config());
+            NETWORK.lock().replace(stack);
+        }
+        None => log::info!("no network device found"),
+    }
+}
+
+/// Creates a new NETWORK service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::NetworkService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the NETWORK Portal.
+pub fn network_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<NetworkServiceRequest>().unwrap();
+    let response = handle_request(&request, process.pid());
+    utcb.store_data(&response).unwrap();
+    *do_reply = true;
+}
+
+fn handle_request(request: &NetworkServiceRequest, pid: ProcessId) -> NetworkServiceResponse {
+    with_network_stack(|stack| {
+        let reply = match request {
+            NetworkServiceRequest::Config => NetworkServiceReply::Config(stack.config()),
+            NetworkServiceRequest::SendFrame(frame) => {
+                stack.send_frame(frame)?;
+                NetworkServiceReply::Done
+            }
+            NetworkServiceRequest::ReceiveFrame => {
+                NetworkServiceReply::Frame(stack.receive_frame()?)
+            }
+            NetworkServiceRequest::UdpSocket => {
+                NetworkServiceReply::Fd(to_fd(open_udp_socket(stack, pid)))
+            }
+            NetworkServiceRequest::UdpBind { fd, addr } => {
+                stack.udp_bind(pid, from_fd(*fd), *addr)?;
+                NetworkServiceReply::Done
+            }
+            NetworkServiceRequest::UdpConnect { fd, addr } => {
+                stack.udp_connect(pid, from_fd(*fd), *addr)?;
+                NetworkServiceReply::Done
+            }
+            NetworkServiceRequest::UdpSend { fd, data, dest } => {
+                NetworkServiceReply::Sent(stack.udp_send(pid, from_fd(*fd), data, *dest)?)
+            }
+            NetworkServiceRequest::UdpRecv { fd, max_len } => {
+                // the reply must fit into the UTCB
+                let max_len = min(*max_len, NETWORK_MAX_IPC_DATA);
+                let (data, from) = stack.udp_recv(pid, from_fd(*fd), max_len)?;
+                NetworkServiceReply::Received { data, from }
+            }
+            NetworkServiceRequest::TcpSocket => {
+                NetworkServiceReply::Fd(to_fd(open_tcp_socket(stack, pid)))
+            }
+            NetworkServiceRequest::TcpBind { fd, addr } => {
+                stack.tcp_bind(pid, from_fd(*fd), *addr)?;
+                NetworkServiceReply::Done
+            }
+            NetworkServiceRequest::TcpListen { fd, backlog } => {
+                stack.tcp_listen(pid, from_fd(*fd), *backlog)?;
+                NetworkServiceReply::Done
+            }
+            NetworkServiceRequest::TcpAccept { fd } => {
+                let (fd, peer) = accept_tcp_connection(stack, pid, from_fd(*fd))?;
+                NetworkServiceReply::Accepted {
+                    fd: to_fd(fd),
+                    peer,
+                }
+            }
+            NetworkServiceRequest::TcpConnect { fd, addr } => {
+                stack.tcp_connect(pid, from_fd(*fd), *addr)?;
+                NetworkServiceReply::Done
+            }
+            NetworkServiceRequest::TcpSend { fd, data } => {
+                NetworkServiceReply::Sent(stack.tcp_send(pid, from_fd(*fd), data)?)
+            }
+            NetworkServiceRequest::TcpRecv { fd, max_len } => {
+                // the reply must fit into the UTCB
+                let max_len = min(*max_len, NETWORK_MAX_IPC_DATA);
+                let (data, from) = stack.tcp_recv(pid, from_fd(*fd), max_len)?;
+                NetworkServiceReply::Received { data, from }
+            }
+        };
+        Ok(reply)
+    })
+}
+
+/// Runs `f` with the network stack. Fails with [`NetworkError::NoDevice`] if the system
+/// has no network device. Handles the timeouts of TCP first; the stack has no timer of its
+/// own.
+pub fn with_network_stack<T>(
+    f: impl FnOnce(&mut NetworkStack<VirtioNet>) -> Result<T, NetworkError>,
+) -> Result<T, NetworkError> {
+    let mut network = NETWORK.lock();
+    let stack = network.as_mut().ok_or(NetworkError::NoDevice)?;
+    stack.update_time(time::monotonic_ns());
+    f(stack)
+}
+
+/// Creates a UDP socket. The file server reserves the file descriptor for it.
+pub fn udp_socket(pid: ProcessId) -> Result<FileDescriptor, NetworkError> {
+    with_network_stack(|stack| Ok(open_udp_socket(stack, pid)))
+}
+
+/// Checks if the file descriptor of the process refers to a UDP socket.
+pub fn is_udp_socket(pid: ProcessId, fd: FileDescriptor) -> bool {
+    with_network_stack(|stack| Ok(stack.is_udp_socket(pid, fd))).unwrap_or(false)
+}
+
+/// Creates a TCP socket. The file server reserves the file descriptor for it.
+pub fn tcp_socket(pid: ProcessId) -> Result<FileDescriptor, NetworkError> {
+    with_network_stack(|stack| Ok(open_tcp_socket(stack, pid)))
+}
+
+/// Checks if the file descriptor of the process refers to a TCP socket.
+pub fn is_tcp_socket(pid: ProcessId, fd: FileDescriptor) -> bool {
+    with_network_stack(|stack| Ok(stack.is_tcp_socket(pid, fd))).unwrap_or(false)
+}
+
+/// Takes the next connection of a listening TCP socket. The file server reserves the file
+/// descriptor for it. Returns the file descriptor and the peer.
+pub fn tcp_accept(
+    pid: ProcessId,
+    fd: FileDescriptor,
+) -> Result<(FileDescriptor, SocketAddrV4), NetworkError> {
+    with_network_stack(|stack| accept_tcp_connection(stack, pid, fd))
+}
+
+/// Tells which operations on the UDP or TCP socket don't block. UDP sockets are always
+/// writable. `None` if the file descriptor doesn't refer to a socket of the network stack.
+pub fn socket_readiness(pid: ProcessId, fd: FileDescriptor) -> Option<Readiness> {
+    with_network_stack(|stack| {
+        if stack.is_tcp_socket(pid, fd) {
+            return stack.tcp_readiness(pid, fd);
+        }
+        Ok(Readiness {
+            readable: stack.udp_readable(pid, fd)?,
+            writable: true,
+            hang_up: false,
+        })
+    })
+    .ok()
+}
+
+/// Destroys the UDP or TCP socket behind a file descriptor that was closed. Does nothing
+/// if the file descriptor doesn't refer to a socket of the network stack.
+pub fn close_socket(pid: ProcessId, fd: FileDescriptor) {
+    let _ = with_network_stack(|stack| {
+        stack.close(pid, fd);
+        Ok(())
+    });
+}
+
+/// Destroys all UDP and TCP sockets of a process that exited.
+pub fn release_process(pid: ProcessId) {
+    let _ = with_network_stack(|stack| {
+        stack.close_all(pid);
+        Ok(())
+    });
+}
+
+fn open_udp_socket(stack: &mut NetworkStack<VirtioNet>, pid: ProcessId) -> FileDescriptor {
+    let fd = libfileserver::FILESYSTEM.lock().reserve_fd(pid);
+    stack.udp_socket(pid, fd);
+    fd
+}
+
+fn open_tcp_socket(stack: &mut NetworkStack<VirtioNet>, pid: ProcessId) -> FileDescriptor {
+    let fd = libfileserver::FILESYSTEM.lock().reserve_fd(pid);
+    stack.tcp_socket(pid, fd);
+    fd
+}
+
+fn accept_tcp_connection(
+    stack: &mut NetworkStack<VirtioNet>,
+    pid: ProcessId,
+    fd: FileDescriptor,
+) -> Result<(FileDescriptor, SocketAddrV4), NetworkError> {
+    stack.tcp_accept(pid, fd, || libfileserver::FILESYSTEM.lock().reserve_fd(pid))
+}
+
+fn from_fd(fd: FD) -> FileDescriptor {
+    (fd.raw() as u64).into()
+}
+
+fn to_fd(fd: FileDescriptor) -> FD {
+    FD::new(fd.val() as i32)
+}
@@ -0,0 +1,1272 @@
+//! Module for [`NetworkStack`].
+
+use super::tcp::{
+    OutSegment,
+    TcpConnection,
+    TcpState,
+};
+use super::wire::{
+    ArpPacket,
+    EthernetFrame,
+    Ipv4Packet,
+    TcpSegment,
+    UdpDatagram,
+    ARP_OP_REPLY,
+    ARP_OP_REQUEST,
+    ETHER_TYPE_ARP,
+    ETHER_TYPE_IPV4,
+    IPV4_HEADER_SIZE,
+    IP_PROTOCOL_TCP,
+    IP_PROTOCOL_UDP,
+    MTU,
+    TCP_FLAG_ACK,
+    TCP_FLAG_RST,
+    TCP_FLAG_SYN,
+    UDP_HEADER_SIZE,
+};
+use crate::hw::net::NetDevice;
+use alloc::collections::{
+    BTreeMap,
+    VecDeque,
+};
+use alloc::vec::Vec;
+use core::cmp::{
+    max,
+    min,
+};
+use core::ops::RangeInclusive;
+use libfileserver::{
+    FileDescriptor,
+    Readiness,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::network::{
+    Ipv4Address,
+    MacAddress,
+    NetworkConfig,
+    NetworkError,
+    SocketAddrV4,
+};
+
+/// Maximum payload of a UDP datagram. Fragmentation is not supported, therefore each
+/// datagram must fit into a single frame.
+pub const MAX_UDP_PAYLOAD: usize = MTU - IPV4_HEADER_SIZE - UDP_HEADER_SIZE;
+
+/// Ports that unbound sockets get when they send the first datagram or connect.
+const EPHEMERAL_PORTS: RangeInclusive<u16> = 49152..=u16::MAX;
+
+/// Maximum number of received frames that wait for [`NetworkStack::receive_frame`].
+/// Older frames get dropped.
+const RAW_FRAME_QUEUE_CAPACITY: usize = 64;
+/// Maximum number of datagrams in the receive queue of a socket. Further datagrams
+/// get dropped.
+const SOCKET_QUEUE_CAPACITY: usize = 64;
+/// Maximum number of IPv4 packets that wait for the ARP reply of their next hop.
+const ARP_PENDING_CAPACITY: usize = 16;
+
+/// Minimal IPv4 network stack on top of a [`NetDevice`]. It speaks ARP, UDP, and TCP
+/// and has a static configuration. See [`TcpConnection`] for the limits of TCP.
+///
+/// Sockets are identified by a process and a file descriptor. The file server hands out
+/// the file descriptors, so that they don't collide with files and local sockets.
+///
+/// The stack has no thread of its own. It processes received frames whenever someone
+/// calls into it, and the timeouts of TCP whenever [`Self::update_time`] gets called.
+/// Like the file server, it never blocks.
+#[derive(Debug)]
+pub struct NetworkStack<D: NetDevice> {
+    device: D,
+    config: NetworkConfig,
+    arp_cache: BTreeMap<Ipv4Address, MacAddress>,
+    /// IPv4 packets that wait for the ARP reply of their next hop.
+    arp_pending: Vec<(Ipv4Address, Vec<u8>)>,
+    /// Received frames that the stack didn't consume itself.
+    raw_frames: VecDeque<Vec<u8>>,
+    udp_sockets: BTreeMap<(ProcessId, FileDescriptor), UdpSocket>,
+    /// Maps bound UDP ports to their sockets.
+    udp_ports: BTreeMap<u16, (ProcessId, FileDescriptor)>,
+    tcp_sockets: BTreeMap<(ProcessId, FileDescriptor), TcpSocket>,
+    /// Maps bound TCP ports to their sockets. Accepted connections use the port of the
+    /// listening socket.
+    tcp_ports: BTreeMap<u16, (ProcessId, FileDescriptor)>,
+    /// TCP connections by local port and peer. They outlive their socket until the peer
+    /// acknowledged the end of the connection.
+    tcp_conns: BTreeMap<TcpConnKey, TcpConnection>,
+    next_ephemeral_port: u16,
+    next_ip_id: u16,
+    /// The time of the last [`Self::update_time`].
+    now_ns: u64,
+}
+
+impl<D: NetDevice> NetworkStack<D> {
+    pub fn new(device: D, addr: Ipv4Address, prefix_len: u8, gateway: Ipv4Address) -> Self {
+        let config = NetworkConfig {
+            mac: device.mac(),
+            addr,
+            prefix_len,
+            gateway,
+        };
+        Self {
+            device,
+            config,
+            arp_cache: BTreeMap::new(),
+            arp_pending: Vec::new(),
+            raw_frames: VecDeque::new(),
+            udp_sockets: BTreeMap::new(),
+            udp_ports: BTreeMap::new(),
+            tcp_sockets: BTreeMap::new(),
+            tcp_ports: BTreeMap::new(),
+            tcp_conns: BTreeMap::new(),
+            next_ephemeral_port: *EPHEMERAL_PORTS.start(),
+            next_ip_id: 0,
+            now_ns: 0,
+        }
+    }
+
+    pub fn config(&self) -> NetworkConfig {
+        self.config
+    }
+
+    /// Processes all frames that the device received so far.
+    pub fn poll(&mut self) {
+        while let Some(frame) = self.device.receive() {
+            self.process_frame(frame);
+        }
+    }
+
+    /// Advances the clock of the stack to `now_ns`, e.g. the monotonic time. Handles the
+    /// timeouts of the TCP connections, such as retransmissions.
+    pub fn update_time(&mut self, now_ns: u64) {
+        self.now_ns = now_ns;
+        let mut segments = Vec::new();
+        for (key, conn) in self.tcp_conns.iter_mut() {
+            let mut out = Vec::new();
+            conn.on_timer(now_ns, &mut out);
+            if !out.is_empty() {
+                segments.push((*key, out));
+            }
+        }
+        for (key, out) in segments {
+            self.send_tcp(key, out);
+        }
+        self.tcp_conns.retain(|_, conn| !conn.is_gone());
+    }
+
+    /// Sends a raw Ethernet frame.
+    pub fn send_frame(&mut self, frame: &[u8]) -> Result<(), NetworkError> {
+        self.device.transmit(frame)
+    }
+
+    /// Takes the oldest received frame that the stack didn't consume itself.
+    pub fn receive_frame(&mut self) -> Result<Vec<u8>, NetworkError> {
+        self.poll();
+        self.raw_frames.pop_front().ok_or(NetworkError::WouldBlock)
+    }
+
+    /// Checks if the file descriptor of the process refers to a UDP socket.
+    pub fn is_udp_socket(&self, pid: ProcessId, fd: FileDescriptor) -> bool {
+        self.udp_sockets.contains_key(&(pid, fd))
+    }
+
+    /// Creates an unbound UDP socket for a file descriptor that the file server
+    /// reserved for the process.
+    pub fn udp_socket(&mut self, pid: ProcessId, fd: FileDescriptor) {
+        self.udp_sockets.insert((pid, fd), UdpSocket::default());
+    }
+
+    /// Binds a socket to a local port. Port zero selects a free port.
+    pub fn udp_bind(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        addr: SocketAddrV4,
+    ) -> Result<(), NetworkError> {
+        if !addr.addr.is_unspecified() && addr.addr != self.config.addr {
+            return Err(NetworkError::AddressNotAvailable);
+        }
+        if self.udp_socket_mut(pid, fd)?.port.is_some() {
+            return Err(NetworkError::InvalidArgument);
+        }
+        let port = match addr.port {
+            0 => self.free_port(IP_PROTOCOL_UDP)?,
+            port if self.udp_ports.contains_key(&port) => return Err(NetworkError::AddressInUse),
+            port => port,
+        };
+        self.udp_ports.insert(port, (pid, fd));
+        self.udp_socket_mut(pid, fd)?.port.replace(port);
+        Ok(())
+    }
+
+    /// Sets the default destination of a socket. Afterwards, the socket only receives
+    /// datagrams from this address.
+    pub fn udp_connect(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        addr: SocketAddrV4,
+    ) -> Result<(), NetworkError> {
+        if addr.port == 0 {
+            return Err(NetworkError::InvalidArgument);
+        }
+        self.bind_if_unbound(pid, fd)?;
+        self.udp_socket_mut(pid, fd)?.peer.replace(addr);
+        Ok(())
+    }
+
+    /// Sends a datagram. Returns the number of sent bytes. If the hardware address of the
+    /// next hop is unknown, the datagram waits for the ARP reply.
+    pub fn udp_send(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        data: &[u8],
+        dest: Option<SocketAddrV4>,
+    ) -> Result<usize, NetworkError> {
+        let dest = dest
+            .or(self.udp_socket_mut(pid, fd)?.peer)
+            .ok_or(NetworkError::DestinationRequired)?;
+        if data.len() > MAX_UDP_PAYLOAD {
+            return Err(NetworkError::MessageTooLong);
+        }
+        let src_port = self.bind_if_unbound(pid, fd)?;
+        let datagram = UdpDatagram {
+            src_port,
+            dst_port: dest.port,
+            payload: data,
+        }
+        .emit(self.config.addr, dest.addr);
+        self.send_ipv4(dest.addr, IP_PROTOCOL_UDP, &datagram)?;
+        Ok(data.len())
+    }
+
+    /// Receives the oldest datagram and its sender. The part of the datagram that exceeds
+    /// `max_len` gets discarded.
+    pub fn udp_recv(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        max_len: usize,
+    ) -> Result<(Vec<u8>, SocketAddrV4), NetworkError> {
+        self.poll();
+        let (from, mut data) = self
+            .udp_socket_mut(pid, fd)?
+            .rx_queue
+            .pop_front()
+            .ok_or(NetworkError::WouldBlock)?;
+        data.truncate(max_len);
+        Ok((data, from))
+    }
+
+    /// Checks if the socket received a datagram, i.e. if [`Self::udp_recv`] doesn't fail
+    /// with [`NetworkError::WouldBlock`].
+    pub fn udp_readable(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<bool, NetworkError> {
+        self.poll();
+        Ok(!self.udp_socket_mut(pid, fd)?.rx_queue.is_empty())
+    }
+
+    /// Destroys the UDP socket, if the file descriptor refers to one.
+    pub fn udp_close(&mut self, pid: ProcessId, fd: FileDescriptor) {
+        if let Some(port) = self
+            .udp_sockets
+            .remove(&(pid, fd))
+            .and_then(|socket| socket.port)
+        {
+            self.udp_ports.remove(&port);
+        }
+    }
+
+    /// Checks if the file descriptor of the process refers to a TCP socket.
+    pub fn is_tcp_socket(&self, pid: ProcessId, fd: FileDescriptor) -> bool {
+        self.tcp_sockets.contains_key(&(pid, fd))
+    }
+
+    /// Creates an unconnected TCP socket for a file descriptor that the file server
+    /// reserved for the process.
+    pub fn tcp_socket(&mut self, pid: ProcessId, fd: FileDescriptor) {
+        self.tcp_sockets
+            .insert((pid, fd), TcpSocket::Unconnected { port: None });
+    }
+
+    /// Binds an unconnected TCP socket to a local port. Port zero selects a free port.
+    pub fn tcp_bind(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        addr: SocketAddrV4,
+    ) -> Result<(), NetworkError> {
+        if !addr.addr.is_unspecified() && addr.addr != self.config.addr {
+            return Err(NetworkError::AddressNotAvailable);
+        }
+        if !matches!(
+            self.tcp_socket_mut(pid, fd)?,
+            TcpSocket::Unconnected { port: None }
+        ) {
+            return Err(NetworkError::InvalidArgument);
+        }
+        let port = match addr.port {
+            0 => self.free_port(IP_PROTOCOL_TCP)?,
+            port if self.tcp_ports.contains_key(&port) => return Err(NetworkError::AddressInUse),
+            port => port,
+        };
+        self.tcp_ports.insert(port, (pid, fd));
+        *self.tcp_socket_mut(pid, fd)? = TcpSocket::Unconnected { port: Some(port) };
+        Ok(())
+    }
+
+    /// Lets the TCP socket accept connections. At most `backlog` connections wait for
+    /// [`Self::tcp_accept`]; further SYNs get ignored. Listening again only changes the
+    /// backlog.
+    pub fn tcp_listen(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        backlog: usize,
+    ) -> Result<(), NetworkError> {
+        // like Linux, accept at least one connection
+        let backlog = max(backlog, 1);
+        match self.tcp_socket_mut(pid, fd)? {
+            TcpSocket::Unconnected { .. } => {}
+            TcpSocket::Listener {
+                backlog: old_backlog,
+                ..
+            } => {
+                *old_backlog = backlog;
+                return Ok(());
+            }
+            TcpSocket::Stream { .. } => return Err(NetworkError::InvalidArgument),
+        }
+        let port = self.tcp_bind_if_unbound(pid, fd)?;
+        *self.tcp_socket_mut(pid, fd)? = TcpSocket::Listener {
+            port,
+            backlog,
+            pending: VecDeque::new(),
+        };
+        Ok(())
+    }
+
+    /// Takes the oldest connection of the listening socket whose handshake is done. It
+    /// gets the file descriptor that `new_fd` returns. Returns the file descriptor and
+    /// the peer.
+    pub fn tcp_accept(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        new_fd: impl FnOnce() -> FileDescriptor,
+    ) -> Result<(FileDescriptor, SocketAddrV4), NetworkError> {
+        self.poll();
+        let pending = match self.tcp_sockets.get_mut(&(pid, fd)) {
+            Some(TcpSocket::Listener { pending, .. }) => pending,
+            Some(_) => return Err(NetworkError::InvalidArgument),
+            None => return Err(NetworkError::NotASocket),
+        };
+        // forget the connections that failed before they were accepted
+        let conns = &mut self.tcp_conns;
+        pending.retain(|key| {
+            let failed = conns[key].state() == TcpState::Closed;
+            if failed {
+                conns.remove(key);
+            }
+            !failed
+        });
+        let index = pending
+            .iter()
+            .position(|key| self.tcp_conns[key].is_synchronized())
+            .ok_or(NetworkError::WouldBlock)?;
+        let key = pending.remove(index).unwrap();
+
+        let new_fd = new_fd();
+        self.tcp_sockets
+            .insert((pid, new_fd), TcpSocket::Stream { key, bound: false });
+        Ok((new_fd, key.1))
+    }
+
+    /// Connects the TCP socket to the peer. Sends the SYN and fails with
+    /// [`NetworkError::WouldBlock`] until the handshake is done. Afterwards, this succeeds
+    /// as long as the socket is connected to the same peer.
+    pub fn tcp_connect(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        addr: SocketAddrV4,
+    ) -> Result<(), NetworkError> {
+        self.poll();
+        if addr.port == 0 || addr.addr.is_unspecified() {
+            return Err(NetworkError::InvalidArgument);
+        }
+        match *self.tcp_socket_mut(pid, fd)? {
+            TcpSocket::Unconnected { .. } => {}
+            TcpSocket::Listener { .. } => return Err(NetworkError::InvalidArgument),
+            TcpSocket::Stream { key, .. } if key.1 != addr => {
+                return Err(NetworkError::AlreadyConnected)
+            }
+            TcpSocket::Stream { key, .. } => {
+                let conn = &self.tcp_conns[&key];
+                if !conn.is_synchronized() {
+                    return Err(NetworkError::WouldBlock);
+                }
+                return conn.error().map_or(Ok(()), Err);
+            }
+        }
+        let port = self.tcp_bind_if_unbound(pid, fd)?;
+        let key = (port, addr);
+        if self.tcp_conns.contains_key(&key) {
+            // the previous connection between both ports didn't end yet
+            return Err(NetworkError::AddressInUse);
+        }
+        let mut out = Vec::new();
+        let conn = TcpConnection::connect(self.initial_seq(), self.now_ns, &mut out);
+        self.tcp_conns.insert(key, conn);
+        *self.tcp_socket_mut(pid, fd)? = TcpSocket::Stream { key, bound: true };
+        self.send_tcp(key, out);
+        Err(NetworkError::WouldBlock)
+    }
+
+    /// Sends data via a connected TCP socket. Returns the number of bytes that fit into
+    /// the send buffer.
+    pub fn tcp_send(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        data: &[u8],
+    ) -> Result<usize, NetworkError> {
+        self.poll();
+        let key = self.tcp_stream(pid, fd)?;
+        let mut out = Vec::new();
+        let conn = self.tcp_conns.get_mut(&key).unwrap();
+        let res = conn.send(data, self.now_ns, &mut out);
+        self.send_tcp(key, out);
+        res
+    }
+
+    /// Receives at most `max_len` bytes from a connected TCP socket and returns them
+    /// with the peer. No data means that the peer closed the connection.
+    pub fn tcp_recv(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+        max_len: usize,
+    ) -> Result<(Vec<u8>, SocketAddrV4), NetworkError> {
+        self.poll();
+        let key = self.tcp_stream(pid, fd)?;
+        let mut out = Vec::new();
+        let res = self
+            .tcp_conns
+            .get_mut(&key)
+            .unwrap()
+            .recv(max_len, &mut out);
+        self.send_tcp(key, out);
+        Ok((res?, key.1))
+    }
+
+    /// Tells which operations on the TCP socket don't fail with
+    /// [`NetworkError::WouldBlock`]. Listening sockets are readable if a connection can
+    /// be accepted.
+    pub fn tcp_readiness(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<Readiness, NetworkError> {
+        self.poll();
+        let socket = self
+            .tcp_sockets
+            .get(&(pid, fd))
+            .ok_or(NetworkError::NotASocket)?;
+        let readiness = match socket {
+            TcpSocket::Unconnected { .. } => Readiness {
+                hang_up: true,
+                ..Readiness::default()
+            },
+            TcpSocket::Listener { pending, .. } => Readiness {
+                readable: pending
+                    .iter()
+                    .any(|key| self.tcp_conns[key].is_synchronized()),
+                ..Readiness::default()
+            },
+            TcpSocket::Stream { key, .. } => {
+                let conn = &self.tcp_conns[key];
+                Readiness {
+                    readable: conn.is_readable(),
+                    writable: conn.is_writable(),
+                    hang_up: conn.state() == TcpState::Closed,
+                }
+            }
+        };
+        Ok(readiness)
+    }
+
+    /// Destroys the TCP socket, if the file descriptor refers to one. The connection
+    /// sends the remaining data and ends in the background. Connections that a
+    /// listening socket didn't accept yet get reset.
+    pub fn tcp_close(&mut self, pid: ProcessId, fd: FileDescriptor) {
+        let (port, keys) = match self.tcp_sockets.remove(&(pid, fd)) {
+            None => return,
+            Some(TcpSocket::Unconnected { port }) => (port, Vec::new()),
+            Some(TcpSocket::Listener { port, pending, .. }) => {
+                (Some(port), pending.into_iter().collect())
+            }
+            Some(TcpSocket::Stream { key, bound }) => (bound.then(|| key.0), vec![key]),
+        };
+        if let Some(port) = port {
+            self.tcp_ports.remove(&port);
+        }
+        for key in keys {
+            let mut out = Vec::new();
+            let conn = self.tcp_conns.get_mut(&key).unwrap();
+            conn.close(self.now_ns, &mut out);
+            self.send_tcp(key, out);
+        }
+        self.tcp_conns.retain(|_, conn| !conn.is_gone());
+    }
+
+    /// Destroys the UDP or TCP socket, if the file descriptor refers to one.
+    pub fn close(&mut self, pid: ProcessId, fd: FileDescriptor) {
+        self.udp_close(pid, fd);
+        self.tcp_close(pid, fd);
+    }
+
+    /// Destroys all sockets of a process that exited.
+    pub fn close_all(&mut self, pid: ProcessId) {
+        let fds = self
+            .udp_sockets
+            .keys()
+            .chain(self.tcp_sockets.keys())
+            .filter(|(socket_pid, _)| *socket_pid == pid)
+            .map(|(_, fd)| *fd)
+            .collect::<Vec<_>>();
+        for fd in fds {
+            self.close(pid, fd);
+        }
+    }
+
+    fn udp_socket_mut(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<&mut UdpSocket, NetworkError> {
+        self.udp_sockets
+            .get_mut(&(pid, fd))
+            .ok_or(NetworkError::NotASocket)
+    }
+
+    fn tcp_socket_mut(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<&mut TcpSocket, NetworkError> {
+        self.tcp_sockets
+            .get_mut(&(pid, fd))
+            .ok_or(NetworkError::NotASocket)
+    }
+
+    /// Returns the connection of a connected TCP socket.
+    fn tcp_stream(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<TcpConnKey, NetworkError> {
+        match self.tcp_socket_mut(pid, fd)? {
+            TcpSocket::Stream { key, .. } => Ok(*key),
+            _ => Err(NetworkError::NotConnected),
+        }
+    }
+
+    /// Returns the local port of an unconnected TCP socket and binds it to a free port
+    /// first, if necessary.
+    fn tcp_bind_if_unbound(
+        &mut self,
+        pid: ProcessId,
+        fd: FileDescriptor,
+    ) -> Result<u16, NetworkError> {
+        if let TcpSocket::Unconnected { port: Some(port) } = self.tcp_socket_mut(pid, fd)? {
+            return Ok(*port);
+        }
+        self.tcp_bind(pid, fd, SocketAddrV4::new(Ipv4Address::UNSPECIFIED, 0))?;
+        self.tcp_bind_if_unbound(pid, fd)
+    }
+
+    /// Initial sequence number of a new TCP connection. Derived from the clock, as in
+    /// RFC 9293, so that it differs from the one of a previous connection.
+    fn initial_seq(&self) -> u32 {
+        (self.now_ns / 4_000) as u32
+    }
+
+    /// Returns the local port of the socket and binds it to a free port first, if necessary.
+    fn bind_if_unbound(&mut self, pid: ProcessId, fd: FileDescriptor) -> Result<u16, NetworkError> {
+        if let Some(port) = self.udp_socket_mut(pid, fd)?.port {
+            return Ok(port);
+        }
+        self.udp_bind(pid, fd, SocketAddrV4::new(Ipv4Address::UNSPECIFIED, 0))?;
+        Ok(self.udp_socket_mut(pid, fd)?.port.unwrap())
+    }
+
+    /// Returns a free ephemeral port of the protocol.
+    fn free_port(&mut self, protocol: u8) -> Result<u16, NetworkError> {
+        let ports = if protocol == IP_PROTOCOL_TCP {
+            &self.tcp_ports
+        } else {
+            &self.udp_ports
+        };
+        let port_count = EPHEMERAL_PORTS.len();
+        for _ in 0..port_count {
+            let port = self.next_ephemeral_port;
+            self.next_ephemeral_port = if port == *EPHEMERAL_PORTS.end() {
+                *EPHEMERAL_PORTS.start()
+            } else {
+                port + 1
+            };
+            if !ports.contains_key(&port) {
+                return Ok(port);
+            }
+        }
+        Err(NetworkError::AddressInUse)
+    }
+
+    fn send_ipv4(
+        &mut self,
+        dst: Ipv4Address,
+        protocol: u8,
+        payload: &[u8],
+    ) -> Result<(), NetworkError> {
+        let packet = Ipv4Packet {
+            src: self.config.addr,
+            dst,
+            protocol,
+            payload,
+        }
+        .emit(self.next_ip_id);
+        self.next_ip_id = self.next_ip_id.wrapping_add(1);
+
+        if dst == Ipv4Address::BROADCAST {
+            return self.send_ethernet(MacAddress::BROADCAST, ETHER_TYPE_IPV4, &packet);
+        }
+        let next_hop = if dst.is_in_same_network(self.config.addr, self.config.prefix_len) {
+            dst
+        } else {
+            self.config.gateway
+        };
+        match self.arp_cache.get(&next_hop) {
+            Some(mac) => self.send_ethernet(*mac, ETHER_TYPE_IPV4, &packet),
+            None => {
+                if self.arp_pending.len() >= ARP_PENDING_CAPACITY {
+                    return Err(NetworkError::WouldBlock);
+                }
+                self.arp_pending.push((next_hop, packet));
+                self.send_arp(
+                    ARP_OP_REQUEST,
+                    MacAddress::BROADCAST,
+                    MacAddress([0; 6]),
+                    next_hop,
+                )
+            }
+        }
+    }
+
+    fn send_arp(
+        &mut self,
+        operation: u16,
+        dst: MacAddress,
+        target_mac: MacAddress,
+        target_addr: Ipv4Address,
+    ) -> Result<(), NetworkError> {
+        let packet = ArpPacket {
+            operation,
+            sender_mac: self.config.mac,
+            sender_addr: self.config.addr,
+            target_mac,
+            target_addr,
+        }
+        .emit();
+        self.send_ethernet(dst, ETHER_TYPE_ARP, &packet)
+    }
+
+    fn send_ethernet(
+        &mut self,
+        dst: MacAddress,
+        ether_type: u16,
+        payload: &[u8],
+    ) -> Result<(), NetworkError> {
+        let frame = EthernetFrame {
+            dst,
+            src: self.config.mac,
+            ether_type,
+            payload,
+        }
+        .emit();
+        self.device.transmit(&frame)
+    }
+
+    /// Sends the segments of a TCP connection.
+    fn send_tcp(&mut self, (port, peer): TcpConnKey, segments: Vec<OutSegment>) {
+        for segment in segments {
+            let bytes = TcpSegment {
+                src_port: port,
+                dst_port: peer.port,
+                seq: segment.seq,
+                ack: segment.ack,
+                flags: segment.flags,
+                window: segment.window,
+                payload: &segment.payload,
+            }
+            .emit(self.config.addr, peer.addr);
+            // lost segments get retransmitted
+            let _ = self.send_ipv4(peer.addr, IP_PROTOCOL_TCP, &bytes);
+        }
+    }
+
+    fn process_frame(&mut self, bytes: Vec<u8>) {
+        let consumed = match EthernetFrame::parse(&bytes) {
+            Some(frame) if frame.dst != self.config.mac && !frame.dst.is_broadcast() => true,
+            Some(frame) if frame.ether_type == ETHER_TYPE_ARP => {
+                ArpPacket::parse(frame.payload).map_or(false, |packet| self.process_arp(packet))
+            }
+            Some(frame) if frame.ether_type == ETHER_TYPE_IPV4 => {
+                Ipv4Packet::parse(frame.payload).map_or(false, |packet| self.process_ipv4(packet))
+            }
+            Some(_) => false,
+            // malformed
+            None => true,
+        };
+        if !consumed {
+            if self.raw_frames.len() >= RAW_FRAME_QUEUE_CAPACITY {
+                self.raw_frames.pop_front();
+            }
+            self.raw_frames.push_back(bytes);
+        }
+    }
+
+    /// Answers requests for the own address and learns the hardware addresses of hosts
+    /// that talk to us. Returns whether the packet was meant for the stack.
+    fn process_arp(&mut self, packet: ArpPacket) -> bool {
+        if packet.target_addr != self.config.addr {
+            return false;
+        }
+        self.arp_cache.insert(packet.sender_addr, packet.sender_mac);
+        if packet.operation == ARP_OP_REQUEST {
+            // best effort, like everything in ARP
+            let _ = self.send_arp(
+                ARP_OP_REPLY,
+                packet.sender_mac,
+                packet.sender_mac,
+                packet.sender_addr,
+            );
+        }
+
+        let (ready, waiting) = core::mem::take(&mut self.arp_pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(next_hop, _)| *next_hop == packet.sender_addr);
+        self.arp_pending = waiting;
+        for (_, ip_packet) in ready {
+            let _ = self.send_ethernet(packet.sender_mac, ETHER_TYPE_IPV4, &ip_packet);
+        }
+        true
+    }
+
+    /// Delivers UDP datagrams and TCP segments to the sockets. Returns whether the packet
+    /// was consumed.
+    fn process_ipv4(&mut self, packet: Ipv4Packet) -> bool {
+        if packet.dst != self.config.addr && packet.dst != Ipv4Address::BROADCAST {
+            return true;
+        }
+        match packet.protocol {
+            IP_PROTOCOL_UDP => self.process_udp(packet),
+            IP_PROTOCOL_TCP if packet.dst == self.config.addr => self.process_tcp(packet),
+            _ => false,
+        }
+    }
+
+    fn process_udp(&mut self, packet: Ipv4Packet) -> bool {
+        let datagram = match UdpDatagram::parse(packet.payload, packet.src, packet.dst) {
+            Some(datagram) => datagram,
+            None => return true,
+        };
+        let key = match self.udp_ports.get(&datagram.dst_port) {
+            Some(key) => *key,
+            None => return false,
+        };
+        let from = datagram.src_addr(&packet);
+        let socket = self.udp_sockets.get_mut(&key).unwrap();
+        let from_peer = socket.peer.map_or(true, |peer| peer == from);
+        if from_peer && socket.rx_queue.len() < SOCKET_QUEUE_CAPACITY {
+            let len = min(datagram.payload.len(), MAX_UDP_PAYLOAD);
+            socket
+                .rx_queue
+                .push_back((from, Vec::from(&datagram.payload[..len])));
+        }
+        true
+    }
+
+    /// Passes TCP segments to their connection. SYNs for a listening socket create a new
+    /// connection; other segments for a bound port get a reset. Like UDP datagrams,
+    /// segments for unbound ports are left to the receivers of raw frames.
+    fn process_tcp(&mut self, packet: Ipv4Packet) -> bool {
+        let segment = match TcpSegment::parse(packet.payload, packet.src, packet.dst) {
+            Some(segment) => segment,
+            None => return true,
+        };
+        let key = (
+            segment.dst_port,
+            SocketAddrV4::new(packet.src, segment.src_port),
+        );
+        let mut out = Vec::new();
+        if let Some(conn) = self.tcp_conns.get_mut(&key) {
+            conn.on_segment(&segment, self.now_ns, &mut out);
+            self.send_tcp(key, out);
+            return true;
+        }
+
+        let iss = self.initial_seq();
+        let owner = match self.tcp_ports.get(&key.0) {
+            Some(owner) => *owner,
+            None => return false,
+        };
+        match self.tcp_sockets.get_mut(&owner) {
+            Some(TcpSocket::Listener {
+                backlog, pending, ..
+            }) if is_syn(&segment) => {
+                // if the backlog is full, the peer sends the SYN again later
+                if pending.len() < *backlog {
+                    let conn = TcpConnection::accept(iss, &segment, self.now_ns, &mut out);
+                    pending.push_back(key);
+                    self.tcp_conns.insert(key, conn);
+                }
+            }
+            _ => out.extend(reset_reply(&segment)),
+        }
+        self.send_tcp(key, out);
+        true
+    }
+}
+
+/// Local port and peer of a TCP connection.
+type TcpConnKey = (u16, SocketAddrV4);
+
+/// Checks if the segment opens a connection.
+fn is_syn(segment: &TcpSegment) -> bool {
+    segment.flags & (TCP_FLAG_SYN | TCP_FLAG_ACK | TCP_FLAG_RST) == TCP_FLAG_SYN
+}
+
+/// The reset that answers a segment without connection, as in RFC 9293. Resets never get
+/// answered.
+fn reset_reply(segment: &TcpSegment) -> Option<OutSegment> {
+    if segment.has_flag(TCP_FLAG_RST) {
+        return None;
+    }
+    let (seq, ack, flags) = if segment.has_flag(TCP_FLAG_ACK) {
+        (segment.ack, 0, TCP_FLAG_RST)
+    } else {
+        let ack = segment.seq.wrapping_add(segment.seq_len());
+        (0, ack, TCP_FLAG_RST | TCP_FLAG_ACK)
+    };
+    Some(OutSegment {
+        seq,
+        ack,
+        flags,
+        window: 0,
+        payload: Vec::new(),
+    })
+}
+
+#[derive(Debug)]
+enum TcpSocket {
+    /// Neither listening nor connected, but maybe bound to a port.
+    Unconnected { port: Option<u16> },
+    /// Accepts connections on the port. `pending` holds the connections that were not
+    /// accepted yet, including those whose handshake isn't done.
+    Listener {
+        port: u16,
+        backlog: usize,
+        pending: VecDeque<TcpConnKey>,
+    },
+    /// Connection to a peer. `bound` if the socket owns the local port, i.e. if it
+    /// connected itself instead of being accepted.
+    Stream { key: TcpConnKey, bound: bool },
+}
+
+#[derive(Debug, Default)]
+struct UdpSocket {
+    port: Option<u16>,
+    /// Default destination. Datagrams from other senders get dropped.
+    peer: Option<SocketAddrV4>,
+    rx_queue: VecDeque<(SocketAddrV4, Vec<u8>)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::network::wire::TCP_FLAG_FIN;
+
+    const HOST_MAC: MacAddress = MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+    const HOST_ADDR: Ipv4Address = Ipv4Address([10, 0, 2, 15]);
+    const GATEWAY_MAC: MacAddress = MacAddress([0x52, 0x55, 10, 0, 2, 2]);
+    const GATEWAY_ADDR: Ipv4Address = Ipv4Address([10, 0, 2, 2]);
+
+    /// Device that records sent frames and returns injected frames.
+    #[derive(Debug, Default)]
+    struct TestDevice {
+        sent: VecDeque<Vec<u8>>,
+        received: VecDeque<Vec<u8>>,
+    }
+
+    impl NetDevice for TestDevice {
+        fn mac(&self) -> MacAddress {
+            HOST_MAC
+        }
+
+        fn transmit(&mut self, frame: &[u8]) -> Result<(), NetworkError> {
+            self.sent.push_back(Vec::from(frame));
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Option<Vec<u8>> {
+            self.received.pop_front()
+        }
+    }
+
+    fn create_stack() -> NetworkStack<TestDevice> {
+        NetworkStack::new(TestDevice::default(), HOST_ADDR, 24, GATEWAY_ADDR)
+    }
+
+    fn arp_frame(operation: u16, target_mac: MacAddress) -> Vec<u8> {
+        let packet = ArpPacket {
+            operation,
+            sender_mac: GATEWAY_MAC,
+            sender_addr: GATEWAY_ADDR,
+            target_mac,
+            target_addr: HOST_ADDR,
+        }
+        .emit();
+        EthernetFrame {
+            dst: target_mac,
+            src: GATEWAY_MAC,
+            ether_type: ETHER_TYPE_ARP,
+            payload: &packet,
+        }
+        .emit()
+    }
+
+    fn udp_frame(src: SocketAddrV4, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let datagram = UdpDatagram {
+            src_port: src.port,
+            dst_port,
+            payload,
+        }
+        .emit(src.addr, HOST_ADDR);
+        ipv4_frame(src.addr, IP_PROTOCOL_UDP, &datagram)
+    }
+
+    fn tcp_frame(
+        src: SocketAddrV4,
+        dst_port: u16,
+        seq: u32,
+        ack: u32,
+        flags: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let segment = TcpSegment {
+            src_port: src.port,
+            dst_port,
+            seq,
+            ack,
+            flags,
+            window: 4096,
+            payload,
+        }
+        .emit(src.addr, HOST_ADDR);
+        ipv4_frame(src.addr, IP_PROTOCOL_TCP, &segment)
+    }
+
+    fn ipv4_frame(src: Ipv4Address, protocol: u8, payload: &[u8]) -> Vec<u8> {
+        let packet = Ipv4Packet {
+            src,
+            dst: HOST_ADDR,
+            protocol,
+            payload,
+        }
+        .emit(0);
+        EthernetFrame {
+            dst: HOST_MAC,
+            src: GATEWAY_MAC,
+            ether_type: ETHER_TYPE_IPV4,
+            payload: &packet,
+        }
+        .emit()
+    }
+
+    /// Takes the next sent frame, which must be a TCP segment, and returns its flags,
+    /// sequence number, acknowledgement number, and payload.
+    fn sent_tcp(stack: &mut NetworkStack<TestDevice>) -> (u8, u32, u32, Vec<u8>) {
+        let frame = stack.device.sent.pop_front().unwrap();
+        let frame = EthernetFrame::parse(&frame).unwrap();
+        let packet = Ipv4Packet::parse(frame.payload).unwrap();
+        assert_eq!(packet.protocol, IP_PROTOCOL_TCP);
+        let segment = TcpSegment::parse(packet.payload, packet.src, packet.dst).unwrap();
+        (
+            segment.flags,
+            segment.seq,
+            segment.ack,
+            Vec::from(segment.payload),
+        )
+    }
+
+    #[test]
+    fn test_arp_reply() {
+        let mut stack = create_stack();
+        stack
+            .device
+            .received
+            .push_back(arp_frame(ARP_OP_REQUEST, MacAddress::BROADCAST));
+        stack.poll();
+
+        let reply = stack.device.sent.pop_front().unwrap();
+        let reply = EthernetFrame::parse(&reply).unwrap();
+        assert_eq!(reply.dst, GATEWAY_MAC);
+        assert_eq!(reply.ether_type, ETHER_TYPE_ARP);
+        let reply = ArpPacket::parse(reply.payload).unwrap();
+        assert_eq!(reply.operation, ARP_OP_REPLY);
+        assert_eq!(reply.sender_mac, HOST_MAC);
+        assert_eq!(reply.sender_addr, HOST_ADDR);
+        assert_eq!(reply.target_addr, GATEWAY_ADDR);
+        assert!(
+            stack.receive_frame().is_err(),
+            "ARP is consumed by the stack"
+        );
+    }
+
+    #[test]
+    fn test_udp_send_resolves_next_hop() {
+        let mut stack = create_stack();
+        let fd = FileDescriptor::new(3);
+        stack.udp_socket(1, fd);
+        let dest = SocketAddrV4::new(Ipv4Address([1, 1, 1, 1]), 53);
+        assert_eq!(stack.udp_send(1, fd, b"query", Some(dest)), Ok(5));
+
+        // the gateway is the next hop
+        let request = stack.device.sent.pop_front().unwrap();
+        let request = ArpPacket::parse(EthernetFrame::parse(&request).unwrap().payload).unwrap();
+        assert_eq!(request.operation, ARP_OP_REQUEST);
+        assert_eq!(request.target_addr, GATEWAY_ADDR);
+        assert!(stack.device.sent.is_empty());
+
+        stack
+            .device
+            .received
+            .push_back(arp_frame(ARP_OP_REPLY, HOST_MAC));
+        stack.poll();
+        let frame = stack.device.sent.pop_front().unwrap();
+        let frame = EthernetFrame::parse(&frame).unwrap();
+        assert_eq!(frame.dst, GATEWAY_MAC);
+        let packet = Ipv4Packet::parse(frame.payload).unwrap();
+        assert_eq!(packet.src, HOST_ADDR);
+        assert_eq!(packet.dst, dest.addr);
+        let datagram = UdpDatagram::parse(packet.payload, packet.src, packet.dst).unwrap();
+        assert!(EPHEMERAL_PORTS.contains(&datagram.src_port));
+        assert_eq!(datagram.dst_port, 53);
+        assert_eq!(datagram.payload, b"query");
+
+        // the hardware address is known now
+        stack.udp_send(1, fd, b"again", Some(dest)).unwrap();
+        assert_eq!(stack.device.sent.len(), 1);
+    }
+
+    #[test]
+    fn test_udp_recv() {
+        let mut stack = create_stack();
+        let fd = FileDescriptor::new(3);
+        stack.udp_socket(1, fd);
+        stack
+            .udp_bind(1, fd, SocketAddrV4::new(Ipv4Address::UNSPECIFIED, 1337))
+            .unwrap();
+        assert_eq!(stack.udp_recv(1, fd, 100), Err(NetworkError::WouldBlock));
+        assert_eq!(stack.udp_readable(1, fd), Ok(false));
+
+        let sender = SocketAddrV4::new(GATEWAY_ADDR, 4242);
+        stack
+            .device
+            .received
+            .push_back(udp_frame(sender, 1337, b"hello world"));
+        // nobody listens on this port
+        stack
+            .device
+            .received
+            .push_back(udp_frame(sender, 1338, b"unknown"));
+        assert_eq!(stack.udp_readable(1, fd), Ok(true));
+        let (data, from) = stack.udp_recv(1, fd, 5).unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(from, sender);
+        assert!(stack.receive_frame().is_ok());
+
+        // connected sockets only receive from their peer
+        stack
+            .udp_connect(1, fd, SocketAddrV4::new(GATEWAY_ADDR, 1))
+            .unwrap();
+        stack
+            .device
+            .received
+            .push_back(udp_frame(sender, 1337, b"hello world"));
+        assert_eq!(stack.udp_recv(1, fd, 100), Err(NetworkError::WouldBlock));
+    }
+
+    #[test]
+    fn test_udp_bind() {
+        let mut stack = create_stack();
+        let (a, b) = (FileDescriptor::new(3), FileDescriptor::new(4));
+        stack.udp_socket(1, a);
+        stack.udp_socket(1, b);
+        let addr = SocketAddrV4::new(HOST_ADDR, 1337);
+        stack.udp_bind(1, a, addr).unwrap();
+        assert_eq!(
+            stack.udp_bind(1, a, addr),
+            Err(NetworkError::InvalidArgument)
+        );
+        assert_eq!(stack.udp_bind(1, b, addr), Err(NetworkError::AddressInUse));
+        assert_eq!(
+            stack.udp_bind(1, b, SocketAddrV4::new(GATEWAY_ADDR, 1)),
+            Err(NetworkError::AddressNotAvailable)
+        );
+        assert_eq!(
+            stack.udp_send(1, b, b"data", None),
+            Err(NetworkError::DestinationRequired)
+        );
+        assert_eq!(
+            stack.udp_bind(2, a, addr),
+            Err(NetworkError::NotASocket),
+            "file descriptors are per process"
+        );
+
+        // closing releases the port
+        stack.udp_close(1, a);
+        assert!(!stack.is_udp_socket(1, a));
+        stack.udp_bind(1, b, addr).unwrap();
+    }
+
+    #[test]
+    fn test_tcp_connect() {
+        let mut stack = create_stack();
+        stack.arp_cache.insert(GATEWAY_ADDR, GATEWAY_MAC);
+        let fd = FileDescriptor::new(3);
+        stack.tcp_socket(1, fd);
+        let peer = SocketAddrV4::new(GATEWAY_ADDR, 80);
+        assert_eq!(stack.tcp_send(1, fd, b"x"), Err(NetworkError::NotConnected));
+        assert_eq!(
+            stack.tcp_connect(1, fd, peer),
+            Err(NetworkError::WouldBlock)
+        );
+        let (flags, iss, _, _) = sent_tcp(&mut stack);
+        assert_eq!(flags, TCP_FLAG_SYN);
+        assert_eq!(
+            stack.tcp_connect(1, fd, peer),
+            Err(NetworkError::WouldBlock)
+        );
+        assert!(!stack.tcp_readiness(1, fd).unwrap().writable);
+
+        let port = *EPHEMERAL_PORTS.start();
+        let syn_ack = TCP_FLAG_SYN | TCP_FLAG_ACK;
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(peer, port, 5000, iss + 1, syn_ack, &[]));
+        assert_eq!(stack.tcp_connect(1, fd, peer), Ok(()));
+        assert_eq!(sent_tcp(&mut stack).0, TCP_FLAG_ACK);
+        assert_eq!(
+            stack.tcp_connect(1, fd, SocketAddrV4::new(GATEWAY_ADDR, 81)),
+            Err(NetworkError::AlreadyConnected)
+        );
+
+        assert_eq!(stack.tcp_send(1, fd, b"GET /"), Ok(5));
+        let (_, seq, ack, payload) = sent_tcp(&mut stack);
+        assert_eq!(
+            (seq, ack, payload.as_slice()),
+            (iss + 1, 5001, &b"GET /"[..])
+        );
+
+        // the answer acknowledges the request and closes the connection
+        let fin = TCP_FLAG_ACK | TCP_FLAG_FIN;
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(peer, port, 5001, iss + 6, fin, b"ok"));
+        assert!(stack.tcp_readiness(1, fd).unwrap().readable);
+        assert_eq!(stack.tcp_recv(1, fd, 100), Ok((b"ok".to_vec(), peer)));
+        assert_eq!(stack.tcp_recv(1, fd, 100), Ok((Vec::new(), peer)));
+        assert_eq!(sent_tcp(&mut stack).2, 5004);
+
+        // the connection outlives the socket until the peer acknowledges the FIN
+        stack.tcp_close(1, fd);
+        assert!(!stack.is_tcp_socket(1, fd));
+        let (flags, seq, _, _) = sent_tcp(&mut stack);
+        assert_eq!((flags, seq), (fin, iss + 6));
+        stack.update_time(1);
+        assert_eq!(stack.tcp_conns.len(), 1);
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(peer, port, 5004, iss + 7, TCP_FLAG_ACK, &[]));
+        stack.poll();
+        stack.update_time(2);
+        assert!(stack.tcp_conns.is_empty());
+        assert!(stack.tcp_ports.is_empty());
+    }
+
+    #[test]
+    fn test_tcp_accept() {
+        let mut stack = create_stack();
+        stack.arp_cache.insert(GATEWAY_ADDR, GATEWAY_MAC);
+        let fd = FileDescriptor::new(3);
+        let new_fd = FileDescriptor::new(4);
+        stack.tcp_socket(1, fd);
+        stack
+            .tcp_bind(1, fd, SocketAddrV4::new(HOST_ADDR, 8080))
+            .unwrap();
+        stack.tcp_listen(1, fd, 1).unwrap();
+        assert_eq!(
+            stack.tcp_accept(1, fd, || new_fd),
+            Err(NetworkError::WouldBlock)
+        );
+
+        let peer = SocketAddrV4::new(GATEWAY_ADDR, 40000);
+        let other = SocketAddrV4::new(GATEWAY_ADDR, 40001);
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(peer, 8080, 100, 0, TCP_FLAG_SYN, &[]));
+        // the backlog is full
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(other, 8080, 100, 0, TCP_FLAG_SYN, &[]));
+        assert!(!stack.tcp_readiness(1, fd).unwrap().readable);
+        let (flags, iss, ack, _) = sent_tcp(&mut stack);
+        assert_eq!((flags, ack), (TCP_FLAG_SYN | TCP_FLAG_ACK, 101));
+        assert!(stack.device.sent.is_empty());
+
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(peer, 8080, 101, iss + 1, TCP_FLAG_ACK, b"hi"));
+        assert!(stack.tcp_readiness(1, fd).unwrap().readable);
+        assert_eq!(stack.tcp_accept(1, fd, || new_fd), Ok((new_fd, peer)));
+        assert_eq!(stack.tcp_recv(1, new_fd, 100), Ok((b"hi".to_vec(), peer)));
+
+        // no connection for this segment
+        stack.device.sent.clear();
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(other, 8080, 100, 7, TCP_FLAG_ACK, &[]));
+        stack.poll();
+        let (flags, seq, _, _) = sent_tcp(&mut stack);
+        assert_eq!((flags, seq), (TCP_FLAG_RST, 7));
+
+        // nobody uses this port
+        stack
+            .device
+            .received
+            .push_back(tcp_frame(peer, 8081, 100, 0, TCP_FLAG_SYN, &[]));
+        assert!(stack.receive_frame().is_ok());
+        assert!(stack.device.sent.is_empty());
+    }
+}
@@ -0,0 +1,685 @@
+//! Module for [`TcpConnection`].
+
+use super::wire::{
+    TcpSegment,
+    TCP_FLAG_ACK,
+    TCP_FLAG_FIN,
+    TCP_FLAG_PSH,
+    TCP_FLAG_RST,
+    TCP_FLAG_SYN,
+};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::cmp::{
+    max,
+    min,
+};
+use libhrstd::rt::services::network::NetworkError;
+
+/// Maximum segment size that every host accepts without negotiation (RFC 9293). The
+/// stack sends no options, hence the peer also uses this size.
+pub(super) const MSS: usize = 536;
+/// Capacity of the send buffer and the receive buffer of a connection. The receive
+/// window must fit into 16 bits.
+const BUFFER_CAPACITY: usize = 16 * 1024;
+
+/// Retransmission timeout of the first retransmission. It doubles with each further one.
+const INITIAL_RTO_NS: u64 = 1_000_000_000;
+/// Number of retransmissions of the same segment after which the connection fails.
+const MAX_RETRANSMISSIONS: u32 = 6;
+/// Duration of [`TcpState::TimeWait`]. Much shorter than the two maximum segment
+/// lifetimes of RFC 9293; the stack only talks to the gateway of QEMU.
+const TIME_WAIT_NS: u64 = 1_000_000_000;
+/// Time that a closed connection waits in [`TcpState::FinWait2`] for the FIN of the
+/// peer, like `tcp_fin_timeout` of Linux.
+const FIN_WAIT_2_TIMEOUT_NS: u64 = 60_000_000_000;
+
+/// States of a connection, as in RFC 9293. Listening is a property of the socket, see
+/// [`super::NetworkStack::tcp_listen`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum TcpState {
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    Closed,
+}
+
+/// Segment that a connection sends. The stack adds the ports and addresses.
+#[derive(Debug, PartialEq)]
+pub(super) struct OutSegment {
+    pub(super) seq: u32,
+    pub(super) ack: u32,
+    pub(super) flags: u8,
+    pub(super) window: u16,
+    pub(super) payload: Vec<u8>,
+}
+
+/// A TCP connection without the addresses. The stack feeds it with the received segments
+/// and the current time; all functions append the segments to send to `out`.
+///
+/// It keeps things simple: segments that arrive out of order get dropped, and a
+/// retransmission sends everything from the oldest unacknowledged byte again (go-back-N).
+/// The stack only talks to the gateway of QEMU, which rarely loses segments.
+#[derive(Debug)]
+pub(super) struct TcpConnection {
+    state: TcpState,
+    /// Initial sequence number, i.e. the one of the SYN.
+    iss: u32,
+    /// Oldest sequence number that the peer didn't acknowledge yet.
+    snd_una: u32,
+    /// Next sequence number to send. Goes back to `snd_una` for a retransmission.
+    snd_nxt: u32,
+    /// Highest sequence number that was sent so far, plus one.
+    snd_max: u32,
+    /// Receive window of the peer.
+    snd_wnd: u32,
+    /// Next sequence number that the peer sends.
+    rcv_nxt: u32,
+    /// Data that the peer didn't acknowledge yet. Starts at `snd_una`, once the SYN was
+    /// acknowledged.
+    tx_buffer: VecDeque<u8>,
+    /// Received data that the application didn't read yet.
+    rx_buffer: VecDeque<u8>,
+    /// The application closed the connection; a FIN follows the data in `tx_buffer`.
+    fin_queued: bool,
+    /// The peer sent a FIN, i.e. no more data arrives.
+    fin_received: bool,
+    /// Why the connection ended, if the peer didn't close it regularly.
+    error: Option<NetworkError>,
+    /// Deadline of the retransmission, or the end of `TimeWait` and `FinWait2`.
+    timer_ns: Option<u64>,
+    rto_ns: u64,
+    retransmissions: u32,
+}
+
+impl TcpConnection {
+    /// Active open: sends the SYN.
+    pub(super) fn connect(iss: u32, now_ns: u64, out: &mut Vec<OutSegment>) -> Self {
+        let mut conn = Self::new(TcpState::SynSent, iss, 0);
+        conn.transmit(now_ns, false, out);
+        conn
+    }
+
+    /// Passive open for the SYN of a peer: sends the SYN-ACK.
+    pub(super) fn accept(
+        iss: u32,
+        syn: &TcpSegment,
+        now_ns: u64,
+        out: &mut Vec<OutSegment>,
+    ) -> Self {
+        let mut conn = Self::new(TcpState::SynReceived, iss, syn.seq.wrapping_add(1));
+        conn.snd_wnd = syn.window as u32;
+        conn.transmit(now_ns, false, out);
+        conn
+    }
+
+    fn new(state: TcpState, iss: u32, rcv_nxt: u32) -> Self {
+        Self {
+            state,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss,
+            snd_max: iss,
+            snd_wnd: 0,
+            rcv_nxt,
+            tx_buffer: VecDeque::new(),
+            rx_buffer: VecDeque::new(),
+            fin_queued: false,
+            fin_received: false,
+            error: None,
+            timer_ns: None,
+            rto_ns: INITIAL_RTO_NS,
+            retransmissions: 0,
+        }
+    }
+
+    pub(super) fn state(&self) -> TcpState {
+        self.state
+    }
+
+    /// Checks if the handshake is done, i.e. the connection can be accepted.
+    pub(super) fn is_synchronized(&self) -> bool {
+        !matches!(self.state, TcpState::SynSent | TcpState::SynReceived)
+    }
+
+    /// Checks if the connection ended and the application closed it, i.e. if the stack
+    /// can forget it.
+    pub(super) fn is_gone(&self) -> bool {
+        self.state == TcpState::Closed && self.fin_queued
+    }
+
+    /// Why the connection ended, if the peer didn't close it regularly.
+    pub(super) fn error(&self) -> Option<NetworkError> {
+        self.error
+    }
+
+    /// Checks if [`Self::recv`] doesn't fail with [`NetworkError::WouldBlock`].
+    pub(super) fn is_readable(&self) -> bool {
+        !self.rx_buffer.is_empty() || self.fin_received || self.error.is_some()
+    }
+
+    /// Checks if [`Self::send`] doesn't fail with [`NetworkError::WouldBlock`].
+    pub(super) fn is_writable(&self) -> bool {
+        let open = matches!(self.state, TcpState::Established | TcpState::CloseWait);
+        (open && self.tx_buffer.len() < BUFFER_CAPACITY) || self.error.is_some()
+    }
+
+    /// Queues data for the peer. Returns the number of bytes that fit into the send
+    /// buffer.
+    pub(super) fn send(
+        &mut self,
+        data: &[u8],
+        now_ns: u64,
+        out: &mut Vec<OutSegment>,
+    ) -> Result<usize, NetworkError> {
+        match self.state {
+            TcpState::Established | TcpState::CloseWait => {}
+            TcpState::SynSent | TcpState::SynReceived => return Err(NetworkError::WouldBlock),
+            _ => return Err(self.error.unwrap_or(NetworkError::BrokenPipe)),
+        }
+        let len = min(data.len(), BUFFER_CAPACITY - self.tx_buffer.len());
+        if len == 0 && !data.is_empty() {
+            return Err(NetworkError::WouldBlock);
+        }
+        self.tx_buffer.extend(&data[..len]);
+        self.transmit(now_ns, false, out);
+        Ok(len)
+    }
+
+    /// Takes at most `max_len` received bytes. Returns no data once the peer closed the
+    /// connection and everything was read.
+    pub(super) fn recv(
+        &mut self,
+        max_len: usize,
+        out: &mut Vec<OutSegment>,
+    ) -> Result<Vec<u8>, NetworkError> {
+        if !self.rx_buffer.is_empty() {
+            let window_was_small = (self.rx_window() as usize) < MSS;
+            let len = min(max_len, self.rx_buffer.len());
+            let data = self.rx_buffer.drain(..len).collect();
+            // otherwise, the peer waits for the retransmission timeout
+            if window_was_small && self.rx_window() as usize >= MSS && !self.fin_received {
+                self.send_ack(out);
+            }
+            return Ok(data);
+        }
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if self.fin_received {
+            Ok(Vec::new())
+        } else {
+            Err(NetworkError::WouldBlock)
+        }
+    }
+
+    /// The application closed the connection. Sends a FIN after the remaining data, or
+    /// a reset if the handshake isn't done yet.
+    pub(super) fn close(&mut self, now_ns: u64, out: &mut Vec<OutSegment>) {
+        self.fin_queued = true;
+        match self.state {
+            TcpState::SynSent => self.state = TcpState::Closed,
+            TcpState::SynReceived => {
+                out.push(self.segment(self.snd_nxt, TCP_FLAG_RST, Vec::new()));
+                self.state = TcpState::Closed;
+            }
+            TcpState::Established => self.state = TcpState::FinWait1,
+            TcpState::CloseWait => self.state = TcpState::LastAck,
+            _ => {}
+        }
+        self.transmit(now_ns, false, out);
+    }
+
+    /// Processes a segment of the peer.
+    pub(super) fn on_segment(
+        &mut self,
+        segment: &TcpSegment,
+        now_ns: u64,
+        out: &mut Vec<OutSegment>,
+    ) {
+        if segment.has_flag(TCP_FLAG_RST) {
+            self.on_reset(segment);
+            return;
+        }
+        match self.state {
+            TcpState::Closed => return,
+            TcpState::SynSent => {
+                // simultaneous open is not supported
+                let is_syn_ack = segment.has_flag(TCP_FLAG_SYN) && segment.has_flag(TCP_FLAG_ACK);
+                if is_syn_ack && segment.ack == self.iss.wrapping_add(1) {
+                    self.rcv_nxt = segment.seq.wrapping_add(1);
+                    self.snd_wnd = segment.window as u32;
+                    self.acknowledge(segment.ack);
+                    self.state = TcpState::Established;
+                    self.send_ack(out);
+                    self.transmit(now_ns, false, out);
+                }
+                return;
+            }
+            _ if segment.has_flag(TCP_FLAG_SYN) => {
+                // the peer didn't get the SYN-ACK or the ACK of its SYN
+                if self.state == TcpState::SynReceived {
+                    self.snd_nxt = self.iss;
+                    self.transmit(now_ns, false, out);
+                } else {
+                    self.send_ack(out);
+                }
+                return;
+            }
+            _ => {}
+        }
+        if !segment.has_flag(TCP_FLAG_ACK) {
+            return;
+        }
+        if self.state == TcpState::SynReceived {
+            if segment.ack != self.iss.wrapping_add(1) {
+                return;
+            }
+            self.state = TcpState::Established;
+        }
+        self.process_ack(segment, now_ns);
+        self.process_data(segment, now_ns, out);
+        self.transmit(now_ns, false, out);
+    }
+
+    /// Handles the timeouts: retransmits unacknowledged segments and ends `TimeWait`.
+    pub(super) fn on_timer(&mut self, now_ns: u64, out: &mut Vec<OutSegment>) {
+        match self.timer_ns {
+            Some(deadline) if now_ns >= deadline => self.timer_ns = None,
+            _ => return,
+        }
+        match self.state {
+            TcpState::TimeWait | TcpState::FinWait2 => {
+                self.state = TcpState::Closed;
+                return;
+            }
+            TcpState::Closed => return,
+            _ => {}
+        }
+        if self.retransmissions == MAX_RETRANSMISSIONS {
+            if self.is_synchronized() {
+                out.push(self.segment(self.snd_nxt, TCP_FLAG_RST, Vec::new()));
+            }
+            self.abort(NetworkError::TimedOut);
+            return;
+        }
+        self.retransmissions += 1;
+        self.rto_ns = self.rto_ns.saturating_mul(2);
+        self.snd_nxt = self.snd_una;
+        // probes the window of the peer, if it is zero
+        self.transmit(now_ns, true, out);
+    }
+
+    /// Sends the SYN, data, and the FIN, as far as the window of the peer allows it.
+    /// A `probe` ignores a zero window and sends a single byte.
+    fn transmit(&mut self, now_ns: u64, probe: bool, out: &mut Vec<OutSegment>) {
+        match self.state {
+            TcpState::SynSent | TcpState::SynReceived => {
+                if self.snd_nxt == self.iss {
+                    let flags = if self.state == TcpState::SynSent {
+                        TCP_FLAG_SYN
+                    } else {
+                        TCP_FLAG_SYN | TCP_FLAG_ACK
+                    };
+                    out.push(self.segment(self.iss, flags, Vec::new()));
+                    self.snd_nxt = self.iss.wrapping_add(1);
+                }
+            }
+            TcpState::TimeWait | TcpState::Closed => return,
+            _ => {
+                let window = if probe {
+                    max(self.snd_wnd, 1)
+                } else {
+                    self.snd_wnd
+                } as usize;
+                loop {
+                    let sent = self.snd_nxt.wrapping_sub(self.snd_una) as usize;
+                    let unsent = self.tx_buffer.len().saturating_sub(sent);
+                    let len = min(min(unsent, MSS), window.saturating_sub(sent));
+                    if len > 0 {
+                        let payload = self.tx_buffer.range(sent..sent + len).copied().collect();
+                        let flags = TCP_FLAG_ACK | TCP_FLAG_PSH;
+                        out.push(self.segment(self.snd_nxt, flags, payload));
+                        self.snd_nxt = self.snd_nxt.wrapping_add(len as u32);
+                    } else {
+                        if self.fin_queued && sent == self.tx_buffer.len() {
+                            let flags = TCP_FLAG_ACK | TCP_FLAG_FIN;
+                            out.push(self.segment(self.snd_nxt, flags, Vec::new()));
+                            self.snd_nxt = self.snd_nxt.wrapping_add(1);
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        if seq_lt(self.snd_max, self.snd_nxt) {
+            self.snd_max = self.snd_nxt;
+        }
+        let waits = self.snd_una != self.snd_max || !self.tx_buffer.is_empty();
+        if waits && self.timer_ns.is_none() {
+            self.timer_ns = Some(now_ns.saturating_add(self.rto_ns));
+        }
+    }
+
+    /// Processes the acknowledgement and the window of a segment.
+    fn process_ack(&mut self, segment: &TcpSegment, now_ns: u64) {
+        if seq_lt(self.snd_max, segment.ack) || seq_lt(segment.ack, self.snd_una) {
+            // acknowledges something that wasn't sent or is old
+            return;
+        }
+        self.snd_wnd = segment.window as u32;
+        if segment.ack == self.snd_una {
+            return;
+        }
+        let acked = segment.ack.wrapping_sub(self.snd_una) as usize;
+        let fin_acked = self.fin_queued && acked > self.tx_buffer.len();
+        self.acknowledge(segment.ack);
+        if fin_acked {
+            self.state = match self.state {
+                TcpState::FinWait1 => {
+                    self.timer_ns = Some(now_ns.saturating_add(FIN_WAIT_2_TIMEOUT_NS));
+                    TcpState::FinWait2
+                }
+                TcpState::Closing => {
+                    self.timer_ns = Some(now_ns.saturating_add(TIME_WAIT_NS));
+                    TcpState::TimeWait
+                }
+                TcpState::LastAck => TcpState::Closed,
+                state => state,
+            };
+        }
+    }
+
+    /// Removes the acknowledged data from the send buffer and stops the retransmission
+    /// timer. The SYN and the FIN occupy a sequence number but no byte of the buffer.
+    fn acknowledge(&mut self, ack: u32) {
+        let acked = ack.wrapping_sub(self.snd_una) as usize;
+        let syn_acked = self.snd_una == self.iss;
+        let data = min(acked - syn_acked as usize, self.tx_buffer.len());
+        self.tx_buffer.drain(..data);
+        self.snd_una = ack;
+        if seq_lt(self.snd_nxt, ack) {
+            self.snd_nxt = ack;
+        }
+        self.retransmissions = 0;
+        self.rto_ns = INITIAL_RTO_NS;
+        self.timer_ns = None;
+    }
+
+    /// Stores the data of a segment in the receive buffer and processes the FIN. Only
+    /// accepts segments in order.
+    fn process_data(&mut self, segment: &TcpSegment, now_ns: u64, out: &mut Vec<OutSegment>) {
+        if segment.seq_len() == 0 {
+            return;
+        }
+        // retransmissions may contain data that was already received
+        let duplicate = self.rcv_nxt.wrapping_sub(segment.seq) as usize;
+        if seq_lt(self.rcv_nxt, segment.seq) || duplicate > segment.payload.len() {
+            // out of order or nothing new
+            self.send_ack(out);
+            return;
+        }
+        let payload = &segment.payload[duplicate..];
+        let open = matches!(
+            self.state,
+            TcpState::Established | TcpState::FinWait1 | TcpState::FinWait2
+        );
+        let len = if open {
+            min(payload.len(), BUFFER_CAPACITY - self.rx_buffer.len())
+        } else {
+            0
+        };
+        self.rx_buffer.extend(&payload[..len]);
+        self.rcv_nxt = self.rcv_nxt.wrapping_add(len as u32);
+
+        if open && len == payload.len() && segment.has_flag(TCP_FLAG_FIN) {
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            self.fin_received = true;
+            self.state = match self.state {
+                TcpState::Established => TcpState::CloseWait,
+                TcpState::FinWait1 => TcpState::Closing,
+                _ => {
+                    self.timer_ns = Some(now_ns.saturating_add(TIME_WAIT_NS));
+                    TcpState::TimeWait
+                }
+            };
+        }
+        self.send_ack(out);
+    }
+
+    fn on_reset(&mut self, segment: &TcpSegment) {
+        match self.state {
+            TcpState::SynSent => {
+                if segment.has_flag(TCP_FLAG_ACK) && segment.ack == self.iss.wrapping_add(1) {
+                    self.abort(NetworkError::ConnectionRefused);
+                }
+            }
+            TcpState::Closed => {}
+            TcpState::TimeWait => self.state = TcpState::Closed,
+            _ => {
+                // only resets within the window are genuine
+                let offset = segment.seq.wrapping_sub(self.rcv_nxt);
+                if offset < max(self.rx_window() as u32, 1) {
+                    self.abort(NetworkError::ConnectionReset);
+                }
+            }
+        }
+    }
+
+    fn abort(&mut self, err: NetworkError) {
+        self.state = TcpState::Closed;
+        self.error = Some(err);
+        self.tx_buffer.clear();
+        self.timer_ns = None;
+    }
+
+    fn send_ack(&self, out: &mut Vec<OutSegment>) {
+        out.push(self.segment(self.snd_nxt, TCP_FLAG_ACK, Vec::new()));
+    }
+
+    fn segment(&self, seq: u32, flags: u8, payload: Vec<u8>) -> OutSegment {
+        OutSegment {
+            seq,
+            ack: self.rcv_nxt,
+            flags,
+            window: self.rx_window(),
+            payload,
+        }
+    }
+
+    /// Free space in the receive buffer.
+    fn rx_window(&self) -> u16 {
+        (BUFFER_CAPACITY - self.rx_buffer.len()) as u16
+    }
+}
+
+/// Compares sequence numbers, which wrap around.
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISS: u32 = u32::MAX - 1;
+    const PEER_ISS: u32 = 1000;
+
+    fn segment(seq: u32, ack: u32, flags: u8, payload: &[u8]) -> TcpSegment {
+        TcpSegment {
+            src_port: 80,
+            dst_port: 49152,
+            seq,
+            ack,
+            flags,
+            window: 4096,
+            payload,
+        }
+    }
+
+    /// Returns an established connection. The sequence numbers wrap around soon.
+    fn established() -> TcpConnection {
+        let mut out = Vec::new();
+        let mut conn = TcpConnection::connect(ISS, 0, &mut out);
+        assert_eq!(out.pop().unwrap().flags, TCP_FLAG_SYN);
+        let syn_ack = segment(PEER_ISS, ISS + 1, TCP_FLAG_SYN | TCP_FLAG_ACK, &[]);
+        conn.on_segment(&syn_ack, 0, &mut out);
+        assert_eq!(conn.state(), TcpState::Established);
+        let ack = out.pop().unwrap();
+        assert_eq!(
+            (ack.flags, ack.seq, ack.ack),
+            (TCP_FLAG_ACK, ISS + 1, PEER_ISS + 1)
+        );
+        assert!(out.is_empty());
+        conn
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        let mut out = Vec::new();
+        let mut conn = TcpConnection::connect(ISS, 0, &mut out);
+        assert_eq!(conn.recv(10, &mut out), Err(NetworkError::WouldBlock));
+        conn.on_segment(
+            &segment(0, ISS.wrapping_add(1), TCP_FLAG_RST | TCP_FLAG_ACK, &[]),
+            0,
+            &mut out,
+        );
+        assert_eq!(conn.state(), TcpState::Closed);
+        assert_eq!(conn.error(), Some(NetworkError::ConnectionRefused));
+        assert!(conn.is_readable());
+    }
+
+    #[test]
+    fn test_send_and_retransmit() {
+        let mut conn = established();
+        let mut out = Vec::new();
+        let data = [7; MSS + 10];
+        assert_eq!(conn.send(&data, 0, &mut out), Ok(data.len()));
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].seq, ISS.wrapping_add(1));
+        assert_eq!(out[0].payload.len(), MSS);
+        assert_eq!(out[1].seq, ISS.wrapping_add(1 + MSS as u32));
+        assert_eq!(out[1].payload.len(), 10);
+        out.clear();
+
+        // the first segment arrived, the second one got lost
+        let ack = segment(
+            PEER_ISS + 1,
+            ISS.wrapping_add(1 + MSS as u32),
+            TCP_FLAG_ACK,
+            &[],
+        );
+        conn.on_segment(&ack, 0, &mut out);
+        assert!(out.is_empty());
+        conn.on_timer(INITIAL_RTO_NS - 1, &mut out);
+        assert!(out.is_empty());
+        conn.on_timer(INITIAL_RTO_NS, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].seq, ISS.wrapping_add(1 + MSS as u32));
+        assert_eq!(out[0].payload, [7; 10]);
+        out.clear();
+
+        // the peer never answers
+        let mut now = INITIAL_RTO_NS;
+        while conn.error().is_none() {
+            now += 1_000_000_000_000;
+            conn.on_timer(now, &mut out);
+        }
+        assert_eq!(conn.error(), Some(NetworkError::TimedOut));
+        assert_eq!(out.pop().unwrap().flags, TCP_FLAG_RST);
+        assert_eq!(out.len(), MAX_RETRANSMISSIONS as usize - 1);
+        assert_eq!(conn.send(b"x", now, &mut out), Err(NetworkError::TimedOut));
+    }
+
+    #[test]
+    fn test_receive_and_close() {
+        let mut conn = established();
+        let mut out = Vec::new();
+        let seq = PEER_ISS + 1;
+        conn.on_segment(&segment(seq, ISS + 1, TCP_FLAG_ACK, b"hello"), 0, &mut out);
+        assert_eq!(out.pop().unwrap().ack, seq + 5);
+        // retransmission with new data
+        conn.on_segment(
+            &segment(seq, ISS + 1, TCP_FLAG_ACK, b"hello world"),
+            0,
+            &mut out,
+        );
+        assert_eq!(out.pop().unwrap().ack, seq + 11);
+        // out of order
+        conn.on_segment(
+            &segment(seq + 20, ISS + 1, TCP_FLAG_ACK, b"later"),
+            0,
+            &mut out,
+        );
+        assert_eq!(out.pop().unwrap().ack, seq + 11);
+        assert_eq!(conn.recv(100, &mut out).unwrap(), b"hello world");
+        assert_eq!(conn.recv(100, &mut out), Err(NetworkError::WouldBlock));
+
+        // the peer closes first
+        let fin = TCP_FLAG_ACK | TCP_FLAG_FIN;
+        conn.on_segment(&segment(seq + 11, ISS + 1, fin, &[]), 0, &mut out);
+        assert_eq!(conn.state(), TcpState::CloseWait);
+        assert_eq!(out.pop().unwrap().ack, seq + 12);
+        assert_eq!(conn.recv(100, &mut out).unwrap(), b"");
+
+        conn.close(0, &mut out);
+        assert_eq!(conn.state(), TcpState::LastAck);
+        let our_fin = out.pop().unwrap();
+        assert_eq!(our_fin.flags, fin);
+        assert!(!conn.is_gone());
+        let ack = segment(seq + 12, ISS.wrapping_add(2), TCP_FLAG_ACK, &[]);
+        conn.on_segment(&ack, 0, &mut out);
+        assert!(conn.is_gone());
+    }
+
+    #[test]
+    fn test_passive_open_and_active_close() {
+        let mut out = Vec::new();
+        let syn = segment(PEER_ISS, 0, TCP_FLAG_SYN, &[]);
+        let mut conn = TcpConnection::accept(ISS, &syn, 0, &mut out);
+        let syn_ack = out.pop().unwrap();
+        assert_eq!(syn_ack.flags, TCP_FLAG_SYN | TCP_FLAG_ACK);
+        assert_eq!(syn_ack.ack, PEER_ISS + 1);
+        assert!(!conn.is_synchronized());
+
+        // the SYN-ACK got lost
+        conn.on_segment(&syn, 0, &mut out);
+        assert_eq!(out.pop().unwrap(), syn_ack);
+
+        let seq = PEER_ISS + 1;
+        conn.on_segment(
+            &segment(seq, ISS.wrapping_add(1), TCP_FLAG_ACK, b"hi"),
+            0,
+            &mut out,
+        );
+        assert!(conn.is_synchronized());
+        assert_eq!(conn.recv(100, &mut out).unwrap(), b"hi");
+        out.clear();
+
+        conn.close(0, &mut out);
+        assert_eq!(conn.state(), TcpState::FinWait1);
+        assert_eq!(out.pop().unwrap().flags, TCP_FLAG_ACK | TCP_FLAG_FIN);
+        conn.on_segment(
+            &segment(seq + 2, ISS.wrapping_add(2), TCP_FLAG_ACK, &[]),
+            0,
+            &mut out,
+        );
+        assert_eq!(conn.state(), TcpState::FinWait2);
+        let fin = TCP_FLAG_ACK | TCP_FLAG_FIN;
+        conn.on_segment(
+            &segment(seq + 2, ISS.wrapping_add(2), fin, &[]),
+            0,
+            &mut out,
+        );
+        assert_eq!(conn.state(), TcpState::TimeWait);
+        assert_eq!(out.pop().unwrap().ack, seq + 3);
+        conn.on_timer(TIME_WAIT_NS, &mut out);
+        assert!(conn.is_gone());
+    }
+}
@@ -0,0 +1,430 @@
+//! Parsing and construction of the packet formats that the network stack understands:
+//! Ethernet II, ARP for IPv4, IPv4 without options and fragmentation, UDP, and TCP.
+
+use alloc::vec::Vec;
+use libhrstd::rt::services::network::{
+    Ipv4Address,
+    MacAddress,
+    SocketAddrV4,
+};
+
+pub(super) const ETHER_TYPE_IPV4: u16 = 0x0800;
+pub(super) const ETHER_TYPE_ARP: u16 = 0x0806;
+pub(super) const IP_PROTOCOL_TCP: u8 = 6;
+pub(super) const IP_PROTOCOL_UDP: u8 = 17;
+
+pub(super) const ARP_OP_REQUEST: u16 = 1;
+pub(super) const ARP_OP_REPLY: u16 = 2;
+
+pub(super) const ETHERNET_HEADER_SIZE: usize = 14;
+pub(super) const IPV4_HEADER_SIZE: usize = 20;
+pub(super) const UDP_HEADER_SIZE: usize = 8;
+pub(super) const TCP_HEADER_SIZE: usize = 20;
+/// Maximum payload of an Ethernet frame.
+pub(super) const MTU: usize = 1500;
+const ARP_PACKET_SIZE: usize = 28;
+
+const IPV4_TTL: u8 = 64;
+/// "Don't fragment" flag in the fragment offset field.
+const IPV4_FLAG_DF: u16 = 0x4000;
+/// "More fragments" flag and the fragment offset.
+const IPV4_FRAGMENT_MASK: u16 = 0x3fff;
+
+pub(super) const TCP_FLAG_FIN: u8 = 0x01;
+pub(super) const TCP_FLAG_SYN: u8 = 0x02;
+pub(super) const TCP_FLAG_RST: u8 = 0x04;
+pub(super) const TCP_FLAG_PSH: u8 = 0x08;
+pub(super) const TCP_FLAG_ACK: u8 = 0x10;
+
+/// Ethernet II frame without the frame check sequence.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct EthernetFrame<'a> {
+    pub(super) dst: MacAddress,
+    pub(super) src: MacAddress,
+    pub(super) ether_type: u16,
+    pub(super) payload: &'a [u8],
+}
+
+impl<'a> EthernetFrame<'a> {
+    pub(super) fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() < ETHERNET_HEADER_SIZE {
+            return None;
+        }
+        Some(Self {
+            dst: MacAddress(bytes[0..6].try_into().unwrap()),
+            src: MacAddress(bytes[6..12].try_into().unwrap()),
+            ether_type: read_u16(bytes, 12),
+            payload: &bytes[ETHERNET_HEADER_SIZE..],
+        })
+    }
+
+    pub(super) fn emit(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ETHERNET_HEADER_SIZE + self.payload.len());
+        bytes.extend_from_slice(&self.dst.0);
+        bytes.extend_from_slice(&self.src.0);
+        bytes.extend_from_slice(&self.ether_type.to_be_bytes());
+        bytes.extend_from_slice(self.payload);
+        bytes
+    }
+}
+
+/// ARP packet for IPv4 over Ethernet.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct ArpPacket {
+    pub(super) operation: u16,
+    pub(super) sender_mac: MacAddress,
+    pub(super) sender_addr: Ipv4Address,
+    pub(super) target_mac: MacAddress,
+    pub(super) target_addr: Ipv4Address,
+}
+
+impl ArpPacket {
+    /// Hardware type Ethernet, protocol type IPv4, and the sizes of both addresses.
+    const HEADER: [u8; 6] = [0, 1, 0x08, 0x00, 6, 4];
+
+    pub(super) fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < ARP_PACKET_SIZE || bytes[..6] != Self::HEADER {
+            return None;
+        }
+        Some(Self {
+            operation: read_u16(bytes, 6),
+            sender_mac: MacAddress(bytes[8..14].try_into().unwrap()),
+            sender_addr: Ipv4Address(bytes[14..18].try_into().unwrap()),
+            target_mac: MacAddress(bytes[18..24].try_into().unwrap()),
+            target_addr: Ipv4Address(bytes[24..28].try_into().unwrap()),
+        })
+    }
+
+    pub(super) fn emit(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ARP_PACKET_SIZE);
+        bytes.extend_from_slice(&Self::HEADER);
+        bytes.extend_from_slice(&self.operation.to_be_bytes());
+        bytes.extend_from_slice(&self.sender_mac.0);
+        bytes.extend_from_slice(&self.sender_addr.0);
+        bytes.extend_from_slice(&self.target_mac.0);
+        bytes.extend_from_slice(&self.target_addr.0);
+        bytes
+    }
+}
+
+/// IPv4 packet. Options are skipped; fragments are not supported.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct Ipv4Packet<'a> {
+    pub(super) src: Ipv4Address,
+    pub(super) dst: Ipv4Address,
+    pub(super) protocol: u8,
+    pub(super) payload: &'a [u8],
+}
+
+impl<'a> Ipv4Packet<'a> {
+    /// Parses the packet and verifies the header checksum. Returns `None` for malformed
+    /// packets and fragments.
+    pub(super) fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() < IPV4_HEADER_SIZE || bytes[0] >> 4 != 4 {
+            return None;
+        }
+        let header_len = (bytes[0] & 0xf) as usize * 4;
+        let total_len = read_u16(bytes, 2) as usize;
+        if header_len < IPV4_HEADER_SIZE || total_len < header_len || total_len > bytes.len() {
+            return None;
+        }
+        if read_u16(bytes, 6) & IPV4_FRAGMENT_MASK != 0 {
+            return None;
+        }
+        if checksum(&[&bytes[..header_len]]) != 0 {
+            return None;
+        }
+        Some(Self {
+            protocol: bytes[9],
+            src: Ipv4Address(bytes[12..16].try_into().unwrap()),
+            dst: Ipv4Address(bytes[16..20].try_into().unwrap()),
+            payload: &bytes[header_len..total_len],
+        })
+    }
+
+    /// Creates the packet with the given identification field.
+    pub(super) fn emit(&self, id: u16) -> Vec<u8> {
+        let total_len = (IPV4_HEADER_SIZE + self.payload.len()) as u16;
+        let mut bytes = Vec::with_capacity(total_len as usize);
+        // version 4, header length of 5 words, no type of service
+        bytes.extend_from_slice(&[0x45, 0]);
+        bytes.extend_from_slice(&total_len.to_be_bytes());
+        bytes.extend_from_slice(&id.to_be_bytes());
+        bytes.extend_from_slice(&IPV4_FLAG_DF.to_be_bytes());
+        bytes.extend_from_slice(&[IPV4_TTL, self.protocol, 0, 0]);
+        bytes.extend_from_slice(&self.src.0);
+        bytes.extend_from_slice(&self.dst.0);
+        let checksum = checksum(&[&bytes]);
+        bytes[10..12].copy_from_slice(&checksum.to_be_bytes());
+        bytes.extend_from_slice(self.payload);
+        bytes
+    }
+}
+
+/// UDP datagram.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct UdpDatagram<'a> {
+    pub(super) src_port: u16,
+    pub(super) dst_port: u16,
+    pub(super) payload: &'a [u8],
+}
+
+impl<'a> UdpDatagram<'a> {
+    /// Parses the datagram and verifies the checksum, if the sender provided one.
+    /// `src` and `dst` are the addresses of the surrounding IPv4 packet.
+    pub(super) fn parse(bytes: &'a [u8], src: Ipv4Address, dst: Ipv4Address) -> Option<Self> {
+        if bytes.len() < UDP_HEADER_SIZE {
+            return None;
+        }
+        let len = read_u16(bytes, 4) as usize;
+        if len < UDP_HEADER_SIZE || len > bytes.len() {
+            return None;
+        }
+        let bytes = &bytes[..len];
+        let pseudo_header = pseudo_header(src, dst, IP_PROTOCOL_UDP, len);
+        if read_u16(bytes, 6) != 0 && checksum(&[&pseudo_header, bytes]) != 0 {
+            return None;
+        }
+        Some(Self {
+            src_port: read_u16(bytes, 0),
+            dst_port: read_u16(bytes, 2),
+            payload: &bytes[UDP_HEADER_SIZE..],
+        })
+    }
+
+    /// Creates the datagram including the checksum.
+    pub(super) fn emit(&self, src: Ipv4Address, dst: Ipv4Address) -> Vec<u8> {
+        let len = UDP_HEADER_SIZE + self.payload.len();
+        let mut bytes = Vec::with_capacity(len);
+        bytes.extend_from_slice(&self.src_port.to_be_bytes());
+        bytes.extend_from_slice(&self.dst_port.to_be_bytes());
+        bytes.extend_from_slice(&(len as u16).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+        bytes.extend_from_slice(self.payload);
+        let checksum = match checksum(&[&pseudo_header(src, dst, IP_PROTOCOL_UDP, len), &bytes]) {
+            // zero means "no checksum"; the one's complement has two zeros
+            0 => 0xffff,
+            checksum => checksum,
+        };
+        bytes[6..8].copy_from_slice(&checksum.to_be_bytes());
+        bytes
+    }
+
+    /// The sender of the datagram.
+    pub(super) fn src_addr(&self, packet: &Ipv4Packet) -> SocketAddrV4 {
+        SocketAddrV4::new(packet.src, self.src_port)
+    }
+}
+
+/// TCP segment. Options are skipped when parsing and never sent.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct TcpSegment<'a> {
+    pub(super) src_port: u16,
+    pub(super) dst_port: u16,
+    pub(super) seq: u32,
+    pub(super) ack: u32,
+    /// `TCP_FLAG_*`
+    pub(super) flags: u8,
+    pub(super) window: u16,
+    pub(super) payload: &'a [u8],
+}
+
+impl<'a> TcpSegment<'a> {
+    /// Parses the segment and verifies the checksum. `src` and `dst` are the addresses of
+    /// the surrounding IPv4 packet.
+    pub(super) fn parse(bytes: &'a [u8], src: Ipv4Address, dst: Ipv4Address) -> Option<Self> {
+        if bytes.len() < TCP_HEADER_SIZE {
+            return None;
+        }
+        let header_len = (bytes[12] >> 4) as usize * 4;
+        if header_len < TCP_HEADER_SIZE || header_len > bytes.len() {
+            return None;
+        }
+        if checksum(&[
+            &pseudo_header(src, dst, IP_PROTOCOL_TCP, bytes.len()),
+            bytes,
+        ]) != 0
+        {
+            return None;
+        }
+        Some(Self {
+            src_port: read_u16(bytes, 0),
+            dst_port: read_u16(bytes, 2),
+            seq: read_u32(bytes, 4),
+            ack: read_u32(bytes, 8),
+            flags: bytes[13],
+            window: read_u16(bytes, 14),
+            payload: &bytes[header_len..],
+        })
+    }
+
+    /// Creates the segment including the checksum.
+    pub(super) fn emit(&self, src: Ipv4Address, dst: Ipv4Address) -> Vec<u8> {
+        let len = TCP_HEADER_SIZE + self.payload.len();
+        let mut bytes = Vec::with_capacity(len);
+        bytes.extend_from_slice(&self.src_port.to_be_bytes());
+        bytes.extend_from_slice(&self.dst_port.to_be_bytes());
+        bytes.extend_from_slice(&self.seq.to_be_bytes());
+        bytes.extend_from_slice(&self.ack.to_be_bytes());
+        // header length of 5 words
+        bytes.extend_from_slice(&[(TCP_HEADER_SIZE as u8 / 4) << 4, self.flags]);
+        bytes.extend_from_slice(&self.window.to_be_bytes());
+        // checksum and urgent pointer
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(self.payload);
+        let checksum = checksum(&[&pseudo_header(src, dst, IP_PROTOCOL_TCP, len), &bytes]);
+        bytes[16..18].copy_from_slice(&checksum.to_be_bytes());
+        bytes
+    }
+
+    pub(super) fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Length of the segment in the sequence space: SYN and FIN count as one byte.
+    pub(super) fn seq_len(&self) -> u32 {
+        self.payload.len() as u32
+            + self.has_flag(TCP_FLAG_SYN) as u32
+            + self.has_flag(TCP_FLAG_FIN) as u32
+    }
+}
+
+/// Pseudo header of the UDP and TCP checksums.
+fn pseudo_header(src: Ipv4Address, dst: Ipv4Address, protocol: u8, len: usize) -> [u8; 12] {
+    let mut header = [0; 12];
+    header[0..4].copy_from_slice(&src.0);
+    header[4..8].copy_from_slice(&dst.0);
+    header[9] = protocol;
+    header[10..12].copy_from_slice(&(len as u16).to_be_bytes());
+    header
+}
+
+/// Internet checksum (RFC 1071) over the concatenation of all chunks. Each chunk except
+/// the last one must have an even length. Verifying data that includes its checksum
+/// results in zero.
+pub(super) fn checksum(chunks: &[&[u8]]) -> u16 {
+    let mut sum = chunks
+        .iter()
+        .flat_map(|chunk| chunk.chunks(2))
+        .map(|word| match *word {
+            [high, low] => u16::from_be_bytes([high, low]) as u32,
+            [high] => u16::from_be_bytes([high, 0]) as u32,
+            _ => unreachable!(),
+        })
+        .sum::<u32>();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        // example of RFC 1071
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(checksum(&[&data]), !0xddf2);
+        assert_eq!(checksum(&[&data[..4], &data[4..]]), !0xddf2);
+    }
+
+    #[test]
+    fn test_udp_in_ipv4_in_ethernet() {
+        let src = Ipv4Address([10, 0, 2, 15]);
+        let dst = Ipv4Address([10, 0, 2, 2]);
+        let udp = UdpDatagram {
+            src_port: 49152,
+            dst_port: 53,
+            payload: b"hello",
+        }
+        .emit(src, dst);
+        let ip = Ipv4Packet {
+            src,
+            dst,
+            protocol: IP_PROTOCOL_UDP,
+            payload: &udp,
+        }
+        .emit(1);
+        let frame = EthernetFrame {
+            dst: MacAddress::BROADCAST,
+            src: MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]),
+            ether_type: ETHER_TYPE_IPV4,
+            payload: &ip,
+        }
+        .emit();
+        assert_eq!(
+            frame.len(),
+            ETHERNET_HEADER_SIZE + IPV4_HEADER_SIZE + UDP_HEADER_SIZE + 5
+        );
+
+        let frame = EthernetFrame::parse(&frame).unwrap();
+        assert_eq!(frame.ether_type, ETHER_TYPE_IPV4);
+        let packet = Ipv4Packet::parse(frame.payload).unwrap();
+        assert_eq!(packet.src, src);
+        assert_eq!(packet.dst, dst);
+        let datagram = UdpDatagram::parse(packet.payload, packet.src, packet.dst).unwrap();
+        assert_eq!(datagram.src_addr(&packet), SocketAddrV4::new(src, 49152));
+        assert_eq!(datagram.dst_port, 53);
+        assert_eq!(datagram.payload, b"hello");
+
+        // corrupted payload
+        let mut corrupted = Vec::from(packet.payload);
+        corrupted[UDP_HEADER_SIZE] ^= 1;
+        assert!(UdpDatagram::parse(&corrupted, src, dst).is_none());
+        // corrupted header
+        let mut corrupted = ip.clone();
+        corrupted[8] ^= 1;
+        assert!(Ipv4Packet::parse(&corrupted).is_none());
+    }
+
+    #[test]
+    fn test_tcp() {
+        let src = Ipv4Address([10, 0, 2, 15]);
+        let dst = Ipv4Address([10, 0, 2, 2]);
+        let segment = TcpSegment {
+            src_port: 49152,
+            dst_port: 80,
+            seq: 0xdead_beef,
+            ack: 42,
+            flags: TCP_FLAG_ACK | TCP_FLAG_FIN,
+            window: 1024,
+            payload: b"bye",
+        };
+        let bytes = segment.emit(src, dst);
+        assert_eq!(bytes.len(), TCP_HEADER_SIZE + 3);
+        let parsed = TcpSegment::parse(&bytes, src, dst).unwrap();
+        assert_eq!(parsed, segment);
+        assert_eq!(parsed.seq_len(), 4);
+
+        // the checksum covers the addresses
+        assert!(TcpSegment::parse(&bytes, src, Ipv4Address([10, 0, 2, 3])).is_none());
+        let mut corrupted = bytes.clone();
+        corrupted[TCP_HEADER_SIZE] ^= 1;
+        assert!(TcpSegment::parse(&corrupted, src, dst).is_none());
+    }
+
+    #[test]
+    fn test_arp() {
+        let packet = ArpPacket {
+            operation: ARP_OP_REQUEST,
+            sender_mac: MacAddress([0x52, 0x54, 0, 0x12, 0x34, 0x56]),
+            sender_addr: Ipv4Address([10, 0, 2, 15]),
+            target_mac: MacAddress([0; 6]),
+            target_addr: Ipv4Address([10, 0, 2, 2]),
+        };
+        let bytes = packet.emit();
+        assert_eq!(bytes.len(), ARP_PACKET_SIZE);
+        assert_eq!(ArpPacket::parse(&bytes), Some(packet));
+        assert_eq!(ArpPacket::parse(&bytes[1..]), None);
+    }
+}
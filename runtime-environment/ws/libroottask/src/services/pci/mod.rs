@@ -0,0 +1,306 @@
+//! PCI service. Driver processes look up the PCI functions that the roottask enumerated at
+//! boot, see [`crate::hw::pci`], and claim a function to get its registers and its MSI.
+//!
+//! Like the shared memory service, the roottask maps the registers of a memory BAR once
+//! more into its own address space and delegates this alias, so that it can revoke exactly
+//! the mapping of the process. The roottask delegates the I/O ports of an I/O BAR directly.
+//! Only privileged processes (see [`is_privileged`]) are drivers; the service refuses
+//! all other callers.
+//! BARs that are smaller than a page share the page with their neighbours, therefore the
+//! process may see the registers of other functions.
+
+use crate::hw::pci::{
+    self,
+    PciConfigSpace,
+    PciOwner,
+};
+use crate::io_port::request_io_ports;
+use crate::irq;
+use crate::mem::{
+    map_phys_into_roottask,
+    revoke_from_roottask,
+};
+use crate::process::{
+    is_privileged,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::syscall::{
+    sys_pd_ctrl_delegate,
+    sys_revoke,
+    DelegateFlags,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::libhedron::{
+    CrdPortIO,
+    MemCapPermissions,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::irq::{
+    Irq,
+    IrqAttachment,
+    IrqServiceError,
+    IrqSource,
+    PciFunction,
+};
+use libhrstd::rt::services::pci::{
+    PciBarKind,
+    PciBarMapping,
+    PciService,
+    PciServiceError,
+    PciServiceRequest,
+    PciServiceResponse,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::delegation::DelegationBuilder;
+
+/// The functions that processes claimed and what they got from them.
+static CLAIMS: SimpleMutex<BTreeMap<PciFunction, Claim>> = SimpleMutex::new(BTreeMap::new());
+
+/// Creates a new PCI service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::PciService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the PCI Portal.
+pub fn pci_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<PciServiceRequest>().unwrap();
+    log::trace!("pci request from pid={}: {:?}", process.pid(), request);
+    match request {
+        PciServiceRequest::Device(request) => rpc_serve::<PciService, _>(request, utcb, |r| {
+            check_privileged(process)?;
+            pci::device(r.index as usize).ok_or(PciServiceError::NoSuchDevice)
+        }),
+        PciServiceRequest::MapBar(request) => {
+            rpc_serve::<PciService, _>(request, utcb, |r| map_bar(process, r.function, r.bar))
+        }
+        PciServiceRequest::AttachMsi(request) => {
+            rpc_serve::<PciService, _>(request, utcb, |r| attach_msi(process, r.function))
+        }
+        PciServiceRequest::Release(request) => {
+            rpc_serve::<PciService, _>(request, utcb, |r| release(process.pid(), r.function))
+        }
+    }
+    *do_reply = true;
+}
+
+/// Maps a BAR of the function into the process. See
+/// [`libhrstd::rt::services::pci::PciMapBarRequest`].
+pub fn map_bar(
+    process: &Process,
+    function: PciFunction,
+    bar: u8,
+) -> PciServiceResponse<PciBarMapping> {
+    check_privileged(process)?;
+    let info = pci::claim(function, PciOwner::Process(process.pid()))?;
+    let bar_info = info
+        .bars
+        .get(bar as usize)
+        .copied()
+        .flatten()
+        .ok_or(PciServiceError::NoSuchBar)?;
+    let mut claims = CLAIMS.lock();
+    let claim = claim_mut(&mut claims, function);
+    if let Some(mapping) = claim.bars.get(&bar) {
+        return Ok(mapping.mapping);
+    }
+
+    let mapping = match bar_info.kind {
+        PciBarKind::Io => {
+            let crd = CrdPortIO::new(bar_info.base as u16, bar_info.size.trailing_zeros() as u8);
+            request_io_ports(RootCapSpace::RootPd.val(), crd)
+                .and_then(|_| {
+                    sys_pd_ctrl_delegate(
+                        RootCapSpace::RootPd.val(),
+                        process.pd_obj().cap_sel(),
+                        crd,
+                        crd,
+                        DelegateFlags::default(),
+                    )
+                })
+                .map_err(|e| {
+                    log::warn!(
+                        "can't delegate I/O ports of BAR {} of {:?}: {:?}",
+                        bar,
+                        function,
+                        e
+                    );
+                    PciServiceError::DelegationFailed
+                })?;
+            BarMapping {
+                mapping: PciBarMapping::Io(bar_info.base as u16),
+                _resource: BarResource::Io(crd),
+            }
+        }
+        PciBarKind::Memory { .. } => {
+            let page_mask = PAGE_SIZE as u64 - 1;
+            let phys_start = bar_info.base & !page_mask;
+            let phys_end = (bar_info.base + bar_info.size + page_mask) & !page_mask;
+            let page_count = ((phys_end - phys_start) / PAGE_SIZE as u64) as usize;
+            let u_addr = process
+                .memory_manager_mut()
+                .reserve_mmap_area(page_count)
+                .map_err(|_| PciServiceError::OutOfMemory)?;
+            let r_alias = map_phys_into_roottask(phys_start, page_count);
+            let resource = BarResource::Memory {
+                r_alias,
+                page_count,
+            };
+            DelegationBuilder::mem(r_alias..r_alias + (page_count * PAGE_SIZE) as u64)
+                .perms(MemCapPermissions::RW)
+                .at(u_addr)
+                .to(process.pd_obj().cap_sel())
+                .map_err(|e| {
+                    log::warn!("can't map BAR {} of {:?}: {:?}", bar, function, e);
+                    PciServiceError::DelegationFailed
+                })?;
+            BarMapping {
+                mapping: PciBarMapping::Memory(u_addr + (bar_info.base - phys_start)),
+                _resource: resource,
+            }
+        }
+    };
+    log::debug!(
+        "mapped BAR {} of {:?} to pid={}: {:?}",
+        bar,
+        function,
+        process.pid(),
+        mapping.mapping
+    );
+    let result = mapping.mapping;
+    claim.bars.insert(bar, mapping);
+    Ok(result)
+}
+
+/// Attaches the MSI of the function to the process. See
+/// [`libhrstd::rt::services::pci::PciAttachMsiRequest`].
+pub fn attach_msi(process: &Process, function: PciFunction) -> PciServiceResponse<IrqAttachment> {
+    check_privileged(process)?;
+    pci::claim(function, PciOwner::Process(process.pid()))?;
+    let mut claims = CLAIMS.lock();
+    let claim = claim_mut(&mut claims, function);
+    if claim.msi.is_some() {
+        return Err(PciServiceError::Irq(IrqServiceError::Busy));
+    }
+    let attachment =
+        irq::attach_to_process(process, IrqSource::Msi(function)).map_err(PciServiceError::Irq)?;
+    claim.msi.replace(attachment.irq);
+    Ok(attachment)
+}
+
+/// Unmaps the BARs and detaches the MSI of a function of the process and frees it. See
+/// [`libhrstd::rt::services::pci::PciReleaseRequest`].
+pub fn release(pid: ProcessId, function: PciFunction) -> PciServiceResponse<()> {
+    pci::release(function, PciOwner::Process(pid))?;
+    // the drop revokes the BARs
+    let claim = CLAIMS.lock().remove(&function);
+    if let Some(Claim { msi: Some(irq), .. }) = claim {
+        // the process may have detached it via the interrupt service already
+        if irq::detach_from_process(pid, irq).is_err() {
+            log::debug!("pid={} detached the MSI of {:?} already", pid, function);
+        }
+    }
+    pci_config_space().disable_device(function.into());
+    log::debug!("pid={} released {:?}", pid, function);
+    Ok(())
+}
+
+/// Releases all functions of a stopped process.
+pub fn release_process(pid: ProcessId) {
+    for function in pci::claimed_by(PciOwner::Process(pid)) {
+        release(pid, function).unwrap();
+    }
+}
+
+fn check_privileged(caller: &Process) -> PciServiceResponse<()> {
+    if is_privileged(caller.pid()) {
+        Ok(())
+    } else {
+        log::debug!("pid={} isn't allowed to use PCI functions", caller.pid());
+        Err(PciServiceError::PermissionDenied)
+    }
+}
+
+/// Returns the claim of a function that the caller claimed via [`pci::claim`]. Enables the function when the
+/// process claims it.
+fn claim_mut(claims: &mut BTreeMap<PciFunction, Claim>, function: PciFunction) -> &mut Claim {
+    claims.entry(function).or_insert_with(|| {
+        pci_config_space().enable_device(function.into());
+        Claim::default()
+    })
+}
+
+fn pci_config_space() -> PciConfigSpace {
+    PciConfigSpace::new(RootCapSpace::RootPd.val())
+        .expect("the roottask can access the PCI configuration space")
+}
+
+/// What a process got from a function that it claimed.
+#[derive(Debug, Default)]
+struct Claim {
+    bars: BTreeMap<u8, BarMapping>,
+    msi: Option<Irq>,
+}
+
+/// A BAR that the process can access. Dropping it revokes the access.
+#[derive(Debug)]
+struct BarMapping {
+    mapping: PciBarMapping,
+    /// Revokes the access when dropped.
+    _resource: BarResource,
+}
+
+#[derive(Debug)]
+enum BarResource {
+    /// The alias of the registers in the roottask, see the module description.
+    Memory {
+        r_alias: u64,
+        page_count: usize,
+    },
+    Io(CrdPortIO),
+}
+
+impl Drop for BarResource {
+    fn drop(&mut self) {
+        match *self {
+            Self::Memory {
+                r_alias,
+                page_count,
+            } => {
+                revoke_from_roottask(r_alias, page_count);
+            }
+            Self::Io(crd) => {
+                // keeps the ports of the roottask
+                if let Err(e) = sys_revoke(crd, false) {
+                    log::warn!("can't revoke I/O ports {:?}: {:?}", crd, e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Performance counter service. Programs the performance counters of the CPU (see
+//! [`crate::hw::pmu`]) on behalf of processes, e.g. benchmarks, which can report retired
+//! instructions, cache misses, and branch mispredictions besides TSC ticks.
+//!
+//! The counters belong to a CPU, not to a process, and the service handler runs on the
+//! CPU of the caller. Hence, a process owns the counters of its CPU from its start
+//! request until its stop request or its exit, and other processes on the same CPU can't
+//! use them meanwhile. The counters also count the other processes on the CPU, hence only
+//! privileged processes (see [`is_privileged`]) can start them.
+
+use crate::hw::pmu;
+use crate::hw::pmu::PmuInfo;
+use crate::process::{
+    is_privileged,
+    process_cpu,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::perf_counter::{
+    PerfCounts,
+    PerfEvent,
+    PerfInfo,
+    PerfService,
+    PerfServiceError,
+    PerfServiceRequest,
+    PerfServiceResponse,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// The process that owns the counters of a CPU and the events that they count, by CPU.
+static SESSIONS: SimpleMutex<BTreeMap<u64, Session>> = SimpleMutex::new(BTreeMap::new());
+
+#[derive(Debug)]
+struct Session {
+    pid: ProcessId,
+    /// Counter `i` counts `events[i]`.
+    events: Vec<PerfEvent>,
+}
+
+/// Creates a new performance counter service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::PerfService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the performance counter Portal.
+pub fn perf_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<PerfServiceRequest>().unwrap();
+    let cpu = process_cpu(process.pid()).unwrap_or(0);
+    let pmu = PmuInfo::current();
+    match request {
+        PerfServiceRequest::Info(request) => {
+            rpc_serve::<PerfService, _>(request, utcb, |_| PerfInfo {
+                version: pmu.version,
+                num_counters: pmu.num_counters,
+                counter_width: pmu.counter_width,
+                events: pmu.events(),
+            })
+        }
+        PerfServiceRequest::Start(request) => rpc_serve::<PerfService, _>(request, utcb, |r| {
+            start(process.pid(), cpu, &pmu, r.events)
+        }),
+        PerfServiceRequest::Read(request) => {
+            rpc_serve::<PerfService, _>(request, utcb, |_| read(process.pid(), cpu, &pmu, false))
+        }
+        PerfServiceRequest::Stop(request) => {
+            rpc_serve::<PerfService, _>(request, utcb, |_| read(process.pid(), cpu, &pmu, true))
+        }
+    }
+    *do_reply = true;
+}
+
+fn start(
+    pid: ProcessId,
+    cpu: u64,
+    pmu: &PmuInfo,
+    events: Vec<PerfEvent>,
+) -> PerfServiceResponse<()> {
+    if !is_privileged(pid) {
+        log::debug!("pid={} isn't allowed to use the performance counters", pid);
+        return Err(PerfServiceError::PermissionDenied);
+    }
+    check_events(pmu, &events)?;
+    let mut sessions = SESSIONS.lock();
+    if sessions
+        .get(&cpu)
+        .map_or(false, |session| session.pid != pid)
+    {
+        return Err(PerfServiceError::Busy);
+    }
+    pmu::start(pmu, &events).map_err(|e| {
+        log::warn!("can't program the performance counters: {:?}", e);
+        PerfServiceError::Unsupported
+    })?;
+    log::debug!(
+        "pid={} started the performance counters of cpu={}: {:?}",
+        pid,
+        cpu,
+        events
+    );
+    sessions.insert(cpu, Session { pid, events });
+    Ok(())
+}
+
+/// Returns the counts of the session of the process. With `stop`, also ends the session.
+fn read(pid: ProcessId, cpu: u64, pmu: &PmuInfo, stop: bool) -> PerfServiceResponse<PerfCounts> {
+    let mut sessions = SESSIONS.lock();
+    let session = sessions
+        .get(&cpu)
+        .filter(|session| session.pid == pid)
+        .ok_or(PerfServiceError::NotStarted)?;
+    let num = session.events.len();
+    if stop {
+        // stop first, so that the IPC of the next calls doesn't add to the counts
+        pmu::disable(pmu, num).map_err(|_| PerfServiceError::Unsupported)?;
+    }
+    let values = pmu::read(pmu, num).map_err(|_| PerfServiceError::Unsupported)?;
+    let counts = PerfCounts {
+        counts: session.events.iter().copied().zip(values).collect(),
+    };
+    if stop {
+        sessions.remove(&cpu);
+    }
+    Ok(counts)
+}
+
+/// The CPU must support each event and have a counter for it.
+fn check_events(pmu: &PmuInfo, events: &[PerfEvent]) -> PerfServiceResponse<()> {
+    if pmu.version == 0 {
+        return Err(PerfServiceError::Unsupported);
+    }
+    let has_duplicates = events
+        .iter()
+        .enumerate()
+        .any(|(i, event)| events[..i].contains(event));
+    if events.is_empty() || events.len() > pmu.num_counters as usize || has_duplicates {
+        return Err(PerfServiceError::InvalidEvents);
+    }
+    match events.iter().find(|event| !pmu.supports(**event)) {
+        Some(event) => Err(PerfServiceError::UnsupportedEvent(*event)),
+        None => Ok(()),
+    }
+}
+
+/// Releases the counters that the process owns, i.e. when the process exits. The counters
+/// keep counting until the next process starts them.
+pub fn unregister_process(pid: ProcessId) {
+    SESSIONS.lock().retain(|_, session| session.pid != pid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_events() {
+        let pmu = PmuInfo::from_cpuid(0x0730_0202, 1 << 6);
+        assert_eq!(
+            check_events(
+                &pmu,
+                &[PerfEvent::InstructionsRetired, PerfEvent::LlcMisses]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check_events(&pmu, &[PerfEvent::BranchMispredictions]),
+            Err(PerfServiceError::UnsupportedEvent(
+                PerfEvent::BranchMispredictions
+            ))
+        );
+        assert_eq!(
+            check_events(&pmu, &[]),
+            Err(PerfServiceError::InvalidEvents)
+        );
+        assert_eq!(
+            check_events(&pmu, &[PerfEvent::CoreCycles, PerfEvent::CoreCycles]),
+            Err(PerfServiceError::InvalidEvents)
+        );
+        // only 2 counters
+        assert_eq!(
+            check_events(
+                &pmu,
+                &[
+                    PerfEvent::CoreCycles,
+                    PerfEvent::InstructionsRetired,
+                    PerfEvent::LlcMisses
+                ]
+            ),
+            Err(PerfServiceError::InvalidEvents)
+        );
+        assert_eq!(
+            check_events(&PmuInfo::from_cpuid(0, 0), &[PerfEvent::CoreCycles]),
+            Err(PerfServiceError::Unsupported)
+        );
+    }
+}
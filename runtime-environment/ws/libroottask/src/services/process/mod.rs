@@ -0,0 +1,551 @@
+//! Process service. Lets a process start a program from the file system at runtime, e.g.
+//! a program of the userland tarball below [`crate::rt::userland::USERLAND_MOUNT_POINT`],
+//! spawn a program with some of its open files like `posix_spawn()`, wait for its children, exit, handle its own faults, and pass capabilities to other
+//! processes. During development, it replaces programs of the tarball with new versions
+//! from the file system, e.g. ones that a developer uploaded, so that the next launch uses
+//! them. See [`crate::process::exit_process`] and [`crate::cap_transfer`].
+//!
+//! The service EC can't start the process itself, because the process manager is locked
+//! while a portal handler runs. Hence, the handler only validates the program, reserves
+//! the PID, and queues the launch. The main global EC of the roottask starts the queued
+//! processes in [`crate::services::timer::timer_loop`].
+
+use crate::cap_transfer::{
+    release_received_cap,
+    transfer_cap,
+    CapTransferError,
+};
+use crate::gdb_stub;
+use crate::mem::MappedMemory;
+use crate::process::{
+    allocate_pid,
+    assign_process_cap_sels,
+    exit_process,
+    exit_status,
+    free_pid,
+    is_privileged,
+    reap_process,
+    select_syscall_abi,
+    set_syscall_trace,
+    signal_target,
+    FaultHandler,
+    Process,
+    SyscallAbi,
+    PROCESS_MNG,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::rt::userland::{
+    copy_to_page_aligned_dest,
+    with_file,
+};
+use crate::services::timer::wake_main_ec;
+use crate::smp;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::TypedItem;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::fs::FsError;
+use libhrstd::rt::services::process::{
+    PreopenedFile,
+    ProcessSendCapResponse,
+    ProcessServiceError,
+    ProcessServiceRequest,
+    ProcessServiceResponse,
+    ProcessStatus,
+    ProcessStatusResponse,
+    SpawnFd,
+};
+use libhrstd::rt::services::scheduling::SchedulingParams;
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Processes that the service accepted but that are not started yet.
+static QUEUED_LAUNCHES: SimpleMutex<Vec<QueuedLaunch>> = SimpleMutex::new(Vec::new());
+
+/// A program that the main EC starts next.
+#[derive(Debug)]
+struct QueuedLaunch {
+    pid: ProcessId,
+    parent: ProcessId,
+    path: String,
+    elf_file: MappedMemory,
+    syscall_abi: SyscallAbi,
+    argv: Vec<String>,
+    envp: Vec<String>,
+    sched_params: SchedulingParams,
+    cpu: Option<u64>,
+    aslr: bool,
+}
+
+/// Creates a new PROCESS service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::ProcessService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the PROCESS Portal.
+pub fn process_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<ProcessServiceRequest>().unwrap();
+    match request {
+        ProcessServiceRequest::Launch {
+            path,
+            argv,
+            envp,
+            sched_params,
+            cpu,
+            preopened,
+            aslr,
+            debug,
+            trace,
+        } => {
+            let sched_params = sched_params.unwrap_or(SchedulingParams::DEFAULT);
+            let response = launch(
+                process,
+                path,
+                argv,
+                envp,
+                sched_params,
+                cpu,
+                &preopened,
+                aslr,
+                debug,
+                trace,
+            );
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::Spawn {
+            path,
+            argv,
+            envp,
+            fds,
+        } => {
+            let response = spawn(process, path, argv, envp, &fds);
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::Status { pid } => {
+            let response = status(process, pid);
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::SendCap { item, to } => {
+            let response = send_cap(process, &item, to);
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::Reload { path, source } => {
+            let response = reload(process, &path, &source);
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::Exit { status } => {
+            exit_process(process.pid(), status);
+            utcb.store_data(&Ok::<(), ProcessServiceError>(())).unwrap();
+        }
+        ProcessServiceRequest::SetFaultHandler { entry, stack_top } => {
+            let response = set_fault_handler(process, entry, stack_top);
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::SetSyscallTrace { pid, enabled } => {
+            let response = check_parent(process, pid).map(|_| set_syscall_trace(pid, enabled));
+            utcb.store_data(&response).unwrap();
+        }
+        ProcessServiceRequest::ReleaseCap { sel } => {
+            let response = release_received_cap(process.pid(), sel).map_err(map_cap_transfer_error);
+            utcb.store_data(&response).unwrap();
+        }
+    }
+    *do_reply = true;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn launch(
+    caller: &Process,
+    path: String,
+    mut argv: Vec<String>,
+    envp: Vec<String>,
+    sched_params: SchedulingParams,
+    cpu: Option<u64>,
+    preopened: &[PreopenedFile],
+    aslr: bool,
+    debug: bool,
+    trace: bool,
+) -> ProcessServiceResponse {
+    check_permission(caller)?;
+    check_strings(&argv, &envp)?;
+    if !sched_params.is_valid() || !cpu.map_or(true, smp::is_online) {
+        return Err(ProcessServiceError::InvalidArgument);
+    }
+    if !PreopenedFile::are_valid(preopened) {
+        return Err(ProcessServiceError::InvalidArgument);
+    }
+    let (syscall_abi, elf_file) = load_program(caller, &path)?;
+    let pid = allocate_pid().ok_or(ProcessServiceError::TooManyProcesses)?;
+    preopen_files(caller.pid(), pid, preopened)
+        .and_then(|_| assign_cap_sels(pid))
+        .map_err(|err| abandon_pid(pid, err))?;
+    if argv.is_empty() {
+        argv.push(path.clone());
+    }
+
+    log::info!(
+        "pid={} launches '{}' as pid={} ({:?}): argv={:?}, envp={:?}, {:?}, cpu={:?}, preopened={:?}, aslr={}, debug={}, trace={}",
+        caller.pid(),
+        path,
+        pid,
+        syscall_abi,
+        argv,
+        envp,
+        sched_params,
+        cpu,
+        preopened,
+        aslr,
+        debug,
+        trace
+    );
+    if debug {
+        gdb_stub::register(pid);
+    }
+    if trace {
+        set_syscall_trace(pid, true);
+    }
+    queue_launch(QueuedLaunch {
+        pid,
+        parent: caller.pid(),
+        path,
+        elf_file,
+        syscall_abi,
+        argv,
+        envp,
+        sched_params,
+        cpu,
+        aslr,
+    });
+    Ok(pid)
+}
+
+/// Starts the program with the open files `fds` of the caller and the default parameters
+/// of [`launch`]. See [`ProcessServiceRequest::Spawn`].
+fn spawn(
+    caller: &Process,
+    path: String,
+    argv: Vec<String>,
+    envp: Vec<String>,
+    fds: &[SpawnFd],
+) -> ProcessServiceResponse {
+    check_permission(caller)?;
+    check_strings(&argv, &envp)?;
+    if !SpawnFd::are_valid(fds) {
+        return Err(ProcessServiceError::InvalidArgument);
+    }
+    let (syscall_abi, elf_file) = load_program(caller, &path)?;
+    let pid = allocate_pid().ok_or(ProcessServiceError::TooManyProcesses)?;
+    inherit_files(caller.pid(), pid, fds)
+        .and_then(|_| assign_cap_sels(pid))
+        .map_err(|err| abandon_pid(pid, err))?;
+    queue_spawn(caller, pid, path, elf_file, syscall_abi, argv, envp);
+    Ok(pid)
+}
+
+/// Starts the program as the process `pid`, whose PID the caller already reserved with
+/// [`allocate_pid`], with the default parameters of [`launch`]. The new process keeps the
+/// open files that `pid` has. Used by `execve()` of Linux processes after `vfork()`.
+pub fn spawn_with_pid(
+    caller: &Process,
+    pid: ProcessId,
+    path: String,
+    argv: Vec<String>,
+    envp: Vec<String>,
+) -> Result<(), ProcessServiceError> {
+    check_permission(caller)?;
+    check_strings(&argv, &envp)?;
+    let (syscall_abi, elf_file) = load_program(caller, &path)?;
+    assign_cap_sels(pid)?;
+    queue_spawn(caller, pid, path, elf_file, syscall_abi, argv, envp);
+    Ok(())
+}
+
+fn assign_cap_sels(pid: ProcessId) -> Result<(), ProcessServiceError> {
+    assign_process_cap_sels(pid)
+        .map(|_| ())
+        .ok_or(ProcessServiceError::TooManyProcesses)
+}
+
+/// Gives up the PID of a process that can't be started, together with the files that it
+/// got already. Returns `err`.
+fn abandon_pid(pid: ProcessId, err: ProcessServiceError) -> ProcessServiceError {
+    libfileserver::FILESYSTEM.lock().release_process(pid);
+    free_pid(pid);
+    err
+}
+
+fn queue_spawn(
+    caller: &Process,
+    pid: ProcessId,
+    path: String,
+    elf_file: MappedMemory,
+    syscall_abi: SyscallAbi,
+    mut argv: Vec<String>,
+    envp: Vec<String>,
+) {
+    if argv.is_empty() {
+        argv.push(path.clone());
+    }
+    log::info!(
+        "pid={} spawns '{}' as pid={} ({:?}): argv={:?}, envp={:?}",
+        caller.pid(),
+        path,
+        pid,
+        syscall_abi,
+        argv,
+        envp
+    );
+    queue_launch(QueuedLaunch {
+        pid,
+        parent: caller.pid(),
+        path,
+        elf_file,
+        syscall_abi,
+        argv,
+        envp,
+        sched_params: SchedulingParams::DEFAULT,
+        cpu: None,
+        aslr: false,
+    });
+}
+
+/// Lets the main EC start the process.
+fn queue_launch(launch: QueuedLaunch) {
+    QUEUED_LAUNCHES.lock().push(launch);
+    wake_main_ec();
+}
+
+/// Copies the program at `path` into memory that the new process can map. The file is
+/// opened on behalf of the caller.
+fn load_program(
+    caller: &Process,
+    path: &str,
+) -> Result<(SyscallAbi, MappedMemory), ProcessServiceError> {
+    let root = caller.parent().unwrap();
+    with_file(caller.pid(), path, |data| {
+        let syscall_abi = select_syscall_abi(data, path, None)?;
+        Ok((syscall_abi, copy_to_page_aligned_dest(data, &root)))
+    })
+    .ok_or(ProcessServiceError::NotFound)?
+}
+
+/// The strings become C strings in the address space of the new process, hence they must
+/// not contain null bytes.
+fn check_strings(argv: &[String], envp: &[String]) -> Result<(), ProcessServiceError> {
+    if argv.iter().chain(envp.iter()).any(|s| s.contains('\0')) {
+        Err(ProcessServiceError::InvalidArgument)
+    } else {
+        Ok(())
+    }
+}
+
+/// Replaces the program at `path` with the ELF file at `source`.
+fn reload(caller: &Process, path: &str, source: &str) -> Result<(), ProcessServiceError> {
+    check_permission(caller)?;
+    let data = with_file(caller.pid(), source, |data| {
+        select_syscall_abi(data, source, None).map(|_| data.to_vec())
+    })
+    .ok_or(ProcessServiceError::NotFound)??;
+    log::info!(
+        "pid={} replaces '{}' with '{}' ({} bytes)",
+        caller.pid(),
+        path,
+        source,
+        data.len()
+    );
+    libfileserver::FILESYSTEM
+        .lock()
+        .replace_file(path, data)
+        .map_err(|_| ProcessServiceError::ReloadUnsupported)
+}
+
+/// Opens the files on behalf of the caller for the new process. If a file can't be opened,
+/// the new process gets none of them.
+fn preopen_files(
+    caller: ProcessId,
+    child: ProcessId,
+    preopened: &[PreopenedFile],
+) -> Result<(), ProcessServiceError> {
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    for (i, file) in preopened.iter().enumerate() {
+        let fd = (file.fd.raw() as u64).into();
+        if fs
+            .preopen_file(caller, child, fd, &file.path, file.flags)
+            .is_err()
+        {
+            log::debug!("pid={} can't pre-open '{}'", caller, file.path);
+            for file in &preopened[..i] {
+                let _ = fs.close_file(child, (file.fd.raw() as u64).into());
+            }
+            return Err(ProcessServiceError::NotFound);
+        }
+    }
+    Ok(())
+}
+
+/// Gives the new process copies of the open files of the caller. If a file can't be
+/// inherited, the new process gets none of them.
+fn inherit_files(
+    caller: ProcessId,
+    child: ProcessId,
+    fds: &[SpawnFd],
+) -> Result<(), ProcessServiceError> {
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    for (i, fd) in fds.iter().enumerate() {
+        let parent_fd = (fd.parent_fd.raw() as u64).into();
+        let child_fd = (fd.child_fd.raw() as u64).into();
+        if let Err(err) = fs.inherit_file(caller, parent_fd, child, child_fd) {
+            log::debug!(
+                "pid={} can't pass fd={} to pid={}: {:?}",
+                caller,
+                fd.parent_fd.raw(),
+                child,
+                err
+            );
+            for fd in &fds[..i] {
+                let _ = fs.close_file(child, (fd.child_fd.raw() as u64).into());
+            }
+            return Err(match err {
+                FsError::BadFd => ProcessServiceError::NotFound,
+                FsError::Unsupported => ProcessServiceError::Unsupported,
+                _ => ProcessServiceError::InvalidArgument,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Only the parent of a process and privileged processes can query its status. Once the
+/// parent got the exit status, the PID gets reused, see [`reap_process`].
+fn status(caller: &Process, pid: ProcessId) -> ProcessStatusResponse {
+    check_parent(caller, pid)?;
+    let status = exit_status(pid).map_or(ProcessStatus::Running, ProcessStatus::Exited);
+    let is_parent = signal_target(pid).and_then(|target| target.parent) == Some(caller.pid());
+    if is_parent && status != ProcessStatus::Running {
+        reap_process(pid);
+    }
+    Ok(status)
+}
+
+/// Fails unless the caller is the parent of the process or privileged.
+fn check_parent(caller: &Process, pid: ProcessId) -> Result<(), ProcessServiceError> {
+    // a queued process has no signal target yet
+    let parent = QUEUED_LAUNCHES
+        .lock()
+        .iter()
+        .find(|launch| launch.pid == pid)
+        .map(|launch| launch.parent)
+        .or_else(|| signal_target(pid).and_then(|target| target.parent))
+        .ok_or(ProcessServiceError::NoSuchProcess)?;
+    if parent != caller.pid() && !is_privileged(caller.pid()) {
+        return Err(ProcessServiceError::PermissionDenied);
+    }
+    Ok(())
+}
+
+/// Processes can only push capabilities to their parent and to their children, unless
+/// they are privileged, so that they can't fill the capability space of arbitrary
+/// processes.
+fn send_cap(caller: &Process, item: &TypedItem, to: ProcessId) -> ProcessSendCapResponse {
+    // the roottask doesn't receive capabilities this way
+    let target = match signal_target(to) {
+        Some(target) if to != ROOTTASK_PROCESS_PID && exit_status(to).is_none() => target,
+        _ => return Err(ProcessServiceError::NoSuchProcess),
+    };
+    let is_parent = target.parent == Some(caller.pid());
+    let is_child = signal_target(caller.pid()).and_then(|target| target.parent) == Some(to);
+    if !is_parent && !is_child && !is_privileged(caller.pid()) {
+        log::debug!(
+            "pid={} isn't allowed to send capabilities to pid={}",
+            caller.pid(),
+            to
+        );
+        return Err(ProcessServiceError::PermissionDenied);
+    }
+    transfer_cap(caller.pd_obj().cap_sel(), item, to).map_err(map_cap_transfer_error)
+}
+
+fn map_cap_transfer_error(err: CapTransferError) -> ProcessServiceError {
+    match err {
+        CapTransferError::InvalidItem | CapTransferError::NotReceived => {
+            ProcessServiceError::InvalidCapability
+        }
+        CapTransferError::CapSpaceFull => ProcessServiceError::CapSpaceFull,
+    }
+}
+
+/// Linux processes catch signals instead, see
+/// [`crate::roottask_exception::handle_unresolved_fault`].
+fn set_fault_handler(
+    caller: &Process,
+    entry: u64,
+    stack_top: u64,
+) -> Result<(), ProcessServiceError> {
+    if caller.syscall_abi() != SyscallAbi::NativeHedron {
+        return Err(ProcessServiceError::Unsupported);
+    }
+    let handler = match (entry, stack_top) {
+        (0, _) => None,
+        (_, 0) => return Err(ProcessServiceError::InvalidArgument),
+        (entry, stack_top) => Some(FaultHandler { entry, stack_top }),
+    };
+    log::debug!("pid={} sets fault handler {:x?}", caller.pid(), handler);
+    caller.set_fault_handler(handler);
+    Ok(())
+}
+
+/// Only privileged processes may launch or reload programs. This prevents that launched
+/// programs launch further programs without limits. See [`is_privileged`].
+fn check_permission(caller: &Process) -> Result<(), ProcessServiceError> {
+    if is_privileged(caller.pid()) {
+        Ok(())
+    } else {
+        log::debug!("pid={} isn't allowed to launch programs", caller.pid());
+        Err(ProcessServiceError::PermissionDenied)
+    }
+}
+
+/// Starts all processes that the service queued. Must be called by the main global EC of
+/// the roottask, which doesn't hold the lock of the process manager.
+pub fn start_queued_processes() {
+    let launches = core::mem::take(&mut *QUEUED_LAUNCHES.lock());
+    for launch in launches {
+        PROCESS_MNG.lock().start_process_with_pid(
+            launch.pid,
+            launch.elf_file,
+            launch.path,
+            launch.syscall_abi,
+            launch.parent,
+            launch.argv,
+            launch.envp,
+            launch.sched_params,
+            launch.cpu,
+            launch.aslr,
+        );
+    }
+}
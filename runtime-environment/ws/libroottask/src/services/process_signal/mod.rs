@@ -0,0 +1,92 @@
+//! Process signal service. Lets the roottask or the parent of a process send a signal
+//! to it, for example to interrupt or terminate a child. Linux processes use the
+//! `kill` and `tgkill` syscalls instead.
+
+use crate::process::{
+    post_signal,
+    sig_valid,
+    signal_target,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::rc::Rc;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::process_signal::{
+    ProcessSignal,
+    ProcessSignalServiceError,
+    ProcessSignalServiceRequest,
+    ProcessSignalServiceResponse,
+};
+use libhrstd::service_ids::ServiceId;
+
+/// Creates a new PROCESS SIGNAL service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::ProcessSignalService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the PROCESS SIGNAL Portal.
+pub fn process_signal_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<ProcessSignalServiceRequest>().unwrap();
+    let response = signal_process(process, request.pid, request.signal);
+    utcb.store_data(&response).unwrap();
+    *do_reply = true;
+}
+
+/// Sends `signal` from `sender` to the process with the given PID. The roottask may
+/// signal every process, other processes only themselves and their children.
+///
+/// `SIGKILL`, `SIGSTOP`, and `SIGCONT` take effect right away. Linux processes handle
+/// other signals themselves; native processes get their default action right away, see
+/// [`post_signal`]. The roottask can't be signalled.
+pub fn signal_process(
+    sender: &Process,
+    pid: ProcessId,
+    signal: ProcessSignal,
+) -> ProcessSignalServiceResponse {
+    let sig = signal.signum();
+    if !sig_valid(sig) {
+        return Err(ProcessSignalServiceError::InvalidSignal);
+    }
+    let target = signal_target(pid).ok_or(ProcessSignalServiceError::NoSuchProcess)?;
+
+    let privileged = sender.pid() == ROOTTASK_PROCESS_PID
+        || sender.pid() == pid
+        || target.parent == Some(sender.pid());
+    if !privileged {
+        log::debug!(
+            "pid={} isn't allowed to send signal {} to pid={}",
+            sender.pid(),
+            sig,
+            pid
+        );
+        return Err(ProcessSignalServiceError::PermissionDenied);
+    }
+    if !post_signal(pid, sig) {
+        return Err(ProcessSignalServiceError::Unsupported);
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Scheduling service. Lets a process query and adjust the priority and the time quantum
+//! of its own SC and of the SCs of its children at runtime. Privileged processes can
+//! adjust every process. See [`crate::process::set_scheduling_params`]. The initial
+//! parameters come from the process service, see
+//! [`libhrstd::rt::services::process::ProcessServiceRequest::Launch`].
+
+use crate::process::{
+    is_privileged,
+    scheduling_params,
+    set_scheduling_params,
+    signal_target,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::rc::Rc;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::scheduling::{
+    SchedulingServiceError,
+    SchedulingServiceRequest,
+    SchedulingServiceResponse,
+};
+use libhrstd::service_ids::ServiceId;
+
+/// Creates a new SCHEDULING service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::SchedulingService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the SCHEDULING Portal.
+pub fn scheduling_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<SchedulingServiceRequest>().unwrap();
+    let response = handle_request(process, request);
+    utcb.store_data(&response).unwrap();
+    *do_reply = true;
+}
+
+fn handle_request(
+    caller: &Process,
+    request: SchedulingServiceRequest,
+) -> SchedulingServiceResponse {
+    match request {
+        SchedulingServiceRequest::Get { pid } => {
+            let pid = pid.unwrap_or_else(|| caller.pid());
+            scheduling_params(pid).ok_or(SchedulingServiceError::NoSuchProcess)
+        }
+        SchedulingServiceRequest::Set { pid, params } => {
+            let pid = pid.unwrap_or_else(|| caller.pid());
+            check_permission(caller, pid)?;
+            set_scheduling_params(pid, params)?;
+            log::info!(
+                "pid={} set the scheduling params of pid={}: priority={}, quantum={}µs",
+                caller.pid(),
+                pid,
+                params.priority,
+                params.quantum_us
+            );
+            Ok(params)
+        }
+    }
+}
+
+/// Privileged processes (see [`is_privileged`]) may adjust every process, other processes
+/// only themselves and their children.
+fn check_permission(caller: &Process, pid: ProcessId) -> Result<(), SchedulingServiceError> {
+    let target = signal_target(pid).ok_or(SchedulingServiceError::NoSuchProcess)?;
+    let privileged =
+        is_privileged(caller.pid()) || caller.pid() == pid || target.parent == Some(caller.pid());
+    if privileged {
+        Ok(())
+    } else {
+        log::debug!(
+            "pid={} isn't allowed to adjust the scheduling params of pid={}",
+            caller.pid(),
+            pid
+        );
+        Err(SchedulingServiceError::PermissionDenied)
+    }
+}
@@ -0,0 +1,368 @@
+//! Semaphore service. Processes create named or anonymous semaphores, i.e. Hedron SMs, that
+//! the roottask owns. The roottask delegates each SM with the permissions for "up" and
+//! "down" into the PD of each process that creates or opens it. Afterwards, the processes
+//! synchronize directly via Hedron.
+//!
+//! The delegations aren't tracked per PD, see
+//! [`libhrstd::kobjects::PdObject::track_delegation`], because revoking them from one
+//! process would revoke them from all processes that share the SM. Instead, the roottask
+//! destroys the SM with all its delegations once the last process closed it and it has no
+//! name anymore. Like the received capabilities, the selectors of semaphores in the
+//! capability space of a process are never reused, see [`MAX_SEMAPHORES_PER_PROCESS`].
+
+use crate::process::{
+    alloc_cap_sels,
+    free_cap_sels,
+    process_cap_sels,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::cap_space::user::UserAppCapSpace;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::syscall::{
+    sys_create_sm,
+    sys_pd_ctrl_delegate,
+    sys_revoke,
+    DelegateFlags,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::libhedron::{
+    CrdObj,
+    CrdObjSM,
+    SMCapPermissions,
+};
+use libhrstd::process::consts::{
+    ProcessId,
+    NUM_PROCESSES,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::rt::services::semaphore::{
+    is_valid_semaphore_name,
+    SemCreateRequest,
+    Semaphore,
+    SemaphoreService,
+    SemaphoreServiceError,
+    SemaphoreServiceRequest,
+    SemaphoreServiceResponse,
+    MAX_SEMAPHORES_PER_PROCESS,
+    SEM_VALUE_MAX,
+};
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// All semaphores of all processes.
+static SEMAPHORES: SimpleMutex<SemaphoreTable> = SimpleMutex::new(SemaphoreTable::new());
+
+/// Creates a new semaphore service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::SemaphoreService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the semaphore Portal.
+pub fn semaphore_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<SemaphoreServiceRequest>().unwrap();
+    log::trace!(
+        "semaphore request from pid={}: {:?}",
+        process.pid(),
+        request
+    );
+    let pid = process.pid();
+    match request {
+        SemaphoreServiceRequest::Create(request) => {
+            rpc_serve::<SemaphoreService, _>(request, utcb, |r| create(pid, r))
+        }
+        SemaphoreServiceRequest::Open(request) => {
+            rpc_serve::<SemaphoreService, _>(request, utcb, |r| {
+                let mut table = SEMAPHORES.lock();
+                let sm_sel = table
+                    .lookup(&r.name)
+                    .ok_or(SemaphoreServiceError::NotFound)?;
+                open(&mut table, pid, sm_sel)
+            })
+        }
+        SemaphoreServiceRequest::Close(request) => {
+            rpc_serve::<SemaphoreService, _>(request, utcb, |r| {
+                let destroyed = SEMAPHORES.lock().close(pid, r.sem.sel())?;
+                destroyed.into_iter().for_each(destroy_sm);
+                Ok(())
+            })
+        }
+        SemaphoreServiceRequest::Unlink(request) => {
+            rpc_serve::<SemaphoreService, _>(request, utcb, |r| {
+                let destroyed = SEMAPHORES.lock().unlink(&r.name)?;
+                destroyed.into_iter().for_each(destroy_sm);
+                Ok(())
+            })
+        }
+    }
+    *do_reply = true;
+}
+
+fn create(pid: ProcessId, request: SemCreateRequest) -> SemaphoreServiceResponse<Semaphore> {
+    if request.value > SEM_VALUE_MAX {
+        return Err(SemaphoreServiceError::InvalidValue);
+    }
+    if let Some(name) = &request.name {
+        if !is_valid_semaphore_name(name) {
+            return Err(SemaphoreServiceError::InvalidName);
+        }
+    }
+
+    let mut table = SEMAPHORES.lock();
+    if let Some(sm_sel) = request.name.as_deref().and_then(|name| table.lookup(name)) {
+        return if request.exclusive {
+            Err(SemaphoreServiceError::Exists)
+        } else {
+            open(&mut table, pid, sm_sel)
+        };
+    }
+    // fail before the SM exists
+    table.check_free_slot(pid)?;
+
+    let sm_sel = alloc_cap_sels(1).ok_or(SemaphoreServiceError::OutOfResources)?;
+    if let Err(e) = sys_create_sm(sm_sel, RootCapSpace::RootPd.val(), request.value) {
+        log::warn!("can't create SM for pid={}: {:?}", pid, e);
+        free_cap_sels(sm_sel, 1);
+        return Err(SemaphoreServiceError::OutOfResources);
+    }
+    log::debug!(
+        "pid={} created semaphore {} ({:?}) with value {}",
+        pid,
+        sm_sel,
+        request.name,
+        request.value
+    );
+    table.insert(sm_sel, request.name);
+    open(&mut table, pid, sm_sel)
+}
+
+/// Gives the process a handle of the SM and delegates the SM if the process doesn't have it
+/// yet.
+fn open(
+    table: &mut SemaphoreTable,
+    pid: ProcessId,
+    sm_sel: CapSel,
+) -> SemaphoreServiceResponse<Semaphore> {
+    let (sel, is_new) = table.open(pid, sm_sel)?;
+    if is_new {
+        let pd = process_cap_sels(pid)
+            .expect("running processes have capability selectors")
+            .pd();
+        let perms = SMCapPermissions::UP | SMCapPermissions::DOWN;
+        sys_pd_ctrl_delegate(
+            RootCapSpace::RootPd.val(),
+            pd,
+            CrdObjSM::new(sm_sel, 0, perms),
+            CrdObjSM::new(sel, 0, perms),
+            DelegateFlags::default(),
+        )
+        .unwrap();
+    }
+    Ok(Semaphore::from_sel(sel))
+}
+
+/// Revokes the SM from all processes, destroys it, and frees its selector.
+fn destroy_sm(sm_sel: CapSel) {
+    if let Err(e) = sys_revoke(CrdObj::new(sm_sel, 0), true) {
+        log::error!("can't revoke semaphore {}: {:?}", sm_sel, e);
+    }
+    free_cap_sels(sm_sel, 1);
+    log::debug!("destroyed semaphore {}", sm_sel);
+}
+
+/// Closes all semaphores of a stopped process. Anonymous and unlinked semaphores that no
+/// other process has open get destroyed.
+pub fn close_semaphores(pid: ProcessId) {
+    let destroyed = SEMAPHORES.lock().close_all(pid);
+    destroyed.into_iter().for_each(destroy_sm);
+}
+
+/// A semaphore of the roottask.
+#[derive(Debug)]
+struct Sm {
+    /// `None` for anonymous and unlinked semaphores.
+    name: Option<String>,
+    /// Number of processes that have it open.
+    users: u64,
+}
+
+/// Semaphores by their selector in the roottask and the handles of processes. Contains
+/// only plain data; the caller performs the syscalls for the SMs that it returns.
+#[derive(Debug)]
+struct SemaphoreTable {
+    sms: BTreeMap<CapSel, Sm>,
+    /// Selector of the SM by the process and the selector in its capability space.
+    handles: BTreeMap<(ProcessId, CapSel), CapSel>,
+    /// Number of semaphore selectors that each process used so far, indexed by PID.
+    used_sels: [u64; NUM_PROCESSES as usize],
+}
+
+impl SemaphoreTable {
+    const fn new() -> Self {
+        Self {
+            sms: BTreeMap::new(),
+            handles: BTreeMap::new(),
+            used_sels: [0; NUM_PROCESSES as usize],
+        }
+    }
+
+    fn insert(&mut self, sm_sel: CapSel, name: Option<String>) {
+        self.sms.insert(sm_sel, Sm { name, users: 0 });
+    }
+
+    /// Returns the selector of the SM with the name.
+    fn lookup(&self, name: &str) -> Option<CapSel> {
+        self.sms
+            .iter()
+            .find(|(_, sm)| sm.name.as_deref() == Some(name))
+            .map(|(sm_sel, _)| *sm_sel)
+    }
+
+    /// Fails if the process can't get another semaphore selector.
+    fn check_free_slot(&self, pid: ProcessId) -> SemaphoreServiceResponse<()> {
+        if self.used_sels[pid as usize] < MAX_SEMAPHORES_PER_PROCESS {
+            Ok(())
+        } else {
+            Err(SemaphoreServiceError::TooManySemaphores)
+        }
+    }
+
+    /// Returns the selector of the SM in the capability space of the process and whether
+    /// the process just got it, i.e. whether the roottask must delegate it.
+    fn open(&mut self, pid: ProcessId, sm_sel: CapSel) -> SemaphoreServiceResponse<(CapSel, bool)> {
+        if let Some((&(_, sel), _)) = self
+            .handles
+            .iter()
+            .find(|((handle_pid, _), handle_sm)| *handle_pid == pid && **handle_sm == sm_sel)
+        {
+            return Ok((sel, false));
+        }
+        self.check_free_slot(pid)?;
+        let sel = UserAppCapSpace::SemaphoreBase.val() + self.used_sels[pid as usize];
+        self.used_sels[pid as usize] += 1;
+        self.handles.insert((pid, sel), sm_sel);
+        self.sms.get_mut(&sm_sel).unwrap().users += 1;
+        Ok((sel, true))
+    }
+
+    /// Removes the handle of the process. Returns the SM if nobody can use it anymore.
+    fn close(&mut self, pid: ProcessId, sel: CapSel) -> SemaphoreServiceResponse<Option<CapSel>> {
+        let sm_sel = self
+            .handles
+            .remove(&(pid, sel))
+            .ok_or(SemaphoreServiceError::UnknownSemaphore)?;
+        let sm = self.sms.get_mut(&sm_sel).unwrap();
+        sm.users -= 1;
+        Ok(self.remove_if_unused(sm_sel))
+    }
+
+    /// Removes the name of the SM. Returns the SM if nobody can use it anymore.
+    fn unlink(&mut self, name: &str) -> SemaphoreServiceResponse<Option<CapSel>> {
+        let sm_sel = self.lookup(name).ok_or(SemaphoreServiceError::NotFound)?;
+        self.sms.get_mut(&sm_sel).unwrap().name = None;
+        Ok(self.remove_if_unused(sm_sel))
+    }
+
+    /// Removes all handles of the process and returns the SMs that nobody can use anymore.
+    /// The selectors of the process are free again for the next process with its PID.
+    fn close_all(&mut self, pid: ProcessId) -> Vec<CapSel> {
+        self.used_sels[pid as usize] = 0;
+        let sels = self
+            .handles
+            .keys()
+            .filter(|(handle_pid, _)| *handle_pid == pid)
+            .map(|(_, sel)| *sel)
+            .collect::<Vec<_>>();
+        sels.into_iter()
+            .filter_map(|sel| self.close(pid, sel).unwrap())
+            .collect()
+    }
+
+    fn remove_if_unused(&mut self, sm_sel: CapSel) -> Option<CapSel> {
+        let sm = &self.sms[&sm_sel];
+        (sm.users == 0 && sm.name.is_none()).then(|| {
+            self.sms.remove(&sm_sel);
+            sm_sel
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_semaphore_table() {
+        let base = UserAppCapSpace::SemaphoreBase.val();
+        let mut table = SemaphoreTable::new();
+        table.insert(1000, Some("jobs".to_string()));
+        assert_eq!(table.lookup("jobs"), Some(1000));
+        assert_eq!(table.open(1, 1000), Ok((base, true)));
+        // the same process gets the same selector
+        assert_eq!(table.open(1, 1000), Ok((base, false)));
+        assert_eq!(table.open(2, 1000), Ok((base, true)));
+
+        table.insert(1001, None);
+        assert_eq!(table.open(1, 1001), Ok((base + 1, true)));
+
+        // named semaphores survive until they are unlinked
+        assert_eq!(table.close(1, base), Ok(None));
+        assert_eq!(
+            table.close(1, base),
+            Err(SemaphoreServiceError::UnknownSemaphore)
+        );
+        assert_eq!(table.unlink("jobs"), Ok(None));
+        assert_eq!(table.lookup("jobs"), None);
+
+        // selectors are never reused while the process runs
+        table.insert(1002, None);
+        assert_eq!(table.open(1, 1002), Ok((base + 2, true)));
+
+        assert_eq!(table.close_all(2), [1000]);
+        assert_eq!(table.close_all(1), [1001, 1002]);
+        assert!(table.sms.is_empty());
+
+        // the next process with the PID starts from the beginning
+        table.insert(1003, None);
+        assert_eq!(table.open(1, 1003), Ok((base, true)));
+    }
+
+    #[test]
+    fn test_too_many_semaphores() {
+        let mut table = SemaphoreTable::new();
+        for sm_sel in 0..MAX_SEMAPHORES_PER_PROCESS {
+            table.insert(sm_sel, None);
+            assert!(table.open(1, sm_sel).is_ok());
+        }
+        assert_eq!(
+            table.check_free_slot(1),
+            Err(SemaphoreServiceError::TooManySemaphores)
+        );
+        assert_eq!(table.check_free_slot(2), Ok(()));
+    }
+}
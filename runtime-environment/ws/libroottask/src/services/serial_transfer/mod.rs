@@ -0,0 +1,194 @@
+//! Serial transfer service. Transfers files between the file system and a host over the
+//! serial port with the protocol of [`libhrstd::util::serial_transfer`]; the host runs
+//! `serialxfer-host`. The shell offers it as `send FILE` and `recv FILE`, e.g. to get a new
+//! build of a program onto real hardware and reload it.
+//!
+//! The service holds the lock of the stdout writer during the whole transfer, hence no
+//! other output interferes with the packets. The console and all other services stall
+//! until the transfer is done.
+
+use crate::process::{
+    is_privileged,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::rt::userland::with_file;
+use crate::services::stdout;
+use crate::services::stdout::StdoutWriter;
+use crate::time;
+use alloc::rc::Rc;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::rt::services::serial_transfer::{
+    SerialTransferError,
+    SerialTransferRequest,
+    SerialTransferResponse,
+    SerialTransferService,
+    SERIAL_TRANSFER_MAX_FILE_SIZE,
+};
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutexGuard;
+use libhrstd::util::serial_transfer::{
+    recv_file,
+    send_file,
+    SerialLink,
+    TransferError,
+    BYTE_TIMEOUT_MS,
+};
+
+/// Creates a new serial transfer service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::SerialTransferService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the serial transfer Portal.
+pub fn serial_transfer_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<SerialTransferRequest>().unwrap();
+    match request {
+        SerialTransferRequest::Send(request) => {
+            rpc_serve::<SerialTransferService, _>(request, utcb, |r| send(process, &r.path))
+        }
+        SerialTransferRequest::Recv(request) => {
+            rpc_serve::<SerialTransferService, _>(request, utcb, |r| recv(process, &r.path))
+        }
+    }
+    *do_reply = true;
+}
+
+/// Sends the file at `path` to the host.
+fn send(caller: &Process, path: &str) -> SerialTransferResponse {
+    check_permission(caller)?;
+    let data = with_file(caller.pid(), path, |data| data.to_vec())
+        .ok_or(SerialTransferError::FileError)?;
+    let name = path.rsplit('/').next().unwrap_or(path);
+    log::info!(
+        "sending {} ({} bytes) over the serial port",
+        path,
+        data.len()
+    );
+    // the link must be gone before the logger writes again
+    let res = send_file(&mut WriterLink::new(), name, &data);
+    res.map_err(map_error)?;
+    log::info!("sent {}", path);
+    Ok(data.len() as u64)
+}
+
+/// Receives a file from the host and stores it at `path`.
+fn recv(caller: &Process, path: &str) -> SerialTransferResponse {
+    check_permission(caller)?;
+    log::info!("waiting for a file from the host for {}", path);
+    let res = recv_file(&mut WriterLink::new(), SERIAL_TRANSFER_MAX_FILE_SIZE);
+    let file = res.map_err(map_error)?;
+    write_file(caller.pid(), path, &file.data)?;
+    log::info!(
+        "received {} ({} bytes) as {}",
+        file.name,
+        file.data.len(),
+        path
+    );
+    Ok(file.data.len() as u64)
+}
+
+/// Replaces the file at `path` on behalf of `caller` with a new one that contains `data`.
+/// The file is executable, as it is usually a program.
+fn write_file(caller: ProcessId, path: &str, data: &[u8]) -> Result<(), SerialTransferError> {
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    // fails if there is no such file
+    let _ = fs.unlink_file(caller, path);
+    let fd = fs
+        .open_or_create_file(
+            caller,
+            path,
+            FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+            0o755,
+        )
+        .map_err(|_| SerialTransferError::FileError)?;
+    let mut res = Ok(());
+    let mut written = 0;
+    while written < data.len() {
+        match fs.write_file(caller, fd, &data[written..]) {
+            Ok(count) if count > 0 => written += count,
+            _ => {
+                res = Err(SerialTransferError::FileError);
+                break;
+            }
+        }
+    }
+    fs.close_file(caller, fd).unwrap();
+    res
+}
+
+fn check_permission(caller: &Process) -> Result<(), SerialTransferError> {
+    if is_privileged(caller.pid()) {
+        Ok(())
+    } else {
+        log::debug!("pid={} isn't allowed to transfer files", caller.pid());
+        Err(SerialTransferError::PermissionDenied)
+    }
+}
+
+fn map_error(error: TransferError) -> SerialTransferError {
+    log::warn!("serial transfer failed: {:?}", error);
+    match error {
+        TransferError::Timeout => SerialTransferError::Timeout,
+        TransferError::Cancelled => SerialTransferError::Cancelled,
+        TransferError::TooLarge => SerialTransferError::TooLarge,
+        TransferError::TooManyErrors | TransferError::InvalidHeader => {
+            SerialTransferError::TransferFailed
+        }
+    }
+}
+
+/// The serial port, locked for the duration of a transfer.
+struct WriterLink<'a> {
+    writer: SimpleMutexGuard<'a, StdoutWriter>,
+    timeout_ticks: u64,
+}
+
+impl<'a> WriterLink<'a> {
+    fn new() -> Self {
+        Self {
+            writer: stdout::writer_mut(),
+            timeout_ticks: time::ns_to_ticks(BYTE_TIMEOUT_MS * 1_000_000),
+        }
+    }
+}
+
+impl<'a> SerialLink for WriterLink<'a> {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.writer.write_serial_bytes(bytes);
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        let begin = time::tsc_now();
+        while time::tsc_now() - begin < self.timeout_ticks {
+            if let Some(byte) = self.writer.try_read_byte() {
+                return Some(byte);
+            }
+            core::hint::spin_loop();
+        }
+        None
+    }
+}
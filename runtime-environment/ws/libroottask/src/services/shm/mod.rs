@@ -0,0 +1,692 @@
+//! Shared memory service. Processes create named or anonymous segments of memory that the
+//! roottask owns. The roottask maps a segment into the mmap area of each process that asks
+//! for it, with the permissions that the process chooses. Linux apps get segments via
+//! `memfd_create`, `shm_open`, and `mmap(MAP_SHARED)`, see the foreign syscall service.
+//!
+//! Hedron revokes a capability from all PDs that got it from the same capability of the
+//! roottask. Hence, the roottask doesn't delegate the memory of a segment directly, but
+//! maps the frames once more into its own address space for each mapping of a process and
+//! delegates this alias, see [`Attachment`]. Revoking the alias removes exactly this one
+//! mapping. The delegations aren't tracked per PD, see
+//! [`libhrstd::kobjects::PdObject::track_delegation`]; processes that exit drop their
+//! mappings in [`release_process`].
+//!
+//! The creator of a segment owns it. Like a file, the segment has a mode that decides
+//! which processes may open it for reading or writing, see
+//! [`libhrstd::rt::services::shm::ShmCreateRequest`]. The way a process opened a segment
+//! limits how it can map and resize it. The sizes of the segments of an owner count
+//! against [`SHM_SIZE_MAX_PER_PROCESS`].
+
+use crate::mem::{
+    map_phys_into_roottask,
+    revoke_from_roottask,
+    PhysAddr,
+    PHYS_FRAME_ALLOC,
+};
+use crate::process::Process;
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::mem::PAGE_SIZE;
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::MemCapPermissions;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::mem::calc_page_count;
+use libhrstd::process::consts::{
+    ProcessId,
+    ROOTTASK_PROCESS_PID,
+};
+use libhrstd::rt::services::rpc::rpc_serve;
+use libhrstd::rt::services::shm::{
+    is_valid_shm_name,
+    ShmAccess,
+    ShmId,
+    ShmInfo,
+    ShmService,
+    ShmServiceError,
+    ShmServiceRequest,
+    ShmServiceResponse,
+    SHM_SIZE_MAX,
+    SHM_SIZE_MAX_PER_PROCESS,
+};
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+use libhrstd::util::delegation::DelegationBuilder;
+
+/// All segments and mappings of all processes.
+static SHM: SimpleMutex<ShmTable> = SimpleMutex::new(ShmTable::new());
+
+/// Creates a new shared memory service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::ShmService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the shared memory Portal.
+pub fn shm_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<ShmServiceRequest>().unwrap();
+    log::trace!("shm request from pid={}: {:?}", process.pid(), request);
+    let pid = process.pid();
+    match request {
+        ShmServiceRequest::Create(request) => rpc_serve::<ShmService, _>(request, utcb, |r| {
+            create(
+                pid,
+                r.name.as_deref(),
+                r.size,
+                r.exclusive,
+                r.mode,
+                r.access,
+            )
+        }),
+        ShmServiceRequest::Open(request) => {
+            rpc_serve::<ShmService, _>(request, utcb, |r| open(pid, &r.name, r.access))
+        }
+        ShmServiceRequest::Resize(request) => {
+            rpc_serve::<ShmService, _>(request, utcb, |r| resize(pid, r.id, r.size))
+        }
+        ShmServiceRequest::Map(request) => rpc_serve::<ShmService, _>(request, utcb, |r| {
+            let size = size(pid, r.id)?;
+            let perm = match r.access {
+                ShmAccess::ReadOnly => MemCapPermissions::READ,
+                ShmAccess::ReadWrite => MemCapPermissions::RW,
+            };
+            map(process, r.id, 0, size, perm)
+        }),
+        ShmServiceRequest::Unmap(request) => rpc_serve::<ShmService, _>(request, utcb, |r| {
+            unmap(pid, r.addr)
+                .then(|| ())
+                .ok_or(ShmServiceError::UnknownSegment)
+        }),
+        ShmServiceRequest::Close(request) => {
+            rpc_serve::<ShmService, _>(request, utcb, |r| close(pid, r.id))
+        }
+        ShmServiceRequest::Unlink(request) => {
+            rpc_serve::<ShmService, _>(request, utcb, |r| unlink(pid, &r.name))
+        }
+    }
+    *do_reply = true;
+}
+
+/// Creates a segment of `size` zeroed bytes with the permissions `mode`, which the process
+/// owns, and opens it with `access`. A segment with the name gets opened instead, unless
+/// `exclusive` is set.
+pub fn create(
+    pid: ProcessId,
+    name: Option<&str>,
+    size: u64,
+    exclusive: bool,
+    mode: u16,
+    access: ShmAccess,
+) -> ShmServiceResponse<ShmInfo> {
+    if let Some(name) = name {
+        if !is_valid_shm_name(name) {
+            return Err(ShmServiceError::InvalidName);
+        }
+    }
+    let mut table = SHM.lock();
+    if let Some(id) = name.and_then(|name| table.lookup(name)) {
+        return if exclusive {
+            Err(ShmServiceError::Exists)
+        } else {
+            table.open(pid, id, access)
+        };
+    }
+    table.check_limit(Some(pid), size)?;
+    let memory = ShmMemory::alloc(size)?;
+    let id = table.insert(pid, name.map(String::from), size, mode & 0o777, memory);
+    log::debug!(
+        "pid={} created shm segment {:?} ({:?}) with {} bytes and mode {:o}",
+        pid,
+        id,
+        name,
+        size,
+        mode
+    );
+    table.open(pid, id, access)
+}
+
+/// Opens the segment with the name for the process with `access`.
+pub fn open(pid: ProcessId, name: &str, access: ShmAccess) -> ShmServiceResponse<ShmInfo> {
+    let mut table = SHM.lock();
+    let id = table.lookup(name).ok_or(ShmServiceError::NotFound)?;
+    table.open(pid, id, access)
+}
+
+/// Returns the size in bytes of a segment that the process has open.
+pub fn size(pid: ProcessId, id: ShmId) -> ShmServiceResponse<u64> {
+    SHM.lock()
+        .segment_mut(pid, id, ShmAccess::ReadOnly)
+        .map(|segment| segment.size)
+}
+
+/// Returns the permissions of a segment that the process has open.
+pub fn mode(pid: ProcessId, id: ShmId) -> ShmServiceResponse<u16> {
+    SHM.lock()
+        .segment_mut(pid, id, ShmAccess::ReadOnly)
+        .map(|segment| segment.mode)
+}
+
+/// Changes the size of a segment that the process has open for writing. Keeps the contents
+/// up to the new size. Fails while the segment is mapped.
+pub fn resize(pid: ProcessId, id: ShmId, size: u64) -> ShmServiceResponse<()> {
+    let mut table = SHM.lock();
+    let segment = table.segment_mut(pid, id, ShmAccess::ReadWrite)?;
+    if segment.size == size {
+        return Ok(());
+    }
+    if segment.is_mapped() {
+        return Err(ShmServiceError::Busy);
+    }
+    if size > segment.size {
+        let (owner, growth) = (segment.owner, size - segment.size);
+        table.check_limit(owner, growth)?;
+    }
+    let segment = table.segment_mut(pid, id, ShmAccess::ReadWrite)?;
+    let mut memory = ShmMemory::alloc(size)?;
+    if let (Some(old), Some(new)) = (&segment.memory, &mut memory) {
+        let len = segment.size.min(size) as usize;
+        Rc::get_mut(new).unwrap().mem_as_mut()[..len].copy_from_slice(&old.mem_as_ref()[..len]);
+    }
+    segment.memory = memory;
+    segment.size = size;
+    Ok(())
+}
+
+/// Returns `len` bytes of a segment that the process has open, from `offset` on. Stops at
+/// the end of the segment.
+pub fn read(pid: ProcessId, id: ShmId, offset: u64, len: u64) -> ShmServiceResponse<Vec<u8>> {
+    let mut table = SHM.lock();
+    let segment = table.segment_mut(pid, id, ShmAccess::ReadOnly)?;
+    let begin = offset.min(segment.size) as usize;
+    let end = offset.saturating_add(len).min(segment.size) as usize;
+    Ok(segment
+        .memory
+        .as_ref()
+        .map(|memory| memory.mem_as_ref()[begin..end].to_vec())
+        .unwrap_or_default())
+}
+
+/// Maps `len` bytes of a segment that the process has open, from the page-aligned `offset`
+/// on, into the mmap area of the process with the permissions `perm`. Returns the address
+/// of the mapping. The mapping must not exceed the segment, rounded up to full pages.
+/// Writable mappings need a segment that the process has open for writing.
+pub fn map(
+    process: &Process,
+    id: ShmId,
+    offset: u64,
+    len: u64,
+    perm: MemCapPermissions,
+) -> ShmServiceResponse<u64> {
+    let access = if perm.contains(MemCapPermissions::WRITE) {
+        ShmAccess::ReadWrite
+    } else {
+        ShmAccess::ReadOnly
+    };
+    let mut table = SHM.lock();
+    let segment = table.segment_mut(process.pid(), id, access)?;
+    let page_count = calc_page_count(len as usize);
+    let first_page = (offset / PAGE_SIZE as u64) as usize;
+    let memory = match &segment.memory {
+        Some(memory) if page_count > 0 && first_page + page_count <= memory.page_count => {
+            memory.clone()
+        }
+        _ => return Err(ShmServiceError::InvalidSize),
+    };
+    let u_addr = process
+        .memory_manager_mut()
+        .reserve_mmap_area(page_count)
+        .map_err(|_| ShmServiceError::OutOfMemory)?;
+    let attachment = Attachment::new(memory, first_page, page_count);
+    DelegationBuilder::mem(attachment.r_alias..attachment.r_alias + attachment.len())
+        .perms(perm)
+        .at(u_addr)
+        .to(process.pd_obj().cap_sel())
+        .unwrap();
+    log::debug!(
+        "mapped shm segment {:?} to 0x{:x} of pid={} with {:?}",
+        id,
+        u_addr,
+        process.pid(),
+        perm
+    );
+    table
+        .attachments
+        .insert((process.pid(), u_addr), attachment);
+    Ok(u_addr)
+}
+
+/// Removes the mapping of a segment at `u_addr` from the process. Returns `false` if no
+/// segment is mapped there.
+pub fn unmap(pid: ProcessId, u_addr: u64) -> bool {
+    // the drop revokes the mapping; the memory lives on if somebody else uses it
+    SHM.lock().attachments.remove(&(pid, u_addr)).is_some()
+}
+
+/// Closes a segment of the process. Mappings of the segment stay valid.
+pub fn close(pid: ProcessId, id: ShmId) -> ShmServiceResponse<()> {
+    SHM.lock().close(pid, id)
+}
+
+/// Removes the name of a segment on behalf of the process.
+pub fn unlink(pid: ProcessId, name: &str) -> ShmServiceResponse<()> {
+    SHM.lock().unlink(pid, name)
+}
+
+/// Removes all mappings and closes all segments of a stopped process. The memory of a
+/// segment gets freed once nobody can use it anymore. Segments that the process owns lose
+/// their owner, so that a new process with the same PID doesn't get them.
+pub fn release_process(pid: ProcessId) {
+    let mut table = SHM.lock();
+    table.attachments.retain(|(att_pid, _), _| *att_pid != pid);
+    table.close_all(pid);
+    table
+        .segments
+        .values_mut()
+        .filter(|segment| segment.owner == Some(pid))
+        .for_each(|segment| segment.owner = None);
+}
+
+/// The memory of a segment. Physical frames that the roottask maps into its own address
+/// space. Dropping it revokes the mapping and returns the frames.
+#[derive(Debug)]
+struct ShmMemory {
+    r_address: u64,
+    phys_address: PhysAddr,
+    page_count: usize,
+}
+
+impl ShmMemory {
+    /// Allocates zeroed memory of `size` bytes, rounded up to full pages. Returns `None`
+    /// for zero bytes.
+    fn alloc(size: u64) -> ShmServiceResponse<Option<Rc<Self>>> {
+        if size > SHM_SIZE_MAX {
+            return Err(ShmServiceError::InvalidSize);
+        }
+        if size == 0 {
+            return Ok(None);
+        }
+        let page_count = calc_page_count(size as usize);
+        let phys_address = PHYS_FRAME_ALLOC
+            .lock()
+            .alloc(page_count)
+            .ok_or(ShmServiceError::OutOfMemory)?;
+        let r_address = map_phys_into_roottask(phys_address, page_count);
+        let mut memory = Self {
+            r_address,
+            phys_address,
+            page_count,
+        };
+        // frames keep the data of their previous owner
+        memory.mem_as_mut().fill(0);
+        Ok(Some(Rc::new(memory)))
+    }
+
+    fn len(&self) -> usize {
+        self.page_count * PAGE_SIZE
+    }
+
+    fn mem_as_ref(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.r_address as *const u8, self.len()) }
+    }
+
+    fn mem_as_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.r_address as *mut u8, self.len()) }
+    }
+}
+
+impl Drop for ShmMemory {
+    fn drop(&mut self) {
+        // all aliases are gone already, because they keep the memory alive
+        if revoke_from_roottask(self.r_address, self.page_count) {
+            PHYS_FRAME_ALLOC
+                .lock()
+                .free(self.phys_address, self.page_count);
+        }
+    }
+}
+
+/// A mapping of a segment in a process. The roottask maps the frames of the mapping once
+/// more and delegates this alias to the process. Dropping it revokes the alias and with it
+/// the mapping of the process.
+#[derive(Debug)]
+struct Attachment {
+    r_alias: u64,
+    page_count: usize,
+    /// Keeps the frames allocated while the process can access them.
+    _memory: Rc<ShmMemory>,
+}
+
+impl Attachment {
+    fn new(memory: Rc<ShmMemory>, first_page: usize, page_count: usize) -> Self {
+        let phys_address = memory.phys_address + (first_page * PAGE_SIZE) as u64;
+        Self {
+            r_alias: map_phys_into_roottask(phys_address, page_count),
+            page_count,
+            _memory: memory,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        (self.page_count * PAGE_SIZE) as u64
+    }
+}
+
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        revoke_from_roottask(self.r_alias, self.page_count);
+    }
+}
+
+/// A shared memory segment.
+#[derive(Debug)]
+struct Segment {
+    /// `None` for anonymous and unlinked segments.
+    name: Option<String>,
+    /// The creator. `None` after it exited.
+    owner: Option<ProcessId>,
+    /// Permission bits, see [`libhrstd::rt::services::shm::ShmCreateRequest::mode`].
+    mode: u16,
+    size: u64,
+    /// `None` if the segment is empty.
+    memory: Option<Rc<ShmMemory>>,
+    /// Number of processes that have it open.
+    users: u64,
+}
+
+impl Segment {
+    /// Returns whether a process has the memory mapped.
+    fn is_mapped(&self) -> bool {
+        self.memory
+            .as_ref()
+            .map_or(false, |memory| Rc::strong_count(memory) > 1)
+    }
+
+    /// Returns whether the mode lets the process open the segment with `access`. The
+    /// roottask may open every segment.
+    fn permits(&self, pid: ProcessId, access: ShmAccess) -> bool {
+        let bits = if pid == ROOTTASK_PROCESS_PID {
+            0o7
+        } else if self.owner == Some(pid) {
+            self.mode >> 6
+        } else {
+            self.mode
+        };
+        let needed = match access {
+            ShmAccess::ReadOnly => 0o4,
+            ShmAccess::ReadWrite => 0o6,
+        };
+        bits & needed == needed
+    }
+}
+
+/// Handles of a process for a segment, i.e. how often it has the segment open.
+#[derive(Debug)]
+struct Handle {
+    count: u64,
+    /// Whether the process opened the segment for writing at least once.
+    writable: bool,
+}
+
+/// Segments by their id, the number of times that each process opened each segment, and
+/// the mappings in processes by the process and the address.
+#[derive(Debug)]
+struct ShmTable {
+    segments: BTreeMap<ShmId, Segment>,
+    handles: BTreeMap<(ProcessId, ShmId), Handle>,
+    attachments: BTreeMap<(ProcessId, u64), Attachment>,
+    next_id: u64,
+}
+
+impl ShmTable {
+    const fn new() -> Self {
+        Self {
+            segments: BTreeMap::new(),
+            handles: BTreeMap::new(),
+            attachments: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    fn insert(
+        &mut self,
+        owner: ProcessId,
+        name: Option<String>,
+        size: u64,
+        mode: u16,
+        memory: Option<Rc<ShmMemory>>,
+    ) -> ShmId {
+        let id = ShmId::new(self.next_id);
+        self.next_id += 1;
+        let segment = Segment {
+            name,
+            owner: Some(owner),
+            mode,
+            size,
+            memory,
+            users: 0,
+        };
+        self.segments.insert(id, segment);
+        id
+    }
+
+    /// Returns the id of the segment with the name.
+    fn lookup(&self, name: &str) -> Option<ShmId> {
+        self.segments
+            .iter()
+            .find(|(_, segment)| segment.name.as_deref() == Some(name))
+            .map(|(id, _)| *id)
+    }
+
+    /// Checks that the segments of the owner can grow by `growth` bytes. Segments whose
+    /// owner exited can't grow.
+    fn check_limit(&self, owner: Option<ProcessId>, growth: u64) -> ShmServiceResponse<()> {
+        let owner = owner.ok_or(ShmServiceError::LimitExceeded)?;
+        let owned = self
+            .segments
+            .values()
+            .filter(|segment| segment.owner == Some(owner))
+            .map(|segment| segment.size)
+            .sum::<u64>();
+        if owned.saturating_add(growth) > SHM_SIZE_MAX_PER_PROCESS {
+            log::debug!("shm segments of pid={} would exceed the limit", owner);
+            Err(ShmServiceError::LimitExceeded)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the segment if the process has it open with `access`.
+    fn segment_mut(
+        &mut self,
+        pid: ProcessId,
+        id: ShmId,
+        access: ShmAccess,
+    ) -> ShmServiceResponse<&mut Segment> {
+        let handle = self
+            .handles
+            .get(&(pid, id))
+            .ok_or(ShmServiceError::UnknownSegment)?;
+        if access == ShmAccess::ReadWrite && !handle.writable {
+            return Err(ShmServiceError::PermissionDenied);
+        }
+        Ok(self.segments.get_mut(&id).unwrap())
+    }
+
+    fn open(
+        &mut self,
+        pid: ProcessId,
+        id: ShmId,
+        access: ShmAccess,
+    ) -> ShmServiceResponse<ShmInfo> {
+        let segment = self.segments.get_mut(&id).unwrap();
+        if !segment.permits(pid, access) {
+            return Err(ShmServiceError::PermissionDenied);
+        }
+        let handle = self.handles.entry((pid, id)).or_insert(Handle {
+            count: 0,
+            writable: false,
+        });
+        handle.count += 1;
+        handle.writable |= access == ShmAccess::ReadWrite;
+        if handle.count == 1 {
+            segment.users += 1;
+        }
+        Ok(ShmInfo {
+            id,
+            size: segment.size,
+        })
+    }
+
+    fn close(&mut self, pid: ProcessId, id: ShmId) -> ShmServiceResponse<()> {
+        let handle = self
+            .handles
+            .get_mut(&(pid, id))
+            .ok_or(ShmServiceError::UnknownSegment)?;
+        handle.count -= 1;
+        if handle.count == 0 {
+            self.handles.remove(&(pid, id));
+            self.segments.get_mut(&id).unwrap().users -= 1;
+            self.remove_if_unused(id);
+        }
+        Ok(())
+    }
+
+    /// Removes the name of the segment if the process owns it, or if the owner exited and
+    /// the process may write it. The roottask may unlink every segment.
+    fn unlink(&mut self, pid: ProcessId, name: &str) -> ShmServiceResponse<()> {
+        let id = self.lookup(name).ok_or(ShmServiceError::NotFound)?;
+        let segment = self.segments.get_mut(&id).unwrap();
+        let allowed = match segment.owner {
+            Some(owner) => owner == pid || pid == ROOTTASK_PROCESS_PID,
+            None => segment.permits(pid, ShmAccess::ReadWrite),
+        };
+        if !allowed {
+            return Err(ShmServiceError::PermissionDenied);
+        }
+        segment.name = None;
+        self.remove_if_unused(id);
+        Ok(())
+    }
+
+    /// Removes all handles of the process.
+    fn close_all(&mut self, pid: ProcessId) {
+        let ids = self
+            .handles
+            .keys()
+            .filter(|(handle_pid, _)| *handle_pid == pid)
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.handles.get_mut(&(pid, id)).unwrap().count = 1;
+            self.close(pid, id).unwrap();
+        }
+    }
+
+    /// Removes the segment if nobody can open it anymore. Mappings keep the memory alive.
+    fn remove_if_unused(&mut self, id: ShmId) {
+        let segment = &self.segments[&id];
+        if segment.users == 0 && segment.name.is_none() {
+            self.segments.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_shm_table() {
+        let mut table = ShmTable::new();
+        let id = table.insert(1, Some("frames".to_string()), 0, 0o644, None);
+        assert_eq!(table.lookup("frames"), Some(id));
+        assert_eq!(
+            table.open(1, id, ShmAccess::ReadWrite),
+            Ok(ShmInfo { id, size: 0 })
+        );
+        // e.g. two file descriptors of a Linux app
+        assert!(table.open(1, id, ShmAccess::ReadOnly).is_ok());
+        assert!(table.open(2, id, ShmAccess::ReadOnly).is_ok());
+        assert_eq!(table.segments[&id].users, 2);
+        assert!(table.segment_mut(3, id, ShmAccess::ReadOnly).is_err());
+
+        // only the owner may write and unlink the segment
+        assert_eq!(
+            table.open(2, id, ShmAccess::ReadWrite),
+            Err(ShmServiceError::PermissionDenied)
+        );
+        assert!(table.segment_mut(2, id, ShmAccess::ReadOnly).is_ok());
+        assert_eq!(
+            table.segment_mut(2, id, ShmAccess::ReadWrite).err(),
+            Some(ShmServiceError::PermissionDenied)
+        );
+        assert!(table.segment_mut(1, id, ShmAccess::ReadWrite).is_ok());
+        assert_eq!(
+            table.unlink(2, "frames"),
+            Err(ShmServiceError::PermissionDenied)
+        );
+
+        assert_eq!(table.close(1, id), Ok(()));
+        assert!(table.segment_mut(1, id, ShmAccess::ReadOnly).is_ok());
+        assert_eq!(table.close(1, id), Ok(()));
+        assert_eq!(table.close(1, id), Err(ShmServiceError::UnknownSegment));
+
+        // named segments survive until they are unlinked
+        table.close_all(2);
+        assert_eq!(table.lookup("frames"), Some(id));
+        assert_eq!(table.unlink(1, "frames"), Ok(()));
+        assert_eq!(table.unlink(1, "frames"), Err(ShmServiceError::NotFound));
+        assert!(table.segments.is_empty());
+
+        // anonymous segments go away with the last handle; ids are never reused
+        let anon = table.insert(1, None, 0, 0o600, None);
+        assert_ne!(anon, id);
+        assert!(table.open(1, anon, ShmAccess::ReadWrite).is_ok());
+        assert!(table.open(1, anon, ShmAccess::ReadWrite).is_ok());
+        table.close_all(1);
+        assert!(table.segments.is_empty());
+        assert!(table.handles.is_empty());
+    }
+
+    #[test]
+    fn test_shm_limit() {
+        let mut table = ShmTable::new();
+        assert_eq!(table.check_limit(Some(1), SHM_SIZE_MAX_PER_PROCESS), Ok(()));
+        let id = table.insert(1, Some("big".to_string()), SHM_SIZE_MAX, 0o666, None);
+        assert_eq!(
+            table.check_limit(Some(1), SHM_SIZE_MAX_PER_PROCESS),
+            Err(ShmServiceError::LimitExceeded)
+        );
+        assert_eq!(table.check_limit(Some(2), SHM_SIZE_MAX_PER_PROCESS), Ok(()));
+
+        // segments of exited owners can't grow, but others may unlink them
+        table.segments.get_mut(&id).unwrap().owner = None;
+        assert_eq!(
+            table.check_limit(None, 1),
+            Err(ShmServiceError::LimitExceeded)
+        );
+        assert_eq!(table.unlink(2, "big"), Ok(()));
+        assert!(table.segments.is_empty());
+    }
+}
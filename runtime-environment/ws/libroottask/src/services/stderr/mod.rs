@@ -1,5 +1,7 @@
+use crate::log_timestamp;
 use crate::process::Process;
 use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::services::stdout::reassembly::MsgReassembler;
 use alloc::rc::Rc;
 use core::fmt::Write;
 use libhrstd::kobjects::{
@@ -11,6 +13,8 @@ use libhrstd::libhedron::CapSel;
 use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
 use libhrstd::libhedron::HIP;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::stdout::OutputChunk;
 use libhrstd::service_ids::ServiceId;
 use libhrstd::sync::mutex::{
     SimpleMutex,
@@ -20,6 +24,9 @@ use libhrstd::sync::mutex::{
 /// Global instance of the writer. Protects/synchronizes writers.
 static STDERR_WRITER: SimpleMutex<StderrWriter> = SimpleMutex::new(StderrWriter::new());
 
+/// Incomplete messages that processes send in multiple chunks.
+static PENDING_MSGS: SimpleMutex<MsgReassembler> = SimpleMutex::new(MsgReassembler::new());
+
 /// Initializes the stderr writer struct. Afterwards [`writer`] can be called.
 pub fn init_writer(_hip: &HIP) {
     let mut lock = STDERR_WRITER.lock();
@@ -33,6 +40,11 @@ pub fn writer_mut<'a>() -> SimpleMutexGuard<'a, StderrWriter> {
     STDERR_WRITER.lock()
 }
 
+/// Drops the incomplete message of an exited process.
+pub fn discard_pending_msg(pid: ProcessId) {
+    PENDING_MSGS.lock().discard(pid);
+}
+
 /// Creates a new STDERR service PT, which can be delegated to a new process.
 pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
     let service = ServiceId::StderrService;
@@ -54,10 +66,21 @@ pub fn stderr_service_handler(
     do_reply: &mut bool,
 ) {
     // currently STDERR maps to STDOUT
-    let msg = utcb.load_data::<&str>().unwrap();
-    {
+    let chunk = utcb.load_data::<OutputChunk>().unwrap();
+    let msg = PENDING_MSGS.lock().push(process.pid(), &chunk);
+    // the whole message at once, so that the output of others can't split it
+    if let Some(msg) = msg {
         let mut writer = STDERR_WRITER.lock();
-        let res = write!(&mut writer, "[STDERR PID={}] {}\n", process.pid(), msg,);
+        let res = match log_timestamp::now() {
+            Some(timestamp) => write!(
+                &mut writer,
+                "{}[STDERR PID={}] {}\n",
+                timestamp,
+                process.pid(),
+                msg,
+            ),
+            None => write!(&mut writer, "[STDERR PID={}] {}\n", process.pid(), msg,),
+        };
         // drop before unwrap, because otherwise deadlock happens on panic
         // (panic needs lock to STDOUT_WRITER)
         core::mem::drop(writer);
@@ -87,6 +110,17 @@ impl StderrWriter {
         }
         self.init = true;
     }
+
+    /// Forwards binary data to stdout. See [`StdoutWriter::write_bytes`].
+    ///
+    /// [`StdoutWriter::write_bytes`]: super::stdout::StdoutWriter::write_bytes
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        if !self.init {
+            // note that Rust logger might not be initialized yet
+            panic!("not initialized");
+        }
+        super::stdout::writer_mut().write_bytes(bytes)
+    }
 }
 
 impl Write for StderrWriter {
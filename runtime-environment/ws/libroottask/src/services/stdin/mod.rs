@@ -0,0 +1,96 @@
+//! Stdin service. Lets processes read the input of the console, i.e. the bytes that the
+//! serial port and the PS/2 keyboard received, see [`crate::hw::input`]. The service never
+//! blocks. All processes share the same input.
+
+use crate::hw::input;
+use crate::process::Process;
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::services::stdout;
+use alloc::rc::Rc;
+use alloc::string::String;
+use core::fmt::Write;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::rt::services::stdin::{
+    StdinServiceRequest,
+    StdinServiceResponse,
+    STDIN_MAX_READ,
+};
+use libhrstd::service_ids::ServiceId;
+
+const BACKSPACE: char = '\x08';
+/// Most terminals send this for the backspace key.
+const DELETE: char = '\x7f';
+
+/// Creates a new STDIN service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::StdinService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the STDIN Portal.
+pub fn stdin_service_handler(
+    _pt: &Rc<PtObject>,
+    _process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<StdinServiceRequest>().unwrap();
+    let response = read_available(request.max_len.min(STDIN_MAX_READ), request.echo);
+    utcb.store_data(&response).unwrap();
+    *do_reply = true;
+}
+
+/// Checks if the console has input that a read returns immediately.
+pub fn input_available() -> bool {
+    input::has_input()
+}
+
+/// Reads at most `max_len` bytes of the input that already arrived, but never more than
+/// one line. Never blocks. Also used by `read` of Linux programs on their standard input.
+pub fn read_available(max_len: usize, echo: bool) -> StdinServiceResponse {
+    let bytes = input::read(max_len);
+    if echo {
+        let _ = stdout::writer_mut().write_str(&echo_text(&bytes));
+    }
+    bytes
+}
+
+/// Returns what a terminal shows for the input: line breaks start a new line,
+/// backspace erases the previous character, and other control characters are hidden.
+fn echo_text(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for char in String::from_utf8_lossy(bytes).chars() {
+        match char {
+            '\r' | '\n' => text.push_str("\r\n"),
+            BACKSPACE | DELETE => text.push_str("\x08 \x08"),
+            char if char.is_control() => {}
+            char => text.push(char),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_echo_text() {
+        assert_eq!(echo_text(b"ls /bin\r"), "ls /bin\r\n");
+        assert_eq!(echo_text(b"cd\x7f\x08\x1b"), "cd\x08 \x08\x08 \x08");
+    }
+}
@@ -22,14 +22,19 @@ impl DebugconWriter {
     pub fn init(&mut self, root_pd_sel: CapSel) {
         request_io_port(root_pd_sel, Self::DEBUGCON_PORT).unwrap();
     }
+
+    /// Writes the bytes to the I/O port.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|b| unsafe {
+            outb(Self::DEBUGCON_PORT, *b);
+        });
+    }
 }
 
 impl Write for DebugconWriter {
     /// Writes the data to the I/O port.
     fn write_str(&mut self, msg: &str) -> core::fmt::Result {
-        msg.bytes().for_each(|b| unsafe {
-            outb(Self::DEBUGCON_PORT, b);
-        });
+        self.write_bytes(msg.as_bytes());
         Ok(())
     }
 }
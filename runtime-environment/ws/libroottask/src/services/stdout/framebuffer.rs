@@ -0,0 +1,165 @@
+use crate::hw::framebuffer::font::{
+    self,
+    GLYPH_HEIGHT,
+    GLYPH_WIDTH,
+};
+use crate::hw::framebuffer::Framebuffer;
+use core::fmt::Write;
+
+/// Each row of a glyph covers two pixel lines, which gives the proportions of the VGA text
+/// mode.
+const SCALE_Y: u32 = 2;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT * SCALE_Y;
+const TAB_WIDTH: u32 = 8;
+/// Light gray on black, like the VGA text mode.
+const FOREGROUND: u32 = 0x00aa_aaaa;
+const BACKGROUND: u32 = 0x0000_0000;
+
+/// Text console on a framebuffer. Scrolls up when the output reaches the bottom and
+/// wraps long lines. Escape sequences, e.g. colors, are dropped.
+///
+/// **There should only be one instance of this!**
+#[derive(Debug)]
+pub(super) struct FramebufferWriter {
+    fb: Framebuffer,
+    columns: u32,
+    rows: u32,
+    column: u32,
+    row: u32,
+    escape: Escape,
+}
+
+/// Progress in an escape sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// After the escape character.
+    Started,
+    /// In a control sequence, i.e. after `ESC [`, until the final byte.
+    Csi,
+}
+
+impl FramebufferWriter {
+    /// Clears the framebuffer and starts at the top left corner.
+    pub fn new(fb: Framebuffer) -> Self {
+        fb.fill_rect(0, 0, fb.width, fb.height, BACKGROUND);
+        Self {
+            fb,
+            columns: fb.width / GLYPH_WIDTH,
+            rows: fb.height / CELL_HEIGHT,
+            column: 0,
+            row: 0,
+            escape: Escape::None,
+        }
+    }
+
+    fn write_char(&mut self, char: char) {
+        match (self.escape, char) {
+            (Escape::None, '\x1b') => self.escape = Escape::Started,
+            (Escape::Started, '[') => self.escape = Escape::Csi,
+            (Escape::Started, _) => self.escape = Escape::None,
+            // parameters and intermediate bytes
+            (Escape::Csi, '\x20'..='\x3f') => {}
+            (Escape::Csi, _) => self.escape = Escape::None,
+            (Escape::None, '\n') => self.new_line(),
+            (Escape::None, '\r') => self.column = 0,
+            (Escape::None, '\x08') => self.column = self.column.saturating_sub(1),
+            (Escape::None, '\t') => {
+                let column = (self.column / TAB_WIDTH + 1) * TAB_WIDTH;
+                while self.column < column.min(self.columns) {
+                    self.draw(' ');
+                }
+            }
+            (Escape::None, char) if char.is_control() => {}
+            (Escape::None, char) => self.draw(char),
+        }
+    }
+
+    /// Draws the character at the cursor and advances it.
+    fn draw(&mut self, char: char) {
+        if self.column == self.columns {
+            self.new_line();
+        }
+        self.fb.draw_glyph(
+            self.column * GLYPH_WIDTH,
+            self.row * CELL_HEIGHT,
+            font::glyph(char),
+            SCALE_Y,
+            FOREGROUND,
+            BACKGROUND,
+        );
+        self.column += 1;
+    }
+
+    /// Moves the cursor to the start of the next line.
+    fn new_line(&mut self) {
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.fb.scroll_up(CELL_HEIGHT, BACKGROUND);
+        }
+    }
+}
+
+impl Write for FramebufferWriter {
+    /// Draws the text.
+    fn write_str(&mut self, msg: &str) -> core::fmt::Result {
+        msg.chars().for_each(|char| self.write_char(char));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hw::framebuffer::BYTES_PER_PIXEL;
+    use alloc::string::String;
+    use alloc::vec;
+
+    /// Returns the text in a row of the console: the character whose glyph matches the
+    /// pixels of each cell, or `#` if none does.
+    fn text_row(pixels: &[u32], writer: &FramebufferWriter, row: u32) -> String {
+        let matches = |char: char, column: u32| {
+            font::glyph(char).iter().enumerate().all(|(y, bits)| {
+                let y = row * CELL_HEIGHT + y as u32 * SCALE_Y;
+                (0..GLYPH_WIDTH).all(|x| {
+                    let pixel = pixels[(y * writer.fb.width + column * GLYPH_WIDTH + x) as usize];
+                    (pixel == FOREGROUND) == (bits & (1 << x) != 0)
+                })
+            })
+        };
+        (0..writer.columns)
+            .map(|column| {
+                (' '..='~')
+                    .find(|char| matches(*char, column))
+                    .unwrap_or('#')
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_framebuffer_writer() {
+        // 16 columns and 3 rows
+        let mut pixels = vec![0xff_u32; 128 * 48];
+        let fb = Framebuffer {
+            r_addr: pixels.as_mut_ptr() as u64,
+            width: 128,
+            height: 48,
+            pitch: 128 * BYTES_PER_PIXEL,
+        };
+        let mut writer = FramebufferWriter::new(fb);
+        assert!(pixels.iter().all(|pixel| *pixel == BACKGROUND));
+
+        write!(writer, "ab\x1b[1;31mc\x1b[0m\r\nxyz\x08\x08Y\n1\tA").unwrap();
+        assert_eq!(text_row(&pixels, &writer, 0), "abc             ");
+        assert_eq!(text_row(&pixels, &writer, 1), "xYz             ");
+        assert_eq!(text_row(&pixels, &writer, 2), "1       A       ");
+
+        // scrolls and wraps the line
+        write!(writer, "\n0123456789abcdefXY").unwrap();
+        assert_eq!(text_row(&pixels, &writer, 0), "1       A       ");
+        assert_eq!(text_row(&pixels, &writer, 1), "0123456789abcdef");
+        assert_eq!(text_row(&pixels, &writer, 2), "XY              ");
+    }
+}
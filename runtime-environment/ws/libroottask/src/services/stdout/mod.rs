@@ -1,6 +1,11 @@
+use crate::hw::framebuffer::Framebuffer;
+use crate::hw::virtio_console::VirtioConsole;
+use crate::log_timestamp;
 use crate::process::Process;
 use crate::pt_multiplex::roottask_generic_portal_callback;
 use crate::services::stdout::debugcon::DebugconWriter;
+use crate::services::stdout::framebuffer::FramebufferWriter;
+use crate::services::stdout::reassembly::MsgReassembler;
 use crate::services::stdout::serial::SerialWriter;
 use alloc::rc::Rc;
 use core::fmt::{
@@ -16,6 +21,8 @@ use libhrstd::libhedron::CapSel;
 use libhrstd::libhedron::Mtd;
 use libhrstd::libhedron::Utcb;
 use libhrstd::libhedron::HIP;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::stdout::OutputChunk;
 use libhrstd::service_ids::ServiceId;
 use libhrstd::sync::mutex::{
     SimpleMutex,
@@ -24,11 +31,16 @@ use libhrstd::sync::mutex::{
 use runs_inside_qemu::runs_inside_qemu;
 
 mod debugcon;
+mod framebuffer;
+pub mod reassembly;
 mod serial;
 
 /// Global instance of the writer. Protects/synchronizes writers.
 static STDOUT_WRITER: SimpleMutex<StdoutWriter> = SimpleMutex::new(StdoutWriter::new());
 
+/// Incomplete messages that processes send in multiple chunks.
+static PENDING_MSGS: SimpleMutex<MsgReassembler> = SimpleMutex::new(MsgReassembler::new());
+
 /// Initializes the stdout writer struct. Afterwards [`writer`] can be called.
 pub fn init_writer(hip: &HIP) {
     let mut writer = STDOUT_WRITER.lock();
@@ -42,6 +54,11 @@ pub fn writer_mut<'a>() -> SimpleMutexGuard<'a, StdoutWriter> {
     STDOUT_WRITER.lock()
 }
 
+/// Drops the incomplete message of an exited process.
+pub fn discard_pending_msg(pid: ProcessId) {
+    PENDING_MSGS.lock().discard(pid);
+}
+
 /// Creates a new STDOUT service PT, which can be delegated to a new process.
 pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
     let service = ServiceId::StdoutService;
@@ -62,10 +79,21 @@ pub fn stdout_service_handler(
     utcb: &mut Utcb,
     do_reply: &mut bool,
 ) {
-    let msg = utcb.load_data::<&str>().unwrap();
-    {
+    let chunk = utcb.load_data::<OutputChunk>().unwrap();
+    let msg = PENDING_MSGS.lock().push(process.pid(), &chunk);
+    // the whole message at once, so that the output of others can't split it
+    if let Some(msg) = msg {
         let mut writer = STDOUT_WRITER.lock();
-        let res = write!(&mut writer, "[STDOUT PID={}] {}\n", process.pid(), msg,);
+        let res = match log_timestamp::now() {
+            Some(timestamp) => write!(
+                &mut writer,
+                "{}[STDOUT PID={}] {}\n",
+                timestamp,
+                process.pid(),
+                msg,
+            ),
+            None => write!(&mut writer, "[STDOUT PID={}] {}\n", process.pid(), msg,),
+        };
         // drop before unwrap, because otherwise deadlock happens on panic
         // (panic needs lock to STDOUT_WRITER)
         core::mem::drop(writer);
@@ -75,7 +103,9 @@ pub fn stdout_service_handler(
 }
 
 /// Handles the locations where Stdout-Output goes to.
-/// In our case, only Serial- and Debugcon, since we don't have any Display-driver.
+/// In our case Serial and Debugcon, and optionally a framebuffer console (see
+/// [`crate::hw::framebuffer`]). A virtio-console (see [`crate::hw::virtio_console`])
+/// replaces the serial port for output and input once it is attached.
 ///
 /// THERE SHOULD NEVER BE MORE THAN A SINGLE INSTANCE OF THIS.
 /// [`STDOUT_WRITER`] is the only instance allowed!
@@ -99,16 +129,85 @@ impl StdoutWriter {
         let inner = StdoutWriterInner::new(hip);
         self.inner.replace(inner);
     }
+
+    /// Checks if the console transport received a byte that wasn't read yet.
+    pub fn has_input(&mut self) -> bool {
+        let inner = self.inner.as_mut().expect("call init_writer() first");
+        match inner.virtio_console {
+            Some(ref mut console) => console.has_input(),
+            None => inner.serial_writer.has_input(),
+        }
+    }
+
+    /// Returns the next byte that the console transport (the serial port or the
+    /// virtio-console) received, if there is one. The transport is also an input of the
+    /// console; see [`crate::hw::input`].
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        let inner = self.inner.as_mut().expect("call init_writer() first");
+        match inner.virtio_console {
+            Some(ref mut console) => console.try_read_byte(),
+            None => inner.serial_writer.try_read_byte(),
+        }
+    }
+
+    /// Forwards binary data to all available destinations, for example records of the
+    /// binary log format (see [`crate::log_format`]).
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let inner = self.inner.as_mut().expect("call init_writer() first");
+        inner.write_transport_bytes(bytes);
+        if let Some(ref mut writer) = inner.debugcon_writer {
+            writer.write_bytes(bytes);
+        }
+    }
+
+    /// Adds the text console on the framebuffer as a destination of the text output.
+    /// Binary data doesn't go there.
+    pub fn attach_framebuffer(&mut self, fb: Framebuffer) {
+        self.inner
+            .as_mut()
+            .expect("call init_writer() first")
+            .framebuffer_writer
+            .replace(FramebufferWriter::new(fb));
+    }
+
+    /// Replaces the serial port with the virtio-console as the transport of the console.
+    /// Text output, binary data, and input use it from now on.
+    pub fn attach_virtio_console(&mut self, mut console: VirtioConsole) {
+        console
+            .write_str("+++ STDOUT via VirtioConsole ready +++ \n")
+            .unwrap();
+        self.inner
+            .as_mut()
+            .expect("call init_writer() first")
+            .virtio_console
+            .replace(console);
+    }
+
+    /// Writes binary data only to the console transport (the serial port or the
+    /// virtio-console), e.g. the packets of a file transfer with the host (see
+    /// [`crate::services::serial_transfer`]).
+    pub fn write_serial_bytes(&mut self, bytes: &[u8]) {
+        self.inner
+            .as_mut()
+            .expect("call init_writer() first")
+            .write_transport_bytes(bytes);
+    }
 }
 
 impl Write for StdoutWriter {
     /// Forwards the write to all available destinations.
     fn write_str(&mut self, msg: &str) -> core::fmt::Result {
         if let Some(ref mut inner) = self.inner {
-            inner.serial_writer.write_str(msg)?;
+            match inner.virtio_console {
+                Some(ref mut console) => console.write_str(msg)?,
+                None => inner.serial_writer.write_str(msg)?,
+            }
             if let Some(ref mut writer) = inner.debugcon_writer {
                 writer.write_str(msg)?;
             }
+            if let Some(ref mut writer) = inner.framebuffer_writer {
+                writer.write_str(msg)?;
+            }
             Ok(())
         } else {
             // note that Rust logger might not be initialized yet
@@ -121,6 +220,10 @@ impl Write for StdoutWriter {
 struct StdoutWriterInner {
     debugcon_writer: Option<DebugconWriter>,
     serial_writer: SerialWriter,
+    /// Attached after boot, once the PCI devices are known.
+    framebuffer_writer: Option<FramebufferWriter>,
+    /// Replaces the serial port if attached.
+    virtio_console: Option<VirtioConsole>,
 }
 
 impl StdoutWriterInner {
@@ -145,6 +248,16 @@ impl StdoutWriterInner {
         Self {
             debugcon_writer,
             serial_writer,
+            framebuffer_writer: None,
+            virtio_console: None,
+        }
+    }
+
+    /// Writes binary data to the serial port or, if attached, the virtio-console.
+    fn write_transport_bytes(&mut self, bytes: &[u8]) {
+        match self.virtio_console {
+            Some(ref mut console) => console.write_bytes(bytes),
+            None => self.serial_writer.write_bytes(bytes),
         }
     }
 }
@@ -0,0 +1,126 @@
+//! Reassembly of the messages that processes send in multiple [`OutputChunk`]s to the
+//! STDOUT and STDERR services. The services output a message only once it is complete,
+//! hence the output of other processes never splits it.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::stdout::OutputChunk;
+
+/// Upper bound for the buffered bytes of a single incomplete message. Prevents that a
+/// process exhausts the heap of the roottask.
+pub const MAX_PENDING_MSG_LEN: usize = 64 * 1024;
+
+/// Buffers the chunks of incomplete messages per process.
+#[derive(Debug)]
+pub struct MsgReassembler {
+    pending: BTreeMap<ProcessId, String>,
+}
+
+impl MsgReassembler {
+    pub const fn new() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a chunk of process `pid`. Returns the whole message after the last chunk.
+    /// Messages that consist of a single chunk don't get copied. If the buffered part
+    /// of a message exceeds [`MAX_PENDING_MSG_LEN`], it returns that part early.
+    pub fn push<'a>(&mut self, pid: ProcessId, chunk: &OutputChunk<'a>) -> Option<Cow<'a, str>> {
+        match self.pending.remove(&pid) {
+            None if chunk.last => Some(Cow::Borrowed(chunk.msg)),
+            pending => {
+                let mut msg = pending.unwrap_or_default();
+                msg.push_str(chunk.msg);
+                if chunk.last || msg.len() >= MAX_PENDING_MSG_LEN {
+                    Some(Cow::Owned(msg))
+                } else {
+                    self.pending.insert(pid, msg);
+                    None
+                }
+            }
+        }
+    }
+
+    /// Drops the incomplete message of an exited process.
+    pub fn discard(&mut self, pid: ProcessId) {
+        self.pending.remove(&pid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn chunks(msg: &str, chunk_len: usize) -> Vec<OutputChunk<'_>> {
+        let count = (msg.len() + chunk_len - 1) / chunk_len;
+        (0..count)
+            .map(|i| OutputChunk {
+                msg: &msg[i * chunk_len..((i + 1) * chunk_len).min(msg.len())],
+                last: i == count - 1,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_chunk_is_borrowed() {
+        let mut reassembler = MsgReassembler::new();
+        let msg = reassembler.push(
+            1,
+            &OutputChunk {
+                msg: "Hallo",
+                last: true,
+            },
+        );
+        assert!(matches!(msg, Some(Cow::Borrowed("Hallo"))));
+    }
+
+    #[test]
+    fn test_interleaved_multi_page_msgs() {
+        let msg_a = "a".repeat(3 * 4096 + 17);
+        let msg_b = "b".repeat(2 * 4096);
+        let chunks_a = chunks(&msg_a, 4000);
+        let chunks_b = chunks(&msg_b, 4000);
+        assert_eq!(chunks_a.len(), 4);
+        assert_eq!(chunks_b.len(), 3);
+
+        let mut reassembler = MsgReassembler::new();
+        let mut output = Vec::new();
+        for i in 0..chunks_a.len() {
+            output.extend(reassembler.push(1, &chunks_a[i]));
+            if let Some(chunk) = chunks_b.get(i) {
+                output.extend(reassembler.push(2, chunk));
+            }
+        }
+        assert_eq!(output, [msg_b.as_str(), msg_a.as_str()]);
+        assert!(reassembler.pending.is_empty());
+    }
+
+    #[test]
+    fn test_pending_limit_and_discard() {
+        let msg = "x".repeat(MAX_PENDING_MSG_LEN + 5000);
+        let chunks = chunks(&msg, 4000);
+        let mut reassembler = MsgReassembler::new();
+        let output = chunks
+            .iter()
+            .filter_map(|chunk| reassembler.push(1, chunk))
+            .collect::<Vec<_>>();
+        // the first part comes early, the rest after the last chunk
+        assert_eq!(output.len(), 2);
+        assert!(output[0].len() >= MAX_PENDING_MSG_LEN);
+        assert_eq!(output.concat(), msg);
+
+        reassembler.push(
+            1,
+            &OutputChunk {
+                msg: "incomplete",
+                last: false,
+            },
+        );
+        reassembler.discard(1);
+        assert!(reassembler.pending.is_empty());
+    }
+}
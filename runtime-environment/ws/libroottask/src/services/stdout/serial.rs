@@ -12,6 +12,12 @@ use libhrstd::libhedron::{
     HIP,
 };
 use uart_16550::SerialPort;
+use x86::io::inb;
+
+/// Offset of the line status register from the base port.
+const LINE_STATUS_REG: u16 = 5;
+/// Set in the line status register if a received byte is available.
+const LINE_STATUS_DATA_READY: u8 = 1 << 0;
 
 /// Logger that uses I/O port 0x3f8. See `serial_port.rs`.
 ///
@@ -55,6 +61,29 @@ impl SerialWriter {
         self.port.replace(port);
         Ok(())
     }
+
+    /// Checks if the serial port received a byte that wasn't read yet.
+    pub fn has_input(&self) -> bool {
+        assert!(self.port.is_some(), "call init() first");
+        unsafe { inb(self.port_base + LINE_STATUS_REG) & LINE_STATUS_DATA_READY != 0 }
+    }
+
+    /// Returns the next received byte, if there is one. Never blocks, unlike
+    /// [`SerialPort::receive`].
+    pub fn try_read_byte(&mut self) -> Option<u8> {
+        if self.has_input() {
+            unsafe { Some(inb(self.port_base)) }
+        } else {
+            None
+        }
+    }
+
+    /// Writes the bytes unmodified, unlike [`Write::write_str`], which translates the
+    /// backspace character.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        let port = self.port.as_mut().unwrap();
+        bytes.iter().for_each(|b| port.send_raw(*b));
+    }
 }
 
 impl Write for SerialWriter {
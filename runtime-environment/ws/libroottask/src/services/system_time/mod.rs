@@ -0,0 +1,79 @@
+//! System time service. Lets every process read the wall clock and the monotonic clock
+//! and privileged processes adjust the wall clock. See [`crate::time::set_realtime_ns`].
+
+use crate::process::{
+    is_privileged,
+    Process,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::time;
+use alloc::rc::Rc;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+};
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::rt::services::system_time::{
+    SystemTime,
+    SystemTimeServiceError,
+    SystemTimeServiceRequest,
+    SystemTimeServiceResponse,
+};
+use libhrstd::service_ids::ServiceId;
+
+/// Creates a new SYSTEM TIME service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::SystemTimeService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the SYSTEM TIME Portal.
+pub fn system_time_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<SystemTimeServiceRequest>().unwrap();
+    let response = handle_request(process, request);
+    utcb.store_data(&response).unwrap();
+    *do_reply = true;
+}
+
+fn handle_request(
+    caller: &Process,
+    request: SystemTimeServiceRequest,
+) -> SystemTimeServiceResponse {
+    match request {
+        SystemTimeServiceRequest::Get => Ok(system_time()),
+        SystemTimeServiceRequest::Set {
+            realtime_ns,
+            adjustment,
+        } => {
+            if !is_privileged(caller.pid()) {
+                log::debug!("pid={} isn't allowed to adjust the time", caller.pid());
+                return Err(SystemTimeServiceError::PermissionDenied);
+            }
+            time::set_realtime_ns(caller.pid(), realtime_ns, adjustment);
+            Ok(system_time())
+        }
+    }
+}
+
+fn system_time() -> SystemTime {
+    SystemTime {
+        realtime_ns: time::realtime_ns(),
+        monotonic_ns: time::monotonic_ns(),
+        tsc_freq_khz: time::tsc_freq_khz(),
+    }
+}
@@ -0,0 +1,356 @@
+//! Timer service. User processes can create one-shot and periodic timers. Each timer
+//! has a dedicated semaphore that the roottask delegates into the PD of the process.
+//! Every time the timer expires, the roottask performs an "up" operation on it.
+//!
+//! Additionally, the roottask uses the same infrastructure for the `alarm` of Linux
+//! processes, which raises SIGALRM instead. See [`set_alarm`]. A periodic timer also
+//! checks the CPU time of processes with a CPU limit, see [`watch_cpu_limit`].
+//!
+//! The expiration of timers is handled by the main global EC of the roottask inside
+//! [`timer_loop`] after the roottask is initialized. It sleeps until the next timer
+//! expires with the help of [`TscDeadlineTimer`].
+
+use crate::hw::timer::TscDeadlineTimer;
+use crate::process::{
+    check_cpu_limit,
+    process_cap_sels,
+    raise_signal,
+    stop_exited_processes,
+    Process,
+    SigNum,
+    SIGALRM,
+};
+use crate::pt_multiplex::roottask_generic_portal_callback;
+use crate::services::{
+    fs,
+    process,
+};
+use crate::time;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use libhrstd::cap_space::root::RootCapSpace;
+use libhrstd::kobjects::{
+    LocalEcObject,
+    PtCtx,
+    PtObject,
+    SmObject,
+};
+use libhrstd::libhedron::syscall::sys_sm_up;
+use libhrstd::libhedron::CapSel;
+use libhrstd::libhedron::Mtd;
+use libhrstd::libhedron::Utcb;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::timer::{
+    timer_sm_sel,
+    TimerId,
+    TimerServiceError,
+    TimerServiceRequest,
+    TimerServiceResponse,
+    MAX_TIMERS_PER_PROCESS,
+};
+use libhrstd::service_ids::ServiceId;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// All active timers of all processes. Shared between the service EC and the
+/// main EC of the roottask, hence it only contains plain data.
+static TIMERS: SimpleMutex<TimerQueue> = SimpleMutex::new(TimerQueue::new());
+
+/// Semaphores of the timers. They are created on first usage of a timer ID and
+/// reused afterwards until the process stops.
+static TIMER_SMS: SimpleMutex<BTreeMap<(ProcessId, TimerId), Rc<SmObject>>> =
+    SimpleMutex::new(BTreeMap::new());
+
+/// The timer that wakes up [`timer_loop`]. Uses the SM of the main EC of the roottask.
+static HW_TIMER: TscDeadlineTimer = TscDeadlineTimer::new(RootCapSpace::RootSmSleep.val());
+
+/// Creates a new TIMER service PT, which can be delegated to a new process.
+pub fn create_service_pt(base_cap_sel: CapSel, ec: &Rc<LocalEcObject>) -> Rc<PtObject> {
+    let service = ServiceId::TimerService;
+    // adds itself to the local EC for services
+    PtObject::create(
+        base_cap_sel + service.val(),
+        &ec,
+        Mtd::empty(),
+        roottask_generic_portal_callback,
+        PtCtx::Service(service),
+    )
+}
+
+/// Handles the functionality of the TIMER Portal.
+pub fn timer_service_handler(
+    _pt: &Rc<PtObject>,
+    process: &Process,
+    utcb: &mut Utcb,
+    do_reply: &mut bool,
+) {
+    let request = utcb.load_data::<TimerServiceRequest>().unwrap();
+    log::trace!("timer request from pid={}: {:?}", process.pid(), request);
+
+    let response: TimerServiceResponse = match request {
+        TimerServiceRequest::CreateOneShot { delay_ns } => create_timer(process, delay_ns, None),
+        TimerServiceRequest::CreatePeriodic { period_ns } => {
+            if period_ns == 0 {
+                Err(TimerServiceError::InvalidPeriod)
+            } else {
+                create_timer(process, period_ns, Some(period_ns))
+            }
+        }
+        TimerServiceRequest::Cancel(id) if id < MAX_TIMERS_PER_PROCESS => TIMERS
+            .lock()
+            .remove(process.pid(), id)
+            .map(|timer| timer.id),
+        TimerServiceRequest::Cancel(_) => Err(TimerServiceError::UnknownTimer),
+    };
+
+    // the next deadline might have changed
+    HW_TIMER.kick();
+
+    utcb.store_data(&response).unwrap();
+    *do_reply = true;
+}
+
+/// Creates a timer for the given process. Makes sure that the process has the
+/// semaphore of the timer in its capability space.
+fn create_timer(process: &Process, delay_ns: u64, period_ns: Option<u64>) -> TimerServiceResponse {
+    let tsc_deadline = time::tsc_now()
+        .checked_add(time::ns_to_ticks(delay_ns))
+        .ok_or(TimerServiceError::DelayTooLarge)?;
+    let mut timers = TIMERS.lock();
+    let id = timers
+        .next_free_id(process.pid())
+        .ok_or(TimerServiceError::TooManyTimers)?;
+
+    let sm_sel = TIMER_SMS
+        .lock()
+        .entry((process.pid(), id))
+        .or_insert_with(|| {
+            let root = process.parent().unwrap();
+            let sm = SmObject::create(
+                process_cap_sels(process.pid())
+                    .expect("running processes have capability selectors")
+                    .timer_sm(id),
+                &root.pd_obj(),
+            );
+            sm.delegate(&process.pd_obj(), timer_sm_sel(id));
+            sm
+        })
+        .sel();
+
+    timers.insert(Timer {
+        pid: process.pid(),
+        id,
+        event: TimerEvent::SmUp(sm_sel),
+        tsc_deadline,
+        tsc_period: period_ns.map(time::ns_to_ticks),
+    });
+    Ok(id)
+}
+
+/// Timer ID of the alarm of a process. It is outside the range of the timer service,
+/// hence user processes can't accidentally cancel it.
+const ALARM_TIMER_ID: TimerId = MAX_TIMERS_PER_PROCESS;
+
+/// Arms the alarm of a process, which raises SIGALRM after `delay_ns` nanoseconds. A
+/// previous alarm gets replaced. A delay of zero only cancels the previous alarm.
+/// Returns the remaining nanoseconds of the previous alarm, if there was one.
+pub fn set_alarm(pid: ProcessId, delay_ns: u64) -> Option<u64> {
+    let tsc_now = time::tsc_now();
+    let mut timers = TIMERS.lock();
+    let previous = timers
+        .remove(pid, ALARM_TIMER_ID)
+        .ok()
+        .map(|timer| time::ticks_to_ns(timer.tsc_deadline.saturating_sub(tsc_now)));
+    if delay_ns > 0 {
+        timers.insert(Timer {
+            pid,
+            id: ALARM_TIMER_ID,
+            event: TimerEvent::Signal(SIGALRM),
+            tsc_deadline: tsc_now.saturating_add(time::ns_to_ticks(delay_ns)),
+            tsc_period: None,
+        });
+    }
+    drop(timers);
+
+    // the next deadline might have changed
+    HW_TIMER.kick();
+    previous
+}
+
+/// Timer ID of the CPU limit check of a process. Like [`ALARM_TIMER_ID`], it is outside the
+/// range of the timer service.
+const CPU_LIMIT_TIMER_ID: TimerId = MAX_TIMERS_PER_PROCESS + 1;
+
+/// The CPU limit of processes has a granularity of seconds.
+const CPU_LIMIT_PERIOD_NS: u64 = 1_000_000_000;
+
+/// Starts or stops checking the CPU time of a process each second, see [`check_cpu_limit`].
+pub fn watch_cpu_limit(pid: ProcessId, enabled: bool) {
+    let mut timers = TIMERS.lock();
+    let _ = timers.remove(pid, CPU_LIMIT_TIMER_ID);
+    if enabled {
+        let period = time::ns_to_ticks(CPU_LIMIT_PERIOD_NS);
+        timers.insert(Timer {
+            pid,
+            id: CPU_LIMIT_TIMER_ID,
+            event: TimerEvent::CheckCpuLimit,
+            tsc_deadline: time::tsc_now() + period,
+            tsc_period: Some(period),
+        });
+    }
+    drop(timers);
+
+    // the next deadline might have changed
+    HW_TIMER.kick();
+}
+
+/// Removes all timers of a stopped process, including its alarm and CPU limit check. The
+/// semaphores of the timers are dropped, because their selectors get reused.
+pub fn cancel_timers(pid: ProcessId) {
+    TIMERS
+        .lock()
+        .0
+        .retain(|(timer_pid, _), _| *timer_pid != pid);
+    TIMER_SMS.lock().retain(|(sm_pid, _), _| *sm_pid != pid);
+}
+
+/// Wakes up the main global EC of the roottask inside [`timer_loop`], for example to start
+/// the processes that the process service queued or to stop exited processes.
+pub fn wake_main_ec() {
+    HW_TIMER.kick();
+}
+
+/// Handles the expiration of all timers. Never returns. Must be called by the main
+/// global EC of the roottask, after everything is initialized.
+///
+/// Additionally starts the processes that the process service queued and stops the
+/// processes that exited, because the service EC can't do this itself. See
+/// [`process::start_queued_processes`] and [`stop_exited_processes`]. It also processes
+/// the file system rings, whose kick semaphore is the semaphore of this EC. See
+/// [`fs::process_fs_rings`].
+pub fn timer_loop() -> ! {
+    loop {
+        let next_deadline = TIMERS.lock().next_deadline();
+        HW_TIMER.wait(next_deadline);
+        TIMERS.lock().fire_expired(time::tsc_now());
+        fs::process_fs_rings();
+        process::start_queued_processes();
+        stop_exited_processes();
+    }
+}
+
+/// An active timer.
+#[derive(Debug)]
+struct Timer {
+    pid: ProcessId,
+    id: TimerId,
+    event: TimerEvent,
+    tsc_deadline: u64,
+    tsc_period: Option<u64>,
+}
+
+/// What happens when a [`Timer`] expires.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum TimerEvent {
+    /// "Up" operation on the SM in the capability space of the roottask.
+    SmUp(CapSel),
+    /// Raises the signal for the process of the timer.
+    Signal(SigNum),
+    /// Checks the CPU time of the process. The timer stops if there is nothing to check
+    /// anymore.
+    CheckCpuLimit,
+}
+
+/// All active timers ordered by process and timer ID.
+#[derive(Debug)]
+struct TimerQueue(BTreeMap<(ProcessId, TimerId), Timer>);
+
+impl TimerQueue {
+    const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    fn insert(&mut self, timer: Timer) {
+        self.0.insert((timer.pid, timer.id), timer);
+    }
+
+    fn remove(&mut self, pid: ProcessId, id: TimerId) -> Result<Timer, TimerServiceError> {
+        self.0
+            .remove(&(pid, id))
+            .ok_or(TimerServiceError::UnknownTimer)
+    }
+
+    /// Returns the lowest timer ID that is not in use by the given process.
+    fn next_free_id(&self, pid: ProcessId) -> Option<TimerId> {
+        (0..MAX_TIMERS_PER_PROCESS).find(|id| !self.0.contains_key(&(pid, *id)))
+    }
+
+    /// Returns the TSC value at which the next timer expires.
+    fn next_deadline(&self) -> Option<u64> {
+        self.0.values().map(|timer| timer.tsc_deadline).min()
+    }
+
+    /// Triggers the events of all expired timers. Periodic timers get re-armed,
+    /// one-shot timers get removed. If a periodic timer missed expirations, they are
+    /// skipped. A CPU limit check can stop its timer.
+    fn fire_expired(&mut self, tsc_now: u64) {
+        self.0.retain(|_, timer| {
+            if timer.tsc_deadline > tsc_now {
+                return true;
+            }
+            let keep = match timer.event {
+                TimerEvent::SmUp(sm_sel) => {
+                    sys_sm_up(sm_sel).unwrap();
+                    true
+                }
+                TimerEvent::Signal(sig) => {
+                    raise_signal(timer.pid, sig);
+                    true
+                }
+                TimerEvent::CheckCpuLimit => check_cpu_limit(timer.pid),
+            };
+            match timer.tsc_period {
+                Some(period) if keep => {
+                    timer.tsc_deadline = timer.tsc_deadline.saturating_add(period);
+                    if timer.tsc_deadline <= tsc_now {
+                        timer.tsc_deadline = tsc_now.saturating_add(period);
+                    }
+                    true
+                }
+                _ => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_queue() {
+        let mut queue = TimerQueue::new();
+        assert_eq!(queue.next_free_id(1), Some(0));
+        assert_eq!(queue.next_deadline(), None);
+
+        for (id, deadline) in [(0, 300), (1, 100)] {
+            queue.insert(Timer {
+                pid: 1,
+                id,
+                event: TimerEvent::SmUp(0),
+                tsc_deadline: deadline,
+                tsc_period: None,
+            });
+        }
+        assert_eq!(queue.next_free_id(1), Some(2));
+        assert_eq!(queue.next_free_id(2), Some(0));
+        assert_eq!(queue.next_deadline(), Some(100));
+
+        assert_eq!(queue.remove(1, 1).map(|timer| timer.id), Ok(1));
+        assert_eq!(
+            queue.remove(1, 1).map(|timer| timer.id),
+            Err(TimerServiceError::UnknownTimer)
+        );
+        assert_eq!(queue.next_free_id(1), Some(1));
+        assert_eq!(queue.next_deadline(), Some(300));
+    }
+}
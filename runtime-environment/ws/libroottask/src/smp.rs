@@ -0,0 +1,99 @@
+//! Multi-core support. Hedron brings up all CPUs itself and reports the online CPUs in the
+//! HIP. The roottask creates its exception and service local ECs on every online CPU (see
+//! [`crate::roottask_exception::init`] and [`crate::services::init_services`]) and
+//! distributes new processes round-robin across the CPUs, unless the caller requests a
+//! specific CPU or the roottask runs in deterministic mode.
+//!
+//! Hedron binds each EC permanently to its CPU and portals can only be called from the
+//! CPU of their local EC. Hence, a process stays on its CPU for its whole lifetime and all
+//! portals of a process are bound to the local ECs of its CPU. The main global EC of the
+//! roottask, and therefore the timer loop, stays on CPU 0.
+
+use core::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+use libhrstd::libhedron::consts::NUM_CPUS;
+use libhrstd::libhedron::HIP;
+
+/// Bitmask of the online CPUs. Bit `n` corresponds to CPU `n`. CPU 0 (the boot CPU) is
+/// online all the time.
+static ONLINE_CPUS: AtomicU64 = AtomicU64::new(1);
+
+/// Counter for the round-robin selection of CPUs for new processes.
+static NEXT_CPU: AtomicU64 = AtomicU64::new(0);
+
+/// Reads the online CPUs from the HIP. Must be called before the roottask creates its
+/// local ECs.
+pub fn init(hip: &HIP) {
+    // the bitmask needs one bit per CPU
+    const _: () = assert!(NUM_CPUS <= u64::BITS as usize);
+    let online_cpus = hip
+        .online_cpu_iterator()
+        .fold(0, |mask, (cpu, _)| mask | 1 << cpu);
+    assert_ne!(online_cpus & 1, 0, "CPU 0 must be online");
+    ONLINE_CPUS.store(online_cpus, Ordering::SeqCst);
+    log::info!(
+        "{} CPUs online: {:?}",
+        online_cpus.count_ones(),
+        online_cpus_iter().collect::<alloc::vec::Vec<_>>()
+    );
+}
+
+/// Returns the bitmask of the online CPUs. Bit `n` corresponds to CPU `n`.
+pub fn online_cpus() -> u64 {
+    ONLINE_CPUS.load(Ordering::SeqCst)
+}
+
+/// Returns the number of online CPUs.
+pub fn online_cpu_count() -> u64 {
+    online_cpus().count_ones() as u64
+}
+
+/// Returns true if `cpu` is online.
+pub fn is_online(cpu: u64) -> bool {
+    cpu < NUM_CPUS as u64 && online_cpus() & (1 << cpu) != 0
+}
+
+/// Iterates over the numbers of all online CPUs in ascending order.
+pub fn online_cpus_iter() -> impl Iterator<Item = u64> {
+    let mask = online_cpus();
+    (0..NUM_CPUS as u64).filter(move |cpu| mask & (1 << cpu) != 0)
+}
+
+/// Returns the CPU for the next process. Cycles through all online CPUs, except in the
+/// deterministic mode, see [`crate::deterministic`].
+pub fn next_cpu() -> u64 {
+    if crate::deterministic::is_enabled() {
+        return 0;
+    }
+    let n = NEXT_CPU.fetch_add(1, Ordering::SeqCst);
+    nth_cpu(online_cpus(), n)
+}
+
+/// Returns the CPU at position `n` (modulo the count) in the set of CPUs of `mask`.
+fn nth_cpu(mask: u64, n: u64) -> u64 {
+    debug_assert_ne!(mask, 0);
+    let n = n % mask.count_ones() as u64;
+    (0..NUM_CPUS as u64)
+        .filter(|cpu| mask & (1 << cpu) != 0)
+        .nth(n as usize)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nth_cpu() {
+        assert_eq!(nth_cpu(0b1, 0), 0);
+        assert_eq!(nth_cpu(0b1, 5), 0);
+        let mask = 0b1011;
+        let cpus = (0..6)
+            .map(|n| nth_cpu(mask, n))
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(cpus, [0, 1, 3, 0, 1, 3]);
+        assert_eq!(nth_cpu(1 << 63 | 1, 1), 63);
+    }
+}
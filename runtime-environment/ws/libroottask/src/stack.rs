@@ -2,6 +2,9 @@
 //! because it reduces distribution of responsibility/functionality across Rust code,
 //! assembler code and the linker script.
 
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::alloc::Layout;
 use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::syscall::{
     sys_pd_ctrl_delegate,
@@ -13,6 +16,7 @@ use libhrstd::libhedron::{
     MemCapPermissions,
 };
 use libhrstd::mem::PageAlignedByteBuf;
+use libhrstd::sync::mutex::SimpleMutex;
 
 /// SSE feature requires 128 bit/16 byte stack alignment on x86_64.
 /// In the spec I found instructions, such as movaps, that also want
@@ -35,6 +39,10 @@ const ALIGNMENT_LOAD_OFFSET: usize = 8;
 /// Helper struct for [`StaticStack`].
 type Page = PageAlignedByteBuf<PAGE_SIZE>;
 
+/// The guard pages of all stacks with an active guard page by their address, with the name
+/// of the thread that runs on the stack. See [`guard_page_owner`].
+static GUARD_PAGES: SimpleMutex<BTreeMap<u64, String>> = SimpleMutex::new(BTreeMap::new());
+
 /// A static stack object (assigned to a global static variable) helps us
 /// to define the initial stack for the roottask from Rust. The symbol to
 /// the stack begin itself can be exported and referenced by the assembly code.
@@ -69,6 +77,14 @@ impl<const PAGE_NUM: usize> StaticStack<PAGE_NUM> {
         }
     }
 
+    /// Allocates a zeroed stack on the heap that is never freed. For stacks whose number is
+    /// only known at runtime, such as the stacks of the local ECs of each CPU.
+    pub fn new_leaked() -> &'static Self {
+        let ptr = unsafe { alloc::alloc::alloc_zeroed(Layout::new::<Self>()) };
+        assert!(!ptr.is_null(), "out of memory");
+        unsafe { &*ptr.cast::<Self>() }
+    }
+
     /// Returns the pointer (inclusive!) to the top of the stack. The pointer is PAGE-aligned.
     /// From there, the stack can grow downwards.
     /// We waste almost a full page here, because we have the following problem:
@@ -105,10 +121,11 @@ impl<const PAGE_NUM: usize> StaticStack<PAGE_NUM> {
     }
 
     /// Marks the guard page as unmapped by performing a syscall
-    /// and revoking the rights of this page.
+    /// and revoking the rights of this page. Remembers `thread`, the name of the thread
+    /// that runs on the stack, for the report of a stack overflow, see [`guard_page_owner`].
     ///
     /// Must provide the capability selector for this protection domain.
-    pub unsafe fn activate_guard_page(&self, pd_sel: CapSel) {
+    pub unsafe fn activate_guard_page(&self, pd_sel: CapSel, thread: String) {
         // CRD for exactly one single page
         let crd = CrdMem::new(
             self.get_guard_page().page_num() as u64,
@@ -124,6 +141,9 @@ impl<const PAGE_NUM: usize> StaticStack<PAGE_NUM> {
             DelegateFlags::new(true, false, false, true, 0),
         )
         .unwrap();
+        GUARD_PAGES
+            .lock()
+            .insert(self.get_guard_page().self_ptr() as u64, thread);
     }
 
     /// TEST/Debug method.
@@ -145,6 +165,22 @@ impl<const PAGE_NUM: usize> StaticStack<PAGE_NUM> {
     }
 }
 
+/// Returns the name of the thread whose stack has its active guard page at `addr`, i.e. the
+/// thread whose stack overflowed if `addr` caused a page fault.
+pub fn guard_page_owner(addr: u64) -> Option<String> {
+    let page_addr = addr & !(PAGE_SIZE as u64 - 1);
+    GUARD_PAGES.lock().get(&page_addr).cloned()
+}
+
+/// Returns the stack top (see [`StaticStack::get_stack_top_ptr`]) of the stack that
+/// `stack_ptr` points into. Only valid if `stack_ptr` lies in the topmost page of the
+/// stack, e.g. right after the entry into a portal callback. Helps callbacks that must
+/// reply without looking up their local EC.
+pub const fn stack_top_of_top_page(stack_ptr: u64) -> u64 {
+    let page_end = (stack_ptr & !(PAGE_SIZE as u64 - 1)) + PAGE_SIZE as u64;
+    page_end - STACK_ALIGNMENT as u64 + ALIGNMENT_LOAD_OFFSET as u64
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,4 +202,16 @@ mod tests {
         // test compiles
         let _trusted_stack_ptr = StaticGlobalPtr::new(ptr);
     }
+
+    #[test]
+    fn test_stack_top_of_top_page() {
+        let stack = StaticStack::<2>::new_leaked();
+        let top = stack.get_stack_top_ptr() as u64;
+        assert_eq!(stack_top_of_top_page(top), top);
+        assert_eq!(stack_top_of_top_page(top - 1024), top);
+        assert_eq!(
+            stack.get_guard_page().self_ptr() as u64 % PAGE_SIZE as u64,
+            0
+        );
+    }
 }
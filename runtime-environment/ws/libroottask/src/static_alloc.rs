@@ -0,0 +1,403 @@
+//! Module for [`GlobalBuddyAllocator`], the allocator behind the static heap of the
+//! roottask.
+//!
+//! The chunk allocator that the roottask used before scans a bitmap for a free range of
+//! chunks in each allocation, which gets slow and fragments badly once the heap is full of
+//! small and long-living allocations, e.g. of the in-memory file system. A buddy allocator
+//! keeps a free list per block size instead. Allocations and deallocations cost at most one
+//! split or merge per block size, no matter how full the heap is.
+
+use core::alloc::{
+    GlobalAlloc,
+    Layout,
+};
+use core::fmt::{
+    Debug,
+    Formatter,
+};
+use core::ptr::{
+    null_mut,
+    NonNull,
+};
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Size of the smallest block. Smaller allocations occupy a whole block.
+pub const BUDDY_MIN_BLOCK_SIZE: usize = 1 << MIN_ORDER;
+
+/// Power of two of [`BUDDY_MIN_BLOCK_SIZE`].
+const MIN_ORDER: usize = 6;
+
+/// Number of different block sizes. The largest block has `2^(MIN_ORDER + ORDER_COUNT - 1)`
+/// bytes.
+const ORDER_COUNT: usize = 32;
+
+/// Returns the size in bytes of the bitmap that [`BuddyAllocator`] needs for a heap of
+/// `heap_size` bytes. The bitmap has one bit per block of each size.
+pub const fn buddy_bitmap_size(heap_size: usize) -> usize {
+    let mut bits = 0;
+    let mut level = 0;
+    while level < ORDER_COUNT {
+        bits += heap_size >> (MIN_ORDER + level);
+        level += 1;
+    }
+    (bits + 7) / 8
+}
+
+/// Node of the doubly linked free list of a block size. Lives inside the free block itself.
+#[repr(C)]
+struct FreeBlock {
+    prev: *mut FreeBlock,
+    next: *mut FreeBlock,
+}
+
+/// Buddy allocator on a static backing storage. The heap consists of blocks whose size is
+/// a power of two of at least [`BUDDY_MIN_BLOCK_SIZE`] and whose offset in the heap is a
+/// multiple of their size. An allocation takes the smallest free block that fits and
+/// splits it in halves, the buddies, until one half is as small as possible. A
+/// deallocation merges the block with its buddy as long as the buddy is free.
+///
+/// The bitmap tells which blocks are free, so that a deallocation finds out in O(1) whether
+/// it can merge. The heap doesn't need a size that is a power of two.
+///
+/// Blocks are aligned to their size relative to the begin of the heap. Hence, allocations
+/// with an alignment that the begin of the heap doesn't have fail. Page-aligned heaps are
+/// recommended.
+pub struct BuddyAllocator<'a> {
+    heap: &'a mut [u8],
+    bitmap: &'a mut [u8],
+    /// First free block of each size. Index 0 holds blocks of [`BUDDY_MIN_BLOCK_SIZE`].
+    free_lists: [*mut FreeBlock; ORDER_COUNT],
+    /// Index of the first bit of each size in the bitmap.
+    bitmap_offsets: [usize; ORDER_COUNT],
+    /// Bytes in blocks that are currently allocated.
+    used_bytes: usize,
+    /// The free lists get built lazily, because a const constructor can't write into the heap.
+    initialized: bool,
+}
+
+impl<'a> BuddyAllocator<'a> {
+    /// Constructor. The bitmap must have at least [`buddy_bitmap_size`] bytes.
+    pub const fn new(heap: &'a mut [u8], bitmap: &'a mut [u8]) -> Self {
+        Self {
+            heap,
+            bitmap,
+            free_lists: [null_mut(); ORDER_COUNT],
+            bitmap_offsets: [0; ORDER_COUNT],
+            used_bytes: 0,
+            initialized: false,
+        }
+    }
+
+    /// Splits the whole heap into the largest possible free blocks.
+    fn init(&mut self) {
+        assert!(
+            self.bitmap.len() >= buddy_bitmap_size(self.heap.len()),
+            "bitmap too small"
+        );
+        self.initialized = true;
+        self.bitmap.fill(0);
+        let mut bit = 0;
+        for (level, offset) in self.bitmap_offsets.iter_mut().enumerate() {
+            *offset = bit;
+            bit += self.heap.len() >> (MIN_ORDER + level);
+        }
+
+        let mut offset = 0;
+        loop {
+            let level = (0..ORDER_COUNT).rev().find(|level| {
+                let size = Self::block_size(*level);
+                offset % size == 0 && offset + size <= self.heap.len()
+            });
+            match level {
+                Some(level) => {
+                    self.push_free(level, offset);
+                    offset += Self::block_size(level);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Allocates a block for `layout`. Returns `None` if no block is free, the layout
+    /// exceeds the largest block, or the heap lacks the alignment.
+    pub fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        if !self.initialized {
+            self.init();
+        }
+        if self.heap.as_ptr() as usize % layout.align() != 0 {
+            return None;
+        }
+        let level = Self::level_for(layout)?;
+        let free_level = (level..ORDER_COUNT).find(|level| !self.free_lists[*level].is_null())?;
+        let offset = self.pop_free(free_level);
+        // the upper halves of the split blocks become free blocks of the smaller sizes
+        for split_level in (level..free_level).rev() {
+            self.push_free(split_level, offset + Self::block_size(split_level));
+        }
+        self.used_bytes += Self::block_size(level);
+        NonNull::new(unsafe { self.heap.as_mut_ptr().add(offset) })
+    }
+
+    /// Frees a block that [`Self::allocate`] returned for the same `layout`.
+    pub fn deallocate(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        let mut level = Self::level_for(layout).expect("layout was never allocated");
+        let mut offset = ptr.as_ptr() as usize - self.heap.as_ptr() as usize;
+        assert!(offset < self.heap.len(), "pointer not in heap");
+        self.used_bytes -= Self::block_size(level);
+        while level < ORDER_COUNT - 1 {
+            let buddy = offset ^ Self::block_size(level);
+            if !self.is_free(level, buddy) {
+                break;
+            }
+            self.remove_free(level, buddy);
+            offset = offset.min(buddy);
+            level += 1;
+        }
+        self.push_free(level, offset);
+    }
+
+    /// Returns the share of the heap that allocated blocks occupy, between 0 and 1.
+    pub fn usage(&self) -> f32 {
+        self.used_bytes as f32 / self.heap.len() as f32
+    }
+
+    /// Returns the size of the block that an allocation of `layout` occupies, if any.
+    pub fn occupied_size(layout: Layout) -> Option<usize> {
+        Self::level_for(layout).map(Self::block_size)
+    }
+
+    /// Returns the index of the smallest block size that fits `layout`.
+    fn level_for(layout: Layout) -> Option<usize> {
+        let size = layout
+            .size()
+            .max(layout.align())
+            .max(BUDDY_MIN_BLOCK_SIZE)
+            .checked_next_power_of_two()?;
+        let level = size.trailing_zeros() as usize - MIN_ORDER;
+        if level < ORDER_COUNT {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    const fn block_size(level: usize) -> usize {
+        1 << (MIN_ORDER + level)
+    }
+
+    /// Returns the index of the bit of the block at `offset` or `None` if the block isn't
+    /// part of the heap.
+    fn bit(&self, level: usize, offset: usize) -> Option<usize> {
+        let index = offset >> (MIN_ORDER + level);
+        if index < self.heap.len() >> (MIN_ORDER + level) {
+            Some(self.bitmap_offsets[level] + index)
+        } else {
+            None
+        }
+    }
+
+    fn is_free(&self, level: usize, offset: usize) -> bool {
+        self.bit(level, offset)
+            .map_or(false, |bit| self.bitmap[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    fn set_free(&mut self, level: usize, offset: usize, free: bool) {
+        let bit = self.bit(level, offset).unwrap();
+        if free {
+            self.bitmap[bit / 8] |= 1 << (bit % 8);
+        } else {
+            self.bitmap[bit / 8] &= !(1 << (bit % 8));
+        }
+    }
+
+    fn block_ptr(&mut self, offset: usize) -> *mut FreeBlock {
+        unsafe { self.heap.as_mut_ptr().add(offset).cast() }
+    }
+
+    fn push_free(&mut self, level: usize, offset: usize) {
+        let block = self.block_ptr(offset);
+        let head = self.free_lists[level];
+        unsafe {
+            block.write(FreeBlock {
+                prev: null_mut(),
+                next: head,
+            });
+            if !head.is_null() {
+                (*head).prev = block;
+            }
+        }
+        self.free_lists[level] = block;
+        self.set_free(level, offset, true);
+    }
+
+    fn remove_free(&mut self, level: usize, offset: usize) {
+        let block = self.block_ptr(offset);
+        unsafe {
+            let FreeBlock { prev, next } = block.read();
+            if prev.is_null() {
+                self.free_lists[level] = next;
+            } else {
+                (*prev).next = next;
+            }
+            if !next.is_null() {
+                (*next).prev = prev;
+            }
+        }
+        self.set_free(level, offset, false);
+    }
+
+    /// Takes the first block of a non-empty free list and returns its offset.
+    fn pop_free(&mut self, level: usize) -> usize {
+        let offset = self.free_lists[level] as usize - self.heap.as_ptr() as usize;
+        self.remove_free(level, offset);
+        offset
+    }
+}
+
+impl Debug for BuddyAllocator<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BuddyAllocator")
+            .field("heap", &self.heap.as_ptr())
+            .field("heap_len", &self.heap.len())
+            .field("used_bytes", &self.used_bytes)
+            .finish()
+    }
+}
+
+/// Synchronized wrapper around [`BuddyAllocator`] that implements [`GlobalAlloc`], like
+/// `simple_chunk_allocator::GlobalChunkAllocator` does for the chunk allocator.
+#[derive(Debug)]
+pub struct GlobalBuddyAllocator<'a>(SimpleMutex<BuddyAllocator<'a>>);
+
+impl<'a> GlobalBuddyAllocator<'a> {
+    /// Constructor. See [`BuddyAllocator::new`].
+    pub const fn new(heap: &'a mut [u8], bitmap: &'a mut [u8]) -> Self {
+        Self(SimpleMutex::new(BuddyAllocator::new(heap, bitmap)))
+    }
+
+    /// Wrapper around [`BuddyAllocator::usage`].
+    pub fn usage(&self) -> f32 {
+        self.0.lock().usage()
+    }
+}
+
+unsafe impl GlobalAlloc for GlobalBuddyAllocator<'_> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0
+            .lock()
+            .allocate(layout)
+            .map_or(null_mut(), NonNull::as_ptr)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().deallocate(NonNull::new(ptr).unwrap(), layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Runs `fnc` with a page-aligned heap of `size` bytes and a matching bitmap.
+    fn with_heap(size: usize, fnc: impl FnOnce(&mut BuddyAllocator)) {
+        let layout = Layout::from_size_align(size, 4096).unwrap();
+        let mut bitmap = vec![0; buddy_bitmap_size(size)];
+        unsafe {
+            let heap = alloc::alloc::alloc_zeroed(layout);
+            let mut alloc =
+                BuddyAllocator::new(core::slice::from_raw_parts_mut(heap, size), &mut bitmap);
+            fnc(&mut alloc);
+            alloc::alloc::dealloc(heap, layout);
+        }
+    }
+
+    fn layout(size: usize) -> Layout {
+        Layout::from_size_align(size, 1).unwrap()
+    }
+
+    #[test]
+    fn test_buddy_alloc_split_and_merge() {
+        with_heap(4096, |alloc| {
+            let a = alloc.allocate(layout(1)).unwrap();
+            let b = alloc.allocate(layout(64)).unwrap();
+            let c = alloc.allocate(layout(65)).unwrap();
+            let heap = a.as_ptr() as usize;
+            assert_eq!(b.as_ptr() as usize - heap, 64);
+            assert_eq!(c.as_ptr() as usize - heap, 128, "next 128 byte block");
+            assert_eq!(alloc.usage(), 256.0 / 4096.0);
+            assert_eq!(alloc.allocate(layout(4096)), None);
+
+            alloc.deallocate(b, layout(64));
+            alloc.deallocate(a, layout(1));
+            alloc.deallocate(c, layout(65));
+            assert_eq!(alloc.usage(), 0.0);
+            // all buddies merged again
+            assert_eq!(
+                alloc.allocate(layout(4096)).unwrap().as_ptr() as usize,
+                heap
+            );
+        });
+    }
+
+    #[test]
+    fn test_buddy_alloc_odd_heap_size() {
+        with_heap(3 * 1024, |alloc| {
+            let a = alloc.allocate(layout(2048)).unwrap();
+            let b = alloc.allocate(layout(1024)).unwrap();
+            assert_eq!(b.as_ptr() as usize - a.as_ptr() as usize, 2048);
+            assert_eq!(alloc.allocate(layout(1)), None);
+            alloc.deallocate(a, layout(2048));
+            alloc.deallocate(b, layout(1024));
+            // the blocks have no buddies, hence no block of 4096 bytes
+            assert_eq!(alloc.allocate(layout(3072)), None);
+            assert!(alloc.allocate(layout(2048)).is_some());
+        });
+    }
+
+    #[test]
+    fn test_buddy_alloc_alignment() {
+        with_heap(8192, |alloc| {
+            let _small = alloc.allocate(layout(8)).unwrap();
+            let aligned = alloc
+                .allocate(Layout::from_size_align(8, 1024).unwrap())
+                .unwrap();
+            assert_eq!(aligned.as_ptr() as usize % 1024, 0);
+            assert_eq!(
+                BuddyAllocator::occupied_size(Layout::from_size_align(8, 1024).unwrap()),
+                Some(1024)
+            );
+        });
+    }
+
+    #[test]
+    fn test_buddy_alloc_no_overlap() {
+        with_heap(64 * 1024, |alloc| {
+            let mut blocks = Vec::new();
+            let mut rng = 0x2803_u64;
+            for round in 0..2000_usize {
+                rng = rng.wrapping_mul(6364136223846793005).wrapping_add(1);
+                let size = (rng >> 33) as usize % 700 + 1;
+                if rng % 3 == 0 && !blocks.is_empty() {
+                    let (ptr, size, tag): (NonNull<u8>, usize, u8) =
+                        blocks.swap_remove(rng as usize % blocks.len());
+                    let data = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), size) };
+                    assert!(
+                        data.iter().all(|byte| *byte == tag),
+                        "block was overwritten"
+                    );
+                    alloc.deallocate(ptr, layout(size));
+                } else if let Some(ptr) = alloc.allocate(layout(size)) {
+                    let tag = round as u8;
+                    unsafe { ptr.as_ptr().write_bytes(tag, size) };
+                    blocks.push((ptr, size, tag));
+                }
+            }
+            for (ptr, size, _) in blocks {
+                alloc.deallocate(ptr, layout(size));
+            }
+            assert_eq!(alloc.usage(), 0.0);
+            assert!(alloc.allocate(layout(64 * 1024)).is_some());
+        });
+    }
+}
@@ -0,0 +1,234 @@
+//! Time related helpers for the roottask. Hedron reports the frequency of the
+//! time stamp counter (TSC) in the [`HIP`]. This module uses it to convert
+//! between TSC ticks and nanoseconds. If the HIP reports no frequency, the roottask
+//! measures it against the [`Pit`].
+//!
+//! Additionally, this module maintains the wall clock (UTC). It starts with the time of
+//! the [`CmosRtc`] and advances with the TSC. Privileged processes can adjust it, see
+//! [`set_realtime_ns`].
+
+use crate::hw::pit::Pit;
+use crate::hw::rtc::CmosRtc;
+use core::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+use libhrstd::libhedron::{
+    CapSel,
+    HIP,
+};
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::system_time::TimeAdjustment;
+use libhrstd::sync::mutex::SimpleMutex;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// TSC frequency in kHz, taken from the [`HIP`] or measured during [`init`].
+static TSC_FREQ_KHZ: AtomicU64 = AtomicU64::new(0);
+
+/// The wall clock. See [`realtime_ns`].
+static WALL_CLOCK: SimpleMutex<WallClock> = SimpleMutex::new(WallClock::new());
+
+/// The RTC, if the roottask could take it over during [`init_wall_clock`]. Gets the new
+/// time if the wall clock is adjusted, so that the time survives reboots.
+static RTC: SimpleMutex<Option<CmosRtc>> = SimpleMutex::new(None);
+
+/// Stores the TSC frequency from the HIP or, if it reports none, calibrates the TSC
+/// against the PIT. Must be called once during roottask startup, before any other
+/// function of this module is used.
+pub fn init(hip: &HIP, root_pd_sel: CapSel) {
+    let freq_khz = match hip.freq_tsc() as u64 {
+        0 => {
+            let pit = Pit::new(root_pd_sel)
+                .expect("HIP reports no TSC frequency and the PIT is unavailable");
+            let freq_khz = pit.measure_tsc_freq_khz();
+            log::info!(
+                "TSC frequency: {} kHz (calibrated against the PIT)",
+                freq_khz
+            );
+            freq_khz
+        }
+        freq_khz => {
+            log::debug!("TSC frequency: {} kHz (HIP)", freq_khz);
+            freq_khz
+        }
+    };
+    assert_ne!(freq_khz, 0, "TSC frequency of zero");
+    TSC_FREQ_KHZ.store(freq_khz, Ordering::SeqCst);
+}
+
+/// Returns true if [`init`] already stored the TSC frequency. Afterwards, TSC ticks can be
+/// converted to nanoseconds.
+pub fn is_calibrated() -> bool {
+    TSC_FREQ_KHZ.load(Ordering::SeqCst) != 0
+}
+
+/// Returns the TSC frequency in kHz.
+pub fn tsc_freq_khz() -> u64 {
+    let freq_khz = TSC_FREQ_KHZ.load(Ordering::SeqCst);
+    assert_ne!(freq_khz, 0, "call init() first");
+    freq_khz
+}
+
+/// Returns the current value of the TSC.
+pub fn tsc_now() -> u64 {
+    unsafe { x86::time::rdtscp() }
+}
+
+/// Converts nanoseconds to TSC ticks. Saturates at `u64::MAX`.
+pub fn ns_to_ticks(ns: u64) -> u64 {
+    u64::try_from(ns as u128 * tsc_freq_khz() as u128 / 1_000_000).unwrap_or(u64::MAX)
+}
+
+/// Converts TSC ticks to nanoseconds.
+pub fn ticks_to_ns(ticks: u64) -> u64 {
+    (ticks as u128 * 1_000_000 / tsc_freq_khz() as u128) as u64
+}
+
+/// Initializes the wall clock with the time of the RTC. Must be called after [`init`].
+pub fn init_wall_clock(root_pd_sel: CapSel) {
+    match CmosRtc::new(root_pd_sel) {
+        Ok(rtc) => {
+            let unix_secs = rtc.read();
+            WALL_CLOCK.lock().step(tsc_now(), unix_secs * NS_PER_SEC);
+            RTC.lock().replace(rtc);
+            log::info!("wall clock: {}s since the Unix epoch (RTC)", unix_secs);
+        }
+        Err(_) => log::warn!("can't access the RTC; the wall clock starts at the Unix epoch"),
+    }
+}
+
+/// Returns the nanoseconds since the TSC started, i.e. roughly since boot. Never jumps.
+pub fn monotonic_ns() -> u64 {
+    ticks_to_ns(tsc_now())
+}
+
+/// Returns the wall-clock time in nanoseconds since the Unix epoch (UTC).
+pub fn realtime_ns() -> u64 {
+    WALL_CLOCK.lock().realtime_ns(tsc_now())
+}
+
+/// Adjusts the wall clock on behalf of process `caller` and writes the new time back to
+/// the RTC. Emits an audit log entry. Returns the previous wall-clock time.
+pub fn set_realtime_ns(caller: ProcessId, realtime_ns: u64, adjustment: TimeAdjustment) -> u64 {
+    let now = tsc_now();
+    let mut wall_clock = WALL_CLOCK.lock();
+    let previous = wall_clock.realtime_ns(now);
+    match adjustment {
+        TimeAdjustment::Step => wall_clock.step(now, realtime_ns),
+        TimeAdjustment::Smear { duration_ns } => {
+            wall_clock.smear(now, realtime_ns, ns_to_ticks(duration_ns))
+        }
+    }
+    drop(wall_clock);
+
+    // the RTC gets the new time immediately, also if the wall clock smears
+    if let Some(rtc) = RTC.lock().as_ref() {
+        rtc.write(realtime_ns / NS_PER_SEC);
+    }
+    log::info!(
+        "audit: pid={} set the wall clock from {}ns to {}ns ({:?})",
+        caller,
+        previous,
+        realtime_ns,
+        adjustment
+    );
+    previous
+}
+
+/// The wall clock. It had the value `base_ns` when the TSC had the value `base_tsc`. An
+/// optional [`Smear`] gets applied gradually from then on.
+#[derive(Debug)]
+struct WallClock {
+    base_tsc: u64,
+    base_ns: u64,
+    smear: Option<Smear>,
+}
+
+/// Offset that gets applied linearly during `duration_ticks`. A negative offset slows the
+/// clock down instead of letting it jump back. The clock stays monotonic as long as the
+/// offset is smaller than the duration.
+#[derive(Debug, Copy, Clone)]
+struct Smear {
+    offset_ns: i64,
+    duration_ticks: u64,
+}
+
+impl WallClock {
+    const fn new() -> Self {
+        Self {
+            base_tsc: 0,
+            base_ns: 0,
+            smear: None,
+        }
+    }
+
+    /// Returns the time in nanoseconds since the Unix epoch at the given TSC value.
+    fn realtime_ns(&self, tsc: u64) -> u64 {
+        let elapsed_ticks = tsc.saturating_sub(self.base_tsc);
+        let mut realtime_ns = (self.base_ns + ticks_to_ns(elapsed_ticks)) as i128;
+        if let Some(smear) = self.smear {
+            let progress = elapsed_ticks.min(smear.duration_ticks) as i128;
+            realtime_ns += smear.offset_ns as i128 * progress / smear.duration_ticks as i128;
+        }
+        realtime_ns.max(0) as u64
+    }
+
+    /// Sets the clock to `realtime_ns` at once.
+    fn step(&mut self, tsc: u64, realtime_ns: u64) {
+        self.base_tsc = tsc;
+        self.base_ns = realtime_ns;
+        self.smear = None;
+    }
+
+    /// Lets the clock reach `realtime_ns` gradually within `duration_ticks`.
+    fn smear(&mut self, tsc: u64, realtime_ns: u64, duration_ticks: u64) {
+        if duration_ticks == 0 {
+            return self.step(tsc, realtime_ns);
+        }
+        let current_ns = self.realtime_ns(tsc);
+        self.step(tsc, current_ns);
+        self.smear.replace(Smear {
+            offset_ns: (realtime_ns as i128 - current_ns as i128) as i64,
+            duration_ticks,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsc_conversion() {
+        // 2 GHz
+        TSC_FREQ_KHZ.store(2_000_000, Ordering::SeqCst);
+        assert_eq!(ns_to_ticks(1), 2);
+        assert_eq!(ns_to_ticks(1_000_000_000), 2_000_000_000);
+        assert_eq!(ticks_to_ns(2_000_000_000), 1_000_000_000);
+        assert_eq!(ticks_to_ns(ns_to_ticks(1337)), 1337);
+        assert_eq!(ns_to_ticks(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_wall_clock() {
+        // 2 GHz
+        TSC_FREQ_KHZ.store(2_000_000, Ordering::SeqCst);
+        let mut clock = WallClock::new();
+        assert_eq!(clock.realtime_ns(2_000), 1_000);
+
+        clock.step(2_000, 5_000_000_000);
+        assert_eq!(clock.realtime_ns(2_000), 5_000_000_000);
+        assert_eq!(clock.realtime_ns(4_000), 5_000_001_000);
+
+        // one second back within 10 seconds; the clock runs slower but never backwards
+        clock.smear(4_000, 4_000_001_000, ns_to_ticks(10 * NS_PER_SEC));
+        assert_eq!(clock.realtime_ns(4_000), 5_000_001_000);
+        let halfway = clock.realtime_ns(4_000 + ns_to_ticks(5 * NS_PER_SEC));
+        assert_eq!(halfway, 9_500_001_000);
+        let done = clock.realtime_ns(4_000 + ns_to_ticks(10 * NS_PER_SEC));
+        assert_eq!(done, 14_000_001_000);
+        let after = clock.realtime_ns(4_000 + ns_to_ticks(11 * NS_PER_SEC));
+        assert_eq!(after, 15_000_001_000);
+    }
+}
@@ -0,0 +1,159 @@
+//! Name and version of the system as Linux processes see it via `uname()` and in the file
+//! `/etc/os-release`. Programs parse both, e.g. to print the system or to check the
+//! version of the kernel. The boot arguments `uname_sysname=<name>`,
+//! `uname_release=<release>`, `uname_machine=<machine>`, and `hostname=<name>` (see
+//! [`crate::rt::boot_args`]) replace the defaults. Each value has at most
+//! [`MAX_FIELD_LEN`] bytes.
+//!
+//! The default release looks like a version of Linux, because glibc refuses to run on
+//! kernels that it considers too old.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt::Write;
+use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::sync::mutex::SimpleMutex;
+
+/// Path of the file that describes the system.
+const OS_RELEASE_PATH: &str = "/etc/os-release";
+
+/// Fields of `struct utsname` have 65 bytes including the null byte.
+pub const MAX_FIELD_LEN: usize = 64;
+
+static UNAME: SimpleMutex<Uname> = SimpleMutex::new(Uname {
+    sysname: Cow::Borrowed("Hedron-Rust-RT"),
+    nodename: Cow::Borrowed("hedron"),
+    release: Cow::Borrowed("5.15.0-hedron"),
+    version: Cow::Borrowed(concat!("#1 SMP ", env!("CARGO_PKG_VERSION"))),
+    machine: Cow::Borrowed("x86_64"),
+});
+
+/// The fields that `uname()` reports, except for the NIS domain name, which is always
+/// `(none)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uname {
+    pub sysname: Cow<'static, str>,
+    pub nodename: Cow<'static, str>,
+    pub release: Cow<'static, str>,
+    pub version: Cow<'static, str>,
+    pub machine: Cow<'static, str>,
+}
+
+/// Returns the current name and version of the system.
+pub fn uname() -> Uname {
+    UNAME.lock().clone()
+}
+
+/// Sets the name of the system from the value of a boot argument. Returns false if the
+/// value is invalid.
+pub fn set_sysname(value: &str) -> bool {
+    update_field(value, |uname, value| uname.sysname = value)
+}
+
+/// Sets the name of the host from the value of a boot argument. Returns false if the value
+/// is invalid.
+pub fn set_nodename(value: &str) -> bool {
+    update_field(value, |uname, value| uname.nodename = value)
+}
+
+/// Sets the release of the system from the value of a boot argument. Returns false if the
+/// value is invalid.
+pub fn set_release(value: &str) -> bool {
+    update_field(value, |uname, value| uname.release = value)
+}
+
+/// Sets the hardware name from the value of a boot argument. Returns false if the value is
+/// invalid.
+pub fn set_machine(value: &str) -> bool {
+    update_field(value, |uname, value| uname.machine = value)
+}
+
+fn update_field(value: &str, update: impl FnOnce(&mut Uname, Cow<'static, str>)) -> bool {
+    if !is_valid_field(value) {
+        return false;
+    }
+    update(&mut UNAME.lock(), Cow::Owned(String::from(value)));
+    true
+}
+
+/// Fields are non-empty and fit into `struct utsname` as a C string.
+fn is_valid_field(value: &str) -> bool {
+    !value.is_empty() && value.len() <= MAX_FIELD_LEN && !value.contains('\0')
+}
+
+/// Creates `/etc/os-release` from the current name and version of the system. Must be
+/// called after the boot arguments are applied. The heap must be initialized.
+pub fn init() {
+    let content = render_os_release(&uname());
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    let fd = fs
+        .open_or_create_file(
+            ROOTTASK_PROCESS_PID,
+            OS_RELEASE_PATH,
+            FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+            0o444,
+        )
+        .unwrap();
+    fs.write_file(ROOTTASK_PROCESS_PID, fd, content.as_bytes())
+        .unwrap();
+    fs.close_file(ROOTTASK_PROCESS_PID, fd).unwrap();
+}
+
+/// Returns the content of `/etc/os-release` in the format of systemd.
+fn render_os_release(uname: &Uname) -> String {
+    let id = uname
+        .sysname
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '.' | '_' | '-' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect::<String>();
+    let mut out = String::new();
+    writeln!(out, "NAME=\"{}\"", uname.sysname).unwrap();
+    writeln!(out, "ID={}", id).unwrap();
+    writeln!(out, "VERSION_ID=\"{}\"", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(
+        out,
+        "PRETTY_NAME=\"{} {} ({})\"",
+        uname.sysname,
+        env!("CARGO_PKG_VERSION"),
+        uname.machine
+    )
+    .unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_field() {
+        assert!(is_valid_field("x86_64"));
+        assert!(is_valid_field(&"a".repeat(MAX_FIELD_LEN)));
+        assert!(!is_valid_field(&"a".repeat(MAX_FIELD_LEN + 1)));
+        assert!(!is_valid_field(""));
+        assert!(!is_valid_field("a\0b"));
+    }
+
+    #[test]
+    fn test_render_os_release() {
+        let uname = Uname {
+            sysname: Cow::Borrowed("My RT"),
+            nodename: Cow::Borrowed("host"),
+            release: Cow::Borrowed("5.15.0"),
+            version: Cow::Borrowed("#1"),
+            machine: Cow::Borrowed("x86_64"),
+        };
+        assert_eq!(
+            render_os_release(&uname),
+            format!(
+                "NAME=\"My RT\"\nID=my_rt\nVERSION_ID=\"{0}\"\nPRETTY_NAME=\"My RT {0} (x86_64)\"\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Host tool that turns the output of the roottask in the binary log format (boot argument
+//! `log_format=binary`) back into text. See [`libhrstd::util::binary_log`] for the format.
+//! The records reference strings in the image of the roottask, hence the tool needs the
+//! ELF file of the exact build that produced the output. Text between the records, such
+//! as the output of other processes, passes through unchanged.
+//!
+//! Usage: `logdecoder-host <roottask ELF> [<log file>]`. Reads the log from STDIN, if no
+//! log file is given. Example: `logdecoder-host build/roottask-bin qemu_debugcon.txt`.
+
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+
+use libhrstd::util::binary_log::{
+    BinaryLogDecodeError,
+    BinaryLogMsg,
+    BinaryLogRecord,
+    BinaryLogTimestamp,
+    StrRef,
+};
+use std::io::{
+    Read,
+    Write,
+};
+use std::process::exit;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+/// ELF program header type of a loadable segment.
+const PT_LOAD: u32 = 1;
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} <roottask ELF> [<log file>]", args[0]);
+        exit(1);
+    }
+    let elf = std::fs::read(&args[1]).unwrap_or_else(|e| {
+        eprintln!("can't read {}: {}", args[1], e);
+        exit(1);
+    });
+    let image = Image::parse(&elf).unwrap_or_else(|| {
+        eprintln!("{} is not a 64-bit ELF file", args[1]);
+        exit(1);
+    });
+    let log = match args.get(2) {
+        Some(path) => std::fs::read(path).unwrap_or_else(|e| {
+            eprintln!("can't read {}: {}", path, e);
+            exit(1);
+        }),
+        None => {
+            let mut log = Vec::new();
+            std::io::stdin().read_to_end(&mut log).unwrap();
+            log
+        }
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let mut rest = log.as_slice();
+    while !rest.is_empty() {
+        // text up to the next possible record passes through
+        let text_len = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+        out.write_all(&rest[..text_len]).unwrap();
+        rest = &rest[text_len..];
+        if rest.is_empty() {
+            break;
+        }
+        match BinaryLogRecord::decode(rest) {
+            Ok((record, len)) => {
+                writeln!(out, "{}", image.fmt_record(&record)).unwrap();
+                rest = &rest[len..];
+            }
+            // the log ends within a record
+            Err(BinaryLogDecodeError::Incomplete) => {
+                eprintln!("log ends with an incomplete record");
+                break;
+            }
+            Err(BinaryLogDecodeError::NoMagic | BinaryLogDecodeError::Invalid) => {
+                out.write_all(&rest[..1]).unwrap();
+                rest = &rest[1..];
+            }
+        }
+    }
+}
+
+/// Loadable segments of the ELF file of the roottask.
+#[derive(Debug)]
+struct Image<'a> {
+    /// Virtual address and content of each segment.
+    segments: Vec<(u64, &'a [u8])>,
+}
+
+impl<'a> Image<'a> {
+    /// Parses the program headers of a 64-bit little-endian ELF file.
+    fn parse(elf: &'a [u8]) -> Option<Self> {
+        if elf.get(..5)? != b"\x7fELF\x02" {
+            return None;
+        }
+        let phoff = read_u64(elf, 0x20)? as usize;
+        let phentsize = read_u16(elf, 0x36)? as usize;
+        let phnum = read_u16(elf, 0x38)? as usize;
+        let mut segments = Vec::new();
+        for i in 0..phnum {
+            let phdr = elf.get(phoff + i * phentsize..phoff + (i + 1) * phentsize)?;
+            if read_u32(phdr, 0)? != PT_LOAD {
+                continue;
+            }
+            let offset = read_u64(phdr, 0x08)? as usize;
+            let vaddr = read_u64(phdr, 0x10)?;
+            let filesz = read_u64(phdr, 0x20)? as usize;
+            segments.push((vaddr, elf.get(offset..offset + filesz)?));
+        }
+        Some(Self { segments })
+    }
+
+    /// Returns the referenced string of the image.
+    fn resolve(&self, str_ref: StrRef) -> Option<&'a str> {
+        if str_ref.is_null() {
+            return None;
+        }
+        let addr = str_ref.addr as u64;
+        let (vaddr, data) = self
+            .segments
+            .iter()
+            .find(|(vaddr, data)| (*vaddr..*vaddr + data.len() as u64).contains(&addr))?;
+        let start = (addr - vaddr) as usize;
+        std::str::from_utf8(data.get(start..start + str_ref.len as usize)?).ok()
+    }
+
+    /// Formats the record like the text log of the roottask, without colors.
+    fn fmt_record(&self, record: &BinaryLogRecord) -> String {
+        let timestamp = match record.timestamp {
+            Some(BinaryLogTimestamp::Ns(ns)) => {
+                format!("[{:>5}.{:09}] ", ns / NS_PER_SEC, ns % NS_PER_SEC)
+            }
+            Some(BinaryLogTimestamp::Ticks(ticks)) => format!("[tsc={}] ", ticks),
+            None => String::new(),
+        };
+        let crate_name = self
+            .resolve(record.target)
+            .map(|module| module.split_once("::").map(|x| x.0).unwrap_or(module))
+            .unwrap_or("<unknown mod>");
+        // remove full system path, only keep file path in project
+        let file = self
+            .resolve(record.file)
+            .map(|f| f.find("/src").map(|index| &f[index + 1..]).unwrap_or(f))
+            .unwrap_or("<unknown file>");
+        let msg = match record.msg {
+            BinaryLogMsg::Static(msg) => self.resolve(msg).unwrap_or("<unknown msg>"),
+            BinaryLogMsg::Inline(msg) => msg,
+        };
+        format!(
+            "{}[{:>5}] {}:{:>15}@{}: {}",
+            timestamp,
+            record.level.as_str(),
+            crate_name,
+            file,
+            record.line,
+            msg
+        )
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
@@ -0,0 +1,288 @@
+//! Benchmarks that the roottask runs during startup, before it starts the userland. They
+//! measure PD-internal IPC, native system calls, the heap, and the in-memory file system
+//! without IPC. Only built with the feature `boot_bench`; the cross-PD benchmarks of user
+//! applications are part of `native-bench-bin`.
+
+use alloc::vec::Vec;
+use core::alloc::{
+    GlobalAlloc,
+    Layout,
+};
+use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::time::{
+    Duration,
+    Instant,
+};
+use libhrstd::util::bench_report::BenchReport;
+use libhrstd::util::BenchHelper;
+use libroottask::services::init_roottask_echo_pts;
+use libroottask::static_alloc::{
+    buddy_bitmap_size,
+    GlobalBuddyAllocator,
+};
+use libroottask::time;
+use simple_chunk_allocator::{
+    heap,
+    heap_bitmap,
+    GlobalChunkAllocator,
+    PageAligned,
+    DEFAULT_CHUNK_SIZE,
+};
+
+/// Performs several PD-internal IPC benchmarks and measures native system call
+/// performance from a Native Hedron App (i.e. the roottask).
+pub fn do_bench() {
+    log::info!("benchmarking starts");
+    let (echo_pt, raw_echo_pt) = init_roottask_echo_pts();
+    // ############################################################################
+    // MEASURE NATIVE SYSTEM CALL PERFORMANCE
+    let native_syscall_costs = BenchHelper::<_>::bench_direct_stats(|i| unsafe {
+        raw_echo_pt.ctrl(i).unwrap();
+    });
+    // ############################################################################
+    // MEASURE ECHO SYSCALL PERFORMANCE (PD-internal IPC with my PT multiplexing mechanism)
+    let echo_call_costs = BenchHelper::<_>::bench_direct_stats(|_| echo_pt.call().unwrap());
+    // ############################################################################
+    // MEASURE RAW ECHO SYSCALL PERFORMANCE (pure PD-internal IPC)
+    let raw_echo_call_costs = BenchHelper::<_>::bench_direct_stats(|_| raw_echo_pt.call().unwrap());
+    // ############################################################################
+    // MEASURE ROOTTASK ALLOCATION COSTS (1 Byte)
+    let alloc_1_byte_costs = BenchHelper::<_>::bench_direct_stats(|_| {
+        let vec = Vec::<u8>::with_capacity(1);
+        unsafe {
+            let _x = core::ptr::read_volatile(vec.as_ptr());
+        }
+    });
+    // ############################################################################
+    // MEASURE ROOTTASK ALLOCATION COSTS (4096 Byte)
+    let alloc_4096_byte_costs = BenchHelper::<_>::bench_direct_stats(|_| {
+        let vec = Vec::<u8>::with_capacity(4096);
+        unsafe {
+            let _x = core::ptr::read_volatile(vec.as_ptr());
+        }
+    });
+    // ############################################################################
+    // COMPARE THE PREVIOUS CHUNK ALLOCATOR WITH THE BUDDY ALLOCATOR OF THE HEAP
+    let (chunk_alloc_costs, buddy_alloc_costs) = bench_allocators();
+    // ############################################################################
+    // MEASURE FILE SYSTEM PERFORMANCE WITHIN ROOTTASK: open, write &close
+    let fs_open_write_close_costs = BenchHelper::<_>::bench_direct_stats(|_| {
+        // Don't use the same lock to better simulate the costs of a real world scenario.
+        let fd = libfileserver::FILESYSTEM
+            .lock()
+            .open_or_create_file(
+                0,
+                "/tmp/roottask_bench1",
+                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+                0o777,
+            )
+            .unwrap();
+        let data = [0xd_u8, 0xe, 0xa, 0xd, 0xb, 0xe, 0xe, 0xf];
+        libfileserver::FILESYSTEM
+            .lock()
+            .write_file(0, fd, &[0xd, 0xe, 0xa, 0xd, 0xb, 0xe, 0xe, 0xf])
+            .unwrap();
+        libfileserver::FILESYSTEM
+            .lock()
+            .lseek_file(0, fd, 0)
+            .unwrap();
+        let mut fs_lock = libfileserver::FILESYSTEM.lock();
+        let read_data = fs_lock.read_file(0, fd, data.len()).unwrap();
+        assert_eq!(&data, read_data, "written data must equal to read data");
+        drop(fs_lock);
+        libfileserver::FILESYSTEM.lock().close_file(0, fd).unwrap();
+    });
+    // ############################################################################
+
+    let tsc_freq_khz = time::tsc_freq_khz();
+    for (name, unit, stats) in [
+        (
+            "native pt_ctrl syscall costs costs",
+            "pt_ctrl syscall",
+            native_syscall_costs,
+        ),
+        (
+            "raw echo call costs               ",
+            "call syscall (PD-internal IPC)",
+            raw_echo_call_costs,
+        ),
+        (
+            "echo call costs                   ",
+            "call syscall (PD-internal IPC)",
+            echo_call_costs,
+        ),
+        (
+            "roottask 1 bytes mem alloc costs  ",
+            "allocation (no IPC; pure internal)",
+            alloc_1_byte_costs,
+        ),
+        (
+            "roottask 4096 byte mem alloc costs",
+            "allocation (no IPC; pure internal)",
+            alloc_4096_byte_costs,
+        ),
+        (
+            "roottask fs open,w+r&close costs  ",
+            "(open, write, read & close) (no IPC; pure internal)",
+            fs_open_write_close_costs,
+        ),
+    ] {
+        log::info!(
+            "{}: {} ticks / {}; median={} p99={} stddev={} ticks; mean={}ns",
+            name,
+            stats.mean,
+            unit,
+            stats.median,
+            stats.p99,
+            stats.stddev,
+            stats.to_ns(tsc_freq_khz).mean
+        );
+    }
+    for (name, costs) in [("chunk", chunk_alloc_costs), ("buddy", buddy_alloc_costs)] {
+        log::info!(
+            "{} allocator alloc+dealloc costs: {} ticks (64 byte), {} ticks (4096 byte), {} ticks (512 byte, fragmented heap)",
+            name,
+            costs.small,
+            costs.page,
+            costs.fragmented
+        );
+    }
+
+    let mut report = BenchReport::new(
+        "roottask",
+        &libhrstd::build_info!().git_hash,
+        Instant::now().val(),
+    );
+    report
+        .with_tsc_freq_khz(tsc_freq_khz)
+        .add_stats("native pt_ctrl syscall", native_syscall_costs)
+        .add_stats("raw echo call", raw_echo_call_costs)
+        .add_stats("echo call", echo_call_costs)
+        .add_stats("alloc 1 byte", alloc_1_byte_costs)
+        .add_stats("alloc 4096 byte", alloc_4096_byte_costs)
+        .add_stats("fs open write read close", fs_open_write_close_costs)
+        .add("chunk alloc 64 byte", chunk_alloc_costs.small)
+        .add("chunk alloc 4096 byte", chunk_alloc_costs.page)
+        .add(
+            "chunk alloc 512 byte fragmented",
+            chunk_alloc_costs.fragmented,
+        )
+        .add("buddy alloc 64 byte", buddy_alloc_costs.small)
+        .add("buddy alloc 4096 byte", buddy_alloc_costs.page)
+        .add(
+            "buddy alloc 512 byte fragmented",
+            buddy_alloc_costs.fragmented,
+        );
+    persist_bench_report(&report);
+
+    log::info!("benchmarking done");
+}
+
+/// Writes the report as JSON and CSV into the file system, so that it is available for
+/// later analysis inside the system, e.g. by the bench tool, or on a host.
+fn persist_bench_report(report: &BenchReport) {
+    let mut fs = libfileserver::FILESYSTEM.lock();
+    for (path, data) in [
+        (report.path(), report.to_json()),
+        (report.csv_path(), report.to_csv()),
+    ] {
+        let fd = fs
+            .open_or_create_file(
+                ROOTTASK_PROCESS_PID,
+                &path,
+                FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+                0o644,
+            )
+            .unwrap();
+        fs.write_file(ROOTTASK_PROCESS_PID, fd, data.as_bytes())
+            .unwrap();
+        fs.close_file(ROOTTASK_PROCESS_PID, fd).unwrap();
+    }
+    log::info!(
+        "bench results written to {} and {}",
+        report.path(),
+        report.csv_path()
+    );
+}
+
+/// Size of each heap of [`bench_allocators`].
+const BENCH_HEAP_SIZE: usize = 1048576;
+const BENCH_CHUNK_AMOUNT: usize = BENCH_HEAP_SIZE / DEFAULT_CHUNK_SIZE;
+static mut BENCH_CHUNK_HEAP: PageAligned<[u8; BENCH_HEAP_SIZE]> =
+    heap!(chunks = BENCH_CHUNK_AMOUNT);
+static mut BENCH_CHUNK_BITMAP: PageAligned<[u8; BENCH_CHUNK_AMOUNT / 8]> =
+    heap_bitmap!(chunks = BENCH_CHUNK_AMOUNT);
+static mut BENCH_BUDDY_HEAP: PageAligned<[u8; BENCH_HEAP_SIZE]> =
+    PageAligned::new([0; BENCH_HEAP_SIZE]);
+static mut BENCH_BUDDY_BITMAP: PageAligned<[u8; buddy_bitmap_size(BENCH_HEAP_SIZE)]> =
+    PageAligned::new([0; buddy_bitmap_size(BENCH_HEAP_SIZE)]);
+
+static BENCH_CHUNK_ALLOC: GlobalChunkAllocator = unsafe {
+    GlobalChunkAllocator::new(
+        BENCH_CHUNK_HEAP.deref_mut_const(),
+        BENCH_CHUNK_BITMAP.deref_mut_const(),
+    )
+};
+static BENCH_BUDDY_ALLOC: GlobalBuddyAllocator = unsafe {
+    GlobalBuddyAllocator::new(
+        BENCH_BUDDY_HEAP.deref_mut_const(),
+        BENCH_BUDDY_BITMAP.deref_mut_const(),
+    )
+};
+
+/// Costs of an allocation and deallocation with an allocator, see [`bench_allocators`].
+#[derive(Debug, Copy, Clone)]
+struct AllocatorBench {
+    /// 64 bytes on an empty heap.
+    pub small: Duration,
+    /// 4096 bytes on an empty heap.
+    pub page: Duration,
+    /// 512 bytes on a heap whose first half is full of 256 byte holes.
+    pub fragmented: Duration,
+}
+
+/// Microbenchmarks that compare the chunk allocator, which the roottask used before, with
+/// the buddy allocator. Each allocator gets its own heap of [`BENCH_HEAP_SIZE`] bytes.
+/// Returns the costs of the chunk allocator and of the buddy allocator.
+fn bench_allocators() -> (AllocatorBench, AllocatorBench) {
+    (
+        bench_allocator(&BENCH_CHUNK_ALLOC),
+        bench_allocator(&BENCH_BUDDY_ALLOC),
+    )
+}
+
+fn bench_allocator(alloc: &dyn GlobalAlloc) -> AllocatorBench {
+    let alloc_dealloc = |size| {
+        let layout = Layout::from_size_align(size, 8).unwrap();
+        BenchHelper::<_>::bench_direct(|_| unsafe {
+            let ptr = alloc.alloc(layout);
+            assert!(!ptr.is_null(), "bench heap is full");
+            alloc.dealloc(ptr, layout);
+        })
+    };
+    let small = alloc_dealloc(64);
+    let page = alloc_dealloc(4096);
+
+    // fill the first half of the heap and free every second allocation
+    let filler = Layout::from_size_align(256, 8).unwrap();
+    let filler_ptrs = (0..BENCH_HEAP_SIZE / 2 / filler.size())
+        .map(|_| unsafe { alloc.alloc(filler) })
+        .collect::<alloc::vec::Vec<_>>();
+    filler_ptrs
+        .iter()
+        .step_by(2)
+        .for_each(|ptr| unsafe { alloc.dealloc(*ptr, filler) });
+    let fragmented = alloc_dealloc(512);
+    filler_ptrs
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .for_each(|ptr| unsafe { alloc.dealloc(*ptr, filler) });
+
+    AllocatorBench {
+        small,
+        page,
+        fragmented,
+    }
+}
@@ -27,6 +27,8 @@
 // any global definitions required to be in assembly
 global_asm!(include_str!("assembly.S"));
 
+#[cfg(feature = "boot_bench")]
+mod boot_bench;
 mod panic;
 mod roottask_heap;
 mod roottask_logger;
@@ -40,26 +42,38 @@ extern crate alloc;
 #[macro_use]
 extern crate libhrstd;
 
-use alloc::vec::Vec;
 use core::arch::global_asm;
 use libhrstd::cap_space::root::RootCapSpace;
-use libhrstd::kobjects::{
-    PtObject,
-    SmObject,
-};
+use libhrstd::kobjects::SmObject;
 use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::Utcb;
 use libhrstd::libhedron::HIP;
-use libhrstd::rt::services::fs::FsOpenFlags;
-use libhrstd::util::BenchHelper;
+use libroottask::mem::{
+    PHYS_FRAME_ALLOC,
+    ROOTTASK_HEAP_STATS,
+};
 use libroottask::process;
-use libroottask::rt::userland;
-use libroottask::services::init_roottask_echo_pts;
+use libroottask::rt::{
+    boot_args,
+    devfs,
+    hostfs,
+    procfs,
+    userland,
+};
+use libroottask::static_alloc::BUDDY_MIN_BLOCK_SIZE;
 use libroottask::{
+    fs_quota,
+    hedron_features,
+    hw,
+    irq,
     roottask_exception,
+    safe_mode,
+    service_stats,
     services,
+    smp,
+    time,
+    uname,
 };
-use simple_chunk_allocator::DEFAULT_CHUNK_SIZE;
 
 #[no_mangle]
 fn roottask_rust_entry(hip_addr: u64, utcb_addr: u64) -> ! {
@@ -68,11 +82,28 @@ fn roottask_rust_entry(hip_addr: u64, utcb_addr: u64) -> ! {
 
     services::init_writers(hip);
     roottask_logger::init();
+    roottask_heap::init();
+    // user memory comes from here and no longer from the heap
+    PHYS_FRAME_ALLOC.lock().init(hip);
 
     // unsafe {ROOTTASK_STACK.test_rw_guard_page()};
     // log::info!("guard-page inactive");
     roottask_stack::init(hip);
     // unsafe {ROOTTASK_STACK.test_rw_guard_page()};
+    time::init(hip, RootCapSpace::RootPd.val());
+    time::init_wall_clock(RootCapSpace::RootPd.val());
+    // files get their timestamps from the wall clock
+    libfileserver::set_clock(time::realtime_ns);
+    hedron_features::init(hip);
+    smp::init(hip);
+    hw::acpi::init(hip);
+    irq::init(hip);
+    hw::pci::init(hip);
+    services::build_info::init(libhrstd::build_info!(), hip);
+    service_stats::init();
+    fs_quota::init();
+    devfs::init();
+    procfs::init();
 
     #[rustfmt::skip]
     {
@@ -85,7 +116,7 @@ fn roottask_rust_entry(hip_addr: u64, utcb_addr: u64) -> ! {
         log::debug!("heap bottom (incl) : 0x{:016x}", roottask_heap::HEAP_BEGIN_PTR.val());
         log::debug!("heap size          : {:>18}", roottask_heap::HEAP_SIZE);
         log::debug!("heap size (pages)  : {:>18}", roottask_heap::HEAP_SIZE / PAGE_SIZE);
-        log::debug!("heap size (chunks) : {:>18}", roottask_heap::HEAP_SIZE / DEFAULT_CHUNK_SIZE);
+        log::debug!("heap size (blocks) : {:>18}", roottask_heap::HEAP_SIZE / BUDDY_MIN_BLOCK_SIZE);
 
         log::debug!("utcb ptr           : 0x{:016x}", utcb_addr);
         log::debug!("hip ptr            : 0x{:016x}", hip_addr);
@@ -98,20 +129,38 @@ fn roottask_rust_entry(hip_addr: u64, utcb_addr: u64) -> ! {
     process::PROCESS_MNG.lock().register_startup_exc_callback();
 
     let root_process = process::PROCESS_MNG.lock().root().clone();
-    let root_sm = SmObject::create(RootCapSpace::RootSmSleep.val(), &root_process.pd_obj());
+    boot_args::init(hip, &root_process);
+    // the boot arguments select the framebuffer and the virtio-console
+    if let Some(framebuffer) = hw::framebuffer::init() {
+        services::stdout::writer_mut().attach_framebuffer(framebuffer);
+    }
+    if let Some(console) = hw::virtio_console::VirtioConsole::init(&root_process) {
+        services::stdout::writer_mut().attach_virtio_console(console);
+    }
+    uname::init();
+    let _root_sm = SmObject::create(RootCapSpace::RootSmSleep.val(), &root_process.pd_obj());
 
     services::init_services(process::PROCESS_MNG.lock().root());
-    let (echo_pt, raw_echo_pt) = init_roottask_echo_pts();
+    // safe mode skips the drivers and the benchmarks
+    if !safe_mode::is_enabled() {
+        services::network::init(&root_process);
+        hw::input::init(RootCapSpace::RootPd.val());
+        hostfs::init(&root_process);
+    }
 
     log::info!("Rust Roottask started successfully");
 
     // Check how the allocation costs changes if the heap is already really full.
     // let _vec = Vec::<u8>::with_capacity(1024 * 1024 * 2); // 2 MebiByte
-    do_bench(&echo_pt, &raw_echo_pt);
+    #[cfg(feature = "boot_bench")]
+    if !safe_mode::is_enabled() {
+        boot_bench::do_bench();
+    }
 
     // NOW READY TO START PROCESSES
     let userland = userland::InitialUserland::load(hip, &root_process);
-    // in "bootstrap" I hard-code the ELF file that should be started
+    // the userland tar is mounted at /bin now; "bootstrap" starts the programs listed in
+    // /bin/autostart or the hard-coded ELF file
     userland.bootstrap();
     log::info!("Userland bootstrapped");
 
@@ -133,96 +182,13 @@ fn roottask_rust_entry(hip_addr: u64, utcb_addr: u64) -> ! {
         }
     }*/
 
-    // Puts the main thread to sleep nicely; there is no need for a busy loop
-    root_sm.sem_down();
-    unreachable!();
-}
+    // The main thread is done with its work; report how much of the static heap is used
+    // so far, so that HEAP_SIZE can be tuned.
+    ROOTTASK_HEAP_STATS.log_report();
+    #[cfg(feature = "lock_stats")]
+    libhrstd::sync::lock_stats::LOCK_STATS.log_report();
 
-/// Performs several PD-internal IPC benchmarks and measures native system call
-/// performance from a Native Hedron App (i.e. the roottask).
-fn do_bench(echo_pt: &PtObject, raw_echo_pt: &PtObject) {
-    log::info!("benchmarking starts");
-    // ############################################################################
-    // MEASURE NATIVE SYSTEM CALL PERFORMANCE
-    let native_syscall_costs = BenchHelper::<_>::bench_direct(|i| unsafe {
-        raw_echo_pt.ctrl(i).unwrap();
-    });
-    // ############################################################################
-    // MEASURE ECHO SYSCALL PERFORMANCE (PD-internal IPC with my PT multiplexing mechanism)
-    let echo_call_costs = BenchHelper::<_>::bench_direct(|_| echo_pt.call().unwrap());
-    // ############################################################################
-    // MEASURE RAW ECHO SYSCALL PERFORMANCE (pure PD-internal IPC)
-    let raw_echo_call_costs = BenchHelper::<_>::bench_direct(|_| raw_echo_pt.call().unwrap());
-    // ############################################################################
-    // MEASURE ROOTTASK ALLOCATION COSTS (1 Byte)
-    let alloc_1_byte_costs = BenchHelper::<_>::bench_direct(|_| {
-        let vec = Vec::<u8>::with_capacity(1);
-        unsafe {
-            let _x = core::ptr::read_volatile(vec.as_ptr());
-        }
-    });
-    // ############################################################################
-    // MEASURE ROOTTASK ALLOCATION COSTS (4096 Byte)
-    let alloc_4096_byte_costs = BenchHelper::<_>::bench_direct(|_| {
-        let vec = Vec::<u8>::with_capacity(4096);
-        unsafe {
-            let _x = core::ptr::read_volatile(vec.as_ptr());
-        }
-    });
-    // ############################################################################
-    // MEASURE FILE SYSTEM PERFORMANCE WITHIN ROOTTASK: open, write &close
-    let fs_open_write_close_costs = BenchHelper::<_>::bench_direct(|_| {
-        // Don't use the same lock to better simulate the costs of a real world scenario.
-        let fd = libfileserver::FILESYSTEM
-            .lock()
-            .open_or_create_file(
-                0,
-                "/tmp/roottask_bench1",
-                FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
-                0o777,
-            )
-            .unwrap();
-        let data = [0xd_u8, 0xe, 0xa, 0xd, 0xb, 0xe, 0xe, 0xf];
-        libfileserver::FILESYSTEM
-            .lock()
-            .write_file(0, fd, &[0xd, 0xe, 0xa, 0xd, 0xb, 0xe, 0xe, 0xf])
-            .unwrap();
-        libfileserver::FILESYSTEM
-            .lock()
-            .lseek_file(0, fd, 0)
-            .unwrap();
-        let mut fs_lock = libfileserver::FILESYSTEM.lock();
-        let read_data = fs_lock.read_file(0, fd, data.len()).unwrap();
-        assert_eq!(&data, read_data, "written data must equal to read data");
-        drop(fs_lock);
-        libfileserver::FILESYSTEM.lock().close_file(0, fd).unwrap();
-    });
-    // ############################################################################
-
-    log::info!(
-        "native pt_ctrl syscall costs costs: {} ticks / pt_ctrl syscall",
-        native_syscall_costs
-    );
-    log::info!(
-        "raw echo call costs               : {} ticks / call syscall (PD-internal IPC)",
-        raw_echo_call_costs
-    );
-    log::info!(
-        "echo call costs                   : {} ticks / call syscall (PD-internal IPC)",
-        echo_call_costs
-    );
-    log::info!(
-        "roottask 1 bytes mem alloc costs  : {} ticks / allocation (no IPC; pure internal)",
-        alloc_1_byte_costs
-    );
-    log::info!(
-        "roottask 4096 byte mem alloc costs: {} ticks / allocation (no IPC; pure internal)",
-        alloc_4096_byte_costs
-    );
-    log::info!(
-        "roottask fs open,w+r&close costs  : {} ticks / (open, write, read & close) (no IPC; pure internal)",
-        fs_open_write_close_costs
-    );
-
-    log::info!("benchmarking done");
+    // The main thread handles the expiration of the timers of the timer service from now on.
+    // It sleeps nicely in between; there is no need for a busy loop.
+    services::timer::timer_loop();
 }
@@ -1,11 +1,28 @@
 use crate::PAGE_SIZE;
+use arrayvec::ArrayString;
 use core::arch::asm;
 use core::panic::PanicInfo;
 use core::sync::atomic::{
     compiler_fence,
     Ordering,
 };
+use libhrstd::util::backtrace::{
+    current_frame_pointer,
+    write_backtrace,
+    EmbeddedSymbols,
+};
 use libhrstd::util::panic_msg::generate_panic_msg;
+use libroottask::mem::ROOTTASK_HEAP_STATS;
+
+/// Filled with the functions of the roottask after linking, see
+/// [`libhrstd::util::backtrace`].
+#[used]
+#[link_section = ".hedron_symbols"]
+static SYMBOLS: EmbeddedSymbols<{ 512 * 1024 }> = EmbeddedSymbols::new();
+
+/// The backtrace only follows frames that far above the first frame. All stacks of the
+/// roottask are smaller, but it doesn't know which stack the panicking thread runs on.
+const BACKTRACE_RANGE: u64 = 1024 * 1024;
 
 /// Writes 0x2EEDCOFFEE into r8 to r15, writes a nice panic message to the logger,
 /// and aborts the program in an endless loop.
@@ -26,8 +43,31 @@ fn panic_handler(info: &PanicInfo) -> ! {
     }
 
     log::error!("{}", generate_panic_msg::<PAGE_SIZE>(info));
+    log_backtrace();
+    // The roottask can't continue; helps to find out if the heap was exhausted.
+    ROOTTASK_HEAP_STATS.log_report();
+    // shows the owners of the locks, e.g. after a deadlock
+    #[cfg(feature = "lock_stats")]
+    libhrstd::sync::lock_stats::LOCK_STATS.log_report();
 
     loop {
         compiler_fence(Ordering::SeqCst);
     }
 }
+
+/// Logs the backtrace of the panicking thread.
+fn log_backtrace() {
+    let rbp = current_frame_pointer();
+    let range = rbp..rbp + BACKTRACE_RANGE;
+    let mut out = ArrayString::<PAGE_SIZE>::new();
+    // truncated output is still helpful
+    let _ = unsafe {
+        write_backtrace(
+            &mut out,
+            rbp,
+            |addr| range.contains(&addr) && range.contains(&(addr + 15)),
+            SYMBOLS.table(),
+        )
+    };
+    log::error!("{}", out);
+}
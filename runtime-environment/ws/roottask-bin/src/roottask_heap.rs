@@ -1,25 +1,28 @@
 //! Allocator for the roottask - the HEAP. The roottask uses a statically allocated array
 //! as backing storage for the HEAP. The memory is mapped and available after Hedron starts the
-//! roottask.
+//! roottask. The buddy allocator of [`libroottask::static_alloc`] manages it.
 
-use core::alloc::Layout;
+use core::alloc::{
+    GlobalAlloc,
+    Layout,
+};
 use libhrstd::sync::static_global_ptr::StaticGlobalPtr;
-use simple_chunk_allocator::{
-    heap,
-    heap_bitmap,
-    GlobalChunkAllocator,
+use libroottask::mem::ROOTTASK_HEAP_STATS;
+use libroottask::static_alloc::{
+    buddy_bitmap_size,
+    BuddyAllocator,
+    GlobalBuddyAllocator,
 };
+use simple_chunk_allocator::PageAligned;
 
-// times chunk_size==256 => 24MiB
+// 24MiB
 // I need a relatively large heap for the in-mem file system benchmark
 // The benchmark itself requires lots of heap but also the in-mem file system
 // additionally, fragmentation makes this hard .. so yeah.. big heap required
-const CHUNK_AMOUNT: usize = 98304;
-static mut HEAP: simple_chunk_allocator::PageAligned<[u8; 25165824]> = heap!(chunks = CHUNK_AMOUNT);
-static mut BITMAP: simple_chunk_allocator::PageAligned<[u8; 12288]> =
-    heap_bitmap!(chunks = CHUNK_AMOUNT);
-
-pub static HEAP_SIZE: usize = unsafe { HEAP.deref_const().len() };
+pub const HEAP_SIZE: usize = 25165824;
+static mut HEAP: PageAligned<[u8; HEAP_SIZE]> = PageAligned::new([0; HEAP_SIZE]);
+static mut BITMAP: PageAligned<[u8; buddy_bitmap_size(HEAP_SIZE)]> =
+    PageAligned::new([0; buddy_bitmap_size(HEAP_SIZE)]);
 
 /// Begin address of the heap.
 pub static HEAP_BEGIN_PTR: StaticGlobalPtr<u8> =
@@ -30,13 +33,41 @@ pub static HEAP_END_PTR: StaticGlobalPtr<u8> =
     unsafe { StaticGlobalPtr::new(HEAP_BEGIN_PTR.get().add(HEAP_SIZE)) };
 
 #[global_allocator]
-static ALLOC: GlobalChunkAllocator =
-    unsafe { GlobalChunkAllocator::new(HEAP.deref_mut_const(), BITMAP.deref_mut_const()) };
+static ALLOC: TrackingBuddyAllocator = TrackingBuddyAllocator(unsafe {
+    GlobalBuddyAllocator::new(HEAP.deref_mut_const(), BITMAP.deref_mut_const())
+});
+
+/// Initializes the heap statistics. Must be called early during roottask startup.
+pub fn init() {
+    ROOTTASK_HEAP_STATS.init(HEAP_SIZE);
+}
 
-/// Wrapper around [`GlobalStaticChunkAllocator::usage`].
+/// Wrapper around [`GlobalBuddyAllocator::usage`].
 #[allow(unused)]
 pub fn usage() -> f32 {
-    ALLOC.usage()
+    ALLOC.0.usage()
+}
+
+/// Wraps the [`GlobalBuddyAllocator`] and records all successful allocations with the size
+/// of their buddy block in [`ROOTTASK_HEAP_STATS`].
+#[derive(Debug)]
+struct TrackingBuddyAllocator(GlobalBuddyAllocator<'static>);
+
+unsafe impl GlobalAlloc for TrackingBuddyAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.0.alloc(layout);
+        if !ptr.is_null() {
+            let block_size = BuddyAllocator::occupied_size(layout).unwrap();
+            ROOTTASK_HEAP_STATS.record_alloc(layout.size(), block_size);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout);
+        let block_size = BuddyAllocator::occupied_size(layout).unwrap();
+        ROOTTASK_HEAP_STATS.record_dealloc(layout.size(), block_size);
+    }
 }
 
 #[alloc_error_handler]
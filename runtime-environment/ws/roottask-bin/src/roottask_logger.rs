@@ -1,30 +1,55 @@
-//! Module to initialize typical Rust logging for the Roottask itself.
-
-use arrayvec::ArrayString;
+//! Module to initialize typical Rust logging for the Roottask itself. Each record goes to
+//! the log buffer (see [`libroottask::log_buffer`]) and, unless it is disabled, to the
+//! console.
+
+use alloc::string::String;
+use arrayvec::{
+    ArrayString,
+    ArrayVec,
+};
 use core::fmt::Write;
+use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
+use libhrstd::rt::services::logging::{
+    LogLevel,
+    LOG_MAX_MSG_LEN,
+};
 use libhrstd::sync::mutex::SimpleMutex;
 use libhrstd::util::ansi::{
     AnsiStyle,
     Color,
     TextStyle,
 };
+use libhrstd::util::binary_log::{
+    BinaryLogMsg,
+    BinaryLogRecord,
+    StrRef,
+    BINARY_LOG_MAX_HEADER_LEN,
+};
+use libroottask::log_format::LogFormat;
 use libroottask::services::stderr::StderrWriter;
+use libroottask::{
+    log_buffer,
+    log_format,
+    log_timestamp,
+};
 use log::{
     Level,
-    LevelFilter,
     Log,
     Metadata,
     Record,
 };
 
+/// Maximum length of the formatted message of a binary log record.
+const BINARY_LOG_MAX_MSG_LEN: usize = 1024;
+
 /// Logger instance that gets passed to the [`log`]-crate.
 /// Synchronizes all logs.
 static LOGGER: GenericLogger = GenericLogger::new();
 
 /// Initializes the Rust logger for the root task. Forwards to the default STDERR location.
 pub fn init() {
-    // log::set_max_level(LevelFilter::max());
-    log::set_max_level(LevelFilter::Info);
+    // the filters of the log buffer can lower or raise it later
+    log::set_max_level(log_buffer::level(ROOTTASK_PROCESS_PID).level_filter());
     log::set_logger(&LOGGER).expect("call this only once!");
 
     // Q&D: execute this once, so catch the logging-messages, which gives us nice
@@ -88,6 +113,19 @@ impl GenericLogger {
         let mut line = ArrayString::<5>::new();
         write!(&mut line, "{}", record.line().unwrap_or(0)).unwrap();
 
+        // the heap might not be initialized yet
+        if let Some(timestamp) = log_timestamp::now() {
+            let mut buf = ArrayString::<32>::new();
+            write!(&mut buf, "{}", timestamp).unwrap();
+            let _ = write!(
+                writer,
+                "{}",
+                AnsiStyle::new()
+                    .msg(buf.as_str())
+                    .text_style(TextStyle::Dimmed)
+            );
+        }
+
         let res = writeln!(
             writer,
             "[{level:>5}] {crate_name}:{file:>15}{at_sign}{line}{double_point} {msg}",
@@ -109,6 +147,31 @@ impl GenericLogger {
         if res.is_err() {}
     }
 
+    /// Builds a record of the binary log format in a stack-allocated array. See
+    /// [`log_format`].
+    fn encode_msg(writer: &mut StderrWriter, record: &Record) {
+        let mut text = ArrayString::<BINARY_LOG_MAX_MSG_LEN>::new();
+        let msg = match record.args().as_str().and_then(StrRef::from_static) {
+            Some(msg) => BinaryLogMsg::Static(msg),
+            None => {
+                // a too long message gets truncated
+                let _ = write!(&mut text, "{}", record.args());
+                BinaryLogMsg::Inline(text.as_str())
+            }
+        };
+        let binary_record = BinaryLogRecord {
+            timestamp: log_timestamp::now().map(Into::into),
+            level: record.level(),
+            target: StrRef::from_static_or_null(record.module_path_static()),
+            file: StrRef::from_static_or_null(record.file_static()),
+            line: record.line().unwrap_or(0),
+            msg,
+        };
+        let mut buf = ArrayVec::<u8, { BINARY_LOG_MAX_HEADER_LEN + BINARY_LOG_MAX_MSG_LEN }>::new();
+        binary_record.encode(&mut buf);
+        writer.write_bytes(&buf);
+    }
+
     /// Gets the style for "DEBUG", "ERROR" etc.
     fn style_for_level<'a>(level: Level) -> AnsiStyle<'a> {
         match level {
@@ -126,17 +189,40 @@ impl GenericLogger {
 }
 
 impl Log for GenericLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        // log everything
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        log_buffer::is_enabled(
+            ROOTTASK_PROCESS_PID,
+            metadata.target(),
+            metadata.level().into(),
+        )
     }
 
     fn log(&self, record: &Record) {
+        let level = LogLevel::from(record.level());
+        if !log_buffer::is_enabled(ROOTTASK_PROCESS_PID, record.target(), level) {
+            return;
+        }
+        let mut msg = ArrayString::<LOG_MAX_MSG_LEN>::new();
+        // a too long message gets truncated
+        let _ = write!(&mut msg, "{}", record.args());
+        log_buffer::record(
+            ROOTTASK_PROCESS_PID,
+            level,
+            record.target(),
+            String::from(msg.as_str()),
+        );
+        if !log_buffer::is_console_enabled() {
+            return;
+        }
+
         // this is synchronized, because this may be invoked by multiple portals
         // (which are called from other PDs/global ECs).
         self.lock.lock().execute_while_locked(|| {
             let mut writer = crate::services::stderr::writer_mut();
-            Self::fmt_msg(&mut writer, record)
+            match log_format::get() {
+                LogFormat::Text => Self::fmt_msg(&mut writer, record),
+                LogFormat::Binary => Self::encode_msg(&mut writer, record),
+            }
         });
     }
 
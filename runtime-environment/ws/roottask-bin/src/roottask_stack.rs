@@ -1,6 +1,7 @@
 //! Initial global stack for the roottask. Referenced in `assembly.S`.
 //! See [`ROOTTASK_STACK`]
 
+use alloc::string::String;
 use libhrstd::libhedron::mem::PAGE_SIZE;
 use libhrstd::libhedron::HIP;
 use libhrstd::sync::static_global_ptr::StaticGlobalPtr;
@@ -31,7 +32,7 @@ static ROOTTASK_STACK_TOP_PTR: StaticGlobalPtr<u8> =
 /// Marks the guard-page of the corresponding [`StaticStack`] as not
 /// read- and writeable, i.e. not present. Performs a syscall for that.
 pub fn init(hip: &HIP) {
-    unsafe { ROOTTASK_STACK.activate_guard_page(hip.root_pd()) }
+    unsafe { ROOTTASK_STACK.activate_guard_page(hip.root_pd(), String::from("main")) }
     log::debug!(
         "guard page for root task stack is active! Stackoverflow will result in PF exception now."
     );
@@ -0,0 +1,23 @@
+//! Shared parts of the CPU hog and the latency probe.
+
+use libhrstd::rt::services::scheduling::{
+    scheduling_service,
+    SchedulingParams,
+    SchedulingServiceRequest,
+};
+
+/// The probe writes the quantum in microseconds (decimal) that both programs should use
+/// into this file. The hog polls it. The probe is not the parent of the hog; hence, it
+/// can't adjust the SC of the hog directly.
+pub const QUANTUM_CONTROL_FILE: &str = "/tmp/schedbench-quantum";
+
+/// Adjusts the time quantum of the SC of the calling process. Keeps the default priority,
+/// so that the hog and the probe get scheduled round-robin.
+pub fn set_own_quantum(quantum_us: u64) {
+    let params = SchedulingParams {
+        quantum_us,
+        ..SchedulingParams::DEFAULT
+    };
+    scheduling_service(SchedulingServiceRequest::Set { pid: None, params })
+        .expect("must be able to adjust own scheduling params");
+}
@@ -0,0 +1,79 @@
+//! CPU hog for the scheduling benchmark. Never blocks; it only yields the CPU when Hedron
+//! preempts it at the end of its time quantum. Follows the quantum that the probe requests
+//! in [`common::QUANTUM_CONTROL_FILE`].
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use alloc::string::String;
+use libhrstd::fs::File;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::scheduling::SchedulingParams;
+use libhrstd::rt::user_logger::UserRustLogger;
+
+mod common;
+mod panic;
+
+/// Busy iterations between two checks of the control file. Short compared to the
+/// smallest quantum, so that a new quantum gets applied within the next time slice.
+const SPIN_ITERATIONS: u64 = 100_000;
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+    log::info!(
+        "sched hog started; follows the quantum in {}",
+        common::QUANTUM_CONTROL_FILE
+    );
+
+    let mut control = File::open(
+        common::QUANTUM_CONTROL_FILE,
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+        0o644,
+    )
+    .expect("must open the quantum control file");
+    let mut quantum_us = SchedulingParams::DEFAULT.quantum_us;
+    loop {
+        for _ in 0..SPIN_ITERATIONS {
+            core::hint::spin_loop();
+        }
+
+        let _ = control.lseek(0);
+        let requested_quantum_us = control
+            .read_to_vec()
+            .ok()
+            .and_then(|content| String::from_utf8(content).ok())
+            .and_then(|content| content.trim().parse::<u64>().ok())
+            .filter(|requested| *requested > 0 && *requested != quantum_us);
+        if let Some(requested_quantum_us) = requested_quantum_us {
+            common::set_own_quantum(requested_quantum_us);
+            quantum_us = requested_quantum_us;
+            log::debug!("sched hog runs with quantum={}µs", quantum_us);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Latency probe for the scheduling benchmark. Runs next to the CPU hog at the same
+//! priority. The probe reads the TSC in a tight loop; each gap between two reads that is
+//! larger than [`PREEMPTION_THRESHOLD_TICKS`] is a period in which the probe was preempted,
+//! i.e. how long it had to wait for the CPU. The distribution of these gaps is captured
+//! for several time quanta and stored as bench report in the file system.
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use alloc::vec::Vec;
+use libhrstd::fs::File;
+use libhrstd::rt::services::fs::FsOpenFlags;
+use libhrstd::rt::services::scheduling::SchedulingParams;
+use libhrstd::rt::user_logger::UserRustLogger;
+use libhrstd::time::{
+    tsc_freq_khz,
+    Instant,
+};
+use libhrstd::util::bench_report::BenchReport;
+use libhrstd::util::percentile;
+
+mod common;
+mod panic;
+
+/// Time quanta in microseconds that get measured.
+const QUANTA_US: [u64; 5] = [1_000, 2_000, 5_000, 10_000, 20_000];
+
+/// Number of preemptions that get recorded per quantum.
+const SAMPLES: usize = 100;
+
+/// Preemptions that get discarded after a quantum change, because the hog picks up the
+/// new quantum with a delay.
+const WARMUP_SAMPLES: usize = 5;
+
+/// Gaps between two TSC reads that are shorter are caused by interrupts and not by
+/// preemptions. Roughly 10µs on a 2 GHz CPU.
+const PREEMPTION_THRESHOLD_TICKS: u64 = 20_000;
+
+/// Upper bound for the measurement of a single quantum. Prevents that the probe spins
+/// forever if the hog doesn't run.
+const MAX_MEASUREMENT_TICKS: u64 = 100_000_000_000;
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+    log::info!("sched probe started");
+
+    let mut control = File::open(
+        common::QUANTUM_CONTROL_FILE,
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR,
+        0o644,
+    )
+    .expect("must open the quantum control file");
+    let mut report = BenchReport::new(
+        "sched_probe",
+        &libhrstd::build_info!().git_hash,
+        Instant::now().val(),
+    );
+    report.with_tsc_freq_khz(tsc_freq_khz());
+
+    for quantum_us in QUANTA_US {
+        control.lseek(0).unwrap();
+        control
+            .write_all(format!("{}", quantum_us).as_bytes())
+            .unwrap();
+        common::set_own_quantum(quantum_us);
+
+        let mut gaps = measure_preemptions(WARMUP_SAMPLES + SAMPLES);
+        if gaps.len() <= WARMUP_SAMPLES {
+            log::warn!(
+                "quantum={}µs: only {} preemptions; is the sched hog running?",
+                quantum_us,
+                gaps.len()
+            );
+            continue;
+        }
+        let mut gaps = gaps.split_off(WARMUP_SAMPLES);
+        gaps.sort_unstable();
+
+        log::info!(
+            "quantum={:>6}µs: {} preemptions, latency [ticks] min={} p50={} p90={} p99={} max={}",
+            quantum_us,
+            gaps.len(),
+            gaps[0],
+            percentile(&gaps, 50),
+            percentile(&gaps, 90),
+            percentile(&gaps, 99),
+            gaps[gaps.len() - 1]
+        );
+        for (name, value) in [
+            ("min", gaps[0]),
+            ("p50", percentile(&gaps, 50)),
+            ("p90", percentile(&gaps, 90)),
+            ("p99", percentile(&gaps, 99)),
+            ("max", gaps[gaps.len() - 1]),
+        ] {
+            report.add(
+                &format!("sched latency [quantum={}us] {}", quantum_us, name),
+                value,
+            );
+        }
+    }
+
+    // back to normal
+    common::set_own_quantum(SchedulingParams::DEFAULT.quantum_us);
+    control.lseek(0).unwrap();
+    control
+        .write_all(format!("{}", SchedulingParams::DEFAULT.quantum_us).as_bytes())
+        .unwrap();
+    control.close().unwrap();
+
+    let mut file = File::open(
+        &report.path(),
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_WRONLY,
+        0o644,
+    )
+    .expect("must create the bench report");
+    file.write_all(report.to_json().as_bytes()).unwrap();
+    file.close().unwrap();
+    log::info!("bench results written to {}", report.path());
+
+    loop {}
+}
+
+/// Spins until the given number of preemptions were observed or the time is up. Returns
+/// the duration of each preemption in ticks.
+fn measure_preemptions(count: usize) -> Vec<u64> {
+    let mut gaps = Vec::with_capacity(count);
+    let begin = Instant::now().val();
+    let mut last = begin;
+    while gaps.len() < count && last - begin < MAX_MEASUREMENT_TICKS {
+        let now = Instant::now().val();
+        if now - last > PREEMPTION_THRESHOLD_TICKS {
+            gaps.push(now - last);
+        }
+        last = now;
+    }
+    gaps
+}
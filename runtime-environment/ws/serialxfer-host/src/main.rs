@@ -0,0 +1,159 @@
+//! Host tool that transfers files over the serial port from and to the runtime environment.
+//! It is the other side of the `send` and `recv` commands of the shell. See
+//! [`libhrstd::util::serial_transfer`] for the protocol.
+//!
+//! Usage:
+//! - `serialxfer-host send <file> [<serial device>]` sends a file; run `recv FILE` in the
+//!   shell first.
+//! - `serialxfer-host recv <dir> [<serial device>]` stores a file in `dir`; run
+//!   `send FILE` in the shell first.
+//!
+//! Without a serial device, the tool talks via STDIN and STDOUT, which suits the external
+//! transfer commands of terminal programs, e.g. `picocom --send-cmd "serialxfer-host send"`.
+//! The tool doesn't configure the serial device; put it into raw mode first, e.g. with
+//! `stty -F /dev/ttyUSB0 115200 raw -echo`.
+
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+
+use libhrstd::util::serial_transfer::{
+    recv_file,
+    send_file,
+    SerialLink,
+    BYTE_TIMEOUT_MS,
+};
+use std::fs::OpenOptions;
+use std::io::{
+    Read,
+    Write,
+};
+use std::path::Path;
+use std::process::exit;
+use std::sync::mpsc::{
+    channel,
+    Receiver,
+};
+use std::time::Duration;
+
+/// Largest file that the tool receives.
+const MAX_FILE_SIZE: usize = 1024 * 1024 * 1024;
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let (command, path, device) = match args.as_slice() {
+        [_, command, path] => (command.as_str(), path, None),
+        [_, command, path, device] => (command.as_str(), path, Some(device)),
+        _ => usage(&args[0]),
+    };
+    let mut link = match device {
+        Some(device) => {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(device)
+                .unwrap_or_else(|e| {
+                    eprintln!("can't open {}: {}", device, e);
+                    exit(1);
+                });
+            let reader = file.try_clone().unwrap();
+            HostLink::new(reader, Box::new(file))
+        }
+        None => HostLink::new(std::io::stdin(), Box::new(std::io::stdout())),
+    };
+
+    match command {
+        "send" => {
+            let data = std::fs::read(path).unwrap_or_else(|e| {
+                eprintln!("can't read {}: {}", path, e);
+                exit(1);
+            });
+            let name = Path::new(path).file_name().unwrap().to_string_lossy();
+            eprintln!("sending {} ({} bytes)", path, data.len());
+            if let Err(e) = send_file(&mut link, &name, &data) {
+                eprintln!("transfer failed: {:?}", e);
+                exit(1);
+            }
+            eprintln!("done");
+        }
+        "recv" => {
+            eprintln!("waiting for a file");
+            let file = recv_file(&mut link, MAX_FILE_SIZE).unwrap_or_else(|e| {
+                eprintln!("transfer failed: {:?}", e);
+                exit(1);
+            });
+            // never write outside of the directory
+            let name = Path::new(&file.name)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("received"));
+            let dest = Path::new(path).join(name);
+            std::fs::write(&dest, &file.data).unwrap_or_else(|e| {
+                eprintln!("can't write {}: {}", dest.display(), e);
+                exit(1);
+            });
+            eprintln!("received {} ({} bytes)", dest.display(), file.data.len());
+        }
+        _ => usage(&args[0]),
+    }
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!(
+        "usage: {} send <file> [<serial device>] | recv <dir> [<serial device>]",
+        program
+    );
+    exit(1);
+}
+
+/// Serial link over a reader and a writer. A thread reads the bytes, so that reads can
+/// time out.
+struct HostLink {
+    bytes: Receiver<u8>,
+    writer: Box<dyn Write>,
+}
+
+impl HostLink {
+    fn new(mut reader: impl Read + Send + 'static, writer: Box<dyn Write>) -> Self {
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let mut buf = [0; 1024];
+            loop {
+                let count = match reader.read(&mut buf) {
+                    Ok(count) if count > 0 => count,
+                    _ => break,
+                };
+                if buf[..count].iter().any(|byte| tx.send(*byte).is_err()) {
+                    break;
+                }
+            }
+        });
+        Self { bytes: rx, writer }
+    }
+}
+
+impl SerialLink for HostLink {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.writer.write_all(bytes).unwrap();
+        self.writer.flush().unwrap();
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        self.bytes
+            .recv_timeout(Duration::from_millis(BYTE_TIMEOUT_MS))
+            .ok()
+    }
+}
@@ -0,0 +1,352 @@
+//! Built-in commands of the shell. They run inside the shell process; only programs get
+//! launched via the process service.
+
+use crate::cmdline::{
+    resolve_path,
+    Command,
+};
+use crate::{
+    print,
+    print_err,
+    selfcheck,
+    Shell,
+    PROGRAM_DIR,
+};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    fs_service_close,
+    fs_service_list_dir,
+    fs_service_open,
+    fs_service_read,
+    FsCloseRequest,
+    FsListDirRequest,
+    FsOpenFlags,
+    FsOpenRequest,
+    FsReadRequest,
+};
+use libhrstd::rt::services::process::{
+    process_service_exit,
+    process_service_reload,
+    process_service_set_syscall_trace,
+};
+use libhrstd::rt::services::serial_transfer::{
+    serial_transfer_recv,
+    serial_transfer_send,
+};
+
+/// Number of bytes that `cat` reads per call of the file system service.
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// A command that the shell executes itself.
+#[derive(Debug)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// Synopsis and description for `help`.
+    pub usage: &'static str,
+    /// Gets the arguments without the name of the command.
+    pub run: fn(&mut Shell, &[String]),
+}
+
+const BUILTINS: [Builtin; 15] = [
+    Builtin {
+        name: "cat",
+        usage: "cat FILE...      prints the content of files",
+        run: cat,
+    },
+    Builtin {
+        name: "cd",
+        usage: "cd [DIR]         changes the working directory (default: /)",
+        run: cd,
+    },
+    Builtin {
+        name: "echo",
+        usage: "echo [ARG...]    prints the arguments",
+        run: echo,
+    },
+    Builtin {
+        name: "exit",
+        usage: "exit [STATUS]    terminates the shell",
+        run: exit,
+    },
+    Builtin {
+        name: "gdb",
+        usage: "gdb PROG [ARG...] starts a program that waits for GDB on the serial port",
+        run: gdb,
+    },
+    Builtin {
+        name: "help",
+        usage: "help             prints this help",
+        run: help,
+    },
+    Builtin {
+        name: "jobs",
+        usage: "jobs             lists the programs that run in the background",
+        run: jobs,
+    },
+    Builtin {
+        name: "ls",
+        usage: "ls [DIR]         lists the content of a directory",
+        run: ls,
+    },
+    Builtin {
+        name: "pwd",
+        usage: "pwd              prints the working directory",
+        run: pwd,
+    },
+    Builtin {
+        name: "recv",
+        usage: "recv FILE        receives a file from the host over the serial port",
+        run: recv,
+    },
+    Builtin {
+        name: "reload",
+        usage: "reload PROG FILE replaces a program with the ELF file until the next reboot",
+        run: reload,
+    },
+    Builtin {
+        name: "selfcheck",
+        usage: "selfcheck        checks the health of the services of the roottask",
+        run: selfcheck,
+    },
+    Builtin {
+        name: "send",
+        usage: "send FILE        sends a file to the host over the serial port",
+        run: send,
+    },
+    Builtin {
+        name: "strace",
+        usage: "strace PROG [ARG...] runs a program and prints its syscalls; \
+                strace -p PID [off] records the syscalls of a running program",
+        run: strace,
+    },
+    Builtin {
+        name: "wait",
+        usage: "wait [PID...]    waits for background programs (default: all)",
+        run: wait,
+    },
+];
+
+/// Returns the built-in command with the given name.
+pub fn find(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+fn cat(shell: &mut Shell, args: &[String]) {
+    for arg in args {
+        let path = resolve_path(&shell.cwd, arg);
+        match read_file(&path) {
+            Some(data) => print(String::from_utf8_lossy(&data).trim_end_matches('\n')),
+            None => print_err(&format!("cat: {}: no such file", path)),
+        }
+    }
+}
+
+fn cd(shell: &mut Shell, args: &[String]) {
+    let dir = resolve_path(&shell.cwd, args.first().map_or("/", |dir| dir.as_str()));
+    // directories only exist implicitly as prefix of the paths of files
+    if dir != "/" && fs_service_list_dir(FsListDirRequest::new(dir.clone())).is_empty() {
+        print_err(&format!("cd: {}: no such directory", dir));
+    } else {
+        shell.cwd = dir;
+    }
+}
+
+fn echo(_shell: &mut Shell, args: &[String]) {
+    print(&args.join(" "));
+}
+
+fn exit(_shell: &mut Shell, args: &[String]) {
+    let status = args
+        .first()
+        .and_then(|status| status.parse().ok())
+        .unwrap_or(0);
+    process_service_exit(status);
+}
+
+fn gdb(shell: &mut Shell, args: &[String]) {
+    if args.is_empty() {
+        return print_err("usage: gdb PROG [ARG...]");
+    }
+    print("connect GDB on the host, e.g. `target remote /dev/ttyUSB0`");
+    let command = Command {
+        argv: args.to_vec(),
+        envp: Vec::new(),
+        background: false,
+        preopened: Vec::new(),
+    };
+    shell.launch(command, true, false);
+}
+
+fn help(_shell: &mut Shell, _args: &[String]) {
+    let mut text = String::from("built-in commands:");
+    for builtin in &BUILTINS {
+        text.push_str("\n  ");
+        text.push_str(builtin.usage);
+    }
+    text.push_str(&format!(
+        "\nother commands start programs; names without a slash are looked up in {}\n\
+         'a; b' runs a and b one after another, 'a & b' runs a in the background\n\
+         '3<file' and '4>file' give a program files at fixed file descriptors",
+        PROGRAM_DIR
+    ));
+    print(&text);
+}
+
+fn jobs(shell: &mut Shell, _args: &[String]) {
+    for pid in &shell.jobs {
+        print(&format!("[{}] running", pid));
+    }
+}
+
+fn ls(shell: &mut Shell, args: &[String]) {
+    let dir = resolve_path(&shell.cwd, args.first().map_or(".", |dir| dir.as_str()));
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+    // the file system lists all files below the directory; only show the direct entries
+    let entries = fs_service_list_dir(FsListDirRequest::new(dir.clone()))
+        .iter()
+        .filter_map(|path| path.strip_prefix(&prefix))
+        .map(|relative| match relative.split_once('/') {
+            Some((subdir, _)) => format!("{}/", subdir),
+            None => String::from(relative),
+        })
+        .collect::<BTreeSet<_>>();
+    if !entries.is_empty() {
+        print(&entries.into_iter().collect::<Vec<_>>().join("\n"));
+    } else if dir != "/" {
+        print_err(&format!("ls: {}: no such directory", dir));
+    }
+}
+
+fn pwd(shell: &mut Shell, _args: &[String]) {
+    print(&shell.cwd);
+}
+
+fn recv(shell: &mut Shell, args: &[String]) {
+    let file = match args {
+        [file] => resolve_path(&shell.cwd, file),
+        _ => return print_err("usage: recv FILE"),
+    };
+    print("start `serialxfer-host send FILE` on the host");
+    match serial_transfer_recv(&file) {
+        Ok(size) => print(&format!("received {} ({} bytes)", file, size)),
+        Err(e) => print_err(&format!("recv: {}: {:?}", file, e)),
+    }
+}
+
+fn reload(shell: &mut Shell, args: &[String]) {
+    let (program, file) = match args {
+        [program, file] => (program, file),
+        _ => return print_err("usage: reload PROG FILE"),
+    };
+    // like the programs that the shell launches
+    let path = if program.contains('/') {
+        resolve_path(&shell.cwd, program)
+    } else {
+        format!("{}/{}", PROGRAM_DIR, program)
+    };
+    let file = resolve_path(&shell.cwd, file);
+    match process_service_reload(&path, &file) {
+        Ok(()) => print(&format!("{} reloaded from {}", path, file)),
+        Err(e) => print_err(&format!("reload: {}: {:?}", path, e)),
+    }
+}
+
+fn selfcheck(_shell: &mut Shell, _args: &[String]) {
+    selfcheck::run();
+}
+
+fn send(shell: &mut Shell, args: &[String]) {
+    let file = match args {
+        [file] => resolve_path(&shell.cwd, file),
+        _ => return print_err("usage: send FILE"),
+    };
+    print("start `serialxfer-host recv` on the host");
+    match serial_transfer_send(&file) {
+        Ok(size) => print(&format!("sent {} ({} bytes)", file, size)),
+        Err(e) => print_err(&format!("send: {}: {:?}", file, e)),
+    }
+}
+
+fn strace(shell: &mut Shell, args: &[String]) {
+    match args {
+        [] => print_err("usage: strace PROG [ARG...] or strace -p PID [off]"),
+        [flag, rest @ ..] if flag == "-p" => {
+            let (pid, enabled) = match rest {
+                [pid] => (pid, true),
+                [pid, off] if off == "off" => (pid, false),
+                _ => return print_err("usage: strace -p PID [off]"),
+            };
+            let pid = match pid.parse::<ProcessId>() {
+                Ok(pid) => pid,
+                Err(_) => return print_err(&format!("strace: {}: invalid PID", pid)),
+            };
+            match process_service_set_syscall_trace(pid, enabled) {
+                Ok(()) if enabled => print(&format!("see /proc/{}/trace", pid)),
+                Ok(()) => {}
+                Err(e) => print_err(&format!("strace: {}: {:?}", pid, e)),
+            }
+        }
+        _ => {
+            let command = Command {
+                argv: args.to_vec(),
+                envp: Vec::new(),
+                background: false,
+                preopened: Vec::new(),
+            };
+            if let Some(pid) = shell.launch(command, false, true) {
+                let path = format!("/proc/{}/trace", pid);
+                match read_file(&path) {
+                    Some(trace) => print(String::from_utf8_lossy(&trace).trim_end_matches('\n')),
+                    None => print_err(&format!("strace: {}: no such file", path)),
+                }
+            }
+        }
+    }
+}
+
+fn wait(shell: &mut Shell, args: &[String]) {
+    let pids = if args.is_empty() {
+        core::mem::take(&mut shell.jobs)
+    } else {
+        let mut pids = Vec::new();
+        for arg in args {
+            match arg.parse::<ProcessId>() {
+                Ok(pid) => pids.push(pid),
+                Err(_) => return print_err(&format!("wait: {}: invalid PID", arg)),
+            }
+        }
+        shell.jobs.retain(|job| !pids.contains(job));
+        pids
+    };
+    let mut remaining = pids.into_iter();
+    for pid in remaining.by_ref() {
+        if !shell.wait(pid) {
+            break;
+        }
+    }
+    // if interrupted, the remaining programs stay in the background
+    shell.jobs.extend(remaining);
+}
+
+/// Reads the whole file. Returns `None` if it can't be opened.
+fn read_file(path: &str) -> Option<Vec<u8>> {
+    let fd = fs_service_open(FsOpenRequest::new(
+        String::from(path),
+        FsOpenFlags::O_RDONLY,
+        0,
+    ))
+    .ok()?;
+    let mut data = Vec::new();
+    let mut buf = vec![0; READ_CHUNK_SIZE];
+    loop {
+        match fs_service_read(FsReadRequest::new(fd, buf.as_mut_ptr() as usize, buf.len())) {
+            Ok(0) | Err(_) => break,
+            Ok(read_bytes) => data.extend_from_slice(&buf[..read_bytes]),
+        }
+    }
+    let _ = fs_service_close(FsCloseRequest::new(fd));
+    Some(data)
+}
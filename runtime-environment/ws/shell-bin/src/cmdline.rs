@@ -0,0 +1,113 @@
+//! Parsing of command lines. The syntax is a small subset of a POSIX shell:
+//!
+//! - `;` separates commands that run one after another
+//! - a trailing `&` runs a command in the background
+//! - leading words in the form `KEY=VALUE` are environment variables of the command
+//! - `N<path`, `N>path`, and `N>>path` with `N >= 3` open `path` for reading, writing,
+//!   or appending before the program starts; the program finds it at file descriptor `N`
+//!
+//! Words are separated by whitespace. There is no quoting, hence arguments can't contain
+//! spaces.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::rt::services::fs::{
+    FsOpenFlags,
+    FD,
+};
+use libhrstd::rt::services::process::PreopenedFile;
+
+/// A single command of a command line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Command {
+    /// Arguments; the first one is the program or the built-in command. Never empty.
+    pub argv: Vec<String>,
+    /// Environment variables in the form `KEY=VALUE`.
+    pub envp: Vec<String>,
+    /// The shell doesn't wait for the command to finish.
+    pub background: bool,
+    /// Files that the program gets at fixed file descriptors. The paths are not resolved
+    /// yet.
+    pub preopened: Vec<PreopenedFile>,
+}
+
+/// Splits a command line into its commands. Skips empty commands.
+pub fn parse_line(line: &str) -> Vec<Command> {
+    line.split(';')
+        .flat_map(|part| {
+            // "a & b" runs "a" in the background and "b" in the foreground
+            let mut jobs = part.split('&').collect::<Vec<_>>();
+            let foreground = jobs.pop().map(|cmd| (cmd, false));
+            jobs.into_iter()
+                .map(|cmd| (cmd, true))
+                .chain(foreground)
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|(cmd, background)| parse_command(cmd, background))
+        .collect()
+}
+
+fn parse_command(cmd: &str, background: bool) -> Option<Command> {
+    let mut words = cmd.split_whitespace().peekable();
+    let mut envp = Vec::new();
+    while let Some(var) = words.next_if(|word| word.contains('=')) {
+        envp.push(String::from(var));
+    }
+    let mut argv = Vec::new();
+    let mut preopened = Vec::new();
+    for word in words {
+        match parse_redirect(word) {
+            Some(file) => preopened.push(file),
+            None => argv.push(String::from(word)),
+        }
+    }
+    (!argv.is_empty()).then(|| Command {
+        argv,
+        envp,
+        background,
+        preopened,
+    })
+}
+
+/// Parses a word in the form `N<path`, `N>path`, or `N>>path`.
+fn parse_redirect(word: &str) -> Option<PreopenedFile> {
+    let digits = word.find(|c: char| !c.is_ascii_digit())?;
+    let fd = word[..digits].parse::<i32>().ok()?;
+    let rest = &word[digits..];
+    let (flags, path) = if let Some(path) = rest.strip_prefix(">>") {
+        (
+            FsOpenFlags::O_WRONLY | FsOpenFlags::O_CREAT | FsOpenFlags::O_APPEND,
+            path,
+        )
+    } else if let Some(path) = rest.strip_prefix('>') {
+        (
+            FsOpenFlags::O_WRONLY | FsOpenFlags::O_CREAT | FsOpenFlags::O_TRUNC,
+            path,
+        )
+    } else {
+        (FsOpenFlags::O_RDONLY, rest.strip_prefix('<')?)
+    };
+    (!path.is_empty()).then(|| PreopenedFile {
+        fd: FD::new(fd),
+        path: String::from(path),
+        flags,
+    })
+}
+
+/// Resolves `path` relative to the working directory `cwd` and removes `.` and `..`
+/// components. `cwd` must be absolute. The result never ends with a slash, except for the
+/// root directory.
+pub fn resolve_path(cwd: &str, path: &str) -> String {
+    let mut components = Vec::new();
+    let relative_to = if path.starts_with('/') { "" } else { cwd };
+    for component in relative_to.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    format!("/{}", components.join("/"))
+}
@@ -0,0 +1,306 @@
+//! Interactive shell for the console. Reads command lines from the stdin service, runs the
+//! built-in commands (see [`builtins`]) against the file system, and launches all other
+//! commands as programs via the process service. See [`cmdline`] for the syntax.
+//!
+//! If the shell gets arguments, it runs them as a single command line and exits instead,
+//! e.g. `native-shell-bin selfcheck`.
+//!
+//! Each write to STDOUT becomes a separate line, hence the prompt stands on its own line.
+//! The roottask echoes the input. Ctrl+C discards the current line or stops waiting for a
+//! program; the program then continues in the background.
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+// I see a benefit here: Even tho it might not be usable from the outside world,
+// it may contain useful information about how the implementation works.
+#![allow(rustdoc::private_intra_doc_links)]
+#![allow(rustdoc::missing_doc_code_examples)]
+#![feature(alloc_error_handler)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate alloc;
+
+use crate::cmdline::{
+    parse_line,
+    resolve_path,
+    Command,
+};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libhrstd::fs::access;
+use libhrstd::process::consts::ProcessId;
+use libhrstd::rt::services::fs::{
+    FsAccessMode,
+    FsError,
+};
+use libhrstd::rt::services::process::{
+    process_service,
+    process_service_exit,
+    process_service_status,
+    PreopenedFile,
+    ProcessServiceRequest,
+    ProcessStatus,
+};
+use libhrstd::rt::services::stderr::stderr_service;
+use libhrstd::rt::services::stdin::{
+    stdin_service,
+    STDIN_MAX_READ,
+};
+use libhrstd::rt::services::stdout::stdout_service;
+use libhrstd::rt::services::timer::{
+    timer_service_create_periodic,
+    timer_service_wait,
+    TimerId,
+};
+use libhrstd::rt::user_logger::UserRustLogger;
+
+mod builtins;
+mod cmdline;
+mod panic;
+mod selfcheck;
+
+/// Directory in which the shell looks up programs whose name contains no slash. The
+/// roottask mounts the userland tarball there.
+pub const PROGRAM_DIR: &str = "/bin";
+
+/// Interval in which the shell polls for input and for the state of programs.
+const POLL_INTERVAL_NS: u64 = 20_000_000;
+
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x08;
+/// Most terminals send this for the backspace key.
+const DELETE: u8 = 0x7f;
+
+#[no_mangle]
+fn start() {
+    UserRustLogger::init();
+    let mut shell = Shell::new();
+    let args = libhrstd::rt::env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        for command in parse_line(&args.join(" ")) {
+            shell.run(command);
+        }
+        process_service_exit(0);
+    }
+    print("hrsh: type 'help' to list the built-in commands");
+    loop {
+        shell.report_finished_jobs();
+        print(&format!("{} $", shell.cwd));
+        let line = shell.read_line();
+        for command in parse_line(&line) {
+            shell.run(command);
+        }
+    }
+}
+
+/// Writes a message to STDOUT.
+pub fn print(msg: &str) {
+    stdout_service(msg);
+}
+
+/// Writes an error message to STDERR.
+pub fn print_err(msg: &str) {
+    stderr_service(msg);
+}
+
+/// State of the shell.
+#[derive(Debug)]
+pub struct Shell {
+    /// Absolute path of the working directory. Relative paths of built-in commands and
+    /// programs are relative to it.
+    pub cwd: String,
+    /// Programs that run in the background.
+    pub jobs: Vec<ProcessId>,
+    /// Input that was received but not processed yet.
+    input: VecDeque<u8>,
+    /// The last processed input was a carriage return. Terminals send `\r\n` or `\r` for
+    /// the enter key; this prevents an empty line in the first case.
+    after_cr: bool,
+    /// Periodic timer that paces the polling.
+    poll_timer: TimerId,
+}
+
+impl Shell {
+    fn new() -> Self {
+        Self {
+            cwd: String::from("/"),
+            jobs: Vec::new(),
+            input: VecDeque::new(),
+            after_cr: false,
+            poll_timer: timer_service_create_periodic(POLL_INTERVAL_NS)
+                .expect("the shell needs a timer"),
+        }
+    }
+
+    /// Blocks until a line of input is complete. Ctrl+C discards the line and returns an
+    /// empty line.
+    fn read_line(&mut self) -> String {
+        let mut line = Vec::new();
+        loop {
+            while let Some(byte) = self.input.pop_front() {
+                let after_cr = core::mem::replace(&mut self.after_cr, byte == b'\r');
+                match byte {
+                    b'\n' if after_cr => {}
+                    b'\r' | b'\n' => return String::from_utf8_lossy(&line).into_owned(),
+                    CTRL_C => {
+                        print("^C");
+                        return String::new();
+                    }
+                    BACKSPACE | DELETE => {
+                        // removes a whole UTF-8 code point
+                        while let Some(byte) = line.pop() {
+                            if byte & 0xc0 != 0x80 {
+                                break;
+                            }
+                        }
+                    }
+                    byte if byte.is_ascii_control() => {}
+                    byte => line.push(byte),
+                }
+            }
+            self.poll_input();
+        }
+    }
+
+    /// Waits for the next poll interval and fetches the input that arrived meanwhile.
+    fn poll_input(&mut self) {
+        timer_service_wait(self.poll_timer);
+        self.input.extend(stdin_service(STDIN_MAX_READ, true));
+    }
+
+    /// Runs a built-in command or launches a program.
+    fn run(&mut self, command: Command) {
+        if let Some(builtin) = builtins::find(&command.argv[0]) {
+            if command.background {
+                print_err(&format!(
+                    "{}: built-in commands can't run in the background",
+                    builtin.name
+                ));
+            } else if !command.preopened.is_empty() {
+                print_err(&format!(
+                    "{}: built-in commands don't support redirections",
+                    builtin.name
+                ));
+            } else {
+                (builtin.run)(self, &command.argv[1..]);
+            }
+            return;
+        }
+        self.launch(command, false, false);
+    }
+
+    /// Launches the program of the command and waits for it unless it runs in the
+    /// background. With `debug`, the program waits for GDB on the serial port. With
+    /// `trace`, its syscalls are recorded in `/proc/<pid>/trace`. Returns the PID.
+    pub fn launch(&mut self, command: Command, debug: bool, trace: bool) -> Option<ProcessId> {
+        let path = if command.argv[0].contains('/') {
+            resolve_path(&self.cwd, &command.argv[0])
+        } else {
+            format!("{}/{}", PROGRAM_DIR, command.argv[0])
+        };
+        let error = match access(&path, FsAccessMode::X_OK) {
+            Ok(()) => None,
+            Err(FsError::NotFound) => Some(format!("{}: not found", path)),
+            Err(FsError::Perm) => Some(format!("{}: permission denied", path)),
+            Err(e) => Some(format!("{}: {:?}", path, e)),
+        };
+        if let Some(error) = error {
+            print_err(&error);
+            return None;
+        }
+        let preopened = command
+            .preopened
+            .into_iter()
+            .map(|file| PreopenedFile {
+                path: resolve_path(&self.cwd, &file.path),
+                ..file
+            })
+            .collect();
+        let request = ProcessServiceRequest::Launch {
+            path: path.clone(),
+            argv: command.argv,
+            envp: command.envp,
+            sched_params: None,
+            cpu: None,
+            preopened,
+            aslr: false,
+            debug,
+            trace,
+        };
+        match process_service(request) {
+            Ok(pid) if command.background => {
+                print(&format!("[{}] {}", pid, path));
+                self.jobs.push(pid);
+                Some(pid)
+            }
+            Ok(pid) => {
+                self.wait(pid);
+                Some(pid)
+            }
+            Err(e) => {
+                print_err(&format!("{}: can't launch: {:?}", path, e));
+                None
+            }
+        }
+    }
+
+    /// Blocks until the process exits and reports a non-zero exit status. Returns `false`
+    /// if Ctrl+C interrupted the wait; the process becomes a background job then.
+    pub fn wait(&mut self, pid: ProcessId) -> bool {
+        loop {
+            match process_service_status(pid) {
+                Ok(ProcessStatus::Running) => {}
+                Ok(ProcessStatus::Exited(0)) => return true,
+                Ok(ProcessStatus::Exited(status)) => {
+                    print(&format!("[{}] exited with status {}", pid, status));
+                    return true;
+                }
+                Err(e) => {
+                    print_err(&format!("[{}] can't wait: {:?}", pid, e));
+                    return true;
+                }
+            }
+            if let Some(pos) = self.input.iter().position(|byte| *byte == CTRL_C) {
+                // the interrupt is consumed; input after it stays for the next line
+                self.input.drain(..=pos);
+                print(&format!("[{}] continues in the background", pid));
+                self.jobs.push(pid);
+                return false;
+            }
+            self.poll_input();
+        }
+    }
+
+    /// Reports and forgets the background programs that exited.
+    fn report_finished_jobs(&mut self) {
+        self.jobs.retain(|pid| match process_service_status(*pid) {
+            Ok(ProcessStatus::Running) => true,
+            Ok(ProcessStatus::Exited(status)) => {
+                print(&format!("[{}] done, exit status {}", pid, status));
+                false
+            }
+            Err(e) => {
+                print_err(&format!("[{}] lost: {:?}", pid, e));
+                false
+            }
+        });
+    }
+}
@@ -0,0 +1,517 @@
+//! Self-test of the services of the roottask, see the built-in command `selfcheck`. It
+//! sends a canary request to each service, verifies the response and the latency, and
+//! prints a short health report. Useful after porting the system to new hardware or to
+//! another revision of Hedron. The roottask runs it during boot if it gets the boot
+//! argument `selfcheck=on`.
+//!
+//! The latencies include the IPC round trips of a check. User apps don't know the
+//! frequency of the TSC, hence the self-test calibrates it against the monotonic clock of
+//! the system time service during the run. The thresholds are generous enough for QEMU
+//! without hardware acceleration.
+
+use crate::print;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use libhrstd::mem::UserPtrOrEmbedded;
+use libhrstd::process::consts::ROOTTASK_PROCESS_PID;
+use libhrstd::rt::services::allocate::{
+    alloc_service,
+    dealloc_service,
+};
+use libhrstd::rt::services::build_info::build_info_service;
+use libhrstd::rt::services::echo::call_echo_service;
+use libhrstd::rt::services::fs::{
+    fs_service_close,
+    fs_service_lseek,
+    fs_service_open,
+    fs_service_read,
+    fs_service_write,
+    FsCloseRequest,
+    FsError,
+    FsLseekRequest,
+    FsOpenFlags,
+    FsOpenRequest,
+    FsReadRequest,
+    FsWriteRequest,
+    FD,
+};
+use libhrstd::rt::services::irq::{
+    irq_service_attach,
+    irq_service_detach,
+    Irq,
+    IrqServiceError,
+    IrqSource,
+};
+use libhrstd::rt::services::logging::{
+    log_service_read,
+    LogLevel,
+};
+use libhrstd::rt::services::name::name_service_lookup;
+use libhrstd::rt::services::pci::{
+    pci_service_device,
+    pci_service_release,
+    PciServiceError,
+};
+use libhrstd::rt::services::perf_counter::{
+    perf_service_info,
+    perf_service_start,
+    perf_service_stop,
+    PerfEvent,
+    PerfServiceError,
+};
+use libhrstd::rt::services::process::{
+    process_service_status,
+    ProcessStatus,
+};
+use libhrstd::rt::services::scheduling::{
+    scheduling_service,
+    SchedulingServiceRequest,
+};
+use libhrstd::rt::services::semaphore::{
+    semaphore_service_close,
+    semaphore_service_create,
+};
+use libhrstd::rt::services::shm::{
+    shm_service_close,
+    shm_service_create,
+    shm_service_map,
+    shm_service_unmap,
+    ShmAccess,
+};
+use libhrstd::rt::services::stdin::stdin_service;
+use libhrstd::rt::services::system_time::{
+    system_time_service,
+    SystemTimeServiceRequest,
+};
+use libhrstd::rt::services::timer::{
+    timer_service_cancel,
+    timer_service_create_one_shot,
+    timer_service_wait,
+};
+use libhrstd::time::Instant;
+
+/// File of the round trip through the file system service.
+const CANARY_FILE: &str = "/tmp/.selfcheck";
+
+/// Data of the canary requests that carry data.
+const CANARY: &[u8] = b"selfcheck canary";
+
+/// Delay of the timer of the timer service check.
+const TIMER_DELAY_NS: u64 = 1_000_000;
+
+/// A canary request to a single service.
+struct Check {
+    service: &'static str,
+    /// Maximum latency of the whole check in microseconds.
+    max_latency_us: u64,
+    /// Sends the request(s) and verifies the response.
+    run: fn() -> Result<(), String>,
+}
+
+const CHECKS: [Check; 16] = [
+    Check {
+        service: "echo",
+        max_latency_us: 2_000,
+        run: check_echo,
+    },
+    Check {
+        service: "allocate",
+        max_latency_us: 4_000,
+        run: check_allocate,
+    },
+    Check {
+        service: "fs",
+        max_latency_us: 10_000,
+        run: check_fs,
+    },
+    Check {
+        service: "system_time",
+        max_latency_us: 4_000,
+        run: check_system_time,
+    },
+    Check {
+        service: "timer",
+        max_latency_us: 20_000,
+        run: check_timer,
+    },
+    Check {
+        service: "build_info",
+        max_latency_us: 2_000,
+        run: check_build_info,
+    },
+    Check {
+        service: "name",
+        max_latency_us: 2_000,
+        run: check_name,
+    },
+    Check {
+        service: "process",
+        max_latency_us: 2_000,
+        run: check_process,
+    },
+    Check {
+        service: "scheduling",
+        max_latency_us: 2_000,
+        run: check_scheduling,
+    },
+    Check {
+        service: "stdin",
+        max_latency_us: 2_000,
+        run: check_stdin,
+    },
+    Check {
+        service: "log",
+        max_latency_us: 2_000,
+        run: check_log,
+    },
+    Check {
+        service: "perf",
+        max_latency_us: 2_000,
+        run: check_perf,
+    },
+    Check {
+        service: "semaphore",
+        max_latency_us: 2_000,
+        run: check_semaphore,
+    },
+    Check {
+        service: "shm",
+        max_latency_us: 4_000,
+        run: check_shm,
+    },
+    Check {
+        service: "irq",
+        max_latency_us: 2_000,
+        run: check_irq,
+    },
+    Check {
+        service: "pci",
+        max_latency_us: 2_000,
+        run: check_pci,
+    },
+];
+
+/// Outcome of a [`Check`].
+struct Outcome {
+    check: &'static Check,
+    result: Result<(), String>,
+    ticks: u64,
+}
+
+/// Runs all checks and prints the health report.
+pub fn run() {
+    let begin = (Instant::now(), monotonic_ns());
+    let outcomes = CHECKS
+        .iter()
+        .map(|check| {
+            let begin = Instant::now();
+            let result = (check.run)();
+            Outcome {
+                check,
+                result,
+                ticks: Instant::now() - begin,
+            }
+        })
+        .collect::<Vec<_>>();
+    let end = (Instant::now(), monotonic_ns());
+
+    // TSC ticks per microsecond
+    let ticks_per_us = match (begin, end) {
+        ((begin_tsc, Some(begin_ns)), (end_tsc, Some(end_ns))) if end_ns > begin_ns => {
+            Some(((end_tsc - begin_tsc) * 1000 / (end_ns - begin_ns)).max(1))
+        }
+        _ => None,
+    };
+
+    let mut report = String::from("selfcheck:\n  service      status  latency");
+    let mut healthy = 0;
+    for outcome in &outcomes {
+        let latency_us = ticks_per_us.map(|ticks_per_us| outcome.ticks / ticks_per_us);
+        let (status, detail) = match (&outcome.result, latency_us) {
+            (Err(e), _) => ("FAIL", e.clone()),
+            (Ok(()), Some(latency_us)) if latency_us > outcome.check.max_latency_us => {
+                ("SLOW", format!("limit {} us", outcome.check.max_latency_us))
+            }
+            (Ok(()), _) => {
+                healthy += 1;
+                ("ok", String::new())
+            }
+        };
+        let latency = latency_us.map_or_else(
+            || format!("{} ticks", outcome.ticks),
+            |latency_us| format!("{} us", latency_us),
+        );
+        let line = format!(
+            "\n  {:<12} {:<7} {:<10} {}",
+            outcome.check.service, status, latency, detail
+        );
+        report.push_str(line.trim_end());
+    }
+    if ticks_per_us.is_none() {
+        report.push_str("\n  latencies unchecked: the monotonic clock is not available");
+    }
+    report.push_str(&format!(
+        "\nselfcheck: {}/{} services healthy",
+        healthy,
+        outcomes.len()
+    ));
+    print(&report);
+}
+
+/// Returns the nanoseconds since boot, as reported by the system time service.
+fn monotonic_ns() -> Option<u64> {
+    system_time_service(SystemTimeServiceRequest::Get)
+        .ok()
+        .map(|time| time.monotonic_ns)
+}
+
+fn check_echo() -> Result<(), String> {
+    // the echo service has no response; a failed call panics
+    call_echo_service();
+    Ok(())
+}
+
+fn check_allocate() -> Result<(), String> {
+    let layout = Layout::from_size_align(CANARY.len(), 8).unwrap();
+    let ptr = alloc_service(layout);
+    if ptr.is_null() {
+        return Err(String::from("allocation failed"));
+    }
+    let data = unsafe { core::slice::from_raw_parts_mut(ptr, CANARY.len()) };
+    data.copy_from_slice(CANARY);
+    let intact = data == CANARY;
+    unsafe { dealloc_service(ptr as u64, layout) };
+    if intact {
+        Ok(())
+    } else {
+        Err(String::from("memory doesn't keep data"))
+    }
+}
+
+fn check_fs() -> Result<(), String> {
+    let fd = fs_service_open(FsOpenRequest::new(
+        String::from(CANARY_FILE),
+        FsOpenFlags::O_CREAT | FsOpenFlags::O_RDWR | FsOpenFlags::O_TRUNC,
+        0o600,
+    ))
+    .map_err(|e| format!("can't open {}: {:?}", CANARY_FILE, e))?;
+    let res = fs_round_trip(fd);
+    let _ = fs_service_close(FsCloseRequest::new(fd));
+    let (written, read, buf) = res.map_err(|e| format!("{:?}", e))?;
+    if written != CANARY.len() {
+        Err(format!("wrote {} of {} bytes", written, CANARY.len()))
+    } else if buf[..read] != *CANARY {
+        Err(String::from("read other data than written"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes the canary to the opened file and reads it back. Returns the number of written
+/// bytes, the number of read bytes, and the read buffer.
+fn fs_round_trip(fd: FD) -> Result<(usize, usize, Vec<u8>), FsError> {
+    let written = fs_service_write(FsWriteRequest::new(
+        fd,
+        UserPtrOrEmbedded::new_slice(CANARY),
+        CANARY.len(),
+    ))?;
+    fs_service_lseek(FsLseekRequest::new(fd, 0))?;
+    let mut buf = vec![0_u8; CANARY.len()];
+    let read = fs_service_read(FsReadRequest::new(fd, buf.as_mut_ptr() as usize, buf.len()))?;
+    Ok((written, read, buf))
+}
+
+fn check_system_time() -> Result<(), String> {
+    let first =
+        system_time_service(SystemTimeServiceRequest::Get).map_err(|e| format!("{:?}", e))?;
+    let second =
+        system_time_service(SystemTimeServiceRequest::Get).map_err(|e| format!("{:?}", e))?;
+    if second.monotonic_ns < first.monotonic_ns {
+        Err(String::from("monotonic clock goes backwards"))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_timer() -> Result<(), String> {
+    let begin_ns = monotonic_ns();
+    let timer = timer_service_create_one_shot(TIMER_DELAY_NS).map_err(|e| format!("{:?}", e))?;
+    timer_service_wait(timer);
+    let end_ns = monotonic_ns();
+    // frees the ID
+    let _ = timer_service_cancel(timer);
+    match (begin_ns, end_ns) {
+        (Some(begin_ns), Some(end_ns)) if end_ns - begin_ns < TIMER_DELAY_NS => Err(format!(
+            "fired after {} of {} ns",
+            end_ns - begin_ns,
+            TIMER_DELAY_NS
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_build_info() -> Result<(), String> {
+    let own = libhrstd::build_info!();
+    let response = build_info_service();
+    if response.platform.online_cpus == 0 {
+        Err(String::from("no online CPUs"))
+    } else if !own.same_commit(&response.roottask) {
+        Err(format!(
+            "roottask is {}, shell is {}",
+            response.roottask.git_hash, own.git_hash
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_name() -> Result<(), String> {
+    name_service_lookup("echo")
+        .map(|_| ())
+        .map_err(|e| format!("lookup of echo: {:?}", e))
+}
+
+fn check_process() -> Result<(), String> {
+    match process_service_status(ROOTTASK_PROCESS_PID) {
+        Ok(ProcessStatus::Running) => Ok(()),
+        Ok(status) => Err(format!("roottask is {:?}", status)),
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+fn check_scheduling() -> Result<(), String> {
+    scheduling_service(SchedulingServiceRequest::Get { pid: None })
+        .map(|_| ())
+        .map_err(|e| format!("{:?}", e))
+}
+
+fn check_stdin() -> Result<(), String> {
+    // must not consume input of the user
+    let input = stdin_service(0, false);
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("returned {} bytes instead of none", input.len()))
+    }
+}
+
+fn check_log() -> Result<(), String> {
+    // no records follow the end of the log
+    let (entries, next) = log_service_read(u64::MAX, None, LogLevel::Trace);
+    if !entries.is_empty() {
+        Err(format!("returned {} records after the end", entries.len()))
+    } else if next == u64::MAX {
+        Err(String::from("doesn't report the end of the log"))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_perf() -> Result<(), String> {
+    // CPUs without performance counters, e.g. QEMU without KVM, are fine
+    if !perf_service_info()
+        .events
+        .contains(&PerfEvent::InstructionsRetired)
+    {
+        return Ok(());
+    }
+    match perf_service_start(&[PerfEvent::InstructionsRetired]) {
+        // only shells that the roottask started itself may use the counters
+        Err(PerfServiceError::PermissionDenied) => return Ok(()),
+        res => res.map_err(|e| format!("start failed: {:?}", e))?,
+    }
+    let counts = perf_service_stop().map_err(|e| format!("stop failed: {:?}", e))?;
+    match counts.get(PerfEvent::InstructionsRetired) {
+        Some(count) if count > 0 => Ok(()),
+        count => Err(format!("counted {:?} instructions", count)),
+    }
+}
+
+fn check_semaphore() -> Result<(), String> {
+    // each run uses one of the semaphore selectors of the shell
+    let sem =
+        semaphore_service_create(None, 1, false).map_err(|e| format!("create failed: {:?}", e))?;
+    let counts = [sem.try_down(), sem.try_down()];
+    sem.up();
+    let after_up = sem.try_down();
+    semaphore_service_close(sem).map_err(|e| format!("close failed: {:?}", e))?;
+    if counts == [true, false] && after_up {
+        Ok(())
+    } else {
+        Err(format!(
+            "unexpected down operations: {:?}, after up: {}",
+            counts, after_up
+        ))
+    }
+}
+
+fn check_shm() -> Result<(), String> {
+    let info = shm_service_create(None, 4096, false, 0o600, ShmAccess::ReadWrite)
+        .map_err(|e| format!("create failed: {:?}", e))?;
+    let writer = shm_service_map(info.id, ShmAccess::ReadWrite)
+        .map_err(|e| format!("map failed: {:?}", e))?;
+    let reader = shm_service_map(info.id, ShmAccess::ReadOnly)
+        .map_err(|e| format!("map failed: {:?}", e))?;
+    // both mappings share the memory
+    let seen = unsafe {
+        core::ptr::copy_nonoverlapping(CANARY.as_ptr(), writer as *mut u8, CANARY.len());
+        core::slice::from_raw_parts(reader as *const u8, CANARY.len()).to_vec()
+    };
+    for addr in [writer, reader] {
+        shm_service_unmap(addr).map_err(|e| format!("unmap failed: {:?}", e))?;
+    }
+    shm_service_close(info.id).map_err(|e| format!("close failed: {:?}", e))?;
+    if seen == CANARY {
+        Ok(())
+    } else {
+        Err(format!("second mapping contains {:?}", seen))
+    }
+}
+
+/// Only checks the rejection of invalid requests; the shell doesn't drive devices.
+fn check_irq() -> Result<(), String> {
+    let attached = irq_service_attach(IrqSource::Gsi(u32::MAX));
+    // only drivers, i.e. shells that the roottask started itself, may attach interrupts
+    let refused = [
+        IrqServiceError::InvalidIrq,
+        IrqServiceError::PermissionDenied,
+    ];
+    if !refused.iter().any(|e| attached == Err(*e)) {
+        return Err(format!("attach of an invalid GSI returned {:?}", attached));
+    }
+    let detached = irq_service_detach(Irq::new(0, 0));
+    if detached == Err(IrqServiceError::UnknownIrq) {
+        Ok(())
+    } else {
+        Err(format!(
+            "detach of an unknown interrupt returned {:?}",
+            detached
+        ))
+    }
+}
+
+/// Only checks the enumeration and the rejection of invalid requests; claiming a device
+/// would take it away from its driver.
+fn check_pci() -> Result<(), String> {
+    let device = pci_service_device(u32::MAX);
+    // only drivers, i.e. shells that the roottask started itself, may use the service
+    if device == Err(PciServiceError::PermissionDenied) {
+        return Ok(());
+    }
+    if device != Err(PciServiceError::NoSuchDevice) {
+        return Err(format!("query of an invalid index returned {:?}", device));
+    }
+    match pci_service_device(0) {
+        // machines without PCI
+        Err(PciServiceError::NoSuchDevice) => Ok(()),
+        Err(e) => Err(format!("query of the first device failed: {:?}", e)),
+        Ok(info) => match pci_service_release(info.function) {
+            Err(PciServiceError::NotClaimed) => Ok(()),
+            released => Err(format!(
+                "release of the unclaimed {:?} returned {:?}",
+                info.function, released
+            )),
+        },
+    }
+}
@@ -0,0 +1,208 @@
+//! Host tool that embeds the functions of a binary into the binary itself, so that the
+//! panic handler can resolve the addresses of its backtrace. It fills the section
+//! [`SYMBOL_SECTION_NAME`] of the ELF file in place; see [`libhrstd::util::backtrace`].
+//!
+//! Usage: `symembed-host <ELF>...`; the build runs it for the roottask and all native apps.
+//! Running it twice is fine, as it overwrites the section completely.
+
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+
+use libhrstd::util::backtrace::{
+    demangle,
+    encode_symbol_table,
+    Symbol,
+    SYMBOL_SECTION_NAME,
+};
+use std::ops::Range;
+use std::process::exit;
+
+/// ELF section header type of the symbol table.
+const SHT_SYMTAB: u32 = 2;
+
+/// ELF section header type of sections without content in the file, e.g. `.bss`.
+const SHT_NOBITS: u32 = 8;
+
+/// ELF symbol type of functions.
+const STT_FUNC: u8 = 2;
+
+/// Size of an entry of the ELF symbol table.
+const SYM_ENTRY_SIZE: usize = 24;
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() < 2 {
+        eprintln!("usage: {} <ELF>...", args[0]);
+        exit(1);
+    }
+    for path in &args[1..] {
+        if let Err(e) = embed_symbols(path) {
+            eprintln!("{}: {}", path, e);
+            exit(1);
+        }
+    }
+}
+
+/// Writes the symbol table of the ELF file at `path` into its section
+/// [`SYMBOL_SECTION_NAME`].
+fn embed_symbols(path: &str) -> Result<(), String> {
+    let mut elf = std::fs::read(path).map_err(|e| format!("can't read: {}", e))?;
+    let sections = Sections::parse(&elf).ok_or("not a 64-bit ELF file")?;
+    let target = sections
+        .find_by_name(SYMBOL_SECTION_NAME)
+        .ok_or("has no section for the symbols; see the linker script")?;
+    let symbols = sections
+        .functions()
+        .ok_or("has no symbol table; the binary must not be stripped")?;
+    let count = symbols.len();
+    let table = encode_symbol_table(symbols);
+    if table.len() > target.len() {
+        return Err(format!(
+            "the symbols need {} bytes, but the section has only {} bytes; enlarge the EmbeddedSymbols of the binary",
+            table.len(),
+            target.len()
+        ));
+    }
+    elf[target.clone()].fill(0);
+    elf[target.start..target.start + table.len()].copy_from_slice(&table);
+    std::fs::write(path, &elf).map_err(|e| format!("can't write: {}", e))?;
+    println!(
+        "{}: embedded {} functions ({} of {} bytes)",
+        path,
+        count,
+        table.len(),
+        target.len()
+    );
+    Ok(())
+}
+
+/// A section of an ELF file.
+#[derive(Debug, Clone)]
+struct Section {
+    name_offset: u32,
+    ty: u32,
+    link: u32,
+    /// Range of the content in the file.
+    content: Range<usize>,
+}
+
+/// The sections of a 64-bit little-endian ELF file.
+#[derive(Debug)]
+struct Sections<'a> {
+    elf: &'a [u8],
+    sections: Vec<Section>,
+    /// Index of the section with the names of the sections.
+    names_index: usize,
+}
+
+impl<'a> Sections<'a> {
+    fn parse(elf: &'a [u8]) -> Option<Self> {
+        if elf.get(..5)? != b"\x7fELF\x02" {
+            return None;
+        }
+        let shoff = read_u64(elf, 0x28)? as usize;
+        let shentsize = read_u16(elf, 0x3a)? as usize;
+        let shnum = read_u16(elf, 0x3c)? as usize;
+        let names_index = read_u16(elf, 0x3e)? as usize;
+        let mut sections = Vec::with_capacity(shnum);
+        for i in 0..shnum {
+            let shdr = elf.get(shoff + i * shentsize..shoff + (i + 1) * shentsize)?;
+            let ty = read_u32(shdr, 4)?;
+            let offset = read_u64(shdr, 0x18)? as usize;
+            let size = match ty {
+                SHT_NOBITS => 0,
+                _ => read_u64(shdr, 0x20)? as usize,
+            };
+            elf.get(offset..offset + size)?;
+            let section = Section {
+                name_offset: read_u32(shdr, 0)?,
+                ty,
+                link: read_u32(shdr, 0x28)?,
+                content: offset..offset + size,
+            };
+            sections.push(section);
+        }
+        Some(Self {
+            elf,
+            sections,
+            names_index,
+        })
+    }
+
+    /// Returns the range of the content of the section `name` in the file.
+    fn find_by_name(&self, name: &str) -> Option<Range<usize>> {
+        let names = self.content(self.names_index)?;
+        self.sections
+            .iter()
+            .find(|section| read_str(names, section.name_offset as usize) == Some(name))
+            .map(|section| section.content.clone())
+    }
+
+    /// Returns the functions of the symbol table with demangled names.
+    fn functions(&self) -> Option<Vec<Symbol>> {
+        let symtab = self
+            .sections
+            .iter()
+            .find(|section| section.ty == SHT_SYMTAB)?;
+        let symbols = &self.elf[symtab.content.clone()];
+        let names = self.content(symtab.link as usize)?;
+        let functions = symbols
+            .chunks_exact(SYM_ENTRY_SIZE)
+            .filter(|sym| sym[4] & 0xf == STT_FUNC)
+            .filter_map(|sym| {
+                let addr = read_u64(sym, 8)?;
+                let size = read_u64(sym, 0x10)?;
+                let name = read_str(names, read_u32(sym, 0)? as usize)?;
+                (size > 0).then(|| Symbol {
+                    addr,
+                    size: size.min(u32::MAX as u64) as u32,
+                    name: demangle(name),
+                })
+            })
+            .collect();
+        Some(functions)
+    }
+
+    fn content(&self, index: usize) -> Option<&'a [u8]> {
+        self.elf.get(self.sections.get(index)?.content.clone())
+    }
+}
+
+/// Reads the null-terminated string at `offset`.
+fn read_str(bytes: &[u8], offset: usize) -> Option<&str> {
+    let bytes = bytes.get(offset..)?;
+    let len = bytes.iter().position(|b| *b == 0)?;
+    std::str::from_utf8(&bytes[..len]).ok()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
@@ -0,0 +1,298 @@
+//! Build tasks of the fake cargo workspace (see README) that need more than a single cargo
+//! command. Run them from the `ws` directory via `cargo xtask <task>`; the alias lives in
+//! `ws/.cargo/config.toml`.
+//!
+//! Tasks:
+//! - `feature-matrix [<flavor>...]` builds libhrstd and representative binaries for each
+//!   supported combination of its runtime features (a "flavor", see [`FLAVORS`]) and runs
+//!   the host tests of the flavor. It also verifies that libhrstd rejects unsupported
+//!   combinations with a `compile_error!`, see [`UNSUPPORTED`]. The regular build only
+//!   builds the combinations that the binaries use, hence the others break silently.
+//!   Without arguments, it builds all flavors and checks the unsupported combinations.
+//! - `feature-matrix --list` prints the flavors.
+//!
+//! Each crate pins its toolchain and its target in its own directory, hence the task runs
+//! cargo inside the directory of each crate, like `build-cargo-fake-workspace.sh`.
+
+#![deny(
+    clippy::all,
+    clippy::cargo,
+    clippy::nursery,
+    // clippy::restriction,
+    // clippy::pedantic
+)]
+// now allow a few rules which are denied by the above statement
+// --> they are ridiculous and not necessary
+#![allow(
+    clippy::suboptimal_flops,
+    clippy::redundant_pub_crate,
+    clippy::fallible_impl_from
+)]
+#![deny(missing_debug_implementations)]
+#![deny(rustdoc::all)]
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::{
+    exit,
+    Command,
+};
+
+/// A supported combination of the features of libhrstd.
+#[derive(Debug)]
+struct Flavor {
+    name: &'static str,
+    description: &'static str,
+    /// Features of libhrstd, without its default features.
+    features: &'static [&'static str],
+    /// Crates whose host tests cover the flavor, besides libhrstd.
+    tested_crates: &'static [&'static str],
+    /// Binaries whose manifests select the flavor.
+    binaries: &'static [Binary],
+}
+
+/// A binary that links libhrstd. The directory is relative to the `ws` directory.
+#[derive(Debug)]
+struct Binary {
+    dir: &'static str,
+    /// Additional arguments of `cargo build`.
+    args: &'static [&'static str],
+}
+
+/// A combination of features that libhrstd must reject at compile time.
+#[derive(Debug)]
+struct Unsupported {
+    features: &'static [&'static str],
+    /// Part of the message of the `compile_error!`.
+    error: &'static str,
+}
+
+const FLAVORS: [Flavor; 4] = [
+    Flavor {
+        name: "roottask",
+        description: "no runtime; the roottask and the host tools",
+        features: &[],
+        tested_crates: &["libfileserver", "libroottask"],
+        binaries: &[
+            Binary {
+                dir: "roottask-bin",
+                args: &[],
+            },
+            Binary {
+                dir: "logdecoder-host",
+                args: &[],
+            },
+            Binary {
+                dir: "serialxfer-host",
+                args: &[],
+            },
+        ],
+    },
+    Flavor {
+        name: "roottask-lock-stats",
+        description: "no runtime; instrumented locks of the roottask",
+        features: &["lock_stats"],
+        tested_crates: &[],
+        binaries: &[Binary {
+            dir: "roottask-bin",
+            args: &["--features", "lock_stats"],
+        }],
+    },
+    Flavor {
+        name: "native",
+        description: "native Hedron apps",
+        features: &["native_rust_rt"],
+        tested_crates: &[],
+        binaries: &[Binary {
+            dir: "helloworld-bin",
+            args: &[],
+        }],
+    },
+    Flavor {
+        name: "hybrid",
+        description: "Linux apps that also use the services of the runtime environment",
+        features: &["foreign_rust_rt"],
+        tested_crates: &[],
+        binaries: &[Binary {
+            dir: "../../static-foreign-apps/Rust",
+            args: &[
+                "--bin",
+                "hello_world_hybrid",
+                "--target",
+                "x86_64-unknown-linux-musl",
+            ],
+        }],
+    },
+];
+
+const UNSUPPORTED: [Unsupported; 1] = [Unsupported {
+    features: &["native_rust_rt", "foreign_rust_rt"],
+    error: "mutually exclusive",
+}];
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        Some("feature-matrix") => feature_matrix(&args[2..]),
+        _ => {
+            eprintln!("usage: cargo xtask feature-matrix [--list | <flavor>...]");
+            exit(1);
+        }
+    }
+}
+
+/// See module description.
+fn feature_matrix(args: &[String]) {
+    if args.iter().any(|arg| arg == "--list") {
+        for flavor in &FLAVORS {
+            println!(
+                "{:<20} [{}] {}",
+                flavor.name,
+                flavor.features.join(","),
+                flavor.description
+            );
+        }
+        return;
+    }
+    let unknown = args
+        .iter()
+        .filter(|arg| FLAVORS.iter().all(|flavor| flavor.name != *arg))
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        eprintln!("unknown flavors: {:?}; see --list", unknown);
+        exit(1);
+    }
+
+    let ws = ws_dir();
+    let mut failures = Vec::new();
+    let selected = FLAVORS
+        .iter()
+        .filter(|flavor| args.is_empty() || args.contains(&String::from(flavor.name)));
+    for flavor in selected {
+        println!("flavor {} [{}]", flavor.name, flavor.features.join(","));
+        for (step, res) in build_flavor(&ws, flavor) {
+            report(&step, &res);
+            if let Err(output) = res {
+                failures.push((step, output));
+            }
+        }
+    }
+    if args.is_empty() {
+        for unsupported in &UNSUPPORTED {
+            let step = format!("reject [{}]", unsupported.features.join(","));
+            let res = check_rejected(&ws, unsupported);
+            report(&step, &res);
+            if let Err(output) = res {
+                failures.push((step, output));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        println!("feature matrix: ok");
+        return;
+    }
+    for (step, output) in &failures {
+        eprintln!("\n== {}\n{}", step, output);
+    }
+    eprintln!("feature matrix: {} step(s) failed", failures.len());
+    exit(1);
+}
+
+/// Checks and tests libhrstd with the features of the flavor, runs the tests of the other
+/// crates of the flavor, and builds its binaries. Returns the result of each step.
+fn build_flavor(ws: &Path, flavor: &Flavor) -> Vec<(String, Result<(), String>)> {
+    let mut feature_args = vec!["--no-default-features"];
+    let features = flavor.features.join(",");
+    if !features.is_empty() {
+        feature_args.extend(["--features", features.as_str()]);
+    }
+    let libhrstd = ws.join("libhrstd");
+
+    let mut steps = Vec::new();
+    steps.push((
+        String::from("check libhrstd"),
+        cargo(&libhrstd, "check", &feature_args),
+    ));
+    steps.push((
+        String::from("test libhrstd"),
+        cargo(&libhrstd, "test", &feature_args),
+    ));
+    for name in flavor.tested_crates {
+        steps.push((format!("test {}", name), cargo(&ws.join(name), "test", &[])));
+    }
+    for binary in flavor.binaries {
+        let step = format!("build {} {}", binary.dir, binary.args.join(" "));
+        steps.push((
+            String::from(step.trim_end()),
+            cargo(&ws.join(binary.dir), "build", binary.args),
+        ));
+    }
+    steps
+}
+
+/// Verifies that libhrstd fails to compile with the features and reports the expected
+/// error, i.e. doesn't fail for another reason.
+fn check_rejected(ws: &Path, unsupported: &Unsupported) -> Result<(), String> {
+    let features = unsupported.features.join(",");
+    let output = cargo_command(&ws.join("libhrstd"), "check")
+        .args(["--no-default-features", "--features", features.as_str()])
+        .output()
+        .map_err(|e| format!("can't run cargo: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        Err(String::from(
+            "compiles, but must fail with a compile_error!",
+        ))
+    } else if !stderr.contains(unsupported.error) {
+        Err(format!(
+            "fails without the expected error \"{}\":\n{}",
+            unsupported.error, stderr
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Runs a cargo subcommand in the directory of a crate. Returns the output of cargo if it
+/// fails.
+fn cargo(dir: &Path, subcommand: &str, args: &[&str]) -> Result<(), String> {
+    let output = cargo_command(dir, subcommand)
+        .args(args)
+        .output()
+        .map_err(|e| format!("can't run cargo: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+fn cargo_command(dir: &Path, subcommand: &str) -> Command {
+    let mut command = Command::new("cargo");
+    command
+        .arg(subcommand)
+        .current_dir(dir)
+        // rustup sets the toolchain of the xtask; each crate selects its own
+        .env_remove("RUSTUP_TOOLCHAIN");
+    command
+}
+
+fn report(step: &str, res: &Result<(), String>) {
+    let status = if res.is_ok() { "ok" } else { "FAIL" };
+    println!("  {:<60} {}", step, status);
+}
+
+/// Returns the `ws` directory, i.e. the parent of this crate.
+fn ws_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("the xtask crate lives in the ws directory")
+        .to_path_buf()
+}
@@ -0,0 +1,5 @@
+include!("../../runtime-environment/ws/libhrstd/build_helpers/build_info_env.rs");
+
+fn main() {
+    emit_build_info_env();
+}
@@ -1,7 +1,9 @@
 use libhrstd::kobjects::PdObject;
+use libhrstd::rt::hybrid_rt::{fs, hybrid_path, runs_under_hedron, HybridPath};
 use log::LevelFilter;
 use simple_logger::SimpleLogger;
-use std::env::var;
+use std::fs::OpenOptions;
+use std::os::unix::io::AsRawFd;
 
 fn main() {
     // to get log messages from libhrstd
@@ -15,7 +17,7 @@ fn main() {
 
     println!("Hello world from hybrid Linux application written in Rust!");
 
-    if var("LINUX_UNDER_HEDRON").is_ok() {
+    if runs_under_hedron() {
         println!("This Linux binary executes under Hedron");
         let self_pd = PdObject::self_in_user_cap_space(1);
         println!("Executing Hedron-native system call now:");
@@ -31,4 +33,32 @@ fn main() {
     } else {
         println!("This Linux binary executes under native Linux");
     }
+
+    hybrid_file_io();
+}
+
+/// Writes a file with a Linux syscall and reads it back on the path that fits the
+/// environment: natively under Hedron and with a Linux syscall under Linux.
+fn hybrid_file_io() {
+    let path = "/tmp/hello_world_hybrid";
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .read(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+    let fd = file.as_raw_fd();
+    let msg = b"Hello from both worlds!";
+    fs::write_via(HybridPath::Linux, fd, msg).unwrap();
+    fs::seek(fd, 0).unwrap();
+    let mut buf = [0; 64];
+    let count = fs::read(fd, &mut buf).unwrap();
+    println!(
+        "read {:?} from file via {:?} path",
+        std::str::from_utf8(&buf[..count]).unwrap(),
+        hybrid_path()
+    );
+    drop(file);
+    std::fs::remove_file(path).unwrap();
 }
@@ -1,16 +1,18 @@
 use libhrstd::cap_space::user::UserAppCapSpace;
 use libhrstd::kobjects::{LocalEcObject, PdObject, PortalIdentifier, PtCtx, PtObject};
 use libhrstd::libhedron::Mtd;
+use libhrstd::rt::hybrid_rt::{fs as hybrid_fs, runs_under_hedron, HybridPath};
 use libhrstd::rt::services::echo::{call_echo_service, call_raw_echo_service};
 use libhrstd::time::Instant;
+use libhrstd::util::bench_report::{BenchReport, BENCH_RESULTS_DIR};
 use libhrstd::util::BenchHelper;
 use log::{Metadata, Record};
 use std::cell::RefCell;
 use std::collections::BTreeMap;
-use std::env::var;
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
 use std::rc::Rc;
 
 struct Logger;
@@ -42,19 +44,64 @@ fn main() {
     log::set_logger(&Logger).unwrap();
     println!("Hello world from Hybrid Foreign Benchmark!");
 
-    if var("LINUX_UNDER_HEDRON").is_ok() {
+    let source = if runs_under_hedron() {
+        "hybrid_benchmark_hedron"
+    } else {
+        "hybrid_benchmark_linux"
+    };
+    let mut report = BenchReport::new(
+        source,
+        &libhrstd::build_info!().git_hash,
+        Instant::now().val(),
+    );
+
+    if runs_under_hedron() {
         println!("This Linux binary runs as a hybrid foreign application under Hedron");
-        hedron_hybrid_bench_native_pt_ctrl_syscall();
-        hedron_bench_raw_echo_pt_call();
-        hedron_bench_echo_pt_call();
+        report.add(
+            "native pt_ctrl syscall",
+            hedron_hybrid_bench_native_pt_ctrl_syscall(),
+        );
+        report.add("raw echo call", hedron_bench_raw_echo_pt_call());
+        report.add("echo call", hedron_bench_echo_pt_call());
+        for (path, (write_res, read_res)) in hedron_hybrid_bench_fs_paths() {
+            report.add(&format!("hybrid fs write [path={path:?}]"), write_res);
+            report.add(&format!("hybrid fs read [path={path:?}]"), read_res);
+        }
     } else {
         println!("This Linux binary executes under native Linux");
     }
 
-    linux_bench_cheap_foreign_set_tid_address_syscall();
-    linux_bench_expensive_fs_fstat();
-    linux_bench_expensive_fs_open();
-    linux_bench_file_system_microbenchmark();
+    report.add(
+        "set_tid_address syscall",
+        linux_bench_cheap_foreign_set_tid_address_syscall(),
+    );
+    report.add("fstat syscall", linux_bench_expensive_fs_fstat());
+    report.add("open syscall", linux_bench_expensive_fs_open());
+    for ((file_size, buffer_size), (write_res, read_res)) in
+        linux_bench_file_system_microbenchmark()
+    {
+        report.add(
+            &format!("fs write [file_size={file_size}, buf_size={buffer_size}]"),
+            write_res,
+        );
+        report.add(
+            &format!("fs read [file_size={file_size}, buf_size={buffer_size}]"),
+            read_res,
+        );
+    }
+
+    persist_bench_report(&report);
+}
+
+/// Writes the results to the file system, so that they can be analyzed later without
+/// capturing the output. Under Hedron, the bench tool can list and diff the runs.
+fn persist_bench_report(report: &BenchReport) {
+    // Hedron has no real directories but on Linux this might be necessary
+    let _ = fs::create_dir_all(BENCH_RESULTS_DIR);
+    match fs::write(report.path(), report.to_json()) {
+        Ok(()) => println!("bench results written to {}", report.path()),
+        Err(e) => println!("can't write bench results to {}: {}", report.path(), e),
+    }
 }
 
 fn pt_entry(_id: PortalIdentifier) -> ! {
@@ -63,7 +110,7 @@ fn pt_entry(_id: PortalIdentifier) -> ! {
 
 /// Executes a Hedron syscall from a foreign app multiple
 /// times and calculates the average clock ticks per call.
-fn hedron_hybrid_bench_native_pt_ctrl_syscall() {
+fn hedron_hybrid_bench_native_pt_ctrl_syscall() -> u64 {
     println!();
     println!("BENCH: NATIVE SYSCALL FROM HYBRID FOREIGN APP");
     let self_pd = PdObject::self_in_user_cap_space(UserAppCapSpace::Pd.val());
@@ -86,13 +133,65 @@ fn hedron_hybrid_bench_native_pt_ctrl_syscall() {
         "avg: {} ticks / syscall (Native Syscall from Hybrid App)",
         duration_per_iteration
     );
+    duration_per_iteration
+}
+
+/// Writes and reads the same file once with Linux syscalls and once with native calls of
+/// the file system service. Both paths work on the same file descriptor. Returns the
+/// write and read results for each path.
+fn hedron_hybrid_bench_fs_paths() -> Vec<(HybridPath, (u64, u64))> {
+    println!();
+    println!("BENCH: HYBRID FILE I/O (LINUX VS NATIVE PATH)");
+    let path = "/tmp/diplom_evaluation_test_hybrid_paths";
+    let file_size = 0x10000;
+    let buffer_size = 0x4000;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .read(true)
+        .write(true)
+        .open(path)
+        .unwrap();
+    let fd = file.as_raw_fd();
+
+    let data = (0..file_size).map(|i| i as u8).collect::<Vec<_>>();
+    let mut read_buffer = vec![0; file_size];
+    let results = [HybridPath::Linux, HybridPath::Native]
+        .iter()
+        .map(|&hybrid_path| {
+            let write_res = BenchHelper::<_>::bench_direct(|_| {
+                hybrid_fs::seek_via(hybrid_path, fd, 0).unwrap();
+                for chunk in data.chunks(buffer_size) {
+                    let count = hybrid_fs::write_via(hybrid_path, fd, chunk).unwrap();
+                    assert_eq!(count, chunk.len());
+                }
+            });
+            let read_res = BenchHelper::<_>::bench_direct(|_| {
+                hybrid_fs::seek_via(hybrid_path, fd, 0).unwrap();
+                for chunk in read_buffer.chunks_mut(buffer_size) {
+                    let count = hybrid_fs::read_via(hybrid_path, fd, chunk).unwrap();
+                    assert_eq!(count, chunk.len());
+                }
+            });
+            assert_eq!(data, read_buffer, "the paths must see the same data");
+            println!(
+                "avg: {} ticks / write, {} ticks / read of {} bytes ({:?} path)",
+                write_res, read_res, file_size, hybrid_path
+            );
+            (hybrid_path, (write_res, read_res))
+        })
+        .collect();
+
+    drop(file);
+    fs::remove_file(path).unwrap();
+    results
 }
 
 /// Executes a cheap Linux system call from the Linux App multiple
 /// times and calculates the average clock ticks per call.
 ///
 /// This is a Cross-PD IPC.
-fn linux_bench_cheap_foreign_set_tid_address_syscall() {
+fn linux_bench_cheap_foreign_set_tid_address_syscall() -> u64 {
     println!();
     println!("BENCH: CHEAP FOREIGN set_tid_address SYSCALL");
     let duration_per_iteration = BenchHelper::<_>::bench_direct(|_| unsafe {
@@ -104,17 +203,18 @@ fn linux_bench_cheap_foreign_set_tid_address_syscall() {
         "avg: {} ticks / set_tid_address() syscall",
         duration_per_iteration
     );
-    if var("LINUX_UNDER_HEDRON").is_ok() {
+    if runs_under_hedron() {
         print!(" (foreign syscall Cross-PD IPC)");
     }
     println!();
+    duration_per_iteration
 }
 
 /// Executes a cheap Linux system call from the Linux App multiple
 /// times and calculates the average clock ticks per call.
 ///
 /// This is a Cross-PD IPC.
-fn linux_bench_expensive_fs_open() {
+fn linux_bench_expensive_fs_open() -> u64 {
     println!();
     println!("BENCH: EXPENSIVE FOREIGN open SYSCALL");
     let path = "/tmp/diplom_evaluation_test_rwos8uf9sg";
@@ -130,18 +230,19 @@ fn linux_bench_expensive_fs_open() {
             .unwrap();
     });
     print!("avg: {} ticks / open() syscall", duration_per_iteration);
-    if var("LINUX_UNDER_HEDRON").is_ok() {
+    if runs_under_hedron() {
         print!(" (foreign syscall Cross-PD IPC)");
     }
     println!();
     //fs::remove_file(path).unwrap();
+    duration_per_iteration
 }
 
 /// Executes a cheap Linux system call from the Linux App multiple
 /// times and calculates the average clock ticks per call.
 ///
 /// This is a Cross-PD IPC.
-fn linux_bench_expensive_fs_fstat() {
+fn linux_bench_expensive_fs_fstat() -> u64 {
     println!();
     println!("BENCH: EXPENSIVE FOREIGN fstat SYSCALL)");
     let path = "/tmp/diplom_evaluation_test_r15156sg";
@@ -164,16 +265,18 @@ fn linux_bench_expensive_fs_fstat() {
         }
     });
     print!("avg: {} ticks / fstat() syscall", duration_per_iteration);
-    if var("LINUX_UNDER_HEDRON").is_ok() {
+    if runs_under_hedron() {
         print!(" (foreign syscall Cross-PD IPC)");
     }
     println!();
     fs::remove_file(path).unwrap();
+    duration_per_iteration
 }
 
 /// Performs the file system microbenchmark that runs under Linux as well as Hedron.
-/// Consists of multiple small sub benchmarks.
-fn linux_bench_file_system_microbenchmark() {
+/// Consists of multiple small sub benchmarks. Returns the write and read results for
+/// each pair of file size and buffer size.
+fn linux_bench_file_system_microbenchmark() -> BTreeMap<(usize, usize), (u64, u64)> {
     println!();
     println!("LINUX BENCH: File System Microbenchmark");
     let bench_file_path = "/tmp/foobar";
@@ -316,11 +419,12 @@ fn linux_bench_file_system_microbenchmark() {
 
 
     let _ = std::fs::remove_file(bench_file_path);
+    bench_results
 }
 
 /// Calculates the average time to call the RAW ECHO SERVICE PT. This is the raw cost of
 /// cross-PD IPC.
-fn hedron_bench_raw_echo_pt_call() {
+fn hedron_bench_raw_echo_pt_call() -> u64 {
     println!();
     println!("BENCH: RAW ECHO SERVICE PT");
     let duration_per_iteration = BenchHelper::<_>::bench_direct(|_| call_raw_echo_service());
@@ -328,11 +432,12 @@ fn hedron_bench_raw_echo_pt_call() {
         "avg: {} ticks / syscall (raw Cross-PD IPC)",
         duration_per_iteration
     );
+    duration_per_iteration
 }
 
 /// Calculates the average time to call the REGULAR ECHO SERVICE PT. This is the cost of
 /// cross-PD IPC including my PT multiplexing mechanism.
-fn hedron_bench_echo_pt_call() {
+fn hedron_bench_echo_pt_call() -> u64 {
     println!();
     println!("BENCH: ECHO SERVICE PT");
     let duration_per_iteration = BenchHelper::<_>::bench_direct(|_| call_echo_service());
@@ -340,6 +445,7 @@ fn hedron_bench_echo_pt_call() {
         "avg: {} ticks / syscall (Cross-PD IPC)",
         duration_per_iteration
     );
+    duration_per_iteration
 }
 
 // when this runs under Hedron I can't use cool and fancy features of the "rand" library.